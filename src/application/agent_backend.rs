@@ -0,0 +1,83 @@
+use std::{future::Future, pin::Pin};
+
+use serde_json::{Map, Value};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::domain::models::ChatMessage;
+
+pub type AgentBackendFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Receives chunks of reply text while a backend streams a response.
+pub type AgentDeltaSender = UnboundedSender<String>;
+
+/// One agent turn handed to the backend: the user input plus the run it belongs to.
+#[derive(Debug, Clone, Copy)]
+pub struct AgentTurn<'a> {
+    pub run_id: &'a str,
+    pub agent_id: &'a str,
+    pub session_key: &'a str,
+    pub input: &'a str,
+    /// The agent's configured model, or the cheaper one a spent cost budget switched the run to;
+    /// `None` leaves the choice to the backend.
+    pub model: Option<&'a str>,
+    /// Messages pinned via `chat.pin`; always part of the context regardless of history windows.
+    pub pinned: &'a [ChatMessage],
+    /// Live entries of the session's `session.kv` scratchpad, keyed by entry key.
+    pub scratchpad: &'a Map<String, Value>,
+}
+
+/// Produces assistant replies for `agent` runs and `chat.send`.
+///
+/// Embedders swap the built-in echo backend via `SharedState::set_agent_backend` or
+/// `ServerBuilder::agent_backend`. An `Err` fails the run with the returned message.
+pub trait AgentBackend: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn respond<'a>(&'a self, turn: AgentTurn<'a>)
+    -> AgentBackendFuture<'a, Result<String, String>>;
+
+    /// Like [`respond`](Self::respond), but sends the reply to `deltas` in chunks as it is
+    /// produced; the returned text is the whole reply. The default sends it as one chunk.
+    fn respond_streaming<'a>(
+        &'a self,
+        turn: AgentTurn<'a>,
+        deltas: AgentDeltaSender,
+    ) -> AgentBackendFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let reply = self.respond(turn).await?;
+            let _ = deltas.send(reply.clone());
+            Ok(reply)
+        })
+    }
+}
+
+/// Default backend: replies with `Echo: <input>`, streamed word by word.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EchoAgentBackend;
+
+impl AgentBackend for EchoAgentBackend {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn respond<'a>(
+        &'a self,
+        turn: AgentTurn<'a>,
+    ) -> AgentBackendFuture<'a, Result<String, String>> {
+        Box::pin(async move { Ok(format!("Echo: {}", turn.input)) })
+    }
+
+    fn respond_streaming<'a>(
+        &'a self,
+        turn: AgentTurn<'a>,
+        deltas: AgentDeltaSender,
+    ) -> AgentBackendFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let reply = format!("Echo: {}", turn.input);
+            for chunk in reply.split_inclusive(' ') {
+                let _ = deltas.send(chunk.to_owned());
+            }
+            Ok(reply)
+        })
+    }
+}
@@ -0,0 +1,633 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tokio::process::Command;
+use tracing::warn;
+
+use crate::{
+    application::{
+        config::{AttachmentScanAction, AttachmentScanConfig, AttachmentScannerKind},
+        state::SharedState,
+    },
+    encoding::decode_base64,
+    protocol::{ERROR_INVALID_REQUEST, ERROR_UNAVAILABLE, ErrorShape},
+    security::signatures::hex_encode,
+    storage::now_unix_ms,
+};
+
+const ATTACHMENT_SCAN_EVENT: &str = "attachment.scan";
+/// Upper bound on attachments carried by one message.
+pub const MAX_ATTACHMENTS: usize = 10;
+/// Upper bound on the decoded size of one attachment.
+pub const MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024;
+const DEFAULT_SCAN_TIMEOUT_SECS: u64 = 30;
+const MAX_SCAN_TIMEOUT_SECS: u64 = 300;
+const ATTACHMENTS_DIR: &str = "attachments";
+const QUARANTINE_DIR: &str = "quarantine";
+const STAGING_DIR: &str = ".staging";
+const DEFAULT_BLOCKED_MIME_TYPES: &[&str] = &[
+    "application/x-msdownload",
+    "application/x-executable",
+    "application/x-mach-binary",
+    "application/x-sh",
+];
+
+/// Compiled form of the `attachmentScan` config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentScan {
+    scanner: Option<Scanner>,
+    action: AttachmentScanAction,
+    blocked_mime_types: Vec<String>,
+    fail_open: bool,
+    timeout: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Scanner {
+    Command(Vec<String>),
+    Http { url: String, token: Option<String> },
+}
+
+impl Scanner {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Command(_) => "command",
+            Self::Http { .. } => "http",
+        }
+    }
+}
+
+/// An attachment as clients send it with `chat.send` or a channel inbound message.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentInput {
+    pub name: String,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    /// The content, base64-encoded.
+    pub data: String,
+}
+
+/// What checking one attachment found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ScanOutcome {
+    Clean {
+        scanner: Option<&'static str>,
+    },
+    Detected {
+        scanner: &'static str,
+        signature: String,
+    },
+    Failed {
+        scanner: &'static str,
+        error: String,
+    },
+}
+
+impl AttachmentScan {
+    pub fn compile(config: AttachmentScanConfig) -> Result<Self, String> {
+        let scanner = match config.scanner {
+            None => None,
+            Some(AttachmentScannerKind::Command) => {
+                let command = config
+                    .command
+                    .into_iter()
+                    .map(|part| part.trim().to_owned())
+                    .filter(|part| !part.is_empty())
+                    .collect::<Vec<_>>();
+                if command.is_empty() {
+                    return Err(
+                        "attachmentScan.command is required for the command scanner".to_owned()
+                    );
+                }
+                Some(Scanner::Command(command))
+            }
+            Some(AttachmentScannerKind::Http) => {
+                let url = config
+                    .url
+                    .map(|url| url.trim().to_owned())
+                    .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+                    .ok_or("attachmentScan.url must be an http(s) URL for the http scanner")?;
+                Some(Scanner::Http {
+                    url,
+                    token: config
+                        .token
+                        .map(|token| token.trim().to_owned())
+                        .filter(|token| !token.is_empty()),
+                })
+            }
+        };
+
+        let timeout_secs = config.timeout_secs.unwrap_or(DEFAULT_SCAN_TIMEOUT_SECS);
+        if !(1..=MAX_SCAN_TIMEOUT_SECS).contains(&timeout_secs) {
+            return Err(format!(
+                "attachmentScan.timeoutSecs must be between 1 and {MAX_SCAN_TIMEOUT_SECS}"
+            ));
+        }
+
+        let blocked_mime_types = match config.blocked_mime_types {
+            Some(types) => types
+                .iter()
+                .map(|pattern| pattern.trim().to_ascii_lowercase())
+                .filter(|pattern| !pattern.is_empty())
+                .map(|pattern| {
+                    if pattern.contains('/') {
+                        Ok(pattern)
+                    } else {
+                        Err(format!(
+                            "attachmentScan.blockedMimeTypes entry must look like type/subtype: {pattern}"
+                        ))
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => DEFAULT_BLOCKED_MIME_TYPES
+                .iter()
+                .map(|pattern| (*pattern).to_owned())
+                .collect(),
+        };
+
+        Ok(Self {
+            scanner,
+            action: config.action,
+            blocked_mime_types,
+            fail_open: config.fail_open,
+            timeout: Duration::from_secs(timeout_secs),
+        })
+    }
+
+    fn blocks_mime_type(&self, mime_type: &str) -> bool {
+        self.blocked_mime_types
+            .iter()
+            .any(|pattern| match pattern.strip_suffix("/*") {
+                Some(major) => mime_type
+                    .split_once('/')
+                    .is_some_and(|(candidate, _)| candidate == major),
+                None => pattern == mime_type,
+            })
+    }
+
+    async fn scan(
+        &self,
+        staged: &Path,
+        name: &str,
+        mime_types: &[&str],
+        bytes: &[u8],
+    ) -> ScanOutcome {
+        if let Some(blocked) = mime_types
+            .iter()
+            .find(|mime_type| self.blocks_mime_type(mime_type))
+        {
+            return ScanOutcome::Detected {
+                scanner: "mime",
+                signature: format!("blocked MIME type {blocked}"),
+            };
+        }
+        let Some(scanner) = &self.scanner else {
+            return ScanOutcome::Clean { scanner: None };
+        };
+        let result = match scanner {
+            Scanner::Command(command) => scan_with_command(command, staged, self.timeout).await,
+            Scanner::Http { url, token } => {
+                scan_with_http(
+                    url,
+                    token.as_deref(),
+                    name,
+                    mime_types[0],
+                    bytes,
+                    self.timeout,
+                )
+                .await
+            }
+        };
+        match result {
+            Ok(None) => ScanOutcome::Clean {
+                scanner: Some(scanner.label()),
+            },
+            Ok(Some(signature)) => ScanOutcome::Detected {
+                scanner: scanner.label(),
+                signature,
+            },
+            Err(error) => ScanOutcome::Failed {
+                scanner: scanner.label(),
+                error,
+            },
+        }
+    }
+}
+
+/// Checks and stores the attachments of one message, returning the records kept on the message.
+///
+/// Every attachment is scanned before any is stored, so a `reject` finding refuses the whole
+/// message and leaves nothing behind. Findings are reported to operators as `attachment.scan`
+/// events.
+pub async fn store_attachments(
+    state: &SharedState,
+    method: &str,
+    session_key: &str,
+    inputs: Vec<AttachmentInput>,
+) -> Result<Vec<Value>, ErrorShape> {
+    if inputs.len() > MAX_ATTACHMENTS {
+        return Err(invalid(format!(
+            "invalid {method} params: at most {MAX_ATTACHMENTS} attachments per message"
+        )));
+    }
+    let mut decoded = Vec::with_capacity(inputs.len());
+    for (index, input) in inputs.into_iter().enumerate() {
+        let name = input.name.trim();
+        if name.is_empty() || name.chars().any(char::is_control) {
+            return Err(invalid(format!(
+                "invalid {method} params: attachments[{index}].name is required"
+            )));
+        }
+        let bytes = decode_base64(&input.data).ok_or_else(|| {
+            invalid(format!(
+                "invalid {method} params: attachments[{index}].data must be base64"
+            ))
+        })?;
+        if bytes.len() > MAX_ATTACHMENT_BYTES {
+            return Err(invalid(format!(
+                "invalid {method} params: attachments[{index}] exceeds {MAX_ATTACHMENT_BYTES} bytes"
+            )));
+        }
+        let declared = input
+            .mime_type
+            .map(|mime_type| mime_type.trim().to_ascii_lowercase())
+            .filter(|mime_type| !mime_type.is_empty());
+        decoded.push((name.to_owned(), declared, bytes));
+    }
+    if decoded.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let root = attachments_dir(state);
+    let staging = root.join(STAGING_DIR);
+    create_dir(&staging).await?;
+
+    let policy = state.config().attachment_scan.clone();
+    let mut checked = Vec::with_capacity(decoded.len());
+    for (name, declared, bytes) in decoded {
+        let id = format!("att-{}", uuid::Uuid::new_v4());
+        let detected = sniff_mime_type(&bytes);
+        let mime_type = declared
+            .clone()
+            .or_else(|| detected.map(str::to_owned))
+            .unwrap_or_else(|| "application/octet-stream".to_owned());
+        let outcome = match &policy {
+            Some(policy) => {
+                let staged = staging.join(&id);
+                let outcome = match tokio::fs::write(&staged, &bytes).await {
+                    Ok(()) => {
+                        let mut mime_types = vec![mime_type.as_str()];
+                        mime_types.extend(detected.filter(|detected| *detected != mime_type));
+                        policy.scan(&staged, &name, &mime_types, &bytes).await
+                    }
+                    Err(error) => ScanOutcome::Failed {
+                        scanner: "storage",
+                        error: error.to_string(),
+                    },
+                };
+                let _ = tokio::fs::remove_file(&staged).await;
+                Some(outcome)
+            }
+            None => None,
+        };
+        checked.push((id, name, mime_type, detected, bytes, outcome));
+    }
+
+    // A finding (or a scanner failure unless failOpen) gets the configured action.
+    let flagged = |outcome: &Option<ScanOutcome>| match (outcome, &policy) {
+        (Some(ScanOutcome::Detected { .. }), Some(policy)) => Some(policy.action),
+        (Some(ScanOutcome::Failed { .. }), Some(policy)) if !policy.fail_open => {
+            Some(policy.action)
+        }
+        _ => None,
+    };
+
+    let rejected = checked
+        .iter()
+        .find(|(.., outcome)| flagged(outcome) == Some(AttachmentScanAction::Reject));
+    if let Some((_, name, _, _, _, outcome)) = rejected {
+        let reason = outcome.as_ref().map(describe).unwrap_or_default();
+        report(state, session_key, name, "reject", &reason).await;
+        return Err(invalid(format!("attachment {name} was rejected: {reason}")));
+    }
+
+    let mut records = Vec::with_capacity(checked.len());
+    for (id, name, mime_type, detected, bytes, outcome) in checked {
+        let action = flagged(&outcome);
+        let quarantined = action == Some(AttachmentScanAction::Quarantine);
+        let dir = if quarantined {
+            root.join(QUARANTINE_DIR)
+        } else {
+            root.clone()
+        };
+        create_dir(&dir).await?;
+        tokio::fs::write(dir.join(&id), &bytes)
+            .await
+            .map_err(|error| {
+                ErrorShape::new(
+                    ERROR_UNAVAILABLE,
+                    format!("failed to store attachment {name}: {error}"),
+                )
+            })?;
+
+        if action.is_some()
+            && let Some(outcome) = &outcome
+        {
+            let label = if quarantined { "quarantine" } else { "tag" };
+            report(state, session_key, &name, label, &describe(outcome)).await;
+        }
+
+        records.push(json!({
+            "id": id,
+            "name": name,
+            "mimeType": mime_type,
+            "detectedMimeType": detected,
+            "size": bytes.len(),
+            "sha256": hex_encode(&Sha256::digest(&bytes)),
+            "status": if quarantined { "quarantined" } else { "stored" },
+            "scan": outcome.map(|outcome| scan_record(&outcome, action)),
+        }));
+    }
+    Ok(records)
+}
+
+fn scan_record(outcome: &ScanOutcome, action: Option<AttachmentScanAction>) -> Value {
+    let action = action.map(|action| match action {
+        AttachmentScanAction::Reject => "reject",
+        AttachmentScanAction::Quarantine => "quarantine",
+        AttachmentScanAction::Tag => "tag",
+    });
+    let scanned_at_ms = now_unix_ms();
+    match outcome {
+        ScanOutcome::Clean { scanner } => json!({
+            "status": "clean",
+            "scanner": scanner,
+            "scannedAtMs": scanned_at_ms,
+        }),
+        ScanOutcome::Detected { scanner, signature } => json!({
+            "status": "detected",
+            "scanner": scanner,
+            "signature": signature,
+            "action": action,
+            "scannedAtMs": scanned_at_ms,
+        }),
+        ScanOutcome::Failed { scanner, error } => json!({
+            "status": "error",
+            "scanner": scanner,
+            "error": error,
+            "action": action,
+            "scannedAtMs": scanned_at_ms,
+        }),
+    }
+}
+
+fn describe(outcome: &ScanOutcome) -> String {
+    match outcome {
+        ScanOutcome::Clean { .. } => "clean".to_owned(),
+        ScanOutcome::Detected { scanner, signature } => format!("{scanner} found {signature}"),
+        ScanOutcome::Failed { scanner, error } => format!("{scanner} scan failed: {error}"),
+    }
+}
+
+async fn report(state: &SharedState, session_key: &str, name: &str, action: &str, reason: &str) {
+    let message = format!("attachment {name} flagged ({action}): {reason}");
+    warn!("{message} for session {session_key}");
+    let _ = state
+        .append_gateway_log("warn", &message, Some(ATTACHMENT_SCAN_EVENT), None)
+        .await;
+    state
+        .publish_gateway_event(
+            ATTACHMENT_SCAN_EVENT,
+            json!({
+                "sessionKey": session_key,
+                "name": name,
+                "action": action,
+                "reason": reason,
+            }),
+        )
+        .await;
+}
+
+/// Runs a command-line scanner on the staged file. Follows the `clamscan` convention: exit 0 is
+/// clean, 1 is a finding (named by a `<path>: <signature> FOUND` line), anything else a failure.
+async fn scan_with_command(
+    command: &[String],
+    path: &Path,
+    timeout: Duration,
+) -> Result<Option<String>, String> {
+    let mut process = Command::new(&command[0]);
+    process
+        .args(&command[1..])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(timeout, process.output())
+        .await
+        .map_err(|_| format!("timed out after {}s", timeout.as_secs()))?
+        .map_err(|error| format!("failed to run {}: {error}", command[0]))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match output.status.code() {
+        Some(0) => Ok(None),
+        Some(1) => Ok(Some(
+            stdout
+                .lines()
+                .find_map(|line| line.trim().strip_suffix(" FOUND"))
+                .and_then(|line| line.rsplit_once(": "))
+                .map_or_else(
+                    || "infected".to_owned(),
+                    |(_, signature)| signature.to_owned(),
+                ),
+        )),
+        code => Err(format!(
+            "{} exited with {}: {}",
+            command[0],
+            code.map_or_else(|| "a signal".to_owned(), |code| format!("status {code}")),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+/// Posts the bytes to a scanning service, which answers `{"infected": bool, "signature": ...}`.
+async fn scan_with_http(
+    url: &str,
+    token: Option<&str>,
+    name: &str,
+    mime_type: &str,
+    bytes: &[u8],
+    timeout: Duration,
+) -> Result<Option<String>, String> {
+    #[derive(Deserialize)]
+    struct ScanResponse {
+        infected: bool,
+        #[serde(default)]
+        signature: Option<String>,
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|error| format!("failed to construct http client: {error}"))?;
+    let mut request = client
+        .post(url)
+        .header("content-type", "application/octet-stream")
+        .header("x-reclaw-attachment-name", name)
+        .header("x-reclaw-attachment-type", mime_type)
+        .body(bytes.to_vec());
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|error| format!("request failed: {error}"))?;
+    if !response.status().is_success() {
+        return Err(format!("scanner returned status {}", response.status()));
+    }
+    let response: ScanResponse = response
+        .json()
+        .await
+        .map_err(|error| format!("invalid scanner response: {error}"))?;
+    Ok(response
+        .infected
+        .then(|| response.signature.unwrap_or_else(|| "infected".to_owned())))
+}
+
+/// MIME type implied by the leading bytes of well-known formats.
+fn sniff_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"MZ", "application/x-msdownload"),
+        (b"\x7fELF", "application/x-executable"),
+        (b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"\xce\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"#!", "application/x-sh"),
+    ];
+    if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+        .map(|(_, mime_type)| *mime_type)
+}
+
+/// Stored attachments live next to the database: `attachments/<id>`, with flagged files under
+/// `attachments/quarantine/`.
+fn attachments_dir(state: &SharedState) -> PathBuf {
+    state
+        .config()
+        .db_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(ATTACHMENTS_DIR)
+}
+
+async fn create_dir(dir: &Path) -> Result<(), ErrorShape> {
+    tokio::fs::create_dir_all(dir).await.map_err(|error| {
+        ErrorShape::new(
+            ERROR_UNAVAILABLE,
+            format!("failed to create attachment directory: {error}"),
+        )
+    })
+}
+
+fn invalid(message: String) -> ErrorShape {
+    ErrorShape::new(ERROR_INVALID_REQUEST, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{AttachmentScan, ScanOutcome, sniff_mime_type};
+    use crate::application::config::{AttachmentScanConfig, AttachmentScannerKind};
+
+    #[test]
+    fn sniffing_and_blocked_types_flag_executables() {
+        assert_eq!(
+            sniff_mime_type(b"MZ\x90\x00"),
+            Some("application/x-msdownload")
+        );
+        assert_eq!(sniff_mime_type(b"%PDF-1.7"), Some("application/pdf"));
+        assert_eq!(sniff_mime_type(b"hello"), None);
+
+        let scan = AttachmentScan::compile(AttachmentScanConfig {
+            blocked_mime_types: Some(vec!["Video/*".to_owned(), "application/zip".to_owned()]),
+            ..AttachmentScanConfig::default()
+        })
+        .expect("config should compile");
+        assert!(scan.blocks_mime_type("video/mp4"));
+        assert!(scan.blocks_mime_type("application/zip"));
+        assert!(!scan.blocks_mime_type("application/pdf"));
+
+        assert!(
+            AttachmentScan::compile(AttachmentScanConfig {
+                scanner: Some(AttachmentScannerKind::Http),
+                url: Some("ftp://scanner".to_owned()),
+                ..AttachmentScanConfig::default()
+            })
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn command_scanner_reports_clamscan_findings() {
+        let scan = AttachmentScan::compile(AttachmentScanConfig {
+            scanner: Some(AttachmentScannerKind::Command),
+            command: vec![
+                "sh".to_owned(),
+                "-c".to_owned(),
+                "grep -q EICAR \"$0\" && echo \"$0: Eicar-Test-Signature FOUND\" && exit 1; exit 0"
+                    .to_owned(),
+            ],
+            ..AttachmentScanConfig::default()
+        })
+        .expect("config should compile");
+        let dir = tempfile::tempdir().expect("temp dir");
+        let infected = dir.path().join("infected");
+        let clean = dir.path().join("clean");
+        std::fs::write(&infected, "X5O EICAR test").expect("write");
+        std::fs::write(&clean, "hello").expect("write");
+
+        assert_eq!(
+            scan.scan(&infected, "a.txt", &["text/plain"], b"").await,
+            ScanOutcome::Detected {
+                scanner: "command",
+                signature: "Eicar-Test-Signature".to_owned(),
+            }
+        );
+        assert_eq!(
+            scan.scan(&clean, "b.txt", &["text/plain"], b"").await,
+            ScanOutcome::Clean {
+                scanner: Some("command")
+            }
+        );
+        assert!(matches!(
+            scan.scan(
+                Path::new("/nonexistent"),
+                "c.exe",
+                &["application/x-msdownload"],
+                b""
+            )
+            .await,
+            ScanOutcome::Detected {
+                scanner: "mime",
+                ..
+            }
+        ));
+    }
+}
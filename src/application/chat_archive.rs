@@ -0,0 +1,288 @@
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use tracing::{info, warn};
+
+use crate::{
+    application::{config::ChatArchiveConfig, state::SharedState},
+    domain::{
+        error::DomainError,
+        models::{ChatArchiveSegment, ChatMessage},
+    },
+    storage::now_unix_ms,
+};
+
+/// Messages read per archive pass; a session with more is split over several segments.
+const ARCHIVE_BATCH_SIZE: usize = 5_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArchiveReport {
+    pub segments: u64,
+    pub messages: u64,
+}
+
+/// Moves every unpinned message older than `config.after` into segment files, one segment per
+/// session and batch.
+pub async fn archive_old_messages(
+    state: &SharedState,
+    config: &ChatArchiveConfig,
+) -> Result<ArchiveReport, DomainError> {
+    let after_ms = u64::try_from(config.after.as_millis()).unwrap_or(u64::MAX);
+    let before_ms = now_unix_ms().saturating_sub(after_ms);
+    let mut report = ArchiveReport::default();
+    loop {
+        let batch = state
+            .list_archivable_chat_messages(before_ms, ARCHIVE_BATCH_SIZE)
+            .await?;
+        let exhausted = batch.len() < ARCHIVE_BATCH_SIZE;
+        let mut rest = batch.as_slice();
+        while let Some((session_key, _)) = rest.first() {
+            let split = rest
+                .iter()
+                .position(|(key, _)| key != session_key)
+                .unwrap_or(rest.len());
+            let messages = rest[..split]
+                .iter()
+                .map(|(_, message)| message.clone())
+                .collect::<Vec<_>>();
+            write_segment(state, &config.dir, session_key, messages).await?;
+            report.segments += 1;
+            report.messages += u64::try_from(split).unwrap_or(0);
+            rest = &rest[split..];
+        }
+        if exhausted {
+            return Ok(report);
+        }
+    }
+}
+
+async fn write_segment(
+    state: &SharedState,
+    dir: &Path,
+    session_key: &str,
+    messages: Vec<ChatMessage>,
+) -> Result<(), DomainError> {
+    let (Some(first), Some(last)) = (messages.first(), messages.last()) else {
+        return Ok(());
+    };
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let file_name = format!(
+        "{}/{}-{}-{id}.jsonl.gz",
+        session_dir_name(session_key),
+        first.ts,
+        last.ts
+    );
+    let segment = ChatArchiveSegment {
+        id,
+        session_key: session_key.to_owned(),
+        file_name,
+        first_ts_ms: first.ts,
+        last_ts_ms: last.ts,
+        message_count: u64::try_from(messages.len()).unwrap_or(0),
+        created_at_ms: now_unix_ms(),
+    };
+    let message_ids = messages
+        .iter()
+        .map(|message| message.id.clone())
+        .collect::<Vec<_>>();
+
+    let path = dir.join(&segment.file_name);
+    let write_path = path.clone();
+    tokio::task::spawn_blocking(move || write_segment_file(&write_path, &messages))
+        .await
+        .map_err(|error| DomainError::Storage(format!("archive writer failed: {error}")))??;
+
+    if let Err(error) = state
+        .commit_chat_archive_segment(&segment, &message_ids)
+        .await
+    {
+        // The messages are still in SQLite; drop the orphaned file.
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Writes through a temporary file so a crash never leaves a truncated segment behind.
+fn write_segment_file(path: &Path, messages: &[ChatMessage]) -> Result<(), DomainError> {
+    let storage_error = |error: std::io::Error| {
+        DomainError::Storage(format!("failed to write chat archive: {error}"))
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(storage_error)?;
+    }
+    let tmp_path = path.with_extension("gz.tmp");
+    let mut encoder = GzEncoder::new(
+        File::create(&tmp_path).map_err(storage_error)?,
+        Compression::default(),
+    );
+    for message in messages {
+        let line = serde_json::to_string(message).map_err(|error| {
+            DomainError::Storage(format!("failed to serialize archived message: {error}"))
+        })?;
+        encoder.write_all(line.as_bytes()).map_err(storage_error)?;
+        encoder.write_all(b"\n").map_err(storage_error)?;
+    }
+    encoder
+        .finish()
+        .and_then(|file| file.sync_all())
+        .map_err(storage_error)?;
+    fs::rename(&tmp_path, path).map_err(storage_error)
+}
+
+fn read_segment_file(path: &Path) -> Result<Vec<ChatMessage>, String> {
+    let file =
+        File::open(path).map_err(|error| format!("failed to open {}: {error}", path.display()))?;
+    BufReader::new(GzDecoder::new(file))
+        .lines()
+        .map(|line| {
+            let line =
+                line.map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+            serde_json::from_str::<ChatMessage>(&line)
+                .map_err(|error| format!("corrupt entry in {}: {error}", path.display()))
+        })
+        .collect()
+}
+
+/// Completes `live` (the newest messages from SQLite, oldest first) with archived messages until
+/// `limit` is reached, newest segments first. Unreadable segments are skipped with a warning.
+pub async fn read_through(
+    state: &SharedState,
+    dir: &Path,
+    session_key: &str,
+    live: Vec<ChatMessage>,
+    limit: Option<usize>,
+) -> Result<Vec<ChatMessage>, DomainError> {
+    let segments = state.list_chat_archive_segments(session_key).await?;
+    if segments.is_empty() {
+        return Ok(live);
+    }
+
+    let mut archived = Vec::new();
+    let mut loaded = live.len();
+    for segment in segments {
+        if limit.is_some_and(|limit| loaded >= limit) {
+            break;
+        }
+        let path = dir.join(&segment.file_name);
+        match tokio::task::spawn_blocking(move || read_segment_file(&path)).await {
+            Ok(Ok(messages)) => {
+                loaded += messages.len();
+                archived.push(messages);
+            }
+            Ok(Err(error)) => warn!("skipping chat archive segment {}: {error}", segment.id),
+            Err(error) => warn!("skipping chat archive segment {}: {error}", segment.id),
+        }
+    }
+
+    let mut messages = archived.into_iter().rev().flatten().collect::<Vec<_>>();
+    messages.extend(live);
+    messages.sort_by_key(|message| message.ts);
+    if let Some(limit) = limit {
+        let excess = messages.len().saturating_sub(limit);
+        messages.drain(..excess);
+    }
+    Ok(messages)
+}
+
+/// Removes segment files after their index rows were deleted.
+pub async fn remove_segment_files(dir: &Path, segments: &[ChatArchiveSegment]) {
+    for segment in segments {
+        let path = dir.join(&segment.file_name);
+        if let Err(error) = tokio::fs::remove_file(&path).await
+            && error.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!("failed to remove chat archive {}: {error}", path.display());
+        }
+    }
+}
+
+/// Session keys contain `:` and may contain other characters that are unsafe in paths.
+fn session_dir_name(session_key: &str) -> String {
+    session_key
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.') {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_owned()
+}
+
+pub fn spawn_chat_archiver(state: SharedState) -> Option<tokio::task::JoinHandle<()>> {
+    let config = state.config().chat_archive.clone()?;
+    info!("chat archiving enabled (dir={})", config.dir.display());
+
+    Some(tokio::spawn(async move {
+        loop {
+            if state.is_standby() {
+                tokio::time::sleep(config.interval).await;
+                continue;
+            }
+            match archive_old_messages(&state, &config).await {
+                Ok(report) if report.messages > 0 => info!(
+                    "archived {} chat messages into {} segments",
+                    report.messages, report.segments
+                ),
+                Ok(_) => {}
+                Err(error) => {
+                    warn!("chat archiving failed: {error}");
+                    let _ = state
+                        .append_gateway_log(
+                            "warn",
+                            &format!("chat archiving failed: {error}"),
+                            Some("chat.archive"),
+                            None,
+                        )
+                        .await;
+                }
+            }
+            tokio::time::sleep(config.interval).await;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{read_segment_file, session_dir_name, write_segment_file};
+    use crate::domain::models::ChatMessage;
+
+    #[test]
+    fn segments_round_trip_through_gzip_jsonl() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let path = temp
+            .path()
+            .join(session_dir_name("agent:main:telegram:chat:42"))
+            .join("1-2-seg.jsonl.gz");
+        let messages = (1..=2)
+            .map(|ts| ChatMessage {
+                id: format!("msg-{ts}"),
+                role: "user".to_owned(),
+                text: format!("hello {ts}"),
+                status: "final".to_owned(),
+                ts,
+                metadata: json!({ "source": "test" }),
+                pinned: false,
+            })
+            .collect::<Vec<_>>();
+        write_segment_file(&path, &messages).expect("segment should write");
+        assert!(path.starts_with(temp.path().join("agent_main_telegram_chat_42")));
+        assert!(!path.with_extension("gz.tmp").exists());
+
+        let read = read_segment_file(&path).expect("segment should read");
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].text, "hello 2");
+        assert_eq!(read[0].metadata["source"], "test");
+        assert_eq!(session_dir_name("../etc"), "_etc");
+    }
+}
@@ -0,0 +1,647 @@
+use std::{collections::BTreeMap, future::Future, pin::Pin, sync::Arc};
+
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    application::{
+        config::{ChatCommandsConfig, ChatCommandsRuleConfig, normalize_channel_plugin_key},
+        state::SharedState,
+    },
+    rpc::methods::agents,
+    storage::now_unix_ms,
+};
+
+pub type ChatCommandFuture<'a> = Pin<Box<dyn Future<Output = Result<String, String>> + Send + 'a>>;
+
+const CONVERSATION_SETTINGS_PREFIX: &str = "runtime/chat/conversations/";
+const DEFAULT_MUTE_MS: u64 = 60 * 60 * 1_000;
+const MAX_MUTE_MS: u64 = 30 * 24 * 60 * 60 * 1_000;
+const DEFAULT_AGENT_ID: &str = "main";
+
+/// The channel conversation an inbound message arrived in.
+#[derive(Debug, Clone, Copy)]
+pub struct ChatConversation<'a> {
+    pub channel: &'a str,
+    pub conversation_id: &'a str,
+    pub session_key: &'a str,
+    pub agent_id: &'a str,
+    pub sender_id: Option<&'a str>,
+    /// Language the sender's client reported (`metadata.locale`), if any.
+    pub locale: Option<&'a str>,
+}
+
+/// One slash command as handed to its [`ChatCommand`].
+#[derive(Debug, Clone, Copy)]
+pub struct ChatCommandInvocation<'a> {
+    pub conversation: ChatConversation<'a>,
+    /// Text after the command name, trimmed.
+    pub args: &'a str,
+    /// The sender's locale, else the channel's configured one, else `en`.
+    pub locale: &'a str,
+}
+
+/// A slash command answered in channel conversations before the message reaches the agent.
+///
+/// The built-in `/status`, `/reset`, `/agent`, `/mute` and `/help` are registered by default;
+/// embedders add or replace commands via `SharedState::register_chat_command` or
+/// `ServerBuilder::chat_command`. An `Err` is sent back to the sender as the reply.
+pub trait ChatCommand: Send + Sync {
+    /// Name without the leading `/`, e.g. `status`.
+    fn name(&self) -> &str;
+
+    /// One line for `/help` in `locale`; commands without a translation answer in English.
+    fn help(&self, locale: &str) -> String;
+
+    fn run<'a>(
+        &'a self,
+        state: &'a SharedState,
+        invocation: ChatCommandInvocation<'a>,
+    ) -> ChatCommandFuture<'a>;
+}
+
+/// Registered commands by name.
+#[derive(Clone)]
+pub struct ChatCommandRegistry {
+    commands: BTreeMap<String, Arc<dyn ChatCommand>>,
+}
+
+impl Default for ChatCommandRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            commands: BTreeMap::new(),
+        };
+        registry.register(Arc::new(StatusCommand));
+        registry.register(Arc::new(ResetCommand));
+        registry.register(Arc::new(AgentCommand));
+        registry.register(Arc::new(MuteCommand));
+        registry.register(Arc::new(HelpCommand));
+        registry
+    }
+}
+
+impl ChatCommandRegistry {
+    /// Adds `command`, replacing any command registered under the same name.
+    pub fn register(&mut self, command: Arc<dyn ChatCommand>) {
+        self.commands
+            .insert(command.name().trim().to_ascii_lowercase(), command);
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ChatCommand>> {
+        self.commands.get(name).cloned()
+    }
+
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        self.commands.keys().cloned().collect()
+    }
+}
+
+/// Compiled form of the `chatCommands` config: which commands each channel answers and in which
+/// language `/help` replies.
+#[derive(Debug, Clone, Default)]
+pub struct ChatCommands {
+    defaults: ChatCommandsRuleConfig,
+    channels: BTreeMap<String, ChatCommandsRuleConfig>,
+}
+
+impl ChatCommands {
+    pub fn compile(config: ChatCommandsConfig) -> Result<Self, String> {
+        let mut channels = BTreeMap::new();
+        for (key, rule) in config.channels {
+            let channel = normalize_channel_plugin_key(&key).ok_or_else(|| {
+                format!("chatCommands.channels key must contain only [a-z0-9._-]: {key}")
+            })?;
+            let rule = normalize_rule(&format!("chatCommands.channels.{channel}"), rule)?;
+            channels.insert(channel, rule);
+        }
+        Ok(Self {
+            defaults: normalize_rule("chatCommands", config.defaults)?,
+            channels,
+        })
+    }
+
+    /// Whether `channel` answers `/command`. Commands are on unless disabled for the channel or
+    /// left out of its `commands` list.
+    #[must_use]
+    pub fn allows(&self, channel: &str, command: &str) -> bool {
+        let rule = self.channels.get(channel);
+        let enabled = rule
+            .and_then(|rule| rule.enabled)
+            .or(self.defaults.enabled)
+            .unwrap_or(true);
+        let commands = rule
+            .and_then(|rule| rule.commands.as_ref())
+            .or(self.defaults.commands.as_ref());
+        enabled && commands.is_none_or(|commands| commands.iter().any(|name| name == command))
+    }
+
+    #[must_use]
+    pub fn locale(&self, channel: &str) -> Option<&str> {
+        self.channels
+            .get(channel)
+            .and_then(|rule| rule.locale.as_deref())
+            .or(self.defaults.locale.as_deref())
+    }
+}
+
+fn normalize_rule(
+    scope: &str,
+    mut rule: ChatCommandsRuleConfig,
+) -> Result<ChatCommandsRuleConfig, String> {
+    if let Some(commands) = rule.commands.as_mut() {
+        for name in commands.iter_mut() {
+            let normalized = name.trim().trim_start_matches('/').to_ascii_lowercase();
+            if !is_command_name(&normalized) {
+                return Err(format!(
+                    "{scope}.commands entries must contain only [a-z0-9_-]: {name}"
+                ));
+            }
+            *name = normalized;
+        }
+    }
+    rule.locale = rule
+        .locale
+        .map(|locale| locale.trim().to_ascii_lowercase())
+        .filter(|locale| !locale.is_empty());
+    Ok(rule)
+}
+
+/// Per-conversation choices made through commands, kept across restarts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationSettings {
+    /// Agent selected with `/agent`; `None` keeps the channel's default.
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    /// While in the future, messages are not passed to the agent.
+    #[serde(default)]
+    pub muted_until_ms: Option<u64>,
+}
+
+impl ConversationSettings {
+    #[must_use]
+    pub fn muted_at(&self, now_ms: u64) -> bool {
+        self.muted_until_ms.is_some_and(|until| until > now_ms)
+    }
+}
+
+pub async fn conversation_settings(
+    state: &SharedState,
+    channel: &str,
+    conversation_id: &str,
+) -> ConversationSettings {
+    state
+        .get_config_entry_value(&settings_key(channel, conversation_id))
+        .await
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+async fn save_conversation_settings(
+    state: &SharedState,
+    channel: &str,
+    conversation_id: &str,
+    settings: &ConversationSettings,
+) -> Result<(), String> {
+    let key = settings_key(channel, conversation_id);
+    let result = if *settings == ConversationSettings::default() {
+        state.delete_config_entry_value(&key).await.map(|_| ())
+    } else {
+        let value = serde_json::to_value(settings)
+            .map_err(|error| format!("failed to encode conversation settings: {error}"))?;
+        state.set_config_entry_value(&key, &value).await.map(|_| ())
+    };
+    result.map_err(|error| error.to_string())
+}
+
+fn settings_key(channel: &str, conversation_id: &str) -> String {
+    format!("{CONVERSATION_SETTINGS_PREFIX}{channel}/{conversation_id}")
+}
+
+/// Answers `text` when it is an enabled slash command. Ordinary messages, unknown commands, and
+/// channels without `chatCommands` return `None` and go on to the agent.
+pub async fn dispatch(
+    state: &SharedState,
+    text: &str,
+    conversation: ChatConversation<'_>,
+) -> Option<String> {
+    let config = state.config().chat_commands.as_ref()?;
+    let (name, args) = parse_command(text)?;
+    if !config.allows(conversation.channel, &name) {
+        return None;
+    }
+    let command = state.chat_commands().await.get(&name)?;
+    let locale = conversation
+        .locale
+        .map(|locale| locale.trim().to_ascii_lowercase())
+        .filter(|locale| !locale.is_empty())
+        .or_else(|| config.locale(conversation.channel).map(str::to_owned))
+        .unwrap_or_else(|| "en".to_owned());
+
+    let invocation = ChatCommandInvocation {
+        conversation,
+        args,
+        locale: &locale,
+    };
+    Some(match command.run(state, invocation).await {
+        Ok(reply) | Err(reply) => reply,
+    })
+}
+
+/// Splits `/name args` (or Telegram's `/name@bot args`) into a lowercased name and the rest.
+fn parse_command(text: &str) -> Option<(String, &str)> {
+    let rest = text.trim().strip_prefix('/')?;
+    let (head, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let name = head
+        .split_once('@')
+        .map_or(head, |(name, _)| name)
+        .to_ascii_lowercase();
+    is_command_name(&name).then(|| (name, args.trim()))
+}
+
+fn is_command_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_' || ch == '-')
+}
+
+/// The primary language subtag if the built-in commands translate it, else `en`.
+fn language(locale: &str) -> &'static str {
+    match locale.split(['-', '_']).next().unwrap_or_default() {
+        "de" => "de",
+        "es" => "es",
+        "fr" => "fr",
+        _ => "en",
+    }
+}
+
+/// Parses `30s`, `15m`, `1h` or `2d`.
+fn parse_mute_duration(value: &str) -> Option<u64> {
+    let value = value.trim().to_ascii_lowercase();
+    let split = value.find(|ch: char| !ch.is_ascii_digit())?;
+    let (amount, unit) = value.split_at(split);
+    let amount = amount.parse::<u64>().ok().filter(|amount| *amount > 0)?;
+    let unit_ms = match unit {
+        "s" => 1_000,
+        "m" => 60 * 1_000,
+        "h" => 60 * 60 * 1_000,
+        "d" => 24 * 60 * 60 * 1_000,
+        _ => return None,
+    };
+    amount.checked_mul(unit_ms)
+}
+
+fn format_time(at_ms: u64) -> String {
+    i64::try_from(at_ms)
+        .ok()
+        .and_then(|at_ms| Utc.timestamp_millis_opt(at_ms).single())
+        .map_or_else(
+            || at_ms.to_string(),
+            |at| at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        )
+}
+
+struct StatusCommand;
+
+impl ChatCommand for StatusCommand {
+    fn name(&self) -> &str {
+        "status"
+    }
+
+    fn help(&self, locale: &str) -> String {
+        match language(locale) {
+            "de" => "/status – zeigt Agent, Sitzung und Stummschaltung",
+            "es" => "/status – muestra el agente, la sesión y el silencio",
+            "fr" => "/status – affiche l'agent, la session et la mise en sourdine",
+            _ => "/status – show the agent, session, and mute state",
+        }
+        .to_owned()
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a SharedState,
+        invocation: ChatCommandInvocation<'a>,
+    ) -> ChatCommandFuture<'a> {
+        Box::pin(async move {
+            let conversation = invocation.conversation;
+            let settings =
+                conversation_settings(state, conversation.channel, conversation.conversation_id)
+                    .await;
+            let replies = match settings.muted_until_ms {
+                Some(until) if until > now_unix_ms() => {
+                    format!("muted until {}", format_time(until))
+                }
+                _ => "active".to_owned(),
+            };
+            Ok(format!(
+                "Agent: {}\nSession: {}\nReplies: {replies}",
+                conversation.agent_id, conversation.session_key
+            ))
+        })
+    }
+}
+
+struct ResetCommand;
+
+impl ChatCommand for ResetCommand {
+    fn name(&self) -> &str {
+        "reset"
+    }
+
+    fn help(&self, locale: &str) -> String {
+        match language(locale) {
+            "de" => "/reset – löscht den Verlauf dieser Unterhaltung",
+            "es" => "/reset – borra el historial de esta conversación",
+            "fr" => "/reset – efface l'historique de cette conversation",
+            _ => "/reset – clear this conversation's history",
+        }
+        .to_owned()
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a SharedState,
+        invocation: ChatCommandInvocation<'a>,
+    ) -> ChatCommandFuture<'a> {
+        Box::pin(async move {
+            let counts = state
+                .purge_session_data(invocation.conversation.session_key)
+                .await
+                .map_err(|error| format!("Reset failed: {error}"))?;
+            Ok(format!(
+                "Session reset: removed {} message(s).",
+                counts.messages
+            ))
+        })
+    }
+}
+
+struct AgentCommand;
+
+impl ChatCommand for AgentCommand {
+    fn name(&self) -> &str {
+        "agent"
+    }
+
+    fn help(&self, locale: &str) -> String {
+        match language(locale) {
+            "de" => "/agent <id> – wechselt den Agenten dieser Unterhaltung",
+            "es" => "/agent <id> – cambia el agente de esta conversación",
+            "fr" => "/agent <id> – change l'agent de cette conversation",
+            _ => "/agent <id> – switch this conversation to another agent",
+        }
+        .to_owned()
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a SharedState,
+        invocation: ChatCommandInvocation<'a>,
+    ) -> ChatCommandFuture<'a> {
+        Box::pin(async move {
+            let conversation = invocation.conversation;
+            let known = agents::load_agents(state)
+                .await
+                .map_err(|error| format!("Agents unavailable: {}", error.message))?
+                .into_iter()
+                .map(|agent| agent.agent_id)
+                .collect::<Vec<_>>();
+            let requested = invocation.args.trim().to_ascii_lowercase();
+            if requested.is_empty() {
+                return Ok(format!(
+                    "Agent: {}\nAvailable: {}",
+                    conversation.agent_id,
+                    known.join(", ")
+                ));
+            }
+            if !known.contains(&requested) {
+                return Err(format!(
+                    "Unknown agent \"{requested}\". Available: {}",
+                    known.join(", ")
+                ));
+            }
+
+            let mut settings =
+                conversation_settings(state, conversation.channel, conversation.conversation_id)
+                    .await;
+            settings.agent_id = (requested != DEFAULT_AGENT_ID).then(|| requested.clone());
+            save_conversation_settings(
+                state,
+                conversation.channel,
+                conversation.conversation_id,
+                &settings,
+            )
+            .await?;
+            Ok(format!("Switched to agent {requested}."))
+        })
+    }
+}
+
+struct MuteCommand;
+
+impl ChatCommand for MuteCommand {
+    fn name(&self) -> &str {
+        "mute"
+    }
+
+    fn help(&self, locale: &str) -> String {
+        match language(locale) {
+            "de" => "/mute [1h|off] – schaltet den Agenten hier stumm (Standard 1h)",
+            "es" => "/mute [1h|off] – silencia al agente aquí (por defecto 1h)",
+            "fr" => "/mute [1h|off] – met l'agent en sourdine ici (1h par défaut)",
+            _ => "/mute [1h|off] – stop agent replies here for a while (default 1h)",
+        }
+        .to_owned()
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a SharedState,
+        invocation: ChatCommandInvocation<'a>,
+    ) -> ChatCommandFuture<'a> {
+        Box::pin(async move {
+            let conversation = invocation.conversation;
+            let mut settings =
+                conversation_settings(state, conversation.channel, conversation.conversation_id)
+                    .await;
+            let args = invocation.args.trim();
+            let reply = if args.eq_ignore_ascii_case("off") {
+                settings.muted_until_ms = None;
+                "Agent replies resumed.".to_owned()
+            } else {
+                let duration_ms = if args.is_empty() {
+                    DEFAULT_MUTE_MS
+                } else {
+                    parse_mute_duration(args)
+                        .filter(|duration_ms| *duration_ms <= MAX_MUTE_MS)
+                        .ok_or_else(|| {
+                            format!(
+                                "Invalid duration \"{args}\"; use e.g. 30m, 1h or 2d (at most 30d), or off."
+                            )
+                        })?
+                };
+                let until = now_unix_ms().saturating_add(duration_ms);
+                settings.muted_until_ms = Some(until);
+                format!("Agent muted until {}.", format_time(until))
+            };
+            save_conversation_settings(
+                state,
+                conversation.channel,
+                conversation.conversation_id,
+                &settings,
+            )
+            .await?;
+            Ok(reply)
+        })
+    }
+}
+
+struct HelpCommand;
+
+impl ChatCommand for HelpCommand {
+    fn name(&self) -> &str {
+        "help"
+    }
+
+    fn help(&self, locale: &str) -> String {
+        match language(locale) {
+            "de" => "/help [Befehl] – listet die verfügbaren Befehle",
+            "es" => "/help [comando] – muestra los comandos disponibles",
+            "fr" => "/help [commande] – liste les commandes disponibles",
+            _ => "/help [command] – list the available commands",
+        }
+        .to_owned()
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a SharedState,
+        invocation: ChatCommandInvocation<'a>,
+    ) -> ChatCommandFuture<'a> {
+        Box::pin(async move {
+            let channel = invocation.conversation.channel;
+            let registry = state.chat_commands().await;
+            let allowed = |name: &str| {
+                state
+                    .config()
+                    .chat_commands
+                    .as_ref()
+                    .is_some_and(|config| config.allows(channel, name))
+            };
+
+            let requested = invocation
+                .args
+                .trim()
+                .trim_start_matches('/')
+                .to_ascii_lowercase();
+            if !requested.is_empty() {
+                return registry
+                    .get(&requested)
+                    .filter(|_| allowed(&requested))
+                    .map(|command| command.help(invocation.locale))
+                    .ok_or_else(|| format!("/{requested} ?"));
+            }
+
+            let heading = match language(invocation.locale) {
+                "de" => "Verfügbare Befehle:",
+                "es" => "Comandos disponibles:",
+                "fr" => "Commandes disponibles :",
+                _ => "Available commands:",
+            };
+            let lines = registry
+                .names()
+                .into_iter()
+                .filter(|name| allowed(name))
+                .filter_map(|name| registry.get(&name))
+                .map(|command| command.help(invocation.locale))
+                .collect::<Vec<_>>();
+            Ok(format!("{heading}\n{}", lines.join("\n")))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::application::config::{ChatCommandsConfig, ChatCommandsRuleConfig};
+
+    use super::{ChatCommands, language, parse_command, parse_mute_duration};
+
+    #[test]
+    fn parse_command_splits_name_and_args() {
+        assert_eq!(parse_command("/Mute 1h"), Some(("mute".to_owned(), "1h")));
+        assert_eq!(
+            parse_command("  /agent@reclaw_bot   ops "),
+            Some(("agent".to_owned(), "ops"))
+        );
+        assert_eq!(parse_command("/status"), Some(("status".to_owned(), "")));
+        assert_eq!(parse_command("/usr/bin/env"), None);
+        assert_eq!(parse_command("hello /status"), None);
+        assert_eq!(parse_command("/"), None);
+    }
+
+    #[test]
+    fn mute_durations_and_locales_parse() {
+        assert_eq!(parse_mute_duration("30m"), Some(30 * 60 * 1_000));
+        assert_eq!(parse_mute_duration("2D"), Some(2 * 24 * 60 * 60 * 1_000));
+        assert_eq!(parse_mute_duration("0h"), None);
+        assert_eq!(parse_mute_duration("1w"), None);
+        assert_eq!(parse_mute_duration("h"), None);
+        assert_eq!(language("de-AT"), "de");
+        assert_eq!(language("pt_BR"), "en");
+    }
+
+    #[test]
+    fn channel_rules_override_defaults() {
+        let commands = ChatCommands::compile(ChatCommandsConfig {
+            defaults: ChatCommandsRuleConfig {
+                enabled: None,
+                commands: None,
+                locale: Some("DE".to_owned()),
+            },
+            channels: BTreeMap::from([
+                (
+                    "Slack".to_owned(),
+                    ChatCommandsRuleConfig {
+                        enabled: None,
+                        commands: Some(vec!["/Status".to_owned(), "help".to_owned()]),
+                        locale: Some("fr".to_owned()),
+                    },
+                ),
+                (
+                    "discord".to_owned(),
+                    ChatCommandsRuleConfig {
+                        enabled: Some(false),
+                        commands: None,
+                        locale: None,
+                    },
+                ),
+            ]),
+        })
+        .expect("config should compile");
+
+        assert!(commands.allows("telegram", "reset"));
+        assert!(commands.allows("slack", "status"));
+        assert!(!commands.allows("slack", "reset"));
+        assert!(!commands.allows("discord", "help"));
+        assert_eq!(commands.locale("slack"), Some("fr"));
+        assert_eq!(commands.locale("telegram"), Some("de"));
+
+        let invalid = ChatCommandsConfig {
+            defaults: ChatCommandsRuleConfig {
+                enabled: None,
+                commands: Some(vec!["sta tus".to_owned()]),
+                locale: None,
+            },
+            channels: BTreeMap::new(),
+        };
+        assert!(ChatCommands::compile(invalid).is_err());
+    }
+}
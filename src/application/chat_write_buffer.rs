@@ -0,0 +1,262 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify};
+use tracing::{info, warn};
+
+use crate::{
+    application::state::SharedState,
+    domain::{error::DomainError, models::ChatMessage},
+    storage::SqliteStore,
+};
+
+/// Buffered messages at which the appending caller flushes instead of waiting for the window.
+const MAX_BUFFERED_MESSAGES: usize = 512;
+/// Pause before retrying a flush that failed, so a broken database is not hammered.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// One `append_chat_messages` call: the session key and its messages.
+type PendingAppend = (String, Vec<ChatMessage>);
+
+/// Chat message appends waiting to be committed together. Appends are acknowledged once buffered;
+/// reads flush first, and shutdown flushes what is left or spills it next to the database.
+#[derive(Default)]
+pub struct ChatWriteBuffer {
+    pending: Mutex<Vec<PendingAppend>>,
+    /// Serializes flushes so batches commit in the order they were appended.
+    flushing: Mutex<()>,
+    wake: Notify,
+    closed: AtomicBool,
+}
+
+/// One buffered append as stored in the spill file.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpilledAppend {
+    session_key: String,
+    messages: Vec<ChatMessage>,
+}
+
+impl ChatWriteBuffer {
+    /// Buffers an append and reports whether the buffer is full enough that the caller should
+    /// flush now. Returns `None` once the buffer is closed and the caller has to write directly.
+    pub async fn push(&self, session_key: &str, messages: &[ChatMessage]) -> Option<bool> {
+        let mut pending = self.pending.lock().await;
+        if self.closed.load(Ordering::SeqCst) {
+            return None;
+        }
+        pending.push((session_key.to_owned(), messages.to_vec()));
+        self.wake.notify_one();
+        let buffered = pending
+            .iter()
+            .map(|(_, messages)| messages.len())
+            .sum::<usize>();
+        Some(buffered >= MAX_BUFFERED_MESSAGES)
+    }
+
+    /// Commits everything buffered in one transaction and returns the number of messages written.
+    /// On failure the messages stay buffered, ahead of any appended meanwhile.
+    pub async fn flush(&self, store: &SqliteStore) -> Result<usize, DomainError> {
+        let _flushing = self.flushing.lock().await;
+        let batch = std::mem::take(&mut *self.pending.lock().await);
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        match store.append_chat_message_batch(&batch).await {
+            Ok(()) => Ok(batch.iter().map(|(_, messages)| messages.len()).sum()),
+            Err(error) => {
+                let mut pending = self.pending.lock().await;
+                let appended = std::mem::replace(&mut *pending, batch);
+                pending.extend(appended);
+                Err(error)
+            }
+        }
+    }
+
+    /// Stops buffering, so later appends go straight to the store, and flushes the rest. What
+    /// cannot be committed is written to `spill_path` and replayed on the next start.
+    pub async fn close(&self, store: &SqliteStore, spill_path: &Path) {
+        {
+            let _pending = self.pending.lock().await;
+            self.closed.store(true, Ordering::SeqCst);
+        }
+        let error = match self.flush(store).await {
+            Ok(_) => return,
+            Err(error) => error,
+        };
+        let batch = std::mem::take(&mut *self.pending.lock().await);
+        let count = batch
+            .iter()
+            .map(|(_, messages)| messages.len())
+            .sum::<usize>();
+        match write_spill(spill_path, batch) {
+            Ok(()) => warn!(
+                "chat write flush failed on shutdown ({error}); spilled {count} message(s) to {}",
+                spill_path.display()
+            ),
+            Err(spill_error) => warn!(
+                "chat write flush failed on shutdown ({error}) and {count} message(s) could not be spilled: {spill_error}"
+            ),
+        }
+    }
+}
+
+/// File that holds buffered appends a shutdown could not commit.
+#[must_use]
+pub fn spill_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".chat-pending.jsonl");
+    db_path.with_file_name(name)
+}
+
+/// Flushes the buffer `window` after the first buffered append, retrying failed flushes.
+/// Returns `None` unless `chatWriteBatchMs` is configured.
+pub fn spawn_chat_write_flusher(state: SharedState) -> Option<tokio::task::JoinHandle<()>> {
+    let window = state.config().chat_write_batch?;
+    info!(
+        "batching chat message writes every {}ms",
+        window.as_millis()
+    );
+
+    Some(tokio::spawn(async move {
+        let Some(buffer) = state.chat_write_buffer() else {
+            return;
+        };
+        loop {
+            buffer.wake.notified().await;
+            tokio::time::sleep(window).await;
+            while let Err(error) = state.flush_chat_writes().await {
+                warn!("chat write flush failed: {error}");
+                tokio::time::sleep(RETRY_INTERVAL).await;
+            }
+        }
+    }))
+}
+
+/// Commits appends spilled by an earlier shutdown and removes the spill file.
+pub async fn recover_spilled(store: &SqliteStore, spill_path: &Path) -> Result<usize, DomainError> {
+    let batch = match read_spill(spill_path) {
+        Ok(Some(batch)) => batch,
+        Ok(None) => return Ok(0),
+        Err(error) => {
+            return Err(DomainError::Storage(format!(
+                "failed to read spilled chat writes from {}: {error}",
+                spill_path.display()
+            )));
+        }
+    };
+    let count = batch.iter().map(|(_, messages)| messages.len()).sum();
+    store.append_chat_message_batch(&batch).await?;
+    fs::remove_file(spill_path).map_err(|error| {
+        DomainError::Storage(format!(
+            "failed to remove spilled chat writes {}: {error}",
+            spill_path.display()
+        ))
+    })?;
+    info!("recovered {count} spilled chat message(s)");
+    Ok(count)
+}
+
+fn write_spill(path: &Path, batch: Vec<PendingAppend>) -> std::io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    for (session_key, messages) in batch {
+        let line = serde_json::to_string(&SpilledAppend {
+            session_key,
+            messages,
+        })?;
+        writeln!(file, "{line}")?;
+    }
+    file.sync_all()
+}
+
+fn read_spill(path: &Path) -> std::io::Result<Option<Vec<PendingAppend>>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    let mut batch = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let append: SpilledAppend = serde_json::from_str(&line)?;
+        batch.push((append.session_key, append.messages));
+    }
+    Ok(Some(batch))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{read_spill, spill_path, write_spill};
+    use crate::domain::models::ChatMessage;
+
+    fn message(id: &str) -> ChatMessage {
+        ChatMessage {
+            id: id.to_owned(),
+            role: "user".to_owned(),
+            text: format!("text of {id}"),
+            status: "final".to_owned(),
+            ts: 1,
+            metadata: serde_json::json!({}),
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn spill_path_sits_next_to_the_database() {
+        assert_eq!(
+            spill_path(Path::new("/var/lib/reclaw/reclaw.db")),
+            Path::new("/var/lib/reclaw/reclaw.db.chat-pending.jsonl")
+        );
+    }
+
+    #[test]
+    fn spilled_appends_round_trip_in_order() {
+        let dir = tempfile::tempdir().expect("temp dir should be created");
+        let path = dir.path().join("pending.jsonl");
+        assert!(read_spill(&path).expect("missing file reads").is_none());
+
+        write_spill(
+            &path,
+            vec![("agent:main:a".to_owned(), vec![message("m1")])],
+        )
+        .expect("spill should write");
+        write_spill(
+            &path,
+            vec![(
+                "agent:main:b".to_owned(),
+                vec![message("m2"), message("m3")],
+            )],
+        )
+        .expect("spill should append");
+
+        let batch = read_spill(&path)
+            .expect("spill should read")
+            .expect("spill file should exist");
+        let ids = batch
+            .iter()
+            .flat_map(|(session_key, messages)| {
+                messages
+                    .iter()
+                    .map(move |message| format!("{session_key}/{}", message.id))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec!["agent:main:a/m1", "agent:main:b/m2", "agent:main:b/m3"]
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+
+use regex_automata::meta::Regex;
+use serde_json::json;
+use tracing::warn;
+
+use crate::application::{
+    config::{
+        ContentAction, ContentActionsConfig, ContentPolicyConfig, ContentSeverity,
+        normalize_channel_plugin_key,
+    },
+    state::SharedState,
+};
+
+const CONTENT_POLICY_EVENT: &str = "content.policy";
+const DEFAULT_REPLACEMENT: &str = "***";
+
+/// Compiled form of the `contentPolicy` config.
+#[derive(Debug, Clone)]
+pub struct ContentPolicy {
+    rules: Vec<(ContentSeverity, Regex)>,
+    actions: SeverityActions,
+    channels: BTreeMap<String, ChannelOverride>,
+    replacement: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SeverityActions {
+    low: ContentAction,
+    medium: ContentAction,
+    high: ContentAction,
+}
+
+impl SeverityActions {
+    fn overlay(self, config: &ContentActionsConfig) -> Self {
+        Self {
+            low: config.low.unwrap_or(self.low),
+            medium: config.medium.unwrap_or(self.medium),
+            high: config.high.unwrap_or(self.high),
+        }
+    }
+
+    fn action(self, severity: ContentSeverity) -> ContentAction {
+        match severity {
+            ContentSeverity::Low => self.low,
+            ContentSeverity::Medium => self.medium,
+            ContentSeverity::High => self.high,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ChannelOverride {
+    enabled: bool,
+    actions: SeverityActions,
+}
+
+/// Outcome of checking one message that matched at least one rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentVerdict {
+    /// Strongest action among the matches.
+    pub action: ContentAction,
+    /// The message with redacted matches replaced.
+    pub text: String,
+    pub severity: ContentSeverity,
+    pub matches: usize,
+}
+
+impl ContentPolicy {
+    pub fn compile(config: ContentPolicyConfig) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for (index, rule) in config.rules.iter().enumerate() {
+            let words = rule
+                .words
+                .iter()
+                .map(|word| word.trim())
+                .filter(|word| !word.is_empty())
+                .map(|word| format!(r"(?i)\b{}\b", escape_literal(word)));
+            let patterns = rule
+                .patterns
+                .iter()
+                .map(|pattern| pattern.trim().to_owned())
+                .filter(|pattern| !pattern.is_empty());
+            let sources = words.chain(patterns).collect::<Vec<_>>();
+            if sources.is_empty() {
+                return Err(format!(
+                    "contentPolicy.rules[{index}] needs words or patterns"
+                ));
+            }
+            let regex = Regex::new_many(&sources).map_err(|error| {
+                format!("contentPolicy.rules[{index}] has an invalid pattern: {error}")
+            })?;
+            rules.push((rule.severity, regex));
+        }
+
+        let actions = SeverityActions {
+            low: ContentAction::Flag,
+            medium: ContentAction::Redact,
+            high: ContentAction::Block,
+        }
+        .overlay(&config.actions);
+        let mut channels = BTreeMap::new();
+        for (channel, channel_config) in config.channels {
+            let key = normalize_channel_plugin_key(&channel).ok_or_else(|| {
+                format!("contentPolicy.channels key must contain only [a-z0-9._-]: {channel}")
+            })?;
+            channels.insert(
+                key,
+                ChannelOverride {
+                    enabled: channel_config.enabled.unwrap_or(true),
+                    actions: actions.overlay(&channel_config.actions),
+                },
+            );
+        }
+
+        Ok(Self {
+            rules,
+            actions,
+            channels,
+            replacement: config
+                .replacement
+                .unwrap_or_else(|| DEFAULT_REPLACEMENT.to_owned()),
+        })
+    }
+
+    /// Returns `None` when the text is clean or the policy is disabled for `channel`.
+    #[must_use]
+    pub fn check(&self, channel: &str, text: &str) -> Option<ContentVerdict> {
+        let actions = match self.channels.get(channel) {
+            Some(channel) if !channel.enabled => return None,
+            Some(channel) => channel.actions,
+            None => self.actions,
+        };
+
+        let mut spans = Vec::new();
+        let mut strongest: Option<(ContentAction, ContentSeverity)> = None;
+        for (severity, regex) in &self.rules {
+            for found in regex.find_iter(text) {
+                let action = actions.action(*severity);
+                strongest = strongest.max(Some((action, *severity)));
+                spans.push((found.start(), found.end(), action));
+            }
+        }
+        let (action, severity) = strongest?;
+
+        spans.sort_unstable();
+        let mut redacted = String::with_capacity(text.len());
+        let mut cursor = 0;
+        for (start, end, span_action) in &spans {
+            if *span_action < ContentAction::Redact || *end <= cursor {
+                continue;
+            }
+            redacted.push_str(&text[cursor..(*start).max(cursor)]);
+            redacted.push_str(&self.replacement);
+            cursor = *end;
+        }
+        redacted.push_str(&text[cursor..]);
+
+        Some(ContentVerdict {
+            action,
+            text: redacted,
+            severity,
+            matches: spans.len(),
+        })
+    }
+}
+
+/// Escapes regex metacharacters so a configured word matches literally.
+fn escape_literal(word: &str) -> String {
+    let mut escaped = String::with_capacity(word.len());
+    for ch in word.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(ch) {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Applies the content policy to a channel message travelling in `direction` (`inbound` or
+/// `outbound`). Returns the text to pass on, or `None` when the message is blocked. Every match
+/// is counted and reported to operators with a `content.policy` event.
+pub async fn enforce(
+    state: &SharedState,
+    channel: &str,
+    direction: &'static str,
+    session_key: &str,
+    text: &str,
+) -> Option<String> {
+    let Some(policy) = &state.config().content_policy else {
+        return Some(text.to_owned());
+    };
+    let Some(verdict) = policy.check(channel, text) else {
+        return Some(text.to_owned());
+    };
+
+    state
+        .count_content_policy_action(channel, direction, verdict.action)
+        .await;
+    if verdict.action == ContentAction::Block {
+        let message = format!("content policy blocked an {direction} {channel} message");
+        warn!("{message} for session {session_key}");
+        let _ = state
+            .append_gateway_log("warn", &message, Some(CONTENT_POLICY_EVENT), None)
+            .await;
+    }
+    state
+        .publish_gateway_event(
+            CONTENT_POLICY_EVENT,
+            json!({
+                "action": verdict.action.label(),
+                "direction": direction,
+                "channel": channel,
+                "sessionKey": session_key,
+                "severity": verdict.severity.label(),
+                "matches": verdict.matches,
+                "text": text,
+            }),
+        )
+        .await;
+
+    (verdict.action != ContentAction::Block).then_some(verdict.text)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::ContentPolicy;
+    use crate::application::config::{
+        ContentAction, ContentActionsConfig, ContentChannelConfig, ContentPolicyConfig,
+        ContentRuleConfig, ContentSeverity,
+    };
+
+    fn policy() -> ContentPolicy {
+        ContentPolicy::compile(ContentPolicyConfig {
+            rules: vec![
+                ContentRuleConfig {
+                    severity: ContentSeverity::Medium,
+                    words: vec!["darn".to_owned(), "c++".to_owned()],
+                    patterns: Vec::new(),
+                },
+                ContentRuleConfig {
+                    severity: ContentSeverity::High,
+                    words: Vec::new(),
+                    patterns: vec![r"\d{4}-\d{4}-\d{4}-\d{4}".to_owned()],
+                },
+            ],
+            channels: BTreeMap::from([
+                (
+                    "Slack".to_owned(),
+                    ContentChannelConfig {
+                        enabled: None,
+                        actions: ContentActionsConfig {
+                            medium: Some(ContentAction::Flag),
+                            ..ContentActionsConfig::default()
+                        },
+                    },
+                ),
+                (
+                    "webchat".to_owned(),
+                    ContentChannelConfig {
+                        enabled: Some(false),
+                        actions: ContentActionsConfig::default(),
+                    },
+                ),
+            ]),
+            ..ContentPolicyConfig::default()
+        })
+        .expect("policy should compile")
+    }
+
+    #[test]
+    fn matches_redact_block_and_follow_channel_overrides() {
+        let policy = policy();
+        assert_eq!(policy.check("telegram", "darned good"), None);
+
+        let redacted = policy
+            .check("telegram", "Darn it, darn")
+            .expect("words should match");
+        assert_eq!(redacted.action, ContentAction::Redact);
+        assert_eq!(redacted.text, "*** it, ***");
+        assert_eq!(redacted.matches, 2);
+
+        let blocked = policy
+            .check("telegram", "darn, card 1234-5678-9012-3456")
+            .expect("pattern should match");
+        assert_eq!(blocked.action, ContentAction::Block);
+        assert_eq!(blocked.severity, ContentSeverity::High);
+
+        let flagged = policy.check("slack", "darn").expect("words should match");
+        assert_eq!(flagged.action, ContentAction::Flag);
+        assert_eq!(flagged.text, "darn");
+        assert_eq!(policy.check("webchat", "darn"), None);
+
+        assert!(
+            ContentPolicy::compile(ContentPolicyConfig {
+                rules: vec![ContentRuleConfig {
+                    severity: ContentSeverity::Low,
+                    words: Vec::new(),
+                    patterns: vec!["(".to_owned()],
+                }],
+                ..ContentPolicyConfig::default()
+            })
+            .is_err()
+        );
+    }
+}
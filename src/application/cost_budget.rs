@@ -0,0 +1,582 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde_json::{Value, json};
+
+use crate::{
+    application::{
+        config::{CostBudgetAction, CostBudgetConfig, CostBudgetsConfig},
+        state::SharedState,
+    },
+    domain::{
+        models::{AgentRunRecord, RunCostScope},
+        session_key::canonicalize_session_key,
+    },
+    protocol::{ERROR_UNAVAILABLE, ErrorShape},
+    rpc::dispatcher::map_domain_error,
+    storage::now_unix_ms,
+};
+
+/// Price of 1000 tokens `usage.cost` and unpriced models assume.
+pub const DEFAULT_PRICE_PER_1K_TOKENS_USD: f64 = 0.0025;
+/// Event published when spend crosses a budget's warning share or its limit.
+pub const BUDGET_EVENT: &str = "usage.budget";
+
+const DEFAULT_WARN_AT_PERCENT: u8 = 80;
+const CHARS_PER_TOKEN: usize = 4;
+const DAY_MS: u64 = 86_400_000;
+const WILDCARD: &str = "*";
+
+/// Compiled form of the `costBudgets` config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostBudgets {
+    price_per_1k_tokens_usd: f64,
+    model_prices: BTreeMap<String, f64>,
+    warn_at_percent: u8,
+    agents: BTreeMap<String, Budget>,
+    sessions: BTreeMap<String, Budget>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Budget {
+    daily_usd: Option<f64>,
+    monthly_usd: Option<f64>,
+    /// Set for `onExceeded = "downgrade"`; runs over budget are rejected otherwise.
+    downgrade_model: Option<String>,
+}
+
+/// Estimated size and price of one run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunCost {
+    pub tokens: u64,
+    pub usd: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Period {
+    Daily,
+    Monthly,
+}
+
+impl Period {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Monthly => "monthly",
+        }
+    }
+
+    /// Start of the UTC day or month containing `now_ms`, and the start of the next one.
+    fn window(self, now_ms: u64) -> (u64, u64) {
+        match self {
+            Self::Daily => {
+                let start = now_ms - now_ms % DAY_MS;
+                (start, start + DAY_MS)
+            }
+            Self::Monthly => {
+                let now = DateTime::<Utc>::from_timestamp_millis(
+                    i64::try_from(now_ms).unwrap_or(i64::MAX),
+                )
+                .unwrap_or_default();
+                let (next_year, next_month) = if now.month() == 12 {
+                    (now.year() + 1, 1)
+                } else {
+                    (now.year(), now.month() + 1)
+                };
+                (
+                    month_start_ms(now.year(), now.month()),
+                    month_start_ms(next_year, next_month),
+                )
+            }
+        }
+    }
+}
+
+fn month_start_ms(year: i32, month: u32) -> u64 {
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map_or(0, |start| {
+            u64::try_from(start.and_utc().timestamp_millis()).unwrap_or(0)
+        })
+}
+
+fn scope_name(scope: RunCostScope) -> &'static str {
+    match scope {
+        RunCostScope::Agent => "agent",
+        RunCostScope::Session => "session",
+    }
+}
+
+fn round_usd(value: f64) -> f64 {
+    (value * 10_000.0).round() / 10_000.0
+}
+
+impl CostBudgets {
+    pub fn compile(config: CostBudgetsConfig) -> Result<Self, String> {
+        let price_per_1k_tokens_usd = config
+            .price_per_1k_tokens_usd
+            .unwrap_or(DEFAULT_PRICE_PER_1K_TOKENS_USD);
+        if !(price_per_1k_tokens_usd.is_finite() && price_per_1k_tokens_usd >= 0.0) {
+            return Err("costBudgets.pricePer1kTokensUsd must not be negative".to_owned());
+        }
+        let mut model_prices = BTreeMap::new();
+        for (model, price) in config.model_prices {
+            if !(price.is_finite() && price >= 0.0) {
+                return Err(format!(
+                    "costBudgets.modelPrices.{model} must not be negative"
+                ));
+            }
+            model_prices.insert(model.trim().to_owned(), price);
+        }
+        let warn_at_percent = config.warn_at_percent.unwrap_or(DEFAULT_WARN_AT_PERCENT);
+        if !(1..=100).contains(&warn_at_percent) {
+            return Err("costBudgets.warnAtPercent must be between 1 and 100".to_owned());
+        }
+
+        let mut agents = BTreeMap::new();
+        for (agent_id, budget) in config.agents {
+            let agent_id = agent_id.trim().to_ascii_lowercase();
+            let budget = compile_budget(&format!("costBudgets.agents.{agent_id}"), budget)?;
+            agents.insert(agent_id, budget);
+        }
+        let mut sessions = BTreeMap::new();
+        for (session_key, budget) in config.sessions {
+            let session_key = if session_key.trim() == WILDCARD {
+                WILDCARD.to_owned()
+            } else {
+                canonicalize_session_key(&session_key)
+                    .map_err(|error| format!("costBudgets.sessions.{session_key}: {error}"))?
+            };
+            let budget = compile_budget(&format!("costBudgets.sessions.{session_key}"), budget)?;
+            sessions.insert(session_key, budget);
+        }
+
+        Ok(Self {
+            price_per_1k_tokens_usd,
+            model_prices,
+            warn_at_percent,
+            agents,
+            sessions,
+        })
+    }
+
+    /// Estimated cost of a run from the characters of its input and output.
+    #[must_use]
+    pub fn run_cost(&self, model: Option<&str>, input: &str, output: &str) -> RunCost {
+        let chars = input.chars().count() + output.chars().count();
+        let tokens = u64::try_from(chars.div_ceil(CHARS_PER_TOKEN)).unwrap_or(u64::MAX);
+        let price = model
+            .and_then(|model| self.model_prices.get(model))
+            .copied()
+            .unwrap_or(self.price_per_1k_tokens_usd);
+        RunCost {
+            tokens,
+            usd: tokens as f64 / 1_000.0 * price,
+        }
+    }
+
+    /// The budget `key` counts against and the config key it came from.
+    fn budget(&self, scope: RunCostScope, key: &str) -> Option<(&str, &Budget)> {
+        let budgets = match scope {
+            RunCostScope::Agent => &self.agents,
+            RunCostScope::Session => &self.sessions,
+        };
+        budgets
+            .get_key_value(key)
+            .or_else(|| budgets.get_key_value(WILDCARD))
+            .map(|(rule, budget)| (rule.as_str(), budget))
+    }
+}
+
+fn compile_budget(scope: &str, config: CostBudgetConfig) -> Result<Budget, String> {
+    for (field, limit) in [
+        ("dailyUsd", config.daily_usd),
+        ("monthlyUsd", config.monthly_usd),
+    ] {
+        if limit.is_some_and(|limit| !(limit.is_finite() && limit > 0.0)) {
+            return Err(format!("{scope}.{field} must be greater than 0"));
+        }
+    }
+    if config.daily_usd.is_none() && config.monthly_usd.is_none() {
+        return Err(format!("{scope} needs dailyUsd or monthlyUsd"));
+    }
+    let downgrade_model = match config.on_exceeded {
+        CostBudgetAction::Reject => None,
+        CostBudgetAction::Downgrade => Some(
+            config
+                .downgrade_model
+                .map(|model| model.trim().to_owned())
+                .filter(|model| !model.is_empty())
+                .ok_or_else(|| format!("{scope}.downgradeModel is required to downgrade"))?,
+        ),
+    };
+    Ok(Budget {
+        daily_usd: config.daily_usd,
+        monthly_usd: config.monthly_usd,
+        downgrade_model,
+    })
+}
+
+/// Spend of `key` in the current `period`.
+async fn spent(
+    state: &SharedState,
+    scope: RunCostScope,
+    key: &str,
+    period: Period,
+    now_ms: u64,
+) -> Result<f64, ErrorShape> {
+    let (since, _) = period.window(now_ms);
+    Ok(state
+        .agent_run_costs(scope, Some(key), since)
+        .await
+        .map_err(map_domain_error)?
+        .into_iter()
+        .map(|(_, usd)| usd)
+        .sum())
+}
+
+/// Checks the agent's and the session's budgets before a run. Returns the model a spent
+/// `downgrade` budget switches the run to; a spent `reject` budget fails with `UNAVAILABLE`.
+pub async fn admit(
+    state: &SharedState,
+    agent_id: &str,
+    session_key: &str,
+) -> Result<Option<String>, ErrorShape> {
+    let Some(budgets) = state.config().cost_budgets.as_ref() else {
+        return Ok(None);
+    };
+    let now = now_unix_ms();
+    let mut downgrade = None;
+    for (scope, key) in [
+        (RunCostScope::Agent, agent_id),
+        (RunCostScope::Session, session_key),
+    ] {
+        let Some((_, budget)) = budgets.budget(scope, key) else {
+            continue;
+        };
+        for (period, limit) in [
+            (Period::Daily, budget.daily_usd),
+            (Period::Monthly, budget.monthly_usd),
+        ] {
+            let Some(limit) = limit else {
+                continue;
+            };
+            let spent = spent(state, scope, key, period, now).await?;
+            if spent < limit {
+                continue;
+            }
+            match &budget.downgrade_model {
+                Some(model) => {
+                    downgrade.get_or_insert_with(|| model.clone());
+                }
+                None => {
+                    let (_, resets_at) = period.window(now);
+                    return Err(ErrorShape::new(
+                        ERROR_UNAVAILABLE,
+                        format!(
+                            "{} budget of {} {key} is spent: ${:.4} of ${limit:.4}",
+                            period.name(),
+                            scope_name(scope),
+                            round_usd(spent),
+                        ),
+                    )
+                    .with_details(json!({
+                        "scope": scope_name(scope),
+                        "id": key,
+                        "period": period.name(),
+                        "limitUsd": limit,
+                        "spentUsd": round_usd(spent),
+                        "resetsAtMs": resets_at,
+                    }))
+                    .with_retry(resets_at.saturating_sub(now)));
+                }
+            }
+        }
+    }
+    Ok(downgrade)
+}
+
+/// Stores the run's estimated cost under `metadata.cost` so budgets and `usage.status` count
+/// it. Does nothing without `costBudgets`.
+pub fn record_cost(state: &SharedState, run: &mut AgentRunRecord, model: Option<&str>) {
+    let Some(budgets) = state.config().cost_budgets.as_ref() else {
+        return;
+    };
+    let cost = budgets.run_cost(model, &run.input, &run.output);
+    if let Some(metadata) = run.metadata.as_object_mut() {
+        metadata.insert(
+            "cost".to_owned(),
+            json!({
+                "model": model,
+                "estimatedTokens": cost.tokens,
+                "usd": cost.usd,
+            }),
+        );
+    }
+}
+
+/// Publishes a `usage.budget` event for every budget whose warning share or limit the
+/// recorded run crossed.
+pub async fn publish_crossings(state: &SharedState, run: &AgentRunRecord) {
+    let Some(budgets) = state.config().cost_budgets.as_ref() else {
+        return;
+    };
+    let Some(cost) = run
+        .metadata
+        .pointer("/cost/usd")
+        .and_then(Value::as_f64)
+        .filter(|cost| *cost > 0.0)
+    else {
+        return;
+    };
+    let now = run.created_at_ms;
+    let keys = [
+        (RunCostScope::Agent, Some(run.agent_id.as_str())),
+        (RunCostScope::Session, run.session_key.as_deref()),
+    ];
+    for (scope, key) in keys {
+        let Some(key) = key else {
+            continue;
+        };
+        let Some((_, budget)) = budgets.budget(scope, key) else {
+            continue;
+        };
+        for (period, limit) in [
+            (Period::Daily, budget.daily_usd),
+            (Period::Monthly, budget.monthly_usd),
+        ] {
+            let Some(limit) = limit else {
+                continue;
+            };
+            let Ok(after) = spent(state, scope, key, period, now).await else {
+                continue;
+            };
+            let before = after - cost;
+            let warn_at = limit * f64::from(budgets.warn_at_percent) / 100.0;
+            let crossed = if before < limit && after >= limit {
+                "exceeded"
+            } else if before < warn_at && after >= warn_at {
+                "warning"
+            } else {
+                continue;
+            };
+            let message = format!(
+                "{} {key} reached {:.0}% of its {} budget (${:.4} of ${limit:.4})",
+                scope_name(scope),
+                after / limit * 100.0,
+                period.name(),
+                round_usd(after),
+            );
+            let _ = state
+                .append_gateway_log("warn", &message, Some("usage.budget"), None)
+                .await;
+            state
+                .publish_gateway_event(
+                    BUDGET_EVENT,
+                    json!({
+                        "scope": scope_name(scope),
+                        "id": key,
+                        "period": period.name(),
+                        "state": crossed,
+                        "limitUsd": limit,
+                        "spentUsd": round_usd(after),
+                        "percent": (after / limit * 100.0).round(),
+                        "action": if budget.downgrade_model.is_some() { "downgrade" } else { "reject" },
+                        "runId": run.id,
+                        "ts": now_unix_ms(),
+                    }),
+                )
+                .await;
+        }
+    }
+}
+
+/// `usage.status.budgets`: spend against every configured budget this day and month, or
+/// `null` without `costBudgets`. Wildcard budgets list each agent or session with spend.
+pub async fn status_payload(state: &SharedState) -> Result<Value, ErrorShape> {
+    let Some(budgets) = state.config().cost_budgets.as_ref() else {
+        return Ok(Value::Null);
+    };
+    let now = now_unix_ms();
+    let mut payload = json!({
+        "pricePer1kTokensUsd": budgets.price_per_1k_tokens_usd,
+        "modelPrices": budgets.model_prices,
+        "warnAtPercent": budgets.warn_at_percent,
+    });
+    for (scope, field, configured) in [
+        (RunCostScope::Agent, "agents", &budgets.agents),
+        (RunCostScope::Session, "sessions", &budgets.sessions),
+    ] {
+        let mut spend = BTreeMap::<String, [f64; 2]>::new();
+        for key in configured.keys().filter(|key| *key != WILDCARD) {
+            spend.insert(key.clone(), [0.0; 2]);
+        }
+        for (index, period) in [Period::Daily, Period::Monthly].into_iter().enumerate() {
+            let (since, _) = period.window(now);
+            for (key, usd) in state
+                .agent_run_costs(scope, None, since)
+                .await
+                .map_err(map_domain_error)?
+            {
+                if budgets.budget(scope, &key).is_some() {
+                    spend.entry(key).or_insert([0.0; 2])[index] = usd;
+                }
+            }
+        }
+        let entries = spend
+            .into_iter()
+            .filter_map(|(key, spent)| {
+                let (rule, budget) = budgets.budget(scope, &key)?;
+                Some(budget_status(budgets, &key, rule, budget, spent, now))
+            })
+            .collect::<Vec<_>>();
+        payload[field] = Value::Array(entries);
+    }
+    Ok(payload)
+}
+
+fn budget_status(
+    budgets: &CostBudgets,
+    key: &str,
+    rule: &str,
+    budget: &Budget,
+    spent: [f64; 2],
+    now_ms: u64,
+) -> Value {
+    let period_status = |period: Period, limit: Option<f64>, spent: f64| {
+        let limit = limit?;
+        let warn_at = limit * f64::from(budgets.warn_at_percent) / 100.0;
+        let state = if spent >= limit {
+            "exceeded"
+        } else if spent >= warn_at {
+            "warning"
+        } else {
+            "ok"
+        };
+        Some(json!({
+            "limitUsd": limit,
+            "spentUsd": round_usd(spent),
+            "percent": (spent / limit * 100.0).round(),
+            "state": state,
+            "resetsAtMs": period.window(now_ms).1,
+        }))
+    };
+    json!({
+        "id": key,
+        "budget": rule,
+        "onExceeded": if budget.downgrade_model.is_some() { "downgrade" } else { "reject" },
+        "downgradeModel": budget.downgrade_model,
+        "daily": period_status(Period::Daily, budget.daily_usd, spent[0]),
+        "monthly": period_status(Period::Monthly, budget.monthly_usd, spent[1]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{CostBudgets, Period};
+    use crate::{
+        application::config::{CostBudgetAction, CostBudgetConfig, CostBudgetsConfig},
+        domain::models::RunCostScope,
+    };
+
+    #[test]
+    fn budgets_compile_price_runs_and_resolve_wildcards() {
+        let budgets = CostBudgets::compile(CostBudgetsConfig {
+            model_prices: BTreeMap::from([("big".to_owned(), 0.01)]),
+            agents: BTreeMap::from([
+                (
+                    "Ops".to_owned(),
+                    CostBudgetConfig {
+                        daily_usd: Some(1.0),
+                        on_exceeded: CostBudgetAction::Downgrade,
+                        downgrade_model: Some("small".to_owned()),
+                        ..CostBudgetConfig::default()
+                    },
+                ),
+                (
+                    "*".to_owned(),
+                    CostBudgetConfig {
+                        monthly_usd: Some(5.0),
+                        ..CostBudgetConfig::default()
+                    },
+                ),
+            ]),
+            sessions: BTreeMap::from([(
+                "agent:main: main".to_owned(),
+                CostBudgetConfig {
+                    daily_usd: Some(0.5),
+                    ..CostBudgetConfig::default()
+                },
+            )]),
+            ..CostBudgetsConfig::default()
+        })
+        .expect("budgets should compile");
+
+        let cost = budgets.run_cost(Some("big"), "hello", "Echo: hello");
+        assert_eq!(cost.tokens, 4);
+        assert!((cost.usd - 0.00004).abs() < 1e-12);
+        assert!((budgets.run_cost(None, "abcd", "").usd - 0.0000025).abs() < 1e-12);
+
+        let (rule, ops) = budgets
+            .budget(RunCostScope::Agent, "ops")
+            .expect("ops has a budget");
+        assert_eq!(rule, "ops");
+        assert_eq!(ops.downgrade_model.as_deref(), Some("small"));
+        assert_eq!(
+            budgets
+                .budget(RunCostScope::Agent, "main")
+                .map(|(rule, _)| rule),
+            Some("*")
+        );
+        assert!(
+            budgets
+                .budget(RunCostScope::Session, "agent:main:main")
+                .is_some()
+        );
+        assert!(
+            budgets
+                .budget(RunCostScope::Session, "agent:main:other")
+                .is_none()
+        );
+
+        for invalid in [
+            CostBudgetConfig::default(),
+            CostBudgetConfig {
+                daily_usd: Some(0.0),
+                ..CostBudgetConfig::default()
+            },
+            CostBudgetConfig {
+                daily_usd: Some(1.0),
+                on_exceeded: CostBudgetAction::Downgrade,
+                ..CostBudgetConfig::default()
+            },
+        ] {
+            let config = CostBudgetsConfig {
+                agents: BTreeMap::from([("main".to_owned(), invalid)]),
+                ..CostBudgetsConfig::default()
+            };
+            assert!(CostBudgets::compile(config).is_err());
+        }
+    }
+
+    #[test]
+    fn periods_are_utc_days_and_calendar_months() {
+        // 2024-02-29T13:00:00Z
+        let now = 1_709_211_600_000;
+        assert_eq!(
+            Period::Daily.window(now),
+            (1_709_164_800_000, 1_709_251_200_000)
+        );
+        assert_eq!(
+            Period::Monthly.window(now),
+            (1_706_745_600_000, 1_709_251_200_000)
+        );
+        // 2024-12-31T23:59:59Z rolls over into January.
+        assert_eq!(
+            Period::Monthly.window(1_735_689_599_000).1,
+            1_735_689_600_000
+        );
+    }
+}
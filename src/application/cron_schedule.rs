@@ -1,6 +1,10 @@
-use chrono::{DateTime, Duration as ChronoDuration, Timelike, Utc};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, LocalResult, NaiveDate, NaiveDateTime, Offset,
+    SecondsFormat, TimeZone, Timelike, Utc,
+};
+use chrono_tz::Tz;
 
-use crate::domain::models::CronSchedule;
+use crate::domain::models::{CronJobRecord, CronSchedule};
 
 pub fn compute_next_run_ms(schedule: &CronSchedule, from_ms: u64) -> Result<Option<u64>, String> {
     match schedule.kind.as_str() {
@@ -48,7 +52,8 @@ pub fn compute_next_run_ms(schedule: &CronSchedule, from_ms: u64) -> Result<Opti
                 .map(str::trim)
                 .filter(|expr| !expr.is_empty())
                 .ok_or_else(|| "schedule.expr is required for kind=cron".to_owned())?;
-            let next = compute_next_cron_time(expr, from_ms)?;
+            let tz = schedule_timezone(schedule)?;
+            let next = compute_next_cron_time(expr, tz, parse_dst_policy(schedule)?, from_ms)?;
             Ok(Some(next))
         }
         "once" => Ok(None),
@@ -56,6 +61,266 @@ pub fn compute_next_run_ms(schedule: &CronSchedule, from_ms: u64) -> Result<Opti
     }
 }
 
+/// Human-readable schedule summary shared by every client, e.g.
+/// `every weekday at 09:00 Europe/Berlin, next run in 3h`. Descriptions are English; a US locale
+/// (`en-US`) switches clock times to the 12-hour form.
+#[must_use]
+pub fn describe_schedule(
+    schedule: &CronSchedule,
+    next_run_ms: Option<u64>,
+    now_ms: u64,
+    locale: &str,
+) -> String {
+    let twelve_hour = uses_twelve_hour_clock(locale);
+    let mut description = match schedule.kind.as_str() {
+        "at" => schedule.at.as_deref().map(str::trim).map_or_else(
+            || "once".to_owned(),
+            |at| match parse_rfc3339_ms(at)
+                .ok()
+                .and_then(|ms| DateTime::<Utc>::from_timestamp_millis(i64::try_from(ms).ok()?))
+            {
+                Some(at) => format!(
+                    "once on {} at {} UTC",
+                    at.format("%Y-%m-%d"),
+                    format_clock(at.hour(), at.minute(), twelve_hour)
+                ),
+                None => format!("once at {at}"),
+            },
+        ),
+        "every" => schedule
+            .every_ms
+            .map_or_else(|| "every interval".to_owned(), describe_interval),
+        "cron" => {
+            let expr = schedule.expr.as_deref().unwrap_or_default().trim();
+            let (text, has_clock) = describe_cron_expr(expr, twelve_hour);
+            match schedule.tz.as_deref().map(str::trim) {
+                Some(tz) if has_clock && !tz.is_empty() => format!("{text} {tz}"),
+                _ => text,
+            }
+        }
+        "once" => "once".to_owned(),
+        other => format!("{other} schedule"),
+    };
+
+    if let Some(next_run_ms) = next_run_ms {
+        if next_run_ms <= now_ms {
+            description.push_str(", next run due now");
+        } else {
+            description.push_str(", next run in ");
+            description.push_str(&format_relative(next_run_ms - now_ms));
+        }
+    }
+    description
+}
+
+/// [`describe_schedule`] for a stored job; disabled jobs say so instead of naming a next run.
+#[must_use]
+pub fn describe_job(job: &CronJobRecord, now_ms: u64, locale: &str) -> String {
+    if job.enabled {
+        describe_schedule(&job.schedule, job.next_run_ms, now_ms, locale)
+    } else {
+        format!(
+            "{}, disabled",
+            describe_schedule(&job.schedule, None, now_ms, locale)
+        )
+    }
+}
+
+fn uses_twelve_hour_clock(locale: &str) -> bool {
+    matches!(
+        locale
+            .trim()
+            .replace('_', "-")
+            .to_ascii_lowercase()
+            .as_str(),
+        "en-us" | "en-ca" | "en-au" | "en-ph"
+    )
+}
+
+fn format_clock(hour: u32, minute: u32, twelve_hour: bool) -> String {
+    if !twelve_hour {
+        return format!("{hour:02}:{minute:02}");
+    }
+    let suffix = if hour < 12 { "AM" } else { "PM" };
+    let display = match hour % 12 {
+        0 => 12,
+        other => other,
+    };
+    format!("{display}:{minute:02} {suffix}")
+}
+
+fn describe_interval(every_ms: u64) -> String {
+    const UNITS: &[(u64, &str)] = &[
+        (86_400_000, "day"),
+        (3_600_000, "hour"),
+        (60_000, "minute"),
+        (1_000, "second"),
+    ];
+    for (unit_ms, unit) in UNITS {
+        if every_ms >= *unit_ms && every_ms.is_multiple_of(*unit_ms) {
+            let count = every_ms / unit_ms;
+            return if count == 1 {
+                format!("every {unit}")
+            } else {
+                format!("every {count} {unit}s")
+            };
+        }
+    }
+    format!("every {every_ms}ms")
+}
+
+fn format_relative(delta_ms: u64) -> String {
+    let seconds = delta_ms.div_ceil(1_000);
+    let (days, hours, minutes) = (
+        seconds / 86_400,
+        (seconds % 86_400) / 3_600,
+        (seconds % 3_600) / 60,
+    );
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{seconds}s"),
+        (0, 0, minutes) => format!("{minutes}m"),
+        (0, hours, 0) => format!("{hours}h"),
+        (0, hours, minutes) => format!("{hours}h {minutes}m"),
+        (days, 0, _) => format!("{days}d"),
+        (days, hours, _) => format!("{days}d {hours}h"),
+    }
+}
+
+/// Returns the description and whether it names a clock time (so a timezone applies).
+fn describe_cron_expr(expr: &str, twelve_hour: bool) -> (String, bool) {
+    let fallback = (format!("cron \"{expr}\""), false);
+    let mut parts = expr.split_whitespace().collect::<Vec<_>>();
+    if parts.len() == 6 {
+        parts.remove(0);
+    }
+    let [minute, hour, dom, month, dow] = parts[..] else {
+        return fallback;
+    };
+    let Some((every_days, on_days)) = describe_days(dom, month, dow) else {
+        return fallback;
+    };
+
+    if let (Ok(minute), Ok(hour)) = (minute.parse::<u32>(), hour.parse::<u32>()) {
+        if minute > 59 || hour > 23 {
+            return fallback;
+        }
+        return (
+            format!(
+                "{every_days} at {}",
+                format_clock(hour, minute, twelve_hour)
+            ),
+            true,
+        );
+    }
+
+    let frequency = match (minute, hour) {
+        ("*", "*") => "every minute".to_owned(),
+        (minute, "*") => {
+            if let Some(step) = minute
+                .strip_prefix("*/")
+                .and_then(|s| s.parse::<u32>().ok())
+            {
+                format!("every {step} minutes")
+            } else if let Ok(minute) = minute.parse::<u32>() {
+                format!("hourly at :{minute:02}")
+            } else {
+                return fallback;
+            }
+        }
+        (minute, hour) => {
+            match (
+                minute.parse::<u32>(),
+                hour.strip_prefix("*/").and_then(|s| s.parse::<u32>().ok()),
+            ) {
+                (Ok(minute), Some(step)) => format!("every {step} hours at :{minute:02}"),
+                _ => return fallback,
+            }
+        }
+    };
+    if on_days.is_empty() {
+        (frequency, false)
+    } else {
+        (format!("{frequency} {on_days}"), false)
+    }
+}
+
+/// Describes the day fields as (`every weekday`, `on weekdays`); the second form qualifies a
+/// sub-daily frequency and is empty for every day.
+fn describe_days(dom: &str, month: &str, dow: &str) -> Option<(String, String)> {
+    const WEEKDAYS: [&str; 7] = [
+        "Sunday",
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+    ];
+    const MONTHS: [&str; 12] = [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ];
+
+    match (dom, month, dow) {
+        ("*", "*", "*") => Some(("every day".to_owned(), String::new())),
+        ("*", "*", "1-5" | "MON-FRI" | "mon-fri") => {
+            Some(("every weekday".to_owned(), "on weekdays".to_owned()))
+        }
+        ("*", "*", "0,6" | "6,0" | "6-7" | "SAT,SUN" | "sat,sun") => {
+            Some(("every weekend day".to_owned(), "on weekends".to_owned()))
+        }
+        ("*", "*", days) => {
+            let names = days
+                .split(',')
+                .map(|day| {
+                    let index = day.parse::<usize>().ok()? % 7;
+                    Some(WEEKDAYS[index])
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let list = names.join(", ");
+            let plural = names
+                .iter()
+                .map(|name| format!("{name}s"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Some((format!("every {list}"), format!("on {plural}")))
+        }
+        (day, "*", "*") => {
+            let day = day
+                .parse::<u32>()
+                .ok()
+                .filter(|day| (1..=31).contains(day))?;
+            let text = format!("on day {day} of every month");
+            Some((text.clone(), text))
+        }
+        (day, month, "*") => {
+            let day = day
+                .parse::<u32>()
+                .ok()
+                .filter(|day| (1..=31).contains(day))?;
+            let month = month
+                .parse::<usize>()
+                .ok()
+                .and_then(|month| MONTHS.get(month.checked_sub(1)?))?;
+            Some((
+                format!("every year on {month} {day}"),
+                format!("on {month} {day}"),
+            ))
+        }
+        _ => None,
+    }
+}
+
 fn parse_rfc3339_ms(value: &str) -> Result<u64, String> {
     let parsed = DateTime::parse_from_rfc3339(value)
         .map_err(|error| format!("invalid RFC3339 timestamp: {error}"))?;
@@ -66,101 +331,285 @@ fn parse_rfc3339_ms(value: &str) -> Result<u64, String> {
     Ok(u64::try_from(millis).unwrap_or(u64::MAX))
 }
 
-fn compute_next_cron_time(expr: &str, from_ms: u64) -> Result<u64, String> {
-    let parts = expr.split_whitespace().collect::<Vec<_>>();
-    if parts.len() != 5 && parts.len() != 6 {
-        return Err("cron expression must contain 5 or 6 fields".to_owned());
-    }
-
-    let minute_index = if parts.len() == 6 { 1 } else { 0 };
-    let hour_index = minute_index + 1;
-    let dom_index = minute_index + 2;
-    let month_index = minute_index + 3;
-    let dow_index = minute_index + 4;
+fn compute_next_cron_time(expr: &str, tz: Tz, dst: DstPolicy, from_ms: u64) -> Result<u64, String> {
+    let cron = CronExpr::parse(expr)?;
+    let from = i64::try_from(from_ms).unwrap_or(i64::MAX);
+    let start = DateTime::<Utc>::from_timestamp_millis(from)
+        .ok_or_else(|| "invalid timestamp for cron computation".to_owned())?
+        .with_timezone(&tz)
+        .date_naive();
+    // Hour-wildcard expressions follow the wall clock through transitions, like Vixie cron.
+    let wall_clock = cron.hours.wildcard;
 
-    if parts[hour_index] != "*"
-        || parts[dom_index] != "*"
-        || parts[month_index] != "*"
-        || parts[dow_index] != "*"
+    // A DST shift can move a later local time before an earlier one, so keep looking a few
+    // local hours past the first hit.
+    let mut best: Option<i64> = None;
+    let mut horizon: Option<NaiveDateTime> = None;
+    'search: for day in start
+        .pred_opt()
+        .unwrap_or(start)
+        .iter_days()
+        .take(CRON_SEARCH_DAYS)
     {
-        return Err("only minute-based cron expressions are supported currently".to_owned());
+        if !cron.matches_day(day) {
+            continue;
+        }
+        for hour in &cron.hours.values {
+            for minute in &cron.minutes.values {
+                let Some(local) = day.and_hms_opt(*hour, *minute, 0) else {
+                    continue;
+                };
+                if horizon.is_some_and(|horizon| local > horizon) {
+                    break 'search;
+                }
+                for instant in resolve_local_time(tz, local, dst, wall_clock)
+                    .into_iter()
+                    .flatten()
+                {
+                    if instant > from && best.is_none_or(|best| instant < best) {
+                        best = Some(instant);
+                        horizon.get_or_insert(local + ChronoDuration::hours(3));
+                    }
+                }
+            }
+        }
     }
 
-    let matcher = parse_minute_matcher(parts[minute_index])?;
-    let start = DateTime::<Utc>::from_timestamp_millis(i64::try_from(from_ms).unwrap_or(i64::MAX))
-        .ok_or_else(|| "invalid timestamp for cron computation".to_owned())?;
+    best.map(|best| u64::try_from(best).unwrap_or(u64::MAX))
+        .ok_or_else(|| "unable to compute next cron occurrence within 4 years".to_owned())
+}
 
-    for offset in 1..=(60 * 24 * 7) {
-        let candidate = (start + ChronoDuration::minutes(offset))
-            .with_second(0)
-            .and_then(|value| value.with_nanosecond(0))
-            .ok_or_else(|| "failed to normalize cron candidate".to_owned())?;
+/// Days searched for the next cron occurrence; long enough for a February 29 schedule.
+const CRON_SEARCH_DAYS: usize = 4 * 366 + 2;
 
-        if matcher.matches(candidate.minute()) {
-            let ms = candidate.timestamp_millis();
-            return Ok(u64::try_from(ms).unwrap_or(u64::MAX));
-        }
+/// How cron times that a DST transition skips or repeats are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DstPolicy {
+    /// Repeated times run on their first occurrence; skipped times run shifted forward by the
+    /// gap (02:30 becomes 03:30).
+    RunOnce,
+    /// Repeated and skipped times do not run that day.
+    Skip,
+}
+
+fn parse_dst_policy(schedule: &CronSchedule) -> Result<DstPolicy, String> {
+    match schedule.dst.as_deref().map(str::trim) {
+        None | Some("" | "runOnce") => Ok(DstPolicy::RunOnce),
+        Some("skip") => Ok(DstPolicy::Skip),
+        Some(other) => Err(format!("schedule.dst must be runOnce or skip, got {other}")),
     }
+}
 
-    Err("unable to compute next cron occurrence in 7-day search window".to_owned())
+/// Timezone `schedule` is evaluated in; UTC when `tz` is unset.
+pub fn schedule_timezone(schedule: &CronSchedule) -> Result<Tz, String> {
+    match schedule
+        .tz
+        .as_deref()
+        .map(str::trim)
+        .filter(|tz| !tz.is_empty())
+    {
+        Some(name) => name
+            .parse::<Tz>()
+            .map_err(|_| format!("schedule.tz is not a known IANA zone: {name}")),
+        None => Ok(chrono_tz::UTC),
+    }
 }
 
-struct MinuteMatcher {
-    mode: MinuteMatchMode,
+/// The job's resolved timezone and its next run as an RFC 3339 time in that zone. Jobs with an
+/// unknown zone are reported in UTC.
+#[must_use]
+pub fn local_next_run(job: &CronJobRecord) -> (String, Option<String>) {
+    let tz = schedule_timezone(&job.schedule).unwrap_or(chrono_tz::UTC);
+    let local = job
+        .next_run_ms
+        .and_then(|ms| DateTime::<Utc>::from_timestamp_millis(i64::try_from(ms).ok()?))
+        .map(|at| {
+            at.with_timezone(&tz)
+                .to_rfc3339_opts(SecondsFormat::Secs, false)
+        });
+    (tz.name().to_owned(), local)
 }
 
-impl MinuteMatcher {
-    fn matches(&self, minute: u32) -> bool {
-        match self.mode {
-            MinuteMatchMode::Any => true,
-            MinuteMatchMode::Every(step) => minute.is_multiple_of(step),
-            MinuteMatchMode::Exact(value) => minute == value,
+/// UTC instants (ms) at which local time `local` in `tz` runs: none, one, or both occurrences
+/// of a repeated time.
+fn resolve_local_time(
+    tz: Tz,
+    local: NaiveDateTime,
+    dst: DstPolicy,
+    wall_clock: bool,
+) -> [Option<i64>; 2] {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(at) => [Some(at.timestamp_millis()), None],
+        LocalResult::Ambiguous(first, second) if wall_clock => [
+            Some(first.timestamp_millis()),
+            Some(second.timestamp_millis()),
+        ],
+        LocalResult::Ambiguous(first, _) => match dst {
+            DstPolicy::RunOnce => [Some(first.timestamp_millis()), None],
+            DstPolicy::Skip => [None, None],
+        },
+        LocalResult::None if wall_clock || dst == DstPolicy::Skip => [None, None],
+        LocalResult::None => {
+            // Read the skipped time with the offset in force before the gap.
+            let shifted = tz
+                .from_local_datetime(&(local - ChronoDuration::hours(3)))
+                .earliest()
+                .and_then(|before| before.offset().fix().from_local_datetime(&local).single())
+                .map(|at| at.timestamp_millis());
+            [shifted, None]
         }
     }
 }
 
-enum MinuteMatchMode {
-    Any,
-    Every(u32),
-    Exact(u32),
+/// A parsed 5-field (or 6-field, seconds first and ignored) cron expression.
+struct CronExpr {
+    minutes: CronField,
+    hours: CronField,
+    days_of_month: CronField,
+    months: CronField,
+    days_of_week: CronField,
 }
 
-fn parse_minute_matcher(field: &str) -> Result<MinuteMatcher, String> {
-    let trimmed = field.trim();
-    if trimmed == "*" {
-        return Ok(MinuteMatcher {
-            mode: MinuteMatchMode::Any,
-        });
+impl CronExpr {
+    fn parse(expr: &str) -> Result<Self, String> {
+        const MONTH_NAMES: &[&str] = &[
+            "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+        ];
+        const WEEKDAY_NAMES: &[&str] = &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+        let mut parts = expr.split_whitespace().collect::<Vec<_>>();
+        if parts.len() == 6 {
+            parts.remove(0);
+        }
+        let [minute, hour, dom, month, dow] = parts[..] else {
+            return Err("cron expression must contain 5 or 6 fields".to_owned());
+        };
+
+        let mut days_of_week = CronField::parse(dow, "day-of-week", 0, 7, WEEKDAY_NAMES, 0)?;
+        // Both 0 and 7 are Sunday.
+        if days_of_week.values.contains(&7) {
+            days_of_week.values.retain(|day| *day != 7);
+            if !days_of_week.values.contains(&0) {
+                days_of_week.values.insert(0, 0);
+            }
+        }
+        Ok(Self {
+            minutes: CronField::parse(minute, "minute", 0, 59, &[], 0)?,
+            hours: CronField::parse(hour, "hour", 0, 23, &[], 0)?,
+            days_of_month: CronField::parse(dom, "day-of-month", 1, 31, &[], 0)?,
+            months: CronField::parse(month, "month", 1, 12, MONTH_NAMES, 1)?,
+            days_of_week,
+        })
     }
 
-    if let Some(step_text) = trimmed.strip_prefix("*/") {
-        let step = step_text
-            .parse::<u32>()
-            .map_err(|_| "invalid minute step in cron expression".to_owned())?;
-        if !(1..=59).contains(&step) {
-            return Err("minute step must be between 1 and 59".to_owned());
+    /// Day-of-month and day-of-week match either way when both are restricted.
+    fn matches_day(&self, day: NaiveDate) -> bool {
+        if !self.months.values.contains(&day.month()) {
+            return false;
+        }
+        let dom = self.days_of_month.values.contains(&day.day());
+        let dow = self
+            .days_of_week
+            .values
+            .contains(&day.weekday().num_days_from_sunday());
+        match (self.days_of_month.wildcard, self.days_of_week.wildcard) {
+            (true, true) => true,
+            (true, false) => dow,
+            (false, true) => dom,
+            (false, false) => dom || dow,
         }
-        return Ok(MinuteMatcher {
-            mode: MinuteMatchMode::Every(step),
-        });
     }
+}
 
-    let value = trimmed
-        .parse::<u32>()
-        .map_err(|_| "invalid minute value in cron expression".to_owned())?;
-    if value > 59 {
-        return Err("minute value must be between 0 and 59".to_owned());
+/// The values one cron field matches, in ascending order.
+struct CronField {
+    values: Vec<u32>,
+    /// The field starts with `*`, so it does not restrict on its own.
+    wildcard: bool,
+}
+
+impl CronField {
+    fn parse(
+        text: &str,
+        label: &str,
+        min: u32,
+        max: u32,
+        names: &[&str],
+        first_name: u32,
+    ) -> Result<Self, String> {
+        let parse_value = |value: &str| -> Result<u32, String> {
+            let parsed = match names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(value))
+            {
+                Some(index) => u32::try_from(index).unwrap_or(u32::MAX) + first_name,
+                None => value
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid {label} value in cron expression"))?,
+            };
+            if (min..=max).contains(&parsed) {
+                Ok(parsed)
+            } else {
+                Err(format!("{label} value must be between {min} and {max}"))
+            }
+        };
+
+        let mut values = Vec::new();
+        for item in text.trim().split(',') {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => {
+                    let step = step
+                        .parse::<u32>()
+                        .map_err(|_| format!("invalid {label} step in cron expression"))?;
+                    if !(1..=max).contains(&step) {
+                        return Err(format!("{label} step must be between 1 and {max}"));
+                    }
+                    (range, step)
+                }
+                None => (item, 1),
+            };
+            let (low, high) = match range {
+                "*" => (min, max),
+                range => match range.split_once('-') {
+                    Some((low, high)) => (parse_value(low)?, parse_value(high)?),
+                    None => {
+                        let value = parse_value(range)?;
+                        (value, if item.contains('/') { max } else { value })
+                    }
+                },
+            };
+            if low > high {
+                return Err(format!("invalid {label} range in cron expression"));
+            }
+            values.extend((low..=high).step_by(usize::try_from(step).unwrap_or(1)));
+        }
+        values.sort_unstable();
+        values.dedup();
+        Ok(Self {
+            values,
+            wildcard: text.trim().starts_with('*'),
+        })
     }
-    Ok(MinuteMatcher {
-        mode: MinuteMatchMode::Exact(value),
-    })
 }
 
 #[cfg(test)]
 mod tests {
     use crate::domain::models::CronSchedule;
 
-    use super::compute_next_run_ms;
+    use super::{compute_next_run_ms, describe_schedule, local_next_run};
+
+    fn schedule(kind: &str) -> CronSchedule {
+        CronSchedule {
+            kind: kind.to_owned(),
+            at: None,
+            every_ms: None,
+            anchor_ms: None,
+            expr: None,
+            tz: None,
+            stagger_ms: None,
+            dst: None,
+        }
+    }
 
     #[test]
     fn cron_every_schedule_computes_next_run() {
@@ -172,6 +621,7 @@ mod tests {
             expr: None,
             tz: None,
             stagger_ms: None,
+            dst: None,
         };
         let next = compute_next_run_ms(&schedule, 10).expect("next run should compute");
         assert_eq!(next, Some(1_010));
@@ -187,9 +637,240 @@ mod tests {
             expr: Some("* * * * *".to_owned()),
             tz: Some("UTC".to_owned()),
             stagger_ms: None,
+            dst: None,
         };
         let now = 1_700_000_000_000_u64;
         let next = compute_next_run_ms(&schedule, now).expect("cron next run should compute");
         assert!(next.expect("next run should exist") > now);
     }
+
+    #[test]
+    fn schedule_descriptions_cover_kinds_locales_and_next_run() {
+        let now = 1_700_000_000_000_u64;
+        let weekday = CronSchedule {
+            expr: Some("0 9 * * 1-5".to_owned()),
+            tz: Some("Europe/Berlin".to_owned()),
+            ..schedule("cron")
+        };
+        assert_eq!(
+            describe_schedule(&weekday, Some(now + 3 * 3_600_000), now, "en"),
+            "every weekday at 09:00 Europe/Berlin, next run in 3h"
+        );
+        assert_eq!(
+            describe_schedule(&weekday, None, now, "en_US"),
+            "every weekday at 9:00 AM Europe/Berlin"
+        );
+
+        let every = CronSchedule {
+            every_ms: Some(15 * 60_000),
+            ..schedule("every")
+        };
+        assert_eq!(
+            describe_schedule(&every, Some(now + 90_000), now, "en"),
+            "every 15 minutes, next run in 1m"
+        );
+
+        let quarter_hourly = CronSchedule {
+            expr: Some("*/15 * * * 1,3".to_owned()),
+            tz: Some("UTC".to_owned()),
+            ..schedule("cron")
+        };
+        assert_eq!(
+            describe_schedule(&quarter_hourly, Some(now), now, "en"),
+            "every 15 minutes on Mondays, Wednesdays, next run due now"
+        );
+
+        let at = CronSchedule {
+            at: Some("2024-06-01T18:30:00Z".to_owned()),
+            ..schedule("at")
+        };
+        assert_eq!(
+            describe_schedule(&at, None, now, "en-US"),
+            "once on 2024-06-01 at 6:30 PM UTC"
+        );
+
+        let odd = CronSchedule {
+            expr: Some("0 9 L * *".to_owned()),
+            ..schedule("cron")
+        };
+        assert_eq!(
+            describe_schedule(&odd, None, now, "en"),
+            "cron \"0 9 L * *\""
+        );
+    }
+
+    fn ms(rfc3339: &str) -> u64 {
+        u64::try_from(
+            chrono::DateTime::parse_from_rfc3339(rfc3339)
+                .expect("timestamp should parse")
+                .timestamp_millis(),
+        )
+        .expect("timestamp should be positive")
+    }
+
+    fn cron(expr: &str, tz: &str, dst: Option<&str>) -> CronSchedule {
+        CronSchedule {
+            expr: Some(expr.to_owned()),
+            tz: Some(tz.to_owned()),
+            dst: dst.map(str::to_owned),
+            ..schedule("cron")
+        }
+    }
+
+    fn runs(schedule: &CronSchedule, from: &str, count: usize) -> Vec<u64> {
+        let mut from = ms(from);
+        (0..count)
+            .map(|_| {
+                from = compute_next_run_ms(schedule, from)
+                    .expect("next run should compute")
+                    .expect("next run should exist");
+                from
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cron_fields_evaluate_in_the_schedule_timezone() {
+        let weekday = cron("0 9 * * MON-FRI", "Europe/Berlin", None);
+        // Friday 2024-06-07 10:00 Berlin: next is Monday 09:00 CEST.
+        assert_eq!(
+            runs(&weekday, "2024-06-07T08:00:00Z", 2),
+            vec![ms("2024-06-10T07:00:00Z"), ms("2024-06-11T07:00:00Z")]
+        );
+
+        let hourly = cron("0 * * * *", "Asia/Kolkata", None);
+        assert_eq!(
+            runs(&hourly, "2024-06-07T08:00:00Z", 1),
+            vec![ms("2024-06-07T08:30:00Z")]
+        );
+
+        let leap_day = cron("30 6 29 2 *", "UTC", None);
+        assert_eq!(
+            runs(&leap_day, "2024-03-01T00:00:00Z", 1),
+            vec![ms("2028-02-29T06:30:00Z")]
+        );
+
+        let day_or_weekday = cron("0 0 1 * SUN", "UTC", None);
+        assert_eq!(
+            runs(&day_or_weekday, "2024-06-01T12:00:00Z", 2),
+            vec![ms("2024-06-02T00:00:00Z"), ms("2024-06-09T00:00:00Z")]
+        );
+
+        for (expr, tz) in [
+            ("0 25 * * *", "UTC"),
+            ("0 9 * 13 *", "UTC"),
+            ("0 9 * * 8", "UTC"),
+            ("0 9 * *", "UTC"),
+            ("0 9 * * *", "Mars/Olympus"),
+        ] {
+            assert!(
+                compute_next_run_ms(&cron(expr, tz, None), 0).is_err(),
+                "{expr} {tz}"
+            );
+        }
+        assert!(compute_next_run_ms(&cron("0 9 * * *", "UTC", Some("twice")), 0).is_err());
+    }
+
+    #[test]
+    fn cron_dst_gaps_run_shifted_or_skip() {
+        // Europe/Berlin springs forward from 02:00 to 03:00 on 2024-03-31.
+        let run_once = cron("30 2 * * *", "Europe/Berlin", None);
+        assert_eq!(
+            runs(&run_once, "2024-03-30T12:00:00Z", 2),
+            vec![
+                ms("2024-03-31T03:30:00+02:00"),
+                ms("2024-04-01T02:30:00+02:00")
+            ]
+        );
+
+        let skip = cron("30 2 * * *", "Europe/Berlin", Some("skip"));
+        assert_eq!(
+            runs(&skip, "2024-03-30T12:00:00Z", 1),
+            vec![ms("2024-04-01T02:30:00+02:00")]
+        );
+
+        // Hour-wildcard schedules follow the wall clock and have nothing to shift.
+        let every_half_hour = cron("*/30 * * * *", "Europe/Berlin", None);
+        assert_eq!(
+            runs(&every_half_hour, "2024-03-31T01:10:00+01:00", 3),
+            vec![
+                ms("2024-03-31T01:30:00+01:00"),
+                ms("2024-03-31T03:00:00+02:00"),
+                ms("2024-03-31T03:30:00+02:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn cron_dst_repeated_times_run_once_or_skip() {
+        // Europe/Berlin falls back from 03:00 to 02:00 on 2024-10-27.
+        let run_once = cron("30 2 * * *", "Europe/Berlin", Some("runOnce"));
+        assert_eq!(
+            runs(&run_once, "2024-10-26T12:00:00Z", 2),
+            vec![
+                ms("2024-10-27T02:30:00+02:00"),
+                ms("2024-10-28T02:30:00+01:00")
+            ]
+        );
+
+        let skip = cron("30 2 * * *", "Europe/Berlin", Some("skip"));
+        assert_eq!(
+            runs(&skip, "2024-10-26T12:00:00Z", 1),
+            vec![ms("2024-10-28T02:30:00+01:00")]
+        );
+
+        let every_half_hour = cron("*/30 * * * *", "Europe/Berlin", None);
+        assert_eq!(
+            runs(&every_half_hour, "2024-10-27T02:10:00+02:00", 4),
+            vec![
+                ms("2024-10-27T02:30:00+02:00"),
+                ms("2024-10-27T02:00:00+01:00"),
+                ms("2024-10-27T02:30:00+01:00"),
+                ms("2024-10-27T03:00:00+01:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn local_next_run_reports_the_resolved_zone() {
+        let mut job = crate::domain::models::CronJobRecord {
+            id: "job".to_owned(),
+            name: "job".to_owned(),
+            enabled: true,
+            schedule: cron("30 2 * * *", "America/New_York", None),
+            payload: crate::domain::models::CronPayload {
+                kind: "systemEvent".to_owned(),
+                text: Some("tick".to_owned()),
+                message: None,
+                model: None,
+                thinking: None,
+                timeout_seconds: None,
+                script: None,
+                agent_id: None,
+                session_key: None,
+            },
+            metadata: serde_json::Value::Null,
+            created_at_ms: 0,
+            updated_at_ms: 0,
+            last_run_ms: None,
+            next_run_ms: Some(ms("2024-06-07T06:30:00Z")),
+            max_concurrent: None,
+        };
+        assert_eq!(
+            local_next_run(&job),
+            (
+                "America/New_York".to_owned(),
+                Some("2024-06-07T02:30:00-04:00".to_owned())
+            )
+        );
+
+        job.schedule.tz = None;
+        assert_eq!(
+            local_next_run(&job),
+            (
+                "UTC".to_owned(),
+                Some("2024-06-07T06:30:00+00:00".to_owned())
+            )
+        );
+    }
 }
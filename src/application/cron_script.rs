@@ -0,0 +1,1108 @@
+use std::{collections::BTreeMap, future::Future, pin::Pin, time::Duration};
+
+use serde_json::{Map, Number, Value, json};
+
+use crate::{
+    application::state::SharedState,
+    domain::models::CronPayload,
+    protocol::RequestFrame,
+    rpc::{SessionContext, dispatcher, policy},
+    storage::now_unix_ms,
+};
+
+const MAX_SCRIPT_BYTES: usize = 16 * 1024;
+const MAX_OPERATIONS: usize = 10_000;
+const MAX_HOST_CALLS: usize = 32;
+const MAX_OUTPUT_BYTES: usize = 16 * 1024;
+/// Deepest nesting of blocks, brackets, and operator chains the parser accepts, so neither
+/// parsing nor evaluation can exhaust the stack.
+const MAX_NESTING_DEPTH: usize = 64;
+/// Largest string, and largest array or map counted over all of its contents, a script can build.
+const MAX_VALUE_BYTES: usize = 64 * 1024;
+/// Deepest nesting of arrays and maps a script can build.
+const MAX_VALUE_DEPTH: usize = 32;
+/// Operations between yields to the runtime, so the time limit can interrupt a busy script.
+const YIELD_EVERY_OPERATIONS: usize = 256;
+const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(10);
+const MAX_TIME_LIMIT: Duration = Duration::from_secs(60);
+
+/// Config entries under this prefix hold runtime state and secrets; scripts cannot read them.
+const PRIVATE_CONFIG_PREFIX: &str = "runtime/";
+
+/// API functions served by the host, with their accepted argument counts.
+const HOST_FUNCTIONS: &[(&str, usize, usize)] =
+    &[("send", 2, 2), ("invoke", 2, 3), ("config", 1, 1)];
+
+const BINARY_LEVELS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+const PUNCTUATION: &[&str] = &[
+    "#{", "==", "!=", "<=", ">=", "&&", "||", "(", ")", "{", "}", "[", "]", ",", ";", ":", ".",
+    "=", "+", "-", "*", "/", "%", "<", ">", "!",
+];
+const KEYWORDS: &[&str] = &["let", "if", "else", "while", "for", "in", "true", "false"];
+
+/// What a script can reach outside its own variables.
+pub trait ScriptHost: Sync {
+    /// Runs one of [`HOST_FUNCTIONS`]; the argument count is already checked.
+    fn call(
+        &self,
+        function: &str,
+        args: Vec<Value>,
+    ) -> impl Future<Output = Result<Value, String>> + Send;
+
+    /// Receives each `print` line as soon as the script produces it.
+    fn print(&self, line: &str) -> impl Future<Output = ()> + Send;
+}
+
+/// A parsed `script` cron payload: a small Rhai-like language with `let`, assignment, `if`/
+/// `else`, `while`, `for .. in`, string/number/bool/array/`#{}` map values, and the functions
+/// `print`, `send`, `invoke`, `config`, `len`, `now` and `to_string`.
+#[derive(Debug, Clone)]
+pub struct Script {
+    statements: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Let(String, Expr),
+    Assign(String, Expr),
+    If(Vec<(Expr, Vec<Stmt>)>, Option<Vec<Stmt>>),
+    While(Expr, Vec<Stmt>),
+    For(String, Expr, Vec<Stmt>),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Var(String),
+    Array(Vec<Expr>),
+    Map(Vec<(String, Expr)>),
+    Call(String, Vec<Expr>),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(Number),
+    Punct(&'static str),
+}
+
+impl Script {
+    pub fn compile(source: &str) -> Result<Self, String> {
+        if source.len() > MAX_SCRIPT_BYTES {
+            return Err(format!("script must be at most {MAX_SCRIPT_BYTES} bytes"));
+        }
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            depth: 0,
+        };
+        let mut statements = Vec::new();
+        while !parser.at_end() {
+            statements.push(parser.statement()?);
+        }
+        Ok(Self { statements })
+    }
+
+    /// Runs the script to completion and returns everything it printed.
+    pub async fn run<H: ScriptHost>(
+        &self,
+        host: &H,
+        time_limit: Duration,
+    ) -> Result<String, String> {
+        let mut interpreter = Interpreter {
+            host,
+            scopes: vec![BTreeMap::new()],
+            operations: 0,
+            host_calls: 0,
+            output: String::new(),
+        };
+        match tokio::time::timeout(time_limit, interpreter.block(&self.statements)).await {
+            Ok(Ok(())) => Ok(interpreter.output),
+            Ok(Err(error)) => Err(error),
+            Err(_) => Err(format!(
+                "script timed out after {}s",
+                time_limit.as_secs_f64()
+            )),
+        }
+    }
+}
+
+/// Runs a `script` payload for cron run `run_id`: `send` and `invoke` go through the RPC
+/// dispatcher with `operator.write` only, and `print` lines stream as cron output chunks.
+pub async fn run_payload(
+    state: &SharedState,
+    run_id: &str,
+    payload: &CronPayload,
+) -> Result<String, String> {
+    let source = payload
+        .script
+        .as_deref()
+        .ok_or_else(|| "script payload requires script".to_owned())?;
+    let script = Script::compile(source)?;
+    let time_limit = payload
+        .timeout_seconds
+        .map_or(DEFAULT_TIME_LIMIT, Duration::from_secs)
+        .min(MAX_TIME_LIMIT);
+    let host = CronScriptHost {
+        state,
+        run_id,
+        session: SessionContext {
+            conn_id: format!("cron-script-{run_id}"),
+            role: "operator".to_owned(),
+            scopes: vec![policy::WRITE_SCOPE.to_owned()],
+            client_id: "cron-script".to_owned(),
+            client_mode: "cron".to_owned(),
+        },
+    };
+    script.run(&host, time_limit).await
+}
+
+struct CronScriptHost<'a> {
+    state: &'a SharedState,
+    run_id: &'a str,
+    session: SessionContext,
+}
+
+impl CronScriptHost<'_> {
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let request = RequestFrame {
+            frame_type: "req".to_owned(),
+            id: format!("{}-{}", self.session.conn_id, uuid::Uuid::new_v4()),
+            method: method.to_owned(),
+            params: Some(params),
+            timeout_ms: None,
+        };
+        let response = dispatcher::dispatch_request(self.state, &self.session, &request).await;
+        match response.error {
+            Some(error) => Err(format!("{method} failed: {}", error.message)),
+            None => Ok(response.payload.unwrap_or(Value::Null)),
+        }
+    }
+}
+
+impl ScriptHost for CronScriptHost<'_> {
+    async fn call(&self, function: &str, mut args: Vec<Value>) -> Result<Value, String> {
+        match function {
+            "send" => {
+                let message = args.pop().map(display).unwrap_or_default();
+                let session_key = args.pop().unwrap_or(Value::Null);
+                self.request(
+                    "send",
+                    json!({ "sessionKey": session_key, "message": message }),
+                )
+                .await
+            }
+            "invoke" => {
+                let mut params = json!({ "nodeId": args[0], "command": args[1] });
+                match args.get(2) {
+                    Some(Value::Array(items)) => {
+                        params["args"] = Value::Array(
+                            items
+                                .iter()
+                                .cloned()
+                                .map(display)
+                                .map(Value::String)
+                                .collect(),
+                        );
+                    }
+                    Some(input) => params["input"] = input.clone(),
+                    None => {}
+                }
+                self.request("node.invoke", params).await
+            }
+            "config" => {
+                let key = args[0]
+                    .as_str()
+                    .ok_or_else(|| "config key must be a string".to_owned())?;
+                if key.starts_with(PRIVATE_CONFIG_PREFIX) {
+                    return Err(format!("config entry {key} is not readable from scripts"));
+                }
+                self.state
+                    .get_config_entry_value(key)
+                    .await
+                    .map(Option::unwrap_or_default)
+                    .map_err(|error| error.to_string())
+            }
+            other => Err(format!("unknown function {other}")),
+        }
+    }
+
+    async fn print(&self, line: &str) {
+        self.state.append_cron_run_output(self.run_id, line).await;
+    }
+}
+
+type EvalFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+struct Interpreter<'h, H> {
+    host: &'h H,
+    scopes: Vec<BTreeMap<String, Value>>,
+    operations: usize,
+    host_calls: usize,
+    output: String,
+}
+
+impl<H: ScriptHost> Interpreter<'_, H> {
+    fn tick(&mut self) -> Result<(), String> {
+        self.operations += 1;
+        if self.operations > MAX_OPERATIONS {
+            return Err(format!("script exceeded {MAX_OPERATIONS} operations"));
+        }
+        Ok(())
+    }
+
+    fn block<'a>(&'a mut self, statements: &'a [Stmt]) -> EvalFuture<'a, ()> {
+        Box::pin(async move {
+            self.scopes.push(BTreeMap::new());
+            let mut result = Ok(());
+            for statement in statements {
+                result = self.statement(statement).await;
+                if result.is_err() {
+                    break;
+                }
+            }
+            self.scopes.pop();
+            result
+        })
+    }
+
+    async fn statement(&mut self, statement: &Stmt) -> Result<(), String> {
+        self.tick()?;
+        if self.operations.is_multiple_of(YIELD_EVERY_OPERATIONS) {
+            tokio::task::yield_now().await;
+        }
+        match statement {
+            Stmt::Let(name, expr) => {
+                let value = self.eval(expr).await?;
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(name.clone(), value);
+                }
+            }
+            Stmt::Assign(name, expr) => {
+                let value = self.eval(expr).await?;
+                let slot = self
+                    .scopes
+                    .iter_mut()
+                    .rev()
+                    .find_map(|scope| scope.get_mut(name))
+                    .ok_or_else(|| format!("variable {name} is not defined"))?;
+                *slot = value;
+            }
+            Stmt::If(branches, otherwise) => {
+                for (condition, body) in branches {
+                    let condition = self.eval(condition).await?;
+                    if truthy(&condition)? {
+                        return self.block(body).await;
+                    }
+                }
+                if let Some(body) = otherwise {
+                    self.block(body).await?;
+                }
+            }
+            Stmt::While(condition, body) => loop {
+                let condition = self.eval(condition).await?;
+                if !truthy(&condition)? {
+                    break;
+                }
+                self.block(body).await?;
+            },
+            Stmt::For(name, iterable, body) => {
+                let items = match self.eval(iterable).await? {
+                    Value::Array(items) => items,
+                    Value::Object(map) => {
+                        map.into_iter().map(|(key, _)| Value::String(key)).collect()
+                    }
+                    Value::String(text) => text
+                        .chars()
+                        .map(|ch| Value::String(ch.to_string()))
+                        .collect(),
+                    other => return Err(format!("cannot iterate over {}", type_name(&other))),
+                };
+                for item in items {
+                    self.scopes.push(BTreeMap::from([(name.clone(), item)]));
+                    let result = self.block(body).await;
+                    self.scopes.pop();
+                    result?;
+                }
+            }
+            Stmt::Expr(expr) => {
+                self.eval(expr).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn eval<'a>(&'a mut self, expr: &'a Expr) -> EvalFuture<'a, Value> {
+        Box::pin(async move {
+            self.tick()?;
+            match expr {
+                Expr::Literal(value) => Ok(value.clone()),
+                Expr::Var(name) => self
+                    .scopes
+                    .iter()
+                    .rev()
+                    .find_map(|scope| scope.get(name))
+                    .cloned()
+                    .ok_or_else(|| format!("variable {name} is not defined")),
+                Expr::Array(items) => {
+                    let mut values = Vec::with_capacity(items.len());
+                    for item in items {
+                        values.push(self.eval(item).await?);
+                    }
+                    bounded(Value::Array(values))
+                }
+                Expr::Map(entries) => {
+                    let mut map = Map::new();
+                    for (key, value) in entries {
+                        let value = self.eval(value).await?;
+                        map.insert(key.clone(), value);
+                    }
+                    bounded(Value::Object(map))
+                }
+                Expr::Call(name, args) => {
+                    let mut values = Vec::with_capacity(args.len());
+                    for arg in args {
+                        values.push(self.eval(arg).await?);
+                    }
+                    self.call(name, values).await
+                }
+                Expr::Field(target, field) => match self.eval(target).await? {
+                    Value::Object(map) => Ok(map.get(field).cloned().unwrap_or_default()),
+                    other => Err(format!("{} has no field {field}", type_name(&other))),
+                },
+                Expr::Index(target, index) => {
+                    let target = self.eval(target).await?;
+                    let index = self.eval(index).await?;
+                    match (&target, &index) {
+                        (Value::Array(items), Value::Number(number)) => {
+                            let position = number
+                                .as_u64()
+                                .and_then(|position| usize::try_from(position).ok())
+                                .filter(|position| *position < items.len())
+                                .ok_or_else(|| format!("index {number} is out of bounds"))?;
+                            Ok(items[position].clone())
+                        }
+                        (Value::Object(map), Value::String(key)) => {
+                            Ok(map.get(key).cloned().unwrap_or_default())
+                        }
+                        _ => Err(format!(
+                            "cannot index {} with {}",
+                            type_name(&target),
+                            type_name(&index)
+                        )),
+                    }
+                }
+                Expr::Not(operand) => {
+                    let value = self.eval(operand).await?;
+                    Ok(Value::Bool(!truthy(&value)?))
+                }
+                Expr::Negate(operand) => match self.eval(operand).await? {
+                    Value::Number(number) => match number.as_i64() {
+                        Some(value) => value
+                            .checked_neg()
+                            .map(Value::from)
+                            .ok_or_else(|| "integer overflow".to_owned()),
+                        None => Ok(float(-number.as_f64().unwrap_or_default())),
+                    },
+                    other => Err(format!("cannot negate {}", type_name(&other))),
+                },
+                Expr::Binary(op, left, right) => {
+                    let left = self.eval(left).await?;
+                    // `&&` and `||` short-circuit like in Rhai.
+                    match *op {
+                        "&&" if !truthy(&left)? => return Ok(Value::Bool(false)),
+                        "||" if truthy(&left)? => return Ok(Value::Bool(true)),
+                        "&&" | "||" => {
+                            let right = self.eval(right).await?;
+                            return Ok(Value::Bool(truthy(&right)?));
+                        }
+                        _ => {}
+                    }
+                    let right = self.eval(right).await?;
+                    bounded(binary(op, left, right)?)
+                }
+            }
+        })
+    }
+
+    async fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, String> {
+        match (name, args.len()) {
+            ("print", 1) => {
+                let line = args.into_iter().next().map(display).unwrap_or_default();
+                if self.output.len() + line.len() + 1 > MAX_OUTPUT_BYTES {
+                    return Err(format!("script output exceeded {MAX_OUTPUT_BYTES} bytes"));
+                }
+                if !self.output.is_empty() {
+                    self.output.push('\n');
+                }
+                self.output.push_str(&line);
+                self.host.print(&line).await;
+                Ok(Value::Null)
+            }
+            ("len", 1) => match &args[0] {
+                Value::String(text) => Ok(Value::from(text.chars().count())),
+                Value::Array(items) => Ok(Value::from(items.len())),
+                Value::Object(map) => Ok(Value::from(map.len())),
+                other => Err(format!("len is not defined for {}", type_name(other))),
+            },
+            ("now", 0) => Ok(Value::from(now_unix_ms())),
+            ("to_string", 1) => Ok(Value::String(
+                args.into_iter().next().map(display).unwrap_or_default(),
+            )),
+            _ => {
+                let Some((_, min, max)) = HOST_FUNCTIONS
+                    .iter()
+                    .find(|(function, _, _)| *function == name)
+                else {
+                    return Err(format!(
+                        "unknown function {name} with {} arguments",
+                        args.len()
+                    ));
+                };
+                if args.len() < *min || args.len() > *max {
+                    return Err(format!("{name} takes {min} to {max} arguments"));
+                }
+                self.host_calls += 1;
+                if self.host_calls > MAX_HOST_CALLS {
+                    return Err(format!("script exceeded {MAX_HOST_CALLS} API calls"));
+                }
+                self.host.call(name, args).await
+            }
+        }
+    }
+}
+
+fn binary(op: &str, left: Value, right: Value) -> Result<Value, String> {
+    match op {
+        "==" => return Ok(Value::Bool(left == right)),
+        "!=" => return Ok(Value::Bool(left != right)),
+        _ => {}
+    }
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => numeric(op, &left, &right),
+        (Value::String(left), Value::String(right)) if op != "+" => {
+            compare(op, left.cmp(&right)).ok_or_else(|| format!("cannot apply {op} to strings"))
+        }
+        (Value::String(left), right) if op == "+" => Ok(Value::String(left + &display(right))),
+        (left, Value::String(right)) if op == "+" => Ok(Value::String(display(left) + &right)),
+        (Value::Array(mut left), Value::Array(right)) if op == "+" => {
+            left.extend(right);
+            Ok(Value::Array(left))
+        }
+        (left, right) => Err(format!(
+            "cannot apply {op} to {} and {}",
+            type_name(&left),
+            type_name(&right)
+        )),
+    }
+}
+
+/// Rejects values past [`MAX_VALUE_BYTES`] or [`MAX_VALUE_DEPTH`], walking them without recursion.
+fn bounded(value: Value) -> Result<Value, String> {
+    let mut bytes = 0usize;
+    let mut pending = vec![(&value, 1usize)];
+    while let Some((current, depth)) = pending.pop() {
+        if depth > MAX_VALUE_DEPTH {
+            return Err(format!(
+                "script values can be nested at most {MAX_VALUE_DEPTH} deep"
+            ));
+        }
+        bytes += match current {
+            Value::String(text) => text.len(),
+            Value::Array(items) => {
+                pending.extend(items.iter().map(|item| (item, depth + 1)));
+                items.len()
+            }
+            Value::Object(map) => {
+                pending.extend(map.iter().map(|(_, item)| (item, depth + 1)));
+                map.keys().map(String::len).sum()
+            }
+            _ => 8,
+        };
+        if bytes > MAX_VALUE_BYTES {
+            return Err(format!(
+                "script values can be at most {MAX_VALUE_BYTES} bytes"
+            ));
+        }
+    }
+    Ok(value)
+}
+
+fn numeric(op: &str, left: &Number, right: &Number) -> Result<Value, String> {
+    if let (Some(left), Some(right)) = (left.as_i64(), right.as_i64()) {
+        if let Some(ordering) = compare(op, left.cmp(&right)) {
+            return Ok(ordering);
+        }
+        let result = match op {
+            "+" => left.checked_add(right),
+            "-" => left.checked_sub(right),
+            "*" => left.checked_mul(right),
+            "/" | "%" if right == 0 => return Err("division by zero".to_owned()),
+            "/" => left.checked_div(right),
+            "%" => left.checked_rem(right),
+            _ => return Err(format!("cannot apply {op} to numbers")),
+        };
+        return result
+            .map(Value::from)
+            .ok_or_else(|| "integer overflow".to_owned());
+    }
+
+    let (left, right) = (
+        left.as_f64().unwrap_or_default(),
+        right.as_f64().unwrap_or_default(),
+    );
+    if let Some(ordering) = left
+        .partial_cmp(&right)
+        .and_then(|ordering| compare(op, ordering))
+    {
+        return Ok(ordering);
+    }
+    match op {
+        "+" => Ok(float(left + right)),
+        "-" => Ok(float(left - right)),
+        "*" => Ok(float(left * right)),
+        "/" => Ok(float(left / right)),
+        "%" => Ok(float(left % right)),
+        _ => Err(format!("cannot apply {op} to numbers")),
+    }
+}
+
+fn compare(op: &str, ordering: std::cmp::Ordering) -> Option<Value> {
+    let result = match op {
+        "<" => ordering.is_lt(),
+        "<=" => ordering.is_le(),
+        ">" => ordering.is_gt(),
+        ">=" => ordering.is_ge(),
+        _ => return None,
+    };
+    Some(Value::Bool(result))
+}
+
+fn float(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+fn truthy(value: &Value) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("expected a boolean, got {}", type_name(value)))
+}
+
+fn display(value: Value) -> String {
+    match value {
+        Value::String(text) => text,
+        Value::Null => "()".to_owned(),
+        other => other.to_string(),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "()",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "map",
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    let mut line = 1;
+    while let Some(&(start, ch)) = chars.peek() {
+        if ch == '\n' {
+            line += 1;
+            chars.next();
+        } else if ch.is_whitespace() {
+            chars.next();
+        } else if source[start..].starts_with("//") {
+            while chars.next_if(|(_, ch)| *ch != '\n').is_some() {}
+        } else if ch == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                let Some((_, ch)) = chars.next() else {
+                    return Err(format!("line {line}: unterminated string"));
+                };
+                match ch {
+                    '"' => break,
+                    '\\' => match chars.next().map(|(_, ch)| ch) {
+                        Some('n') => text.push('\n'),
+                        Some('t') => text.push('\t'),
+                        Some(escaped @ ('"' | '\\')) => text.push(escaped),
+                        _ => return Err(format!("line {line}: invalid escape in string")),
+                    },
+                    '\n' => return Err(format!("line {line}: unterminated string")),
+                    other => text.push(other),
+                }
+            }
+            tokens.push((Token::Str(text), line));
+        } else if ch.is_ascii_digit() {
+            let mut end = start;
+            while let Some((index, ch)) =
+                chars.next_if(|(_, ch)| ch.is_ascii_digit() || *ch == '.' || *ch == '_')
+            {
+                end = index + ch.len_utf8();
+            }
+            let literal = source[start..end].replace('_', "");
+            let number = match literal.parse::<i64>() {
+                Ok(value) => Number::from(value),
+                Err(_) => literal
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .ok_or_else(|| format!("line {line}: invalid number {literal}"))?,
+            };
+            tokens.push((Token::Number(number), line));
+        } else if ch.is_alphabetic() || ch == '_' {
+            let mut end = start;
+            while let Some((index, ch)) =
+                chars.next_if(|(_, ch)| ch.is_alphanumeric() || *ch == '_')
+            {
+                end = index + ch.len_utf8();
+            }
+            tokens.push((Token::Ident(source[start..end].to_owned()), line));
+        } else {
+            let punct = PUNCTUATION
+                .iter()
+                .find(|punct| source[start..].starts_with(**punct))
+                .ok_or_else(|| format!("line {line}: unexpected character {ch:?}"))?;
+            for _ in 0..punct.len() {
+                chars.next();
+            }
+            tokens.push((Token::Punct(punct), line));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    /// Runs `parse` one nesting level deeper, failing past [`MAX_NESTING_DEPTH`].
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        self.descend()?;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn descend(&mut self) -> Result<(), String> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(self.error(&format!(
+                "script nests deeper than {MAX_NESTING_DEPTH} levels"
+            )));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn error(&self, message: &str) -> String {
+        let line = self
+            .tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line);
+        format!("line {line}: {message}")
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Punct(candidate)) if *candidate == punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), String> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {punct}")))
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(name)) if name == keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Ident(name)) if !KEYWORDS.contains(&name.as_str()) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.error("expected a name")),
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, String> {
+        self.expect_punct("{")?;
+        self.nested(|parser| {
+            let mut statements = Vec::new();
+            while !parser.eat_punct("}") {
+                if parser.at_end() {
+                    return Err(parser.error("expected }"));
+                }
+                statements.push(parser.statement()?);
+            }
+            Ok(statements)
+        })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, String> {
+        if self.eat_keyword("let") {
+            let name = self.identifier()?;
+            self.expect_punct("=")?;
+            let value = self.expression()?;
+            self.end_statement()?;
+            return Ok(Stmt::Let(name, value));
+        }
+        if self.eat_keyword("if") {
+            let mut branches = vec![(self.expression()?, self.block()?)];
+            let mut otherwise = None;
+            while self.eat_keyword("else") {
+                if self.eat_keyword("if") {
+                    branches.push((self.expression()?, self.block()?));
+                } else {
+                    otherwise = Some(self.block()?);
+                    break;
+                }
+            }
+            return Ok(Stmt::If(branches, otherwise));
+        }
+        if self.eat_keyword("while") {
+            return Ok(Stmt::While(self.expression()?, self.block()?));
+        }
+        if self.eat_keyword("for") {
+            let name = self.identifier()?;
+            if !self.eat_keyword("in") {
+                return Err(self.error("expected in"));
+            }
+            return Ok(Stmt::For(name, self.expression()?, self.block()?));
+        }
+
+        let expr = self.expression()?;
+        if self.eat_punct("=") {
+            let Expr::Var(name) = expr else {
+                return Err(self.error("only variables can be assigned"));
+            };
+            let value = self.expression()?;
+            self.end_statement()?;
+            return Ok(Stmt::Assign(name, value));
+        }
+        self.end_statement()?;
+        Ok(Stmt::Expr(expr))
+    }
+
+    /// Statements end with `;`, which may be left out before `}` or at the end of the script.
+    fn end_statement(&mut self) -> Result<(), String> {
+        if self.eat_punct(";") || self.at_end() || self.peek() == Some(&Token::Punct("}")) {
+            Ok(())
+        } else {
+            Err(self.error("expected ;"))
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        self.nested(|parser| parser.binary(0))
+    }
+
+    /// Each chained operator deepens the tree the interpreter walks, so chains count as nesting.
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        let Some(operators) = BINARY_LEVELS.get(level) else {
+            return self.unary();
+        };
+        let entered = self.depth;
+        let mut left = self.binary(level + 1)?;
+        while let Some(Token::Punct(op)) = self.peek() {
+            let Some(op) = operators.iter().copied().find(|candidate| candidate == op) else {
+                break;
+            };
+            self.pos += 1;
+            self.descend()?;
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        self.depth = entered;
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat_punct("!") {
+            return Ok(Expr::Not(Box::new(self.nested(Self::unary)?)));
+        }
+        if self.eat_punct("-") {
+            return Ok(Expr::Negate(Box::new(self.nested(Self::unary)?)));
+        }
+        let entered = self.depth;
+        let result = self.postfix();
+        self.depth = entered;
+        result
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat_punct(".") {
+                self.descend()?;
+                expr = Expr::Field(Box::new(expr), self.identifier()?);
+            } else if self.eat_punct("[") {
+                self.descend()?;
+                let index = self.expression()?;
+                self.expect_punct("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.error("unexpected end of script"));
+        };
+        self.pos += 1;
+        match token {
+            Token::Str(text) => Ok(Expr::Literal(Value::String(text))),
+            Token::Number(number) => Ok(Expr::Literal(Value::Number(number))),
+            Token::Ident(name) if name == "true" => Ok(Expr::Literal(Value::Bool(true))),
+            Token::Ident(name) if name == "false" => Ok(Expr::Literal(Value::Bool(false))),
+            Token::Ident(name) if KEYWORDS.contains(&name.as_str()) => {
+                self.pos -= 1;
+                Err(self.error(&format!("unexpected {name}")))
+            }
+            Token::Ident(name) => {
+                if !self.eat_punct("(") {
+                    return Ok(Expr::Var(name));
+                }
+                let args = self.list(")")?;
+                Ok(Expr::Call(name, args))
+            }
+            Token::Punct("(") => {
+                if self.eat_punct(")") {
+                    return Ok(Expr::Literal(Value::Null));
+                }
+                let expr = self.expression()?;
+                self.expect_punct(")")?;
+                Ok(expr)
+            }
+            Token::Punct("[") => Ok(Expr::Array(self.list("]")?)),
+            Token::Punct("#{") => {
+                let mut entries = Vec::new();
+                while !self.eat_punct("}") {
+                    let key = match self.peek().cloned() {
+                        Some(Token::Ident(key) | Token::Str(key)) => key,
+                        _ => return Err(self.error("expected a map key")),
+                    };
+                    self.pos += 1;
+                    self.expect_punct(":")?;
+                    entries.push((key, self.expression()?));
+                    if !self.eat_punct(",") {
+                        self.expect_punct("}")?;
+                        break;
+                    }
+                }
+                Ok(Expr::Map(entries))
+            }
+            Token::Punct(punct) => {
+                self.pos -= 1;
+                Err(self.error(&format!("unexpected {punct}")))
+            }
+        }
+    }
+
+    /// Comma-separated expressions up to `close`, allowing a trailing comma.
+    fn list(&mut self, close: &str) -> Result<Vec<Expr>, String> {
+        let mut items = Vec::new();
+        while !self.eat_punct(close) {
+            items.push(self.expression()?);
+            if !self.eat_punct(",") {
+                self.expect_punct(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, time::Duration};
+
+    use serde_json::{Value, json};
+
+    use super::{Script, ScriptHost};
+
+    #[derive(Default)]
+    struct RecordingHost {
+        calls: Mutex<Vec<(String, Vec<Value>)>>,
+    }
+
+    impl ScriptHost for RecordingHost {
+        async fn call(&self, function: &str, args: Vec<Value>) -> Result<Value, String> {
+            self.calls
+                .lock()
+                .expect("calls lock")
+                .push((function.to_owned(), args));
+            match function {
+                "config" => Ok(json!({ "threshold": 3 })),
+                _ => Ok(json!({ "ok": true })),
+            }
+        }
+
+        async fn print(&self, _line: &str) {}
+    }
+
+    #[tokio::test]
+    async fn scripts_evaluate_and_call_the_host_api() {
+        let script = Script::compile(
+            r#"
+            // Alert once per failing check.
+            let limit = config("ops/limits").threshold;
+            let failing = [];
+            for check in #{ disk: 5, cpu: 1, "memory": 4 } {
+                failing = failing + [check];
+            }
+            let i = 0;
+            while i < len(failing) {
+                if failing[i] == "cpu" { i = i + 1; } else {
+                    send("agent:main:ops", "check " + failing[i] + " over " + limit);
+                    i = i + 1
+                }
+            }
+            print("sent " + (len(failing) - 1) + " alerts");
+            print(2 * 3 % 4 == 2 && !false)
+            "#,
+        )
+        .expect("script should compile");
+        let host = RecordingHost::default();
+        let output = script
+            .run(&host, Duration::from_secs(1))
+            .await
+            .expect("script should run");
+
+        assert_eq!(output, "sent 2 alerts\ntrue");
+        let calls = host.calls.lock().expect("calls lock").clone();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0], ("config".to_owned(), vec![json!("ops/limits")]));
+        assert_eq!(
+            calls[1],
+            (
+                "send".to_owned(),
+                vec![json!("agent:main:ops"), json!("check disk over 3")]
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn scripts_are_bounded_and_report_errors() {
+        assert!(
+            Script::compile("let x = ;")
+                .expect_err("syntax error")
+                .starts_with("line 1:")
+        );
+        assert!(Script::compile("print(\"open)").is_err());
+
+        let host = RecordingHost::default();
+        let spin = Script::compile("while true { }").expect("script should compile");
+        assert!(
+            spin.run(&host, Duration::from_secs(1))
+                .await
+                .expect_err("loop should hit the operation limit")
+                .contains("operations")
+        );
+        let calls = Script::compile("while true { config(\"a\"); }").expect("script");
+        assert!(
+            calls
+                .run(&host, Duration::from_secs(1))
+                .await
+                .expect_err("loop should hit the call limit")
+                .contains("API calls")
+        );
+        for (source, error) in [
+            ("print(missing)", "variable missing is not defined"),
+            ("if 1 { }", "expected a boolean, got number"),
+            ("send(\"agent:main:main\")", "send takes 2 to 2 arguments"),
+            ("print(1 / 0)", "division by zero"),
+            (
+                "let s = \"ab\"; while true { s = s + s; }",
+                "script values can be at most 65536 bytes",
+            ),
+            (
+                "let a = [1]; while true { a = [a]; }",
+                "script values can be nested at most 32 deep",
+            ),
+            (
+                "let x = \"xxxxxxxx\"; x = x + x + x + x + x + x + x + x; \
+                 let a = []; while true { a = a + [x]; }",
+                "script values can be at most 65536 bytes",
+            ),
+        ] {
+            let script = Script::compile(source).expect("script should compile");
+            assert_eq!(
+                script.run(&host, Duration::from_secs(1)).await,
+                Err(error.to_owned()),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn deeply_nested_scripts_are_rejected_without_overflowing_the_stack() {
+        let parens = format!("print({}1{});", "(".repeat(8_000), ")".repeat(8_000));
+        let chain = format!("print(1{});", " + 1".repeat(4_000));
+        let negations = format!("print({}true);", "!".repeat(8_000));
+        let blocks = format!("{}{}", "if true { ".repeat(1_000), "}".repeat(1_000));
+        let indexes = format!("let a = [1]; print(a{});", "[0]".repeat(4_000));
+        for source in [parens, chain, negations, blocks, indexes] {
+            assert!(
+                Script::compile(&source)
+                    .expect_err("nesting should be limited")
+                    .contains("nests deeper than 64 levels"),
+                "{}",
+                &source[..20]
+            );
+        }
+        Script::compile(&format!("print({}1{});", "(".repeat(20), ")".repeat(20)))
+            .expect("moderate nesting should compile");
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{
+    application::config::{Args, DbMigrateToArgs, command_db_path},
+    domain::error::DomainError,
+    storage::{PostgresMigrationOptions, SqliteStore},
+};
+
+pub async fn migrate_to(args: &Args, command: &DbMigrateToArgs) -> Result<(), DomainError> {
+    let db_path = command_db_path(args)
+        .map_err(|error| DomainError::InvalidRequest(format!("configuration error: {error}")))?;
+    let store = SqliteStore::connect(&db_path).await?;
+    println!("migrating {} to postgres", db_path.display());
+
+    let report = store
+        .migrate_to_postgres(
+            &command.target_url,
+            PostgresMigrationOptions {
+                replace: command.replace,
+                cutover: command.cutover,
+            },
+            &|progress| {
+                println!(
+                    "[{}/{}] {}: {}/{} rows",
+                    progress.table_index + 1,
+                    progress.table_count,
+                    progress.table,
+                    progress.rows_copied,
+                    progress.total_rows
+                );
+            },
+        )
+        .await?;
+
+    for table in &report.tables {
+        println!(
+            "verified {} rows={} checksum={}",
+            table.table, table.target_rows, table.target_checksum
+        );
+    }
+    println!(
+        "db migrate-to complete (target={}, tables={}, rows={}, cutover={})",
+        report.target,
+        report.tables.len(),
+        report.rows,
+        report.cutover
+    );
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+    process::Stdio,
+    time::Instant,
+};
+
+use serde::Serialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+    sync::mpsc::Sender,
+};
+
+use crate::application::config::ExecRunnerConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecChunk {
+    pub stream: ExecStream,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExecCommand {
+    pub command: String,
+    pub cwd: Option<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecOutcome {
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub truncated: bool,
+    pub output_bytes: usize,
+    pub duration_ms: u64,
+    pub cwd: String,
+}
+
+/// Resolves `cwd` inside the sandbox root, rejecting absolute paths and parent traversal.
+pub fn resolve_sandbox_dir(root: &Path, cwd: Option<&str>) -> Result<PathBuf, String> {
+    let Some(relative) = cwd.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(root.to_path_buf());
+    };
+
+    let relative = Path::new(relative);
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err("cwd must be a relative path inside the exec workdir".to_owned());
+    }
+    Ok(root.join(relative))
+}
+
+/// Runs `command` through `sh -c` inside the sandbox workdir with an allowlisted environment.
+/// Output lines are forwarded to `chunks` until `max_output_bytes` is reached.
+pub async fn run_command(
+    config: &ExecRunnerConfig,
+    command: &ExecCommand,
+    chunks: Sender<ExecChunk>,
+) -> Result<ExecOutcome, String> {
+    let cwd = resolve_sandbox_dir(&config.workdir, command.cwd.as_deref())?;
+    tokio::fs::create_dir_all(&cwd)
+        .await
+        .map_err(|error| format!("failed to create exec workdir {}: {error}", cwd.display()))?;
+
+    let mut process = Command::new("sh");
+    process
+        .arg("-c")
+        .arg(&command.command)
+        .current_dir(&cwd)
+        .env_clear()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    for name in &config.env_allowlist {
+        if let Some(value) = command.env.get(name) {
+            process.env(name, value);
+        } else if let Ok(value) = std::env::var(name) {
+            process.env(name, value);
+        }
+    }
+
+    let started = Instant::now();
+    let mut child = process
+        .spawn()
+        .map_err(|error| format!("failed to spawn command: {error}"))?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    let (line_tx, mut line_rx) = tokio::sync::mpsc::channel::<ExecChunk>(64);
+    let readers = [
+        stdout.map(|pipe| tokio::spawn(forward_lines(pipe, ExecStream::Stdout, line_tx.clone()))),
+        stderr.map(|pipe| tokio::spawn(forward_lines(pipe, ExecStream::Stderr, line_tx.clone()))),
+    ];
+    drop(line_tx);
+
+    let mut output_bytes = 0usize;
+    let mut truncated = false;
+    let collect = async {
+        while let Some(chunk) = line_rx.recv().await {
+            if truncated {
+                continue;
+            }
+            output_bytes = output_bytes.saturating_add(chunk.text.len());
+            if output_bytes > config.max_output_bytes {
+                truncated = true;
+                continue;
+            }
+            let _ = chunks.send(chunk).await;
+        }
+        child.wait().await
+    };
+
+    let waited = tokio::time::timeout(config.timeout, collect).await;
+    let (status, timed_out) = match waited {
+        Ok(status) => (
+            status.map_err(|error| format!("failed to wait for command: {error}"))?,
+            false,
+        ),
+        Err(_) => {
+            let _ = child.kill().await;
+            (
+                child
+                    .wait()
+                    .await
+                    .map_err(|error| format!("failed to reap command: {error}"))?,
+                true,
+            )
+        }
+    };
+    for reader in readers.into_iter().flatten() {
+        reader.abort();
+    }
+
+    Ok(ExecOutcome {
+        exit_code: status.code(),
+        timed_out,
+        truncated,
+        output_bytes: output_bytes.min(config.max_output_bytes),
+        duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+        cwd: cwd.display().to_string(),
+    })
+}
+
+async fn forward_lines(pipe: impl AsyncRead + Unpin, stream: ExecStream, tx: Sender<ExecChunk>) {
+    let mut lines = BufReader::new(pipe).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if tx
+            .send(ExecChunk {
+                stream,
+                text: format!("{line}\n"),
+            })
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, path::Path, time::Duration};
+
+    use super::{ExecCommand, ExecStream, resolve_sandbox_dir, run_command};
+    use crate::application::config::ExecRunnerConfig;
+
+    #[test]
+    fn resolve_sandbox_dir_rejects_escapes() {
+        let root = Path::new("/srv/exec");
+        assert_eq!(
+            resolve_sandbox_dir(root, Some("jobs/1")).expect("relative path should resolve"),
+            root.join("jobs/1")
+        );
+        assert!(resolve_sandbox_dir(root, Some("../etc")).is_err());
+        assert!(resolve_sandbox_dir(root, Some("/etc")).is_err());
+    }
+
+    #[tokio::test]
+    async fn run_command_streams_output_with_allowlisted_env() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let config = ExecRunnerConfig {
+            enabled: true,
+            workdir: temp.path().to_path_buf(),
+            env_allowlist: vec!["PATH".to_owned(), "GREETING".to_owned()],
+            timeout: Duration::from_secs(5),
+            max_output_bytes: 1_024,
+        };
+        let mut env = BTreeMap::new();
+        env.insert("GREETING".to_owned(), "hello".to_owned());
+        env.insert("SECRET".to_owned(), "leak".to_owned());
+        let command = ExecCommand {
+            command: "echo \"$GREETING-$SECRET\"; echo oops >&2; exit 3".to_owned(),
+            cwd: Some("work".to_owned()),
+            env,
+        };
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let outcome = run_command(&config, &command, tx)
+            .await
+            .expect("command should run");
+        assert_eq!(outcome.exit_code, Some(3));
+        assert!(!outcome.timed_out);
+        assert!(temp.path().join("work").is_dir());
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            chunks.push((chunk.stream, chunk.text));
+        }
+        assert!(chunks.contains(&(ExecStream::Stdout, "hello-\n".to_owned())));
+        assert!(chunks.contains(&(ExecStream::Stderr, "oops\n".to_owned())));
+    }
+}
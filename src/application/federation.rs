@@ -0,0 +1,886 @@
+use std::time::{Duration, Instant};
+
+use ring::{
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::{
+    application::{config::FederationConfig, node_affinity, state::SharedState},
+    protocol::{ERROR_INVALID_REQUEST, ERROR_UNAVAILABLE, ErrorShape},
+    rpc::{SessionContext, deadline},
+    security::signatures::{hex_decode, hex_encode, verify_ed25519_hex},
+    storage::now_unix_ms,
+};
+
+pub const PEER_HEADER: &str = "x-reclaw-peer";
+pub const TIMESTAMP_HEADER: &str = "x-reclaw-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-reclaw-signature";
+/// Random per-request value; the response is signed over the request's nonce.
+pub const NONCE_HEADER: &str = "x-reclaw-nonce";
+/// Client mode of sessions serving requests from a peer; their requests are never forwarded on.
+pub const FEDERATION_CLIENT_MODE: &str = "federation";
+/// Session keys and node ids of the form `peer:<peerId>:<id>` address a paired gateway.
+pub const PEER_NAMESPACE: &str = "peer";
+/// Signed requests older or newer than this are rejected, and nonces are remembered this long.
+pub const MAX_CLOCK_SKEW_MS: u64 = 5 * 60 * 1000;
+/// Methods a peer may call here, with the param naming the routed session or node.
+pub const ROUTED_METHODS: &[(&str, &str)] = &[
+    ("send", "sessionKey"),
+    ("chat.send", "sessionKey"),
+    ("agent", "sessionKey"),
+    ("node.invoke", "nodeId"),
+];
+
+const IDENTITY_KEY: &str = "runtime/federation/identity";
+const PEER_PREFIX: &str = "runtime/federation/peer/";
+const INVITE_PREFIX: &str = "runtime/federation/invite/";
+const INVITE_TOKEN_PREFIX: &str = "rfi_";
+const INVITE_TTL_MS: u64 = 15 * 60 * 1000;
+const DEFAULT_HEALTH_INTERVAL_SECS: u64 = 60;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_NONCE_LEN: usize = 128;
+
+/// Compiled form of the `federation` config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Federation {
+    gateway_id: String,
+    public_url: String,
+    health_interval: Duration,
+}
+
+impl Federation {
+    pub fn compile(config: FederationConfig) -> Result<Self, String> {
+        let gateway_id = config.gateway_id.trim().to_ascii_lowercase();
+        if !is_peer_id(&gateway_id) {
+            return Err(
+                "federation.gatewayId must be non-empty and use a-z, 0-9, '-' or '_'".to_owned(),
+            );
+        }
+        let public_url = normalize_url(&config.public_url)
+            .ok_or("federation.publicUrl must be an http(s) URL")?;
+        let interval_secs = config
+            .health_interval_secs
+            .unwrap_or(DEFAULT_HEALTH_INTERVAL_SECS);
+        if interval_secs == 0 {
+            return Err("federation.healthIntervalSecs must be greater than 0".to_owned());
+        }
+        Ok(Self {
+            gateway_id,
+            public_url,
+            health_interval: Duration::from_secs(interval_secs),
+        })
+    }
+
+    #[must_use]
+    pub fn gateway_id(&self) -> &str {
+        &self.gateway_id
+    }
+
+    #[must_use]
+    pub fn public_url(&self) -> &str {
+        &self.public_url
+    }
+}
+
+/// A paired gateway, stored under `runtime/federation/peer/<id>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerRecord {
+    pub id: String,
+    pub url: String,
+    /// Hex-encoded Ed25519 key the peer signs its requests and responses with.
+    pub public_key: String,
+    pub paired_at_ms: u64,
+    #[serde(default)]
+    pub health: PeerHealth,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerHealth {
+    /// `up`, `down`, or `unknown` before the first check.
+    #[serde(default = "unknown_status")]
+    pub status: String,
+    #[serde(default)]
+    pub checked_at_ms: Option<u64>,
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Signature headers of a peer request or response.
+#[derive(Debug, Clone)]
+pub struct SignedHeaders {
+    pub peer_id: String,
+    pub timestamp_ms: u64,
+    pub nonce: String,
+    pub signature: String,
+}
+
+/// What a signature covers besides the body: who sent it to whom, for which route, when, and
+/// the nonce of the request (a response repeats the nonce of the request it answers).
+#[derive(Debug, Clone, Copy)]
+pub struct SignedEnvelope<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+    /// Route under `/federation/`, e.g. `rpc`.
+    pub route: &'a str,
+    pub timestamp_ms: u64,
+    pub nonce: &'a str,
+}
+
+/// This gateway's Ed25519 key pair, created on first use.
+pub struct Identity {
+    key_pair: Ed25519KeyPair,
+}
+
+impl Identity {
+    #[must_use]
+    pub fn public_key(&self) -> String {
+        hex_encode(self.key_pair.public_key().as_ref())
+    }
+
+    #[must_use]
+    pub fn sign(&self, envelope: &SignedEnvelope<'_>, body: &[u8]) -> String {
+        hex_encode(self.key_pair.sign(&signing_input(envelope, body)).as_ref())
+    }
+}
+
+pub async fn identity(state: &SharedState) -> Result<Identity, String> {
+    let stored = state
+        .get_config_entry_value(IDENTITY_KEY)
+        .await
+        .map_err(|error| error.to_string())?;
+    if let Some(pkcs8) = stored
+        .as_ref()
+        .and_then(|value| value.get("pkcs8"))
+        .and_then(Value::as_str)
+        .and_then(hex_decode)
+    {
+        let key_pair = Ed25519KeyPair::from_pkcs8(&pkcs8)
+            .map_err(|_| "stored federation identity is invalid".to_owned())?;
+        return Ok(Identity { key_pair });
+    }
+
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+        .map_err(|_| "failed to generate federation identity".to_owned())?;
+    let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+        .map_err(|_| "failed to load federation identity".to_owned())?;
+    state
+        .set_config_entry_value(
+            IDENTITY_KEY,
+            &json!({ "pkcs8": hex_encode(pkcs8.as_ref()) }),
+        )
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(Identity { key_pair })
+}
+
+/// Issues a one-time token another gateway passes to `federation.pair` within 15 minutes.
+pub async fn create_invite(state: &SharedState, federation: &Federation) -> Result<Value, String> {
+    let identity = identity(state).await?;
+    let token = format!(
+        "{INVITE_TOKEN_PREFIX}{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let expires_at_ms = now_unix_ms().saturating_add(INVITE_TTL_MS);
+    state
+        .set_config_entry_value(
+            &invite_key(&token),
+            &json!({ "expiresAtMs": expires_at_ms }),
+        )
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(json!({
+        "gatewayId": federation.gateway_id,
+        "url": federation.public_url,
+        "publicKey": identity.public_key(),
+        "token": token,
+        "expiresAtMs": expires_at_ms,
+    }))
+}
+
+/// Pairs with the gateway at `url` using its invite token. The peer's response must be signed by
+/// `public_key`, the key the operator received with the invite.
+pub async fn pair_with(
+    state: &SharedState,
+    federation: &Federation,
+    gateway_id: &str,
+    url: &str,
+    token: &str,
+    public_key: &str,
+) -> Result<PeerRecord, String> {
+    let peer_id = gateway_id.trim().to_ascii_lowercase();
+    if !is_peer_id(&peer_id) {
+        return Err("gatewayId must use a-z, 0-9, '-' or '_'".to_owned());
+    }
+    if peer_id == federation.gateway_id {
+        return Err("cannot pair a gateway with itself".to_owned());
+    }
+    if get_peer(state, &peer_id).await?.is_some() {
+        return Err(already_paired(&peer_id));
+    }
+    let url = normalize_url(url).ok_or("url must be an http(s) URL")?;
+    let public_key = public_key.trim().to_ascii_lowercase();
+    if hex_decode(&public_key).is_none_or(|bytes| bytes.len() != 32) {
+        return Err("publicKey must be a hex-encoded Ed25519 key".to_owned());
+    }
+    let identity = identity(state).await?;
+    let body = json!({
+        "token": token,
+        "peer": {
+            "id": federation.gateway_id,
+            "url": federation.public_url,
+            "publicKey": identity.public_key(),
+        },
+    });
+    let (headers, response) =
+        signed_post(&identity, federation, &url, &peer_id, "pair", &body).await?;
+    if headers.peer_id != peer_id {
+        return Err(format!("response came from {} instead", headers.peer_id));
+    }
+    verify_signed(
+        &headers,
+        &public_key,
+        federation.gateway_id(),
+        "pair",
+        &response,
+    )?;
+    let response: Value = serde_json::from_slice(&response)
+        .map_err(|error| format!("invalid pairing response: {error}"))?;
+    if response.get("id").and_then(Value::as_str) != Some(peer_id.as_str()) {
+        return Err("pairing response has no valid gateway id".to_owned());
+    }
+
+    let now = now_unix_ms();
+    let peer = PeerRecord {
+        id: peer_id,
+        url,
+        public_key,
+        paired_at_ms: now,
+        health: PeerHealth {
+            status: "up".to_owned(),
+            checked_at_ms: Some(now),
+            latency_ms: None,
+            error: None,
+        },
+    };
+    insert_peer(state, &peer).await?;
+    info!("paired with federation peer {}", peer.id);
+    Ok(peer)
+}
+
+/// Accepts a pairing request signed by the key it announces, consuming the invite token, and
+/// refuses a gateway id that is already paired. Returns the body to send back (signed by the
+/// caller).
+pub async fn accept_pairing(
+    state: &SharedState,
+    federation: &Federation,
+    headers: &SignedHeaders,
+    body: &[u8],
+) -> Result<Value, String> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PairPeer {
+        id: String,
+        url: String,
+        public_key: String,
+    }
+    #[derive(Deserialize)]
+    struct PairRequest {
+        token: String,
+        peer: PairPeer,
+    }
+
+    let request: PairRequest = serde_json::from_slice(body)
+        .map_err(|error| format!("invalid pairing request: {error}"))?;
+    let peer_id = request.peer.id.trim().to_ascii_lowercase();
+    if !is_peer_id(&peer_id) || peer_id != headers.peer_id || peer_id == federation.gateway_id {
+        return Err("pairing request has no valid gateway id".to_owned());
+    }
+    let url = normalize_url(&request.peer.url).ok_or("pairing request has no valid url")?;
+    let public_key = request.peer.public_key.trim().to_ascii_lowercase();
+    verify_signed(headers, &public_key, federation.gateway_id(), "pair", body)?;
+
+    // The invite is the only credential here: a self-signed request proves nothing until it
+    // consumes one, so nothing is cached or revealed before that.
+    let invite_key = invite_key(&request.token);
+    let invite = state
+        .get_config_entry_value(&invite_key)
+        .await
+        .map_err(|error| error.to_string())?;
+    let consumed = state
+        .delete_config_entry_value(&invite_key)
+        .await
+        .map_err(|error| error.to_string())?;
+    let expires_at_ms = invite
+        .as_ref()
+        .and_then(|invite| invite.get("expiresAtMs"))
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+    if !consumed || expires_at_ms < now_unix_ms() {
+        return Err("invite token is invalid or expired".to_owned());
+    }
+    remember_nonce(state, headers).await?;
+
+    let identity = identity(state).await?;
+    let peer = PeerRecord {
+        id: peer_id,
+        url,
+        public_key,
+        paired_at_ms: now_unix_ms(),
+        health: PeerHealth::default(),
+    };
+    insert_peer(state, &peer).await?;
+    info!("accepted federation pairing from {}", peer.id);
+    Ok(json!({
+        "id": federation.gateway_id,
+        "url": federation.public_url,
+        "publicKey": identity.public_key(),
+    }))
+}
+
+/// Checks a signed request from a paired peer to `/federation/<route>` and returns the peer.
+/// A nonce is accepted once.
+pub async fn authenticate_peer(
+    state: &SharedState,
+    federation: &Federation,
+    headers: &SignedHeaders,
+    route: &str,
+    body: &[u8],
+) -> Result<PeerRecord, String> {
+    let peer = get_peer(state, &headers.peer_id)
+        .await?
+        .ok_or_else(|| format!("unknown peer {}", headers.peer_id))?;
+    verify_signed(
+        headers,
+        &peer.public_key,
+        federation.gateway_id(),
+        route,
+        body,
+    )?;
+    remember_nonce(state, headers).await?;
+    Ok(peer)
+}
+
+/// Rejects a request whose nonce this peer already used within the clock-skew window. Only
+/// called once the request is authenticated (a paired peer's signature, or a consumed pairing
+/// invite), so unauthenticated requests cannot fill the cache.
+async fn remember_nonce(state: &SharedState, headers: &SignedHeaders) -> Result<(), String> {
+    let key = format!("{}:{}", headers.peer_id, headers.nonce);
+    let expires_at_ms = headers.timestamp_ms.saturating_add(MAX_CLOCK_SKEW_MS);
+    if state.remember_federation_nonce(&key, expires_at_ms).await {
+        Ok(())
+    } else {
+        Err(format!("replayed request from {}", headers.peer_id))
+    }
+}
+
+/// Session a peer's forwarded requests run under: `operator.write` only.
+#[must_use]
+pub fn peer_session(peer_id: &str) -> SessionContext {
+    SessionContext {
+        conn_id: format!("peer-{peer_id}-{}", uuid::Uuid::new_v4()),
+        role: "operator".to_owned(),
+        scopes: vec![crate::rpc::policy::WRITE_SCOPE.to_owned()],
+        client_id: format!("{PEER_NAMESPACE}:{peer_id}"),
+        client_mode: FEDERATION_CLIENT_MODE.to_owned(),
+    }
+}
+
+/// Forwards a routed method whose session key or node id is `peer:<peerId>:<id>` to that peer,
+/// and `node.invoke` for a node pinned to a faster peer. Returns `None` when federation is off
+/// or the target is local.
+pub async fn forward_if_remote(
+    state: &SharedState,
+    session: &SessionContext,
+    method: &str,
+    params: Option<&Value>,
+) -> Option<Result<Value, ErrorShape>> {
+    let federation = state.config().federation.as_ref()?;
+    let (_, field) = ROUTED_METHODS.iter().find(|(name, _)| *name == method)?;
+    let target = params?.get(*field)?.as_str()?;
+    let Some((peer_id, remote)) = split_peer_target(target) else {
+        if method == "node.invoke" && session.client_mode != FEDERATION_CLIENT_MODE {
+            return node_affinity::route_invoke(state, federation, target.trim(), params?).await;
+        }
+        return None;
+    };
+    if session.client_mode == FEDERATION_CLIENT_MODE {
+        return Some(Err(ErrorShape::new(
+            ERROR_INVALID_REQUEST,
+            "requests from a peer cannot be forwarded to another peer",
+        )));
+    }
+
+    let mut params = params.cloned().unwrap_or_default();
+    params[*field] = Value::String(remote.to_owned());
+    Some(call_peer(state, federation, peer_id, method, params).await)
+}
+
+pub(crate) async fn call_peer(
+    state: &SharedState,
+    federation: &Federation,
+    peer_id: &str,
+    method: &str,
+    params: Value,
+) -> Result<Value, ErrorShape> {
+    let unavailable = |message: String| ErrorShape::new(ERROR_UNAVAILABLE, message);
+    let peer = get_peer(state, peer_id)
+        .await
+        .map_err(unavailable)?
+        .ok_or_else(|| {
+            ErrorShape::new(
+                ERROR_INVALID_REQUEST,
+                format!("unknown federation peer {peer_id}"),
+            )
+        })?;
+    let started = Instant::now();
+    let response = request_peer(
+        state,
+        federation,
+        &peer,
+        "rpc",
+        &json!({
+            "method": method,
+            "params": params,
+            "timeoutMs": deadline::remaining()
+                .map(|left| u64::try_from(left.as_millis()).unwrap_or(u64::MAX)),
+        }),
+    )
+    .await;
+    record_health(state, peer, started, response.as_ref().err()).await;
+    let response =
+        response.map_err(|error| unavailable(format!("peer {peer_id} is unreachable: {error}")))?;
+
+    if response.get("ok").and_then(Value::as_bool) == Some(true) {
+        return Ok(response.get("payload").cloned().unwrap_or_default());
+    }
+    let error = response.get("error");
+    let text = |key: &str| {
+        error
+            .and_then(|error| error.get(key))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned()
+    };
+    let mut shape = ErrorShape::new(
+        ERROR_UNAVAILABLE,
+        format!("peer {peer_id}: {}", text("message")),
+    );
+    let code = text("code");
+    if !code.is_empty() {
+        shape.code = code;
+    }
+    Err(shape)
+}
+
+/// Probes every peer's `/federation/health` on the configured interval.
+pub fn spawn_peer_health_monitor(state: SharedState) -> Option<tokio::task::JoinHandle<()>> {
+    let federation = state.config().federation.clone()?;
+    info!(
+        "federation enabled as {} (health checks every {}s)",
+        federation.gateway_id,
+        federation.health_interval.as_secs()
+    );
+
+    Some(tokio::spawn(async move {
+        if let Err(error) = identity(&state).await {
+            warn!("federation identity unavailable: {error}");
+        }
+        let mut ticker = tokio::time::interval(federation.health_interval);
+        loop {
+            ticker.tick().await;
+            let peers = match list_peers(&state).await {
+                Ok(peers) => peers,
+                Err(error) => {
+                    warn!("failed to list federation peers: {error}");
+                    continue;
+                }
+            };
+            for peer in peers {
+                let started = Instant::now();
+                let result = request_peer(&state, &federation, &peer, "health", &json!({})).await;
+                if let Err(error) = &result {
+                    warn!("federation peer {} is down: {error}", peer.id);
+                }
+                record_health(&state, peer, started, result.as_ref().err()).await;
+            }
+        }
+    }))
+}
+
+/// `status.federation`: this gateway's id and key plus every peer with its latest health.
+pub async fn status(state: &SharedState) -> Option<Value> {
+    let federation = state.config().federation.as_ref()?;
+    let public_key = identity(state)
+        .await
+        .map(|identity| identity.public_key())
+        .ok();
+    let peers = list_peers(state).await.unwrap_or_default();
+    Some(json!({
+        "gatewayId": federation.gateway_id,
+        "publicUrl": federation.public_url,
+        "publicKey": public_key,
+        "peers": peers,
+    }))
+}
+
+pub async fn list_peers(state: &SharedState) -> Result<Vec<PeerRecord>, String> {
+    let entries = state
+        .list_config_entries(PEER_PREFIX, None)
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value(entry.value).ok())
+        .collect())
+}
+
+pub async fn remove_peer(state: &SharedState, peer_id: &str) -> Result<bool, String> {
+    state
+        .delete_config_entry_value(&format!("{PEER_PREFIX}{}", peer_id.trim()))
+        .await
+        .map_err(|error| error.to_string())
+}
+
+async fn get_peer(state: &SharedState, peer_id: &str) -> Result<Option<PeerRecord>, String> {
+    let value = state
+        .get_config_entry_value(&format!("{PEER_PREFIX}{peer_id}"))
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(value.and_then(|value| serde_json::from_value(value).ok()))
+}
+
+/// Stores a newly paired peer, refusing an id that is already paired: re-pairing would silently
+/// replace its key, so the peer has to be unpaired first.
+async fn insert_peer(state: &SharedState, peer: &PeerRecord) -> Result<(), String> {
+    let value = serde_json::to_value(peer).map_err(|error| error.to_string())?;
+    let stored = state
+        .get_or_insert_config_entry_value(&format!("{PEER_PREFIX}{}", peer.id), &value)
+        .await
+        .map_err(|error| error.to_string())?;
+    if stored != value {
+        return Err(already_paired(&peer.id));
+    }
+    Ok(())
+}
+
+fn already_paired(peer_id: &str) -> String {
+    format!("gateway {peer_id} is already paired; unpair it first")
+}
+
+async fn save_peer(state: &SharedState, peer: &PeerRecord) -> Result<(), String> {
+    let value = serde_json::to_value(peer).map_err(|error| error.to_string())?;
+    state
+        .set_config_entry_value(&format!("{PEER_PREFIX}{}", peer.id), &value)
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(())
+}
+
+async fn record_health(
+    state: &SharedState,
+    mut peer: PeerRecord,
+    started: Instant,
+    error: Option<&String>,
+) {
+    peer.health = PeerHealth {
+        status: if error.is_some() { "down" } else { "up" }.to_owned(),
+        checked_at_ms: Some(now_unix_ms()),
+        latency_ms: error
+            .is_none()
+            .then(|| u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)),
+        error: error.cloned(),
+    };
+    if let Err(error) = save_peer(state, &peer).await {
+        warn!("failed to record federation peer health: {error}");
+    }
+}
+
+/// Sends a signed request to `/federation/<route>` of a paired peer and checks that the
+/// response is signed by the peer's key.
+async fn request_peer(
+    state: &SharedState,
+    federation: &Federation,
+    peer: &PeerRecord,
+    route: &str,
+    body: &Value,
+) -> Result<Value, String> {
+    let identity = identity(state).await?;
+    let (headers, response) =
+        signed_post(&identity, federation, &peer.url, &peer.id, route, body).await?;
+    if headers.peer_id != peer.id {
+        return Err(format!("response came from {} instead", headers.peer_id));
+    }
+    verify_signed(
+        &headers,
+        &peer.public_key,
+        federation.gateway_id(),
+        route,
+        &response,
+    )?;
+    serde_json::from_slice(&response).map_err(|error| format!("invalid peer response: {error}"))
+}
+
+/// Posts a signed request to `<base_url>/federation/<route>` on gateway `to`. The returned
+/// headers carry the request's nonce, which the response must be signed over.
+async fn signed_post(
+    identity: &Identity,
+    federation: &Federation,
+    base_url: &str,
+    to: &str,
+    route: &str,
+    body: &Value,
+) -> Result<(SignedHeaders, Vec<u8>), String> {
+    let body = body.to_string();
+    let timestamp_ms = now_unix_ms();
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let signature = identity.sign(
+        &SignedEnvelope {
+            from: &federation.gateway_id,
+            to,
+            route,
+            timestamp_ms,
+            nonce: &nonce,
+        },
+        body.as_bytes(),
+    );
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|error| format!("failed to construct http client: {error}"))?;
+    let response = client
+        .post(format!("{base_url}/federation/{route}"))
+        .header("content-type", "application/json")
+        .header(PEER_HEADER, &federation.gateway_id)
+        .header(TIMESTAMP_HEADER, timestamp_ms.to_string())
+        .header(NONCE_HEADER, &nonce)
+        .header(SIGNATURE_HEADER, signature)
+        .body(body)
+        .send()
+        .await
+        .map_err(|error| format!("request failed: {error}"))?;
+    let status = response.status();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    };
+    let headers = parse_signed_headers(
+        header(PEER_HEADER).as_deref(),
+        header(TIMESTAMP_HEADER).as_deref(),
+        Some(&nonce),
+        header(SIGNATURE_HEADER).as_deref(),
+    );
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|error| format!("failed to read response: {error}"))?;
+    if !status.is_success() {
+        let message = serde_json::from_slice::<Value>(&bytes)
+            .ok()
+            .and_then(|body| {
+                let error = body.get("error")?;
+                error
+                    .get("message")
+                    .unwrap_or(error)
+                    .as_str()
+                    .map(str::to_owned)
+            })
+            .unwrap_or_else(|| String::from_utf8_lossy(&bytes).into_owned());
+        return Err(format!("status {status}: {message}"));
+    }
+    Ok((headers?, bytes.to_vec()))
+}
+
+/// Reads the signature headers of a peer request or response.
+pub fn parse_signed_headers(
+    peer_id: Option<&str>,
+    timestamp_ms: Option<&str>,
+    nonce: Option<&str>,
+    signature: Option<&str>,
+) -> Result<SignedHeaders, String> {
+    let peer_id = peer_id
+        .map(|id| id.trim().to_ascii_lowercase())
+        .filter(|id| is_peer_id(id))
+        .ok_or_else(|| format!("missing or invalid {PEER_HEADER}"))?;
+    let timestamp_ms = timestamp_ms
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .ok_or_else(|| format!("missing or invalid {TIMESTAMP_HEADER}"))?;
+    let nonce = nonce
+        .map(str::trim)
+        .filter(|nonce| {
+            !nonce.is_empty()
+                && nonce.len() <= MAX_NONCE_LEN
+                && nonce
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+        })
+        .ok_or_else(|| format!("missing or invalid {NONCE_HEADER}"))?
+        .to_owned();
+    let signature = signature
+        .map(str::trim)
+        .filter(|signature| !signature.is_empty())
+        .ok_or_else(|| format!("missing {SIGNATURE_HEADER}"))?
+        .to_owned();
+    Ok(SignedHeaders {
+        peer_id,
+        timestamp_ms,
+        nonce,
+        signature,
+    })
+}
+
+/// Checks that `headers` sign `body` as sent by their peer to gateway `to` on `route`.
+fn verify_signed(
+    headers: &SignedHeaders,
+    public_key: &str,
+    to: &str,
+    route: &str,
+    body: &[u8],
+) -> Result<(), String> {
+    if now_unix_ms().abs_diff(headers.timestamp_ms) > MAX_CLOCK_SKEW_MS {
+        return Err("signature timestamp is outside the allowed clock skew".to_owned());
+    }
+    let message = signing_input(
+        &SignedEnvelope {
+            from: &headers.peer_id,
+            to,
+            route,
+            timestamp_ms: headers.timestamp_ms,
+            nonce: &headers.nonce,
+        },
+        body,
+    );
+    if verify_ed25519_hex(public_key, &message, &headers.signature) {
+        Ok(())
+    } else {
+        Err(format!("invalid signature from {}", headers.peer_id))
+    }
+}
+
+/// Bytes covered by a peer signature:
+/// `<from>\n<to>\nPOST /federation/<route>\n<timestampMs>\n<nonce>\n<body>`.
+fn signing_input(envelope: &SignedEnvelope<'_>, body: &[u8]) -> Vec<u8> {
+    let SignedEnvelope {
+        from,
+        to,
+        route,
+        timestamp_ms,
+        nonce,
+    } = envelope;
+    let mut input =
+        format!("{from}\n{to}\nPOST /federation/{route}\n{timestamp_ms}\n{nonce}\n").into_bytes();
+    input.extend_from_slice(body);
+    input
+}
+
+/// Splits `peer:<peerId>:<id>` into the peer id and the id on that peer.
+fn split_peer_target(target: &str) -> Option<(&str, &str)> {
+    let rest = target.strip_prefix(PEER_NAMESPACE)?.strip_prefix(':')?;
+    let (peer_id, remote) = rest.split_once(':')?;
+    (!peer_id.is_empty() && !remote.is_empty()).then_some((peer_id, remote))
+}
+
+fn invite_key(token: &str) -> String {
+    format!(
+        "{INVITE_PREFIX}{}",
+        hex_encode(&Sha256::digest(token.trim().as_bytes()))
+    )
+}
+
+fn normalize_url(raw: &str) -> Option<String> {
+    let url = raw.trim().trim_end_matches('/');
+    (url.starts_with("http://") || url.starts_with("https://")).then(|| url.to_owned())
+}
+
+fn is_peer_id(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-' || ch == '_')
+}
+
+fn unknown_status() -> String {
+    "unknown".to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::{
+        rand::SystemRandom,
+        signature::{Ed25519KeyPair, KeyPair},
+    };
+
+    use super::{Identity, SignedEnvelope, parse_signed_headers, split_peer_target, verify_signed};
+    use crate::{security::signatures::hex_encode, storage::now_unix_ms};
+
+    #[test]
+    fn signatures_bind_sender_recipient_route_nonce_and_body() {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).expect("key");
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).expect("key pair");
+        let public_key = hex_encode(key_pair.public_key().as_ref());
+        let identity = Identity { key_pair };
+        let now = now_unix_ms();
+        let signature = identity.sign(
+            &SignedEnvelope {
+                from: "home-a",
+                to: "home-b",
+                route: "rpc",
+                timestamp_ms: now,
+                nonce: "n1",
+            },
+            b"{}",
+        );
+        let headers = |timestamp: &str, nonce: &str| {
+            parse_signed_headers(
+                Some("home-a"),
+                Some(timestamp),
+                Some(nonce),
+                Some(&signature),
+            )
+            .expect("headers should parse")
+        };
+
+        let signed = headers(&now.to_string(), "n1");
+        assert_eq!(
+            verify_signed(&signed, &public_key, "home-b", "rpc", b"{}"),
+            Ok(())
+        );
+        assert!(verify_signed(&signed, &public_key, "home-b", "rpc", b"{\"x\":1}").is_err());
+        assert!(verify_signed(&signed, &public_key, "home-c", "rpc", b"{}").is_err());
+        assert!(verify_signed(&signed, &public_key, "home-b", "health", b"{}").is_err());
+        let other_nonce = headers(&now.to_string(), "n2");
+        assert!(verify_signed(&other_nonce, &public_key, "home-b", "rpc", b"{}").is_err());
+        let stale = headers("1000", "n1");
+        assert!(verify_signed(&stale, &public_key, "home-b", "rpc", b"{}").is_err());
+
+        assert!(parse_signed_headers(Some("Home A"), Some("1"), Some("n"), Some("ab")).is_err());
+        assert!(parse_signed_headers(Some("home-a"), Some("1"), None, Some("ab")).is_err());
+        assert!(parse_signed_headers(Some("home-a"), Some("1"), Some("a b"), Some("ab")).is_err());
+    }
+
+    #[test]
+    fn peer_targets_split_into_peer_and_remote_id() {
+        assert_eq!(
+            split_peer_target("peer:home-b:agent:main:main"),
+            Some(("home-b", "agent:main:main"))
+        );
+        assert_eq!(
+            split_peer_target("peer:home-b:node-1"),
+            Some(("home-b", "node-1"))
+        );
+        assert_eq!(split_peer_target("agent:main:main"), None);
+        assert_eq!(split_peer_target("peer:home-b"), None);
+    }
+}
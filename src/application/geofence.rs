@@ -0,0 +1,359 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    application::state::SharedState,
+    rpc::{
+        SessionContext,
+        methods::{agent, system},
+        policy,
+        schema::rpc_params,
+    },
+    storage::now_unix_ms,
+};
+
+const FENCE_PREFIX: &str = "runtime/geofences/";
+const FENCE_STATE_PREFIX: &str = "runtime/geofence-state/";
+/// Mean Earth radius used for haversine distances.
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    pub fn validate(&self, field: &str) -> Result<(), String> {
+        if !self.lat.is_finite() || !(-90.0..=90.0).contains(&self.lat) {
+            return Err(format!("{field}.lat must be between -90 and 90"));
+        }
+        if !self.lon.is_finite() || !(-180.0..=180.0).contains(&self.lon) {
+            return Err(format!("{field}.lon must be between -180 and 180"));
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn distance_m(&self, other: &Self) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_M * a.sqrt().min(1.0).asin()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeofenceTrigger {
+    Enter,
+    Exit,
+    #[default]
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transition {
+    Enter,
+    Exit,
+}
+
+impl GeofenceTrigger {
+    fn matches(self, transition: Transition) -> bool {
+        matches!(
+            (self, transition),
+            (Self::Both, _) | (Self::Enter, Transition::Enter) | (Self::Exit, Transition::Exit)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum GeofenceAction {
+    /// Dispatches an agent run, like a hook mapping with `action: "agent"`.
+    #[serde(rename_all = "camelCase")]
+    Agent {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        agent_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session_key: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+    /// Records a heartbeat wake, like `wake`.
+    Wake {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+}
+
+rpc_params! {
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Geofence {
+        pub id: String,
+        /// Limits the fence to one node; every node is watched when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub node_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub name: Option<String>,
+        pub center: GeoPoint,
+        pub radius_m: f64,
+        #[serde(default)]
+        pub on: GeofenceTrigger,
+        pub action: GeofenceAction,
+        #[serde(default)]
+        pub updated_at_ms: u64,
+    }
+}
+
+impl Geofence {
+    fn normalize(mut self) -> Result<Self, String> {
+        self.id = self.id.trim().to_owned();
+        if self.id.is_empty() || self.id.contains('/') {
+            return Err("id is required and must not contain '/'".to_owned());
+        }
+        self.node_id = self.node_id.and_then(non_empty);
+        self.name = self.name.and_then(non_empty);
+        self.center.validate("center")?;
+        if !self.radius_m.is_finite() || self.radius_m <= 0.0 {
+            return Err("radiusM must be greater than 0".to_owned());
+        }
+        self.action = match self.action {
+            GeofenceAction::Agent {
+                agent_id,
+                session_key,
+                message,
+            } => GeofenceAction::Agent {
+                agent_id: agent_id.and_then(non_empty),
+                session_key: session_key.and_then(non_empty),
+                message: message.and_then(non_empty),
+            },
+            GeofenceAction::Wake { reason } => GeofenceAction::Wake {
+                reason: reason.and_then(non_empty),
+            },
+        };
+        self.updated_at_ms = now_unix_ms();
+        Ok(self)
+    }
+
+    fn applies_to(&self, node_id: &str) -> bool {
+        self.node_id.as_deref().is_none_or(|id| id == node_id)
+    }
+
+    fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FenceState {
+    inside: bool,
+    changed_at_ms: u64,
+}
+
+/// A node with no recorded state counts as outside, so its first report inside a fence enters it.
+#[must_use]
+pub fn transition(was_inside: bool, inside: bool) -> Option<Transition> {
+    match (was_inside, inside) {
+        (false, true) => Some(Transition::Enter),
+        (true, false) => Some(Transition::Exit),
+        _ => None,
+    }
+}
+
+pub async fn set_fence(state: &SharedState, fence: Geofence) -> Result<Geofence, String> {
+    let fence = fence.normalize()?;
+    let value = serde_json::to_value(&fence).map_err(|error| error.to_string())?;
+    state
+        .set_config_entry_value(&format!("{FENCE_PREFIX}{}", fence.id), &value)
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(fence)
+}
+
+pub async fn list_fences(state: &SharedState) -> Result<Vec<Geofence>, String> {
+    let entries = state
+        .list_config_entries(FENCE_PREFIX, None)
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value(entry.value).ok())
+        .collect())
+}
+
+pub async fn remove_fence(state: &SharedState, fence_id: &str) -> Result<bool, String> {
+    let fence_id = fence_id.trim();
+    let state_prefix = format!("{FENCE_STATE_PREFIX}{fence_id}/");
+    let entries = state
+        .list_config_entries(&state_prefix, None)
+        .await
+        .map_err(|error| error.to_string())?;
+    for entry in entries {
+        state
+            .delete_config_entry_value(&entry.key)
+            .await
+            .map_err(|error| error.to_string())?;
+    }
+    state
+        .delete_config_entry_value(&format!("{FENCE_PREFIX}{fence_id}"))
+        .await
+        .map_err(|error| error.to_string())
+}
+
+/// Compares `location` with every fence watching `node_id` and runs the action of each fence
+/// whose boundary the node just crossed. Returns the crossings that fired.
+pub async fn evaluate(
+    state: &SharedState,
+    node_id: &str,
+    location: GeoPoint,
+) -> Result<Vec<Value>, String> {
+    let mut fired = Vec::new();
+    for fence in list_fences(state).await? {
+        if !fence.applies_to(node_id) {
+            continue;
+        }
+        let distance_m = fence.center.distance_m(&location);
+        let inside = distance_m <= fence.radius_m;
+        let state_key = format!("{FENCE_STATE_PREFIX}{}/{node_id}", fence.id);
+        let previous: FenceState = state
+            .get_config_entry_value(&state_key)
+            .await
+            .map_err(|error| error.to_string())?
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let Some(crossed) = transition(previous.inside, inside) else {
+            continue;
+        };
+        let now = now_unix_ms();
+        state
+            .set_config_entry_value(
+                &state_key,
+                &json!(FenceState {
+                    inside,
+                    changed_at_ms: now,
+                }),
+            )
+            .await
+            .map_err(|error| error.to_string())?;
+        if !fence.on.matches(crossed) {
+            continue;
+        }
+
+        let crossing = json!({
+            "fenceId": fence.id,
+            "nodeId": node_id,
+            "transition": crossed,
+            "location": location,
+            "distanceM": distance_m.round(),
+            "ts": now,
+        });
+        run_action(state, &fence, node_id, crossed, now).await;
+        state
+            .publish_gateway_event("node.geofence", crossing.clone())
+            .await;
+        fired.push(crossing);
+    }
+    Ok(fired)
+}
+
+async fn run_action(
+    state: &SharedState,
+    fence: &Geofence,
+    node_id: &str,
+    crossed: Transition,
+    now: u64,
+) {
+    let verb = match crossed {
+        Transition::Enter => "entered",
+        Transition::Exit => "left",
+    };
+    let session = SessionContext {
+        conn_id: format!("geofence-{}", uuid::Uuid::new_v4()),
+        role: "operator".to_owned(),
+        scopes: policy::default_operator_scopes(),
+        client_id: "geofence".to_owned(),
+        client_mode: "geofence".to_owned(),
+    };
+    let result = match &fence.action {
+        GeofenceAction::Agent {
+            agent_id,
+            session_key,
+            message,
+        } => {
+            let params = json!({
+                "message": message
+                    .clone()
+                    .unwrap_or_else(|| format!("Node {node_id} {verb} geofence {}", fence.label())),
+                "name": format!("geofence:{}", fence.id),
+                "agentId": agent_id,
+                "sessionKey": session_key,
+                "idempotencyKey": format!("geofence-{}-{node_id}-{now}", fence.id),
+            });
+            agent::handle_agent(state, &session, Some(&params)).await
+        }
+        GeofenceAction::Wake { reason } => {
+            let params = json!({
+                "reason": reason
+                    .clone()
+                    .unwrap_or_else(|| format!("geofence:{}", fence.id)),
+            });
+            system::handle_wake(state, &session, Some(&params)).await
+        }
+    };
+
+    let message = match result {
+        Ok(_) => format!("node {node_id} {verb} geofence {}", fence.id),
+        Err(error) => format!(
+            "node {node_id} {verb} geofence {} but its action failed: {}",
+            fence.id, error.message
+        ),
+    };
+    let _ = state
+        .append_gateway_log("info", &message, Some("node.geofence"), None)
+        .await;
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GeoPoint, GeofenceTrigger, Transition, transition};
+
+    #[test]
+    fn haversine_distance_and_crossings() {
+        let paris = GeoPoint {
+            lat: 48.8566,
+            lon: 2.3522,
+        };
+        let london = GeoPoint {
+            lat: 51.5074,
+            lon: -0.1278,
+        };
+        let distance = paris.distance_m(&london);
+        assert!((distance - 343_550.0).abs() < 1_000.0, "{distance}");
+        assert!(paris.distance_m(&paris).abs() < f64::EPSILON);
+        assert!(
+            GeoPoint {
+                lat: 91.0,
+                lon: 0.0
+            }
+            .validate("center")
+            .is_err()
+        );
+
+        assert_eq!(transition(false, true), Some(Transition::Enter));
+        assert_eq!(transition(true, false), Some(Transition::Exit));
+        assert_eq!(transition(false, false), None);
+        assert_eq!(transition(true, true), None);
+        assert!(GeofenceTrigger::Both.matches(Transition::Exit));
+        assert!(!GeofenceTrigger::Enter.matches(Transition::Exit));
+    }
+}
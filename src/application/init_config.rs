@@ -178,13 +178,19 @@ fn base_config_template(target: &ConfigTarget) -> String {
 host = \"127.0.0.1\"\n\
 port = 18789\n\
 cronEnabled = true\n\
+# cronMaxWorkers = 4 # cron runs executing at once; per-job limits come from maxConcurrent\n\
 logFilter = \"info\"\n\
 jsonLogs = false\n\
 dbPath = \"{}\"\n\
+# appendOnly = false # keep deleted conversation rows as tombstones\n\
+# chatWriteBatchMs = 5 # commit chat messages in batches within this window\n\
+# nodeFilesDir = \"/var/lib/reclaw/node-files\" # enables node.file.push / node.file.pull\n\
 \n\
 # Set only one of gatewayToken or gatewayPassword.\n\
 # gatewayToken = \"replace-me\"\n\
 # gatewayPassword = \"replace-me\"\n\
+# Or load the token from an OS keychain or a TPM-sealed systemd credential.\n\
+# gatewayTokenSecret = \"keychain:gateway-token\" # or \"tpm:gateway-token\"\n\
 \n\
 # Optional bearer token for /channels/inbound (recommended when exposed).\n\
 # channelsInboundToken = \"replace-me\"\n\
@@ -193,6 +199,7 @@ dbPath = \"{}\"\n\
 # telegramWebhookSecret = \"replace-me\"\n\
 # telegramBotToken = \"replace-me\"\n\
 # telegramApiBaseUrl = \"https://api.telegram.org\"\n\
+# telegramPublicBaseUrl = \"https://bot.example.com\"\n\
 \n\
 # Additional channel webhook tokens (Authorization: Bearer <token>).\n\
 # discordWebhookToken = \"replace-me\"\n\
@@ -207,6 +214,10 @@ dbPath = \"{}\"\n\
 # whatsappWebhookToken = \"replace-me\"\n\
 # whatsappOutboundUrl = \"https://relay.example/whatsapp\"\n\
 # whatsappOutboundToken = \"replace-me\"\n\
+# mattermostWebhookToken = \"replace-me\" # outgoing webhook token, sent in the body\n\
+# mattermostIncomingWebhookUrl = \"https://mattermost.example/hooks/replace-me\"\n\
+# rocketchatWebhookToken = \"replace-me\" # outgoing webhook token, sent in the body\n\
+# rocketchatIncomingWebhookUrl = \"https://rocketchat.example/hooks/replace-me\"\n\
 \n\
 # External plugin webhook bridge (optional).\n\
 # Uses POST /channels/{{channel}}/webhook fallback when no in-process adapter is registered.\n\
@@ -215,6 +226,9 @@ dbPath = \"{}\"\n\
 # token = \"replace-me\" # sent via x-reclaw-plugin-token\n\
 # timeoutMs = 10000\n\
 \n\
+# Directory of channel adapter manifests (<channel>.toml or .json, each naming a command or url).\n\
+# channelPluginsDir = \"/etc/reclaw/channel-plugins\"\n\
+\n\
 # Hooks ingress (OpenClaw-compatible /hooks/* flow).\n\
 # hooksEnabled = true\n\
 # hooksToken = \"replace-me\"\n\
@@ -238,7 +252,12 @@ dbPath = \"{}\"\n\
 \n\
 # HTTP compatibility endpoints (disabled by default).\n\
 # openaiChatCompletionsEnabled = true\n\
-# openresponsesEnabled = true\n",
+# openresponsesEnabled = true\n\
+\n\
+# Anonymous status summary at /status/public (disabled by default).\n\
+# publicStatusEnabled = true\n\
+# publicStatusCacheSecs = 30\n\
+# publicStatusRateLimitPerMinute = 30\n",
         db_path.display()
     )
 }
@@ -0,0 +1,230 @@
+use std::time::Duration;
+
+use serde_json::{Value, json};
+
+use crate::{
+    application::{
+        exec_runner::{self, ExecChunk, ExecCommand, ExecStream},
+        state::SharedState,
+    },
+    rpc::methods::{
+        agents::AgentInlineExec,
+        approvals::{self, ExecPolicyDecision},
+    },
+};
+
+/// Fence languages that run through `sh -c`.
+const SHELL_LANGUAGES: &[&str] = &["sh", "bash", "shell"];
+/// Info-string word that opts a block in, e.g. `` ```sh exec ``.
+const EXEC_MARKER: &str = "exec";
+
+/// A fenced block marked executable. `end` is the byte offset just past its closing fence line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutableBlock {
+    pub code: String,
+    pub end: usize,
+}
+
+/// Finds fenced shell blocks whose info string carries the `exec` marker.
+#[must_use]
+pub fn executable_blocks(text: &str) -> Vec<ExecutableBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(bool, String)> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        offset += line.len();
+        let trimmed = line.trim_end();
+        match &mut open {
+            None => {
+                if let Some(info) = trimmed.strip_prefix("```") {
+                    let mut words = info.split_whitespace();
+                    let executable = words
+                        .next()
+                        .is_some_and(|lang| SHELL_LANGUAGES.contains(&lang))
+                        && words.any(|word| word == EXEC_MARKER);
+                    open = Some((executable, String::new()));
+                }
+            }
+            Some((executable, code)) => {
+                if trimmed == "```" {
+                    if *executable && !code.trim().is_empty() {
+                        blocks.push(ExecutableBlock {
+                            code: std::mem::take(code),
+                            end: offset,
+                        });
+                    }
+                    open = None;
+                } else {
+                    code.push_str(line);
+                }
+            }
+        }
+    }
+    blocks
+}
+
+/// Runs the executable blocks of `reply` through the exec runner and inserts each result after
+/// its block. A block only runs when an `exact` entry of the agent's exec allowlist names it;
+/// nothing waits for an approval, so every other block is rejected. Returns the new reply and one
+/// report per block.
+pub(crate) async fn render(
+    state: &SharedState,
+    settings: &AgentInlineExec,
+    agent_id: &str,
+    reply: &str,
+) -> (String, Vec<Value>) {
+    let blocks = executable_blocks(reply);
+    if blocks.is_empty() {
+        return (reply.to_owned(), Vec::new());
+    }
+    let mut config = state.config().exec.clone();
+    config.timeout = config
+        .timeout
+        .min(Duration::from_millis(settings.timeout_ms));
+
+    let mut rendered = String::with_capacity(reply.len());
+    let mut reports = Vec::new();
+    let mut copied = 0;
+    for (index, block) in blocks.into_iter().enumerate() {
+        rendered.push_str(&reply[copied..block.end]);
+        copied = block.end;
+        if !rendered.ends_with('\n') {
+            rendered.push('\n');
+        }
+        let command = block.code.trim().to_owned();
+        let (result, report) = if !config.enabled {
+            skipped("exec runner is disabled")
+        } else if index >= settings.max_blocks {
+            skipped("block limit reached")
+        } else {
+            run_block(state, &config, agent_id, &command).await
+        };
+        rendered.push_str(&result);
+        reports.push(report);
+    }
+    rendered.push_str(&reply[copied..]);
+    (rendered, reports)
+}
+
+async fn run_block(
+    state: &SharedState,
+    config: &crate::application::config::ExecRunnerConfig,
+    agent_id: &str,
+    command: &str,
+) -> (String, Value) {
+    match approvals::evaluate_inline_exec_policy(state, agent_id, command).await {
+        Err(error) => return skipped(&error.message),
+        Ok(ExecPolicyDecision::Deny(reason)) => return rejected(&format!("denied: {reason}")),
+        // The reply is delivered once rendering finishes, so there is nothing an approval could
+        // resume; filing one would leave a request that never runs anything.
+        Ok(ExecPolicyDecision::Ask) => {
+            return rejected(
+                "approval required; inline blocks only run commands with an exact allowlist entry",
+            );
+        }
+        Ok(ExecPolicyDecision::Allow) => {}
+    }
+
+    let (chunk_tx, mut chunk_rx) = tokio::sync::mpsc::channel::<ExecChunk>(64);
+    let request = ExecCommand {
+        command: command.to_owned(),
+        ..ExecCommand::default()
+    };
+    let run = exec_runner::run_command(config, &request, chunk_tx);
+    tokio::pin!(run);
+    let mut output = String::new();
+    let outcome = loop {
+        tokio::select! {
+            outcome = &mut run => {
+                while let Ok(chunk) = chunk_rx.try_recv() {
+                    push_chunk(&mut output, &chunk);
+                }
+                break outcome;
+            }
+            Some(chunk) = chunk_rx.recv() => push_chunk(&mut output, &chunk),
+        }
+    };
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(error) => return skipped(&error),
+    };
+
+    let status = if outcome.timed_out {
+        "timed-out"
+    } else {
+        "completed"
+    };
+    // The fence outgrows any backtick run in the output, so output cannot close it early.
+    let fence = "`".repeat(longest_backtick_run(&output).max(2) + 1);
+    let mut result = format!("{fence}text\n{output}");
+    if !output.is_empty() && !output.ends_with('\n') {
+        result.push('\n');
+    }
+    result.push_str(&fence);
+    result.push('\n');
+    result.push_str(&match (outcome.timed_out, outcome.exit_code) {
+        (true, _) => format!("[timed out after {}ms]\n", config.timeout.as_millis()),
+        (false, Some(code)) => format!("[exit code {code}]\n"),
+        (false, None) => "[exit code none]\n".to_owned(),
+    });
+    (
+        result,
+        json!({
+            "status": status,
+            "exitCode": outcome.exit_code,
+            "timedOut": outcome.timed_out,
+            "truncated": outcome.truncated,
+            "durationMs": outcome.duration_ms,
+        }),
+    )
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|ch| ch != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default()
+}
+
+fn push_chunk(output: &mut String, chunk: &ExecChunk) {
+    if chunk.stream == ExecStream::Stderr {
+        output.push_str("stderr: ");
+    }
+    output.push_str(&chunk.text);
+}
+
+fn skipped(reason: &str) -> (String, Value) {
+    (
+        format!("[not run: {reason}]\n"),
+        json!({ "status": "skipped", "reason": reason }),
+    )
+}
+
+fn rejected(reason: &str) -> (String, Value) {
+    (
+        format!("[not run: {reason}]\n"),
+        json!({ "status": "rejected", "reason": reason }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{executable_blocks, longest_backtick_run};
+
+    #[test]
+    fn only_shell_blocks_marked_exec_are_collected() {
+        let reply = "Try this:\n```sh exec\necho hi\n```\n```sh\nrm -rf /\n```\n```python exec\nprint(1)\n```\n```bash exec\n\n```\n```shell exec\nls\n```";
+        let blocks = executable_blocks(reply);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].code, "echo hi\n");
+        assert_eq!(
+            &reply[..blocks[0].end],
+            "Try this:\n```sh exec\necho hi\n```\n"
+        );
+        assert_eq!(blocks[1].code, "ls\n");
+        assert_eq!(blocks[1].end, reply.len());
+
+        assert_eq!(longest_backtick_run("a ``` b ````` c"), 5);
+        assert_eq!(longest_backtick_run("plain"), 0);
+    }
+}
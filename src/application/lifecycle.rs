@@ -0,0 +1,428 @@
+//! Planned stops and maintenance windows.
+//!
+//! `system.shutdown` and `system.restart` schedule a stop after an optional delay, warning
+//! clients with a `shutdown` event and optionally draining in-flight runs first. While the server
+//! drains or sits in a `system.maintenance` window it turns away new runs and webhooks, and reads
+//! keep working.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use serde_json::{Value, json};
+use tokio::{
+    sync::{Mutex, RwLock, oneshot, watch},
+    task::JoinHandle,
+};
+use tracing::info;
+
+use crate::{application::state::SharedState, storage::now_unix_ms};
+
+pub const SHUTDOWN_EVENT: &str = "shutdown";
+pub const MAINTENANCE_EVENT: &str = "maintenance";
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StopKind {
+    Shutdown,
+    Restart,
+}
+
+impl StopKind {
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Shutdown => "shutdown",
+            Self::Restart => "restart",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceWindow {
+    pub reason: Option<String>,
+    pub since_ms: u64,
+    /// When the window ends on its own; `None` lasts until disabled.
+    pub until_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledStop {
+    pub kind: StopKind,
+    pub reason: Option<String>,
+    pub requested_at_ms: u64,
+    pub at_ms: u64,
+    pub drain: bool,
+    pub drain_timeout_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct StopRequest {
+    pub kind: StopKind,
+    pub delay: Duration,
+    pub drain: bool,
+    pub drain_timeout: Duration,
+    pub reason: Option<String>,
+}
+
+/// Per-process stop and maintenance state held by [`SharedState`].
+#[derive(Debug)]
+pub struct Lifecycle {
+    maintenance: RwLock<Option<MaintenanceWindow>>,
+    draining: AtomicBool,
+    in_flight: Arc<AtomicUsize>,
+    scheduled: Mutex<Option<(ScheduledStop, JoinHandle<()>)>>,
+    stop: watch::Sender<Option<StopKind>>,
+}
+
+/// Counts a run or webhook as in flight until dropped, so a draining stop waits for it.
+#[derive(Debug)]
+pub struct WorkGuard(Arc<AtomicUsize>);
+
+impl Drop for WorkGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self {
+            maintenance: RwLock::new(None),
+            draining: AtomicBool::new(false),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            scheduled: Mutex::new(None),
+            stop: watch::Sender::new(None),
+        }
+    }
+}
+
+impl Lifecycle {
+    /// The active maintenance window; windows past `untilMs` count as ended.
+    pub async fn maintenance(&self) -> Option<MaintenanceWindow> {
+        let window = self.maintenance.read().await.clone()?;
+        if window.until_ms.is_some_and(|until| until <= now_unix_ms()) {
+            return None;
+        }
+        Some(window)
+    }
+
+    #[must_use]
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Why new runs and webhooks are turned away right now, with a retry hint in milliseconds
+    /// when the window has a known end.
+    pub async fn rejection(&self) -> Option<(String, Option<u64>)> {
+        if self.is_draining() {
+            return Some((
+                "the server is draining before a scheduled stop".to_owned(),
+                None,
+            ));
+        }
+        let window = self.maintenance().await?;
+        let message = match &window.reason {
+            Some(reason) => format!("the server is in maintenance: {reason}"),
+            None => "the server is in maintenance".to_owned(),
+        };
+        let retry_after_ms = window
+            .until_ms
+            .map(|until| until.saturating_sub(now_unix_ms()));
+        Some((message, retry_after_ms))
+    }
+
+    #[must_use]
+    pub fn begin_work(&self) -> WorkGuard {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        WorkGuard(Arc::clone(&self.in_flight))
+    }
+
+    #[must_use]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    pub async fn scheduled(&self) -> Option<ScheduledStop> {
+        self.scheduled
+            .lock()
+            .await
+            .as_ref()
+            .map(|(stop, _)| stop.clone())
+    }
+
+    /// The stop requested so far, if any.
+    #[must_use]
+    pub fn requested_stop(&self) -> Option<StopKind> {
+        *self.stop.borrow()
+    }
+
+    /// Stops the server now; the first request wins.
+    pub fn request_stop(&self, kind: StopKind) {
+        self.stop.send_if_modified(|current| {
+            if current.is_some() {
+                return false;
+            }
+            *current = Some(kind);
+            true
+        });
+    }
+
+    /// Resolves once a stop is requested.
+    pub async fn stopped(&self) -> StopKind {
+        let mut receiver = self.stop.subscribe();
+        loop {
+            if let Some(kind) = *receiver.borrow_and_update() {
+                return kind;
+            }
+            if receiver.changed().await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+
+    /// Aborts a pending scheduled stop without announcing it, when the server stops anyway.
+    pub async fn abandon_scheduled(&self) {
+        if let Some((_, task)) = self.scheduled.lock().await.take() {
+            task.abort();
+        }
+    }
+}
+
+/// Schedules a stop, replacing any pending one, and warns clients with a `shutdown` event.
+pub async fn schedule_stop(
+    state: &SharedState,
+    request: StopRequest,
+) -> Result<ScheduledStop, String> {
+    let lifecycle = state.lifecycle();
+    if let Some(kind) = lifecycle.requested_stop() {
+        return Err(format!("a {} is already in progress", kind.label()));
+    }
+    let requested_at_ms = now_unix_ms();
+    let scheduled = ScheduledStop {
+        kind: request.kind,
+        reason: request.reason,
+        requested_at_ms,
+        at_ms: requested_at_ms
+            .saturating_add(u64::try_from(request.delay.as_millis()).unwrap_or(u64::MAX)),
+        drain: request.drain,
+        drain_timeout_ms: u64::try_from(request.drain_timeout.as_millis()).unwrap_or(u64::MAX),
+    };
+
+    // The task waits for `announced` so an immediate stop cannot announce `draining` before
+    // `scheduled`, while the slot lock is released before the log and event awaits.
+    let (announce, announced) = oneshot::channel::<()>();
+    let mut slot = lifecycle.scheduled.lock().await;
+    if let Some((_, previous)) = slot.take() {
+        previous.abort();
+        lifecycle.draining.store(false, Ordering::Relaxed);
+    }
+    let task = tokio::spawn({
+        let state = state.clone();
+        let scheduled = scheduled.clone();
+        async move {
+            let _ = announced.await;
+            run_scheduled_stop(state, scheduled, request.delay, request.drain_timeout).await;
+        }
+    });
+    *slot = Some((scheduled.clone(), task));
+    drop(slot);
+
+    let _ = state
+        .append_gateway_log(
+            "warn",
+            &format!(
+                "system {} scheduled in {}ms{}",
+                scheduled.kind.label(),
+                request.delay.as_millis(),
+                scheduled
+                    .reason
+                    .as_deref()
+                    .map(|reason| format!(": {reason}"))
+                    .unwrap_or_default()
+            ),
+            Some("system"),
+            None,
+        )
+        .await;
+    state
+        .publish_gateway_event(SHUTDOWN_EVENT, stop_payload(&scheduled, "scheduled"))
+        .await;
+    let _ = announce.send(());
+    Ok(scheduled)
+}
+
+/// Cancels the pending scheduled stop, if any, and tells clients it is off.
+pub async fn cancel_stop(state: &SharedState) -> Option<ScheduledStop> {
+    let lifecycle = state.lifecycle();
+    if lifecycle.requested_stop().is_some() {
+        return None;
+    }
+    let (scheduled, task) = lifecycle.scheduled.lock().await.take()?;
+    task.abort();
+    lifecycle.draining.store(false, Ordering::Relaxed);
+    let _ = state
+        .append_gateway_log(
+            "info",
+            &format!("system {} cancelled", scheduled.kind.label()),
+            Some("system"),
+            None,
+        )
+        .await;
+    state
+        .publish_gateway_event(SHUTDOWN_EVENT, stop_payload(&scheduled, "cancelled"))
+        .await;
+    Some(scheduled)
+}
+
+async fn run_scheduled_stop(
+    state: SharedState,
+    scheduled: ScheduledStop,
+    delay: Duration,
+    drain_timeout: Duration,
+) {
+    tokio::time::sleep(delay).await;
+    let lifecycle = state.lifecycle();
+    if scheduled.drain {
+        lifecycle.draining.store(true, Ordering::Relaxed);
+        state
+            .publish_gateway_event(SHUTDOWN_EVENT, stop_payload(&scheduled, "draining"))
+            .await;
+        let deadline = Instant::now() + drain_timeout;
+        while lifecycle.in_flight() > 0 && Instant::now() < deadline {
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+    }
+
+    let mut payload = stop_payload(&scheduled, "stopping");
+    payload["inFlight"] = json!(lifecycle.in_flight());
+    state.publish_gateway_event(SHUTDOWN_EVENT, payload).await;
+    let message = format!("system {} starting now", scheduled.kind.label());
+    info!("{message}");
+    let _ = state
+        .append_gateway_log("warn", &message, Some("system"), None)
+        .await;
+    // Dropping our own handle detaches the task rather than aborting it.
+    lifecycle.scheduled.lock().await.take();
+    lifecycle.request_stop(scheduled.kind);
+}
+
+/// Starts or ends a maintenance window and announces the change with a `maintenance` event.
+pub async fn set_maintenance(state: &SharedState, window: Option<MaintenanceWindow>) {
+    let changed = {
+        let mut current = state.lifecycle().maintenance.write().await;
+        let changed = *current != window;
+        *current = window.clone();
+        changed
+    };
+    if !changed {
+        return;
+    }
+    let message = match &window {
+        Some(window) => format!(
+            "maintenance mode enabled{}",
+            window
+                .reason
+                .as_deref()
+                .map(|reason| format!(": {reason}"))
+                .unwrap_or_default()
+        ),
+        None => "maintenance mode disabled".to_owned(),
+    };
+    let _ = state
+        .append_gateway_log("info", &message, Some("system"), None)
+        .await;
+    state
+        .publish_gateway_event(
+            MAINTENANCE_EVENT,
+            json!({
+                "enabled": window.is_some(),
+                "maintenance": window,
+                "ts": now_unix_ms(),
+            }),
+        )
+        .await;
+}
+
+/// Maintenance, drain, and scheduled-stop state as reported by the `system.*` methods.
+pub async fn status_payload(state: &SharedState) -> Value {
+    let lifecycle = state.lifecycle();
+    let maintenance = lifecycle.maintenance().await;
+    json!({
+        "maintenance": {
+            "enabled": maintenance.is_some(),
+            "window": maintenance,
+        },
+        "draining": lifecycle.is_draining(),
+        "inFlight": lifecycle.in_flight(),
+        "scheduledStop": lifecycle.scheduled().await,
+    })
+}
+
+fn stop_payload(scheduled: &ScheduledStop, phase: &str) -> Value {
+    json!({
+        "kind": scheduled.kind,
+        "phase": phase,
+        "reason": scheduled.reason,
+        "restart": scheduled.kind == StopKind::Restart,
+        "atMs": scheduled.at_ms,
+        "delayMs": scheduled.at_ms.saturating_sub(now_unix_ms()),
+        "drain": scheduled.drain,
+        "drainTimeoutMs": scheduled.drain_timeout_ms,
+        "ts": now_unix_ms(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Lifecycle, MaintenanceWindow, StopKind};
+    use crate::storage::now_unix_ms;
+
+    #[tokio::test]
+    async fn maintenance_windows_expire_and_stops_latch_the_first_request() {
+        let lifecycle = Lifecycle::default();
+        assert!(lifecycle.rejection().await.is_none());
+
+        *lifecycle.maintenance.write().await = Some(MaintenanceWindow {
+            reason: Some("db upgrade".to_owned()),
+            since_ms: now_unix_ms(),
+            until_ms: Some(now_unix_ms() + 60_000),
+        });
+        let (message, retry_after_ms) = lifecycle.rejection().await.expect("should reject");
+        assert!(message.contains("db upgrade"));
+        assert!(retry_after_ms.is_some_and(|ms| ms > 0 && ms <= 60_000));
+
+        lifecycle
+            .maintenance
+            .write()
+            .await
+            .as_mut()
+            .expect("window should exist")
+            .until_ms = Some(now_unix_ms() - 1);
+        assert!(lifecycle.maintenance().await.is_none());
+
+        {
+            let _guard = lifecycle.begin_work();
+            assert_eq!(lifecycle.in_flight(), 1);
+        }
+        assert_eq!(lifecycle.in_flight(), 0);
+
+        lifecycle.request_stop(StopKind::Restart);
+        lifecycle.request_stop(StopKind::Shutdown);
+        let stopped = tokio::time::timeout(Duration::from_secs(1), lifecycle.stopped())
+            .await
+            .expect("stop should resolve");
+        assert_eq!(stopped, StopKind::Restart);
+    }
+}
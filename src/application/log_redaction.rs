@@ -0,0 +1,280 @@
+use std::{
+    io::{self, Write},
+    sync::Arc,
+};
+
+use regex_automata::meta::Regex;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::application::config::{LogRedactionConfig, LogRedactionRuleConfig};
+
+const DEFAULT_REPLACEMENT: &str = "[REDACTED]";
+
+/// Rules applied unless `logRedaction.builtins` is `false`. Rules with a capture group keep the
+/// surrounding text and replace only the group.
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    ("bearer-token", r"(?i)\bbearer\s+([A-Za-z0-9\-._~+/]+=*)"),
+    (
+        "hook-token",
+        r#"(?i)(?:x-openclaw-token|hooks?[_-]?token|\btoken)"?\s*[=:]\s*"?([^\s"'&,;]+)"#,
+    ),
+    (
+        "phone-number",
+        r"\+\d{1,3}[\s.-]?\(?\d{1,4}\)?(?:[\s.-]?\d{2,4}){2,4}",
+    ),
+];
+
+/// Compiled form of the `logRedaction` config.
+#[derive(Debug, Clone)]
+pub struct LogRedaction {
+    rules: Vec<(String, String)>,
+    regex: Option<Regex>,
+    replacement: String,
+}
+
+/// Result of redacting one string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redacted {
+    pub text: String,
+    /// Name of the rule behind each replaced match, in order.
+    pub rules: Vec<String>,
+}
+
+impl LogRedaction {
+    pub fn compile(config: LogRedactionConfig) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        if config.builtins.unwrap_or(true) {
+            rules.extend(
+                BUILTIN_RULES
+                    .iter()
+                    .map(|(name, pattern)| ((*name).to_owned(), (*pattern).to_owned())),
+            );
+        }
+        for (index, LogRedactionRuleConfig { name, pattern }) in
+            config.rules.into_iter().enumerate()
+        {
+            let pattern = pattern.trim().to_owned();
+            if pattern.is_empty() {
+                return Err(format!("logRedaction.rules[{index}].pattern is required"));
+            }
+            let name = name
+                .map(|name| name.trim().to_owned())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| format!("rules[{index}]"));
+            rules.push((name, pattern));
+        }
+        let replacement = config
+            .replacement
+            .unwrap_or_else(|| DEFAULT_REPLACEMENT.to_owned());
+        Self::build(rules, replacement)
+            .map_err(|error| format!("logRedaction has an invalid pattern: {error}"))
+    }
+
+    /// The built-in rules with the default replacement.
+    #[must_use]
+    pub fn builtin() -> Self {
+        Self::compile(LogRedactionConfig::default()).expect("built-in redaction rules compile")
+    }
+
+    /// Returns a copy with `patterns` appended as rules named `patterns[<index>]`.
+    pub fn with_patterns(&self, patterns: &[String]) -> Result<Self, String> {
+        let mut rules = self.rules.clone();
+        for (index, pattern) in patterns.iter().enumerate() {
+            let pattern = pattern.trim();
+            if pattern.is_empty() {
+                return Err(format!("patterns[{index}] must not be empty"));
+            }
+            Regex::new(pattern)
+                .map_err(|error| format!("patterns[{index}] is invalid: {error}"))?;
+            rules.push((format!("patterns[{index}]"), pattern.to_owned()));
+        }
+        Self::build(rules, self.replacement.clone())
+    }
+
+    fn build(rules: Vec<(String, String)>, replacement: String) -> Result<Self, String> {
+        let regex = if rules.is_empty() {
+            None
+        } else {
+            let sources = rules
+                .iter()
+                .map(|(_, pattern)| pattern.as_str())
+                .collect::<Vec<_>>();
+            Some(Regex::new_many(&sources).map_err(|error| error.to_string())?)
+        };
+        Ok(Self {
+            rules,
+            regex,
+            replacement,
+        })
+    }
+
+    #[must_use]
+    pub fn rule_names(&self) -> Vec<&str> {
+        self.rules.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[must_use]
+    pub fn apply(&self, text: &str) -> Redacted {
+        let mut redacted = Redacted {
+            text: String::new(),
+            rules: Vec::new(),
+        };
+        let Some(regex) = &self.regex else {
+            redacted.text = text.to_owned();
+            return redacted;
+        };
+
+        let mut cursor = 0;
+        for captures in regex.captures_iter(text) {
+            let (Some(pattern), Some(span)) = (
+                captures.pattern(),
+                captures
+                    .get_group(1)
+                    .or_else(|| captures.get_match().map(|found| found.span())),
+            ) else {
+                continue;
+            };
+            if span.is_empty() {
+                continue;
+            }
+            redacted.text.push_str(&text[cursor..span.start]);
+            redacted.text.push_str(&self.replacement);
+            cursor = span.end;
+            redacted
+                .rules
+                .push(self.rules[pattern.as_usize()].0.clone());
+        }
+        redacted.text.push_str(&text[cursor..]);
+        redacted
+    }
+
+    #[must_use]
+    pub fn redact(&self, text: &str) -> String {
+        self.apply(text).text
+    }
+}
+
+/// `MakeWriter` for the tracing subscriber that redacts every formatted event before it is
+/// written to stdout.
+#[derive(Debug, Clone)]
+pub struct RedactingMakeWriter {
+    redaction: Arc<LogRedaction>,
+}
+
+impl RedactingMakeWriter {
+    #[must_use]
+    pub fn new(redaction: LogRedaction) -> Self {
+        Self {
+            redaction: Arc::new(redaction),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for RedactingMakeWriter {
+    type Writer = RedactingWriter<io::Stdout>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter::new(Arc::clone(&self.redaction), io::stdout())
+    }
+}
+
+/// Buffers one event and writes its redacted form to `inner` when dropped, so a match split
+/// across several writes is still caught.
+pub struct RedactingWriter<W: Write> {
+    redaction: Arc<LogRedaction>,
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> RedactingWriter<W> {
+    pub fn new(redaction: Arc<LogRedaction>, inner: W) -> Self {
+        Self {
+            redaction,
+            inner,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for RedactingWriter<W> {
+    fn drop(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let text = String::from_utf8_lossy(&self.buffer);
+        let _ = self
+            .inner
+            .write_all(self.redaction.redact(&text).as_bytes());
+        let _ = self.inner.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, sync::Arc};
+
+    use super::{LogRedaction, RedactingWriter};
+    use crate::application::config::{LogRedactionConfig, LogRedactionRuleConfig};
+
+    #[test]
+    fn builtin_and_configured_rules_redact_logs() {
+        let redaction = LogRedaction::builtin();
+        let redacted = redaction.apply(
+            "auth Authorization: Bearer abc.def-123 from +1 415-555-0100 with hooksToken=s3cr3t",
+        );
+        assert_eq!(
+            redacted.text,
+            "auth Authorization: Bearer [REDACTED] from [REDACTED] with hooksToken=[REDACTED]"
+        );
+        assert_eq!(
+            redacted.rules,
+            vec!["bearer-token", "phone-number", "hook-token"]
+        );
+        assert_eq!(
+            redaction.redact(r#"{"token": "abc", "count": 3}"#),
+            r#"{"token": "[REDACTED]", "count": 3}"#
+        );
+        assert_eq!(
+            redaction.redact("order 12345 shipped"),
+            "order 12345 shipped"
+        );
+
+        let custom = LogRedaction::compile(LogRedactionConfig {
+            builtins: Some(false),
+            rules: vec![LogRedactionRuleConfig {
+                name: Some("api-key".to_owned()),
+                pattern: r"sk-[a-z0-9]{6,}".to_owned(),
+            }],
+            replacement: Some("***".to_owned()),
+        })
+        .expect("config should compile");
+        assert_eq!(
+            custom.redact("key sk-abcdef123 bearer xyz"),
+            "key *** bearer xyz"
+        );
+        assert!(custom.with_patterns(&["(".to_owned()]).is_err());
+        let extended = custom
+            .with_patterns(&["xyz".to_owned()])
+            .expect("pattern should compile");
+        assert_eq!(extended.apply("bearer xyz").rules, vec!["patterns[0]"]);
+
+        let mut output = Vec::new();
+        {
+            let mut writer = RedactingWriter::new(Arc::new(redaction), &mut output);
+            writer.write_all(b"call from +44 20 7946 ").unwrap();
+            writer.write_all(b"0958\n").unwrap();
+        }
+        assert_eq!(String::from_utf8(output).unwrap(), "call from [REDACTED]\n");
+    }
+}
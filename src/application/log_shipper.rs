@@ -0,0 +1,301 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::{Value, json};
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{ClientConfig, RootCertStore, pki_types::ServerName},
+};
+use tracing::{info, warn};
+
+use crate::{
+    application::{
+        config::{LogShipTarget, LogShippingConfig},
+        state::SharedState,
+    },
+    domain::{error::DomainError, models::LogShipment},
+    storage::now_unix_ms,
+};
+
+const SHIP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Consecutive failures stretch the retry delay up to this multiple of the ship interval.
+const MAX_BACKOFF_FACTOR: u32 = 32;
+/// IANA private enterprise number used for the syslog structured-data element.
+const SYSLOG_SD_ID: &str = "reclaw@32473";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogShipStatus {
+    pub shipped: u64,
+    pub last_shipped_at_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+}
+
+/// Delivers buffered entries to the configured collector.
+pub struct LogShipper {
+    config: LogShippingConfig,
+    http: reqwest::Client,
+    tls: TlsConnector,
+    hostname: String,
+}
+
+impl LogShipper {
+    pub fn new(config: LogShippingConfig) -> Result<Self, DomainError> {
+        let http = reqwest::Client::builder()
+            .timeout(SHIP_TIMEOUT)
+            .build()
+            .map_err(|error| {
+                DomainError::Unavailable(format!("failed to build log shipping client: {error}"))
+            })?;
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let tls = TlsConnector::from(Arc::new(
+            ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        ));
+        let hostname = std::env::var("HOSTNAME")
+            .ok()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| "-".to_owned());
+        Ok(Self {
+            config,
+            http,
+            tls,
+            hostname,
+        })
+    }
+
+    async fn send(&self, batch: &[LogShipment]) -> Result<(), String> {
+        match &self.config.target {
+            LogShipTarget::Http(url) => self.send_http(url, batch).await,
+            LogShipTarget::Syslog { host, port, tls } => {
+                let frames = batch
+                    .iter()
+                    .map(|shipment| {
+                        syslog_frame(shipment, &self.hostname, self.config.token.as_deref())
+                    })
+                    .collect::<String>();
+                tokio::time::timeout(
+                    SHIP_TIMEOUT,
+                    self.send_syslog(host, *port, *tls, frames.as_bytes()),
+                )
+                .await
+                .map_err(|_| format!("syslog write to {host}:{port} timed out"))?
+            }
+        }
+    }
+
+    async fn send_http(&self, url: &str, batch: &[LogShipment]) -> Result<(), String> {
+        let mut request = self.http.post(url).json(&json!({
+            "source": "reclaw",
+            "host": self.hostname,
+            "entries": batch,
+        }));
+        if let Some(token) = &self.config.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|error| format!("log collector request failed: {error}"))?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("log collector returned {}", response.status()))
+        }
+    }
+
+    async fn send_syslog(
+        &self,
+        host: &str,
+        port: u16,
+        tls: bool,
+        data: &[u8],
+    ) -> Result<(), String> {
+        let stream = TcpStream::connect((host, port))
+            .await
+            .map_err(|error| format!("failed to connect to syslog {host}:{port}: {error}"))?;
+        if !tls {
+            return write_all(stream, data).await;
+        }
+        let server_name = ServerName::try_from(host.to_owned())
+            .map_err(|error| format!("invalid syslog host {host}: {error}"))?;
+        let stream = self
+            .tls
+            .connect(server_name, stream)
+            .await
+            .map_err(|error| format!("syslog TLS handshake with {host}:{port} failed: {error}"))?;
+        write_all(stream, data).await
+    }
+}
+
+async fn write_all<S: tokio::io::AsyncWrite + Unpin>(
+    mut stream: S,
+    data: &[u8],
+) -> Result<(), String> {
+    stream
+        .write_all(data)
+        .await
+        .map_err(|error| format!("syslog write failed: {error}"))?;
+    stream
+        .shutdown()
+        .await
+        .map_err(|error| format!("syslog close failed: {error}"))
+}
+
+/// Ships every buffered entry in batches, acknowledging each batch once the collector accepts it.
+/// Returns how many entries were shipped; on failure the unshipped entries stay buffered.
+pub async fn ship_pending(state: &SharedState, shipper: &LogShipper) -> Result<u64, String> {
+    let mut shipped = 0_u64;
+    loop {
+        let batch = state
+            .list_log_shipments(shipper.config.batch_size)
+            .await
+            .map_err(|error| error.to_string())?;
+        let Some(last) = batch.last() else {
+            return Ok(shipped);
+        };
+        shipper.send(&batch).await?;
+        state
+            .ack_log_shipments(last.seq)
+            .await
+            .map_err(|error| error.to_string())?;
+        shipped += u64::try_from(batch.len()).unwrap_or(0);
+        if batch.len() < shipper.config.batch_size {
+            return Ok(shipped);
+        }
+    }
+}
+
+pub fn spawn_log_shipper(state: SharedState) -> Option<tokio::task::JoinHandle<()>> {
+    let config = state.config().log_shipping.clone()?;
+    let interval = config.interval;
+    let shipper = match LogShipper::new(config) {
+        Ok(shipper) => shipper,
+        Err(error) => {
+            warn!("log shipping disabled: {error}");
+            return None;
+        }
+    };
+    info!("log shipping enabled");
+
+    Some(tokio::spawn(async move {
+        let mut failures = 0_u32;
+        loop {
+            tokio::time::sleep(interval.saturating_mul(backoff_factor(failures))).await;
+            if state.is_standby() {
+                continue;
+            }
+            let result = ship_pending(&state, &shipper).await;
+            if let Err(error) = &result {
+                warn!("log shipping failed; entries stay buffered: {error}");
+            }
+            failures = state
+                .record_log_ship_result(result)
+                .await
+                .consecutive_failures;
+        }
+    }))
+}
+
+fn backoff_factor(failures: u32) -> u32 {
+    2_u32
+        .saturating_pow(failures.min(MAX_BACKOFF_FACTOR.ilog2()))
+        .min(MAX_BACKOFF_FACTOR)
+}
+
+/// One RFC 5424 message with RFC 6587 octet-counting framing (`<len> <msg>`). Audit entries use
+/// facility `log audit` (13), gateway logs `local0` (16); the message body is the entry JSON.
+fn syslog_frame(shipment: &LogShipment, hostname: &str, token: Option<&str>) -> String {
+    let (facility, severity) = if shipment.kind == "audit" {
+        (13, 5)
+    } else {
+        let level = shipment.entry.get("level").and_then(Value::as_str);
+        (16, syslog_severity(level.unwrap_or("info")))
+    };
+    let timestamp = i64::try_from(shipment.ts)
+        .ok()
+        .and_then(DateTime::<Utc>::from_timestamp_millis)
+        .map_or_else(
+            || "-".to_owned(),
+            |ts| ts.to_rfc3339_opts(SecondsFormat::Millis, true),
+        );
+    let structured = token.map_or_else(
+        || "-".to_owned(),
+        |token| format!("[{SYSLOG_SD_ID} token=\"{}\"]", escape_sd_value(token)),
+    );
+    let message = format!(
+        "<{}>1 {timestamp} {hostname} reclaw {} {} {structured} {}",
+        facility * 8 + severity,
+        std::process::id(),
+        shipment.kind,
+        shipment.entry
+    );
+    format!("{} {message}", message.len())
+}
+
+fn syslog_severity(level: &str) -> u8 {
+    match level.to_ascii_lowercase().as_str() {
+        "error" => 3,
+        "warn" | "warning" => 4,
+        "debug" | "trace" => 7,
+        _ => 6,
+    }
+}
+
+fn escape_sd_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
+
+#[must_use]
+pub fn status_payload(status: &LogShipStatus, pending: u64) -> Value {
+    json!({
+        "pending": pending,
+        "shipped": status.shipped,
+        "lastShippedAtMs": status.last_shipped_at_ms,
+        "lastError": status.last_error,
+        "consecutiveFailures": status.consecutive_failures,
+        "checkedAtMs": now_unix_ms(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{backoff_factor, syslog_frame};
+    use crate::domain::models::LogShipment;
+
+    #[test]
+    fn syslog_frames_are_octet_counted_rfc5424_messages() {
+        let shipment = LogShipment {
+            seq: 7,
+            kind: "log".to_owned(),
+            entry: json!({ "level": "warn", "message": "rpc error" }),
+            ts: 1_700_000_000_123,
+        };
+        let frame = syslog_frame(&shipment, "gw-1", Some("se\"cret"));
+        let (length, message) = frame.split_once(' ').expect("frame should be counted");
+        assert_eq!(length.parse::<usize>().ok(), Some(message.len()));
+        assert!(message.starts_with("<132>1 2023-11-14T22:13:20.123Z gw-1 reclaw "));
+        assert!(message.contains(" log [reclaw@32473 token=\"se\\\"cret\"] {"));
+
+        let audit = LogShipment {
+            kind: "audit".to_owned(),
+            ..shipment
+        };
+        assert!(syslog_frame(&audit, "gw-1", None).contains("<109>1 "));
+
+        assert_eq!(backoff_factor(0), 1);
+        assert_eq!(backoff_factor(3), 8);
+        assert_eq!(backoff_factor(40), 32);
+    }
+}
@@ -1,5 +1,36 @@
+pub mod agent_backend;
+pub mod attachment_scan;
+pub mod chat_archive;
+pub mod chat_commands;
+pub mod chat_write_buffer;
 pub mod config;
+pub mod content_policy;
+pub mod cost_budget;
 pub mod cron_schedule;
+pub mod cron_script;
+pub mod db_command;
+pub mod exec_runner;
+pub mod federation;
+pub mod geofence;
 pub mod init_config;
+pub mod inline_exec;
+pub mod lifecycle;
+pub mod log_redaction;
+pub mod log_shipper;
+pub mod node_affinity;
+pub mod node_files;
+pub mod notifier;
+pub mod overload;
+pub mod replication;
+pub mod reply_processing;
+pub mod secrets;
+pub mod seed;
+pub mod self_monitor;
+pub mod server;
+pub mod snapshots;
 pub mod startup;
 pub mod state;
+pub mod systemd;
+pub mod translator;
+pub mod webhook_sources;
+pub mod workspace_quota;
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    application::{
+        federation::{self, Federation},
+        state::SharedState,
+    },
+    protocol::{ERROR_UNAVAILABLE, ErrorShape},
+    storage::now_unix_ms,
+};
+
+const AFFINITY_PREFIX: &str = "runtime/node-affinity/";
+/// Reported round trips above this are treated as measurement errors.
+pub const MAX_RTT_MS: u64 = 60_000;
+
+/// Latency a node measured to the gateways of a federation, stored under
+/// `runtime/node-affinity/<nodeId>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeAffinity {
+    pub node_id: String,
+    /// Round-trip time to each gateway, keyed by gateway id.
+    pub rtt_ms: BTreeMap<String, u64>,
+    /// Fastest reachable gateway when the node last reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<String>,
+    pub reported_at_ms: u64,
+}
+
+/// Gateways to try for a node, fastest first. Peers that are down or no longer paired are
+/// skipped. This gateway is always a candidate, last when the node did not report it.
+#[must_use]
+pub fn route_order(
+    rtt_ms: &BTreeMap<String, u64>,
+    local_id: &str,
+    peers_up: &BTreeMap<String, bool>,
+) -> Vec<String> {
+    let mut candidates = rtt_ms
+        .iter()
+        .filter(|(gateway, _)| {
+            gateway.as_str() == local_id || peers_up.get(*gateway).copied().unwrap_or(false)
+        })
+        .map(|(gateway, rtt)| (*rtt, gateway.clone()))
+        .collect::<Vec<_>>();
+    candidates.sort();
+    let mut order = candidates
+        .into_iter()
+        .map(|(_, gateway)| gateway)
+        .collect::<Vec<_>>();
+    if !order.iter().any(|gateway| gateway == local_id) {
+        order.push(local_id.to_owned());
+    }
+    order
+}
+
+/// Peer id → whether its latest health check did not fail.
+async fn peers_up(state: &SharedState) -> Result<BTreeMap<String, bool>, String> {
+    Ok(federation::list_peers(state)
+        .await?
+        .into_iter()
+        .map(|peer| {
+            let up = peer.health.status != "down";
+            (peer.id, up)
+        })
+        .collect())
+}
+
+pub async fn report(
+    state: &SharedState,
+    federation: &Federation,
+    node_id: &str,
+    rtt_ms: BTreeMap<String, u64>,
+) -> Result<(NodeAffinity, Vec<String>), String> {
+    let peers = peers_up(state).await?;
+    for (gateway, rtt) in &rtt_ms {
+        if gateway != federation.gateway_id() && !peers.contains_key(gateway) {
+            return Err(format!("unknown gateway {gateway}"));
+        }
+        if *rtt > MAX_RTT_MS {
+            return Err(format!("rttMs.{gateway} must be at most {MAX_RTT_MS}"));
+        }
+    }
+    let order = route_order(&rtt_ms, federation.gateway_id(), &peers);
+    let affinity = NodeAffinity {
+        node_id: node_id.to_owned(),
+        rtt_ms,
+        pinned: order.first().cloned(),
+        reported_at_ms: now_unix_ms(),
+    };
+    let value = serde_json::to_value(&affinity).map_err(|error| error.to_string())?;
+    state
+        .set_config_entry_value(&format!("{AFFINITY_PREFIX}{node_id}"), &value)
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok((affinity, order))
+}
+
+/// Every stored affinity (or the one of `node_id`) with its current route order.
+pub async fn list(
+    state: &SharedState,
+    federation: &Federation,
+    node_id: Option<&str>,
+) -> Result<Vec<Value>, String> {
+    let peers = peers_up(state).await?;
+    let entries = state
+        .list_config_entries(AFFINITY_PREFIX, None)
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value::<NodeAffinity>(entry.value).ok())
+        .filter(|affinity| node_id.is_none_or(|id| id == affinity.node_id))
+        .map(|affinity| {
+            let route = route_order(&affinity.rtt_ms, federation.gateway_id(), &peers);
+            let mut value = json!(affinity);
+            value["route"] = json!(route);
+            value
+        })
+        .collect())
+}
+
+/// Sends `node.invoke` for a node pinned to a peer through the fastest reachable gateway,
+/// failing over down the route when a peer is unreachable. Returns `None` when the invoke
+/// should run on this gateway.
+pub async fn route_invoke(
+    state: &SharedState,
+    federation: &Federation,
+    node_id: &str,
+    params: &Value,
+) -> Option<Result<Value, ErrorShape>> {
+    let affinity: NodeAffinity = state
+        .get_config_entry_value(&format!("{AFFINITY_PREFIX}{node_id}"))
+        .await
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_value(value).ok())?;
+    let peers = peers_up(state).await.ok()?;
+    let local_id = federation.gateway_id();
+
+    let mut failed = Vec::new();
+    for gateway in route_order(&affinity.rtt_ms, local_id, &peers) {
+        if gateway == local_id {
+            log_failover(state, node_id, &failed, &gateway).await;
+            return None;
+        }
+        match federation::call_peer(state, federation, &gateway, "node.invoke", params.clone())
+            .await
+        {
+            Ok(mut payload) => {
+                log_failover(state, node_id, &failed, &gateway).await;
+                payload["routedVia"] = json!(gateway);
+                return Some(Ok(payload));
+            }
+            Err(error) if error.code == ERROR_UNAVAILABLE => failed.push(gateway),
+            Err(error) => return Some(Err(error)),
+        }
+    }
+    None
+}
+
+async fn log_failover(state: &SharedState, node_id: &str, failed: &[String], gateway: &str) {
+    if failed.is_empty() {
+        return;
+    }
+    let message = format!(
+        "node.invoke for {node_id} failed over from {} to {gateway}",
+        failed.join(", ")
+    );
+    let _ = state
+        .append_gateway_log("warn", &message, Some("node.invoke"), None)
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::route_order;
+
+    #[test]
+    fn routes_fastest_reachable_gateway_first_and_falls_back_to_local() {
+        let rtt = BTreeMap::from([
+            ("home-a".to_owned(), 80),
+            ("home-b".to_owned(), 20),
+            ("home-c".to_owned(), 40),
+            ("home-d".to_owned(), 10),
+        ]);
+        let mut peers = BTreeMap::from([("home-b".to_owned(), true), ("home-c".to_owned(), true)]);
+        assert_eq!(
+            route_order(&rtt, "home-a", &peers),
+            vec!["home-b", "home-c", "home-a"]
+        );
+
+        peers.insert("home-b".to_owned(), false);
+        assert_eq!(
+            route_order(&rtt, "home-a", &peers),
+            vec!["home-c", "home-a"]
+        );
+        assert_eq!(
+            route_order(
+                &BTreeMap::from([("home-c".to_owned(), 5)]),
+                "home-x",
+                &peers
+            ),
+            vec!["home-c", "home-x"]
+        );
+    }
+}
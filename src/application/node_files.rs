@@ -0,0 +1,401 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tokio::io::AsyncReadExt;
+use tracing::warn;
+
+use crate::{
+    application::{config::NodeFilesConfig, state::SharedState},
+    domain::{
+        error::DomainError,
+        models::{NodeFileTransfer, NodeInvokeInput},
+    },
+    security::signatures::hex_encode,
+    storage::now_unix_ms,
+};
+
+pub const DIRECTION_PUSH: &str = "push";
+pub const DIRECTION_PULL: &str = "pull";
+/// Sent as the checksum of a pushed file and accepted as the node's checksum of a pulled one.
+pub const CHECKSUM_HEADER: &str = "x-checksum-sha256";
+
+const DEFAULT_TRANSFER_TTL_MS: u64 = 60 * 60 * 1_000;
+const MAX_TRANSFER_TTL_MS: u64 = 7 * 24 * 60 * 60 * 1_000;
+const MAX_OPEN_TRANSFERS_PER_NODE: usize = 16;
+const TOKEN_PREFIX: &str = "nft_";
+const READ_BUFFER_BYTES: usize = 64 * 1024;
+
+/// What a node is asked to fetch from or send to the gateway.
+pub enum TransferRequest {
+    /// Sends `source`, relative to the node files directory, to `node_path` on the node.
+    Push { source: String },
+    /// Fetches `node_path` from the node into `dest`, at most `max_bytes` long and optionally
+    /// checked against `sha256`.
+    Pull {
+        dest: Option<String>,
+        sha256: Option<String>,
+        max_bytes: Option<u64>,
+    },
+}
+
+/// The node files settings, or an error naming the option that enables them.
+pub fn config(state: &SharedState) -> Result<&NodeFilesConfig, DomainError> {
+    state.config().node_files.as_ref().ok_or_else(|| {
+        DomainError::Unavailable(
+            "node file transfers are disabled; set nodeFilesDir to enable them".to_owned(),
+        )
+    })
+}
+
+/// Records a transfer and hands the node a `file.push` or `file.pull` invoke carrying its
+/// one-time token, queued until the node connects or polls if it is offline.
+pub async fn start_transfer(
+    state: &SharedState,
+    node_id: &str,
+    node_path: &str,
+    request: TransferRequest,
+    ttl_ms: Option<u64>,
+) -> Result<NodeFileTransfer, DomainError> {
+    let config = config(state)?;
+    match state.get_node(node_id).await? {
+        Some(node) if node.paired => {}
+        _ => {
+            return Err(DomainError::NotPaired(format!(
+                "node is not paired: {node_id}"
+            )));
+        }
+    }
+    let open = list_transfers(state, Some(node_id), usize::MAX)
+        .await?
+        .into_iter()
+        .filter(is_open)
+        .count();
+    if open >= MAX_OPEN_TRANSFERS_PER_NODE {
+        return Err(DomainError::Unavailable(format!(
+            "node {node_id} already has {MAX_OPEN_TRANSFERS_PER_NODE} open file transfers"
+        )));
+    }
+
+    let now = now_unix_ms();
+    let transfer_id = format!("xfer-{}", uuid::Uuid::new_v4());
+    let token = format!(
+        "{TOKEN_PREFIX}{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let mut transfer = NodeFileTransfer {
+        transfer_id: transfer_id.clone(),
+        node_id: node_id.to_owned(),
+        direction: String::new(),
+        gateway_path: String::new(),
+        node_path: node_path.to_owned(),
+        size_bytes: None,
+        max_bytes: config.max_bytes,
+        sha256: None,
+        transferred_bytes: 0,
+        status: "pending".to_owned(),
+        invoke_id: None,
+        error: None,
+        token_hash: hash_token(&token),
+        created_at_ms: now,
+        updated_at_ms: now,
+        expires_at_ms: now.saturating_add(
+            ttl_ms
+                .unwrap_or(DEFAULT_TRANSFER_TTL_MS)
+                .clamp(1_000, MAX_TRANSFER_TTL_MS),
+        ),
+    };
+
+    match request {
+        TransferRequest::Push { source } => {
+            let path = resolve_gateway_path(&config.dir, &source)?;
+            let (size, sha256) = file_digest(&path).await.map_err(|error| {
+                DomainError::InvalidRequest(format!("cannot read source {source}: {error}"))
+            })?;
+            if size > config.max_bytes {
+                return Err(DomainError::InvalidRequest(format!(
+                    "source {source} is {size} bytes; the limit is {}",
+                    config.max_bytes
+                )));
+            }
+            transfer.direction = DIRECTION_PUSH.to_owned();
+            transfer.gateway_path = source;
+            transfer.size_bytes = Some(size);
+            transfer.sha256 = Some(sha256);
+        }
+        TransferRequest::Pull {
+            dest,
+            sha256,
+            max_bytes,
+        } => {
+            let dest = match dest {
+                Some(dest) => dest,
+                None => default_pull_dest(node_id, node_path)?,
+            };
+            resolve_gateway_path(&config.dir, &dest)?;
+            if let Some(sha256) = &sha256
+                && !is_sha256_hex(sha256)
+            {
+                return Err(DomainError::InvalidRequest(
+                    "sha256 must be 64 hex characters".to_owned(),
+                ));
+            }
+            transfer.direction = DIRECTION_PULL.to_owned();
+            transfer.gateway_path = dest;
+            transfer.sha256 = sha256.map(|sha256| sha256.to_ascii_lowercase());
+            transfer.max_bytes = max_bytes.map_or(config.max_bytes, |max_bytes| {
+                max_bytes.clamp(1, config.max_bytes)
+            });
+        }
+    }
+    state.upsert_node_file_transfer(&transfer).await?;
+
+    let queued = state
+        .queue_node_invoke(
+            NodeInvokeInput {
+                node_id: node_id.to_owned(),
+                command: format!("file.{}", transfer.direction),
+                args: Vec::new(),
+                input: Some(invoke_input(&transfer, &token)),
+            },
+            transfer.expires_at_ms,
+        )
+        .await;
+    match queued {
+        Ok(queued) => transfer.invoke_id = Some(queued.invoke.request_id),
+        Err(error) => {
+            finish(state, &mut transfer, "failed", Some(error.to_string())).await?;
+            return Err(error);
+        }
+    }
+    state.upsert_node_file_transfer(&transfer).await?;
+    if let Err(error) = state.deliver_queued_node_invokes_to(node_id).await {
+        warn!("failed to deliver file transfer {transfer_id} to node {node_id}: {error}");
+    }
+    Ok(transfer)
+}
+
+/// Looks up a transfer after expiring overdue ones.
+pub async fn get_transfer(
+    state: &SharedState,
+    transfer_id: &str,
+) -> Result<Option<NodeFileTransfer>, DomainError> {
+    expire_transfers(state).await?;
+    state.get_node_file_transfer(transfer_id).await
+}
+
+/// Lists transfers newest first after expiring overdue ones.
+pub async fn list_transfers(
+    state: &SharedState,
+    node_id: Option<&str>,
+    limit: usize,
+) -> Result<Vec<NodeFileTransfer>, DomainError> {
+    expire_transfers(state).await?;
+    state.list_node_file_transfers(node_id, limit).await
+}
+
+/// Cancels an open transfer and its invoke if the node has not picked it up yet. Returns the
+/// transfer as it stands, closed or not.
+pub async fn cancel_transfer(
+    state: &SharedState,
+    transfer_id: &str,
+) -> Result<(bool, NodeFileTransfer), DomainError> {
+    let mut transfer = get_transfer(state, transfer_id)
+        .await?
+        .ok_or_else(|| DomainError::NotFound(format!("unknown transfer: {transfer_id}")))?;
+    if !is_open(&transfer) {
+        return Ok((false, transfer));
+    }
+    if let Some(invoke_id) = &transfer.invoke_id {
+        state.cancel_queued_node_invoke(invoke_id).await?;
+    }
+    finish(state, &mut transfer, "cancelled", None).await?;
+    Ok((true, transfer))
+}
+
+/// Closes `transfer` with `status`, dropping the partial file of an unfinished pull.
+pub async fn finish(
+    state: &SharedState,
+    transfer: &mut NodeFileTransfer,
+    status: &str,
+    error: Option<String>,
+) -> Result<(), DomainError> {
+    if status != "completed" {
+        remove_partial(state, transfer).await;
+    }
+    transfer.status = status.to_owned();
+    transfer.error = error;
+    transfer.updated_at_ms = now_unix_ms();
+    state.upsert_node_file_transfer(transfer).await
+}
+
+#[must_use]
+pub fn is_open(transfer: &NodeFileTransfer) -> bool {
+    transfer.status == "pending" || transfer.status == "active"
+}
+
+/// Checks a presented token against the transfer's stored hash.
+#[must_use]
+pub fn verify_token(transfer: &NodeFileTransfer, token: &str) -> bool {
+    hash_token(token)
+        .as_bytes()
+        .ct_eq(transfer.token_hash.as_bytes())
+        .into()
+}
+
+/// Joins a relative path onto the node files directory, refusing absolute paths and `..`.
+pub fn resolve_gateway_path(dir: &Path, relative: &str) -> Result<PathBuf, DomainError> {
+    let relative = Path::new(relative.trim());
+    let mut components = relative.components().peekable();
+    if components.peek().is_none()
+        || !components.all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(DomainError::InvalidRequest(format!(
+            "path must be relative to the node files directory: {}",
+            relative.display()
+        )));
+    }
+    Ok(dir.join(relative))
+}
+
+/// The transfer's file under the node files directory.
+pub fn gateway_path(
+    state: &SharedState,
+    transfer: &NodeFileTransfer,
+) -> Result<PathBuf, DomainError> {
+    resolve_gateway_path(&config(state)?.dir, &transfer.gateway_path)
+}
+
+/// Where a pull writes until its checksum is verified: beside the destination, per transfer.
+#[must_use]
+pub fn partial_path(dest: &Path, transfer_id: &str) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{transfer_id}.part"));
+    dest.with_file_name(name)
+}
+
+/// Size and lowercase hex SHA-256 of a file, read in chunks.
+pub async fn file_digest(path: &Path) -> std::io::Result<(u64, String)> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0_u8; READ_BUFFER_BYTES];
+    let mut size = 0_u64;
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((size, hex_encode(&hasher.finalize())))
+}
+
+#[must_use]
+pub fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64 && value.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+async fn expire_transfers(state: &SharedState) -> Result<(), DomainError> {
+    for transfer in state.expire_node_file_transfers().await? {
+        remove_partial(state, &transfer).await;
+    }
+    Ok(())
+}
+
+async fn remove_partial(state: &SharedState, transfer: &NodeFileTransfer) {
+    if transfer.direction != DIRECTION_PULL {
+        return;
+    }
+    let Ok(dest) = gateway_path(state, transfer) else {
+        return;
+    };
+    let _ = tokio::fs::remove_file(partial_path(&dest, &transfer.transfer_id)).await;
+}
+
+fn default_pull_dest(node_id: &str, node_path: &str) -> Result<String, DomainError> {
+    let file_name = node_path
+        .rsplit(['/', '\\'])
+        .find(|segment| !segment.is_empty() && *segment != "." && *segment != "..")
+        .ok_or_else(|| {
+            DomainError::InvalidRequest(format!(
+                "dest is required when path has no file name: {node_path}"
+            ))
+        })?;
+    Ok(format!("{node_id}/{file_name}"))
+}
+
+fn invoke_input(transfer: &NodeFileTransfer, token: &str) -> Value {
+    let mut input = json!({
+        "transferId": transfer.transfer_id,
+        "direction": transfer.direction,
+        "path": transfer.node_path,
+        "url": format!("/nodes/{}/files/{}", transfer.node_id, transfer.transfer_id),
+        "token": token,
+        "maxBytes": transfer.max_bytes,
+        "sha256": transfer.sha256,
+        "expiresAtMs": transfer.expires_at_ms,
+    });
+    if let Some(size) = transfer.size_bytes {
+        input["sizeBytes"] = json!(size);
+    }
+    input
+}
+
+fn hash_token(token: &str) -> String {
+    hex_encode(&Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{default_pull_dest, is_sha256_hex, partial_path, resolve_gateway_path};
+
+    #[test]
+    fn gateway_paths_stay_inside_the_node_files_directory() {
+        let dir = Path::new("/srv/node-files");
+        assert_eq!(
+            resolve_gateway_path(dir, "models/tiny.bin").expect("relative path resolves"),
+            Path::new("/srv/node-files/models/tiny.bin")
+        );
+        for rejected in [
+            "",
+            "/etc/passwd",
+            "../secrets",
+            "models/../../x",
+            "./config",
+        ] {
+            assert!(
+                resolve_gateway_path(dir, rejected).is_err(),
+                "{rejected:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn pulls_default_to_the_node_path_file_name_under_the_node_id() {
+        assert_eq!(
+            default_pull_dest("node-1", "/var/log/app.log").expect("file name found"),
+            "node-1/app.log"
+        );
+        assert_eq!(
+            default_pull_dest("node-1", "C:\\logs\\boot.txt").expect("file name found"),
+            "node-1/boot.txt"
+        );
+        assert!(default_pull_dest("node-1", "/").is_err());
+        assert_eq!(
+            partial_path(Path::new("/srv/node-1/app.log"), "xfer-1"),
+            Path::new("/srv/node-1/app.log.xfer-1.part")
+        );
+    }
+
+    #[test]
+    fn checksums_are_64_hex_characters() {
+        assert!(is_sha256_hex(&"ab".repeat(32)));
+        assert!(!is_sha256_hex("abc"));
+        assert!(!is_sha256_hex(&"zz".repeat(32)));
+    }
+}
@@ -0,0 +1,635 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{ClientConfig, RootCertStore, pki_types::ServerName},
+};
+use tracing::{info, warn};
+
+use crate::application::{
+    config::{EscalationConfig, NotifierConfig},
+    state::SharedState,
+};
+
+/// Gateway events that can escalate, grouped into the classes escalation rules are keyed by.
+const EVENT_CLASSES: &[(&str, &[&str])] = &[
+    ("approvals", &["exec.approval.requested"]),
+    ("pairing", &["node.pair.requested", "device.pair.requested"]),
+];
+const DEFAULT_AFTER_MINUTES: u64 = 10;
+const SEND_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Compiled form of the `escalation` config.
+#[derive(Debug, Clone)]
+pub struct EscalationPolicy {
+    notifiers: BTreeMap<String, Notifier>,
+    /// Enabled classes only.
+    rules: BTreeMap<&'static str, EscalationRule>,
+}
+
+#[derive(Debug, Clone)]
+struct EscalationRule {
+    after: Duration,
+    notifiers: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Notifier {
+    Webhook {
+        url: String,
+        token: Option<String>,
+    },
+    Ntfy {
+        url: String,
+        token: Option<String>,
+        priority: Option<u8>,
+    },
+    Email {
+        server: SmtpServer,
+        from: String,
+        to: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SmtpServer {
+    host: String,
+    port: u16,
+    /// Implicit TLS (`smtps://`); plain `smtp://` is meant for local relays.
+    tls: bool,
+    credentials: Option<(String, String)>,
+}
+
+/// What an escalated notification says, independent of the notifier that carries it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub event: String,
+    pub class: &'static str,
+    pub title: String,
+    pub message: String,
+    /// Signed approval link from the event payload, when one was issued.
+    pub link: Option<String>,
+    pub payload: Value,
+}
+
+impl EscalationPolicy {
+    pub fn compile(config: EscalationConfig) -> Result<Self, String> {
+        if config.notifiers.is_empty() {
+            return Err("escalation needs at least one notifier".to_owned());
+        }
+        let mut notifiers = BTreeMap::new();
+        for (name, notifier) in config.notifiers {
+            let name = name.trim().to_owned();
+            if name.is_empty() {
+                return Err("escalation.notifiers keys must not be empty".to_owned());
+            }
+            let compiled = Notifier::compile(notifier)
+                .map_err(|error| format!("escalation.notifiers.{name}: {error}"))?;
+            notifiers.insert(name, compiled);
+        }
+
+        if let Some(unknown) = config
+            .events
+            .keys()
+            .find(|class| !EVENT_CLASSES.iter().any(|(known, _)| known == class))
+        {
+            return Err(format!(
+                "escalation.events key must be approvals or pairing: {unknown}"
+            ));
+        }
+        let mut rules = BTreeMap::new();
+        for (class, _) in EVENT_CLASSES {
+            let rule = config.events.get(*class).cloned().unwrap_or_default();
+            if !rule.enabled.unwrap_or(true) {
+                continue;
+            }
+            let names = rule
+                .notifiers
+                .unwrap_or_else(|| notifiers.keys().cloned().collect());
+            if let Some(unknown) = names.iter().find(|name| !notifiers.contains_key(*name)) {
+                return Err(format!(
+                    "escalation.events.{class} references unknown notifier {unknown}"
+                ));
+            }
+            let minutes = rule
+                .after_minutes
+                .or(config.after_minutes)
+                .unwrap_or(DEFAULT_AFTER_MINUTES);
+            rules.insert(
+                *class,
+                EscalationRule {
+                    after: Duration::from_secs(minutes.saturating_mul(60)),
+                    notifiers: names,
+                },
+            );
+        }
+        Ok(Self { notifiers, rules })
+    }
+
+    fn rule_for(&self, event: &str) -> Option<(&'static str, &EscalationRule)> {
+        let (class, _) = EVENT_CLASSES
+            .iter()
+            .find(|(_, events)| events.contains(&event))?;
+        self.rules.get(class).map(|rule| (*class, rule))
+    }
+}
+
+impl Notifier {
+    fn compile(config: NotifierConfig) -> Result<Self, String> {
+        let http_url = |url: String| {
+            let url = url.trim().to_owned();
+            if url.starts_with("http://") || url.starts_with("https://") {
+                Ok(url)
+            } else {
+                Err("url must use http:// or https://".to_owned())
+            }
+        };
+        match config {
+            NotifierConfig::Webhook { url, token } => Ok(Self::Webhook {
+                url: http_url(url)?,
+                token,
+            }),
+            NotifierConfig::Ntfy {
+                url,
+                token,
+                priority,
+            } => {
+                if priority.is_some_and(|priority| !(1..=5).contains(&priority)) {
+                    return Err("priority must be between 1 and 5".to_owned());
+                }
+                Ok(Self::Ntfy {
+                    url: http_url(url)?,
+                    token,
+                    priority,
+                })
+            }
+            NotifierConfig::Email { smtp, from, to } => {
+                let from = from.trim().to_owned();
+                let to = to
+                    .iter()
+                    .map(|address| address.trim().to_owned())
+                    .filter(|address| !address.is_empty())
+                    .collect::<Vec<_>>();
+                if from.is_empty() || to.is_empty() {
+                    return Err("email notifiers need from and at least one to address".to_owned());
+                }
+                Ok(Self::Email {
+                    server: parse_smtp_url(&smtp)?,
+                    from,
+                    to,
+                })
+            }
+        }
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        match self {
+            Self::Webhook { url, token } => {
+                let mut request = http_client()?.post(url).json(notification);
+                if let Some(token) = token {
+                    request = request.bearer_auth(token);
+                }
+                check_response(request.send().await)
+            }
+            Self::Ntfy {
+                url,
+                token,
+                priority,
+            } => {
+                let mut request = http_client()?
+                    .post(url)
+                    .header("Title", &notification.title)
+                    .header("Tags", notification.class)
+                    .body(notification.message.clone());
+                if let Some(priority) = priority {
+                    request = request.header("Priority", priority.to_string());
+                }
+                if let Some(link) = &notification.link {
+                    request = request.header("Click", link);
+                }
+                if let Some(token) = token {
+                    request = request.bearer_auth(token);
+                }
+                check_response(request.send().await)
+            }
+            Self::Email { server, from, to } => send_mail(server, from, to, notification).await,
+        }
+    }
+}
+
+/// Escalates a gateway event to the notifiers of its class once no operator has been connected
+/// for the class threshold. While an operator is connected the gateway event is all that is
+/// sent; when the last one left recently, the check repeats when the threshold is reached.
+pub async fn escalate(state: &SharedState, event: &str, payload: &Value) {
+    let Some(policy) = &state.config().escalation else {
+        return;
+    };
+    let Some((class, rule)) = policy.rule_for(event) else {
+        return;
+    };
+    let Some(absent) = state.operator_absence().await else {
+        return;
+    };
+
+    let notification = Notification::from_event(event, class, payload);
+    let after = rule.after;
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Some(wait) = after.checked_sub(absent).filter(|wait| !wait.is_zero()) {
+            tokio::time::sleep(wait).await;
+            if state
+                .operator_absence()
+                .await
+                .is_none_or(|absent| absent < after)
+            {
+                return;
+            }
+        }
+        notify(&state, &notification).await;
+    });
+}
+
+async fn notify(state: &SharedState, notification: &Notification) {
+    let Some(policy) = &state.config().escalation else {
+        return;
+    };
+    let Some((_, rule)) = policy.rule_for(&notification.event) else {
+        return;
+    };
+    for name in &rule.notifiers {
+        let Some(notifier) = policy.notifiers.get(name) else {
+            continue;
+        };
+        let result = tokio::time::timeout(SEND_TIMEOUT, notifier.send(notification))
+            .await
+            .unwrap_or_else(|_| Err("timed out".to_owned()));
+        let (level, message) = match result {
+            Ok(()) => {
+                info!("escalated {} via {name}", notification.event);
+                (
+                    "info",
+                    format!("escalated {} via {name}", notification.event),
+                )
+            }
+            Err(error) => {
+                warn!(
+                    "escalating {} via {name} failed: {error}",
+                    notification.event
+                );
+                (
+                    "warn",
+                    format!(
+                        "escalating {} via {name} failed: {error}",
+                        notification.event
+                    ),
+                )
+            }
+        };
+        let _ = state
+            .append_gateway_log(level, &message, Some("notifications.escalation"), None)
+            .await;
+    }
+}
+
+impl Notification {
+    fn from_event(event: &str, class: &'static str, payload: &Value) -> Self {
+        let text = |pointer: &str| payload.pointer(pointer).and_then(Value::as_str);
+        let (title, message) = match event {
+            "exec.approval.requested" => (
+                "Exec approval requested".to_owned(),
+                format!(
+                    "Agent {} wants to run: {}",
+                    text("/request/agentId").unwrap_or("main"),
+                    text("/request/command").unwrap_or_default()
+                ),
+            ),
+            _ => (
+                "Pairing requested".to_owned(),
+                format!(
+                    "{} ({}) asked to pair",
+                    text("/request/displayName")
+                        .or_else(|| text("/request/nodeId"))
+                        .or_else(|| text("/request/deviceId"))
+                        .unwrap_or("unknown"),
+                    text("/request/platform").unwrap_or("unknown")
+                ),
+            ),
+        };
+        Self {
+            event: event.to_owned(),
+            class,
+            title,
+            message,
+            link: text("/link/url").map(str::to_owned),
+            payload: payload.clone(),
+        }
+    }
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(SEND_TIMEOUT)
+        .build()
+        .map_err(|error| format!("failed to construct http client: {error}"))
+}
+
+fn check_response(response: Result<reqwest::Response, reqwest::Error>) -> Result<(), String> {
+    let response = response.map_err(|error| format!("request failed: {error}"))?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("notifier returned {}", response.status()))
+    }
+}
+
+/// Parses `smtp://` or `smtps://[user:password@]host[:port]`; ports default to 25 and 465.
+fn parse_smtp_url(raw: &str) -> Result<SmtpServer, String> {
+    let raw = raw.trim();
+    let (tls, rest) = if let Some(rest) = raw.strip_prefix("smtps://") {
+        (true, rest)
+    } else if let Some(rest) = raw.strip_prefix("smtp://") {
+        (false, rest)
+    } else {
+        return Err("smtp must start with smtp:// or smtps://".to_owned());
+    };
+    let rest = rest.trim_end_matches('/');
+    let (credentials, address) = match rest.rsplit_once('@') {
+        Some((userinfo, address)) => {
+            let (user, password) = userinfo
+                .split_once(':')
+                .ok_or_else(|| "smtp credentials must be user:password".to_owned())?;
+            (Some((user.to_owned(), password.to_owned())), address)
+        }
+        None => (None, rest),
+    };
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse::<u16>()
+                .map_err(|_| format!("invalid smtp port {port}"))?,
+        ),
+        None => (address, if tls { 465 } else { 25 }),
+    };
+    if host.is_empty() {
+        return Err("smtp host is required".to_owned());
+    }
+    Ok(SmtpServer {
+        host: host.to_owned(),
+        port,
+        tls,
+        credentials,
+    })
+}
+
+async fn send_mail(
+    server: &SmtpServer,
+    from: &str,
+    to: &[String],
+    notification: &Notification,
+) -> Result<(), String> {
+    let stream = TcpStream::connect((server.host.as_str(), server.port))
+        .await
+        .map_err(|error| {
+            format!(
+                "failed to connect to {}:{}: {error}",
+                server.host, server.port
+            )
+        })?;
+    let message = mail_message(from, to, notification);
+    if !server.tls {
+        return smtp_exchange(BufStream::new(stream), server, from, to, &message).await;
+    }
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let connector = TlsConnector::from(Arc::new(
+        ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    ));
+    let server_name = ServerName::try_from(server.host.clone())
+        .map_err(|error| format!("invalid smtp host {}: {error}", server.host))?;
+    let stream = connector
+        .connect(server_name, stream)
+        .await
+        .map_err(|error| format!("TLS handshake with {} failed: {error}", server.host))?;
+    smtp_exchange(BufStream::new(stream), server, from, to, &message).await
+}
+
+async fn smtp_exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: BufStream<S>,
+    server: &SmtpServer,
+    from: &str,
+    to: &[String],
+    message: &str,
+) -> Result<(), String> {
+    expect_reply(&mut stream, 220).await?;
+    smtp_command(&mut stream, "EHLO reclaw", 250).await?;
+    if let Some((user, password)) = &server.credentials {
+        let token = STANDARD.encode(format!("\0{user}\0{password}"));
+        smtp_command(&mut stream, &format!("AUTH PLAIN {token}"), 235).await?;
+    }
+    smtp_command(&mut stream, &format!("MAIL FROM:<{from}>"), 250).await?;
+    for recipient in to {
+        smtp_command(&mut stream, &format!("RCPT TO:<{recipient}>"), 250).await?;
+    }
+    smtp_command(&mut stream, "DATA", 354).await?;
+    smtp_command(&mut stream, &format!("{message}\r\n."), 250).await?;
+    let _ = smtp_command(&mut stream, "QUIT", 221).await;
+    Ok(())
+}
+
+async fn smtp_command<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufStream<S>,
+    line: &str,
+    expected: u16,
+) -> Result<(), String> {
+    stream
+        .write_all(format!("{line}\r\n").as_bytes())
+        .await
+        .map_err(|error| format!("smtp write failed: {error}"))?;
+    stream
+        .flush()
+        .await
+        .map_err(|error| format!("smtp write failed: {error}"))?;
+    expect_reply(stream, expected).await
+}
+
+/// Reads one reply, skipping the `250-` continuation lines of multiline replies.
+async fn expect_reply<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufStream<S>,
+    expected: u16,
+) -> Result<(), String> {
+    loop {
+        let mut line = String::new();
+        let read = stream
+            .read_line(&mut line)
+            .await
+            .map_err(|error| format!("smtp read failed: {error}"))?;
+        if read == 0 {
+            return Err("smtp server closed the connection".to_owned());
+        }
+        let code = line
+            .get(..3)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| format!("malformed smtp reply: {}", line.trim_end()))?;
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        return if code == expected {
+            Ok(())
+        } else {
+            Err(format!("smtp server replied {}", line.trim_end()))
+        };
+    }
+}
+
+/// Builds the DATA section: headers, then the body with CRLF line endings and dot-stuffing.
+fn mail_message(from: &str, to: &[String], notification: &Notification) -> String {
+    let subject = notification.title.replace(['\r', '\n'], " ");
+    let mut body = notification.message.clone();
+    if let Some(link) = &notification.link {
+        body.push_str(&format!("\n\nReview: {link}"));
+    }
+    let body = body
+        .lines()
+        .map(|line| {
+            if line.starts_with('.') {
+                format!(".{line}")
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    format!(
+        "From: {from}\r\nTo: {}\r\nSubject: [reclaw] {subject}\r\nDate: {}\r\nMessage-ID: <{}@reclaw>\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{body}",
+        to.join(", "),
+        chrono::Utc::now().to_rfc2822(),
+        uuid::Uuid::new_v4().simple(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, time::Duration};
+
+    use serde_json::json;
+
+    use super::{EscalationPolicy, Notification, mail_message, parse_smtp_url};
+    use crate::application::config::{EscalationConfig, EscalationRuleConfig, NotifierConfig};
+
+    #[test]
+    fn policy_resolves_event_classes_and_thresholds() {
+        let policy = EscalationPolicy::compile(EscalationConfig {
+            after_minutes: Some(5),
+            notifiers: BTreeMap::from([
+                (
+                    "phone".to_owned(),
+                    NotifierConfig::Ntfy {
+                        url: "https://ntfy.example/ops".to_owned(),
+                        token: None,
+                        priority: Some(4),
+                    },
+                ),
+                (
+                    "mail".to_owned(),
+                    NotifierConfig::Email {
+                        smtp: "smtps://bot@example.com:secret@smtp.example.com".to_owned(),
+                        from: "bot@example.com".to_owned(),
+                        to: vec!["ops@example.com".to_owned()],
+                    },
+                ),
+            ]),
+            events: BTreeMap::from([
+                (
+                    "approvals".to_owned(),
+                    EscalationRuleConfig {
+                        after_minutes: Some(1),
+                        notifiers: Some(vec!["phone".to_owned()]),
+                        ..EscalationRuleConfig::default()
+                    },
+                ),
+                (
+                    "pairing".to_owned(),
+                    EscalationRuleConfig {
+                        enabled: Some(false),
+                        ..EscalationRuleConfig::default()
+                    },
+                ),
+            ]),
+        })
+        .expect("policy should compile");
+
+        let (class, rule) = policy
+            .rule_for("exec.approval.requested")
+            .expect("approvals should escalate");
+        assert_eq!(class, "approvals");
+        assert_eq!(rule.after, Duration::from_secs(60));
+        assert_eq!(rule.notifiers, vec!["phone"]);
+        assert!(policy.rule_for("node.pair.requested").is_none());
+        assert!(policy.rule_for("chat").is_none());
+
+        let unknown = EscalationPolicy::compile(EscalationConfig {
+            notifiers: BTreeMap::from([(
+                "hook".to_owned(),
+                NotifierConfig::Webhook {
+                    url: "https://push.example/notify".to_owned(),
+                    token: None,
+                },
+            )]),
+            events: BTreeMap::from([(
+                "approvals".to_owned(),
+                EscalationRuleConfig {
+                    notifiers: Some(vec!["pager".to_owned()]),
+                    ..EscalationRuleConfig::default()
+                },
+            )]),
+            ..EscalationConfig::default()
+        });
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn smtp_urls_and_messages_are_well_formed() {
+        let server = parse_smtp_url("smtps://bot@example.com:p@ss@smtp.example.com")
+            .expect("smtps url should parse");
+        assert_eq!(server.host, "smtp.example.com");
+        assert_eq!(server.port, 465);
+        assert_eq!(
+            server.credentials,
+            Some(("bot@example.com".to_owned(), "p@ss".to_owned()))
+        );
+        let relay = parse_smtp_url("smtp://127.0.0.1:2525").expect("smtp url should parse");
+        assert_eq!(
+            (relay.port, relay.tls, relay.credentials),
+            (2525, false, None)
+        );
+        assert!(parse_smtp_url("mail.example.com").is_err());
+
+        let notification = Notification::from_event(
+            "exec.approval.requested",
+            "approvals",
+            &json!({
+                "request": { "command": "rm -rf build", "agentId": "ops" },
+                "link": { "url": "https://gw.example/approve?t=1" },
+            }),
+        );
+        assert_eq!(notification.message, "Agent ops wants to run: rm -rf build");
+        let message = mail_message(
+            "bot@example.com",
+            &["ops@example.com".to_owned()],
+            &notification,
+        );
+        assert!(message.contains("Subject: [reclaw] Exec approval requested\r\n"));
+        assert!(message.ends_with("rm -rf build\r\n\r\nReview: https://gw.example/approve?t=1"));
+    }
+}
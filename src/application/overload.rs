@@ -0,0 +1,207 @@
+use serde::Serialize;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::{
+    application::{config::OverloadLimits, self_monitor::GuardrailBreach, state::SharedState},
+    storage::now_unix_ms,
+};
+
+const OVERLOAD_EVENT: &str = "overload";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverloadSample {
+    pub event_queue_depth: u64,
+    /// `None` when no request finished during the interval.
+    pub dispatch_latency_ms: Option<u64>,
+    /// `None` when the storage probe failed.
+    pub db_latency_ms: Option<u64>,
+    pub sampled_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverloadStatus {
+    pub shedding: bool,
+    pub since_ms: Option<u64>,
+    pub last_breach_at_ms: Option<u64>,
+    pub sample: OverloadSample,
+    pub breaches: Vec<GuardrailBreach>,
+}
+
+#[must_use]
+pub fn evaluate_overload(limits: &OverloadLimits, sample: &OverloadSample) -> Vec<GuardrailBreach> {
+    [
+        (
+            "eventQueueDepth",
+            Some(sample.event_queue_depth),
+            limits.max_event_queue_depth,
+        ),
+        (
+            "dispatchLatencyMs",
+            sample.dispatch_latency_ms,
+            limits.max_dispatch_latency_ms,
+        ),
+        (
+            "dbLatencyMs",
+            sample.db_latency_ms,
+            limits.max_db_latency_ms,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(metric, value, limit)| match (value, limit) {
+        (Some(value), Some(limit)) if value > limit => Some(GuardrailBreach {
+            metric,
+            value,
+            limit,
+        }),
+        _ => None,
+    })
+    .collect()
+}
+
+/// Shedding starts on the first breach and stops once no breach was seen for `cooldown_ms`, so
+/// a server hovering around a threshold does not flap.
+#[must_use]
+pub fn next_status(
+    previous: &OverloadStatus,
+    sample: OverloadSample,
+    breaches: Vec<GuardrailBreach>,
+    cooldown_ms: u64,
+) -> OverloadStatus {
+    let now = sample.sampled_at_ms;
+    let last_breach_at_ms = if breaches.is_empty() {
+        previous.last_breach_at_ms
+    } else {
+        Some(now)
+    };
+    let shedding = !breaches.is_empty()
+        || (previous.shedding
+            && last_breach_at_ms.is_some_and(|at| now.saturating_sub(at) < cooldown_ms));
+    let since_ms = match (shedding, previous.shedding) {
+        (true, true) => previous.since_ms,
+        (true, false) => Some(now),
+        (false, _) => None,
+    };
+    OverloadStatus {
+        shedding,
+        since_ms,
+        last_breach_at_ms,
+        sample,
+        breaches,
+    }
+}
+
+/// Samples the overload signals and records the result, notifying operators when shedding
+/// starts or stops.
+pub async fn check_overload(state: &SharedState) -> OverloadStatus {
+    let limits = &state.config().overload;
+    let sample = OverloadSample {
+        event_queue_depth: state.event_queue_depth().await,
+        dispatch_latency_ms: state.take_dispatch_latency().map(duration_ms),
+        db_latency_ms: state.probe_storage_latency().await.ok().map(duration_ms),
+        sampled_at_ms: now_unix_ms(),
+    };
+    let breaches = evaluate_overload(limits, &sample);
+    let previous = state.overload_status().await;
+    let cooldown_ms = u64::try_from(limits.cooldown.as_millis()).unwrap_or(u64::MAX);
+    let status = next_status(&previous, sample, breaches, cooldown_ms);
+    state.record_overload_status(status.clone()).await;
+
+    if status.shedding && !previous.shedding {
+        let message = format!("server overloaded, shedding load: {:?}", status.breaches);
+        warn!("{message}");
+        let _ = state
+            .append_gateway_log("warn", &message, Some("overload"), None)
+            .await;
+        state
+            .publish_gateway_event(
+                OVERLOAD_EVENT,
+                json!({
+                    "state": "shedding",
+                    "breaches": status.breaches,
+                    "sample": status.sample,
+                }),
+            )
+            .await;
+    } else if !status.shedding && previous.shedding {
+        info!("server load back within limits, shedding stopped");
+        state
+            .publish_gateway_event(
+                OVERLOAD_EVENT,
+                json!({
+                    "state": "recovered",
+                    "sample": status.sample,
+                    "shedForMs": status.sample.sampled_at_ms
+                        .saturating_sub(previous.since_ms.unwrap_or(status.sample.sampled_at_ms)),
+                }),
+            )
+            .await;
+    }
+
+    status
+}
+
+/// Rounds up so any measurable latency exceeds a zero threshold.
+fn duration_ms(duration: std::time::Duration) -> u64 {
+    u64::try_from(duration.as_micros().div_ceil(1_000)).unwrap_or(u64::MAX)
+}
+
+pub fn spawn_overload_detector(state: SharedState) -> Option<tokio::task::JoinHandle<()>> {
+    if !state.config().overload.is_enabled() {
+        return None;
+    }
+    info!("overload detector enabled");
+
+    let interval = state.config().overload.check_interval;
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let _ = check_overload(&state).await;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OverloadSample, OverloadStatus, evaluate_overload, next_status};
+    use crate::application::config::OverloadLimits;
+
+    #[test]
+    fn shedding_starts_on_breach_and_stops_after_cooldown() {
+        let limits = OverloadLimits {
+            max_dispatch_latency_ms: Some(200),
+            max_db_latency_ms: Some(50),
+            ..OverloadLimits::default()
+        };
+        let sample = |at: u64, dispatch: Option<u64>| OverloadSample {
+            event_queue_depth: 250,
+            dispatch_latency_ms: dispatch,
+            db_latency_ms: Some(5),
+            sampled_at_ms: at,
+        };
+
+        let breaches = evaluate_overload(&limits, &sample(1_000, Some(900)));
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].metric, "dispatchLatencyMs");
+        let shedding = next_status(
+            &OverloadStatus::default(),
+            sample(1_000, Some(900)),
+            breaches,
+            5_000,
+        );
+        assert!(shedding.shedding);
+        assert_eq!(shedding.since_ms, Some(1_000));
+
+        let quiet = sample(4_000, None);
+        assert!(evaluate_overload(&limits, &quiet).is_empty());
+        let cooling = next_status(&shedding, quiet, Vec::new(), 5_000);
+        assert!(cooling.shedding);
+        assert_eq!(cooling.since_ms, Some(1_000));
+        let recovered = next_status(&cooling, sample(6_000, Some(10)), Vec::new(), 5_000);
+        assert!(!recovered.shedding);
+        assert_eq!(recovered.since_ms, None);
+    }
+}
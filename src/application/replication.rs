@@ -0,0 +1,837 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use subtle::ConstantTimeEq;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+use crate::{
+    application::{config::ReplicationConfig, state::SharedState},
+    domain::{
+        error::DomainError,
+        models::{ReplicatedChange, ReplicationState},
+    },
+    storage::now_unix_ms,
+};
+
+const DEFAULT_HEARTBEAT_INTERVAL_MS: u64 = 2_000;
+const DEFAULT_FAILOVER_AFTER_MISSES: u32 = 3;
+const DEFAULT_SYNC_INTERVAL_MS: u64 = 10_000;
+/// A standby that has not checked in for this many heartbeat intervals is no longer advertised
+/// to clients.
+const STANDBY_STALE_INTERVALS: u32 = 5;
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(300);
+/// Most changes shipped per `/replication/changes` page.
+pub const CHANGES_PAGE_LIMIT: usize = 500;
+/// Captured changes are kept at most this long, so a primary without a standby does not grow
+/// its change log forever; a standby further behind reloads a snapshot.
+const CHANGE_RETENTION_MS: u64 = 24 * 60 * 60 * 1_000;
+pub const PROMOTED_EVENT: &str = "replication.promoted";
+
+/// Compiled form of the `replication` config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replication {
+    token: String,
+    primary_url: Option<String>,
+    advertise_url: Option<String>,
+    heartbeat_interval: Duration,
+    failover_after_misses: u32,
+    sync_interval: Duration,
+    auto_promote: bool,
+}
+
+impl Replication {
+    pub fn compile(config: ReplicationConfig) -> Result<Self, String> {
+        let token = config.token.trim().to_owned();
+        if token.is_empty() {
+            return Err("replication.token is required".to_owned());
+        }
+        let url = |raw: Option<String>, field: &str| {
+            raw.map(|raw| {
+                let url = raw.trim().trim_end_matches('/');
+                (url.starts_with("http://") || url.starts_with("https://"))
+                    .then(|| url.to_owned())
+                    .ok_or(format!("replication.{field} must be an http(s) URL"))
+            })
+            .transpose()
+        };
+        let heartbeat_interval_ms = config
+            .heartbeat_interval_ms
+            .unwrap_or(DEFAULT_HEARTBEAT_INTERVAL_MS);
+        let sync_interval_ms = config.sync_interval_ms.unwrap_or(DEFAULT_SYNC_INTERVAL_MS);
+        if heartbeat_interval_ms == 0 || sync_interval_ms == 0 {
+            return Err(
+                "replication.heartbeatIntervalMs and syncIntervalMs must be greater than 0"
+                    .to_owned(),
+            );
+        }
+        let failover_after_misses = config
+            .failover_after_misses
+            .unwrap_or(DEFAULT_FAILOVER_AFTER_MISSES);
+        if failover_after_misses == 0 {
+            return Err("replication.failoverAfterMisses must be greater than 0".to_owned());
+        }
+        Ok(Self {
+            token,
+            primary_url: url(config.primary_url, "primaryUrl")?,
+            advertise_url: url(config.advertise_url, "advertiseUrl")?,
+            heartbeat_interval: Duration::from_millis(heartbeat_interval_ms),
+            failover_after_misses,
+            sync_interval: Duration::from_millis(sync_interval_ms),
+            auto_promote: config.auto_promote.unwrap_or(true),
+        })
+    }
+
+    #[must_use]
+    pub fn primary_url(&self) -> Option<&str> {
+        self.primary_url.as_deref()
+    }
+
+    #[must_use]
+    pub fn advertise_url(&self) -> Option<&str> {
+        self.advertise_url.as_deref()
+    }
+
+    /// Checks a bearer token presented on `/replication/*`.
+    #[must_use]
+    pub fn accepts_token(&self, provided: Option<&str>) -> bool {
+        provided.is_some_and(|provided| provided.as_bytes().ct_eq(self.token.as_bytes()).into())
+    }
+}
+
+/// Replication progress kept in memory. The epoch, the applied sequence and fencing are also
+/// persisted in `replication_state`, and loaded from there on start.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationStatus {
+    pub epoch: u64,
+    pub last_heartbeat_at_ms: Option<u64>,
+    pub missed_heartbeats: u32,
+    pub last_sync_at_ms: Option<u64>,
+    pub last_sync_rows: Option<u64>,
+    pub last_sync_error: Option<String>,
+    pub syncs: u64,
+    /// Last primary change a standby applied.
+    pub applied_seq: u64,
+    /// Full snapshots a standby loaded: its first sync, and any after falling behind the
+    /// primary's change log or a promotion.
+    pub snapshots: u64,
+    pub promoted_at_ms: Option<u64>,
+    pub promotion_reason: Option<String>,
+    /// When a promoted instance got its previous primary to step down.
+    pub previous_primary_fenced_at_ms: Option<u64>,
+    /// When a newer primary fenced this one, and where it said clients should go.
+    pub fenced_at_ms: Option<u64>,
+    pub fenced_by_url: Option<String>,
+    /// Standby that last checked in, as seen by the primary.
+    pub standby_url: Option<String>,
+    pub standby_seen_at_ms: Option<u64>,
+    pub standby_applied_seq: Option<u64>,
+}
+
+impl ReplicationStatus {
+    #[must_use]
+    pub fn from_state(state: &ReplicationState) -> Self {
+        Self {
+            epoch: state.epoch,
+            applied_seq: state.applied_seq,
+            fenced_at_ms: state.fenced_at_ms,
+            fenced_by_url: state.fenced_by_url.clone(),
+            standby_url: state.peer_url.clone(),
+            ..Self::default()
+        }
+    }
+}
+
+/// One page of `/replication/changes`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangesPage {
+    pub epoch: u64,
+    pub changes: Vec<ReplicatedChange>,
+    /// More changes follow the last one on this page.
+    pub more: bool,
+}
+
+/// A database snapshot on disk, removed once dropped so a streamed download cleans up after
+/// itself.
+#[derive(Debug)]
+pub struct StagingFile {
+    path: PathBuf,
+}
+
+impl StagingFile {
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StagingFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// `replication.status` and `health.replication`.
+pub async fn status_payload(state: &SharedState) -> Option<Value> {
+    let replication = state.config().replication.as_ref()?;
+    let mut payload = json!(state.replication_status().await);
+    payload["role"] = json!(role(state));
+    payload["primaryUrl"] = json!(current_primary_url(state).await);
+    payload["advertiseUrl"] = json!(replication.advertise_url);
+    payload["autoPromote"] = json!(replication.auto_promote);
+    Some(payload)
+}
+
+/// The primary a read-only instance defers to: the one it follows, or the newer primary that
+/// fenced it.
+pub async fn current_primary_url(state: &SharedState) -> Option<String> {
+    let replication = state.config().replication.as_ref()?;
+    if !state.is_standby() {
+        return replication.primary_url.clone();
+    }
+    state
+        .replication_status()
+        .await
+        .fenced_by_url
+        .or_else(|| replication.primary_url.clone())
+}
+
+/// `hello-ok.replication`: where clients should reconnect. A standby points at its primary; a
+/// primary names the standby that would take over, while that standby keeps checking in.
+pub async fn reconnect_hint(state: &SharedState) -> Option<Value> {
+    let replication = state.config().replication.as_ref()?;
+    if state.is_standby() {
+        return Some(json!({
+            "role": "standby",
+            "primaryUrl": current_primary_url(state).await,
+        }));
+    }
+    let status = state.replication_status().await;
+    let standby_url = status
+        .standby_url
+        .filter(|_| {
+            status.standby_seen_at_ms.is_some_and(|seen| {
+                now_unix_ms().saturating_sub(seen) <= stale_after_ms(replication)
+            })
+        })
+        .map(Value::String)
+        .unwrap_or(Value::Null);
+    Some(json!({
+        "role": "primary",
+        "primaryUrl": replication.advertise_url,
+        "standbyUrl": standby_url,
+    }))
+}
+
+fn stale_after_ms(replication: &Replication) -> u64 {
+    u64::try_from((replication.heartbeat_interval * STANDBY_STALE_INTERVALS).as_millis())
+        .unwrap_or(u64::MAX)
+}
+
+fn role(state: &SharedState) -> &'static str {
+    if state.is_standby() {
+        "standby"
+    } else {
+        "primary"
+    }
+}
+
+/// Answers a standby's heartbeat, remembers where it can be reached, and notes how far it has
+/// applied the change log so the primary can prune behind it.
+pub async fn accept_heartbeat(
+    state: &SharedState,
+    standby_url: Option<String>,
+    applied_seq: Option<u64>,
+) -> Value {
+    if !state.is_standby() {
+        let now = now_unix_ms();
+        let previous = state.replication_status().await.standby_url;
+        if let Some(url) = standby_url.as_deref()
+            && previous.as_deref() != Some(url)
+            && let Err(error) = state.set_replication_peer(url).await
+        {
+            warn!("failed to remember standby {url}: {error}");
+        }
+        state
+            .update_replication_status(|status| {
+                if standby_url.is_some() {
+                    status.standby_url = standby_url;
+                }
+                status.standby_seen_at_ms = Some(now);
+                if applied_seq.is_some() {
+                    status.standby_applied_seq = applied_seq;
+                }
+            })
+            .await;
+    }
+    json!({
+        "role": role(state),
+        "epoch": state.replication_status().await.epoch,
+        "ts": now_unix_ms(),
+    })
+}
+
+/// `/replication/epoch`: lets a primary that lost track of its standby find out whether it was
+/// replaced.
+pub async fn epoch_report(state: &SharedState) -> Value {
+    json!({
+        "role": role(state),
+        "epoch": state.replication_status().await.epoch,
+        "primaryUrl": state
+            .config()
+            .replication
+            .as_ref()
+            .and_then(|replication| replication.advertise_url.clone()),
+    })
+}
+
+/// Captured changes after `after_seq`, or `None` once they were pruned and the standby has to
+/// load a snapshot instead.
+pub async fn changes_page(
+    state: &SharedState,
+    after_seq: u64,
+    limit: usize,
+) -> Result<Option<ChangesPage>, DomainError> {
+    let limit = limit.clamp(1, CHANGES_PAGE_LIMIT);
+    let Some(mut changes) = state.list_replicated_changes(after_seq, limit + 1).await? else {
+        return Ok(None);
+    };
+    let more = changes.len() > limit;
+    changes.truncate(limit);
+    Ok(Some(ChangesPage {
+        epoch: state.replication_status().await.epoch,
+        changes,
+        more,
+    }))
+}
+
+/// A consistent copy of the database for a standby to stream.
+pub async fn snapshot_file(state: &SharedState) -> Result<StagingFile, DomainError> {
+    let staging = StagingFile {
+        path: staging_path(state, "outgoing"),
+    };
+    state.vacuum_into(staging.path()).await?;
+    Ok(staging)
+}
+
+/// Leaves standby mode under a new epoch: starts capturing changes, lifts the read-only gate,
+/// and tells connected clients. The previous primary is fenced once it can be reached.
+pub async fn promote(state: &SharedState, reason: &str) -> Result<ReplicationStatus, String> {
+    let Some(replication) = state.config().replication.as_ref() else {
+        return Err("replication is not configured".to_owned());
+    };
+    if !state.promote_standby() {
+        return Err("this instance is already the primary".to_owned());
+    }
+    let epoch = state.replication_status().await.epoch + 1;
+    let persisted = async {
+        state.set_replication_epoch(epoch, None, None).await?;
+        state.set_change_capture(true).await
+    }
+    .await;
+    if let Err(error) = persisted {
+        warn!("promotion to epoch {epoch} was not persisted: {error}");
+    }
+    let now = now_unix_ms();
+    let status = state
+        .update_replication_status(|status| {
+            status.epoch = epoch;
+            status.promoted_at_ms = Some(now);
+            status.promotion_reason = Some(reason.to_owned());
+            status.previous_primary_fenced_at_ms = None;
+            status.fenced_at_ms = None;
+            status.fenced_by_url = None;
+        })
+        .await;
+    let message = format!("promoted to primary at epoch {epoch}: {reason}");
+    warn!("{message}");
+    let _ = state
+        .append_gateway_log("warn", &message, Some("replication"), None)
+        .await;
+    state
+        .publish_gateway_event(
+            PROMOTED_EVENT,
+            json!({
+                "primaryUrl": replication.advertise_url,
+                "previousPrimaryUrl": replication.primary_url,
+                "reason": reason,
+                "epoch": epoch,
+                "ts": now,
+            }),
+        )
+        .await;
+    Ok(status)
+}
+
+/// `/replication/fence`: a promoted standby telling this primary it was replaced. Only a newer
+/// epoch is accepted.
+pub async fn accept_fence(
+    state: &SharedState,
+    epoch: u64,
+    primary_url: Option<&str>,
+) -> Result<Value, String> {
+    let current = state.replication_status().await.epoch;
+    if epoch <= current {
+        return Err(format!(
+            "epoch {epoch} is not newer than this instance's epoch {current}"
+        ));
+    }
+    step_down(state, epoch, primary_url)
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(json!({ "epoch": epoch }))
+}
+
+/// Turns this primary read-only for good after a newer one took over, pointing clients at it.
+async fn step_down(
+    state: &SharedState,
+    epoch: u64,
+    primary_url: Option<&str>,
+) -> Result<(), DomainError> {
+    let now = now_unix_ms();
+    state
+        .set_replication_epoch(epoch, Some(now), primary_url)
+        .await?;
+    state.demote_to_standby();
+    state
+        .update_replication_status(|status| {
+            status.epoch = epoch;
+            status.fenced_at_ms = Some(now);
+            status.fenced_by_url = primary_url.map(str::to_owned);
+        })
+        .await;
+    let message = format!(
+        "fenced by a newer primary at epoch {epoch} ({}); refusing writes",
+        primary_url.unwrap_or("url unknown")
+    );
+    warn!("{message}");
+    let _ = state
+        .append_gateway_log("warn", &message, Some("replication"), None)
+        .await;
+    Ok(())
+}
+
+/// Before a primary serves writes: asks the standby that last checked in whether it has been
+/// promoted since, and steps down if so.
+pub async fn check_superseded_on_start(state: &SharedState) {
+    let Some(replication) = state.config().replication.as_ref() else {
+        return;
+    };
+    if state.is_standby() {
+        return;
+    }
+    let Ok(client) = http_client(replication) else {
+        return;
+    };
+    if let Err(error) = check_superseded(state, &client, replication).await {
+        warn!("could not ask the last standby whether it was promoted: {error}");
+    }
+}
+
+async fn check_superseded(
+    state: &SharedState,
+    client: &reqwest::Client,
+    replication: &Replication,
+) -> Result<(), String> {
+    let status = state.replication_status().await;
+    let Some(peer_url) = status.standby_url else {
+        return Ok(());
+    };
+    let response = client
+        .get(format!("{peer_url}/replication/epoch"))
+        .bearer_auth(&replication.token)
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("epoch check failed with {}", response.status()));
+    }
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|error| error.to_string())?;
+    let epoch = body.get("epoch").and_then(Value::as_u64).unwrap_or(0);
+    if body.get("role").and_then(Value::as_str) == Some("primary") && epoch > status.epoch {
+        let primary_url = body
+            .get("primaryUrl")
+            .and_then(Value::as_str)
+            .unwrap_or(&peer_url);
+        step_down(state, epoch, Some(primary_url))
+            .await
+            .map_err(|error| error.to_string())?;
+    }
+    Ok(())
+}
+
+fn http_client(replication: &Replication) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(replication.heartbeat_interval.max(Duration::from_secs(1)))
+        .build()
+        .map_err(|error| format!("replication http client unavailable: {error}"))
+}
+
+/// Runs replication for either role. A standby heartbeats its primary, applies its change log
+/// and promotes itself once `failoverAfterMisses` heartbeats in a row go unanswered. A primary
+/// prunes its change log, fences the primary it replaced, and steps down if its standby turns
+/// out to have been promoted while it was out of reach.
+pub fn spawn_replicator(state: SharedState) -> Option<tokio::task::JoinHandle<()>> {
+    let replication = state.config().replication.clone()?;
+    if let Some(primary_url) = replication.primary_url() {
+        info!(
+            "standby following {primary_url} (heartbeat every {}ms, sync every {}ms)",
+            replication.heartbeat_interval.as_millis(),
+            replication.sync_interval.as_millis()
+        );
+    }
+
+    Some(tokio::spawn(async move {
+        let client = match http_client(&replication) {
+            Ok(client) => client,
+            Err(error) => {
+                warn!("{error}");
+                return;
+            }
+        };
+        let mut ticker = tokio::time::interval(replication.heartbeat_interval);
+        let mut last_sync: Option<Instant> = None;
+        let mut last_prune: Option<Instant> = None;
+        loop {
+            ticker.tick().await;
+            if !state.is_standby() {
+                lead(&state, &client, &replication, &mut last_prune).await;
+                continue;
+            }
+            // A fenced primary has nothing to follow until it is reconfigured as a standby.
+            let Some(primary_url) = replication.primary_url.clone() else {
+                continue;
+            };
+            follow(&state, &client, &replication, &primary_url, &mut last_sync).await;
+        }
+    }))
+}
+
+async fn lead(
+    state: &SharedState,
+    client: &reqwest::Client,
+    replication: &Replication,
+    last_prune: &mut Option<Instant>,
+) {
+    let status = state.replication_status().await;
+    if let Some(previous) = replication.primary_url.as_deref()
+        && status.promoted_at_ms.is_some()
+        && status.previous_primary_fenced_at_ms.is_none()
+        && fence(client, replication, previous, status.epoch)
+            .await
+            .is_ok()
+    {
+        info!(
+            "fenced previous primary {previous} at epoch {}",
+            status.epoch
+        );
+        let now = now_unix_ms();
+        state
+            .update_replication_status(|status| status.previous_primary_fenced_at_ms = Some(now))
+            .await;
+    }
+
+    if last_prune.is_none_or(|at| at.elapsed() >= replication.sync_interval) {
+        *last_prune = Some(Instant::now());
+        let before_ms = now_unix_ms().saturating_sub(CHANGE_RETENTION_MS);
+        if let Err(error) = state
+            .prune_replicated_changes(status.standby_applied_seq.unwrap_or(0), before_ms)
+            .await
+        {
+            warn!("failed to prune the replication change log: {error}");
+        }
+    }
+
+    // A standby that stopped checking in may have promoted itself; ask before writing on.
+    let quiet = status.standby_seen_at_ms.is_some_and(|seen| {
+        let failover_ms = u64::try_from(
+            (replication.heartbeat_interval * replication.failover_after_misses).as_millis(),
+        )
+        .unwrap_or(u64::MAX);
+        now_unix_ms().saturating_sub(seen) > failover_ms
+    });
+    if quiet {
+        let _ = check_superseded(state, client, replication).await;
+    }
+}
+
+async fn fence(
+    client: &reqwest::Client,
+    replication: &Replication,
+    previous_primary_url: &str,
+    epoch: u64,
+) -> Result<(), String> {
+    let response = client
+        .post(format!("{previous_primary_url}/replication/fence"))
+        .bearer_auth(&replication.token)
+        .json(&json!({ "epoch": epoch, "primaryUrl": replication.advertise_url }))
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("fence failed with {}", response.status()))
+    }
+}
+
+async fn follow(
+    state: &SharedState,
+    client: &reqwest::Client,
+    replication: &Replication,
+    primary_url: &str,
+    last_sync: &mut Option<Instant>,
+) {
+    if let Err(error) = heartbeat(state, client, replication, primary_url).await {
+        let status = state
+            .update_replication_status(|status| status.missed_heartbeats += 1)
+            .await;
+        warn!(
+            "primary {primary_url} missed heartbeat {}/{}: {error}",
+            status.missed_heartbeats, replication.failover_after_misses
+        );
+        if replication.auto_promote && status.missed_heartbeats >= replication.failover_after_misses
+        {
+            let reason = format!(
+                "primary {primary_url} missed {} heartbeats",
+                status.missed_heartbeats
+            );
+            let _ = promote(state, &reason).await;
+        }
+        return;
+    }
+
+    let now = now_unix_ms();
+    state
+        .update_replication_status(|status| {
+            status.last_heartbeat_at_ms = Some(now);
+            status.missed_heartbeats = 0;
+        })
+        .await;
+    if last_sync.is_some_and(|at| at.elapsed() < replication.sync_interval) {
+        return;
+    }
+    *last_sync = Some(Instant::now());
+    let result = sync(state, client, replication, primary_url).await;
+    if let Err(error) = &result {
+        warn!("standby sync from {primary_url} failed: {error}");
+    }
+    state
+        .update_replication_status(|status| match result {
+            Ok(rows) => {
+                status.last_sync_at_ms = Some(now_unix_ms());
+                status.last_sync_rows = Some(rows);
+                status.last_sync_error = None;
+                status.syncs += 1;
+            }
+            Err(error) => status.last_sync_error = Some(error),
+        })
+        .await;
+}
+
+async fn heartbeat(
+    state: &SharedState,
+    client: &reqwest::Client,
+    replication: &Replication,
+    primary_url: &str,
+) -> Result<(), String> {
+    let applied_seq = state.replication_status().await.applied_seq;
+    let response = client
+        .post(format!("{primary_url}/replication/heartbeat"))
+        .bearer_auth(&replication.token)
+        .json(&json!({ "standbyUrl": replication.advertise_url, "appliedSeq": applied_seq }))
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("heartbeat failed with {}", response.status()));
+    }
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|error| error.to_string())?;
+    match body.get("role").and_then(Value::as_str) {
+        Some("primary") => {}
+        role => return Err(format!("peer reports role {}", role.unwrap_or("unknown"))),
+    }
+    // A primary behind this instance's epoch was replaced; following it would undo the writes
+    // made since, so it counts as gone.
+    let epoch = body.get("epoch").and_then(Value::as_u64).unwrap_or(0);
+    let own = state.replication_status().await.epoch;
+    if epoch < own {
+        return Err(format!(
+            "primary is on epoch {epoch}, behind this instance's epoch {own}"
+        ));
+    }
+    Ok(())
+}
+
+/// Applies the primary's change log page by page. The first sync, a standby that fell behind
+/// the pruned log, and a primary on another epoch all load a full snapshot instead.
+async fn sync(
+    state: &SharedState,
+    client: &reqwest::Client,
+    replication: &Replication,
+    primary_url: &str,
+) -> Result<u64, String> {
+    let status = state.replication_status().await;
+    if status.snapshots == 0 && status.applied_seq == 0 {
+        return load_snapshot(state, client, replication, primary_url).await;
+    }
+    let mut after_seq = status.applied_seq;
+    let mut applied = 0;
+    loop {
+        let response = client
+            .get(format!(
+                "{primary_url}/replication/changes?afterSeq={after_seq}&limit={CHANGES_PAGE_LIMIT}"
+            ))
+            .bearer_auth(&replication.token)
+            .send()
+            .await
+            .map_err(|error| error.to_string())?;
+        if response.status() == reqwest::StatusCode::GONE {
+            return load_snapshot(state, client, replication, primary_url).await;
+        }
+        if !response.status().is_success() {
+            return Err(format!("changes failed with {}", response.status()));
+        }
+        let page = response
+            .json::<ChangesPage>()
+            .await
+            .map_err(|error| error.to_string())?;
+        if page.epoch != status.epoch {
+            return load_snapshot(state, client, replication, primary_url).await;
+        }
+        if !state.is_standby() {
+            return Err("promoted while syncing".to_owned());
+        }
+        if let Some(seq) = state
+            .apply_replicated_changes(&page.changes)
+            .await
+            .map_err(|error| error.to_string())?
+        {
+            after_seq = seq;
+            state
+                .update_replication_status(|status| status.applied_seq = seq)
+                .await;
+        }
+        applied += u64::try_from(page.changes.len()).unwrap_or(u64::MAX);
+        if !page.more {
+            return Ok(applied);
+        }
+    }
+}
+
+/// Streams the primary's snapshot to disk and loads it, taking over its epoch and change log
+/// position.
+async fn load_snapshot(
+    state: &SharedState,
+    client: &reqwest::Client,
+    replication: &Replication,
+    primary_url: &str,
+) -> Result<u64, String> {
+    let mut response = client
+        .get(format!("{primary_url}/replication/snapshot"))
+        .bearer_auth(&replication.token)
+        .timeout(SNAPSHOT_TIMEOUT)
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("snapshot failed with {}", response.status()));
+    }
+    let staging = StagingFile {
+        path: staging_path(state, "incoming"),
+    };
+    let mut file = tokio::fs::File::create(staging.path())
+        .await
+        .map_err(|error| error.to_string())?;
+    while let Some(chunk) = response.chunk().await.map_err(|error| error.to_string())? {
+        file.write_all(&chunk)
+            .await
+            .map_err(|error| error.to_string())?;
+    }
+    file.sync_all().await.map_err(|error| error.to_string())?;
+    drop(file);
+    if !state.is_standby() {
+        return Err("promoted while syncing".to_owned());
+    }
+    let rows = state
+        .restore_from_snapshot(staging.path())
+        .await
+        .map_err(|error| error.to_string())?;
+    let stored = state
+        .load_replication_state()
+        .await
+        .map_err(|error| error.to_string())?;
+    state
+        .update_replication_status(|status| {
+            status.epoch = stored.epoch;
+            status.applied_seq = stored.applied_seq;
+            status.snapshots += 1;
+        })
+        .await;
+    Ok(rows)
+}
+
+fn staging_path(state: &SharedState, direction: &str) -> PathBuf {
+    let name = format!(".replication-{direction}-{}.sqlite", uuid::Uuid::new_v4());
+    state
+        .config()
+        .db_path
+        .parent()
+        .map_or_else(|| PathBuf::from(&name), |dir| dir.join(&name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Replication;
+    use crate::application::config::ReplicationConfig;
+
+    #[test]
+    fn compile_normalizes_urls_and_checks_tokens() {
+        let replication = Replication::compile(ReplicationConfig {
+            token: " secret ".to_owned(),
+            primary_url: Some("https://gw-a.example.com/".to_owned()),
+            ..ReplicationConfig::default()
+        })
+        .expect("config should compile");
+        assert_eq!(replication.primary_url(), Some("https://gw-a.example.com"));
+        assert_eq!(replication.advertise_url(), None);
+        assert_eq!(replication.failover_after_misses, 3);
+        assert!(replication.auto_promote);
+        assert!(replication.accepts_token(Some("secret")));
+        assert!(!replication.accepts_token(Some("secret2")));
+        assert!(!replication.accepts_token(None));
+
+        for invalid in [
+            ReplicationConfig::default(),
+            ReplicationConfig {
+                token: "t".to_owned(),
+                primary_url: Some("gw-a.example.com".to_owned()),
+                ..ReplicationConfig::default()
+            },
+            ReplicationConfig {
+                token: "t".to_owned(),
+                failover_after_misses: Some(0),
+                ..ReplicationConfig::default()
+            },
+            ReplicationConfig {
+                token: "t".to_owned(),
+                heartbeat_interval_ms: Some(0),
+                ..ReplicationConfig::default()
+            },
+        ] {
+            assert!(Replication::compile(invalid).is_err());
+        }
+    }
+}
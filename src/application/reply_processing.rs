@@ -0,0 +1,494 @@
+use std::collections::BTreeMap;
+
+use crate::application::config::{
+    ReplyFormat, ReplyProcessingConfig, ReplyProcessingRuleConfig, normalize_channel_plugin_key,
+};
+
+/// Compiled form of the `replyProcessing` config. The default value applies the built-in channel
+/// profiles only.
+#[derive(Debug, Clone, Default)]
+pub struct ReplyProcessing {
+    defaults: ReplyProcessingRuleConfig,
+    channels: BTreeMap<String, ReplyProcessingRuleConfig>,
+    agents: BTreeMap<String, ReplyProcessingRuleConfig>,
+}
+
+/// An agent reply ready for delivery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessedReply {
+    /// The converted reply with its footer, before splitting.
+    pub text: String,
+    /// One entry per message to send, in order.
+    pub parts: Vec<String>,
+    pub suppress_unfurl: bool,
+    /// The dialect `text` is written in, which Telegram passes on as `parse_mode`.
+    pub format: ReplyFormat,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Emphasis {
+    Bold,
+    Italic,
+    Strike,
+}
+
+impl ReplyProcessing {
+    pub fn compile(config: ReplyProcessingConfig) -> Result<Self, String> {
+        validate_rule("replyProcessing", &config.defaults)?;
+        Ok(Self {
+            defaults: config.defaults,
+            channels: normalize_overrides("channels", config.channels)?,
+            agents: normalize_overrides("agents", config.agents)?,
+        })
+    }
+
+    /// Applies the rule for `channel` and `agent_id`: format conversion, footer, then splitting.
+    /// The rule starts from the channel's built-in profile; configured fields override it.
+    #[must_use]
+    pub fn process(&self, channel: &str, agent_id: &str, reply: &str) -> ProcessedReply {
+        let mut rule = channel_profile(channel);
+        for layer in [
+            Some(&self.defaults),
+            self.channels.get(channel),
+            self.agents.get(agent_id),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            rule.format = layer.format.or(rule.format);
+            rule.max_length = layer.max_length.or(rule.max_length);
+            rule.suppress_unfurl = layer.suppress_unfurl.or(rule.suppress_unfurl);
+            rule.footer = layer.footer.clone().or(rule.footer);
+        }
+
+        let format = rule.format.unwrap_or_default();
+        let mut text = convert(reply, format);
+        if let Some(footer) = rule
+            .footer
+            .as_deref()
+            .map(str::trim)
+            .filter(|footer| !footer.is_empty())
+        {
+            text = format!("{}\n\n{}", text.trim_end(), convert(footer, format));
+        }
+        let parts = match rule.max_length {
+            Some(max_length) => split_message(&text, max_length),
+            None => vec![text.clone()],
+        };
+        ProcessedReply {
+            text,
+            parts,
+            suppress_unfurl: rule.suppress_unfurl.unwrap_or(false),
+            format,
+        }
+    }
+}
+
+/// Built-in format and message length limit of each channel adapter.
+fn channel_profile(channel: &str) -> ReplyProcessingRuleConfig {
+    let (format, max_length) = match channel {
+        "telegram" => (ReplyFormat::Telegram, 4096),
+        "slack" => (ReplyFormat::Slack, 40_000),
+        "whatsapp" => (ReplyFormat::Whatsapp, 4096),
+        "signal" => (ReplyFormat::Plain, 2000),
+        "discord" => (ReplyFormat::Markdown, 2000),
+        "mattermost" => (ReplyFormat::Markdown, 16_383),
+        "rocketchat" => (ReplyFormat::Markdown, 5000),
+        "teams" => (ReplyFormat::Markdown, 28_000),
+        _ => return ReplyProcessingRuleConfig::default(),
+    };
+    ReplyProcessingRuleConfig {
+        format: Some(format),
+        max_length: Some(max_length),
+        ..ReplyProcessingRuleConfig::default()
+    }
+}
+
+fn normalize_overrides(
+    kind: &str,
+    overrides: BTreeMap<String, ReplyProcessingRuleConfig>,
+) -> Result<BTreeMap<String, ReplyProcessingRuleConfig>, String> {
+    let mut normalized = BTreeMap::new();
+    for (key, rule) in overrides {
+        let normalized_key = normalize_channel_plugin_key(&key).ok_or_else(|| {
+            format!("replyProcessing.{kind} key must contain only [a-z0-9._-]: {key}")
+        })?;
+        validate_rule(&format!("replyProcessing.{kind}.{normalized_key}"), &rule)?;
+        normalized.insert(normalized_key, rule);
+    }
+    Ok(normalized)
+}
+
+fn validate_rule(scope: &str, rule: &ReplyProcessingRuleConfig) -> Result<(), String> {
+    if rule.max_length == Some(0) {
+        return Err(format!("{scope}.maxLength must be greater than 0"));
+    }
+    Ok(())
+}
+
+/// Converts agent markdown line by line; fenced code blocks are kept verbatim.
+fn convert(text: &str, format: ReplyFormat) -> String {
+    if format == ReplyFormat::Markdown {
+        return text.to_owned();
+    }
+    let mut lines = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            if format != ReplyFormat::Plain {
+                lines.push("```".to_owned());
+            }
+        } else if in_fence {
+            lines.push(escape_code(line, format));
+        } else {
+            lines.push(convert_line(line, format));
+        }
+    }
+    lines.join("\n")
+}
+
+fn convert_line(line: &str, format: ReplyFormat) -> String {
+    let trimmed = line.trim_start();
+    let hashes = trimmed.chars().take_while(|ch| *ch == '#').count();
+    if (1..=6).contains(&hashes)
+        && let Some(heading) = trimmed[hashes..].strip_prefix(' ')
+    {
+        let heading = convert_inline(heading.trim(), format);
+        return match format {
+            ReplyFormat::Plain | ReplyFormat::Markdown => heading,
+            ReplyFormat::Slack | ReplyFormat::Telegram | ReplyFormat::Whatsapp => {
+                format!("*{heading}*")
+            }
+        };
+    }
+    if let Some(item) = trimmed.strip_prefix("* ") {
+        let bullet = if format == ReplyFormat::Telegram {
+            "\\-"
+        } else {
+            "-"
+        };
+        return format!("{bullet} {}", convert_inline(item, format));
+    }
+    // Quotes need a literal `>`, which the text escaping would otherwise turn into `&gt;` (Slack)
+    // or `\>` (Telegram).
+    if matches!(format, ReplyFormat::Slack | ReplyFormat::Telegram)
+        && let Some(quoted) = trimmed.strip_prefix('>')
+    {
+        return format!(">{}", convert_inline(quoted, format));
+    }
+    convert_inline(line, format)
+}
+
+fn convert_inline(text: &str, format: ReplyFormat) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut previous = None;
+    while let Some(ch) = rest.chars().next() {
+        if let Some((converted, consumed)) = convert_span(rest, previous, format) {
+            out.push_str(&converted);
+            previous = rest[..consumed].chars().next_back();
+            rest = &rest[consumed..];
+            continue;
+        }
+        out.push_str(&escape_text(&rest[..ch.len_utf8()], format));
+        previous = Some(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    out
+}
+
+/// Converts the code span, link, or emphasis starting at `rest`, returning the replacement and
+/// the number of bytes consumed.
+fn convert_span(
+    rest: &str,
+    previous: Option<char>,
+    format: ReplyFormat,
+) -> Option<(String, usize)> {
+    if let Some(after) = rest.strip_prefix('`') {
+        // An empty span is not code; its backticks are escaped as text.
+        let end = after.find('`').filter(|end| *end > 0)?;
+        let code = escape_code(&after[..end], format);
+        let converted = if format == ReplyFormat::Plain {
+            code
+        } else {
+            format!("`{code}`")
+        };
+        return Some((converted, end + 2));
+    }
+
+    if let Some(after) = rest.strip_prefix('[') {
+        let label_end = after.find("](")?;
+        let url_start = label_end + 2;
+        let url_len = after[url_start..].find(')')?;
+        let label = convert_inline(&after[..label_end], format);
+        let url = &after[url_start..url_start + url_len];
+        let converted = match format {
+            ReplyFormat::Slack => format!("<{url}|{label}>"),
+            ReplyFormat::Telegram => format!("[{label}]({})", escape_chars(url, ")\\")),
+            _ if label == url => url.to_owned(),
+            _ => format!("{label} ({url})"),
+        };
+        return Some((converted, 1 + url_start + url_len + 1));
+    }
+
+    for (marker, emphasis) in [
+        ("**", Emphasis::Bold),
+        ("__", Emphasis::Bold),
+        ("~~", Emphasis::Strike),
+        ("*", Emphasis::Italic),
+        ("_", Emphasis::Italic),
+    ] {
+        let Some(after) = rest.strip_prefix(marker) else {
+            continue;
+        };
+        // `snake_case` words and markers followed by a space are plain text.
+        if after.is_empty()
+            || after.starts_with(char::is_whitespace)
+            || (marker.starts_with('_') && previous.is_some_and(char::is_alphanumeric))
+        {
+            continue;
+        }
+        let Some(end) = after.find(marker) else {
+            continue;
+        };
+        let inner = &after[..end];
+        if inner.is_empty() || inner.ends_with(char::is_whitespace) {
+            continue;
+        }
+        let inner = convert_inline(inner, format);
+        let converted = match (format, emphasis) {
+            (ReplyFormat::Plain | ReplyFormat::Markdown, _) => inner,
+            (_, Emphasis::Bold) => format!("*{inner}*"),
+            (_, Emphasis::Italic) => format!("_{inner}_"),
+            (_, Emphasis::Strike) => format!("~{inner}~"),
+        };
+        return Some((converted, marker.len() * 2 + end));
+    }
+    None
+}
+
+/// Slack treats `&`, `<`, and `>` as control characters in message text; Telegram `MarkdownV2`
+/// rejects any unescaped markup character outside an entity.
+fn escape_text(text: &str, format: ReplyFormat) -> String {
+    match format {
+        ReplyFormat::Slack => text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;"),
+        ReplyFormat::Telegram => escape_chars(text, "_*[]()~`>#+-=|{}.!\\"),
+        _ => text.to_owned(),
+    }
+}
+
+/// Escaping inside code spans and code blocks, where Telegram only treats the characters that
+/// could end the entity as special.
+fn escape_code(text: &str, format: ReplyFormat) -> String {
+    match format {
+        ReplyFormat::Telegram => escape_chars(text, "`\\"),
+        _ => escape_text(text, format),
+    }
+}
+
+fn escape_chars(text: &str, special: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if special.contains(ch) {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Splits `text` into parts of at most `max_chars` characters, preferring paragraph, line, and
+/// word boundaries in the second half of each part.
+fn split_message(text: &str, max_chars: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = text.trim();
+    while rest.chars().count() > max_chars {
+        let limit = rest
+            .char_indices()
+            .nth(max_chars)
+            .map_or(rest.len(), |(index, _)| index);
+        let window = &rest[..limit];
+        let cut = ["\n\n", "\n", " "]
+            .iter()
+            .find_map(|separator| {
+                window
+                    .rfind(separator)
+                    .filter(|at| *at > 0 && *at >= limit / 2)
+            })
+            .unwrap_or(limit);
+        // A hard cut must not separate a Telegram escape from the character it escapes.
+        let trailing_escapes = window.chars().rev().take_while(|ch| *ch == '\\').count();
+        let cut = if cut == limit && trailing_escapes % 2 == 1 && cut > 1 {
+            cut - 1
+        } else {
+            cut
+        };
+        parts.push(rest[..cut].trim_end().to_owned());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() || parts.is_empty() {
+        parts.push(rest.to_owned());
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{ReplyProcessing, convert, split_message};
+    use crate::application::config::{
+        ReplyFormat, ReplyProcessingConfig, ReplyProcessingRuleConfig,
+    };
+
+    #[test]
+    fn markdown_converts_to_channel_dialects() {
+        let reply = "## Status\n**Build** is _green_, see [the log](https://ci.example/1) & ~~old~~ `a<b`\n* item with snake_case_name\n```rust\nlet x = **y**;\n```";
+        assert_eq!(
+            convert(reply, ReplyFormat::Slack),
+            "*Status*\n*Build* is _green_, see <https://ci.example/1|the log> &amp; ~old~ `a&lt;b`\n- item with snake_case_name\n```\nlet x = **y**;\n```"
+        );
+        assert_eq!(
+            convert(reply, ReplyFormat::Whatsapp),
+            "*Status*\n*Build* is _green_, see the log (https://ci.example/1) & ~old~ `a<b`\n- item with snake_case_name\n```\nlet x = **y**;\n```"
+        );
+        assert_eq!(
+            convert(reply, ReplyFormat::Plain),
+            "Status\nBuild is green, see the log (https://ci.example/1) & old a<b\n- item with snake_case_name\nlet x = **y**;"
+        );
+        assert_eq!(convert(reply, ReplyFormat::Markdown), reply);
+        assert_eq!(convert("2 * 3 * 4", ReplyFormat::Slack), "2 * 3 * 4");
+    }
+
+    #[test]
+    fn telegram_markdown_v2_escapes_everything_outside_entities() {
+        let reply = "## Done!\n**v1.2** is _live_ (see [notes](https://x.example/a_b)) ~~old~~\n* 50% off = 1+1\n> quoted.\n`a\\b` and `x` ```\n```\nif a > b { c.d() } `e`\n```";
+        assert_eq!(
+            convert(reply, ReplyFormat::Telegram),
+            "*Done\\!*\n*v1\\.2* is _live_ \\(see [notes](https://x.example/a_b)\\) ~old~\n\\- 50% off \\= 1\\+1\n> quoted\\.\n`a\\\\b` and `x` \\`\\`\\`\n```\nif a > b { c.d() } \\`e\\`\n```"
+        );
+        assert_eq!(
+            convert(
+                "2 * 3 * 4 - snake_case_name #1 [x | y] {z}",
+                ReplyFormat::Telegram
+            ),
+            "2 \\* 3 \\* 4 \\- snake\\_case\\_name \\#1 \\[x \\| y\\] \\{z\\}"
+        );
+        assert_eq!(
+            convert("unclosed *bold and `code", ReplyFormat::Telegram),
+            "unclosed \\*bold and \\`code"
+        );
+    }
+
+    #[test]
+    fn channel_profiles_apply_without_config_and_yield_to_configured_rules() {
+        let profiles = ReplyProcessing::default();
+        let telegram = profiles.process("telegram", "main", "**hi** there.");
+        assert_eq!(telegram.text, "*hi* there\\.");
+        assert_eq!(telegram.format, ReplyFormat::Telegram);
+        assert_eq!(
+            profiles.process("slack", "main", "**a** & b").text,
+            "*a* &amp; b"
+        );
+        assert_eq!(
+            profiles
+                .process("signal", "main", "**a** [b](https://c)")
+                .text,
+            "a b (https://c)"
+        );
+        let discord = profiles.process("discord", "main", &"word ".repeat(500));
+        assert_eq!(discord.parts.len(), 2);
+        assert!(
+            discord
+                .parts
+                .iter()
+                .all(|part| part.chars().count() <= 2000)
+        );
+        assert_eq!(profiles.process("extchat", "main", "**a**").text, "**a**");
+
+        let configured = ReplyProcessing::compile(ReplyProcessingConfig {
+            channels: BTreeMap::from([(
+                "telegram".to_owned(),
+                ReplyProcessingRuleConfig {
+                    format: Some(ReplyFormat::Plain),
+                    ..ReplyProcessingRuleConfig::default()
+                },
+            )]),
+            ..ReplyProcessingConfig::default()
+        })
+        .expect("config should compile");
+        let plain = configured.process("telegram", "main", &format!("**hi** {}", "x".repeat(5000)));
+        assert_eq!(plain.format, ReplyFormat::Plain);
+        assert!(plain.parts[0].starts_with("hi "));
+        assert_eq!(plain.parts.len(), 2);
+    }
+
+    #[test]
+    fn long_replies_split_on_boundaries_without_breaking_characters() {
+        let parts = split_message("first paragraph here\n\nsecond one follows", 24);
+        assert_eq!(parts, vec!["first paragraph here", "second one follows"]);
+
+        let parts = split_message("ééééééééé", 4);
+        assert_eq!(parts, vec!["éééé", "éééé", "é"]);
+        assert_eq!(split_message("short", 10), vec!["short"]);
+        assert_eq!(split_message("abc\\.def", 4), vec!["abc", "\\.de", "f"]);
+        assert_eq!(split_message("ab\\\\cd", 4), vec!["ab\\\\", "cd"]);
+    }
+
+    #[test]
+    fn agent_rules_override_channel_rules_field_by_field() {
+        let processing = ReplyProcessing::compile(ReplyProcessingConfig {
+            defaults: ReplyProcessingRuleConfig {
+                footer: Some("-- sent by reclaw".to_owned()),
+                ..ReplyProcessingRuleConfig::default()
+            },
+            channels: BTreeMap::from([(
+                "Slack".to_owned(),
+                ReplyProcessingRuleConfig {
+                    format: Some(ReplyFormat::Slack),
+                    suppress_unfurl: Some(true),
+                    ..ReplyProcessingRuleConfig::default()
+                },
+            )]),
+            agents: BTreeMap::from([(
+                "ops".to_owned(),
+                ReplyProcessingRuleConfig {
+                    footer: Some(String::new()),
+                    max_length: Some(8),
+                    ..ReplyProcessingRuleConfig::default()
+                },
+            )]),
+        })
+        .expect("config should compile");
+
+        let main = processing.process("slack", "main", "**hi**");
+        assert_eq!(main.text, "*hi*\n\n-- sent by reclaw");
+        assert!(main.suppress_unfurl);
+        let ops = processing.process("slack", "ops", "**hello** world");
+        assert_eq!(ops.parts, vec!["*hello*", "world"]);
+        assert!(ops.suppress_unfurl);
+        assert_eq!(
+            processing.process("extchat", "main", "**hi**").parts,
+            vec!["**hi**\n\n-- sent by reclaw"]
+        );
+        let telegram = processing.process("telegram", "main", "**hi**");
+        assert_eq!(telegram.parts, vec!["*hi*\n\n\\-\\- sent by reclaw"]);
+        assert_eq!(telegram.format, ReplyFormat::Telegram);
+
+        let invalid = ReplyProcessing::compile(ReplyProcessingConfig {
+            agents: BTreeMap::from([(
+                "ops".to_owned(),
+                ReplyProcessingRuleConfig {
+                    max_length: Some(0),
+                    ..ReplyProcessingRuleConfig::default()
+                },
+            )]),
+            ..ReplyProcessingConfig::default()
+        });
+        assert!(invalid.is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Secrets kept in an OS keychain or sealed by a TPM instead of plaintext env or config.
+//!
+//! `gatewayTokenSecret` and `hooksTokenSecret` hold a reference that is resolved once at startup:
+//!
+//! - `keychain:<account>` reads the generic password stored for service `reclaw` and `<account>`,
+//!   through `security` on macOS and `secret-tool` (libsecret) elsewhere. Needs the `keychain`
+//!   feature.
+//! - `tpm:<path>` decrypts a credential sealed with `systemd-creds encrypt --with-key=tpm2`, and
+//!   `tpm:<name>` reads a credential systemd already unsealed into `$CREDENTIALS_DIRECTORY` for a
+//!   unit with `LoadCredentialEncrypted=<name>:...`. Needs the `tpm` feature.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+/// Keychain service the `keychain:` accounts are looked up under.
+pub const KEYCHAIN_SERVICE: &str = "reclaw";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretRef {
+    Keychain { account: String },
+    Tpm { credential: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretSource {
+    Unset,
+    Plaintext,
+    Keychain,
+    Tpm,
+}
+
+impl SecretSource {
+    /// Whether the secret is sealed by hardware rather than by software or not at all.
+    #[must_use]
+    pub fn is_hardware_backed(self) -> bool {
+        self == Self::Tpm
+    }
+}
+
+impl SecretRef {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        let (scheme, target) = raw.split_once(':').ok_or_else(|| {
+            format!("secret reference {raw:?} must be keychain:<account> or tpm:<credential>")
+        })?;
+        let target = target.trim();
+        if target.is_empty() {
+            return Err(format!("secret reference {raw:?} names no {scheme} entry"));
+        }
+        match scheme {
+            "keychain" => Ok(Self::Keychain {
+                account: target.to_owned(),
+            }),
+            "tpm" => Ok(Self::Tpm {
+                credential: target.to_owned(),
+            }),
+            _ => Err(format!(
+                "secret reference {raw:?} uses unknown backend {scheme:?}; expected keychain or tpm"
+            )),
+        }
+    }
+
+    #[must_use]
+    pub fn source(&self) -> SecretSource {
+        match self {
+            Self::Keychain { .. } => SecretSource::Keychain,
+            Self::Tpm { .. } => SecretSource::Tpm,
+        }
+    }
+
+    /// The reference as written in config; it names the entry, never the secret.
+    #[must_use]
+    pub fn label(&self) -> String {
+        match self {
+            Self::Keychain { account } => format!("keychain:{account}"),
+            Self::Tpm { credential } => format!("tpm:{credential}"),
+        }
+    }
+
+    /// Loads the secret. `credentials_dir` is `$CREDENTIALS_DIRECTORY` for `tpm:<name>`.
+    pub fn load(&self, credentials_dir: Option<&Path>) -> Result<String, String> {
+        let secret = match self {
+            Self::Keychain { account } => load_keychain(account)?,
+            Self::Tpm { credential } => load_tpm(credential, credentials_dir)?,
+        };
+        let secret = secret.trim_end_matches(['\r', '\n']).to_owned();
+        if secret.is_empty() {
+            return Err(format!("{} is empty", self.label()));
+        }
+        Ok(secret)
+    }
+}
+
+/// Resolves one secret setting from its plaintext value or its `*Secret` reference, which are
+/// mutually exclusive. Returns the secret and, when it came from a reference, that reference.
+pub fn resolve(
+    key: &str,
+    plaintext: Option<String>,
+    reference: Option<String>,
+) -> Result<(Option<String>, Option<SecretRef>), String> {
+    let reference = reference
+        .map(|raw| raw.trim().to_owned())
+        .filter(|raw| !raw.is_empty());
+    let Some(reference) = reference else {
+        return Ok((plaintext, None));
+    };
+    if plaintext.is_some() {
+        return Err(format!("set either {key} or {key}Secret, not both"));
+    }
+    let reference =
+        SecretRef::parse(&reference).map_err(|error| format!("{key}Secret: {error}"))?;
+    let credentials_dir = std::env::var_os("CREDENTIALS_DIRECTORY");
+    let secret = reference
+        .load(credentials_dir.as_deref().map(Path::new))
+        .map_err(|error| format!("failed to load {key}Secret: {error}"))?;
+    Ok((Some(secret), Some(reference)))
+}
+
+#[cfg(feature = "keychain")]
+fn load_keychain(account: &str) -> Result<String, String> {
+    #[cfg(target_os = "macos")]
+    let (program, args) = (
+        "security",
+        vec![
+            "find-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            account,
+            "-w",
+        ],
+    );
+    #[cfg(not(target_os = "macos"))]
+    let (program, args) = (
+        "secret-tool",
+        vec!["lookup", "service", KEYCHAIN_SERVICE, "account", account],
+    );
+    run_command(program, &args)
+}
+
+#[cfg(not(feature = "keychain"))]
+fn load_keychain(_account: &str) -> Result<String, String> {
+    Err("keychain secrets need a build with the `keychain` feature".to_owned())
+}
+
+#[cfg(feature = "tpm")]
+fn load_tpm(credential: &str, credentials_dir: Option<&Path>) -> Result<String, String> {
+    if !credential.contains('/') {
+        let dir = credentials_dir.ok_or_else(|| {
+            format!(
+                "tpm:{credential} needs $CREDENTIALS_DIRECTORY; load it with LoadCredentialEncrypted= or give a path"
+            )
+        })?;
+        let path = dir.join(credential);
+        return std::fs::read_to_string(&path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()));
+    }
+    run_command("systemd-creds", &["decrypt", "--name=", credential, "-"])
+}
+
+#[cfg(not(feature = "tpm"))]
+fn load_tpm(_credential: &str, _credentials_dir: Option<&Path>) -> Result<String, String> {
+    Err("tpm secrets need a build with the `tpm` feature".to_owned())
+}
+
+#[cfg(any(feature = "keychain", feature = "tpm"))]
+fn run_command(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|error| format!("failed to run {program}: {error}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+        return Err(format!("{program} exited with {}: {stderr}", output.status));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{program} returned a non-UTF-8 secret"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SecretRef, SecretSource, resolve};
+
+    #[test]
+    fn secret_references_parse_and_conflict_with_plaintext() {
+        assert_eq!(
+            SecretRef::parse("keychain:gateway-token"),
+            Ok(SecretRef::Keychain {
+                account: "gateway-token".to_owned()
+            })
+        );
+        let tpm = SecretRef::parse(" tpm:/etc/reclaw/hooks.cred ").expect("tpm ref should parse");
+        assert_eq!(tpm.label(), "tpm:/etc/reclaw/hooks.cred");
+        assert!(tpm.source().is_hardware_backed());
+        assert!(!SecretSource::Keychain.is_hardware_backed());
+        assert!(SecretRef::parse("vault:token").is_err());
+        assert!(SecretRef::parse("keychain:").is_err());
+        assert!(SecretRef::parse("plain").is_err());
+
+        assert_eq!(
+            resolve("gatewayToken", Some("plain".to_owned()), None),
+            Ok((Some("plain".to_owned()), None))
+        );
+        assert!(
+            resolve(
+                "gatewayToken",
+                Some("plain".to_owned()),
+                Some("tpm:token".to_owned())
+            )
+            .is_err()
+        );
+    }
+
+    #[cfg(feature = "tpm")]
+    #[test]
+    fn tpm_credentials_load_from_the_credentials_directory() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        std::fs::write(temp.path().join("gateway-token"), "sealed-secret\n")
+            .expect("credential should write");
+
+        let reference = SecretRef::parse("tpm:gateway-token").expect("ref should parse");
+        assert_eq!(
+            reference.load(Some(temp.path())),
+            Ok("sealed-secret".to_owned())
+        );
+        assert!(reference.load(None).is_err());
+        assert!(
+            SecretRef::parse("tpm:missing")
+                .expect("ref should parse")
+                .load(Some(temp.path()))
+                .is_err()
+        );
+    }
+}
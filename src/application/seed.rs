@@ -0,0 +1,205 @@
+use serde_json::{Value, json};
+use tracing::info;
+
+use crate::{
+    application::{
+        config::{SeedAgentConfig, SeedCronJobConfig},
+        state::SharedState,
+    },
+    domain::{
+        error::DomainError,
+        models::{CronJobRecord, CronPayload, CronSchedule},
+    },
+    rpc::methods::{agents, cron},
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SeedCounts {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SeedReport {
+    pub agents: SeedCounts,
+    pub cron_jobs: SeedCounts,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SeedOutcome {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+impl SeedCounts {
+    fn record(&mut self, outcome: SeedOutcome) {
+        match outcome {
+            SeedOutcome::Created => self.created += 1,
+            SeedOutcome::Updated => self.updated += 1,
+            SeedOutcome::Unchanged => self.unchanged += 1,
+        }
+    }
+}
+
+/// Brings agents and cron jobs in line with the `[seed]` config. Entries are matched by agent id
+/// (derived from the name) and cron job id, created when missing and patched only when a declared
+/// field differs, so restarts are no-ops. Runtime state not named in the seed is left alone.
+pub async fn apply_seed(state: &SharedState) -> Result<SeedReport, DomainError> {
+    let seed = state.config().seed.clone();
+    let mut report = SeedReport::default();
+
+    for agent in &seed.agents {
+        let outcome = seed_agent(state, agent).await.map_err(|error| {
+            DomainError::InvalidRequest(format!("seed agent {}: {}", agent.name, error.message))
+        })?;
+        report.agents.record(outcome);
+    }
+    for job in &seed.cron_jobs {
+        let outcome = seed_cron_job(state, job).await.map_err(|error| {
+            DomainError::InvalidRequest(format!("seed cron job {}: {}", job.id, error.message))
+        })?;
+        report.cron_jobs.record(outcome);
+    }
+
+    if !seed.agents.is_empty() || !seed.cron_jobs.is_empty() {
+        info!(
+            "seed applied agents={:?} cron_jobs={:?}",
+            report.agents, report.cron_jobs
+        );
+    }
+    Ok(report)
+}
+
+async fn seed_agent(
+    state: &SharedState,
+    seed: &SeedAgentConfig,
+) -> Result<SeedOutcome, crate::protocol::ErrorShape> {
+    let agent_id = agents::normalize_agent_id(seed.name.trim());
+    let existing = agents::load_agents(state)
+        .await?
+        .into_iter()
+        .find(|agent| agent.agent_id == agent_id);
+
+    let Some(existing) = existing else {
+        agents::handle_create(
+            state,
+            Some(&json!({
+                "name": seed.name.trim(),
+                "workspace": seed.workspace,
+                "model": seed.model,
+                "avatar": seed.avatar,
+                "emoji": seed.emoji,
+            })),
+        )
+        .await?;
+        return Ok(SeedOutcome::Created);
+    };
+
+    let mut patch = serde_json::Map::new();
+    if existing.name != seed.name.trim() {
+        patch.insert("name".to_owned(), json!(seed.name.trim()));
+    }
+    if seed.model.is_some() && existing.model != seed.model {
+        patch.insert("model".to_owned(), json!(seed.model));
+    }
+    if seed.avatar.is_some() && existing.avatar != seed.avatar {
+        patch.insert("avatar".to_owned(), json!(seed.avatar));
+    }
+    if let Some(workspace) = seed.workspace.as_deref() {
+        let resolved = agents::resolve_workspace_path(state, Some(workspace), &agent_id);
+        if existing.workspace != resolved.display().to_string() {
+            patch.insert("workspace".to_owned(), json!(workspace));
+        }
+    }
+    if let Some(emoji) = seed.emoji.as_deref().map(str::trim)
+        && existing.emoji.as_deref().unwrap_or_default() != emoji
+    {
+        patch.insert("emoji".to_owned(), json!(emoji));
+    }
+    if patch.is_empty() {
+        return Ok(SeedOutcome::Unchanged);
+    }
+    patch.insert("agentId".to_owned(), json!(agent_id));
+    agents::handle_update(state, Some(&Value::Object(patch))).await?;
+    Ok(SeedOutcome::Updated)
+}
+
+async fn seed_cron_job(
+    state: &SharedState,
+    seed: &SeedCronJobConfig,
+) -> Result<SeedOutcome, crate::protocol::ErrorShape> {
+    let id = seed.id.trim();
+    let name = seed
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map_or_else(|| format!("Cron {id}"), ToOwned::to_owned);
+    let metadata = seed.metadata.clone().unwrap_or_else(|| json!({}));
+
+    let existing = state
+        .get_cron_job(id)
+        .await
+        .map_err(crate::rpc::dispatcher::map_domain_error)?;
+    let Some(existing) = existing else {
+        cron::handle_add(
+            state,
+            Some(&json!({
+                "id": id,
+                "name": name,
+                "enabled": seed.enabled,
+                "schedule": seed.schedule,
+                "payload": seed.payload,
+                "metadata": metadata,
+            })),
+        )
+        .await?;
+        return Ok(SeedOutcome::Created);
+    };
+
+    if cron_job_matches(&existing, &name, seed, &metadata)? {
+        return Ok(SeedOutcome::Unchanged);
+    }
+    cron::handle_update(
+        state,
+        Some(&json!({
+            "id": id,
+            "patch": {
+                "name": name,
+                "enabled": seed.enabled,
+                "schedule": seed.schedule,
+                "payload": seed.payload,
+                "metadata": metadata,
+            },
+        })),
+    )
+    .await?;
+    Ok(SeedOutcome::Updated)
+}
+
+/// Compares through the typed records so omitted optional fields don't count as drift.
+fn cron_job_matches(
+    existing: &CronJobRecord,
+    name: &str,
+    seed: &SeedCronJobConfig,
+    metadata: &Value,
+) -> Result<bool, crate::protocol::ErrorShape> {
+    let invalid = |field: &str, error: serde_json::Error| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid {field}: {error}"),
+        )
+    };
+    let schedule = serde_json::from_value::<CronSchedule>(seed.schedule.clone())
+        .map_err(|error| invalid("schedule", error))?;
+    let payload = serde_json::from_value::<CronPayload>(seed.payload.clone())
+        .map_err(|error| invalid("payload", error))?;
+
+    Ok(existing.name == name
+        && existing.enabled == seed.enabled
+        && json!(existing.schedule) == json!(schedule)
+        && json!(existing.payload) == json!(payload)
+        && &existing.metadata == metadata)
+}
@@ -0,0 +1,174 @@
+use std::path::Path;
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{
+    application::{
+        config::{GuardrailAction, ResourceGuardrails},
+        state::SharedState,
+    },
+    storage::now_unix_ms,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceSample {
+    pub rss_bytes: Option<u64>,
+    pub open_fds: Option<u64>,
+    pub tokio_tasks: Option<u64>,
+    pub db_bytes: Option<u64>,
+    pub sampled_at_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardrailBreach {
+    pub metric: &'static str,
+    pub value: u64,
+    pub limit: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceStatus {
+    pub sample: ResourceSample,
+    pub breaches: Vec<GuardrailBreach>,
+}
+
+impl ResourceStatus {
+    #[must_use]
+    pub fn is_breached(&self) -> bool {
+        !self.breaches.is_empty()
+    }
+}
+
+#[must_use]
+pub fn sample_resources(db_path: &Path) -> ResourceSample {
+    ResourceSample {
+        rss_bytes: read_rss_bytes(),
+        open_fds: count_open_fds(),
+        tokio_tasks: tokio::runtime::Handle::try_current()
+            .ok()
+            .and_then(|handle| u64::try_from(handle.metrics().num_alive_tasks()).ok()),
+        db_bytes: database_bytes(db_path),
+        sampled_at_ms: now_unix_ms(),
+    }
+}
+
+#[must_use]
+pub fn evaluate_guardrails(
+    guardrails: &ResourceGuardrails,
+    sample: &ResourceSample,
+) -> Vec<GuardrailBreach> {
+    [
+        ("rssBytes", sample.rss_bytes, guardrails.max_rss_bytes),
+        ("openFds", sample.open_fds, guardrails.max_open_fds),
+        ("tokioTasks", sample.tokio_tasks, guardrails.max_tasks),
+        ("dbBytes", sample.db_bytes, guardrails.max_db_bytes),
+    ]
+    .into_iter()
+    .filter_map(|(metric, value, limit)| match (value, limit) {
+        (Some(value), Some(limit)) if value > limit => Some(GuardrailBreach {
+            metric,
+            value,
+            limit,
+        }),
+        _ => None,
+    })
+    .collect()
+}
+
+/// Samples resource usage, evaluates guardrails, and records the result on the shared state.
+pub async fn refresh_resource_status(state: &SharedState) -> ResourceStatus {
+    let sample = sample_resources(&state.config().db_path);
+    let breaches = evaluate_guardrails(&state.config().guardrails, &sample);
+    let status = ResourceStatus { sample, breaches };
+    let previous = state.record_resource_status(status.clone()).await;
+
+    if state.config().guardrails.has_action(GuardrailAction::Log) {
+        let was_breached = previous.as_ref().is_some_and(ResourceStatus::is_breached);
+        if status.is_breached() && !was_breached {
+            warn!("resource guardrails exceeded: {:?}", status.breaches);
+        } else if !status.is_breached() && was_breached {
+            info!("resource usage back within guardrails");
+        }
+    }
+
+    status
+}
+
+pub fn spawn_self_monitor(state: SharedState) -> Option<tokio::task::JoinHandle<()>> {
+    if !state.config().self_monitor_enabled {
+        info!("self monitor disabled by runtime config");
+        return None;
+    }
+
+    let interval = state.config().self_monitor_interval;
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let _ = refresh_resource_status(&state).await;
+        }
+    }))
+}
+
+fn read_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib.saturating_mul(1024))
+}
+
+fn count_open_fds() -> Option<u64> {
+    let entries = std::fs::read_dir("/proc/self/fd").ok()?;
+    u64::try_from(entries.count()).ok()
+}
+
+fn database_bytes(db_path: &Path) -> Option<u64> {
+    let main = std::fs::metadata(db_path).ok()?.len();
+    let wal_path = db_path.with_extension(
+        db_path
+            .extension()
+            .and_then(|value| value.to_str())
+            .map_or_else(|| "wal".to_owned(), |ext| format!("{ext}-wal")),
+    );
+    let wal = std::fs::metadata(wal_path).map_or(0, |meta| meta.len());
+    Some(main.saturating_add(wal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResourceSample, evaluate_guardrails};
+    use crate::application::config::ResourceGuardrails;
+
+    #[test]
+    fn evaluate_guardrails_reports_only_configured_breaches() {
+        let guardrails = ResourceGuardrails {
+            max_rss_bytes: Some(1_000),
+            max_open_fds: Some(64),
+            max_tasks: None,
+            max_db_bytes: Some(10),
+            ..ResourceGuardrails::default()
+        };
+        let sample = ResourceSample {
+            rss_bytes: Some(2_000),
+            open_fds: Some(12),
+            tokio_tasks: Some(9_999),
+            db_bytes: None,
+            sampled_at_ms: 1,
+        };
+
+        let breaches = evaluate_guardrails(&guardrails, &sample);
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].metric, "rssBytes");
+        assert_eq!(breaches[0].limit, 1_000);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use serde_json::Value;
+use tokio::{net::TcpListener, sync::oneshot, task::JoinHandle};
+
+use crate::{
+    application::{
+        agent_backend::AgentBackend, chat_commands::ChatCommand, config::RuntimeConfig,
+        startup::serve_state, state::SharedState, translator::Translator,
+    },
+    domain::error::DomainError,
+    interfaces::webhooks::{self, ChannelWebhookRegistry},
+    rpc::{
+        methods::{known_events, known_methods},
+        middleware::DispatchHook,
+    },
+};
+
+/// Programmatic entry point for embedding the gateway in another binary.
+///
+/// Unlike `startup::run`, the builder does not install a tracing subscriber or listen for
+/// process signals; the embedder owns both and stops the server through [`ServerHandle`].
+pub struct ServerBuilder {
+    config: RuntimeConfig,
+    listener: Option<TcpListener>,
+    webhook_registry: ChannelWebhookRegistry,
+    agent_backend: Option<Arc<dyn AgentBackend>>,
+    replay_backends: Vec<Arc<dyn AgentBackend>>,
+    translator: Option<Arc<dyn Translator>>,
+    dispatch_hooks: Vec<Arc<dyn DispatchHook>>,
+    chat_commands: Vec<Arc<dyn ChatCommand>>,
+}
+
+impl ServerBuilder {
+    #[must_use]
+    pub fn new(config: RuntimeConfig) -> Self {
+        Self {
+            config,
+            listener: None,
+            webhook_registry: webhooks::default_registry(),
+            agent_backend: None,
+            replay_backends: Vec::new(),
+            translator: None,
+            dispatch_hooks: Vec::new(),
+            chat_commands: Vec::new(),
+        }
+    }
+
+    /// Overrides the SQLite database path from the config.
+    #[must_use]
+    pub fn db_path(mut self, db_path: impl Into<PathBuf>) -> Self {
+        self.config.db_path = db_path.into();
+        self
+    }
+
+    /// Serves on an already-bound listener instead of binding `config.bind_addr()`.
+    #[must_use]
+    pub fn listener(mut self, listener: TcpListener) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
+    /// Replaces the built-in channel webhook adapters.
+    #[must_use]
+    pub fn webhook_registry(mut self, registry: ChannelWebhookRegistry) -> Self {
+        self.webhook_registry = registry;
+        self
+    }
+
+    /// Replaces the built-in echo backend for `agent` runs and `chat.send`.
+    #[must_use]
+    pub fn agent_backend(mut self, backend: Arc<dyn AgentBackend>) -> Self {
+        self.agent_backend = Some(backend);
+        self
+    }
+
+    /// Registers an extra backend that `agent.replay` can select by name.
+    #[must_use]
+    pub fn replay_backend(mut self, backend: Arc<dyn AgentBackend>) -> Self {
+        self.replay_backends.push(backend);
+        self
+    }
+
+    /// Replaces the translator configured by `translationUrl` for `chat.send` turns.
+    #[must_use]
+    pub fn translator(mut self, translator: Arc<dyn Translator>) -> Self {
+        self.translator = Some(translator);
+        self
+    }
+
+    #[must_use]
+    pub fn dispatch_hook(mut self, hook: Arc<dyn DispatchHook>) -> Self {
+        self.dispatch_hooks.push(hook);
+        self
+    }
+
+    /// Adds a slash command for channel conversations, replacing a built-in one of the same
+    /// name. Commands are only answered where `chatCommands` enables them.
+    #[must_use]
+    pub fn chat_command(mut self, command: Arc<dyn ChatCommand>) -> Self {
+        self.chat_commands.push(command);
+        self
+    }
+
+    /// Opens storage, binds the listener when none was supplied, and starts serving in the
+    /// background.
+    pub async fn start(self) -> Result<ServerHandle, DomainError> {
+        let listener = match self.listener {
+            Some(listener) => listener,
+            None => TcpListener::bind(self.config.bind_addr())
+                .await
+                .map_err(|error| {
+                    DomainError::Unavailable(format!("failed to bind listener: {error}"))
+                })?,
+        };
+        let local_addr = listener.local_addr().map_err(|error| {
+            DomainError::Unavailable(format!("failed to read listener address: {error}"))
+        })?;
+
+        let state = SharedState::new(self.config, known_methods(), known_events()).await?;
+        if let Some(backend) = self.agent_backend {
+            state.set_agent_backend(backend).await;
+        }
+        for backend in self.replay_backends {
+            state.register_agent_backend(backend).await;
+        }
+        if let Some(translator) = self.translator {
+            state.set_translator(translator).await;
+        }
+        for hook in self.dispatch_hooks {
+            state.register_dispatch_hook(hook).await;
+        }
+        for command in self.chat_commands {
+            state.register_chat_command(command).await;
+        }
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let join = tokio::spawn(serve_state(
+            listener,
+            state.clone(),
+            self.webhook_registry,
+            async move {
+                let _ = shutdown_rx.await;
+            },
+        ));
+
+        Ok(ServerHandle {
+            local_addr,
+            state,
+            shutdown: Some(shutdown_tx),
+            join,
+        })
+    }
+}
+
+/// A running embedded server. Dropping the handle also requests graceful shutdown, but only
+/// [`ServerHandle::stop`] waits for it to finish.
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    state: SharedState,
+    shutdown: Option<oneshot::Sender<()>>,
+    join: JoinHandle<Result<(), DomainError>>,
+}
+
+impl ServerHandle {
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    #[must_use]
+    pub fn state(&self) -> &SharedState {
+        &self.state
+    }
+
+    /// Same payload as the `health` RPC.
+    pub async fn health(&self) -> Result<Value, DomainError> {
+        self.state.health_payload().await
+    }
+
+    pub async fn connection_count(&self) -> usize {
+        self.state.connection_count().await
+    }
+
+    /// Requests graceful shutdown and waits for the server and its background tasks to exit.
+    pub async fn stop(mut self) -> Result<(), DomainError> {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        self.wait().await
+    }
+
+    /// Waits for the server to exit without requesting shutdown. `system.restart` also ends an
+    /// embedded server; `state().lifecycle().requested_stop()` tells the embedder to start it
+    /// again.
+    pub async fn wait(self) -> Result<(), DomainError> {
+        self.join
+            .await
+            .map_err(|error| DomainError::Unavailable(format!("server task failed: {error}")))?
+    }
+}
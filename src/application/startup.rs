@@ -1,18 +1,24 @@
-use std::future::Future;
+use std::{future::Future, net::SocketAddr};
 
-use tokio::net::TcpListener;
+use tokio::{net::TcpListener, sync::watch};
 use tracing::{error, info, warn};
 use tracing_subscriber::{EnvFilter, fmt};
 
 use crate::{
     application::{
-        config::{Args, Command, RuntimeConfig},
-        init_config,
+        chat_archive, chat_write_buffer,
+        config::{Args, Command, DbCommand, RuntimeConfig},
+        db_command, federation, init_config,
+        lifecycle::StopKind,
+        log_redaction::{LogRedaction, RedactingMakeWriter},
+        log_shipper, overload, replication, seed, self_monitor, snapshots,
         state::SharedState,
+        systemd, webhook_sources,
     },
     domain::error::DomainError,
-    interfaces::http,
-    rpc::methods::{known_events, known_methods},
+    interfaces::{http, quiet_hours, telegram_webhook, webhooks},
+    rpc::methods::{agents, known_events, known_methods},
+    storage::STORAGE_CUTOVER_KEY,
 };
 
 pub async fn run(args: Args) -> Result<(), DomainError> {
@@ -20,19 +26,54 @@ pub async fn run(args: Args) -> Result<(), DomainError> {
         return match command {
             Command::InitConfig(command_args) => init_config::run(&command_args)
                 .map_err(|error| DomainError::Unavailable(format!("init-config failed: {error}"))),
+            Command::Db(db_args) => match db_args.command {
+                DbCommand::MigrateTo(command_args) => {
+                    db_command::migrate_to(&args, &command_args).await
+                }
+            },
         };
     }
 
-    let config = RuntimeConfig::from_args(args)
+    let config = RuntimeConfig::from_args(args.clone())
         .map_err(|error| DomainError::InvalidRequest(format!("configuration error: {error}")))?;
 
-    init_logging(&config.log_filter, config.json_logs)?;
-    let listener = TcpListener::bind(config.bind_addr())
-        .await
-        .map_err(|error| DomainError::Unavailable(format!("failed to bind listener: {error}")))?;
+    init_logging(
+        &config.log_filter,
+        config.json_logs,
+        config.log_redaction.clone(),
+    )?;
+    let listener = match systemd::activated_listener().map_err(DomainError::Unavailable)? {
+        Some(listener) => TcpListener::from_std(listener).map_err(|error| {
+            DomainError::Unavailable(format!(
+                "failed to adopt socket-activated listener: {error}"
+            ))
+        })?,
+        None => TcpListener::bind(config.bind_addr())
+            .await
+            .map_err(|error| {
+                DomainError::Unavailable(format!("failed to bind listener: {error}"))
+            })?,
+    };
+    let local_addr = listener.local_addr().map_err(|error| {
+        DomainError::Unavailable(format!("failed to read listener address: {error}"))
+    })?;
+    info!(
+        "starting reclaw-core addr={local_addr} auth_mode={}",
+        config.auth_mode.label()
+    );
 
-    let signal = shutdown_signal();
-    run_with_listener(listener, config, signal).await
+    serve_with_restarts(
+        listener,
+        config,
+        || {
+            RuntimeConfig::from_args(args.clone()).map_err(|error| {
+                DomainError::InvalidRequest(format!("configuration error on restart: {error}"))
+            })
+        },
+        Some(local_addr),
+        shutdown_signal(),
+    )
+    .await
 }
 
 pub async fn run_with_listener(
@@ -47,9 +88,137 @@ pub async fn run_with_listener(
         config.auth_mode.label()
     );
 
-    let state = SharedState::new(config, known_methods(), known_events()).await?;
+    let restart_config = config.clone();
+    serve_with_restarts(
+        listener,
+        config,
+        || Ok(restart_config.clone()),
+        None,
+        shutdown,
+    )
+    .await
+}
+
+/// Serves `config` until `shutdown` resolves or `system.shutdown` runs. `system.restart` rebuilds
+/// the state from `reload_config` and serves again on the same listening socket, so clients only
+/// need to reconnect. `notify_addr` enables the systemd readiness notifier.
+async fn serve_with_restarts(
+    listener: TcpListener,
+    mut config: RuntimeConfig,
+    mut reload_config: impl FnMut() -> Result<RuntimeConfig, DomainError>,
+    notify_addr: Option<SocketAddr>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), DomainError> {
+    let listener = listener
+        .into_std()
+        .map_err(|error| DomainError::Unavailable(format!("failed to detach listener: {error}")))?;
+    // Outlives each serve loop, so a signal during a restart still stops the process.
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let shutdown_task = tokio::spawn(async move {
+        shutdown.await;
+        let _ = shutdown_tx.send(true);
+    });
+
+    let result = loop {
+        let serve_listener = match listener.try_clone().and_then(TcpListener::from_std) {
+            Ok(serve_listener) => serve_listener,
+            Err(error) => {
+                break Err(DomainError::Unavailable(format!(
+                    "failed to reuse listener: {error}"
+                )));
+            }
+        };
+        let state = match SharedState::new(config, known_methods(), known_events()).await {
+            Ok(state) => state,
+            Err(error) => break Err(error),
+        };
+        let notifier_task =
+            notify_addr.and_then(|addr| systemd::spawn_notifier(state.clone(), addr));
+        let mut serve_shutdown = shutdown_rx.clone();
+        let served = serve_state(
+            serve_listener,
+            state.clone(),
+            webhooks::default_registry(),
+            async move {
+                let _ = serve_shutdown.wait_for(|stopped| *stopped).await;
+            },
+        )
+        .await;
+        if let Some(task) = notifier_task {
+            task.abort();
+            let _ = task.await;
+        }
+        if let Err(error) = served {
+            break Err(error);
+        }
+        if state.lifecycle().requested_stop() != Some(StopKind::Restart) || *shutdown_rx.borrow() {
+            break Ok(());
+        }
+        drop(state);
+        info!("restarting reclaw-core in place");
+        if notify_addr.is_some() {
+            systemd::notify("RELOADING=1");
+        }
+        config = match reload_config() {
+            Ok(config) => config,
+            Err(error) => break Err(error),
+        };
+    };
+
+    shutdown_task.abort();
+    let _ = shutdown_task.await;
+    if notify_addr.is_some() {
+        systemd::notify("STOPPING=1");
+    }
+    result
+}
+
+/// Runs the background tasks (chat write flusher, cron, self-monitor, quiet-hours flusher, log
+/// shipper, webhook source range refresh, chat archiver, snapshot publisher, replicator)
+/// alongside the HTTP/WS server for an already-built state, stopping them once the server shuts
+/// down. Buffered chat writes are flushed last.
+pub(crate) async fn serve_state(
+    listener: TcpListener,
+    state: SharedState,
+    webhook_registry: webhooks::ChannelWebhookRegistry,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), DomainError> {
+    if let Ok(Some(cutover)) = state.get_config_entry_value(STORAGE_CUTOVER_KEY).await {
+        warn!(
+            "sqlite store was cut over to postgres (target={}); writes here are no longer migrated",
+            cutover
+                .get("target")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or("unknown")
+        );
+    }
+
+    seed::apply_seed(&state).await?;
+    replication::check_superseded_on_start(&state).await;
+
+    let chat_write_task = chat_write_buffer::spawn_chat_write_flusher(state.clone());
     let cron_task = spawn_cron_scheduler(state.clone());
-    let serve_result = http::serve(listener, state, shutdown).await;
+    let memory_task = spawn_memory_rotator(state.clone());
+    let monitor_task = self_monitor::spawn_self_monitor(state.clone());
+    let quiet_hours_task = quiet_hours::spawn_outbound_flusher(state.clone());
+    let log_shipper_task = log_shipper::spawn_log_shipper(state.clone());
+    let source_ranges_task = webhook_sources::spawn_source_range_refresher(state.clone());
+    let chat_archive_task = chat_archive::spawn_chat_archiver(state.clone());
+    let snapshot_task = snapshots::spawn_snapshot_publisher(state.clone());
+    let overload_task = overload::spawn_overload_detector(state.clone());
+    let federation_task = federation::spawn_peer_health_monitor(state.clone());
+    let replication_task = replication::spawn_replicator(state.clone());
+    let telegram_webhook_task = telegram_webhook::spawn_webhook_manager(state.clone());
+    let stop_state = state.clone();
+    let shutdown = async move {
+        tokio::select! {
+            () = shutdown => stop_state.lifecycle().request_stop(StopKind::Shutdown),
+            _ = stop_state.lifecycle().stopped() => {}
+        }
+    };
+    let serve_result =
+        http::serve_with_webhooks(listener, state.clone(), webhook_registry, shutdown).await;
+    state.lifecycle().abandon_scheduled().await;
 
     if let Some(task) = cron_task {
         task.abort();
@@ -57,13 +226,64 @@ pub async fn run_with_listener(
             warn!("cron scheduler task aborted: {error}");
         }
     }
+    memory_task.abort();
+    let _ = memory_task.await;
+    if let Some(task) = monitor_task {
+        task.abort();
+        let _ = task.await;
+    }
+    if let Some(task) = quiet_hours_task {
+        task.abort();
+        let _ = task.await;
+    }
+    if let Some(task) = log_shipper_task {
+        task.abort();
+        let _ = task.await;
+    }
+    if let Some(task) = source_ranges_task {
+        task.abort();
+        let _ = task.await;
+    }
+    if let Some(task) = chat_archive_task {
+        task.abort();
+        let _ = task.await;
+    }
+    if let Some(task) = snapshot_task {
+        task.abort();
+        let _ = task.await;
+    }
+    if let Some(task) = overload_task {
+        task.abort();
+        let _ = task.await;
+    }
+    if let Some(task) = federation_task {
+        task.abort();
+        let _ = task.await;
+    }
+    if let Some(task) = replication_task {
+        task.abort();
+        let _ = task.await;
+    }
+    if let Some(task) = telegram_webhook_task {
+        task.abort();
+        let _ = task.await;
+        telegram_webhook::unregister_webhook(&state).await;
+    }
+    if let Some(task) = chat_write_task {
+        task.abort();
+        let _ = task.await;
+    }
+    state.close_chat_writes().await;
 
     serve_result
 }
 
-fn init_logging(filter: &str, json_logs: bool) -> Result<(), DomainError> {
+fn init_logging(filter: &str, json_logs: bool, redaction: LogRedaction) -> Result<(), DomainError> {
     let env_filter = EnvFilter::try_new(filter).unwrap_or_else(|_| EnvFilter::new("info"));
-    let builder = fmt().with_env_filter(env_filter).with_target(false);
+    let builder = fmt()
+        .with_env_filter(env_filter)
+        .with_target(false)
+        .with_writer(RedactingMakeWriter::new(redaction));
 
     if json_logs {
         builder.json().try_init().map_err(|error| {
@@ -89,6 +309,9 @@ fn spawn_cron_scheduler(state: SharedState) -> Option<tokio::task::JoinHandle<()
         let mut ticker = tokio::time::interval(poll_interval);
         loop {
             ticker.tick().await;
+            if state.is_standby() || state.lifecycle().rejection().await.is_some() {
+                continue;
+            }
             if let Err(error) = state.tick_cron_jobs().await {
                 error!("cron tick failed: {error}");
             }
@@ -96,7 +319,43 @@ fn spawn_cron_scheduler(state: SharedState) -> Option<tokio::task::JoinHandle<()
     }))
 }
 
+/// Periodically rotates agent memory files that grew past `memoryMaxBytes` outside the RPCs.
+fn spawn_memory_rotator(state: SharedState) -> tokio::task::JoinHandle<()> {
+    let interval = state.config().memory_rotation_interval;
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if state.is_standby() {
+                continue;
+            }
+            if let Err(error) = agents::rotate_agent_memory_files(&state).await {
+                warn!("memory file rotation failed: {}", error.message);
+            }
+        }
+    })
+}
+
+/// Resolves on Ctrl-C or, on unix, on the SIGTERM systemd sends to stop the service.
 async fn shutdown_signal() {
-    let _ = tokio::signal::ctrl_c().await;
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(error) => {
+                warn!("failed to install SIGTERM handler: {error}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        () = terminate => {}
+    }
     info!("shutdown signal received");
 }
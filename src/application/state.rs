@@ -1,29 +1,66 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::Path,
     sync::{
         Arc,
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     },
     time::{Duration, Instant},
 };
 
 use serde_json::{Map, Value, json};
 use tokio::sync::RwLock;
-use tokio::sync::mpsc::{Receiver, Sender, channel, error::TrySendError};
+use tokio::sync::mpsc::{Receiver, Sender, channel, error::TrySendError, unbounded_channel};
+use tokio::sync::{Mutex, MutexGuard, OwnedSemaphorePermit, Semaphore, oneshot};
+use tracing::warn;
 
 use crate::{
-    application::{config::RuntimeConfig, cron_schedule::compute_next_run_ms},
+    application::{
+        agent_backend::{AgentBackend, EchoAgentBackend},
+        chat_archive,
+        chat_commands::{ChatCommand, ChatCommandRegistry},
+        chat_write_buffer::{self, ChatWriteBuffer},
+        config::{
+            ConnectionLimitAction, ConnectionLimits, ContentAction, GuardrailAction,
+            HookOverflowAction, RuntimeConfig,
+        },
+        cron_schedule::{compute_next_run_ms, describe_job, local_next_run},
+        cron_script,
+        lifecycle::Lifecycle,
+        log_shipper::{self, LogShipStatus},
+        overload::OverloadStatus,
+        replication::{self, ReplicationStatus},
+        self_monitor::ResourceStatus,
+        snapshots,
+        translator::{HttpTranslator, Translator},
+    },
     domain::{
         error::DomainError,
         models::{
-            AgentRunRecord, ChatMessage, ConfigEntry, CronJobPatch, CronJobRecord, CronRunRecord,
-            NodeEventRecord, NodeInvokeInput, NodeInvokeRecord, NodePairRequestInput,
-            NodePairRequestRecord, NodeRecord, SessionRecord,
+            AgentRunRecord, AgentUsageRecord, ChannelDirectoryEntry, ChannelDirectoryInput,
+            ChatArchiveSegment, ChatMessage, ChatReadMarker, ConfigEntry, CronJobPatch,
+            CronJobRecord, CronOutputChunk, CronRunQuery, CronRunRecord, CronRunStats,
+            DeliveryStatus, GatewayLogEntry, GatewayLogQuery, IdentityLinkInput, JournaledEvent,
+            LogShipment, MessageDelivery, NodeEventRecord, NodeFileTransfer, NodeInvokeInput,
+            NodeInvokeRecord, NodeMetadataEntry, NodePairRequestInput, NodePairRequestRecord,
+            NodeRecord, PersonRecord, PrivacyAuditRecord, QueuedNodeInvoke, QueuedOutboundMessage,
+            ReplicatedChange, ReplicationState, RunCostScope, SessionKvEntry, SessionPurgeCounts,
+            SessionRecord, ToolCallRecord, ToolDefinition, ToolGrant, WizardSession,
         },
+        session_key::canonicalize_session_key,
+    },
+    protocol::{ClientFeatures, PresenceEntry, Snapshot, StateVersion},
+    rpc::{
+        SessionContext,
+        methods::agent,
+        middleware::{DispatchHook, DispatchHookRegistry},
+        policy,
     },
-    protocol::{PresenceEntry, Snapshot, StateVersion},
     security::rate_limit::AuthRateLimiter,
-    storage::{SqliteStore, now_unix_ms},
+    storage::{
+        IdempotencyClaim, MigrationProgress, PostgresMigrationOptions, PostgresMigrationReport,
+        QueryMetrics, RedisBackend, SqliteStore, now_unix_ms,
+    },
 };
 
 #[derive(Clone)]
@@ -40,11 +77,60 @@ struct InnerState {
     clients: RwLock<HashMap<String, ConnectedClient>>,
     auth_rate_limiter: AuthRateLimiter,
     control_plane_rate_limiter: AuthRateLimiter,
+    api_key_rate_limiter: AuthRateLimiter,
+    public_status_rate_limiter: AuthRateLimiter,
+    /// Last `/status/public` payload and when it was built.
+    public_status_cache: RwLock<Option<(Instant, Value)>>,
+    redis: Option<RedisBackend>,
     presence_version: AtomicU64,
+    /// When the last operator connection closed; startup until one connects.
+    operator_last_seen_ms: AtomicU64,
     health_version: AtomicU64,
     gateway_event_subscribers: RwLock<HashMap<String, Sender<GatewayEventEnvelope>>>,
+    /// Broadcast filters set with `events.subscribe`, by connection id.
+    gateway_event_filters: RwLock<HashMap<String, GatewayEventFilter>>,
+    connection_evictors: RwLock<HashMap<String, oneshot::Sender<String>>>,
+    connection_evictions: AtomicU64,
+    gateway_log_appends: AtomicU64,
+    connection_rejections: AtomicU64,
     cron_enabled: RwLock<bool>,
     cron_last_tick_ms: RwLock<Option<u64>>,
+    cron_live_runs: RwLock<HashMap<String, LiveCronRun>>,
+    cron_workers: Arc<Semaphore>,
+    cron_queued: AtomicUsize,
+    /// Queued or executing runs per job id, checked against each job's `maxConcurrent`.
+    cron_job_runs: RwLock<HashMap<String, u32>>,
+    /// How far behind schedule the most overdue job was at the last tick.
+    cron_scheduler_lag_ms: AtomicU64,
+    dispatch_hooks: RwLock<DispatchHookRegistry>,
+    chat_commands: RwLock<ChatCommandRegistry>,
+    chat_writes: Option<ChatWriteBuffer>,
+    /// Pull transfers with a chunk being written, so a second upload to one is refused.
+    node_file_uploads: RwLock<HashSet<String>>,
+    /// Serializes read-modify-write cycles of the stored device state.
+    device_state_writes: Mutex<()>,
+    /// Nonces of signed federation requests seen within the clock-skew window, by
+    /// `<peerId>:<nonce>`, with the time each can be forgotten.
+    federation_nonces: RwLock<HashMap<String, u64>>,
+    agent_backend: RwLock<Arc<dyn AgentBackend>>,
+    /// Every backend seen by name, so `agent.replay` can target one that is no longer active.
+    agent_backends: RwLock<HashMap<String, Arc<dyn AgentBackend>>>,
+    translator: RwLock<Option<Arc<dyn Translator>>>,
+    resource_status: RwLock<Option<ResourceStatus>>,
+    overload_status: RwLock<OverloadStatus>,
+    shedding_load: AtomicBool,
+    shed_requests: AtomicU64,
+    dispatch_latency_total_us: AtomicU64,
+    dispatch_latency_count: AtomicU64,
+    content_policy_counters: RwLock<BTreeMap<(String, &'static str, ContentAction), u64>>,
+    hook_dispatch_slots: Arc<Semaphore>,
+    hook_dispatch_queued: AtomicUsize,
+    hook_dispatch_rejected: AtomicU64,
+    log_ship_status: RwLock<LogShipStatus>,
+    /// Set while this instance follows a primary; cleared for good on promotion.
+    standby: AtomicBool,
+    replication_status: RwLock<ReplicationStatus>,
+    lifecycle: Lifecycle,
 }
 
 #[derive(Debug, Clone)]
@@ -63,6 +149,50 @@ pub struct ConnectedClient {
     pub remote_ip: Option<String>,
     pub connected_at: Instant,
     pub connected_at_ms: u64,
+    pub features: ClientFeatures,
+    /// Paired device whose token authenticated the connection.
+    pub device_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct LiveCronRun {
+    job_id: String,
+    manual: bool,
+    started_at_ms: u64,
+    chunks: Vec<CronOutputChunk>,
+}
+
+/// Snapshot of a cron run for `cron.runs.tail`: buffered output while running, the stored
+/// record once finished.
+#[derive(Debug, Clone)]
+pub enum CronRunTail {
+    Running {
+        run_id: String,
+        job_id: String,
+        manual: bool,
+        started_at_ms: u64,
+        chunks: Vec<CronOutputChunk>,
+    },
+    Finished(CronRunRecord),
+}
+
+/// A held hook dispatch slot; dropping it lets the next queued hook run.
+#[derive(Debug)]
+pub struct HookDispatchSlot {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// Result of registering a new connection against its credential's connection cap.
+#[derive(Debug)]
+pub enum ConnectionAdmission {
+    /// Registered; `eviction_rx` resolves with a reason if the connection is evicted later.
+    Admitted {
+        evicted: Vec<String>,
+        eviction_rx: oneshot::Receiver<String>,
+    },
+    Rejected {
+        limit: usize,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -70,9 +200,57 @@ pub struct GatewayEventEnvelope {
     pub event: String,
     pub payload: Value,
     pub ts: u64,
+    /// Event journal sequence number, set on [`ACKED_GATEWAY_EVENTS`].
+    pub seq: Option<u64>,
+}
+
+/// Broadcast events a connection narrowed itself to with `events.subscribe`. Events addressed to
+/// the connection itself are always delivered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GatewayEventFilter {
+    pub events: BTreeSet<String>,
+    /// Drops events whose payload `sessionKey` is a different session.
+    pub session_key: Option<String>,
+    /// Drops events whose payload `nodeId` is a different node.
+    pub node_id: Option<String>,
+}
+
+impl GatewayEventFilter {
+    /// Payloads without the filtered field pass, so server-wide events are not lost to a scope.
+    #[must_use]
+    pub fn allows(&self, event: &str, payload: &Value) -> bool {
+        let scoped = |field: &str, wanted: &Option<String>| match (wanted, payload.get(field)) {
+            (Some(wanted), Some(Value::String(actual))) => wanted == actual,
+            _ => true,
+        };
+        self.events.contains(event)
+            && scoped("sessionKey", &self.session_key)
+            && scoped("nodeId", &self.node_id)
+    }
 }
 
 const GATEWAY_EVENT_BUFFER_CAPACITY: usize = 256;
+/// Events kept in the event journal until each client that negotiated `supportsEventAck`
+/// acknowledges them with `events.ack`; unacknowledged ones are redelivered on reconnect.
+pub const ACKED_GATEWAY_EVENTS: &[&str] = &["exec.approval.requested", "node.invoke.request"];
+/// How long journaled events and the cursors of clients that stopped connecting are kept.
+const EVENT_JOURNAL_RETENTION_MS: u64 = 7 * 24 * 60 * 60 * 1_000;
+/// Most journaled events redelivered to one reconnecting client; the rest follow on the next
+/// reconnect after these are acknowledged.
+const EVENT_REPLAY_LIMIT: usize = 1_000;
+/// Node status for nodes that reach the gateway through `/nodes/{id}/poll`.
+pub const NODE_STATUS_POLLING: &str = "polling";
+/// Gateway log retention is enforced every this many appends rather than on each write.
+const GATEWAY_LOG_TRIM_INTERVAL: u64 = 64;
+/// How long a claimed idempotency key stays reserved in Redis.
+const IDEMPOTENCY_KEY_TTL_MS: u64 = 24 * 60 * 60 * 1_000;
+/// Config entries read through Redis are cached this long; writes invalidate immediately.
+const CONFIG_ENTRY_CACHE_TTL_MS: u64 = 30_000;
+/// Random id of this database, generated on first start; scopes its cached config entries in Redis.
+const STORE_ID_KEY: &str = "runtime/storeId";
+/// Node metadata fields owned by `node.metadata.update` rather than the connect handshake.
+pub const NODE_REPORTED_METADATA_KEYS: &[&str] =
+    &["location", "battery", "network", "reportedAtMs"];
 
 impl SharedState {
     pub async fn new(
@@ -81,14 +259,51 @@ impl SharedState {
         events: Vec<String>,
     ) -> Result<Self, DomainError> {
         let store = SqliteStore::connect(&config.db_path).await?;
+        store.set_append_only(config.append_only).await?;
+        let replication_state = store.load_replication_state().await?;
+        // A primary fenced by a newer one stays read-only until it is reconfigured.
+        let standby = config.replication.as_ref().is_some_and(|replication| {
+            replication.primary_url().is_some() || replication_state.fenced_at_ms.is_some()
+        });
+        store
+            .set_change_capture(config.replication.is_some() && !standby)
+            .await?;
+        // Appends a failed shutdown flush could not commit go in before anything else runs.
+        let spill_path = chat_write_buffer::spill_path(&config.db_path);
+        if let Err(error) = chat_write_buffer::recover_spilled(&store, &spill_path).await {
+            warn!("{error}");
+        }
+        let redis = match config.redis_url.as_deref() {
+            Some(url) => {
+                let store_id = load_store_id(&store).await?;
+                Some(RedisBackend::connect(url, &config.redis_key_prefix, &store_id).await?)
+            }
+            None => None,
+        };
+        let translator = config.translation_url.clone().map(|url| {
+            Arc::new(HttpTranslator::new(url, config.translation_api_key.clone()))
+                as Arc<dyn Translator>
+        });
+        let limiter = |max_attempts: u32, window: Duration, scope: &'static str| {
+            let limiter = AuthRateLimiter::new(max_attempts, window);
+            match &redis {
+                Some(backend) => limiter.with_shared_backend(backend.clone(), scope),
+                None => limiter,
+            }
+        };
 
         Ok(Self {
             inner: Arc::new(InnerState {
-                auth_rate_limiter: AuthRateLimiter::new(
-                    config.auth_max_attempts,
-                    config.auth_window,
+                auth_rate_limiter: limiter(config.auth_max_attempts, config.auth_window, "auth"),
+                control_plane_rate_limiter: limiter(3, Duration::from_secs(60), "control-plane"),
+                api_key_rate_limiter: limiter(60, Duration::from_secs(60), "api-key"),
+                public_status_rate_limiter: limiter(
+                    config.public_status_rate_limit_per_minute,
+                    Duration::from_secs(60),
+                    "public-status",
                 ),
-                control_plane_rate_limiter: AuthRateLimiter::new(3, Duration::from_secs(60)),
+                public_status_cache: RwLock::new(None),
+                redis,
                 started_at: Instant::now(),
                 methods,
                 events,
@@ -96,10 +311,49 @@ impl SharedState {
                 store,
                 cron_enabled: RwLock::new(config.cron_enabled),
                 cron_last_tick_ms: RwLock::new(None),
+                cron_live_runs: RwLock::new(HashMap::new()),
+                cron_workers: Arc::new(Semaphore::new(config.cron_max_workers)),
+                cron_queued: AtomicUsize::new(0),
+                cron_job_runs: RwLock::new(HashMap::new()),
+                cron_scheduler_lag_ms: AtomicU64::new(0),
+                dispatch_hooks: RwLock::new(DispatchHookRegistry::default()),
+                chat_commands: RwLock::new(ChatCommandRegistry::default()),
+                chat_writes: config.chat_write_batch.map(|_| ChatWriteBuffer::default()),
+                node_file_uploads: RwLock::new(HashSet::new()),
+                device_state_writes: Mutex::new(()),
+                federation_nonces: RwLock::new(HashMap::new()),
+                agent_backend: RwLock::new(Arc::new(EchoAgentBackend)),
+                agent_backends: RwLock::new(HashMap::from([(
+                    EchoAgentBackend.name().to_owned(),
+                    Arc::new(EchoAgentBackend) as Arc<dyn AgentBackend>,
+                )])),
+                translator: RwLock::new(translator),
+                resource_status: RwLock::new(None),
+                overload_status: RwLock::new(OverloadStatus::default()),
+                shedding_load: AtomicBool::new(false),
+                shed_requests: AtomicU64::new(0),
+                dispatch_latency_total_us: AtomicU64::new(0),
+                dispatch_latency_count: AtomicU64::new(0),
+                content_policy_counters: RwLock::new(BTreeMap::new()),
+                hook_dispatch_slots: Arc::new(Semaphore::new(
+                    config.hooks_dispatch.max_concurrency,
+                )),
+                hook_dispatch_queued: AtomicUsize::new(0),
+                hook_dispatch_rejected: AtomicU64::new(0),
+                log_ship_status: RwLock::new(LogShipStatus::default()),
+                standby: AtomicBool::new(standby),
+                replication_status: RwLock::new(ReplicationStatus::from_state(&replication_state)),
+                lifecycle: Lifecycle::default(),
                 config,
                 presence_version: AtomicU64::new(0),
+                operator_last_seen_ms: AtomicU64::new(now_unix_ms()),
                 health_version: AtomicU64::new(0),
                 gateway_event_subscribers: RwLock::new(HashMap::new()),
+                gateway_event_filters: RwLock::new(HashMap::new()),
+                connection_evictors: RwLock::new(HashMap::new()),
+                connection_evictions: AtomicU64::new(0),
+                gateway_log_appends: AtomicU64::new(0),
+                connection_rejections: AtomicU64::new(0),
             }),
         })
     }
@@ -139,16 +393,362 @@ impl SharedState {
         self.inner.control_plane_rate_limiter.clone()
     }
 
-    pub async fn register_client(&self, client: ConnectedClient) -> Result<(), DomainError> {
+    #[must_use]
+    pub fn api_key_rate_limiter(&self) -> AuthRateLimiter {
+        self.inner.api_key_rate_limiter.clone()
+    }
+
+    #[must_use]
+    pub fn public_status_rate_limiter(&self) -> AuthRateLimiter {
+        self.inner.public_status_rate_limiter.clone()
+    }
+
+    /// Registers an embedder-provided hook around RPC dispatch.
+    pub async fn register_dispatch_hook(&self, hook: Arc<dyn DispatchHook>) {
+        self.inner.dispatch_hooks.write().await.register(hook);
+    }
+
+    pub async fn dispatch_hooks(&self) -> DispatchHookRegistry {
+        self.inner.dispatch_hooks.read().await.clone()
+    }
+
+    /// Adds an embedder-provided slash command, replacing a built-in one of the same name.
+    pub async fn register_chat_command(&self, command: Arc<dyn ChatCommand>) {
+        self.inner.chat_commands.write().await.register(command);
+    }
+
+    pub async fn chat_commands(&self) -> ChatCommandRegistry {
+        self.inner.chat_commands.read().await.clone()
+    }
+
+    pub async fn set_agent_backend(&self, backend: Arc<dyn AgentBackend>) {
+        self.register_agent_backend(backend.clone()).await;
+        *self.inner.agent_backend.write().await = backend;
+    }
+
+    /// Makes `backend` available to `agent.replay` by name without serving new runs with it.
+    pub async fn register_agent_backend(&self, backend: Arc<dyn AgentBackend>) {
         self.inner
-            .clients
+            .agent_backends
+            .write()
+            .await
+            .insert(backend.name().to_owned(), backend);
+    }
+
+    pub async fn find_agent_backend(&self, name: &str) -> Option<Arc<dyn AgentBackend>> {
+        self.inner.agent_backends.read().await.get(name).cloned()
+    }
+
+    pub async fn agent_backend(&self) -> Arc<dyn AgentBackend> {
+        self.inner.agent_backend.read().await.clone()
+    }
+
+    pub async fn set_translator(&self, translator: Arc<dyn Translator>) {
+        *self.inner.translator.write().await = Some(translator);
+    }
+
+    pub async fn translator(&self) -> Option<Arc<dyn Translator>> {
+        self.inner.translator.read().await.clone()
+    }
+
+    pub async fn record_resource_status(&self, status: ResourceStatus) -> Option<ResourceStatus> {
+        self.inner.resource_status.write().await.replace(status)
+    }
+
+    pub async fn resource_status(&self) -> Option<ResourceStatus> {
+        self.inner.resource_status.read().await.clone()
+    }
+
+    pub async fn record_overload_status(&self, status: OverloadStatus) {
+        self.inner
+            .shedding_load
+            .store(status.shedding, Ordering::Relaxed);
+        *self.inner.overload_status.write().await = status;
+    }
+
+    pub async fn overload_status(&self) -> OverloadStatus {
+        self.inner.overload_status.read().await.clone()
+    }
+
+    /// Whether the overload detector currently sheds webhooks and low-priority methods.
+    #[must_use]
+    pub fn is_shedding_load(&self) -> bool {
+        self.inner.shedding_load.load(Ordering::Relaxed)
+    }
+
+    /// Whether this instance is a read-only standby following a primary.
+    #[must_use]
+    pub fn is_standby(&self) -> bool {
+        self.inner.standby.load(Ordering::Relaxed)
+    }
+
+    /// Scheduled stops, draining, and maintenance mode.
+    #[must_use]
+    pub fn lifecycle(&self) -> &Lifecycle {
+        &self.inner.lifecycle
+    }
+
+    /// Leaves standby mode; returns `false` when this instance already was the primary.
+    pub fn promote_standby(&self) -> bool {
+        self.inner.standby.swap(false, Ordering::Relaxed)
+    }
+
+    /// Turns a fenced primary read-only; returns `false` when it already was.
+    pub fn demote_to_standby(&self) -> bool {
+        !self.inner.standby.swap(true, Ordering::Relaxed)
+    }
+
+    pub async fn replication_status(&self) -> ReplicationStatus {
+        self.inner.replication_status.read().await.clone()
+    }
+
+    pub async fn update_replication_status(
+        &self,
+        update: impl FnOnce(&mut ReplicationStatus),
+    ) -> ReplicationStatus {
+        let mut status = self.inner.replication_status.write().await;
+        update(&mut status);
+        status.clone()
+    }
+
+    pub fn count_shed_request(&self) {
+        self.inner.shed_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Waits for a hook dispatch slot per `hooksMaxConcurrency` and `hooksOverflow`. Returns
+    /// `None` when the hook must be refused because no slot is free and the queue cannot take it.
+    pub async fn acquire_hook_dispatch_slot(&self) -> Option<HookDispatchSlot> {
+        let limits = self.inner.config.hooks_dispatch;
+        if limits.max_concurrency == 0 {
+            return Some(HookDispatchSlot { _permit: None });
+        }
+        let slots = self.inner.hook_dispatch_slots.clone();
+        if let Ok(permit) = slots.clone().try_acquire_owned() {
+            return Some(HookDispatchSlot {
+                _permit: Some(permit),
+            });
+        }
+
+        let queued = &self.inner.hook_dispatch_queued;
+        if limits.overflow == HookOverflowAction::Reject
+            || queued.fetch_add(1, Ordering::SeqCst) >= limits.max_queue_depth
+        {
+            if limits.overflow == HookOverflowAction::Enqueue {
+                queued.fetch_sub(1, Ordering::SeqCst);
+            }
+            self.inner
+                .hook_dispatch_rejected
+                .fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        let permit = slots.acquire_owned().await.ok();
+        queued.fetch_sub(1, Ordering::SeqCst);
+        permit.map(|permit| HookDispatchSlot {
+            _permit: Some(permit),
+        })
+    }
+
+    pub async fn count_content_policy_action(
+        &self,
+        channel: &str,
+        direction: &'static str,
+        action: ContentAction,
+    ) {
+        *self
+            .inner
+            .content_policy_counters
             .write()
             .await
-            .insert(client.conn_id.clone(), client.clone());
+            .entry((channel.to_owned(), direction, action))
+            .or_default() += 1;
+    }
+
+    pub fn record_dispatch_latency(&self, elapsed: Duration) {
+        if self.inner.config.overload.max_dispatch_latency_ms.is_none() {
+            return;
+        }
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.inner
+            .dispatch_latency_total_us
+            .fetch_add(micros, Ordering::Relaxed);
+        self.inner
+            .dispatch_latency_count
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Mean dispatch latency since the previous call, `None` if nothing was dispatched.
+    pub fn take_dispatch_latency(&self) -> Option<Duration> {
+        let count = self.inner.dispatch_latency_count.swap(0, Ordering::Relaxed);
+        let total = self
+            .inner
+            .dispatch_latency_total_us
+            .swap(0, Ordering::Relaxed);
+        (count > 0).then(|| Duration::from_micros(total / count))
+    }
+
+    /// Deepest backlog across the per-connection event push queues.
+    pub async fn event_queue_depth(&self) -> u64 {
+        self.inner
+            .gateway_event_subscribers
+            .read()
+            .await
+            .values()
+            .map(|tx| tx.max_capacity().saturating_sub(tx.capacity()))
+            .max()
+            .map_or(0, |depth| u64::try_from(depth).unwrap_or(u64::MAX))
+    }
+
+    /// Times one storage read that bypasses the config cache.
+    pub async fn probe_storage_latency(&self) -> Result<Duration, DomainError> {
+        let started = Instant::now();
+        self.inner
+            .store
+            .get_config_entry("runtime/overload/probe")
+            .await?;
+        Ok(started.elapsed())
+    }
+
+    pub async fn list_log_shipments(&self, limit: usize) -> Result<Vec<LogShipment>, DomainError> {
+        self.inner.store.list_log_shipments(limit).await
+    }
+
+    pub async fn ack_log_shipments(&self, seq: u64) -> Result<u64, DomainError> {
+        self.inner.store.ack_log_shipments(seq).await
+    }
+
+    /// Folds one shipper pass into the status reported by `health`.
+    pub async fn record_log_ship_result(&self, result: Result<u64, String>) -> LogShipStatus {
+        let mut status = self.inner.log_ship_status.write().await;
+        match result {
+            Ok(0) => status.consecutive_failures = 0,
+            Ok(shipped) => {
+                status.shipped = status.shipped.saturating_add(shipped);
+                status.last_shipped_at_ms = Some(now_unix_ms());
+                status.consecutive_failures = 0;
+            }
+            Err(error) => {
+                status.last_error = Some(error);
+                status.consecutive_failures = status.consecutive_failures.saturating_add(1);
+            }
+        }
+        status.clone()
+    }
+
+    /// Buffers an entry for the log shipper; a full or failing buffer never blocks the caller.
+    async fn buffer_log_shipment(&self, kind: &str, entry: Value, ts: u64) {
+        let Some(shipping) = &self.inner.config.log_shipping else {
+            return;
+        };
+        if let Err(error) = self
+            .inner
+            .store
+            .enqueue_log_shipment(kind, &entry, ts, shipping.buffer_max_entries)
+            .await
+        {
+            tracing::warn!("failed to buffer {kind} entry for log shipping: {error}");
+        }
+    }
+
+    /// Whether `action` is configured and the latest resource sample breached a guardrail.
+    pub async fn guardrail_engaged(&self, action: GuardrailAction) -> bool {
+        if !self.inner.config.guardrails.has_action(action) {
+            return false;
+        }
+
+        self.inner
+            .resource_status
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(ResourceStatus::is_breached)
+    }
+
+    /// How long no operator connection has been open, or `None` while one is connected.
+    pub async fn operator_absence(&self) -> Option<Duration> {
+        if self
+            .inner
+            .clients
+            .read()
+            .await
+            .values()
+            .any(|client| client.role == "operator")
+        {
+            return None;
+        }
+        let last_seen_ms = self.inner.operator_last_seen_ms.load(Ordering::Relaxed);
+        Some(Duration::from_millis(
+            now_unix_ms().saturating_sub(last_seen_ms),
+        ))
+    }
+
+    /// Registers `client` unless its credential's connection cap refuses it. The cap is checked
+    /// and the connection inserted under one lock, so concurrent connects cannot all slip under
+    /// it. Under `evictOldest` the oldest live connections sharing the credential are told to
+    /// close.
+    pub async fn register_client(
+        &self,
+        client: ConnectedClient,
+    ) -> Result<ConnectionAdmission, DomainError> {
+        let (evicted, eviction_rx) = {
+            let mut clients = self.inner.clients.write().await;
+            let mut evictors = self.inner.connection_evictors.write().await;
+            let key = connection_limit_key(&client);
+            let evicted = match connections_over_limit(
+                self.config().connection_limits,
+                &clients,
+                &evictors,
+                &client,
+                &key,
+            ) {
+                Ok(evicted) => evicted,
+                Err(limit) => {
+                    self.inner
+                        .connection_rejections
+                        .fetch_add(1, Ordering::Relaxed);
+                    return Ok(ConnectionAdmission::Rejected { limit });
+                }
+            };
+            for conn_id in &evicted {
+                if let Some(evictor) = evictors.remove(conn_id) {
+                    let _ = evictor.send(format!("connection limit reached for {key}"));
+                }
+            }
+            self.inner.connection_evictions.fetch_add(
+                u64::try_from(evicted.len()).unwrap_or(u64::MAX),
+                Ordering::Relaxed,
+            );
+            let (tx, rx) = oneshot::channel();
+            evictors.insert(client.conn_id.clone(), tx);
+            clients.insert(client.conn_id.clone(), client.clone());
+            (evicted, rx)
+        };
         self.inner.presence_version.fetch_add(1, Ordering::Relaxed);
+        self.publish_presence_delta("connect", &client).await;
+        // Open the ack cursor before `hello-ok` so events journaled while the connection
+        // subscribes are owed to it.
+        if client.features.supports_event_ack {
+            self.inner
+                .store
+                .open_event_cursor(&event_ack_key(&client), now_unix_ms())
+                .await?;
+        }
 
         if client.role == "node" {
             let node_id = runtime_node_id(&client);
+            let mut metadata = json!({
+                "remoteIp": client.remote_ip,
+                "modelIdentifier": client.model_identifier,
+                "version": client.client_version,
+                "features": client.features,
+            });
+            // Keep what the node last reported through `node.metadata.update` across reconnects.
+            if let Some(previous) = self.inner.store.get_node(&node_id).await? {
+                for key in NODE_REPORTED_METADATA_KEYS {
+                    if let Some(value) = previous.metadata.get(*key) {
+                        metadata[*key] = value.clone();
+                    }
+                }
+            }
             let node = NodeRecord {
                 id: node_id.clone(),
                 display_name: client
@@ -161,24 +761,70 @@ impl SharedState {
                 paired: true,
                 status: "online".to_owned(),
                 last_seen_ms: client.connected_at_ms,
-                metadata: json!({
-                    "remoteIp": client.remote_ip,
-                    "modelIdentifier": client.model_identifier,
-                    "version": client.client_version,
-                }),
+                metadata,
             };
             self.inner.store.upsert_node(&node).await?;
         }
 
-        Ok(())
+        Ok(ConnectionAdmission::Admitted {
+            evicted,
+            eviction_rx,
+        })
+    }
+
+    /// Closes the live connections authenticated by `device_id`'s tokens, limited to `role` when
+    /// given. Returns how many were told to close.
+    pub async fn evict_device_connections(
+        &self,
+        device_id: &str,
+        role: Option<&str>,
+        reason: &str,
+    ) -> usize {
+        let conn_ids = self
+            .inner
+            .clients
+            .read()
+            .await
+            .values()
+            .filter(|client| {
+                client.device_id.as_deref() == Some(device_id)
+                    && role.is_none_or(|role| client.role == role)
+            })
+            .map(|client| client.conn_id.clone())
+            .collect::<Vec<_>>();
+        let mut evictors = self.inner.connection_evictors.write().await;
+        let mut evicted = 0;
+        for conn_id in conn_ids {
+            if let Some(evictor) = evictors.remove(&conn_id) {
+                let _ = evictor.send(reason.to_owned());
+                evicted += 1;
+            }
+        }
+        evicted
     }
 
     pub async fn unregister_client(&self, conn_id: &str) -> Result<(), DomainError> {
-        let removed = self.inner.clients.write().await.remove(conn_id);
+        self.inner.connection_evictors.write().await.remove(conn_id);
+        let (removed, node_still_connected) = {
+            let mut clients = self.inner.clients.write().await;
+            let removed = clients.remove(conn_id);
+            let node_still_connected = removed.as_ref().is_some_and(|removed| {
+                clients.values().any(|client| {
+                    client.role == "node" && runtime_node_id(client) == runtime_node_id(removed)
+                })
+            });
+            (removed, node_still_connected)
+        };
         self.unregister_gateway_event_subscriber(conn_id).await;
         if let Some(client) = removed {
             self.inner.presence_version.fetch_add(1, Ordering::Relaxed);
-            if client.role == "node" {
+            if client.role == "operator" {
+                self.inner
+                    .operator_last_seen_ms
+                    .store(now_unix_ms(), Ordering::Relaxed);
+            }
+            self.publish_presence_delta("disconnect", &client).await;
+            if client.role == "node" && !node_still_connected {
                 let node_id = runtime_node_id(&client);
                 if let Some(mut node) = self.inner.store.get_node(&node_id).await? {
                     node.status = "offline".to_owned();
@@ -209,6 +855,59 @@ impl SharedState {
             .write()
             .await
             .remove(conn_id);
+        self.inner
+            .gateway_event_filters
+            .write()
+            .await
+            .remove(conn_id);
+    }
+
+    /// Adds `events` to the connection's broadcast allowlist, replacing the session and node
+    /// scope where given, and returns the resulting filter. The filter is left unchanged when
+    /// the allowlist would grow past `max_events`.
+    pub async fn subscribe_gateway_events(
+        &self,
+        conn_id: &str,
+        events: Vec<String>,
+        session_key: Option<String>,
+        node_id: Option<String>,
+        max_events: usize,
+    ) -> Result<GatewayEventFilter, DomainError> {
+        let mut filters = self.inner.gateway_event_filters.write().await;
+        let mut filter = filters.get(conn_id).cloned().unwrap_or_default();
+        filter.events.extend(events);
+        if filter.events.len() > max_events {
+            return Err(DomainError::InvalidRequest(format!(
+                "at most {max_events} events may be subscribed"
+            )));
+        }
+        if session_key.is_some() {
+            filter.session_key = session_key;
+        }
+        if node_id.is_some() {
+            filter.node_id = node_id;
+        }
+        filters.insert(conn_id.to_owned(), filter.clone());
+        Ok(filter)
+    }
+
+    /// Removes `events` from the connection's allowlist, or drops the filter altogether when
+    /// `events` is `None`. Returns the filter left in place, if any.
+    pub async fn unsubscribe_gateway_events(
+        &self,
+        conn_id: &str,
+        events: Option<Vec<String>>,
+    ) -> Option<GatewayEventFilter> {
+        let mut filters = self.inner.gateway_event_filters.write().await;
+        let Some(events) = events else {
+            filters.remove(conn_id);
+            return None;
+        };
+        let filter = filters.get_mut(conn_id)?;
+        for event in &events {
+            filter.events.remove(event);
+        }
+        Some(filter.clone())
     }
 
     pub async fn publish_gateway_event(&self, event: &str, payload: Value) {
@@ -221,10 +920,18 @@ impl SharedState {
         event: &str,
         payload: Value,
     ) {
+        let ts = now_unix_ms();
+        let seq = if ACKED_GATEWAY_EVENTS.contains(&event) {
+            self.journal_gateway_event(target_conn_id, event, &payload, ts)
+                .await
+        } else {
+            None
+        };
         let envelope = GatewayEventEnvelope {
             event: event.to_owned(),
             payload,
-            ts: now_unix_ms(),
+            ts,
+            seq,
         };
 
         let subscribers = {
@@ -235,10 +942,18 @@ impl SharedState {
                     .cloned()
                     .map(|tx| vec![(conn_id.to_owned(), tx)])
                     .unwrap_or_default(),
-                None => guard
-                    .iter()
-                    .map(|(conn_id, tx)| (conn_id.clone(), tx.clone()))
-                    .collect::<Vec<_>>(),
+                None => {
+                    let filters = self.inner.gateway_event_filters.read().await;
+                    guard
+                        .iter()
+                        .filter(|(conn_id, _)| {
+                            filters.get(*conn_id).is_none_or(|filter| {
+                                filter.allows(&envelope.event, &envelope.payload)
+                            })
+                        })
+                        .map(|(conn_id, tx)| (conn_id.clone(), tx.clone()))
+                        .collect::<Vec<_>>()
+                }
             }
         };
 
@@ -258,36 +973,221 @@ impl SharedState {
         }
     }
 
-    pub async fn connection_count(&self) -> usize {
-        self.inner.clients.read().await.len()
+    /// Journals an acknowledged event for every client, or for the client behind
+    /// `target_conn_id`. A failed write still lets the event go out, just without a `seq`.
+    async fn journal_gateway_event(
+        &self,
+        target_conn_id: Option<&str>,
+        event: &str,
+        payload: &Value,
+        ts: u64,
+    ) -> Option<u64> {
+        let target_key = match target_conn_id {
+            Some(conn_id) => Some(event_ack_key(self.inner.clients.read().await.get(conn_id)?)),
+            None => None,
+        };
+        match self
+            .inner
+            .store
+            .append_journaled_event(
+                event,
+                payload,
+                target_key.as_deref(),
+                ts,
+                ts.saturating_sub(EVENT_JOURNAL_RETENTION_MS),
+            )
+            .await
+        {
+            Ok(seq) => Some(seq),
+            Err(error) => {
+                tracing::warn!("failed to journal {event} event: {error}");
+                None
+            }
+        }
     }
 
-    pub async fn health_payload(&self) -> Result<Value, DomainError> {
-        let connections = self.connection_count().await;
-        let sessions = self.inner.store.list_sessions().await?;
-        let nodes = self.inner.store.list_nodes().await?;
-        let jobs = self.inner.store.list_cron_jobs().await?;
-        let chats = self
+    /// Journaled events `conn_id` has not acknowledged yet, oldest first. Empty unless the
+    /// connection negotiated `supportsEventAck`.
+    pub async fn unacked_gateway_events(
+        &self,
+        conn_id: &str,
+    ) -> Result<Vec<GatewayEventEnvelope>, DomainError> {
+        let Some(key) = self
+            .inner
+            .clients
+            .read()
+            .await
+            .get(conn_id)
+            .filter(|client| client.features.supports_event_ack)
+            .map(event_ack_key)
+        else {
+            return Ok(Vec::new());
+        };
+        let acked_seq = self
             .inner
             .store
-            .list_chat_messages("agent:main:main", None)
+            .open_event_cursor(&key, now_unix_ms())
+            .await?;
+        let events = self
+            .inner
+            .store
+            .list_unacked_events(&key, acked_seq, EVENT_REPLAY_LIMIT)
+            .await?;
+        Ok(events
+            .into_iter()
+            .map(|event| GatewayEventEnvelope {
+                event: event.event,
+                payload: event.payload,
+                ts: event.ts,
+                seq: Some(event.seq),
+            })
+            .collect())
+    }
+
+    /// Acknowledges the journaled events up to `seq` for the client behind `conn_id`. Returns
+    /// the acknowledged sequence number and how many events are still owed.
+    pub async fn ack_gateway_events(
+        &self,
+        conn_id: &str,
+        seq: u64,
+    ) -> Result<(u64, u64), DomainError> {
+        let key = self
+            .inner
+            .clients
+            .read()
             .await
-            .unwrap_or_default();
+            .get(conn_id)
+            .filter(|client| client.features.supports_event_ack)
+            .map(event_ack_key)
+            .ok_or_else(|| {
+                DomainError::InvalidRequest(
+                    "connect with features.supportsEventAck to acknowledge events".to_owned(),
+                )
+            })?;
+        self.inner.store.ack_events(&key, seq, now_unix_ms()).await
+    }
 
-        let health = json!({
-            "ok": true,
-            "ts": now_unix_ms(),
-            "runtime": "rust",
-            "version": self.config().runtime_version,
-            "protocolVersion": crate::protocol::PROTOCOL_VERSION,
-            "authMode": self.auth_mode_label(),
-            "uptimeMs": self.uptime_ms(),
-            "connectedClients": connections,
+    pub async fn connection_count(&self) -> usize {
+        self.inner.clients.read().await.len()
+    }
+
+    /// Anonymous summary served at `/status/public`. It carries no ids, names, or addresses, and
+    /// is rebuilt at most once per `publicStatusCacheSecs`.
+    pub async fn public_status_payload(&self) -> Value {
+        let ttl = self.config().public_status_cache_ttl;
+        if let Some((built_at, payload)) = self.inner.public_status_cache.read().await.as_ref()
+            && built_at.elapsed() < ttl
+        {
+            return payload.clone();
+        }
+
+        let status = if self.lifecycle().is_draining() {
+            "stopping"
+        } else if self.lifecycle().maintenance().await.is_some() {
+            "maintenance"
+        } else if self.is_shedding_load() {
+            "degraded"
+        } else {
+            "ok"
+        };
+        let connected_nodes = self
+            .inner
+            .clients
+            .read()
+            .await
+            .values()
+            .filter(|client| client.role == "node")
+            .map(runtime_node_id)
+            .collect::<HashSet<_>>()
+            .len();
+        let payload = json!({
+            "ok": true,
+            "status": status,
+            "version": self.config().runtime_version,
+            "uptimeMs": self.uptime_ms(),
+            "nodes": {
+                "connected": connected_nodes,
+            },
+            "generatedAtMs": now_unix_ms(),
+        });
+        *self.inner.public_status_cache.write().await = Some((Instant::now(), payload.clone()));
+        payload
+    }
+
+    pub async fn health_payload(&self) -> Result<Value, DomainError> {
+        let connections = self.connection_count().await;
+        let sessions = self.inner.store.list_sessions().await?;
+        let nodes = self.inner.store.list_nodes().await?;
+        let jobs = self.inner.store.list_cron_jobs().await?;
+        let chats = self
+            .inner
+            .store
+            .list_chat_messages("agent:main:main", None)
+            .await
+            .unwrap_or_default();
+
+        let mut health = json!({
+            "ok": true,
+            "ts": now_unix_ms(),
+            "runtime": "rust",
+            "version": self.config().runtime_version,
+            "protocolVersion": crate::protocol::PROTOCOL_VERSION,
+            "authMode": self.auth_mode_label(),
+            "uptimeMs": self.uptime_ms(),
+            "connectedClients": connections,
             "sessions": sessions.len(),
             "chatMessages": chats.len(),
             "cronJobs": jobs.len(),
             "nodes": nodes.len(),
+            "connectionLimits": {
+                "evictions": self.inner.connection_evictions.load(Ordering::Relaxed),
+                "rejections": self.inner.connection_rejections.load(Ordering::Relaxed),
+            },
         });
+        if self.inner.config.overload.is_enabled() {
+            let status = self.inner.overload_status.read().await;
+            health["overload"] = json!({
+                "shedding": status.shedding,
+                "sinceMs": status.since_ms,
+                "breaches": status.breaches,
+                "sample": status.sample,
+                "shedRequests": self.inner.shed_requests.load(Ordering::Relaxed),
+            });
+        }
+        if self.inner.config.hooks_enabled {
+            let limits = self.inner.config.hooks_dispatch;
+            health["hooksDispatch"] = json!({
+                "maxConcurrency": limits.max_concurrency,
+                "active": limits
+                    .max_concurrency
+                    .saturating_sub(self.inner.hook_dispatch_slots.available_permits()),
+                "queued": self.inner.hook_dispatch_queued.load(Ordering::SeqCst),
+                "maxQueueDepth": limits.max_queue_depth,
+                "overflow": limits.overflow.label(),
+                "rejected": self.inner.hook_dispatch_rejected.load(Ordering::Relaxed),
+            });
+        }
+        if self.inner.config.content_policy.is_some() {
+            let mut counters = Map::new();
+            for ((channel, direction, action), count) in
+                self.inner.content_policy_counters.read().await.iter()
+            {
+                counters.entry(channel.clone()).or_insert_with(|| json!({}))[*direction]
+                    [action.label()] = json!(count);
+            }
+            health["contentPolicy"] = Value::Object(counters);
+        }
+        if self.inner.config.snapshots.is_some() {
+            health["snapshots"] = snapshots::status_payload(self).await;
+        }
+        if self.inner.config.log_shipping.is_some() {
+            let pending = self.inner.store.count_log_shipments().await?;
+            let status = self.inner.log_ship_status.read().await;
+            health["logShipping"] = log_shipper::status_payload(&status, pending);
+        }
+        if let Some(status) = replication::status_payload(self).await {
+            health["replication"] = status;
+        }
 
         self.inner.health_version.fetch_add(1, Ordering::Relaxed);
         Ok(health)
@@ -325,12 +1225,27 @@ impl SharedState {
     }
 
     pub async fn get_config_entry_value(&self, key: &str) -> Result<Option<Value>, DomainError> {
-        Ok(self
+        if let Some(redis) = &self.inner.redis {
+            match redis.cached_config_entry(key).await {
+                Ok(Some(value)) => return Ok(Some(value)),
+                Ok(None) => {}
+                Err(error) => tracing::warn!("config cache read failed for {key}: {error}"),
+            }
+        }
+        let value = self
             .inner
             .store
             .get_config_entry(key)
             .await?
-            .map(|entry| entry.value))
+            .map(|entry| entry.value);
+        if let (Some(redis), Some(value)) = (&self.inner.redis, &value)
+            && let Err(error) = redis
+                .cache_config_entry(key, value, CONFIG_ENTRY_CACHE_TTL_MS)
+                .await
+        {
+            tracing::warn!("config cache write failed for {key}: {error}");
+        }
+        Ok(value)
     }
 
     pub async fn set_config_entry_value(
@@ -338,11 +1253,113 @@ impl SharedState {
         key: &str,
         value: &Value,
     ) -> Result<ConfigEntry, DomainError> {
-        self.inner.store.set_config_entry(key, value).await
+        let entry = self.inner.store.set_config_entry(key, value).await?;
+        self.invalidate_cached_config_entry(key).await;
+        Ok(entry)
+    }
+
+    /// Stores `value` under `key` unless an entry exists and returns the stored value, so
+    /// concurrent first uses of a generated value (a secret, an id) settle on one.
+    pub async fn get_or_insert_config_entry_value(
+        &self,
+        key: &str,
+        value: &Value,
+    ) -> Result<Value, DomainError> {
+        if let Some(existing) = self.get_config_entry_value(key).await? {
+            return Ok(existing);
+        }
+        let entry = self
+            .inner
+            .store
+            .insert_config_entry_if_absent(key, value)
+            .await?;
+        self.invalidate_cached_config_entry(key).await;
+        Ok(entry.value)
+    }
+
+    /// Replaces `key` with `next` only while it still holds `expected`; reports whether it did.
+    pub async fn compare_and_set_config_entry_value(
+        &self,
+        key: &str,
+        expected: &Value,
+        next: &Value,
+    ) -> Result<bool, DomainError> {
+        let swapped = self
+            .inner
+            .store
+            .compare_and_set_config_entry(key, expected, next)
+            .await?;
+        self.invalidate_cached_config_entry(key).await;
+        Ok(swapped)
     }
 
     pub async fn delete_config_entry_value(&self, key: &str) -> Result<bool, DomainError> {
-        self.inner.store.delete_config_entry(key).await
+        let deleted = self.inner.store.delete_config_entry(key).await?;
+        self.invalidate_cached_config_entry(key).await;
+        Ok(deleted)
+    }
+
+    /// Writes `entries` atomically, optionally replacing everything else under `replace_prefix`;
+    /// returns the keys the replacement removed.
+    pub async fn set_config_entry_values(
+        &self,
+        entries: &[(String, Value)],
+        replace_prefix: Option<&str>,
+    ) -> Result<Vec<String>, DomainError> {
+        let removed = self
+            .inner
+            .store
+            .set_config_entries(entries, replace_prefix)
+            .await?;
+        for key in entries.iter().map(|(key, _)| key).chain(&removed) {
+            self.invalidate_cached_config_entry(key).await;
+        }
+        Ok(removed)
+    }
+
+    pub async fn delete_config_entry_values(
+        &self,
+        prefix: Option<&str>,
+        keys: &[String],
+        dry_run: bool,
+    ) -> Result<Vec<String>, DomainError> {
+        let deleted = self
+            .inner
+            .store
+            .delete_config_entries(prefix, keys, dry_run)
+            .await?;
+        if !dry_run {
+            for key in &deleted {
+                self.invalidate_cached_config_entry(key).await;
+            }
+        }
+        Ok(deleted)
+    }
+
+    async fn invalidate_cached_config_entry(&self, key: &str) {
+        if let Some(redis) = &self.inner.redis
+            && let Err(error) = redis.invalidate_config_entry(key).await
+        {
+            tracing::warn!("config cache invalidation failed for {key}: {error}");
+        }
+    }
+
+    /// Reserves an idempotency key across every instance sharing Redis. Without Redis, or while
+    /// it is unreachable, the local run table is the only record, so the claim succeeds.
+    pub async fn claim_idempotency_key(&self, key: &str, fingerprint: &str) -> IdempotencyClaim {
+        let Some(redis) = &self.inner.redis else {
+            return IdempotencyClaim::Claimed;
+        };
+        match redis
+            .claim_idempotency_key(key, fingerprint, IDEMPOTENCY_KEY_TTL_MS)
+            .await
+        {
+            Ok(claim) => claim,
+            Err(error) => {
+                tracing::warn!("idempotency claim for {key} fell back to local runs: {error}");
+                IdempotencyClaim::Claimed
+            }
+        }
     }
 
     pub async fn list_config_entries(
@@ -360,24 +1377,52 @@ impl SharedState {
         method: Option<&str>,
         conn_id: Option<&str>,
     ) -> Result<(), DomainError> {
-        let ts = now_unix_ms();
-        let key = format!("logs/{ts}-{}", uuid::Uuid::new_v4());
-        let entry = json!({
-            "id": key,
-            "level": level,
-            "message": message,
-            "method": method,
-            "connId": conn_id,
-            "ts": ts,
-        });
-        let _ = self.set_config_entry_value(&key, &entry).await?;
+        let entry = GatewayLogEntry {
+            id: format!("log-{}", uuid::Uuid::new_v4()),
+            level: level.to_owned(),
+            message: self.inner.config.log_redaction.redact(message),
+            method: method.map(str::to_owned),
+            conn_id: conn_id.map(str::to_owned),
+            ts: now_unix_ms(),
+        };
+        self.inner.store.append_gateway_log(&entry).await?;
+        self.buffer_log_shipment("log", json!(entry), entry.ts)
+            .await;
+
+        let appends = self
+            .inner
+            .gateway_log_appends
+            .fetch_add(1, Ordering::Relaxed)
+            .saturating_add(1);
+        if appends.is_multiple_of(GATEWAY_LOG_TRIM_INTERVAL) {
+            self.inner
+                .store
+                .trim_gateway_logs(self.inner.config.gateway_log_max_entries)
+                .await?;
+        }
         Ok(())
     }
 
+    pub async fn list_gateway_logs(
+        &self,
+        query: &GatewayLogQuery,
+    ) -> Result<Vec<GatewayLogEntry>, DomainError> {
+        self.inner.store.list_gateway_logs(query).await
+    }
+
+    pub async fn count_gateway_logs(&self) -> Result<u64, DomainError> {
+        self.inner.store.count_gateway_logs().await
+    }
+
     pub async fn list_sessions(&self) -> Result<Vec<SessionRecord>, DomainError> {
         self.inner.store.list_sessions().await
     }
 
+    pub async fn list_agent_usage(&self) -> Result<Vec<AgentUsageRecord>, DomainError> {
+        self.flush_chat_writes().await?;
+        self.inner.store.list_agent_usage().await
+    }
+
     pub async fn get_session(&self, id: &str) -> Result<Option<SessionRecord>, DomainError> {
         self.inner.store.get_session(id).await
     }
@@ -386,11 +1431,20 @@ impl SharedState {
         self.inner.store.upsert_session(session).await
     }
 
+    pub async fn upsert_sessions(&self, sessions: &[SessionRecord]) -> Result<(), DomainError> {
+        self.inner.store.upsert_sessions(sessions).await
+    }
+
+    pub fn storage_query_metrics(&self) -> &QueryMetrics {
+        self.inner.store.query_metrics()
+    }
+
     pub async fn remove_session(&self, id: &str) -> Result<bool, DomainError> {
         self.inner.store.remove_session(id).await
     }
 
     pub async fn clear_sessions(&self) -> Result<u64, DomainError> {
+        self.flush_chat_writes().await?;
         self.inner.store.clear_sessions().await
     }
 
@@ -398,29 +1452,705 @@ impl SharedState {
         self.inner.store.compact_sessions(max_age_ms).await
     }
 
+    pub async fn record_channel_directory_entry(
+        &self,
+        input: &ChannelDirectoryInput,
+    ) -> Result<ChannelDirectoryEntry, DomainError> {
+        self.inner.store.record_channel_directory_entry(input).await
+    }
+
+    pub async fn get_channel_directory_entry(
+        &self,
+        channel: &str,
+        conversation_id: &str,
+    ) -> Result<Option<ChannelDirectoryEntry>, DomainError> {
+        self.inner
+            .store
+            .get_channel_directory_entry(channel, conversation_id)
+            .await
+    }
+
+    pub async fn list_channel_directory_entries(
+        &self,
+        channel: Option<&str>,
+        query: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ChannelDirectoryEntry>, DomainError> {
+        self.inner
+            .store
+            .list_channel_directory_entries(channel, query, limit)
+            .await
+    }
+
+    pub async fn list_session_keys_like(&self, pattern: &str) -> Result<Vec<String>, DomainError> {
+        self.flush_chat_writes().await?;
+        self.inner.store.list_session_keys_like(pattern).await
+    }
+
+    /// Purges the session from SQLite and deletes its archive segments, counting archived
+    /// messages in `messages`. In append-only mode the rows become tombstones and the segment
+    /// files stay on disk.
+    pub async fn purge_session_data(
+        &self,
+        session_key: &str,
+    ) -> Result<SessionPurgeCounts, DomainError> {
+        self.flush_chat_writes().await?;
+        let mut counts = self.inner.store.purge_session_data(session_key).await?;
+        let segments = self
+            .inner
+            .store
+            .delete_chat_archive_segments(session_key)
+            .await?;
+        counts.messages += segments
+            .iter()
+            .map(|segment| segment.message_count)
+            .sum::<u64>();
+        if let Some(archive) = &self.config().chat_archive
+            && !self.config().append_only
+        {
+            chat_archive::remove_segment_files(&archive.dir, &segments).await;
+        }
+        Ok(counts)
+    }
+
+    pub async fn delete_channel_directory_entry(
+        &self,
+        channel: &str,
+        conversation_id: &str,
+    ) -> Result<bool, DomainError> {
+        self.inner
+            .store
+            .delete_channel_directory_entry(channel, conversation_id)
+            .await
+    }
+
+    pub async fn record_privacy_audit(
+        &self,
+        record: &PrivacyAuditRecord,
+    ) -> Result<(), DomainError> {
+        self.inner.store.record_privacy_audit(record).await?;
+        self.buffer_log_shipment("audit", json!(record), record.created_at_ms)
+            .await;
+        Ok(())
+    }
+
+    pub async fn list_privacy_audit(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<PrivacyAuditRecord>, DomainError> {
+        self.inner.store.list_privacy_audit(limit).await
+    }
+
+    pub async fn upsert_tool(&self, tool: &ToolDefinition) -> Result<(), DomainError> {
+        self.inner.store.upsert_tool(tool).await
+    }
+
+    pub async fn get_tool(&self, name: &str) -> Result<Option<ToolDefinition>, DomainError> {
+        self.inner.store.get_tool(name).await
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<ToolDefinition>, DomainError> {
+        self.inner.store.list_tools().await
+    }
+
+    pub async fn delete_tool(&self, name: &str) -> Result<bool, DomainError> {
+        self.inner.store.delete_tool(name).await
+    }
+
+    pub async fn grant_tool(&self, grant: &ToolGrant) -> Result<(), DomainError> {
+        self.inner.store.grant_tool(grant).await
+    }
+
+    pub async fn revoke_tool(&self, agent_id: &str, tool_name: &str) -> Result<bool, DomainError> {
+        self.inner.store.revoke_tool(agent_id, tool_name).await
+    }
+
+    pub async fn list_tool_grants(
+        &self,
+        agent_id: Option<&str>,
+    ) -> Result<Vec<ToolGrant>, DomainError> {
+        self.inner.store.list_tool_grants(agent_id).await
+    }
+
+    pub async fn has_tool_grant(
+        &self,
+        agent_id: &str,
+        tool_name: &str,
+    ) -> Result<bool, DomainError> {
+        self.inner.store.has_tool_grant(agent_id, tool_name).await
+    }
+
+    pub async fn upsert_wizard_session(&self, session: &WizardSession) -> Result<(), DomainError> {
+        self.inner.store.upsert_wizard_session(session).await
+    }
+
+    pub async fn get_wizard_session(&self, id: &str) -> Result<Option<WizardSession>, DomainError> {
+        self.inner.store.get_wizard_session(id).await
+    }
+
+    pub async fn latest_wizard_session(
+        &self,
+        kind: &str,
+    ) -> Result<Option<WizardSession>, DomainError> {
+        self.inner.store.latest_wizard_session(kind).await
+    }
+
+    pub async fn upsert_tool_call(&self, call: &ToolCallRecord) -> Result<(), DomainError> {
+        self.inner.store.upsert_tool_call(call).await
+    }
+
+    pub async fn list_tool_calls_by_run(
+        &self,
+        run_id: &str,
+    ) -> Result<Vec<ToolCallRecord>, DomainError> {
+        self.inner.store.list_tool_calls_by_run(run_id).await
+    }
+
+    pub async fn enqueue_outbound_message(
+        &self,
+        message: &QueuedOutboundMessage,
+    ) -> Result<(), DomainError> {
+        self.inner.store.enqueue_outbound_message(message).await
+    }
+
+    /// Starts tracking an outbound reply to `conversation_id` on `channel`.
+    pub async fn record_message_delivery(
+        &self,
+        session_key: &str,
+        run_id: Option<&str>,
+        channel: &str,
+        conversation_id: &str,
+    ) -> Result<MessageDelivery, DomainError> {
+        let now = now_unix_ms();
+        let delivery = MessageDelivery {
+            id: format!("dlv-{}", uuid::Uuid::new_v4()),
+            session_key: session_key.to_owned(),
+            run_id: run_id.map(str::to_owned),
+            channel: channel.to_owned(),
+            conversation_id: conversation_id.to_owned(),
+            status: DeliveryStatus::Queued,
+            platform_message_id: None,
+            error: None,
+            created_at_ms: now,
+            updated_at_ms: now,
+            sent_at_ms: None,
+            delivered_at_ms: None,
+            read_at_ms: None,
+        };
+        self.inner.store.upsert_message_delivery(&delivery).await?;
+        Ok(delivery)
+    }
+
+    /// Moves a delivery to `status` (a send result or a platform receipt) and publishes a
+    /// `chat.delivery` event. Backwards or duplicate transitions leave the record unchanged.
+    pub async fn advance_message_delivery(
+        &self,
+        mut delivery: MessageDelivery,
+        status: DeliveryStatus,
+        platform_message_id: Option<String>,
+        error: Option<String>,
+    ) -> Result<MessageDelivery, DomainError> {
+        if !delivery.status.can_advance_to(status) {
+            return Ok(delivery);
+        }
+
+        let now = now_unix_ms();
+        match status {
+            DeliveryStatus::Sent => delivery.sent_at_ms = Some(now),
+            DeliveryStatus::Delivered => delivery.delivered_at_ms = Some(now),
+            DeliveryStatus::Read => {
+                delivery.delivered_at_ms.get_or_insert(now);
+                delivery.read_at_ms = Some(now);
+            }
+            DeliveryStatus::Queued | DeliveryStatus::Failed => {}
+        }
+        delivery.status = status;
+        if platform_message_id.is_some() {
+            delivery.platform_message_id = platform_message_id;
+        }
+        delivery.error = error;
+        delivery.updated_at_ms = now;
+        self.inner.store.upsert_message_delivery(&delivery).await?;
+
+        self.publish_gateway_event(
+            "chat.delivery",
+            json!({
+                "deliveryId": delivery.id,
+                "sessionKey": delivery.session_key,
+                "runId": delivery.run_id,
+                "channel": delivery.channel,
+                "status": delivery.status,
+                "error": delivery.error,
+            }),
+        )
+        .await;
+        Ok(delivery)
+    }
+
+    /// Applies a platform receipt addressed by `delivery_id` or by the platform message id the
+    /// send returned. Returns `None` when no tracked delivery matches.
+    pub async fn apply_delivery_receipt(
+        &self,
+        channel: &str,
+        delivery_id: Option<&str>,
+        platform_message_id: Option<&str>,
+        status: DeliveryStatus,
+        error: Option<String>,
+    ) -> Result<Option<MessageDelivery>, DomainError> {
+        let delivery = match (delivery_id, platform_message_id) {
+            (Some(delivery_id), _) => self
+                .inner
+                .store
+                .get_message_delivery(delivery_id)
+                .await?
+                .filter(|delivery| delivery.channel == channel),
+            (None, Some(platform_message_id)) => {
+                self.inner
+                    .store
+                    .find_message_delivery_by_platform_id(channel, platform_message_id)
+                    .await?
+            }
+            (None, None) => None,
+        };
+        let Some(delivery) = delivery else {
+            return Ok(None);
+        };
+        let platform_message_id = platform_message_id.map(str::to_owned);
+        self.advance_message_delivery(delivery, status, platform_message_id, error)
+            .await
+            .map(Some)
+    }
+
+    pub async fn get_message_delivery(
+        &self,
+        id: &str,
+    ) -> Result<Option<MessageDelivery>, DomainError> {
+        self.inner.store.get_message_delivery(id).await
+    }
+
+    pub async fn list_message_deliveries(
+        &self,
+        session_key: Option<&str>,
+        run_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<MessageDelivery>, DomainError> {
+        self.inner
+            .store
+            .list_message_deliveries(session_key, run_id, limit)
+            .await
+    }
+
+    pub async fn list_outbound_messages(
+        &self,
+        channel: Option<&str>,
+        due_at_ms: u64,
+        limit: usize,
+    ) -> Result<Vec<QueuedOutboundMessage>, DomainError> {
+        self.inner
+            .store
+            .list_outbound_messages(channel, due_at_ms, limit)
+            .await
+    }
+
+    pub async fn delete_outbound_message(&self, id: &str) -> Result<bool, DomainError> {
+        self.inner.store.delete_outbound_message(id).await
+    }
+
+    pub async fn record_outbound_attempt(&self, id: &str) -> Result<u32, DomainError> {
+        self.inner.store.record_outbound_attempt(id).await
+    }
+
+    pub async fn link_person_identity(
+        &self,
+        input: &IdentityLinkInput,
+    ) -> Result<PersonRecord, DomainError> {
+        self.inner.store.link_person_identity(input).await
+    }
+
+    pub async fn unlink_person_identity(
+        &self,
+        channel: &str,
+        external_id: &str,
+    ) -> Result<Option<String>, DomainError> {
+        self.inner
+            .store
+            .unlink_person_identity(channel, external_id)
+            .await
+    }
+
+    pub async fn get_person(&self, person_id: &str) -> Result<Option<PersonRecord>, DomainError> {
+        self.inner.store.get_person(person_id).await
+    }
+
+    pub async fn find_person_by_identity(
+        &self,
+        channel: &str,
+        external_id: &str,
+    ) -> Result<Option<PersonRecord>, DomainError> {
+        match self
+            .inner
+            .store
+            .find_person_id_by_identity(channel, external_id)
+            .await?
+        {
+            Some(person_id) => self.inner.store.get_person(&person_id).await,
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_persons(&self, limit: usize) -> Result<Vec<PersonRecord>, DomainError> {
+        self.inner.store.list_persons(limit).await
+    }
+
+    /// Resolves a human-readable name for a channel session key
+    /// (`agent:{agent}:{channel}:chat:{conversation}`) from the channel directory, or for a
+    /// shared person session (`agent:{agent}:person:{personId}`) from the linked person.
+    pub async fn resolve_session_display_name(&self, session_key: &str) -> Option<String> {
+        self.resolve_session_display_names(&[session_key])
+            .await
+            .remove(session_key)
+    }
+
+    /// [`Self::resolve_session_display_name`] for many sessions at once, with one person and one
+    /// directory lookup for the whole list. Keys without a name are left out.
+    pub async fn resolve_session_display_names(
+        &self,
+        session_keys: &[&str],
+    ) -> HashMap<String, String> {
+        let mut person_ids = Vec::new();
+        let mut conversations = Vec::new();
+        for session_key in session_keys {
+            match session_display_source(session_key) {
+                Some(SessionDisplaySource::Person(person_id)) => person_ids.push(person_id),
+                Some(SessionDisplaySource::Conversation(channel, conversation)) => {
+                    conversations.push((channel, conversation));
+                }
+                None => {}
+            }
+        }
+
+        let persons = if person_ids.is_empty() {
+            HashMap::new()
+        } else {
+            self.inner
+                .store
+                .person_display_names(&person_ids)
+                .await
+                .unwrap_or_default()
+        };
+        let entries = if conversations.is_empty() {
+            HashMap::new()
+        } else {
+            self.inner
+                .store
+                .get_channel_directory_entries(&conversations)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|entry| {
+                    (
+                        (entry.channel.clone(), entry.conversation_id.clone()),
+                        entry,
+                    )
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        let mut names = HashMap::new();
+        for session_key in session_keys {
+            let name = match session_display_source(session_key) {
+                Some(SessionDisplaySource::Person(person_id)) => persons
+                    .get(person_id)
+                    .map(|name| name.clone().unwrap_or_else(|| person_id.to_owned())),
+                Some(SessionDisplaySource::Conversation(channel, conversation)) => entries
+                    .get(&(channel.to_owned(), conversation.to_owned()))
+                    .and_then(|entry| {
+                        entry
+                            .title
+                            .clone()
+                            .or_else(|| entry.participants.last().cloned())
+                    })
+                    .map(|name| format!("{name} ({channel})")),
+                None => None,
+            };
+            if let Some(name) = name {
+                names.insert((*session_key).to_owned(), name);
+            }
+        }
+        names
+    }
+
+    /// Appends through the write buffer when `chatWriteBatchMs` is set: the messages are
+    /// committed with others a few milliseconds later, or before the next chat read.
     pub async fn append_chat_messages(
         &self,
         session_key: &str,
         messages: &[ChatMessage],
     ) -> Result<(), DomainError> {
+        if let Some(buffer) = &self.inner.chat_writes {
+            match buffer.push(session_key, messages).await {
+                Some(false) => return Ok(()),
+                Some(true) => return self.flush_chat_writes().await.map(|_| ()),
+                None => {}
+            }
+        }
         self.inner
             .store
             .append_chat_messages(session_key, messages)
             .await
     }
 
+    pub(crate) fn chat_write_buffer(&self) -> Option<&ChatWriteBuffer> {
+        self.inner.chat_writes.as_ref()
+    }
+
+    /// Commits buffered chat appends; a no-op without `chatWriteBatchMs`.
+    pub async fn flush_chat_writes(&self) -> Result<usize, DomainError> {
+        match &self.inner.chat_writes {
+            Some(buffer) => buffer.flush(&self.inner.store).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Flushes the write buffer for shutdown and sends later appends straight to the store.
+    pub async fn close_chat_writes(&self) {
+        if let Some(buffer) = &self.inner.chat_writes {
+            buffer
+                .close(
+                    &self.inner.store,
+                    &chat_write_buffer::spill_path(&self.config().db_path),
+                )
+                .await;
+        }
+    }
+
+    pub async fn search_chat_messages(
+        &self,
+        query: &str,
+        session_keys: Option<&[String]>,
+        limit: usize,
+    ) -> Result<Vec<(String, ChatMessage)>, DomainError> {
+        self.flush_chat_writes().await?;
+        self.inner
+            .store
+            .search_chat_messages(query, session_keys, limit)
+            .await
+    }
+
+    /// Lists the newest `limit` messages, oldest first, reading through archive segments when
+    /// SQLite alone cannot fill the window.
     pub async fn list_chat_messages(
         &self,
         session_key: &str,
         limit: Option<usize>,
     ) -> Result<Vec<ChatMessage>, DomainError> {
-        self.inner
+        self.flush_chat_writes().await?;
+        let messages = self
+            .inner
             .store
             .list_chat_messages(session_key, limit)
+            .await?;
+        match &self.config().chat_archive {
+            Some(archive) if limit.is_none_or(|limit| messages.len() < limit) => {
+                chat_archive::read_through(self, &archive.dir, session_key, messages, limit).await
+            }
+            _ => Ok(messages),
+        }
+    }
+
+    pub async fn list_archivable_chat_messages(
+        &self,
+        before_ms: u64,
+        limit: usize,
+    ) -> Result<Vec<(String, ChatMessage)>, DomainError> {
+        self.flush_chat_writes().await?;
+        self.inner
+            .store
+            .list_archivable_chat_messages(before_ms, limit)
+            .await
+    }
+
+    pub async fn vacuum_into(&self, path: &Path) -> Result<(), DomainError> {
+        self.flush_chat_writes().await?;
+        self.inner.store.vacuum_into(path).await
+    }
+
+    pub async fn restore_from_snapshot(&self, path: &Path) -> Result<u64, DomainError> {
+        self.inner.store.restore_from_snapshot(path).await
+    }
+
+    pub async fn set_change_capture(&self, enabled: bool) -> Result<(), DomainError> {
+        self.inner.store.set_change_capture(enabled).await
+    }
+
+    pub async fn list_replicated_changes(
+        &self,
+        after_seq: u64,
+        limit: usize,
+    ) -> Result<Option<Vec<ReplicatedChange>>, DomainError> {
+        self.flush_chat_writes().await?;
+        self.inner
+            .store
+            .list_replicated_changes(after_seq, limit)
+            .await
+    }
+
+    pub async fn apply_replicated_changes(
+        &self,
+        changes: &[ReplicatedChange],
+    ) -> Result<Option<u64>, DomainError> {
+        self.inner.store.apply_replicated_changes(changes).await
+    }
+
+    pub async fn prune_replicated_changes(
+        &self,
+        through_seq: u64,
+        before_ms: u64,
+    ) -> Result<u64, DomainError> {
+        self.inner
+            .store
+            .prune_replicated_changes(through_seq, before_ms)
+            .await
+    }
+
+    pub async fn load_replication_state(&self) -> Result<ReplicationState, DomainError> {
+        self.inner.store.load_replication_state().await
+    }
+
+    pub async fn set_replication_epoch(
+        &self,
+        epoch: u64,
+        fenced_at_ms: Option<u64>,
+        fenced_by_url: Option<&str>,
+    ) -> Result<(), DomainError> {
+        self.inner
+            .store
+            .set_replication_epoch(epoch, fenced_at_ms, fenced_by_url)
+            .await
+    }
+
+    pub async fn set_replication_peer(&self, peer_url: &str) -> Result<(), DomainError> {
+        self.inner.store.set_replication_peer(peer_url).await
+    }
+
+    pub async fn commit_chat_archive_segment(
+        &self,
+        segment: &ChatArchiveSegment,
+        message_ids: &[String],
+    ) -> Result<(), DomainError> {
+        self.inner
+            .store
+            .commit_chat_archive_segment(segment, message_ids)
+            .await
+    }
+
+    pub async fn list_chat_archive_segments(
+        &self,
+        session_key: &str,
+    ) -> Result<Vec<ChatArchiveSegment>, DomainError> {
+        self.inner
+            .store
+            .list_chat_archive_segments(session_key)
+            .await
+    }
+
+    pub async fn list_pinned_chat_messages(
+        &self,
+        session_key: &str,
+    ) -> Result<Vec<ChatMessage>, DomainError> {
+        // Pins can only name committed messages, so buffered appends cannot change this.
+        self.inner
+            .store
+            .list_pinned_chat_messages(session_key)
+            .await
+    }
+
+    pub async fn set_chat_message_pinned(
+        &self,
+        session_key: &str,
+        message_id: &str,
+        pinned: bool,
+        pinned_by: Option<&str>,
+    ) -> Result<bool, DomainError> {
+        self.flush_chat_writes().await?;
+        self.inner
+            .store
+            .set_chat_message_pinned(session_key, message_id, pinned, pinned_by)
+            .await
+    }
+
+    pub async fn mark_chat_read(
+        &self,
+        client_id: &str,
+        session_key: &str,
+        message_id: &str,
+    ) -> Result<Option<ChatReadMarker>, DomainError> {
+        self.flush_chat_writes().await?;
+        self.inner
+            .store
+            .mark_chat_read(client_id, session_key, message_id)
+            .await
+    }
+
+    pub async fn list_session_kv(
+        &self,
+        session_key: &str,
+    ) -> Result<Vec<SessionKvEntry>, DomainError> {
+        self.inner.store.list_session_kv(session_key).await
+    }
+
+    pub async fn get_session_kv(
+        &self,
+        session_key: &str,
+        key: &str,
+    ) -> Result<Option<SessionKvEntry>, DomainError> {
+        self.inner.store.get_session_kv(session_key, key).await
+    }
+
+    pub async fn set_session_kv(
+        &self,
+        session_key: &str,
+        entry: &SessionKvEntry,
+        max_keys: usize,
+        max_total_bytes: u64,
+    ) -> Result<(), DomainError> {
+        self.inner
+            .store
+            .set_session_kv(session_key, entry, max_keys, max_total_bytes)
+            .await
+    }
+
+    pub async fn delete_session_kv(
+        &self,
+        session_key: &str,
+        key: &str,
+    ) -> Result<bool, DomainError> {
+        self.inner.store.delete_session_kv(session_key, key).await
+    }
+
+    pub async fn count_unread_chat_messages(
+        &self,
+        client_id: &str,
+    ) -> Result<HashMap<String, u64>, DomainError> {
+        self.flush_chat_writes().await?;
+        self.inner.store.count_unread_chat_messages(client_id).await
+    }
+
+    pub async fn migrate_to_postgres(
+        &self,
+        target_url: &str,
+        options: PostgresMigrationOptions,
+        progress: &(dyn Fn(&MigrationProgress) + Send + Sync),
+    ) -> Result<PostgresMigrationReport, DomainError> {
+        self.flush_chat_writes().await?;
+        self.inner
+            .store
+            .migrate_to_postgres(target_url, options, progress)
             .await
     }
 
     pub async fn count_chat_messages(&self) -> Result<u64, DomainError> {
+        self.flush_chat_writes().await?;
         self.inner.store.count_chat_messages().await
     }
 
@@ -460,6 +2190,15 @@ impl SharedState {
         self.inner.store.count_agent_runs().await
     }
 
+    pub async fn agent_run_costs(
+        &self,
+        scope: RunCostScope,
+        key: Option<&str>,
+        since_ms: u64,
+    ) -> Result<Vec<(String, f64)>, DomainError> {
+        self.inner.store.agent_run_costs(scope, key, since_ms).await
+    }
+
     pub async fn list_agent_runs_by_session(
         &self,
         session_key: &str,
@@ -500,14 +2239,47 @@ impl SharedState {
         job_id: Option<&str>,
         limit: Option<usize>,
     ) -> Result<Vec<CronRunRecord>, DomainError> {
-        self.inner.store.list_cron_runs(job_id, limit).await
+        self.query_cron_runs(&CronRunQuery {
+            job_id: job_id.map(str::to_owned),
+            limit,
+            ..CronRunQuery::default()
+        })
+        .await
+    }
+
+    pub async fn query_cron_runs(
+        &self,
+        query: &CronRunQuery,
+    ) -> Result<Vec<CronRunRecord>, DomainError> {
+        self.inner.store.list_cron_runs(query).await
     }
 
-    pub async fn cron_status(&self) -> Result<Value, DomainError> {
-        let jobs = self.list_cron_jobs().await?;
+    pub async fn cron_run_stats(
+        &self,
+        query: &CronRunQuery,
+    ) -> Result<Vec<CronRunStats>, DomainError> {
+        self.inner.store.cron_run_stats(query).await
+    }
+
+    pub async fn cron_status(&self, locale: &str) -> Result<Value, DomainError> {
+        let now = now_unix_ms();
+        let jobs = self
+            .list_cron_jobs()
+            .await?
+            .iter()
+            .map(|job| {
+                let mut value = json!(job);
+                value["description"] = json!(describe_job(job, now, locale));
+                let (timezone, next_run_local) = local_next_run(job);
+                value["timezone"] = json!(timezone);
+                value["nextRunLocal"] = json!(next_run_local);
+                value
+            })
+            .collect::<Vec<_>>();
         let runs = self.list_cron_runs(None, Some(50)).await?;
         let enabled = *self.inner.cron_enabled.read().await;
         let last_tick_ms = *self.inner.cron_last_tick_ms.read().await;
+        let max_workers = self.config().cron_max_workers;
 
         Ok(json!({
             "enabled": enabled,
@@ -515,14 +2287,95 @@ impl SharedState {
             "runs": runs,
             "lastTickMs": last_tick_ms,
             "pollIntervalMs": self.config().cron_poll_interval.as_millis(),
+            "schedulerLagMs": self.inner.cron_scheduler_lag_ms.load(Ordering::Relaxed),
+            "workers": {
+                "max": max_workers,
+                "busy": max_workers.saturating_sub(self.inner.cron_workers.available_permits()),
+                "queued": self.inner.cron_queued.load(Ordering::SeqCst),
+            },
             "storePath": self.config().db_path.display().to_string(),
         }))
     }
 
+    /// Returns the live or stored state of `run_id`, or of the latest run of `job_id` (a running one
+    /// wins over stored history).
+    pub async fn cron_run_tail(
+        &self,
+        run_id: Option<&str>,
+        job_id: Option<&str>,
+    ) -> Result<Option<CronRunTail>, DomainError> {
+        let live = {
+            let runs = self.inner.cron_live_runs.read().await;
+            match (run_id, job_id) {
+                (Some(run_id), _) => runs.get_key_value(run_id),
+                (None, Some(job_id)) => runs
+                    .iter()
+                    .filter(|(_, run)| run.job_id == job_id)
+                    .max_by_key(|(_, run)| run.started_at_ms),
+                (None, None) => None,
+            }
+            .map(|(run_id, run)| CronRunTail::Running {
+                run_id: run_id.clone(),
+                job_id: run.job_id.clone(),
+                manual: run.manual,
+                started_at_ms: run.started_at_ms,
+                chunks: run.chunks.clone(),
+            })
+        };
+        if live.is_some() {
+            return Ok(live);
+        }
+
+        let stored = match (run_id, job_id) {
+            (Some(run_id), _) => self.inner.store.get_cron_run(run_id).await?,
+            (None, Some(job_id)) => self
+                .list_cron_runs(Some(job_id), Some(1))
+                .await?
+                .into_iter()
+                .next(),
+            (None, None) => None,
+        };
+        Ok(stored.map(CronRunTail::Finished))
+    }
+
+    /// Buffers output for a running cron run and streams it as a `cron` `output` event.
+    pub async fn append_cron_run_output(&self, run_id: &str, text: &str) {
+        let (job_id, chunk) = {
+            let mut runs = self.inner.cron_live_runs.write().await;
+            let Some(run) = runs.get_mut(run_id) else {
+                return;
+            };
+            let chunk = CronOutputChunk {
+                seq: u64::try_from(run.chunks.len() + 1).unwrap_or(u64::MAX),
+                text: text.to_owned(),
+                ts_ms: now_unix_ms(),
+            };
+            run.chunks.push(chunk.clone());
+            (run.job_id.clone(), chunk)
+        };
+
+        self.publish_gateway_event(
+            "cron",
+            json!({
+                "phase": "output",
+                "runId": run_id,
+                "jobId": job_id,
+                "seq": chunk.seq,
+                "text": chunk.text,
+            }),
+        )
+        .await;
+    }
+
+    /// Runs `id` on the next free cron worker and waits for it. Manual runs count toward the
+    /// job's `maxConcurrent` but are never refused by it.
     pub async fn run_cron_job_now(&self, id: &str) -> Result<CronRunRecord, DomainError> {
-        self.run_cron_job_internal(id, true).await
+        self.claim_cron_job_run(id, None).await;
+        self.run_claimed_cron_job(id, true).await
     }
 
+    /// Dispatches every due job whose `maxConcurrent` allows another run to the cron worker pool
+    /// and returns how many were dispatched; the runs complete in the background.
     pub async fn tick_cron_jobs(&self) -> Result<usize, DomainError> {
         if !*self.inner.cron_enabled.read().await {
             return Ok(0);
@@ -534,42 +2387,186 @@ impl SharedState {
             *last_tick = Some(now);
         }
 
-        let due_job_ids = self
+        let due_jobs = self
             .list_cron_jobs()
             .await?
             .into_iter()
             .filter(|job| job.enabled && job.next_run_ms.is_some_and(|next| next <= now))
-            .map(|job| job.id)
             .collect::<Vec<_>>();
+        let lag_ms = due_jobs
+            .iter()
+            .filter_map(|job| job.next_run_ms)
+            .map(|next| now.saturating_sub(next))
+            .max()
+            .unwrap_or(0);
+        self.inner
+            .cron_scheduler_lag_ms
+            .store(lag_ms, Ordering::Relaxed);
+
+        let mut dispatched = 0_usize;
+        for job in due_jobs {
+            if !self
+                .claim_cron_job_run(&job.id, Some(job.max_concurrent.unwrap_or(1)))
+                .await
+            {
+                continue;
+            }
+            // Move past the occurrence being dispatched so later ticks do not start it again;
+            // the finished run reschedules from its finish time as before.
+            let next_run_ms = compute_next_run_ms(&job.schedule, now).unwrap_or(None);
+            if let Err(error) = self
+                .inner
+                .store
+                .update_cron_job_runtime(&job.id, job.last_run_ms, next_run_ms)
+                .await
+            {
+                self.release_cron_job_run(&job.id).await;
+                return Err(error);
+            }
+            let state = self.clone();
+            tokio::spawn(async move {
+                if let Err(error) = state.run_claimed_cron_job(&job.id, false).await {
+                    tracing::warn!("cron job {} failed to run: {error}", job.id);
+                }
+            });
+            dispatched = dispatched.saturating_add(1);
+        }
+
+        Ok(dispatched)
+    }
 
-        let mut executed = 0_usize;
-        for id in due_job_ids {
-            if self.run_cron_job_internal(&id, false).await.is_ok() {
-                executed = executed.saturating_add(1);
+    /// Counts a queued run of `id`. With a `limit`, refuses once that many runs are queued or
+    /// executing.
+    async fn claim_cron_job_run(&self, id: &str, limit: Option<u32>) -> bool {
+        let mut runs = self.inner.cron_job_runs.write().await;
+        let active = runs.entry(id.to_owned()).or_insert(0);
+        if limit.is_some_and(|limit| *active >= limit) {
+            return false;
+        }
+        *active += 1;
+        true
+    }
+
+    async fn release_cron_job_run(&self, id: &str) {
+        let mut runs = self.inner.cron_job_runs.write().await;
+        if let Some(active) = runs.get_mut(id) {
+            *active = active.saturating_sub(1);
+            if *active == 0 {
+                runs.remove(id);
             }
         }
+    }
 
-        Ok(executed)
+    /// Waits for a cron worker, runs the claimed job on it, and releases the claim.
+    async fn run_claimed_cron_job(
+        &self,
+        id: &str,
+        manual: bool,
+    ) -> Result<CronRunRecord, DomainError> {
+        let queued_at = Instant::now();
+        self.inner.cron_queued.fetch_add(1, Ordering::SeqCst);
+        let permit = self.inner.cron_workers.clone().acquire_owned().await;
+        self.inner.cron_queued.fetch_sub(1, Ordering::SeqCst);
+        let queue_wait_ms = u64::try_from(queued_at.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+        let result = match permit {
+            Ok(_permit) => {
+                let _work = self.lifecycle().begin_work();
+                self.run_cron_job_internal(id, manual, queue_wait_ms).await
+            }
+            Err(_) => Err(DomainError::Unavailable(
+                "cron worker pool is closed".to_owned(),
+            )),
+        };
+        self.release_cron_job_run(id).await;
+        result
     }
 
     async fn run_cron_job_internal(
         &self,
         id: &str,
         manual: bool,
+        queue_wait_ms: u64,
     ) -> Result<CronRunRecord, DomainError> {
         let Some(mut job) = self.get_cron_job(id).await? else {
             return Err(DomainError::NotFound(format!("cron job not found: {id}")));
         };
 
+        let run_id = format!("run-{}", uuid::Uuid::new_v4());
         let started = now_unix_ms();
-        let result = execute_cron_payload(&job.payload, started);
-        let finished = now_unix_ms();
-
+        self.inner.cron_live_runs.write().await.insert(
+            run_id.clone(),
+            LiveCronRun {
+                job_id: job.id.clone(),
+                manual,
+                started_at_ms: started,
+                chunks: Vec::new(),
+            },
+        );
+        self.publish_gateway_event(
+            "cron",
+            json!({
+                "phase": "started",
+                "runId": run_id,
+                "jobId": job.id,
+                "manual": manual,
+                "startedAtMs": started,
+                "queueWaitMs": queue_wait_ms,
+            }),
+        )
+        .await;
+
+        // Scripts stream their `print` lines and agent turns their reply text as they run.
+        let (result, agent_run_id) = if job.payload.kind == "script" {
+            (
+                cron_script::run_payload(self, &run_id, &job.payload).await,
+                None,
+            )
+        } else {
+            execute_cron_payload(self, &job, &run_id, started).await
+        };
         let (status, output, error) = match result {
             Ok(output) => ("ok".to_owned(), Some(output), None),
             Err(error) => ("error".to_owned(), None, Some(error)),
         };
+        let run = CronRunRecord {
+            id: run_id.clone(),
+            job_id: job.id.clone(),
+            status,
+            output,
+            error,
+            manual,
+            started_at_ms: started,
+            finished_at_ms: now_unix_ms(),
+            queue_wait_ms,
+            agent_run_id,
+        };
+        let recorded = self.finish_cron_run(&mut job, run).await;
+        // The stored run supersedes the live buffer only once it is readable (or failed to store).
+        self.inner.cron_live_runs.write().await.remove(&run_id);
+        let run = recorded?;
+        self.publish_gateway_event(
+            "cron",
+            json!({
+                "phase": "finished",
+                "runId": run.id,
+                "jobId": run.job_id,
+                "status": run.status,
+                "error": run.error,
+                "agentRunId": run.agent_run_id,
+                "finishedAtMs": run.finished_at_ms,
+            }),
+        )
+        .await;
+        Ok(run)
+    }
 
+    async fn finish_cron_run(
+        &self,
+        job: &mut CronJobRecord,
+        run: CronRunRecord,
+    ) -> Result<CronRunRecord, DomainError> {
+        let finished = run.finished_at_ms;
         job.last_run_ms = Some(finished);
         job.updated_at_ms = finished;
         job.next_run_ms =
@@ -586,26 +2583,19 @@ impl SharedState {
                     payload: Some(job.payload.clone()),
                     metadata: Some(job.metadata.clone()),
                     next_run_ms: Some(job.next_run_ms),
+                    max_concurrent: Some(job.max_concurrent),
                 },
             )
             .await?;
 
-        let run = CronRunRecord {
-            id: format!("run-{}", uuid::Uuid::new_v4()),
-            job_id: job.id.clone(),
-            status,
-            output,
-            error,
-            manual,
-            started_at_ms: started,
-            finished_at_ms: finished,
-        };
-
         self.inner.store.add_cron_run(&run).await?;
-        self.inner
-            .store
-            .prune_cron_runs(self.config().cron_runs_limit)
-            .await?;
+        // Pruned runs would only move to tombstones in append-only mode, so history is kept.
+        if !self.config().append_only {
+            self.inner
+                .store
+                .prune_cron_runs(self.config().cron_runs_limit)
+                .await?;
+        }
         Ok(run)
     }
 
@@ -629,11 +2619,17 @@ impl SharedState {
         self.inner.store.rename_node(id, display_name).await
     }
 
+    /// Stores a pending pair request that expires after the configured node pairing window.
     pub async fn add_node_pair_request(
         &self,
         input: NodePairRequestInput,
     ) -> Result<NodePairRequestRecord, DomainError> {
-        self.inner.store.add_node_pair_request(input).await
+        let window =
+            u64::try_from(self.config().request_expiry.node_pair.as_millis()).unwrap_or(u64::MAX);
+        self.inner
+            .store
+            .add_node_pair_request(input, now_unix_ms().saturating_add(window))
+            .await
     }
 
     pub async fn list_node_pair_requests(&self) -> Result<Vec<NodePairRequestRecord>, DomainError> {
@@ -679,6 +2675,162 @@ impl SharedState {
         self.inner.store.get_node_invoke(request_id).await
     }
 
+    pub async fn queue_node_invoke(
+        &self,
+        input: NodeInvokeInput,
+        expires_at_ms: u64,
+    ) -> Result<QueuedNodeInvoke, DomainError> {
+        self.inner
+            .store
+            .queue_node_invoke(input, expires_at_ms)
+            .await
+    }
+
+    /// Lists live queued invokes, expiring any that are past their deadline first.
+    pub async fn list_queued_node_invokes(
+        &self,
+        node_id: Option<&str>,
+    ) -> Result<Vec<QueuedNodeInvoke>, DomainError> {
+        self.inner
+            .store
+            .expire_queued_node_invokes(now_unix_ms())
+            .await?;
+        self.inner.store.list_queued_node_invokes(node_id).await
+    }
+
+    pub async fn cancel_queued_node_invoke(
+        &self,
+        request_id: &str,
+    ) -> Result<Option<NodeInvokeRecord>, DomainError> {
+        self.inner
+            .store
+            .dequeue_node_invoke(request_id, "cancelled")
+            .await
+    }
+
+    /// Pushes invokes queued while the node was offline to its freshly connected `conn_id` as
+    /// `node.invoke.request` events. Returns how many were delivered.
+    pub async fn deliver_queued_node_invokes(&self, conn_id: &str) -> Result<usize, DomainError> {
+        let Some(node_id) = self
+            .inner
+            .clients
+            .read()
+            .await
+            .get(conn_id)
+            .filter(|client| client.role == "node")
+            .map(runtime_node_id)
+        else {
+            return Ok(0);
+        };
+
+        let invokes = self.take_queued_node_invokes(&node_id).await?;
+        for invoke in &invokes {
+            self.publish_gateway_event_for(
+                Some(conn_id),
+                "node.invoke.request",
+                queued_node_invoke_payload(invoke),
+            )
+            .await;
+        }
+        Ok(invokes.len())
+    }
+
+    /// Pushes `node_id`'s queued invokes to its live connection right away, if it has one.
+    pub async fn deliver_queued_node_invokes_to(
+        &self,
+        node_id: &str,
+    ) -> Result<usize, DomainError> {
+        let conn_id = self
+            .inner
+            .clients
+            .read()
+            .await
+            .values()
+            .find(|client| client.role == "node" && runtime_node_id(client) == node_id)
+            .map(|client| client.conn_id.clone());
+        match conn_id {
+            Some(conn_id) => self.deliver_queued_node_invokes(&conn_id).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Takes the live invokes queued for `node_id`, marking them `delivered`.
+    pub async fn take_queued_node_invokes(
+        &self,
+        node_id: &str,
+    ) -> Result<Vec<NodeInvokeRecord>, DomainError> {
+        let mut taken = Vec::new();
+        for queued in self.list_queued_node_invokes(Some(node_id)).await? {
+            if let Some(invoke) = self
+                .inner
+                .store
+                .dequeue_node_invoke(&queued.invoke.request_id, "delivered")
+                .await?
+            {
+                taken.push(invoke);
+            }
+        }
+        Ok(taken)
+    }
+
+    /// Records a `/nodes/{id}/poll` heartbeat. A polling node shows as `polling` unless it is
+    /// also connected over WebSocket, so `node.invoke` with `queueIfOffline` queues for it.
+    pub async fn record_node_poll(
+        &self,
+        node_id: &str,
+        display_name: Option<String>,
+        platform: Option<String>,
+    ) -> Result<NodeRecord, DomainError> {
+        let now = now_unix_ms();
+        let mut node = match self.inner.store.get_node(node_id).await? {
+            Some(node) => node,
+            None => NodeRecord {
+                id: node_id.to_owned(),
+                display_name: node_id.to_owned(),
+                platform: "unknown".to_owned(),
+                device_family: None,
+                commands: Vec::new(),
+                paired: true,
+                status: NODE_STATUS_POLLING.to_owned(),
+                last_seen_ms: now,
+                metadata: json!({}),
+            },
+        };
+        if node.status != "online" {
+            node.status = NODE_STATUS_POLLING.to_owned();
+        }
+        if let Some(display_name) = display_name {
+            node.display_name = display_name;
+        }
+        if let Some(platform) = platform {
+            node.platform = platform;
+        }
+        node.last_seen_ms = now;
+        self.inner.store.upsert_node(&node).await?;
+        Ok(node)
+    }
+
+    /// Acknowledges journaled events up to `ack_seq` for a polling node and returns the
+    /// acknowledged sequence number with the events it is still owed, oldest first.
+    pub async fn poll_node_events(
+        &self,
+        node_id: &str,
+        ack_seq: Option<u64>,
+    ) -> Result<(u64, Vec<JournaledEvent>), DomainError> {
+        let key = format!("node:{node_id}");
+        let now = now_unix_ms();
+        let mut acked_seq = self.inner.store.open_event_cursor(&key, now).await?;
+        if let Some(seq) = ack_seq {
+            acked_seq = self.inner.store.ack_events(&key, seq, now).await?.0;
+        }
+        let events = self
+            .inner
+            .store
+            .list_unacked_events(&key, acked_seq, EVENT_REPLAY_LIMIT)
+            .await?;
+        Ok((acked_seq, events))
+    }
+
     pub async fn add_node_event(
         &self,
         node_id: String,
@@ -702,6 +2854,102 @@ impl SharedState {
         self.inner.store.list_node_events(node_id, limit).await
     }
 
+    pub async fn add_node_metadata_entry(
+        &self,
+        node_id: &str,
+        metadata: Value,
+    ) -> Result<NodeMetadataEntry, DomainError> {
+        let entry = self
+            .inner
+            .store
+            .add_node_metadata_entry(node_id, metadata)
+            .await?;
+        self.inner
+            .store
+            .trim_node_metadata_history(node_id, 500)
+            .await?;
+        Ok(entry)
+    }
+
+    pub async fn list_node_metadata_history(
+        &self,
+        node_id: &str,
+        limit: usize,
+    ) -> Result<Vec<NodeMetadataEntry>, DomainError> {
+        self.inner
+            .store
+            .list_node_metadata_history(node_id, limit)
+            .await
+    }
+
+    pub async fn upsert_node_file_transfer(
+        &self,
+        transfer: &NodeFileTransfer,
+    ) -> Result<(), DomainError> {
+        self.inner.store.upsert_node_file_transfer(transfer).await
+    }
+
+    pub async fn get_node_file_transfer(
+        &self,
+        transfer_id: &str,
+    ) -> Result<Option<NodeFileTransfer>, DomainError> {
+        self.inner.store.get_node_file_transfer(transfer_id).await
+    }
+
+    pub async fn list_node_file_transfers(
+        &self,
+        node_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<NodeFileTransfer>, DomainError> {
+        self.inner
+            .store
+            .list_node_file_transfers(node_id, limit)
+            .await
+    }
+
+    /// Marks open transfers past their deadline `expired` and returns them.
+    pub async fn expire_node_file_transfers(&self) -> Result<Vec<NodeFileTransfer>, DomainError> {
+        self.inner
+            .store
+            .expire_node_file_transfers(now_unix_ms())
+            .await
+    }
+
+    /// Claims the pull transfer for one chunk upload; `false` while another is being written.
+    pub async fn begin_node_file_upload(&self, transfer_id: &str) -> bool {
+        self.inner
+            .node_file_uploads
+            .write()
+            .await
+            .insert(transfer_id.to_owned())
+    }
+
+    pub async fn end_node_file_upload(&self, transfer_id: &str) {
+        self.inner
+            .node_file_uploads
+            .write()
+            .await
+            .remove(transfer_id);
+    }
+
+    /// Held while the stored device state is loaded, changed, and saved back.
+    pub(crate) async fn lock_device_state(&self) -> MutexGuard<'_, ()> {
+        self.inner.device_state_writes.lock().await
+    }
+
+    /// Records a federation request nonce until `expires_at_ms`; `false` when it was already
+    /// seen, i.e. the request is a replay.
+    pub async fn remember_federation_nonce(&self, key: &str, expires_at_ms: u64) -> bool {
+        let now = now_unix_ms();
+        let mut nonces = self.inner.federation_nonces.write().await;
+        nonces.retain(|_, expires| *expires > now);
+        if nonces.contains_key(key) {
+            return false;
+        }
+        nonces.insert(key.to_owned(), expires_at_ms);
+        true
+    }
+
     async fn presence_entries(&self) -> Vec<PresenceEntry> {
         let now = Instant::now();
         self.inner
@@ -709,54 +2957,283 @@ impl SharedState {
             .read()
             .await
             .values()
-            .map(|client| PresenceEntry {
-                host: client
-                    .display_name
-                    .clone()
-                    .or_else(|| Some(client.client_id.clone())),
-                ip: client.remote_ip.clone(),
-                version: Some(client.client_version.clone()),
-                platform: Some(client.platform.clone()),
-                device_family: client.device_family.clone(),
-                model_identifier: client.model_identifier.clone(),
-                mode: Some(client.mode.clone()),
-                last_input_seconds: Some(now.duration_since(client.connected_at).as_secs()),
-                reason: Some("connect".to_owned()),
-                tags: None,
-                text: None,
-                ts: client.connected_at_ms,
-                device_id: None,
-                roles: Some(vec![client.role.clone()]),
-                scopes: if client.scopes.is_empty() {
-                    None
-                } else {
-                    Some(client.scopes.clone())
-                },
-                instance_id: client.instance_id.clone(),
-            })
+            .map(|client| presence_entry(client, now))
             .collect()
     }
+
+    /// Sends a single-entry `presence` event to connections that negotiated delta sync.
+    async fn publish_presence_delta(&self, action: &str, client: &ConnectedClient) {
+        let targets = self
+            .inner
+            .clients
+            .read()
+            .await
+            .values()
+            .filter(|other| other.conn_id != client.conn_id && other.features.supports_delta_sync)
+            .map(|other| other.conn_id.clone())
+            .collect::<Vec<_>>();
+        if targets.is_empty() {
+            return;
+        }
+
+        let payload = json!({
+            "action": action,
+            "connId": client.conn_id,
+            "entry": presence_entry(client, Instant::now()),
+            "stateVersion": self.inner.presence_version.load(Ordering::Relaxed),
+        });
+        for conn_id in targets {
+            self.publish_gateway_event_for(Some(&conn_id), "presence", payload.clone())
+                .await;
+        }
+    }
+
+    /// Features negotiated by the node's live connection, if it has one.
+    pub async fn node_features(&self, node_id: &str) -> Option<ClientFeatures> {
+        self.inner
+            .clients
+            .read()
+            .await
+            .values()
+            .find(|client| client.role == "node" && runtime_node_id(client) == node_id)
+            .map(|client| client.features)
+    }
+
+    /// Node id registered for the live node connection `conn_id`.
+    pub async fn node_id_for_connection(&self, conn_id: &str) -> Option<String> {
+        self.inner
+            .clients
+            .read()
+            .await
+            .get(conn_id)
+            .filter(|client| client.role == "node")
+            .map(runtime_node_id)
+    }
+}
+
+fn presence_entry(client: &ConnectedClient, now: Instant) -> PresenceEntry {
+    PresenceEntry {
+        host: client
+            .display_name
+            .clone()
+            .or_else(|| Some(client.client_id.clone())),
+        ip: client.remote_ip.clone(),
+        version: Some(client.client_version.clone()),
+        platform: Some(client.platform.clone()),
+        device_family: client.device_family.clone(),
+        model_identifier: client.model_identifier.clone(),
+        mode: Some(client.mode.clone()),
+        last_input_seconds: Some(now.duration_since(client.connected_at).as_secs()),
+        reason: Some("connect".to_owned()),
+        tags: None,
+        text: None,
+        ts: client.connected_at_ms,
+        device_id: None,
+        roles: Some(vec![client.role.clone()]),
+        scopes: if client.scopes.is_empty() {
+            None
+        } else {
+            Some(client.scopes.clone())
+        },
+        instance_id: client.instance_id.clone(),
+        features: (client.features != ClientFeatures::default()).then_some(client.features),
+    }
 }
 
-fn execute_cron_payload(
-    payload: &crate::domain::models::CronPayload,
+/// Runs a non-script payload. `agentTurn` dispatches an agent run and also returns its id once
+/// the run was recorded, so failed runs stay traceable.
+async fn execute_cron_payload(
+    state: &SharedState,
+    job: &CronJobRecord,
+    cron_run_id: &str,
     ts: u64,
-) -> Result<String, String> {
+) -> (Result<String, String>, Option<String>) {
+    let payload = &job.payload;
     match payload.kind.as_str() {
-        "systemEvent" => Ok(format!(
-            "systemEvent:{} @{}",
-            payload.text.clone().unwrap_or_default(),
-            ts
-        )),
-        "agentTurn" => Ok(format!(
-            "agentTurn:{} @{}",
-            payload.message.clone().unwrap_or_default(),
-            ts
-        )),
-        other => Err(format!("unsupported cron payload kind: {other}")),
+        "systemEvent" => {
+            let output = format!(
+                "systemEvent:{} @{}",
+                payload.text.clone().unwrap_or_default(),
+                ts
+            );
+            state.append_cron_run_output(cron_run_id, &output).await;
+            (Ok(output), None)
+        }
+        "agentTurn" => {
+            let agent_id = payload
+                .agent_id
+                .as_deref()
+                .map(str::trim)
+                .filter(|agent_id| !agent_id.is_empty())
+                .unwrap_or("main");
+            let session_key = match &payload.session_key {
+                Some(session_key) => canonicalize_session_key(session_key),
+                None => canonicalize_session_key(&format!("agent:{agent_id}:cron:{}", job.id)),
+            };
+            let session_key = match session_key {
+                Ok(session_key) => session_key,
+                Err(error) => return (Err(format!("invalid agentTurn sessionKey: {error}")), None),
+            };
+            let agent_run_id = format!("cron-{cron_run_id}");
+            let session = SessionContext {
+                conn_id: format!("cron-{cron_run_id}"),
+                role: "operator".to_owned(),
+                scopes: policy::default_operator_scopes(),
+                client_id: "cron".to_owned(),
+                client_mode: "cron".to_owned(),
+            };
+            let params = json!({
+                "runId": agent_run_id,
+                "agentId": agent_id,
+                "sessionKey": session_key,
+                "message": payload.message,
+            });
+            let (tx, mut rx) = unbounded_channel();
+            let mut respond = std::pin::pin!(agent::handle_agent_streaming(
+                state,
+                &session,
+                Some(&params),
+                tx
+            ));
+            let response = loop {
+                tokio::select! {
+                    biased;
+                    Some(delta) = rx.recv() => state.append_cron_run_output(cron_run_id, &delta).await,
+                    response = &mut respond => break response,
+                }
+            };
+            while let Ok(delta) = rx.try_recv() {
+                state.append_cron_run_output(cron_run_id, &delta).await;
+            }
+            match response {
+                Ok(response) => (
+                    Ok(response
+                        .pointer("/result/output")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_owned()),
+                    Some(agent_run_id),
+                ),
+                Err(error) => {
+                    let recorded = matches!(state.get_agent_run(&agent_run_id).await, Ok(Some(_)));
+                    (Err(error.message), recorded.then_some(agent_run_id))
+                }
+            }
+        }
+        other => (Err(format!("unsupported cron payload kind: {other}")), None),
+    }
+}
+
+async fn load_store_id(store: &SqliteStore) -> Result<String, DomainError> {
+    if let Some(id) = store
+        .get_config_entry(STORE_ID_KEY)
+        .await?
+        .and_then(|entry| entry.value.as_str().map(str::to_owned))
+    {
+        return Ok(id);
+    }
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    store
+        .set_config_entry(STORE_ID_KEY, &Value::String(id.clone()))
+        .await?;
+    Ok(id)
+}
+
+/// Nodes share a connection cap per node id; operators per credential, i.e. the paired device
+/// whose token they presented, else the gateway's shared secret.
+fn connection_limit_key(client: &ConnectedClient) -> String {
+    if client.role == "node" {
+        return format!("node:{}", runtime_node_id(client));
+    }
+    match &client.device_id {
+        Some(device_id) => format!("operator:device:{device_id}"),
+        None => "operator:gateway".to_owned(),
     }
 }
 
+/// Live connections `client` displaces under `limits`, oldest first, or `Err(limit)` when the
+/// cap rejects it. Connections already told to close no longer count.
+fn connections_over_limit(
+    limits: ConnectionLimits,
+    clients: &HashMap<String, ConnectedClient>,
+    evictors: &HashMap<String, oneshot::Sender<String>>,
+    client: &ConnectedClient,
+    key: &str,
+) -> Result<Vec<String>, usize> {
+    let max = limits.max_for_role(&client.role);
+    if max == 0 {
+        return Ok(Vec::new());
+    }
+    let mut holders = clients
+        .values()
+        .filter(|existing| {
+            existing.role == client.role
+                && evictors.contains_key(&existing.conn_id)
+                && connection_limit_key(existing) == key
+        })
+        .map(|existing| (existing.connected_at, existing.conn_id.clone()))
+        .collect::<Vec<_>>();
+    if holders.len() < max {
+        return Ok(Vec::new());
+    }
+    if limits.action == ConnectionLimitAction::Reject {
+        return Err(max);
+    }
+    holders.sort();
+    let excess = holders.len() + 1 - max;
+    Ok(holders
+        .into_iter()
+        .take(excess)
+        .map(|(_, conn_id)| conn_id)
+        .collect())
+}
+
+/// `node.invoke.request` payload for an invoke that waited in the offline queue.
+pub fn queued_node_invoke_payload(invoke: &NodeInvokeRecord) -> Value {
+    json!({
+        "requestId": invoke.request_id,
+        "nodeId": invoke.node_id,
+        "command": invoke.command,
+        "args": invoke.args,
+        "input": invoke.input,
+        "requestedAtMs": invoke.requested_at_ms,
+        "queued": true,
+    })
+}
+
+/// Where a session's display name comes from, parsed from its key.
+enum SessionDisplaySource<'a> {
+    Person(&'a str),
+    Conversation(&'a str, &'a str),
+}
+
+fn session_display_source(session_key: &str) -> Option<SessionDisplaySource<'_>> {
+    if let Some(person_id) = session_key
+        .strip_prefix("agent:")
+        .and_then(|rest| rest.split_once(":person:"))
+        .map(|(_, person_id)| person_id)
+    {
+        return Some(SessionDisplaySource::Person(person_id));
+    }
+
+    let mut parts = session_key.split(':');
+    let (Some("agent"), Some(_agent), Some(channel), Some("chat"), Some(conversation)) = (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) else {
+        return None;
+    };
+    Some(SessionDisplaySource::Conversation(channel, conversation))
+}
+
+/// Identifies a client across reconnects for the event journal.
+fn event_ack_key(client: &ConnectedClient) -> String {
+    format!("{}:{}", client.role, runtime_node_id(client))
+}
+
 fn runtime_node_id(client: &ConnectedClient) -> String {
     client
         .instance_id
@@ -777,7 +3254,9 @@ pub fn sanitize_scopes(scopes: &[String]) -> Vec<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::sanitize_scopes;
+    use serde_json::json;
+
+    use super::{GatewayEventFilter, sanitize_scopes};
 
     #[test]
     fn sanitize_scopes_deduplicates_values() {
@@ -792,4 +3271,19 @@ mod tests {
         assert!(sanitized.contains(&"operator.admin".to_owned()));
         assert!(sanitized.contains(&"operator.config.write".to_owned()));
     }
+
+    #[test]
+    fn gateway_event_filter_scopes_by_session_and_node() {
+        let filter = GatewayEventFilter {
+            events: ["chat".to_owned(), "node.geofence".to_owned()].into(),
+            session_key: Some("agent:main:a".to_owned()),
+            node_id: Some("node-1".to_owned()),
+        };
+        assert!(filter.allows("chat", &json!({ "sessionKey": "agent:main:a" })));
+        assert!(!filter.allows("chat", &json!({ "sessionKey": "agent:main:b" })));
+        assert!(filter.allows("node.geofence", &json!({ "nodeId": "node-1" })));
+        assert!(!filter.allows("node.geofence", &json!({ "nodeId": "node-2" })));
+        assert!(filter.allows("chat", &json!({ "state": "final" })));
+        assert!(!filter.allows("presence", &json!({})));
+    }
 }
@@ -0,0 +1,198 @@
+//! systemd integration: socket activation (`LISTEN_FDS`) and `sd_notify` readiness, status, and
+//! watchdog messages. Everything is a no-op when the process was not started by systemd.
+
+use std::{net::SocketAddr, time::Duration};
+
+use tracing::{info, warn};
+
+use crate::application::state::SharedState;
+
+/// First file descriptor systemd passes to a socket-activated service.
+const LISTEN_FDS_START: i32 = 3;
+
+/// Takes over the first socket passed by systemd socket activation, so the gateway can serve a
+/// privileged port it could not bind itself. Returns `None` when the variables are absent or
+/// addressed to another process.
+pub fn activated_listener() -> Result<Option<std::net::TcpListener>, String> {
+    let Some(count) = activated_fd_count(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    )?
+    else {
+        return Ok(None);
+    };
+    if count > 1 {
+        warn!("systemd passed {count} sockets; serving the first and ignoring the rest");
+    }
+    take_listener(LISTEN_FDS_START).map(Some)
+}
+
+/// Number of sockets passed to this process, validated against `LISTEN_PID`.
+fn activated_fd_count(
+    listen_pid: Option<&str>,
+    listen_fds: Option<&str>,
+    pid: u32,
+) -> Result<Option<u32>, String> {
+    let (Some(listen_pid), Some(listen_fds)) = (listen_pid, listen_fds) else {
+        return Ok(None);
+    };
+    if listen_pid.trim().parse::<u32>().ok() != Some(pid) {
+        return Ok(None);
+    }
+    let count = listen_fds
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| format!("LISTEN_FDS must be a number, got {listen_fds:?}"))?;
+    Ok((count > 0).then_some(count))
+}
+
+#[cfg(unix)]
+fn take_listener(fd: i32) -> Result<std::net::TcpListener, String> {
+    use std::os::fd::FromRawFd;
+
+    // SAFETY: systemd hands the process ownership of the descriptors starting at
+    // LISTEN_FDS_START, validated via LISTEN_PID above, and nothing else in the process uses them.
+    let inherited = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+    // The inherited descriptor lacks FD_CLOEXEC; a duplicate keeps exec'd commands from holding
+    // the listening socket open.
+    let listener = inherited
+        .try_clone()
+        .map_err(|error| format!("failed to take over socket-activated fd {fd}: {error}"))?;
+    drop(inherited);
+    listener
+        .local_addr()
+        .map_err(|error| format!("socket-activated fd {fd} is not a TCP listener: {error}"))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|error| format!("failed to configure socket-activated fd {fd}: {error}"))?;
+    Ok(listener)
+}
+
+#[cfg(not(unix))]
+fn take_listener(_fd: i32) -> Result<std::net::TcpListener, String> {
+    Err("socket activation is only supported on unix".to_owned())
+}
+
+/// Sends one `sd_notify` message (such as `READY=1`) to `NOTIFY_SOCKET`, if set. Delivery
+/// failures are logged and otherwise ignored.
+pub fn notify(message: &str) {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(error) = send_notify(&socket.to_string_lossy(), message) {
+        warn!("failed to notify systemd ({message}): {error}");
+    }
+}
+
+#[cfg(unix)]
+fn send_notify(socket: &str, message: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let sender = UnixDatagram::unbound()?;
+    #[cfg(target_os = "linux")]
+    if let Some(name) = socket.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+
+        let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        sender.send_to_addr(message.as_bytes(), &address)?;
+        return Ok(());
+    }
+    sender.send_to(message.as_bytes(), socket)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_notify(_socket: &str, _message: &str) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Half the watchdog timeout systemd expects pings within, when `WATCHDOG_USEC` applies to this
+/// process.
+fn watchdog_interval(
+    watchdog_usec: Option<&str>,
+    watchdog_pid: Option<&str>,
+    pid: u32,
+) -> Option<Duration> {
+    if let Some(watchdog_pid) = watchdog_pid
+        && watchdog_pid.trim().parse::<u32>().ok() != Some(pid)
+    {
+        return None;
+    }
+    let usec = watchdog_usec?.trim().parse::<u64>().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// Reports readiness to systemd and, when the unit sets `WatchdogSec`, pings the watchdog for as
+/// long as storage answers. A stalled runtime or database stops the pings so systemd restarts the
+/// service. Returns `None` outside systemd.
+pub fn spawn_notifier(
+    state: SharedState,
+    local_addr: SocketAddr,
+) -> Option<tokio::task::JoinHandle<()>> {
+    std::env::var_os("NOTIFY_SOCKET")?;
+    notify(&format!("READY=1\nSTATUS=serving on {local_addr}"));
+    let interval = watchdog_interval(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    )?;
+    info!(
+        "systemd watchdog enabled, pinging every {}ms",
+        interval.as_millis()
+    );
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match tokio::time::timeout(interval, state.probe_storage_latency()).await {
+                Ok(Ok(_)) => notify("WATCHDOG=1"),
+                Ok(Err(error)) => {
+                    warn!("skipping systemd watchdog ping: storage probe failed: {error}")
+                }
+                Err(_) => warn!("skipping systemd watchdog ping: storage probe timed out"),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{activated_fd_count, send_notify, watchdog_interval};
+
+    #[test]
+    fn socket_activation_and_watchdog_only_apply_to_this_process() {
+        assert_eq!(activated_fd_count(None, None, 42), Ok(None));
+        assert_eq!(activated_fd_count(Some("41"), Some("1"), 42), Ok(None));
+        assert_eq!(activated_fd_count(Some("42"), Some("0"), 42), Ok(None));
+        assert_eq!(activated_fd_count(Some("42"), Some("2"), 42), Ok(Some(2)));
+        assert!(activated_fd_count(Some("42"), Some("many"), 42).is_err());
+
+        assert_eq!(watchdog_interval(None, None, 42), None);
+        assert_eq!(
+            watchdog_interval(Some("10000000"), None, 42),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            watchdog_interval(Some("10000000"), Some("42"), 42),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(watchdog_interval(Some("10000000"), Some("7"), 42), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 42), None);
+    }
+
+    #[test]
+    fn notify_messages_reach_the_notify_socket() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let path = temp.path().join("notify.sock");
+        let receiver =
+            std::os::unix::net::UnixDatagram::bind(&path).expect("notify socket should bind");
+
+        send_notify(&path.to_string_lossy(), "READY=1").expect("notify should send");
+        let mut buffer = [0_u8; 64];
+        let read = receiver.recv(&mut buffer).expect("notify should arrive");
+        assert_eq!(&buffer[..read], b"READY=1");
+    }
+}
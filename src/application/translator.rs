@@ -0,0 +1,254 @@
+use std::{future::Future, pin::Pin, time::Duration};
+
+use serde_json::{Value, json};
+use tracing::warn;
+
+use crate::application::state::SharedState;
+
+pub type TranslatorFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TranslationRequest<'a> {
+    pub text: &'a str,
+    /// `None` asks the provider to detect the language.
+    pub source_language: Option<&'a str>,
+    pub target_language: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Translation {
+    pub text: String,
+    /// The requested or detected source language.
+    pub source_language: String,
+}
+
+/// Translates chat turns between the user's language and the agent language.
+///
+/// Configure the built-in LibreTranslate-compatible provider with `translationUrl`, or install
+/// another one via `SharedState::set_translator` or `ServerBuilder::translator`.
+pub trait Translator: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn translate<'a>(
+        &'a self,
+        request: TranslationRequest<'a>,
+    ) -> TranslatorFuture<'a, Result<Translation, String>>;
+}
+
+/// Talks to a LibreTranslate-compatible `POST /translate` endpoint.
+pub struct HttpTranslator {
+    url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl HttpTranslator {
+    #[must_use]
+    pub fn new(url: String, api_key: Option<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        Self {
+            url,
+            api_key,
+            client,
+        }
+    }
+}
+
+impl Translator for HttpTranslator {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        request: TranslationRequest<'a>,
+    ) -> TranslatorFuture<'a, Result<Translation, String>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .post(&self.url)
+                .json(&json!({
+                    "q": request.text,
+                    "source": request.source_language.unwrap_or("auto"),
+                    "target": request.target_language,
+                    "format": "text",
+                    "api_key": self.api_key,
+                }))
+                .send()
+                .await
+                .map_err(|error| format!("translation request failed: {error}"))?;
+            if !response.status().is_success() {
+                return Err(format!(
+                    "translation provider returned {}",
+                    response.status()
+                ));
+            }
+            let body = response
+                .json::<Value>()
+                .await
+                .map_err(|error| format!("translation response decode failed: {error}"))?;
+            parse_http_translation(&body, request.source_language)
+        })
+    }
+}
+
+fn parse_http_translation(body: &Value, source: Option<&str>) -> Result<Translation, String> {
+    let text = body
+        .get("translatedText")
+        .and_then(Value::as_str)
+        .ok_or_else(|| "translation response has no translatedText".to_owned())?;
+    let source_language = body
+        .get("detectedLanguage")
+        .and_then(|detected| detected.get("language"))
+        .and_then(Value::as_str)
+        .or(source)
+        .ok_or_else(|| "translation response has no detectedLanguage".to_owned())?;
+    Ok(Translation {
+        text: text.to_owned(),
+        source_language: source_language.to_owned(),
+    })
+}
+
+/// A user message prepared for the agent. `user_language` is set when the message was written
+/// in another language than the agent's, so the reply can be translated back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InboundTranslation {
+    pub text: String,
+    pub user_language: Option<String>,
+    /// `translation` metadata for the stored user message (original text and languages).
+    pub metadata: Option<Value>,
+}
+
+/// Translates `text` into the agent language when a translator is installed and the text is in
+/// another language. Provider failures fall back to the original text.
+pub async fn translate_inbound(state: &SharedState, text: &str) -> InboundTranslation {
+    let untranslated = InboundTranslation {
+        text: text.to_owned(),
+        user_language: None,
+        metadata: None,
+    };
+    let Some(translator) = state.translator().await else {
+        return untranslated;
+    };
+    let agent_language = state.config().translation_language.as_str();
+    let translation = match translator
+        .translate(TranslationRequest {
+            text,
+            source_language: None,
+            target_language: agent_language,
+        })
+        .await
+    {
+        Ok(translation) => translation,
+        Err(error) => {
+            warn!(
+                "inbound translation via {} failed: {error}",
+                translator.name()
+            );
+            return untranslated;
+        }
+    };
+    if same_language(&translation.source_language, agent_language) {
+        return untranslated;
+    }
+
+    InboundTranslation {
+        metadata: Some(translation_metadata(
+            translator.name(),
+            text,
+            &translation.source_language,
+            agent_language,
+        )),
+        text: translation.text,
+        user_language: Some(translation.source_language),
+    }
+}
+
+/// Translates an agent reply back into `user_language`; returns the text to deliver and the
+/// `translation` metadata for the stored assistant message.
+pub async fn translate_reply(
+    state: &SharedState,
+    reply: &str,
+    user_language: Option<&str>,
+) -> (String, Option<Value>) {
+    let (Some(user_language), Some(translator)) = (user_language, state.translator().await) else {
+        return (reply.to_owned(), None);
+    };
+    let agent_language = state.config().translation_language.as_str();
+    match translator
+        .translate(TranslationRequest {
+            text: reply,
+            source_language: Some(agent_language),
+            target_language: user_language,
+        })
+        .await
+    {
+        Ok(translation) => {
+            let metadata =
+                translation_metadata(translator.name(), reply, agent_language, user_language);
+            (translation.text, Some(metadata))
+        }
+        Err(error) => {
+            warn!(
+                "reply translation via {} failed: {error}",
+                translator.name()
+            );
+            (reply.to_owned(), None)
+        }
+    }
+}
+
+fn translation_metadata(provider: &str, original: &str, source: &str, target: &str) -> Value {
+    json!({
+        "provider": provider,
+        "originalText": original,
+        "sourceLanguage": source,
+        "targetLanguage": target,
+    })
+}
+
+/// Compares primary language subtags, so `en-US` matches `en`.
+fn same_language(left: &str, right: &str) -> bool {
+    let primary = |tag: &str| {
+        tag.trim()
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    };
+    primary(left) == primary(right)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{parse_http_translation, same_language};
+
+    #[test]
+    fn http_translations_parse_and_languages_compare_by_primary_subtag() {
+        let translation = parse_http_translation(
+            &json!({
+                "translatedText": "Hello",
+                "detectedLanguage": { "confidence": 92.0, "language": "de" }
+            }),
+            None,
+        )
+        .expect("translation should parse");
+        assert_eq!(translation.text, "Hello");
+        assert_eq!(translation.source_language, "de");
+        assert_eq!(
+            parse_http_translation(&json!({ "translatedText": "Hallo" }), Some("en"))
+                .map(|translation| translation.source_language),
+            Ok("en".to_owned())
+        );
+        assert!(parse_http_translation(&json!({ "error": "bad" }), None).is_err());
+
+        assert!(same_language("en-US", "EN"));
+        assert!(same_language("pt_BR", "pt"));
+        assert!(!same_language("de", "en"));
+    }
+}
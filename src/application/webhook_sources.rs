@@ -0,0 +1,212 @@
+use std::{net::IpAddr, time::Duration};
+
+use serde_json::{Value, json};
+use tracing::{info, warn};
+
+use crate::{
+    application::state::SharedState,
+    security::source_ip::{IpCidr, contains_ip},
+    storage::now_unix_ms,
+};
+
+/// Fetched ranges are cached per channel so restarts and other instances keep them when the
+/// publisher is unreachable.
+const SOURCE_RANGES_PREFIX: &str = "runtime/webhook-sources/";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maps a webhook route to the channel whose source rule applies.
+#[must_use]
+pub fn channel_for_path(path: &str, slack_events_path: &str) -> Option<String> {
+    if path == slack_events_path {
+        return Some("slack".to_owned());
+    }
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+    match segments.as_slice() {
+        ["channels", channel, "webhook" | "inbound" | "receipts"]
+        | ["channels", channel, "inbound", "batch"] => Some(channel.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+/// Returns whether `ip` may call `channel`'s webhook routes. Channels without a
+/// `webhookSources` rule accept every source.
+pub async fn source_allowed(state: &SharedState, channel: &str, ip: IpAddr) -> bool {
+    let Some(rule) = state.config().webhook_sources.get(channel) else {
+        return true;
+    };
+    if contains_ip(&rule.cidrs, ip) {
+        return true;
+    }
+    let Some(url) = &rule.ranges_url else {
+        return false;
+    };
+    cached_ranges(state, channel, url)
+        .await
+        .is_some_and(|ranges| contains_ip(&ranges, ip))
+}
+
+async fn cached_ranges(state: &SharedState, channel: &str, url: &str) -> Option<Vec<IpCidr>> {
+    let cached = state
+        .get_config_entry_value(&format!("{SOURCE_RANGES_PREFIX}{channel}"))
+        .await
+        .ok()??;
+    if cached.get("rangesUrl").and_then(Value::as_str) != Some(url) {
+        return None;
+    }
+    Some(
+        cached
+            .get("cidrs")
+            .and_then(Value::as_array)?
+            .iter()
+            .filter_map(Value::as_str)
+            .filter_map(|raw| raw.parse::<IpCidr>().ok())
+            .collect(),
+    )
+}
+
+/// Fetches every configured `rangesUrl` and caches the parsed ranges. A failed fetch keeps the
+/// previously cached ranges.
+pub async fn refresh_published_ranges(state: &SharedState, client: &reqwest::Client) {
+    for (channel, rule) in &state.config().webhook_sources {
+        let Some(url) = &rule.ranges_url else {
+            continue;
+        };
+        match fetch_ranges(client, url).await {
+            Ok(ranges) => {
+                let cidrs = ranges.iter().map(ToString::to_string).collect::<Vec<_>>();
+                let _ = state
+                    .set_config_entry_value(
+                        &format!("{SOURCE_RANGES_PREFIX}{channel}"),
+                        &json!({
+                            "rangesUrl": url,
+                            "fetchedAtMs": now_unix_ms(),
+                            "cidrs": cidrs,
+                        }),
+                    )
+                    .await;
+            }
+            Err(error) => {
+                warn!("failed to refresh {channel} webhook source ranges: {error}");
+                let _ = state
+                    .append_gateway_log(
+                        "warn",
+                        &format!("failed to refresh {channel} webhook source ranges: {error}"),
+                        Some("channels.webhookSources"),
+                        None,
+                    )
+                    .await;
+            }
+        }
+    }
+}
+
+async fn fetch_ranges(client: &reqwest::Client, url: &str) -> Result<Vec<IpCidr>, String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|error| format!("request to {url} failed: {error}"))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "request to {url} failed with {}",
+            response.status()
+        ));
+    }
+    let body = response
+        .text()
+        .await
+        .map_err(|error| format!("response from {url} is unreadable: {error}"))?;
+    parse_published_ranges(&body)
+}
+
+/// Accepts a JSON array of CIDRs, an object with a `ranges`, `cidrs`, or `prefixes` array, or
+/// plain text with one CIDR per line (`#` starts a comment).
+fn parse_published_ranges(body: &str) -> Result<Vec<IpCidr>, String> {
+    let entries: Vec<String> = match serde_json::from_str::<Value>(body) {
+        Ok(document) => {
+            let list = document.as_array().or_else(|| {
+                ["ranges", "cidrs", "prefixes"]
+                    .iter()
+                    .find_map(|key| document.get(key).and_then(Value::as_array))
+            });
+            list.ok_or_else(|| "published ranges document has no range list".to_owned())?
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_owned)
+                .collect()
+        }
+        Err(_) => body
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(str::to_owned)
+            .collect(),
+    };
+    let ranges = entries
+        .iter()
+        .map(|entry| entry.parse::<IpCidr>())
+        .collect::<Result<Vec<_>, _>>()?;
+    if ranges.is_empty() {
+        return Err("published ranges list is empty".to_owned());
+    }
+    Ok(ranges)
+}
+
+pub fn spawn_source_range_refresher(state: SharedState) -> Option<tokio::task::JoinHandle<()>> {
+    if !state
+        .config()
+        .webhook_sources
+        .values()
+        .any(|rule| rule.ranges_url.is_some())
+    {
+        return None;
+    }
+    let client = match reqwest::Client::builder().timeout(FETCH_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(error) => {
+            warn!("webhook source range refresh disabled: {error}");
+            return None;
+        }
+    };
+    info!("webhook source range refresh enabled");
+
+    Some(tokio::spawn(async move {
+        let interval = state.config().webhook_source_refresh_interval;
+        loop {
+            refresh_published_ranges(&state, &client).await;
+            tokio::time::sleep(interval).await;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{channel_for_path, parse_published_ranges};
+
+    #[test]
+    fn published_ranges_parse_and_routes_map_to_channels() {
+        let from_json = parse_published_ranges(r#"{ "ranges": ["10.0.0.0/8", "2001:db8::/32"] }"#)
+            .expect("json ranges should parse");
+        assert_eq!(from_json.len(), 2);
+        let from_text =
+            parse_published_ranges("# telegram\n149.154.160.0/20\n\n91.108.4.0/22 # dc\n")
+                .expect("text ranges should parse");
+        assert_eq!(from_text[1].to_string(), "91.108.4.0/22");
+        assert!(parse_published_ranges("[]").is_err());
+        assert!(parse_published_ranges("not a range").is_err());
+
+        assert_eq!(
+            channel_for_path("/channels/Telegram/webhook", "/slack/events"),
+            Some("telegram".to_owned())
+        );
+        assert_eq!(
+            channel_for_path("/slack/events", "/slack/events"),
+            Some("slack".to_owned())
+        );
+        assert_eq!(
+            channel_for_path("/channels/signal/inbound/batch", "/slack/events"),
+            Some("signal".to_owned())
+        );
+        assert_eq!(channel_for_path("/channels/inbound", "/slack/events"), None);
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use tokio::fs;
+
+/// Bytes used by every file under `root`. A missing workspace uses nothing.
+pub async fn directory_usage(root: &Path) -> io::Result<u64> {
+    let mut used = 0_u64;
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(error),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                used = used.saturating_add(entry.metadata().await?.len());
+            }
+        }
+    }
+    Ok(used)
+}
+
+/// Bytes used by all `workspaces` together; agents sharing a directory count it once.
+pub async fn total_usage<'a>(workspaces: impl IntoIterator<Item = &'a str>) -> io::Result<u64> {
+    let unique = workspaces
+        .into_iter()
+        .map(PathBuf::from)
+        .collect::<BTreeSet<_>>();
+    let mut total = 0_u64;
+    for workspace in unique {
+        total = total.saturating_add(directory_usage(&workspace).await?);
+    }
+    Ok(total)
+}
+
+/// Why a write does not fit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuotaExceeded {
+    /// The agent's workspace would outgrow its `quotaBytes`.
+    Agent { projected: u64, quota: u64 },
+    /// All agent workspaces together would outgrow `agentWorkspaceBudgetBytes`.
+    Budget { projected: u64, budget: u64 },
+}
+
+impl QuotaExceeded {
+    #[must_use]
+    pub fn describe(&self, agent_id: &str) -> String {
+        match self {
+            Self::Agent { projected, quota } => format!(
+                "workspace of agent \"{agent_id}\" would grow to {projected} bytes, over its quota of {quota} bytes"
+            ),
+            Self::Budget { projected, budget } => format!(
+                "agent workspaces would grow to {projected} bytes, over the budget of {budget} bytes"
+            ),
+        }
+    }
+}
+
+/// Current usage and limits a workspace write is checked against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkspaceUsage {
+    pub agent_used: u64,
+    pub agent_quota: Option<u64>,
+    pub total_used: u64,
+    pub budget: Option<u64>,
+}
+
+impl WorkspaceUsage {
+    /// Checks replacing `old_bytes` with `new_bytes`. Writes that do not grow the workspace
+    /// always pass, so an agent over its quota can still shrink its files.
+    pub fn check(&self, old_bytes: u64, new_bytes: u64) -> Result<(), QuotaExceeded> {
+        let growth = new_bytes.saturating_sub(old_bytes);
+        if growth == 0 {
+            return Ok(());
+        }
+        let projected = self.agent_used.saturating_add(growth);
+        if let Some(quota) = self.agent_quota
+            && projected > quota
+        {
+            return Err(QuotaExceeded::Agent { projected, quota });
+        }
+        let projected = self.total_used.saturating_add(growth);
+        if let Some(budget) = self.budget
+            && projected > budget
+        {
+            return Err(QuotaExceeded::Budget { projected, budget });
+        }
+        Ok(())
+    }
+
+    /// Records a write that passed [`Self::check`].
+    pub fn record(&mut self, old_bytes: u64, new_bytes: u64) {
+        let growth = new_bytes.saturating_sub(old_bytes);
+        self.agent_used = self.agent_used.saturating_add(growth);
+        self.total_used = self.total_used.saturating_add(growth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QuotaExceeded, WorkspaceUsage};
+
+    #[test]
+    fn writes_are_checked_against_agent_quota_then_total_budget() {
+        let mut usage = WorkspaceUsage {
+            agent_used: 900,
+            agent_quota: Some(1_000),
+            total_used: 4_900,
+            budget: Some(5_000),
+        };
+        assert_eq!(usage.check(0, 100), Ok(()));
+        assert_eq!(
+            usage.check(0, 101),
+            Err(QuotaExceeded::Agent {
+                projected: 1_001,
+                quota: 1_000
+            })
+        );
+        assert_eq!(usage.check(50, 150), Ok(()));
+        assert_eq!(usage.check(500, 10), Ok(()));
+
+        usage.agent_quota = None;
+        assert_eq!(
+            usage.check(0, 200),
+            Err(QuotaExceeded::Budget {
+                projected: 5_100,
+                budget: 5_000
+            })
+        );
+        usage.record(0, 100);
+        assert_eq!(usage.total_used, 5_000);
+        assert!(usage.check(0, 1).is_err());
+        assert_eq!(usage.check(10, 10), Ok(()));
+    }
+}
@@ -1,2 +1,3 @@
 pub mod error;
 pub mod models;
+pub mod session_key;
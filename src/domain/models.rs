@@ -12,6 +12,16 @@ pub struct SessionRecord {
     pub updated_at_ms: u64,
 }
 
+/// Per-agent counters kept current by the store as sessions and messages are written.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentUsageRecord {
+    pub agent_id: String,
+    pub sessions_count: u64,
+    pub messages_count: u64,
+    pub last_activity_at_ms: Option<u64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChatMessage {
@@ -21,6 +31,251 @@ pub struct ChatMessage {
     pub status: String,
     pub ts: u64,
     pub metadata: Value,
+    /// Pinned messages are always part of the agent context and listed first by `chat.history`.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// Last message of a session an operator client has displayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatReadMarker {
+    pub client_id: String,
+    pub session_key: String,
+    pub message_id: String,
+    /// `ts` of the marked message; later messages count as unread.
+    pub message_ts: u64,
+    pub marked_at_ms: u64,
+}
+
+/// One entry of a session's `session.kv` scratchpad.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionKvEntry {
+    pub key: String,
+    pub value: Value,
+    /// Length of `value` serialized as JSON, which is what the size limits count.
+    pub size_bytes: u64,
+    pub updated_at_ms: u64,
+    pub expires_at_ms: Option<u64>,
+}
+
+/// A gateway event kept in the event journal until its recipients acknowledge it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournaledEvent {
+    pub seq: u64,
+    pub event: String,
+    pub payload: Value,
+    pub ts: u64,
+}
+
+/// A row written on a replication primary, shipped to its standby in `seq` order. `key` holds
+/// the primary key columns in order; `row` is every column of an upserted row and absent for a
+/// delete.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicatedChange {
+    pub seq: u64,
+    pub table: String,
+    pub operation: String,
+    pub key: Value,
+    #[serde(default)]
+    pub row: Option<Value>,
+}
+
+/// This instance's own replication bookkeeping, which is never replicated.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationState {
+    /// Promotion epoch: bumped by every promotion, so an older primary can tell it was replaced.
+    pub epoch: u64,
+    /// Last primary change a standby applied.
+    pub applied_seq: u64,
+    /// Changes up to this sequence number were pruned from this primary's change log.
+    pub pruned_seq: u64,
+    /// Standby that last checked in with this primary.
+    pub peer_url: Option<String>,
+    /// Set once a newer primary fenced this one; it then stays read-only across restarts.
+    pub fenced_at_ms: Option<u64>,
+    /// Where that newer primary said clients should reconnect.
+    pub fenced_by_url: Option<String>,
+}
+
+/// Progress through a guided `wizard.*` flow, kept in storage so a client can resume it after
+/// reconnecting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WizardSession {
+    pub id: String,
+    pub kind: String,
+    /// `active`, `completed`, or `cancelled`.
+    pub status: String,
+    pub step_index: usize,
+    /// Accepted input, keyed by step id.
+    pub answers: Value,
+    pub result: Option<Value>,
+    pub started_by: Option<String>,
+    pub cancel_reason: Option<String>,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+    pub completed_at_ms: Option<u64>,
+}
+
+/// A compressed JSONL file holding archived messages of one session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatArchiveSegment {
+    pub id: String,
+    pub session_key: String,
+    /// Path relative to the configured archive directory.
+    pub file_name: String,
+    pub first_ts_ms: u64,
+    pub last_ts_ms: u64,
+    pub message_count: u64,
+    pub created_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelDirectoryEntry {
+    pub channel: String,
+    pub conversation_id: String,
+    pub title: Option<String>,
+    pub kind: String,
+    pub participants: Vec<String>,
+    pub message_count: u64,
+    pub first_seen_ms: u64,
+    pub last_seen_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChannelDirectoryInput {
+    pub channel: String,
+    pub conversation_id: String,
+    pub title: Option<String>,
+    pub kind: Option<String>,
+    pub participant: Option<String>,
+    pub seen_at_ms: u64,
+}
+
+/// A logical person linking identities from several channels.
+/// Row counts removed when a session's data is purged for a data-subject request.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPurgeCounts {
+    pub sessions: u64,
+    pub messages: u64,
+    pub runs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivacyAuditRecord {
+    pub id: String,
+    pub action: String,
+    pub subject: Value,
+    pub summary: Value,
+    pub requested_by: String,
+    pub created_at_ms: u64,
+}
+
+/// How a registered tool runs when an agent run calls it through the gateway.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ToolExecutor {
+    Node {
+        node_id: String,
+        command: String,
+    },
+    Http {
+        url: String,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    Builtin {
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+    pub executor: ToolExecutor,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolGrant {
+    pub agent_id: String,
+    pub tool_name: String,
+    pub granted_at_ms: u64,
+}
+
+/// One tool invocation made on behalf of an agent run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallRecord {
+    pub id: String,
+    pub run_id: String,
+    pub agent_id: String,
+    pub tool_name: String,
+    pub args: Value,
+    pub status: String,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    pub started_at_ms: u64,
+    pub completed_at_ms: Option<u64>,
+}
+
+/// An outbound channel message held back by quiet hours until `release_at_ms`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedOutboundMessage {
+    pub id: String,
+    pub channel: String,
+    pub payload: Value,
+    pub attempts: u32,
+    pub queued_at_ms: u64,
+    pub release_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonRecord {
+    pub person_id: String,
+    pub display_name: Option<String>,
+    pub shared_session: bool,
+    pub identities: Vec<PersonIdentity>,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonIdentity {
+    pub channel: String,
+    pub external_id: String,
+    pub linked_at_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdentityLinkInput {
+    pub person_id: Option<String>,
+    pub display_name: Option<String>,
+    pub shared_session: Option<bool>,
+    pub channel: String,
+    pub external_id: String,
+    pub linked_at_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +300,13 @@ pub struct AgentRunRecord {
     pub completed_at_ms: Option<u64>,
 }
 
+/// How agent run spend (`metadata.cost.usd`) is grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunCostScope {
+    Agent,
+    Session,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CronSchedule {
@@ -53,8 +315,13 @@ pub struct CronSchedule {
     pub every_ms: Option<u64>,
     pub anchor_ms: Option<u64>,
     pub expr: Option<String>,
+    /// IANA zone `cron` expressions are evaluated in; UTC when unset.
+    #[serde(alias = "timezone")]
     pub tz: Option<String>,
     pub stagger_ms: Option<u64>,
+    /// How `cron` times hit by a DST transition run: `runOnce` (default) or `skip`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dst: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,6 +333,16 @@ pub struct CronPayload {
     pub model: Option<String>,
     pub thinking: Option<String>,
     pub timeout_seconds: Option<u64>,
+    /// Source of a `script` payload; `timeoutSeconds` bounds its run time.
+    #[serde(default)]
+    pub script: Option<String>,
+    /// Agent an `agentTurn` payload runs as; `main` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+    /// Session an `agentTurn` writes its chat history to; `agent:<agentId>:cron:<jobId>` when
+    /// unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_key: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,6 +358,9 @@ pub struct CronJobRecord {
     pub updated_at_ms: u64,
     pub last_run_ms: Option<u64>,
     pub next_run_ms: Option<u64>,
+    /// Runs of this job allowed at once, queued or executing; `None` means one.
+    #[serde(default)]
+    pub max_concurrent: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -94,9 +374,51 @@ pub struct CronRunRecord {
     pub manual: bool,
     pub started_at_ms: u64,
     pub finished_at_ms: u64,
+    /// Time the run spent waiting for a free cron worker before it started.
+    #[serde(default)]
+    pub queue_wait_ms: u64,
+    /// The agent run an `agentTurn` payload dispatched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_run_id: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+/// `cron.runs` filters; `None` matches any value. Runs are listed newest first.
+#[derive(Debug, Clone, Default)]
+pub struct CronRunQuery {
+    pub job_id: Option<String>,
+    pub status: Option<String>,
+    pub manual: Option<bool>,
+    pub since_ms: Option<u64>,
+    pub until_ms: Option<u64>,
+    /// Page cursor: only runs ordered after this `(started_at_ms, run_id)` position.
+    pub before: Option<(u64, String)>,
+    pub limit: Option<usize>,
+}
+
+/// Aggregates over the runs of one job matching a `CronRunQuery`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CronRunStats {
+    pub job_id: String,
+    pub runs: u64,
+    pub ok: u64,
+    pub errors: u64,
+    pub success_rate: f64,
+    pub avg_duration_ms: u64,
+    pub last_started_at_ms: u64,
+}
+
+/// One increment of output from a cron run that is still executing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CronOutputChunk {
+    /// Position in the run's output, starting at 1.
+    pub seq: u64,
+    pub text: String,
+    pub ts_ms: u64,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct CronJobPatch {
     pub name: Option<String>,
     pub enabled: Option<bool>,
@@ -104,6 +426,7 @@ pub struct CronJobPatch {
     pub payload: Option<CronPayload>,
     pub metadata: Option<Value>,
     pub next_run_ms: Option<Option<u64>>,
+    pub max_concurrent: Option<Option<u32>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -134,6 +457,9 @@ pub struct NodePairRequestRecord {
     pub reason: Option<String>,
     pub created_at_ms: u64,
     pub resolved_at_ms: Option<u64>,
+    /// Pending requests past this time are marked `expired`; unset on requests stored before
+    /// expiry existed.
+    pub expires_at_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -152,6 +478,45 @@ pub struct NodeInvokeRecord {
     pub completed_at_ms: Option<u64>,
 }
 
+/// An invoke held for an offline node until it reconnects or `expires_at_ms` passes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedNodeInvoke {
+    #[serde(flatten)]
+    pub invoke: NodeInvokeRecord,
+    pub expires_at_ms: u64,
+}
+
+/// A file moving between the gateway's node files directory and a paired node. The node is
+/// handed a `file.push` or `file.pull` invoke carrying a transfer token and moves the bytes over
+/// HTTP with it; the transfer itself only keeps the token's hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeFileTransfer {
+    pub transfer_id: String,
+    pub node_id: String,
+    /// `push` (gateway to node) or `pull` (node to gateway).
+    pub direction: String,
+    /// Relative to the node files directory.
+    pub gateway_path: String,
+    pub node_path: String,
+    /// Known up front for pushes; for pulls, once the node sends its first chunk.
+    pub size_bytes: Option<u64>,
+    /// Largest file the transfer accepts.
+    pub max_bytes: u64,
+    pub sha256: Option<String>,
+    pub transferred_bytes: u64,
+    /// `pending`, `active`, `completed`, `failed`, `cancelled`, or `expired`.
+    pub status: String,
+    pub invoke_id: Option<String>,
+    pub error: Option<String>,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+    pub expires_at_ms: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NodeEventRecord {
@@ -162,6 +527,15 @@ pub struct NodeEventRecord {
     pub ts: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeMetadataEntry {
+    pub id: String,
+    pub node_id: String,
+    pub metadata: Value,
+    pub ts: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct NodePairRequestInput {
     pub node_id: String,
@@ -180,6 +554,115 @@ pub struct NodeInvokeInput {
     pub input: Option<Value>,
 }
 
+/// Lifecycle of an outbound channel reply. `Delivered`/`Read` only appear when the platform or
+/// bridge reports them back as receipts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Queued,
+    Sent,
+    Delivered,
+    Read,
+    Failed,
+}
+
+impl DeliveryStatus {
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Sent => "sent",
+            Self::Delivered => "delivered",
+            Self::Read => "read",
+            Self::Failed => "failed",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "queued" => Some(Self::Queued),
+            "sent" => Some(Self::Sent),
+            "delivered" => Some(Self::Delivered),
+            "read" => Some(Self::Read),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+
+    /// Whether a receipt for `next` may replace this status. Statuses only move forward, and a
+    /// failure is ignored once the platform confirmed delivery.
+    #[must_use]
+    pub fn can_advance_to(self, next: Self) -> bool {
+        match (self, next) {
+            (Self::Failed, _) => false,
+            (Self::Delivered | Self::Read, Self::Failed) => false,
+            (_, Self::Failed) => true,
+            (current, next) => next.rank() > current.rank(),
+        }
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            Self::Queued => 0,
+            Self::Sent => 1,
+            Self::Delivered => 2,
+            Self::Read => 3,
+            Self::Failed => 4,
+        }
+    }
+}
+
+/// Delivery tracking for one outbound channel reply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageDelivery {
+    pub id: String,
+    pub session_key: String,
+    pub run_id: Option<String>,
+    pub channel: String,
+    pub conversation_id: String,
+    pub status: DeliveryStatus,
+    pub platform_message_id: Option<String>,
+    pub error: Option<String>,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+    pub sent_at_ms: Option<u64>,
+    pub delivered_at_ms: Option<u64>,
+    pub read_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayLogEntry {
+    pub id: String,
+    pub level: String,
+    pub message: String,
+    pub method: Option<String>,
+    pub conn_id: Option<String>,
+    pub ts: u64,
+}
+
+/// A gateway log or audit entry buffered for the remote log shipper.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogShipment {
+    pub seq: u64,
+    /// `log` for gateway log entries, `audit` for privacy audit records.
+    pub kind: String,
+    pub entry: Value,
+    pub ts: u64,
+}
+
+/// `logs.tail` filters; `None` matches any value.
+#[derive(Debug, Clone, Default)]
+pub struct GatewayLogQuery {
+    pub level: Option<String>,
+    pub method: Option<String>,
+    pub conn_id: Option<String>,
+    pub limit: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ConfigEntry {
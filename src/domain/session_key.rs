@@ -0,0 +1,164 @@
+use std::fmt;
+
+/// Longest accepted session key, in bytes.
+pub const MAX_SESSION_KEY_LEN: usize = 512;
+
+/// Namespace of keys owned by one agent: `agent:<agentId>:<name>[:...]`.
+pub const AGENT_NAMESPACE: &str = "agent";
+
+/// A validated session key in canonical form: `<namespace>:<segment>[:<segment>...]`.
+///
+/// Canonicalization trims every segment and lowercases the namespace and, for `agent:` keys,
+/// the agent id, so `Agent:Main: main` and `agent:main:main` name the same session. Segment
+/// contents are otherwise kept verbatim because conversation ids are case-sensitive on most
+/// channels.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionKey(String);
+
+impl SessionKey {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() {
+            return Err("must not be empty".to_owned());
+        }
+        if trimmed.chars().any(char::is_control) {
+            return Err("must not contain control characters".to_owned());
+        }
+
+        let mut segments = trimmed.split(':').map(str::trim).collect::<Vec<_>>();
+        if segments.len() < 2 {
+            return Err("must be namespaced as <namespace>:<name>".to_owned());
+        }
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err("must not contain empty segments".to_owned());
+        }
+
+        let namespace = segments[0].to_ascii_lowercase();
+        if !is_identifier(&namespace) {
+            return Err(format!(
+                "namespace {} may only use a-z, 0-9, '-' and '_'",
+                segments[0]
+            ));
+        }
+        segments[0] = &namespace;
+
+        let agent_id;
+        if namespace == AGENT_NAMESPACE {
+            if segments.len() < 3 {
+                return Err("must be agent:<agentId>:<name>".to_owned());
+            }
+            agent_id = segments[1].to_ascii_lowercase();
+            if !is_identifier(&agent_id) {
+                return Err(format!(
+                    "agent id {} may only use a-z, 0-9, '-' and '_'",
+                    segments[1]
+                ));
+            }
+            segments[1] = &agent_id;
+        }
+
+        let canonical = segments.join(":");
+        if canonical.len() > MAX_SESSION_KEY_LEN {
+            return Err(format!("must be at most {MAX_SESSION_KEY_LEN} bytes"));
+        }
+        Ok(Self(canonical))
+    }
+
+    /// Formats the key of an agent-owned session from its agent id and name segments.
+    pub fn agent(agent_id: &str, name: &[&str]) -> Result<Self, String> {
+        let mut raw = format!("{AGENT_NAMESPACE}:{agent_id}");
+        for segment in name {
+            raw.push(':');
+            raw.push_str(segment);
+        }
+        Self::parse(&raw)
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn namespace(&self) -> &str {
+        self.0.split(':').next().unwrap_or_default()
+    }
+
+    /// Agent id of an `agent:` key; `None` for other namespaces.
+    #[must_use]
+    pub fn agent_id(&self) -> Option<&str> {
+        let mut segments = self.0.split(':');
+        (segments.next() == Some(AGENT_NAMESPACE))
+            .then(|| segments.next())
+            .flatten()
+    }
+
+    #[must_use]
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for SessionKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+/// Canonical form of `raw`, or why it is not a valid session key.
+pub fn canonicalize_session_key(raw: &str) -> Result<String, String> {
+    SessionKey::parse(raw).map(SessionKey::into_string)
+}
+
+fn is_identifier(value: &str) -> bool {
+    value
+        .chars()
+        .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-' || ch == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SessionKey, canonicalize_session_key};
+
+    #[test]
+    fn parse_canonicalizes_whitespace_and_case() {
+        assert_eq!(
+            canonicalize_session_key(" Agent:Main: main ").as_deref(),
+            Ok("agent:main:main")
+        );
+        assert_eq!(
+            canonicalize_session_key("agent:main:teams:chat:19:Ops@thread.v2").as_deref(),
+            Ok("agent:main:teams:chat:19:Ops@thread.v2")
+        );
+        assert_eq!(
+            canonicalize_session_key("HOOK: GitHub").as_deref(),
+            Ok("hook:GitHub")
+        );
+
+        let key = SessionKey::agent("Ops", &["telegram", "chat", "42"]).expect("key should format");
+        assert_eq!(key.to_string(), "agent:ops:telegram:chat:42");
+        assert_eq!(key.namespace(), "agent");
+        assert_eq!(key.agent_id(), Some("ops"));
+        assert_eq!(SessionKey::parse("hook:x").expect("key").agent_id(), None);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_keys() {
+        for raw in [
+            "",
+            "main",
+            "agent:main",
+            "agent::main",
+            "agent:main:",
+            "agent:ma in:main",
+            "my space:main",
+            "hook:a\nb",
+        ] {
+            assert!(
+                SessionKey::parse(raw).is_err(),
+                "{raw:?} should be rejected"
+            );
+        }
+        assert!(SessionKey::parse(&format!("hook:{}", "x".repeat(512))).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! Text codecs shared by every layer: base64 as found in mail, webhooks and JWTs, and URL
+//! percent-encoding.
+
+use base64::{
+    Engine, alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+};
+
+/// Standard alphabet, padding optional, stray low bits ignored, as MIME encoders produce.
+const LENIENT_BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+        .with_decode_allow_trailing_bits(true),
+);
+
+/// Decodes standard or URL-safe base64, ignoring whitespace (MIME line breaks) and padding.
+#[must_use]
+pub fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let normalized = input
+        .chars()
+        .filter(|ch| !ch.is_ascii_whitespace())
+        .map(|ch| match ch {
+            '-' => '+',
+            '_' => '/',
+            ch => ch,
+        })
+        .collect::<String>();
+    LENIENT_BASE64.decode(normalized.trim_end_matches('=')).ok()
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters, so the result fits a path
+/// segment or a query value.
+#[must_use]
+pub fn percent_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            out.push(char::from(byte));
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+/// Decodes a form-urlencoded value: `+` is a space, malformed escapes are kept as they are, and
+/// invalid UTF-8 is replaced.
+#[must_use]
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let escaped = value
+                    .get(index + 1..index + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(byte) = escaped {
+                    out.push(byte);
+                    index += 2;
+                } else {
+                    out.push(b'%');
+                }
+            }
+            byte => out.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_base64, percent_decode, percent_encode};
+
+    #[test]
+    fn codecs_round_trip_and_tolerate_mail_formatting() {
+        assert_eq!(decode_base64("aGk6dGhlcmU="), Some(b"hi:there".to_vec()));
+        assert_eq!(
+            decode_base64("aGk6\r\ndGhl cmU"),
+            Some(b"hi:there".to_vec())
+        );
+        assert_eq!(decode_base64("-_8"), decode_base64("+/8="));
+        assert_eq!(decode_base64("a*b"), None);
+
+        let encoded = percent_encode("req 1/a+b");
+        assert_eq!(encoded, "req%201%2Fa%2Bb");
+        assert_eq!(percent_decode(&encoded), "req 1/a+b");
+        assert_eq!(percent_decode("a+b%2x%41"), "a b%2xA");
+    }
+}
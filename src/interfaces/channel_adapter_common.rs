@@ -8,13 +8,17 @@ use serde_json::{Value, json};
 use tracing::warn;
 
 use crate::{
-    application::state::SharedState,
-    interfaces::channels::{InboundMessageRequest, InboundProcessResult, ingest_inbound_message},
+    application::{config::RuntimeConfig, state::SharedState},
+    domain::models::{DeliveryStatus, MessageDelivery},
+    interfaces::{
+        channels::{InboundMessageRequest, InboundProcessResult, ingest_inbound_message},
+        quiet_hours,
+    },
     storage::now_unix_ms,
 };
 
-pub(crate) struct ChannelInboundEvent {
-    pub channel: &'static str,
+pub(crate) struct ChannelInboundEvent<'a> {
+    pub channel: &'a str,
     pub conversation_id: String,
     pub text: String,
     pub sender_id: Option<String>,
@@ -41,10 +45,39 @@ pub(crate) fn require_channel_bearer_token(
     Ok(())
 }
 
+/// Verifies the shared token outgoing webhooks (Mattermost, Rocket.Chat) send in their body.
+pub(crate) fn require_channel_body_token(
+    payload: &Value,
+    token: &Option<String>,
+    channel: &str,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    let Some(token) = token.as_deref() else {
+        return Err(unavailable(format!(
+            "{channel} webhook token is not configured"
+        )));
+    };
+    let provided = payload
+        .get("token")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if !bool::from(subtle::ConstantTimeEq::ct_eq(
+        provided.as_bytes(),
+        token.as_bytes(),
+    )) {
+        return Err(unauthorized("invalid or missing webhook token"));
+    }
+
+    Ok(())
+}
+
 pub(crate) fn unauthorized(message: impl Into<String>) -> (StatusCode, Json<Value>) {
     error_response(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", message)
 }
 
+pub(crate) fn forbidden(message: impl Into<String>) -> (StatusCode, Json<Value>) {
+    error_response(StatusCode::FORBIDDEN, "FORBIDDEN", message)
+}
+
 pub(crate) fn unavailable(message: impl Into<String>) -> (StatusCode, Json<Value>) {
     error_response(StatusCode::SERVICE_UNAVAILABLE, "UNAVAILABLE", message)
 }
@@ -66,7 +99,7 @@ pub(crate) fn accepted_false(reason: impl Into<String>) -> (StatusCode, Json<Val
 
 pub(crate) async fn ingest_channel_message(
     state: &SharedState,
-    event: ChannelInboundEvent,
+    event: ChannelInboundEvent<'_>,
 ) -> Result<InboundProcessResult, (StatusCode, Json<Value>)> {
     let inbound = InboundMessageRequest {
         channel: event.channel.to_owned(),
@@ -77,6 +110,7 @@ pub(crate) async fn ingest_channel_message(
         message_id: event.message_id,
         idempotency_key: Some(event.idempotency_key),
         metadata: event.metadata,
+        attachments: Vec::new(),
     };
 
     let result = ingest_inbound_message(state, inbound).await;
@@ -104,11 +138,21 @@ pub(crate) struct OutboundReplyDispatch<'a> {
     pub conversation_id: &'a str,
     pub source_sender_id: Option<&'a str>,
     pub source_message_id: Option<&'a str>,
-    pub reply: Option<&'a str>,
+    /// Messages to relay in order; each one is a separate delivery.
+    pub replies: &'a [String],
+    pub suppress_unfurl: bool,
     pub session_key: &'a str,
     pub run_id: Option<&'a str>,
     pub metadata: Option<Value>,
     pub log_scope: &'static str,
+    pub urgent: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OutboundOutcome {
+    Skipped,
+    Sent,
+    Queued,
 }
 
 pub(crate) async fn maybe_dispatch_outbound_reply(
@@ -116,62 +160,225 @@ pub(crate) async fn maybe_dispatch_outbound_reply(
     outbound_url: Option<&str>,
     outbound_token: Option<&str>,
     dispatch: OutboundReplyDispatch<'_>,
-) -> bool {
-    let Some(reply) = dispatch
-        .reply
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-    else {
-        return false;
-    };
+) -> OutboundOutcome {
+    let replies = dispatch
+        .replies
+        .iter()
+        .map(|reply| reply.trim())
+        .filter(|reply| !reply.is_empty())
+        .collect::<Vec<_>>();
+    if replies.is_empty() {
+        return OutboundOutcome::Skipped;
+    }
     let Some(url) = outbound_url
         .map(str::trim)
         .filter(|value| !value.is_empty())
     else {
-        return false;
+        return OutboundOutcome::Skipped;
     };
 
-    let mut payload = json!({
-        "channel": dispatch.channel,
-        "conversationId": dispatch.conversation_id,
-        "reply": reply,
-        "sessionKey": dispatch.session_key,
-        "runId": dispatch.run_id,
-        "sourceSenderId": dispatch.source_sender_id,
-        "sourceMessageId": dispatch.source_message_id,
-    });
-    if let Some(metadata) = dispatch.metadata
-        && let Some(object) = payload.as_object_mut()
-    {
-        object.insert("metadata".to_owned(), metadata);
+    let mut outcome = OutboundOutcome::Skipped;
+    for (index, reply) in replies.iter().enumerate() {
+        let delivery = start_delivery(
+            state,
+            dispatch.session_key,
+            dispatch.run_id,
+            dispatch.channel,
+            dispatch.conversation_id,
+        )
+        .await;
+        let mut payload = json!({
+            "channel": dispatch.channel,
+            "conversationId": dispatch.conversation_id,
+            "reply": reply,
+            "sessionKey": dispatch.session_key,
+            "runId": dispatch.run_id,
+            "deliveryId": delivery.as_ref().map(|delivery| delivery.id.as_str()),
+            "sourceSenderId": dispatch.source_sender_id,
+            "sourceMessageId": dispatch.source_message_id,
+        });
+        if let Some(object) = payload.as_object_mut() {
+            if let Some(metadata) = &dispatch.metadata {
+                object.insert("metadata".to_owned(), metadata.clone());
+            }
+            if replies.len() > 1 {
+                object.insert("part".to_owned(), json!(index + 1));
+                object.insert("parts".to_owned(), json!(replies.len()));
+            }
+            if dispatch.suppress_unfurl {
+                object.insert("unfurlLinks".to_owned(), Value::Bool(false));
+            }
+        }
+
+        if quiet_hours::hold_if_quiet(state, dispatch.channel, &payload, dispatch.urgent).await {
+            outcome = OutboundOutcome::Queued;
+            continue;
+        }
+
+        match post_json(url, outbound_token, &payload).await {
+            Ok(body) => {
+                finish_delivery(state, delivery, Ok(platform_message_id(&body))).await;
+                if outcome == OutboundOutcome::Skipped {
+                    outcome = OutboundOutcome::Sent;
+                }
+            }
+            Err(error) => {
+                finish_delivery(state, delivery, Err(&error)).await;
+                warn!(
+                    "{} outbound relay failed for channel {}: {}",
+                    dispatch.log_scope, dispatch.channel, error
+                );
+                let _ = state
+                    .append_gateway_log(
+                        "warn",
+                        &format!(
+                            "{} outbound relay failed for channel {}: {}",
+                            dispatch.log_scope, dispatch.channel, error
+                        ),
+                        Some(dispatch.log_scope),
+                        None,
+                    )
+                    .await;
+                // Later parts would arrive without the one that failed.
+                return OutboundOutcome::Skipped;
+            }
+        }
     }
+    outcome
+}
 
-    match post_json(url, outbound_token, &payload).await {
-        Ok(()) => true,
+/// A reply an adapter sends straight to the platform rather than through a relay.
+pub(crate) struct DirectReplyDispatch<'a> {
+    pub channel: &'static str,
+    pub conversation_id: &'a str,
+    pub session_key: &'a str,
+    pub run_id: Option<&'a str>,
+    pub log_scope: &'static str,
+    pub urgent: bool,
+}
+
+/// Sends each reply part through `quiet_hours::deliver_payload` as its own tracked delivery.
+/// `payload` builds the channel's queued payload from a part and its delivery id. Sending stops
+/// at the first failure so later parts never arrive without an earlier one.
+pub(crate) async fn send_reply_parts(
+    state: &SharedState,
+    dispatch: DirectReplyDispatch<'_>,
+    parts: &[String],
+    payload: impl Fn(&str, Option<&str>) -> Value,
+) -> OutboundOutcome {
+    let mut outcome = OutboundOutcome::Skipped;
+    for part in parts {
+        let delivery = start_delivery(
+            state,
+            dispatch.session_key,
+            dispatch.run_id,
+            dispatch.channel,
+            dispatch.conversation_id,
+        )
+        .await;
+        let part_payload = payload(part, delivery.as_ref().map(|delivery| delivery.id.as_str()));
+        if quiet_hours::hold_if_quiet(state, dispatch.channel, &part_payload, dispatch.urgent).await
+        {
+            outcome = OutboundOutcome::Queued;
+            continue;
+        }
+        match quiet_hours::deliver_payload(state, dispatch.channel, &part_payload).await {
+            Ok(platform_message_id) => {
+                finish_delivery(state, delivery, Ok(platform_message_id)).await;
+                if outcome == OutboundOutcome::Skipped {
+                    outcome = OutboundOutcome::Sent;
+                }
+            }
+            Err(error) => {
+                finish_delivery(state, delivery, Err(&error)).await;
+                warn!("{} outbound send failed: {error}", dispatch.channel);
+                let _ = state
+                    .append_gateway_log(
+                        "warn",
+                        &format!("{} outbound send failed: {error}", dispatch.channel),
+                        Some(dispatch.log_scope),
+                        None,
+                    )
+                    .await;
+                return OutboundOutcome::Skipped;
+            }
+        }
+    }
+    outcome
+}
+
+/// Starts tracking an outbound reply; tracking failures never block the reply itself.
+pub(crate) async fn start_delivery(
+    state: &SharedState,
+    session_key: &str,
+    run_id: Option<&str>,
+    channel: &str,
+    conversation_id: &str,
+) -> Option<MessageDelivery> {
+    match state
+        .record_message_delivery(session_key, run_id, channel, conversation_id)
+        .await
+    {
+        Ok(delivery) => Some(delivery),
         Err(error) => {
-            warn!(
-                "{} outbound relay failed for channel {}: {}",
-                dispatch.log_scope, dispatch.channel, error
-            );
-            let _ = state
-                .append_gateway_log(
-                    "warn",
-                    &format!(
-                        "{} outbound relay failed for channel {}: {}",
-                        dispatch.log_scope, dispatch.channel, error
-                    ),
-                    Some(dispatch.log_scope),
-                    None,
-                )
-                .await;
-            false
+            warn!("failed to record {channel} message delivery: {error}");
+            None
         }
     }
 }
 
+/// Records the send attempt of a tracked delivery: `Ok` carries the platform message id when
+/// the relay or platform returned one.
+pub(crate) async fn finish_delivery(
+    state: &SharedState,
+    delivery: Option<MessageDelivery>,
+    result: Result<Option<String>, &str>,
+) {
+    let Some(delivery) = delivery else {
+        return;
+    };
+    let (status, platform_message_id, error) = match result {
+        Ok(platform_message_id) => (DeliveryStatus::Sent, platform_message_id, None),
+        Err(error) => (DeliveryStatus::Failed, None, Some(error.to_owned())),
+    };
+    if let Err(error) = state
+        .advance_message_delivery(delivery, status, platform_message_id, error)
+        .await
+    {
+        warn!("failed to update message delivery: {error}");
+    }
+}
+
+/// Extracts the platform message id a relay reports in its response (`messageId` or `id`).
+pub(crate) fn platform_message_id(body: &Value) -> Option<String> {
+    match body.get("messageId").or_else(|| body.get("id"))? {
+        Value::String(id) if !id.trim().is_empty() => Some(id.trim().to_owned()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Resolves the configured relay endpoint (`url`, `token`) for a bridged channel.
+pub(crate) fn outbound_relay_target<'a>(
+    config: &'a RuntimeConfig,
+    channel: &str,
+) -> Option<(&'a str, Option<&'a str>)> {
+    let (url, token) = match channel {
+        "discord" => (&config.discord_outbound_url, &config.discord_outbound_token),
+        "slack" => (&config.slack_outbound_url, &config.slack_outbound_token),
+        "signal" => (&config.signal_outbound_url, &config.signal_outbound_token),
+        "whatsapp" => (
+            &config.whatsapp_outbound_url,
+            &config.whatsapp_outbound_token,
+        ),
+        _ => return None,
+    };
+    url.as_deref().map(|url| (url, token.as_deref()))
+}
+
 pub(crate) fn accepted_true_with_outbound(
     result: &InboundProcessResult,
-    outbound_sent: bool,
+    outbound: OutboundOutcome,
 ) -> (StatusCode, Json<Value>) {
     (
         StatusCode::OK,
@@ -181,7 +388,8 @@ pub(crate) fn accepted_true_with_outbound(
             "sessionKey": result.session_key,
             "runId": result.run_id,
             "reply": result.reply,
-            "outboundSent": outbound_sent,
+            "outboundSent": outbound == OutboundOutcome::Sent,
+            "outboundQueued": outbound == OutboundOutcome::Queued,
         })),
     )
 }
@@ -248,7 +456,13 @@ fn has_bearer_token(headers: &HeaderMap, expected: &str) -> bool {
     subtle::ConstantTimeEq::ct_eq(token.as_bytes(), expected.as_bytes()).into()
 }
 
-async fn post_json(url: &str, token: Option<&str>, payload: &Value) -> Result<(), String> {
+/// POSTs `payload` and returns the JSON response body (`Null` when the body is empty or not
+/// JSON).
+pub(crate) async fn post_json(
+    url: &str,
+    token: Option<&str>,
+    payload: &Value,
+) -> Result<Value, String> {
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(10))
         .build()
@@ -269,5 +483,6 @@ async fn post_json(url: &str, token: Option<&str>, payload: &Value) -> Result<()
         return Err(format!("unexpected status {status}: {body}"));
     }
 
-    Ok(())
+    let body = response.bytes().await.unwrap_or_default();
+    Ok(serde_json::from_slice(&body).unwrap_or(Value::Null))
 }
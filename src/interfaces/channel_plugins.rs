@@ -0,0 +1,400 @@
+//! Channel webhook adapters served by external plugins declared in `channelPluginsDir`.
+//!
+//! Every webhook call runs three plugin steps, each a JSON request answered with a JSON reply:
+//! `verify` authenticates the call, `parse` extracts the inbound messages, and `respond` gets the
+//! agent replies and shapes the HTTP response the platform receives. The contract is documented
+//! in `docs/spec/channel-adapters.md`.
+
+use std::process::Stdio;
+
+use axum::{
+    Json,
+    http::{HeaderMap, StatusCode},
+};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use tokio::{io::AsyncWriteExt, process::Command, time::timeout};
+use tracing::warn;
+
+use crate::{
+    application::{
+        config::{ChannelPlugin, ChannelPluginTransport},
+        state::SharedState,
+    },
+    interfaces::channels::InboundProcessResult,
+};
+
+use super::channel_adapter_common as common;
+
+const PLUGIN_TOKEN_HEADER: &str = "x-reclaw-plugin-token";
+const PLUGIN_CHANNEL_HEADER: &str = "x-reclaw-channel";
+const PLUGIN_CHANNEL_ENV: &str = "RECLAW_CHANNEL";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyReply {
+    #[serde(default)]
+    ok: bool,
+    #[serde(default)]
+    status: Option<u16>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParseReply {
+    #[serde(default)]
+    messages: Vec<PluginInboundMessage>,
+    /// Why nothing was accepted, reported when `messages` is empty.
+    #[serde(default)]
+    reason: Option<String>,
+    /// Returned as-is when `messages` is empty, e.g. to answer a platform's URL verification.
+    #[serde(default)]
+    response: Option<HttpReply>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PluginInboundMessage {
+    conversation_id: String,
+    text: String,
+    #[serde(default)]
+    sender_id: Option<String>,
+    #[serde(default)]
+    message_id: Option<String>,
+    #[serde(default)]
+    metadata: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HttpReply {
+    #[serde(default)]
+    status: Option<u16>,
+    #[serde(default)]
+    body: Option<Value>,
+}
+
+pub(crate) async fn dispatch_webhook(
+    state: &SharedState,
+    channel: &str,
+    plugin: &ChannelPlugin,
+    headers: &HeaderMap,
+    payload: Value,
+) -> (StatusCode, Json<Value>) {
+    let headers = headers_json(headers);
+    let request = |op: &str| {
+        json!({
+            "op": op,
+            "channel": channel,
+            "headers": headers,
+            "payload": payload,
+        })
+    };
+
+    let verify = match call_step::<VerifyReply>(state, channel, plugin, request("verify")).await {
+        Ok(reply) => reply,
+        Err(error) => return error,
+    };
+    if !verify.ok {
+        let status = verify
+            .status
+            .and_then(|status| StatusCode::from_u16(status).ok())
+            .filter(|status| status.is_client_error() || status.is_server_error())
+            .unwrap_or(StatusCode::UNAUTHORIZED);
+        return (
+            status,
+            Json(json!({
+                "ok": false,
+                "error": {
+                    "code": "UNAUTHORIZED",
+                    "message": verify
+                        .message
+                        .unwrap_or_else(|| format!("{channel} plugin rejected the webhook")),
+                }
+            })),
+        );
+    }
+
+    let parsed = match call_step::<ParseReply>(state, channel, plugin, request("parse")).await {
+        Ok(reply) => reply,
+        Err(error) => return error,
+    };
+    if parsed.messages.is_empty() {
+        if let Some(response) = parsed.response {
+            return http_reply(response, json!({ "ok": true, "accepted": false }));
+        }
+        return common::accepted_false(parsed.reason.unwrap_or_else(|| "no-messages".to_owned()));
+    }
+
+    let mut results = Vec::new();
+    let mut duplicates = 0_usize;
+    for message in parsed.messages {
+        let dedupe_key = message
+            .message_id
+            .as_deref()
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| format!("runtime/plugin/{channel}/event/{id}"));
+        if let Some(key) = &dedupe_key
+            && common::is_duplicate_event(state, key).await
+        {
+            duplicates += 1;
+            continue;
+        }
+        let idempotency_key = message
+            .message_id
+            .as_deref()
+            .map(|id| format!("plugin-{channel}-{id}"))
+            .unwrap_or_else(|| format!("plugin-{channel}-{}", uuid::Uuid::new_v4()));
+        let result = match common::ingest_channel_message(
+            state,
+            common::ChannelInboundEvent {
+                channel,
+                conversation_id: message.conversation_id.clone(),
+                text: message.text,
+                sender_id: message.sender_id,
+                message_id: message.message_id.clone(),
+                idempotency_key,
+                metadata: Some(plugin_metadata(channel, message.metadata)),
+            },
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(error) => return error,
+        };
+        if let (Some(key), Some(id)) = (&dedupe_key, message.message_id.as_deref()) {
+            common::mark_event_processed(state, key, channel, id, &result).await;
+        }
+        results.push((message.conversation_id, message.message_id, result));
+    }
+    if results.is_empty() {
+        return (
+            StatusCode::OK,
+            Json(json!({
+                "ok": true,
+                "accepted": false,
+                "duplicate": true,
+            })),
+        );
+    }
+
+    let results = results
+        .iter()
+        .map(|(conversation_id, message_id, result)| {
+            result_json(conversation_id, message_id.as_deref(), result)
+        })
+        .collect::<Vec<_>>();
+    let default_body = json!({
+        "ok": true,
+        "accepted": true,
+        "duplicates": duplicates,
+        "results": results,
+    });
+    let mut respond = request("respond");
+    respond["results"] = Value::Array(results);
+    match call_step::<HttpReply>(state, channel, plugin, respond).await {
+        Ok(reply) => http_reply(reply, default_body),
+        Err(error) => error,
+    }
+}
+
+/// Runs one contract step, turning transport and shape failures into a `502 BAD_GATEWAY`.
+async fn call_step<T: serde::de::DeserializeOwned>(
+    state: &SharedState,
+    channel: &str,
+    plugin: &ChannelPlugin,
+    request: Value,
+) -> Result<T, (StatusCode, Json<Value>)> {
+    let op = request["op"].as_str().unwrap_or_default().to_owned();
+    let error = match call_plugin(channel, plugin, &request).await {
+        Ok(reply) => match serde_json::from_value::<T>(reply) {
+            Ok(reply) => return Ok(reply),
+            Err(error) => format!("reply shape is invalid: {error}"),
+        },
+        Err(error) => error,
+    };
+    let message = format!("{channel} plugin {op} failed: {error}");
+    warn!("{message}");
+    let _ = state
+        .append_gateway_log("warn", &message, Some("channels.plugin"), None)
+        .await;
+    Err((
+        StatusCode::BAD_GATEWAY,
+        Json(json!({
+            "ok": false,
+            "error": {
+                "code": "BAD_GATEWAY",
+                "message": message,
+            }
+        })),
+    ))
+}
+
+async fn call_plugin(
+    channel: &str,
+    plugin: &ChannelPlugin,
+    request: &Value,
+) -> Result<Value, String> {
+    match &plugin.transport {
+        ChannelPluginTransport::Command { program, args } => {
+            let input = serde_json::to_vec(request)
+                .map_err(|error| format!("failed to encode request: {error}"))?;
+            let mut child = Command::new(program)
+                .args(args)
+                .env(PLUGIN_CHANNEL_ENV, channel)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|error| format!("failed to start {}: {error}", program.display()))?;
+            let run = async {
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin
+                        .write_all(&input)
+                        .await
+                        .map_err(|error| format!("failed to write request: {error}"))?;
+                }
+                child
+                    .wait_with_output()
+                    .await
+                    .map_err(|error| format!("failed to run plugin: {error}"))
+            };
+            let output = timeout(plugin.timeout, run)
+                .await
+                .map_err(|_| format!("timed out after {}ms", plugin.timeout.as_millis()))??;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+                return Err(format!(
+                    "exited with {}{}",
+                    output.status,
+                    if stderr.is_empty() {
+                        String::new()
+                    } else {
+                        format!(": {stderr}")
+                    }
+                ));
+            }
+            serde_json::from_slice(&output.stdout)
+                .map_err(|error| format!("reply must be valid JSON: {error}"))
+        }
+        ChannelPluginTransport::Url { url, token } => {
+            let client = reqwest::Client::builder()
+                .timeout(plugin.timeout)
+                .build()
+                .map_err(|error| format!("failed to construct http client: {error}"))?;
+            let mut http_request = client
+                .post(url)
+                .header(PLUGIN_CHANNEL_HEADER, channel)
+                .json(request);
+            if let Some(token) = token.as_deref() {
+                http_request = http_request.header(PLUGIN_TOKEN_HEADER, token);
+            }
+            let response = http_request
+                .send()
+                .await
+                .map_err(|error| format!("request failed: {error}"))?;
+            let status = response.status();
+            let body = response
+                .bytes()
+                .await
+                .map_err(|error| format!("failed to read reply: {error}"))?;
+            if !status.is_success() {
+                return Err(format!(
+                    "unexpected status {status}: {}",
+                    String::from_utf8_lossy(&body)
+                ));
+            }
+            serde_json::from_slice(&body)
+                .map_err(|error| format!("reply must be valid JSON: {error}"))
+        }
+    }
+}
+
+fn headers_json(headers: &HeaderMap) -> Value {
+    let mut object = Map::new();
+    for (name, value) in headers {
+        if let Ok(value) = value.to_str() {
+            object
+                .entry(name.as_str())
+                .and_modify(|existing| {
+                    if let Value::String(existing) = existing {
+                        existing.push_str(", ");
+                        existing.push_str(value);
+                    }
+                })
+                .or_insert_with(|| Value::String(value.to_owned()));
+        }
+    }
+    Value::Object(object)
+}
+
+fn plugin_metadata(channel: &str, metadata: Option<Value>) -> Value {
+    let mut object = match metadata {
+        Some(Value::Object(object)) => object,
+        _ => Map::new(),
+    };
+    object.insert("source".to_owned(), json!("plugin"));
+    object.insert("plugin".to_owned(), json!(channel));
+    Value::Object(object)
+}
+
+fn result_json(
+    conversation_id: &str,
+    message_id: Option<&str>,
+    result: &InboundProcessResult,
+) -> Value {
+    json!({
+        "conversationId": conversation_id,
+        "messageId": message_id,
+        "sessionKey": result.session_key,
+        "runId": result.run_id,
+        "reply": result.reply,
+        "replyParts": result.reply_parts,
+    })
+}
+
+fn http_reply(reply: HttpReply, default_body: Value) -> (StatusCode, Json<Value>) {
+    let status = reply
+        .status
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .unwrap_or(StatusCode::OK);
+    let body = match reply.body {
+        Some(Value::Null) | None => default_body,
+        Some(body) => body,
+    };
+    (status, Json(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue, StatusCode};
+    use serde_json::json;
+
+    use super::{HttpReply, headers_json, http_reply};
+
+    #[test]
+    fn plugin_replies_shape_the_http_response() {
+        let mut headers = HeaderMap::new();
+        headers.append("x-sig", HeaderValue::from_static("a"));
+        headers.append("x-sig", HeaderValue::from_static("b"));
+        assert_eq!(headers_json(&headers), json!({"x-sig": "a, b"}));
+
+        let (status, body) = http_reply(HttpReply::default(), json!({"ok": true}));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.0, json!({"ok": true}));
+
+        let (status, body) = http_reply(
+            HttpReply {
+                status: Some(202),
+                body: Some(json!({"challenge": "xyz"})),
+            },
+            json!({"ok": true}),
+        );
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(body.0, json!({"challenge": "xyz"}));
+    }
+}
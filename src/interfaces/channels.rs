@@ -8,10 +8,25 @@ use serde::Deserialize;
 use serde_json::{Value, json};
 
 use crate::{
-    application::state::SharedState,
-    rpc::{SessionContext, methods, policy},
+    application::{
+        chat_commands::{self, ChatConversation},
+        config::ReplyFormat,
+        content_policy,
+        reply_processing::{ProcessedReply, ReplyProcessing},
+        state::SharedState,
+    },
+    domain::models::{ChannelDirectoryInput, DeliveryStatus},
+    rpc::{
+        SessionContext,
+        methods::{self, takeover},
+        policy,
+    },
+    storage::now_unix_ms,
 };
 
+/// Upper bound on messages accepted by one `/channels/{channel}/inbound/batch` request.
+const MAX_INBOUND_BATCH: usize = 100;
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InboundMessageRequest {
@@ -28,6 +43,9 @@ pub struct InboundMessageRequest {
     pub idempotency_key: Option<String>,
     #[serde(default)]
     pub metadata: Option<Value>,
+    /// Files sent with the message (`name`, `mimeType`, base64 `data`), scanned before storage.
+    #[serde(default)]
+    pub attachments: Vec<Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -45,6 +63,29 @@ pub struct ChannelInboundRequest {
     pub idempotency_key: Option<String>,
     #[serde(default)]
     pub metadata: Option<Value>,
+    /// Files sent with the message (`name`, `mimeType`, base64 `data`), scanned before storage.
+    #[serde(default)]
+    pub attachments: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelInboundBatchRequest {
+    pub messages: Vec<ChannelInboundRequest>,
+}
+
+/// Delivery receipt a channel bridge reports for a reply it relayed. The delivery is addressed
+/// by the `deliveryId` the gateway sent with the reply or by the platform `messageId`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelReceiptRequest {
+    #[serde(default)]
+    pub delivery_id: Option<String>,
+    #[serde(default)]
+    pub message_id: Option<String>,
+    pub status: String,
+    #[serde(default)]
+    pub error: Option<String>,
 }
 
 pub async fn inbound_handler(
@@ -70,16 +111,166 @@ pub async fn inbound_channel_handler(
         message_id: payload.message_id,
         idempotency_key: payload.idempotency_key,
         metadata: payload.metadata,
+        attachments: payload.attachments,
     };
     ingress_response(&state, &headers, inbound).await
 }
 
+/// Accepts a burst of messages from one bridge. The batch is rejected as a whole when any
+/// message is invalid; otherwise duplicates (within the batch or of earlier runs) are settled
+/// in one pass, each conversation's directory row is written once, and the remaining messages
+/// are processed in order with a result per item.
+pub async fn inbound_batch_handler(
+    Path(channel): Path<String>,
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(payload): Json<ChannelInboundBatchRequest>,
+) -> impl IntoResponse {
+    if let Some(rejection) = reject_unauthorized(&state, &headers) {
+        return rejection;
+    }
+    if payload.messages.is_empty() {
+        return bad_request("messages must not be empty");
+    }
+    if payload.messages.len() > MAX_INBOUND_BATCH {
+        return bad_request(&format!(
+            "messages must not exceed {MAX_INBOUND_BATCH} items"
+        ));
+    }
+
+    let mut normalized = Vec::with_capacity(payload.messages.len());
+    let mut errors = Vec::new();
+    for (index, message) in payload.messages.into_iter().enumerate() {
+        let request = InboundMessageRequest {
+            channel: channel.clone(),
+            conversation_id: message.conversation_id,
+            text: message.text,
+            agent_id: message.agent_id,
+            sender_id: message.sender_id,
+            message_id: message.message_id,
+            idempotency_key: message.idempotency_key,
+            metadata: message.metadata,
+            attachments: message.attachments,
+        };
+        match normalize_inbound(request) {
+            Ok(inbound) => normalized.push(inbound),
+            Err(message) => errors.push(json!({ "index": index, "message": message })),
+        }
+    }
+    if !errors.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "ok": false,
+                "error": {
+                    "code": crate::protocol::ERROR_INVALID_REQUEST,
+                    "message": "batch contains invalid messages",
+                },
+                "errors": errors,
+            })),
+        );
+    }
+
+    let mut results = vec![Value::Null; normalized.len()];
+    let mut pending = Vec::with_capacity(normalized.len());
+    let mut first_index = std::collections::HashMap::new();
+    for (index, inbound) in normalized.into_iter().enumerate() {
+        if let Some(original) = first_index.get(&inbound.idempotency_key) {
+            results[index] = json!({
+                "index": index,
+                "status": "duplicate",
+                "duplicateOf": original,
+                "runId": inbound.idempotency_key,
+            });
+            continue;
+        }
+        first_index.insert(inbound.idempotency_key.clone(), index);
+        if let Ok(Some(run)) = state.get_agent_run(&inbound.idempotency_key).await {
+            results[index] = json!({
+                "index": index,
+                "status": "duplicate",
+                "sessionKey": run.session_key,
+                "runId": run.id,
+            });
+            continue;
+        }
+        pending.push((index, inbound));
+    }
+
+    for (_, inbound) in &mut pending {
+        resolve_person_session(&state, inbound).await;
+    }
+    // Later messages carry the freshest naming hints for their conversation.
+    let mut directory = std::collections::BTreeMap::new();
+    for (_, inbound) in &pending {
+        directory.insert(inbound.conversation.clone(), inbound);
+    }
+    for inbound in directory.into_values() {
+        record_directory_entry(&state, inbound).await;
+    }
+
+    for (index, inbound) in pending {
+        results[index] = match dispatch_inbound(&state, inbound).await {
+            Ok(result) => json!({
+                "index": index,
+                "status": "processed",
+                "sessionKey": result.session_key,
+                "runId": result.run_id,
+                "reply": result.reply,
+                "replyParts": result.reply_parts,
+                "unfurlLinks": !result.suppress_unfurl,
+                "replyFormat": result.reply_format,
+            }),
+            Err(error) => json!({
+                "index": index,
+                "status": "failed",
+                "error": error,
+            }),
+        };
+    }
+
+    let count_status = |status: &str| {
+        results
+            .iter()
+            .filter(|result| result["status"] == status)
+            .count()
+    };
+    let summary = json!({
+        "ok": true,
+        "count": results.len(),
+        "processed": count_status("processed"),
+        "duplicates": count_status("duplicate"),
+        "failed": count_status("failed"),
+        "results": results,
+    });
+    (StatusCode::OK, Json(summary))
+}
+
 #[derive(Debug)]
 struct NormalizedInbound {
     channel: String,
+    conversation: String,
+    agent_id: String,
+    sender_id: Option<String>,
     text: String,
     session_key: String,
     idempotency_key: String,
+    /// The conversation id as the channel sent it, for operator replies during a takeover.
+    raw_conversation_id: String,
+    service_url: Option<String>,
+    directory: DirectoryHints,
+    attachments: Vec<Value>,
+    /// The sender's language from `metadata.locale`, for slash command help.
+    locale: Option<String>,
+}
+
+/// Conversation naming hints adapters pass through inbound metadata
+/// (`conversationTitle`, `conversationKind`, `senderName`).
+#[derive(Debug, Default)]
+struct DirectoryHints {
+    title: Option<String>,
+    kind: Option<String>,
+    participant: Option<String>,
 }
 
 #[derive(Debug)]
@@ -87,15 +278,162 @@ pub struct InboundProcessResult {
     pub session_key: String,
     pub run_id: Option<String>,
     pub reply: Option<String>,
+    /// The reply split into the messages adapters send, in order; empty without a reply.
+    pub reply_parts: Vec<String>,
+    /// Set when `replyProcessing` asks adapters to disable link previews for this reply.
+    pub suppress_unfurl: bool,
+    /// Markup dialect of `reply` and `reply_parts`.
+    pub reply_format: ReplyFormat,
 }
 
 pub async fn ingest_inbound_message(
     state: &SharedState,
     payload: InboundMessageRequest,
 ) -> Result<InboundProcessResult, crate::protocol::ErrorShape> {
-    let inbound = normalize_inbound(payload).map_err(|message| {
+    let mut inbound = normalize_inbound(payload).map_err(|message| {
         crate::protocol::ErrorShape::new(crate::protocol::ERROR_INVALID_REQUEST, message)
     })?;
+    let settings = if state.config().chat_commands.is_some() {
+        chat_commands::conversation_settings(state, &inbound.channel, &inbound.conversation).await
+    } else {
+        chat_commands::ConversationSettings::default()
+    };
+    if let Some(agent_id) = settings.agent_id.as_deref() {
+        inbound.agent_id = agent_id.to_owned();
+        inbound.session_key =
+            conversation_session_key(agent_id, &inbound.channel, &inbound.conversation);
+    }
+    resolve_person_session(state, &mut inbound).await;
+    // A channel retry reuses the idempotency key of a message `chat.send` already ran; counting it
+    // again would inflate the directory.
+    let retried = state
+        .get_agent_run(&inbound.idempotency_key)
+        .await
+        .is_ok_and(|run| run.is_some());
+    if !retried {
+        record_directory_entry(state, &inbound).await;
+    }
+
+    let command_reply = chat_commands::dispatch(
+        state,
+        &inbound.text,
+        ChatConversation {
+            channel: &inbound.channel,
+            conversation_id: &inbound.conversation,
+            session_key: &inbound.session_key,
+            agent_id: &inbound.agent_id,
+            sender_id: inbound.sender_id.as_deref(),
+            locale: inbound.locale.as_deref(),
+        },
+    )
+    .await;
+    if let Some(reply) = command_reply {
+        // Command replies skip the agent and chat history but are formatted like agent replies.
+        let reply = process_reply(state, &inbound, &reply);
+        return Ok(InboundProcessResult {
+            session_key: inbound.session_key,
+            run_id: None,
+            suppress_unfurl: reply.suppress_unfurl,
+            reply_format: reply.format,
+            reply_parts: reply.parts,
+            reply: Some(reply.text),
+        });
+    }
+    if settings.muted_at(now_unix_ms()) {
+        return Ok(no_reply(inbound.session_key));
+    }
+
+    dispatch_inbound(state, inbound).await
+}
+
+fn no_reply(session_key: String) -> InboundProcessResult {
+    InboundProcessResult {
+        session_key,
+        run_id: None,
+        reply: None,
+        reply_parts: Vec::new(),
+        suppress_unfurl: false,
+        reply_format: ReplyFormat::default(),
+    }
+}
+
+fn process_reply(state: &SharedState, inbound: &NormalizedInbound, reply: &str) -> ProcessedReply {
+    match &state.config().reply_processing {
+        Some(processing) => processing.process(&inbound.channel, &inbound.agent_id, reply),
+        None => ReplyProcessing::default().process(&inbound.channel, &inbound.agent_id, reply),
+    }
+}
+
+pub(crate) fn conversation_session_key(
+    agent_id: &str,
+    channel: &str,
+    conversation: &str,
+) -> String {
+    format!("agent:{agent_id}:{channel}:chat:{conversation}")
+}
+
+async fn resolve_person_session(state: &SharedState, inbound: &mut NormalizedInbound) {
+    // Senders linked to a person with a shared session converge on one session across channels.
+    let identity = inbound
+        .sender_id
+        .as_deref()
+        .unwrap_or(inbound.conversation.as_str());
+    if let Ok(Some(person)) = state
+        .find_person_by_identity(&inbound.channel, identity)
+        .await
+        && person.shared_session
+    {
+        inbound.session_key = format!("agent:{}:person:{}", inbound.agent_id, person.person_id);
+    }
+}
+
+async fn record_directory_entry(state: &SharedState, inbound: &NormalizedInbound) {
+    let _ = state
+        .record_channel_directory_entry(&ChannelDirectoryInput {
+            channel: inbound.channel.clone(),
+            conversation_id: inbound.conversation.clone(),
+            title: inbound.directory.title.clone(),
+            kind: inbound.directory.kind.clone(),
+            participant: inbound.directory.participant.clone(),
+            seen_at_ms: now_unix_ms(),
+        })
+        .await;
+}
+
+/// Runs a normalized message through content policy, takeover routing, and the agent.
+async fn dispatch_inbound(
+    state: &SharedState,
+    mut inbound: NormalizedInbound,
+) -> Result<InboundProcessResult, crate::protocol::ErrorShape> {
+    let Some(text) = content_policy::enforce(
+        state,
+        &inbound.channel,
+        "inbound",
+        &inbound.session_key,
+        &inbound.text,
+    )
+    .await
+    else {
+        return Ok(no_reply(inbound.session_key));
+    };
+    inbound.text = text;
+
+    // Sessions under operator takeover skip the agent; operators reply via `chat.takeover.reply`.
+    if takeover::route_inbound(
+        state,
+        &inbound.session_key,
+        takeover::TakeoverTarget {
+            channel: inbound.channel.clone(),
+            conversation_id: inbound.raw_conversation_id.clone(),
+            service_url: inbound.service_url.clone(),
+        },
+        inbound.sender_id.as_deref(),
+        &inbound.text,
+    )
+    .await?
+    {
+        return Ok(no_reply(inbound.session_key));
+    }
 
     let session = SessionContext {
         conn_id: format!("http-inbound-{}", uuid::Uuid::new_v4()),
@@ -109,6 +447,7 @@ pub async fn ingest_inbound_message(
         "sessionKey": inbound.session_key,
         "message": inbound.text,
         "idempotencyKey": inbound.idempotency_key,
+        "attachments": inbound.attachments,
     });
     let payload = methods::chat::handle_send(state, &session, Some(&params)).await?;
 
@@ -116,10 +455,21 @@ pub async fn ingest_inbound_message(
         .get("runId")
         .and_then(Value::as_str)
         .map(str::to_owned);
-    let reply = payload
-        .get("message")
-        .and_then(Value::as_str)
-        .map(str::to_owned);
+    // History keeps the agent's original reply; only the delivered copy is filtered.
+    let reply = match payload.get("message").and_then(Value::as_str) {
+        Some(reply) => {
+            content_policy::enforce(
+                state,
+                &inbound.channel,
+                "outbound",
+                &inbound.session_key,
+                reply,
+            )
+            .await
+        }
+        None => None,
+    };
+    let reply = reply.map(|reply| process_reply(state, &inbound, &reply));
 
     Ok(InboundProcessResult {
         session_key: params
@@ -128,7 +478,13 @@ pub async fn ingest_inbound_message(
             .unwrap_or_default()
             .to_owned(),
         run_id,
-        reply,
+        suppress_unfurl: reply.as_ref().is_some_and(|reply| reply.suppress_unfurl),
+        reply_format: reply.as_ref().map(|reply| reply.format).unwrap_or_default(),
+        reply_parts: reply
+            .as_ref()
+            .map(|reply| reply.parts.clone())
+            .unwrap_or_default(),
+        reply: reply.map(|reply| reply.text),
     })
 }
 
@@ -154,8 +510,23 @@ fn normalize_inbound(input: InboundMessageRequest) -> Result<NormalizedInbound,
         return Err("text is required".to_owned());
     }
 
-    let _ = input.sender_id;
-    let _ = input.metadata;
+    let directory = directory_hints(input.metadata.as_ref(), input.sender_id.as_deref());
+    let service_url = input
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("serviceUrl"))
+        .and_then(Value::as_str)
+        .map(str::to_owned);
+    let locale = input
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("locale"))
+        .and_then(Value::as_str)
+        .map(str::to_owned);
+    let sender_id = input
+        .sender_id
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty());
 
     let message_part = input
         .message_id
@@ -171,13 +542,42 @@ fn normalize_inbound(input: InboundMessageRequest) -> Result<NormalizedInbound,
 
     Ok(NormalizedInbound {
         channel: channel.clone(),
+        session_key: conversation_session_key(&agent_id, &channel, &conversation),
+        conversation,
+        agent_id,
+        sender_id,
         text,
-        session_key: format!("agent:{agent_id}:{channel}:chat:{conversation}"),
         idempotency_key,
+        raw_conversation_id: input.conversation_id.trim().to_owned(),
+        service_url,
+        directory,
+        attachments: input.attachments,
+        locale,
     })
 }
 
-fn normalize_segment(value: &str) -> String {
+fn directory_hints(metadata: Option<&Value>, sender_id: Option<&str>) -> DirectoryHints {
+    let read = |key: &str| {
+        metadata
+            .and_then(|value| value.get(key))
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_owned)
+    };
+    let sender_id = sender_id
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned);
+
+    DirectoryHints {
+        title: read("conversationTitle"),
+        kind: read("conversationKind"),
+        participant: read("senderName").or(sender_id),
+    }
+}
+
+pub(crate) fn normalize_segment(value: &str) -> String {
     let mut out = String::new();
     let mut pending_dash = false;
 
@@ -215,24 +615,108 @@ fn has_bearer_token(headers: &HeaderMap, expected: &str) -> bool {
     subtle::ConstantTimeEq::ct_eq(token.as_bytes(), expected.as_bytes()).into()
 }
 
-async fn ingress_response(
-    state: &SharedState,
-    headers: &HeaderMap,
-    payload: InboundMessageRequest,
-) -> (StatusCode, Json<Value>) {
-    if let Some(required_token) = &state.config().channels_inbound_token
-        && !has_bearer_token(headers, required_token)
+pub async fn receipts_handler(
+    Path(channel): Path<String>,
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(payload): Json<ChannelReceiptRequest>,
+) -> impl IntoResponse {
+    if let Some(rejection) = reject_unauthorized(&state, &headers) {
+        return rejection;
+    }
+
+    let Some(status) = DeliveryStatus::parse(&payload.status)
+        .filter(|status| !matches!(status, DeliveryStatus::Queued))
+    else {
+        return bad_request("status must be one of sent, delivered, read, failed");
+    };
+    let delivery_id = payload.delivery_id.as_deref().map(str::trim);
+    let message_id = payload.message_id.as_deref().map(str::trim);
+    if delivery_id.is_none_or(str::is_empty) && message_id.is_none_or(str::is_empty) {
+        return bad_request("deliveryId or messageId is required");
+    }
+
+    match state
+        .apply_delivery_receipt(
+            channel.trim(),
+            delivery_id.filter(|value| !value.is_empty()),
+            message_id.filter(|value| !value.is_empty()),
+            status,
+            payload.error,
+        )
+        .await
     {
-        return (
-            StatusCode::UNAUTHORIZED,
+        Ok(Some(delivery)) => (
+            StatusCode::OK,
+            Json(json!({
+                "ok": true,
+                "deliveryId": delivery.id,
+                "status": delivery.status,
+            })),
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
             Json(json!({
                 "ok": false,
                 "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "invalid or missing bearer token",
+                    "code": "NOT_FOUND",
+                    "message": "unknown delivery",
                 }
             })),
-        );
+        ),
+        Err(error) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "ok": false,
+                "error": {
+                    "code": crate::protocol::ERROR_UNAVAILABLE,
+                    "message": error.to_string(),
+                }
+            })),
+        ),
+    }
+}
+
+fn reject_unauthorized(
+    state: &SharedState,
+    headers: &HeaderMap,
+) -> Option<(StatusCode, Json<Value>)> {
+    let required_token = state.config().channels_inbound_token.as_ref()?;
+    if has_bearer_token(headers, required_token) {
+        return None;
+    }
+    Some((
+        StatusCode::UNAUTHORIZED,
+        Json(json!({
+            "ok": false,
+            "error": {
+                "code": "UNAUTHORIZED",
+                "message": "invalid or missing bearer token",
+            }
+        })),
+    ))
+}
+
+fn bad_request(message: &str) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({
+            "ok": false,
+            "error": {
+                "code": crate::protocol::ERROR_INVALID_REQUEST,
+                "message": message,
+            }
+        })),
+    )
+}
+
+async fn ingress_response(
+    state: &SharedState,
+    headers: &HeaderMap,
+    payload: InboundMessageRequest,
+) -> (StatusCode, Json<Value>) {
+    if let Some(rejection) = reject_unauthorized(state, headers) {
+        return rejection;
     }
 
     match ingest_inbound_message(state, payload).await {
@@ -243,6 +727,9 @@ async fn ingress_response(
                 "sessionKey": result.session_key,
                 "runId": result.run_id,
                 "reply": result.reply,
+                "replyParts": result.reply_parts,
+                "unfurlLinks": !result.suppress_unfurl,
+                "replyFormat": result.reply_format,
             })),
         ),
         Err(error) => {
@@ -264,11 +751,30 @@ async fn ingress_response(
 
 #[cfg(test)]
 mod tests {
-    use super::normalize_segment;
+    use serde_json::json;
+
+    use super::{directory_hints, normalize_segment};
 
     #[test]
     fn normalize_segment_preserves_alphanumeric_shape() {
         assert_eq!(normalize_segment("Telegram Chat 123"), "telegram-chat-123");
         assert_eq!(normalize_segment("###"), "");
     }
+
+    #[test]
+    fn directory_hints_prefer_sender_name_over_id() {
+        let metadata = json!({
+            "conversationTitle": " Ops Room ",
+            "conversationKind": "group",
+            "senderName": "Alice",
+        });
+        let hints = directory_hints(Some(&metadata), Some("1001"));
+        assert_eq!(hints.title.as_deref(), Some("Ops Room"));
+        assert_eq!(hints.kind.as_deref(), Some("group"));
+        assert_eq!(hints.participant.as_deref(), Some("Alice"));
+
+        let fallback = directory_hints(None, Some("1001"));
+        assert_eq!(fallback.participant.as_deref(), Some("1001"));
+        assert!(fallback.title.is_none());
+    }
 }
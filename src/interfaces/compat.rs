@@ -1,18 +1,109 @@
-use axum::http::{HeaderMap, header};
+use axum::http::{HeaderMap, StatusCode, header};
 use serde_json::Value;
 
 use crate::{
     application::state::SharedState,
     protocol::ConnectAuth,
+    rpc::{methods::apikeys, policy},
     security::auth::{self, AuthFailureReason},
 };
 
-pub(crate) fn authorize_gateway_http(
+/// Caller identity resolved from an HTTP `Authorization` header.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpPrincipal {
+    pub(crate) scopes: Vec<String>,
+    pub(crate) api_key_id: Option<String>,
+}
+
+impl HttpPrincipal {
+    /// Client id recorded on dispatched requests; API key callers are keyed by
+    /// key id so per-client limits apply to each key separately.
+    pub(crate) fn client_id(&self, fallback: &str) -> String {
+        match self.api_key_id.as_deref() {
+            Some(id) => format!("apikey:{id}"),
+            None => fallback.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum HttpAuthRejection {
+    Unauthorized(AuthFailureReason),
+    RateLimited {
+        key_name: String,
+        retry_after_ms: u64,
+    },
+    Unavailable(String),
+}
+
+impl HttpAuthRejection {
+    pub(crate) fn status(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    pub(crate) fn error_type(&self) -> &'static str {
+        match self {
+            Self::Unauthorized(_) => "authentication_error",
+            Self::RateLimited { .. } => "rate_limit_error",
+            Self::Unavailable(_) => "api_error",
+        }
+    }
+
+    pub(crate) fn message(&self) -> String {
+        match self {
+            Self::Unauthorized(reason) => auth::auth_failure_error(*reason).message,
+            Self::RateLimited {
+                key_name,
+                retry_after_ms,
+            } => format!(
+                "rate limit exceeded for api key {key_name}; retry after {}s",
+                retry_after_ms.div_ceil(1_000)
+            ),
+            Self::Unavailable(message) => message.clone(),
+        }
+    }
+}
+
+/// Authorizes an HTTP caller with either a named API key or the gateway
+/// credential. API keys carry their own scopes and per-minute rate limit;
+/// the gateway credential grants the default operator scopes.
+pub(crate) async fn authorize_gateway_http(
     state: &SharedState,
     headers: &HeaderMap,
-) -> Result<(), AuthFailureReason> {
+) -> Result<HttpPrincipal, HttpAuthRejection> {
+    if let Some(secret) = bearer_token(headers) {
+        let grant = apikeys::resolve_api_key(state, secret)
+            .await
+            .map_err(|error| HttpAuthRejection::Unavailable(error.message))?;
+        if let Some(grant) = grant {
+            let decision = state
+                .api_key_rate_limiter()
+                .record_with_limit(&grant.id, grant.rate_limit_per_minute)
+                .await;
+            if !decision.allowed {
+                return Err(HttpAuthRejection::RateLimited {
+                    key_name: grant.name,
+                    retry_after_ms: decision.retry_after_ms,
+                });
+            }
+            return Ok(HttpPrincipal {
+                scopes: grant.scopes,
+                api_key_id: Some(grant.id),
+            });
+        }
+    }
+
     let auth = auth_from_headers(headers);
     auth::authorize(&state.config().auth_mode, auth.as_ref())
+        .map_err(HttpAuthRejection::Unauthorized)?;
+    Ok(HttpPrincipal {
+        scopes: policy::default_operator_scopes(),
+        api_key_id: None,
+    })
 }
 
 pub(crate) fn normalize_segment(value: &str) -> String {
@@ -67,16 +158,20 @@ pub(crate) fn extract_text_content(content: &Value) -> String {
     read_part(content).unwrap_or_default()
 }
 
-fn auth_from_headers(headers: &HeaderMap) -> Option<ConnectAuth> {
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
     let raw = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
-    let token = raw
-        .strip_prefix("Bearer ")
+    raw.strip_prefix("Bearer ")
         .map(str::trim)
-        .filter(|value| !value.is_empty())?;
+        .filter(|value| !value.is_empty())
+}
+
+pub(crate) fn auth_from_headers(headers: &HeaderMap) -> Option<ConnectAuth> {
+    let token = bearer_token(headers)?;
 
     Some(ConnectAuth {
         token: Some(token.to_owned()),
         device_token: None,
+        refresh_token: None,
         // For HTTP, bearer auth is accepted in both token and password modes.
         password: Some(token.to_owned()),
     })
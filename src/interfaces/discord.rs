@@ -3,7 +3,7 @@ use serde_json::{Value, json};
 
 use crate::application::state::SharedState;
 
-use super::{channel_adapter_common as common, webhooks::WebhookFuture};
+use super::{channel_adapter_common as common, quiet_hours, webhooks::WebhookFuture};
 
 const DISCORD_EVENTS_PREFIX: &str = "runtime/discord/event/";
 
@@ -80,7 +80,7 @@ pub(crate) fn dispatch_webhook<'a>(
         };
 
         common::mark_event_processed(state, &dedupe_key, "discord", &message_id, &result).await;
-        let outbound_sent = common::maybe_dispatch_outbound_reply(
+        let outbound = common::maybe_dispatch_outbound_reply(
             state,
             state.config().discord_outbound_url.as_deref(),
             state.config().discord_outbound_token.as_deref(),
@@ -89,18 +89,20 @@ pub(crate) fn dispatch_webhook<'a>(
                 conversation_id: &outbound_conversation_id,
                 source_sender_id: outbound_sender_id.as_deref(),
                 source_message_id: Some(message_id.as_str()),
-                reply: result.reply.as_deref(),
+                replies: &result.reply_parts,
+                suppress_unfurl: result.suppress_unfurl,
                 session_key: &result.session_key,
                 run_id: result.run_id.as_deref(),
                 metadata: Some(json!({
                     "source": "discord",
                 })),
                 log_scope: "channels.discord.webhook",
+                urgent: quiet_hours::is_urgent(headers),
             },
         )
         .await;
 
-        common::accepted_true_with_outbound(&result, outbound_sent)
+        common::accepted_true_with_outbound(&result, outbound)
     })
 }
 
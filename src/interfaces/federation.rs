@@ -0,0 +1,220 @@
+use axum::{
+    Json,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    application::{
+        federation::{
+            self, Federation, NONCE_HEADER, PEER_HEADER, ROUTED_METHODS, SIGNATURE_HEADER,
+            SignedEnvelope, SignedHeaders, TIMESTAMP_HEADER,
+        },
+        state::SharedState,
+    },
+    protocol::RequestFrame,
+    rpc::dispatcher,
+    storage::now_unix_ms,
+};
+
+#[derive(Debug, Deserialize)]
+struct PeerRpcRequest {
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+    /// What is left of the caller's deadline, if it set one.
+    #[serde(default, rename = "timeoutMs")]
+    timeout_ms: Option<u64>,
+}
+
+/// `POST /federation/pair`: completes a pairing started with `federation.pair` on the peer.
+pub async fn pair_handler(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(federation) = state.config().federation.clone() else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            "UNAVAILABLE",
+            "federation is disabled",
+        );
+    };
+    let signed = match signed_headers(&headers) {
+        Ok(signed) => signed,
+        Err(error) => return error_response(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", error),
+    };
+    match federation::accept_pairing(&state, &federation, &signed, &body).await {
+        Ok(payload) => signed_response(&state, &federation, &signed, "pair", &payload).await,
+        Err(error) => error_response(StatusCode::FORBIDDEN, "FORBIDDEN", error),
+    }
+}
+
+/// `POST /federation/rpc`: runs a routed method on behalf of a paired peer.
+pub async fn rpc_handler(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(federation) = state.config().federation.clone() else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            "UNAVAILABLE",
+            "federation is disabled",
+        );
+    };
+    let (signed, peer) = match authenticate(&state, &federation, &headers, "rpc", &body).await {
+        Ok(authenticated) => authenticated,
+        Err(response) => return response,
+    };
+    let request = match serde_json::from_slice::<PeerRpcRequest>(&body) {
+        Ok(request) => request,
+        Err(error) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "INVALID_REQUEST",
+                format!("invalid federation rpc payload: {error}"),
+            );
+        }
+    };
+    if !ROUTED_METHODS
+        .iter()
+        .any(|(method, _)| *method == request.method)
+    {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            "FORBIDDEN",
+            format!("{} cannot be called by a federation peer", request.method),
+        );
+    }
+
+    let response = dispatcher::dispatch_request(
+        &state,
+        &federation::peer_session(&peer.id),
+        &RequestFrame {
+            frame_type: "req".to_owned(),
+            id: format!("federation-{}", uuid::Uuid::new_v4()),
+            method: request.method,
+            params: request.params,
+            timeout_ms: request.timeout_ms,
+        },
+    )
+    .await;
+    let payload = json!({
+        "ok": response.ok,
+        "payload": response.payload,
+        "error": response.error,
+    });
+    signed_response(&state, &federation, &signed, "rpc", &payload).await
+}
+
+/// `POST /federation/health`: answers a paired peer's health probe.
+pub async fn health_handler(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(federation) = state.config().federation.clone() else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            "UNAVAILABLE",
+            "federation is disabled",
+        );
+    };
+    let signed = match authenticate(&state, &federation, &headers, "health", &body).await {
+        Ok((signed, _)) => signed,
+        Err(response) => return response,
+    };
+    let payload = json!({
+        "ok": true,
+        "gatewayId": federation.gateway_id(),
+        "ts": now_unix_ms(),
+    });
+    signed_response(&state, &federation, &signed, "health", &payload).await
+}
+
+async fn authenticate(
+    state: &SharedState,
+    federation: &Federation,
+    headers: &HeaderMap,
+    route: &str,
+    body: &[u8],
+) -> Result<(SignedHeaders, federation::PeerRecord), Response> {
+    let signed = signed_headers(headers)
+        .map_err(|error| error_response(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", error))?;
+    let peer = federation::authenticate_peer(state, federation, &signed, route, body)
+        .await
+        .map_err(|error| error_response(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", error))?;
+    Ok((signed, peer))
+}
+
+fn signed_headers(headers: &HeaderMap) -> Result<SignedHeaders, String> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    federation::parse_signed_headers(
+        header(PEER_HEADER),
+        header(TIMESTAMP_HEADER),
+        header(NONCE_HEADER),
+        header(SIGNATURE_HEADER),
+    )
+}
+
+/// Answers `request` with `payload` signed for its sender over the request's nonce.
+async fn signed_response(
+    state: &SharedState,
+    federation: &Federation,
+    request: &SignedHeaders,
+    route: &str,
+    payload: &Value,
+) -> Response {
+    let identity = match federation::identity(state).await {
+        Ok(identity) => identity,
+        Err(error) => {
+            return error_response(StatusCode::SERVICE_UNAVAILABLE, "UNAVAILABLE", error);
+        }
+    };
+    let body = payload.to_string();
+    let timestamp_ms = now_unix_ms();
+    let signature = identity.sign(
+        &SignedEnvelope {
+            from: federation.gateway_id(),
+            to: &request.peer_id,
+            route,
+            timestamp_ms,
+            nonce: &request.nonce,
+        },
+        body.as_bytes(),
+    );
+
+    let mut response =
+        (StatusCode::OK, [("content-type", "application/json")], body).into_response();
+    let headers = response.headers_mut();
+    for (name, value) in [
+        (PEER_HEADER, federation.gateway_id().to_owned()),
+        (TIMESTAMP_HEADER, timestamp_ms.to_string()),
+        (NONCE_HEADER, request.nonce.clone()),
+        (SIGNATURE_HEADER, signature),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+    response
+}
+
+fn error_response(status: StatusCode, code: &str, message: impl Into<String>) -> Response {
+    (
+        status,
+        Json(json!({
+            "ok": false,
+            "error": {
+                "code": code,
+                "message": message.into(),
+            },
+        })),
+    )
+        .into_response()
+}
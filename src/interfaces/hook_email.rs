@@ -0,0 +1,511 @@
+use serde_json::{Map, Value, json};
+
+use crate::encoding::{decode_base64, percent_decode};
+
+/// Message template used when an email mapping does not configure its own.
+pub(crate) const DEFAULT_TEMPLATE: &str = "Email from {{from}}: {{subject}}\n\n{{text}}";
+pub(crate) const DEFAULT_NAME: &str = "Email";
+
+const MAX_MIME_DEPTH: usize = 8;
+
+/// An inbound email normalized from a provider parse webhook.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct InboundEmail {
+    pub from: Option<String>,
+    pub to: Vec<String>,
+    pub subject: Option<String>,
+    pub text: Option<String>,
+    pub html: Option<String>,
+    pub message_id: Option<String>,
+}
+
+/// Parses a SendGrid Inbound Parse (`multipart/form-data`), Mailgun-style routes
+/// (`application/x-www-form-urlencoded`), or SES (direct or SNS-wrapped JSON) request body.
+/// Plain JSON `{from, to, subject, text, html, messageId}` is accepted as well.
+pub(crate) fn parse_inbound_email(content_type: &str, body: &[u8]) -> Result<InboundEmail, String> {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let email = match essence.as_str() {
+        "multipart/form-data" => {
+            let boundary = header_param(content_type, "boundary")
+                .ok_or("multipart body is missing its boundary")?;
+            from_fields(&parse_form_data(&String::from_utf8_lossy(body), &boundary))
+        }
+        "application/x-www-form-urlencoded" => {
+            from_fields(&parse_urlencoded(&String::from_utf8_lossy(body)))
+        }
+        _ => {
+            let value = serde_json::from_slice::<Value>(body)
+                .map_err(|error| format!("invalid email JSON payload: {error}"))?;
+            from_json(&value)?
+        }
+    };
+
+    if email.to.is_empty() {
+        return Err("inbound email has no recipients".to_owned());
+    }
+    Ok(email)
+}
+
+/// Returns the lowercased plus-address tag of `agent+github@host`, if any.
+pub(crate) fn plus_tag(address: &str) -> Option<String> {
+    let (local, _) = address.rsplit_once('@')?;
+    let (_, tag) = local.split_once('+')?;
+    let tag = tag.trim().to_ascii_lowercase();
+    (!tag.is_empty()).then_some(tag)
+}
+
+/// Template payload for one recipient: `{{subject}}`, `{{text}}`, `{{tag}}`, and so on.
+pub(crate) fn template_payload(
+    email: &InboundEmail,
+    recipient: &str,
+    tag: Option<&str>,
+) -> Map<String, Value> {
+    let payload = json!({
+        "source": "email",
+        "from": email.from,
+        "to": email.to,
+        "recipient": recipient,
+        "tag": tag,
+        "subject": email.subject,
+        "text": email.text,
+        "html": email.html,
+        "messageId": email.message_id,
+    });
+    payload.as_object().cloned().unwrap_or_default()
+}
+
+fn from_fields(fields: &[(String, String)]) -> InboundEmail {
+    let field = |names: &[&str]| {
+        names.iter().find_map(|name| {
+            fields
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim().to_owned())
+                .filter(|value| !value.is_empty())
+        })
+    };
+
+    // SendGrid "raw" mode posts the full MIME message instead of parsed fields.
+    let mime = field(&["email"]).map(|raw| parse_mime(&raw));
+    let raw_headers = field(&["headers"]).map(|raw| parse_headers(&raw));
+
+    // The SMTP envelope holds the actual delivery recipients; `to` may only list the visible ones.
+    let envelope_to = field(&["envelope"])
+        .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
+        .map(|envelope| string_list(envelope.get("to")))
+        .filter(|to| !to.is_empty());
+    let to = envelope_to.unwrap_or_else(|| {
+        field(&["recipient", "to"])
+            .map(|list| split_addresses(&list))
+            .unwrap_or_default()
+    });
+
+    InboundEmail {
+        from: field(&["from", "sender"]),
+        to,
+        subject: field(&["subject"]).or_else(|| mime.as_ref()?.subject.clone()),
+        text: field(&["text", "body-plain"]).or_else(|| mime.as_ref()?.text.clone()),
+        html: field(&["html", "body-html"]).or_else(|| mime.as_ref()?.html.clone()),
+        message_id: field(&["message-id"])
+            .or_else(|| header_value(raw_headers.as_deref()?, "message-id"))
+            .or_else(|| mime.as_ref()?.message_id.clone()),
+    }
+}
+
+fn from_json(value: &Value) -> Result<InboundEmail, String> {
+    // SNS delivers SES notifications as a JSON string in `Message`.
+    if value.get("Type").and_then(Value::as_str) == Some("Notification")
+        && let Some(message) = value.get("Message").and_then(Value::as_str)
+    {
+        let inner = serde_json::from_str::<Value>(message)
+            .map_err(|error| format!("invalid SNS notification message: {error}"))?;
+        return from_json(&inner);
+    }
+    if value.get("Type").and_then(Value::as_str) == Some("SubscriptionConfirmation") {
+        return Err("SNS subscription confirmations must be confirmed out of band".to_owned());
+    }
+
+    if let Some(mail) = value.get("mail") {
+        let headers = mail.get("commonHeaders");
+        let mut to = string_list(value.pointer("/receipt/recipients"));
+        if to.is_empty() {
+            to = string_list(mail.get("destination"));
+        }
+        let content = value.get("content").and_then(Value::as_str).map(|content| {
+            let base64 = value
+                .pointer("/receipt/action/encoding")
+                .and_then(Value::as_str)
+                .is_some_and(|encoding| encoding.eq_ignore_ascii_case("BASE64"));
+            let raw = if base64 {
+                decode_base64(content)
+                    .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                    .unwrap_or_default()
+            } else {
+                content.to_owned()
+            };
+            parse_mime(&raw)
+        });
+        return Ok(InboundEmail {
+            from: string_list(headers.and_then(|headers| headers.get("from")))
+                .into_iter()
+                .next()
+                .or_else(|| string_at(mail.get("source"))),
+            to,
+            subject: string_at(headers.and_then(|headers| headers.get("subject")))
+                .or_else(|| content.as_ref()?.subject.clone()),
+            text: content.as_ref().and_then(|content| content.text.clone()),
+            html: content.as_ref().and_then(|content| content.html.clone()),
+            message_id: string_at(headers.and_then(|headers| headers.get("messageId")))
+                .or_else(|| string_at(mail.get("messageId"))),
+        });
+    }
+
+    Ok(InboundEmail {
+        from: string_at(value.get("from")),
+        to: match value.get("to") {
+            Some(Value::String(list)) => split_addresses(list),
+            other => string_list(other),
+        },
+        subject: string_at(value.get("subject")),
+        text: string_at(value.get("text")),
+        html: string_at(value.get("html")),
+        message_id: string_at(value.get("messageId")),
+    })
+}
+
+#[derive(Debug, Default)]
+struct MimeMessage {
+    subject: Option<String>,
+    message_id: Option<String>,
+    text: Option<String>,
+    html: Option<String>,
+}
+
+fn parse_mime(raw: &str) -> MimeMessage {
+    let (headers, _) = split_head(raw);
+    let headers = parse_headers(headers);
+    let mut message = MimeMessage {
+        subject: header_value(&headers, "subject"),
+        message_id: header_value(&headers, "message-id"),
+        ..MimeMessage::default()
+    };
+    collect_mime_bodies(raw, &mut message, 0);
+    message
+}
+
+/// Keeps the first inline `text/plain` and `text/html` bodies, descending into multiparts.
+fn collect_mime_bodies(raw: &str, message: &mut MimeMessage, depth: usize) {
+    let (head, body) = split_head(raw);
+    let headers = parse_headers(head);
+    let content_type =
+        header_value(&headers, "content-type").unwrap_or_else(|| "text/plain".to_owned());
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    if essence.starts_with("multipart/") {
+        if depth < MAX_MIME_DEPTH
+            && let Some(boundary) = header_param(&content_type, "boundary")
+        {
+            for part in split_multipart(body, &boundary) {
+                collect_mime_bodies(part, message, depth + 1);
+            }
+        }
+        return;
+    }
+    if header_value(&headers, "content-disposition")
+        .is_some_and(|disposition| disposition.to_ascii_lowercase().starts_with("attachment"))
+    {
+        return;
+    }
+
+    let slot = match essence.as_str() {
+        "text/plain" => &mut message.text,
+        "text/html" => &mut message.html,
+        _ => return,
+    };
+    if slot.is_none() {
+        let decoded = decode_transfer_encoding(
+            body,
+            header_value(&headers, "content-transfer-encoding").as_deref(),
+        );
+        let trimmed = decoded.trim();
+        if !trimmed.is_empty() {
+            *slot = Some(trimmed.to_owned());
+        }
+    }
+}
+
+fn decode_transfer_encoding(body: &str, encoding: Option<&str>) -> String {
+    match encoding.map(str::to_ascii_lowercase).as_deref() {
+        Some("base64") => decode_base64(body)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_default(),
+        Some("quoted-printable") => decode_quoted_printable(body),
+        _ => body.to_owned(),
+    }
+}
+
+fn decode_quoted_printable(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b'=' {
+            output.push(bytes[index]);
+            index += 1;
+            continue;
+        }
+        match (bytes.get(index + 1), bytes.get(index + 2)) {
+            (Some(b'\r'), Some(b'\n')) => index += 3,
+            (Some(b'\n'), _) => index += 2,
+            (Some(high), Some(low)) => match (hex_value(*high), hex_value(*low)) {
+                (Some(high), Some(low)) => {
+                    output.push(high << 4 | low);
+                    index += 3;
+                }
+                _ => {
+                    output.push(b'=');
+                    index += 1;
+                }
+            },
+            _ => {
+                output.push(b'=');
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+/// Splits a message into its header block and body at the first blank line.
+fn split_head(raw: &str) -> (&str, &str) {
+    if let Some(index) = raw.find("\r\n\r\n") {
+        return (&raw[..index], &raw[index + 4..]);
+    }
+    if let Some(index) = raw.find("\n\n") {
+        return (&raw[..index], &raw[index + 2..]);
+    }
+    (raw, "")
+}
+
+/// Parses an RFC 5322 header block, unfolding continuation lines; names are lowercased.
+fn parse_headers(block: &str) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in block.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+        }
+    }
+    headers
+}
+
+fn header_value(headers: &[(String, String)], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.clone())
+        .filter(|value| !value.is_empty())
+}
+
+/// Reads a `name=value` parameter from a structured header such as `Content-Type`.
+fn header_param(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_owned())
+            .filter(|value| !value.is_empty())
+    })
+}
+
+fn split_multipart<'a>(body: &'a str, boundary: &str) -> Vec<&'a str> {
+    let delimiter = format!("--{boundary}");
+    body.split(delimiter.as_str())
+        .skip(1)
+        .take_while(|part| !part.starts_with("--"))
+        .map(|part| {
+            part.strip_prefix("\r\n")
+                .or_else(|| part.strip_prefix('\n'))
+                .unwrap_or(part)
+        })
+        .map(|part| {
+            part.strip_suffix("\r\n")
+                .or_else(|| part.strip_suffix('\n'))
+                .unwrap_or(part)
+        })
+        .collect()
+}
+
+/// Reads the non-file fields of a `multipart/form-data` body.
+fn parse_form_data(body: &str, boundary: &str) -> Vec<(String, String)> {
+    split_multipart(body, boundary)
+        .into_iter()
+        .filter_map(|part| {
+            let (head, value) = split_head(part);
+            let disposition = header_value(&parse_headers(head), "content-disposition")?;
+            if header_param(&disposition, "filename").is_some() {
+                return None;
+            }
+            Some((header_param(&disposition, "name")?, value.to_owned()))
+        })
+        .collect()
+}
+
+fn parse_urlencoded(body: &str) -> Vec<(String, String)> {
+    body.split('&')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let key = percent_decode(key);
+            (!key.is_empty()).then(|| (key, percent_decode(value)))
+        })
+        .collect()
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Splits an address list on commas outside quoted display names and keeps the bare addresses.
+fn split_addresses(list: &str) -> Vec<String> {
+    let mut addresses = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for character in list.chars() {
+        match character {
+            '"' => {
+                quoted = !quoted;
+                current.push(character);
+            }
+            ',' if !quoted => {
+                addresses.extend(bare_address(&current));
+                current.clear();
+            }
+            _ => current.push(character),
+        }
+    }
+    addresses.extend(bare_address(&current));
+    addresses
+}
+
+/// `"Name" <user@host>` -> `user@host`, lowercased.
+fn bare_address(value: &str) -> Option<String> {
+    let value = value.trim();
+    let address = match (value.rfind('<'), value.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &value[start + 1..end],
+        _ => value,
+    };
+    let address = address.trim().to_ascii_lowercase();
+    address.contains('@').then_some(address)
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(Value::as_str)
+                .filter_map(bare_address)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn string_at(value: Option<&Value>) -> Option<String> {
+    value
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{parse_inbound_email, plus_tag};
+
+    #[test]
+    fn sendgrid_form_data_uses_envelope_recipients() {
+        let body = "--xyz\r\n\
+            Content-Disposition: form-data; name=\"to\"\r\n\r\n\
+            \"Bot, Agent\" <agent@example.com>\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"envelope\"\r\n\r\n\
+            {\"to\":[\"Agent+GitHub@example.com\"],\"from\":\"dev@example.com\"}\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"subject\"\r\n\r\n\
+            PR opened\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+            Please review #42\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"attachment1\"; filename=\"a.txt\"\r\n\r\n\
+            ignored\r\n\
+            --xyz--\r\n";
+
+        let email = parse_inbound_email("multipart/form-data; boundary=xyz", body.as_bytes())
+            .expect("form data should parse");
+        assert_eq!(email.to, ["agent+github@example.com"]);
+        assert_eq!(email.subject.as_deref(), Some("PR opened"));
+        assert_eq!(email.text.as_deref(), Some("Please review #42"));
+        assert_eq!(plus_tag(&email.to[0]).as_deref(), Some("github"));
+        assert_eq!(plus_tag("agent@example.com"), None);
+    }
+
+    #[test]
+    fn ses_notification_extracts_text_from_raw_mime() {
+        let mime = "From: dev@example.com\r\n\
+            Subject: Deploy\r\n\
+            Content-Type: multipart/alternative; boundary=\"b1\"\r\n\r\n\
+            --b1\r\n\
+            Content-Type: text/plain; charset=utf-8\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\r\n\
+            Ship it =E2=9C=85 to=\r\n prod\r\n\
+            --b1\r\n\
+            Content-Type: text/html\r\n\r\n\
+            <p>Ship it</p>\r\n\
+            --b1--\r\n";
+        let notification = json!({
+            "Type": "Notification",
+            "Message": json!({
+                "mail": {
+                    "source": "dev@example.com",
+                    "destination": ["agent+ops@example.com"],
+                    "commonHeaders": { "subject": "Deploy", "messageId": "<m1@example.com>" }
+                },
+                "content": mime
+            })
+            .to_string()
+        });
+
+        let email = parse_inbound_email("text/plain", notification.to_string().as_bytes())
+            .expect("SNS notification should parse");
+        assert_eq!(email.to, ["agent+ops@example.com"]);
+        assert_eq!(email.from.as_deref(), Some("dev@example.com"));
+        assert_eq!(email.text.as_deref(), Some("Ship it \u{2705} to prod"));
+        assert_eq!(email.html.as_deref(), Some("<p>Ship it</p>"));
+        assert_eq!(email.message_id.as_deref(), Some("<m1@example.com>"));
+    }
+}
@@ -0,0 +1,273 @@
+use serde_json::{Map, Value};
+
+use crate::{application::config::HookProvider, security::signatures::verify_hmac_sha256_hex};
+
+const STRIPE_SIGNATURE_HEADER: &str = "stripe-signature";
+const STRIPE_TOLERANCE_MS: u64 = 5 * 60 * 1_000;
+const GITHUB_SIGNATURE_HEADER: &str = "x-hub-signature-256";
+const GITHUB_EVENT_HEADER: &str = "x-github-event";
+const GITHUB_DELIVERY_HEADER: &str = "x-github-delivery";
+const LINEAR_SIGNATURE_HEADER: &str = "linear-signature";
+const LINEAR_DELIVERY_HEADER: &str = "linear-delivery";
+
+/// Verifies the provider's HMAC-SHA256 signature over the raw request body.
+/// `headers` are the lowercased request headers.
+pub(crate) fn verify_signature(
+    provider: HookProvider,
+    secret: &str,
+    headers: &Map<String, Value>,
+    body: &[u8],
+    now_ms: u64,
+) -> Result<(), String> {
+    let secret = secret.trim().as_bytes();
+    match provider {
+        HookProvider::Stripe => {
+            let header = header_str(headers, STRIPE_SIGNATURE_HEADER)
+                .ok_or("missing Stripe-Signature header")?;
+            let mut timestamp = None;
+            let mut signatures = Vec::new();
+            for part in header.split(',') {
+                match part.trim().split_once('=') {
+                    Some(("t", value)) => timestamp = Some(value.trim()),
+                    Some(("v1", value)) => signatures.push(value.trim()),
+                    _ => {}
+                }
+            }
+            let timestamp = timestamp.ok_or("Stripe-Signature header has no timestamp")?;
+            let timestamp_ms = timestamp
+                .parse::<u64>()
+                .map_err(|_| "Stripe-Signature timestamp is invalid")?
+                .saturating_mul(1_000);
+            if now_ms.abs_diff(timestamp_ms) > STRIPE_TOLERANCE_MS {
+                return Err("Stripe-Signature timestamp is outside the tolerance window".to_owned());
+            }
+            let mut signed = Vec::with_capacity(timestamp.len() + 1 + body.len());
+            signed.extend_from_slice(timestamp.as_bytes());
+            signed.push(b'.');
+            signed.extend_from_slice(body);
+            if signatures
+                .iter()
+                .any(|signature| verify_hmac_sha256_hex(secret, &signed, signature))
+            {
+                Ok(())
+            } else {
+                Err("Stripe signature mismatch".to_owned())
+            }
+        }
+        HookProvider::Github => {
+            let signature = header_str(headers, GITHUB_SIGNATURE_HEADER)
+                .and_then(|value| value.trim().strip_prefix("sha256="))
+                .ok_or("missing X-Hub-Signature-256 header")?;
+            if verify_hmac_sha256_hex(secret, body, signature) {
+                Ok(())
+            } else {
+                Err("GitHub signature mismatch".to_owned())
+            }
+        }
+        HookProvider::Linear => {
+            let signature = header_str(headers, LINEAR_SIGNATURE_HEADER)
+                .ok_or("missing Linear-Signature header")?;
+            if verify_hmac_sha256_hex(secret, body, signature) {
+                Ok(())
+            } else {
+                Err("Linear signature mismatch".to_owned())
+            }
+        }
+    }
+}
+
+/// Extracts `{provider, type, id, action, objectType, objectId}` from a provider payload so
+/// mappings can match on `match.type` and templates can use `{{event.*}}` uniformly.
+pub(crate) fn canonical_event(
+    provider: HookProvider,
+    headers: &Map<String, Value>,
+    payload: &Map<String, Value>,
+) -> Map<String, Value> {
+    let (event_type, id, action, object_type, object_id) = match provider {
+        HookProvider::Stripe => {
+            let object = payload.get("data").and_then(|data| data.get("object"));
+            (
+                string_at(payload.get("type")),
+                string_at(payload.get("id")),
+                None,
+                string_at(object.and_then(|object| object.get("object"))),
+                string_at(object.and_then(|object| object.get("id"))),
+            )
+        }
+        HookProvider::Github => {
+            let event = header_str(headers, GITHUB_EVENT_HEADER).map(str::to_owned);
+            let action = string_at(payload.get("action"));
+            let object = event.as_deref().and_then(|event| payload.get(event));
+            (
+                match (&event, &action) {
+                    (Some(event), Some(action)) => Some(format!("{event}.{action}")),
+                    (event, _) => event.clone(),
+                },
+                header_str(headers, GITHUB_DELIVERY_HEADER).map(str::to_owned),
+                action,
+                event.clone(),
+                string_at(
+                    object.and_then(|object| object.get("number").or_else(|| object.get("id"))),
+                ),
+            )
+        }
+        HookProvider::Linear => {
+            let object_type = string_at(payload.get("type"));
+            let action = string_at(payload.get("action"));
+            (
+                match (&object_type, &action) {
+                    (Some(kind), Some(action)) => Some(format!("{kind}.{action}")),
+                    (kind, _) => kind.clone(),
+                },
+                header_str(headers, LINEAR_DELIVERY_HEADER).map(str::to_owned),
+                action,
+                object_type,
+                string_at(payload.get("data").and_then(|data| data.get("id"))),
+            )
+        }
+    };
+
+    let mut event = Map::new();
+    event.insert(
+        "provider".to_owned(),
+        Value::String(provider.label().to_owned()),
+    );
+    for (key, value) in [
+        ("type", event_type),
+        ("id", id),
+        ("action", action),
+        ("objectType", object_type),
+        ("objectId", object_id),
+    ] {
+        event.insert(key.to_owned(), value.map_or(Value::Null, Value::String));
+    }
+    event
+}
+
+/// Message/text template used when a provider mapping does not configure its own.
+pub(crate) fn default_template(provider: HookProvider) -> &'static str {
+    match provider {
+        HookProvider::Stripe => {
+            "Stripe event {{event.type}} ({{event.id}}) for {{event.objectType}} {{event.objectId}}"
+        }
+        HookProvider::Github => {
+            "GitHub event {{event.type}} on {{payload.repository.full_name}} by {{payload.sender.login}}"
+        }
+        HookProvider::Linear => {
+            "Linear event {{event.type}} for {{event.objectType}} {{event.objectId}}: {{payload.data.title}}"
+        }
+    }
+}
+
+pub(crate) fn default_name(provider: HookProvider) -> &'static str {
+    match provider {
+        HookProvider::Stripe => "Stripe",
+        HookProvider::Github => "GitHub",
+        HookProvider::Linear => "Linear",
+    }
+}
+
+fn header_str<'a>(headers: &'a Map<String, Value>, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn string_at(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(text) if !text.trim().is_empty() => Some(text.trim().to_owned()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Map, Value, json};
+
+    use super::{canonical_event, verify_signature};
+    use crate::{
+        application::config::HookProvider,
+        security::signatures::{hex_encode, hmac_sha256},
+    };
+
+    fn headers(entries: &[(&str, String)]) -> Map<String, Value> {
+        entries
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), Value::String(value.clone())))
+            .collect()
+    }
+
+    #[test]
+    fn stripe_signature_checks_timestamp_and_v1_digest() {
+        let body = br#"{"id":"evt_1","type":"invoice.paid"}"#;
+        let signed = [b"1700000000.".as_slice(), body.as_slice()].concat();
+        let signature = hex_encode(&hmac_sha256(b"whsec_test", &signed));
+        let valid = headers(&[(
+            "stripe-signature",
+            format!("t=1700000000,v1=deadbeef,v1={signature}"),
+        )]);
+
+        assert!(
+            verify_signature(
+                HookProvider::Stripe,
+                "whsec_test",
+                &valid,
+                body,
+                1_700_000_010_000
+            )
+            .is_ok()
+        );
+        assert!(
+            verify_signature(
+                HookProvider::Stripe,
+                "whsec_test",
+                &valid,
+                body,
+                1_700_001_000_000
+            )
+            .is_err()
+        );
+        assert!(
+            verify_signature(
+                HookProvider::Stripe,
+                "other",
+                &valid,
+                body,
+                1_700_000_010_000
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn canonical_event_normalizes_github_and_linear_payloads() {
+        let github = canonical_event(
+            HookProvider::Github,
+            &headers(&[
+                ("x-github-event", "pull_request".to_owned()),
+                ("x-github-delivery", "d-1".to_owned()),
+            ]),
+            json!({ "action": "opened", "pull_request": { "number": 42 } })
+                .as_object()
+                .expect("payload should be an object"),
+        );
+        assert_eq!(github["type"], "pull_request.opened");
+        assert_eq!(github["id"], "d-1");
+        assert_eq!(github["objectId"], "42");
+
+        let linear = canonical_event(
+            HookProvider::Linear,
+            &Map::new(),
+            json!({ "type": "Issue", "action": "create", "data": { "id": "iss-1" } })
+                .as_object()
+                .expect("payload should be an object"),
+        );
+        assert_eq!(linear["type"], "Issue.create");
+        assert_eq!(linear["objectType"], "Issue");
+        assert_eq!(linear["objectId"], "iss-1");
+        assert_eq!(linear["id"], Value::Null);
+    }
+}
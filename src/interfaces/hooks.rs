@@ -18,9 +18,15 @@ use tokio::time::timeout;
 
 use crate::{
     application::{
-        config::{HookMappingAction, HookMappingConfig, HookMappingTransformConfig, RuntimeConfig},
+        config::{
+            HookMappingAction, HookMappingConfig, HookMappingTransformConfig, HookMatchPredicate,
+            RuntimeConfig,
+        },
         state::SharedState,
     },
+    domain::session_key::canonicalize_session_key,
+    encoding::decode_base64,
+    interfaces::{hook_email, hook_providers},
     protocol::ERROR_INVALID_REQUEST,
     rpc::{
         SessionContext,
@@ -35,11 +41,16 @@ const HOOKS_LAST_WAKE_KEY: &str = "hooks/last-wake";
 const HOOKS_PENDING_WAKE_PREFIX: &str = "hooks/pending-wake/";
 const HOOKS_AUTH_SCOPE_PREFIX: &str = "hooks-auth:";
 const HOOKS_TOKEN_HEADER: &str = "x-openclaw-token";
+const HOOKS_EMAIL_SUBPATH: &str = "email";
 const HOOKS_SESSION_POLICY_ERROR: &str = "sessionKey is disabled for external /hooks/agent payloads; set hooksAllowRequestSessionKey=true to enable";
 const HOOKS_QUERY_TOKEN_ERROR: &str = "Hook token must be provided via Authorization: Bearer <token> or X-OpenClaw-Token header (query parameters are not allowed).";
 const HOOKS_TRANSFORM_CONTEXT_ENV: &str = "RECLAW_HOOK_CONTEXT_JSON";
 const HOOKS_TRANSFORM_EXPORT_ENV: &str = "RECLAW_HOOK_TRANSFORM_EXPORT";
 const HOOKS_TRANSFORM_TIMEOUT: Duration = Duration::from_secs(5);
+const CLOUDEVENTS_SPEC_VERSION: &str = "1.0";
+const CLOUDEVENTS_STRUCTURED_CONTENT_TYPE: &str = "application/cloudevents+json";
+const CLOUDEVENTS_BATCH_CONTENT_TYPE: &str = "application/cloudevents-batch+json";
+const CLOUDEVENTS_HEADER_PREFIX: &str = "ce-";
 const HOOKS_JS_TRANSFORM_RUNNER: &str = r#"
 import { pathToFileURL } from 'node:url';
 
@@ -125,6 +136,10 @@ struct HookTemplateContext<'a> {
     path: &'a str,
     query: &'a Map<String, Value>,
     url: &'a str,
+    /// Canonical provider event (`{{event.*}}`), present for `provider` mappings only.
+    event: Option<&'a Map<String, Value>>,
+    /// Dispatch result body, available to mapping `responseTemplate` rendering only.
+    response: Option<&'a Map<String, Value>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -148,10 +163,18 @@ struct HookTransformOverride {
     session_key: Option<String>,
 }
 
+/// A rendered `scheduleCron` mapping: `job` holds `cron.add` params (`id`, `name`, `schedule`,
+/// `payload`, `metadata`), with only `id` set when an existing job is run now.
+#[derive(Debug)]
+struct HookCronNormalized {
+    job: Map<String, Value>,
+}
+
 #[derive(Debug)]
 enum HookResolvedAction {
     Wake(HookWakeNormalized),
     Agent(HookAgentNormalized),
+    ScheduleCron(HookCronNormalized),
 }
 
 pub async fn root_handler(
@@ -195,7 +218,16 @@ async fn handle_request(
         );
     }
 
-    if let Err(response) = authorize_request(&state, &request_headers, remote_addr).await {
+    let normalized_subpath = subpath.trim_matches('/');
+    if state.config().hooks_email_enabled && normalized_subpath == HOOKS_EMAIL_SUBPATH {
+        return handle_email(state, remote_addr, request).await;
+    }
+
+    // Provider mappings authenticate with their own webhook signature instead of the hooks token.
+    let provider_path = has_provider_mapping(&state, normalized_subpath);
+    if !provider_path
+        && let Err(response) = authorize_request(&state, &request_headers, remote_addr, false).await
+    {
         return response;
     }
 
@@ -210,7 +242,16 @@ async fn handle_request(
         }
     };
 
-    let parsed = if body.is_empty() {
+    let cloud_event = match parse_cloud_event(&request_headers, &body) {
+        Ok(value) => value,
+        Err(error) => {
+            return error_response(StatusCode::BAD_REQUEST, "INVALID_REQUEST", error);
+        }
+    };
+
+    let parsed = if let Some(event) = cloud_event {
+        Value::Object(event)
+    } else if body.is_empty() {
         Value::Object(Map::new())
     } else {
         match serde_json::from_slice::<Value>(&body) {
@@ -225,20 +266,12 @@ async fn handle_request(
         }
     };
     let payload = parsed.as_object().cloned().unwrap_or_default();
-    let normalized_subpath = subpath.trim_matches('/');
     if normalized_subpath.is_empty() {
         return error_response(StatusCode::NOT_FOUND, "NOT_FOUND", "not found");
     }
     let normalized_headers = normalize_hook_headers(&request_headers);
     let query_values = parse_query_values(&request_uri);
     let request_url = request_uri.to_string();
-    let template_context = HookTemplateContext {
-        payload: &payload,
-        headers: &normalized_headers,
-        path: normalized_subpath,
-        query: &query_values,
-        url: &request_url,
-    };
 
     match normalized_subpath {
         "wake" => {
@@ -260,18 +293,227 @@ async fn handle_request(
             dispatch_agent(state, normalized, HookSessionKeySource::Request).await
         }
         _ => {
-            let Some(mapped) = resolve_mapping(&state, normalized_subpath, &payload) else {
+            let resolved = resolve_mappings(
+                &state,
+                normalized_subpath,
+                &payload,
+                &normalized_headers,
+                &query_values,
+            );
+            if resolved.matched.is_empty() {
+                return error_response(StatusCode::NOT_FOUND, "NOT_FOUND", "not found");
+            }
+            let mut skipped = resolved.skipped;
+            let mut dispatched = Vec::new();
+            let mut primary: Option<((StatusCode, Json<Value>), bool)> = None;
+            let mut matched = resolved.matched.into_iter();
+            for (index, mapped, event) in matched.by_ref() {
+                let label = mapping_label(&mapped, index);
+                let authorized = match (mapped.provider, mapped.secret.as_deref()) {
+                    (Some(provider), Some(secret)) => {
+                        match hook_providers::verify_signature(
+                            provider,
+                            secret,
+                            &normalized_headers,
+                            &body,
+                            now_unix_ms(),
+                        ) {
+                            Ok(()) => Ok(()),
+                            Err(error) => {
+                                tracing::debug!(
+                                    "hook signature rejected path={normalized_subpath} mapping={label}: {error}"
+                                );
+                                Err(record_auth_failure(&state, remote_addr).await)
+                            }
+                        }
+                    }
+                    _ if provider_path => {
+                        authorize_request(&state, &request_headers, remote_addr, false).await
+                    }
+                    _ => Ok(()),
+                };
+                if let Err(response) = authorized {
+                    // Only the first mapping decides the response; later ones are just skipped.
+                    if primary.is_none() {
+                        return response;
+                    }
+                    skipped.push(json!({
+                        "id": label,
+                        "priority": mapped.priority,
+                        "reason": "unauthorized",
+                    }));
+                    continue;
+                }
+
+                let continues = mapped.r#continue;
+                let templated = mapped.response_template.is_some();
+                let priority = mapped.priority;
+                let template_context = HookTemplateContext {
+                    payload: &payload,
+                    headers: &normalized_headers,
+                    path: normalized_subpath,
+                    query: &query_values,
+                    url: &request_url,
+                    event: event.as_ref(),
+                    response: None,
+                };
+                let response = dispatch_mapping(state.clone(), mapped, &template_context).await;
+                dispatched.push(json!({
+                    "id": label,
+                    "priority": priority,
+                    "status": response.0.as_u16(),
+                    "ok": response.1.get("ok").and_then(Value::as_bool) == Some(true),
+                }));
+                if primary.is_none() {
+                    primary = Some((response, templated));
+                }
+                if !continues {
+                    break;
+                }
+            }
+            for (index, mapped, _) in matched {
+                skipped.push(json!({
+                    "id": mapping_label(&mapped, index),
+                    "priority": mapped.priority,
+                    "reason": "shadowed",
+                }));
+            }
+
+            let Some(((status, Json(mut body)), templated)) = primary else {
                 return error_response(StatusCode::NOT_FOUND, "NOT_FOUND", "not found");
             };
-            dispatch_mapping(state, mapped, &template_context).await
+            // Templated bodies are provider acknowledgements and are answered verbatim.
+            if !templated && let Some(object) = body.as_object_mut() {
+                object.insert(
+                    "mappings".to_owned(),
+                    json!({
+                        "dispatched": dispatched,
+                        "skipped": skipped,
+                    }),
+                );
+            }
+            (status, Json(body))
         }
     }
 }
 
+/// Inbound email parse endpoint. Each recipient's plus-address tag (`agent+<tag>@host`) selects
+/// the mapping at `email/<tag>`; when no tagged mapping matches, the mapping at `email` is used.
+async fn handle_email(
+    state: SharedState,
+    remote_addr: SocketAddr,
+    request: Request,
+) -> (StatusCode, Json<Value>) {
+    let request_uri = request.uri().clone();
+    let request_headers = request.headers().clone();
+    // Email providers can only carry credentials in the webhook URL, i.e. as Basic auth.
+    if let Err(response) = authorize_request(&state, &request_headers, remote_addr, true).await {
+        return response;
+    }
+
+    let body = match to_bytes(request.into_body(), state.config().hooks_max_body_bytes).await {
+        Ok(body) => body,
+        Err(_) => {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "PAYLOAD_TOO_LARGE",
+                "payload too large",
+            );
+        }
+    };
+    let content_type = request_headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let email = match hook_email::parse_inbound_email(content_type, &body) {
+        Ok(email) => email,
+        Err(error) => return error_response(StatusCode::BAD_REQUEST, "INVALID_REQUEST", error),
+    };
+
+    let normalized_headers = normalize_hook_headers(&request_headers);
+    let query_values = parse_query_values(&request_uri);
+    let recipients = email
+        .to
+        .iter()
+        .map(|recipient| {
+            let tag = hook_email::plus_tag(recipient);
+            let payload = hook_email::template_payload(&email, recipient, tag.as_deref());
+            (tag, payload)
+        })
+        .collect::<Vec<_>>();
+    let tagged = recipients.iter().find_map(|(tag, payload)| {
+        let path = format!("{HOOKS_EMAIL_SUBPATH}/{}", tag.as_deref()?);
+        let mapping =
+            find_email_mapping(&state, &path, payload, &normalized_headers, &query_values)?;
+        Some((path, mapping, payload))
+    });
+    let resolved = tagged.or_else(|| {
+        let (_, payload) = recipients.first()?;
+        let mapping = find_email_mapping(
+            &state,
+            HOOKS_EMAIL_SUBPATH,
+            payload,
+            &normalized_headers,
+            &query_values,
+        )?;
+        Some((HOOKS_EMAIL_SUBPATH.to_owned(), mapping, payload))
+    });
+    // Answer 200 for unrouted mail so the provider does not keep retrying it.
+    let Some((path, mapping, payload)) = resolved else {
+        return (
+            StatusCode::OK,
+            Json(json!({
+                "ok": true,
+                "skipped": true,
+            })),
+        );
+    };
+
+    let request_url = request_uri.to_string();
+    let template_context = HookTemplateContext {
+        payload,
+        headers: &normalized_headers,
+        path: &path,
+        query: &query_values,
+        url: &request_url,
+        event: None,
+        response: None,
+    };
+    dispatch_mapping(state, with_email_defaults(mapping), &template_context).await
+}
+
+fn find_email_mapping(
+    state: &SharedState,
+    path: &str,
+    payload: &Map<String, Value>,
+    headers: &Map<String, Value>,
+    query: &Map<String, Value>,
+) -> Option<HookMappingConfig> {
+    let target = normalize_mapping_path(path);
+    mappings_by_priority(&state.config().hooks_mappings)
+        .into_iter()
+        .find(|(_, mapping)| mapping_matches(mapping, &target, payload, headers, query))
+        .map(|(_, mapping)| mapping.clone())
+}
+
+fn with_email_defaults(mut mapping: HookMappingConfig) -> HookMappingConfig {
+    if mapping.message.is_none() && mapping.message_template.is_none() {
+        mapping.message_template = Some(hook_email::DEFAULT_TEMPLATE.to_owned());
+    }
+    if mapping.text.is_none() && mapping.text_template.is_none() {
+        mapping.text_template = Some(hook_email::DEFAULT_TEMPLATE.to_owned());
+    }
+    if mapping.name.is_none() {
+        mapping.name = Some(hook_email::DEFAULT_NAME.to_owned());
+    }
+    mapping
+}
+
 async fn authorize_request(
     state: &SharedState,
     headers: &HeaderMap,
     remote_addr: SocketAddr,
+    allow_basic_auth: bool,
 ) -> Result<(), (StatusCode, Json<Value>)> {
     let Some(expected_token) = state.config().hooks_token.as_deref() else {
         return Err(error_response(
@@ -284,23 +526,15 @@ async fn authorize_request(
     let provided_token = extract_hook_token(headers);
     let rate_limit_key = format!("{HOOKS_AUTH_SCOPE_PREFIX}{}", remote_addr.ip());
 
-    if !token_matches(provided_token, expected_token) {
-        let decision = state
-            .control_plane_rate_limiter()
-            .record_failure(&rate_limit_key)
-            .await;
-        if !decision.allowed {
-            return Err(error_response(
-                StatusCode::TOO_MANY_REQUESTS,
-                "RATE_LIMITED",
-                "too many failed authentication attempts",
-            ));
-        }
-        return Err(error_response(
-            StatusCode::UNAUTHORIZED,
-            "UNAUTHORIZED",
-            "unauthorized",
-        ));
+    let basic_matches = || {
+        allow_basic_auth
+            && token_matches(
+                extract_basic_auth_password(headers).as_deref(),
+                expected_token,
+            )
+    };
+    if !token_matches(provided_token, expected_token) && !basic_matches() {
+        return Err(record_auth_failure(state, remote_addr).await);
     }
 
     state
@@ -310,6 +544,25 @@ async fn authorize_request(
     Ok(())
 }
 
+async fn record_auth_failure(
+    state: &SharedState,
+    remote_addr: SocketAddr,
+) -> (StatusCode, Json<Value>) {
+    let rate_limit_key = format!("{HOOKS_AUTH_SCOPE_PREFIX}{}", remote_addr.ip());
+    let decision = state
+        .control_plane_rate_limiter()
+        .record_failure(&rate_limit_key)
+        .await;
+    if !decision.allowed {
+        return error_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            "RATE_LIMITED",
+            "too many failed authentication attempts",
+        );
+    }
+    error_response(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", "unauthorized")
+}
+
 async fn dispatch_wake(
     state: SharedState,
     normalized: HookWakeNormalized,
@@ -369,6 +622,15 @@ async fn dispatch_agent(
         .agent_id
         .unwrap_or_else(|| state.config().hooks_default_agent_id.clone());
 
+    // Held until the run finishes so a burst of hooks cannot run unbounded agent dispatches.
+    let Some(_slot) = state.acquire_hook_dispatch_slot().await else {
+        return error_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            "RATE_LIMITED",
+            "hook dispatch queue is full",
+        );
+    };
+
     if normalized.wake_mode == HookWakeMode::Now {
         let wake_params = json!({
             "reason": "hook:agent",
@@ -436,12 +698,100 @@ async fn dispatch_mapping(
         Err(error) => return error_response(StatusCode::SERVICE_UNAVAILABLE, "UNAVAILABLE", error),
     };
 
-    match resolved {
+    let dispatched = match resolved {
         HookResolvedAction::Wake(wake) => dispatch_wake(state, wake).await,
         HookResolvedAction::Agent(agent) => {
             dispatch_agent(state, agent, HookSessionKeySource::Mapping).await
         }
+        HookResolvedAction::ScheduleCron(cron) => dispatch_cron(state, cron).await,
+    };
+    customize_mapping_response(&mapping, context, dispatched)
+}
+
+/// Creates the mapped cron job, reschedules it when `jobId` names an existing job, or runs that
+/// job now when the mapping has no schedule.
+async fn dispatch_cron(state: SharedState, cron: HookCronNormalized) -> (StatusCode, Json<Value>) {
+    let job_id = cron
+        .job
+        .get("id")
+        .and_then(Value::as_str)
+        .map(str::to_owned);
+    if !cron.job.contains_key("schedule") {
+        let job_id = job_id.unwrap_or_default();
+        return match methods::cron::handle_run(&state, Some(&json!({ "id": job_id }))).await {
+            Ok(run) => (
+                StatusCode::OK,
+                Json(json!({
+                    "ok": true,
+                    "jobId": job_id,
+                    "runId": run.get("id"),
+                    "status": run.get("status"),
+                })),
+            ),
+            Err(error) => map_error_shape(error),
+        };
+    }
+
+    let existing = match job_id.as_deref() {
+        Some(job_id) => match state.get_cron_job(job_id).await {
+            Ok(job) => job.is_some(),
+            Err(error) => return map_error_shape(map_domain_error(error)),
+        },
+        None => false,
+    };
+    let result = if existing {
+        let mut patch = cron.job.clone();
+        patch.remove("id");
+        let params = json!({ "id": job_id, "patch": patch });
+        methods::cron::handle_update(&state, Some(&params)).await
+    } else {
+        methods::cron::handle_add(&state, Some(&Value::Object(cron.job))).await
+    };
+    match result {
+        Ok(job) => (
+            StatusCode::OK,
+            Json(json!({
+                "ok": true,
+                "jobId": job.get("id"),
+                "nextRunMs": job.get("nextRunMs"),
+                "rescheduled": existing,
+            })),
+        ),
+        Err(error) => map_error_shape(error),
+    }
+}
+
+/// Applies the mapping `responseStatus`/`responseTemplate` to a successful dispatch so
+/// providers that expect a specific acknowledgement body can be answered directly.
+fn customize_mapping_response(
+    mapping: &HookMappingConfig,
+    context: &HookTemplateContext<'_>,
+    (status, Json(body)): (StatusCode, Json<Value>),
+) -> (StatusCode, Json<Value>) {
+    if body.get("ok").and_then(Value::as_bool) != Some(true) {
+        return (status, Json(body));
     }
+
+    let status = mapping
+        .response_status
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .unwrap_or(status);
+    let Some(template) = mapping.response_template.as_ref() else {
+        return (status, Json(body));
+    };
+    let response_context = HookTemplateContext {
+        payload: context.payload,
+        headers: context.headers,
+        path: context.path,
+        query: context.query,
+        url: context.url,
+        event: context.event,
+        response: body.as_object(),
+    };
+    (
+        status,
+        Json(render_template_value(template, &response_context)),
+    )
 }
 
 fn build_mapping_action(
@@ -451,6 +801,7 @@ fn build_mapping_action(
     match mapping.action {
         HookMappingAction::Wake => {
             let text = trim_non_empty(resolve_mapped_text(mapping, context))
+                .or_else(|| provider_default_text(mapping, context))
                 .ok_or_else(|| "hook mapping requires text".to_owned())?;
             Ok(HookResolvedAction::Wake(HookWakeNormalized {
                 text,
@@ -459,52 +810,376 @@ fn build_mapping_action(
         }
         HookMappingAction::Agent => {
             let message = trim_non_empty(resolve_mapped_message(mapping, context))
+                .or_else(|| provider_default_text(mapping, context))
                 .ok_or_else(|| "hook mapping requires message".to_owned())?;
             Ok(HookResolvedAction::Agent(HookAgentNormalized {
                 message,
-                name: trim_non_empty(mapping.name.clone()).unwrap_or_else(|| "Hook".to_owned()),
+                name: trim_non_empty(mapping.name.clone()).unwrap_or_else(|| {
+                    mapping
+                        .provider
+                        .map_or("Hook", hook_providers::default_name)
+                        .to_owned()
+                }),
                 agent_id: trim_non_empty(mapping.agent_id.clone()),
                 wake_mode: HookWakeMode::from_raw(mapping.wake_mode.as_deref()),
                 session_key: trim_non_empty(mapping.session_key.clone()),
             }))
         }
+        HookMappingAction::ScheduleCron => build_cron_action(mapping, context),
     }
 }
 
-fn resolve_mapping(
+fn build_cron_action(
+    mapping: &HookMappingConfig,
+    context: &HookTemplateContext<'_>,
+) -> Result<HookResolvedAction, String> {
+    let cron = mapping
+        .cron
+        .as_ref()
+        .ok_or_else(|| "hook mapping requires cron".to_owned())?;
+    let mut job = Map::new();
+    if let Some(job_id) = cron
+        .job_id
+        .as_deref()
+        .and_then(|template| trim_non_empty(Some(render_template(template, context))))
+    {
+        job.insert("id".to_owned(), Value::String(job_id));
+    }
+
+    let schedule = match (&cron.schedule, cron.delay_ms) {
+        (Some(schedule), _) => Some(render_template_value(schedule, context)),
+        (None, Some(delay_ms)) => {
+            let at = i64::try_from(now_unix_ms().saturating_add(delay_ms))
+                .ok()
+                .and_then(chrono::DateTime::from_timestamp_millis)
+                .ok_or_else(|| "hook mapping cron.delayMs is out of range".to_owned())?;
+            Some(json!({
+                "kind": "at",
+                "at": at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            }))
+        }
+        (None, None) => None,
+    };
+    let Some(schedule) = schedule else {
+        if !job.contains_key("id") {
+            return Err("hook mapping requires cron.jobId".to_owned());
+        }
+        return Ok(HookResolvedAction::ScheduleCron(HookCronNormalized { job }));
+    };
+
+    let payload = match &cron.payload {
+        Some(payload) => render_template_value(payload, context),
+        None => {
+            if let Some(message) = trim_non_empty(resolve_mapped_message(mapping, context)) {
+                json!({ "kind": "agentTurn", "message": message })
+            } else if let Some(text) = trim_non_empty(resolve_mapped_text(mapping, context)) {
+                json!({ "kind": "systemEvent", "text": text })
+            } else {
+                return Err("hook mapping requires cron.payload, message, or text".to_owned());
+            }
+        }
+    };
+    if let Some(name) = cron
+        .name
+        .as_deref()
+        .and_then(|template| trim_non_empty(Some(render_template(template, context))))
+    {
+        job.insert("name".to_owned(), Value::String(name));
+    }
+    job.insert("schedule".to_owned(), schedule);
+    job.insert("payload".to_owned(), payload);
+    job.insert(
+        "metadata".to_owned(),
+        json!({
+            "source": "hook",
+            "hookPath": context.path,
+            "mappingId": mapping.id,
+        }),
+    );
+    Ok(HookResolvedAction::ScheduleCron(HookCronNormalized { job }))
+}
+
+fn provider_default_text(
+    mapping: &HookMappingConfig,
+    context: &HookTemplateContext<'_>,
+) -> Option<String> {
+    let provider = mapping.provider?;
+    trim_non_empty(Some(render_template(
+        hook_providers::default_template(provider),
+        context,
+    )))
+}
+
+/// A matching mapping with its config index and provider event.
+type MatchedMapping = (usize, HookMappingConfig, Option<Map<String, Value>>);
+
+/// Mappings for `subpath` that matched, in dispatch order, plus a `{ id, priority, reason }` entry
+/// for each one on the path that did not match.
+struct ResolvedMappings {
+    matched: Vec<MatchedMapping>,
+    skipped: Vec<Value>,
+}
+
+/// Resolves the mappings for `subpath` by descending `priority`, ties in config order. Provider
+/// mappings match `match.type`/`match.subject` against the canonical provider event.
+fn resolve_mappings(
     state: &SharedState,
     subpath: &str,
     payload: &Map<String, Value>,
-) -> Option<HookMappingConfig> {
+    headers: &Map<String, Value>,
+    query: &Map<String, Value>,
+) -> ResolvedMappings {
     let target = normalize_mapping_path(subpath);
-    state
-        .config()
-        .hooks_mappings
-        .iter()
-        .find(|mapping| mapping_matches(mapping, &target, payload))
-        .cloned()
+    let config = state.config();
+    let mut resolved = ResolvedMappings {
+        matched: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for (index, mapping) in mappings_by_priority(&config.hooks_mappings) {
+        let event = mapping
+            .provider
+            .map(|provider| hook_providers::canonical_event(provider, headers, payload));
+        match mapping_mismatch(
+            mapping,
+            &target,
+            event.as_ref().unwrap_or(payload),
+            headers,
+            query,
+        ) {
+            None => resolved.matched.push((index, mapping.clone(), event)),
+            Some("path") => {}
+            Some(reason) => resolved.skipped.push(json!({
+                "id": mapping_label(mapping, index),
+                "priority": mapping.priority,
+                "reason": reason,
+            })),
+        }
+    }
+    resolved
+}
+
+/// Configured mappings with their index, by descending `priority`; the sort is stable so equal
+/// priorities keep config order.
+fn mappings_by_priority(mappings: &[HookMappingConfig]) -> Vec<(usize, &HookMappingConfig)> {
+    let mut ordered = mappings.iter().enumerate().collect::<Vec<_>>();
+    ordered.sort_by_key(|(_, mapping)| std::cmp::Reverse(mapping.priority));
+    ordered
+}
+
+/// The mapping `id`, or `#<index>` for mappings without one.
+fn mapping_label(mapping: &HookMappingConfig, index: usize) -> String {
+    mapping
+        .id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map_or_else(|| format!("#{index}"), str::to_owned)
+}
+
+fn has_provider_mapping(state: &SharedState, subpath: &str) -> bool {
+    if matches!(subpath, "wake" | "agent") {
+        return false;
+    }
+    let target = normalize_mapping_path(subpath);
+    state.config().hooks_mappings.iter().any(|mapping| {
+        mapping.provider.is_some()
+            && mapping_path_value(mapping)
+                .is_some_and(|path| normalize_mapping_path(&path) == target)
+    })
 }
 
+/// `headers` and `query` are the normalized request maps; payload predicates see the same
+/// payload as `match.type` (the canonical event for provider mappings).
 fn mapping_matches(
     mapping: &HookMappingConfig,
     target_path: &str,
     payload: &Map<String, Value>,
+    headers: &Map<String, Value>,
+    query: &Map<String, Value>,
 ) -> bool {
+    mapping_mismatch(mapping, target_path, payload, headers, query).is_none()
+}
+
+/// The first rule of `mapping` the request fails (`path`, `match.type`, `match.subject`,
+/// `match.headers`, `match.query`, `match.payload`, `source`), or `None` when it matches.
+fn mapping_mismatch(
+    mapping: &HookMappingConfig,
+    target_path: &str,
+    payload: &Map<String, Value>,
+    headers: &Map<String, Value>,
+    query: &Map<String, Value>,
+) -> Option<&'static str> {
     let Some(mapping_path) = mapping_path_value(mapping) else {
-        return false;
+        return Some("path");
     };
     if normalize_mapping_path(&mapping_path) != target_path {
-        return false;
+        return Some("path");
     }
 
-    let Some(match_source) = mapping_match_source_value(mapping) else {
-        return true;
-    };
-    payload
+    if let Some(rule) = mapping.r#match.as_ref() {
+        if !attribute_matches(payload, "type", rule.r#type.as_deref()) {
+            return Some("match.type");
+        }
+        if !attribute_matches(payload, "subject", rule.subject.as_deref()) {
+            return Some("match.subject");
+        }
+        if !rule.headers.iter().flatten().all(|(name, predicate)| {
+            predicate_matches(predicate, headers.get(&name.to_ascii_lowercase()))
+        }) {
+            return Some("match.headers");
+        }
+        if !rule
+            .query
+            .iter()
+            .flatten()
+            .all(|(name, predicate)| predicate_matches(predicate, query.get(name)))
+        {
+            return Some("match.query");
+        }
+        if !rule
+            .payload
+            .iter()
+            .flatten()
+            .all(|(path, predicate)| predicate_matches(predicate, lookup_path(payload, path)))
+        {
+            return Some("match.payload");
+        }
+    }
+
+    let match_source = mapping_match_source_value(mapping)?;
+    let source_ok = payload
         .get("source")
         .and_then(Value::as_str)
         .map(str::trim)
-        .is_some_and(|source| source == match_source)
+        .is_some_and(|source| source == match_source);
+    (!source_ok).then_some("source")
+}
+
+/// Matches a top-level string attribute exactly, or by prefix when the pattern ends with `*`.
+fn attribute_matches(payload: &Map<String, Value>, key: &str, pattern: Option<&str>) -> bool {
+    let Some(pattern) = pattern.map(str::trim).filter(|value| !value.is_empty()) else {
+        return true;
+    };
+    let Some(value) = payload.get(key).and_then(Value::as_str).map(str::trim) else {
+        return false;
+    };
+
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => value == pattern,
+    }
+}
+
+/// Strings compare as-is; numbers and booleans by their JSON text, other values as compact JSON.
+fn predicate_matches(predicate: &HookMatchPredicate, value: Option<&Value>) -> bool {
+    let text = value.map(|value| match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    });
+    match predicate {
+        HookMatchPredicate::Equals(expected) => text.as_deref() == Some(expected.as_str()),
+        HookMatchPredicate::Operator {
+            equals,
+            regex,
+            exists,
+        } => {
+            if exists.is_some_and(|exists| exists != text.is_some()) {
+                return false;
+            }
+            if let Some(expected) = equals
+                && text.as_deref() != Some(expected.as_str())
+            {
+                return false;
+            }
+            match regex {
+                Some(pattern) => text.is_some_and(|text| {
+                    regex_automata::meta::Regex::new(pattern)
+                        .is_ok_and(|regex| regex.is_match(text.as_str()))
+                }),
+                None => true,
+            }
+        }
+    }
+}
+
+/// Normalizes a CloudEvents 1.0 HTTP request (structured or binary mode) into an event envelope
+/// `{specversion, id, type, source, subject?, time?, datacontenttype?, data, ...extensions}`.
+/// Returns `Ok(None)` for plain JSON requests.
+fn parse_cloud_event(
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Option<Map<String, Value>>, String> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+        })
+        .unwrap_or_default();
+
+    if content_type == CLOUDEVENTS_BATCH_CONTENT_TYPE {
+        return Err("batched CloudEvents are not supported".to_owned());
+    }
+
+    let event = if content_type == CLOUDEVENTS_STRUCTURED_CONTENT_TYPE {
+        let value = serde_json::from_slice::<Value>(body)
+            .map_err(|error| format!("invalid CloudEvent payload: {error}"))?;
+        let Value::Object(event) = value else {
+            return Err("invalid CloudEvent payload: expected a JSON object".to_owned());
+        };
+        event
+    } else if headers.contains_key("ce-specversion") {
+        let mut event = Map::new();
+        for (name, value) in headers {
+            let Some(attribute) = name.as_str().strip_prefix(CLOUDEVENTS_HEADER_PREFIX) else {
+                continue;
+            };
+            let Ok(text) = value.to_str() else {
+                continue;
+            };
+            event.insert(attribute.to_owned(), Value::String(text.to_owned()));
+        }
+        if !content_type.is_empty() {
+            event.insert(
+                "datacontenttype".to_owned(),
+                Value::String(content_type.clone()),
+            );
+        }
+        let data = if body.is_empty() {
+            Value::Null
+        } else if content_type.is_empty() || content_type.ends_with("json") {
+            serde_json::from_slice::<Value>(body)
+                .map_err(|error| format!("invalid CloudEvent data: {error}"))?
+        } else {
+            Value::String(String::from_utf8_lossy(body).into_owned())
+        };
+        event.insert("data".to_owned(), data);
+        event
+    } else {
+        return Ok(None);
+    };
+
+    if event.get("specversion").and_then(Value::as_str) != Some(CLOUDEVENTS_SPEC_VERSION) {
+        return Err(format!(
+            "unsupported CloudEvent specversion; expected {CLOUDEVENTS_SPEC_VERSION}"
+        ));
+    }
+    for required in ["id", "source", "type"] {
+        if event
+            .get(required)
+            .and_then(Value::as_str)
+            .is_none_or(|value| value.trim().is_empty())
+        {
+            return Err(format!("CloudEvent attribute {required} is required"));
+        }
+    }
+
+    Ok(Some(event))
 }
 
 fn mapping_path_value(mapping: &HookMappingConfig) -> Option<String> {
@@ -579,6 +1254,7 @@ fn merge_hook_action(
     let base_kind = match base {
         HookResolvedAction::Wake(_) => HookMappingAction::Wake,
         HookResolvedAction::Agent(_) => HookMappingAction::Agent,
+        HookResolvedAction::ScheduleCron(_) => HookMappingAction::ScheduleCron,
     };
     let effective_kind = override_data.kind.unwrap_or(base_kind);
 
@@ -586,7 +1262,7 @@ fn merge_hook_action(
         HookMappingAction::Wake => {
             let base_wake = match base {
                 HookResolvedAction::Wake(value) => Some(value),
-                HookResolvedAction::Agent(_) => None,
+                HookResolvedAction::Agent(_) | HookResolvedAction::ScheduleCron(_) => None,
             };
             let text = trim_non_empty(override_data.text)
                 .or_else(|| base_wake.as_ref().map(|value| value.text.clone()))
@@ -609,7 +1285,7 @@ fn merge_hook_action(
         }
         HookMappingAction::Agent => {
             let base_agent = match base {
-                HookResolvedAction::Wake(_) => None,
+                HookResolvedAction::Wake(_) | HookResolvedAction::ScheduleCron(_) => None,
                 HookResolvedAction::Agent(value) => Some(value),
             };
             let message = trim_non_empty(override_data.message)
@@ -648,6 +1324,13 @@ fn merge_hook_action(
                 }),
             }))
         }
+        // The cron job comes from the mapping's `cron` table; a transform can only keep or skip it.
+        HookMappingAction::ScheduleCron => match base {
+            HookResolvedAction::ScheduleCron(cron) => Ok(HookResolvedAction::ScheduleCron(cron)),
+            HookResolvedAction::Wake(_) | HookResolvedAction::Agent(_) => {
+                Err("hook transform cannot switch a mapping to scheduleCron".to_owned())
+            }
+        },
     }
 }
 
@@ -865,13 +1548,45 @@ fn render_template(template: &str, context: &HookTemplateContext<'_>) -> String
     out
 }
 
+fn render_template_value(template: &Value, context: &HookTemplateContext<'_>) -> Value {
+    match template {
+        Value::String(text) => Value::String(render_template(text, context)),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_template_value(item, context))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), render_template_value(value, context)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
 fn resolve_template_expr(context: &HookTemplateContext<'_>, expr: &str) -> String {
     if expr == "path" {
         return context.path.to_owned();
     }
+    let empty = Map::new();
+    if expr == "runId" {
+        return context
+            .response
+            .and_then(|response| response.get("runId"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned();
+    }
 
     let (source, expr) = if let Some(rest) = expr.strip_prefix("payload.") {
         (context.payload, rest)
+    } else if let Some(rest) = expr.strip_prefix("event.") {
+        (context.event.unwrap_or(&empty), rest)
+    } else if let Some(rest) = expr.strip_prefix("response.") {
+        (context.response.unwrap_or(&empty), rest)
     } else if let Some(rest) = expr.strip_prefix("headers.") {
         (context.headers, rest)
     } else if let Some(rest) = expr.strip_prefix("query.") {
@@ -880,27 +1595,7 @@ fn resolve_template_expr(context: &HookTemplateContext<'_>, expr: &str) -> Strin
         (context.payload, expr)
     };
 
-    let segments = parse_template_segments(expr);
-    let mut segments_iter = segments.into_iter();
-    let Some(first) = segments_iter.next() else {
-        return String::new();
-    };
-
-    let mut cursor = match first {
-        TemplateSegment::Key(key) => source.get(&key),
-        TemplateSegment::Index(_) => None,
-    };
-
-    for segment in segments_iter {
-        let Some(value) = cursor else {
-            return String::new();
-        };
-        cursor = match segment {
-            TemplateSegment::Key(key) => value.get(&key),
-            TemplateSegment::Index(index) => value.get(index),
-        };
-    }
-    let Some(value) = cursor else {
+    let Some(value) = lookup_path(source, expr) else {
         return String::new();
     };
 
@@ -913,6 +1608,22 @@ fn resolve_template_expr(context: &HookTemplateContext<'_>, expr: &str) -> Strin
     serde_json::to_string(value).unwrap_or_default()
 }
 
+/// Resolves a dotted path with `[index]` segments (`commits[0].id`) inside `source`.
+fn lookup_path<'a>(source: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    let mut segments = parse_template_segments(path).into_iter();
+    let mut cursor = match segments.next()? {
+        TemplateSegment::Key(key) => source.get(&key),
+        TemplateSegment::Index(_) => None,
+    };
+    for segment in segments {
+        cursor = match segment {
+            TemplateSegment::Key(key) => cursor?.get(&key),
+            TemplateSegment::Index(index) => cursor?.get(index),
+        };
+    }
+    cursor
+}
+
 #[derive(Debug, Clone)]
 enum TemplateSegment {
     Key(String),
@@ -990,7 +1701,8 @@ fn resolve_session_key_policy(
         if source == HookSessionKeySource::Request && !config.hooks_allow_request_session_key {
             return Err(HOOKS_SESSION_POLICY_ERROR.to_owned());
         }
-        return Ok(session_key);
+        return canonicalize_session_key(&session_key)
+            .map_err(|error| format!("sessionKey {error}"));
     }
 
     if let Some(default_key) = &config.hooks_default_session_key {
@@ -1073,6 +1785,17 @@ fn extract_hook_token(headers: &HeaderMap) -> Option<&str> {
         .filter(|value| !value.is_empty())
 }
 
+/// Password of an `Authorization: Basic` header; the user name is ignored.
+fn extract_basic_auth_password(headers: &HeaderMap) -> Option<String> {
+    let auth = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let encoded = auth
+        .strip_prefix("Basic ")
+        .or_else(|| auth.strip_prefix("basic "))?;
+    let decoded = String::from_utf8(decode_base64(encoded.trim())?).ok()?;
+    let (_, password) = decoded.split_once(':')?;
+    Some(password.to_owned())
+}
+
 fn token_matches(found: Option<&str>, expected: &str) -> bool {
     let Some(found) = found else {
         return false;
@@ -1128,10 +1851,12 @@ fn error_response(
 mod tests {
     use super::{
         HOOKS_SESSION_POLICY_ERROR, HookSessionKeySource, HookTemplateContext, has_token_query,
-        mapping_matches, normalize_mapping_path, render_template, resolve_session_key_policy,
-        resolve_transform_module_path,
+        lookup_path, mapping_matches, normalize_mapping_path, parse_cloud_event, predicate_matches,
+        render_template, resolve_session_key_policy, resolve_transform_module_path,
+    };
+    use crate::application::config::{
+        HookMappingAction, HookMappingConfig, HookMatchPredicate, RuntimeConfig,
     };
-    use crate::application::config::{HookMappingAction, HookMappingConfig, RuntimeConfig};
 
     #[test]
     fn token_query_detector_matches_token_field() {
@@ -1172,6 +1897,11 @@ mod tests {
             r#match: Some(crate::application::config::HookMappingMatchConfig {
                 path: Some("github/push".to_owned()),
                 source: Some("github".to_owned()),
+                r#type: None,
+                subject: None,
+                headers: None,
+                query: None,
+                payload: None,
             }),
             action: HookMappingAction::Agent,
             match_source: None,
@@ -1184,6 +1914,13 @@ mod tests {
             agent_id: None,
             session_key: None,
             transform: None,
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
         };
         let payload = serde_json::json!({
             "source": "github",
@@ -1192,7 +1929,52 @@ mod tests {
         .cloned()
         .unwrap_or_default();
 
-        assert!(mapping_matches(&mapping, "github/push", &payload));
+        let empty = serde_json::Map::new();
+        assert!(mapping_matches(
+            &mapping,
+            "github/push",
+            &payload,
+            &empty,
+            &empty
+        ));
+    }
+
+    #[test]
+    fn match_predicates_compare_text_and_check_existence() {
+        let operator = |equals: Option<&str>, regex: Option<&str>, exists: Option<bool>| {
+            HookMatchPredicate::Operator {
+                equals: equals.map(str::to_owned),
+                regex: regex.map(str::to_owned),
+                exists,
+            }
+        };
+        let payload = serde_json::json!({ "n": 3, "tags": ["a"], "name": "main" });
+        let payload = payload.as_object().cloned().unwrap_or_default();
+
+        assert!(predicate_matches(
+            &HookMatchPredicate::Equals("3".to_owned()),
+            lookup_path(&payload, "n")
+        ));
+        assert!(predicate_matches(
+            &operator(None, Some("^ma"), None),
+            lookup_path(&payload, "name")
+        ));
+        assert!(!predicate_matches(
+            &operator(None, Some("^ma"), None),
+            lookup_path(&payload, "missing")
+        ));
+        assert!(predicate_matches(
+            &operator(None, None, Some(true)),
+            lookup_path(&payload, "tags[0]")
+        ));
+        assert!(predicate_matches(
+            &operator(None, None, Some(false)),
+            lookup_path(&payload, "tags[1]")
+        ));
+        assert!(!predicate_matches(
+            &operator(Some("dev"), Some("^ma"), None),
+            lookup_path(&payload, "name")
+        ));
     }
 
     #[test]
@@ -1211,6 +1993,8 @@ mod tests {
             path: "github/push",
             query: &query,
             url: "/hooks/github/push",
+            event: None,
+            response: None,
         };
         let rendered = render_template(
             "repo={{repo}} actor={{actor.name}} first={{commits[0].id}}",
@@ -1240,6 +2024,8 @@ mod tests {
             path: "github/template",
             query: &query,
             url: "/hooks/github/template?kind=push",
+            event: None,
+            response: None,
         };
 
         let rendered = render_template(
@@ -1255,4 +2041,36 @@ mod tests {
         let result = resolve_transform_module_path(&transforms_dir, "../evil.mjs");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn parse_cloud_event_reads_binary_mode_headers() {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("ce-specversion", "1.0".parse().expect("header"));
+        headers.insert("ce-id", "evt-1".parse().expect("header"));
+        headers.insert("ce-source", "/billing".parse().expect("header"));
+        headers.insert(
+            "ce-type",
+            "com.example.invoice.paid".parse().expect("header"),
+        );
+        headers.insert("ce-subject", "inv-9".parse().expect("header"));
+        headers.insert(
+            axum::http::header::CONTENT_TYPE,
+            "application/json".parse().expect("header"),
+        );
+
+        let event = parse_cloud_event(&headers, br#"{"amount":42}"#)
+            .expect("binary event should parse")
+            .expect("binary event should be detected");
+        assert_eq!(event["type"], "com.example.invoice.paid");
+        assert_eq!(event["subject"], "inv-9");
+        assert_eq!(event["data"]["amount"], 42);
+
+        headers.remove("ce-id");
+        assert!(parse_cloud_event(&headers, b"{}").is_err());
+        assert!(
+            parse_cloud_event(&axum::http::HeaderMap::new(), b"{}")
+                .expect("plain json should parse")
+                .is_none()
+        );
+    }
 }
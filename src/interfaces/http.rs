@@ -3,23 +3,33 @@ use std::{future::Future, net::SocketAddr};
 use axum::routing::post;
 use axum::{
     Json, Router,
-    extract::{Extension, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{ConnectInfo, Extension, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::get,
 };
 use tokio::net::TcpListener;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
-    application::state::SharedState,
+    application::{
+        config::{AuthMode, GuardrailAction},
+        state::SharedState,
+        webhook_sources,
+    },
     domain::error::DomainError,
     interfaces::{
-        channels, hooks, openai, openresponses, slack_http, telegram, tools_invoke, webhooks, ws,
+        channels, compat, federation, hooks, node_files, node_poll, openai, openresponses,
+        replication, setup, slack_http, telegram, tools_invoke, webhooks, ws,
     },
     rpc::methods::{health, status},
+    security::{origin::check_origin_and_host, source_ip::client_ip},
 };
 
+/// Hosts `/setup` answers to when `allowedHosts` is not configured.
+const SETUP_DEFAULT_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
 pub fn build_router(state: SharedState) -> Router {
     build_router_with_webhooks(state, webhooks::default_registry())
 }
@@ -29,18 +39,16 @@ pub fn build_router_with_webhooks(
     webhook_registry: webhooks::ChannelWebhookRegistry,
 ) -> Router {
     let slack_events_path = state.config().slack_events_path.clone();
-    let mut router = Router::new()
-        .route("/", get(ws::ws_handler))
-        .route("/ws", get(ws::ws_handler))
-        .route("/healthz", get(healthz_handler))
-        .route("/readyz", get(readyz_handler))
-        .route("/info", get(info_handler))
-        .route("/tools/invoke", post(tools_invoke::invoke_handler))
+    let mut webhooks_router = Router::new()
         .route("/channels/inbound", post(channels::inbound_handler))
         .route(
             "/channels/{channel}/inbound",
             post(channels::inbound_channel_handler),
         )
+        .route(
+            "/channels/{channel}/inbound/batch",
+            post(channels::inbound_batch_handler),
+        )
         .route(
             "/channels/telegram/webhook",
             post(telegram::webhook_handler),
@@ -48,27 +56,119 @@ pub fn build_router_with_webhooks(
         .route(
             "/channels/{channel}/webhook",
             post(webhooks::channel_webhook_handler),
-        );
-    router = router.route(slack_events_path.as_str(), post(slack_http::events_handler));
-    router = router.layer(Extension(webhook_registry));
+        )
+        .route(
+            "/channels/{channel}/receipts",
+            post(channels::receipts_handler),
+        )
+        .route(slack_events_path.as_str(), post(slack_http::events_handler));
 
     if state.config().hooks_enabled {
         let hooks_base_path = state.config().hooks_path.clone();
         let hooks_subpath = format!("{hooks_base_path}/{{*subpath}}");
-        router = router
+        webhooks_router = webhooks_router
             .route(hooks_base_path.as_str(), post(hooks::root_handler))
             .route(hooks_subpath.as_str(), post(hooks::subpath_handler));
     }
 
+    // Routes a browser page could reach with ambient credentials.
+    // Of those, the ones that start runs, closed during maintenance and draining.
+    let mut runs_router = Router::new().route("/tools/invoke", post(tools_invoke::invoke_handler));
+
     if state.config().openai_chat_completions_enabled {
-        router = router.route(
+        runs_router = runs_router.route(
             "/v1/chat/completions",
             post(openai::chat_completions_handler),
         );
     }
 
     if state.config().openresponses_enabled {
-        router = router.route("/v1/responses", post(openresponses::responses_handler));
+        runs_router = runs_router.route("/v1/responses", post(openresponses::responses_handler));
+    }
+
+    let browser_router = Router::new()
+        .route("/", get(ws::ws_handler))
+        .route("/ws", get(ws::ws_handler))
+        .merge(runs_router.route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            maintenance_guard,
+        )));
+
+    let mut router = Router::new()
+        .route("/healthz", get(healthz_handler))
+        .route("/readyz", get(readyz_handler))
+        .route("/info", get(info_handler))
+        .route("/metrics", get(metrics_handler))
+        // Nodes authenticate like WebSocket nodes; they are not browser pages.
+        .route("/nodes/{id}/poll", post(node_poll::poll_handler))
+        .merge(
+            browser_router.route_layer(middleware::from_fn_with_state(state.clone(), origin_guard)),
+        )
+        .merge(
+            webhooks_router
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    webhook_source_guard,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    shed_webhooks_guard,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    maintenance_guard,
+                )),
+        )
+        .layer(Extension(webhook_registry));
+
+    // Anonymous and embeddable, so it sits outside the origin guard.
+    if state.config().public_status_enabled {
+        router = router.route("/status/public", get(public_status_handler));
+    }
+
+    // Peer gateways authenticate with signatures instead of gateway credentials.
+    if state.config().federation.is_some() {
+        router = router
+            .route("/federation/pair", post(federation::pair_handler))
+            .route("/federation/rpc", post(federation::rpc_handler))
+            .route("/federation/health", post(federation::health_handler));
+    }
+
+    // Nodes move transfer bytes with the transfer's one-time token.
+    if state.config().node_files.is_some() {
+        router = router.route(
+            "/nodes/{id}/files/{transfer_id}",
+            get(node_files::download_handler).put(node_files::upload_handler),
+        );
+    }
+
+    // Standbys authenticate with the shared replication token.
+    if state.config().replication.is_some() {
+        router = router
+            .route(
+                "/replication/heartbeat",
+                post(replication::heartbeat_handler),
+            )
+            .route("/replication/changes", get(replication::changes_handler))
+            .route("/replication/snapshot", get(replication::snapshot_handler))
+            .route("/replication/epoch", get(replication::epoch_handler))
+            .route("/replication/fence", post(replication::fence_handler));
+    }
+
+    // A browser page could reach it too, and DNS rebinding would pass a same-origin check, so the
+    // Host must be loopback unless `allowedHosts` says otherwise.
+    if state.config().auth_mode == AuthMode::None {
+        router = router.merge(
+            Router::new()
+                .route(
+                    "/setup",
+                    get(setup::status_handler).post(setup::complete_handler),
+                )
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    setup_origin_guard,
+                )),
+        );
     }
 
     router.with_state(state)
@@ -78,6 +178,15 @@ pub async fn serve(
     listener: TcpListener,
     state: SharedState,
     shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), DomainError> {
+    serve_with_webhooks(listener, state, webhooks::default_registry(), shutdown).await
+}
+
+pub async fn serve_with_webhooks(
+    listener: TcpListener,
+    state: SharedState,
+    webhook_registry: webhooks::ChannelWebhookRegistry,
+    shutdown: impl Future<Output = ()> + Send + 'static,
 ) -> Result<(), DomainError> {
     let local_addr = listener.local_addr().map_err(|error| {
         DomainError::Unavailable(format!("failed to read listener address: {error}"))
@@ -93,13 +202,199 @@ pub async fn serve(
 
     axum::serve(
         listener,
-        build_router(state).into_make_service_with_connect_info::<SocketAddr>(),
+        build_router_with_webhooks(state, webhook_registry)
+            .into_make_service_with_connect_info::<SocketAddr>(),
     )
     .with_graceful_shutdown(shutdown)
     .await
     .map_err(|error| DomainError::Unavailable(format!("server runtime error: {error}")))
 }
 
+async fn origin_guard(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let config = state.config();
+    guard_origin_and_host(
+        request,
+        next,
+        &config.allowed_origins,
+        &config.allowed_hosts,
+    )
+    .await
+}
+
+/// `origin_guard` for `/setup`, with loopback hosts as the default Host allowlist.
+async fn setup_origin_guard(
+    State(state): State<SharedState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = state.config();
+    let loopback_hosts = SETUP_DEFAULT_HOSTS.map(str::to_owned);
+    let allowed_hosts = if config.allowed_hosts.is_empty() {
+        &loopback_hosts[..]
+    } else {
+        &config.allowed_hosts[..]
+    };
+    guard_origin_and_host(request, next, &config.allowed_origins, allowed_hosts).await
+}
+
+async fn guard_origin_and_host(
+    request: Request,
+    next: Next,
+    allowed_origins: &[String],
+    allowed_hosts: &[String],
+) -> Response {
+    if let Err(message) = check_origin_and_host(request.headers(), allowed_origins, allowed_hosts) {
+        warn!(
+            "rejected {} {}: {message}",
+            request.method(),
+            request.uri().path()
+        );
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "ok": false,
+                "error": {
+                    "code": "FORBIDDEN",
+                    "message": message,
+                },
+            })),
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Turns away new runs and webhooks during maintenance and while a scheduled stop drains, and
+/// counts admitted requests as in flight so draining waits for them.
+async fn maintenance_guard(
+    State(state): State<SharedState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some((message, retry_after_ms)) = state.lifecycle().rejection().await {
+        let retry_after_secs = retry_after_ms.map_or(60, |ms| ms.div_ceil(1000).max(1));
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, retry_after_secs.to_string())],
+            Json(serde_json::json!({
+                "ok": false,
+                "error": {
+                    "code": "UNAVAILABLE",
+                    "message": message,
+                },
+            })),
+        )
+            .into_response();
+    }
+
+    let _work = state.lifecycle().begin_work();
+    next.run(request).await
+}
+
+async fn shed_webhooks_guard(
+    State(state): State<SharedState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.is_standby() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "ok": false,
+                "error": {
+                    "code": "UNAVAILABLE",
+                    "message": "webhook ingress is disabled on a read-only standby",
+                },
+            })),
+        )
+            .into_response();
+    }
+    if state.is_shedding_load() {
+        state.count_shed_request();
+        let retry_after_secs = state.config().overload.cooldown.as_secs().max(1);
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, retry_after_secs.to_string())],
+            Json(serde_json::json!({
+                "ok": false,
+                "error": {
+                    "code": "UNAVAILABLE",
+                    "message": "webhook ingress is shedding load: server overloaded",
+                },
+            })),
+        )
+            .into_response();
+    }
+    if state.guardrail_engaged(GuardrailAction::ShedWebhooks).await {
+        let retry_after_secs = state.config().self_monitor_interval.as_secs().max(1);
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, retry_after_secs.to_string())],
+            Json(serde_json::json!({
+                "ok": false,
+                "error": {
+                    "code": "UNAVAILABLE",
+                    "message": "webhook ingress is shedding load: resource guardrails exceeded",
+                },
+            })),
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Rejects channel webhook calls from outside the channel's configured source networks.
+async fn webhook_source_guard(
+    State(state): State<SharedState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = state.config();
+    let Some(channel) =
+        webhook_sources::channel_for_path(request.uri().path(), &config.slack_events_path)
+            .filter(|channel| config.webhook_sources.contains_key(channel))
+    else {
+        return next.run(request).await;
+    };
+    let source =
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(remote)| {
+                client_ip(*remote, request.headers(), &config.webhook_trusted_proxies)
+            });
+    if let Some(ip) = source
+        && webhook_sources::source_allowed(&state, &channel, ip).await
+    {
+        return next.run(request).await;
+    }
+
+    let source = source.map_or_else(|| "unknown".to_owned(), |ip| ip.to_string());
+    let message = format!("rejected {channel} webhook from {source}: source address not allowed");
+    warn!("{message}");
+    let _ = state
+        .append_gateway_log(
+            "warn",
+            &message,
+            Some(&format!("channels.{channel}.webhook")),
+            None,
+        )
+        .await;
+    (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({
+            "ok": false,
+            "error": {
+                "code": "FORBIDDEN",
+                "message": "webhook source address not allowed",
+            },
+        })),
+    )
+        .into_response()
+}
+
 async fn healthz_handler(State(state): State<SharedState>) -> impl IntoResponse {
     match state.health_payload().await {
         Ok(payload) => (StatusCode::OK, Json(payload)).into_response(),
@@ -120,6 +415,87 @@ async fn readyz_handler(State(state): State<SharedState>) -> impl IntoResponse {
     (StatusCode::OK, Json(payload))
 }
 
+/// Prometheus text metrics for callers holding the gateway credential or an API key.
+async fn metrics_handler(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    if let Err(rejection) = compat::authorize_gateway_http(&state, &headers).await {
+        let code = match rejection.status() {
+            StatusCode::TOO_MANY_REQUESTS => "RATE_LIMITED",
+            StatusCode::SERVICE_UNAVAILABLE => "UNAVAILABLE",
+            _ => "UNAUTHORIZED",
+        };
+        return (
+            rejection.status(),
+            Json(serde_json::json!({
+                "ok": false,
+                "error": {
+                    "code": code,
+                    "message": rejection.message(),
+                },
+            })),
+        )
+            .into_response();
+    }
+
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.storage_query_metrics().render_prometheus(),
+    )
+        .into_response()
+}
+
+async fn public_status_handler(
+    State(state): State<SharedState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    request: Request,
+) -> Response {
+    let config = state.config();
+    let ip = client_ip(
+        remote_addr,
+        request.headers(),
+        &config.webhook_trusted_proxies,
+    );
+    let decision = state
+        .public_status_rate_limiter()
+        .record_with_limit(&ip.to_string(), config.public_status_rate_limit_per_minute)
+        .await;
+    if !decision.allowed {
+        let retry_after_secs = decision.retry_after_ms.div_ceil(1000).max(1);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [
+                (header::RETRY_AFTER, retry_after_secs.to_string()),
+                (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*".to_owned()),
+            ],
+            Json(serde_json::json!({
+                "ok": false,
+                "error": {
+                    "code": "RATE_LIMITED",
+                    "message": "too many status requests",
+                },
+            })),
+        )
+            .into_response();
+    }
+
+    let payload = state.public_status_payload().await;
+    (
+        StatusCode::OK,
+        [
+            (
+                header::CACHE_CONTROL,
+                format!(
+                    "public, max-age={}",
+                    config.public_status_cache_ttl.as_secs()
+                ),
+            ),
+            (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*".to_owned()),
+        ],
+        Json(payload),
+    )
+        .into_response()
+}
+
 async fn info_handler(State(state): State<SharedState>) -> impl IntoResponse {
     let payload = status::info_payload(&state);
     (StatusCode::OK, Json(payload))
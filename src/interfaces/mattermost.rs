@@ -0,0 +1,172 @@
+use axum::http::{HeaderMap, StatusCode};
+use serde_json::{Value, json};
+
+use crate::application::state::SharedState;
+
+use super::{channel_adapter_common as common, quiet_hours, webhooks::WebhookFuture};
+
+const MATTERMOST_EVENTS_PREFIX: &str = "runtime/mattermost/event/";
+
+/// Handles a Mattermost outgoing webhook (content type `application/json`). Replies are posted
+/// through the configured incoming webhook into the originating channel.
+pub(crate) fn dispatch_webhook<'a>(
+    state: &'a SharedState,
+    headers: &'a HeaderMap,
+    payload: Value,
+) -> WebhookFuture<'a> {
+    Box::pin(async move {
+        if let Err(error) = common::require_channel_body_token(
+            &payload,
+            &state.config().mattermost_webhook_token,
+            "mattermost",
+        ) {
+            return error;
+        }
+
+        let Some(conversation_id) = read_str(&payload, "channel_id") else {
+            return common::accepted_false("no-channel");
+        };
+        let text = strip_trigger_word(
+            read_str(&payload, "text").unwrap_or_default(),
+            read_str(&payload, "trigger_word"),
+        );
+        if text.is_empty() {
+            return common::accepted_false("no-text");
+        }
+        let Some(post_id) = read_str(&payload, "post_id") else {
+            return common::accepted_false("no-post-id");
+        };
+
+        let dedupe_key = format!("{MATTERMOST_EVENTS_PREFIX}{post_id}");
+        if common::is_duplicate_event(state, &dedupe_key).await {
+            return (
+                StatusCode::OK,
+                axum::Json(json!({
+                    "ok": true,
+                    "accepted": false,
+                    "duplicate": true,
+                })),
+            );
+        }
+
+        let channel_name = read_str(&payload, "channel_name");
+        let result = match common::ingest_channel_message(
+            state,
+            common::ChannelInboundEvent {
+                channel: "mattermost",
+                conversation_id: conversation_id.to_owned(),
+                text,
+                sender_id: read_str(&payload, "user_id").map(str::to_owned),
+                message_id: Some(post_id.to_owned()),
+                idempotency_key: format!("mattermost-{post_id}"),
+                metadata: Some(json!({
+                    "source": "mattermost",
+                    "teamDomain": read_str(&payload, "team_domain"),
+                    "conversationTitle": channel_name,
+                    "senderName": read_str(&payload, "user_name"),
+                })),
+            },
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(error) => return error,
+        };
+        common::mark_event_processed(state, &dedupe_key, "mattermost", post_id, &result).await;
+
+        let mut outbound = common::OutboundOutcome::Skipped;
+        if state.config().mattermost_incoming_webhook_url.is_some() {
+            outbound = common::send_reply_parts(
+                state,
+                common::DirectReplyDispatch {
+                    channel: "mattermost",
+                    conversation_id,
+                    session_key: &result.session_key,
+                    run_id: result.run_id.as_deref(),
+                    log_scope: "channels.mattermost.webhook",
+                    urgent: quiet_hours::is_urgent(headers),
+                },
+                &result.reply_parts,
+                |text, delivery_id| {
+                    json!({
+                        "conversationId": conversation_id,
+                        "channelName": channel_name,
+                        "text": text,
+                        "deliveryId": delivery_id,
+                    })
+                },
+            )
+            .await;
+        }
+
+        common::accepted_true_with_outbound(&result, outbound)
+    })
+}
+
+/// Posts a reply through the incoming webhook. Mattermost addresses channels by name, so a
+/// payload without `channelName` falls back to the name recorded in the channel directory.
+pub(crate) async fn send_mattermost_reply(
+    state: &SharedState,
+    payload: &Value,
+) -> Result<Option<String>, String> {
+    let url = state
+        .config()
+        .mattermost_incoming_webhook_url
+        .as_deref()
+        .ok_or_else(|| "mattermost incoming webhook url is not configured".to_owned())?;
+    let conversation_id = read_str(payload, "conversationId")
+        .ok_or_else(|| "mattermost reply has no conversationId".to_owned())?;
+    let channel_name = match read_str(payload, "channelName") {
+        Some(name) => name.to_owned(),
+        None => state
+            .get_channel_directory_entry("mattermost", &conversation_id.to_ascii_lowercase())
+            .await
+            .ok()
+            .flatten()
+            .and_then(|entry| entry.title)
+            .ok_or_else(|| format!("mattermost channel {conversation_id} has no known name"))?,
+    };
+    let body = common::post_json(
+        url,
+        None,
+        &json!({
+            "channel": channel_name,
+            "text": read_str(payload, "text").unwrap_or_default(),
+        }),
+    )
+    .await?;
+    Ok(common::platform_message_id(&body))
+}
+
+/// Outgoing webhooks keyed on trigger words include the word in `text`; the agent gets the rest.
+fn strip_trigger_word(text: &str, trigger_word: Option<&str>) -> String {
+    let text = text.trim();
+    trigger_word
+        .and_then(|word| text.strip_prefix(word))
+        .unwrap_or(text)
+        .trim()
+        .to_owned()
+}
+
+fn read_str<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_trigger_word;
+
+    #[test]
+    fn trigger_words_are_stripped_from_message_text() {
+        assert_eq!(
+            strip_trigger_word(" #ask what is up? ", Some("#ask")),
+            "what is up?"
+        );
+        assert_eq!(strip_trigger_word("hello", None), "hello");
+        assert_eq!(strip_trigger_word("hello", Some("#ask")), "hello");
+    }
+}
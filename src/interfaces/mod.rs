@@ -1,16 +1,30 @@
 pub(crate) mod channel_adapter_common;
+pub(crate) mod channel_plugins;
 pub mod channels;
 pub(crate) mod compat;
 pub mod discord;
+pub mod federation;
+pub(crate) mod hook_email;
+pub(crate) mod hook_providers;
 pub mod hooks;
 pub mod http;
+pub mod mattermost;
+pub mod node_files;
+pub mod node_poll;
 pub mod openai;
 pub mod openresponses;
+pub mod quiet_hours;
+pub mod replication;
+pub mod rocketchat;
+pub mod setup;
 pub mod signal;
 pub mod slack;
 pub mod slack_http;
+pub mod teams;
 pub mod telegram;
+pub mod telegram_webhook;
 pub mod tools_invoke;
 pub mod webhooks;
 pub mod whatsapp;
 pub(crate) mod ws;
+pub mod ws_recording;
@@ -0,0 +1,634 @@
+use std::io::SeekFrom;
+
+use axum::{
+    Json,
+    body::{Body, Bytes},
+    extract::{Path, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::{StreamExt, stream};
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tracing::warn;
+
+use crate::{
+    application::{
+        node_files::{self, CHECKSUM_HEADER, DIRECTION_PULL, DIRECTION_PUSH},
+        state::SharedState,
+    },
+    domain::models::NodeFileTransfer,
+    storage::now_unix_ms,
+};
+
+const READ_CHUNK_BYTES: u64 = 64 * 1024;
+
+/// `GET /nodes/{id}/files/{transferId}`: serves a pushed file, honouring a single `Range` so
+/// an interrupted download resumes where it stopped.
+pub async fn download_handler(
+    State(state): State<SharedState>,
+    Path((node_id, transfer_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    let mut transfer =
+        match authorize(&state, &headers, &node_id, &transfer_id, DIRECTION_PUSH).await {
+            Ok(transfer) => transfer,
+            Err(response) => return response,
+        };
+    let source = match node_files::gateway_path(&state, &transfer) {
+        Ok(source) => source,
+        Err(error) => {
+            return error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "UNAVAILABLE",
+                error.to_string(),
+            );
+        }
+    };
+    let size = transfer.size_bytes.unwrap_or(0);
+    let opened = match tokio::fs::File::open(&source).await {
+        Ok(file) => file.metadata().await.map(|metadata| (file, metadata.len())),
+        Err(error) => Err(error),
+    };
+    let mut file = match opened {
+        Ok((file, len)) if len == size => file,
+        Ok(_) => {
+            return fail(
+                &state,
+                &mut transfer,
+                "source file changed since the transfer started",
+            )
+            .await;
+        }
+        Err(error) => {
+            return fail(
+                &state,
+                &mut transfer,
+                &format!("cannot read source file: {error}"),
+            )
+            .await;
+        }
+    };
+
+    let range = headers.get(header::RANGE).map(|value| {
+        value
+            .to_str()
+            .ok()
+            .and_then(|value| parse_range(value, size))
+    });
+    let (start, end) = match range {
+        None => (0, size),
+        Some(Some(span)) => span,
+        Some(None) => {
+            let mut response = error_response(
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "INVALID_REQUEST",
+                format!("range is not satisfiable for a {size}-byte file"),
+            );
+            insert_header(
+                &mut response,
+                header::CONTENT_RANGE,
+                format!("bytes */{size}"),
+            );
+            return response;
+        }
+    };
+    if start > 0
+        && let Err(error) = file.seek(SeekFrom::Start(start)).await
+    {
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "UNAVAILABLE",
+            format!("cannot seek source file: {error}"),
+        );
+    }
+    if transfer.status == "pending" {
+        transfer.status = "active".to_owned();
+        transfer.updated_at_ms = now_unix_ms();
+        if let Err(response) = save(&state, &transfer).await {
+            return response;
+        }
+    }
+
+    if start == end {
+        record_push_progress(&state, &transfer_id, end).await;
+    }
+    // Progress is recorded as the last chunk is handed over; a client that already holds its
+    // declared length may never poll the stream again.
+    let body = stream::unfold(Some((file, end - start)), move |pending| {
+        let state = state.clone();
+        let transfer_id = transfer_id.clone();
+        async move {
+            let (mut file, remaining) = pending?;
+            if remaining == 0 {
+                return None;
+            }
+            let mut buffer =
+                vec![0_u8; usize::try_from(remaining.min(READ_CHUNK_BYTES)).unwrap_or(0)];
+            match file.read(&mut buffer).await {
+                Ok(0) => Some((
+                    Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "source file shrank during the download",
+                    )),
+                    None,
+                )),
+                Ok(read) => {
+                    buffer.truncate(read);
+                    let remaining = remaining - read as u64;
+                    if remaining == 0 {
+                        record_push_progress(&state, &transfer_id, end).await;
+                    }
+                    Some((Ok(Bytes::from(buffer)), Some((file, remaining))))
+                }
+                Err(error) => Some((Err(error), None)),
+            }
+        }
+    });
+
+    let mut response = Body::from_stream(body).into_response();
+    if range.is_some() {
+        *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+        insert_header(
+            &mut response,
+            header::CONTENT_RANGE,
+            format!("bytes {start}-{}/{size}", end - 1),
+        );
+    }
+    insert_header(
+        &mut response,
+        header::CONTENT_LENGTH,
+        (end - start).to_string(),
+    );
+    insert_header(
+        &mut response,
+        header::CONTENT_TYPE,
+        "application/octet-stream".to_owned(),
+    );
+    insert_header(&mut response, header::ACCEPT_RANGES, "bytes".to_owned());
+    if let Some(sha256) = &transfer.sha256 {
+        insert_header(
+            &mut response,
+            header::HeaderName::from_static(CHECKSUM_HEADER),
+            sha256.clone(),
+        );
+    }
+    response
+}
+
+/// `PUT /nodes/{id}/files/{transferId}`: appends one chunk of a pulled file. `Content-Range:
+/// bytes <start>-<end>/<total>` must start at the bytes received so far; `bytes */<total>`
+/// reports that offset without sending data. The file is checked and moved into place once
+/// all `<total>` bytes have arrived.
+pub async fn upload_handler(
+    State(state): State<SharedState>,
+    Path((node_id, transfer_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    if !state.begin_node_file_upload(&transfer_id).await {
+        return error_response(
+            StatusCode::CONFLICT,
+            "UNAVAILABLE",
+            "another upload to this transfer is in progress",
+        );
+    }
+    let response = receive_chunk(&state, &node_id, &transfer_id, &headers, body).await;
+    state.end_node_file_upload(&transfer_id).await;
+    response
+}
+
+async fn receive_chunk(
+    state: &SharedState,
+    node_id: &str,
+    transfer_id: &str,
+    headers: &HeaderMap,
+    body: Body,
+) -> Response {
+    let mut transfer = match authorize(state, headers, node_id, transfer_id, DIRECTION_PULL).await {
+        Ok(transfer) => transfer,
+        Err(response) => return response,
+    };
+    let Some((span, total)) = headers
+        .get(header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_content_range)
+    else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "INVALID_REQUEST",
+            "Content-Range: bytes <start>-<end>/<total> or bytes */<total> is required",
+        );
+    };
+    if total > transfer.max_bytes {
+        let limit = transfer.max_bytes;
+        let mut response = fail(
+            state,
+            &mut transfer,
+            &format!("file is {total} bytes; the limit is {limit}"),
+        )
+        .await;
+        *response.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+        return response;
+    }
+    if let Some(size) = transfer.size_bytes
+        && size != total
+    {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "INVALID_REQUEST",
+            format!("file size changed from {size} to {total} bytes"),
+        );
+    }
+    if let Some(checksum) = headers.get(CHECKSUM_HEADER) {
+        let checksum = checksum
+            .to_str()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        if !node_files::is_sha256_hex(&checksum) {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "INVALID_REQUEST",
+                format!("{CHECKSUM_HEADER} must be 64 hex characters"),
+            );
+        }
+        match &transfer.sha256 {
+            Some(expected) if *expected != checksum => {
+                let error = format!("node reported sha256 {checksum}, expected {expected}");
+                let mut response = fail(state, &mut transfer, &error).await;
+                *response.status_mut() = StatusCode::UNPROCESSABLE_ENTITY;
+                return response;
+            }
+            Some(_) => {}
+            None => transfer.sha256 = Some(checksum),
+        }
+    }
+
+    let dest = match node_files::gateway_path(state, &transfer) {
+        Ok(dest) => dest,
+        Err(error) => {
+            return error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "UNAVAILABLE",
+                error.to_string(),
+            );
+        }
+    };
+    let partial = node_files::partial_path(&dest, transfer_id);
+    transfer.size_bytes = Some(total);
+    transfer.status = "active".to_owned();
+    transfer.updated_at_ms = now_unix_ms();
+
+    let Some((start, end)) = span else {
+        if transfer.transferred_bytes == total {
+            return complete_pull(state, &mut transfer, &partial, &dest).await;
+        }
+        return match save(state, &transfer).await {
+            Ok(()) => progress_response(&transfer),
+            Err(response) => response,
+        };
+    };
+    if start != transfer.transferred_bytes || end > total {
+        if let Err(response) = save(state, &transfer).await {
+            return response;
+        }
+        let mut response = error_response(
+            StatusCode::CONFLICT,
+            "INVALID_REQUEST",
+            format!(
+                "chunk must start at byte {} and end by byte {total}",
+                transfer.transferred_bytes
+            ),
+        );
+        insert_header(
+            &mut response,
+            header::HeaderName::from_static("x-received-bytes"),
+            transfer.transferred_bytes.to_string(),
+        );
+        return response;
+    }
+
+    let written = match write_chunk(&partial, &dest, start, end - start, body).await {
+        Ok(written) => written,
+        Err((written, error)) => {
+            transfer.transferred_bytes = start + written;
+            if let Err(response) = save(state, &transfer).await {
+                return response;
+            }
+            return error_response(StatusCode::BAD_REQUEST, "INVALID_REQUEST", error);
+        }
+    };
+    transfer.transferred_bytes = start + written;
+    if transfer.transferred_bytes == total {
+        return complete_pull(state, &mut transfer, &partial, &dest).await;
+    }
+    match save(state, &transfer).await {
+        Ok(()) => progress_response(&transfer),
+        Err(response) => response,
+    }
+}
+
+/// Appends up to `expected` body bytes to the partial file, which is first cut back to `start`
+/// in case an earlier chunk was written but never recorded. On error, returns how many bytes
+/// did land so the transfer can resume after them.
+async fn write_chunk(
+    partial: &std::path::Path,
+    dest: &std::path::Path,
+    start: u64,
+    expected: u64,
+    body: Body,
+) -> Result<u64, (u64, String)> {
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|error| (0, format!("cannot create destination directory: {error}")))?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(partial)
+        .await
+        .map_err(|error| (0, format!("cannot open partial file: {error}")))?;
+    file.set_len(start)
+        .await
+        .map_err(|error| (0, format!("cannot resize partial file: {error}")))?;
+    file.seek(SeekFrom::Start(start))
+        .await
+        .map_err(|error| (0, format!("cannot seek partial file: {error}")))?;
+
+    let mut written = 0_u64;
+    let mut failure = None;
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(error) => {
+                failure = Some(format!("upload interrupted: {error}"));
+                break;
+            }
+        };
+        let room = usize::try_from(expected - written).unwrap_or(usize::MAX);
+        if chunk.len() > room {
+            failure = Some(format!(
+                "chunk body is longer than its {expected}-byte range"
+            ));
+            break;
+        }
+        if let Err(error) = file.write_all(&chunk).await {
+            failure = Some(format!("cannot write partial file: {error}"));
+            break;
+        }
+        written += chunk.len() as u64;
+    }
+    if let Err(error) = file.sync_data().await {
+        return Err((0, format!("cannot sync partial file: {error}")));
+    }
+    if failure.is_none() && written < expected {
+        failure = Some(format!(
+            "chunk ended after {written} of its {expected} bytes"
+        ));
+    }
+    match failure {
+        Some(error) => Err((written, error)),
+        None => Ok(written),
+    }
+}
+
+/// Verifies the received file against the expected checksum and moves it into place.
+async fn complete_pull(
+    state: &SharedState,
+    transfer: &mut NodeFileTransfer,
+    partial: &std::path::Path,
+    dest: &std::path::Path,
+) -> Response {
+    let (size, sha256) = match node_files::file_digest(partial).await {
+        Ok(digest) => digest,
+        Err(error) => {
+            return fail(
+                state,
+                transfer,
+                &format!("cannot read received file: {error}"),
+            )
+            .await;
+        }
+    };
+    if Some(size) != transfer.size_bytes {
+        return fail(
+            state,
+            transfer,
+            &format!(
+                "received {size} bytes, expected {}",
+                transfer.size_bytes.unwrap_or(0)
+            ),
+        )
+        .await;
+    }
+    if let Some(expected) = &transfer.sha256
+        && *expected != sha256
+    {
+        let error = format!("received sha256 {sha256}, expected {expected}");
+        let mut response = fail(state, transfer, &error).await;
+        *response.status_mut() = StatusCode::UNPROCESSABLE_ENTITY;
+        return response;
+    }
+    if let Err(error) = tokio::fs::rename(partial, dest).await {
+        return fail(
+            state,
+            transfer,
+            &format!("cannot move received file into place: {error}"),
+        )
+        .await;
+    }
+    transfer.sha256 = Some(sha256);
+    if let Err(error) = node_files::finish(state, transfer, "completed", None).await {
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "UNAVAILABLE",
+            error.to_string(),
+        );
+    }
+    progress_response(transfer)
+}
+
+/// Checks that the transfer exists for `node_id` in `direction`, that the bearer token is its
+/// one-time token, and that it is still open.
+async fn authorize(
+    state: &SharedState,
+    headers: &HeaderMap,
+    node_id: &str,
+    transfer_id: &str,
+    direction: &str,
+) -> Result<NodeFileTransfer, Response> {
+    let transfer = node_files::get_transfer(state, transfer_id)
+        .await
+        .map_err(|error| {
+            error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "UNAVAILABLE",
+                error.to_string(),
+            )
+        })?
+        .filter(|transfer| transfer.node_id == node_id.trim() && transfer.direction == direction)
+        .ok_or_else(|| {
+            error_response(
+                StatusCode::NOT_FOUND,
+                "INVALID_REQUEST",
+                format!("unknown {direction} transfer for node {node_id}"),
+            )
+        })?;
+
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .unwrap_or_default();
+    if token.is_empty() || !node_files::verify_token(&transfer, token) {
+        return Err(error_response(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "a valid transfer token is required",
+        ));
+    }
+    if !node_files::is_open(&transfer) {
+        return Err(error_response(
+            StatusCode::GONE,
+            "INVALID_REQUEST",
+            format!("transfer is {}", transfer.status),
+        ));
+    }
+    Ok(transfer)
+}
+
+async fn record_push_progress(state: &SharedState, transfer_id: &str, end: u64) {
+    let Ok(Some(mut transfer)) = state.get_node_file_transfer(transfer_id).await else {
+        return;
+    };
+    if !node_files::is_open(&transfer) {
+        return;
+    }
+    transfer.transferred_bytes = transfer.transferred_bytes.max(end);
+    let saved = if Some(transfer.transferred_bytes) == transfer.size_bytes {
+        node_files::finish(state, &mut transfer, "completed", None).await
+    } else {
+        transfer.updated_at_ms = now_unix_ms();
+        state.upsert_node_file_transfer(&transfer).await
+    };
+    if let Err(error) = saved {
+        warn!("failed to record progress of file transfer {transfer_id}: {error}");
+    }
+}
+
+/// Fails the transfer and answers with the reason.
+async fn fail(state: &SharedState, transfer: &mut NodeFileTransfer, error: &str) -> Response {
+    if let Err(save_error) =
+        node_files::finish(state, transfer, "failed", Some(error.to_owned())).await
+    {
+        warn!(
+            "failed to record failure of file transfer {}: {save_error}",
+            transfer.transfer_id
+        );
+    }
+    error_response(StatusCode::CONFLICT, "INVALID_REQUEST", error)
+}
+
+async fn save(state: &SharedState, transfer: &NodeFileTransfer) -> Result<(), Response> {
+    state
+        .upsert_node_file_transfer(transfer)
+        .await
+        .map_err(|error| {
+            error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "UNAVAILABLE",
+                error.to_string(),
+            )
+        })
+}
+
+fn progress_response(transfer: &NodeFileTransfer) -> Response {
+    Json(json!({
+        "ok": true,
+        "transferId": transfer.transfer_id,
+        "status": transfer.status,
+        "receivedBytes": transfer.transferred_bytes,
+        "sizeBytes": transfer.size_bytes,
+        "sha256": transfer.sha256,
+    }))
+    .into_response()
+}
+
+/// Parses a single `bytes=` range into a half-open span of a `size`-byte file.
+fn parse_range(value: &str, size: u64) -> Option<(u64, u64)> {
+    let (start, end) = value.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => (size.saturating_sub(suffix.parse().ok()?), size),
+        (start, "") => (start.parse().ok()?, size),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.saturating_add(1).min(size),
+        ),
+    };
+    (start < end).then_some((start, end))
+}
+
+/// Parses `bytes <start>-<end>/<total>` into a half-open span and the total, or
+/// `bytes */<total>` into no span.
+fn parse_content_range(value: &str) -> Option<(Option<(u64, u64)>, u64)> {
+    let (span, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let total = total.trim().parse().ok()?;
+    if span.trim() == "*" {
+        return Some((None, total));
+    }
+    let (start, end) = span.split_once('-')?;
+    let start: u64 = start.trim().parse().ok()?;
+    let end = end.trim().parse::<u64>().ok()?.checked_add(1)?;
+    (start < end).then_some((Some((start, end)), total))
+}
+
+fn insert_header(response: &mut Response, name: header::HeaderName, value: String) {
+    if let Ok(value) = HeaderValue::from_str(&value) {
+        response.headers_mut().insert(name, value);
+    }
+}
+
+fn error_response(status: StatusCode, code: &str, message: impl Into<String>) -> Response {
+    (
+        status,
+        Json(json!({
+            "ok": false,
+            "error": {
+                "code": code,
+                "message": message.into(),
+            },
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_content_range, parse_range};
+
+    #[test]
+    fn ranges_resolve_to_half_open_spans() {
+        assert_eq!(parse_range("bytes=0-", 10), Some((0, 10)));
+        assert_eq!(parse_range("bytes=4-6", 10), Some((4, 7)));
+        assert_eq!(parse_range("bytes=4-99", 10), Some((4, 10)));
+        assert_eq!(parse_range("bytes=-3", 10), Some((7, 10)));
+        assert_eq!(parse_range("bytes=10-", 10), None);
+        assert_eq!(parse_range("items=0-1", 10), None);
+    }
+
+    #[test]
+    fn content_ranges_carry_a_span_or_only_the_total() {
+        assert_eq!(
+            parse_content_range("bytes 0-99/1000"),
+            Some((Some((0, 100)), 1000))
+        );
+        assert_eq!(parse_content_range("bytes */1000"), Some((None, 1000)));
+        assert_eq!(parse_content_range("bytes 5-4/10"), None);
+        assert_eq!(parse_content_range("bytes 0-1"), None);
+    }
+}
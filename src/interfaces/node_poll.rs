@@ -0,0 +1,222 @@
+use std::net::SocketAddr;
+
+use axum::{
+    Json,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    application::state::{SharedState, queued_node_invoke_payload},
+    rpc::methods::device,
+    security::{
+        auth::{AuthFailureReason, auth_failure_error, authorize},
+        handshake_challenge,
+    },
+};
+
+use super::compat::auth_from_headers;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PollRequest {
+    /// Acknowledges this journaled event and every earlier one.
+    #[serde(default)]
+    ack_seq: Option<u64>,
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    platform: Option<String>,
+    /// Outcomes of invokes delivered by earlier polls, as `node.invoke.result` takes them.
+    #[serde(default)]
+    results: Vec<PollInvokeResult>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PollInvokeResult {
+    request_id: String,
+    status: String,
+    #[serde(default)]
+    payload: Option<Value>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// `POST /nodes/{id}/poll`: heartbeat for nodes that cannot hold a WebSocket open. Returns the
+/// invokes queued for the node and the journaled events it has not acknowledged.
+pub async fn poll_handler(
+    State(state): State<SharedState>,
+    Path(node_id): Path<String>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    request: Option<Json<PollRequest>>,
+) -> Response {
+    let node_id = node_id.trim().to_owned();
+    if node_id.is_empty() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "INVALID_REQUEST",
+            "node id is required",
+        );
+    }
+    if let Err(response) = authorize_node(&state, &headers, remote_addr, &node_id).await {
+        return response;
+    }
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+
+    let mut results = Vec::with_capacity(request.results.len());
+    for result in request.results {
+        let owned = matches!(
+            state.get_node_invoke(&result.request_id).await,
+            Ok(Some(invoke)) if invoke.node_id == node_id
+        );
+        let outcome = if owned {
+            state
+                .update_node_invoke_result(
+                    &result.request_id,
+                    result.status,
+                    result.payload,
+                    result.error,
+                )
+                .await
+                .map_err(|error| error.to_string())
+        } else {
+            Err(format!("unknown invoke for node {node_id}"))
+        };
+        results.push(match outcome {
+            Ok(_) => json!({ "requestId": result.request_id, "ok": true }),
+            Err(error) => json!({ "requestId": result.request_id, "ok": false, "error": error }),
+        });
+    }
+
+    let polled = async {
+        let node = state
+            .record_node_poll(&node_id, request.display_name, request.platform)
+            .await?;
+        let invokes = state.take_queued_node_invokes(&node_id).await?;
+        let (acked_seq, events) = state.poll_node_events(&node_id, request.ack_seq).await?;
+        Ok::<_, crate::domain::error::DomainError>((node, invokes, acked_seq, events))
+    };
+    match polled.await {
+        Ok((node, invokes, acked_seq, events)) => Json(json!({
+            "ok": true,
+            "nodeId": node_id,
+            "status": node.status,
+            "invokes": invokes.iter().map(queued_node_invoke_payload).collect::<Vec<_>>(),
+            "events": events,
+            "ackedSeq": acked_seq,
+            "results": results,
+            "pollIntervalMs": state.config().tick_interval_ms,
+        }))
+        .into_response(),
+        Err(error) => error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "UNAVAILABLE",
+            error.to_string(),
+        ),
+    }
+}
+
+/// Applies the WebSocket handshake's checks: the auth failure limiter, the handshake challenge
+/// (which polling cannot answer, so a verified credential is required while it is on), and either the
+/// gateway credential or a node device token paired as `node_id`.
+async fn authorize_node(
+    state: &SharedState,
+    headers: &HeaderMap,
+    remote_addr: SocketAddr,
+    node_id: &str,
+) -> Result<(), Response> {
+    let limiter_key = format!("{}:{node_id}", remote_addr.ip());
+    let limiter = state.auth_rate_limiter();
+    let decision = limiter.check(&limiter_key).await;
+    if !decision.allowed {
+        return Err(rate_limited(decision.retry_after_ms));
+    }
+
+    let auth = auth_from_headers(headers);
+    let token = auth.as_ref().and_then(|auth| auth.token.as_deref());
+    let mut device_verified = false;
+    let authorized = match token {
+        Some(token) if token.starts_with(device::ACCESS_TOKEN_PREFIX) => {
+            let grant = device::authenticate_device_token(state, token, Some("node"))
+                .await
+                .map_err(|error| {
+                    error_response(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "UNAVAILABLE",
+                        error.message,
+                    )
+                })?;
+            match grant {
+                Some(grant) if grant.device_id == node_id => {
+                    device_verified = true;
+                    Ok(())
+                }
+                Some(_) => {
+                    return Err(error_response(
+                        StatusCode::FORBIDDEN,
+                        "UNAUTHORIZED",
+                        format!("device token is not paired as node {node_id}"),
+                    ));
+                }
+                None => Err(AuthFailureReason::InvalidCredentials),
+            }
+        }
+        _ => authorize(&state.config().auth_mode, auth.as_ref()),
+    }
+    .and_then(|()| {
+        if state.config().handshake_challenge.is_some()
+            && !handshake_challenge::credential_verified(&state.config().auth_mode, device_verified)
+        {
+            Err(AuthFailureReason::MissingCredentials)
+        } else {
+            Ok(())
+        }
+    });
+
+    if let Err(reason) = authorized {
+        let record = limiter.record_failure(&limiter_key).await;
+        if !record.allowed {
+            return Err(rate_limited(record.retry_after_ms));
+        }
+        return Err(error_response(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            auth_failure_error(reason).message,
+        ));
+    }
+    limiter.reset(&limiter_key).await;
+    Ok(())
+}
+
+fn rate_limited(retry_after_ms: u64) -> Response {
+    let mut response = error_response(
+        StatusCode::TOO_MANY_REQUESTS,
+        "UNAVAILABLE",
+        "unauthorized: too many failed attempts",
+    );
+    if let Ok(value) = retry_after_ms.div_ceil(1_000).to_string().parse() {
+        response
+            .headers_mut()
+            .insert(axum::http::header::RETRY_AFTER, value);
+    }
+    response
+}
+
+fn error_response(status: StatusCode, code: &str, message: impl Into<String>) -> Response {
+    (
+        status,
+        Json(json!({
+            "ok": false,
+            "error": {
+                "code": code,
+                "message": message.into(),
+            },
+        })),
+    )
+        .into_response()
+}
@@ -17,7 +17,6 @@ use crate::{
     application::state::SharedState,
     protocol::ERROR_INVALID_REQUEST,
     rpc::{SessionContext, methods, policy},
-    security::auth,
     storage::now_unix_ms,
 };
 
@@ -41,10 +40,16 @@ pub async fn responses_handler(
     headers: HeaderMap,
     payload: Result<Json<Value>, JsonRejection>,
 ) -> Response {
-    if let Err(reason) = authorize_gateway_http(&state, &headers) {
-        let message = auth::auth_failure_error(reason).message;
-        return responses_error(StatusCode::UNAUTHORIZED, &message, "authentication_error");
-    }
+    let principal = match authorize_gateway_http(&state, &headers).await {
+        Ok(principal) => principal,
+        Err(rejection) => {
+            return responses_error(
+                rejection.status(),
+                &rejection.message(),
+                rejection.error_type(),
+            );
+        }
+    };
 
     let Json(raw_payload) = match payload {
         Ok(payload) => payload,
@@ -94,10 +99,13 @@ pub async fn responses_handler(
     let session = SessionContext {
         conn_id: format!("http-openresponses-{}", uuid::Uuid::new_v4()),
         role: "operator".to_owned(),
-        scopes: policy::default_operator_scopes(),
-        client_id: "openresponses-http".to_owned(),
+        scopes: principal.scopes.clone(),
+        client_id: principal.client_id("openresponses-http"),
         client_mode: "openresponses-http".to_owned(),
     };
+    if let Err(error) = policy::authorize_session(&session, "chat.send") {
+        return responses_error(StatusCode::FORBIDDEN, &error.message, "permission_error");
+    }
 
     let rpc_result = methods::chat::handle_send(&state, &session, Some(&params)).await;
     let rpc_payload = match rpc_result {
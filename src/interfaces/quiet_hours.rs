@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use axum::http::HeaderMap;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{
+    application::state::SharedState,
+    domain::{
+        error::DomainError,
+        models::{MessageDelivery, QueuedOutboundMessage},
+    },
+    interfaces::{channel_adapter_common as common, mattermost, rocketchat, teams, telegram},
+    storage::now_unix_ms,
+};
+
+/// Header channel bridges set to deliver a reply immediately, even during quiet hours.
+pub const URGENT_HEADER: &str = "x-reclaw-urgent";
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(15);
+const FLUSH_BATCH_LIMIT: usize = 100;
+const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlushSummary {
+    pub sent: usize,
+    pub failed: usize,
+    pub dropped: usize,
+}
+
+#[must_use]
+pub fn is_urgent(headers: &HeaderMap) -> bool {
+    headers
+        .get(URGENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| matches!(value.trim(), "1" | "true" | "yes"))
+}
+
+/// Queues `payload` when `channel` is inside its quiet-hours window. Returns `true` when the
+/// message was held back and must not be sent now.
+pub(crate) async fn hold_if_quiet(
+    state: &SharedState,
+    channel: &str,
+    payload: &Value,
+    urgent: bool,
+) -> bool {
+    let Some(window) = state.config().quiet_hours.get(channel) else {
+        return false;
+    };
+    let now = now_unix_ms();
+    if urgent || !window.is_quiet_at(now) {
+        return false;
+    }
+
+    let message = QueuedOutboundMessage {
+        id: format!("outbound-{}", uuid::Uuid::new_v4()),
+        channel: channel.to_owned(),
+        payload: payload.clone(),
+        attempts: 0,
+        queued_at_ms: now,
+        release_at_ms: window.next_end_ms(now),
+    };
+    match state.enqueue_outbound_message(&message).await {
+        Ok(()) => true,
+        Err(error) => {
+            // Prefer a delivery during quiet hours over losing the reply.
+            warn!("failed to queue {channel} outbound message for quiet hours: {error}");
+            false
+        }
+    }
+}
+
+/// Delivers queued messages whose quiet-hours window has ended. `force` sends everything queued
+/// regardless of the window.
+pub async fn flush_outbound_queue(
+    state: &SharedState,
+    channel: Option<&str>,
+    force: bool,
+) -> Result<FlushSummary, DomainError> {
+    let now = now_unix_ms();
+    let due_at_ms = if force { u64::MAX } else { now };
+    let messages = state
+        .list_outbound_messages(channel, due_at_ms, FLUSH_BATCH_LIMIT)
+        .await?;
+
+    let mut summary = FlushSummary::default();
+    for message in messages {
+        let still_quiet = state
+            .config()
+            .quiet_hours
+            .get(&message.channel)
+            .is_some_and(|window| window.is_quiet_at(now));
+        if still_quiet && !force {
+            continue;
+        }
+
+        match deliver_payload(state, &message.channel, &message.payload).await {
+            Ok(platform_message_id) => {
+                state.delete_outbound_message(&message.id).await?;
+                common::finish_delivery(
+                    state,
+                    queued_delivery(state, &message).await,
+                    Ok(platform_message_id),
+                )
+                .await;
+                summary.sent += 1;
+            }
+            Err(error) => {
+                let attempts = state.record_outbound_attempt(&message.id).await?;
+                warn!(
+                    "queued {} outbound delivery failed (attempt {attempts}): {error}",
+                    message.channel
+                );
+                if attempts >= MAX_DELIVERY_ATTEMPTS {
+                    state.delete_outbound_message(&message.id).await?;
+                    common::finish_delivery(
+                        state,
+                        queued_delivery(state, &message).await,
+                        Err(&error),
+                    )
+                    .await;
+                    let _ = state
+                        .append_gateway_log(
+                            "warn",
+                            &format!(
+                                "dropped queued {} outbound message {} after {attempts} attempts: {error}",
+                                message.channel, message.id
+                            ),
+                            Some("channels.quietHours"),
+                            None,
+                        )
+                        .await;
+                    summary.dropped += 1;
+                } else {
+                    summary.failed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+pub fn spawn_outbound_flusher(state: SharedState) -> Option<tokio::task::JoinHandle<()>> {
+    if state.config().quiet_hours.is_empty() {
+        return None;
+    }
+
+    info!(
+        "quiet hours configured for {} channel(s)",
+        state.config().quiet_hours.len()
+    );
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if state.is_standby() {
+                continue;
+            }
+            if let Err(error) = flush_outbound_queue(&state, None, false).await {
+                warn!("quiet hours flush failed: {error}");
+            }
+        }
+    }))
+}
+
+async fn queued_delivery(
+    state: &SharedState,
+    message: &QueuedOutboundMessage,
+) -> Option<MessageDelivery> {
+    let delivery_id = message.payload.get("deliveryId").and_then(Value::as_str)?;
+    state.get_message_delivery(delivery_id).await.ok().flatten()
+}
+
+/// Sends a reply payload in the shape each channel queues: `chatId`/`text` for Telegram,
+/// `serviceUrl`/`conversationId`/`text` for Teams, `conversationId`/`text` for Mattermost and
+/// Rocket.Chat, and the relay body for bridged channels.
+pub(crate) async fn deliver_payload(
+    state: &SharedState,
+    channel: &str,
+    payload: &Value,
+) -> Result<Option<String>, String> {
+    if channel == "telegram" {
+        let bot_token = state
+            .config()
+            .telegram_bot_token
+            .as_deref()
+            .ok_or_else(|| "telegram bot token is not configured".to_owned())?;
+        let chat_id = payload
+            .get("chatId")
+            .and_then(Value::as_i64)
+            .ok_or_else(|| "telegram reply has no chatId".to_owned())?;
+        let text = payload
+            .get("text")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let disable_link_preview = payload
+            .get("disableLinkPreview")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        return telegram::send_telegram_message(
+            state,
+            bot_token,
+            chat_id,
+            text,
+            payload.get("parseMode").and_then(Value::as_str),
+            disable_link_preview,
+        )
+        .await;
+    }
+    if channel == "teams" {
+        return teams::send_teams_reply(state, payload).await;
+    }
+    if channel == "mattermost" {
+        return mattermost::send_mattermost_reply(state, payload).await;
+    }
+    if channel == "rocketchat" {
+        return rocketchat::send_rocketchat_reply(state, payload).await;
+    }
+
+    let (url, token) = common::outbound_relay_target(state.config(), channel)
+        .ok_or_else(|| format!("{channel} outbound relay is not configured"))?;
+    let body = common::post_json(url, token, payload).await?;
+    Ok(common::platform_message_id(&body))
+}
@@ -0,0 +1,218 @@
+use axum::{
+    Json,
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::stream;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::AsyncReadExt;
+
+use crate::application::{replication, state::SharedState};
+
+const SNAPSHOT_CHUNK_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeartbeatRequest {
+    #[serde(default)]
+    standby_url: Option<String>,
+    #[serde(default)]
+    applied_seq: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangesQuery {
+    #[serde(default)]
+    after_seq: u64,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FenceRequest {
+    epoch: u64,
+    #[serde(default)]
+    primary_url: Option<String>,
+}
+
+/// `POST /replication/heartbeat`: a standby checking that this primary is alive.
+pub async fn heartbeat_handler(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    request: Option<Json<HeartbeatRequest>>,
+) -> Response {
+    if let Some(response) = reject_unauthorized(&state, &headers) {
+        return response;
+    }
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let mut payload =
+        replication::accept_heartbeat(&state, request.standby_url, request.applied_seq).await;
+    payload["ok"] = json!(true);
+    Json(payload).into_response()
+}
+
+/// `GET /replication/changes?afterSeq=`: the next page of this primary's change log, or `410`
+/// once the requested changes were pruned and a snapshot is needed.
+pub async fn changes_handler(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<ChangesQuery>,
+) -> Response {
+    if let Some(response) = reject_unauthorized(&state, &headers) {
+        return response;
+    }
+    if let Some(response) = reject_standby(&state) {
+        return response;
+    }
+    let limit = query.limit.unwrap_or(replication::CHANGES_PAGE_LIMIT);
+    match replication::changes_page(&state, query.after_seq, limit).await {
+        Ok(Some(page)) => Json(page).into_response(),
+        Ok(None) => error_response(
+            StatusCode::GONE,
+            "UNAVAILABLE",
+            "changes were pruned; load a snapshot",
+        ),
+        Err(error) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "UNAVAILABLE",
+            error.to_string(),
+        ),
+    }
+}
+
+/// `GET /replication/snapshot`: a consistent copy of this primary's database, streamed from a
+/// staging file that is removed once the download ends.
+pub async fn snapshot_handler(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    if let Some(response) = reject_unauthorized(&state, &headers) {
+        return response;
+    }
+    if let Some(response) = reject_standby(&state) {
+        return response;
+    }
+    let opened = async {
+        let staging = replication::snapshot_file(&state)
+            .await
+            .map_err(|error| error.to_string())?;
+        let file = tokio::fs::File::open(staging.path())
+            .await
+            .map_err(|error| format!("failed to open snapshot: {error}"))?;
+        let size = file
+            .metadata()
+            .await
+            .map_err(|error| format!("failed to read snapshot: {error}"))?
+            .len();
+        Ok::<_, String>((staging, file, size))
+    }
+    .await;
+    let (staging, file, size) = match opened {
+        Ok(opened) => opened,
+        Err(error) => {
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "UNAVAILABLE", error);
+        }
+    };
+    let body = stream::unfold(Some((file, staging)), |pending| async move {
+        let (mut file, staging) = pending?;
+        let mut buffer = vec![0_u8; SNAPSHOT_CHUNK_BYTES];
+        match file.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(read) => {
+                buffer.truncate(read);
+                Some((Ok(Bytes::from(buffer)), Some((file, staging))))
+            }
+            Err(error) => Some((Err(error), None)),
+        }
+    });
+    (
+        [
+            (header::CONTENT_TYPE, "application/vnd.sqlite3".to_owned()),
+            (header::CONTENT_LENGTH, size.to_string()),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// `GET /replication/epoch`: this instance's role and promotion epoch.
+pub async fn epoch_handler(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    if let Some(response) = reject_unauthorized(&state, &headers) {
+        return response;
+    }
+    let mut payload = replication::epoch_report(&state).await;
+    payload["ok"] = json!(true);
+    Json(payload).into_response()
+}
+
+/// `POST /replication/fence`: a promoted standby with a newer epoch retiring this primary.
+pub async fn fence_handler(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(request): Json<FenceRequest>,
+) -> Response {
+    if let Some(response) = reject_unauthorized(&state, &headers) {
+        return response;
+    }
+    match replication::accept_fence(&state, request.epoch, request.primary_url.as_deref()).await {
+        Ok(mut payload) => {
+            payload["ok"] = json!(true);
+            Json(payload).into_response()
+        }
+        Err(error) => error_response(StatusCode::CONFLICT, "INVALID_REQUEST", error),
+    }
+}
+
+fn reject_standby(state: &SharedState) -> Option<Response> {
+    state.is_standby().then(|| {
+        error_response(
+            StatusCode::CONFLICT,
+            "UNAVAILABLE",
+            "this instance is a standby",
+        )
+    })
+}
+
+/// The error response for a request without the replication token, if any.
+fn reject_unauthorized(state: &SharedState, headers: &HeaderMap) -> Option<Response> {
+    let Some(replication) = state.config().replication.as_ref() else {
+        return Some(error_response(
+            StatusCode::NOT_FOUND,
+            "UNAVAILABLE",
+            "replication is disabled",
+        ));
+    };
+    (!replication.accepts_token(bearer_token(headers))).then(|| {
+        error_response(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "invalid replication token",
+        )
+    })
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+fn error_response(status: StatusCode, code: &str, message: impl Into<String>) -> Response {
+    (
+        status,
+        Json(json!({
+            "ok": false,
+            "error": {
+                "code": code,
+                "message": message.into(),
+            },
+        })),
+    )
+        .into_response()
+}
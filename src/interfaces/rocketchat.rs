@@ -0,0 +1,141 @@
+use axum::http::{HeaderMap, StatusCode};
+use serde_json::{Value, json};
+
+use crate::application::state::SharedState;
+
+use super::{channel_adapter_common as common, quiet_hours, webhooks::WebhookFuture};
+
+const ROCKETCHAT_EVENTS_PREFIX: &str = "runtime/rocketchat/event/";
+
+/// Handles a Rocket.Chat outgoing webhook. Replies are posted through the configured incoming
+/// webhook into the originating room.
+pub(crate) fn dispatch_webhook<'a>(
+    state: &'a SharedState,
+    headers: &'a HeaderMap,
+    payload: Value,
+) -> WebhookFuture<'a> {
+    Box::pin(async move {
+        if let Err(error) = common::require_channel_body_token(
+            &payload,
+            &state.config().rocketchat_webhook_token,
+            "rocketchat",
+        ) {
+            return error;
+        }
+
+        // Messages posted by bots, including our own incoming-webhook replies, carry `bot`.
+        if payload
+            .get("bot")
+            .is_some_and(|bot| !bot.is_null() && bot != &Value::Bool(false))
+        {
+            return common::accepted_false("bot-message");
+        }
+        let Some(conversation_id) = read_str(&payload, "channel_id") else {
+            return common::accepted_false("no-channel");
+        };
+        let Some(text) = read_str(&payload, "text") else {
+            return common::accepted_false("no-text");
+        };
+        let Some(message_id) = read_str(&payload, "message_id") else {
+            return common::accepted_false("no-message-id");
+        };
+
+        let dedupe_key = format!("{ROCKETCHAT_EVENTS_PREFIX}{message_id}");
+        if common::is_duplicate_event(state, &dedupe_key).await {
+            return (
+                StatusCode::OK,
+                axum::Json(json!({
+                    "ok": true,
+                    "accepted": false,
+                    "duplicate": true,
+                })),
+            );
+        }
+
+        let result = match common::ingest_channel_message(
+            state,
+            common::ChannelInboundEvent {
+                channel: "rocketchat",
+                conversation_id: conversation_id.to_owned(),
+                text: text.to_owned(),
+                sender_id: read_str(&payload, "user_id").map(str::to_owned),
+                message_id: Some(message_id.to_owned()),
+                idempotency_key: format!("rocketchat-{message_id}"),
+                metadata: Some(json!({
+                    "source": "rocketchat",
+                    "siteUrl": read_str(&payload, "siteUrl"),
+                    "conversationTitle": read_str(&payload, "channel_name"),
+                    "senderName": read_str(&payload, "user_name"),
+                })),
+            },
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(error) => return error,
+        };
+        common::mark_event_processed(state, &dedupe_key, "rocketchat", message_id, &result).await;
+
+        let mut outbound = common::OutboundOutcome::Skipped;
+        if state.config().rocketchat_incoming_webhook_url.is_some() {
+            outbound = common::send_reply_parts(
+                state,
+                common::DirectReplyDispatch {
+                    channel: "rocketchat",
+                    conversation_id,
+                    session_key: &result.session_key,
+                    run_id: result.run_id.as_deref(),
+                    log_scope: "channels.rocketchat.webhook",
+                    urgent: quiet_hours::is_urgent(headers),
+                },
+                &result.reply_parts,
+                |text, delivery_id| {
+                    json!({
+                        "conversationId": conversation_id,
+                        "text": text,
+                        "deliveryId": delivery_id,
+                    })
+                },
+            )
+            .await;
+        }
+
+        common::accepted_true_with_outbound(&result, outbound)
+    })
+}
+
+/// Posts a reply through the incoming webhook, addressing the room by id.
+pub(crate) async fn send_rocketchat_reply(
+    state: &SharedState,
+    payload: &Value,
+) -> Result<Option<String>, String> {
+    let url = state
+        .config()
+        .rocketchat_incoming_webhook_url
+        .as_deref()
+        .ok_or_else(|| "rocketchat incoming webhook url is not configured".to_owned())?;
+    let conversation_id = read_str(payload, "conversationId")
+        .ok_or_else(|| "rocketchat reply has no conversationId".to_owned())?;
+    let body = common::post_json(
+        url,
+        None,
+        &json!({
+            "channel": conversation_id,
+            "text": read_str(payload, "text").unwrap_or_default(),
+        }),
+    )
+    .await?;
+    Ok(body
+        .get("message")
+        .and_then(|message| message.get("_id"))
+        .and_then(Value::as_str)
+        .map(str::to_owned))
+}
+
+fn read_str<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
@@ -0,0 +1,258 @@
+use std::{io::Write, net::SocketAddr, path::Path};
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{ConnectInfo, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    application::state::SharedState,
+    rpc::{dispatcher::map_domain_error, methods::agents},
+    storage::now_unix_ms,
+};
+
+pub(crate) const SETUP_STATE_KEY: &str = "runtime/setup/state";
+const DEFAULT_AGENT_ID: &str = "main";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SetupRequest {
+    agent_name: Option<String>,
+    hooks_enabled: Option<bool>,
+}
+
+pub async fn status_handler(
+    State(state): State<SharedState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+) -> (StatusCode, Json<Value>) {
+    if let Err(response) = ensure_available(&state, remote_addr).await {
+        return response;
+    }
+
+    let config_path = state.config().config_path.clone();
+    (
+        StatusCode::OK,
+        Json(json!({
+            "ok": true,
+            "available": true,
+            "configPath": config_path.as_deref().map(|path| path.display().to_string()),
+            "configExists": config_path.as_deref().is_some_and(Path::exists),
+            "steps": ["gatewayToken", "hooksToken", "agent", "writeConfig"],
+        })),
+    )
+}
+
+pub async fn complete_handler(
+    State(state): State<SharedState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    body: Bytes,
+) -> (StatusCode, Json<Value>) {
+    if let Err(response) = ensure_available(&state, remote_addr).await {
+        return response;
+    }
+
+    let request = if body.is_empty() {
+        SetupRequest::default()
+    } else {
+        match serde_json::from_slice::<SetupRequest>(&body) {
+            Ok(request) => request,
+            Err(error) => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    "INVALID_REQUEST",
+                    format!("invalid setup payload: {error}"),
+                );
+            }
+        }
+    };
+
+    let Some(config_path) = state.config().config_path.clone() else {
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "UNAVAILABLE",
+            "unable to resolve a config file path; pass --config",
+        );
+    };
+    if config_path.exists() {
+        return error_response(
+            StatusCode::CONFLICT,
+            "CONFLICT",
+            format!("config already exists at {}", config_path.display()),
+        );
+    }
+
+    let agent_id = match request
+        .agent_name
+        .and_then(trim_non_empty)
+        .filter(|name| !name.eq_ignore_ascii_case(DEFAULT_AGENT_ID))
+    {
+        Some(name) => match agents::handle_create(&state, Some(&json!({ "name": name }))).await {
+            Ok(created) => created
+                .get("agentId")
+                .and_then(Value::as_str)
+                .unwrap_or(DEFAULT_AGENT_ID)
+                .to_owned(),
+            Err(error) => {
+                return error_response(StatusCode::BAD_REQUEST, &error.code, error.message);
+            }
+        },
+        None => DEFAULT_AGENT_ID.to_owned(),
+    };
+
+    let gateway_token = generate_token("gwt");
+    let hooks_token = generate_token("hkt");
+    let hooks_enabled = request.hooks_enabled.unwrap_or(true);
+    let content = setup_config_template(
+        &state.config().db_path,
+        &gateway_token,
+        &hooks_token,
+        hooks_enabled,
+        &agent_id,
+    );
+    if let Err(error) = write_config_file(&config_path, &content) {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "UNAVAILABLE", error);
+    }
+
+    let marker = json!({
+        "completedAtMs": now_unix_ms(),
+        "configPath": config_path.display().to_string(),
+        "agentId": agent_id,
+    });
+    if let Err(error) = state
+        .set_config_entry_value(SETUP_STATE_KEY, &marker)
+        .await
+        .map_err(map_domain_error)
+    {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, &error.code, error.message);
+    }
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "ok": true,
+            "configPath": config_path.display().to_string(),
+            "gatewayToken": gateway_token,
+            "hooksToken": hooks_token,
+            "hooksEnabled": hooks_enabled,
+            "agentId": agent_id,
+            "restartRequired": true,
+        })),
+    )
+}
+
+async fn ensure_available(
+    state: &SharedState,
+    remote_addr: SocketAddr,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    if !remote_addr.ip().is_loopback() {
+        return Err(error_response(
+            StatusCode::FORBIDDEN,
+            "FORBIDDEN",
+            "setup is only available from localhost",
+        ));
+    }
+
+    match state.get_config_entry_value(SETUP_STATE_KEY).await {
+        Ok(None) => Ok(()),
+        Ok(Some(_)) => Err(error_response(
+            StatusCode::GONE,
+            "GONE",
+            "setup has already been completed",
+        )),
+        Err(error) => Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "UNAVAILABLE",
+            error.to_string(),
+        )),
+    }
+}
+
+pub(crate) fn generate_token(prefix: &str) -> String {
+    format!(
+        "{prefix}_{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+fn setup_config_template(
+    db_path: &Path,
+    gateway_token: &str,
+    hooks_token: &str,
+    hooks_enabled: bool,
+    agent_id: &str,
+) -> String {
+    format!(
+        "# Reclaw Core static runtime configuration\n\
+# Generated by the /setup bootstrap flow.\n\
+\n\
+host = \"127.0.0.1\"\n\
+port = 18789\n\
+cronEnabled = true\n\
+logFilter = \"info\"\n\
+jsonLogs = false\n\
+dbPath = {}\n\
+\n\
+gatewayToken = {}\n\
+\n\
+hooksEnabled = {hooks_enabled}\n\
+hooksToken = {}\n\
+hooksDefaultAgentId = {}\n",
+        toml_string(&db_path.display().to_string()),
+        toml_string(gateway_token),
+        toml_string(hooks_token),
+        toml_string(agent_id),
+    )
+}
+
+pub(crate) fn toml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_owned())
+}
+
+pub(crate) fn write_config_file(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|error| format!("failed to create {}: {error}", parent.display()))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    // The file holds the gateway and hooks tokens, so only the owner may read it.
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .map_err(|error| format!("failed to create {}: {error}", path.display()))?;
+    file.write_all(content.as_bytes())
+        .map_err(|error| format!("failed to write {}: {error}", path.display()))
+}
+
+fn trim_non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
+
+fn error_response(
+    status: StatusCode,
+    code: &str,
+    message: impl Into<String>,
+) -> (StatusCode, Json<Value>) {
+    (
+        status,
+        Json(json!({
+            "ok": false,
+            "error": {
+                "code": code,
+                "message": message.into(),
+            },
+        })),
+    )
+}
@@ -3,7 +3,7 @@ use serde_json::{Value, json};
 
 use crate::application::state::SharedState;
 
-use super::{channel_adapter_common as common, webhooks::WebhookFuture};
+use super::{channel_adapter_common as common, quiet_hours, webhooks::WebhookFuture};
 
 const SIGNAL_EVENTS_PREFIX: &str = "runtime/signal/event/";
 
@@ -89,7 +89,7 @@ pub(crate) fn dispatch_webhook<'a>(
         };
 
         common::mark_event_processed(state, &dedupe_key, "signal", &timestamp, &result).await;
-        let outbound_sent = common::maybe_dispatch_outbound_reply(
+        let outbound = common::maybe_dispatch_outbound_reply(
             state,
             state.config().signal_outbound_url.as_deref(),
             state.config().signal_outbound_token.as_deref(),
@@ -98,17 +98,19 @@ pub(crate) fn dispatch_webhook<'a>(
                 conversation_id: &outbound_conversation_id,
                 source_sender_id: Some(outbound_conversation_id.as_str()),
                 source_message_id: Some(timestamp.as_str()),
-                reply: result.reply.as_deref(),
+                replies: &result.reply_parts,
+                suppress_unfurl: result.suppress_unfurl,
                 session_key: &result.session_key,
                 run_id: result.run_id.as_deref(),
                 metadata: Some(json!({
                     "source": "signal",
                 })),
                 log_scope: "channels.signal.webhook",
+                urgent: quiet_hours::is_urgent(headers),
             },
         )
         .await;
 
-        common::accepted_true_with_outbound(&result, outbound_sent)
+        common::accepted_true_with_outbound(&result, outbound)
     })
 }
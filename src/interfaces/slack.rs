@@ -4,7 +4,7 @@ use serde_json::{Value, json};
 
 use crate::application::state::SharedState;
 
-use super::{channel_adapter_common as common, webhooks::WebhookFuture};
+use super::{channel_adapter_common as common, quiet_hours, webhooks::WebhookFuture};
 
 const SLACK_EVENTS_PREFIX: &str = "runtime/slack/event/";
 
@@ -126,7 +126,7 @@ pub(crate) fn dispatch_webhook<'a>(
         };
 
         common::mark_event_processed(state, &dedupe_key, "slack", &dedupe_id, &result).await;
-        let outbound_sent = common::maybe_dispatch_outbound_reply(
+        let outbound = common::maybe_dispatch_outbound_reply(
             state,
             state.config().slack_outbound_url.as_deref(),
             state.config().slack_outbound_token.as_deref(),
@@ -135,7 +135,8 @@ pub(crate) fn dispatch_webhook<'a>(
                 conversation_id: &outbound_conversation_id,
                 source_sender_id: None,
                 source_message_id: Some(dedupe_id.as_str()),
-                reply: result.reply.as_deref(),
+                replies: &result.reply_parts,
+                suppress_unfurl: result.suppress_unfurl,
                 session_key: &result.session_key,
                 run_id: result.run_id.as_deref(),
                 metadata: Some(json!({
@@ -143,10 +144,11 @@ pub(crate) fn dispatch_webhook<'a>(
                     "eventId": dedupe_id,
                 })),
                 log_scope: "channels.slack.webhook",
+                urgent: quiet_hours::is_urgent(headers),
             },
         )
         .await;
 
-        common::accepted_true_with_outbound(&result, outbound_sent)
+        common::accepted_true_with_outbound(&result, outbound)
     })
 }
@@ -0,0 +1,453 @@
+use std::time::Duration;
+
+use axum::{
+    Json,
+    http::{HeaderMap, StatusCode, header},
+};
+use serde_json::{Value, json};
+use tracing::warn;
+
+use crate::{
+    application::state::SharedState,
+    encoding::percent_encode,
+    interfaces::{channel_adapter_common as common, quiet_hours},
+    security::jwt::{self, JwtError, RsaJwk},
+    storage::now_unix_ms,
+};
+
+use super::webhooks::WebhookFuture;
+
+const TEAMS_ACTIVITIES_PREFIX: &str = "runtime/teams/activity/";
+const TEAMS_JWKS_KEY: &str = "runtime/teams/jwks";
+const TEAMS_ACCESS_TOKEN_KEY: &str = "runtime/teams/access-token";
+const BOT_FRAMEWORK_ISSUER: &str = "https://api.botframework.com";
+const BOT_FRAMEWORK_SCOPE: &str = "https://api.botframework.com/.default";
+/// Signing keys rotate rarely; an unknown `kid` forces an earlier refresh.
+const JWKS_TTL_MS: u64 = 24 * 60 * 60 * 1_000;
+/// Forced refreshes wait this long after the last fetch, so tokens naming made-up `kid`s cannot
+/// make every request refetch the keys.
+const JWKS_MIN_REFRESH_MS: u64 = 5 * 60 * 1_000;
+const CLOCK_SKEW_SECS: u64 = 5 * 60;
+/// Connector tokens are refreshed this long before they expire.
+const ACCESS_TOKEN_MARGIN_MS: u64 = 5 * 60 * 1_000;
+
+pub(crate) fn dispatch_webhook<'a>(
+    state: &'a SharedState,
+    headers: &'a HeaderMap,
+    payload: Value,
+) -> WebhookFuture<'a> {
+    Box::pin(async move {
+        let Some(app_id) = state.config().teams_app_id.as_deref() else {
+            return common::unavailable("teams app id is not configured");
+        };
+        let Some(token) = bearer_token(headers) else {
+            return common::unauthorized("missing bot framework token");
+        };
+        let service_url = read_str(&payload, &["serviceUrl"]).unwrap_or_default();
+        if let Err(error) = authenticate(state, token, app_id, service_url).await {
+            warn!("rejected teams activity: {error}");
+            return common::unauthorized(format!("invalid bot framework token: {error}"));
+        }
+
+        let tenant_id = read_str(&payload, &["channelData", "tenant", "id"])
+            .or_else(|| read_str(&payload, &["conversation", "tenantId"]));
+        if !tenant_allowed(&state.config().teams_tenant_allowlist, tenant_id) {
+            return common::forbidden("teams tenant is not allowed");
+        }
+
+        if read_str(&payload, &["type"]) != Some("message") {
+            return common::accepted_false("unsupported-activity");
+        }
+        let Some(conversation_id) = read_str(&payload, &["conversation", "id"]) else {
+            return common::accepted_false("no-conversation");
+        };
+        let text = strip_mentions(read_str(&payload, &["text"]).unwrap_or_default());
+        if text.is_empty() {
+            return common::accepted_false("no-text");
+        }
+        let Some(activity_id) = read_str(&payload, &["id"]) else {
+            return common::accepted_false("no-activity-id");
+        };
+        if service_url.is_empty() {
+            return common::bad_request("teams activity has no serviceUrl");
+        }
+
+        let dedupe_key = format!("{TEAMS_ACTIVITIES_PREFIX}{activity_id}");
+        if common::is_duplicate_event(state, &dedupe_key).await {
+            return (
+                StatusCode::OK,
+                Json(json!({
+                    "ok": true,
+                    "accepted": false,
+                    "duplicate": true,
+                })),
+            );
+        }
+
+        let result = match common::ingest_channel_message(
+            state,
+            common::ChannelInboundEvent {
+                channel: "teams",
+                conversation_id: conversation_id.to_owned(),
+                text,
+                sender_id: read_str(&payload, &["from", "id"]).map(str::to_owned),
+                message_id: Some(activity_id.to_owned()),
+                idempotency_key: format!("teams-{activity_id}"),
+                metadata: Some(json!({
+                    "source": "teams",
+                    "serviceUrl": service_url,
+                    "tenantId": tenant_id,
+                    "conversationTitle": read_str(&payload, &["conversation", "name"]),
+                    "conversationKind": conversation_kind(
+                        read_str(&payload, &["conversation", "conversationType"]),
+                    ),
+                    "senderName": read_str(&payload, &["from", "name"]),
+                })),
+            },
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(error) => return error,
+        };
+        common::mark_event_processed(state, &dedupe_key, "teams", activity_id, &result).await;
+
+        let mut outbound = common::OutboundOutcome::Skipped;
+        if state.config().teams_app_password.is_some() {
+            outbound = common::send_reply_parts(
+                state,
+                common::DirectReplyDispatch {
+                    channel: "teams",
+                    conversation_id,
+                    session_key: &result.session_key,
+                    run_id: result.run_id.as_deref(),
+                    log_scope: "channels.teams.webhook",
+                    urgent: quiet_hours::is_urgent(headers),
+                },
+                &result.reply_parts,
+                |text, delivery_id| {
+                    json!({
+                        "serviceUrl": service_url,
+                        "conversationId": conversation_id,
+                        "replyToId": activity_id,
+                        "text": text,
+                        "deliveryId": delivery_id,
+                    })
+                },
+            )
+            .await;
+        }
+
+        common::accepted_true_with_outbound(&result, outbound)
+    })
+}
+
+/// Posts a reply through the Bot Connector API. `payload` carries `serviceUrl`,
+/// `conversationId`, `text`, and optionally `replyToId`; returns the connector activity id.
+pub(crate) async fn send_teams_reply(
+    state: &SharedState,
+    payload: &Value,
+) -> Result<Option<String>, String> {
+    let service_url = read_str(payload, &["serviceUrl"])
+        .ok_or_else(|| "teams reply has no serviceUrl".to_owned())?
+        .trim_end_matches('/');
+    let conversation_id = read_str(payload, &["conversationId"])
+        .ok_or_else(|| "teams reply has no conversationId".to_owned())?;
+    let reply_to_id = read_str(payload, &["replyToId"]);
+    let conversation = percent_encode(conversation_id);
+    let url = match reply_to_id {
+        Some(reply_to_id) => format!(
+            "{service_url}/v3/conversations/{conversation}/activities/{}",
+            percent_encode(reply_to_id)
+        ),
+        None => format!("{service_url}/v3/conversations/{conversation}/activities"),
+    };
+    let access_token = connector_access_token(state).await?;
+    let body = common::post_json(
+        &url,
+        Some(&access_token),
+        &json!({
+            "type": "message",
+            "text": read_str(payload, &["text"]).unwrap_or_default(),
+            "replyToId": reply_to_id,
+        }),
+    )
+    .await?;
+    Ok(common::platform_message_id(&body))
+}
+
+async fn authenticate(
+    state: &SharedState,
+    token: &str,
+    app_id: &str,
+    service_url: &str,
+) -> Result<(), String> {
+    let keys = signing_keys(state, false).await?;
+    let claims = match jwt::verify_rs256(token, &keys) {
+        Err(JwtError::UnknownKey(_)) => {
+            let keys = signing_keys(state, true).await?;
+            jwt::verify_rs256(token, &keys)
+        }
+        other => other,
+    }
+    .map_err(|error| error.to_string())?;
+    validate_claims(&claims, app_id, service_url, now_unix_ms() / 1_000)
+}
+
+/// Bot Framework rules for channel-issued tokens: issuer, audience (the bot's app id), lifetime
+/// with clock skew, and a `serviceurl` claim that matches the activity.
+fn validate_claims(
+    claims: &Value,
+    app_id: &str,
+    service_url: &str,
+    now_secs: u64,
+) -> Result<(), String> {
+    if claims.get("iss").and_then(Value::as_str) != Some(BOT_FRAMEWORK_ISSUER) {
+        return Err("unexpected issuer".to_owned());
+    }
+    if claims.get("aud").and_then(Value::as_str) != Some(app_id) {
+        return Err("token was not issued for this app".to_owned());
+    }
+    let expires_at = claims
+        .get("exp")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| "token has no expiry".to_owned())?;
+    if expires_at.saturating_add(CLOCK_SKEW_SECS) <= now_secs {
+        return Err("token has expired".to_owned());
+    }
+    if claims
+        .get("nbf")
+        .and_then(Value::as_u64)
+        .is_some_and(|not_before| not_before > now_secs.saturating_add(CLOCK_SKEW_SECS))
+    {
+        return Err("token is not valid yet".to_owned());
+    }
+    let claimed = claims
+        .get("serviceurl")
+        .and_then(Value::as_str)
+        .ok_or_else(|| "token has no serviceurl".to_owned())?;
+    if claimed.trim_end_matches('/') != service_url.trim_end_matches('/') {
+        return Err("token serviceurl does not match the activity".to_owned());
+    }
+    Ok(())
+}
+
+/// Signing keys from the Bot Framework OpenID metadata, cached in the config store. With
+/// `refresh`, keys fetched within `JWKS_MIN_REFRESH_MS` are returned as they are.
+async fn signing_keys(state: &SharedState, refresh: bool) -> Result<Vec<RsaJwk>, String> {
+    let metadata_url = state.config().teams_openid_metadata_url.as_str();
+    let max_age_ms = if refresh {
+        JWKS_MIN_REFRESH_MS
+    } else {
+        JWKS_TTL_MS
+    };
+    if let Ok(Some(cached)) = state.get_config_entry_value(TEAMS_JWKS_KEY).await
+        && cached.get("metadataUrl").and_then(Value::as_str) == Some(metadata_url)
+        && cached
+            .get("fetchedAtMs")
+            .and_then(Value::as_u64)
+            .is_some_and(|fetched| now_unix_ms().saturating_sub(fetched) < max_age_ms)
+        && let Ok(keys) = serde_json::from_value::<Vec<RsaJwk>>(cached["keys"].clone())
+    {
+        return Ok(keys);
+    }
+
+    let client = http_client()?;
+    let metadata = get_json(&client, metadata_url).await?;
+    let jwks_uri = metadata
+        .get("jwks_uri")
+        .and_then(Value::as_str)
+        .ok_or_else(|| "openid metadata has no jwks_uri".to_owned())?;
+    let keys = jwt::parse_jwks(&get_json(&client, jwks_uri).await?);
+    let _ = state
+        .set_config_entry_value(
+            TEAMS_JWKS_KEY,
+            &json!({
+                "metadataUrl": metadata_url,
+                "fetchedAtMs": now_unix_ms(),
+                "keys": keys,
+            }),
+        )
+        .await;
+    Ok(keys)
+}
+
+/// Client-credentials token for the Bot Connector API, cached until shortly before expiry.
+async fn connector_access_token(state: &SharedState) -> Result<String, String> {
+    if let Ok(Some(cached)) = state.get_config_entry_value(TEAMS_ACCESS_TOKEN_KEY).await
+        && let Some(token) = cached.get("accessToken").and_then(Value::as_str)
+        && cached
+            .get("expiresAtMs")
+            .and_then(Value::as_u64)
+            .is_some_and(|expires| expires > now_unix_ms().saturating_add(ACCESS_TOKEN_MARGIN_MS))
+    {
+        return Ok(token.to_owned());
+    }
+
+    let config = state.config();
+    let (Some(app_id), Some(password)) = (&config.teams_app_id, &config.teams_app_password) else {
+        return Err("teams app credentials are not configured".to_owned());
+    };
+    let response = http_client()?
+        .post(&config.teams_token_url)
+        .form(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", app_id.as_str()),
+            ("client_secret", password.as_str()),
+            ("scope", BOT_FRAMEWORK_SCOPE),
+        ])
+        .send()
+        .await
+        .map_err(|error| format!("teams token request failed: {error}"))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "teams token request failed with {}",
+            response.status()
+        ));
+    }
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|error| format!("teams token response decode failed: {error}"))?;
+    let token = body
+        .get("access_token")
+        .and_then(Value::as_str)
+        .ok_or_else(|| "teams token response has no access_token".to_owned())?
+        .to_owned();
+    let expires_in_ms = body
+        .get("expires_in")
+        .and_then(Value::as_u64)
+        .unwrap_or(3_600)
+        .saturating_mul(1_000);
+    let _ = state
+        .set_config_entry_value(
+            TEAMS_ACCESS_TOKEN_KEY,
+            &json!({
+                "accessToken": token,
+                "expiresAtMs": now_unix_ms().saturating_add(expires_in_ms),
+            }),
+        )
+        .await;
+    Ok(token)
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|error| format!("failed to construct http client: {error}"))
+}
+
+async fn get_json(client: &reqwest::Client, url: &str) -> Result<Value, String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|error| format!("request to {url} failed: {error}"))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "request to {url} failed with {}",
+            response.status()
+        ));
+    }
+    response
+        .json::<Value>()
+        .await
+        .map_err(|error| format!("response from {url} is not json: {error}"))
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+fn tenant_allowed(allowlist: &[String], tenant_id: Option<&str>) -> bool {
+    allowlist.is_empty()
+        || tenant_id.is_some_and(|tenant| {
+            allowlist
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(tenant.trim()))
+        })
+}
+
+fn conversation_kind(conversation_type: Option<&str>) -> &'static str {
+    match conversation_type {
+        Some("groupChat") => "group",
+        Some("channel") => "channel",
+        _ => "direct",
+    }
+}
+
+/// Drops `<at>…</at>` mention markup (Teams prefixes channel messages with the bot mention).
+fn strip_mentions(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<at>") {
+        out.push_str(&rest[..start]);
+        match rest[start..].find("</at>") {
+            Some(end) => rest = &rest[start + end + "</at>".len()..],
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    out.push_str(rest);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn read_str<'a>(value: &'a Value, path: &[&str]) -> Option<&'a str> {
+    path.iter()
+        .try_fold(value, |value, key| value.get(key))?
+        .as_str()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{strip_mentions, tenant_allowed, validate_claims};
+
+    #[test]
+    fn bot_framework_claims_mentions_and_tenants_are_checked() {
+        let claims = json!({
+            "iss": "https://api.botframework.com",
+            "aud": "app-1",
+            "exp": 2_000,
+            "nbf": 1_000,
+            "serviceurl": "https://smba.example/emea/",
+        });
+        let service_url = "https://smba.example/emea";
+        assert_eq!(
+            validate_claims(&claims, "app-1", service_url, 1_500),
+            Ok(())
+        );
+        assert!(validate_claims(&claims, "app-2", service_url, 1_500).is_err());
+        assert!(validate_claims(&claims, "app-1", service_url, 2_400).is_err());
+        assert!(validate_claims(&claims, "app-1", "https://evil.example", 1_500).is_err());
+        let mut unbound = claims;
+        unbound
+            .as_object_mut()
+            .expect("claims object")
+            .remove("serviceurl");
+        assert!(validate_claims(&unbound, "app-1", service_url, 1_500).is_err());
+
+        assert_eq!(
+            strip_mentions("<at>Reclaw</at>  deploy <b>now</b>"),
+            "deploy <b>now</b>"
+        );
+        assert_eq!(strip_mentions("hi <at>broken"), "hi <at>broken");
+
+        assert!(tenant_allowed(&[], None));
+        assert!(tenant_allowed(&["tenant-a".to_owned()], Some("TENANT-A")));
+        assert!(!tenant_allowed(&["tenant-a".to_owned()], Some("tenant-b")));
+        assert!(!tenant_allowed(&["tenant-a".to_owned()], None));
+    }
+}
@@ -8,9 +8,11 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use tracing::warn;
 
-use crate::{application::state::SharedState, interfaces::channels};
+use crate::{
+    application::{config::ReplyFormat, state::SharedState},
+    interfaces::{channel_adapter_common as common, channels, quiet_hours},
+};
 
 const TELEGRAM_SECRET_HEADER: &str = "x-telegram-bot-api-secret-token";
 const TELEGRAM_UPDATES_PREFIX: &str = "runtime/telegram/update/";
@@ -44,18 +46,73 @@ pub struct TelegramMessage {
 #[serde(rename_all = "camelCase")]
 pub struct TelegramChat {
     pub id: i64,
+    #[serde(default, rename = "type")]
+    pub chat_type: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default, rename = "first_name", alias = "firstName")]
+    pub first_name: Option<String>,
+    #[serde(default, rename = "last_name", alias = "lastName")]
+    pub last_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TelegramUser {
     pub id: i64,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default, rename = "first_name", alias = "firstName")]
+    pub first_name: Option<String>,
+    #[serde(default, rename = "last_name", alias = "lastName")]
+    pub last_name: Option<String>,
+}
+
+impl TelegramChat {
+    fn display_name(&self) -> Option<String> {
+        self.title
+            .clone()
+            .or_else(|| telegram_person_name(self.first_name.as_deref(), self.last_name.as_deref()))
+            .or_else(|| self.username.as_ref().map(|name| format!("@{name}")))
+    }
+
+    fn directory_kind(&self) -> &'static str {
+        match self.chat_type.as_deref() {
+            Some("group" | "supergroup") => "group",
+            Some("channel") => "channel",
+            _ => "direct",
+        }
+    }
+}
+
+impl TelegramUser {
+    fn display_name(&self) -> Option<String> {
+        telegram_person_name(self.first_name.as_deref(), self.last_name.as_deref())
+            .or_else(|| self.username.as_ref().map(|name| format!("@{name}")))
+    }
+}
+
+fn telegram_person_name(first_name: Option<&str>, last_name: Option<&str>) -> Option<String> {
+    let name = [first_name, last_name]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if name.is_empty() { None } else { Some(name) }
 }
 
 #[derive(Debug, Serialize)]
 struct TelegramSendMessageBody {
     chat_id: i64,
     text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parse_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    link_preview_options: Option<Value>,
 }
 
 pub async fn webhook_handler(
@@ -173,12 +230,16 @@ async fn handle_webhook_update(
         conversation_id: message.chat.id.to_string(),
         text,
         agent_id: Some("main".to_owned()),
-        sender_id: message.from.map(|user| user.id.to_string()),
+        sender_id: message.from.as_ref().map(|user| user.id.to_string()),
         message_id: Some(message.message_id.to_string()),
         idempotency_key: Some(format!("telegram-{}", update.update_id)),
         metadata: Some(json!({
             "updateId": update.update_id,
+            "conversationTitle": message.chat.display_name(),
+            "conversationKind": message.chat.directory_kind(),
+            "senderName": message.from.as_ref().and_then(TelegramUser::display_name),
         })),
+        attachments: Vec::new(),
     };
 
     let result = channels::ingest_inbound_message(state, inbound).await;
@@ -211,22 +272,33 @@ async fn handle_webhook_update(
         )
         .await;
 
-    let mut outbound_sent = false;
-    if let (Some(bot_token), Some(reply)) = (&state.config().telegram_bot_token, &result.reply) {
-        match send_telegram_message(state, bot_token, message.chat.id, reply).await {
-            Ok(()) => outbound_sent = true,
-            Err(error) => {
-                warn!("telegram outbound send failed: {error}");
-                let _ = state
-                    .append_gateway_log(
-                        "warn",
-                        &format!("telegram outbound send failed: {error}"),
-                        Some("channels.telegram.webhook"),
-                        None,
-                    )
-                    .await;
-            }
-        }
+    let mut outbound = common::OutboundOutcome::Skipped;
+    if state.config().telegram_bot_token.is_some() {
+        let chat_id = message.chat.id;
+        let disable_link_preview = result.suppress_unfurl;
+        let parse_mode = (result.reply_format == ReplyFormat::Telegram).then_some("MarkdownV2");
+        outbound = common::send_reply_parts(
+            state,
+            common::DirectReplyDispatch {
+                channel: "telegram",
+                conversation_id: &chat_id.to_string(),
+                session_key: &result.session_key,
+                run_id: result.run_id.as_deref(),
+                log_scope: "channels.telegram.webhook",
+                urgent: quiet_hours::is_urgent(headers),
+            },
+            &result.reply_parts,
+            |text, delivery_id| {
+                json!({
+                    "chatId": chat_id,
+                    "text": text,
+                    "deliveryId": delivery_id,
+                    "disableLinkPreview": disable_link_preview,
+                    "parseMode": parse_mode,
+                })
+            },
+        )
+        .await;
     }
 
     (
@@ -237,22 +309,28 @@ async fn handle_webhook_update(
             "sessionKey": result.session_key,
             "runId": result.run_id,
             "reply": result.reply,
-            "outboundSent": outbound_sent,
+            "outboundSent": outbound == common::OutboundOutcome::Sent,
+            "outboundQueued": outbound == common::OutboundOutcome::Queued,
         })),
     )
 }
 
-async fn send_telegram_message(
+/// Sends `text` via the Bot API and returns the Telegram `message_id` when reported.
+pub(crate) async fn send_telegram_message(
     state: &SharedState,
     bot_token: &str,
     chat_id: i64,
     text: &str,
-) -> Result<(), String> {
+    parse_mode: Option<&str>,
+    disable_link_preview: bool,
+) -> Result<Option<String>, String> {
     let base_url = state.config().telegram_api_base_url.trim_end_matches('/');
     let url = format!("{base_url}/bot{bot_token}/sendMessage");
     let body = TelegramSendMessageBody {
         chat_id,
         text: text.to_owned(),
+        parse_mode: parse_mode.map(str::to_owned),
+        link_preview_options: disable_link_preview.then(|| json!({ "is_disabled": true })),
     };
 
     let client = reqwest::Client::builder()
@@ -282,7 +360,11 @@ async fn send_telegram_message(
         return Err(format!("telegram API returned failure payload: {payload}"));
     }
 
-    Ok(())
+    Ok(payload
+        .get("result")
+        .and_then(|result| result.get("message_id"))
+        .and_then(Value::as_i64)
+        .map(|message_id| message_id.to_string()))
 }
 
 fn valid_telegram_secret(headers: &HeaderMap, expected: &str) -> bool {
@@ -0,0 +1,253 @@
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use tracing::{info, warn};
+
+use crate::{application::state::SharedState, storage::now_unix_ms};
+
+/// Last registration and `getWebhookInfo` result, reported under the telegram channel in
+/// `channels.status`.
+const WEBHOOK_STATUS_KEY: &str = "runtime/telegram/webhook";
+const VERIFY_INTERVAL: Duration = Duration::from_secs(300);
+/// Undelivered updates at or above this count are reported as a backlog.
+const PENDING_BACKLOG_THRESHOLD: u64 = 100;
+const API_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The webhook URL Telegram should deliver to for `public_base_url`.
+#[must_use]
+pub fn webhook_url(public_base_url: &str) -> String {
+    format!(
+        "{}/channels/telegram/webhook",
+        public_base_url.trim_end_matches('/')
+    )
+}
+
+/// Registers the webhook at startup and re-checks it with `getWebhookInfo` every five minutes.
+/// Returns `None` unless `telegramPublicBaseUrl` is configured.
+pub fn spawn_webhook_manager(state: SharedState) -> Option<tokio::task::JoinHandle<()>> {
+    let public_base_url = state.config().telegram_public_base_url.clone()?;
+    let url = webhook_url(&public_base_url);
+    info!("managing telegram webhook registration for {url}");
+
+    Some(tokio::spawn(async move {
+        let mut registered = false;
+        let mut ticker = tokio::time::interval(VERIFY_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if state.is_standby() {
+                continue;
+            }
+            if !registered {
+                match register_webhook(&state, &url).await {
+                    Ok(()) => registered = true,
+                    Err(error) => {
+                        warn!("telegram setWebhook failed: {error}");
+                        record_status(&state, &url, json!({ "registrationError": error })).await;
+                        continue;
+                    }
+                }
+            }
+            if let Err(error) = verify_webhook(&state, &url).await {
+                warn!("telegram getWebhookInfo failed: {error}");
+                record_status(&state, &url, json!({ "verifyError": error })).await;
+            }
+        }
+    }))
+}
+
+/// Calls `deleteWebhook` so Telegram holds updates while the gateway is down. A no-op unless
+/// the registration is managed and this instance is the primary.
+pub async fn unregister_webhook(state: &SharedState) {
+    let Some(public_base_url) = state.config().telegram_public_base_url.clone() else {
+        return;
+    };
+    if state.is_standby() {
+        return;
+    }
+    let url = webhook_url(&public_base_url);
+    match call_bot_api(state, "deleteWebhook", &json!({})).await {
+        Ok(_) => {
+            info!("telegram webhook deleted");
+            record_status(
+                state,
+                &url,
+                json!({ "registered": false, "deletedAtMs": now_unix_ms() }),
+            )
+            .await;
+        }
+        Err(error) => warn!("telegram deleteWebhook failed: {error}"),
+    }
+}
+
+/// The recorded webhook status, when the registration is managed.
+pub async fn webhook_status(state: &SharedState) -> Option<Value> {
+    state.config().telegram_public_base_url.as_ref()?;
+    state
+        .get_config_entry_value(WEBHOOK_STATUS_KEY)
+        .await
+        .ok()
+        .flatten()
+}
+
+async fn register_webhook(state: &SharedState, url: &str) -> Result<(), String> {
+    let secret = state
+        .config()
+        .telegram_webhook_secret
+        .clone()
+        .unwrap_or_default();
+    call_bot_api(
+        state,
+        "setWebhook",
+        &json!({ "url": url, "secret_token": secret }),
+    )
+    .await?;
+    info!("telegram webhook registered at {url}");
+    record_status(
+        state,
+        url,
+        json!({
+            "registered": true,
+            "registeredAtMs": now_unix_ms(),
+            "registrationError": null,
+        }),
+    )
+    .await;
+    Ok(())
+}
+
+async fn verify_webhook(state: &SharedState, url: &str) -> Result<(), String> {
+    let info = call_bot_api(state, "getWebhookInfo", &json!({})).await?;
+    let registered_url = info.get("url").and_then(Value::as_str).unwrap_or_default();
+    let pending_update_count = info
+        .get("pending_update_count")
+        .and_then(Value::as_u64)
+        .unwrap_or(0);
+    let drift = webhook_drift(url, registered_url, pending_update_count);
+    if !drift.is_empty() {
+        warn!(
+            "telegram webhook drift ({}): registered url {registered_url:?}, {pending_update_count} pending update(s)",
+            drift.join(", ")
+        );
+    }
+
+    record_status(
+        state,
+        url,
+        json!({
+            "registeredUrl": registered_url,
+            "pendingUpdateCount": pending_update_count,
+            "lastErrorMessage": info.get("last_error_message").and_then(Value::as_str),
+            "lastErrorDateMs": info
+                .get("last_error_date")
+                .and_then(Value::as_u64)
+                .map(|seconds| seconds * 1_000),
+            "drift": drift,
+            "checkedAtMs": now_unix_ms(),
+            "verifyError": null,
+        }),
+    )
+    .await;
+    Ok(())
+}
+
+fn webhook_drift(
+    expected_url: &str,
+    registered_url: &str,
+    pending_update_count: u64,
+) -> Vec<&'static str> {
+    let mut drift = Vec::new();
+    if registered_url != expected_url {
+        drift.push("urlMismatch");
+    }
+    if pending_update_count >= PENDING_BACKLOG_THRESHOLD {
+        drift.push("pendingBacklog");
+    }
+    drift
+}
+
+/// Merges `fields` into the stored status; `null` fields are removed.
+async fn record_status(state: &SharedState, url: &str, fields: Value) {
+    let mut status = state
+        .get_config_entry_value(WEBHOOK_STATUS_KEY)
+        .await
+        .ok()
+        .flatten()
+        .filter(|status| status.get("url").and_then(Value::as_str) == Some(url))
+        .and_then(|status| match status {
+            Value::Object(map) => Some(map),
+            _ => None,
+        })
+        .unwrap_or_default();
+    status.insert("managed".to_owned(), Value::Bool(true));
+    status.insert("url".to_owned(), Value::String(url.to_owned()));
+    if let Value::Object(fields) = fields {
+        for (key, value) in fields {
+            if value.is_null() {
+                status.remove(&key);
+            } else {
+                status.insert(key, value);
+            }
+        }
+    }
+    let _ = state
+        .set_config_entry_value(WEBHOOK_STATUS_KEY, &Value::Object(status))
+        .await;
+}
+
+/// Calls a Bot API method and returns its `result`.
+async fn call_bot_api(state: &SharedState, method: &str, body: &Value) -> Result<Value, String> {
+    let bot_token = state
+        .config()
+        .telegram_bot_token
+        .as_deref()
+        .ok_or_else(|| "telegram bot token is not configured".to_owned())?;
+    let base_url = state.config().telegram_api_base_url.trim_end_matches('/');
+    let client = reqwest::Client::builder()
+        .timeout(API_TIMEOUT)
+        .build()
+        .map_err(|error| format!("failed to construct http client: {error}"))?;
+
+    let response = client
+        .post(format!("{base_url}/bot{bot_token}/{method}"))
+        .json(body)
+        .send()
+        .await
+        .map_err(|error| format!("telegram request failed: {error}"))?;
+    let status = response.status();
+    let payload = response
+        .json::<Value>()
+        .await
+        .map_err(|error| format!("telegram response decode failed: {error}"))?;
+    if !status.is_success() || !payload.get("ok").and_then(Value::as_bool).unwrap_or(false) {
+        let description = payload
+            .get("description")
+            .and_then(Value::as_str)
+            .unwrap_or("no description");
+        return Err(format!("{method} failed with {status}: {description}"));
+    }
+    Ok(payload.get("result").cloned().unwrap_or(Value::Null))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{webhook_drift, webhook_url};
+
+    #[test]
+    fn webhook_url_joins_the_public_base_url() {
+        assert_eq!(
+            webhook_url("https://bot.example.com/"),
+            "https://bot.example.com/channels/telegram/webhook"
+        );
+    }
+
+    #[test]
+    fn drift_reports_url_mismatch_and_pending_backlog() {
+        let url = "https://bot.example.com/channels/telegram/webhook";
+        assert!(webhook_drift(url, url, 3).is_empty());
+        assert_eq!(webhook_drift(url, "", 0), vec!["urlMismatch"]);
+        assert_eq!(
+            webhook_drift(url, "https://old.example.com/hook", 250),
+            vec!["urlMismatch", "pendingBacklog"]
+        );
+    }
+}
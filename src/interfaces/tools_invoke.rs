@@ -10,8 +10,7 @@ use serde_json::{Value, json};
 use crate::{
     application::state::SharedState,
     protocol::{ERROR_INVALID_REQUEST, ERROR_UNAVAILABLE, RequestFrame},
-    rpc::{SessionContext, dispatcher::dispatch_request, policy},
-    security::auth,
+    rpc::{SessionContext, dispatcher::dispatch_request},
 };
 
 use super::compat::authorize_gateway_http;
@@ -33,15 +32,22 @@ pub async fn invoke_handler(
     headers: HeaderMap,
     payload: Result<Json<Value>, JsonRejection>,
 ) -> Response {
-    if let Err(reason) = authorize_gateway_http(&state, &headers) {
-        let message = auth::auth_failure_error(reason).message;
-        return invoke_error(
-            StatusCode::UNAUTHORIZED,
-            "authentication_error",
-            ERROR_INVALID_REQUEST,
-            &message,
-        );
-    }
+    let principal = match authorize_gateway_http(&state, &headers).await {
+        Ok(principal) => principal,
+        Err(rejection) => {
+            let code = if rejection.status() == StatusCode::UNAUTHORIZED {
+                ERROR_INVALID_REQUEST
+            } else {
+                ERROR_UNAVAILABLE
+            };
+            return invoke_error(
+                rejection.status(),
+                rejection.error_type(),
+                code,
+                &rejection.message(),
+            );
+        }
+    };
 
     let Json(raw_payload) = match payload {
         Ok(payload) => payload,
@@ -134,12 +140,13 @@ pub async fn invoke_handler(
         id: format!("tools-invoke-{}", uuid::Uuid::new_v4()),
         method: method.to_owned(),
         params,
+        timeout_ms: None,
     };
     let session = SessionContext {
         conn_id: format!("http-tools-invoke-{}", uuid::Uuid::new_v4()),
         role: "operator".to_owned(),
-        scopes: policy::default_operator_scopes(),
-        client_id: format!("tools-invoke:{session_key}"),
+        scopes: principal.scopes.clone(),
+        client_id: principal.client_id(&format!("tools-invoke:{session_key}")),
         client_mode: "tools-invoke-http".to_owned(),
     };
 
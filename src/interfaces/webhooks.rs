@@ -10,7 +10,9 @@ use serde_json::{Value, json};
 
 use crate::application::{config::ChannelWebhookPluginConfig, state::SharedState};
 
-use super::{discord, signal, slack, telegram, whatsapp};
+use super::{
+    channel_plugins, discord, mattermost, rocketchat, signal, slack, teams, telegram, whatsapp,
+};
 
 pub type WebhookFuture<'a> = Pin<Box<dyn Future<Output = (StatusCode, Json<Value>)> + Send + 'a>>;
 pub type WebhookDispatchFn = for<'a> fn(&'a SharedState, &'a HeaderMap, Value) -> WebhookFuture<'a>;
@@ -41,6 +43,19 @@ pub const WHATSAPP_ADAPTER: ChannelWebhookAdapter = ChannelWebhookAdapter {
     channel: "whatsapp",
     dispatch: whatsapp::dispatch_webhook,
 };
+pub const TEAMS_ADAPTER: ChannelWebhookAdapter = ChannelWebhookAdapter {
+    channel: "teams",
+    dispatch: teams::dispatch_webhook,
+};
+
+pub const MATTERMOST_ADAPTER: ChannelWebhookAdapter = ChannelWebhookAdapter {
+    channel: "mattermost",
+    dispatch: mattermost::dispatch_webhook,
+};
+pub const ROCKETCHAT_ADAPTER: ChannelWebhookAdapter = ChannelWebhookAdapter {
+    channel: "rocketchat",
+    dispatch: rocketchat::dispatch_webhook,
+};
 
 const CHANNEL_PLUGIN_TOKEN_HEADER: &str = "x-reclaw-plugin-token";
 const CHANNEL_PLUGIN_NAME_HEADER: &str = "x-reclaw-channel";
@@ -78,6 +93,9 @@ pub fn default_registry() -> ChannelWebhookRegistry {
         SLACK_ADAPTER,
         SIGNAL_ADAPTER,
         WHATSAPP_ADAPTER,
+        TEAMS_ADAPTER,
+        MATTERMOST_ADAPTER,
+        ROCKETCHAT_ADAPTER,
     ])
 }
 
@@ -94,6 +112,11 @@ pub async fn channel_webhook_handler(
         return adapter(&state, &headers, payload).await;
     }
 
+    if let Some(plugin) = state.config().channel_plugins.get(&channel_key) {
+        return channel_plugins::dispatch_webhook(&state, &channel_key, plugin, &headers, payload)
+            .await;
+    }
+
     if let Some(plugin) = state.config().channel_webhook_plugins.get(&channel_key) {
         return proxy_channel_webhook(&channel_key, plugin, &headers, payload).await;
     }
@@ -225,8 +248,9 @@ async fn proxy_channel_webhook(
 #[cfg(test)]
 mod tests {
     use super::{
-        ChannelWebhookAdapter, ChannelWebhookRegistry, DISCORD_ADAPTER, SIGNAL_ADAPTER,
-        SLACK_ADAPTER, TELEGRAM_ADAPTER, WHATSAPP_ADAPTER, WebhookFuture, normalize_channel_key,
+        ChannelWebhookAdapter, ChannelWebhookRegistry, DISCORD_ADAPTER, MATTERMOST_ADAPTER,
+        ROCKETCHAT_ADAPTER, SIGNAL_ADAPTER, SLACK_ADAPTER, TEAMS_ADAPTER, TELEGRAM_ADAPTER,
+        WHATSAPP_ADAPTER, WebhookFuture, normalize_channel_key,
     };
     use crate::application::state::SharedState;
     use axum::{
@@ -263,12 +287,18 @@ mod tests {
             SLACK_ADAPTER,
             SIGNAL_ADAPTER,
             WHATSAPP_ADAPTER,
+            TEAMS_ADAPTER,
+            MATTERMOST_ADAPTER,
+            ROCKETCHAT_ADAPTER,
         ]);
         assert!(registry.adapter_for("telegram").is_some());
         assert!(registry.adapter_for("discord").is_some());
         assert!(registry.adapter_for("slack").is_some());
         assert!(registry.adapter_for("signal").is_some());
         assert!(registry.adapter_for("whatsapp").is_some());
+        assert!(registry.adapter_for("teams").is_some());
+        assert!(registry.adapter_for("mattermost").is_some());
+        assert!(registry.adapter_for("rocketchat").is_some());
     }
 
     #[test]
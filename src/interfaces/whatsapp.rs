@@ -1,9 +1,9 @@
 use axum::http::HeaderMap;
 use serde_json::{Value, json};
 
-use crate::application::state::SharedState;
+use crate::{application::state::SharedState, domain::models::DeliveryStatus};
 
-use super::{channel_adapter_common as common, webhooks::WebhookFuture};
+use super::{channel_adapter_common as common, quiet_hours, webhooks::WebhookFuture};
 
 const WHATSAPP_EVENTS_PREFIX: &str = "runtime/whatsapp/event/";
 
@@ -21,7 +21,18 @@ pub(crate) fn dispatch_webhook<'a>(
             return error;
         }
 
+        let receipts = apply_whatsapp_statuses(state, &payload).await;
         let Some(message) = first_whatsapp_message(&payload) else {
+            if receipts > 0 {
+                return (
+                    axum::http::StatusCode::OK,
+                    axum::Json(json!({
+                        "ok": true,
+                        "accepted": false,
+                        "receipts": receipts,
+                    })),
+                );
+            }
             return common::accepted_false("no-message");
         };
 
@@ -68,6 +79,7 @@ pub(crate) fn dispatch_webhook<'a>(
             );
         }
 
+        let contact_name = whatsapp_contact_name(&payload, &from);
         let outbound_conversation_id = from.clone();
         let result = match common::ingest_channel_message(
             state,
@@ -80,6 +92,9 @@ pub(crate) fn dispatch_webhook<'a>(
                 idempotency_key: format!("whatsapp-{message_id}"),
                 metadata: Some(json!({
                     "source": "whatsapp",
+                    "conversationTitle": contact_name,
+                    "conversationKind": "direct",
+                    "senderName": contact_name,
                 })),
             },
         )
@@ -90,7 +105,7 @@ pub(crate) fn dispatch_webhook<'a>(
         };
 
         common::mark_event_processed(state, &dedupe_key, "whatsapp", &message_id, &result).await;
-        let outbound_sent = common::maybe_dispatch_outbound_reply(
+        let outbound = common::maybe_dispatch_outbound_reply(
             state,
             state.config().whatsapp_outbound_url.as_deref(),
             state.config().whatsapp_outbound_token.as_deref(),
@@ -99,21 +114,68 @@ pub(crate) fn dispatch_webhook<'a>(
                 conversation_id: &outbound_conversation_id,
                 source_sender_id: Some(outbound_conversation_id.as_str()),
                 source_message_id: Some(message_id.as_str()),
-                reply: result.reply.as_deref(),
+                replies: &result.reply_parts,
+                suppress_unfurl: result.suppress_unfurl,
                 session_key: &result.session_key,
                 run_id: result.run_id.as_deref(),
                 metadata: Some(json!({
                     "source": "whatsapp",
                 })),
                 log_scope: "channels.whatsapp.webhook",
+                urgent: quiet_hours::is_urgent(headers),
             },
         )
         .await;
 
-        common::accepted_true_with_outbound(&result, outbound_sent)
+        common::accepted_true_with_outbound(&result, outbound)
     })
 }
 
+/// Applies Cloud API `statuses` (sent/delivered/read/failed) to tracked deliveries and returns
+/// how many matched.
+async fn apply_whatsapp_statuses(state: &SharedState, payload: &Value) -> usize {
+    let Some(statuses) = payload
+        .get("entry")
+        .and_then(Value::as_array)
+        .and_then(|entries| entries.first())
+        .and_then(|entry| entry.get("changes"))
+        .and_then(Value::as_array)
+        .and_then(|changes| changes.first())
+        .and_then(|change| change.get("value"))
+        .and_then(|value| value.get("statuses"))
+        .and_then(Value::as_array)
+    else {
+        return 0;
+    };
+
+    let mut applied = 0;
+    for entry in statuses {
+        let (Some(message_id), Some(status)) = (
+            entry.get("id").and_then(Value::as_str),
+            entry
+                .get("status")
+                .and_then(Value::as_str)
+                .and_then(DeliveryStatus::parse),
+        ) else {
+            continue;
+        };
+        let error = entry
+            .get("errors")
+            .and_then(Value::as_array)
+            .and_then(|errors| errors.first())
+            .and_then(|error| error.get("title").or_else(|| error.get("message")))
+            .and_then(Value::as_str)
+            .map(str::to_owned);
+        if let Ok(Some(_)) = state
+            .apply_delivery_receipt("whatsapp", None, Some(message_id), status, error)
+            .await
+        {
+            applied += 1;
+        }
+    }
+    applied
+}
+
 fn first_whatsapp_message(payload: &Value) -> Option<&Value> {
     payload
         .get("entry")?
@@ -127,3 +189,29 @@ fn first_whatsapp_message(payload: &Value) -> Option<&Value> {
         .as_array()?
         .first()
 }
+
+fn whatsapp_contact_name(payload: &Value, wa_id: &str) -> Option<String> {
+    payload
+        .get("entry")?
+        .as_array()?
+        .first()?
+        .get("changes")?
+        .as_array()?
+        .first()?
+        .get("value")?
+        .get("contacts")?
+        .as_array()?
+        .iter()
+        .find(|contact| {
+            contact
+                .get("wa_id")
+                .and_then(Value::as_str)
+                .is_none_or(|id| id == wa_id)
+        })?
+        .get("profile")?
+        .get("name")?
+        .as_str()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned)
+}
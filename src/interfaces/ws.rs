@@ -1,27 +1,55 @@
-use std::{net::SocketAddr, time::Instant};
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
 
 use axum::{
     extract::{
         ConnectInfo, State,
-        ws::{Message, WebSocket, WebSocketUpgrade},
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
     },
     response::IntoResponse,
 };
 use serde_json::{Value, json};
-use tokio::time::timeout;
+use tokio::{
+    sync::{mpsc::Receiver, oneshot},
+    time::{sleep_until, timeout},
+};
 use tracing::{debug, error, warn};
 
 use crate::{
-    application::state::{ConnectedClient, SharedState, sanitize_scopes},
+    application::{
+        lifecycle::StopKind,
+        replication,
+        state::{
+            ConnectedClient, ConnectionAdmission, GatewayEventEnvelope, SharedState,
+            sanitize_scopes,
+        },
+    },
     protocol::{
-        ConnectParams, ERROR_INVALID_REQUEST, ErrorShape, GatewayPolicy, HelloFeatures, HelloOk,
-        HelloServer, PROTOCOL_VERSION, parse_request_frame, response_error, response_ok,
+        ClientFeatures, ConnectAuth, ConnectParams, ERROR_INVALID_REQUEST, ErrorShape,
+        GatewayPolicy, HelloFeatures, HelloOk, HelloServer, PROTOCOL_VERSION, ServerCapabilities,
+        is_batch_frame, parse_batch_frame, parse_request_frame, response_error, response_ok,
+    },
+    rpc::{
+        SessionContext,
+        dispatcher::{dispatch_batch, dispatch_request},
+        methods::{
+            channels,
+            device::{self, DeviceGrant},
+            tts,
+        },
+        policy::default_operator_scopes,
+    },
+    security::{
+        auth::{AuthFailureReason, auth_failure_error, authorize},
+        handshake_challenge,
     },
-    rpc::{SessionContext, dispatcher::dispatch_request, policy::default_operator_scopes},
-    security::auth::{auth_failure_error, authorize},
     storage::now_unix_ms,
 };
 
+use super::ws_recording::{FrameDirection, SessionRecorder};
+
 const AGENT_EVENTS_CAPABILITY: &str = "agent-events-v1";
 
 pub async fn ws_handler(
@@ -33,8 +61,21 @@ pub async fn ws_handler(
         .on_upgrade(move |socket| handle_socket(socket, state, remote_addr))
 }
 
-async fn handle_socket(mut socket: WebSocket, state: SharedState, remote_addr: SocketAddr) {
+async fn handle_socket(socket: WebSocket, state: SharedState, remote_addr: SocketAddr) {
     let remote_ip = Some(remote_addr.ip().to_string());
+    let recorder = state.config().ws_record_dir.as_deref().and_then(|dir| {
+        match SessionRecorder::create(dir) {
+            Ok(recorder) => Some(recorder),
+            Err(error) => {
+                warn!("ws recording disabled for remote={remote_addr}: {error}");
+                None
+            }
+        }
+    });
+    let mut socket = GatewaySocket {
+        inner: socket,
+        recorder,
+    };
 
     let handshake = match perform_handshake(&mut socket, &state, remote_ip).await {
         Ok(context) => context,
@@ -44,6 +85,12 @@ async fn handle_socket(mut socket: WebSocket, state: SharedState, remote_addr: S
         }
     };
     let session = handshake.session;
+    let features = handshake.features;
+    let mut eviction_rx = handshake.eviction_rx;
+    let event_interval = features
+        .max_event_rate
+        .map(|rate| Duration::from_secs(1) / rate);
+    let mut next_event_at = tokio::time::Instant::now();
     let mut event_rx = if handshake.accepts_event_push {
         Some(
             state
@@ -53,28 +100,102 @@ async fn handle_socket(mut socket: WebSocket, state: SharedState, remote_addr: S
     } else {
         None
     };
+    let mut replayed_seq = 0;
+    if event_rx.is_some() {
+        match state.unacked_gateway_events(&session.conn_id).await {
+            Ok(events) => {
+                for event in events {
+                    replayed_seq = event.seq.unwrap_or(replayed_seq);
+                    if send_event(&mut socket, event, features.supports_binary_frames)
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+            Err(error) => warn!(
+                "failed to replay unacked events conn={}: {error}",
+                session.conn_id
+            ),
+        }
+    }
+    if session.role == "node"
+        && event_rx.is_some()
+        && let Err(error) = state.deliver_queued_node_invokes(&session.conn_id).await
+    {
+        warn!(
+            "failed to deliver queued node invokes conn={}: {error}",
+            session.conn_id
+        );
+    }
 
     loop {
-        let next = if let Some(rx) = event_rx.as_mut() {
-            tokio::select! {
-                maybe_event = rx.recv() => {
-                    match maybe_event {
-                        Some(event) => {
-                            if send_event(&mut socket, event).await.is_err() {
-                                break;
-                            }
+        let event_ready = tokio::time::Instant::now() >= next_event_at;
+        let next = tokio::select! {
+            reason = &mut eviction_rx => {
+                let reason = reason.unwrap_or_else(|_| "connection evicted".to_owned());
+                debug!("evicting conn={}: {reason}", session.conn_id);
+                let _ = socket
+                    .send(Message::Close(Some(CloseFrame {
+                        code: close_code::POLICY,
+                        reason: reason.into(),
+                    })))
+                    .await;
+                break;
+            }
+            kind = state.lifecycle().stopped() => {
+                // Deliver the final `shutdown` event before closing.
+                if let Some(rx) = event_rx.as_mut() {
+                    while let Ok(event) = rx.try_recv() {
+                        let Some(event) = outgoing_event(event, features, replayed_seq) else {
                             continue;
+                        };
+                        if send_event(&mut socket, event, features.supports_binary_frames)
+                            .await
+                            .is_err()
+                        {
+                            break;
                         }
-                        None => {
-                            event_rx = None;
+                    }
+                }
+                let code = match kind {
+                    StopKind::Shutdown => close_code::AWAY,
+                    StopKind::Restart => close_code::RESTART,
+                };
+                let _ = socket
+                    .send(Message::Close(Some(CloseFrame {
+                        code,
+                        reason: format!("server {}", kind.label()).into(),
+                    })))
+                    .await;
+                break;
+            }
+            () = sleep_until(next_event_at), if !event_ready => continue,
+            maybe_event = recv_gateway_event(&mut event_rx), if event_ready => {
+                match maybe_event {
+                    Some(event) => {
+                        let Some(event) = outgoing_event(event, features, replayed_seq) else {
                             continue;
+                        };
+                        if let Some(interval) = event_interval {
+                            next_event_at = tokio::time::Instant::now() + interval;
+                        }
+                        if send_event(&mut socket, event, features.supports_binary_frames)
+                            .await
+                            .is_err()
+                        {
+                            break;
                         }
+                        continue;
+                    }
+                    None => {
+                        event_rx = None;
+                        continue;
                     }
                 }
-                next = socket.recv() => next,
             }
-        } else {
-            socket.recv().await
+            next = socket.recv() => next,
         };
 
         let Some(next) = next else {
@@ -100,6 +221,24 @@ async fn handle_socket(mut socket: WebSocket, state: SharedState, remote_addr: S
             }
         };
 
+        if is_batch_frame(&text) {
+            let sent = match parse_batch_frame(&text) {
+                Ok(batch) => {
+                    let response = dispatch_batch(&state, &session, &batch).await;
+                    send_response(&mut socket, response).await
+                }
+                Err(error_shape) => {
+                    let request_id =
+                        extract_frame_id(&text).unwrap_or_else(|| "invalid".to_owned());
+                    send_response(&mut socket, response_error(request_id, error_shape)).await
+                }
+            };
+            if sent.is_err() {
+                break;
+            }
+            continue;
+        }
+
         let request = match parse_request_frame(&text) {
             Ok(frame) => frame,
             Err(error_shape) => {
@@ -134,16 +273,118 @@ async fn handle_socket(mut socket: WebSocket, state: SharedState, remote_addr: S
     );
 }
 
+/// Checks device credentials in the connect auth against the requested role, if any. `None` means none were presented and the
+/// gateway secret applies; `Some(None)` means they were presented but are invalid or expired.
+async fn authenticate_device(
+    state: &SharedState,
+    auth: Option<&ConnectAuth>,
+    role: Option<&str>,
+) -> Result<Option<Option<DeviceGrant>>, ErrorShape> {
+    let credential = |value: Option<&String>| {
+        value
+            .map(|value| value.trim().to_owned())
+            .filter(|value| !value.is_empty())
+    };
+    let Some(auth) = auth else {
+        return Ok(None);
+    };
+    if let Some(refresh_token) = credential(auth.refresh_token.as_ref()) {
+        return device::refresh_device_tokens(state, &refresh_token, role)
+            .await
+            .map(Some);
+    }
+    if let Some(device_token) = credential(auth.device_token.as_ref()) {
+        return device::authenticate_device_token(state, &device_token, role)
+            .await
+            .map(Some);
+    }
+    Ok(None)
+}
+
+/// The connection's socket, copying text frames to the session recording when `wsRecordDir` is set.
+struct GatewaySocket {
+    inner: WebSocket,
+    recorder: Option<SessionRecorder>,
+}
+
+impl GatewaySocket {
+    async fn recv(&mut self) -> Option<Result<Message, axum::Error>> {
+        let next = self.inner.recv().await;
+        if let (Some(recorder), Some(Ok(message))) = (self.recorder.as_mut(), next.as_ref()) {
+            record_message(recorder, FrameDirection::In, message);
+        }
+        next
+    }
+
+    async fn send(&mut self, message: Message) -> Result<(), axum::Error> {
+        if let Some(recorder) = self.recorder.as_mut() {
+            record_message(recorder, FrameDirection::Out, &message);
+        }
+        self.inner.send(message).await
+    }
+}
+
+fn record_message(recorder: &mut SessionRecorder, direction: FrameDirection, message: &Message) {
+    match message {
+        Message::Text(text) => recorder.record(direction, text.as_str()),
+        Message::Binary(bytes) => {
+            if let Ok(text) = std::str::from_utf8(bytes) {
+                recorder.record(direction, text);
+            }
+        }
+        Message::Ping(_) | Message::Pong(_) | Message::Close(_) => {}
+    }
+}
+
+async fn recv_gateway_event(
+    event_rx: &mut Option<Receiver<GatewayEventEnvelope>>,
+) -> Option<GatewayEventEnvelope> {
+    match event_rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Drops the journal `seq` for clients that do not acknowledge events, and skips events the
+/// reconnect replay already delivered.
+fn outgoing_event(
+    mut event: GatewayEventEnvelope,
+    features: ClientFeatures,
+    replayed_seq: u64,
+) -> Option<GatewayEventEnvelope> {
+    if !features.supports_event_ack {
+        event.seq = None;
+    } else if event.seq.is_some_and(|seq| seq <= replayed_seq) {
+        return None;
+    }
+    Some(event)
+}
+
 struct HandshakeContext {
     session: SessionContext,
+    features: ClientFeatures,
     accepts_event_push: bool,
+    eviction_rx: oneshot::Receiver<String>,
 }
 
 async fn perform_handshake(
-    socket: &mut WebSocket,
+    socket: &mut GatewaySocket,
     state: &SharedState,
     remote_ip: Option<String>,
 ) -> Result<HandshakeContext, ()> {
+    let challenge = state.config().handshake_challenge.clone();
+    let nonce = uuid::Uuid::new_v4().to_string();
+    if let Some(challenge) = &challenge {
+        let ts = now_unix_ms();
+        let event = GatewayEventEnvelope {
+            event: "connect.challenge".to_owned(),
+            payload: challenge.payload(&nonce, ts),
+            ts,
+            seq: None,
+        };
+        send_event(socket, event, false).await?;
+    }
+
     let text = match timeout(
         state.config().handshake_timeout,
         recv_next_text(socket, state),
@@ -209,7 +450,7 @@ async fn perform_handshake(
         return Err(());
     }
 
-    let role = connect_params
+    let mut role = connect_params
         .role
         .clone()
         .unwrap_or_else(|| "operator".to_owned());
@@ -238,7 +479,26 @@ async fn perform_handshake(
         return Err(());
     }
 
-    if let Err(reason) = authorize(&state.config().auth_mode, connect_params.auth.as_ref()) {
+    let device_grant = match authenticate_device(
+        state,
+        connect_params.auth.as_ref(),
+        connect_params.role.as_deref(),
+    )
+    .await
+    {
+        Ok(grant) => grant,
+        Err(error_shape) => {
+            let response = response_error(request.id, error_shape);
+            let _ = send_response(socket, response).await;
+            return Err(());
+        }
+    };
+    let authorized = match &device_grant {
+        Some(Some(_)) => Ok(()),
+        Some(None) => Err(AuthFailureReason::InvalidCredentials),
+        None => authorize(&state.config().auth_mode, connect_params.auth.as_ref()),
+    };
+    if let Err(reason) = authorized {
         let record = limiter.record_failure(&auth_key).await;
         let mut shape = auth_failure_error(reason);
         if !record.allowed || record.retry_after_ms > 0 {
@@ -250,15 +510,57 @@ async fn perform_handshake(
         return Err(());
     }
 
+    if let Some(challenge) = &challenge
+        && !handshake_challenge::credential_verified(
+            &state.config().auth_mode,
+            matches!(device_grant, Some(Some(_))),
+        )
+        && let Err(mut shape) = challenge
+            .verify(
+                &nonce,
+                connect_params.challenge.as_ref(),
+                remote_ip.as_deref(),
+            )
+            .await
+    {
+        let record = limiter.record_failure(&auth_key).await;
+        if !record.allowed || record.retry_after_ms > 0 {
+            shape = shape.with_retry(record.retry_after_ms);
+        }
+        let response = response_error(request.id, shape);
+        let _ = send_response(socket, response).await;
+        return Err(());
+    }
+
     limiter.reset(&auth_key).await;
+    let device_grant = device_grant.flatten();
+    if let Some(grant) = &device_grant {
+        role.clone_from(&grant.role);
+        if role != "operator" && role != "node" {
+            let response = response_error(
+                request.id,
+                ErrorShape::new(ERROR_INVALID_REQUEST, "invalid role"),
+            );
+            let _ = send_response(socket, response).await;
+            return Err(());
+        }
+    }
 
     let conn_id = uuid::Uuid::new_v4().to_string();
     let accepts_event_push = connect_params
         .caps
         .iter()
         .any(|cap| cap == AGENT_EVENTS_CAPABILITY);
+    let features = connect_params.features.negotiate();
     let mut scopes = sanitize_scopes(&connect_params.scopes);
-    if role == "operator" && scopes.is_empty() {
+    if let Some(grant) = &device_grant {
+        // A device connection never exceeds the scopes its token was issued with.
+        if scopes.is_empty() {
+            scopes.clone_from(&grant.scopes);
+        } else {
+            scopes.retain(|scope| grant.scopes.contains(scope));
+        }
+    } else if role == "operator" && scopes.is_empty() {
         scopes = default_operator_scopes();
     }
     let connected_at = Instant::now();
@@ -279,18 +581,63 @@ async fn perform_handshake(
         remote_ip,
         connected_at,
         connected_at_ms,
+        features,
+        device_id: device_grant.as_ref().map(|grant| grant.device_id.clone()),
     };
 
-    if let Err(error) = state.register_client(registered_client).await {
-        let response = response_error(
-            request.id,
-            ErrorShape::new(
-                crate::protocol::ERROR_UNAVAILABLE,
-                format!("failed to register connection: {error}"),
-            ),
+    let client_id = registered_client.client_id.clone();
+    let eviction_rx = match state.register_client(registered_client).await {
+        Ok(ConnectionAdmission::Admitted {
+            evicted,
+            eviction_rx,
+        }) => {
+            if !evicted.is_empty() {
+                debug!(
+                    "conn={conn_id} evicted {} connection(s) for client={client_id}",
+                    evicted.len(),
+                );
+            }
+            eviction_rx
+        }
+        Ok(ConnectionAdmission::Rejected { limit }) => {
+            let response = response_error(
+                request.id,
+                ErrorShape::new(
+                    crate::protocol::ERROR_UNAVAILABLE,
+                    format!(
+                        "connection limit reached: at most {limit} live connection(s) per {role}"
+                    ),
+                )
+                .with_details(json!({ "limit": limit, "role": role })),
+            );
+            let _ = send_response(socket, response).await;
+            return Err(());
+        }
+        Err(error) => {
+            let _ = state.unregister_client(&conn_id).await;
+            let response = response_error(
+                request.id,
+                ErrorShape::new(
+                    crate::protocol::ERROR_UNAVAILABLE,
+                    format!("failed to register connection: {error}"),
+                ),
+            );
+            let _ = send_response(socket, response).await;
+            return Err(());
+        }
+    };
+    if let Some(grant) = &device_grant
+        && let Err(error) = device::record_device_seen(
+            state,
+            &grant.device_id,
+            Some(&connect_params.client.platform),
+        )
+        .await
+    {
+        debug!(
+            "conn={conn_id} failed to record device {} as seen: {}",
+            grant.device_id, error.message
         );
-        let _ = send_response(socket, response).await;
-        return Err(());
     }
 
     let snapshot = match state.snapshot().await {
@@ -319,10 +666,13 @@ async fn perform_handshake(
         features: HelloFeatures {
             methods: state.methods(),
             events: state.events(),
+            client: features,
+            server: server_capabilities(state).await,
         },
         snapshot,
         canvas_host_url: None,
-        auth: None,
+        auth: device_grant.and_then(|grant| grant.issued),
+        replication: replication::reconnect_hint(state).await,
         policy: GatewayPolicy {
             max_payload: state.config().max_payload_bytes,
             max_buffered_bytes: state.config().max_buffered_bytes,
@@ -361,11 +711,31 @@ async fn perform_handshake(
             client_id: connect_params.client.id,
             client_mode: connect_params.client.mode,
         },
+        features,
         accepts_event_push,
+        eviction_rx,
     })
 }
 
-async fn recv_next_text(socket: &mut WebSocket, state: &SharedState) -> Result<String, ErrorShape> {
+async fn server_capabilities(state: &SharedState) -> ServerCapabilities {
+    let config = state.config();
+    ServerCapabilities {
+        hooks: config.hooks_enabled,
+        openai_chat_completions: config.openai_chat_completions_enabled,
+        openresponses: config.openresponses_enabled,
+        tts: tts::tts_enabled(state).await,
+        cron: config.cron_enabled,
+        channels: channels::configured_channel_ids(config),
+        chat_streaming: true,
+        federation: config.federation.is_some(),
+        replication: config.replication.is_some(),
+    }
+}
+
+async fn recv_next_text(
+    socket: &mut GatewaySocket,
+    state: &SharedState,
+) -> Result<String, ErrorShape> {
     loop {
         let next = socket.recv().await.ok_or_else(|| {
             ErrorShape::new(ERROR_INVALID_REQUEST, "connection closed before handshake")
@@ -401,8 +771,8 @@ fn auth_key(remote_ip: &Option<String>, client_id: &str) -> String {
 }
 
 async fn send_response(
-    socket: &mut WebSocket,
-    response: crate::protocol::ResponseFrame,
+    socket: &mut GatewaySocket,
+    response: impl serde::Serialize,
 ) -> Result<(), ()> {
     let text = match serde_json::to_string(&response) {
         Ok(value) => value,
@@ -421,15 +791,19 @@ async fn send_response(
 }
 
 async fn send_event(
-    socket: &mut WebSocket,
-    event: crate::application::state::GatewayEventEnvelope,
+    socket: &mut GatewaySocket,
+    event: GatewayEventEnvelope,
+    binary: bool,
 ) -> Result<(), ()> {
-    let frame = json!({
+    let mut frame = json!({
         "type": "evt",
         "event": event.event,
         "payload": event.payload,
         "ts": event.ts,
     });
+    if let Some(seq) = event.seq {
+        frame["seq"] = json!(seq);
+    }
     let text = match serde_json::to_string(&frame) {
         Ok(value) => value,
         Err(error) => {
@@ -438,12 +812,14 @@ async fn send_event(
         }
     };
 
-    socket
-        .send(Message::Text(text.into()))
-        .await
-        .map_err(|error| {
-            warn!("failed to send websocket event: {error}");
-        })
+    let message = if binary {
+        Message::Binary(text.into_bytes().into())
+    } else {
+        Message::Text(text.into())
+    };
+    socket.send(message).await.map_err(|error| {
+        warn!("failed to send websocket event: {error}");
+    })
 }
 
 fn message_to_text(
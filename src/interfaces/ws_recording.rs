@@ -0,0 +1,218 @@
+//! Recording of WebSocket sessions for deterministic replay.
+//!
+//! With `wsRecordDir` set, every gateway connection writes a JSON Lines file holding each inbound
+//! and outbound frame with its offset from the connection start. A replay runner feeds the inbound
+//! frames to a fresh server and compares the responses, after [`normalize_frame`] masks the values
+//! that legitimately differ between runs.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::warn;
+
+use crate::storage::now_unix_ms;
+
+/// Placeholder for masked timestamps, durations, and generated ids.
+pub const MASKED: &str = "<masked>";
+
+/// Keys whose values vary between runs regardless of their name's suffix.
+const VOLATILE_KEYS: &[&str] = &[
+    "ts",
+    "connId",
+    "runId",
+    "nonce",
+    "uptime",
+    "version",
+    "commit",
+    "host",
+    "pid",
+    "configPath",
+    "stateDir",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameDirection {
+    In,
+    Out,
+}
+
+/// One line of a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedFrame {
+    /// Milliseconds since the connection was accepted.
+    pub offset_ms: u64,
+    pub direction: FrameDirection,
+    /// The frame as JSON; frames that are not valid JSON are kept as a string.
+    pub frame: Value,
+}
+
+/// Appends the frames of one connection to `<dir>/<startedAtMs>-<uuid>.jsonl`. Write failures
+/// disable the recorder instead of interrupting the connection.
+pub struct SessionRecorder {
+    path: PathBuf,
+    file: Option<File>,
+    started: Instant,
+}
+
+impl SessionRecorder {
+    pub fn create(dir: &Path) -> Result<Self, String> {
+        std::fs::create_dir_all(dir).map_err(|error| {
+            format!("failed to create ws record dir {}: {error}", dir.display())
+        })?;
+        let path = dir.join(format!("{}-{}.jsonl", now_unix_ms(), uuid::Uuid::new_v4()));
+        let file = File::create(&path).map_err(|error| {
+            format!("failed to create ws recording {}: {error}", path.display())
+        })?;
+        Ok(Self {
+            path,
+            file: Some(file),
+            started: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&mut self, direction: FrameDirection, text: &str) {
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        let frame = RecordedFrame {
+            offset_ms: u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            direction,
+            frame: serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_owned())),
+        };
+        let mut line = match serde_json::to_string(&frame) {
+            Ok(line) => line,
+            Err(error) => {
+                warn!("failed to serialize recorded ws frame: {error}");
+                return;
+            }
+        };
+        line.push('\n');
+        if let Err(error) = file.write_all(line.as_bytes()) {
+            warn!(
+                "stopping ws recording {} after write failure: {error}",
+                self.path.display()
+            );
+            self.file = None;
+        }
+    }
+}
+
+/// Reads a recording written by [`SessionRecorder`]. Blank lines are skipped.
+pub fn load_recording(path: &Path) -> Result<Vec<RecordedFrame>, String> {
+    let file = File::open(path)
+        .map_err(|error| format!("failed to open recording {}: {error}", path.display()))?;
+    let mut frames = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line =
+            line.map_err(|error| format!("failed to read recording {}: {error}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let frame = serde_json::from_str::<RecordedFrame>(&line).map_err(|error| {
+            format!(
+                "invalid frame on line {} of {}: {error}",
+                index + 1,
+                path.display()
+            )
+        })?;
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// Masks run-specific values so responses from different runs compare equal: timestamps and
+/// durations (`ts` and keys ending in `Ms`/`At`), generated ids (`connId`, `runId`, `nonce`,
+/// UUID strings), state paths, and server build details.
+pub fn normalize_frame(frame: &Value) -> Value {
+    match frame {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| {
+                    let value = if is_volatile_key(key) && !value.is_null() {
+                        Value::String(MASKED.to_owned())
+                    } else {
+                        normalize_frame(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(normalize_frame).collect()),
+        Value::String(text) if looks_like_uuid(text) => Value::String(MASKED.to_owned()),
+        other => other.clone(),
+    }
+}
+
+fn is_volatile_key(key: &str) -> bool {
+    VOLATILE_KEYS.contains(&key) || key.ends_with("Ms") || key.ends_with("At")
+}
+
+/// Matches bare UUIDs and keys ending in one, such as generated session keys.
+fn looks_like_uuid(text: &str) -> bool {
+    text.len()
+        .checked_sub(36)
+        .and_then(|start| text.get(start..))
+        .is_some_and(|tail| uuid::Uuid::parse_str(tail).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{FrameDirection, MASKED, SessionRecorder, load_recording, normalize_frame};
+
+    #[test]
+    fn recordings_round_trip_and_normalize_volatile_values() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let mut recorder =
+            SessionRecorder::create(&temp.path().join("ws")).expect("recorder should open");
+        recorder.record(
+            FrameDirection::In,
+            r#"{"type":"req","id":"1","method":"health"}"#,
+        );
+        recorder.record(FrameDirection::Out, "not json");
+
+        let frames = load_recording(recorder.path()).expect("recording should load");
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].direction, FrameDirection::In);
+        assert_eq!(frames[0].frame["method"], "health");
+        assert_eq!(frames[1].frame, json!("not json"));
+
+        let normalized = normalize_frame(&json!({
+            "id": "1",
+            "ts": 1,
+            "payload": {
+                "createdAtMs": 5,
+                "expiresAtMs": null,
+                "key": "agent:main:6f1c9d1e-3c44-4b7a-9a3e-2b1c0d9e8f7a",
+                "items": [{"runId": "r", "text": "Echo: hi"}]
+            }
+        }));
+        assert_eq!(
+            normalized,
+            json!({
+                "id": "1",
+                "ts": MASKED,
+                "payload": {
+                    "createdAtMs": MASKED,
+                    "expiresAtMs": null,
+                    "key": MASKED,
+                    "items": [{"runId": MASKED, "text": "Echo: hi"}]
+                }
+            })
+        );
+    }
+}
@@ -1,5 +1,6 @@
 pub mod application;
 pub mod domain;
+pub mod encoding;
 pub mod interfaces;
 pub mod protocol;
 pub mod rpc;
@@ -6,6 +6,7 @@ pub const ERROR_NOT_PAIRED: &str = "NOT_PAIRED";
 pub const ERROR_AGENT_TIMEOUT: &str = "AGENT_TIMEOUT";
 pub const ERROR_INVALID_REQUEST: &str = "INVALID_REQUEST";
 pub const ERROR_UNAVAILABLE: &str = "UNAVAILABLE";
+pub const ERROR_DEADLINE_EXCEEDED: &str = "DEADLINE_EXCEEDED";
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
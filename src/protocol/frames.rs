@@ -9,6 +9,10 @@ pub struct RequestFrame {
     pub method: String,
     #[serde(default)]
     pub params: Option<Value>,
+    /// Milliseconds the client is willing to wait; the call fails with `DEADLINE_EXCEEDED`
+    /// and its work is cancelled once they pass.
+    #[serde(default, rename = "timeoutMs")]
+    pub timeout_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -21,6 +25,46 @@ pub struct ResponseFrame {
     pub payload: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<super::ErrorShape>,
+    /// Set when the request used a deprecated alias of `replacement`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<DeprecationWarning>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeprecationWarning {
+    pub method: String,
+    pub replacement: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchRequestFrame {
+    #[serde(rename = "type")]
+    pub frame_type: String,
+    pub id: String,
+    pub calls: Vec<BatchCall>,
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchCall {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Option<Value>,
+    #[serde(default, rename = "timeoutMs")]
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchResponseFrame {
+    #[serde(rename = "type")]
+    pub frame_type: &'static str,
+    pub id: String,
+    pub results: Vec<ResponseFrame>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -39,6 +83,58 @@ pub struct ConnectParams {
     pub scopes: Vec<String>,
     #[serde(default)]
     pub auth: Option<ConnectAuth>,
+    #[serde(default)]
+    pub features: ClientFeatures,
+    /// Answer to the `connect.challenge` event, required without credentials when the gateway
+    /// has a `handshakeChallenge`.
+    #[serde(default)]
+    pub challenge: Option<ConnectChallengeAnswer>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectChallengeAnswer {
+    pub nonce: String,
+    /// Proof-of-work solution.
+    #[serde(default)]
+    pub solution: Option<String>,
+    #[serde(default)]
+    pub captcha_token: Option<String>,
+}
+
+/// Optional behaviors a client declares at `connect`; the server echoes the negotiated set in
+/// `hello-ok.features.client`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientFeatures {
+    /// Events are pushed as binary WebSocket frames carrying the same JSON.
+    #[serde(default)]
+    pub supports_binary_frames: bool,
+    /// The client applies `presence` delta events instead of re-reading full snapshots.
+    #[serde(default)]
+    pub supports_delta_sync: bool,
+    /// The client acknowledges journaled events with `events.ack` and gets the unacknowledged
+    /// ones redelivered when it reconnects.
+    #[serde(default)]
+    pub supports_event_ack: bool,
+    /// Upper bound on pushed events per second; extra events are paced, not dropped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_event_rate: Option<u32>,
+}
+
+impl ClientFeatures {
+    pub const MAX_EVENT_RATE: u32 = 1_000;
+
+    /// Clamps requested values to what the server supports.
+    #[must_use]
+    pub fn negotiate(self) -> Self {
+        Self {
+            max_event_rate: self
+                .max_event_rate
+                .map(|rate| rate.clamp(1, Self::MAX_EVENT_RATE)),
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -65,6 +161,9 @@ pub struct ConnectAuth {
     pub token: Option<String>,
     #[serde(default)]
     pub device_token: Option<String>,
+    /// Device refresh token; a valid one authenticates and returns a new pair in `hello-ok.auth`.
+    #[serde(default)]
+    pub refresh_token: Option<String>,
     #[serde(default)]
     pub password: Option<String>,
 }
@@ -82,6 +181,9 @@ pub struct HelloOk {
     pub canvas_host_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub auth: Option<Value>,
+    /// Where to reconnect when this gateway is a standby or fails over to one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replication: Option<Value>,
     pub policy: GatewayPolicy,
 }
 
@@ -97,6 +199,27 @@ pub struct HelloServer {
 pub struct HelloFeatures {
     pub methods: Vec<String>,
     pub events: Vec<String>,
+    pub client: ClientFeatures,
+    pub server: ServerCapabilities,
+}
+
+/// Optional subsystems enabled on this server, so clients can hide features whose methods
+/// would fail with `UNAVAILABLE` instead of probing them.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerCapabilities {
+    pub hooks: bool,
+    pub openai_chat_completions: bool,
+    pub openresponses: bool,
+    /// `tts.convert` is enabled.
+    pub tts: bool,
+    pub cron: bool,
+    /// Ids of the channel adapters and plugins configured on this server.
+    pub channels: Vec<String>,
+    /// `chat.send` accepts `stream: true` and pushes `delta` events.
+    pub chat_streaming: bool,
+    pub federation: bool,
+    pub replication: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -160,6 +283,8 @@ pub struct PresenceEntry {
     pub scopes: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub instance_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<ClientFeatures>,
 }
 
 #[derive(Debug, Clone, Serialize)]
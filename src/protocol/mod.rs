@@ -2,17 +2,106 @@ mod errors;
 mod frames;
 
 pub use errors::{
-    ERROR_AGENT_TIMEOUT, ERROR_INVALID_REQUEST, ERROR_NOT_LINKED, ERROR_NOT_PAIRED,
-    ERROR_UNAVAILABLE, ErrorShape,
+    ERROR_AGENT_TIMEOUT, ERROR_DEADLINE_EXCEEDED, ERROR_INVALID_REQUEST, ERROR_NOT_LINKED,
+    ERROR_NOT_PAIRED, ERROR_UNAVAILABLE, ErrorShape,
 };
 pub use frames::{
-    ConnectAuth, ConnectClient, ConnectParams, GatewayPolicy, HelloFeatures, HelloOk, HelloServer,
-    PresenceEntry, RequestFrame, ResponseFrame, Snapshot, StateVersion,
+    BatchCall, BatchRequestFrame, BatchResponseFrame, ClientFeatures, ConnectAuth,
+    ConnectChallengeAnswer, ConnectClient, ConnectParams, DeprecationWarning, GatewayPolicy,
+    HelloFeatures, HelloOk, HelloServer, PresenceEntry, RequestFrame, ResponseFrame,
+    ServerCapabilities, Snapshot, StateVersion,
 };
 
 use serde_json::Value;
 
 pub const PROTOCOL_VERSION: u32 = 3;
+pub const MAX_BATCH_CALLS: usize = 32;
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+pub const MAX_BATCH_CONCURRENCY: usize = 8;
+
+#[derive(serde::Deserialize)]
+struct FrameTypeProbe {
+    #[serde(rename = "type", default)]
+    frame_type: Option<String>,
+}
+
+/// Returns true when `text` is a JSON frame with `type = "batch"`.
+#[must_use]
+pub fn is_batch_frame(text: &str) -> bool {
+    serde_json::from_str::<FrameTypeProbe>(text)
+        .ok()
+        .and_then(|probe| probe.frame_type)
+        .is_some_and(|frame_type| frame_type == "batch")
+}
+
+pub fn parse_batch_frame(text: &str) -> Result<BatchRequestFrame, ErrorShape> {
+    let batch = serde_json::from_str::<BatchRequestFrame>(text).map_err(|error| {
+        ErrorShape::new(
+            ERROR_INVALID_REQUEST,
+            format!("invalid batch frame: {error}"),
+        )
+    })?;
+
+    if batch.id.trim().is_empty() {
+        return Err(ErrorShape::new(
+            ERROR_INVALID_REQUEST,
+            "invalid batch frame: missing id",
+        ));
+    }
+    if batch.calls.is_empty() {
+        return Err(ErrorShape::new(
+            ERROR_INVALID_REQUEST,
+            "invalid batch frame: calls must not be empty",
+        ));
+    }
+    if batch.calls.len() > MAX_BATCH_CALLS {
+        return Err(ErrorShape::new(
+            ERROR_INVALID_REQUEST,
+            format!("invalid batch frame: at most {MAX_BATCH_CALLS} calls are allowed"),
+        ));
+    }
+
+    Ok(batch)
+}
+
+impl BatchRequestFrame {
+    /// Expands the batch into request frames; calls without an id get `{batchId}:{index}`.
+    #[must_use]
+    pub fn requests(&self) -> Vec<RequestFrame> {
+        self.calls
+            .iter()
+            .enumerate()
+            .map(|(index, call)| RequestFrame {
+                frame_type: "req".to_owned(),
+                id: call
+                    .id
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map_or_else(|| format!("{}:{index}", self.id), str::to_owned),
+                method: call.method.trim().to_owned(),
+                params: call.params.clone(),
+                timeout_ms: call.timeout_ms,
+            })
+            .collect()
+    }
+
+    #[must_use]
+    pub fn effective_concurrency(&self) -> usize {
+        self.concurrency
+            .unwrap_or(DEFAULT_BATCH_CONCURRENCY)
+            .clamp(1, MAX_BATCH_CONCURRENCY)
+    }
+}
+
+#[must_use]
+pub fn batch_response(id: impl Into<String>, results: Vec<ResponseFrame>) -> BatchResponseFrame {
+    BatchResponseFrame {
+        frame_type: "batch-res",
+        id: id.into(),
+        results,
+    }
+}
 
 pub fn parse_request_frame(text: &str) -> Result<RequestFrame, ErrorShape> {
     let request = serde_json::from_str::<RequestFrame>(text).map_err(|error| {
@@ -52,6 +141,7 @@ pub fn response_ok(id: impl Into<String>, payload: Value) -> ResponseFrame {
         ok: true,
         payload: Some(payload),
         error: None,
+        deprecation: None,
     }
 }
 
@@ -63,5 +153,6 @@ pub fn response_error(id: impl Into<String>, error: ErrorShape) -> ResponseFrame
         ok: false,
         payload: None,
         error: Some(error),
+        deprecation: None,
     }
 }
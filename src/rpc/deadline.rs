@@ -0,0 +1,58 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// Runs `future` until `timeout` elapses, whichever comes first. Dropping the future on expiry
+/// cancels whatever it was awaiting (storage calls, backend requests, child processes spawned
+/// with `kill_on_drop`). Nested deadlines never extend the one already in effect.
+pub async fn run_with_timeout<F: Future>(timeout: Duration, future: F) -> Option<F::Output> {
+    let requested = Instant::now() + timeout;
+    let deadline = DEADLINE
+        .try_with(|outer| (*outer).min(requested))
+        .unwrap_or(requested);
+    DEADLINE
+        .scope(deadline, tokio::time::timeout_at(deadline.into(), future))
+        .await
+        .ok()
+}
+
+/// Time left before the deadline of the request being served, if it has one.
+#[must_use]
+pub fn remaining() -> Option<Duration> {
+    DEADLINE
+        .try_with(|deadline| deadline.saturating_duration_since(Instant::now()))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{remaining, run_with_timeout};
+
+    #[tokio::test]
+    async fn nested_deadlines_keep_the_earliest() {
+        assert_eq!(remaining(), None);
+        let inner = run_with_timeout(Duration::from_millis(200), async {
+            let outer_left = remaining().expect("deadline should be set");
+            run_with_timeout(Duration::from_secs(60), async {
+                remaining().expect("deadline should be set") <= outer_left
+            })
+            .await
+        })
+        .await;
+        assert_eq!(inner, Some(Some(true)));
+
+        let expired = run_with_timeout(
+            Duration::from_millis(10),
+            tokio::time::sleep(Duration::from_secs(5)),
+        )
+        .await;
+        assert_eq!(expired, None);
+    }
+}
@@ -1,19 +1,184 @@
+use std::time::{Duration, Instant};
+
+use futures_util::{StreamExt, stream};
 use serde_json::json;
 
 use crate::{
-    application::state::SharedState,
+    application::{
+        federation,
+        lifecycle::{StopKind, WorkGuard},
+        replication,
+        state::SharedState,
+    },
     domain::error::DomainError,
     protocol::{
+        BatchRequestFrame, BatchResponseFrame, DeprecationWarning, ERROR_DEADLINE_EXCEEDED,
         ERROR_INVALID_REQUEST, ERROR_NOT_PAIRED, ERROR_UNAVAILABLE, ErrorShape, RequestFrame,
-        ResponseFrame, response_error, response_ok,
+        ResponseFrame, batch_response, response_error, response_ok,
     },
-    rpc::{SessionContext, methods, policy},
+    rpc::{SessionContext, deadline, methods, policy},
 };
 
 pub async fn dispatch_request(
     state: &SharedState,
     session: &SessionContext,
     request: &RequestFrame,
+) -> ResponseFrame {
+    let method = methods::resolve_alias(&request.method)
+        .map_or(request.method.as_str(), |alias| alias.target);
+    let started = Instant::now();
+    let response = match request.timeout_ms {
+        Some(0) => response_error(
+            request.id.clone(),
+            ErrorShape::new(
+                ERROR_INVALID_REQUEST,
+                "invalid request frame: timeoutMs must be greater than 0",
+            ),
+        ),
+        Some(timeout_ms) => deadline::run_with_timeout(
+            Duration::from_millis(timeout_ms),
+            dispatch_with_hooks(state, session, request),
+        )
+        .await
+        .unwrap_or_else(|| {
+            response_error(
+                request.id.clone(),
+                ErrorShape::new(
+                    ERROR_DEADLINE_EXCEEDED,
+                    format!("{method} did not finish within {timeout_ms}ms"),
+                )
+                .with_details(json!({ "method": method, "timeoutMs": timeout_ms })),
+            )
+        }),
+        None => dispatch_with_hooks(state, session, request).await,
+    };
+    if !policy::is_waiting_method(method) {
+        state.record_dispatch_latency(started.elapsed());
+    }
+    response
+}
+
+async fn dispatch_with_hooks(
+    state: &SharedState,
+    session: &SessionContext,
+    request: &RequestFrame,
+) -> ResponseFrame {
+    let hooks = state.dispatch_hooks().await;
+    if hooks.is_empty() {
+        return dispatch_method(state, session, request).await;
+    }
+
+    let mut session = session.clone();
+    let mut request = request.clone();
+    let mut short_circuit = None;
+    for hook in hooks.hooks() {
+        if let Err(error) = hook.pre_dispatch(state, &mut session, &mut request).await {
+            short_circuit = Some(error);
+            break;
+        }
+    }
+
+    let mut response = match short_circuit {
+        Some(error) => response_error(request.id.clone(), error),
+        None => dispatch_method(state, &session, &request).await,
+    };
+    for hook in hooks.hooks().iter().rev() {
+        hook.post_dispatch(state, &session, &request, &mut response)
+            .await;
+    }
+    response
+}
+
+/// Executes every call of a batch frame with bounded concurrency; results keep call order.
+pub async fn dispatch_batch(
+    state: &SharedState,
+    session: &SessionContext,
+    batch: &BatchRequestFrame,
+) -> BatchResponseFrame {
+    let results = stream::iter(batch.requests())
+        .map(|request| async move {
+            if request.method.is_empty() {
+                return response_error(
+                    request.id.clone(),
+                    ErrorShape::new(ERROR_INVALID_REQUEST, "invalid batch call: missing method"),
+                );
+            }
+            dispatch_request(state, session, &request).await
+        })
+        .buffered(batch.effective_concurrency())
+        .collect::<Vec<_>>()
+        .await;
+
+    batch_response(batch.id.clone(), results)
+}
+
+/// Serves deprecated aliases through their replacement method and flags the response.
+async fn dispatch_method(
+    state: &SharedState,
+    session: &SessionContext,
+    request: &RequestFrame,
+) -> ResponseFrame {
+    let Some(alias) = methods::resolve_alias(&request.method) else {
+        return dispatch_canonical(state, session, request).await;
+    };
+    let canonical = RequestFrame {
+        method: alias.target.to_owned(),
+        ..request.clone()
+    };
+    let mut response = dispatch_canonical(state, session, &canonical).await;
+    response.deprecation = Some(DeprecationWarning {
+        method: alias.alias.to_owned(),
+        replacement: alias.target.to_owned(),
+        message: format!("{} is deprecated; use {}", alias.alias, alias.target),
+    });
+    response
+}
+
+/// The shed, standby, and lifecycle gates. Run methods that pass hold a [`WorkGuard`] so a drain
+/// waits for them.
+async fn admit(state: &SharedState, method: &str) -> Result<Option<WorkGuard>, ErrorShape> {
+    if state.is_shedding_load() && policy::is_low_priority_method(method) {
+        state.count_shed_request();
+        let retry_after_ms =
+            u64::try_from(state.config().overload.cooldown.as_millis()).unwrap_or(u64::MAX);
+        return Err(ErrorShape::new(
+            ERROR_UNAVAILABLE,
+            format!("{method} is unavailable while the server sheds load"),
+        )
+        .with_retry(retry_after_ms));
+    }
+    if state.is_standby() && !policy::is_standby_method(method) {
+        let primary_url = replication::current_primary_url(state).await;
+        return Err(ErrorShape::new(
+            ERROR_UNAVAILABLE,
+            format!("{method} is unavailable on a read-only standby"),
+        )
+        .with_details(json!({ "primaryUrl": primary_url })));
+    }
+    if !policy::is_run_method(method) {
+        return Ok(None);
+    }
+    if let Some((message, retry_after_ms)) = state.lifecycle().rejection().await {
+        let mut error = ErrorShape::new(
+            ERROR_UNAVAILABLE,
+            format!("{method} is unavailable: {message}"),
+        )
+        .with_details(json!({
+            "maintenance": state.lifecycle().maintenance().await,
+            "draining": state.lifecycle().is_draining(),
+        }));
+        if let Some(retry_after_ms) = retry_after_ms {
+            error = error.with_retry(retry_after_ms);
+        }
+        return Err(error);
+    }
+    Ok(Some(state.lifecycle().begin_work()))
+}
+
+async fn dispatch_canonical(
+    state: &SharedState,
+    session: &SessionContext,
+    request: &RequestFrame,
 ) -> ResponseFrame {
     if request.method == "connect" {
         return response_error(
@@ -25,10 +190,46 @@ pub async fn dispatch_request(
         );
     }
 
+    // Gated here, after hooks and alias resolution, so a rewritten method can't slip past.
+    let _work = match admit(state, &request.method).await {
+        Ok(work) => work,
+        Err(error) => return response_error(request.id.clone(), error),
+    };
+
     if let Err(error) = policy::authorize_session(session, &request.method) {
         return response_error(request.id.clone(), error);
     }
 
+    let canonical;
+    let request =
+        match methods::canonicalize_session_params(&request.method, request.params.as_ref()) {
+            Ok(Some(params)) => {
+                canonical = RequestFrame {
+                    params: Some(params),
+                    ..request.clone()
+                };
+                &canonical
+            }
+            Ok(None) => request,
+            Err(error) => return response_error(request.id.clone(), error),
+        };
+
+    if let Some(params) = request.params.as_ref().filter(|params| !params.is_null())
+        && let Err(error) = methods::describe::validate_params(&request.method, params)
+    {
+        return response_error(request.id.clone(), error);
+    }
+
+    if let Some(result) =
+        federation::forward_if_remote(state, session, &request.method, request.params.as_ref())
+            .await
+    {
+        return match result {
+            Ok(payload) => response_ok(request.id.clone(), payload),
+            Err(error) => response_error(request.id.clone(), error),
+        };
+    }
+
     if policy::is_control_plane_write_method(&request.method) {
         let key = format!("{}:{}", session.client_id, request.method);
         let decision = state
@@ -64,12 +265,47 @@ pub async fn dispatch_request(
 
     let result = match request.method.as_str() {
         "health" => Ok(methods::health::handle(state, request.params.as_ref()).await),
+        "methods.describe" => methods::describe::handle(request.params.as_ref()),
+        "methods.schema" => methods::describe::handle_schema(request.params.as_ref()),
         "doctor.memory.status" => {
             methods::doctor::handle_memory_status(state, request.params.as_ref()).await
         }
+        "doctor.storage.slowQueries" => {
+            methods::doctor::handle_storage_slow_queries(state, request.params.as_ref()).await
+        }
+        "events.ack" => methods::events::handle_ack(state, session, request.params.as_ref()).await,
+        "events.subscribe" => {
+            methods::events::handle_subscribe(state, session, request.params.as_ref()).await
+        }
+        "events.unsubscribe" => {
+            methods::events::handle_unsubscribe(state, session, request.params.as_ref()).await
+        }
         "logs.tail" => methods::logs::handle_tail(state, request.params.as_ref()).await,
+        "logs.redaction.test" => {
+            methods::logs::handle_redaction_test(state, request.params.as_ref()).await
+        }
         "channels.status" => methods::channels::handle_status(state, request.params.as_ref()).await,
         "channels.logout" => methods::channels::handle_logout(state, request.params.as_ref()).await,
+        "channels.directory.list" => {
+            methods::channels::handle_directory_list(state, request.params.as_ref()).await
+        }
+        "channels.outbound.queue" => {
+            methods::channels::handle_outbound_queue(state, request.params.as_ref()).await
+        }
+        "identities.link" => methods::identities::handle_link(state, request.params.as_ref()).await,
+        "identities.unlink" => {
+            methods::identities::handle_unlink(state, request.params.as_ref()).await
+        }
+        "identities.list" => methods::identities::handle_list(state, request.params.as_ref()).await,
+        "privacy.export" => {
+            methods::privacy::handle_export(state, session, request.params.as_ref()).await
+        }
+        "privacy.delete" => {
+            methods::privacy::handle_delete(state, session, request.params.as_ref()).await
+        }
+        "privacy.audit.list" => {
+            methods::privacy::handle_audit_list(state, request.params.as_ref()).await
+        }
         "status" => Ok(methods::status::handle(state, session).await),
         "usage.status" => methods::usage::handle_status(state, request.params.as_ref()).await,
         "usage.cost" => methods::usage::handle_cost(state, request.params.as_ref()).await,
@@ -86,6 +322,12 @@ pub async fn dispatch_request(
         "config.apply" => methods::config::handle_apply(state, request.params.as_ref()).await,
         "config.patch" => methods::config::handle_patch(state, request.params.as_ref()).await,
         "config.schema" => Ok(methods::config::handle_schema()),
+        "config.entries.bulkSet" => {
+            methods::config::handle_entries_bulk_set(state, request.params.as_ref()).await
+        }
+        "config.entries.bulkDelete" => {
+            methods::config::handle_entries_bulk_delete(state, request.params.as_ref()).await
+        }
         "exec.approvals.get" => {
             methods::approvals::handle_exec_approvals_get(state, request.params.as_ref()).await
         }
@@ -118,14 +360,47 @@ pub async fn dispatch_request(
             )
             .await
         }
-        "wizard.start" => methods::wizard::handle_start(state, request.params.as_ref()).await,
+        "approval.link.create" => {
+            methods::approval_links::handle_create(state, session, request.params.as_ref()).await
+        }
+        "approval.link.get" => {
+            methods::approval_links::handle_get(state, session, request.params.as_ref()).await
+        }
+        "approval.link.resolve" => {
+            methods::approval_links::handle_resolve(state, session, request.params.as_ref()).await
+        }
+        "federation.invite" => methods::federation::handle_invite(state).await,
+        "federation.pair" => methods::federation::handle_pair(state, request.params.as_ref()).await,
+        "federation.peers.list" => methods::federation::handle_peers_list(state).await,
+        "federation.unpair" => {
+            methods::federation::handle_unpair(state, request.params.as_ref()).await
+        }
+        "replication.status" => methods::replication::handle_status(state).await,
+        "replication.promote" => {
+            methods::replication::handle_promote(state, request.params.as_ref()).await
+        }
+        "secrets.status" => methods::secrets::handle_status(state),
+        "exec.run" => methods::exec::handle_run(state, session, request.params.as_ref()).await,
+        "wizard.start" => {
+            methods::wizard::handle_start(state, session, request.params.as_ref()).await
+        }
         "wizard.next" => methods::wizard::handle_next(state, request.params.as_ref()).await,
         "wizard.cancel" => methods::wizard::handle_cancel(state, request.params.as_ref()).await,
         "wizard.status" => methods::wizard::handle_status(state, request.params.as_ref()).await,
         "talk.config" => methods::talk::handle_config(state, request.params.as_ref()).await,
         "talk.mode" => methods::talk::handle_mode(state, request.params.as_ref()).await,
         "models.list" => methods::models::handle_list(state, request.params.as_ref()).await,
-        "tools.catalog" => methods::tools::handle_catalog(state, request.params.as_ref()),
+        "tools.catalog" => methods::tools::handle_catalog(state, request.params.as_ref()).await,
+        "tools.register" => methods::tools::handle_register(state, request.params.as_ref()).await,
+        "tools.unregister" => {
+            methods::tools::handle_unregister(state, request.params.as_ref()).await
+        }
+        "tools.grant" => methods::tools::handle_grant(state, request.params.as_ref()).await,
+        "tools.revoke" => methods::tools::handle_revoke(state, request.params.as_ref()).await,
+        "tools.call" => methods::tools::handle_call(state, request.params.as_ref()).await,
+        "tools.calls.list" => {
+            methods::tools::handle_calls_list(state, request.params.as_ref()).await
+        }
         "agents.list" => methods::agents::handle_list(state, request.params.as_ref()).await,
         "agents.create" => methods::agents::handle_create(state, request.params.as_ref()).await,
         "agents.update" => methods::agents::handle_update(state, request.params.as_ref()).await,
@@ -144,18 +419,33 @@ pub async fn dispatch_request(
         "skills.install" => methods::skills::handle_install(state, request.params.as_ref()).await,
         "skills.update" => methods::skills::handle_update(state, request.params.as_ref()).await,
         "update.run" => methods::update::handle_run(state, session, request.params.as_ref()).await,
+        "db.migrateTo" => {
+            methods::db::handle_migrate_to(state, session, request.params.as_ref()).await
+        }
+        "snapshot.publish" => methods::db::handle_snapshot_publish(state, session).await,
         "voicewake.get" => methods::voicewake::handle_get(state, request.params.as_ref()).await,
         "voicewake.set" => methods::voicewake::handle_set(state, request.params.as_ref()).await,
-        "sessions.list" => methods::sessions::handle_list(state, request.params.as_ref()).await,
+        "sessions.list" => {
+            methods::sessions::handle_list(state, session, request.params.as_ref()).await
+        }
+        "sessions.tags.list" => methods::sessions::handle_tags_list(state).await,
         "sessions.preview" => {
             methods::sessions::handle_preview(state, request.params.as_ref()).await
         }
         "sessions.patch" => methods::sessions::handle_patch(state, request.params.as_ref()).await,
+        "sessions.bulkPatch" => {
+            methods::sessions::handle_bulk_patch(state, request.params.as_ref()).await
+        }
         "sessions.reset" => methods::sessions::handle_reset(state).await,
         "sessions.delete" => methods::sessions::handle_delete(state, request.params.as_ref()).await,
         "sessions.compact" => {
             methods::sessions::handle_compact(state, request.params.as_ref()).await
         }
+        "session.kv.get" => methods::session_kv::handle_get(state, request.params.as_ref()).await,
+        "session.kv.set" => methods::session_kv::handle_set(state, request.params.as_ref()).await,
+        "session.kv.delete" => {
+            methods::session_kv::handle_delete(state, request.params.as_ref()).await
+        }
         "last-heartbeat" => {
             methods::system::handle_last_heartbeat(state, request.params.as_ref()).await
         }
@@ -194,37 +484,121 @@ pub async fn dispatch_request(
         "device.token.revoke" => {
             methods::device::handle_token_revoke(state, request.params.as_ref()).await
         }
+        "device.pair.bulkApprove" => {
+            methods::device::handle_pair_bulk_approve(state, request.params.as_ref()).await
+        }
+        "device.token.bulkRevoke" => {
+            methods::device::handle_token_bulk_revoke(state, request.params.as_ref()).await
+        }
+        "apikeys.list" => methods::apikeys::handle_list(state, request.params.as_ref()).await,
+        "apikeys.create" => methods::apikeys::handle_create(state, request.params.as_ref()).await,
+        "apikeys.rotate" => methods::apikeys::handle_rotate(state, request.params.as_ref()).await,
+        "apikeys.revoke" => methods::apikeys::handle_revoke(state, request.params.as_ref()).await,
         "node.rename" => methods::nodes::handle_rename(state, request.params.as_ref()).await,
         "node.list" => methods::nodes::handle_list(state, request.params.as_ref()).await,
         "node.describe" => methods::nodes::handle_describe(state, request.params.as_ref()).await,
         "node.invoke" => methods::nodes::handle_invoke(state, request.params.as_ref()).await,
+        "node.invoke.pending" => {
+            methods::nodes::handle_invoke_pending(state, request.params.as_ref()).await
+        }
+        "node.invoke.cancel" => {
+            methods::nodes::handle_invoke_cancel(state, request.params.as_ref()).await
+        }
         "node.invoke.result" => {
             methods::nodes::handle_invoke_result(state, request.params.as_ref()).await
         }
         "node.event" => methods::nodes::handle_event(state, session, request.params.as_ref()).await,
+        "node.metadata.update" => {
+            methods::nodes::handle_metadata_update(state, session, request.params.as_ref()).await
+        }
+        "node.latency.report" => {
+            methods::nodes::handle_latency_report(state, session, request.params.as_ref()).await
+        }
+        "node.affinity.list" => {
+            methods::nodes::handle_affinity_list(state, request.params.as_ref()).await
+        }
+        "node.metadata.history" => {
+            methods::nodes::handle_metadata_history(state, request.params.as_ref()).await
+        }
+        "node.file.push" => methods::nodes::handle_file_push(state, request.params.as_ref()).await,
+        "node.file.pull" => methods::nodes::handle_file_pull(state, request.params.as_ref()).await,
+        "node.file.status" => {
+            methods::nodes::handle_file_status(state, request.params.as_ref()).await
+        }
+        "node.file.list" => methods::nodes::handle_file_list(state, request.params.as_ref()).await,
+        "node.file.cancel" => {
+            methods::nodes::handle_file_cancel(state, request.params.as_ref()).await
+        }
+        "node.geofence.set" => {
+            methods::nodes::handle_geofence_set(state, request.params.as_ref()).await
+        }
+        "node.geofence.list" => {
+            methods::nodes::handle_geofence_list(state, request.params.as_ref()).await
+        }
+        "node.geofence.remove" => {
+            methods::nodes::handle_geofence_remove(state, request.params.as_ref()).await
+        }
         "cron.list" => methods::cron::handle_list(state, request.params.as_ref()).await,
         "cron.status" => methods::cron::handle_status(state, request.params.as_ref()).await,
+        "cron.describe" => methods::cron::handle_describe(state, request.params.as_ref()).await,
         "cron.add" => methods::cron::handle_add(state, request.params.as_ref()).await,
         "cron.update" => methods::cron::handle_update(state, request.params.as_ref()).await,
         "cron.remove" => methods::cron::handle_remove(state, request.params.as_ref()).await,
         "cron.run" => methods::cron::handle_run(state, request.params.as_ref()).await,
         "cron.runs" => methods::cron::handle_runs(state, request.params.as_ref()).await,
+        "cron.runs.tail" => methods::cron::handle_runs_tail(state, request.params.as_ref()).await,
+        "cron.templates.list" => methods::cron::handle_templates_list(state).await,
+        "cron.templates.set" => {
+            methods::cron::handle_templates_set(state, request.params.as_ref()).await
+        }
+        "cron.templates.remove" => {
+            methods::cron::handle_templates_remove(state, request.params.as_ref()).await
+        }
         "system-presence" => {
             methods::system::handle_system_presence(state, request.params.as_ref()).await
         }
         "system-event" => {
             methods::system::handle_system_event(state, session, request.params.as_ref()).await
         }
+        "system.shutdown" => {
+            methods::system::handle_stop(state, StopKind::Shutdown, request.params.as_ref()).await
+        }
+        "system.restart" => {
+            methods::system::handle_stop(state, StopKind::Restart, request.params.as_ref()).await
+        }
+        "system.maintenance" => {
+            methods::system::handle_maintenance(state, request.params.as_ref()).await
+        }
         "send" => methods::send::handle_send(state, session, request.params.as_ref()).await,
         "agent" => methods::agent::handle_agent(state, session, request.params.as_ref()).await,
         "agent.identity.get" => {
             methods::agent::handle_agent_identity(state, request.params.as_ref()).await
         }
         "agent.wait" => methods::agent::handle_agent_wait(state, request.params.as_ref()).await,
+        "agent.retry" => methods::agent::handle_agent_retry(state, request.params.as_ref()).await,
+        "agent.replay" => methods::agent::handle_agent_replay(state, request.params.as_ref()).await,
         "browser.request" => methods::browser::handle_request(request.params.as_ref()).await,
         "chat.history" => methods::chat::handle_history(state, request.params.as_ref()).await,
+        "chat.search" => methods::chat::handle_search(state, request.params.as_ref()).await,
         "chat.abort" => methods::chat::handle_abort(state, request.params.as_ref()).await,
+        "chat.deliveryStatus" => {
+            methods::chat::handle_delivery_status(state, request.params.as_ref()).await
+        }
         "chat.send" => methods::chat::handle_send(state, session, request.params.as_ref()).await,
+        "chat.pin" => methods::chat::handle_pin(state, session, request.params.as_ref()).await,
+        "chat.markRead" => {
+            methods::chat::handle_mark_read(state, session, request.params.as_ref()).await
+        }
+        "chat.unpin" => methods::chat::handle_unpin(state, session, request.params.as_ref()).await,
+        "chat.takeover.start" => {
+            methods::takeover::handle_start(state, session, request.params.as_ref()).await
+        }
+        "chat.takeover.end" => {
+            methods::takeover::handle_end(state, session, request.params.as_ref()).await
+        }
+        "chat.takeover.reply" => {
+            methods::takeover::handle_reply(state, session, request.params.as_ref()).await
+        }
         _ => Err(ErrorShape::new(
             ERROR_INVALID_REQUEST,
             format!("unknown method: {}", request.method),
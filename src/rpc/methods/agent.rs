@@ -1,18 +1,29 @@
 use std::time::Duration;
 
 use serde::Deserialize;
-use serde_json::{Value, json};
-use tokio::time::{Instant, sleep};
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+use tokio::time::{Instant, sleep, timeout};
 
 use crate::{
-    application::state::SharedState,
+    application::{
+        agent_backend::{AgentBackend, AgentDeltaSender, AgentTurn},
+        config::GuardrailAction,
+        cost_budget, inline_exec,
+        state::SharedState,
+    },
     domain::models::{AgentRunRecord, ChatMessage, SessionRecord},
     rpc::{
         SessionContext,
         dispatcher::map_domain_error,
-        methods::{parse_optional_params, parse_required_params},
+        methods::{
+            agents::{self, RETRY_CLASS_BACKEND_ERROR, RETRY_CLASS_TIMEOUT},
+            parse_optional_params, parse_required_params, session_kv,
+        },
+        schema::rpc_params,
     },
-    storage::now_unix_ms,
+    security::signatures::hex_encode,
+    storage::{IdempotencyClaim, now_unix_ms},
 };
 
 const RUN_STATUS_QUEUED: &str = "queued";
@@ -23,49 +34,97 @@ const RUN_STATUS_ABORTED: &str = "aborted";
 const AGENT_EVENT_SEQ_START: u64 = 1;
 const AGENT_EVENT_SEQ_ASSISTANT: u64 = 2;
 const AGENT_EVENT_SEQ_END: u64 = 3;
+const ATTEMPT_TRIGGER_INITIAL: &str = "initial";
+const ATTEMPT_TRIGGER_AUTO: &str = "auto";
+const ATTEMPT_TRIGGER_MANUAL: &str = "manual";
+/// Suggested wait before retrying a run whose idempotency key another instance holds.
+const HELD_RUN_RETRY_MS: u64 = 1_000;
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AgentRunParams {
-    #[serde(default)]
-    run_id: Option<String>,
-    #[serde(default)]
-    idempotency_key: Option<String>,
-    #[serde(default)]
-    agent_id: Option<String>,
-    #[serde(default)]
-    session_key: Option<String>,
-    #[serde(default)]
-    input: Option<String>,
-    #[serde(default)]
-    message: Option<String>,
-    #[serde(default)]
-    text: Option<String>,
-    #[serde(default)]
-    deferred: Option<bool>,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AgentWaitParams {
-    run_id: String,
-    #[serde(default)]
-    timeout_ms: Option<u64>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentRunParams {
+        #[serde(default)]
+        run_id: Option<String>,
+        #[serde(default)]
+        idempotency_key: Option<String>,
+        #[serde(default)]
+        agent_id: Option<String>,
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        input: Option<String>,
+        #[serde(default)]
+        message: Option<String>,
+        #[serde(default)]
+        text: Option<String>,
+        #[serde(default)]
+        deferred: Option<bool>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentWaitParams {
+        run_id: String,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentRetryParams {
+        run_id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentReplayParams {
+        run_id: String,
+        #[serde(default)]
+        backend: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AgentIdentityParams {
-    #[serde(default)]
-    agent_id: Option<String>,
-    #[serde(default)]
-    session_key: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentIdentityParams {
+        #[serde(default)]
+        agent_id: Option<String>,
+        #[serde(default)]
+        session_key: Option<String>,
+    }
 }
 
 pub async fn handle_agent(
     state: &SharedState,
     session: &SessionContext,
     params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    run_agent(state, session, params, None).await
+}
+
+/// Like [`handle_agent`], but streams the backend's reply text to `deltas` as it is produced.
+pub(crate) async fn handle_agent_streaming(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+    deltas: AgentDeltaSender,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    run_agent(state, session, params, Some(&deltas)).await
+}
+
+async fn run_agent(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+    deltas: Option<&AgentDeltaSender>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
     let parsed: AgentRunParams = parse_required_params("agent", params)?;
 
@@ -105,6 +164,33 @@ pub async fn handle_agent(
     {
         return resolve_existing_agent_run(existing, &session_key, &agent_id);
     }
+    if let Some(holder) =
+        claim_run_idempotency_key(state, &run_id, "agent", &agent_id, &session_key).await
+    {
+        // The claim may be this instance's own, taken just before the run was stored.
+        if let Some(existing) = state
+            .get_agent_run(&run_id)
+            .await
+            .map_err(map_domain_error)?
+        {
+            return resolve_existing_agent_run(existing, &session_key, &agent_id);
+        }
+        resolve_existing_agent_run(holder, &session_key, &agent_id)?;
+        return Err(run_held_elsewhere(&run_id));
+    }
+
+    if state
+        .guardrail_engaged(GuardrailAction::RefuseAgentRuns)
+        .await
+    {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_UNAVAILABLE,
+            "agent runs are paused: resource guardrails exceeded",
+        )
+        .with_retry(
+            u64::try_from(state.config().self_monitor_interval.as_millis()).unwrap_or(u64::MAX),
+        ));
+    }
 
     ensure_session_exists(state, &session_key).await?;
 
@@ -139,7 +225,7 @@ pub async fn handle_agent(
         ));
     }
 
-    run = execute_agent_run(state, run).await?;
+    run = execute_agent_run(state, run, ATTEMPT_TRIGGER_INITIAL, deltas).await?;
     Ok(agent_method_response(
         &run_id,
         &session_key,
@@ -252,9 +338,14 @@ async fn publish_chat_event_error(
         .await;
 }
 
+/// Runs the backend for `run`, re-dispatching failed attempts per the agent's retry policy.
+/// Every attempt is appended to `metadata.attempts`; `trigger` labels the first one. Reply text
+/// streams to `deltas` when given.
 async fn execute_agent_run(
     state: &SharedState,
     mut run: AgentRunRecord,
+    trigger: &str,
+    deltas: Option<&AgentDeltaSender>,
 ) -> Result<AgentRunRecord, crate::protocol::ErrorShape> {
     let Some(session_key) = run.session_key.clone() else {
         return Err(crate::protocol::ErrorShape::new(
@@ -293,11 +384,18 @@ async fn execute_agent_run(
         .metadata
         .get("originConnId")
         .and_then(Value::as_str)
-        .filter(|value| !value.trim().is_empty());
+        .filter(|value| !value.trim().is_empty())
+        .map(str::to_owned);
+
+    let pinned = state
+        .list_pinned_chat_messages(&session_key)
+        .await
+        .map_err(map_domain_error)?;
+    let scratchpad = session_kv::load_scratchpad(state, &session_key).await?;
 
     publish_agent_event(
         state,
-        target_conn_id,
+        target_conn_id.as_deref(),
         &run.id,
         &session_key,
         "lifecycle",
@@ -306,70 +404,179 @@ async fn execute_agent_run(
     )
     .await;
 
-    let output = format!("Echo: {}", run.input);
-    let messages = vec![
-        ChatMessage {
-            id: format!("msg-{}", uuid::Uuid::new_v4()),
-            role: "user".to_owned(),
-            text: run.input.clone(),
-            status: "final".to_owned(),
-            ts: run.updated_at_ms,
-            metadata: json!({ "runId": run.id }),
-        },
-        ChatMessage {
-            id: format!("msg-{}", uuid::Uuid::new_v4()),
-            role: "assistant".to_owned(),
-            text: output.clone(),
-            status: "final".to_owned(),
-            ts: run.updated_at_ms.saturating_add(1),
-            metadata: json!({ "runId": run.id }),
-        },
-    ];
-
-    if let Err(error) = state.append_chat_messages(&session_key, &messages).await {
-        let failed_at = now_unix_ms();
-        run.status = RUN_STATUS_ERROR.to_owned();
-        run.output = format!("agent execution failed while appending chat messages: {error}");
-        run.updated_at_ms = failed_at;
-        run.completed_at_ms = Some(failed_at);
-        let finalized = state
-            .finalize_agent_run_if_status(&run, RUN_STATUS_RUNNING)
-            .await
-            .map_err(map_domain_error)?;
-        if finalized {
-            publish_agent_event(
+    let backend = state.agent_backend().await;
+    let context = resolve_run_context(
+        state,
+        &run.agent_id,
+        &run.input,
+        &pinned,
+        &scratchpad,
+        backend.name(),
+    )
+    .await?;
+    if let Some(metadata) = run.metadata.as_object_mut() {
+        metadata.insert("context".to_owned(), context);
+    }
+    let agent_model = agents::agent_model(state, &run.agent_id).await?;
+    let model = match cost_budget::admit(state, &run.agent_id, &session_key).await {
+        Ok(Some(downgrade)) => {
+            if let Some(metadata) = run.metadata.as_object_mut() {
+                metadata.insert("downgradedFrom".to_owned(), json!(agent_model));
+            }
+            Some(downgrade)
+        }
+        Ok(None) => agent_model,
+        Err(error) => {
+            let failure = format!("agent run rejected: {}", error.message);
+            return fail_agent_run(
                 state,
-                target_conn_id,
-                &run.id,
+                run,
+                target_conn_id.as_deref(),
                 &session_key,
-                "lifecycle",
-                AGENT_EVENT_SEQ_END,
-                json!({
-                    "phase": "error",
-                    "error": run.output.as_str(),
-                }),
+                failure,
+                error,
             )
             .await;
-            if run_source(&run) == Some("chat.send") {
-                publish_chat_event_error(
-                    state,
-                    target_conn_id,
-                    &run.id,
-                    &session_key,
-                    run.output.as_str(),
-                )
-                .await;
+        }
+    };
+    if let Some(metadata) = run.metadata.as_object_mut() {
+        metadata.insert("model".to_owned(), json!(model));
+    }
+    let policy = agents::agent_retry_policy(state, &run.agent_id).await?;
+    let mut dispatch_attempt = 0_u32;
+    let reply = loop {
+        dispatch_attempt += 1;
+        let started_at_ms = now_unix_ms();
+        let outcome = respond_with_deadline(
+            backend.as_ref(),
+            AgentTurn {
+                run_id: &run.id,
+                agent_id: &run.agent_id,
+                session_key: &session_key,
+                input: &run.input,
+                model: model.as_deref(),
+                pinned: &pinned,
+                scratchpad: &scratchpad,
+            },
+            policy.timeout_ms,
+            deltas.cloned(),
+        )
+        .await;
+        let trigger = if dispatch_attempt == 1 {
+            trigger
+        } else {
+            ATTEMPT_TRIGGER_AUTO
+        };
+        match outcome {
+            Ok(output) => {
+                record_attempt(&mut run, trigger, started_at_ms, None);
+                break Ok(output);
+            }
+            Err((class, message)) => {
+                record_attempt(&mut run, trigger, started_at_ms, Some((class, &message)));
+                if !policy.retries(class, dispatch_attempt) {
+                    break Err(message);
+                }
+                // Persist the history so far; a run aborted meanwhile stops retrying.
+                run.updated_at_ms = now_unix_ms();
+                if !state
+                    .finalize_agent_run_if_status(&run, RUN_STATUS_RUNNING)
+                    .await
+                    .map_err(map_domain_error)?
+                    && let Some(latest) = state
+                        .get_agent_run(&run.id)
+                        .await
+                        .map_err(map_domain_error)?
+                {
+                    return Ok(latest);
+                }
+                sleep(policy.backoff_after(dispatch_attempt)).await;
+                if let Some(existing) = load_terminal_run(state, &run.id).await? {
+                    return Ok(existing);
+                }
             }
         }
-        if !finalized
-            && let Some(latest) = state
-                .get_agent_run(&run.id)
+    };
+    let reply = match reply {
+        Ok(output) => match agents::agent_inline_exec(state, &run.agent_id).await? {
+            Some(settings) => {
+                let (output, reports) =
+                    inline_exec::render(state, &settings, &run.agent_id, &output).await;
+                if !reports.is_empty()
+                    && let Some(metadata) = run.metadata.as_object_mut()
+                {
+                    metadata.insert("inlineExec".to_owned(), Value::Array(reports));
+                }
+                Ok(output)
+            }
+            None => Ok(output),
+        },
+        Err(message) => Err(message),
+    };
+    let appended = match reply {
+        Ok(output) => {
+            let messages = vec![
+                ChatMessage {
+                    id: format!("msg-{}", uuid::Uuid::new_v4()),
+                    role: "user".to_owned(),
+                    text: run.input.clone(),
+                    status: "final".to_owned(),
+                    ts: run.updated_at_ms,
+                    metadata: json!({ "runId": run.id }),
+                    pinned: false,
+                },
+                ChatMessage {
+                    id: format!("msg-{}", uuid::Uuid::new_v4()),
+                    role: "assistant".to_owned(),
+                    text: output.clone(),
+                    status: "final".to_owned(),
+                    ts: run.updated_at_ms.saturating_add(1),
+                    metadata: json!({ "runId": run.id }),
+                    pinned: false,
+                },
+            ];
+            state
+                .append_chat_messages(&session_key, &messages)
                 .await
-                .map_err(map_domain_error)?
-        {
-            return Ok(latest);
+                .map(|()| output)
+                .map_err(|error| {
+                    (
+                        format!("agent execution failed while appending chat messages: {error}"),
+                        map_domain_error(error),
+                    )
+                })
+        }
+        Err(message) => {
+            let failure = format!("agent backend {} failed: {message}", backend.name());
+            let failure = if dispatch_attempt > 1 {
+                format!("{failure} (after {dispatch_attempt} attempts)")
+            } else {
+                failure
+            };
+            Err((
+                failure.clone(),
+                crate::protocol::ErrorShape::new(crate::protocol::ERROR_UNAVAILABLE, failure),
+            ))
+        }
+    };
+
+    let output = match appended {
+        Ok(output) => output,
+        Err((failure, error_shape)) => {
+            return fail_agent_run(
+                state,
+                run,
+                target_conn_id.as_deref(),
+                &session_key,
+                failure,
+                error_shape,
+            )
+            .await;
         }
-        return Err(map_domain_error(error));
+    };
+
+    if let Some(existing) = load_terminal_run(state, &run.id).await? {
+        return Ok(existing);
     }
 
     if let Some(existing) = load_terminal_run(state, &run.id).await? {
@@ -381,14 +588,16 @@ async fn execute_agent_run(
     run.output = output;
     run.updated_at_ms = completed_at;
     run.completed_at_ms = Some(completed_at);
+    cost_budget::record_cost(state, &mut run, model.as_deref());
     let finalized = state
         .finalize_agent_run_if_status(&run, RUN_STATUS_RUNNING)
         .await
         .map_err(map_domain_error)?;
     if finalized {
+        cost_budget::publish_crossings(state, &run).await;
         publish_agent_event(
             state,
-            target_conn_id,
+            target_conn_id.as_deref(),
             &run.id,
             &session_key,
             "assistant",
@@ -398,7 +607,7 @@ async fn execute_agent_run(
         .await;
         publish_agent_event(
             state,
-            target_conn_id,
+            target_conn_id.as_deref(),
             &run.id,
             &session_key,
             "lifecycle",
@@ -409,7 +618,7 @@ async fn execute_agent_run(
         if run_source(&run) == Some("chat.send") {
             publish_chat_event_final(
                 state,
-                target_conn_id,
+                target_conn_id.as_deref(),
                 &run.id,
                 &session_key,
                 run.output.as_str(),
@@ -433,6 +642,213 @@ async fn execute_agent_run(
     Ok(run)
 }
 
+/// Calls the backend, failing with class `timeout` past `timeout_ms` and `backendError` on `Err`.
+async fn respond_with_deadline(
+    backend: &dyn AgentBackend,
+    turn: AgentTurn<'_>,
+    timeout_ms: Option<u64>,
+    deltas: Option<AgentDeltaSender>,
+) -> Result<String, (&'static str, String)> {
+    let respond = match deltas {
+        Some(deltas) => backend.respond_streaming(turn, deltas),
+        None => backend.respond(turn),
+    };
+    let reply = match timeout_ms {
+        Some(timeout_ms) => timeout(Duration::from_millis(timeout_ms), respond)
+            .await
+            .map_err(|_| {
+                (
+                    RETRY_CLASS_TIMEOUT,
+                    format!("timed out after {timeout_ms}ms"),
+                )
+            })?,
+        None => respond.await,
+    };
+    reply.map_err(|message| (RETRY_CLASS_BACKEND_ERROR, message))
+}
+
+fn record_attempt(
+    run: &mut AgentRunRecord,
+    trigger: &str,
+    started_at_ms: u64,
+    failure: Option<(&str, &str)>,
+) {
+    if !run.metadata.is_object() {
+        run.metadata = json!({});
+    }
+    let Some(metadata) = run.metadata.as_object_mut() else {
+        return;
+    };
+    let attempts = metadata
+        .entry("attempts")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if !attempts.is_array() {
+        *attempts = Value::Array(Vec::new());
+    }
+    if let Some(attempts) = attempts.as_array_mut() {
+        attempts.push(json!({
+            "attempt": attempts.len() + 1,
+            "trigger": trigger,
+            "startedAtMs": started_at_ms,
+            "endedAtMs": now_unix_ms(),
+            "status": if failure.is_some() { RUN_STATUS_ERROR } else { RUN_STATUS_COMPLETED },
+            "errorClass": failure.map(|(class, _)| class),
+            "error": failure.map(|(_, message)| message),
+        }));
+    }
+}
+
+/// Everything the backend saw for a turn, kept under `metadata.context` so `agent.replay` can
+/// re-run it later.
+pub(crate) async fn resolve_run_context(
+    state: &SharedState,
+    agent_id: &str,
+    input: &str,
+    pinned: &[ChatMessage],
+    scratchpad: &Map<String, Value>,
+    backend: &str,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let identity = agents::load_agents(state)
+        .await?
+        .into_iter()
+        .find(|agent| agent.agent_id == agent_id)
+        .map_or_else(
+            || json!({ "agentId": agent_id }),
+            |agent| {
+                json!({
+                    "agentId": agent.agent_id,
+                    "name": agent.name,
+                    "model": agent.model,
+                    "avatar": agent.avatar,
+                })
+            },
+        );
+    Ok(json!({
+        "identity": identity,
+        "input": input,
+        "history": pinned,
+        "scratchpad": scratchpad,
+        "configHash": config_snapshot_hash(state).await?,
+        "backend": backend,
+        "resolvedAtMs": now_unix_ms(),
+    }))
+}
+
+async fn config_snapshot_hash(state: &SharedState) -> Result<String, crate::protocol::ErrorShape> {
+    let config = state.get_config_doc().await.map_err(map_domain_error)?;
+    let bytes = serde_json::to_vec(&config).unwrap_or_default();
+    Ok(hex_encode(&Sha256::digest(bytes)))
+}
+
+fn run_attempts(run: &AgentRunRecord) -> Value {
+    run.metadata
+        .get("attempts")
+        .filter(|attempts| attempts.is_array())
+        .cloned()
+        .unwrap_or_else(|| json!([]))
+}
+
+async fn fail_agent_run(
+    state: &SharedState,
+    mut run: AgentRunRecord,
+    target_conn_id: Option<&str>,
+    session_key: &str,
+    failure: String,
+    error_shape: crate::protocol::ErrorShape,
+) -> Result<AgentRunRecord, crate::protocol::ErrorShape> {
+    let failed_at = now_unix_ms();
+    run.status = RUN_STATUS_ERROR.to_owned();
+    run.output = failure;
+    run.updated_at_ms = failed_at;
+    run.completed_at_ms = Some(failed_at);
+    let finalized = state
+        .finalize_agent_run_if_status(&run, RUN_STATUS_RUNNING)
+        .await
+        .map_err(map_domain_error)?;
+    if finalized {
+        publish_agent_event(
+            state,
+            target_conn_id,
+            &run.id,
+            session_key,
+            "lifecycle",
+            AGENT_EVENT_SEQ_END,
+            json!({
+                "phase": "error",
+                "error": run.output.as_str(),
+            }),
+        )
+        .await;
+        if run_source(&run) == Some("chat.send") {
+            publish_chat_event_error(
+                state,
+                target_conn_id,
+                &run.id,
+                session_key,
+                run.output.as_str(),
+            )
+            .await;
+        }
+    }
+    if !finalized
+        && let Some(latest) = state
+            .get_agent_run(&run.id)
+            .await
+            .map_err(map_domain_error)?
+    {
+        return Ok(latest);
+    }
+    Err(error_shape)
+}
+
+/// Claims `run_id` in the shared idempotency store. When another instance already holds it,
+/// returns the holder's source, agent and session as a run record so callers can apply the same
+/// method/agent/session checks they use for local runs before answering with
+/// [`run_held_elsewhere`].
+pub(crate) async fn claim_run_idempotency_key(
+    state: &SharedState,
+    run_id: &str,
+    source: &str,
+    agent_id: &str,
+    session_key: &str,
+) -> Option<AgentRunRecord> {
+    let fingerprint = json!({
+        "source": source,
+        "agentId": agent_id,
+        "sessionKey": session_key,
+    })
+    .to_string();
+    let IdempotencyClaim::Held(holder) = state.claim_idempotency_key(run_id, &fingerprint).await
+    else {
+        return None;
+    };
+
+    let holder: Value = serde_json::from_str(&holder).unwrap_or(Value::Null);
+    let field = |name: &str| holder.get(name).and_then(Value::as_str).map(str::to_owned);
+    Some(AgentRunRecord {
+        id: run_id.to_owned(),
+        agent_id: field("agentId").unwrap_or_default(),
+        input: String::new(),
+        output: String::new(),
+        status: RUN_STATUS_RUNNING.to_owned(),
+        session_key: field("sessionKey"),
+        metadata: json!({ "source": field("source") }),
+        created_at_ms: 0,
+        updated_at_ms: 0,
+        completed_at_ms: None,
+    })
+}
+
+/// The run behind a claimed idempotency key lives on another instance, whose state this one
+/// cannot read; the caller should retry, ideally against that instance.
+pub(crate) fn run_held_elsewhere(run_id: &str) -> crate::protocol::ErrorShape {
+    crate::protocol::ErrorShape::new(
+        crate::protocol::ERROR_UNAVAILABLE,
+        format!("run {run_id} is in progress on another gateway instance"),
+    )
+    .with_retry(HELD_RUN_RETRY_MS)
+}
+
 fn resolve_existing_agent_run(
     existing: AgentRunRecord,
     requested_session_key: &str,
@@ -485,6 +901,229 @@ fn resolve_existing_agent_run(
     }))
 }
 
+pub async fn handle_agent_retry(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: AgentRetryParams = parse_required_params("agent.retry", params)?;
+    let run_id = trim_non_empty(parsed.run_id).ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid agent.retry params: runId is required",
+        )
+    })?;
+    let Some(mut run) = state
+        .get_agent_run(&run_id)
+        .await
+        .map_err(map_domain_error)?
+    else {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("agent run \"{run_id}\" not found"),
+        ));
+    };
+    if run.status != RUN_STATUS_ERROR {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!(
+                "agent run \"{run_id}\" is {}; only failed runs can be retried",
+                run.status
+            ),
+        ));
+    }
+
+    run.status = RUN_STATUS_RUNNING.to_owned();
+    run.output = String::new();
+    run.updated_at_ms = now_unix_ms();
+    run.completed_at_ms = None;
+    if !state
+        .finalize_agent_run_if_status(&run, RUN_STATUS_ERROR)
+        .await
+        .map_err(map_domain_error)?
+    {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("agent run \"{run_id}\" is already being retried"),
+        ));
+    }
+
+    let session_key = run.session_key.clone().unwrap_or_default();
+    let run = execute_agent_run(state, run, ATTEMPT_TRIGGER_MANUAL, None).await?;
+    let mut response = agent_method_response(
+        &run_id,
+        &session_key,
+        Some(run.output.as_str()),
+        run.status.as_str(),
+    );
+    response["attempts"] = run_attempts(&run);
+    Ok(response)
+}
+
+pub async fn handle_agent_replay(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: AgentReplayParams = parse_required_params("agent.replay", params)?;
+    let invalid = |message: String| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid agent.replay params: {message}"),
+        )
+    };
+    let run_id =
+        trim_non_empty(parsed.run_id).ok_or_else(|| invalid("runId is required".to_owned()))?;
+    let run = state
+        .get_agent_run(&run_id)
+        .await
+        .map_err(map_domain_error)?
+        .ok_or_else(|| invalid(format!("agent run \"{run_id}\" not found")))?;
+    if run.status != RUN_STATUS_COMPLETED && run.status != RUN_STATUS_ERROR {
+        return Err(invalid(format!(
+            "agent run \"{run_id}\" is {}; only finished runs can be replayed",
+            run.status
+        )));
+    }
+    let Some(context) = run
+        .metadata
+        .get("context")
+        .filter(|value| value.is_object())
+    else {
+        return Err(invalid(format!(
+            "agent run \"{run_id}\" has no recorded context"
+        )));
+    };
+
+    let backend = match parsed.backend.and_then(trim_non_empty) {
+        Some(name) => state
+            .find_agent_backend(&name)
+            .await
+            .ok_or_else(|| invalid(format!("unknown backend \"{name}\"")))?,
+        None => state.agent_backend().await,
+    };
+    let input = context
+        .get("input")
+        .and_then(Value::as_str)
+        .unwrap_or(run.input.as_str());
+    let history = context
+        .get("history")
+        .cloned()
+        .and_then(|history| serde_json::from_value::<Vec<ChatMessage>>(history).ok())
+        .unwrap_or_default();
+    let scratchpad = context
+        .get("scratchpad")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let session_key = run.session_key.clone().unwrap_or_default();
+    let replay_id = format!("replay-{}", uuid::Uuid::new_v4());
+    let policy = agents::agent_retry_policy(state, &run.agent_id).await?;
+
+    // Replays never touch history or the original run; only the backend is called again.
+    let started_at_ms = now_unix_ms();
+    let outcome = respond_with_deadline(
+        backend.as_ref(),
+        AgentTurn {
+            run_id: &replay_id,
+            agent_id: &run.agent_id,
+            session_key: &session_key,
+            input,
+            model: run.metadata.get("model").and_then(Value::as_str),
+            pinned: &history,
+            scratchpad: &scratchpad,
+        },
+        policy.timeout_ms,
+        None,
+    )
+    .await;
+    let (replay, output) = match outcome {
+        Ok(output) => (
+            json!({ "status": RUN_STATUS_COMPLETED, "output": output }),
+            output,
+        ),
+        Err((class, message)) => (
+            json!({ "status": RUN_STATUS_ERROR, "errorClass": class, "error": message }),
+            format!("agent backend {} failed: {message}", backend.name()),
+        ),
+    };
+
+    let recorded_hash = context.get("configHash").and_then(Value::as_str);
+    let current_hash = config_snapshot_hash(state).await?;
+    Ok(json!({
+        "runId": run_id,
+        "replayId": replay_id,
+        "backend": {
+            "recorded": context.get("backend"),
+            "replay": backend.name(),
+        },
+        "configHash": {
+            "recorded": recorded_hash,
+            "current": current_hash,
+            "changed": recorded_hash != Some(current_hash.as_str()),
+        },
+        "context": context,
+        "original": {
+            "status": run.status,
+            "output": run.output,
+        },
+        "replay": replay,
+        "durationMs": now_unix_ms().saturating_sub(started_at_ms),
+        "identical": output == run.output,
+        "diff": diff_lines(&run.output, &output),
+    }))
+}
+
+/// Line diff from `before` to `after` as `equal`, `delete`, and `insert` hunks along the longest
+/// common subsequence. Very long outputs fall back to replacing everything.
+fn diff_lines(before: &str, after: &str) -> Vec<Value> {
+    const MAX_DIFF_CELLS: usize = 4_000_000;
+
+    let before = before.lines().collect::<Vec<_>>();
+    let after = after.lines().collect::<Vec<_>>();
+    let mut ops = Vec::new();
+    if before.len().saturating_mul(after.len()) > MAX_DIFF_CELLS {
+        ops.extend(before.iter().map(|line| ("delete", *line)));
+        ops.extend(after.iter().map(|line| ("insert", *line)));
+    } else {
+        // lcs[i][j] is the common subsequence length of before[i..] and after[j..].
+        let mut lcs = vec![vec![0_usize; after.len() + 1]; before.len() + 1];
+        for i in (0..before.len()).rev() {
+            for j in (0..after.len()).rev() {
+                lcs[i][j] = if before[i] == after[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < before.len() || j < after.len() {
+            if i < before.len() && j < after.len() && before[i] == after[j] {
+                ops.push(("equal", before[i]));
+                i += 1;
+                j += 1;
+            } else if j == after.len() || (i < before.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                ops.push(("delete", before[i]));
+                i += 1;
+            } else {
+                ops.push(("insert", after[j]));
+                j += 1;
+            }
+        }
+    }
+
+    let mut hunks: Vec<(&str, Vec<&str>)> = Vec::new();
+    for (op, line) in ops {
+        match hunks.last_mut() {
+            Some((last, lines)) if *last == op => lines.push(line),
+            _ => hunks.push((op, vec![line])),
+        }
+    }
+    hunks
+        .into_iter()
+        .map(|(op, lines)| json!({ "op": op, "lines": lines }))
+        .collect()
+}
+
 pub async fn handle_agent_wait(
     state: &SharedState,
     params: Option<&Value>,
@@ -521,7 +1160,9 @@ pub async fn handle_agent_wait(
                     let mut claimed_run = run;
                     claimed_run.status = RUN_STATUS_RUNNING.to_owned();
                     claimed_run.updated_at_ms = updated_at_ms;
-                    let claimed_run = execute_agent_run(state, claimed_run).await?;
+                    let claimed_run =
+                        execute_agent_run(state, claimed_run, ATTEMPT_TRIGGER_INITIAL, None)
+                            .await?;
                     return Ok(agent_wait_payload(&run_id, &claimed_run));
                 }
             }
@@ -566,6 +1207,7 @@ fn agent_wait_payload(run_id: &str, run: &AgentRunRecord) -> Value {
             "output": output,
             "sessionKey": run.session_key,
         },
+        "attempts": run_attempts(run),
     })
 }
 
@@ -669,3 +1311,29 @@ async fn ensure_session_exists(
         .await
         .map_err(map_domain_error)
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::diff_lines;
+
+    #[test]
+    fn diff_lines_groups_changes_into_hunks() {
+        assert_eq!(
+            diff_lines("a\nb\nc\nd", "a\nx\nc\nd\ne"),
+            vec![
+                json!({ "op": "equal", "lines": ["a"] }),
+                json!({ "op": "delete", "lines": ["b"] }),
+                json!({ "op": "insert", "lines": ["x"] }),
+                json!({ "op": "equal", "lines": ["c", "d"] }),
+                json!({ "op": "insert", "lines": ["e"] }),
+            ]
+        );
+        assert_eq!(
+            diff_lines("same", "same"),
+            vec![json!({ "op": "equal", "lines": ["same"] })]
+        );
+        assert!(diff_lines("", "").is_empty());
+    }
+}
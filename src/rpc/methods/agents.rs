@@ -1,14 +1,24 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use tokio::fs;
 
 use crate::{
-    application::state::SharedState,
+    application::{
+        state::SharedState,
+        workspace_quota::{self, WorkspaceUsage},
+    },
     rpc::{
         dispatcher::map_domain_error,
-        methods::{parse_optional_params, parse_required_params},
+        methods::{
+            FieldSelection, approvals::glob_matches, parse_optional_params, parse_required_params,
+        },
+        schema::rpc_params,
     },
     storage::now_unix_ms,
 };
@@ -26,6 +36,17 @@ const DEFAULT_BOOTSTRAP_FILENAME: &str = "BOOTSTRAP.md";
 const DEFAULT_MEMORY_FILENAME: &str = "MEMORY.md";
 const DEFAULT_MEMORY_ALT_FILENAME: &str = "memory.md";
 
+const MEMORY_FILE_NAMES: &[&str] = &[DEFAULT_MEMORY_FILENAME, DEFAULT_MEMORY_ALT_FILENAME];
+
+pub(crate) const RETRY_CLASS_BACKEND_ERROR: &str = "backendError";
+pub(crate) const RETRY_CLASS_TIMEOUT: &str = "timeout";
+const RETRY_CLASSES: &[&str] = &[RETRY_CLASS_BACKEND_ERROR, RETRY_CLASS_TIMEOUT];
+const MAX_RETRY_ATTEMPTS: u32 = 10;
+const MAX_RETRY_BACKOFF_MS: u64 = 10 * 60 * 1_000;
+const MAX_INLINE_EXEC_TIMEOUT_MS: u64 = 30_000;
+const MAX_INLINE_EXEC_BLOCKS: usize = 10;
+const MAX_DENIED_PATTERNS: usize = 32;
+
 const BOOTSTRAP_FILE_NAMES: &[&str] = &[
     DEFAULT_AGENTS_FILENAME,
     DEFAULT_SOUL_FILENAME,
@@ -50,85 +71,301 @@ const ALLOWED_FILE_NAMES: &[&str] = &[
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct AgentRecord {
-    agent_id: String,
-    name: String,
-    workspace: String,
-    model: Option<String>,
-    avatar: Option<String>,
+pub(crate) struct AgentRecord {
+    pub(crate) agent_id: String,
+    pub(crate) name: String,
+    pub(crate) workspace: String,
+    pub(crate) model: Option<String>,
+    pub(crate) avatar: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) emoji: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) retry_policy: Option<AgentRetryPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) inline_exec: Option<AgentInlineExec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) fs_policy: Option<AgentFsPolicy>,
     created_at_ms: u64,
     updated_at_ms: u64,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AgentsListParams {
-    #[serde(default)]
-    include_usage: Option<bool>,
+/// How failed runs of an agent are re-dispatched. `maxAttempts` includes the first try, so the
+/// default of `1` never retries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct AgentRetryPolicy {
+    pub(crate) max_attempts: u32,
+    /// Delay before the second attempt; doubles per attempt up to `maxBackoffMs`.
+    pub(crate) backoff_ms: u64,
+    pub(crate) max_backoff_ms: u64,
+    /// Failure classes that trigger a retry: `backendError` and/or `timeout`.
+    pub(crate) retry_on: Vec<String>,
+    /// Per-attempt backend deadline; a slower reply fails the attempt with class `timeout`.
+    pub(crate) timeout_ms: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AgentsCreateParams {
-    name: String,
-    #[serde(default)]
-    workspace: Option<String>,
-    #[serde(default)]
-    model: Option<String>,
-    #[serde(default)]
-    avatar: Option<String>,
-    #[serde(default)]
-    emoji: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AgentsUpdateParams {
-    #[serde(default)]
-    agent_id: Option<String>,
-    #[serde(default)]
-    id: Option<String>,
-    #[serde(default)]
-    name: Option<String>,
-    #[serde(default)]
-    workspace: Option<String>,
-    #[serde(default)]
-    model: Option<String>,
-    #[serde(default)]
-    avatar: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AgentsDeleteParams {
-    #[serde(default)]
-    agent_id: Option<String>,
-    #[serde(default)]
-    id: Option<String>,
-    #[serde(default)]
-    delete_files: Option<bool>,
+impl Default for AgentRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_ms: 1_000,
+            max_backoff_ms: 30_000,
+            retry_on: RETRY_CLASSES
+                .iter()
+                .map(|class| (*class).to_owned())
+                .collect(),
+            timeout_ms: None,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AgentsFilesListParams {
-    agent_id: String,
+impl AgentRetryPolicy {
+    fn parse(method: &str, raw: Value) -> Result<Self, crate::protocol::ErrorShape> {
+        let invalid = |message: String| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                format!("invalid {method} params: {message}"),
+            )
+        };
+        let policy: Self = serde_json::from_value(raw)
+            .map_err(|error| invalid(format!("retryPolicy is malformed: {error}")))?;
+        if !(1..=MAX_RETRY_ATTEMPTS).contains(&policy.max_attempts) {
+            return Err(invalid(format!(
+                "retryPolicy.maxAttempts must be between 1 and {MAX_RETRY_ATTEMPTS}"
+            )));
+        }
+        if policy.backoff_ms > MAX_RETRY_BACKOFF_MS || policy.max_backoff_ms > MAX_RETRY_BACKOFF_MS
+        {
+            return Err(invalid(format!(
+                "retryPolicy backoff must not exceed {MAX_RETRY_BACKOFF_MS}ms"
+            )));
+        }
+        if policy.timeout_ms == Some(0) {
+            return Err(invalid(
+                "retryPolicy.timeoutMs must be greater than 0".to_owned(),
+            ));
+        }
+        if let Some(unknown) = policy
+            .retry_on
+            .iter()
+            .find(|class| !RETRY_CLASSES.contains(&class.as_str()))
+        {
+            return Err(invalid(format!(
+                "retryPolicy.retryOn has unknown class \"{unknown}\""
+            )));
+        }
+        Ok(policy)
+    }
+
+    /// Whether a failure of `class` on attempt number `attempt` is followed by another attempt.
+    pub(crate) fn retries(&self, class: &str, attempt: u32) -> bool {
+        attempt < self.max_attempts && self.retry_on.iter().any(|entry| entry == class)
+    }
+
+    /// Delay after failed attempt number `attempt` (1-based).
+    pub(crate) fn backoff_after(&self, attempt: u32) -> Duration {
+        let factor = 1_u64 << attempt.saturating_sub(1).min(20);
+        Duration::from_millis(
+            self.backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms.max(self.backoff_ms)),
+        )
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AgentsFilesGetParams {
-    agent_id: String,
-    name: String,
+/// Whether fenced code blocks marked executable in the agent's replies are run before delivery.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct AgentInlineExec {
+    pub(crate) enabled: bool,
+    /// Per-block limit, also capped by the exec runner's own timeout.
+    pub(crate) timeout_ms: u64,
+    /// Blocks past this many are left unrun.
+    pub(crate) max_blocks: usize,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AgentsFilesSetParams {
-    agent_id: String,
-    name: String,
-    #[serde(default)]
-    content: Option<String>,
+impl Default for AgentInlineExec {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: 5_000,
+            max_blocks: 3,
+        }
+    }
+}
+
+impl AgentInlineExec {
+    fn parse(method: &str, raw: Value) -> Result<Self, crate::protocol::ErrorShape> {
+        let invalid = |message: String| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                format!("invalid {method} params: {message}"),
+            )
+        };
+        let settings: Self = serde_json::from_value(raw)
+            .map_err(|error| invalid(format!("inlineExec is malformed: {error}")))?;
+        if !(1..=MAX_INLINE_EXEC_TIMEOUT_MS).contains(&settings.timeout_ms) {
+            return Err(invalid(format!(
+                "inlineExec.timeoutMs must be between 1 and {MAX_INLINE_EXEC_TIMEOUT_MS}"
+            )));
+        }
+        if !(1..=MAX_INLINE_EXEC_BLOCKS).contains(&settings.max_blocks) {
+            return Err(invalid(format!(
+                "inlineExec.maxBlocks must be between 1 and {MAX_INLINE_EXEC_BLOCKS}"
+            )));
+        }
+        Ok(settings)
+    }
+}
+
+/// Limits on an agent's workspace. Denied patterns use `*` wildcards and are matched against
+/// workspace file names.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct AgentFsPolicy {
+    /// Size cap for everything under the workspace, memory archives included.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) quota_bytes: Option<u64>,
+    pub(crate) denied_patterns: Vec<String>,
+}
+
+impl AgentFsPolicy {
+    fn parse(method: &str, raw: Value) -> Result<Self, crate::protocol::ErrorShape> {
+        let invalid = |message: String| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                format!("invalid {method} params: {message}"),
+            )
+        };
+        let mut policy: Self = serde_json::from_value(raw)
+            .map_err(|error| invalid(format!("fsPolicy is malformed: {error}")))?;
+        if policy.quota_bytes == Some(0) {
+            return Err(invalid(
+                "fsPolicy.quotaBytes must be greater than 0".to_owned(),
+            ));
+        }
+        if policy.denied_patterns.len() > MAX_DENIED_PATTERNS {
+            return Err(invalid(format!(
+                "fsPolicy.deniedPatterns allows at most {MAX_DENIED_PATTERNS} patterns"
+            )));
+        }
+        for pattern in &mut policy.denied_patterns {
+            *pattern = pattern.trim().to_owned();
+            if pattern.is_empty() {
+                return Err(invalid(
+                    "fsPolicy.deniedPatterns must not contain empty patterns".to_owned(),
+                ));
+            }
+        }
+        Ok(policy)
+    }
+
+    fn denies(&self, name: &str) -> bool {
+        self.denied_patterns
+            .iter()
+            .any(|pattern| glob_matches(pattern, name))
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentsListParams {
+        #[serde(default)]
+        include_usage: Option<bool>,
+        #[serde(default)]
+        fields: Option<Vec<String>>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentsCreateParams {
+        name: String,
+        #[serde(default)]
+        workspace: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(default)]
+        avatar: Option<String>,
+        #[serde(default)]
+        emoji: Option<String>,
+        #[serde(default)]
+        retry_policy: Option<Value>,
+        #[serde(default)]
+        inline_exec: Option<Value>,
+        #[serde(default)]
+        fs_policy: Option<Value>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentsUpdateParams {
+        #[serde(default)]
+        agent_id: Option<String>,
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        workspace: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(default)]
+        avatar: Option<String>,
+        #[serde(default)]
+        emoji: Option<String>,
+        #[serde(default)]
+        retry_policy: Option<Value>,
+        #[serde(default)]
+        inline_exec: Option<Value>,
+        #[serde(default)]
+        fs_policy: Option<Value>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentsDeleteParams {
+        #[serde(default)]
+        agent_id: Option<String>,
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        delete_files: Option<bool>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentsFilesListParams {
+        agent_id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentsFilesGetParams {
+        agent_id: String,
+        name: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct AgentsFilesSetParams {
+        agent_id: String,
+        name: String,
+        #[serde(default)]
+        content: Option<String>,
+    }
 }
 
 pub async fn handle_list(
@@ -136,50 +373,89 @@ pub async fn handle_list(
     params: Option<&Value>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
     let parsed: AgentsListParams = parse_optional_params("agents.list", params)?;
-    let include_usage = parsed.include_usage.unwrap_or(true);
+    let fields = FieldSelection::parse("agents.list", parsed.fields)?;
+    let include_usage = parsed.include_usage.unwrap_or(true)
+        && ["sessionsCount", "messagesCount", "lastActivityAtMs"]
+            .into_iter()
+            .any(|field| fields.includes(field));
 
     let agents = load_agents(state).await?;
-    let sessions = if include_usage {
-        Some(state.list_sessions().await.map_err(map_domain_error)?)
+    let usage = if include_usage {
+        state
+            .list_agent_usage()
+            .await
+            .map_err(map_domain_error)?
+            .into_iter()
+            .map(|usage| (usage.agent_id.clone(), usage))
+            .collect::<HashMap<_, _>>()
+    } else {
+        HashMap::new()
+    };
+    let include_quota = fields.includes("quota");
+    let budget = state.config().agent_workspace_budget_bytes;
+    let budget_used = if include_quota && budget.is_some() {
+        Some(
+            workspace_quota::total_usage(agents.iter().map(|agent| agent.workspace.as_str()))
+                .await
+                .map_err(storage_error)?,
+        )
     } else {
         None
     };
 
     let mut items = Vec::new();
     for agent in &agents {
-        let sessions_count = sessions
-            .as_ref()
-            .map(|list| {
-                list.iter()
-                    .filter(|session| {
-                        session_agent_id(&session.id).is_some_and(|id| id == agent.agent_id)
-                    })
-                    .count()
-            })
-            .unwrap_or(0);
+        let usage = usage.get(&agent.agent_id).cloned().unwrap_or_default();
 
-        let workspace_path = PathBuf::from(&agent.workspace);
-        let bootstrap_pending = fs::metadata(workspace_path.join(DEFAULT_BOOTSTRAP_FILENAME))
-            .await
-            .is_ok();
+        let bootstrap_pending = if fields.includes("bootstrapPending") {
+            let workspace_path = PathBuf::from(&agent.workspace);
+            fs::metadata(workspace_path.join(DEFAULT_BOOTSTRAP_FILENAME))
+                .await
+                .is_ok()
+        } else {
+            false
+        };
 
-        items.push(json!({
+        let quota = if include_quota {
+            let used = workspace_quota::directory_usage(Path::new(&agent.workspace))
+                .await
+                .map_err(storage_error)?;
+            json!({
+                "usedBytes": used,
+                "quotaBytes": agent.fs_policy.as_ref().and_then(|policy| policy.quota_bytes),
+            })
+        } else {
+            Value::Null
+        };
+
+        items.push(fields.apply(json!({
             "id": agent.agent_id,
             "name": agent.name,
             "workspace": agent.workspace,
             "model": agent.model,
             "avatar": agent.avatar,
+            "emoji": agent.emoji,
+            "retryPolicy": agent.retry_policy.clone().unwrap_or_default(),
+            "inlineExec": agent.inline_exec.clone().unwrap_or_default(),
+            "fsPolicy": agent.fs_policy.clone().unwrap_or_default(),
+            "quota": quota,
             "createdAtMs": agent.created_at_ms,
             "updatedAtMs": agent.updated_at_ms,
-            "sessionsCount": sessions_count,
+            "sessionsCount": usage.sessions_count,
+            "messagesCount": usage.messages_count,
+            "lastActivityAtMs": usage.last_activity_at_ms,
             "bootstrapPending": bootstrap_pending,
-        }));
+        })));
     }
 
     Ok(json!({
         "defaultId": DEFAULT_AGENT_ID,
         "agents": items,
         "count": items.len(),
+        "workspaceBudget": budget_used.map(|used| json!({
+            "usedBytes": used,
+            "budgetBytes": budget,
+        })),
     }))
 }
 
@@ -217,10 +493,28 @@ pub async fn handle_create(
         ));
     }
 
+    let retry_policy = parsed
+        .retry_policy
+        .map(|raw| AgentRetryPolicy::parse("agents.create", raw))
+        .transpose()?;
+    let inline_exec = parsed
+        .inline_exec
+        .map(|raw| AgentInlineExec::parse("agents.create", raw))
+        .transpose()?;
+    let fs_policy = parsed
+        .fs_policy
+        .map(|raw| AgentFsPolicy::parse("agents.create", raw))
+        .transpose()?;
+
     let workspace_path = resolve_workspace_path(state, parsed.workspace.as_deref(), &agent_id);
-    ensure_workspace_bootstrap_files(&workspace_path, &raw_name, parsed.emoji.as_deref())
-        .await
-        .map_err(storage_error)?;
+    ensure_workspace_bootstrap_files(
+        state,
+        &workspace_path,
+        &raw_name,
+        parsed.emoji.as_deref(),
+        &fs_policy.clone().unwrap_or_default(),
+    )
+    .await?;
 
     let now = now_unix_ms();
     let record = AgentRecord {
@@ -229,6 +523,10 @@ pub async fn handle_create(
         workspace: workspace_path.display().to_string(),
         model: parsed.model.and_then(trim_non_empty),
         avatar: parsed.avatar.and_then(trim_non_empty),
+        emoji: parsed.emoji.and_then(trim_non_empty),
+        retry_policy,
+        inline_exec,
+        fs_policy,
         created_at_ms: now,
         updated_at_ms: now,
     };
@@ -272,13 +570,32 @@ pub async fn handle_update(
         next.name = name;
     }
 
+    if let Some(raw) = parsed.fs_policy {
+        next.fs_policy = Some(AgentFsPolicy::parse("agents.update", raw)?);
+    }
+    if let Some(emoji) = parsed.emoji {
+        next.emoji = trim_non_empty(emoji);
+    }
     if let Some(workspace) = parsed.workspace.as_deref() {
         let workspace_path = resolve_workspace_path(state, Some(workspace), &agent_id);
-        ensure_workspace_bootstrap_files(&workspace_path, &next.name, None)
-            .await
-            .map_err(storage_error)?;
+        ensure_workspace_bootstrap_files(
+            state,
+            &workspace_path,
+            &next.name,
+            next.emoji.as_deref(),
+            &next.fs_policy.clone().unwrap_or_default(),
+        )
+        .await?;
         next.workspace = workspace_path.display().to_string();
     }
+    if next.emoji != agents[index].emoji {
+        write_identity_emoji(
+            Path::new(&next.workspace),
+            next.emoji.as_deref(),
+            &next.fs_policy.clone().unwrap_or_default(),
+        )
+        .await?;
+    }
 
     if let Some(model) = parsed.model {
         next.model = trim_non_empty(model);
@@ -286,6 +603,12 @@ pub async fn handle_update(
     if let Some(avatar) = parsed.avatar {
         next.avatar = trim_non_empty(avatar);
     }
+    if let Some(raw) = parsed.retry_policy {
+        next.retry_policy = Some(AgentRetryPolicy::parse("agents.update", raw)?);
+    }
+    if let Some(raw) = parsed.inline_exec {
+        next.inline_exec = Some(AgentInlineExec::parse("agents.update", raw)?);
+    }
     next.updated_at_ms = now_unix_ms();
 
     agents[index] = next.clone();
@@ -352,9 +675,8 @@ pub async fn handle_files_list(
     let parsed: AgentsFilesListParams = parse_required_params("agents.files.list", params)?;
     let agent = resolve_agent_by_id(state, &parsed.agent_id).await?;
     let workspace = PathBuf::from(&agent.workspace);
-    ensure_workspace_bootstrap_files(&workspace, &agent.name, None)
-        .await
-        .map_err(storage_error)?;
+    let policy = agent.fs_policy.clone().unwrap_or_default();
+    ensure_workspace_bootstrap_files(state, &workspace, &agent.name, None, &policy).await?;
 
     let mut files = Vec::new();
     for name in ALLOWED_FILE_NAMES {
@@ -370,15 +692,34 @@ pub async fn handle_files_list(
             "name": name,
             "path": file_path.display().to_string(),
             "missing": missing,
+            "denied": policy.denies(name),
             "size": size,
             "updatedAtMs": updated_at_ms,
         }));
     }
 
+    let mut memory_archives = Vec::new();
+    let mut entries = fs::read_dir(&workspace).await.map_err(storage_error)?;
+    while let Some(entry) = entries.next_entry().await.map_err(storage_error)? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !is_memory_archive_name(&name) {
+            continue;
+        }
+        let meta = entry.metadata().await.ok();
+        memory_archives.push(json!({
+            "name": name,
+            "path": entry.path().display().to_string(),
+            "size": meta.as_ref().map(std::fs::Metadata::len),
+            "updatedAtMs": meta.and_then(|meta| meta.modified().ok().and_then(unix_ms)),
+        }));
+    }
+    memory_archives.sort_by(|left, right| left["name"].as_str().cmp(&right["name"].as_str()));
+
     Ok(json!({
         "agentId": agent.agent_id,
         "workspace": agent.workspace,
         "files": files,
+        "memoryArchives": memory_archives,
     }))
 }
 
@@ -387,12 +728,16 @@ pub async fn handle_files_get(
     params: Option<&Value>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
     let parsed: AgentsFilesGetParams = parse_required_params("agents.files.get", params)?;
-    let name = validate_agent_file_name("agents.files.get", &parsed.name)?;
+    let name = if is_memory_archive_name(parsed.name.trim()) {
+        parsed.name.trim().to_owned()
+    } else {
+        validate_agent_file_name("agents.files.get", &parsed.name)?
+    };
     let agent = resolve_agent_by_id(state, &parsed.agent_id).await?;
+    let policy = agent.fs_policy.clone().unwrap_or_default();
+    ensure_file_allowed("agents.files.get", &policy, &name)?;
     let workspace = PathBuf::from(&agent.workspace);
-    ensure_workspace_bootstrap_files(&workspace, &agent.name, None)
-        .await
-        .map_err(storage_error)?;
+    ensure_workspace_bootstrap_files(state, &workspace, &agent.name, None, &policy).await?;
 
     let path = workspace.join(&name);
     let metadata = fs::metadata(&path).await.ok();
@@ -436,14 +781,52 @@ pub async fn handle_files_set(
 ) -> Result<Value, crate::protocol::ErrorShape> {
     let parsed: AgentsFilesSetParams = parse_required_params("agents.files.set", params)?;
     let name = validate_agent_file_name("agents.files.set", &parsed.name)?;
-    let content = parsed.content.unwrap_or_default();
+    let mut content = parsed.content.unwrap_or_default();
+    let is_memory = MEMORY_FILE_NAMES.contains(&name.as_str());
+    let max_bytes = state.config().agent_file_max_bytes;
+    if content.len() > max_bytes {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid agents.files.set params: {name} exceeds {max_bytes} bytes"),
+        ));
+    }
     let agent = resolve_agent_by_id(state, &parsed.agent_id).await?;
+    let policy = agent.fs_policy.clone().unwrap_or_default();
+    ensure_file_allowed("agents.files.set", &policy, &name)?;
     let workspace = PathBuf::from(&agent.workspace);
     fs::create_dir_all(&workspace)
         .await
         .map_err(storage_error)?;
 
     let path = workspace.join(&name);
+    let old_bytes = fs::metadata(&path).await.map_or(0, |meta| meta.len());
+    let new_bytes = u64::try_from(content.len()).unwrap_or(u64::MAX);
+    workspace_usage(state, &workspace, &policy)
+        .await?
+        .check(old_bytes, new_bytes)
+        .map_err(|exceeded| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                format!(
+                    "invalid agents.files.set params: {}",
+                    exceeded.describe(&agent.agent_id)
+                ),
+            )
+        })?;
+    let mut rotated = None;
+    if is_memory
+        && let Some((archived, kept)) =
+            split_for_rotation(&content, state.config().memory_max_bytes)
+    {
+        let archive = append_memory_archive(&workspace, &name, archived)
+            .await
+            .map_err(storage_error)?;
+        rotated = Some(json!({
+            "archive": archive,
+            "archivedBytes": archived.len(),
+        }));
+        content = kept.to_owned();
+    }
     fs::write(&path, &content).await.map_err(storage_error)?;
     let updated_at_ms = fs::metadata(&path)
         .await
@@ -463,7 +846,8 @@ pub async fn handle_files_set(
             "size": size,
             "updatedAtMs": updated_at_ms,
             "content": content,
-        }
+        },
+        "rotated": rotated,
     }))
 }
 
@@ -490,7 +874,9 @@ async fn resolve_agent_by_id(
         })
 }
 
-async fn load_agents(state: &SharedState) -> Result<Vec<AgentRecord>, crate::protocol::ErrorShape> {
+pub(crate) async fn load_agents(
+    state: &SharedState,
+) -> Result<Vec<AgentRecord>, crate::protocol::ErrorShape> {
     let Some(raw) = state
         .get_config_entry_value(AGENTS_REGISTRY_KEY)
         .await
@@ -509,6 +895,44 @@ async fn load_agents(state: &SharedState) -> Result<Vec<AgentRecord>, crate::pro
     }
 }
 
+/// The agent's retry policy, or the no-retry default for unknown agents.
+pub(crate) async fn agent_retry_policy(
+    state: &SharedState,
+    agent_id: &str,
+) -> Result<AgentRetryPolicy, crate::protocol::ErrorShape> {
+    Ok(load_agents(state)
+        .await?
+        .into_iter()
+        .find(|agent| agent.agent_id == agent_id)
+        .and_then(|agent| agent.retry_policy)
+        .unwrap_or_default())
+}
+
+/// The model configured for the agent, if any.
+pub(crate) async fn agent_model(
+    state: &SharedState,
+    agent_id: &str,
+) -> Result<Option<String>, crate::protocol::ErrorShape> {
+    Ok(load_agents(state)
+        .await?
+        .into_iter()
+        .find(|agent| agent.agent_id == agent_id)
+        .and_then(|agent| agent.model))
+}
+
+/// The agent's inline exec settings when it has them enabled.
+pub(crate) async fn agent_inline_exec(
+    state: &SharedState,
+    agent_id: &str,
+) -> Result<Option<AgentInlineExec>, crate::protocol::ErrorShape> {
+    Ok(load_agents(state)
+        .await?
+        .into_iter()
+        .find(|agent| agent.agent_id == agent_id)
+        .and_then(|agent| agent.inline_exec)
+        .filter(|settings| settings.enabled))
+}
+
 async fn save_agents(
     state: &SharedState,
     agents: &[AgentRecord],
@@ -537,12 +961,20 @@ fn default_main_agent(state: &SharedState) -> AgentRecord {
         workspace: workspace.display().to_string(),
         model: None,
         avatar: None,
+        emoji: None,
+        retry_policy: None,
+        inline_exec: None,
+        fs_policy: None,
         created_at_ms: now,
         updated_at_ms: now,
     }
 }
 
-fn resolve_workspace_path(state: &SharedState, raw: Option<&str>, agent_id: &str) -> PathBuf {
+pub(crate) fn resolve_workspace_path(
+    state: &SharedState,
+    raw: Option<&str>,
+    agent_id: &str,
+) -> PathBuf {
     if let Some(raw) = raw.map(str::trim).filter(|value| !value.is_empty()) {
         return expand_home(raw);
     }
@@ -574,31 +1006,215 @@ fn expand_home(path: &str) -> PathBuf {
     PathBuf::from(path)
 }
 
+/// Writes the missing bootstrap templates. Names denied by the agent's `fsPolicy` and templates
+/// that would not fit its quota or the workspace budget are left missing.
 async fn ensure_workspace_bootstrap_files(
+    state: &SharedState,
     workspace: &Path,
     agent_name: &str,
     emoji: Option<&str>,
-) -> Result<(), std::io::Error> {
-    fs::create_dir_all(workspace).await?;
+    policy: &AgentFsPolicy,
+) -> Result<(), crate::protocol::ErrorShape> {
+    fs::create_dir_all(workspace).await.map_err(storage_error)?;
 
-    for name in BOOTSTRAP_FILE_NAMES {
+    let mut usage = workspace_usage(state, workspace, policy).await?;
+    for name in BOOTSTRAP_FILE_NAMES
+        .iter()
+        .chain(std::iter::once(&DEFAULT_MEMORY_FILENAME))
+    {
         let path = workspace.join(name);
-        if fs::metadata(&path).await.is_ok() {
+        if policy.denies(name) || fs::metadata(&path).await.is_ok() {
             continue;
         }
 
         let template = bootstrap_file_template(name, agent_name, emoji);
-        fs::write(path, template).await?;
+        let bytes = u64::try_from(template.len()).unwrap_or(u64::MAX);
+        if usage.check(0, bytes).is_err() {
+            continue;
+        }
+        fs::write(path, template).await.map_err(storage_error)?;
+        usage.record(0, bytes);
     }
 
-    let memory = workspace.join(DEFAULT_MEMORY_FILENAME);
-    if fs::metadata(&memory).await.is_err() {
-        fs::write(memory, "# Memory\n\n").await?;
+    Ok(())
+}
+
+/// Rewrites the `- Emoji:` line of an existing `IDENTITY.md`, dropping it when `emoji` is unset. A
+/// missing or denied identity file is left alone.
+async fn write_identity_emoji(
+    workspace: &Path,
+    emoji: Option<&str>,
+    policy: &AgentFsPolicy,
+) -> Result<(), crate::protocol::ErrorShape> {
+    let path = workspace.join(DEFAULT_IDENTITY_FILENAME);
+    if policy.denies(DEFAULT_IDENTITY_FILENAME) {
+        return Ok(());
     }
+    let Ok(current) = fs::read_to_string(&path).await else {
+        return Ok(());
+    };
 
+    let emoji_line = emoji.map(|value| format!("- Emoji: {value}"));
+    let mut lines = current
+        .lines()
+        .filter(|line| !line.starts_with("- Emoji:"))
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    if let Some(emoji_line) = emoji_line {
+        let at = lines
+            .iter()
+            .position(|line| line.starts_with("- Name:"))
+            .map_or(lines.len(), |index| index + 1);
+        lines.insert(at, emoji_line);
+    }
+    let mut next = lines.join("\n");
+    next.push('\n');
+    if next != current {
+        fs::write(path, next).await.map_err(storage_error)?;
+    }
     Ok(())
 }
 
+/// Usage of `workspace` and of all agent workspaces, measured only for the limits that are set.
+async fn workspace_usage(
+    state: &SharedState,
+    workspace: &Path,
+    policy: &AgentFsPolicy,
+) -> Result<WorkspaceUsage, crate::protocol::ErrorShape> {
+    let budget = state.config().agent_workspace_budget_bytes;
+    let mut usage = WorkspaceUsage {
+        agent_quota: policy.quota_bytes,
+        budget,
+        ..WorkspaceUsage::default()
+    };
+    if usage.agent_quota.is_some() {
+        usage.agent_used = workspace_quota::directory_usage(workspace)
+            .await
+            .map_err(storage_error)?;
+    }
+    if budget.is_some() {
+        let agents = load_agents(state).await?;
+        let workspace = workspace.display().to_string();
+        usage.total_used = workspace_quota::total_usage(
+            agents
+                .iter()
+                .map(|agent| agent.workspace.as_str())
+                .chain(std::iter::once(workspace.as_str())),
+        )
+        .await
+        .map_err(storage_error)?;
+    }
+    Ok(usage)
+}
+
+fn ensure_file_allowed(
+    method: &str,
+    policy: &AgentFsPolicy,
+    name: &str,
+) -> Result<(), crate::protocol::ErrorShape> {
+    if policy.denies(name) {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid {method} params: \"{name}\" is denied by the agent's fsPolicy"),
+        ));
+    }
+    Ok(())
+}
+
+/// Rotates memory files edited outside `agents.files.set` once they outgrow `max_bytes`.
+/// Rotates every agent's memory files that grew past `memoryMaxBytes` outside
+/// `agents.files.set`, e.g. written by the agent itself.
+pub(crate) async fn rotate_agent_memory_files(
+    state: &SharedState,
+) -> Result<(), crate::protocol::ErrorShape> {
+    let max_bytes = state.config().memory_max_bytes;
+    for agent in load_agents(state).await? {
+        rotate_memory_files(Path::new(&agent.workspace), max_bytes)
+            .await
+            .map_err(storage_error)?;
+    }
+    Ok(())
+}
+
+async fn rotate_memory_files(workspace: &Path, max_bytes: usize) -> Result<(), std::io::Error> {
+    for name in MEMORY_FILE_NAMES {
+        let path = workspace.join(name);
+        let Ok(meta) = fs::metadata(&path).await else {
+            continue;
+        };
+        if usize::try_from(meta.len()).unwrap_or(usize::MAX) <= max_bytes {
+            continue;
+        }
+        let content = fs::read_to_string(&path).await?;
+        if let Some((archived, kept)) = split_for_rotation(&content, max_bytes) {
+            append_memory_archive(workspace, name, archived).await?;
+            fs::write(&path, kept).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Splits oversized memory content into the older head to archive and the newest tail (at most
+/// `max_bytes`, starting on a line boundary where possible) to keep.
+fn split_for_rotation(content: &str, max_bytes: usize) -> Option<(&str, &str)> {
+    if content.len() <= max_bytes {
+        return None;
+    }
+    let mut split = content.len() - max_bytes;
+    while !content.is_char_boundary(split) {
+        split += 1;
+    }
+    if !content[..split].ends_with('\n')
+        && let Some(offset) = content[split..].find('\n')
+        && split + offset + 1 < content.len()
+    {
+        split += offset + 1;
+    }
+    Some(content.split_at(split))
+}
+
+/// Appends `archived` to this month's archive (`MEMORY.md` -> `MEMORY-2024-06.md`) and returns
+/// the archive file name.
+async fn append_memory_archive(
+    workspace: &Path,
+    name: &str,
+    archived: &str,
+) -> Result<String, std::io::Error> {
+    use tokio::io::AsyncWriteExt;
+
+    let stem = name.strip_suffix(".md").unwrap_or(name);
+    let archive = format!("{stem}-{}.md", chrono::Utc::now().format("%Y-%m"));
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(workspace.join(&archive))
+        .await?;
+    file.write_all(archived.as_bytes()).await?;
+    if !archived.ends_with('\n') {
+        file.write_all(b"\n").await?;
+    }
+    file.flush().await?;
+    Ok(archive)
+}
+
+/// `MEMORY-YYYY-MM.md` / `memory-YYYY-MM.md`, as produced by [`append_memory_archive`].
+fn is_memory_archive_name(name: &str) -> bool {
+    let Some(month) = name
+        .strip_prefix("MEMORY-")
+        .or_else(|| name.strip_prefix("memory-"))
+        .and_then(|rest| rest.strip_suffix(".md"))
+    else {
+        return false;
+    };
+    let bytes = month.as_bytes();
+    bytes.len() == 7
+        && bytes[4] == b'-'
+        && bytes
+            .iter()
+            .enumerate()
+            .all(|(index, byte)| index == 4 || byte.is_ascii_digit())
+}
+
 fn bootstrap_file_template(name: &str, agent_name: &str, emoji: Option<&str>) -> String {
     match name {
         DEFAULT_IDENTITY_FILENAME => {
@@ -615,6 +1231,7 @@ fn bootstrap_file_template(name: &str, agent_name: &str, emoji: Option<&str>) ->
         DEFAULT_USER_FILENAME => "# User\n\n".to_owned(),
         DEFAULT_HEARTBEAT_FILENAME => "# Heartbeat\n\n".to_owned(),
         DEFAULT_BOOTSTRAP_FILENAME => "# Bootstrap\n\n".to_owned(),
+        DEFAULT_MEMORY_FILENAME => "# Memory\n\n".to_owned(),
         _ => String::new(),
     }
 }
@@ -634,16 +1251,6 @@ fn validate_agent_file_name(
     ))
 }
 
-fn session_agent_id(session_id: &str) -> Option<&str> {
-    let mut parts = session_id.split(':');
-    let prefix = parts.next()?;
-    if prefix != "agent" {
-        return None;
-    }
-
-    parts.next().filter(|value| !value.trim().is_empty())
-}
-
 fn unix_ms(system_time: std::time::SystemTime) -> Option<u64> {
     let duration = system_time.duration_since(std::time::UNIX_EPOCH).ok()?;
     u64::try_from(duration.as_millis()).ok()
@@ -656,7 +1263,7 @@ fn storage_error(error: std::io::Error) -> crate::protocol::ErrorShape {
     )
 }
 
-fn normalize_agent_id(name: &str) -> String {
+pub(crate) fn normalize_agent_id(name: &str) -> String {
     let mut out = String::new();
     let mut pending_dash = false;
 
@@ -691,11 +1298,28 @@ fn trim_non_empty(value: String) -> Option<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::normalize_agent_id;
+    use super::{is_memory_archive_name, normalize_agent_id, split_for_rotation};
 
     #[test]
     fn normalize_agent_id_strips_invalid_characters() {
         assert_eq!(normalize_agent_id("Team Alpha 🤖"), "team-alpha");
         assert_eq!(normalize_agent_id("___Main___"), "main");
     }
+
+    #[test]
+    fn memory_rotation_keeps_the_newest_whole_lines() {
+        assert_eq!(split_for_rotation("short\n", 64), None);
+        assert_eq!(
+            split_for_rotation("# Memory\nold fact\nnew fact\n", 12),
+            Some(("# Memory\nold fact\n", "new fact\n"))
+        );
+        // A single oversized line is cut on a char boundary rather than dropped.
+        let (archived, kept) = split_for_rotation("ééééé", 4).expect("should rotate");
+        assert_eq!((archived, kept), ("ééé", "éé"));
+
+        assert!(is_memory_archive_name("MEMORY-2024-06.md"));
+        assert!(is_memory_archive_name("memory-2024-06.md"));
+        assert!(!is_memory_archive_name("MEMORY-2024-6.md"));
+        assert!(!is_memory_archive_name("MEMORY-../x.md"));
+    }
 }
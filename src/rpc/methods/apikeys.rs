@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    application::state::SharedState,
+    rpc::{
+        dispatcher::map_domain_error,
+        methods::{parse_optional_params, parse_required_params},
+        policy,
+        schema::rpc_params,
+    },
+    security::api_keys,
+    storage::now_unix_ms,
+};
+
+const API_KEYS_STATE_KEY: &str = "runtime/apikeys/state";
+const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 60;
+const MAX_RATE_LIMIT_PER_MINUTE: u32 = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ApiKeysState {
+    #[serde(default)]
+    keys: Vec<ApiKeyRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiKeyRecord {
+    id: String,
+    name: String,
+    secret_hash: String,
+    hint: String,
+    scopes: Vec<String>,
+    rate_limit_per_minute: u32,
+    created_at_ms: u64,
+    rotated_at_ms: Option<u64>,
+    revoked_at_ms: Option<u64>,
+}
+
+/// Identity granted to an HTTP caller presenting a named API key.
+#[derive(Debug, Clone)]
+pub(crate) struct ApiKeyGrant {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) scopes: Vec<String>,
+    pub(crate) rate_limit_per_minute: u32,
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ApiKeysCreateParams {
+        name: String,
+        #[serde(default)]
+        scopes: Option<Vec<String>>,
+        #[serde(default)]
+        rate_limit_per_minute: Option<u32>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ApiKeysIdParams {
+        id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ApiKeysListParams {
+        #[serde(default)]
+        include_revoked: Option<bool>,
+    }
+}
+
+pub async fn handle_list(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ApiKeysListParams = parse_optional_params("apikeys.list", params)?;
+    let include_revoked = parsed.include_revoked.unwrap_or(false);
+    let current = load_api_keys_state(state).await?;
+
+    Ok(json!({
+        "keys": current
+            .keys
+            .iter()
+            .filter(|entry| include_revoked || entry.revoked_at_ms.is_none())
+            .map(redact_api_key)
+            .collect::<Vec<_>>(),
+    }))
+}
+
+pub async fn handle_create(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ApiKeysCreateParams = parse_required_params("apikeys.create", params)?;
+    let name = trim_non_empty(parsed.name).ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid apikeys.create params: name is required",
+        )
+    })?;
+    let scopes = resolve_scopes(parsed.scopes)?;
+    let rate_limit_per_minute = resolve_rate_limit(parsed.rate_limit_per_minute)?;
+
+    let mut current = load_api_keys_state(state).await?;
+    if current
+        .keys
+        .iter()
+        .any(|entry| entry.revoked_at_ms.is_none() && entry.name == name)
+    {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid apikeys.create params: an active key named {name} already exists"),
+        ));
+    }
+
+    let secret = api_keys::generate_api_key_secret();
+    let record = ApiKeyRecord {
+        id: format!("key-{}", uuid::Uuid::new_v4()),
+        name,
+        secret_hash: api_keys::hash_api_key_secret(&secret),
+        hint: api_keys::api_key_hint(&secret),
+        scopes,
+        rate_limit_per_minute,
+        created_at_ms: now_unix_ms(),
+        rotated_at_ms: None,
+        revoked_at_ms: None,
+    };
+    let summary = redact_api_key(&record);
+    current.keys.push(record);
+
+    save_api_keys_state(state, &current).await?;
+    Ok(json!({
+        "key": summary,
+        "secret": secret,
+    }))
+}
+
+pub async fn handle_rotate(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ApiKeysIdParams = parse_required_params("apikeys.rotate", params)?;
+    let id = require_id("apikeys.rotate", parsed.id)?;
+
+    let mut current = load_api_keys_state(state).await?;
+    let Some(record) = current
+        .keys
+        .iter_mut()
+        .find(|entry| entry.id == id && entry.revoked_at_ms.is_none())
+    else {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "unknown api key id",
+        ));
+    };
+
+    let secret = api_keys::generate_api_key_secret();
+    record.secret_hash = api_keys::hash_api_key_secret(&secret);
+    record.hint = api_keys::api_key_hint(&secret);
+    record.rotated_at_ms = Some(now_unix_ms());
+    let summary = redact_api_key(record);
+
+    save_api_keys_state(state, &current).await?;
+    Ok(json!({
+        "key": summary,
+        "secret": secret,
+    }))
+}
+
+pub async fn handle_revoke(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ApiKeysIdParams = parse_required_params("apikeys.revoke", params)?;
+    let id = require_id("apikeys.revoke", parsed.id)?;
+
+    let mut current = load_api_keys_state(state).await?;
+    let Some(record) = current
+        .keys
+        .iter_mut()
+        .find(|entry| entry.id == id && entry.revoked_at_ms.is_none())
+    else {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "unknown api key id",
+        ));
+    };
+
+    let revoked_at_ms = now_unix_ms();
+    record.revoked_at_ms = Some(revoked_at_ms);
+    record.secret_hash.clear();
+
+    save_api_keys_state(state, &current).await?;
+    Ok(json!({
+        "ok": true,
+        "id": id,
+        "revokedAtMs": revoked_at_ms,
+    }))
+}
+
+/// Resolves a bearer secret to an active API key, if it matches one.
+pub(crate) async fn resolve_api_key(
+    state: &SharedState,
+    secret: &str,
+) -> Result<Option<ApiKeyGrant>, crate::protocol::ErrorShape> {
+    if !api_keys::looks_like_api_key(secret) {
+        return Ok(None);
+    }
+
+    let current = load_api_keys_state(state).await?;
+    Ok(current
+        .keys
+        .into_iter()
+        .filter(|entry| entry.revoked_at_ms.is_none())
+        .find(|entry| api_keys::verify_api_key_secret(secret, &entry.secret_hash))
+        .map(|entry| ApiKeyGrant {
+            id: entry.id,
+            name: entry.name,
+            scopes: entry.scopes,
+            rate_limit_per_minute: entry.rate_limit_per_minute,
+        }))
+}
+
+async fn load_api_keys_state(
+    state: &SharedState,
+) -> Result<ApiKeysState, crate::protocol::ErrorShape> {
+    let Some(raw) = state
+        .get_config_entry_value(API_KEYS_STATE_KEY)
+        .await
+        .map_err(map_domain_error)?
+    else {
+        return Ok(ApiKeysState::default());
+    };
+
+    serde_json::from_value::<ApiKeysState>(raw).map_err(|error| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_UNAVAILABLE,
+            format!("failed to decode api key state: {error}"),
+        )
+    })
+}
+
+async fn save_api_keys_state(
+    state: &SharedState,
+    keys_state: &ApiKeysState,
+) -> Result<(), crate::protocol::ErrorShape> {
+    let payload = serde_json::to_value(keys_state).map_err(|error| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_UNAVAILABLE,
+            format!("failed to encode api key state: {error}"),
+        )
+    })?;
+
+    let _ = state
+        .set_config_entry_value(API_KEYS_STATE_KEY, &payload)
+        .await
+        .map_err(map_domain_error)?;
+    Ok(())
+}
+
+fn resolve_scopes(scopes: Option<Vec<String>>) -> Result<Vec<String>, crate::protocol::ErrorShape> {
+    let Some(scopes) = scopes else {
+        return Ok(vec![
+            policy::READ_SCOPE.to_owned(),
+            policy::WRITE_SCOPE.to_owned(),
+        ]);
+    };
+
+    let known = policy::default_operator_scopes();
+    let mut resolved = Vec::new();
+    for scope in scopes {
+        let Some(scope) = trim_non_empty(scope) else {
+            continue;
+        };
+        if !known.contains(&scope) {
+            return Err(crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                format!("invalid apikeys.create params: unknown scope {scope}"),
+            ));
+        }
+        if !resolved.contains(&scope) {
+            resolved.push(scope);
+        }
+    }
+
+    if resolved.is_empty() {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid apikeys.create params: at least one scope is required",
+        ));
+    }
+    resolved.sort();
+    Ok(resolved)
+}
+
+fn resolve_rate_limit(value: Option<u32>) -> Result<u32, crate::protocol::ErrorShape> {
+    let limit = value.unwrap_or(DEFAULT_RATE_LIMIT_PER_MINUTE);
+    if limit == 0 || limit > MAX_RATE_LIMIT_PER_MINUTE {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!(
+                "invalid apikeys.create params: rateLimitPerMinute must be between 1 and {MAX_RATE_LIMIT_PER_MINUTE}"
+            ),
+        ));
+    }
+    Ok(limit)
+}
+
+fn require_id(method: &str, id: String) -> Result<String, crate::protocol::ErrorShape> {
+    trim_non_empty(id).ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid {method} params: id is required"),
+        )
+    })
+}
+
+fn redact_api_key(record: &ApiKeyRecord) -> Value {
+    json!({
+        "id": record.id,
+        "name": record.name,
+        "hint": record.hint,
+        "scopes": record.scopes,
+        "rateLimitPerMinute": record.rate_limit_per_minute,
+        "createdAtMs": record.created_at_ms,
+        "rotatedAtMs": record.rotated_at_ms,
+        "revokedAtMs": record.revoked_at_ms,
+    })
+}
+
+fn trim_non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
@@ -0,0 +1,275 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    application::state::SharedState,
+    rpc::{
+        SessionContext,
+        dispatcher::map_domain_error,
+        methods::{approvals, nodes, parse_required_params},
+        policy,
+        schema::rpc_params,
+    },
+    security::approval_links::{
+        ApprovalLinkClaims, ApprovalLinkKind, sign_approval_link, verify_approval_link,
+    },
+    storage::now_unix_ms,
+};
+
+const APPROVAL_LINK_SECRET_KEY: &str = "runtime/approval-links/secret";
+const DEFAULT_LINK_TTL_MS: u64 = 10 * 60 * 1_000;
+const MAX_LINK_TTL_MS: u64 = 24 * 60 * 60 * 1_000;
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ApprovalLinkCreateParams {
+        kind: String,
+        id: String,
+        #[serde(default)]
+        ttl_ms: Option<u64>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ApprovalLinkGetParams {
+        link: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ApprovalLinkResolveParams {
+        link: String,
+        decision: String,
+        #[serde(default)]
+        reason: Option<String>,
+        #[serde(default)]
+        duration_ms: Option<u64>,
+    }
+}
+
+pub async fn handle_create(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ApprovalLinkCreateParams = parse_required_params("approval.link.create", params)?;
+    let kind = ApprovalLinkKind::parse(&parsed.kind).ok_or_else(|| {
+        invalid("invalid approval.link.create params: kind must be exec or node.pair")
+    })?;
+    authorize_kind(session, kind)?;
+    let id = trim_non_empty(parsed.id)
+        .ok_or_else(|| invalid("invalid approval.link.create params: id is required"))?;
+
+    let target = load_target(state, kind, &id).await?;
+    if target.get("status").and_then(Value::as_str) != Some("pending") {
+        return Err(invalid("approval is not pending"));
+    }
+    if target
+        .get("expiresAtMs")
+        .and_then(Value::as_u64)
+        .is_some_and(|expires| now_unix_ms() >= expires)
+    {
+        return Err(invalid("approval expired"));
+    }
+    let ttl_ms = parsed
+        .ttl_ms
+        .unwrap_or(DEFAULT_LINK_TTL_MS)
+        .clamp(1_000, MAX_LINK_TTL_MS);
+    issue_link(
+        state,
+        kind,
+        &id,
+        target.get("expiresAtMs").and_then(Value::as_u64),
+        ttl_ms,
+    )
+    .await
+}
+
+pub async fn handle_get(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ApprovalLinkGetParams = parse_required_params("approval.link.get", params)?;
+    let claims = verify_link(state, &parsed.link).await?;
+    authorize_kind(session, claims.kind)?;
+    let target = load_target(state, claims.kind, &claims.id).await?;
+
+    Ok(json!({
+        "kind": claims.kind.label(),
+        "id": claims.id,
+        "expiresAtMs": claims.expires_at_ms,
+        "request": target,
+    }))
+}
+
+pub async fn handle_resolve(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ApprovalLinkResolveParams = parse_required_params("approval.link.resolve", params)?;
+    let claims = verify_link(state, &parsed.link).await?;
+    authorize_kind(session, claims.kind)?;
+    let decision = trim_non_empty(parsed.decision)
+        .ok_or_else(|| invalid("invalid approval.link.resolve params: decision is required"))?;
+
+    let result = match (claims.kind, decision.as_str()) {
+        (ApprovalLinkKind::Exec, _) => {
+            approvals::handle_exec_approval_resolve(
+                state,
+                session,
+                Some(&json!({
+                    "id": claims.id,
+                    "decision": decision,
+                    "durationMs": parsed.duration_ms,
+                })),
+            )
+            .await?
+        }
+        (ApprovalLinkKind::NodePair, "approve" | "reject") => {
+            let params = json!({ "requestId": claims.id, "reason": parsed.reason });
+            if decision == "approve" {
+                nodes::handle_pair_approve(state, Some(&params)).await?
+            } else {
+                nodes::handle_pair_reject(state, Some(&params)).await?
+            }
+        }
+        (ApprovalLinkKind::NodePair, _) => {
+            return Err(invalid("node.pair decision must be approve or reject"));
+        }
+    };
+
+    Ok(json!({
+        "kind": claims.kind.label(),
+        "id": claims.id,
+        "decision": decision,
+        "result": result,
+    }))
+}
+
+/// Signs a link for a pending request; the expiry is capped at the request's own deadline.
+pub(crate) async fn issue_link(
+    state: &SharedState,
+    kind: ApprovalLinkKind,
+    id: &str,
+    request_expires_at_ms: Option<u64>,
+    ttl_ms: u64,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let mut expires_at_ms = now_unix_ms().saturating_add(ttl_ms);
+    if let Some(deadline) = request_expires_at_ms {
+        expires_at_ms = expires_at_ms.min(deadline);
+    }
+    let secret = link_secret(state).await?;
+    let url = sign_approval_link(
+        secret.as_bytes(),
+        &state.config().approval_link_base_url,
+        &ApprovalLinkClaims {
+            kind,
+            id: id.to_owned(),
+            expires_at_ms,
+        },
+    );
+
+    Ok(json!({
+        "url": url,
+        "qr": url,
+        "expiresAtMs": expires_at_ms,
+    }))
+}
+
+/// Default-TTL link for `*.requested` event payloads; `null` if signing fails.
+pub(crate) async fn event_link(
+    state: &SharedState,
+    kind: ApprovalLinkKind,
+    id: &str,
+    request_expires_at_ms: Option<u64>,
+) -> Value {
+    match issue_link(state, kind, id, request_expires_at_ms, DEFAULT_LINK_TTL_MS).await {
+        Ok(link) => link,
+        Err(error) => {
+            tracing::warn!("failed to sign approval link: {}", error.message);
+            Value::Null
+        }
+    }
+}
+
+async fn verify_link(
+    state: &SharedState,
+    link: &str,
+) -> Result<ApprovalLinkClaims, crate::protocol::ErrorShape> {
+    let secret = link_secret(state).await?;
+    verify_approval_link(secret.as_bytes(), link.trim(), now_unix_ms())
+        .map_err(|error| invalid(&error))
+}
+
+/// Linked requests carry the scope of the RPC that would resolve them directly.
+fn authorize_kind(
+    session: &SessionContext,
+    kind: ApprovalLinkKind,
+) -> Result<(), crate::protocol::ErrorShape> {
+    let method = match kind {
+        ApprovalLinkKind::Exec => "exec.approval.resolve",
+        ApprovalLinkKind::NodePair => "node.pair.approve",
+    };
+    policy::authorize_session(session, method)
+}
+
+async fn load_target(
+    state: &SharedState,
+    kind: ApprovalLinkKind,
+    id: &str,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let target = match kind {
+        ApprovalLinkKind::Exec => approvals::load_approval_record(state, id)
+            .await?
+            .map(|record| json!(record)),
+        ApprovalLinkKind::NodePair => state
+            .list_node_pair_requests()
+            .await
+            .map_err(map_domain_error)?
+            .into_iter()
+            .find(|request| request.request_id == id)
+            .map(|request| json!(request)),
+    };
+    target.ok_or_else(|| invalid("unknown approval id"))
+}
+
+/// Per-gateway HMAC key, generated on first use and kept in the config store. Concurrent first
+/// uses all sign with whichever key was stored first.
+async fn link_secret(state: &SharedState) -> Result<String, crate::protocol::ErrorShape> {
+    let candidate = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    match state
+        .get_or_insert_config_entry_value(APPROVAL_LINK_SECRET_KEY, &Value::String(candidate))
+        .await
+        .map_err(map_domain_error)?
+    {
+        Value::String(secret) => Ok(secret),
+        _ => Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_UNAVAILABLE,
+            "approval link secret is not a string",
+        )),
+    }
+}
+
+fn invalid(message: &str) -> crate::protocol::ErrorShape {
+    crate::protocol::ErrorShape::new(crate::protocol::ERROR_INVALID_REQUEST, message)
+}
+
+fn trim_non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
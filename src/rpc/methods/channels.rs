@@ -5,31 +5,61 @@ use serde_json::{Map, Value, json};
 
 use crate::{
     application::state::SharedState,
+    interfaces::telegram_webhook,
     rpc::{
         dispatcher::map_domain_error,
         methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
     storage::now_unix_ms,
 };
 
 const CHANNELS_STATUS_KEY: &str = "runtime/channels/status";
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ChannelsStatusParams {
-    #[serde(default)]
-    include_disabled: Option<bool>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ChannelsStatusParams {
+        #[serde(default)]
+        include_disabled: Option<bool>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ChannelsLogoutParams {
+        #[serde(default)]
+        channel: Option<String>,
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        account_id: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ChannelsLogoutParams {
-    #[serde(default)]
-    channel: Option<String>,
-    #[serde(default)]
-    id: Option<String>,
-    #[serde(default)]
-    account_id: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ChannelsDirectoryListParams {
+        #[serde(default)]
+        channel: Option<String>,
+        #[serde(default)]
+        query: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ChannelsOutboundQueueParams {
+        #[serde(default)]
+        channel: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
 }
 
 pub async fn handle_status(
@@ -56,6 +86,14 @@ pub async fn handle_status(
                 .unwrap_or(true)
         });
     }
+    if let Some(webhook) = telegram_webhook::webhook_status(state).await {
+        let telegram = channels
+            .iter_mut()
+            .find(|item| item.get("id").and_then(Value::as_str) == Some("telegram"));
+        if let Some(Value::Object(telegram)) = telegram {
+            telegram.insert("webhook".to_owned(), webhook);
+        }
+    }
     let channel_views = build_channel_views(&channels);
 
     Ok(json!({
@@ -148,6 +186,88 @@ pub async fn handle_logout(
     }))
 }
 
+pub async fn handle_directory_list(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ChannelsDirectoryListParams =
+        parse_optional_params("channels.directory.list", params)?;
+    let channel = parsed.channel.and_then(trim_non_empty);
+    let query = parsed.query.and_then(trim_non_empty);
+    let limit = parsed.limit.unwrap_or(100).clamp(1, 1_000);
+
+    let entries = state
+        .list_channel_directory_entries(channel.as_deref(), query.as_deref(), limit)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({
+        "ts": now_unix_ms(),
+        "channel": channel,
+        "entries": entries,
+    }))
+}
+
+pub async fn handle_outbound_queue(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ChannelsOutboundQueueParams =
+        parse_optional_params("channels.outbound.queue", params)?;
+    let channel = parsed
+        .channel
+        .and_then(trim_non_empty)
+        .map(|value| value.to_ascii_lowercase());
+    let limit = parsed.limit.unwrap_or(100).clamp(1, 1_000);
+    let now = now_unix_ms();
+
+    let messages = state
+        .list_outbound_messages(channel.as_deref(), u64::MAX, limit)
+        .await
+        .map_err(map_domain_error)?;
+    let quiet_hours = state
+        .config()
+        .quiet_hours
+        .iter()
+        .map(|(channel, window)| {
+            let quiet = window.is_quiet_at(now);
+            (
+                channel.clone(),
+                json!({
+                    "start": window.start.format("%H:%M").to_string(),
+                    "end": window.end.format("%H:%M").to_string(),
+                    "timezone": window.timezone.name(),
+                    "quiet": quiet,
+                    "endsAtMs": quiet.then(|| window.next_end_ms(now)),
+                }),
+            )
+        })
+        .collect::<Map<String, Value>>();
+
+    Ok(json!({
+        "ts": now,
+        "channel": channel,
+        "quietHours": quiet_hours,
+        "messages": messages,
+    }))
+}
+
+/// Ids of the channel adapters and plugins enabled by the runtime config.
+pub(crate) fn configured_channel_ids(
+    config: &crate::application::config::RuntimeConfig,
+) -> Vec<String> {
+    configured_default_channels(config)
+        .iter()
+        .filter(|entry| {
+            !matches!(
+                entry.get("kind").and_then(Value::as_str),
+                Some("internal" | "gateway")
+            )
+        })
+        .filter_map(|entry| entry.get("id").and_then(Value::as_str).map(str::to_owned))
+        .collect()
+}
+
 fn configured_default_channels(config: &crate::application::config::RuntimeConfig) -> Vec<Value> {
     let mut channels = BTreeMap::<String, Value>::new();
     channels.insert(
@@ -217,7 +337,52 @@ fn configured_default_channels(config: &crate::application::config::RuntimeConfi
             }),
         );
     }
-    for channel_id in config.channel_webhook_plugins.keys() {
+    if config.teams_app_id.is_some() {
+        channels.insert(
+            "teams".to_owned(),
+            json!({
+                "id": "teams",
+                "connected": true,
+                "kind": "adapter",
+            }),
+        );
+    }
+    if config.mattermost_webhook_token.is_some() {
+        channels.insert(
+            "mattermost".to_owned(),
+            json!({
+                "id": "mattermost",
+                "connected": true,
+                "kind": "adapter",
+            }),
+        );
+    }
+    if config.rocketchat_webhook_token.is_some() {
+        channels.insert(
+            "rocketchat".to_owned(),
+            json!({
+                "id": "rocketchat",
+                "connected": true,
+                "kind": "adapter",
+            }),
+        );
+    }
+    for seeded in &config.seed.channels {
+        let entry = json!({
+            "id": seeded.id.trim(),
+            "accountId": seeded.account_id,
+            "connected": seeded.connected.unwrap_or(true),
+            "kind": seeded.kind.as_deref().unwrap_or("seed"),
+        });
+        if let Some(key) = channel_entry_key(&entry) {
+            channels.insert(key, entry);
+        }
+    }
+    for channel_id in config
+        .channel_webhook_plugins
+        .keys()
+        .chain(config.channel_plugins.keys())
+    {
         channels.entry(channel_id.clone()).or_insert_with(|| {
             json!({
                 "id": channel_id,
@@ -1,51 +1,122 @@
+use std::collections::HashMap;
+
 use serde::Deserialize;
 use serde_json::{Value, json};
+use tokio::sync::mpsc;
 
 use crate::{
-    application::state::SharedState,
+    application::{
+        agent_backend::{AgentBackend, AgentTurn},
+        attachment_scan::{self, AttachmentInput},
+        cost_budget,
+        state::SharedState,
+        translator,
+    },
     domain::models::{AgentRunRecord, ChatMessage, SessionRecord},
     rpc::{
         SessionContext,
         dispatcher::map_domain_error,
-        methods::{parse_optional_params, parse_required_params},
+        methods::{
+            FieldSelection, agent, agents, parse_optional_params, parse_required_params,
+            session_kv, sessions,
+        },
+        schema::rpc_params,
     },
     storage::now_unix_ms,
 };
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ChatSendParams {
-    #[serde(default)]
-    session_key: Option<String>,
-    #[serde(default)]
-    session_id: Option<String>,
-    message: String,
-    #[serde(default)]
-    idempotency_key: Option<String>,
-    #[serde(default)]
-    deferred: Option<bool>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ChatSendParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        message: String,
+        #[serde(default)]
+        idempotency_key: Option<String>,
+        #[serde(default)]
+        deferred: Option<bool>,
+        /// Publish `chat` delta events while the backend produces the reply.
+        #[serde(default)]
+        stream: Option<bool>,
+        #[serde(default)]
+        attachments: Vec<AttachmentInput>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ChatHistoryParams {
-    #[serde(default)]
-    session_key: Option<String>,
-    #[serde(default)]
-    session_id: Option<String>,
-    #[serde(default)]
-    limit: Option<usize>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ChatHistoryParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        pinned_only: Option<bool>,
+        #[serde(default)]
+        fields: Option<Vec<String>>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ChatAbortParams {
-    #[serde(default)]
-    session_key: Option<String>,
-    #[serde(default)]
-    session_id: Option<String>,
-    #[serde(default)]
-    run_id: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ChatSearchParams {
+        query: String,
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        tags: Option<Vec<String>>,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ChatPinParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        message_id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ChatDeliveryStatusParams {
+        #[serde(default)]
+        delivery_id: Option<String>,
+        #[serde(default)]
+        run_id: Option<String>,
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ChatAbortParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        run_id: Option<String>,
+    }
 }
 
 pub async fn handle_send(
@@ -58,6 +129,13 @@ pub async fn handle_send(
     let session_key = resolve_session_key(parsed.session_key, parsed.session_id)?;
     let inbound = sanitize_chat_message(parsed.message)?;
     let deferred = parsed.deferred.unwrap_or(false);
+    let stream = parsed.stream.unwrap_or(false);
+    if deferred && stream {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid chat.send params: stream cannot be combined with deferred",
+        ));
+    }
 
     let run_id = parsed
         .idempotency_key
@@ -71,6 +149,23 @@ pub async fn handle_send(
     {
         return resolve_existing_chat_run(existing, &session_key);
     }
+    let attachments =
+        attachment_scan::store_attachments(state, "chat.send", &session_key, parsed.attachments)
+            .await?;
+    if let Some(holder) =
+        agent::claim_run_idempotency_key(state, &run_id, "chat.send", "main", &session_key).await
+    {
+        // The claim may be this instance's own, taken just before the run was stored.
+        if let Some(existing) = state
+            .get_agent_run(&run_id)
+            .await
+            .map_err(map_domain_error)?
+        {
+            return resolve_existing_chat_run(existing, &session_key);
+        }
+        resolve_existing_chat_run(holder, &session_key)?;
+        return Err(agent::run_held_elsewhere(&run_id));
+    }
 
     ensure_session_exists(state, &session_key).await?;
 
@@ -87,6 +182,7 @@ pub async fn handle_send(
                 "source": "chat.send",
                 "deferred": true,
                 "originConnId": session.conn_id.as_str(),
+                "attachments": attachments,
             }),
             created_at_ms: now,
             updated_at_ms: now,
@@ -106,16 +202,61 @@ pub async fn handle_send(
         }));
     }
 
-    let reply = format!("Echo: {inbound}");
+    let agent_model = agents::agent_model(state, "main").await?;
+    let downgrade = cost_budget::admit(state, "main", &session_key).await?;
+    let downgraded_from = downgrade.as_ref().map(|_| agent_model.clone());
+    let model = downgrade.or(agent_model);
+
+    let translated = translator::translate_inbound(state, &inbound).await;
+    let pinned = state
+        .list_pinned_chat_messages(&session_key)
+        .await
+        .map_err(map_domain_error)?;
+    let scratchpad = session_kv::load_scratchpad(state, &session_key).await?;
+    let backend = state.agent_backend().await;
+    let context = agent::resolve_run_context(
+        state,
+        "main",
+        &translated.text,
+        &pinned,
+        &scratchpad,
+        backend.name(),
+    )
+    .await?;
+    let turn = AgentTurn {
+        run_id: &run_id,
+        agent_id: "main",
+        session_key: &session_key,
+        input: &translated.text,
+        model: model.as_deref(),
+        pinned: &pinned,
+        scratchpad: &scratchpad,
+    };
+    let (reply, deltas) = if stream {
+        respond_with_chat_deltas(state, backend.as_ref(), turn, &session.conn_id).await
+    } else {
+        (backend.respond(turn).await, 0)
+    };
+    let reply = reply.map_err(|message| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_UNAVAILABLE,
+            format!("agent backend {} failed: {message}", backend.name()),
+        )
+    })?;
+    // Stored messages keep what each side read: the agent-language input and the reply in the
+    // user's language, with the other version under `metadata.translation`.
+    let (reply, reply_translation) =
+        translator::translate_reply(state, &reply, translated.user_language.as_deref()).await;
 
     let messages = vec![
         ChatMessage {
             id: format!("msg-{}", uuid::Uuid::new_v4()),
             role: "user".to_owned(),
-            text: inbound.clone(),
+            text: translated.text.clone(),
             status: "final".to_owned(),
             ts: now,
-            metadata: json!({ "runId": run_id }),
+            metadata: message_metadata(&run_id, translated.metadata, &attachments),
+            pinned: false,
         },
         ChatMessage {
             id: format!("msg-{}", uuid::Uuid::new_v4()),
@@ -123,7 +264,8 @@ pub async fn handle_send(
             text: reply.clone(),
             status: "final".to_owned(),
             ts: now.saturating_add(1),
-            metadata: json!({ "runId": run_id }),
+            metadata: message_metadata(&run_id, reply_translation, &[]),
+            pinned: false,
         },
     ];
 
@@ -132,7 +274,7 @@ pub async fn handle_send(
         .await
         .map_err(map_domain_error)?;
 
-    let run = AgentRunRecord {
+    let mut run = AgentRunRecord {
         id: run_id.clone(),
         agent_id: "main".to_owned(),
         input: inbound,
@@ -143,16 +285,26 @@ pub async fn handle_send(
             "source": "chat.send",
             "deferred": false,
             "originConnId": session.conn_id.as_str(),
+            "userLanguage": translated.user_language,
+            "context": context,
+            "model": model,
         }),
         created_at_ms: now,
         updated_at_ms: now,
         completed_at_ms: Some(now),
     };
+    if let Some(downgraded_from) = downgraded_from
+        && let Some(metadata) = run.metadata.as_object_mut()
+    {
+        metadata.insert("downgradedFrom".to_owned(), json!(downgraded_from));
+    }
+    cost_budget::record_cost(state, &mut run, model.as_deref());
 
     state
         .upsert_agent_run(&run)
         .await
         .map_err(map_domain_error)?;
+    cost_budget::publish_crossings(state, &run).await;
 
     publish_chat_final_event(
         state,
@@ -160,6 +312,7 @@ pub async fn handle_send(
         &run_id,
         &session_key,
         &reply,
+        deltas + 1,
         now,
     )
     .await;
@@ -172,12 +325,72 @@ pub async fn handle_send(
     }))
 }
 
+fn message_metadata(run_id: &str, translation: Option<Value>, attachments: &[Value]) -> Value {
+    let mut metadata = json!({ "runId": run_id });
+    if let Some(translation) = translation {
+        metadata["translation"] = translation;
+    }
+    if !attachments.is_empty() {
+        metadata["attachments"] = Value::from(attachments.to_vec());
+    }
+    metadata
+}
+
+/// Runs the backend in streaming mode and publishes each chunk to `conn_id` as a `chat` event
+/// with `state: delta`. Returns the reply and how many deltas were published.
+async fn respond_with_chat_deltas(
+    state: &SharedState,
+    backend: &dyn AgentBackend,
+    turn: AgentTurn<'_>,
+    conn_id: &str,
+) -> (Result<String, String>, u64) {
+    let (run_id, session_key) = (turn.run_id, turn.session_key);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut respond = backend.respond_streaming(turn, tx);
+    let mut seq = 0;
+    let mut text = String::new();
+    let mut publish = async |delta: String| {
+        seq += 1;
+        text.push_str(&delta);
+        state
+            .publish_gateway_event_for(
+                Some(conn_id),
+                "chat",
+                json!({
+                    "runId": run_id,
+                    "sessionKey": session_key,
+                    "state": "delta",
+                    "seq": seq,
+                    "delta": delta,
+                    "message": {
+                        "role": "assistant",
+                        "content": [{ "type": "text", "text": text }],
+                        "timestamp": now_unix_ms(),
+                    },
+                }),
+            )
+            .await;
+    };
+    let reply = loop {
+        tokio::select! {
+            biased;
+            Some(delta) = rx.recv() => publish(delta).await,
+            reply = &mut respond => break reply,
+        }
+    };
+    while let Ok(delta) = rx.try_recv() {
+        publish(delta).await;
+    }
+    (reply, seq)
+}
+
 async fn publish_chat_final_event(
     state: &SharedState,
     target_conn_id: Option<&str>,
     run_id: &str,
     session_key: &str,
     reply: &str,
+    seq: u64,
     timestamp: u64,
 ) {
     state
@@ -188,7 +401,7 @@ async fn publish_chat_final_event(
                 "runId": run_id,
                 "sessionKey": session_key,
                 "state": "final",
-                "seq": 1,
+                "seq": seq,
                 "message": {
                     "role": "assistant",
                     "content": [{ "type": "text", "text": reply }],
@@ -243,18 +456,268 @@ pub async fn handle_history(
     params: Option<&Value>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
     let parsed: ChatHistoryParams = parse_required_params("chat.history", params)?;
+    let fields = FieldSelection::parse("chat.history", parsed.fields)?;
     let session_key = resolve_session_key(parsed.session_key, parsed.session_id)?;
     let limit = parsed.limit.map(|value| value.clamp(1, 1_000));
 
-    let messages = state
-        .list_chat_messages(&session_key, limit)
+    // Pinned messages lead the response regardless of the history window.
+    let mut messages = state
+        .list_pinned_chat_messages(&session_key)
         .await
         .map_err(map_domain_error)?;
+    if !parsed.pinned_only.unwrap_or(false) {
+        messages.extend(
+            state
+                .list_chat_messages(&session_key, limit)
+                .await
+                .map_err(map_domain_error)?
+                .into_iter()
+                .filter(|message| !message.pinned),
+        );
+    }
+
+    let display_name = state.resolve_session_display_name(&session_key).await;
+
+    // Latest delivery per run, attached to that run's assistant replies.
+    let mut deliveries = HashMap::new();
+    if fields.includes("delivery") {
+        for delivery in state
+            .list_message_deliveries(Some(&session_key), None, 1_000)
+            .await
+            .map_err(map_domain_error)?
+        {
+            if let Some(run_id) = delivery.run_id.clone() {
+                deliveries.entry(run_id).or_insert(delivery);
+            }
+        }
+    }
+
+    let rendered = messages
+        .iter()
+        .map(|message| {
+            let mut value = json!(message);
+            let delivery = (message.role == "assistant")
+                .then(|| message.metadata.get("runId").and_then(Value::as_str))
+                .flatten()
+                .and_then(|run_id| deliveries.get(run_id));
+            if let (Some(delivery), Some(object)) = (delivery, value.as_object_mut()) {
+                object.insert(
+                    "delivery".to_owned(),
+                    json!({
+                        "id": delivery.id,
+                        "channel": delivery.channel,
+                        "status": delivery.status,
+                        "updatedAtMs": delivery.updated_at_ms,
+                    }),
+                );
+            }
+            fields.apply(value)
+        })
+        .collect::<Vec<_>>();
 
     Ok(json!({
         "sessionKey": session_key,
         "sessionId": session_key,
-        "messages": messages,
+        "displayName": display_name,
+        "messages": rendered,
+    }))
+}
+
+/// Searches message text across sessions, optionally narrowed to one `sessionKey` and/or to
+/// sessions carrying every tag in `tags`.
+pub async fn handle_search(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ChatSearchParams = parse_required_params("chat.search", params)?;
+    let query = trim_non_empty(parsed.query).ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid chat.search params: query is required",
+        )
+    })?;
+    let limit = parsed.limit.unwrap_or(50).clamp(1, 500);
+
+    let mut session_keys = parsed
+        .session_key
+        .and_then(trim_non_empty)
+        .map(|session_key| vec![session_key]);
+    if let Some(tags) = parsed.tags.map(|tags| sessions::sanitize_tags(&tags)) {
+        let tagged = state
+            .list_sessions()
+            .await
+            .map_err(map_domain_error)?
+            .into_iter()
+            .filter(|session| sessions::session_has_tags(session, &tags))
+            .map(|session| session.id)
+            .filter(|id| session_keys.as_ref().is_none_or(|keys| keys.contains(id)))
+            .collect();
+        session_keys = Some(tagged);
+    }
+
+    let results = state
+        .search_chat_messages(&query, session_keys.as_deref(), limit)
+        .await
+        .map_err(map_domain_error)?
+        .into_iter()
+        .map(|(session_key, message)| {
+            json!({
+                "sessionKey": session_key,
+                "message": message,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "query": query,
+        "count": results.len(),
+        "results": results,
+    }))
+}
+
+pub async fn handle_pin(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    set_pinned(state, session, "chat.pin", params, true).await
+}
+
+pub async fn handle_unpin(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    set_pinned(state, session, "chat.unpin", params, false).await
+}
+
+async fn set_pinned(
+    state: &SharedState,
+    session: &SessionContext,
+    method: &str,
+    params: Option<&Value>,
+    pinned: bool,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ChatPinParams = parse_required_params(method, params)?;
+    let session_key = resolve_session_key(parsed.session_key, parsed.session_id)?;
+    let message_id = trim_non_empty(parsed.message_id).ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid {method} params: messageId is required"),
+        )
+    })?;
+
+    let found = state
+        .set_chat_message_pinned(
+            &session_key,
+            &message_id,
+            pinned,
+            Some(session.client_id.as_str()),
+        )
+        .await
+        .map_err(map_domain_error)?;
+    if !found {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid {method} params: unknown messageId for sessionKey"),
+        ));
+    }
+
+    Ok(json!({
+        "ok": true,
+        "sessionKey": session_key,
+        "messageId": message_id,
+        "pinned": pinned,
+    }))
+}
+
+/// Records the last message the calling client displayed; `sessions.list` counts later messages
+/// as unread for that client.
+pub async fn handle_mark_read(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ChatPinParams = parse_required_params("chat.markRead", params)?;
+    let session_key = resolve_session_key(parsed.session_key, parsed.session_id)?;
+    let message_id = trim_non_empty(parsed.message_id).ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid chat.markRead params: messageId is required",
+        )
+    })?;
+
+    let marker = state
+        .mark_chat_read(&session.client_id, &session_key, &message_id)
+        .await
+        .map_err(map_domain_error)?
+        .ok_or_else(|| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                "invalid chat.markRead params: unknown messageId for sessionKey",
+            )
+        })?;
+    let unread = state
+        .count_unread_chat_messages(&session.client_id)
+        .await
+        .map_err(map_domain_error)?
+        .get(&session_key)
+        .copied()
+        .unwrap_or(0);
+
+    Ok(json!({
+        "ok": true,
+        "sessionKey": session_key,
+        "messageId": marker.message_id,
+        "markedAtMs": marker.marked_at_ms,
+        "unreadCount": unread,
+    }))
+}
+
+pub async fn handle_delivery_status(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ChatDeliveryStatusParams = parse_optional_params("chat.deliveryStatus", params)?;
+
+    if let Some(delivery_id) = parsed.delivery_id.and_then(trim_non_empty) {
+        let delivery = state
+            .get_message_delivery(&delivery_id)
+            .await
+            .map_err(map_domain_error)?
+            .ok_or_else(|| {
+                crate::protocol::ErrorShape::new(
+                    crate::protocol::ERROR_INVALID_REQUEST,
+                    "unknown deliveryId",
+                )
+            })?;
+        return Ok(json!({
+            "deliveries": [delivery],
+            "count": 1,
+        }));
+    }
+
+    let run_id = parsed.run_id.and_then(trim_non_empty);
+    let session_key = parsed
+        .session_key
+        .or(parsed.session_id)
+        .and_then(trim_non_empty);
+    if run_id.is_none() && session_key.is_none() {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid chat.deliveryStatus params: deliveryId, runId, or sessionKey is required",
+        ));
+    }
+
+    let limit = parsed.limit.unwrap_or(50).clamp(1, 500);
+    let deliveries = state
+        .list_message_deliveries(session_key.as_deref(), run_id.as_deref(), limit)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({
+        "deliveries": deliveries,
+        "count": deliveries.len(),
     }))
 }
 
@@ -6,27 +6,66 @@ use crate::{
     rpc::{
         dispatcher::map_domain_error,
         methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
 };
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ConfigWriteParams {
-    #[serde(default)]
-    config: Option<Value>,
-    #[serde(default)]
-    raw: Option<Value>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ConfigWriteParams {
+        #[serde(default)]
+        config: Option<Value>,
+        #[serde(default)]
+        raw: Option<Value>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ConfigPatchParams {
+        #[serde(default)]
+        patch: Option<Value>,
+        #[serde(default)]
+        raw: Option<Value>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ConfigEntriesBulkSetParams {
+        entries: Vec<ConfigEntryParam>,
+        #[serde(default)]
+        prefix: Option<String>,
+        #[serde(default)]
+        replace: bool,
+    }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct ConfigPatchParams {
-    #[serde(default)]
-    patch: Option<Value>,
-    #[serde(default)]
-    raw: Option<Value>,
+struct ConfigEntryParam {
+    key: String,
+    value: Value,
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ConfigEntriesBulkDeleteParams {
+        #[serde(default)]
+        prefix: Option<String>,
+        #[serde(default)]
+        keys: Vec<String>,
+        #[serde(default)]
+        dry_run: bool,
+    }
 }
 
+const MAX_BULK_ENTRIES: usize = 1_000;
+
 pub async fn handle_get(
     state: &SharedState,
     params: Option<&Value>,
@@ -97,6 +136,115 @@ pub async fn handle_patch(
     }))
 }
 
+pub async fn handle_entries_bulk_set(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ConfigEntriesBulkSetParams =
+        parse_required_params("config.entries.bulkSet", params)?;
+    let prefix = parsed.prefix.and_then(trim_non_empty);
+    if parsed.replace && prefix.is_none() {
+        return Err(invalid_params(
+            "config.entries.bulkSet",
+            "replace requires a prefix",
+        ));
+    }
+    if parsed.entries.len() > MAX_BULK_ENTRIES {
+        return Err(invalid_params(
+            "config.entries.bulkSet",
+            &format!("at most {MAX_BULK_ENTRIES} entries per call"),
+        ));
+    }
+
+    // Later entries win when a key repeats.
+    let mut entries: Vec<(String, Value)> = Vec::with_capacity(parsed.entries.len());
+    for entry in parsed.entries {
+        let Some(key) = trim_non_empty(entry.key) else {
+            return Err(invalid_params(
+                "config.entries.bulkSet",
+                "entry key must be non-empty",
+            ));
+        };
+        if let Some(prefix) = &prefix
+            && !key.starts_with(prefix.as_str())
+        {
+            return Err(invalid_params(
+                "config.entries.bulkSet",
+                &format!("entry key {key} is outside prefix {prefix}"),
+            ));
+        }
+        entries.retain(|(existing, _)| *existing != key);
+        entries.push((key, entry.value));
+    }
+
+    let replace_prefix = if parsed.replace {
+        prefix.as_deref()
+    } else {
+        None
+    };
+    let deleted_keys = state
+        .set_config_entry_values(&entries, replace_prefix)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({
+        "ok": true,
+        "prefix": prefix,
+        "set": entries.len(),
+        "deleted": deleted_keys.len(),
+        "deletedKeys": deleted_keys,
+    }))
+}
+
+pub async fn handle_entries_bulk_delete(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ConfigEntriesBulkDeleteParams =
+        parse_required_params("config.entries.bulkDelete", params)?;
+    let prefix = parsed.prefix.and_then(trim_non_empty);
+    let keys = parsed
+        .keys
+        .into_iter()
+        .filter_map(trim_non_empty)
+        .collect::<Vec<_>>();
+    if prefix.is_none() && keys.is_empty() {
+        return Err(invalid_params(
+            "config.entries.bulkDelete",
+            "prefix or keys required",
+        ));
+    }
+
+    let deleted = state
+        .delete_config_entry_values(prefix.as_deref(), &keys, parsed.dry_run)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({
+        "ok": true,
+        "prefix": prefix,
+        "dryRun": parsed.dry_run,
+        "deleted": deleted.len(),
+        "keys": deleted,
+    }))
+}
+
+fn invalid_params(method: &str, message: &str) -> crate::protocol::ErrorShape {
+    crate::protocol::ErrorShape::new(
+        crate::protocol::ERROR_INVALID_REQUEST,
+        format!("invalid {method} params: {message}"),
+    )
+}
+
+fn trim_non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
+
 #[must_use]
 pub fn handle_schema() -> Value {
     json!({
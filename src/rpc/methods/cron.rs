@@ -1,52 +1,98 @@
+use std::collections::BTreeMap;
+
 use serde::Deserialize;
 use serde_json::{Value, json};
 
 use crate::{
-    application::{cron_schedule::compute_next_run_ms, state::SharedState},
-    domain::models::{CronJobPatch, CronJobRecord, CronPayload, CronSchedule},
+    application::{
+        cron_schedule::{compute_next_run_ms, describe_job, describe_schedule, local_next_run},
+        cron_script::Script,
+        state::{CronRunTail, SharedState},
+    },
+    domain::{
+        models::{CronJobPatch, CronJobRecord, CronPayload, CronRunQuery, CronSchedule},
+        session_key::canonicalize_session_key,
+    },
     rpc::{
         dispatcher::map_domain_error,
-        methods::{parse_optional_params, parse_required_params},
+        methods::{FieldSelection, parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
     storage::now_unix_ms,
 };
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CronListParams {
-    #[serde(default)]
-    include_disabled: Option<bool>,
-    #[serde(default)]
-    limit: Option<usize>,
+/// Templates are config entries so every instance renders derived jobs from the same payload.
+const CRON_TEMPLATE_PREFIX: &str = "runtime/cron-templates/";
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct CronListParams {
+        #[serde(default)]
+        include_disabled: Option<bool>,
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        fields: Option<Vec<String>>,
+        #[serde(default)]
+        locale: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CronStatusParams {}
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct CronStatusParams {
+        #[serde(default)]
+        locale: Option<String>,
+    }
+}
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CronAddParams {
-    #[serde(default)]
-    id: Option<String>,
-    #[serde(default)]
-    name: Option<String>,
-    #[serde(default = "default_true")]
-    enabled: bool,
-    schedule: CronSchedule,
-    payload: CronPayload,
-    #[serde(default)]
-    metadata: Option<Value>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct CronDescribeParams {
+        schedule: CronSchedule,
+        #[serde(default)]
+        locale: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CronUpdateParams {
-    #[serde(default)]
-    id: Option<String>,
-    #[serde(default)]
-    job_id: Option<String>,
-    patch: CronPatchInput,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct CronAddParams {
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default = "default_true")]
+        enabled: bool,
+        schedule: CronSchedule,
+        #[serde(default)]
+        payload: Option<CronPayload>,
+        #[serde(default)]
+        template: Option<String>,
+        #[serde(default)]
+        template_params: Option<BTreeMap<String, String>>,
+        #[serde(default)]
+        metadata: Option<Value>,
+        /// Runs of this job allowed at once; defaults to 1.
+        #[serde(default)]
+        max_concurrent: Option<u32>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct CronUpdateParams {
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        job_id: Option<String>,
+        patch: CronPatchInput,
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -64,26 +110,82 @@ struct CronPatchInput {
     metadata: Option<Value>,
     #[serde(default)]
     next_run_ms: Option<Option<u64>>,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CronIdParams {
     #[serde(default)]
-    id: Option<String>,
+    template_params: Option<BTreeMap<String, String>>,
     #[serde(default)]
-    job_id: Option<String>,
+    max_concurrent: Option<Option<u32>>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CronRunsParams {
-    #[serde(default)]
-    id: Option<String>,
-    #[serde(default)]
-    job_id: Option<String>,
-    #[serde(default)]
-    limit: Option<usize>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct CronTemplateSetParams {
+        id: String,
+        #[serde(default)]
+        name: Option<String>,
+        payload: CronPayload,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct CronTemplateIdParams {
+        id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct CronIdParams {
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        job_id: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct CronRunsParams {
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        job_id: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        status: Option<String>,
+        /// `manual` or `scheduled`.
+        #[serde(default)]
+        trigger: Option<String>,
+        #[serde(default)]
+        since_ms: Option<u64>,
+        #[serde(default)]
+        until_ms: Option<u64>,
+        /// `nextCursor` of the previous page.
+        #[serde(default)]
+        cursor: Option<String>,
+        #[serde(default)]
+        stats: bool,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct CronRunsTailParams {
+        #[serde(default)]
+        run_id: Option<String>,
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        job_id: Option<String>,
+        #[serde(default)]
+        after_seq: Option<u64>,
+    }
 }
 
 pub async fn handle_list(
@@ -91,6 +193,7 @@ pub async fn handle_list(
     params: Option<&Value>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
     let parsed: CronListParams = parse_optional_params("cron.list", params)?;
+    let fields = FieldSelection::parse("cron.list", parsed.fields)?;
     let include_disabled = parsed.include_disabled.unwrap_or(true);
     let mut jobs = state.list_cron_jobs().await.map_err(map_domain_error)?;
     if !include_disabled {
@@ -101,8 +204,26 @@ pub async fn handle_list(
         jobs.truncate(limit);
     }
 
+    let locale = parsed.locale.unwrap_or_default();
+    let now = now_unix_ms();
+    let items = jobs
+        .iter()
+        .map(|job| {
+            let mut item = json!(job);
+            if fields.includes("description") {
+                item["description"] = json!(describe_job(job, now, &locale));
+            }
+            if fields.includes("timezone") || fields.includes("nextRunLocal") {
+                let (timezone, next_run_local) = local_next_run(job);
+                item["timezone"] = json!(timezone);
+                item["nextRunLocal"] = json!(next_run_local);
+            }
+            fields.apply(item)
+        })
+        .collect::<Vec<_>>();
+
     Ok(json!({
-        "jobs": jobs,
+        "jobs": items,
         "count": jobs.len(),
     }))
 }
@@ -111,8 +232,31 @@ pub async fn handle_status(
     state: &SharedState,
     params: Option<&Value>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
-    let _: CronStatusParams = parse_optional_params("cron.status", params)?;
-    state.cron_status().await.map_err(map_domain_error)
+    let parsed: CronStatusParams = parse_optional_params("cron.status", params)?;
+    state
+        .cron_status(&parsed.locale.unwrap_or_default())
+        .await
+        .map_err(map_domain_error)
+}
+
+pub async fn handle_describe(
+    _state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: CronDescribeParams = parse_required_params("cron.describe", params)?;
+    validate_schedule(&parsed.schedule)?;
+
+    let now = now_unix_ms();
+    let next_run_ms = compute_next_run_ms(&parsed.schedule, now).map_err(invalid_cron_error)?;
+    Ok(json!({
+        "description": describe_schedule(
+            &parsed.schedule,
+            next_run_ms,
+            now,
+            &parsed.locale.unwrap_or_default(),
+        ),
+        "nextRunMs": next_run_ms,
+    }))
 }
 
 pub async fn handle_add(
@@ -133,6 +277,43 @@ pub async fn handle_add(
         .and_then(trim_non_empty)
         .unwrap_or_else(|| format!("Cron {id}"));
 
+    let mut metadata = parsed.metadata.unwrap_or_else(|| json!({}));
+    let payload = match (parsed.payload, parsed.template.and_then(trim_non_empty)) {
+        (Some(payload), None) => payload,
+        (None, Some(template_id)) => {
+            let template_params = parsed.template_params.unwrap_or_default();
+            let template = load_template(state, "cron.add", &template_id).await?;
+            let payload = render_template(&template, &template_params)
+                .map_err(|error| invalid_params("cron.add", error))?;
+            let Some(object) = metadata.as_object_mut() else {
+                return Err(invalid_params(
+                    "cron.add",
+                    "metadata must be an object for templated jobs",
+                ));
+            };
+            object.insert(
+                "template".to_owned(),
+                json!({ "id": template_id, "params": template_params }),
+            );
+            payload
+        }
+        (Some(_), Some(_)) => {
+            return Err(invalid_params(
+                "cron.add",
+                "payload and template are mutually exclusive",
+            ));
+        }
+        (None, None) => {
+            return Err(invalid_params(
+                "cron.add",
+                "payload or template is required",
+            ));
+        }
+    };
+
+    validate_payload("cron.add", &payload)?;
+    validate_max_concurrent("cron.add", parsed.max_concurrent)?;
+
     let next_run_ms = if parsed.enabled {
         compute_next_run_ms(&parsed.schedule, now).map_err(invalid_cron_error)?
     } else {
@@ -144,12 +325,13 @@ pub async fn handle_add(
         name,
         enabled: parsed.enabled,
         schedule: parsed.schedule,
-        payload: parsed.payload,
-        metadata: parsed.metadata.unwrap_or_else(|| json!({})),
+        payload,
+        metadata,
         created_at_ms: now,
         updated_at_ms: now,
         last_run_ms: None,
         next_run_ms,
+        max_concurrent: parsed.max_concurrent,
     };
 
     state.add_cron_job(&job).await.map_err(map_domain_error)?;
@@ -176,13 +358,54 @@ pub async fn handle_update(
         None
     };
 
+    let mut payload = parsed.patch.payload;
+    let mut metadata = parsed.patch.metadata;
+    if let Some(template_params) = parsed.patch.template_params {
+        if payload.is_some() {
+            return Err(invalid_params(
+                "cron.update",
+                "payload and templateParams are mutually exclusive",
+            ));
+        }
+        let job = state
+            .get_cron_job(&id)
+            .await
+            .map_err(map_domain_error)?
+            .ok_or_else(|| invalid_params("cron.update", "unknown cron job"))?;
+        let (template_id, _) = template_link(&job)
+            .ok_or_else(|| invalid_params("cron.update", "job is not derived from a template"))?;
+        let template = load_template(state, "cron.update", &template_id).await?;
+        payload = Some(
+            render_template(&template, &template_params)
+                .map_err(|error| invalid_params("cron.update", error))?,
+        );
+        let mut next_metadata = metadata.unwrap_or(job.metadata);
+        let Some(object) = next_metadata.as_object_mut() else {
+            return Err(invalid_params(
+                "cron.update",
+                "metadata must be an object for templated jobs",
+            ));
+        };
+        object.insert(
+            "template".to_owned(),
+            json!({ "id": template_id, "params": template_params }),
+        );
+        metadata = Some(next_metadata);
+    }
+
+    if let Some(payload) = &payload {
+        validate_payload("cron.update", payload)?;
+    }
+    validate_max_concurrent("cron.update", parsed.patch.max_concurrent.flatten())?;
+
     let patch = CronJobPatch {
         name: parsed.patch.name.and_then(trim_non_empty),
         enabled: parsed.patch.enabled,
         schedule: parsed.patch.schedule,
-        payload: parsed.patch.payload,
-        metadata: parsed.patch.metadata,
+        payload,
+        metadata,
         next_run_ms,
+        max_concurrent: parsed.patch.max_concurrent,
     };
 
     let updated = state
@@ -205,6 +428,7 @@ pub async fn handle_remove(
         "ok": true,
         "id": id,
         "removed": removed,
+        "tombstoned": state.config().append_only,
     }))
 }
 
@@ -227,22 +451,439 @@ pub async fn handle_runs(
     params: Option<&Value>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
     let parsed: CronRunsParams = parse_optional_params("cron.runs", params)?;
+    let invalid = |message: &str| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid cron.runs params: {message}"),
+        )
+    };
     let job_id = parsed.id.or(parsed.job_id).and_then(trim_non_empty);
     let limit = parsed.limit.map(|value| value.clamp(1, 1_000));
-
-    let runs = state
-        .list_cron_runs(job_id.as_deref(), limit)
+    let status = parsed.status.and_then(trim_non_empty);
+    if status
+        .as_deref()
+        .is_some_and(|status| !matches!(status, "ok" | "error"))
+    {
+        return Err(invalid("status must be ok or error"));
+    }
+    let manual = match parsed.trigger.and_then(trim_non_empty).as_deref() {
+        None => None,
+        Some("manual") => Some(true),
+        Some("scheduled") => Some(false),
+        Some(_) => return Err(invalid("trigger must be manual or scheduled")),
+    };
+    if let (Some(since), Some(until)) = (parsed.since_ms, parsed.until_ms)
+        && since > until
+    {
+        return Err(invalid("sinceMs must not be after untilMs"));
+    }
+    let before = parsed
+        .cursor
+        .and_then(trim_non_empty)
+        .map(|cursor| parse_runs_cursor(&cursor).ok_or_else(|| invalid("unknown cursor")))
+        .transpose()?;
+
+    let mut query = CronRunQuery {
+        job_id,
+        status,
+        manual,
+        since_ms: parsed.since_ms,
+        until_ms: parsed.until_ms,
+        before,
+        // One extra row tells whether another page follows.
+        limit: limit.map(|limit| limit + 1),
+    };
+    let mut runs = state
+        .query_cron_runs(&query)
         .await
         .map_err(map_domain_error)?;
+    let next_cursor = match limit {
+        Some(limit) if runs.len() > limit => {
+            runs.truncate(limit);
+            runs.last()
+                .map(|run| format!("{}:{}", run.started_at_ms, run.id))
+        }
+        _ => None,
+    };
 
-    Ok(json!({
-        "scope": if job_id.is_some() { "job" } else { "all" },
-        "jobId": job_id,
+    let mut response = json!({
+        "scope": if query.job_id.is_some() { "job" } else { "all" },
+        "jobId": query.job_id,
         "runs": runs,
         "count": runs.len(),
+        "nextCursor": next_cursor,
+    });
+    if parsed.stats {
+        query.before = None;
+        let jobs = state
+            .cron_run_stats(&query)
+            .await
+            .map_err(map_domain_error)?;
+        let total = jobs.iter().map(|job| job.runs).sum::<u64>();
+        let ok = jobs.iter().map(|job| job.ok).sum::<u64>();
+        let duration_ms = jobs
+            .iter()
+            .map(|job| job.avg_duration_ms.saturating_mul(job.runs))
+            .sum::<u64>();
+        response["stats"] = json!({
+            "runs": total,
+            "ok": ok,
+            "errors": total - ok,
+            "successRate": if total == 0 { 0.0 } else { ok as f64 / total as f64 },
+            "avgDurationMs": duration_ms.checked_div(total).unwrap_or(0),
+            "jobs": jobs,
+        });
+    }
+    Ok(response)
+}
+
+/// Cursors have the form `<startedAtMs>:<runId>` of the last run on the previous page.
+fn parse_runs_cursor(cursor: &str) -> Option<(u64, String)> {
+    let (started_at_ms, run_id) = cursor.split_once(':')?;
+    let started_at_ms = started_at_ms.parse().ok()?;
+    (!run_id.is_empty()).then(|| (started_at_ms, run_id.to_owned()))
+}
+
+pub async fn handle_runs_tail(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: CronRunsTailParams = parse_required_params("cron.runs.tail", params)?;
+    let run_id = parsed.run_id.and_then(trim_non_empty);
+    let job_id = parsed.id.or(parsed.job_id).and_then(trim_non_empty);
+    if run_id.is_none() && job_id.is_none() {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid cron.runs.tail params: runId or jobId is required",
+        ));
+    }
+
+    let tail = state
+        .cron_run_tail(run_id.as_deref(), job_id.as_deref())
+        .await
+        .map_err(map_domain_error)?
+        .ok_or_else(|| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                "unknown cron run",
+            )
+        })?;
+
+    Ok(match tail {
+        CronRunTail::Running {
+            run_id,
+            job_id,
+            manual,
+            started_at_ms,
+            mut chunks,
+        } => {
+            // Passing `lastSeq` back as `afterSeq` resumes right after the newest chunk.
+            let last_seq = chunks.last().map_or(0, |chunk| chunk.seq);
+            if let Some(after_seq) = parsed.after_seq {
+                chunks.retain(|chunk| chunk.seq > after_seq);
+            }
+            json!({
+                "runId": run_id,
+                "jobId": job_id,
+                "status": "running",
+                "done": false,
+                "manual": manual,
+                "startedAtMs": started_at_ms,
+                "chunks": chunks,
+                "lastSeq": last_seq,
+            })
+        }
+        CronRunTail::Finished(run) => json!({
+            "runId": run.id,
+            "jobId": run.job_id,
+            "status": run.status,
+            "done": true,
+            "manual": run.manual,
+            "startedAtMs": run.started_at_ms,
+            "finishedAtMs": run.finished_at_ms,
+            "output": run.output,
+            "error": run.error,
+            "chunks": [],
+        }),
+    })
+}
+
+pub async fn handle_templates_list(
+    state: &SharedState,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let entries = state
+        .list_config_entries(CRON_TEMPLATE_PREFIX, None)
+        .await
+        .map_err(map_domain_error)?;
+    let jobs = state.list_cron_jobs().await.map_err(map_domain_error)?;
+    let templates = entries
+        .into_iter()
+        .map(|entry| {
+            let mut template = entry.value;
+            let id = template
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned();
+            template["jobIds"] = json!(derived_job_ids(&jobs, &id));
+            template
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "templates": templates,
+        "count": templates.len(),
+    }))
+}
+
+/// Creates or replaces a template and re-renders every job derived from it. Jobs whose stored
+/// params no longer satisfy the template keep their previous payload and are reported as skipped.
+pub async fn handle_templates_set(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: CronTemplateSetParams = parse_required_params("cron.templates.set", params)?;
+    let id = trim_non_empty(parsed.id)
+        .ok_or_else(|| invalid_params("cron.templates.set", "id is required"))?;
+    if parsed.payload.kind.trim().is_empty() {
+        return Err(invalid_params(
+            "cron.templates.set",
+            "payload kind is required",
+        ));
+    }
+
+    let template = json!({
+        "id": id,
+        "name": parsed.name.and_then(trim_non_empty),
+        "payload": parsed.payload,
+        "placeholders": template_placeholders(&parsed.payload),
+        "updatedAtMs": now_unix_ms(),
+    });
+    state
+        .set_config_entry_value(&template_key(&id), &template)
+        .await
+        .map_err(map_domain_error)?;
+
+    let mut updated = Vec::new();
+    let mut skipped = Vec::new();
+    for job in state.list_cron_jobs().await.map_err(map_domain_error)? {
+        let Some((template_id, template_params)) = template_link(&job) else {
+            continue;
+        };
+        if template_id != id {
+            continue;
+        }
+        let payload = match render_template(&parsed.payload, &template_params) {
+            Ok(payload) => payload,
+            Err(error) => {
+                skipped.push(json!({ "id": job.id, "error": error }));
+                continue;
+            }
+        };
+        let patch = CronJobPatch {
+            payload: Some(payload),
+            ..CronJobPatch::default()
+        };
+        state
+            .update_cron_job(&job.id, patch)
+            .await
+            .map_err(map_domain_error)?;
+        updated.push(job.id);
+    }
+
+    Ok(json!({
+        "template": template,
+        "updated": updated,
+        "skipped": skipped,
     }))
 }
 
+pub async fn handle_templates_remove(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: CronTemplateIdParams = parse_required_params("cron.templates.remove", params)?;
+    let id = trim_non_empty(parsed.id)
+        .ok_or_else(|| invalid_params("cron.templates.remove", "id is required"))?;
+    let jobs = state.list_cron_jobs().await.map_err(map_domain_error)?;
+    let derived = derived_job_ids(&jobs, &id);
+    if !derived.is_empty() {
+        return Err(invalid_params(
+            "cron.templates.remove",
+            format!("template is used by jobs: {}", derived.join(", ")),
+        ));
+    }
+
+    let removed = state
+        .delete_config_entry_value(&template_key(&id))
+        .await
+        .map_err(map_domain_error)?;
+    Ok(json!({
+        "ok": true,
+        "id": id,
+        "removed": removed,
+    }))
+}
+
+async fn load_template(
+    state: &SharedState,
+    method: &str,
+    id: &str,
+) -> Result<CronPayload, crate::protocol::ErrorShape> {
+    let template = state
+        .get_config_entry_value(&template_key(id))
+        .await
+        .map_err(map_domain_error)?
+        .ok_or_else(|| invalid_params(method, format!("unknown template {id}")))?;
+    template
+        .get("payload")
+        .cloned()
+        .and_then(|payload| serde_json::from_value(payload).ok())
+        .ok_or_else(|| invalid_params(method, format!("template {id} has no valid payload")))
+}
+
+fn template_key(id: &str) -> String {
+    format!("{CRON_TEMPLATE_PREFIX}{id}")
+}
+
+/// Reads the `metadata.template` link `cron.add` records on derived jobs.
+fn template_link(job: &CronJobRecord) -> Option<(String, BTreeMap<String, String>)> {
+    let link = job.metadata.get("template")?;
+    let id = link.get("id").and_then(Value::as_str)?.to_owned();
+    let params = link
+        .get("params")
+        .cloned()
+        .and_then(|params| serde_json::from_value(params).ok())
+        .unwrap_or_default();
+    Some((id, params))
+}
+
+fn derived_job_ids(jobs: &[CronJobRecord], template_id: &str) -> Vec<String> {
+    jobs.iter()
+        .filter(|job| template_link(job).is_some_and(|(id, _)| id == template_id))
+        .map(|job| job.id.clone())
+        .collect()
+}
+
+/// Substitutes `{{name}}` placeholders in every text field of the payload.
+fn render_template(
+    template: &CronPayload,
+    params: &BTreeMap<String, String>,
+) -> Result<CronPayload, String> {
+    let render = |field: &Option<String>| {
+        field
+            .as_deref()
+            .map(|text| render_text(text, params))
+            .transpose()
+    };
+    Ok(CronPayload {
+        kind: template.kind.clone(),
+        text: render(&template.text)?,
+        message: render(&template.message)?,
+        model: render(&template.model)?,
+        thinking: render(&template.thinking)?,
+        timeout_seconds: template.timeout_seconds,
+        script: render(&template.script)?,
+        agent_id: render(&template.agent_id)?,
+        session_key: render(&template.session_key)?,
+    })
+}
+
+fn render_text(text: &str, params: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + end].trim();
+        let value = params
+            .get(name)
+            .ok_or_else(|| format!("missing template param {name}"))?;
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(value);
+        rest = &rest[start + 2 + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+fn template_placeholders(template: &CronPayload) -> Vec<String> {
+    let mut names = [
+        &template.text,
+        &template.message,
+        &template.model,
+        &template.thinking,
+        &template.script,
+        &template.agent_id,
+        &template.session_key,
+    ]
+    .into_iter()
+    .flatten()
+    .flat_map(|text| {
+        text.split("{{")
+            .skip(1)
+            .filter_map(|part| part.split_once("}}"))
+            .map(|(name, _)| name.trim().to_owned())
+            .collect::<Vec<_>>()
+    })
+    .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn invalid_params(method: &str, message: impl std::fmt::Display) -> crate::protocol::ErrorShape {
+    crate::protocol::ErrorShape::new(
+        crate::protocol::ERROR_INVALID_REQUEST,
+        format!("invalid {method} params: {message}"),
+    )
+}
+
+/// Script payloads are compiled up front so syntax errors surface on `cron.add`, not at run time.
+fn validate_max_concurrent(
+    method: &str,
+    max_concurrent: Option<u32>,
+) -> Result<(), crate::protocol::ErrorShape> {
+    if max_concurrent == Some(0) {
+        return Err(invalid_params(
+            method,
+            "maxConcurrent must be greater than 0",
+        ));
+    }
+    Ok(())
+}
+
+fn validate_payload(
+    method: &str,
+    payload: &CronPayload,
+) -> Result<(), crate::protocol::ErrorShape> {
+    if payload.kind == "agentTurn" {
+        if payload
+            .message
+            .as_deref()
+            .is_none_or(|message| message.trim().is_empty())
+        {
+            return Err(invalid_params(method, "agentTurn payload requires message"));
+        }
+        if let Some(session_key) = &payload.session_key {
+            canonicalize_session_key(session_key)
+                .map_err(|error| invalid_params(method, format!("sessionKey {error}")))?;
+        }
+        return Ok(());
+    }
+    if payload.kind != "script" {
+        return Ok(());
+    }
+    let source = payload
+        .script
+        .as_deref()
+        .ok_or_else(|| invalid_params(method, "script payload requires script"))?;
+    Script::compile(source)
+        .map(|_| ())
+        .map_err(|error| invalid_params(method, format!("invalid script: {error}")))
+}
+
 fn validate_schedule(schedule: &CronSchedule) -> Result<(), crate::protocol::ErrorShape> {
     if schedule.kind.trim().is_empty() {
         return Err(crate::protocol::ErrorShape::new(
@@ -287,3 +928,41 @@ fn trim_non_empty(value: String) -> Option<String> {
 const fn default_true() -> bool {
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{render_template, template_placeholders};
+    use crate::domain::models::CronPayload;
+
+    #[test]
+    fn templates_render_params_and_report_missing_ones() {
+        let template = CronPayload {
+            kind: "agentTurn".to_owned(),
+            text: None,
+            message: Some("daily report for {{ team }} ({{team}}) {{unterminated".to_owned()),
+            model: None,
+            thinking: None,
+            timeout_seconds: Some(30),
+            script: None,
+            agent_id: None,
+            session_key: Some("agent:main:{{team}}".to_owned()),
+        };
+        assert_eq!(template_placeholders(&template), vec!["team"]);
+
+        let params = BTreeMap::from([("team".to_owned(), "ops".to_owned())]);
+        let rendered = render_template(&template, &params).expect("template should render");
+        assert_eq!(
+            rendered.message.as_deref(),
+            Some("daily report for ops (ops) {{unterminated")
+        );
+        assert_eq!(rendered.session_key.as_deref(), Some("agent:main:ops"));
+        assert_eq!(rendered.timeout_seconds, Some(30));
+
+        assert_eq!(
+            render_template(&template, &BTreeMap::new()).map(|payload| payload.message),
+            Err("missing template param team".to_owned())
+        );
+    }
+}
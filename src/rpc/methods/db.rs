@@ -0,0 +1,92 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+
+use crate::{
+    application::{snapshots, state::SharedState},
+    rpc::{
+        SessionContext, dispatcher::map_domain_error, methods::parse_required_params,
+        schema::rpc_params,
+    },
+    storage::{PostgresMigrationOptions, redact_database_url},
+};
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct DbMigrateToParams {
+        target_url: String,
+        #[serde(default)]
+        replace: Option<bool>,
+        #[serde(default)]
+        cutover: Option<bool>,
+    }
+}
+
+pub async fn handle_migrate_to(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: DbMigrateToParams = parse_required_params("db.migrateTo", params)?;
+    let target_url = parsed.target_url.trim().to_owned();
+    if !target_url.starts_with("postgres://") && !target_url.starts_with("postgresql://") {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid db.migrateTo params: targetUrl must be a postgres:// URL",
+        ));
+    }
+    tracing::info!(
+        "db.migrateTo requested by {} target={}",
+        session.client_id,
+        redact_database_url(&target_url)
+    );
+
+    // The store reports progress synchronously; events are published from a forwarding task.
+    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+    let publisher = state.clone();
+    let forwarder = tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            publisher
+                .publish_gateway_event("db.migrate.progress", json!(progress))
+                .await;
+        }
+    });
+
+    let result = state
+        .migrate_to_postgres(
+            &target_url,
+            PostgresMigrationOptions {
+                replace: parsed.replace.unwrap_or(false),
+                cutover: parsed.cutover.unwrap_or(false),
+            },
+            &move |progress| {
+                let _ = progress_tx.send(progress.clone());
+            },
+        )
+        .await;
+    let _ = forwarder.await;
+
+    Ok(json!(result.map_err(map_domain_error)?))
+}
+
+pub async fn handle_snapshot_publish(
+    state: &SharedState,
+    session: &SessionContext,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let Some(config) = state.config().snapshots.clone() else {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "snapshot.publish requires snapshotTarget to be configured",
+        ));
+    };
+    tracing::info!("snapshot.publish requested by {}", session.client_id);
+
+    let report = snapshots::publish_and_record(state, &config)
+        .await
+        .map_err(map_domain_error)?;
+    Ok(json!({
+        "ok": true,
+        "snapshot": report,
+    }))
+}
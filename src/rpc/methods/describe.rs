@@ -0,0 +1,439 @@
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+use super::{
+    agent, agents, apikeys, approval_links, approvals, channels, chat, config, cron, db, device,
+    doctor, events, exec, federation, identities, logs, models, nodes, privacy, replication, send,
+    session_kv, sessions, skills, system, takeover, talk, tools, tts, update, usage, voicewake,
+    wizard,
+};
+use crate::{
+    application::geofence::Geofence,
+    protocol::{ERROR_INVALID_REQUEST, ErrorShape},
+    rpc::{
+        methods::{BASE_METHODS, METHOD_ALIASES, aliases_of, parse_optional_params, resolve_alias},
+        policy,
+        schema::{self, NoParams, ParamsSchema, rpc_params},
+    },
+};
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct MethodsDescribeParams {
+        #[serde(default)]
+        method: Option<String>,
+        #[serde(default)]
+        methods: Option<Vec<String>>,
+    }
+}
+
+type SchemaFn = fn() -> Value;
+
+/// Params struct of every method, published by `methods.describe` and `methods.schema` and
+/// checked by the dispatcher before the handler runs.
+const PARAM_SCHEMAS: &[(&str, SchemaFn)] = &[
+    ("health", NoParams::schema),
+    ("methods.describe", MethodsDescribeParams::schema),
+    ("methods.schema", MethodsDescribeParams::schema),
+    ("doctor.memory.status", NoParams::schema),
+    (
+        "doctor.storage.slowQueries",
+        doctor::SlowQueriesParams::schema,
+    ),
+    ("events.ack", events::EventsAckParams::schema),
+    ("events.subscribe", events::EventsSubscribeParams::schema),
+    (
+        "events.unsubscribe",
+        events::EventsUnsubscribeParams::schema,
+    ),
+    ("logs.tail", logs::LogsTailParams::schema),
+    ("logs.redaction.test", logs::RedactionTestParams::schema),
+    ("channels.status", channels::ChannelsStatusParams::schema),
+    ("channels.logout", channels::ChannelsLogoutParams::schema),
+    (
+        "channels.directory.list",
+        channels::ChannelsDirectoryListParams::schema,
+    ),
+    (
+        "channels.outbound.queue",
+        channels::ChannelsOutboundQueueParams::schema,
+    ),
+    ("identities.link", identities::IdentitiesLinkParams::schema),
+    (
+        "identities.unlink",
+        identities::IdentitiesUnlinkParams::schema,
+    ),
+    ("identities.list", identities::IdentitiesListParams::schema),
+    ("privacy.export", privacy::PrivacySubjectParams::schema),
+    ("privacy.delete", privacy::PrivacySubjectParams::schema),
+    (
+        "privacy.audit.list",
+        privacy::PrivacyAuditListParams::schema,
+    ),
+    ("status", NoParams::schema),
+    ("usage.status", NoParams::schema),
+    ("usage.cost", usage::UsageCostParams::schema),
+    ("tts.status", NoParams::schema),
+    ("tts.providers", NoParams::schema),
+    ("tts.enable", NoParams::schema),
+    ("tts.disable", NoParams::schema),
+    ("tts.convert", tts::TtsConvertParams::schema),
+    ("tts.setProvider", tts::TtsProviderParams::schema),
+    ("config.get", NoParams::schema),
+    ("config.set", config::ConfigWriteParams::schema),
+    ("config.apply", config::ConfigWriteParams::schema),
+    ("config.patch", config::ConfigPatchParams::schema),
+    ("config.schema", NoParams::schema),
+    (
+        "config.entries.bulkSet",
+        config::ConfigEntriesBulkSetParams::schema,
+    ),
+    (
+        "config.entries.bulkDelete",
+        config::ConfigEntriesBulkDeleteParams::schema,
+    ),
+    (
+        "exec.approvals.get",
+        approvals::ExecApprovalsGetParams::schema,
+    ),
+    (
+        "exec.approvals.set",
+        approvals::ExecApprovalsSetParams::schema,
+    ),
+    (
+        "exec.approvals.node.get",
+        approvals::ExecApprovalsNodeGetParams::schema,
+    ),
+    (
+        "exec.approvals.node.set",
+        approvals::ExecApprovalsNodeSetParams::schema,
+    ),
+    (
+        "exec.approval.request",
+        approvals::ExecApprovalRequestParams::schema,
+    ),
+    (
+        "exec.approval.waitDecision",
+        approvals::ExecApprovalWaitParams::schema,
+    ),
+    (
+        "exec.approval.resolve",
+        approvals::ExecApprovalResolveParams::schema,
+    ),
+    (
+        "approval.link.create",
+        approval_links::ApprovalLinkCreateParams::schema,
+    ),
+    (
+        "approval.link.get",
+        approval_links::ApprovalLinkGetParams::schema,
+    ),
+    (
+        "approval.link.resolve",
+        approval_links::ApprovalLinkResolveParams::schema,
+    ),
+    ("federation.invite", NoParams::schema),
+    ("federation.pair", federation::FederationPairParams::schema),
+    ("federation.peers.list", NoParams::schema),
+    (
+        "federation.unpair",
+        federation::FederationUnpairParams::schema,
+    ),
+    ("replication.status", NoParams::schema),
+    (
+        "replication.promote",
+        replication::ReplicationPromoteParams::schema,
+    ),
+    ("secrets.status", NoParams::schema),
+    ("exec.run", exec::ExecRunParams::schema),
+    ("wizard.start", wizard::WizardStartParams::schema),
+    ("wizard.next", wizard::WizardNextParams::schema),
+    ("wizard.cancel", wizard::WizardCancelParams::schema),
+    ("wizard.status", wizard::WizardStatusParams::schema),
+    ("talk.config", NoParams::schema),
+    ("talk.mode", talk::TalkModeParams::schema),
+    ("models.list", models::ModelsListParams::schema),
+    ("tools.catalog", tools::ToolsCatalogParams::schema),
+    ("tools.register", tools::ToolRegisterParams::schema),
+    ("tools.unregister", tools::ToolNameParams::schema),
+    ("tools.grant", tools::ToolGrantParams::schema),
+    ("tools.revoke", tools::ToolGrantParams::schema),
+    ("tools.call", tools::ToolCallParams::schema),
+    ("tools.calls.list", tools::ToolCallsListParams::schema),
+    ("agents.list", agents::AgentsListParams::schema),
+    ("agents.create", agents::AgentsCreateParams::schema),
+    ("agents.update", agents::AgentsUpdateParams::schema),
+    ("agents.delete", agents::AgentsDeleteParams::schema),
+    ("agents.files.list", agents::AgentsFilesListParams::schema),
+    ("agents.files.get", agents::AgentsFilesGetParams::schema),
+    ("agents.files.set", agents::AgentsFilesSetParams::schema),
+    ("skills.status", skills::SkillsStatusParams::schema),
+    ("skills.bins", NoParams::schema),
+    ("skills.install", skills::SkillsInstallParams::schema),
+    ("skills.update", skills::SkillsUpdateParams::schema),
+    ("update.run", update::UpdateRunParams::schema),
+    ("db.migrateTo", db::DbMigrateToParams::schema),
+    ("snapshot.publish", NoParams::schema),
+    ("voicewake.get", NoParams::schema),
+    ("voicewake.set", voicewake::VoicewakeSetParams::schema),
+    ("sessions.list", sessions::SessionsListParams::schema),
+    ("sessions.tags.list", NoParams::schema),
+    ("sessions.preview", sessions::SessionsPreviewParams::schema),
+    ("sessions.patch", sessions::SessionsPatchParams::schema),
+    (
+        "sessions.bulkPatch",
+        sessions::SessionsBulkPatchParams::schema,
+    ),
+    ("sessions.reset", NoParams::schema),
+    ("sessions.delete", sessions::SessionsDeleteParams::schema),
+    ("sessions.compact", sessions::SessionsCompactParams::schema),
+    ("session.kv.get", session_kv::SessionKvGetParams::schema),
+    ("session.kv.set", session_kv::SessionKvSetParams::schema),
+    (
+        "session.kv.delete",
+        session_kv::SessionKvDeleteParams::schema,
+    ),
+    ("last-heartbeat", NoParams::schema),
+    ("set-heartbeats", system::HeartbeatsSetParams::schema),
+    ("wake", system::WakeParams::schema),
+    ("node.pair.request", nodes::NodePairRequestParams::schema),
+    ("node.pair.list", NoParams::schema),
+    ("node.pair.approve", nodes::NodePairResolveParams::schema),
+    ("node.pair.reject", nodes::NodePairResolveParams::schema),
+    ("node.pair.verify", nodes::NodeVerifyParams::schema),
+    ("device.pair.list", device::DeviceFilter::schema),
+    (
+        "device.pair.approve",
+        device::DevicePairApproveParams::schema,
+    ),
+    ("device.pair.reject", device::DevicePairRejectParams::schema),
+    ("device.pair.remove", device::DevicePairRemoveParams::schema),
+    (
+        "device.pair.bulkApprove",
+        device::DevicePairBulkApproveParams::schema,
+    ),
+    (
+        "device.token.rotate",
+        device::DeviceTokenRotateParams::schema,
+    ),
+    (
+        "device.token.revoke",
+        device::DeviceTokenRevokeParams::schema,
+    ),
+    (
+        "device.token.bulkRevoke",
+        device::DeviceTokenBulkRevokeParams::schema,
+    ),
+    ("apikeys.list", apikeys::ApiKeysListParams::schema),
+    ("apikeys.create", apikeys::ApiKeysCreateParams::schema),
+    ("apikeys.rotate", apikeys::ApiKeysIdParams::schema),
+    ("apikeys.revoke", apikeys::ApiKeysIdParams::schema),
+    ("node.rename", nodes::NodeRenameParams::schema),
+    ("node.list", nodes::NodeListParams::schema),
+    ("node.describe", nodes::NodeIdParams::schema),
+    ("node.invoke", nodes::NodeInvokeParams::schema),
+    (
+        "node.invoke.pending",
+        nodes::NodeInvokePendingParams::schema,
+    ),
+    ("node.invoke.cancel", nodes::NodeInvokeCancelParams::schema),
+    ("node.invoke.result", nodes::NodeInvokeResultParams::schema),
+    ("node.event", nodes::NodeEventParams::schema),
+    (
+        "node.metadata.update",
+        nodes::NodeMetadataUpdateParams::schema,
+    ),
+    (
+        "node.metadata.history",
+        nodes::NodeMetadataHistoryParams::schema,
+    ),
+    (
+        "node.latency.report",
+        nodes::NodeLatencyReportParams::schema,
+    ),
+    ("node.affinity.list", nodes::NodeAffinityListParams::schema),
+    ("node.file.push", nodes::NodeFilePushParams::schema),
+    ("node.file.pull", nodes::NodeFilePullParams::schema),
+    ("node.file.status", nodes::NodeFileTransferParams::schema),
+    ("node.file.list", nodes::NodeFileListParams::schema),
+    ("node.file.cancel", nodes::NodeFileTransferParams::schema),
+    ("node.geofence.set", Geofence::schema),
+    ("node.geofence.list", nodes::NodeGeofenceListParams::schema),
+    (
+        "node.geofence.remove",
+        nodes::NodeGeofenceRemoveParams::schema,
+    ),
+    ("cron.list", cron::CronListParams::schema),
+    ("cron.status", cron::CronStatusParams::schema),
+    ("cron.describe", cron::CronDescribeParams::schema),
+    ("cron.add", cron::CronAddParams::schema),
+    ("cron.update", cron::CronUpdateParams::schema),
+    ("cron.remove", cron::CronIdParams::schema),
+    ("cron.run", cron::CronIdParams::schema),
+    ("cron.runs", cron::CronRunsParams::schema),
+    ("cron.runs.tail", cron::CronRunsTailParams::schema),
+    ("cron.templates.list", NoParams::schema),
+    ("cron.templates.set", cron::CronTemplateSetParams::schema),
+    ("cron.templates.remove", cron::CronTemplateIdParams::schema),
+    ("system-presence", NoParams::schema),
+    ("system-event", system::SystemEventParams::schema),
+    ("system.shutdown", system::SystemStopParams::schema),
+    ("system.restart", system::SystemStopParams::schema),
+    (
+        "system.maintenance",
+        system::SystemMaintenanceParams::schema,
+    ),
+    ("send", send::SendParams::schema),
+    ("agent", agent::AgentRunParams::schema),
+    ("agent.identity.get", agent::AgentIdentityParams::schema),
+    ("agent.wait", agent::AgentWaitParams::schema),
+    ("agent.retry", agent::AgentRetryParams::schema),
+    ("agent.replay", agent::AgentReplayParams::schema),
+    ("browser.request", NoParams::schema),
+    ("chat.history", chat::ChatHistoryParams::schema),
+    ("chat.abort", chat::ChatAbortParams::schema),
+    ("chat.send", chat::ChatSendParams::schema),
+    ("chat.search", chat::ChatSearchParams::schema),
+    (
+        "chat.deliveryStatus",
+        chat::ChatDeliveryStatusParams::schema,
+    ),
+    ("chat.pin", chat::ChatPinParams::schema),
+    ("chat.markRead", chat::ChatPinParams::schema),
+    ("chat.unpin", chat::ChatPinParams::schema),
+    ("chat.takeover.start", takeover::TakeoverParams::schema),
+    ("chat.takeover.end", takeover::TakeoverParams::schema),
+    ("chat.takeover.reply", takeover::TakeoverReplyParams::schema),
+];
+
+pub fn handle(params: Option<&Value>) -> Result<Value, ErrorShape> {
+    let methods = requested_methods("methods.describe", params)?
+        .iter()
+        .map(|method| describe_method(method))
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "count": methods.len(),
+        "methods": methods,
+    }))
+}
+
+/// `methods.schema`: the JSON Schema of each method's params, keyed by method name.
+pub fn handle_schema(params: Option<&Value>) -> Result<Value, ErrorShape> {
+    let methods = requested_methods("methods.schema", params)?
+        .into_iter()
+        .map(|method| {
+            let target = resolve_alias(&method).map_or(method.as_str(), |alias| alias.target);
+            let schema = json!({ "params": params_schema(target) });
+            (method, schema)
+        })
+        .collect::<Map<_, _>>();
+
+    Ok(json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "count": methods.len(),
+        "methods": methods,
+    }))
+}
+
+/// Checks request params against the method's schema before the handler runs, so malformed
+/// params fail the same way for every method.
+pub(crate) fn validate_params(method: &str, params: &Value) -> Result<(), ErrorShape> {
+    let Some((_, params_schema)) = PARAM_SCHEMAS.iter().find(|(name, _)| *name == method) else {
+        return Ok(());
+    };
+    schema::validate(&params_schema(), params).map_err(|error| {
+        ErrorShape::new(
+            ERROR_INVALID_REQUEST,
+            format!("invalid {method} params: {error}"),
+        )
+    })
+}
+
+/// Methods named by `method`/`methods`, or every method and alias when neither is given.
+fn requested_methods(method: &str, params: Option<&Value>) -> Result<Vec<String>, ErrorShape> {
+    let parsed: MethodsDescribeParams = parse_optional_params(method, params)?;
+    let requested = parsed
+        .method
+        .into_iter()
+        .chain(parsed.methods.unwrap_or_default())
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+
+    if requested.is_empty() {
+        return Ok(BASE_METHODS
+            .iter()
+            .copied()
+            .chain(METHOD_ALIASES.iter().map(|alias| alias.alias))
+            .map(str::to_owned)
+            .collect());
+    }
+    for name in &requested {
+        if !BASE_METHODS.contains(&name.as_str()) && resolve_alias(name).is_none() {
+            return Err(ErrorShape::new(
+                ERROR_INVALID_REQUEST,
+                format!("invalid {method} params: unknown method {name}"),
+            ));
+        }
+    }
+    Ok(requested)
+}
+
+fn describe_method(method: &str) -> Value {
+    let (status, target, replacement) = match resolve_alias(method) {
+        Some(alias) => ("deprecated", alias.target, Some(alias.target)),
+        None => ("stable", method, None),
+    };
+    json!({
+        "name": method,
+        "status": status,
+        "replacement": replacement,
+        "aliases": aliases_of(target),
+        "role": policy::required_role(target),
+        "scope": policy::required_scope(target),
+        "params": params_schema(target),
+    })
+}
+
+fn params_schema(method: &str) -> Value {
+    PARAM_SCHEMAS
+        .iter()
+        .find(|(name, _)| *name == method)
+        .map_or_else(
+            || json!({ "type": "object", "additionalProperties": true }),
+            |(_, schema)| schema(),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PARAM_SCHEMAS;
+    use crate::rpc::methods::{BASE_METHODS, METHOD_ALIASES};
+
+    #[test]
+    fn param_schemas_and_aliases_reference_known_methods() {
+        for (method, _) in PARAM_SCHEMAS {
+            assert!(BASE_METHODS.contains(method), "{method} is not a method");
+        }
+        for method in BASE_METHODS {
+            assert!(
+                PARAM_SCHEMAS.iter().any(|(name, _)| name == method),
+                "{method} has no params schema"
+            );
+        }
+        for alias in METHOD_ALIASES {
+            assert!(
+                BASE_METHODS.contains(&alias.target),
+                "{} has no target",
+                alias.alias
+            );
+            assert!(
+                !BASE_METHODS.contains(&alias.alias),
+                "{} shadows a method",
+                alias.alias
+            );
+        }
+    }
+}
@@ -1,18 +1,24 @@
 use std::collections::BTreeMap;
 
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value, json};
+use serde_json::{Value, json};
+use subtle::ConstantTimeEq;
 
 use crate::{
     application::state::SharedState,
     rpc::{
         dispatcher::map_domain_error,
         methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
+    security::api_keys::hash_api_key_secret,
     storage::now_unix_ms,
 };
 
 const DEVICE_STATE_KEY: &str = "runtime/device/state";
+pub(crate) const ACCESS_TOKEN_PREFIX: &str = "dtk_";
+const REFRESH_TOKEN_PREFIX: &str = "drt_";
+const MAX_BULK_ITEMS: usize = 200;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
@@ -32,13 +38,27 @@ struct DevicePairRequest {
     role: Option<String>,
     scopes: Vec<String>,
     created_at_ms: u64,
+    /// Requests stored without one expire `devicePairExpirySecs` after `created_at_ms`.
+    #[serde(default)]
+    expires_at_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct DeviceAuthToken {
     role: String,
-    token: String,
+    /// Only hashes are stored; the plaintext pair is returned once when issued. Entries from
+    /// before token pairs kept a plaintext `token`, which never authenticated a connection; it is
+    /// dropped on the next write, and `device.pair.list` reports `tokenPresent: false` until the
+    /// role is rotated.
+    #[serde(default)]
+    access_token_hash: String,
+    #[serde(default)]
+    access_expires_at_ms: u64,
+    #[serde(default)]
+    refresh_token_hash: String,
+    #[serde(default)]
+    refresh_expires_at_ms: u64,
     scopes: Vec<String>,
     created_at_ms: u64,
     rotated_at_ms: Option<u64>,
@@ -55,80 +75,124 @@ struct PairedDevice {
     approved_scopes: Vec<String>,
     paired_at_ms: u64,
     tokens: BTreeMap<String, DeviceAuthToken>,
+    /// Reported by the device's last token-authenticated connection.
+    #[serde(default)]
+    platform: Option<String>,
+    #[serde(default)]
+    last_seen_ms: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct DevicePairApproveParams {
-    request_id: String,
+/// Identity a connection gets from a device token.
+#[derive(Debug, Clone)]
+pub(crate) struct DeviceGrant {
+    pub device_id: String,
+    pub role: String,
+    pub scopes: Vec<String>,
+    /// New token pair when the grant came from a refresh token, for `hello-ok.auth`.
+    pub issued: Option<Value>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct DevicePairRejectParams {
-    request_id: String,
+rpc_params! {
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct DeviceFilter {
+        #[serde(default)]
+        platform: Option<String>,
+        #[serde(default)]
+        last_seen_before: Option<u64>,
+        #[serde(default)]
+        role: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct DevicePairRemoveParams {
-    device_id: String,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct DevicePairApproveParams {
+        request_id: String,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct DeviceTokenRotateParams {
-    device_id: String,
-    role: String,
-    #[serde(default)]
-    scopes: Option<Vec<String>>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct DevicePairBulkApproveParams {
+        #[serde(default)]
+        request_ids: Option<Vec<String>>,
+        #[serde(default)]
+        all: bool,
+        #[serde(default)]
+        role: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct DeviceTokenRevokeParams {
-    device_id: String,
-    role: String,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct DeviceTokenBulkRevokeParams {
+        #[serde(default)]
+        device_ids: Option<Vec<String>>,
+        #[serde(flatten)]
+        filter: DeviceFilter,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct DevicePairRejectParams {
+        request_id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct DevicePairRemoveParams {
+        device_id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct DeviceTokenRotateParams {
+        device_id: String,
+        role: String,
+        #[serde(default)]
+        scopes: Option<Vec<String>>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct DeviceTokenRevokeParams {
+        device_id: String,
+        role: String,
+    }
 }
 
 pub async fn handle_pair_list(
     state: &SharedState,
     params: Option<&Value>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
-    let _: Map<String, Value> = parse_optional_params("device.pair.list", params)?;
+    let filter: DeviceFilter = parse_optional_params("device.pair.list", params)?;
+    let filter = filter.normalize();
     let mut current = load_device_state(state).await?;
-
-    // Surface pending node pair requests as device-pair candidates.
-    let node_requests = state
-        .list_node_pair_requests()
-        .await
-        .map_err(map_domain_error)?;
-    for node_request in node_requests {
-        if node_request.status != "pending" {
-            continue;
-        }
-        if current
-            .pending
-            .iter()
-            .any(|request| request.request_id == node_request.request_id)
-        {
-            continue;
-        }
-        current.pending.push(DevicePairRequest {
-            request_id: node_request.request_id,
-            device_id: node_request.node_id,
-            display_name: Some(node_request.display_name),
-            role: Some("node".to_owned()),
-            scopes: Vec::new(),
-            created_at_ms: node_request.created_at_ms,
-        });
-    }
+    let pending = pending_requests(state, &current)
+        .await?
+        .into_iter()
+        .filter(|request| filter.matches_request(request))
+        .collect::<Vec<_>>();
+    fill_from_nodes(state, &mut current.paired).await?;
 
     Ok(json!({
-        "pending": current.pending,
+        "pending": pending,
         "paired": current
             .paired
             .iter()
+            .filter(|device| filter.matches_device(device))
             .map(redact_paired_device)
             .collect::<Vec<_>>(),
     }))
@@ -146,46 +210,9 @@ pub async fn handle_pair_approve(
         )
     })?;
 
+    let _writes = state.lock_device_state().await;
     let mut current = load_device_state(state).await?;
-    let mut approved: Option<PairedDevice> = None;
-
-    if let Some(index) = current
-        .pending
-        .iter()
-        .position(|entry| entry.request_id == request_id)
-    {
-        let pending = current.pending.remove(index);
-        let now = now_unix_ms();
-        let paired = PairedDevice {
-            device_id: pending.device_id.clone(),
-            display_name: pending.display_name,
-            role: pending.role,
-            scopes: pending.scopes.clone(),
-            approved_scopes: pending.scopes,
-            paired_at_ms: now,
-            tokens: BTreeMap::new(),
-        };
-        insert_or_replace_paired(&mut current.paired, paired.clone());
-        approved = Some(paired);
-    } else if let Ok(node_request) = state
-        .resolve_node_pair_request(&request_id, true, None)
-        .await
-    {
-        let now = now_unix_ms();
-        let paired = PairedDevice {
-            device_id: node_request.node_id,
-            display_name: Some(node_request.display_name),
-            role: Some("node".to_owned()),
-            scopes: Vec::new(),
-            approved_scopes: Vec::new(),
-            paired_at_ms: now,
-            tokens: BTreeMap::new(),
-        };
-        insert_or_replace_paired(&mut current.paired, paired.clone());
-        approved = Some(paired);
-    }
-
-    let Some(approved) = approved else {
+    let Some(approved) = approve_request(state, &mut current, &request_id).await else {
         return Err(crate::protocol::ErrorShape::new(
             crate::protocol::ERROR_INVALID_REQUEST,
             "unknown requestId",
@@ -199,6 +226,72 @@ pub async fn handle_pair_approve(
     }))
 }
 
+pub async fn handle_pair_bulk_approve(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: DevicePairBulkApproveParams =
+        parse_required_params("device.pair.bulkApprove", params)?;
+    let invalid = |message: &str| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid device.pair.bulkApprove params: {message}"),
+        )
+    };
+
+    let _writes = state.lock_device_state().await;
+    let mut current = load_device_state(state).await?;
+    let request_ids = match (parsed.request_ids, parsed.all) {
+        (Some(_), true) => return Err(invalid("pass either requestIds or all, not both")),
+        (Some(ids), false) => {
+            let mut unique = Vec::new();
+            for id in ids.into_iter().filter_map(trim_non_empty) {
+                if !unique.contains(&id) {
+                    unique.push(id);
+                }
+            }
+            unique
+        }
+        (None, true) => {
+            let filter = DeviceFilter {
+                role: parsed.role,
+                ..DeviceFilter::default()
+            }
+            .normalize();
+            pending_requests(state, &current)
+                .await?
+                .into_iter()
+                .filter(|request| filter.matches_request(request))
+                .map(|request| request.request_id)
+                .collect()
+        }
+        (None, false) => return Err(invalid("requestIds or all is required")),
+    };
+    if request_ids.len() > MAX_BULK_ITEMS {
+        return Err(invalid(&format!(
+            "at most {MAX_BULK_ITEMS} requests per call"
+        )));
+    }
+
+    let mut approved = Vec::new();
+    let mut unknown = Vec::new();
+    for request_id in request_ids {
+        match approve_request(state, &mut current, &request_id).await {
+            Some(device) => approved.push(json!({
+                "requestId": request_id,
+                "device": redact_paired_device(&device),
+            })),
+            None => unknown.push(request_id),
+        }
+    }
+
+    save_device_state(state, &current).await?;
+    Ok(json!({
+        "approved": approved,
+        "unknown": unknown,
+    }))
+}
+
 pub async fn handle_pair_reject(
     state: &SharedState,
     params: Option<&Value>,
@@ -211,6 +304,7 @@ pub async fn handle_pair_reject(
         )
     })?;
 
+    let _writes = state.lock_device_state().await;
     let mut current = load_device_state(state).await?;
     let mut device_id: Option<String> = None;
 
@@ -256,6 +350,7 @@ pub async fn handle_pair_remove(
         )
     })?;
 
+    let _writes = state.lock_device_state().await;
     let mut current = load_device_state(state).await?;
     let before = current.paired.len();
     current.paired.retain(|entry| entry.device_id != device_id);
@@ -267,10 +362,14 @@ pub async fn handle_pair_remove(
     }
 
     save_device_state(state, &current).await?;
+    let disconnected = state
+        .evict_device_connections(&device_id, None, "device was removed")
+        .await;
     Ok(json!({
         "ok": true,
         "deviceId": device_id,
         "removedAtMs": now_unix_ms(),
+        "disconnected": disconnected,
     }))
 }
 
@@ -292,6 +391,7 @@ pub async fn handle_token_rotate(
         )
     })?;
 
+    let _writes = state.lock_device_state().await;
     let mut current = load_device_state(state).await?;
     let Some(device) = current
         .paired
@@ -306,25 +406,22 @@ pub async fn handle_token_rotate(
 
     let scopes = sanitize_scopes(parsed.scopes.unwrap_or_else(|| device.scopes.clone()));
     let now = now_unix_ms();
-    let token = format!("dtk_{}", uuid::Uuid::new_v4().simple());
-    let entry = DeviceAuthToken {
+    let mut entry = DeviceAuthToken {
         role: role.clone(),
-        token: token.clone(),
+        access_token_hash: String::new(),
+        access_expires_at_ms: 0,
+        refresh_token_hash: String::new(),
+        refresh_expires_at_ms: 0,
         scopes: scopes.clone(),
         created_at_ms: now,
         rotated_at_ms: Some(now),
         revoked_at_ms: None,
     };
-    device.tokens.insert(role.clone(), entry);
+    let issued = issue_token_pair(state, &device_id, &mut entry, now);
+    device.tokens.insert(role, entry);
 
     save_device_state(state, &current).await?;
-    Ok(json!({
-        "deviceId": device_id,
-        "role": role,
-        "token": token,
-        "scopes": scopes,
-        "rotatedAtMs": now,
-    }))
+    Ok(issued)
 }
 
 pub async fn handle_token_revoke(
@@ -345,6 +442,7 @@ pub async fn handle_token_revoke(
         )
     })?;
 
+    let _writes = state.lock_device_state().await;
     let mut current = load_device_state(state).await?;
     let Some(device) = current
         .paired
@@ -366,13 +464,367 @@ pub async fn handle_token_revoke(
 
     let revoked_at_ms = now_unix_ms();
     save_device_state(state, &current).await?;
+    let disconnected = state
+        .evict_device_connections(&device_id, Some(&role), "device token was revoked")
+        .await;
     Ok(json!({
         "deviceId": device_id,
         "role": role,
         "revokedAtMs": revoked_at_ms,
+        "disconnected": disconnected,
+    }))
+}
+
+pub async fn handle_token_bulk_revoke(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: DeviceTokenBulkRevokeParams =
+        parse_required_params("device.token.bulkRevoke", params)?;
+    let filter = parsed.filter.normalize();
+    let device_ids = parsed.device_ids.map(|ids| {
+        ids.into_iter()
+            .filter_map(trim_non_empty)
+            .collect::<Vec<_>>()
+    });
+    if device_ids.is_none()
+        && filter.platform.is_none()
+        && filter.last_seen_before.is_none()
+        && filter.role.is_none()
+    {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid device.token.bulkRevoke params: deviceIds or a platform, lastSeenBefore or role filter is required",
+        ));
+    }
+    if device_ids
+        .as_ref()
+        .is_some_and(|ids| ids.len() > MAX_BULK_ITEMS)
+    {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!(
+                "invalid device.token.bulkRevoke params: at most {MAX_BULK_ITEMS} devices per call"
+            ),
+        ));
+    }
+
+    let _writes = state.lock_device_state().await;
+    let mut current = load_device_state(state).await?;
+    fill_from_nodes(state, &mut current.paired).await?;
+    let mut revoked = Vec::new();
+    for device in &mut current.paired {
+        let selected = device_ids
+            .as_ref()
+            .is_none_or(|ids| ids.contains(&device.device_id));
+        if !selected || !filter.matches_device(device) {
+            continue;
+        }
+        let roles = device
+            .tokens
+            .keys()
+            .filter(|role| filter.role.as_ref().is_none_or(|wanted| wanted == *role))
+            .cloned()
+            .collect::<Vec<_>>();
+        for role in roles {
+            device.tokens.remove(&role);
+            revoked.push((device.device_id.clone(), role));
+        }
+    }
+
+    let revoked_at_ms = now_unix_ms();
+    save_device_state(state, &current).await?;
+    let mut results = Vec::new();
+    for (device_id, role) in revoked {
+        let disconnected = state
+            .evict_device_connections(&device_id, Some(&role), "device token was revoked")
+            .await;
+        results.push(json!({
+            "deviceId": device_id,
+            "role": role,
+            "disconnected": disconnected,
+        }));
+    }
+    Ok(json!({
+        "revoked": results,
+        "revokedAtMs": revoked_at_ms,
     }))
 }
 
+/// Notes the platform and time of a token-authenticated connection for `device.pair.list`.
+pub(crate) async fn record_device_seen(
+    state: &SharedState,
+    device_id: &str,
+    platform: Option<&str>,
+) -> Result<(), crate::protocol::ErrorShape> {
+    let _writes = state.lock_device_state().await;
+    let mut current = load_device_state(state).await?;
+    let Some(device) = current
+        .paired
+        .iter_mut()
+        .find(|entry| entry.device_id == device_id)
+    else {
+        return Ok(());
+    };
+    device.last_seen_ms = Some(now_unix_ms());
+    if let Some(platform) = platform.map(str::trim).filter(|value| !value.is_empty()) {
+        device.platform = Some(platform.to_owned());
+    }
+    save_device_state(state, &current).await
+}
+
+/// Resolves an unexpired access token to the device and role it was issued for. With `role`,
+/// only a token issued for that role matches.
+pub(crate) async fn authenticate_device_token(
+    state: &SharedState,
+    access_token: &str,
+    role: Option<&str>,
+) -> Result<Option<DeviceGrant>, crate::protocol::ErrorShape> {
+    if !access_token.starts_with(ACCESS_TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let hash = hash_api_key_secret(access_token);
+    let now = now_unix_ms();
+    let current = load_device_state(state).await?;
+    Ok(current.paired.iter().find_map(|device| {
+        device
+            .tokens
+            .values()
+            .find(|entry| {
+                hash_matches(&hash, &entry.access_token_hash)
+                    && entry.access_expires_at_ms > now
+                    && role.is_none_or(|role| entry.role == role)
+            })
+            .map(|entry| DeviceGrant {
+                device_id: device.device_id.clone(),
+                role: entry.role.clone(),
+                scopes: entry.scopes.clone(),
+                issued: None,
+            })
+    }))
+}
+
+/// Exchanges an unexpired refresh token for a new access/refresh pair. The presented refresh
+/// token stops working and the new one expires a full refresh TTL from now.
+pub(crate) async fn refresh_device_tokens(
+    state: &SharedState,
+    refresh_token: &str,
+    role: Option<&str>,
+) -> Result<Option<DeviceGrant>, crate::protocol::ErrorShape> {
+    if !refresh_token.starts_with(REFRESH_TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let hash = hash_api_key_secret(refresh_token);
+    let now = now_unix_ms();
+    let _writes = state.lock_device_state().await;
+    let mut current = load_device_state(state).await?;
+    let Some((device_id, entry)) = current.paired.iter_mut().find_map(|device| {
+        let device_id = device.device_id.clone();
+        device
+            .tokens
+            .values_mut()
+            .find(|entry| {
+                hash_matches(&hash, &entry.refresh_token_hash)
+                    && entry.refresh_expires_at_ms > now
+                    && role.is_none_or(|role| entry.role == role)
+            })
+            .map(|entry| (device_id, entry))
+    }) else {
+        return Ok(None);
+    };
+
+    entry.rotated_at_ms = Some(now);
+    let issued = issue_token_pair(state, &device_id, entry, now);
+    let grant = DeviceGrant {
+        device_id,
+        role: entry.role.clone(),
+        scopes: entry.scopes.clone(),
+        issued: Some(issued),
+    };
+    save_device_state(state, &current).await?;
+    Ok(Some(grant))
+}
+
+/// Replaces both token hashes on `entry` and returns the plaintext pair for the caller.
+fn issue_token_pair(
+    state: &SharedState,
+    device_id: &str,
+    entry: &mut DeviceAuthToken,
+    now: u64,
+) -> Value {
+    let access_token = format!("{ACCESS_TOKEN_PREFIX}{}", uuid::Uuid::new_v4().simple());
+    let refresh_token = format!(
+        "{REFRESH_TOKEN_PREFIX}{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let ttl_ms = |ttl: std::time::Duration| u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+    entry.access_token_hash = hash_api_key_secret(&access_token);
+    entry.access_expires_at_ms = now.saturating_add(ttl_ms(state.config().device_access_token_ttl));
+    entry.refresh_token_hash = hash_api_key_secret(&refresh_token);
+    entry.refresh_expires_at_ms =
+        now.saturating_add(ttl_ms(state.config().device_refresh_token_ttl));
+
+    json!({
+        "deviceId": device_id,
+        "role": entry.role,
+        "token": access_token,
+        "expiresAtMs": entry.access_expires_at_ms,
+        "refreshToken": refresh_token,
+        "refreshExpiresAtMs": entry.refresh_expires_at_ms,
+        "scopes": entry.scopes,
+        "rotatedAtMs": now,
+    })
+}
+
+fn hash_matches(provided: &str, stored: &str) -> bool {
+    !stored.is_empty() && bool::from(provided.as_bytes().ct_eq(stored.as_bytes()))
+}
+
+impl DeviceFilter {
+    fn normalize(self) -> Self {
+        Self {
+            platform: self.platform.and_then(trim_non_empty),
+            last_seen_before: self.last_seen_before,
+            role: self.role.and_then(trim_non_empty),
+        }
+    }
+
+    /// Pending requests carry neither a platform nor a last-seen time, so only `role` applies.
+    fn matches_request(&self, request: &DevicePairRequest) -> bool {
+        self.role
+            .as_ref()
+            .is_none_or(|role| request.role.as_ref() == Some(role))
+    }
+
+    /// Devices never seen on a token connection count as last seen when they were paired.
+    fn matches_device(&self, device: &PairedDevice) -> bool {
+        let platform_matches = self.platform.as_ref().is_none_or(|platform| {
+            device
+                .platform
+                .as_ref()
+                .is_some_and(|value| value.eq_ignore_ascii_case(platform))
+        });
+        let stale = self
+            .last_seen_before
+            .is_none_or(|before| device.last_seen_ms.unwrap_or(device.paired_at_ms) < before);
+        let role_matches = self.role.as_ref().is_none_or(|role| {
+            device.role.as_ref() == Some(role) || device.tokens.contains_key(role)
+        });
+        platform_matches && stale && role_matches
+    }
+}
+
+/// Stored pending device requests plus pending node pair requests, which are surfaced as
+/// device-pair candidates.
+async fn pending_requests(
+    state: &SharedState,
+    current: &DeviceState,
+) -> Result<Vec<DevicePairRequest>, crate::protocol::ErrorShape> {
+    let mut pending = current.pending.clone();
+    let node_requests = state
+        .list_node_pair_requests()
+        .await
+        .map_err(map_domain_error)?;
+    for node_request in node_requests {
+        if node_request.status != "pending" {
+            continue;
+        }
+        if pending
+            .iter()
+            .any(|request| request.request_id == node_request.request_id)
+        {
+            continue;
+        }
+        pending.push(DevicePairRequest {
+            request_id: node_request.request_id,
+            device_id: node_request.node_id,
+            display_name: Some(node_request.display_name),
+            role: Some("node".to_owned()),
+            scopes: Vec::new(),
+            created_at_ms: node_request.created_at_ms,
+            expires_at_ms: node_request.expires_at_ms,
+        });
+    }
+    Ok(pending)
+}
+
+/// Paired nodes report their platform and last-seen time through the node registry.
+async fn fill_from_nodes(
+    state: &SharedState,
+    paired: &mut [PairedDevice],
+) -> Result<(), crate::protocol::ErrorShape> {
+    if !paired
+        .iter()
+        .any(|device| device.role.as_deref() == Some("node"))
+    {
+        return Ok(());
+    }
+    let nodes = state.list_nodes().await.map_err(map_domain_error)?;
+    for device in paired
+        .iter_mut()
+        .filter(|device| device.role.as_deref() == Some("node"))
+    {
+        let Some(node) = nodes.iter().find(|node| node.id == device.device_id) else {
+            continue;
+        };
+        if device.platform.is_none() && !node.platform.trim().is_empty() {
+            device.platform = Some(node.platform.clone());
+        }
+        if node.last_seen_ms > device.last_seen_ms.unwrap_or(0) {
+            device.last_seen_ms = Some(node.last_seen_ms);
+        }
+    }
+    Ok(())
+}
+
+/// Moves a pending device request, or approves a pending node pair request, into `paired`.
+async fn approve_request(
+    state: &SharedState,
+    current: &mut DeviceState,
+    request_id: &str,
+) -> Option<PairedDevice> {
+    let now = now_unix_ms();
+    let paired = if let Some(index) = current
+        .pending
+        .iter()
+        .position(|entry| entry.request_id == request_id)
+    {
+        let pending = current.pending.remove(index);
+        PairedDevice {
+            device_id: pending.device_id.clone(),
+            display_name: pending.display_name,
+            role: pending.role,
+            scopes: pending.scopes.clone(),
+            approved_scopes: pending.scopes,
+            paired_at_ms: now,
+            tokens: BTreeMap::new(),
+            platform: None,
+            last_seen_ms: None,
+        }
+    } else {
+        let node_request = state
+            .resolve_node_pair_request(request_id, true, None)
+            .await
+            .ok()?;
+        PairedDevice {
+            device_id: node_request.node_id,
+            display_name: Some(node_request.display_name),
+            role: Some("node".to_owned()),
+            scopes: Vec::new(),
+            approved_scopes: Vec::new(),
+            paired_at_ms: now,
+            tokens: BTreeMap::new(),
+            platform: None,
+            last_seen_ms: None,
+        }
+    };
+    insert_or_replace_paired(&mut current.paired, paired.clone());
+    Some(paired)
+}
+
+/// Reads the stored device state. Callers that save it back hold `lock_device_state` from
+/// before the load until after the save, so concurrent changes cannot overwrite each other.
 async fn load_device_state(
     state: &SharedState,
 ) -> Result<DeviceState, crate::protocol::ErrorShape> {
@@ -384,12 +836,28 @@ async fn load_device_state(
         return Ok(DeviceState::default());
     };
 
-    serde_json::from_value::<DeviceState>(raw).map_err(|error| {
+    let mut current = serde_json::from_value::<DeviceState>(raw).map_err(|error| {
         crate::protocol::ErrorShape::new(
             crate::protocol::ERROR_UNAVAILABLE,
             format!("failed to decode device state: {error}"),
         )
-    })
+    })?;
+    drop_expired_requests(state, &mut current, now_unix_ms());
+    Ok(current)
+}
+
+/// Drops pending device requests past their deadline; the next save persists the removal.
+fn drop_expired_requests(state: &SharedState, current: &mut DeviceState, now: u64) {
+    let window =
+        u64::try_from(state.config().request_expiry.device_pair.as_millis()).unwrap_or(u64::MAX);
+    for request in &mut current.pending {
+        request
+            .expires_at_ms
+            .get_or_insert(request.created_at_ms.saturating_add(window));
+    }
+    current
+        .pending
+        .retain(|request| request.expires_at_ms.is_none_or(|expires| now < expires));
 }
 
 async fn save_device_state(
@@ -442,7 +910,9 @@ fn redact_paired_device(device: &PairedDevice) -> Value {
             "createdAtMs": entry.created_at_ms,
             "rotatedAtMs": entry.rotated_at_ms,
             "revokedAtMs": entry.revoked_at_ms,
-            "tokenPresent": !entry.token.is_empty(),
+            "tokenPresent": !entry.access_token_hash.is_empty(),
+            "expiresAtMs": entry.access_expires_at_ms,
+            "refreshExpiresAtMs": entry.refresh_expires_at_ms,
         }));
     }
 
@@ -453,6 +923,8 @@ fn redact_paired_device(device: &PairedDevice) -> Value {
         "scopes": device.scopes,
         "approvedScopes": device.approved_scopes,
         "pairedAtMs": device.paired_at_ms,
+        "platform": device.platform,
+        "lastSeenMs": device.last_seen_ms,
         "tokens": summarized,
     })
 }
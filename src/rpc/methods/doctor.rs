@@ -1,6 +1,23 @@
+use serde::Deserialize;
 use serde_json::{Value, json};
 
-use crate::{application::state::SharedState, rpc::methods::parse_optional_params};
+use crate::{
+    application::{self_monitor, state::SharedState},
+    rpc::{methods::parse_optional_params, schema::rpc_params},
+    storage::SLOW_QUERY_WINDOW,
+};
+
+const DEFAULT_SLOW_QUERIES_LIMIT: usize = 20;
+const MAX_SLOW_QUERIES_LIMIT: usize = 200;
+
+rpc_params! {
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SlowQueriesParams {
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+}
 
 pub async fn handle_memory_status(
     state: &SharedState,
@@ -8,13 +25,49 @@ pub async fn handle_memory_status(
 ) -> Result<Value, crate::protocol::ErrorShape> {
     let _: serde_json::Map<String, Value> = parse_optional_params("doctor.memory.status", params)?;
 
+    let status = self_monitor::refresh_resource_status(state).await;
+    let guardrails = &state.config().guardrails;
+
     Ok(json!({
         "ok": true,
         "runtime": "rust",
         "uptimeMs": state.uptime_ms(),
         "connections": state.connection_count().await,
-        "heapBytes": 0,
-        "rssBytes": 0,
-        "note": "portable memory metrics are unavailable in this build",
+        "rssBytes": status.sample.rss_bytes,
+        "openFds": status.sample.open_fds,
+        "tokioTasks": status.sample.tokio_tasks,
+        "dbBytes": status.sample.db_bytes,
+        "sampledAtMs": status.sample.sampled_at_ms,
+        "guardrails": {
+            "maxRssBytes": guardrails.max_rss_bytes,
+            "maxOpenFds": guardrails.max_open_fds,
+            "maxTasks": guardrails.max_tasks,
+            "maxDbBytes": guardrails.max_db_bytes,
+            "actions": guardrails
+                .actions
+                .iter()
+                .map(|action| action.label())
+                .collect::<Vec<_>>(),
+        },
+        "withinGuardrails": !status.is_breached(),
+        "breaches": status.breaches,
+    }))
+}
+
+/// Storage operations that ran in the last hour, slowest first, to guide index and schema work.
+pub async fn handle_storage_slow_queries(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: SlowQueriesParams = parse_optional_params("doctor.storage.slowQueries", params)?;
+    let limit = parsed
+        .limit
+        .unwrap_or(DEFAULT_SLOW_QUERIES_LIMIT)
+        .clamp(1, MAX_SLOW_QUERIES_LIMIT);
+
+    Ok(json!({
+        "ok": true,
+        "windowMs": SLOW_QUERY_WINDOW.as_millis(),
+        "operations": state.storage_query_metrics().slow_operations(limit),
     }))
 }
@@ -0,0 +1,164 @@
+//! `events.ack`: at-least-once delivery for the event classes in [`ACKED_GATEWAY_EVENTS`].
+//! Clients that connect with `features.supportsEventAck` get those events with a journal `seq`,
+//! acknowledge them cumulatively, and receive the unacknowledged ones again on reconnect.
+//!
+//! `events.subscribe` / `events.unsubscribe`: narrow the broadcast events pushed to one
+//! connection to an allowlist, optionally scoped to a session or node. The filter lives as long
+//! as the connection.
+//!
+//! [`ACKED_GATEWAY_EVENTS`]: crate::application::state::ACKED_GATEWAY_EVENTS
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    application::state::{GatewayEventFilter, SharedState},
+    rpc::{
+        SessionContext,
+        dispatcher::map_domain_error,
+        methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
+    },
+};
+
+/// Most event names one connection may allow.
+pub const MAX_SUBSCRIBED_EVENTS: usize = 64;
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct EventsAckParams {
+        /// Acknowledges this event and every earlier one.
+        seq: u64,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct EventsSubscribeParams {
+        /// Event names added to the connection's allowlist.
+        events: Vec<String>,
+        /// Only events for this session; events without a `sessionKey` still pass.
+        #[serde(default)]
+        session_key: Option<String>,
+        /// Only events for this node; events without a `nodeId` still pass.
+        #[serde(default)]
+        node_id: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct EventsUnsubscribeParams {
+        /// Event names removed from the allowlist; omit to drop the filter and receive every event.
+        #[serde(default)]
+        events: Option<Vec<String>>,
+    }
+}
+
+pub async fn handle_ack(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: EventsAckParams = parse_required_params("events.ack", params)?;
+    let (acked_seq, pending) = state
+        .ack_gateway_events(&session.conn_id, parsed.seq)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({
+        "ok": true,
+        "ackedSeq": acked_seq,
+        "pending": pending,
+    }))
+}
+
+pub async fn handle_subscribe(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: EventsSubscribeParams = parse_required_params("events.subscribe", params)?;
+    let events = event_names("events.subscribe", parsed.events)?;
+    if events.is_empty() {
+        return Err(invalid_params("events.subscribe", "events is required"));
+    }
+    let filter = state
+        .subscribe_gateway_events(
+            &session.conn_id,
+            events,
+            parsed.session_key.and_then(trim_non_empty),
+            parsed.node_id.and_then(trim_non_empty),
+            MAX_SUBSCRIBED_EVENTS,
+        )
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({ "ok": true, "filter": filter_json(Some(&filter)) }))
+}
+
+pub async fn handle_unsubscribe(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: EventsUnsubscribeParams = parse_optional_params("events.unsubscribe", params)?;
+    let events = parsed
+        .events
+        .map(|events| event_names("events.unsubscribe", events))
+        .transpose()?;
+    let filter = state
+        .unsubscribe_gateway_events(&session.conn_id, events)
+        .await;
+
+    Ok(json!({ "ok": true, "filter": filter_json(filter.as_ref()) }))
+}
+
+/// `null` once the connection receives every broadcast event again.
+fn filter_json(filter: Option<&GatewayEventFilter>) -> Value {
+    filter.map_or(Value::Null, |filter| {
+        json!({
+            "events": filter.events,
+            "sessionKey": filter.session_key,
+            "nodeId": filter.node_id,
+        })
+    })
+}
+
+fn event_names(
+    method: &str,
+    events: Vec<String>,
+) -> Result<Vec<String>, crate::protocol::ErrorShape> {
+    if events.len() > MAX_SUBSCRIBED_EVENTS {
+        return Err(invalid_params(
+            method,
+            format!("at most {MAX_SUBSCRIBED_EVENTS} events may be given"),
+        ));
+    }
+    events
+        .into_iter()
+        .map(|event| {
+            trim_non_empty(event)
+                .ok_or_else(|| invalid_params(method, "event names must be non-empty"))
+        })
+        .collect()
+}
+
+fn invalid_params(method: &str, message: impl std::fmt::Display) -> crate::protocol::ErrorShape {
+    crate::protocol::ErrorShape::new(
+        crate::protocol::ERROR_INVALID_REQUEST,
+        format!("invalid {method} params: {message}"),
+    )
+}
+
+fn trim_non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
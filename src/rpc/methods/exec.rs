@@ -0,0 +1,327 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+use crate::{
+    application::{
+        exec_runner::{self, ExecChunk, ExecCommand, ExecStream},
+        state::SharedState,
+    },
+    domain::models::{ChatMessage, SessionRecord},
+    rpc::{
+        SessionContext,
+        dispatcher::map_domain_error,
+        methods::{
+            approvals::{self, ExecApprovalRequest, ExecPolicyDecision},
+            parse_required_params,
+        },
+        schema::rpc_params,
+    },
+    storage::now_unix_ms,
+};
+
+/// Output is appended to the session in chunks of roughly this many bytes.
+const SESSION_FLUSH_BYTES: usize = 4 * 1024;
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ExecRunParams {
+        command: String,
+        #[serde(default)]
+        cwd: Option<String>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+        #[serde(default)]
+        agent_id: Option<String>,
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        approval_id: Option<String>,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    }
+}
+
+pub async fn handle_run(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ExecRunParams = parse_required_params("exec.run", params)?;
+    let mut config = state.config().exec.clone();
+    if !config.enabled {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_UNAVAILABLE,
+            "exec runner is disabled; set execEnabled=true",
+        ));
+    }
+
+    let command = trim_non_empty(parsed.command).ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid exec.run params: command is required",
+        )
+    })?;
+    let agent_id = parsed
+        .agent_id
+        .and_then(trim_non_empty)
+        .unwrap_or_else(|| "main".to_owned());
+    let session_key = parsed
+        .session_key
+        .and_then(trim_non_empty)
+        .unwrap_or_else(|| format!("agent:{agent_id}:main"));
+    let cwd = parsed.cwd.and_then(trim_non_empty);
+    exec_runner::resolve_sandbox_dir(&config.workdir, cwd.as_deref()).map_err(|error| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid exec.run params: {error}"),
+        )
+    })?;
+    if let Some(timeout_ms) = parsed.timeout_ms.filter(|value| *value > 0) {
+        config.timeout = config
+            .timeout
+            .min(std::time::Duration::from_millis(timeout_ms));
+    }
+
+    let approval_id = parsed.approval_id.and_then(trim_non_empty);
+    match approvals::evaluate_exec_policy(
+        state,
+        &agent_id,
+        &command,
+        &parsed.env,
+        cwd.as_deref(),
+        &session_key,
+    )
+    .await?
+    {
+        ExecPolicyDecision::Deny(reason) => {
+            return Err(crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                format!("exec denied: {reason}"),
+            ));
+        }
+        ExecPolicyDecision::Allow => {}
+        ExecPolicyDecision::Ask => match &approval_id {
+            Some(id) => {
+                approvals::consume_exec_approval(
+                    state,
+                    id,
+                    &agent_id,
+                    &command,
+                    &parsed.env,
+                    cwd.as_deref(),
+                    &session_key,
+                )
+                .await?;
+            }
+            None => {
+                let record = approvals::request_exec_approval(
+                    state,
+                    ExecApprovalRequest {
+                        command,
+                        cwd,
+                        node_id: None,
+                        host: Some("gateway".to_owned()),
+                        security: None,
+                        ask: None,
+                        agent_id: Some(agent_id),
+                        resolved_path: None,
+                        session_key: Some(session_key),
+                        requested_by: Some(session.client_id.clone()),
+                        env: parsed.env,
+                    },
+                )
+                .await?;
+                return Ok(json!({
+                    "status": "approval-required",
+                    "approvalId": record.id,
+                    "expiresAtMs": record.expires_at_ms,
+                }));
+            }
+        },
+    }
+
+    ensure_session_exists(state, &session_key).await?;
+    let exec_id = format!("exec-{}", uuid::Uuid::new_v4());
+    let request = ExecCommand {
+        command: command.clone(),
+        cwd,
+        env: parsed.env,
+    };
+
+    let (chunk_tx, mut chunk_rx) = tokio::sync::mpsc::channel::<ExecChunk>(64);
+    let run = exec_runner::run_command(&config, &request, chunk_tx);
+    tokio::pin!(run);
+
+    let mut pending = Vec::<ExecChunk>::new();
+    let mut pending_bytes = 0usize;
+    let outcome = loop {
+        tokio::select! {
+            outcome = &mut run => {
+                while let Ok(chunk) = chunk_rx.try_recv() {
+                    stream_chunk(state, session, &exec_id, &session_key, &chunk).await;
+                    pending.push(chunk);
+                }
+                break outcome;
+            }
+            Some(chunk) = chunk_rx.recv() => {
+                stream_chunk(state, session, &exec_id, &session_key, &chunk).await;
+                pending_bytes += chunk.text.len();
+                pending.push(chunk);
+                if pending_bytes >= SESSION_FLUSH_BYTES {
+                    append_output(state, &exec_id, &session_key, &mut pending).await?;
+                    pending_bytes = 0;
+                }
+            }
+        }
+    };
+    append_output(state, &exec_id, &session_key, &mut pending).await?;
+
+    let outcome = outcome.map_err(|error| {
+        crate::protocol::ErrorShape::new(crate::protocol::ERROR_UNAVAILABLE, error)
+    })?;
+    let status = if outcome.timed_out {
+        "timed-out"
+    } else {
+        "completed"
+    };
+    let summary = ChatMessage {
+        id: format!("msg-{}", uuid::Uuid::new_v4()),
+        role: "tool".to_owned(),
+        text: format!(
+            "$ {command}\n[{status}, exit code {}]",
+            outcome
+                .exit_code
+                .map_or_else(|| "none".to_owned(), |code| code.to_string())
+        ),
+        status: "final".to_owned(),
+        ts: now_unix_ms(),
+        metadata: json!({
+            "source": "exec",
+            "execId": exec_id,
+            "outcome": outcome,
+        }),
+        pinned: false,
+    };
+    state
+        .append_chat_messages(&session_key, std::slice::from_ref(&summary))
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({
+        "ok": true,
+        "execId": exec_id,
+        "status": status,
+        "sessionKey": session_key,
+        "approvalId": approval_id,
+        "exitCode": outcome.exit_code,
+        "timedOut": outcome.timed_out,
+        "truncated": outcome.truncated,
+        "outputBytes": outcome.output_bytes,
+        "durationMs": outcome.duration_ms,
+        "cwd": outcome.cwd,
+    }))
+}
+
+async fn stream_chunk(
+    state: &SharedState,
+    session: &SessionContext,
+    exec_id: &str,
+    session_key: &str,
+    chunk: &ExecChunk,
+) {
+    state
+        .publish_gateway_event_for(
+            Some(&session.conn_id),
+            "exec",
+            json!({
+                "execId": exec_id,
+                "sessionKey": session_key,
+                "stream": chunk.stream,
+                "text": chunk.text,
+            }),
+        )
+        .await;
+}
+
+async fn append_output(
+    state: &SharedState,
+    exec_id: &str,
+    session_key: &str,
+    pending: &mut Vec<ExecChunk>,
+) -> Result<(), crate::protocol::ErrorShape> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let ts = now_unix_ms();
+    let messages = [ExecStream::Stdout, ExecStream::Stderr]
+        .into_iter()
+        .filter_map(|stream| {
+            let text = pending
+                .iter()
+                .filter(|chunk| chunk.stream == stream)
+                .map(|chunk| chunk.text.as_str())
+                .collect::<String>();
+            (!text.is_empty()).then(|| ChatMessage {
+                id: format!("msg-{}", uuid::Uuid::new_v4()),
+                role: "tool".to_owned(),
+                text,
+                status: "streaming".to_owned(),
+                ts,
+                metadata: json!({
+                    "source": "exec",
+                    "execId": exec_id,
+                    "stream": stream,
+                }),
+                pinned: false,
+            })
+        })
+        .collect::<Vec<_>>();
+    pending.clear();
+
+    state
+        .append_chat_messages(session_key, &messages)
+        .await
+        .map_err(map_domain_error)
+}
+
+async fn ensure_session_exists(
+    state: &SharedState,
+    session_key: &str,
+) -> Result<(), crate::protocol::ErrorShape> {
+    if state
+        .get_session(session_key)
+        .await
+        .map_err(map_domain_error)?
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    let now = now_unix_ms();
+    let session = SessionRecord {
+        id: session_key.to_owned(),
+        title: format!("Session {session_key}"),
+        tags: Vec::new(),
+        metadata: Value::Object(Map::new()),
+        created_at_ms: now,
+        updated_at_ms: now,
+    };
+
+    state
+        .upsert_session(&session)
+        .await
+        .map_err(map_domain_error)
+}
+
+fn trim_non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
@@ -0,0 +1,88 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    application::{
+        federation::{self, Federation},
+        state::SharedState,
+    },
+    protocol::{ERROR_INVALID_REQUEST, ERROR_UNAVAILABLE, ErrorShape},
+    rpc::{methods::parse_required_params, schema::rpc_params},
+};
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct FederationPairParams {
+        gateway_id: String,
+        url: String,
+        token: String,
+        public_key: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct FederationUnpairParams {
+        id: String,
+    }
+}
+
+pub async fn handle_invite(state: &SharedState) -> Result<Value, ErrorShape> {
+    let federation = configured(state)?;
+    federation::create_invite(state, &federation)
+        .await
+        .map_err(|error| ErrorShape::new(ERROR_UNAVAILABLE, error))
+}
+
+pub async fn handle_pair(state: &SharedState, params: Option<&Value>) -> Result<Value, ErrorShape> {
+    let federation = configured(state)?;
+    let parsed: FederationPairParams = parse_required_params("federation.pair", params)?;
+    if parsed.token.trim().is_empty() {
+        return Err(ErrorShape::new(
+            ERROR_INVALID_REQUEST,
+            "invalid federation.pair params: token is required",
+        ));
+    }
+    let peer = federation::pair_with(
+        state,
+        &federation,
+        &parsed.gateway_id,
+        &parsed.url,
+        &parsed.token,
+        &parsed.public_key,
+    )
+    .await
+    .map_err(|error| ErrorShape::new(ERROR_INVALID_REQUEST, format!("pairing failed: {error}")))?;
+    Ok(json!({ "peer": peer }))
+}
+
+pub async fn handle_peers_list(state: &SharedState) -> Result<Value, ErrorShape> {
+    configured(state)?;
+    let peers = federation::list_peers(state)
+        .await
+        .map_err(|error| ErrorShape::new(ERROR_UNAVAILABLE, error))?;
+    Ok(json!({ "peers": peers }))
+}
+
+pub async fn handle_unpair(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, ErrorShape> {
+    configured(state)?;
+    let parsed: FederationUnpairParams = parse_required_params("federation.unpair", params)?;
+    let removed = federation::remove_peer(state, &parsed.id)
+        .await
+        .map_err(|error| ErrorShape::new(ERROR_UNAVAILABLE, error))?;
+    Ok(json!({ "id": parsed.id.trim(), "removed": removed }))
+}
+
+pub(crate) fn configured(state: &SharedState) -> Result<Federation, ErrorShape> {
+    state.config().federation.clone().ok_or_else(|| {
+        ErrorShape::new(
+            ERROR_UNAVAILABLE,
+            "federation is not configured; set federation.gatewayId and federation.publicUrl",
+        )
+    })
+}
@@ -0,0 +1,165 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    application::state::SharedState,
+    domain::models::IdentityLinkInput,
+    rpc::{
+        dispatcher::map_domain_error,
+        methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
+    },
+    storage::now_unix_ms,
+};
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct IdentitiesLinkParams {
+        #[serde(default)]
+        person_id: Option<String>,
+        #[serde(default)]
+        display_name: Option<String>,
+        #[serde(default)]
+        shared_session: Option<bool>,
+        channel: String,
+        external_id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct IdentitiesUnlinkParams {
+        channel: String,
+        external_id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct IdentitiesListParams {
+        #[serde(default)]
+        person_id: Option<String>,
+        #[serde(default)]
+        channel: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+}
+
+pub async fn handle_link(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: IdentitiesLinkParams = parse_required_params("identities.link", params)?;
+    let (channel, external_id) =
+        normalize_identity("identities.link", parsed.channel, parsed.external_id)?;
+
+    let person = state
+        .link_person_identity(&IdentityLinkInput {
+            person_id: parsed.person_id.and_then(trim_non_empty),
+            display_name: parsed.display_name.and_then(trim_non_empty),
+            shared_session: parsed.shared_session,
+            channel,
+            external_id,
+            linked_at_ms: now_unix_ms(),
+        })
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({
+        "ok": true,
+        "person": person,
+    }))
+}
+
+pub async fn handle_unlink(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: IdentitiesUnlinkParams = parse_required_params("identities.unlink", params)?;
+    let (channel, external_id) =
+        normalize_identity("identities.unlink", parsed.channel, parsed.external_id)?;
+
+    let person_id = state
+        .unlink_person_identity(&channel, &external_id)
+        .await
+        .map_err(map_domain_error)?;
+    let person = match &person_id {
+        Some(person_id) => state
+            .get_person(person_id)
+            .await
+            .map_err(map_domain_error)?,
+        None => None,
+    };
+
+    Ok(json!({
+        "ok": true,
+        "unlinked": person_id.is_some(),
+        "personId": person_id,
+        "person": person,
+    }))
+}
+
+pub async fn handle_list(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: IdentitiesListParams = parse_optional_params("identities.list", params)?;
+    let channel = parsed
+        .channel
+        .and_then(trim_non_empty)
+        .map(|value| value.to_ascii_lowercase());
+    let limit = parsed.limit.unwrap_or(100).clamp(1, 1_000);
+
+    let mut persons = match parsed.person_id.and_then(trim_non_empty) {
+        Some(person_id) => state
+            .get_person(&person_id)
+            .await
+            .map_err(map_domain_error)?
+            .into_iter()
+            .collect(),
+        None => state.list_persons(limit).await.map_err(map_domain_error)?,
+    };
+    if let Some(channel) = &channel {
+        persons.retain(|person| {
+            person
+                .identities
+                .iter()
+                .any(|identity| identity.channel == *channel)
+        });
+    }
+
+    Ok(json!({
+        "ts": now_unix_ms(),
+        "channel": channel,
+        "persons": persons,
+    }))
+}
+
+fn normalize_identity(
+    method: &str,
+    channel: String,
+    external_id: String,
+) -> Result<(String, String), crate::protocol::ErrorShape> {
+    let channel = trim_non_empty(channel).map(|value| value.to_ascii_lowercase());
+    let external_id = trim_non_empty(external_id);
+    match (channel, external_id) {
+        (Some(channel), Some(external_id)) => Ok((channel, external_id)),
+        _ => Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid {method} params: channel and externalId are required"),
+        )),
+    }
+}
+
+fn trim_non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
@@ -3,77 +3,97 @@ use serde_json::{Value, json};
 
 use crate::{
     application::state::SharedState,
-    rpc::{dispatcher::map_domain_error, methods::parse_optional_params},
+    domain::models::GatewayLogQuery,
+    protocol::{ERROR_INVALID_REQUEST, ErrorShape},
+    rpc::{
+        dispatcher::map_domain_error,
+        methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
+    },
 };
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct LogsTailParams {
-    #[serde(default)]
-    limit: Option<usize>,
-    #[serde(default)]
-    level: Option<String>,
-    #[serde(default)]
-    method: Option<String>,
+const MAX_REDACTION_SAMPLES: usize = 100;
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct LogsTailParams {
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        level: Option<String>,
+        #[serde(default)]
+        method: Option<String>,
+        #[serde(default)]
+        conn_id: Option<String>,
+    }
 }
 
-pub async fn handle_tail(
-    state: &SharedState,
-    params: Option<&Value>,
-) -> Result<Value, crate::protocol::ErrorShape> {
+pub async fn handle_tail(state: &SharedState, params: Option<&Value>) -> Result<Value, ErrorShape> {
     let parsed: LogsTailParams = parse_optional_params("logs.tail", params)?;
 
-    let limit = parsed.limit.unwrap_or(200).clamp(1, 2_000);
-    let level_filter = parsed.level.and_then(normalize_string);
-    let method_filter = parsed.method.and_then(normalize_string);
+    let query = GatewayLogQuery {
+        level: parsed.level.and_then(normalize_string),
+        method: parsed.method.and_then(normalize_string),
+        conn_id: parsed.conn_id.and_then(normalize_string),
+        limit: parsed.limit.unwrap_or(200).clamp(1, 2_000),
+    };
 
     let entries = state
-        .list_config_entries("logs/", Some(limit.saturating_mul(4)))
+        .list_gateway_logs(&query)
         .await
         .map_err(map_domain_error)?;
 
-    let mut out = Vec::new();
-    for entry in entries {
-        let value = entry.value;
-        if !matches_level(&value, level_filter.as_deref()) {
-            continue;
-        }
-        if !matches_method(&value, method_filter.as_deref()) {
-            continue;
-        }
-
-        out.push(value);
-        if out.len() >= limit {
-            break;
-        }
-    }
-
     Ok(json!({
-        "entries": out,
-        "count": out.len(),
+        "entries": entries,
+        "count": entries.len(),
     }))
 }
 
-fn matches_level(value: &Value, expected: Option<&str>) -> bool {
-    let Some(expected) = expected else {
-        return true;
-    };
-
-    value
-        .get("level")
-        .and_then(Value::as_str)
-        .is_some_and(|level| level.eq_ignore_ascii_case(expected))
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct RedactionTestParams {
+        samples: Vec<String>,
+        #[serde(default)]
+        patterns: Vec<String>,
+    }
 }
 
-fn matches_method(value: &Value, expected: Option<&str>) -> bool {
-    let Some(expected) = expected else {
-        return true;
-    };
+/// Runs sample strings through the configured redaction rules plus any candidate `patterns`.
+pub async fn handle_redaction_test(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, ErrorShape> {
+    let parsed: RedactionTestParams = parse_required_params("logs.redaction.test", params)?;
+    if parsed.samples.is_empty() || parsed.samples.len() > MAX_REDACTION_SAMPLES {
+        return Err(ErrorShape::new(
+            ERROR_INVALID_REQUEST,
+            format!("samples must contain 1-{MAX_REDACTION_SAMPLES} strings"),
+        ));
+    }
+    let redaction = state
+        .config()
+        .log_redaction
+        .with_patterns(&parsed.patterns)
+        .map_err(|error| ErrorShape::new(ERROR_INVALID_REQUEST, error))?;
 
-    value
-        .get("method")
-        .and_then(Value::as_str)
-        .is_some_and(|method| method == expected)
+    let results = parsed
+        .samples
+        .iter()
+        .map(|sample| {
+            let redacted = redaction.apply(sample);
+            json!({
+                "input": sample,
+                "output": redacted.text,
+                "matches": redacted.rules,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(json!({
+        "rules": redaction.rule_names(),
+        "results": results,
+    }))
 }
 
 fn normalize_string(input: String) -> Option<String> {
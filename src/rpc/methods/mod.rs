@@ -1,22 +1,35 @@
 pub mod agent;
 pub mod agents;
+pub mod apikeys;
+pub mod approval_links;
 pub mod approvals;
 pub mod browser;
 pub mod channels;
 pub mod chat;
 pub mod config;
 pub mod cron;
+pub mod db;
+pub mod describe;
 pub mod device;
 pub mod doctor;
+pub mod events;
+pub mod exec;
+pub mod federation;
 pub mod health;
+pub mod identities;
 pub mod logs;
 pub mod models;
 pub mod nodes;
+pub mod privacy;
+pub mod replication;
+pub mod secrets;
 pub mod send;
+pub mod session_kv;
 pub mod sessions;
 pub mod skills;
 pub mod status;
 pub mod system;
+pub mod takeover;
 pub mod talk;
 pub mod tools;
 pub mod tts;
@@ -25,17 +38,37 @@ pub mod usage;
 pub mod voicewake;
 pub mod wizard;
 
+use std::collections::HashSet;
+
 use serde::de::DeserializeOwned;
 use serde_json::{Map, Value};
 
-use crate::protocol::{ERROR_INVALID_REQUEST, ErrorShape};
+use crate::{
+    domain::session_key::canonicalize_session_key,
+    protocol::{ERROR_INVALID_REQUEST, ErrorShape},
+};
 
 pub const BASE_METHODS: &[&str] = &[
     "health",
+    "methods.describe",
+    "methods.schema",
     "doctor.memory.status",
+    "doctor.storage.slowQueries",
+    "events.ack",
+    "events.subscribe",
+    "events.unsubscribe",
     "logs.tail",
+    "logs.redaction.test",
     "channels.status",
     "channels.logout",
+    "channels.directory.list",
+    "channels.outbound.queue",
+    "identities.link",
+    "identities.unlink",
+    "identities.list",
+    "privacy.export",
+    "privacy.delete",
+    "privacy.audit.list",
     "status",
     "usage.status",
     "usage.cost",
@@ -50,6 +83,8 @@ pub const BASE_METHODS: &[&str] = &[
     "config.apply",
     "config.patch",
     "config.schema",
+    "config.entries.bulkSet",
+    "config.entries.bulkDelete",
     "exec.approvals.get",
     "exec.approvals.set",
     "exec.approvals.node.get",
@@ -57,6 +92,17 @@ pub const BASE_METHODS: &[&str] = &[
     "exec.approval.request",
     "exec.approval.waitDecision",
     "exec.approval.resolve",
+    "approval.link.create",
+    "approval.link.get",
+    "approval.link.resolve",
+    "federation.invite",
+    "federation.pair",
+    "federation.peers.list",
+    "federation.unpair",
+    "replication.status",
+    "replication.promote",
+    "secrets.status",
+    "exec.run",
     "wizard.start",
     "wizard.next",
     "wizard.cancel",
@@ -65,6 +111,12 @@ pub const BASE_METHODS: &[&str] = &[
     "talk.mode",
     "models.list",
     "tools.catalog",
+    "tools.register",
+    "tools.unregister",
+    "tools.grant",
+    "tools.revoke",
+    "tools.call",
+    "tools.calls.list",
     "agents.list",
     "agents.create",
     "agents.update",
@@ -77,14 +129,21 @@ pub const BASE_METHODS: &[&str] = &[
     "skills.install",
     "skills.update",
     "update.run",
+    "db.migrateTo",
+    "snapshot.publish",
     "voicewake.get",
     "voicewake.set",
     "sessions.list",
+    "sessions.tags.list",
     "sessions.preview",
     "sessions.patch",
+    "sessions.bulkPatch",
     "sessions.reset",
     "sessions.delete",
     "sessions.compact",
+    "session.kv.get",
+    "session.kv.set",
+    "session.kv.delete",
     "last-heartbeat",
     "set-heartbeats",
     "wake",
@@ -97,57 +156,147 @@ pub const BASE_METHODS: &[&str] = &[
     "device.pair.approve",
     "device.pair.reject",
     "device.pair.remove",
+    "device.pair.bulkApprove",
     "device.token.rotate",
     "device.token.revoke",
+    "device.token.bulkRevoke",
+    "apikeys.list",
+    "apikeys.create",
+    "apikeys.rotate",
+    "apikeys.revoke",
     "node.rename",
     "node.list",
     "node.describe",
     "node.invoke",
+    "node.invoke.pending",
+    "node.invoke.cancel",
     "node.invoke.result",
     "node.event",
+    "node.metadata.update",
+    "node.metadata.history",
+    "node.latency.report",
+    "node.affinity.list",
+    "node.file.push",
+    "node.file.pull",
+    "node.file.status",
+    "node.file.list",
+    "node.file.cancel",
+    "node.geofence.set",
+    "node.geofence.list",
+    "node.geofence.remove",
     "cron.list",
     "cron.status",
+    "cron.describe",
     "cron.add",
     "cron.update",
     "cron.remove",
     "cron.run",
     "cron.runs",
+    "cron.runs.tail",
+    "cron.templates.list",
+    "cron.templates.set",
+    "cron.templates.remove",
     "system-presence",
     "system-event",
+    "system.shutdown",
+    "system.restart",
+    "system.maintenance",
     "send",
     "agent",
     "agent.identity.get",
     "agent.wait",
+    "agent.retry",
+    "agent.replay",
     "browser.request",
     "chat.history",
     "chat.abort",
     "chat.send",
+    "chat.search",
+    "chat.deliveryStatus",
+    "chat.pin",
+    "chat.markRead",
+    "chat.unpin",
+    "chat.takeover.start",
+    "chat.takeover.end",
+    "chat.takeover.reply",
 ];
 
 pub const GATEWAY_EVENTS: &[&str] = &[
     "connect.challenge",
     "agent",
     "chat",
+    "chat.delivery",
+    "chat.takeover",
     "presence",
     "tick",
     "talk.mode",
     "shutdown",
+    "maintenance",
     "health",
     "heartbeat",
     "cron",
     "node.pair.requested",
     "node.pair.resolved",
     "node.invoke.request",
+    "node.geofence",
     "device.pair.requested",
     "device.pair.resolved",
     "voicewake.changed",
     "exec.approval.requested",
     "exec.approval.resolved",
+    "exec",
     "update.available",
+    "db.migrate.progress",
+    "overload",
+    "content.policy",
+    "attachment.scan",
+    "replication.promoted",
+    "usage.budget",
+];
+
+/// A legacy method name kept working after a rename. Calls are served by `target` and the
+/// response carries a `deprecation` warning.
+#[derive(Debug, Clone, Copy)]
+pub struct MethodAlias {
+    pub alias: &'static str,
+    pub target: &'static str,
+}
+
+pub const METHOD_ALIASES: &[MethodAlias] = &[
+    MethodAlias {
+        alias: "chat.delivery.status",
+        target: "chat.deliveryStatus",
+    },
+    MethodAlias {
+        alias: "exec.approval.wait",
+        target: "exec.approval.waitDecision",
+    },
+    MethodAlias {
+        alias: "channels.directory",
+        target: "channels.directory.list",
+    },
+    MethodAlias {
+        alias: "privacy.audit",
+        target: "privacy.audit.list",
+    },
 ];
 
 const IMPLEMENTED_METHODS: &[&str] = BASE_METHODS;
 
+#[must_use]
+pub fn resolve_alias(method: &str) -> Option<&'static MethodAlias> {
+    METHOD_ALIASES.iter().find(|alias| alias.alias == method)
+}
+
+#[must_use]
+pub fn aliases_of(method: &str) -> Vec<&'static str> {
+    METHOD_ALIASES
+        .iter()
+        .filter(|alias| alias.target == method)
+        .map(|alias| alias.alias)
+        .collect()
+}
+
 #[must_use]
 pub fn known_methods() -> Vec<String> {
     BASE_METHODS
@@ -221,9 +370,113 @@ pub(crate) fn parse_required_params<T: DeserializeOwned>(
     })
 }
 
+/// Params that name a session in every method.
+const SESSION_KEY_PARAMS: &[&str] = &["sessionKey", "sessionId"];
+
+/// Methods whose `id`/`key` params are session keys, or lists of them.
+const SESSION_ID_PARAMS: &[(&str, &[&str])] = &[
+    ("sessions.patch", &["id", "key"]),
+    ("sessions.delete", &["id", "key"]),
+    ("sessions.preview", &["keys"]),
+];
+
+/// Validates every session key param of `method` and rewrites it to its canonical form.
+/// Returns the rewritten params only when a key changed; blank keys are left to the method's
+/// own "required" check.
+pub(crate) fn canonicalize_session_params(
+    method: &str,
+    params: Option<&Value>,
+) -> Result<Option<Value>, ErrorShape> {
+    let Some(Value::Object(object)) = params else {
+        return Ok(None);
+    };
+    let extra = SESSION_ID_PARAMS
+        .iter()
+        .find(|(name, _)| *name == method)
+        .map_or(&[][..], |(_, fields)| *fields);
+
+    let mut rewritten = object.clone();
+    let mut changed = false;
+    for field in SESSION_KEY_PARAMS.iter().chain(extra) {
+        let values = match rewritten.get_mut(*field) {
+            Some(Value::Array(items)) => items.iter_mut().collect::<Vec<_>>(),
+            Some(value) => vec![value],
+            None => continue,
+        };
+        for value in values {
+            let Some(raw) = value.as_str().filter(|raw| !raw.trim().is_empty()) else {
+                continue;
+            };
+            let canonical = canonicalize_session_key(raw).map_err(|error| {
+                ErrorShape::new(
+                    ERROR_INVALID_REQUEST,
+                    format!("invalid {method} params: {field} {error}"),
+                )
+            })?;
+            if canonical != raw {
+                *value = Value::String(canonical);
+                changed = true;
+            }
+        }
+    }
+    Ok(changed.then_some(Value::Object(rewritten)))
+}
+
+/// Sparse field selection for list RPCs: `fields: ["id", "title"]` keeps only those top-level
+/// keys on each item. Omitting `fields` keeps every field.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FieldSelection {
+    fields: Option<HashSet<String>>,
+}
+
+impl FieldSelection {
+    pub(crate) fn parse(method: &str, fields: Option<Vec<String>>) -> Result<Self, ErrorShape> {
+        let Some(fields) = fields else {
+            return Ok(Self::default());
+        };
+        let fields = fields
+            .into_iter()
+            .map(|field| field.trim().to_owned())
+            .filter(|field| !field.is_empty())
+            .collect::<HashSet<_>>();
+        if fields.is_empty() {
+            return Err(ErrorShape::new(
+                ERROR_INVALID_REQUEST,
+                format!("invalid {method} params: fields must name at least one field"),
+            ));
+        }
+        Ok(Self {
+            fields: Some(fields),
+        })
+    }
+
+    /// Whether `field` is serialized; lets handlers skip computing unselected fields.
+    pub(crate) fn includes(&self, field: &str) -> bool {
+        self.fields
+            .as_ref()
+            .is_none_or(|fields| fields.contains(field))
+    }
+
+    pub(crate) fn apply(&self, mut item: Value) -> Value {
+        if let (Some(fields), Some(object)) = (&self.fields, item.as_object_mut()) {
+            object.retain(|key, _| fields.contains(key));
+        }
+        item
+    }
+
+    pub(crate) fn apply_all<T: serde::Serialize>(&self, items: &[T]) -> Vec<Value> {
+        items
+            .iter()
+            .map(|item| self.apply(serde_json::to_value(item).unwrap_or(Value::Null)))
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{implemented_methods, is_implemented_method, is_known_method};
+    use serde_json::json;
+
+    use super::{FieldSelection, implemented_methods, is_implemented_method, is_known_method};
 
     #[test]
     fn methods_have_known_coverage() {
@@ -233,4 +486,22 @@ mod tests {
         assert!(is_implemented_method("wizard.start"));
         assert_eq!(implemented_methods().len(), super::BASE_METHODS.len());
     }
+
+    #[test]
+    fn field_selection_keeps_only_requested_keys() {
+        let all = FieldSelection::parse("sessions.list", None).expect("no fields is valid");
+        assert!(all.includes("anything"));
+        assert_eq!(
+            all.apply(json!({ "a": 1, "b": 2 })),
+            json!({ "a": 1, "b": 2 })
+        );
+
+        let sparse = FieldSelection::parse("sessions.list", Some(vec![" a ".to_owned()]))
+            .expect("fields should parse");
+        assert!(sparse.includes("a"));
+        assert!(!sparse.includes("b"));
+        assert_eq!(sparse.apply(json!({ "a": 1, "b": 2 })), json!({ "a": 1 }));
+
+        assert!(FieldSelection::parse("sessions.list", Some(vec![" ".to_owned()])).is_err());
+    }
 }
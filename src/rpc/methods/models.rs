@@ -3,19 +3,21 @@ use serde_json::{Value, json};
 
 use crate::{
     application::state::SharedState,
-    rpc::{dispatcher::map_domain_error, methods::parse_optional_params},
+    rpc::{dispatcher::map_domain_error, methods::parse_optional_params, schema::rpc_params},
     storage::now_unix_ms,
 };
 
 const MODELS_CATALOG_KEY: &str = "runtime/models/catalog";
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ModelsListParams {
-    #[serde(default)]
-    provider: Option<String>,
-    #[serde(default)]
-    limit: Option<usize>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ModelsListParams {
+        #[serde(default)]
+        provider: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
 }
 
 pub async fn handle_list(
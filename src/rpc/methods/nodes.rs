@@ -1,98 +1,304 @@
-use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
 use crate::{
-    application::state::SharedState,
+    application::{
+        geofence::{self, GeoPoint, Geofence},
+        node_affinity,
+        node_files::{self, TransferRequest},
+        notifier,
+        state::SharedState,
+    },
     domain::models::{NodeInvokeInput, NodePairRequestInput},
     rpc::{
         SessionContext,
         dispatcher::map_domain_error,
-        methods::{parse_optional_params, parse_required_params},
+        methods::{
+            FieldSelection, approval_links, federation, parse_optional_params,
+            parse_required_params,
+        },
+        schema::rpc_params,
     },
+    security::approval_links::ApprovalLinkKind,
     storage::now_unix_ms,
 };
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct NodePairRequestParams {
-    node_id: String,
-    #[serde(default)]
-    display_name: Option<String>,
-    #[serde(default)]
-    platform: Option<String>,
-    #[serde(default)]
-    device_family: Option<String>,
-    #[serde(default)]
-    commands: Option<Vec<String>>,
-    #[serde(default)]
-    public_key: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct NodePairResolveParams {
-    request_id: String,
-    #[serde(default)]
-    reason: Option<String>,
+const DEFAULT_QUEUED_INVOKE_TTL_MS: u64 = 10 * 60 * 1_000;
+const MAX_QUEUED_INVOKE_TTL_MS: u64 = 7 * 24 * 60 * 60 * 1_000;
+/// Kept below the per-connection event buffer so a reconnect flush cannot overflow it.
+const MAX_QUEUED_INVOKES_PER_NODE: usize = 100;
+const DEFAULT_METADATA_HISTORY_LIMIT: usize = 50;
+const MAX_METADATA_HISTORY_LIMIT: usize = 500;
+const DEFAULT_FILE_TRANSFER_LIST_LIMIT: usize = 50;
+const MAX_FILE_TRANSFER_LIST_LIMIT: usize = 500;
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodePairRequestParams {
+        node_id: String,
+        #[serde(default)]
+        display_name: Option<String>,
+        #[serde(default)]
+        platform: Option<String>,
+        #[serde(default)]
+        device_family: Option<String>,
+        #[serde(default)]
+        commands: Option<Vec<String>>,
+        #[serde(default)]
+        public_key: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct NodeVerifyParams {
-    node_id: String,
-    #[serde(default)]
-    token: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodePairResolveParams {
+        request_id: String,
+        #[serde(default)]
+        reason: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct NodeRenameParams {
-    #[serde(default)]
-    node_id: Option<String>,
-    #[serde(default)]
-    id: Option<String>,
-    display_name: String,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeVerifyParams {
+        node_id: String,
+        #[serde(default)]
+        token: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct NodeIdParams {
-    #[serde(default)]
-    node_id: Option<String>,
-    #[serde(default)]
-    id: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeRenameParams {
+        #[serde(default)]
+        node_id: Option<String>,
+        #[serde(default)]
+        id: Option<String>,
+        display_name: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeListParams {
+        #[serde(default)]
+        fields: Option<Vec<String>>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeIdParams {
+        #[serde(default)]
+        node_id: Option<String>,
+        #[serde(default)]
+        id: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeInvokeParams {
+        node_id: String,
+        command: String,
+        #[serde(default)]
+        args: Option<Vec<String>>,
+        #[serde(default)]
+        input: Option<Value>,
+        #[serde(default)]
+        queue_if_offline: bool,
+        #[serde(default)]
+        ttl_ms: Option<u64>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeInvokePendingParams {
+        #[serde(default)]
+        node_id: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeInvokeCancelParams {
+        request_id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeInvokeResultParams {
+        request_id: String,
+        status: String,
+        #[serde(default)]
+        payload: Option<Value>,
+        #[serde(default)]
+        error: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeEventParams {
+        #[serde(default)]
+        node_id: Option<String>,
+        event: String,
+        #[serde(default)]
+        payload: Option<Value>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeMetadataUpdateParams {
+        #[serde(default)]
+        location: Option<NodeLocation>,
+        #[serde(default)]
+        battery: Option<NodeBattery>,
+        #[serde(default)]
+        network: Option<NodeNetwork>,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct NodeInvokeParams {
-    node_id: String,
-    command: String,
-    #[serde(default)]
-    args: Option<Vec<String>>,
-    #[serde(default)]
-    input: Option<Value>,
+struct NodeLocation {
+    lat: f64,
+    lon: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    accuracy_m: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    altitude_m: Option<f64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct NodeInvokeResultParams {
-    request_id: String,
-    status: String,
-    #[serde(default)]
-    payload: Option<Value>,
-    #[serde(default)]
-    error: Option<String>,
+struct NodeBattery {
+    level: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    charging: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct NodeEventParams {
-    #[serde(default)]
-    node_id: Option<String>,
-    event: String,
-    #[serde(default)]
-    payload: Option<Value>,
+struct NodeNetwork {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ssid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    carrier: Option<String>,
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeMetadataHistoryParams {
+        #[serde(default)]
+        node_id: Option<String>,
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeGeofenceListParams {
+        #[serde(default)]
+        node_id: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeGeofenceRemoveParams {
+        id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeFilePushParams {
+        node_id: String,
+        source: String,
+        path: String,
+        #[serde(default)]
+        ttl_ms: Option<u64>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeFilePullParams {
+        node_id: String,
+        path: String,
+        #[serde(default)]
+        dest: Option<String>,
+        #[serde(default)]
+        sha256: Option<String>,
+        #[serde(default)]
+        max_bytes: Option<u64>,
+        #[serde(default)]
+        ttl_ms: Option<u64>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeFileTransferParams {
+        transfer_id: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeFileListParams {
+        #[serde(default)]
+        node_id: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeLatencyReportParams {
+        rtt_ms: BTreeMap<String, u64>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct NodeAffinityListParams {
+        #[serde(default)]
+        node_id: Option<String>,
+    }
 }
 
 pub async fn handle_pair_request(
@@ -130,6 +336,22 @@ pub async fn handle_pair_request(
         .await
         .map_err(map_domain_error)?;
 
+    let link = approval_links::event_link(
+        state,
+        ApprovalLinkKind::NodePair,
+        &request.request_id,
+        request.expires_at_ms,
+    )
+    .await;
+    let payload = json!({
+        "request": request,
+        "link": link,
+    });
+    notifier::escalate(state, "node.pair.requested", &payload).await;
+    state
+        .publish_gateway_event("node.pair.requested", payload)
+        .await;
+
     Ok(json!({
         "status": "pending",
         "created": true,
@@ -229,12 +451,13 @@ pub async fn handle_list(
     state: &SharedState,
     params: Option<&Value>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
-    let _: serde_json::Map<String, Value> = parse_optional_params("node.list", params)?;
+    let parsed: NodeListParams = parse_optional_params("node.list", params)?;
+    let fields = FieldSelection::parse("node.list", parsed.fields)?;
     let nodes = state.list_nodes().await.map_err(map_domain_error)?;
 
     Ok(json!({
         "ts": now_unix_ms(),
-        "nodes": nodes,
+        "nodes": fields.apply_all(&nodes),
     }))
 }
 
@@ -255,6 +478,15 @@ pub async fn handle_describe(
                 "unknown nodeId",
             )
         })?;
+    // Live connections win; offline nodes report what they last negotiated.
+    let features = match state.node_features(&node.id).await {
+        Some(features) => json!(features),
+        None => node
+            .metadata
+            .get("features")
+            .cloned()
+            .unwrap_or(Value::Null),
+    };
 
     Ok(json!({
         "ts": now_unix_ms(),
@@ -266,6 +498,7 @@ pub async fn handle_describe(
         "paired": node.paired,
         "status": node.status,
         "lastSeenMs": node.last_seen_ms,
+        "features": features,
         "metadata": node.metadata,
     }))
 }
@@ -290,13 +523,49 @@ pub async fn handle_invoke(
         )
     })?;
 
+    let input = NodeInvokeInput {
+        node_id: node_id.clone(),
+        command: command.clone(),
+        args: sanitize_items(parsed.args.unwrap_or_default()),
+        input: parsed.input,
+    };
+
+    if parsed.queue_if_offline && !node_is_online(state, &node_id).await? {
+        let pending = state
+            .list_queued_node_invokes(Some(&node_id))
+            .await
+            .map_err(map_domain_error)?;
+        if pending.len() >= MAX_QUEUED_INVOKES_PER_NODE {
+            return Err(crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_UNAVAILABLE,
+                format!(
+                    "offline queue for node {node_id} is full ({MAX_QUEUED_INVOKES_PER_NODE} pending invokes)"
+                ),
+            ));
+        }
+        let ttl_ms = parsed
+            .ttl_ms
+            .unwrap_or(DEFAULT_QUEUED_INVOKE_TTL_MS)
+            .clamp(1_000, MAX_QUEUED_INVOKE_TTL_MS);
+        let queued = state
+            .queue_node_invoke(input, now_unix_ms().saturating_add(ttl_ms))
+            .await
+            .map_err(map_domain_error)?;
+
+        return Ok(json!({
+            "ok": true,
+            "nodeId": node_id,
+            "command": command,
+            "requestId": queued.invoke.request_id,
+            "status": queued.invoke.status,
+            "queued": true,
+            "expiresAtMs": queued.expires_at_ms,
+            "payload": Value::Null,
+        }));
+    }
+
     let invoke = state
-        .create_node_invoke(NodeInvokeInput {
-            node_id: node_id.clone(),
-            command: command.clone(),
-            args: sanitize_items(parsed.args.unwrap_or_default()),
-            input: parsed.input,
-        })
+        .create_node_invoke(input)
         .await
         .map_err(map_domain_error)?;
 
@@ -310,6 +579,72 @@ pub async fn handle_invoke(
     }))
 }
 
+pub async fn handle_invoke_pending(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeInvokePendingParams = parse_optional_params("node.invoke.pending", params)?;
+    let node_id = parsed.node_id.and_then(trim_non_empty);
+
+    let invokes = state
+        .list_queued_node_invokes(node_id.as_deref())
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({
+        "ts": now_unix_ms(),
+        "nodeId": node_id,
+        "count": invokes.len(),
+        "invokes": invokes,
+    }))
+}
+
+pub async fn handle_invoke_cancel(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeInvokeCancelParams = parse_required_params("node.invoke.cancel", params)?;
+
+    let request_id = trim_non_empty(parsed.request_id).ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid node.invoke.cancel params: requestId is required",
+        )
+    })?;
+
+    if let Some(invoke) = state
+        .cancel_queued_node_invoke(&request_id)
+        .await
+        .map_err(map_domain_error)?
+    {
+        return Ok(json!({ "cancelled": true, "invoke": invoke }));
+    }
+
+    let invoke = state
+        .get_node_invoke(&request_id)
+        .await
+        .map_err(map_domain_error)?
+        .ok_or_else(|| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                "unknown requestId",
+            )
+        })?;
+    Ok(json!({ "cancelled": false, "invoke": invoke }))
+}
+
+/// Nodes are online while at least one node-role connection for them is live.
+async fn node_is_online(
+    state: &SharedState,
+    node_id: &str,
+) -> Result<bool, crate::protocol::ErrorShape> {
+    Ok(state
+        .get_node(node_id)
+        .await
+        .map_err(map_domain_error)?
+        .is_some_and(|node| node.status == "online"))
+}
+
 pub async fn handle_invoke_result(
     state: &SharedState,
     params: Option<&Value>,
@@ -380,6 +715,346 @@ pub async fn handle_event(
     }))
 }
 
+pub async fn handle_metadata_update(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeMetadataUpdateParams = parse_required_params("node.metadata.update", params)?;
+    let invalid = |message: &str| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid node.metadata.update params: {message}"),
+        )
+    };
+    if parsed.location.is_none() && parsed.battery.is_none() && parsed.network.is_none() {
+        return Err(invalid(
+            "at least one of location, battery or network is required",
+        ));
+    }
+    let location = parsed
+        .location
+        .as_ref()
+        .map(|location| {
+            let point = GeoPoint {
+                lat: location.lat,
+                lon: location.lon,
+            };
+            point
+                .validate("location")
+                .map_err(|error| invalid(&error))?;
+            if location
+                .accuracy_m
+                .is_some_and(|accuracy| !accuracy.is_finite() || accuracy < 0.0)
+            {
+                return Err(invalid("location.accuracyM must be a non-negative number"));
+            }
+            Ok(point)
+        })
+        .transpose()?;
+    if let Some(battery) = &parsed.battery
+        && !(0.0..=100.0).contains(&battery.level)
+    {
+        return Err(invalid("battery.level must be between 0 and 100"));
+    }
+    if parsed
+        .network
+        .as_ref()
+        .is_some_and(|network| network.kind.trim().is_empty())
+    {
+        return Err(invalid("network.type is required"));
+    }
+
+    let node_id = state
+        .node_id_for_connection(&session.conn_id)
+        .await
+        .unwrap_or_else(|| session.client_id.clone());
+    let mut node = state
+        .get_node(&node_id)
+        .await
+        .map_err(map_domain_error)?
+        .ok_or_else(|| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                "unknown nodeId",
+            )
+        })?;
+
+    let now = now_unix_ms();
+    let mut update = serde_json::Map::new();
+    for (key, value) in [
+        ("location", parsed.location.map(|value| json!(value))),
+        ("battery", parsed.battery.map(|value| json!(value))),
+        ("network", parsed.network.map(|value| json!(value))),
+    ] {
+        if let Some(value) = value {
+            update.insert(key.to_owned(), value);
+        }
+    }
+    update.insert("reportedAtMs".to_owned(), json!(now));
+    if !node.metadata.is_object() {
+        node.metadata = json!({});
+    }
+    if let Some(metadata) = node.metadata.as_object_mut() {
+        metadata.extend(update.clone());
+    }
+    node.last_seen_ms = now;
+    state.upsert_node(&node).await.map_err(map_domain_error)?;
+    let entry = state
+        .add_node_metadata_entry(&node_id, Value::Object(update))
+        .await
+        .map_err(map_domain_error)?;
+
+    let geofences = match location {
+        Some(point) => geofence::evaluate(state, &node_id, point)
+            .await
+            .map_err(|error| {
+                crate::protocol::ErrorShape::new(crate::protocol::ERROR_UNAVAILABLE, error)
+            })?,
+        None => Vec::new(),
+    };
+
+    Ok(json!({
+        "ok": true,
+        "nodeId": node_id,
+        "entry": entry,
+        "geofences": geofences,
+    }))
+}
+
+pub async fn handle_metadata_history(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeMetadataHistoryParams = parse_required_params("node.metadata.history", params)?;
+    let node_id = resolve_node_id(parsed.node_id, parsed.id, "node.metadata.history")?;
+    let limit = parsed
+        .limit
+        .unwrap_or(DEFAULT_METADATA_HISTORY_LIMIT)
+        .clamp(1, MAX_METADATA_HISTORY_LIMIT);
+    let entries = state
+        .list_node_metadata_history(&node_id, limit)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({
+        "ts": now_unix_ms(),
+        "nodeId": node_id,
+        "entries": entries,
+    }))
+}
+
+pub async fn handle_file_push(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeFilePushParams = parse_required_params("node.file.push", params)?;
+    let node_id = required_field(parsed.node_id, "node.file.push", "nodeId")?;
+    let source = required_field(parsed.source, "node.file.push", "source")?;
+    let path = required_field(parsed.path, "node.file.push", "path")?;
+
+    let transfer = node_files::start_transfer(
+        state,
+        &node_id,
+        &path,
+        TransferRequest::Push { source },
+        parsed.ttl_ms,
+    )
+    .await
+    .map_err(map_domain_error)?;
+    Ok(json!(transfer))
+}
+
+pub async fn handle_file_pull(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeFilePullParams = parse_required_params("node.file.pull", params)?;
+    let node_id = required_field(parsed.node_id, "node.file.pull", "nodeId")?;
+    let path = required_field(parsed.path, "node.file.pull", "path")?;
+
+    let transfer = node_files::start_transfer(
+        state,
+        &node_id,
+        &path,
+        TransferRequest::Pull {
+            dest: parsed.dest.and_then(trim_non_empty),
+            sha256: parsed.sha256.and_then(trim_non_empty),
+            max_bytes: parsed.max_bytes,
+        },
+        parsed.ttl_ms,
+    )
+    .await
+    .map_err(map_domain_error)?;
+    Ok(json!(transfer))
+}
+
+pub async fn handle_file_status(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeFileTransferParams = parse_required_params("node.file.status", params)?;
+    let transfer_id = required_field(parsed.transfer_id, "node.file.status", "transferId")?;
+
+    let transfer = node_files::get_transfer(state, &transfer_id)
+        .await
+        .map_err(map_domain_error)?
+        .ok_or_else(|| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                "unknown transferId",
+            )
+        })?;
+    Ok(json!(transfer))
+}
+
+pub async fn handle_file_list(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeFileListParams = parse_optional_params("node.file.list", params)?;
+    let node_id = parsed.node_id.and_then(trim_non_empty);
+    let limit = parsed
+        .limit
+        .unwrap_or(DEFAULT_FILE_TRANSFER_LIST_LIMIT)
+        .clamp(1, MAX_FILE_TRANSFER_LIST_LIMIT);
+
+    let transfers = node_files::list_transfers(state, node_id.as_deref(), limit)
+        .await
+        .map_err(map_domain_error)?;
+    Ok(json!({
+        "ts": now_unix_ms(),
+        "nodeId": node_id,
+        "count": transfers.len(),
+        "transfers": transfers,
+    }))
+}
+
+pub async fn handle_file_cancel(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeFileTransferParams = parse_required_params("node.file.cancel", params)?;
+    let transfer_id = required_field(parsed.transfer_id, "node.file.cancel", "transferId")?;
+
+    let (cancelled, transfer) = node_files::cancel_transfer(state, &transfer_id)
+        .await
+        .map_err(map_domain_error)?;
+    Ok(json!({ "cancelled": cancelled, "transfer": transfer }))
+}
+
+pub async fn handle_geofence_set(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: Geofence = parse_required_params("node.geofence.set", params)?;
+    let fence = geofence::set_fence(state, parsed).await.map_err(|error| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid node.geofence.set params: {error}"),
+        )
+    })?;
+
+    Ok(json!({ "geofence": fence }))
+}
+
+pub async fn handle_geofence_list(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeGeofenceListParams = parse_optional_params("node.geofence.list", params)?;
+    let node_id = parsed.node_id.and_then(trim_non_empty);
+    let fences = geofence::list_fences(state)
+        .await
+        .map_err(|error| {
+            crate::protocol::ErrorShape::new(crate::protocol::ERROR_UNAVAILABLE, error)
+        })?
+        .into_iter()
+        .filter(|fence| {
+            node_id
+                .as_deref()
+                .is_none_or(|node_id| fence.node_id.as_deref().is_none_or(|id| id == node_id))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "ts": now_unix_ms(),
+        "geofences": fences,
+    }))
+}
+
+pub async fn handle_geofence_remove(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeGeofenceRemoveParams = parse_required_params("node.geofence.remove", params)?;
+    let removed = geofence::remove_fence(state, &parsed.id)
+        .await
+        .map_err(|error| {
+            crate::protocol::ErrorShape::new(crate::protocol::ERROR_UNAVAILABLE, error)
+        })?;
+
+    Ok(json!({ "id": parsed.id.trim(), "removed": removed }))
+}
+
+pub async fn handle_latency_report(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeLatencyReportParams = parse_required_params("node.latency.report", params)?;
+    let federation = federation::configured(state)?;
+    let rtt_ms = parsed
+        .rtt_ms
+        .into_iter()
+        .filter_map(|(gateway, rtt)| trim_non_empty(gateway).map(|gateway| (gateway, rtt)))
+        .collect::<BTreeMap<_, _>>();
+    if rtt_ms.is_empty() {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid node.latency.report params: rttMs needs at least one gateway",
+        ));
+    }
+    let node_id = state
+        .node_id_for_connection(&session.conn_id)
+        .await
+        .unwrap_or_else(|| session.client_id.clone());
+    let (affinity, route) = node_affinity::report(state, &federation, &node_id, rtt_ms)
+        .await
+        .map_err(|error| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                format!("invalid node.latency.report params: {error}"),
+            )
+        })?;
+
+    Ok(json!({
+        "ok": true,
+        "nodeId": node_id,
+        "pinned": affinity.pinned,
+        "route": route,
+    }))
+}
+
+pub async fn handle_affinity_list(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: NodeAffinityListParams = parse_optional_params("node.affinity.list", params)?;
+    let federation = federation::configured(state)?;
+    let node_id = parsed.node_id.and_then(trim_non_empty);
+    let nodes = node_affinity::list(state, &federation, node_id.as_deref())
+        .await
+        .map_err(|error| {
+            crate::protocol::ErrorShape::new(crate::protocol::ERROR_UNAVAILABLE, error)
+        })?;
+
+    Ok(json!({
+        "gatewayId": federation.gateway_id(),
+        "nodes": nodes,
+    }))
+}
+
 async fn handle_pair_resolution(
     state: &SharedState,
     params: Option<&Value>,
@@ -420,6 +1095,19 @@ fn resolve_node_id(
     })
 }
 
+fn required_field(
+    value: String,
+    method: &str,
+    field: &str,
+) -> Result<String, crate::protocol::ErrorShape> {
+    trim_non_empty(value).ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid {method} params: {field} is required"),
+        )
+    })
+}
+
 fn sanitize_items(values: Vec<String>) -> Vec<String> {
     let mut out = Vec::new();
     for value in values {
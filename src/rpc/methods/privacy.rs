@@ -0,0 +1,331 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    application::state::SharedState,
+    domain::models::{PersonRecord, PrivacyAuditRecord, SessionPurgeCounts},
+    rpc::{
+        SessionContext,
+        dispatcher::map_domain_error,
+        methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
+    },
+    storage::now_unix_ms,
+};
+
+const MAX_EXPORT_RUNS_PER_SESSION: usize = 5_000;
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct PrivacySubjectParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        channel: Option<String>,
+        #[serde(default)]
+        external_id: Option<String>,
+        #[serde(default)]
+        confirm: Option<bool>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct PrivacyAuditListParams {
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+}
+
+/// Everything the runtime stores about one data subject.
+struct PrivacySubject {
+    descriptor: Value,
+    session_keys: Vec<String>,
+    directory: Option<(String, String)>,
+    identity: Option<(String, String)>,
+    person: Option<PersonRecord>,
+}
+
+pub async fn handle_export(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: PrivacySubjectParams = parse_required_params("privacy.export", params)?;
+    let subject = resolve_subject(state, "privacy.export", parsed).await?;
+
+    let mut sessions = Vec::with_capacity(subject.session_keys.len());
+    let mut attachments = Vec::new();
+    for session_key in &subject.session_keys {
+        let record = state
+            .get_session(session_key)
+            .await
+            .map_err(map_domain_error)?;
+        let messages = state
+            .list_chat_messages(session_key, None)
+            .await
+            .map_err(map_domain_error)?;
+        let runs = state
+            .list_agent_runs_by_session(session_key, Some(MAX_EXPORT_RUNS_PER_SESSION))
+            .await
+            .map_err(map_domain_error)?;
+
+        for message in &messages {
+            if let Some(items) = message
+                .metadata
+                .get("attachments")
+                .and_then(Value::as_array)
+            {
+                attachments.extend(items.iter().map(|attachment| {
+                    json!({
+                        "sessionKey": session_key,
+                        "messageId": message.id,
+                        "attachment": attachment,
+                    })
+                }));
+            }
+        }
+
+        sessions.push(json!({
+            "sessionKey": session_key,
+            "session": record,
+            "messages": messages,
+            "runs": runs,
+        }));
+    }
+
+    let directory_entry = match &subject.directory {
+        Some((channel, conversation)) => state
+            .get_channel_directory_entry(channel, conversation)
+            .await
+            .map_err(map_domain_error)?,
+        None => None,
+    };
+
+    let summary = json!({
+        "sessions": sessions.len(),
+        "messages": sessions
+            .iter()
+            .filter_map(|entry| entry["messages"].as_array().map(Vec::len))
+            .sum::<usize>(),
+        "attachments": attachments.len(),
+    });
+    let audit_id = record_audit(state, session, "export", &subject, summary.clone()).await?;
+
+    Ok(json!({
+        "ok": true,
+        "auditId": audit_id,
+        "summary": summary,
+        "archive": {
+            "format": "reclaw-privacy-export/v1",
+            "generatedAtMs": now_unix_ms(),
+            "subject": subject.descriptor,
+            "person": subject.person,
+            "sessions": sessions,
+            "attachments": attachments,
+            "directoryEntries": directory_entry.into_iter().collect::<Vec<_>>(),
+        },
+    }))
+}
+
+pub async fn handle_delete(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: PrivacySubjectParams = parse_required_params("privacy.delete", params)?;
+    if parsed.confirm != Some(true) {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid privacy.delete params: confirm=true is required for irreversible deletion",
+        ));
+    }
+    // Append-only keeps every deleted row as a tombstone and archive segments on disk, so the
+    // erasure this method promises cannot happen; refuse rather than audit a delete that wasn't.
+    if state.config().append_only {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "privacy.delete is unavailable while appendOnly is enabled: deleted rows would be kept \
+             as tombstones",
+        ));
+    }
+    let subject = resolve_subject(state, "privacy.delete", parsed).await?;
+
+    let mut purged = SessionPurgeCounts::default();
+    for session_key in &subject.session_keys {
+        let counts = state
+            .purge_session_data(session_key)
+            .await
+            .map_err(map_domain_error)?;
+        purged.sessions += counts.sessions;
+        purged.messages += counts.messages;
+        purged.runs += counts.runs;
+    }
+
+    let directory_entries = match &subject.directory {
+        Some((channel, conversation)) => u64::from(
+            state
+                .delete_channel_directory_entry(channel, conversation)
+                .await
+                .map_err(map_domain_error)?,
+        ),
+        None => 0,
+    };
+    let identities_unlinked = match &subject.identity {
+        Some((channel, external_id)) => u64::from(
+            state
+                .unlink_person_identity(channel, external_id)
+                .await
+                .map_err(map_domain_error)?
+                .is_some(),
+        ),
+        None => 0,
+    };
+
+    let summary = json!({
+        "sessionKeys": subject.session_keys,
+        "sessions": purged.sessions,
+        "messages": purged.messages,
+        "runs": purged.runs,
+        "directoryEntries": directory_entries,
+        "identitiesUnlinked": identities_unlinked,
+    });
+    let audit_id = record_audit(state, session, "delete", &subject, summary.clone()).await?;
+
+    Ok(json!({
+        "ok": true,
+        "auditId": audit_id,
+        "deleted": summary,
+    }))
+}
+
+pub async fn handle_audit_list(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: PrivacyAuditListParams = parse_optional_params("privacy.audit.list", params)?;
+    let limit = parsed.limit.unwrap_or(100).clamp(1, 1_000);
+    let entries = state
+        .list_privacy_audit(limit)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({
+        "ts": now_unix_ms(),
+        "entries": entries,
+    }))
+}
+
+async fn resolve_subject(
+    state: &SharedState,
+    method: &str,
+    params: PrivacySubjectParams,
+) -> Result<PrivacySubject, crate::protocol::ErrorShape> {
+    let session_key = params.session_key.and_then(trim_non_empty);
+    let channel = params
+        .channel
+        .and_then(trim_non_empty)
+        .map(|value| value.to_ascii_lowercase());
+    let external_id = params.external_id.and_then(trim_non_empty);
+
+    match (session_key, channel, external_id) {
+        (Some(session_key), None, None) => {
+            let directory = parse_channel_session_key(&session_key)
+                .map(|(channel, conversation)| (channel.to_owned(), conversation.to_owned()));
+            Ok(PrivacySubject {
+                descriptor: json!({ "sessionKey": session_key }),
+                session_keys: vec![session_key],
+                directory,
+                identity: None,
+                person: None,
+            })
+        }
+        (None, Some(channel), Some(external_id)) => {
+            let conversation = external_id.to_ascii_lowercase();
+            let mut session_keys = state
+                .list_session_keys_like(&format!("agent:%:{channel}:chat:{conversation}"))
+                .await
+                .map_err(map_domain_error)?;
+            // `LIKE` treats `_` in ids as a wildcard; keep exact matches only.
+            session_keys.retain(|key| {
+                parse_channel_session_key(key) == Some((channel.as_str(), conversation.as_str()))
+            });
+
+            let person = state
+                .find_person_by_identity(&channel, &external_id)
+                .await
+                .map_err(map_domain_error)?;
+            if let Some(person) = person.as_ref().filter(|person| person.shared_session) {
+                let suffix = format!(":person:{}", person.person_id);
+                let shared = state
+                    .list_session_keys_like(&format!("agent:%{suffix}"))
+                    .await
+                    .map_err(map_domain_error)?;
+                session_keys.extend(shared.into_iter().filter(|key| key.ends_with(&suffix)));
+            }
+
+            Ok(PrivacySubject {
+                descriptor: json!({ "channel": channel, "externalId": external_id }),
+                session_keys,
+                directory: Some((channel.clone(), conversation)),
+                identity: Some((channel, external_id)),
+                person,
+            })
+        }
+        _ => Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid {method} params: provide either sessionKey or channel+externalId"),
+        )),
+    }
+}
+
+async fn record_audit(
+    state: &SharedState,
+    session: &SessionContext,
+    action: &str,
+    subject: &PrivacySubject,
+    summary: Value,
+) -> Result<String, crate::protocol::ErrorShape> {
+    let record = PrivacyAuditRecord {
+        id: format!("privacy-{}", uuid::Uuid::new_v4()),
+        action: action.to_owned(),
+        subject: subject.descriptor.clone(),
+        summary,
+        requested_by: session.client_id.clone(),
+        created_at_ms: now_unix_ms(),
+    };
+    state
+        .record_privacy_audit(&record)
+        .await
+        .map_err(map_domain_error)?;
+    Ok(record.id)
+}
+
+/// Splits `agent:{agent}:{channel}:chat:{conversation}` into `(channel, conversation)`.
+fn parse_channel_session_key(session_key: &str) -> Option<(&str, &str)> {
+    let mut parts = session_key.split(':');
+    match (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) {
+        (Some("agent"), Some(_), Some(channel), Some("chat"), Some(conversation), None) => {
+            Some((channel, conversation))
+        }
+        _ => None,
+    }
+}
+
+fn trim_non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
@@ -0,0 +1,49 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    application::{replication, state::SharedState},
+    protocol::{ERROR_INVALID_REQUEST, ERROR_UNAVAILABLE, ErrorShape},
+    rpc::{methods::parse_optional_params, schema::rpc_params},
+};
+
+rpc_params! {
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ReplicationPromoteParams {
+        #[serde(default)]
+        reason: Option<String>,
+    }
+}
+
+pub async fn handle_status(state: &SharedState) -> Result<Value, ErrorShape> {
+    replication::status_payload(state)
+        .await
+        .ok_or_else(not_configured)
+}
+
+pub async fn handle_promote(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, ErrorShape> {
+    if state.config().replication.is_none() {
+        return Err(not_configured());
+    }
+    let parsed: ReplicationPromoteParams = parse_optional_params("replication.promote", params)?;
+    let reason = parsed
+        .reason
+        .map(|reason| reason.trim().to_owned())
+        .filter(|reason| !reason.is_empty())
+        .unwrap_or_else(|| "promoted by operator".to_owned());
+    let status = replication::promote(state, &reason)
+        .await
+        .map_err(|error| ErrorShape::new(ERROR_INVALID_REQUEST, error))?;
+    Ok(json!({ "promoted": true, "status": status }))
+}
+
+fn not_configured() -> ErrorShape {
+    ErrorShape::new(
+        ERROR_UNAVAILABLE,
+        "replication is not configured; set replication.token",
+    )
+}
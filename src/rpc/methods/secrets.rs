@@ -0,0 +1,45 @@
+use serde_json::{Value, json};
+
+use crate::{
+    application::{
+        config::AuthMode,
+        secrets::{SecretRef, SecretSource},
+        state::SharedState,
+    },
+    protocol::ErrorShape,
+};
+
+/// Reports where each credential setting came from, never its value.
+pub fn handle_status(state: &SharedState) -> Result<Value, ErrorShape> {
+    let config = state.config();
+    let entry = |key: &'static str, configured: bool| {
+        let reference = config.secret_refs.get(key);
+        let source = match (reference, configured) {
+            (Some(reference), _) => reference.source(),
+            (None, true) => SecretSource::Plaintext,
+            (None, false) => SecretSource::Unset,
+        };
+        json!({
+            "name": key,
+            "configured": configured,
+            "source": source,
+            "plaintext": source == SecretSource::Plaintext,
+            "hardwareBacked": source.is_hardware_backed(),
+            "reference": reference.map(SecretRef::label),
+        })
+    };
+
+    Ok(json!({
+        "secrets": [
+            entry(
+                "gatewayToken",
+                matches!(config.auth_mode, AuthMode::Token(_)),
+            ),
+            entry("hooksToken", config.hooks_token.is_some()),
+        ],
+        "backends": {
+            "keychain": cfg!(feature = "keychain"),
+            "tpm": cfg!(feature = "tpm"),
+        },
+    }))
+}
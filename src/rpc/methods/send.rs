@@ -4,23 +4,33 @@ use serde_json::{Map, Value, json};
 use crate::{
     application::state::SharedState,
     domain::models::{ChatMessage, SessionRecord},
-    rpc::{SessionContext, dispatcher::map_domain_error, methods::parse_required_params},
+    interfaces::channels::{conversation_session_key, normalize_segment},
+    rpc::{
+        SessionContext, dispatcher::map_domain_error, methods::parse_required_params,
+        schema::rpc_params,
+    },
     storage::now_unix_ms,
 };
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SendParams {
-    #[serde(default)]
-    session_key: Option<String>,
-    #[serde(default)]
-    session_id: Option<String>,
-    #[serde(default)]
-    message: Option<String>,
-    #[serde(default)]
-    text: Option<String>,
-    #[serde(default)]
-    channel: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SendParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        message: Option<String>,
+        #[serde(default)]
+        text: Option<String>,
+        #[serde(default)]
+        channel: Option<String>,
+        #[serde(default)]
+        person_id: Option<String>,
+        #[serde(default)]
+        agent_id: Option<String>,
+    }
 }
 
 pub async fn handle_send(
@@ -30,11 +40,28 @@ pub async fn handle_send(
 ) -> Result<Value, crate::protocol::ErrorShape> {
     let parsed: SendParams = parse_required_params("send", params)?;
 
-    let session_key = parsed
-        .session_key
-        .or(parsed.session_id)
-        .and_then(trim_non_empty)
-        .unwrap_or_else(|| "agent:main:main".to_owned());
+    let mut channel = parsed.channel.and_then(trim_non_empty);
+    let person_id = parsed.person_id.and_then(trim_non_empty);
+    let session_key = match (
+        parsed
+            .session_key
+            .or(parsed.session_id)
+            .and_then(trim_non_empty),
+        &person_id,
+    ) {
+        (Some(session_key), _) => session_key,
+        (None, Some(person_id)) => {
+            let agent_id = parsed
+                .agent_id
+                .and_then(trim_non_empty)
+                .unwrap_or_else(|| "main".to_owned());
+            let (session_key, resolved_channel) =
+                resolve_person_session(state, person_id, &agent_id, channel.as_deref()).await?;
+            channel = resolved_channel;
+            session_key
+        }
+        (None, None) => "agent:main:main".to_owned(),
+    };
 
     let message = parsed
         .message
@@ -58,9 +85,11 @@ pub async fn handle_send(
         ts,
         metadata: json!({
             "source": "send",
-            "channel": parsed.channel,
+            "channel": channel,
+            "personId": person_id,
             "requestedBy": session.client_id,
         }),
+        pinned: false,
     };
 
     state
@@ -72,10 +101,74 @@ pub async fn handle_send(
         "ok": true,
         "delivered": true,
         "sessionKey": session_key,
+        "personId": person_id,
+        "channel": channel,
         "message": entry,
     }))
 }
 
+/// Picks the session used to address a linked person: the shared person session when enabled,
+/// otherwise the direct chat of the requested channel (or the most recently linked identity). The
+/// direct chat is the directory's conversation for the identity, keyed the way inbound messages
+/// key it, so a person who never wrote on that channel cannot be addressed there.
+async fn resolve_person_session(
+    state: &SharedState,
+    person_id: &str,
+    agent_id: &str,
+    channel: Option<&str>,
+) -> Result<(String, Option<String>), crate::protocol::ErrorShape> {
+    let person = state
+        .get_person(person_id)
+        .await
+        .map_err(map_domain_error)?
+        .ok_or_else(|| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                format!("invalid send params: unknown person {person_id}"),
+            )
+        })?;
+
+    let identity = match channel {
+        Some(channel) => person
+            .identities
+            .iter()
+            .find(|identity| identity.channel.eq_ignore_ascii_case(channel)),
+        None => person
+            .identities
+            .iter()
+            .max_by_key(|identity| identity.linked_at_ms),
+    }
+    .ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid send params: person {person_id} has no matching identity"),
+        )
+    })?;
+
+    if person.shared_session {
+        return Ok((
+            format!("agent:{agent_id}:person:{}", person.person_id),
+            Some(identity.channel.clone()),
+        ));
+    }
+
+    let channel = normalize_segment(&identity.channel);
+    let entry = state
+        .get_channel_directory_entry(&channel, &normalize_segment(&identity.external_id))
+        .await
+        .map_err(map_domain_error)?
+        .ok_or_else(|| {
+            crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                format!(
+                    "invalid send params: person {person_id} has no known conversation on {channel}"
+                ),
+            )
+        })?;
+    let session_key = conversation_session_key(agent_id, &entry.channel, &entry.conversation_id);
+    Ok((session_key, Some(entry.channel)))
+}
+
 async fn ensure_session_exists(
     state: &SharedState,
     session_key: &str,
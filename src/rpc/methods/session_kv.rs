@@ -0,0 +1,213 @@
+//! `session.kv.*`: a small per-session JSON scratchpad agents use for counters, pending
+//! follow-ups, and other state too short-lived for workspace files. Live entries are handed to
+//! the agent backend with every turn of the session.
+
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+use crate::{
+    application::state::SharedState,
+    domain::models::SessionKvEntry,
+    rpc::{dispatcher::map_domain_error, methods::parse_required_params, schema::rpc_params},
+    storage::now_unix_ms,
+};
+
+pub const MAX_KEY_CHARS: usize = 128;
+pub const MAX_VALUE_BYTES: u64 = 4 * 1024;
+pub const MAX_KEYS: usize = 64;
+pub const MAX_TOTAL_BYTES: u64 = 32 * 1024;
+/// Entries written without `ttlMs` expire after a day; none outlive 30 days.
+pub const DEFAULT_TTL_MS: u64 = 24 * 60 * 60 * 1_000;
+pub const MAX_TTL_MS: u64 = 30 * 24 * 60 * 60 * 1_000;
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SessionKvGetParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        /// Omit to list every live entry of the session.
+        #[serde(default)]
+        key: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SessionKvSetParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        key: String,
+        value: Value,
+        /// Defaults to one day; at most 30 days.
+        #[serde(default)]
+        ttl_ms: Option<u64>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SessionKvDeleteParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        key: String,
+    }
+}
+
+pub async fn handle_get(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let method = "session.kv.get";
+    let parsed: SessionKvGetParams = parse_required_params(method, params)?;
+    let session_key = resolve_session_key(method, parsed.session_key, parsed.session_id)?;
+
+    if let Some(key) = parsed.key {
+        let key = validate_key(method, &key)?;
+        let entry = state
+            .get_session_kv(&session_key, &key)
+            .await
+            .map_err(map_domain_error)?;
+        return Ok(json!({
+            "sessionKey": session_key,
+            "key": key,
+            "found": entry.is_some(),
+            "entry": entry,
+        }));
+    }
+
+    let entries = state
+        .list_session_kv(&session_key)
+        .await
+        .map_err(map_domain_error)?;
+    let total_bytes = entries.iter().map(|entry| entry.size_bytes).sum::<u64>();
+    Ok(json!({
+        "sessionKey": session_key,
+        "count": entries.len(),
+        "totalBytes": total_bytes,
+        "entries": entries,
+        "limits": {
+            "maxKeys": MAX_KEYS,
+            "maxValueBytes": MAX_VALUE_BYTES,
+            "maxTotalBytes": MAX_TOTAL_BYTES,
+            "maxTtlMs": MAX_TTL_MS,
+        },
+    }))
+}
+
+pub async fn handle_set(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let method = "session.kv.set";
+    let parsed: SessionKvSetParams = parse_required_params(method, params)?;
+    let session_key = resolve_session_key(method, parsed.session_key, parsed.session_id)?;
+    let key = validate_key(method, &parsed.key)?;
+    let ttl_ms = parsed.ttl_ms.unwrap_or(DEFAULT_TTL_MS);
+    if ttl_ms == 0 || ttl_ms > MAX_TTL_MS {
+        return Err(invalid_params(
+            method,
+            format!("ttlMs must be between 1 and {MAX_TTL_MS}"),
+        ));
+    }
+    let size_bytes = u64::try_from(parsed.value.to_string().len()).unwrap_or(u64::MAX);
+    if size_bytes > MAX_VALUE_BYTES {
+        return Err(invalid_params(
+            method,
+            format!("value is {size_bytes} bytes; the limit is {MAX_VALUE_BYTES}"),
+        ));
+    }
+
+    let now = now_unix_ms();
+    let entry = SessionKvEntry {
+        key,
+        value: parsed.value,
+        size_bytes,
+        updated_at_ms: now,
+        expires_at_ms: Some(now.saturating_add(ttl_ms)),
+    };
+    state
+        .set_session_kv(&session_key, &entry, MAX_KEYS, MAX_TOTAL_BYTES)
+        .await
+        .map_err(map_domain_error)?;
+    Ok(json!({
+        "ok": true,
+        "sessionKey": session_key,
+        "entry": entry,
+    }))
+}
+
+pub async fn handle_delete(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let method = "session.kv.delete";
+    let parsed: SessionKvDeleteParams = parse_required_params(method, params)?;
+    let session_key = resolve_session_key(method, parsed.session_key, parsed.session_id)?;
+    let key = validate_key(method, &parsed.key)?;
+    let deleted = state
+        .delete_session_kv(&session_key, &key)
+        .await
+        .map_err(map_domain_error)?;
+    Ok(json!({
+        "ok": true,
+        "sessionKey": session_key,
+        "key": key,
+        "deleted": deleted,
+    }))
+}
+
+/// The session's live scratchpad as a `key -> value` object, as handed to the agent backend.
+pub(crate) async fn load_scratchpad(
+    state: &SharedState,
+    session_key: &str,
+) -> Result<Map<String, Value>, crate::protocol::ErrorShape> {
+    Ok(state
+        .list_session_kv(session_key)
+        .await
+        .map_err(map_domain_error)?
+        .into_iter()
+        .map(|entry| (entry.key, entry.value))
+        .collect())
+}
+
+fn resolve_session_key(
+    method: &str,
+    session_key: Option<String>,
+    session_id: Option<String>,
+) -> Result<String, crate::protocol::ErrorShape> {
+    session_key
+        .or(session_id)
+        .map(|key| key.trim().to_owned())
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| invalid_params(method, "sessionKey is required"))
+}
+
+fn validate_key(method: &str, key: &str) -> Result<String, crate::protocol::ErrorShape> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(invalid_params(method, "key is required"));
+    }
+    if key.chars().count() > MAX_KEY_CHARS || key.chars().any(char::is_control) {
+        return Err(invalid_params(
+            method,
+            format!("key must be at most {MAX_KEY_CHARS} printable characters"),
+        ));
+    }
+    Ok(key.to_owned())
+}
+
+fn invalid_params(method: &str, message: impl std::fmt::Display) -> crate::protocol::ErrorShape {
+    crate::protocol::ErrorShape::new(
+        crate::protocol::ERROR_INVALID_REQUEST,
+        format!("invalid {method} params: {message}"),
+    )
+}
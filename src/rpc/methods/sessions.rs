@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::Deserialize;
 use serde_json::{Map, Value, json};
 
@@ -5,75 +7,256 @@ use crate::{
     application::state::SharedState,
     domain::models::SessionRecord,
     rpc::{
+        SessionContext,
         dispatcher::map_domain_error,
-        methods::{parse_optional_params, parse_required_params},
+        methods::{FieldSelection, parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
     storage::now_unix_ms,
 };
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SessionsListParams {
-    #[serde(default)]
-    limit: Option<usize>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SessionsListParams {
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        fields: Option<Vec<String>>,
+        #[serde(default)]
+        tags: Option<Vec<String>>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SessionsPreviewParams {
-    #[serde(default)]
-    keys: Vec<String>,
-    #[serde(default)]
-    limit: Option<usize>,
-    #[serde(default)]
-    max_chars: Option<usize>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SessionsBulkPatchParams {
+        tag: String,
+        #[serde(default)]
+        add_tags: Vec<String>,
+        #[serde(default)]
+        remove_tags: Vec<String>,
+        #[serde(default)]
+        metadata: Option<Value>,
+        #[serde(default)]
+        dry_run: Option<bool>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SessionsPatchParams {
-    #[serde(default)]
-    id: Option<String>,
-    #[serde(default)]
-    key: Option<String>,
-    #[serde(default)]
-    title: Option<String>,
-    #[serde(default)]
-    tags: Option<Vec<String>>,
-    #[serde(default)]
-    metadata: Option<Value>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SessionsPreviewParams {
+        #[serde(default)]
+        keys: Vec<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        max_chars: Option<usize>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SessionsDeleteParams {
-    #[serde(default)]
-    id: Option<String>,
-    #[serde(default)]
-    key: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SessionsPatchParams {
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        key: Option<String>,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        tags: Option<Vec<String>>,
+        #[serde(default)]
+        metadata: Option<Value>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SessionsCompactParams {
-    #[serde(default)]
-    max_age_ms: Option<u64>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SessionsDeleteParams {
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        key: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SessionsCompactParams {
+        #[serde(default)]
+        max_age_ms: Option<u64>,
+    }
 }
 
 pub async fn handle_list(
     state: &SharedState,
+    session: &SessionContext,
     params: Option<&Value>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
     let parsed: SessionsListParams = parse_optional_params("sessions.list", params)?;
+    let fields = FieldSelection::parse("sessions.list", parsed.fields)?;
     let mut sessions = state.list_sessions().await.map_err(map_domain_error)?;
+    if let Some(tags) = parsed.tags.map(|tags| sanitize_tags(&tags)) {
+        sessions.retain(|session| session_has_tags(session, &tags));
+    }
 
     if let Some(limit) = parsed.limit {
         sessions.truncate(limit);
     }
 
+    let unread = if fields.includes("unreadCount") {
+        Some(
+            state
+                .count_unread_chat_messages(&session.client_id)
+                .await
+                .map_err(map_domain_error)?,
+        )
+    } else {
+        None
+    };
+
+    let display_names = if fields.includes("displayName") {
+        let keys = sessions
+            .iter()
+            .map(|session| session.id.as_str())
+            .collect::<Vec<_>>();
+        Some(state.resolve_session_display_names(&keys).await)
+    } else {
+        None
+    };
+
+    let mut rendered = Vec::with_capacity(sessions.len());
+    for record in sessions {
+        let mut value = json!(record);
+        if let Some(object) = value.as_object_mut() {
+            if let Some(display_names) = &display_names {
+                let display_name = display_names.get(&record.id);
+                object.insert("displayName".to_owned(), json!(display_name));
+            }
+            if let Some(unread) = &unread {
+                let count = unread.get(&record.id).copied().unwrap_or(0);
+                object.insert("unreadCount".to_owned(), json!(count));
+            }
+        }
+        rendered.push(fields.apply(value));
+    }
+
+    Ok(json!({
+        "ts": now_unix_ms(),
+        "sessions": rendered,
+    }))
+}
+
+pub async fn handle_tags_list(state: &SharedState) -> Result<Value, crate::protocol::ErrorShape> {
+    let sessions = state.list_sessions().await.map_err(map_domain_error)?;
+    let mut counts = BTreeMap::<&str, (usize, u64)>::new();
+    for session in &sessions {
+        for tag in &session.tags {
+            let entry = counts.entry(tag.as_str()).or_default();
+            entry.0 += 1;
+            entry.1 = entry.1.max(session.updated_at_ms);
+        }
+    }
+
+    let mut tags = counts.into_iter().collect::<Vec<_>>();
+    tags.sort_by(|left, right| right.1.0.cmp(&left.1.0).then(left.0.cmp(right.0)));
+    let tags = tags
+        .into_iter()
+        .map(|(tag, (count, last_updated_at_ms))| {
+            json!({
+                "tag": tag,
+                "count": count,
+                "lastUpdatedAtMs": last_updated_at_ms,
+            })
+        })
+        .collect::<Vec<_>>();
+
     Ok(json!({
         "ts": now_unix_ms(),
-        "sessions": sessions,
+        "tags": tags,
+        "untagged": sessions.iter().filter(|session| session.tags.is_empty()).count(),
+    }))
+}
+
+pub async fn handle_bulk_patch(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: SessionsBulkPatchParams = parse_required_params("sessions.bulkPatch", params)?;
+    let tag = trim_non_empty(parsed.tag).ok_or_else(|| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid sessions.bulkPatch params: tag is required",
+        )
+    })?;
+    let add_tags = sanitize_tags(&parsed.add_tags);
+    let remove_tags = sanitize_tags(&parsed.remove_tags);
+    let metadata = match parsed.metadata {
+        Some(Value::Object(metadata)) => metadata,
+        Some(_) => {
+            return Err(crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                "invalid sessions.bulkPatch params: metadata must be an object",
+            ));
+        }
+        None => Map::new(),
+    };
+    if add_tags.is_empty() && remove_tags.is_empty() && metadata.is_empty() {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            "invalid sessions.bulkPatch params: addTags, removeTags, or metadata is required",
+        ));
+    }
+
+    let now = now_unix_ms();
+    let matched = state
+        .list_sessions()
+        .await
+        .map_err(map_domain_error)?
+        .into_iter()
+        .filter(|session| session.tags.contains(&tag))
+        .collect::<Vec<_>>();
+    let mut updated = Vec::new();
+    for session in &matched {
+        let mut next = session.clone();
+        next.tags.retain(|tag| !remove_tags.contains(tag));
+        for tag in &add_tags {
+            if !next.tags.contains(tag) {
+                next.tags.push(tag.clone());
+            }
+        }
+        if !metadata.is_empty() {
+            let mut merged = next.metadata.as_object().cloned().unwrap_or_default();
+            merged.extend(metadata.clone());
+            next.metadata = Value::Object(merged);
+        }
+        if next.tags != session.tags || next.metadata != session.metadata {
+            next.updated_at_ms = now;
+            updated.push(next);
+        }
+    }
+
+    let dry_run = parsed.dry_run.unwrap_or(false);
+    if !dry_run {
+        state
+            .upsert_sessions(&updated)
+            .await
+            .map_err(map_domain_error)?;
+    }
+
+    Ok(json!({
+        "ok": true,
+        "tag": tag,
+        "dryRun": dry_run,
+        "matched": matched.len(),
+        "updated": updated.len(),
+        "keys": updated.iter().map(|session| session.id.as_str()).collect::<Vec<_>>(),
     }))
 }
 
@@ -126,8 +309,10 @@ pub async fn handle_preview(
             "ok"
         };
 
+        let display_name = state.resolve_session_display_name(&key).await;
         previews.push(json!({
             "key": key,
+            "displayName": display_name,
             "status": status,
             "items": items,
         }));
@@ -203,6 +388,7 @@ pub async fn handle_reset(state: &SharedState) -> Result<Value, crate::protocol:
     Ok(json!({
         "ok": true,
         "removed": removed,
+        "tombstoned": state.config().append_only,
     }))
 }
 
@@ -218,6 +404,7 @@ pub async fn handle_delete(
         "ok": true,
         "key": id,
         "deleted": deleted,
+        "tombstoned": state.config().append_only,
     }))
 }
 
@@ -236,6 +423,7 @@ pub async fn handle_compact(
         "ok": true,
         "removed": removed,
         "maxAgeMs": max_age_ms,
+        "tombstoned": state.config().append_only,
     }))
 }
 
@@ -254,7 +442,12 @@ fn resolve_session_id(
     Ok(value)
 }
 
-fn sanitize_tags(tags: &[String]) -> Vec<String> {
+/// Whether `session` carries every tag in `tags`.
+pub(crate) fn session_has_tags(session: &SessionRecord, tags: &[String]) -> bool {
+    tags.iter().all(|tag| session.tags.contains(tag))
+}
+
+pub(crate) fn sanitize_tags(tags: &[String]) -> Vec<String> {
     let mut out = Vec::new();
     for tag in tags {
         let trimmed = tag.trim();
@@ -8,6 +8,7 @@ use crate::{
     rpc::{
         dispatcher::map_domain_error,
         methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
     storage::now_unix_ms,
 };
@@ -35,32 +36,38 @@ struct SkillConfig {
 
 type SkillEntries = BTreeMap<String, SkillConfig>;
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SkillsStatusParams {
-    #[serde(default)]
-    agent_id: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SkillsStatusParams {
+        #[serde(default)]
+        agent_id: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SkillsInstallParams {
-    name: String,
-    install_id: String,
-    #[serde(default)]
-    timeout_ms: Option<u64>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SkillsInstallParams {
+        name: String,
+        install_id: String,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SkillsUpdateParams {
-    skill_key: String,
-    #[serde(default)]
-    enabled: Option<bool>,
-    #[serde(default)]
-    api_key: Option<String>,
-    #[serde(default)]
-    env: Option<Map<String, Value>>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SkillsUpdateParams {
+        skill_key: String,
+        #[serde(default)]
+        enabled: Option<bool>,
+        #[serde(default)]
+        api_key: Option<String>,
+        #[serde(default)]
+        env: Option<Map<String, Value>>,
+    }
 }
 
 pub async fn handle_status(
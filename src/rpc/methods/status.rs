@@ -1,9 +1,12 @@
 use serde_json::{Value, json};
 
-use crate::{application::state::SharedState, rpc::SessionContext};
+use crate::{
+    application::{federation, state::SharedState},
+    rpc::SessionContext,
+};
 
 pub async fn handle(state: &SharedState, session: &SessionContext) -> Value {
-    json!({
+    let mut payload = json!({
         "ok": true,
         "runtime": "rust",
         "version": state.config().runtime_version,
@@ -17,7 +20,11 @@ pub async fn handle(state: &SharedState, session: &SessionContext) -> Value {
             "clientId": session.client_id,
             "clientMode": session.client_mode,
         }
-    })
+    });
+    if let Some(federation) = federation::status(state).await {
+        payload["federation"] = federation;
+    }
+    payload
 }
 
 #[must_use]
@@ -32,6 +39,7 @@ pub fn info_payload(state: &SharedState) -> Value {
         },
         "protocolVersion": crate::protocol::PROTOCOL_VERSION,
         "authMode": state.auth_mode_label(),
+        "profile": state.config().profile,
         "methods": state.methods(),
         "events": state.events(),
     })
@@ -1,12 +1,18 @@
+use std::time::Duration;
+
 use serde::Deserialize;
 use serde_json::{Value, json};
 
 use crate::{
-    application::state::SharedState,
+    application::{
+        lifecycle::{self, MaintenanceWindow, StopKind, StopRequest},
+        state::SharedState,
+    },
     rpc::{
         SessionContext,
         dispatcher::map_domain_error,
         methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
     storage::now_unix_ms,
 };
@@ -14,27 +20,70 @@ use crate::{
 const LAST_HEARTBEAT_KEY: &str = "system/last-heartbeat";
 const HEARTBEATS_KEY: &str = "system/heartbeats";
 const SYSTEM_EVENT_PREFIX: &str = "system/events/";
+const MAX_STOP_DELAY_MS: u64 = 24 * 60 * 60 * 1000;
+const MAX_DRAIN_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct HeartbeatsSetParams {
+        #[serde(default)]
+        heartbeats: Option<Value>,
+    }
+}
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct HeartbeatsSetParams {
-    #[serde(default)]
-    heartbeats: Option<Value>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct WakeParams {
+        #[serde(default)]
+        reason: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SystemEventParams {
+        event: String,
+        #[serde(default)]
+        payload: Option<Value>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct WakeParams {
-    #[serde(default)]
-    reason: Option<String>,
+rpc_params! {
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SystemStopParams {
+        /// How long to keep serving before the stop begins; defaults to immediately.
+        #[serde(default)]
+        delay_ms: Option<u64>,
+        /// Whether to wait for in-flight runs and webhooks first; defaults to true.
+        #[serde(default)]
+        drain: Option<bool>,
+        #[serde(default)]
+        drain_timeout_ms: Option<u64>,
+        #[serde(default)]
+        reason: Option<String>,
+        /// Cancels the pending scheduled stop instead of scheduling one.
+        #[serde(default)]
+        cancel: Option<bool>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SystemEventParams {
-    event: String,
-    #[serde(default)]
-    payload: Option<Value>,
+rpc_params! {
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct SystemMaintenanceParams {
+        /// Omit to read the current state without changing it.
+        #[serde(default)]
+        enabled: Option<bool>,
+        #[serde(default)]
+        reason: Option<String>,
+        /// Ends the window automatically after this long.
+        #[serde(default)]
+        duration_ms: Option<u64>,
+    }
 }
 
 pub async fn handle_last_heartbeat(
@@ -151,6 +200,95 @@ pub async fn handle_system_event(
     }))
 }
 
+pub async fn handle_stop(
+    state: &SharedState,
+    kind: StopKind,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let method = match kind {
+        StopKind::Shutdown => "system.shutdown",
+        StopKind::Restart => "system.restart",
+    };
+    let parsed: SystemStopParams = parse_optional_params(method, params)?;
+    if parsed.cancel == Some(true) {
+        let cancelled = lifecycle::cancel_stop(state).await;
+        return Ok(json!({
+            "ok": true,
+            "cancelled": cancelled,
+        }));
+    }
+
+    let delay_ms = parsed.delay_ms.unwrap_or(0);
+    if delay_ms > MAX_STOP_DELAY_MS {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("invalid {method} params: delayMs must be at most {MAX_STOP_DELAY_MS}"),
+        ));
+    }
+    let drain_timeout_ms = parsed
+        .drain_timeout_ms
+        .unwrap_or(u64::try_from(lifecycle::DEFAULT_DRAIN_TIMEOUT.as_millis()).unwrap_or(u64::MAX));
+    if drain_timeout_ms > MAX_DRAIN_TIMEOUT_MS {
+        return Err(crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!(
+                "invalid {method} params: drainTimeoutMs must be at most {MAX_DRAIN_TIMEOUT_MS}"
+            ),
+        ));
+    }
+    let scheduled = lifecycle::schedule_stop(
+        state,
+        StopRequest {
+            kind,
+            delay: Duration::from_millis(delay_ms),
+            drain: parsed.drain.unwrap_or(true),
+            drain_timeout: Duration::from_millis(drain_timeout_ms),
+            reason: parsed.reason.and_then(trim_non_empty),
+        },
+    )
+    .await
+    .map_err(|error| {
+        crate::protocol::ErrorShape::new(
+            crate::protocol::ERROR_INVALID_REQUEST,
+            format!("{method} failed: {error}"),
+        )
+    })?;
+
+    Ok(json!({
+        "ok": true,
+        "scheduled": scheduled,
+    }))
+}
+
+pub async fn handle_maintenance(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: SystemMaintenanceParams = parse_optional_params("system.maintenance", params)?;
+    match parsed.enabled {
+        Some(true) => {
+            let since_ms = now_unix_ms();
+            let window = MaintenanceWindow {
+                reason: parsed.reason.and_then(trim_non_empty),
+                since_ms,
+                until_ms: parsed
+                    .duration_ms
+                    .map(|duration_ms| since_ms.saturating_add(duration_ms)),
+            };
+            lifecycle::set_maintenance(state, Some(window)).await;
+        }
+        Some(false) => lifecycle::set_maintenance(state, None).await,
+        None if parsed.reason.is_some() || parsed.duration_ms.is_some() => {
+            return Err(crate::protocol::ErrorShape::new(
+                crate::protocol::ERROR_INVALID_REQUEST,
+                "invalid system.maintenance params: enabled is required with reason or durationMs",
+            ));
+        }
+        None => {}
+    }
+    Ok(lifecycle::status_payload(state).await)
+}
+
 fn trim_non_empty(value: String) -> Option<String> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
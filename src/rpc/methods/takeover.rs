@@ -0,0 +1,445 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::warn;
+
+use crate::{
+    application::state::SharedState,
+    domain::models::ChatMessage,
+    interfaces::{channel_adapter_common as common, quiet_hours},
+    rpc::{
+        SessionContext, dispatcher::map_domain_error, methods::parse_required_params,
+        schema::rpc_params,
+    },
+    storage::now_unix_ms,
+};
+
+/// Active takeovers are config entries so every instance routes the session's inbound messages
+/// to operators.
+const TAKEOVER_PREFIX: &str = "runtime/takeover/";
+const TAKEOVER_EVENT: &str = "chat.takeover";
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct TakeoverParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        reason: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct TakeoverReplyParams {
+        #[serde(default)]
+        session_key: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        message: String,
+    }
+}
+
+/// Where operator replies go: the channel conversation that last wrote to the session.
+#[derive(Debug, Clone)]
+pub struct TakeoverTarget {
+    pub channel: String,
+    pub conversation_id: String,
+    /// Bot Connector endpoint of the conversation, required for Teams replies.
+    pub service_url: Option<String>,
+}
+
+pub async fn handle_start(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: TakeoverParams = parse_required_params("chat.takeover.start", params)?;
+    let session_key =
+        resolve_session_key("chat.takeover.start", parsed.session_key, parsed.session_id)?;
+    if state
+        .get_session(&session_key)
+        .await
+        .map_err(map_domain_error)?
+        .is_none()
+    {
+        return Err(invalid_params("chat.takeover.start", "unknown sessionKey"));
+    }
+    if let Some(existing) = active_takeover(state, &session_key).await {
+        return Ok(json!({
+            "ok": true,
+            "started": false,
+            "takeover": existing,
+        }));
+    }
+
+    // Until the user writes again, replies go to the conversation of the last agent reply.
+    let target = state
+        .list_message_deliveries(Some(&session_key), None, 1)
+        .await
+        .map_err(map_domain_error)?
+        .into_iter()
+        .next()
+        .map(|delivery| {
+            json!({
+                "channel": delivery.channel,
+                "conversationId": delivery.conversation_id,
+            })
+        });
+    let now = now_unix_ms();
+    let reason = parsed.reason.and_then(trim_non_empty);
+    let takeover = json!({
+        "sessionKey": session_key,
+        "operator": session.client_id,
+        "reason": reason,
+        "startedAtMs": now,
+        "target": target,
+    });
+    state
+        .set_config_entry_value(&takeover_key(&session_key), &takeover)
+        .await
+        .map_err(map_domain_error)?;
+
+    let text = match &reason {
+        Some(reason) => format!("{} took over the conversation: {reason}", session.client_id),
+        None => format!("{} took over the conversation", session.client_id),
+    };
+    record_marker(
+        state,
+        &session_key,
+        "started",
+        &session.client_id,
+        text,
+        now,
+    )
+    .await?;
+    state
+        .publish_gateway_event(
+            TAKEOVER_EVENT,
+            json!({
+                "state": "started",
+                "sessionKey": session_key,
+                "operator": session.client_id,
+                "reason": reason,
+            }),
+        )
+        .await;
+
+    Ok(json!({
+        "ok": true,
+        "started": true,
+        "takeover": takeover,
+    }))
+}
+
+pub async fn handle_end(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: TakeoverParams = parse_required_params("chat.takeover.end", params)?;
+    let session_key =
+        resolve_session_key("chat.takeover.end", parsed.session_key, parsed.session_id)?;
+    let ended = state
+        .delete_config_entry_value(&takeover_key(&session_key))
+        .await
+        .map_err(map_domain_error)?;
+    if ended {
+        let now = now_unix_ms();
+        let text = format!(
+            "{} ended the takeover; the agent resumes the conversation",
+            session.client_id
+        );
+        record_marker(state, &session_key, "ended", &session.client_id, text, now).await?;
+        state
+            .publish_gateway_event(
+                TAKEOVER_EVENT,
+                json!({
+                    "state": "ended",
+                    "sessionKey": session_key,
+                    "operator": session.client_id,
+                }),
+            )
+            .await;
+    }
+
+    Ok(json!({
+        "ok": true,
+        "sessionKey": session_key,
+        "ended": ended,
+    }))
+}
+
+pub async fn handle_reply(
+    state: &SharedState,
+    session: &SessionContext,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: TakeoverReplyParams = parse_required_params("chat.takeover.reply", params)?;
+    let session_key =
+        resolve_session_key("chat.takeover.reply", parsed.session_key, parsed.session_id)?;
+    let text = trim_non_empty(parsed.message)
+        .ok_or_else(|| invalid_params("chat.takeover.reply", "message is required"))?;
+    let takeover = active_takeover(state, &session_key).await.ok_or_else(|| {
+        invalid_params(
+            "chat.takeover.reply",
+            "session is not under operator takeover",
+        )
+    })?;
+    let target = takeover
+        .get("target")
+        .and_then(parse_target)
+        .ok_or_else(|| {
+            invalid_params(
+                "chat.takeover.reply",
+                "no channel conversation is known for this session yet",
+            )
+        })?;
+
+    let delivery = common::start_delivery(
+        state,
+        &session_key,
+        None,
+        &target.channel,
+        &target.conversation_id,
+    )
+    .await;
+    let delivery_id = delivery.as_ref().map(|delivery| delivery.id.clone());
+    let Some(payload) = reply_payload(&target, &session_key, &text, delivery_id.as_deref()) else {
+        let error = format!("cannot address {} conversation", target.channel);
+        common::finish_delivery(state, delivery, Err(&error)).await;
+        return Err(invalid_params("chat.takeover.reply", error));
+    };
+
+    let status = if quiet_hours::hold_if_quiet(state, &target.channel, &payload, false).await {
+        "queued"
+    } else {
+        match quiet_hours::deliver_payload(state, &target.channel, &payload).await {
+            Ok(platform_message_id) => {
+                common::finish_delivery(state, delivery, Ok(platform_message_id)).await;
+                "sent"
+            }
+            Err(error) => {
+                common::finish_delivery(state, delivery, Err(&error)).await;
+                warn!(
+                    "operator reply to {} conversation failed: {error}",
+                    target.channel
+                );
+                return Err(crate::protocol::ErrorShape::new(
+                    crate::protocol::ERROR_UNAVAILABLE,
+                    format!("failed to deliver operator reply: {error}"),
+                ));
+            }
+        }
+    };
+
+    let now = now_unix_ms();
+    let message = ChatMessage {
+        id: format!("msg-{}", uuid::Uuid::new_v4()),
+        role: "assistant".to_owned(),
+        text,
+        status: "final".to_owned(),
+        ts: now,
+        metadata: json!({
+            "source": "takeover",
+            "operator": session.client_id,
+            "channel": target.channel,
+            "deliveryId": delivery_id,
+        }),
+        pinned: false,
+    };
+    state
+        .append_chat_messages(&session_key, std::slice::from_ref(&message))
+        .await
+        .map_err(map_domain_error)?;
+    state
+        .publish_gateway_event(
+            TAKEOVER_EVENT,
+            json!({
+                "state": "reply",
+                "sessionKey": session_key,
+                "operator": session.client_id,
+                "message": message,
+            }),
+        )
+        .await;
+
+    Ok(json!({
+        "ok": true,
+        "sessionKey": session_key,
+        "status": status,
+        "deliveryId": delivery_id,
+        "message": message,
+    }))
+}
+
+/// Takes an inbound channel message for a session under takeover: records it in history,
+/// remembers the conversation for replies, and pushes it to operators instead of the agent.
+/// Returns `false` when the session is not under takeover.
+pub async fn route_inbound(
+    state: &SharedState,
+    session_key: &str,
+    target: TakeoverTarget,
+    sender_id: Option<&str>,
+    text: &str,
+) -> Result<bool, crate::protocol::ErrorShape> {
+    let Some(mut takeover) = active_takeover(state, session_key).await else {
+        return Ok(false);
+    };
+
+    let message = ChatMessage {
+        id: format!("msg-{}", uuid::Uuid::new_v4()),
+        role: "user".to_owned(),
+        text: text.to_owned(),
+        status: "final".to_owned(),
+        ts: now_unix_ms(),
+        metadata: json!({
+            "source": "takeover",
+            "channel": target.channel,
+            "senderId": sender_id,
+        }),
+        pinned: false,
+    };
+    state
+        .append_chat_messages(session_key, std::slice::from_ref(&message))
+        .await
+        .map_err(map_domain_error)?;
+
+    takeover["target"] = json!({
+        "channel": target.channel,
+        "conversationId": target.conversation_id,
+        "serviceUrl": target.service_url,
+    });
+    state
+        .set_config_entry_value(&takeover_key(session_key), &takeover)
+        .await
+        .map_err(map_domain_error)?;
+    state
+        .publish_gateway_event(
+            TAKEOVER_EVENT,
+            json!({
+                "state": "inbound",
+                "sessionKey": session_key,
+                "channel": target.channel,
+                "conversationId": target.conversation_id,
+                "senderId": sender_id,
+                "message": message,
+            }),
+        )
+        .await;
+    Ok(true)
+}
+
+async fn active_takeover(state: &SharedState, session_key: &str) -> Option<Value> {
+    state
+        .get_config_entry_value(&takeover_key(session_key))
+        .await
+        .ok()
+        .flatten()
+}
+
+fn takeover_key(session_key: &str) -> String {
+    format!("{TAKEOVER_PREFIX}{session_key}")
+}
+
+fn parse_target(value: &Value) -> Option<TakeoverTarget> {
+    let read = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_owned);
+    Some(TakeoverTarget {
+        channel: read("channel")?,
+        conversation_id: read("conversationId")?,
+        service_url: read("serviceUrl"),
+    })
+}
+
+/// Builds the payload `quiet_hours::deliver_payload` expects for the target's channel.
+fn reply_payload(
+    target: &TakeoverTarget,
+    session_key: &str,
+    text: &str,
+    delivery_id: Option<&str>,
+) -> Option<Value> {
+    match target.channel.as_str() {
+        "telegram" => Some(json!({
+            "chatId": target.conversation_id.parse::<i64>().ok()?,
+            "text": text,
+            "deliveryId": delivery_id,
+        })),
+        "teams" => Some(json!({
+            "serviceUrl": target.service_url.as_deref()?,
+            "conversationId": target.conversation_id,
+            "text": text,
+            "deliveryId": delivery_id,
+        })),
+        "mattermost" | "rocketchat" => Some(json!({
+            "conversationId": target.conversation_id,
+            "text": text,
+            "deliveryId": delivery_id,
+        })),
+        channel => Some(json!({
+            "channel": channel,
+            "conversationId": target.conversation_id,
+            "reply": text,
+            "sessionKey": session_key,
+            "runId": Value::Null,
+            "deliveryId": delivery_id,
+            "metadata": { "takeover": true },
+        })),
+    }
+}
+
+async fn record_marker(
+    state: &SharedState,
+    session_key: &str,
+    marker: &str,
+    operator: &str,
+    text: String,
+    ts: u64,
+) -> Result<(), crate::protocol::ErrorShape> {
+    let message = ChatMessage {
+        id: format!("msg-{}", uuid::Uuid::new_v4()),
+        role: "system".to_owned(),
+        text,
+        status: "final".to_owned(),
+        ts,
+        metadata: json!({
+            "source": "takeover",
+            "takeover": marker,
+            "operator": operator,
+        }),
+        pinned: false,
+    };
+    state
+        .append_chat_messages(session_key, std::slice::from_ref(&message))
+        .await
+        .map_err(map_domain_error)
+}
+
+fn resolve_session_key(
+    method: &str,
+    session_key: Option<String>,
+    session_id: Option<String>,
+) -> Result<String, crate::protocol::ErrorShape> {
+    session_key
+        .or(session_id)
+        .and_then(trim_non_empty)
+        .ok_or_else(|| invalid_params(method, "sessionKey is required"))
+}
+
+fn invalid_params(method: &str, message: impl std::fmt::Display) -> crate::protocol::ErrorShape {
+    crate::protocol::ErrorShape::new(
+        crate::protocol::ERROR_INVALID_REQUEST,
+        format!("invalid {method} params: {message}"),
+    )
+}
+
+fn trim_non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
@@ -6,15 +6,18 @@ use crate::{
     rpc::{
         dispatcher::map_domain_error,
         methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
 };
 
 const TALK_CONFIG_KEY: &str = "runtime/talk/config";
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct TalkModeParams {
-    mode: String,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct TalkModeParams {
+        mode: String,
+    }
 }
 
 pub async fn handle_config(
@@ -1,39 +1,559 @@
-use serde_json::{Value, json};
+use std::time::Duration;
 
-use crate::{application::state::SharedState, rpc::methods::parse_optional_params};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
 
-pub fn handle_catalog(
+use crate::{
+    application::state::SharedState,
+    domain::models::{NodeInvokeInput, ToolCallRecord, ToolDefinition, ToolExecutor, ToolGrant},
+    rpc::{
+        dispatcher::map_domain_error,
+        methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
+    },
+    storage::now_unix_ms,
+};
+
+const BUILTIN_TOOLS: &[(&str, &str)] = &[
+    ("echo", "Return the call arguments unchanged"),
+    ("time.now", "Report the gateway clock"),
+];
+const DEFAULT_HTTP_TOOL_TIMEOUT_MS: u64 = 10_000;
+const TERMINAL_RUN_STATUSES: &[&str] = &["completed", "error", "aborted"];
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ToolsCatalogParams {
+        #[serde(default)]
+        agent_id: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ToolRegisterParams {
+        name: String,
+        #[serde(default)]
+        description: Option<String>,
+        #[serde(default)]
+        input_schema: Option<Value>,
+        executor: ToolExecutor,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ToolNameParams {
+        name: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ToolGrantParams {
+        agent_id: String,
+        tool: String,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ToolCallParams {
+        run_id: String,
+        tool: String,
+        #[serde(default)]
+        args: Option<Value>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct ToolCallsListParams {
+        run_id: String,
+    }
+}
+
+pub async fn handle_catalog(
     state: &SharedState,
     params: Option<&Value>,
 ) -> Result<Value, crate::protocol::ErrorShape> {
-    let _: serde_json::Map<String, Value> = parse_optional_params("tools.catalog", params)?;
-
-    let tools = vec![
-        json!({
-            "id": "gateway.request",
-            "kind": "rpc",
-            "description": "Invoke gateway RPC method",
-        }),
-        json!({
-            "id": "sessions",
-            "kind": "state",
-            "description": "List and mutate sessions",
-        }),
-        json!({
-            "id": "cron",
-            "kind": "scheduler",
-            "description": "Create and run scheduled jobs",
-        }),
-        json!({
-            "id": "nodes",
-            "kind": "device",
-            "description": "Pair and invoke registered nodes",
-        }),
-    ];
+    let parsed: ToolsCatalogParams = parse_optional_params("tools.catalog", params)?;
+    let agent_id = parsed.agent_id.and_then(trim_non_empty);
+
+    let mut tools = state.list_tools().await.map_err(map_domain_error)?;
+    let mut grants = Vec::new();
+    if let Some(agent_id) = agent_id.as_deref() {
+        grants = state
+            .list_tool_grants(Some(agent_id))
+            .await
+            .map_err(map_domain_error)?;
+        tools.retain(|tool| grants.iter().any(|grant| grant.tool_name == tool.name));
+    }
+
+    let builtins = BUILTIN_TOOLS
+        .iter()
+        .map(|(name, description)| {
+            json!({
+                "name": name,
+                "description": description,
+                "executor": { "kind": "builtin", "name": name },
+            })
+        })
+        .collect::<Vec<_>>();
 
     Ok(json!({
         "runtime": "reclaw-core",
         "methods": state.methods(),
+        "agentId": agent_id,
         "tools": tools,
+        "builtins": builtins,
+        "grants": grants,
     }))
 }
+
+pub async fn handle_register(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ToolRegisterParams = parse_required_params("tools.register", params)?;
+    let name = trim_non_empty(parsed.name)
+        .filter(|name| {
+            name.chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '.' | '_' | '-'))
+        })
+        .ok_or_else(|| invalid("tools.register", "name must be non-empty [A-Za-z0-9._-]"))?;
+    if is_builtin(&name) {
+        return Err(invalid(
+            "tools.register",
+            &format!("{name} is a built-in tool"),
+        ));
+    }
+    let input_schema = parsed
+        .input_schema
+        .unwrap_or_else(|| json!({ "type": "object" }));
+    if !input_schema.is_object() {
+        return Err(invalid("tools.register", "inputSchema must be an object"));
+    }
+    validate_executor(&parsed.executor).map_err(|error| invalid("tools.register", &error))?;
+
+    let now = now_unix_ms();
+    let created_at_ms = state
+        .get_tool(&name)
+        .await
+        .map_err(map_domain_error)?
+        .map_or(now, |existing| existing.created_at_ms);
+    let tool = ToolDefinition {
+        name,
+        description: parsed
+            .description
+            .and_then(trim_non_empty)
+            .unwrap_or_default(),
+        input_schema,
+        executor: parsed.executor,
+        created_at_ms,
+        updated_at_ms: now,
+    };
+    state.upsert_tool(&tool).await.map_err(map_domain_error)?;
+
+    Ok(json!({ "ok": true, "tool": tool }))
+}
+
+pub async fn handle_unregister(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ToolNameParams = parse_required_params("tools.unregister", params)?;
+    let name = trim_non_empty(parsed.name)
+        .ok_or_else(|| invalid("tools.unregister", "name is required"))?;
+    let removed = state.delete_tool(&name).await.map_err(map_domain_error)?;
+
+    Ok(json!({ "ok": true, "name": name, "removed": removed }))
+}
+
+pub async fn handle_grant(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let (agent_id, tool) = parse_grant_params("tools.grant", params)?;
+    if !is_builtin(&tool)
+        && state
+            .get_tool(&tool)
+            .await
+            .map_err(map_domain_error)?
+            .is_none()
+    {
+        return Err(invalid("tools.grant", &format!("unknown tool: {tool}")));
+    }
+
+    let grant = ToolGrant {
+        agent_id,
+        tool_name: tool,
+        granted_at_ms: now_unix_ms(),
+    };
+    state.grant_tool(&grant).await.map_err(map_domain_error)?;
+
+    Ok(json!({ "ok": true, "grant": grant }))
+}
+
+pub async fn handle_revoke(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let (agent_id, tool) = parse_grant_params("tools.revoke", params)?;
+    let revoked = state
+        .revoke_tool(&agent_id, &tool)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({ "ok": true, "agentId": agent_id, "tool": tool, "revoked": revoked }))
+}
+
+/// Runs a granted tool on behalf of an in-flight agent run and records the call on that run.
+pub async fn handle_call(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ToolCallParams = parse_required_params("tools.call", params)?;
+    let run_id =
+        trim_non_empty(parsed.run_id).ok_or_else(|| invalid("tools.call", "runId is required"))?;
+    let tool_name =
+        trim_non_empty(parsed.tool).ok_or_else(|| invalid("tools.call", "tool is required"))?;
+    let args = parsed.args.unwrap_or_else(|| Value::Object(Map::new()));
+
+    let Some(run) = state
+        .get_agent_run(&run_id)
+        .await
+        .map_err(map_domain_error)?
+    else {
+        return Err(invalid("tools.call", &format!("unknown runId: {run_id}")));
+    };
+    if TERMINAL_RUN_STATUSES.contains(&run.status.as_str()) {
+        return Err(invalid(
+            "tools.call",
+            &format!("run {run_id} is already {}", run.status),
+        ));
+    }
+    if !state
+        .has_tool_grant(&run.agent_id, &tool_name)
+        .await
+        .map_err(map_domain_error)?
+    {
+        return Err(invalid(
+            "tools.call",
+            &format!("agent {} is not granted tool {tool_name}", run.agent_id),
+        ));
+    }
+
+    let executor = if is_builtin(&tool_name) {
+        ToolExecutor::Builtin {
+            name: tool_name.clone(),
+        }
+    } else {
+        let Some(tool) = state.get_tool(&tool_name).await.map_err(map_domain_error)? else {
+            return Err(invalid("tools.call", &format!("unknown tool: {tool_name}")));
+        };
+        validate_against_schema(&tool.input_schema, &args, "args")
+            .map_err(|error| invalid("tools.call", &error))?;
+        tool.executor
+    };
+
+    let mut call = ToolCallRecord {
+        id: format!("toolcall-{}", uuid::Uuid::new_v4()),
+        run_id,
+        agent_id: run.agent_id,
+        tool_name,
+        args,
+        status: "running".to_owned(),
+        result: None,
+        error: None,
+        started_at_ms: now_unix_ms(),
+        completed_at_ms: None,
+    };
+    state
+        .upsert_tool_call(&call)
+        .await
+        .map_err(map_domain_error)?;
+
+    match execute_tool(state, &executor, &call).await {
+        Ok(result) => {
+            call.status = "completed".to_owned();
+            call.result = Some(result);
+        }
+        Err(error) => {
+            call.status = "failed".to_owned();
+            call.error = Some(error);
+        }
+    }
+    call.completed_at_ms = Some(now_unix_ms());
+    state
+        .upsert_tool_call(&call)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({ "ok": call.status == "completed", "call": call }))
+}
+
+pub async fn handle_calls_list(
+    state: &SharedState,
+    params: Option<&Value>,
+) -> Result<Value, crate::protocol::ErrorShape> {
+    let parsed: ToolCallsListParams = parse_required_params("tools.calls.list", params)?;
+    let run_id = trim_non_empty(parsed.run_id)
+        .ok_or_else(|| invalid("tools.calls.list", "runId is required"))?;
+    let calls = state
+        .list_tool_calls_by_run(&run_id)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(json!({ "runId": run_id, "calls": calls }))
+}
+
+async fn execute_tool(
+    state: &SharedState,
+    executor: &ToolExecutor,
+    call: &ToolCallRecord,
+) -> Result<Value, String> {
+    match executor {
+        ToolExecutor::Builtin { name } => match name.as_str() {
+            "echo" => Ok(call.args.clone()),
+            "time.now" => {
+                let now = now_unix_ms();
+                Ok(json!({
+                    "nowMs": now,
+                    "iso": chrono::DateTime::<chrono::Utc>::from_timestamp_millis(
+                        i64::try_from(now).unwrap_or(i64::MAX),
+                    )
+                    .map(|at| at.to_rfc3339()),
+                }))
+            }
+            other => Err(format!("unknown builtin tool: {other}")),
+        },
+        ToolExecutor::Node { node_id, command } => {
+            let invoke = state
+                .create_node_invoke(NodeInvokeInput {
+                    node_id: node_id.clone(),
+                    command: command.clone(),
+                    args: Vec::new(),
+                    input: Some(call.args.clone()),
+                })
+                .await
+                .map_err(|error| error.to_string())?;
+            match invoke.error {
+                Some(error) => Err(error),
+                None => Ok(json!({
+                    "requestId": invoke.request_id,
+                    "status": invoke.status,
+                    "payload": invoke.result,
+                })),
+            }
+        }
+        ToolExecutor::Http { url, timeout_ms } => {
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_millis(
+                    timeout_ms.unwrap_or(DEFAULT_HTTP_TOOL_TIMEOUT_MS),
+                ))
+                .build()
+                .map_err(|error| format!("failed to construct http client: {error}"))?;
+            let response = client
+                .post(url)
+                .json(&json!({
+                    "tool": call.tool_name,
+                    "callId": call.id,
+                    "runId": call.run_id,
+                    "agentId": call.agent_id,
+                    "args": call.args,
+                }))
+                .send()
+                .await
+                .map_err(|error| format!("http tool request failed: {error}"))?;
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            if !status.is_success() {
+                return Err(format!("http tool returned {status}: {body}"));
+            }
+            Ok(serde_json::from_str(&body).unwrap_or(Value::String(body)))
+        }
+    }
+}
+
+fn validate_executor(executor: &ToolExecutor) -> Result<(), String> {
+    match executor {
+        ToolExecutor::Node { node_id, command } => {
+            if node_id.trim().is_empty() || command.trim().is_empty() {
+                return Err("node executor requires nodeId and command".to_owned());
+            }
+        }
+        ToolExecutor::Http { url, .. } => {
+            let parsed = reqwest::Url::parse(url)
+                .map_err(|error| format!("invalid executor url: {error}"))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err("http executor url must use http or https".to_owned());
+            }
+        }
+        ToolExecutor::Builtin { name } => {
+            if !is_builtin(name) {
+                return Err(format!("unknown builtin tool: {name}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks `value` against the subset of JSON Schema used for tool inputs:
+/// `type`, `enum`, `required`, `properties`, `additionalProperties: false`, and `items`.
+fn validate_against_schema(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type") {
+        let allowed = match expected {
+            Value::String(kind) => vec![kind.as_str()],
+            Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|kind| matches_type(kind, value)) {
+            return Err(format!("{path} must be of type {}", allowed.join("|")));
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array)
+        && !options.contains(value)
+    {
+        return Err(format!("{path} must be one of the enumerated values"));
+    }
+
+    if let Some(object) = value.as_object() {
+        for key in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(key) {
+                return Err(format!("{path}.{key} is required"));
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, field) in object {
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(field_schema) => {
+                    validate_against_schema(field_schema, field, &format!("{path}.{key}"))?;
+                }
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    return Err(format!("{path}.{key} is not allowed"));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let (Some(items), Some(values)) = (schema.get("items"), value.as_array()) {
+        for (index, item) in values.iter().enumerate() {
+            validate_against_schema(items, item, &format!("{path}[{index}]"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn matches_type(kind: &str, value: &Value) -> bool {
+    match kind {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn parse_grant_params(
+    method: &str,
+    params: Option<&Value>,
+) -> Result<(String, String), crate::protocol::ErrorShape> {
+    let parsed: ToolGrantParams = parse_required_params(method, params)?;
+    let agent_id =
+        trim_non_empty(parsed.agent_id).ok_or_else(|| invalid(method, "agentId is required"))?;
+    let tool = trim_non_empty(parsed.tool).ok_or_else(|| invalid(method, "tool is required"))?;
+    Ok((agent_id, tool))
+}
+
+fn is_builtin(name: &str) -> bool {
+    BUILTIN_TOOLS.iter().any(|(builtin, _)| *builtin == name)
+}
+
+fn invalid(method: &str, message: &str) -> crate::protocol::ErrorShape {
+    crate::protocol::ErrorShape::new(
+        crate::protocol::ERROR_INVALID_REQUEST,
+        format!("invalid {method} params: {message}"),
+    )
+}
+
+fn trim_non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::validate_against_schema;
+
+    #[test]
+    fn schema_validation_checks_types_required_and_extra_fields() {
+        let schema = json!({
+            "type": "object",
+            "required": ["city"],
+            "additionalProperties": false,
+            "properties": {
+                "city": { "type": "string" },
+                "days": { "type": "integer" },
+                "units": { "enum": ["metric", "imperial"] },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            }
+        });
+
+        assert!(
+            validate_against_schema(&schema, &json!({ "city": "Oslo", "days": 3 }), "args").is_ok()
+        );
+        assert_eq!(
+            validate_against_schema(&schema, &json!({ "days": 3 }), "args"),
+            Err("args.city is required".to_owned())
+        );
+        assert_eq!(
+            validate_against_schema(&schema, &json!({ "city": "Oslo", "days": 1.5 }), "args"),
+            Err("args.days must be of type integer".to_owned())
+        );
+        assert!(
+            validate_against_schema(
+                &schema,
+                &json!({ "city": "Oslo", "units": "kelvin" }),
+                "args"
+            )
+            .is_err()
+        );
+        assert_eq!(
+            validate_against_schema(
+                &schema,
+                &json!({ "city": "Oslo", "tags": ["a", 1] }),
+                "args"
+            ),
+            Err("args.tags[1] must be of type string".to_owned())
+        );
+        assert_eq!(
+            validate_against_schema(&schema, &json!({ "city": "Oslo", "extra": true }), "args"),
+            Err("args.extra is not allowed".to_owned())
+        );
+    }
+}
@@ -6,25 +6,30 @@ use crate::{
     rpc::{
         dispatcher::map_domain_error,
         methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
 };
 
 const TTS_CONFIG_KEY: &str = "runtime/tts/config";
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct TtsConvertParams {
-    text: String,
-    #[serde(default)]
-    provider: Option<String>,
-    #[serde(default)]
-    voice: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct TtsConvertParams {
+        text: String,
+        #[serde(default)]
+        provider: Option<String>,
+        #[serde(default)]
+        voice: Option<String>,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct TtsProviderParams {
-    provider: String,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct TtsProviderParams {
+        provider: String,
+    }
 }
 
 pub async fn handle_status(
@@ -176,6 +181,15 @@ async fn set_tts_enabled(
     Ok(json!({ "ok": true, "enabled": enabled, "status": config }))
 }
 
+/// Whether `tts.convert` is currently enabled.
+pub(crate) async fn tts_enabled(state: &SharedState) -> bool {
+    load_tts_config(state)
+        .await
+        .ok()
+        .and_then(|config| config.get("enabled").and_then(Value::as_bool))
+        .unwrap_or(false)
+}
+
 async fn load_tts_config(state: &SharedState) -> Result<Value, crate::protocol::ErrorShape> {
     let config = state
         .get_config_entry_value(TTS_CONFIG_KEY)
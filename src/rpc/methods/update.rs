@@ -3,19 +3,24 @@ use serde_json::{Value, json};
 
 use crate::{
     application::state::SharedState,
-    rpc::{SessionContext, dispatcher::map_domain_error, methods::parse_optional_params},
+    rpc::{
+        SessionContext, dispatcher::map_domain_error, methods::parse_optional_params,
+        schema::rpc_params,
+    },
     storage::now_unix_ms,
 };
 
 const UPDATE_LAST_RUN_KEY: &str = "runtime/update/last-run";
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct UpdateRunParams {
-    #[serde(default)]
-    mode: Option<String>,
-    #[serde(default)]
-    note: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct UpdateRunParams {
+        #[serde(default)]
+        mode: Option<String>,
+        #[serde(default)]
+        note: Option<String>,
+    }
 }
 
 pub async fn handle_run(
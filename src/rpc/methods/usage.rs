@@ -2,19 +2,25 @@ use serde::Deserialize;
 use serde_json::{Value, json};
 
 use crate::{
-    application::state::SharedState,
+    application::{
+        cost_budget::{self, DEFAULT_PRICE_PER_1K_TOKENS_USD},
+        state::SharedState,
+    },
     rpc::{
         dispatcher::map_domain_error,
         methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
     storage::now_unix_ms,
 };
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct UsageCostParams {
-    #[serde(default)]
-    period_days: Option<u32>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct UsageCostParams {
+        #[serde(default)]
+        period_days: Option<u32>,
+    }
 }
 
 pub async fn handle_status(
@@ -31,11 +37,8 @@ pub async fn handle_status(
         .await
         .map_err(map_domain_error)?;
     let agent_runs = state.count_agent_runs().await.map_err(map_domain_error)?;
-    let log_entries = state
-        .list_config_entries("logs/", Some(5_000))
-        .await
-        .map_err(map_domain_error)?
-        .len();
+    let log_entries = state.count_gateway_logs().await.map_err(map_domain_error)?;
+    let budgets = cost_budget::status_payload(state).await?;
 
     Ok(json!({
         "ts": now_unix_ms(),
@@ -47,7 +50,8 @@ pub async fn handle_status(
             "chatMessages": chat_messages,
             "agentRuns": agent_runs,
             "logEntries": log_entries,
-        }
+        },
+        "budgets": budgets,
     }))
 }
 
@@ -65,7 +69,7 @@ pub async fn handle_cost(
     let agent_runs = state.count_agent_runs().await.map_err(map_domain_error)? as f64;
 
     let estimated_tokens = (chat_messages * 350.0) + (agent_runs * 500.0);
-    let estimated_cost_usd = (estimated_tokens / 1_000.0) * 0.0025;
+    let estimated_cost_usd = (estimated_tokens / 1_000.0) * DEFAULT_PRICE_PER_1K_TOKENS_USD;
 
     Ok(json!({
         "periodDays": period_days,
@@ -75,7 +79,7 @@ pub async fn handle_cost(
         "assumptions": {
             "avgChatTokens": 350,
             "avgAgentTokens": 500,
-            "pricePer1kTokensUsd": 0.0025,
+            "pricePer1kTokensUsd": DEFAULT_PRICE_PER_1K_TOKENS_USD,
         }
     }))
 }
@@ -6,18 +6,21 @@ use crate::{
     rpc::{
         dispatcher::map_domain_error,
         methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
 };
 
 const VOICEWAKE_CONFIG_KEY: &str = "runtime/voicewake/config";
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct VoicewakeSetParams {
-    #[serde(default)]
-    enabled: Option<bool>,
-    #[serde(default)]
-    phrase: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct VoicewakeSetParams {
+        #[serde(default)]
+        enabled: Option<bool>,
+        #[serde(default)]
+        phrase: Option<String>,
+    }
 }
 
 pub async fn handle_get(
@@ -1,239 +1,683 @@
-use serde::{Deserialize, Serialize};
-use serde_json::{Value, json};
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
 
 use crate::{
-    application::state::SharedState,
+    application::{config::normalize_hooks_path, state::SharedState},
+    domain::models::WizardSession,
+    interfaces::setup::{SETUP_STATE_KEY, generate_token, toml_string, write_config_file},
+    protocol::{ERROR_INVALID_REQUEST, ErrorShape},
     rpc::{
+        SessionContext,
         dispatcher::map_domain_error,
         methods::{parse_optional_params, parse_required_params},
+        schema::rpc_params,
     },
     storage::now_unix_ms,
 };
 
-const WIZARD_PREFIX: &str = "wizard/session/";
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct WizardSession {
-    id: String,
-    status: String,
-    goal: String,
-    step_index: usize,
-    steps: Vec<String>,
-    created_at_ms: u64,
-    updated_at_ms: u64,
-    last_input: Option<String>,
-    cancel_reason: Option<String>,
+const DEFAULT_KIND: &str = "setup";
+const STATUS_ACTIVE: &str = "active";
+const STATUS_COMPLETED: &str = "completed";
+const STATUS_CANCELLED: &str = "cancelled";
+const REDACTED: &str = "***";
+const MIN_PASSWORD_LEN: usize = 8;
+
+struct WizardField {
+    name: &'static str,
+    kind: &'static str,
+    required: bool,
+    secret: bool,
+    choices: &'static [&'static str],
+    description: &'static str,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct WizardStartParams {
-    #[serde(default)]
-    id: Option<String>,
-    #[serde(default)]
-    goal: Option<String>,
-    #[serde(default)]
-    prompt: Option<String>,
+struct WizardStep {
+    id: &'static str,
+    title: &'static str,
+    fields: &'static [WizardField],
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct WizardNextParams {
-    id: String,
-    #[serde(default)]
-    input: Option<String>,
+/// Initial gateway setup: collects auth, channel, and hooks settings, then writes a static
+/// config file on review.
+const SETUP_STEPS: &[WizardStep] = &[
+    WizardStep {
+        id: "auth",
+        title: "Gateway authentication",
+        fields: &[
+            WizardField {
+                name: "mode",
+                kind: "enum",
+                required: false,
+                secret: false,
+                choices: &["token", "password", "none"],
+                description: "How clients authenticate; defaults to token.",
+            },
+            WizardField {
+                name: "token",
+                kind: "string",
+                required: false,
+                secret: true,
+                choices: &[],
+                description: "Gateway token for token mode; generated when omitted.",
+            },
+            WizardField {
+                name: "password",
+                kind: "string",
+                required: false,
+                secret: true,
+                choices: &[],
+                description: "Gateway password for password mode, at least 8 characters.",
+            },
+        ],
+    },
+    WizardStep {
+        id: "channels",
+        title: "Channels",
+        fields: &[
+            WizardField {
+                name: "telegramBotToken",
+                kind: "string",
+                required: false,
+                secret: true,
+                choices: &[],
+                description: "Telegram bot token; requires telegramWebhookSecret.",
+            },
+            WizardField {
+                name: "telegramWebhookSecret",
+                kind: "string",
+                required: false,
+                secret: true,
+                choices: &[],
+                description: "Secret Telegram sends with each webhook delivery.",
+            },
+            WizardField {
+                name: "discordWebhookToken",
+                kind: "string",
+                required: false,
+                secret: true,
+                choices: &[],
+                description: "Token accepted on the Discord webhook.",
+            },
+            WizardField {
+                name: "slackWebhookToken",
+                kind: "string",
+                required: false,
+                secret: true,
+                choices: &[],
+                description: "Token accepted on the Slack webhook.",
+            },
+        ],
+    },
+    WizardStep {
+        id: "hooks",
+        title: "Webhook hooks",
+        fields: &[
+            WizardField {
+                name: "enabled",
+                kind: "boolean",
+                required: false,
+                secret: false,
+                choices: &[],
+                description: "Serve inbound hooks; defaults to true.",
+            },
+            WizardField {
+                name: "token",
+                kind: "string",
+                required: false,
+                secret: true,
+                choices: &[],
+                description: "Hooks token; generated when omitted.",
+            },
+            WizardField {
+                name: "path",
+                kind: "string",
+                required: false,
+                secret: false,
+                choices: &[],
+                description: "Base path for hooks; defaults to /hooks.",
+            },
+        ],
+    },
+    WizardStep {
+        id: "review",
+        title: "Review and write config",
+        fields: &[WizardField {
+            name: "confirm",
+            kind: "boolean",
+            required: true,
+            secret: false,
+            choices: &[],
+            description: "Write the collected settings to the config file.",
+        }],
+    },
+];
+
+const WIZARD_KINDS: &[(&str, &[WizardStep])] = &[(DEFAULT_KIND, SETUP_STEPS)];
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct WizardStartParams {
+        #[serde(default)]
+        kind: Option<String>,
+        #[serde(default)]
+        id: Option<String>,
+        /// Cancels the active session of this kind instead of resuming it.
+        #[serde(default)]
+        restart: bool,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct WizardCancelParams {
-    id: String,
-    #[serde(default)]
-    reason: Option<String>,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct WizardNextParams {
+        id: String,
+        #[serde(default)]
+        input: Option<Value>,
+        /// Returns to the previous step instead of submitting input.
+        #[serde(default)]
+        back: bool,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct WizardStatusParams {
-    id: String,
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct WizardCancelParams {
+        id: String,
+        #[serde(default)]
+        reason: Option<String>,
+    }
+}
+
+rpc_params! {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(crate) struct WizardStatusParams {
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        kind: Option<String>,
+    }
 }
 
 pub async fn handle_start(
     state: &SharedState,
+    session: &SessionContext,
     params: Option<&Value>,
-) -> Result<Value, crate::protocol::ErrorShape> {
-    let parsed: WizardStartParams = parse_required_params("wizard.start", params)?;
-    let goal = parsed
-        .goal
-        .or(parsed.prompt)
+) -> Result<Value, ErrorShape> {
+    let parsed: WizardStartParams = parse_optional_params("wizard.start", params)?;
+    let kind = parsed
+        .kind
         .and_then(trim_non_empty)
-        .ok_or_else(|| {
-            crate::protocol::ErrorShape::new(
-                crate::protocol::ERROR_INVALID_REQUEST,
-                "invalid wizard.start params: goal is required",
-            )
-        })?;
+        .unwrap_or_else(|| DEFAULT_KIND.to_owned());
+    let steps = wizard_steps(&kind)?;
+    let id = parsed.id.and_then(trim_non_empty);
 
-    let now = now_unix_ms();
-    let id = parsed
-        .id
-        .and_then(trim_non_empty)
-        .unwrap_or_else(|| format!("wizard-{}", uuid::Uuid::new_v4()));
+    let existing = match &id {
+        Some(id) => state
+            .get_wizard_session(id)
+            .await
+            .map_err(map_domain_error)?,
+        None => state
+            .latest_wizard_session(&kind)
+            .await
+            .map_err(map_domain_error)?
+            .filter(|existing| existing.status == STATUS_ACTIVE),
+    };
+    if let Some(mut existing) = existing {
+        if existing.kind != kind {
+            return Err(invalid(format!(
+                "wizard session {} is a {} wizard",
+                existing.id, existing.kind
+            )));
+        }
+        if existing.status == STATUS_ACTIVE && !parsed.restart {
+            let mut payload = wizard_response(&existing, steps);
+            payload["resumed"] = Value::Bool(true);
+            return Ok(payload);
+        }
+        if id.is_some() {
+            return Err(invalid(format!(
+                "wizard session {} already exists",
+                existing.id
+            )));
+        }
+        existing.status = STATUS_CANCELLED.to_owned();
+        existing.cancel_reason = Some("restarted".to_owned());
+        existing.updated_at_ms = now_unix_ms();
+        state
+            .upsert_wizard_session(&existing)
+            .await
+            .map_err(map_domain_error)?;
+    }
 
-    let session = WizardSession {
-        id: id.clone(),
-        status: "active".to_owned(),
-        goal,
+    let now = now_unix_ms();
+    let wizard = WizardSession {
+        id: id.unwrap_or_else(|| format!("wizard-{}", uuid::Uuid::new_v4())),
+        kind,
+        status: STATUS_ACTIVE.to_owned(),
         step_index: 0,
-        steps: vec![
-            "collect-requirements".to_owned(),
-            "validate-plan".to_owned(),
-            "apply-changes".to_owned(),
-            "verify-results".to_owned(),
-        ],
+        answers: Value::Object(Map::new()),
+        result: None,
+        started_by: Some(session.client_id.clone()),
+        cancel_reason: None,
         created_at_ms: now,
         updated_at_ms: now,
-        last_input: None,
-        cancel_reason: None,
+        completed_at_ms: None,
     };
+    state
+        .upsert_wizard_session(&wizard)
+        .await
+        .map_err(map_domain_error)?;
 
-    persist_wizard(state, &session).await?;
-    Ok(wizard_response(&session))
+    let mut payload = wizard_response(&wizard, steps);
+    payload["resumed"] = Value::Bool(false);
+    Ok(payload)
 }
 
-pub async fn handle_next(
-    state: &SharedState,
-    params: Option<&Value>,
-) -> Result<Value, crate::protocol::ErrorShape> {
+pub async fn handle_next(state: &SharedState, params: Option<&Value>) -> Result<Value, ErrorShape> {
     let parsed: WizardNextParams = parse_required_params("wizard.next", params)?;
-    let id = trim_non_empty(parsed.id).ok_or_else(|| {
-        crate::protocol::ErrorShape::new(
-            crate::protocol::ERROR_INVALID_REQUEST,
-            "invalid wizard.next params: id is required",
-        )
-    })?;
-
-    let mut session = load_wizard(state, &id).await?;
-    if session.status != "active" {
-        return Err(crate::protocol::ErrorShape::new(
-            crate::protocol::ERROR_INVALID_REQUEST,
-            format!("wizard session is not active: {}", session.status),
-        ));
+    let id = trim_non_empty(parsed.id)
+        .ok_or_else(|| invalid("invalid wizard.next params: id is required"))?;
+
+    let mut wizard = load_active_wizard(state, &id).await?;
+    let steps = wizard_steps(&wizard.kind)?;
+
+    if parsed.back {
+        wizard.step_index = wizard.step_index.saturating_sub(1);
+        wizard.updated_at_ms = now_unix_ms();
+        state
+            .upsert_wizard_session(&wizard)
+            .await
+            .map_err(map_domain_error)?;
+        return Ok(wizard_response(&wizard, steps));
     }
 
-    if session.step_index + 1 >= session.steps.len() {
-        session.status = "completed".to_owned();
+    let step = steps
+        .get(wizard.step_index)
+        .ok_or_else(|| invalid(format!("wizard session {id} has no remaining steps")))?;
+    let input = match parsed.input {
+        None | Some(Value::Null) => Map::new(),
+        Some(Value::Object(input)) => input,
+        Some(_) => {
+            return Err(invalid(
+                "invalid wizard.next params: input must be an object",
+            ));
+        }
+    };
+    let answer =
+        validate_step(step, &input).map_err(|error| invalid(format!("{}: {error}", step.id)))?;
+    if let Value::Object(answers) = &mut wizard.answers {
+        answers.insert(step.id.to_owned(), answer);
     } else {
-        session.step_index += 1;
+        wizard.answers = json!({ step.id: answer });
     }
 
-    session.last_input = parsed.input.and_then(trim_non_empty);
-    session.updated_at_ms = now_unix_ms();
-    persist_wizard(state, &session).await?;
+    let now = now_unix_ms();
+    wizard.updated_at_ms = now;
+    let mut config_toml = None;
+    if wizard.step_index + 1 >= steps.len() {
+        let (content, result) = complete_setup(state, &wizard.answers).await?;
+        config_toml = Some(content);
+        wizard.result = Some(result);
+        wizard.status = STATUS_COMPLETED.to_owned();
+        wizard.completed_at_ms = Some(now);
+    }
+    wizard.step_index = (wizard.step_index + 1).min(steps.len());
+    state
+        .upsert_wizard_session(&wizard)
+        .await
+        .map_err(map_domain_error)?;
 
-    Ok(wizard_response(&session))
+    let mut payload = wizard_response(&wizard, steps);
+    if let Some(config_toml) = config_toml {
+        // Returned once so generated credentials can be copied; never persisted with the session.
+        payload["configToml"] = Value::String(config_toml);
+    }
+    Ok(payload)
 }
 
 pub async fn handle_cancel(
     state: &SharedState,
     params: Option<&Value>,
-) -> Result<Value, crate::protocol::ErrorShape> {
+) -> Result<Value, ErrorShape> {
     let parsed: WizardCancelParams = parse_required_params("wizard.cancel", params)?;
-    let id = trim_non_empty(parsed.id).ok_or_else(|| {
-        crate::protocol::ErrorShape::new(
-            crate::protocol::ERROR_INVALID_REQUEST,
-            "invalid wizard.cancel params: id is required",
-        )
-    })?;
-
-    let mut session = load_wizard(state, &id).await?;
-    session.status = "cancelled".to_owned();
-    session.cancel_reason = parsed.reason.and_then(trim_non_empty);
-    session.updated_at_ms = now_unix_ms();
-    persist_wizard(state, &session).await?;
-
-    Ok(wizard_response(&session))
+    let id = trim_non_empty(parsed.id)
+        .ok_or_else(|| invalid("invalid wizard.cancel params: id is required"))?;
+
+    let mut wizard = load_active_wizard(state, &id).await?;
+    wizard.status = STATUS_CANCELLED.to_owned();
+    wizard.cancel_reason = parsed.reason.and_then(trim_non_empty);
+    wizard.updated_at_ms = now_unix_ms();
+    state
+        .upsert_wizard_session(&wizard)
+        .await
+        .map_err(map_domain_error)?;
+
+    Ok(wizard_response(&wizard, wizard_steps(&wizard.kind)?))
 }
 
 pub async fn handle_status(
     state: &SharedState,
     params: Option<&Value>,
-) -> Result<Value, crate::protocol::ErrorShape> {
+) -> Result<Value, ErrorShape> {
     let parsed: WizardStatusParams = parse_optional_params("wizard.status", params)?;
-    let id = trim_non_empty(parsed.id).ok_or_else(|| {
-        crate::protocol::ErrorShape::new(
-            crate::protocol::ERROR_INVALID_REQUEST,
-            "invalid wizard.status params: id is required",
-        )
-    })?;
-
-    let session = load_wizard(state, &id).await?;
-    Ok(wizard_response(&session))
+    let wizard = match parsed.id.and_then(trim_non_empty) {
+        Some(id) => state
+            .get_wizard_session(&id)
+            .await
+            .map_err(map_domain_error)?
+            .ok_or_else(|| invalid(format!("wizard session not found: {id}")))?,
+        None => {
+            let kind = parsed
+                .kind
+                .and_then(trim_non_empty)
+                .unwrap_or_else(|| DEFAULT_KIND.to_owned());
+            wizard_steps(&kind)?;
+            state
+                .latest_wizard_session(&kind)
+                .await
+                .map_err(map_domain_error)?
+                .ok_or_else(|| invalid(format!("no {kind} wizard session found")))?
+        }
+    };
+
+    Ok(wizard_response(&wizard, wizard_steps(&wizard.kind)?))
 }
 
-async fn persist_wizard(
-    state: &SharedState,
-    session: &WizardSession,
-) -> Result<(), crate::protocol::ErrorShape> {
-    let value = serde_json::to_value(session).map_err(|error| {
-        crate::protocol::ErrorShape::new(
-            crate::protocol::ERROR_UNAVAILABLE,
-            format!("failed to serialize wizard session: {error}"),
-        )
-    })?;
-
-    let key = format!("{WIZARD_PREFIX}{}", session.id);
-    let _ = state
-        .set_config_entry_value(&key, &value)
+async fn load_active_wizard(state: &SharedState, id: &str) -> Result<WizardSession, ErrorShape> {
+    let wizard = state
+        .get_wizard_session(id)
         .await
-        .map_err(map_domain_error)?;
-    Ok(())
+        .map_err(map_domain_error)?
+        .ok_or_else(|| invalid(format!("wizard session not found: {id}")))?;
+    if wizard.status != STATUS_ACTIVE {
+        return Err(invalid(format!(
+            "wizard session is not active: {}",
+            wizard.status
+        )));
+    }
+    Ok(wizard)
+}
+
+fn wizard_steps(kind: &str) -> Result<&'static [WizardStep], ErrorShape> {
+    WIZARD_KINDS
+        .iter()
+        .find(|(name, _)| *name == kind)
+        .map(|(_, steps)| *steps)
+        .ok_or_else(|| invalid(format!("unknown wizard kind: {kind}")))
 }
 
-async fn load_wizard(
+/// Checks `input` against the step's fields and returns the answer to store, with defaults and
+/// generated credentials filled in.
+fn validate_step(step: &WizardStep, input: &Map<String, Value>) -> Result<Value, String> {
+    if let Some(unknown) = input
+        .keys()
+        .find(|key| !step.fields.iter().any(|field| field.name == key.as_str()))
+    {
+        return Err(format!("unknown field {unknown}"));
+    }
+
+    match step.id {
+        "auth" => {
+            let mode = string_field(input, "mode")?.unwrap_or_else(|| "token".to_owned());
+            match mode.as_str() {
+                "token" => {
+                    let token =
+                        string_field(input, "token")?.unwrap_or_else(|| generate_token("gwt"));
+                    Ok(json!({ "mode": mode, "token": token }))
+                }
+                "password" => {
+                    let password = string_field(input, "password")?
+                        .ok_or_else(|| "password is required for password mode".to_owned())?;
+                    if password.chars().count() < MIN_PASSWORD_LEN {
+                        return Err(format!(
+                            "password must be at least {MIN_PASSWORD_LEN} characters"
+                        ));
+                    }
+                    Ok(json!({ "mode": mode, "password": password }))
+                }
+                "none" => Ok(json!({ "mode": mode })),
+                other => Err(format!("unsupported auth mode {other}")),
+            }
+        }
+        "channels" => {
+            let mut answer = Map::new();
+            for field in step.fields {
+                if let Some(value) = string_field(input, field.name)? {
+                    answer.insert(field.name.to_owned(), Value::String(value));
+                }
+            }
+            if answer.contains_key("telegramBotToken")
+                != answer.contains_key("telegramWebhookSecret")
+            {
+                return Err(
+                    "telegramBotToken and telegramWebhookSecret must be set together".to_owned(),
+                );
+            }
+            Ok(Value::Object(answer))
+        }
+        "hooks" => {
+            if !bool_field(input, "enabled")?.unwrap_or(true) {
+                return Ok(json!({ "enabled": false }));
+            }
+            let token = string_field(input, "token")?.unwrap_or_else(|| generate_token("hkt"));
+            let path = normalize_hooks_path(string_field(input, "path")?.unwrap_or_default())?;
+            Ok(json!({ "enabled": true, "token": token, "path": path }))
+        }
+        "review" => {
+            if bool_field(input, "confirm")? != Some(true) {
+                return Err("confirm must be true; use back to revise earlier steps".to_owned());
+            }
+            Ok(json!({ "confirm": true }))
+        }
+        other => Err(format!("unknown step {other}")),
+    }
+}
+
+fn string_field(input: &Map<String, Value>, name: &str) -> Result<Option<String>, String> {
+    match input.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(trim_non_empty(value.clone())),
+        Some(_) => Err(format!("{name} must be a string")),
+    }
+}
+
+fn bool_field(input: &Map<String, Value>, name: &str) -> Result<Option<bool>, String> {
+    match input.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bool(value)) => Ok(Some(*value)),
+        Some(_) => Err(format!("{name} must be a boolean")),
+    }
+}
+
+/// Renders the collected answers as a static config file and writes it when the config path
+/// is known and still free, marking `/setup` as done the same way the HTTP bootstrap does.
+async fn complete_setup(
     state: &SharedState,
-    id: &str,
-) -> Result<WizardSession, crate::protocol::ErrorShape> {
-    let key = format!("{WIZARD_PREFIX}{id}");
-    let raw = state
-        .get_config_entry_value(&key)
-        .await
-        .map_err(map_domain_error)?
-        .ok_or_else(|| {
-            crate::protocol::ErrorShape::new(
-                crate::protocol::ERROR_INVALID_REQUEST,
-                format!("wizard session not found: {id}"),
+    answers: &Value,
+) -> Result<(String, Value), ErrorShape> {
+    let content = setup_config_toml(&state.config().db_path, answers);
+    let config_path = state.config().config_path.clone();
+    let config_exists = config_path.as_deref().is_some_and(Path::exists);
+
+    let mut written = false;
+    if let Some(path) = config_path.as_deref().filter(|_| !config_exists) {
+        write_config_file(path, &content)
+            .map_err(|error| ErrorShape::new(crate::protocol::ERROR_UNAVAILABLE, error))?;
+        written = true;
+        state
+            .set_config_entry_value(
+                SETUP_STATE_KEY,
+                &json!({
+                    "completedAtMs": now_unix_ms(),
+                    "configPath": path.display().to_string(),
+                }),
             )
-        })?;
+            .await
+            .map_err(map_domain_error)?;
+    }
 
-    serde_json::from_value::<WizardSession>(raw).map_err(|error| {
-        crate::protocol::ErrorShape::new(
-            crate::protocol::ERROR_UNAVAILABLE,
-            format!("failed to decode wizard session: {error}"),
-        )
-    })
+    Ok((
+        content,
+        json!({
+            "configPath": config_path.as_deref().map(|path| path.display().to_string()),
+            "configExists": config_exists,
+            "written": written,
+            "restartRequired": written,
+        }),
+    ))
+}
+
+fn setup_config_toml(db_path: &Path, answers: &Value) -> String {
+    let answer = |step: &str, field: &str| answers.get(step).and_then(|step| step.get(field));
+    let mut lines = vec![
+        "# Reclaw Core static runtime configuration".to_owned(),
+        "# Generated by the setup wizard.".to_owned(),
+        String::new(),
+        "host = \"127.0.0.1\"".to_owned(),
+        "port = 18789".to_owned(),
+        format!("dbPath = {}", toml_string(&db_path.display().to_string())),
+        String::new(),
+    ];
+
+    match answer("auth", "mode").and_then(Value::as_str) {
+        Some("password") => {
+            if let Some(password) = answer("auth", "password").and_then(Value::as_str) {
+                lines.push(format!("gatewayPassword = {}", toml_string(password)));
+            }
+        }
+        Some("none") => {
+            lines.push("# No gateway credential: only use on trusted networks.".to_owned())
+        }
+        _ => {
+            if let Some(token) = answer("auth", "token").and_then(Value::as_str) {
+                lines.push(format!("gatewayToken = {}", toml_string(token)));
+            }
+        }
+    }
+
+    if let Some(Value::Object(channels)) = answers.get("channels") {
+        if !channels.is_empty() {
+            lines.push(String::new());
+        }
+        for field in [
+            "telegramBotToken",
+            "telegramWebhookSecret",
+            "discordWebhookToken",
+            "slackWebhookToken",
+        ] {
+            if let Some(value) = channels.get(field).and_then(Value::as_str) {
+                lines.push(format!("{field} = {}", toml_string(value)));
+            }
+        }
+    }
+
+    lines.push(String::new());
+    if answer("hooks", "enabled").and_then(Value::as_bool) == Some(true) {
+        lines.push("hooksEnabled = true".to_owned());
+        if let Some(token) = answer("hooks", "token").and_then(Value::as_str) {
+            lines.push(format!("hooksToken = {}", toml_string(token)));
+        }
+        if let Some(path) = answer("hooks", "path").and_then(Value::as_str) {
+            lines.push(format!("hooksPath = {}", toml_string(path)));
+        }
+    } else {
+        lines.push("hooksEnabled = false".to_owned());
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
 }
 
-fn wizard_response(session: &WizardSession) -> Value {
-    let current_step = session
-        .steps
-        .get(session.step_index)
-        .cloned()
-        .unwrap_or_else(|| "done".to_owned());
+fn wizard_response(wizard: &WizardSession, steps: &[WizardStep]) -> Value {
+    let step = (wizard.status == STATUS_ACTIVE)
+        .then(|| steps.get(wizard.step_index))
+        .flatten()
+        .map(|step| {
+            json!({
+                "id": step.id,
+                "title": step.title,
+                "fields": step.fields.iter().map(field_payload).collect::<Vec<_>>(),
+                "values": redacted_answer(step, wizard.answers.get(step.id)),
+            })
+        });
+
+    let answers = steps
+        .iter()
+        .filter_map(|step| {
+            wizard
+                .answers
+                .get(step.id)
+                .map(|answer| (step.id.to_owned(), redacted_answer(step, Some(answer))))
+        })
+        .collect::<Map<_, _>>();
 
     json!({
-        "id": session.id,
-        "status": session.status,
-        "goal": session.goal,
-        "stepIndex": session.step_index,
-        "currentStep": current_step,
-        "steps": session.steps,
-        "updatedAtMs": session.updated_at_ms,
-        "cancelReason": session.cancel_reason,
+        "id": wizard.id,
+        "kind": wizard.kind,
+        "status": wizard.status,
+        "stepIndex": wizard.step_index,
+        "stepCount": steps.len(),
+        "steps": steps.iter().map(|step| step.id).collect::<Vec<_>>(),
+        "step": step,
+        "answers": answers,
+        "result": wizard.result,
+        "startedBy": wizard.started_by,
+        "cancelReason": wizard.cancel_reason,
+        "createdAtMs": wizard.created_at_ms,
+        "updatedAtMs": wizard.updated_at_ms,
+        "completedAtMs": wizard.completed_at_ms,
     })
 }
 
+fn field_payload(field: &WizardField) -> Value {
+    let mut payload = json!({
+        "name": field.name,
+        "type": field.kind,
+        "required": field.required,
+        "secret": field.secret,
+        "description": field.description,
+    });
+    if !field.choices.is_empty() {
+        payload["choices"] = json!(field.choices);
+    }
+    payload
+}
+
+/// A stored answer with secret fields masked; `null` when the step has no answer yet.
+fn redacted_answer(step: &WizardStep, answer: Option<&Value>) -> Value {
+    let Some(Value::Object(answer)) = answer else {
+        return Value::Null;
+    };
+    Value::Object(
+        answer
+            .iter()
+            .map(|(name, value)| {
+                let secret = step
+                    .fields
+                    .iter()
+                    .any(|field| field.secret && field.name == name.as_str());
+                let value = if secret && !value.is_null() {
+                    Value::String(REDACTED.to_owned())
+                } else {
+                    value.clone()
+                };
+                (name.clone(), value)
+            })
+            .collect(),
+    )
+}
+
+fn invalid(message: impl Into<String>) -> ErrorShape {
+    ErrorShape::new(ERROR_INVALID_REQUEST, message)
+}
+
 fn trim_non_empty(value: String) -> Option<String> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
@@ -245,23 +689,83 @@ fn trim_non_empty(value: String) -> Option<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::wizard_response;
+    use std::path::Path;
+
+    use serde_json::{Map, Value, json};
+
+    use super::{SETUP_STEPS, WizardSession, setup_config_toml, validate_step, wizard_response};
+
+    fn input(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        }
+    }
 
     #[test]
-    fn wizard_response_returns_current_step() {
-        let session = super::WizardSession {
+    fn wizard_response_returns_current_step_and_redacts_secrets() {
+        let session = WizardSession {
             id: "w1".to_owned(),
+            kind: "setup".to_owned(),
             status: "active".to_owned(),
-            goal: "test".to_owned(),
             step_index: 1,
-            steps: vec!["a".to_owned(), "b".to_owned()],
+            answers: json!({ "auth": { "mode": "token", "token": "gwt_secret" } }),
+            result: None,
+            started_by: None,
+            cancel_reason: None,
             created_at_ms: 1,
             updated_at_ms: 2,
-            last_input: None,
-            cancel_reason: None,
+            completed_at_ms: None,
         };
 
-        let payload = wizard_response(&session);
-        assert_eq!(payload["currentStep"], "b");
+        let payload = wizard_response(&session, SETUP_STEPS);
+        assert_eq!(payload["step"]["id"], "channels");
+        assert_eq!(payload["stepCount"], 4);
+        assert_eq!(payload["answers"]["auth"]["mode"], "token");
+        assert_eq!(payload["answers"]["auth"]["token"], "***");
+    }
+
+    #[test]
+    fn setup_steps_validate_and_fill_defaults() {
+        let auth = validate_step(&SETUP_STEPS[0], &input(json!({}))).expect("auth defaults");
+        assert_eq!(auth["mode"], "token");
+        assert!(
+            auth["token"]
+                .as_str()
+                .is_some_and(|token| token.starts_with("gwt_"))
+        );
+        assert!(validate_step(&SETUP_STEPS[0], &input(json!({ "mode": "password" }))).is_err());
+        assert!(validate_step(&SETUP_STEPS[0], &input(json!({ "mode": "other" }))).is_err());
+        assert!(validate_step(&SETUP_STEPS[0], &input(json!({ "extra": true }))).is_err());
+
+        assert!(
+            validate_step(
+                &SETUP_STEPS[1],
+                &input(json!({ "telegramBotToken": "123:abc" }))
+            )
+            .is_err()
+        );
+
+        let hooks = validate_step(&SETUP_STEPS[2], &input(json!({ "path": "events/" })))
+            .expect("hooks should validate");
+        assert_eq!(hooks["path"], "/events");
+        assert!(validate_step(&SETUP_STEPS[3], &input(json!({ "confirm": false }))).is_err());
+    }
+
+    #[test]
+    fn setup_config_toml_renders_collected_answers() {
+        let content = setup_config_toml(
+            Path::new("/var/lib/reclaw/state.db"),
+            &json!({
+                "auth": { "mode": "password", "password": "correct horse" },
+                "channels": { "slackWebhookToken": "slack-token" },
+                "hooks": { "enabled": false },
+            }),
+        );
+        let parsed: toml::Value = toml::from_str(&content).expect("config should parse");
+        assert_eq!(parsed["gatewayPassword"].as_str(), Some("correct horse"));
+        assert_eq!(parsed["slackWebhookToken"].as_str(), Some("slack-token"));
+        assert_eq!(parsed["hooksEnabled"].as_bool(), Some(false));
+        assert!(parsed.get("gatewayToken").is_none());
     }
 }
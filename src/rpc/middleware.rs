@@ -0,0 +1,110 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use crate::{
+    application::state::SharedState,
+    protocol::{ErrorShape, RequestFrame, ResponseFrame},
+};
+
+use super::SessionContext;
+
+pub type DispatchHookFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Extension point for embedders that wrap RPC dispatch.
+///
+/// Hooks run in ascending `priority` order before dispatch (ties keep
+/// registration order) and in reverse order after dispatch. A pre-dispatch
+/// error short-circuits: later pre-dispatch hooks and the method handler are
+/// skipped and the error becomes the response. Post-dispatch hooks still run
+/// for every response, including short-circuited ones.
+pub trait DispatchHook: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Runs before policy checks, so hooks may resolve tenants, grant scopes,
+    /// or rewrite the method and params.
+    fn pre_dispatch<'a>(
+        &'a self,
+        _state: &'a SharedState,
+        _session: &'a mut SessionContext,
+        _request: &'a mut RequestFrame,
+    ) -> DispatchHookFuture<'a, Result<(), ErrorShape>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn post_dispatch<'a>(
+        &'a self,
+        _state: &'a SharedState,
+        _session: &'a SessionContext,
+        _request: &'a RequestFrame,
+        _response: &'a mut ResponseFrame,
+    ) -> DispatchHookFuture<'a, ()> {
+        Box::pin(async {})
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct DispatchHookRegistry {
+    hooks: Vec<Arc<dyn DispatchHook>>,
+}
+
+impl DispatchHookRegistry {
+    pub fn register(&mut self, hook: Arc<dyn DispatchHook>) {
+        let priority = hook.priority();
+        let index = self
+            .hooks
+            .iter()
+            .position(|existing| existing.priority() > priority)
+            .unwrap_or(self.hooks.len());
+        self.hooks.insert(index, hook);
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        self.hooks
+            .iter()
+            .map(|hook| hook.name().to_owned())
+            .collect()
+    }
+
+    pub(crate) fn hooks(&self) -> &[Arc<dyn DispatchHook>] {
+        &self.hooks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{DispatchHook, DispatchHookRegistry};
+
+    struct Named(&'static str, i32);
+
+    impl DispatchHook for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn priority(&self) -> i32 {
+            self.1
+        }
+    }
+
+    #[test]
+    fn registry_orders_by_priority_then_registration() {
+        let mut registry = DispatchHookRegistry::default();
+        registry.register(Arc::new(Named("metrics", 10)));
+        registry.register(Arc::new(Named("auth", -10)));
+        registry.register(Arc::new(Named("tenant", 0)));
+        registry.register(Arc::new(Named("audit", 10)));
+
+        assert_eq!(registry.names(), vec!["auth", "tenant", "metrics", "audit"]);
+    }
+}
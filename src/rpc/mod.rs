@@ -1,6 +1,9 @@
+pub mod deadline;
 pub mod dispatcher;
 pub mod methods;
+pub mod middleware;
 pub mod policy;
+pub mod schema;
 
 #[derive(Debug, Clone)]
 pub struct SessionContext {
@@ -8,14 +8,112 @@ pub const WRITE_SCOPE: &str = "operator.write";
 pub const APPROVALS_SCOPE: &str = "operator.approvals";
 pub const PAIRING_SCOPE: &str = "operator.pairing";
 
-const NODE_ROLE_METHODS: &[&str] = &["node.invoke.result", "node.event", "skills.bins"];
+const NODE_ROLE_METHODS: &[&str] = &[
+    "node.invoke.result",
+    "node.event",
+    "node.metadata.update",
+    "node.latency.report",
+    "skills.bins",
+];
+/// Methods open to every connection role without an operator scope.
+const ANY_ROLE_METHODS: &[&str] = &[
+    "health",
+    "events.ack",
+    "events.subscribe",
+    "events.unsubscribe",
+];
 const CONTROL_PLANE_WRITE_METHODS: &[&str] = &["config.apply", "config.patch", "update.run"];
+/// Reads and exports that are rejected first while the server sheds load.
+const LOW_PRIORITY_METHODS: &[&str] = &[
+    "chat.history",
+    "chat.search",
+    "sessions.list",
+    "sessions.preview",
+    "logs.tail",
+    "usage.status",
+    "usage.cost",
+    "cron.runs",
+    "cron.runs.tail",
+    "privacy.export",
+    "privacy.audit.list",
+    "tools.calls.list",
+    "channels.directory.list",
+    "methods.describe",
+    "methods.schema",
+    "agent.replay",
+];
+/// Methods that wait on agents, nodes, or operators by design; their duration says nothing
+/// about server load.
+const WAITING_METHODS: &[&str] = &[
+    "agent",
+    "agent.wait",
+    "agent.replay",
+    "send",
+    "chat.send",
+    "exec.run",
+    "exec.approval.waitDecision",
+    "node.invoke",
+    "tools.call",
+    "cron.run",
+    "update.run",
+    "db.migrateTo",
+    "snapshot.publish",
+    "federation.pair",
+];
+/// Methods that start agent runs or other new work, turned away during maintenance and while a
+/// scheduled stop drains.
+const RUN_METHODS: &[&str] = &[
+    "agent",
+    "agent.retry",
+    "agent.replay",
+    "send",
+    "chat.send",
+    "wake",
+    "exec.run",
+    "tools.call",
+    "cron.run",
+];
+/// Read-scoped methods that still write state, so a standby rejects them.
+const READ_SCOPED_WRITE_METHODS: &[&str] = &[
+    "chat.markRead",
+    "approval.link.create",
+    "approval.link.resolve",
+];
 
 #[must_use]
 pub fn is_control_plane_write_method(method: &str) -> bool {
     CONTROL_PLANE_WRITE_METHODS.contains(&method)
 }
 
+#[must_use]
+pub fn is_low_priority_method(method: &str) -> bool {
+    LOW_PRIORITY_METHODS.contains(&method)
+}
+
+#[must_use]
+pub fn is_run_method(method: &str) -> bool {
+    RUN_METHODS.contains(&method)
+}
+
+#[must_use]
+pub fn is_waiting_method(method: &str) -> bool {
+    WAITING_METHODS.contains(&method)
+}
+
+/// Methods a read-only standby serves: reads plus the replication controls.
+#[must_use]
+pub fn is_standby_method(method: &str) -> bool {
+    matches!(
+        method,
+        "health"
+            | "replication.status"
+            | "replication.promote"
+            | "system.shutdown"
+            | "system.restart"
+    ) || (required_scope_for_method(method) == Some(READ_SCOPE)
+        && !READ_SCOPED_WRITE_METHODS.contains(&method))
+}
+
 #[must_use]
 pub fn default_operator_scopes() -> Vec<String> {
     vec![
@@ -27,6 +125,27 @@ pub fn default_operator_scopes() -> Vec<String> {
     ]
 }
 
+/// Connection role allowed to call `method`.
+#[must_use]
+pub fn required_role(method: &str) -> &'static str {
+    if ANY_ROLE_METHODS.contains(&method) {
+        "any"
+    } else if NODE_ROLE_METHODS.contains(&method) {
+        "node"
+    } else {
+        "operator"
+    }
+}
+
+/// Operator scope checked for `method`; `None` for any-role and node-role methods.
+#[must_use]
+pub fn required_scope(method: &str) -> Option<&'static str> {
+    if ANY_ROLE_METHODS.contains(&method) || NODE_ROLE_METHODS.contains(&method) {
+        return None;
+    }
+    Some(required_scope_for_method(method).unwrap_or(ADMIN_SCOPE))
+}
+
 pub fn authorize_session(session: &SessionContext, method: &str) -> Result<(), ErrorShape> {
     if method == "health" {
         return Ok(());
@@ -40,6 +159,10 @@ pub fn authorize_session(session: &SessionContext, method: &str) -> Result<(), E
         ));
     }
 
+    if ANY_ROLE_METHODS.contains(&method) {
+        return Ok(());
+    }
+
     if NODE_ROLE_METHODS.contains(&method) {
         if role != "node" {
             return Err(ErrorShape::new(
@@ -92,6 +215,8 @@ fn required_scope_for_method(method: &str) -> Option<&'static str> {
         "exec.approval.request" | "exec.approval.waitDecision" | "exec.approval.resolve" => {
             Some(APPROVALS_SCOPE)
         }
+        // The handlers also require the approvals or pairing scope of the linked request kind.
+        "approval.link.create" | "approval.link.get" | "approval.link.resolve" => Some(READ_SCOPE),
         "node.pair.request"
         | "node.pair.list"
         | "node.pair.approve"
@@ -103,11 +228,20 @@ fn required_scope_for_method(method: &str) -> Option<&'static str> {
         | "device.pair.remove"
         | "device.token.rotate"
         | "device.token.revoke"
+        | "device.pair.bulkApprove"
+        | "device.token.bulkRevoke"
         | "node.rename" => Some(PAIRING_SCOPE),
         "health"
+        | "methods.describe"
+        | "methods.schema"
         | "doctor.memory.status"
+        | "doctor.storage.slowQueries"
         | "logs.tail"
+        | "logs.redaction.test"
         | "channels.status"
+        | "channels.directory.list"
+        | "channels.outbound.queue"
+        | "identities.list"
         | "status"
         | "usage.status"
         | "usage.cost"
@@ -115,31 +249,89 @@ fn required_scope_for_method(method: &str) -> Option<&'static str> {
         | "tts.providers"
         | "models.list"
         | "tools.catalog"
+        | "tools.calls.list"
         | "agents.list"
         | "agent.identity.get"
         | "skills.status"
         | "voicewake.get"
         | "sessions.list"
+        | "sessions.tags.list"
         | "sessions.preview"
+        | "session.kv.get"
         | "cron.list"
         | "cron.status"
+        | "cron.describe"
         | "cron.runs"
+        | "cron.runs.tail"
+        | "cron.templates.list"
         | "system-presence"
         | "last-heartbeat"
         | "node.list"
         | "node.describe"
+        | "node.metadata.history"
+        | "node.affinity.list"
+        | "chat.markRead"
+        | "node.geofence.list"
+        | "node.invoke.pending"
+        | "node.file.status"
+        | "node.file.list"
         | "chat.history"
+        | "chat.search"
+        | "chat.deliveryStatus"
         | "config.get"
         | "talk.config"
         | "agents.files.list"
-        | "agents.files.get" => Some(READ_SCOPE),
-        "send" | "agent" | "agent.wait" | "wake" | "talk.mode" | "tts.enable" | "tts.disable"
-        | "tts.convert" | "tts.setProvider" | "voicewake.set" | "node.invoke" | "chat.send"
-        | "chat.abort" | "browser.request" => Some(WRITE_SCOPE),
-        "channels.logout" | "agents.create" | "agents.update" | "agents.delete"
-        | "skills.install" | "skills.update" | "cron.add" | "cron.update" | "cron.remove"
-        | "cron.run" | "sessions.patch" | "sessions.reset" | "sessions.delete"
-        | "sessions.compact" | "connect" | "set-heartbeats" | "system-event"
+        | "agents.files.get"
+        | "federation.peers.list"
+        | "replication.status" => Some(READ_SCOPE),
+        "send"
+        | "agent"
+        | "agent.wait"
+        | "agent.retry"
+        | "agent.replay"
+        | "wake"
+        | "talk.mode"
+        | "tts.enable"
+        | "tts.disable"
+        | "tts.convert"
+        | "tts.setProvider"
+        | "voicewake.set"
+        | "node.invoke"
+        | "node.invoke.cancel"
+        | "node.file.push"
+        | "node.file.pull"
+        | "node.file.cancel"
+        | "chat.send"
+        | "chat.abort"
+        | "chat.pin"
+        | "chat.unpin"
+        | "session.kv.set"
+        | "session.kv.delete"
+        | "browser.request"
+        | "tools.call"
+        | "chat.takeover.start"
+        | "chat.takeover.end"
+        | "chat.takeover.reply" => Some(WRITE_SCOPE),
+        "channels.logout"
+        | "agents.create"
+        | "agents.update"
+        | "agents.delete"
+        | "skills.install"
+        | "skills.update"
+        | "cron.add"
+        | "cron.update"
+        | "cron.remove"
+        | "cron.run"
+        | "cron.templates.set"
+        | "cron.templates.remove"
+        | "sessions.patch"
+        | "sessions.bulkPatch"
+        | "sessions.reset"
+        | "sessions.delete"
+        | "sessions.compact"
+        | "connect"
+        | "set-heartbeats"
+        | "system-event"
         | "agents.files.set" => Some(ADMIN_SCOPE),
         _ => {
             if method.starts_with("exec.approvals.")
@@ -159,7 +351,11 @@ fn required_scope_for_method(method: &str) -> Option<&'static str> {
 mod tests {
     use crate::rpc::SessionContext;
 
-    use super::{authorize_session, default_operator_scopes};
+    use super::{
+        LOW_PRIORITY_METHODS, READ_SCOPED_WRITE_METHODS, WAITING_METHODS, authorize_session,
+        default_operator_scopes,
+    };
+    use crate::rpc::methods::BASE_METHODS;
 
     #[test]
     fn operator_defaults_can_call_admin_method() {
@@ -187,4 +383,15 @@ mod tests {
         assert!(authorize_session(&session, "chat.send").is_err());
         assert!(authorize_session(&session, "node.event").is_ok());
     }
+
+    #[test]
+    fn load_shedding_lists_name_known_methods() {
+        for method in LOW_PRIORITY_METHODS
+            .iter()
+            .chain(WAITING_METHODS)
+            .chain(READ_SCOPED_WRITE_METHODS)
+        {
+            assert!(BASE_METHODS.contains(method), "{method} is not a method");
+        }
+    }
 }
@@ -0,0 +1,331 @@
+use serde_json::{Map, Value, json};
+
+/// One field of a params struct as written in its definition.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldSpec {
+    pub ident: &'static str,
+    pub ty: &'static str,
+    /// The field's attributes (`serde(...)`, `doc = "..."`), stringified.
+    pub attrs: &'static [&'static str],
+}
+
+/// Params struct whose JSON Schema is derived from its definition by [`rpc_params!`].
+pub trait ParamsSchema {
+    const ATTRS: &'static [&'static str];
+    const FIELDS: &'static [FieldSpec];
+
+    #[must_use]
+    fn schema() -> Value {
+        object_schema(Self::ATTRS, Self::FIELDS)
+    }
+}
+
+/// Defines a params struct and implements [`ParamsSchema`] for it from the same tokens, so the
+/// published schema cannot drift from what the handler deserializes.
+macro_rules! rpc_params {
+    (
+        $(#[$struct_meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$struct_meta])*
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $crate::rpc::schema::ParamsSchema for $name {
+            const ATTRS: &'static [&'static str] = &[$(stringify!($struct_meta)),*];
+            const FIELDS: &'static [$crate::rpc::schema::FieldSpec] = &[
+                $(
+                    $crate::rpc::schema::FieldSpec {
+                        ident: stringify!($field),
+                        ty: stringify!($ty),
+                        attrs: &[$(stringify!($field_meta)),*],
+                    },
+                )*
+            ];
+        }
+    };
+}
+pub(crate) use rpc_params;
+
+rpc_params! {
+    /// Methods that take no params; any object is accepted and ignored.
+    #[derive(Debug, Default, serde::Deserialize)]
+    pub struct NoParams {}
+}
+
+fn object_schema(attrs: &[&str], fields: &[FieldSpec]) -> Value {
+    let camel_case = attrs.iter().any(|attr| attr.contains("camelCase"));
+    let all_default = attrs
+        .iter()
+        .any(|attr| attr.starts_with("serde") && has_word(attr, "default"));
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for field in fields {
+        let serde = field
+            .attrs
+            .iter()
+            .filter(|attr| attr.starts_with("serde"))
+            .copied()
+            .collect::<Vec<_>>();
+        if serde.iter().any(|attr| has_word(attr, "flatten")) {
+            continue;
+        }
+        let name = serde
+            .iter()
+            .find_map(|attr| quoted_after(attr, "rename"))
+            .unwrap_or_else(|| {
+                if camel_case {
+                    to_camel_case(field.ident)
+                } else {
+                    field.ident.to_owned()
+                }
+            });
+        let ty = field.ty.replace(' ', "");
+        let optional = generic_base(&ty) == "Option";
+        let mut schema = type_schema(&ty);
+        if let Some(doc) = field.attrs.iter().find_map(|attr| doc_text(attr)) {
+            schema["description"] = json!(doc);
+        }
+        if !optional && !all_default && !serde.iter().any(|attr| has_word(attr, "default")) {
+            required.push(name.clone());
+        }
+        properties.insert(name, schema);
+    }
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": true,
+    })
+}
+
+/// JSON Schema for a Rust type as written in a struct definition. Types the mapping does not
+/// know (nested structs, enums) accept any value; their handler validates them.
+fn type_schema(ty: &str) -> Value {
+    let base = generic_base(ty);
+    let args = generic_args(ty);
+    match base {
+        "Option" => args
+            .first()
+            .map_or_else(|| json!({}), |inner| type_schema(inner)),
+        "String" | "str" => json!({ "type": "string" }),
+        "bool" => json!({ "type": "boolean" }),
+        "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize" => {
+            json!({ "type": "integer" })
+        }
+        "f32" | "f64" => json!({ "type": "number" }),
+        "Vec" | "BTreeSet" | "HashSet" => json!({
+            "type": "array",
+            "items": args.first().map_or_else(|| json!({}), |inner| type_schema(inner)),
+        }),
+        "Map" | "BTreeMap" | "HashMap" => json!({
+            "type": "object",
+            "additionalProperties": args.get(1).map_or_else(|| json!({}), |inner| type_schema(inner)),
+        }),
+        _ => json!({}),
+    }
+}
+
+/// `Option` for `std::option::Option<Vec<String>>`.
+fn generic_base(ty: &str) -> &str {
+    let path = ty.split('<').next().unwrap_or(ty);
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// Top-level generic arguments: `["String", "Vec<u64>"]` for `BTreeMap<String,Vec<u64>>`.
+fn generic_args(ty: &str) -> Vec<&str> {
+    let (Some(start), Some(end)) = (ty.find('<'), ty.rfind('>')) else {
+        return Vec::new();
+    };
+    let inner = &ty[start + 1..end];
+    let mut args = Vec::new();
+    let mut depth = 0_usize;
+    let mut from = 0;
+    for (index, ch) in inner.char_indices() {
+        match ch {
+            '<' => depth += 1,
+            '>' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                args.push(&inner[from..index]);
+                from = index + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(&inner[from..]);
+    args
+}
+
+fn to_camel_case(ident: &str) -> String {
+    let mut out = String::with_capacity(ident.len());
+    let mut upper = false;
+    for ch in ident.trim_start_matches("r#").chars() {
+        if ch == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(ch.to_uppercase());
+            upper = false;
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+fn has_word(attr: &str, word: &str) -> bool {
+    attr.split(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+        .any(|token| token == word)
+}
+
+/// The string literal following `key =` in an attribute, e.g. `type` for `rename = "type"`.
+fn quoted_after(attr: &str, key: &str) -> Option<String> {
+    let rest = &attr[attr.find(key)? + key.len()..];
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    Some(rest[..rest.find('"')?].to_owned())
+}
+
+/// The text of a `doc = r" ..."` attribute, as doc comments stringify.
+fn doc_text(attr: &str) -> Option<String> {
+    let rest = attr.strip_prefix("doc")?.trim_start().strip_prefix('=')?;
+    let rest = rest
+        .trim_start()
+        .trim_start_matches('r')
+        .trim_start_matches('#');
+    let rest = rest.strip_prefix('"')?;
+    let text = rest[..rest.rfind('"')?].trim();
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+/// Checks `params` against a schema from [`ParamsSchema::schema`]: required fields are present
+/// and every known field has the declared JSON type. Unknown fields pass, as serde ignores them.
+pub fn validate(schema: &Value, params: &Value) -> Result<(), String> {
+    let Some(params) = params.as_object() else {
+        return Err("params must be an object".to_owned());
+    };
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for name in required.iter().filter_map(Value::as_str) {
+            if params.get(name).is_none_or(Value::is_null) {
+                return Err(format!("{name} is required"));
+            }
+        }
+    }
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Ok(());
+    };
+    for (name, value) in params {
+        if let Some(property) = properties.get(name) {
+            check_type(name, property, value)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_type(path: &str, schema: &Value, value: &Value) -> Result<(), String> {
+    if value.is_null() {
+        return Ok(());
+    }
+    let Some(expected) = schema.get("type").and_then(Value::as_str) else {
+        return Ok(());
+    };
+    let matches = match expected {
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    };
+    if !matches {
+        let article = if expected.starts_with(['a', 'i', 'o']) {
+            "an"
+        } else {
+            "a"
+        };
+        return Err(format!("{path} must be {article} {expected}"));
+    }
+    if let (Some(items), Some(values)) = (schema.get("items"), value.as_array()) {
+        for (index, item) in values.iter().enumerate() {
+            check_type(&format!("{path}[{index}]"), items, item)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::{NoParams, ParamsSchema, validate};
+
+    rpc_params! {
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct SampleParams {
+            /// Session to act on.
+            session_key: String,
+            #[serde(default)]
+            limit: Option<u64>,
+            #[serde(default)]
+            tags: Vec<String>,
+            #[serde(rename = "type")]
+            kind: std::collections::BTreeMap<String, f64>,
+        }
+    }
+
+    #[test]
+    fn schemas_follow_struct_definitions_and_validate_params() {
+        assert_eq!(
+            SampleParams::schema(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "sessionKey": { "type": "string", "description": "Session to act on." },
+                    "limit": { "type": "integer" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "type": { "type": "object", "additionalProperties": { "type": "number" } },
+                },
+                "required": ["sessionKey", "type"],
+                "additionalProperties": true,
+            })
+        );
+        assert_eq!(NoParams::schema()["required"], json!([]));
+
+        let schema = SampleParams::schema();
+        let valid = json!({ "sessionKey": "main", "type": {}, "extra": 1, "limit": null });
+        assert_eq!(validate(&schema, &valid), Ok(()));
+        let parsed: SampleParams = serde_json::from_value(valid).expect("params should parse");
+        assert_eq!(parsed.session_key, "main");
+        assert!(parsed.limit.is_none() && parsed.tags.is_empty() && parsed.kind.is_empty());
+
+        for (params, error) in [
+            (json!([]), "params must be an object"),
+            (json!({ "type": {} }), "sessionKey is required"),
+            (
+                json!({ "sessionKey": 1, "type": {} }),
+                "sessionKey must be a string",
+            ),
+            (
+                json!({ "sessionKey": "main", "type": {}, "limit": 1.5 }),
+                "limit must be an integer",
+            ),
+            (
+                json!({ "sessionKey": "main", "type": {}, "tags": ["a", 2] }),
+                "tags[1] must be a string",
+            ),
+        ] {
+            assert_eq!(validate(&schema, &params), Err(error.to_owned()));
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+pub const API_KEY_PREFIX: &str = "rck_";
+
+#[must_use]
+pub fn generate_api_key_secret() -> String {
+    format!(
+        "{API_KEY_PREFIX}{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+#[must_use]
+pub fn looks_like_api_key(value: &str) -> bool {
+    value.starts_with(API_KEY_PREFIX) && value.len() > API_KEY_PREFIX.len()
+}
+
+#[must_use]
+pub fn hash_api_key_secret(secret: &str) -> String {
+    let digest = Sha256::digest(secret.as_bytes());
+    let mut out = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        out.push_str(&format!("{byte:02x}"));
+    }
+    out
+}
+
+#[must_use]
+pub fn verify_api_key_secret(secret: &str, expected_hash: &str) -> bool {
+    let provided = hash_api_key_secret(secret);
+    provided.as_bytes().ct_eq(expected_hash.as_bytes()).into()
+}
+
+#[must_use]
+pub fn api_key_hint(secret: &str) -> String {
+    let tail: String = secret
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("{API_KEY_PREFIX}...{tail}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        api_key_hint, generate_api_key_secret, hash_api_key_secret, looks_like_api_key,
+        verify_api_key_secret,
+    };
+
+    #[test]
+    fn generated_secrets_verify_against_their_hash() {
+        let secret = generate_api_key_secret();
+        assert!(looks_like_api_key(&secret));
+
+        let hash = hash_api_key_secret(&secret);
+        assert_eq!(hash.len(), 64);
+        assert_ne!(hash, secret);
+        assert!(verify_api_key_secret(&secret, &hash));
+        assert!(!verify_api_key_secret("rck_other", &hash));
+        assert!(api_key_hint(&secret).ends_with(&secret[secret.len() - 4..]));
+    }
+}
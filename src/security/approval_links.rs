@@ -0,0 +1,131 @@
+use crate::{
+    encoding::{percent_decode, percent_encode},
+    security::signatures::{hex_encode, hmac_sha256, verify_hmac_sha256_hex},
+};
+
+/// What a signed approval link points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalLinkKind {
+    Exec,
+    NodePair,
+}
+
+impl ApprovalLinkKind {
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Exec => "exec",
+            Self::NodePair => "node.pair",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "exec" => Some(Self::Exec),
+            "node.pair" => Some(Self::NodePair),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApprovalLinkClaims {
+    pub kind: ApprovalLinkKind,
+    pub id: String,
+    pub expires_at_ms: u64,
+}
+
+/// Builds `<base_url>?kind=<kind>&id=<id>&exp=<ms>&sig=<hex>`, where `sig` is HMAC-SHA256 over
+/// `kind\nid\nexp` keyed by the gateway link secret.
+#[must_use]
+pub fn sign_approval_link(secret: &[u8], base_url: &str, claims: &ApprovalLinkClaims) -> String {
+    let signature = hex_encode(&hmac_sha256(secret, signing_input(claims).as_bytes()));
+    format!(
+        "{base_url}?kind={}&id={}&exp={}&sig={signature}",
+        claims.kind.label(),
+        percent_encode(&claims.id),
+        claims.expires_at_ms
+    )
+}
+
+/// Verifies the signature and expiry of a link produced by [`sign_approval_link`]. Only the
+/// query string is inspected, so links keep working if the base URL changes.
+pub fn verify_approval_link(
+    secret: &[u8],
+    link: &str,
+    now_ms: u64,
+) -> Result<ApprovalLinkClaims, String> {
+    let query = link
+        .split_once('?')
+        .map_or(link, |(_, query)| query)
+        .split('#')
+        .next()
+        .unwrap_or_default();
+    let mut kind = None;
+    let mut id = None;
+    let mut expires_at_ms = None;
+    let mut signature = None;
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "kind" => kind = ApprovalLinkKind::parse(value),
+            "id" => id = Some(percent_decode(value)),
+            "exp" => expires_at_ms = value.parse::<u64>().ok(),
+            "sig" => signature = Some(value),
+            _ => {}
+        }
+    }
+
+    let claims = ApprovalLinkClaims {
+        kind: kind.ok_or("approval link has no valid kind")?,
+        id: id
+            .filter(|id| !id.is_empty())
+            .ok_or("approval link has no id")?,
+        expires_at_ms: expires_at_ms.ok_or("approval link has no expiry")?,
+    };
+    let signature = signature.ok_or("approval link is not signed")?;
+    if !verify_hmac_sha256_hex(secret, signing_input(&claims).as_bytes(), signature) {
+        return Err("approval link signature is invalid".to_owned());
+    }
+    if now_ms >= claims.expires_at_ms {
+        return Err("approval link has expired".to_owned());
+    }
+    Ok(claims)
+}
+
+fn signing_input(claims: &ApprovalLinkClaims) -> String {
+    format!(
+        "{}\n{}\n{}",
+        claims.kind.label(),
+        claims.id,
+        claims.expires_at_ms
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApprovalLinkClaims, ApprovalLinkKind, sign_approval_link, verify_approval_link};
+
+    #[test]
+    fn approval_links_round_trip_and_reject_tampering_or_expiry() {
+        let claims = ApprovalLinkClaims {
+            kind: ApprovalLinkKind::Exec,
+            id: "req 1/a".to_owned(),
+            expires_at_ms: 2_000,
+        };
+        let link = sign_approval_link(b"secret", "reclaw://approve", &claims);
+        assert!(link.starts_with("reclaw://approve?kind=exec&id=req%201%2Fa&exp=2000&sig="));
+
+        assert_eq!(verify_approval_link(b"secret", &link, 1_000), Ok(claims));
+        assert!(verify_approval_link(b"secret", &link, 2_000).is_err());
+        assert!(verify_approval_link(b"other", &link, 1_000).is_err());
+        let tampered = link.replace("exp=2000", "exp=9000");
+        assert_eq!(
+            verify_approval_link(b"secret", &tampered, 1_000),
+            Err("approval link signature is invalid".to_owned())
+        );
+        let retargeted = link.replace("kind=exec", "kind=node.pair");
+        assert!(verify_approval_link(b"secret", &retargeted, 1_000).is_err());
+    }
+}
@@ -63,6 +63,7 @@ mod tests {
         let auth = ConnectAuth {
             token: Some("abc".to_owned()),
             device_token: None,
+            refresh_token: None,
             password: None,
         };
 
@@ -75,6 +76,7 @@ mod tests {
         let auth = ConnectAuth {
             token: None,
             device_token: None,
+            refresh_token: None,
             password: Some("zzz".to_owned()),
         };
 
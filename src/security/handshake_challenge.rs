@@ -0,0 +1,213 @@
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    application::config::{AuthMode, HandshakeChallengeConfig, HandshakeChallengeKind},
+    protocol::{ConnectChallengeAnswer, ERROR_UNAVAILABLE, ErrorShape},
+};
+
+const DEFAULT_DIFFICULTY: u32 = 20;
+const MAX_DIFFICULTY: u32 = 32;
+const CAPTCHA_VERIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Compiled form of the `handshakeChallenge` config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeChallenge {
+    Pow {
+        difficulty: u32,
+    },
+    Captcha {
+        verify_url: String,
+        secret: String,
+        site_key: Option<String>,
+    },
+}
+
+impl HandshakeChallenge {
+    pub fn compile(config: HandshakeChallengeConfig) -> Result<Self, String> {
+        match config.kind {
+            HandshakeChallengeKind::Pow => {
+                let difficulty = config.difficulty.unwrap_or(DEFAULT_DIFFICULTY);
+                if !(1..=MAX_DIFFICULTY).contains(&difficulty) {
+                    return Err(format!(
+                        "handshakeChallenge.difficulty must be between 1 and {MAX_DIFFICULTY}"
+                    ));
+                }
+                Ok(Self::Pow { difficulty })
+            }
+            HandshakeChallengeKind::Captcha => {
+                let verify_url = config
+                    .captcha_verify_url
+                    .map(|url| url.trim().to_owned())
+                    .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+                    .ok_or("handshakeChallenge.captchaVerifyUrl must be an http(s) URL")?;
+                let secret = config
+                    .captcha_secret
+                    .map(|secret| secret.trim().to_owned())
+                    .filter(|secret| !secret.is_empty())
+                    .ok_or("handshakeChallenge.captchaSecret is required for captcha")?;
+                Ok(Self::Captcha {
+                    verify_url,
+                    secret,
+                    site_key: config
+                        .captcha_site_key
+                        .map(|key| key.trim().to_owned())
+                        .filter(|key| !key.is_empty()),
+                })
+            }
+        }
+    }
+
+    /// Payload of the `connect.challenge` event sent when a socket opens.
+    #[must_use]
+    pub fn payload(&self, nonce: &str, ts: u64) -> Value {
+        match self {
+            Self::Pow { difficulty } => json!({
+                "nonce": nonce,
+                "ts": ts,
+                "kind": "pow",
+                "algorithm": "sha256",
+                "difficulty": difficulty,
+            }),
+            Self::Captcha { site_key, .. } => json!({
+                "nonce": nonce,
+                "ts": ts,
+                "kind": "captcha",
+                "siteKey": site_key,
+            }),
+        }
+    }
+
+    /// Checks the `connect.challenge` answer against the nonce issued on this socket.
+    pub async fn verify(
+        &self,
+        nonce: &str,
+        answer: Option<&ConnectChallengeAnswer>,
+        remote_ip: Option<&str>,
+    ) -> Result<(), ErrorShape> {
+        let Some(answer) = answer.filter(|answer| answer.nonce == nonce) else {
+            return Err(ErrorShape::new(
+                ERROR_UNAVAILABLE,
+                "unauthorized: handshake challenge required",
+            ));
+        };
+        let solved = match self {
+            Self::Pow { difficulty } => answer
+                .solution
+                .as_deref()
+                .is_some_and(|solution| pow_solves(nonce, solution, *difficulty)),
+            Self::Captcha {
+                verify_url, secret, ..
+            } => match answer.captcha_token.as_deref().map(str::trim) {
+                Some(token) if !token.is_empty() => {
+                    verify_captcha(verify_url, secret, token, remote_ip).await?
+                }
+                _ => false,
+            },
+        };
+        if solved {
+            Ok(())
+        } else {
+            Err(ErrorShape::new(
+                ERROR_UNAVAILABLE,
+                "unauthorized: handshake challenge failed",
+            ))
+        }
+    }
+}
+
+/// Whether an authorized connect proved a credential and so skips the challenge: a device
+/// credential, or a gateway token or password under `token`/`password` auth. In `none` mode any
+/// token passes auth, so nothing but a device credential counts.
+#[must_use]
+pub fn credential_verified(mode: &AuthMode, device_verified: bool) -> bool {
+    device_verified || !matches!(mode, AuthMode::None)
+}
+
+/// Whether `SHA-256("<nonce>:<solution>")` starts with `difficulty` zero bits.
+#[must_use]
+pub fn pow_solves(nonce: &str, solution: &str, difficulty: u32) -> bool {
+    let digest = Sha256::digest(format!("{nonce}:{solution}").as_bytes());
+    leading_zero_bits(&digest) >= difficulty
+}
+
+/// Brute-forces a proof-of-work solution, as a client would.
+#[must_use]
+pub fn solve_pow(nonce: &str, difficulty: u32) -> String {
+    (0_u64..)
+        .map(|counter| counter.to_string())
+        .find(|solution| pow_solves(nonce, solution, difficulty))
+        .unwrap_or_default()
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+async fn verify_captcha(
+    verify_url: &str,
+    secret: &str,
+    token: &str,
+    remote_ip: Option<&str>,
+) -> Result<bool, ErrorShape> {
+    let unavailable = |message: String| ErrorShape::new(ERROR_UNAVAILABLE, message);
+    let client = reqwest::Client::builder()
+        .timeout(CAPTCHA_VERIFY_TIMEOUT)
+        .build()
+        .map_err(|error| unavailable(format!("failed to construct http client: {error}")))?;
+    let mut form = vec![("secret", secret), ("response", token)];
+    if let Some(remote_ip) = remote_ip {
+        form.push(("remoteip", remote_ip));
+    }
+    let response = client
+        .post(verify_url)
+        .form(&form)
+        .send()
+        .await
+        .map_err(|error| unavailable(format!("captcha verification failed: {error}")))?;
+    if !response.status().is_success() {
+        return Err(unavailable(format!(
+            "captcha verification failed with {}",
+            response.status()
+        )));
+    }
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|error| unavailable(format!("captcha verification is not json: {error}")))?;
+    Ok(body.get("success").and_then(Value::as_bool) == Some(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{credential_verified, leading_zero_bits, pow_solves, solve_pow};
+    use crate::application::config::AuthMode;
+
+    #[test]
+    fn pow_solutions_need_the_requested_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0, 0, 0x1f, 0xff]), 19);
+        assert_eq!(leading_zero_bits(&[0x80]), 0);
+        assert_eq!(leading_zero_bits(&[0, 0]), 16);
+
+        let solution = solve_pow("nonce-1", 12);
+        assert!(pow_solves("nonce-1", &solution, 12));
+        assert!(pow_solves("nonce-1", &solution, 1));
+        assert!(!pow_solves("nonce-1", "guess", 24));
+
+        assert!(!credential_verified(&AuthMode::None, false));
+        assert!(credential_verified(&AuthMode::None, true));
+        assert!(credential_verified(
+            &AuthMode::Token("secret".to_owned()),
+            false
+        ));
+    }
+}
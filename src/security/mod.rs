@@ -1,2 +1,9 @@
+pub mod api_keys;
+pub mod approval_links;
 pub mod auth;
+pub mod handshake_challenge;
+pub mod jwt;
+pub mod origin;
 pub mod rate_limit;
+pub mod signatures;
+pub mod source_ip;
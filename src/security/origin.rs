@@ -0,0 +1,142 @@
+use axum::http::{HeaderMap, header};
+
+/// Rejects cross-site WS hijacking and DNS-rebinding attempts before a handshake starts.
+///
+/// Requests without an `Origin` header (CLI clients, bridges, nodes) only face the host check.
+/// Browser origins must be listed in `allowed_origins`, or match the request `Host` when the list
+/// is empty; a `*` entry accepts any origin.
+pub fn check_origin_and_host(
+    headers: &HeaderMap,
+    allowed_origins: &[String],
+    allowed_hosts: &[String],
+) -> Result<(), String> {
+    let host = header_value(headers, header::HOST.as_str());
+
+    if !allowed_hosts.is_empty() {
+        let Some(host) = host.as_deref() else {
+            return Err("missing Host header".to_owned());
+        };
+        let hostname = strip_port(host);
+        if !allowed_hosts
+            .iter()
+            .any(|allowed| allowed == host || allowed == hostname)
+        {
+            return Err(format!("host {host} is not allowed"));
+        }
+    }
+
+    let Some(origin) = header_value(headers, header::ORIGIN.as_str()) else {
+        return Ok(());
+    };
+    if allowed_origins.iter().any(|allowed| allowed == "*") {
+        return Ok(());
+    }
+    if !allowed_origins.is_empty() {
+        return if allowed_origins.contains(&origin) {
+            Ok(())
+        } else {
+            Err(format!("origin {origin} is not allowed"))
+        };
+    }
+
+    let authority = origin.split_once("://").map(|(_, authority)| authority);
+    if authority.is_some() && authority == host.as_deref() {
+        Ok(())
+    } else {
+        Err(format!("cross-origin request from {origin} is not allowed"))
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().trim_end_matches('/').to_ascii_lowercase())
+        .filter(|value| !value.is_empty())
+}
+
+fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return host
+            .split_once(']')
+            .map_or(host, |(ipv6, _)| &host[..=ipv6.len()]);
+    }
+    host.rsplit_once(':')
+        .filter(|(_, port)| port.chars().all(|ch| ch.is_ascii_digit()))
+        .map_or(host, |(hostname, _)| hostname)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue, header};
+
+    use super::check_origin_and_host;
+
+    fn headers(host: &str, origin: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_str(host).expect("host"));
+        if let Some(origin) = origin {
+            headers.insert(
+                header::ORIGIN,
+                HeaderValue::from_str(origin).expect("origin"),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn origin_and_host_checks_default_to_same_origin() {
+        let none: &[String] = &[];
+        assert!(check_origin_and_host(&headers("127.0.0.1:18789", None), none, none).is_ok());
+        assert!(
+            check_origin_and_host(
+                &headers("127.0.0.1:18789", Some("http://127.0.0.1:18789")),
+                none,
+                none
+            )
+            .is_ok()
+        );
+        assert!(
+            check_origin_and_host(
+                &headers("127.0.0.1:18789", Some("https://evil.example")),
+                none,
+                none
+            )
+            .is_err()
+        );
+
+        let origins = vec!["https://app.example".to_owned()];
+        assert!(
+            check_origin_and_host(
+                &headers("gw.example", Some("https://App.example/")),
+                &origins,
+                none
+            )
+            .is_ok()
+        );
+        assert!(
+            check_origin_and_host(
+                &headers("gw.example", Some("https://gw.example")),
+                &origins,
+                none
+            )
+            .is_err()
+        );
+        assert!(
+            check_origin_and_host(
+                &headers("gw.example", Some("null")),
+                &["*".to_owned()],
+                none
+            )
+            .is_ok()
+        );
+
+        let hosts = vec!["gw.example".to_owned(), "[::1]".to_owned()];
+        assert!(check_origin_and_host(&headers("gw.example:443", None), none, &hosts).is_ok());
+        assert!(check_origin_and_host(&headers("[::1]:18789", None), none, &hosts).is_ok());
+        assert_eq!(
+            check_origin_and_host(&headers("rebind.example", None), none, &hosts),
+            Err("host rebind.example is not allowed".to_owned())
+        );
+    }
+}
@@ -2,6 +2,8 @@ use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use tokio::sync::RwLock;
 
+use crate::storage::RedisBackend;
+
 #[derive(Debug, Clone)]
 pub struct RateLimitDecision {
     pub allowed: bool,
@@ -13,6 +15,7 @@ pub struct AuthRateLimiter {
     max_attempts: u32,
     window: Duration,
     state: Arc<RwLock<HashMap<String, Vec<u64>>>>,
+    shared: Option<(RedisBackend, &'static str)>,
 }
 
 impl AuthRateLimiter {
@@ -22,11 +25,45 @@ impl AuthRateLimiter {
             max_attempts,
             window,
             state: Arc::new(RwLock::new(HashMap::new())),
+            shared: None,
         }
     }
 
+    /// Keeps attempts in Redis under `scope` so every instance enforces one shared limit. Redis
+    /// errors fall back to this instance's in-memory window.
+    #[must_use]
+    pub fn with_shared_backend(mut self, backend: RedisBackend, scope: &'static str) -> Self {
+        self.shared = Some((backend, scope));
+        self
+    }
+
+    fn window_ms(&self) -> u64 {
+        u64::try_from(self.window.as_millis()).unwrap_or(u64::MAX)
+    }
+
     pub async fn check(&self, key: &str) -> RateLimitDecision {
         let now = now_unix_ms();
+        if let Some((backend, scope)) = &self.shared {
+            match backend.attempts(scope, key, now, self.window_ms()).await {
+                Ok((count, oldest)) => {
+                    if count >= u64::from(self.max_attempts) {
+                        let retry_after_ms = oldest.map_or(self.window_ms(), |oldest| {
+                            oldest.saturating_add(self.window_ms()).saturating_sub(now)
+                        });
+                        return RateLimitDecision {
+                            allowed: false,
+                            retry_after_ms,
+                        };
+                    }
+                    return RateLimitDecision {
+                        allowed: true,
+                        retry_after_ms: 0,
+                    };
+                }
+                Err(error) => tracing::warn!("shared rate limit check failed ({scope}): {error}"),
+            }
+        }
+
         let mut guard = self.state.write().await;
         let attempts = guard.entry(key.to_owned()).or_default();
         let cutoff = now.saturating_sub(self.window.as_millis() as u64);
@@ -50,14 +87,40 @@ impl AuthRateLimiter {
     }
 
     pub async fn record_failure(&self, key: &str) -> RateLimitDecision {
+        self.record_with_limit(key, self.max_attempts).await
+    }
+
+    /// Records an attempt against a caller-supplied limit, for keys whose
+    /// allowance is configured per entry rather than per limiter.
+    pub async fn record_with_limit(&self, key: &str, max_attempts: u32) -> RateLimitDecision {
         let now = now_unix_ms();
+        if let Some((backend, scope)) = &self.shared {
+            match backend
+                .record_attempt(scope, key, now, self.window_ms())
+                .await
+            {
+                Ok(count) if count > u64::from(max_attempts) => {
+                    return RateLimitDecision {
+                        allowed: false,
+                        retry_after_ms: self.window_ms(),
+                    };
+                }
+                Ok(_) => {
+                    return RateLimitDecision {
+                        allowed: true,
+                        retry_after_ms: 0,
+                    };
+                }
+                Err(error) => tracing::warn!("shared rate limit record failed ({scope}): {error}"),
+            }
+        }
         let mut guard = self.state.write().await;
         let attempts = guard.entry(key.to_owned()).or_default();
         let cutoff = now.saturating_sub(self.window.as_millis() as u64);
         attempts.retain(|attempt| *attempt >= cutoff);
         attempts.push(now);
 
-        if attempts.len() > self.max_attempts as usize {
+        if attempts.len() > max_attempts as usize {
             RateLimitDecision {
                 allowed: false,
                 retry_after_ms: self.window.as_millis() as u64,
@@ -71,6 +134,11 @@ impl AuthRateLimiter {
     }
 
     pub async fn reset(&self, key: &str) {
+        if let Some((backend, scope)) = &self.shared
+            && let Err(error) = backend.clear_attempts(scope, key).await
+        {
+            tracing::warn!("shared rate limit reset failed ({scope}): {error}");
+        }
         self.state.write().await.remove(key);
     }
 }
@@ -96,4 +164,13 @@ mod tests {
         let _ = limiter.record_failure("a").await;
         assert!(!limiter.record_failure("a").await.allowed);
     }
+
+    #[tokio::test]
+    async fn limiter_honors_per_key_limits() {
+        let limiter = AuthRateLimiter::new(1, Duration::from_secs(30));
+        assert!(limiter.record_with_limit("k", 3).await.allowed);
+        assert!(limiter.record_with_limit("k", 3).await.allowed);
+        assert!(limiter.record_with_limit("k", 3).await.allowed);
+        assert!(!limiter.record_with_limit("k", 3).await.allowed);
+    }
 }
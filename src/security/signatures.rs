@@ -0,0 +1,96 @@
+use ring::signature::{ED25519, UnparsedPublicKey};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+const SHA256_BLOCK_BYTES: usize = 64;
+
+/// HMAC-SHA256 (RFC 2104) over `message` keyed by `key`.
+#[must_use]
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; SHA256_BLOCK_BYTES];
+    if key.len() > SHA256_BLOCK_BYTES {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|byte| byte ^ 0x36));
+    inner.update(message);
+    let inner = inner.finalize();
+
+    let mut outer = Sha256::new();
+    outer.update(block.map(|byte| byte ^ 0x5c));
+    outer.update(inner);
+    outer.finalize().into()
+}
+
+#[must_use]
+pub fn hex_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        out.push_str(&format!("{byte:02x}"));
+    }
+    out
+}
+
+/// Decodes lowercase or uppercase hex; `None` for odd lengths or non-hex characters.
+#[must_use]
+pub fn hex_decode(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(value.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// Verifies a hex-encoded Ed25519 signature of `message` by a hex-encoded 32-byte public key.
+#[must_use]
+pub fn verify_ed25519_hex(public_key_hex: &str, message: &[u8], signature_hex: &str) -> bool {
+    let (Some(public_key), Some(signature)) = (
+        hex_decode(public_key_hex.trim()),
+        hex_decode(signature_hex.trim()),
+    ) else {
+        return false;
+    };
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(message, &signature)
+        .is_ok()
+}
+
+/// Compares a hex-encoded HMAC-SHA256 signature in constant time (case-insensitive hex).
+#[must_use]
+pub fn verify_hmac_sha256_hex(key: &[u8], message: &[u8], signature_hex: &str) -> bool {
+    let expected = hex_encode(&hmac_sha256(key, message));
+    let provided = signature_hex.trim().to_ascii_lowercase();
+    provided.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hex_encode, hmac_sha256, verify_hmac_sha256_hex};
+
+    #[test]
+    fn hmac_sha256_matches_rfc_4231_vectors() {
+        assert_eq!(
+            hex_encode(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        let long_key = [0xaa; 131];
+        assert_eq!(
+            hex_encode(&hmac_sha256(
+                &long_key,
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+        assert!(verify_hmac_sha256_hex(
+            b"Jefe",
+            b"what do ya want for nothing?",
+            "5BDCC146BF60754E6A042426089575C75A003F089D2739839DEC58B964EC3843"
+        ));
+        assert!(!verify_hmac_sha256_hex(b"Jefe", b"tampered", "5bdc"));
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use axum::http::HeaderMap;
+
+/// An IPv4 or IPv6 network in CIDR notation; a bare address is a single-host network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    #[must_use]
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_matches(&network.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_matches(&network.octets(), &ip.octets(), self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let raw = raw.trim();
+        let (address, prefix) = match raw.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (raw, None),
+        };
+        let network = address
+            .parse::<IpAddr>()
+            .map_err(|_| format!("invalid CIDR address: {raw}"))?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(|| format!("invalid CIDR prefix length: {raw}"))?,
+            None => max_len,
+        };
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+fn prefix_matches(network: &[u8], ip: &[u8], prefix_len: u8) -> bool {
+    let full_bytes = usize::from(prefix_len / 8);
+    if network[..full_bytes] != ip[..full_bytes] {
+        return false;
+    }
+    let rest = prefix_len % 8;
+    if rest == 0 {
+        return true;
+    }
+    let mask = u8::MAX << (8 - rest);
+    network[full_bytes] & mask == ip[full_bytes] & mask
+}
+
+#[must_use]
+pub fn contains_ip(ranges: &[IpCidr], ip: IpAddr) -> bool {
+    ranges.iter().any(|range| range.contains(ip))
+}
+
+/// Resolves the caller address. When the peer is a trusted proxy, `X-Forwarded-For` is walked
+/// from the right and the first hop that is not itself a trusted proxy is the client.
+#[must_use]
+pub fn client_ip(remote: SocketAddr, headers: &HeaderMap, trusted_proxies: &[IpCidr]) -> IpAddr {
+    let peer = remote.ip().to_canonical();
+    if !contains_ip(trusted_proxies, peer) {
+        return peer;
+    }
+    let hops = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|hop| hop.trim().parse::<IpAddr>().ok())
+        .map(|hop| hop.to_canonical())
+        .collect::<Vec<_>>();
+    hops.iter()
+        .rev()
+        .find(|hop| !contains_ip(trusted_proxies, **hop))
+        .or_else(|| hops.first())
+        .copied()
+        .unwrap_or(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, SocketAddr};
+
+    use axum::http::{HeaderMap, HeaderValue};
+
+    use super::{IpCidr, client_ip};
+
+    fn ip(raw: &str) -> IpAddr {
+        raw.parse().expect("ip should parse")
+    }
+
+    #[test]
+    fn cidrs_match_prefixes_and_forwarded_hops_skip_trusted_proxies() {
+        let telegram = "149.154.160.0/20"
+            .parse::<IpCidr>()
+            .expect("cidr should parse");
+        assert!(telegram.contains(ip("149.154.167.220")));
+        assert!(telegram.contains(ip("::ffff:149.154.175.1")));
+        assert!(!telegram.contains(ip("149.154.176.1")));
+        let v6 = "2001:db8::/33"
+            .parse::<IpCidr>()
+            .expect("cidr should parse");
+        assert!(v6.contains(ip("2001:db8:7fff::1")));
+        assert!(!v6.contains(ip("2001:db8:8000::1")));
+        assert_eq!(
+            "10.0.0.1".parse::<IpCidr>().map(|cidr| cidr.to_string()),
+            Ok("10.0.0.1/32".to_owned())
+        );
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("example.com/8".parse::<IpCidr>().is_err());
+
+        let proxies = vec!["10.0.0.0/8".parse::<IpCidr>().expect("cidr should parse")];
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("6.6.6.6, 149.154.167.220, 10.1.2.3"),
+        );
+        let proxy = SocketAddr::new(ip("10.0.0.5"), 443);
+        assert_eq!(client_ip(proxy, &headers, &proxies), ip("149.154.167.220"));
+        let direct = SocketAddr::new(ip("203.0.113.9"), 443);
+        assert_eq!(client_ip(direct, &headers, &proxies), ip("203.0.113.9"));
+        assert_eq!(
+            client_ip(proxy, &HeaderMap::new(), &proxies),
+            ip("10.0.0.5")
+        );
+    }
+}
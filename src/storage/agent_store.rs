@@ -1,5 +1,8 @@
 use crate::{
-    domain::{error::DomainError, models::AgentRunRecord},
+    domain::{
+        error::DomainError,
+        models::{AgentRunRecord, RunCostScope},
+    },
     storage::{SqliteStore, util},
 };
 
@@ -18,6 +21,7 @@ type AgentRow = (
 
 impl SqliteStore {
     pub async fn upsert_agent_run(&self, run: &AgentRunRecord) -> Result<(), DomainError> {
+        let _timer = self.query_timer("upsert_agent_run");
         let metadata_json =
             util::value_to_json_text(&run.metadata).map_err(DomainError::Storage)?;
         sqlx::query(
@@ -51,6 +55,7 @@ impl SqliteStore {
         to_status: &str,
         updated_at_ms: u64,
     ) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("transition_agent_run_status");
         let result = sqlx::query(
             "UPDATE agent_runs \
              SET status = ?, updated_at_ms = ? \
@@ -74,6 +79,7 @@ impl SqliteStore {
         run: &AgentRunRecord,
         expected_status: &str,
     ) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("finalize_agent_run_if_status");
         let metadata_json =
             util::value_to_json_text(&run.metadata).map_err(DomainError::Storage)?;
         let result = sqlx::query(
@@ -99,6 +105,7 @@ impl SqliteStore {
     }
 
     pub async fn get_agent_run(&self, run_id: &str) -> Result<Option<AgentRunRecord>, DomainError> {
+        let _timer = self.query_timer("get_agent_run");
         let row = sqlx::query_as::<_, AgentRow>(
             "SELECT run_id, agent_id, input, output, status, session_key, metadata_json, created_at_ms, updated_at_ms, completed_at_ms \
              FROM agent_runs WHERE run_id = ? LIMIT 1",
@@ -112,6 +119,7 @@ impl SqliteStore {
     }
 
     pub async fn count_agent_runs(&self) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("count_agent_runs");
         let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM agent_runs")
             .fetch_one(self.pool())
             .await
@@ -122,11 +130,39 @@ impl SqliteStore {
         Ok(u64::try_from(count).unwrap_or(0))
     }
 
+    /// Estimated spend of runs created at or after `since_ms`, summed per agent id or session
+    /// key and limited to `key` when given.
+    pub async fn agent_run_costs(
+        &self,
+        scope: RunCostScope,
+        key: Option<&str>,
+        since_ms: u64,
+    ) -> Result<Vec<(String, f64)>, DomainError> {
+        let _timer = self.query_timer("agent_run_costs");
+        let column = match scope {
+            RunCostScope::Agent => "agent_id",
+            RunCostScope::Session => "session_key",
+        };
+        sqlx::query_as::<_, (String, f64)>(&format!(
+            "SELECT {column}, SUM(json_extract(metadata_json, '$.cost.usd')) FROM agent_runs \
+             WHERE created_at_ms >= ? AND {column} IS NOT NULL AND (? IS NULL OR {column} = ?) \
+             AND json_extract(metadata_json, '$.cost.usd') IS NOT NULL \
+             GROUP BY {column} ORDER BY {column}"
+        ))
+        .bind(i64::try_from(since_ms).unwrap_or(i64::MAX))
+        .bind(key)
+        .bind(key)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to sum agent run costs: {error}")))
+    }
+
     pub async fn list_agent_runs_by_session(
         &self,
         session_key: &str,
         limit: Option<usize>,
     ) -> Result<Vec<AgentRunRecord>, DomainError> {
+        let _timer = self.query_timer("list_agent_runs_by_session");
         let limit = limit.unwrap_or(500).clamp(1, 5_000);
         let rows = sqlx::query_as::<_, AgentRow>(
             "SELECT run_id, agent_id, input, output, status, session_key, metadata_json, created_at_ms, updated_at_ms, completed_at_ms \
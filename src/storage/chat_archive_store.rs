@@ -0,0 +1,108 @@
+use crate::{
+    domain::{error::DomainError, models::ChatArchiveSegment},
+    storage::SqliteStore,
+};
+
+type ChatArchiveSegmentRow = (String, String, String, i64, i64, i64, i64);
+
+impl SqliteStore {
+    /// Indexes a written segment and removes its messages from `chat_messages` in one
+    /// transaction, so a message is always readable from exactly one place.
+    pub async fn commit_chat_archive_segment(
+        &self,
+        segment: &ChatArchiveSegment,
+        message_ids: &[String],
+    ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("commit_chat_archive_segment");
+        let mut tx = self
+            .pool()
+            .begin()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+
+        sqlx::query(
+            "INSERT INTO chat_archive_segments(id, session_key, file_name, first_ts_ms, last_ts_ms, message_count, created_at_ms) \
+             VALUES(?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&segment.id)
+        .bind(&segment.session_key)
+        .bind(&segment.file_name)
+        .bind(i64::try_from(segment.first_ts_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(segment.last_ts_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(segment.message_count).unwrap_or(i64::MAX))
+        .bind(i64::try_from(segment.created_at_ms).unwrap_or(i64::MAX))
+        .execute(&mut *tx)
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to index chat archive segment: {error}"))
+        })?;
+        for message_id in message_ids {
+            sqlx::query("DELETE FROM chat_messages WHERE message_id = ? AND session_key = ?")
+                .bind(message_id)
+                .bind(&segment.session_key)
+                .execute(&mut *tx)
+                .await
+                .map_err(|error| {
+                    DomainError::Storage(format!("failed to remove archived message: {error}"))
+                })?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))
+    }
+
+    /// Lists the archive segments of a session, newest first.
+    pub async fn list_chat_archive_segments(
+        &self,
+        session_key: &str,
+    ) -> Result<Vec<ChatArchiveSegment>, DomainError> {
+        let _timer = self.query_timer("list_chat_archive_segments");
+        let rows = sqlx::query_as::<_, ChatArchiveSegmentRow>(
+            "SELECT id, session_key, file_name, first_ts_ms, last_ts_ms, message_count, created_at_ms \
+             FROM chat_archive_segments WHERE session_key = ? ORDER BY last_ts_ms DESC",
+        )
+        .bind(session_key)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to list chat archive segments: {error}"))
+        })?;
+
+        Ok(rows.into_iter().map(map_segment_row).collect())
+    }
+
+    /// Drops the index rows of a session's segments and returns them so the caller can remove
+    /// the files.
+    pub async fn delete_chat_archive_segments(
+        &self,
+        session_key: &str,
+    ) -> Result<Vec<ChatArchiveSegment>, DomainError> {
+        let _timer = self.query_timer("delete_chat_archive_segments");
+        let rows = sqlx::query_as::<_, ChatArchiveSegmentRow>(
+            "DELETE FROM chat_archive_segments WHERE session_key = ? \
+             RETURNING id, session_key, file_name, first_ts_ms, last_ts_ms, message_count, created_at_ms",
+        )
+        .bind(session_key)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to delete chat archive segments: {error}"))
+        })?;
+
+        Ok(rows.into_iter().map(map_segment_row).collect())
+    }
+}
+
+fn map_segment_row(row: ChatArchiveSegmentRow) -> ChatArchiveSegment {
+    let (id, session_key, file_name, first_ts_ms, last_ts_ms, message_count, created_at_ms) = row;
+    ChatArchiveSegment {
+        id,
+        session_key,
+        file_name,
+        first_ts_ms: u64::try_from(first_ts_ms).unwrap_or(0),
+        last_ts_ms: u64::try_from(last_ts_ms).unwrap_or(0),
+        message_count: u64::try_from(message_count).unwrap_or(0),
+        created_at_ms: u64::try_from(created_at_ms).unwrap_or(0),
+    }
+}
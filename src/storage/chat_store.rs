@@ -1,39 +1,48 @@
+use std::collections::HashMap;
+
 use crate::{
-    domain::{error::DomainError, models::ChatMessage},
-    storage::{SqliteStore, util},
+    domain::{
+        error::DomainError,
+        models::{ChatMessage, ChatReadMarker},
+    },
+    storage::{SqliteStore, now_unix_ms, util},
 };
 
+type ChatMessageRow = (String, String, String, String, String, i64, bool);
+
 impl SqliteStore {
     pub async fn append_chat_messages(
         &self,
         session_key: &str,
         messages: &[ChatMessage],
     ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("append_chat_messages");
         let mut tx = self
             .pool()
             .begin()
             .await
             .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        insert_chat_messages(&mut tx, session_key, messages).await?;
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))?;
+        Ok(())
+    }
 
-        for message in messages {
-            let metadata_json =
-                util::value_to_json_text(&message.metadata).map_err(DomainError::Storage)?;
-            sqlx::query(
-                "INSERT OR REPLACE INTO chat_messages(message_id, session_key, role, text, status, metadata_json, ts_ms) \
-                 VALUES(?, ?, ?, ?, ?, ?, ?)",
-            )
-            .bind(&message.id)
-            .bind(session_key)
-            .bind(&message.role)
-            .bind(&message.text)
-            .bind(&message.status)
-            .bind(metadata_json)
-            .bind(i64::try_from(message.ts).unwrap_or(i64::MAX))
-            .execute(&mut *tx)
+    /// Inserts the appends of several sessions in one transaction, in order.
+    pub async fn append_chat_message_batch(
+        &self,
+        batch: &[(String, Vec<ChatMessage>)],
+    ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("append_chat_message_batch");
+        let mut tx = self
+            .pool()
+            .begin()
             .await
-            .map_err(|error| DomainError::Storage(format!("failed to insert chat message: {error}")))?;
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        for (session_key, messages) in batch {
+            insert_chat_messages(&mut tx, session_key, messages).await?;
         }
-
         tx.commit()
             .await
             .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))?;
@@ -45,9 +54,12 @@ impl SqliteStore {
         session_key: &str,
         limit: Option<usize>,
     ) -> Result<Vec<ChatMessage>, DomainError> {
+        let _timer = self.query_timer("list_chat_messages");
         let mut query = String::from(
-            "SELECT message_id, role, text, status, metadata_json, ts_ms FROM chat_messages \
-             WHERE session_key = ? ORDER BY ts_ms DESC",
+            "SELECT m.message_id, m.role, m.text, m.status, m.metadata_json, m.ts_ms, \
+             p.message_id IS NOT NULL FROM chat_messages m \
+             LEFT JOIN chat_pins p ON p.message_id = m.message_id \
+             WHERE m.session_key = ? ORDER BY m.ts_ms DESC",
         );
 
         if let Some(limit) = limit {
@@ -55,7 +67,7 @@ impl SqliteStore {
             query.push_str(&limit.to_string());
         }
 
-        let rows = sqlx::query_as::<_, (String, String, String, String, String, i64)>(&query)
+        let rows = sqlx::query_as::<_, ChatMessageRow>(&query)
             .bind(session_key)
             .fetch_all(self.pool())
             .await
@@ -71,7 +83,233 @@ impl SqliteStore {
         Ok(messages)
     }
 
+    /// Lists the pinned messages of a session, oldest first.
+    pub async fn list_pinned_chat_messages(
+        &self,
+        session_key: &str,
+    ) -> Result<Vec<ChatMessage>, DomainError> {
+        let _timer = self.query_timer("list_pinned_chat_messages");
+        let rows = sqlx::query_as::<_, ChatMessageRow>(
+            "SELECT m.message_id, m.role, m.text, m.status, m.metadata_json, m.ts_ms, 1 \
+             FROM chat_pins p JOIN chat_messages m ON m.message_id = p.message_id \
+             WHERE p.session_key = ? ORDER BY m.ts_ms ASC",
+        )
+        .bind(session_key)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to list pinned chat messages: {error}"))
+        })?;
+
+        rows.into_iter().map(map_chat_row).collect()
+    }
+
+    /// Pins or unpins a message of `session_key`. Returns `false` when the session has no such
+    /// message.
+    pub async fn set_chat_message_pinned(
+        &self,
+        session_key: &str,
+        message_id: &str,
+        pinned: bool,
+        pinned_by: Option<&str>,
+    ) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("set_chat_message_pinned");
+        let exists = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM chat_messages WHERE session_key = ? AND message_id = ?",
+        )
+        .bind(session_key)
+        .bind(message_id)
+        .fetch_one(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to load chat message: {error}")))?;
+        if exists == 0 {
+            return Ok(false);
+        }
+
+        let result = if pinned {
+            sqlx::query(
+                "INSERT INTO chat_pins(message_id, session_key, pinned_by, pinned_at_ms) \
+                 VALUES(?, ?, ?, ?) ON CONFLICT(message_id) DO NOTHING",
+            )
+            .bind(message_id)
+            .bind(session_key)
+            .bind(pinned_by)
+            .bind(i64::try_from(now_unix_ms()).unwrap_or(i64::MAX))
+            .execute(self.pool())
+            .await
+        } else {
+            sqlx::query("DELETE FROM chat_pins WHERE message_id = ?")
+                .bind(message_id)
+                .execute(self.pool())
+                .await
+        };
+        result
+            .map_err(|error| DomainError::Storage(format!("failed to update chat pin: {error}")))?;
+        Ok(true)
+    }
+
+    /// Moves `client_id`'s read marker for `session_key` to `message_id` unless it already points
+    /// at a later message, and returns the marker now stored. Returns `None` when the session has
+    /// no such message.
+    pub async fn mark_chat_read(
+        &self,
+        client_id: &str,
+        session_key: &str,
+        message_id: &str,
+    ) -> Result<Option<ChatReadMarker>, DomainError> {
+        let _timer = self.query_timer("mark_chat_read");
+        let message_ts = sqlx::query_scalar::<_, i64>(
+            "SELECT ts_ms FROM chat_messages WHERE session_key = ? AND message_id = ?",
+        )
+        .bind(session_key)
+        .bind(message_id)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to load chat message: {error}")))?;
+        let Some(message_ts) = message_ts else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            "INSERT INTO chat_read_markers(client_id, session_key, message_id, message_ts_ms, marked_at_ms) \
+             VALUES(?, ?, ?, ?, ?) \
+             ON CONFLICT(client_id, session_key) DO UPDATE SET \
+               message_id = excluded.message_id, \
+               message_ts_ms = excluded.message_ts_ms, \
+               marked_at_ms = excluded.marked_at_ms \
+             WHERE excluded.message_ts_ms >= chat_read_markers.message_ts_ms",
+        )
+        .bind(client_id)
+        .bind(session_key)
+        .bind(message_id)
+        .bind(message_ts)
+        .bind(i64::try_from(now_unix_ms()).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to mark chat read: {error}")))?;
+
+        let row = sqlx::query_as::<_, (String, i64, i64)>(
+            "SELECT message_id, message_ts_ms, marked_at_ms FROM chat_read_markers \
+             WHERE client_id = ? AND session_key = ?",
+        )
+        .bind(client_id)
+        .bind(session_key)
+        .fetch_one(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to load read marker: {error}")))?;
+        Ok(Some(ChatReadMarker {
+            client_id: client_id.to_owned(),
+            session_key: session_key.to_owned(),
+            message_id: row.0,
+            message_ts: u64::try_from(row.1).unwrap_or(0),
+            marked_at_ms: u64::try_from(row.2).unwrap_or(0),
+        }))
+    }
+
+    /// Messages after `client_id`'s read marker, per session; sessions without a marker count
+    /// every message. Sessions with nothing unread are omitted.
+    pub async fn count_unread_chat_messages(
+        &self,
+        client_id: &str,
+    ) -> Result<HashMap<String, u64>, DomainError> {
+        let _timer = self.query_timer("count_unread_chat_messages");
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT m.session_key, COUNT(*) FROM chat_messages m \
+             LEFT JOIN chat_read_markers r ON r.session_key = m.session_key AND r.client_id = ? \
+             WHERE r.message_ts_ms IS NULL OR m.ts_ms > r.message_ts_ms \
+             GROUP BY m.session_key",
+        )
+        .bind(client_id)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to count unread messages: {error}"))
+        })?;
+        Ok(rows
+            .into_iter()
+            .map(|(session_key, count)| (session_key, u64::try_from(count).unwrap_or(0)))
+            .collect())
+    }
+
+    /// Case-insensitive substring search over message text, newest first. `session_keys`
+    /// restricts the search to those sessions when given.
+    pub async fn search_chat_messages(
+        &self,
+        query: &str,
+        session_keys: Option<&[String]>,
+        limit: usize,
+    ) -> Result<Vec<(String, ChatMessage)>, DomainError> {
+        let _timer = self.query_timer("search_chat_messages");
+        if session_keys.is_some_and(<[String]>::is_empty) {
+            return Ok(Vec::new());
+        }
+        let mut sql = String::from(
+            "SELECT m.session_key, m.message_id, m.role, m.text, m.status, m.metadata_json, \
+             m.ts_ms, p.message_id IS NOT NULL FROM chat_messages m \
+             LEFT JOIN chat_pins p ON p.message_id = m.message_id \
+             WHERE instr(lower(m.text), lower(?)) > 0",
+        );
+        if let Some(keys) = session_keys {
+            sql.push_str(" AND m.session_key IN (");
+            sql.push_str(&vec!["?"; keys.len()].join(", "));
+            sql.push(')');
+        }
+        sql.push_str(" ORDER BY m.ts_ms DESC LIMIT ");
+        sql.push_str(&limit.to_string());
+
+        let mut search =
+            sqlx::query_as::<_, (String, String, String, String, String, String, i64, bool)>(&sql)
+                .bind(query);
+        for key in session_keys.unwrap_or_default() {
+            search = search.bind(key);
+        }
+        let rows = search.fetch_all(self.pool()).await.map_err(|error| {
+            DomainError::Storage(format!("failed to search chat messages: {error}"))
+        })?;
+
+        rows.into_iter()
+            .map(
+                |(session_key, id, role, text, status, metadata_json, ts_ms, pinned)| {
+                    map_chat_row((id, role, text, status, metadata_json, ts_ms, pinned))
+                        .map(|message| (session_key, message))
+                },
+            )
+            .collect()
+    }
+
+    /// Unpinned messages older than `before_ms`, grouped by session and oldest first.
+    pub async fn list_archivable_chat_messages(
+        &self,
+        before_ms: u64,
+        limit: usize,
+    ) -> Result<Vec<(String, ChatMessage)>, DomainError> {
+        let _timer = self.query_timer("list_archivable_chat_messages");
+        let rows = sqlx::query_as::<_, (String, String, String, String, String, String, i64)>(
+            "SELECT m.session_key, m.message_id, m.role, m.text, m.status, m.metadata_json, m.ts_ms \
+             FROM chat_messages m LEFT JOIN chat_pins p ON p.message_id = m.message_id \
+             WHERE m.ts_ms < ? AND p.message_id IS NULL \
+             ORDER BY m.session_key ASC, m.ts_ms ASC LIMIT ?",
+        )
+        .bind(i64::try_from(before_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to list archivable chat messages: {error}"))
+        })?;
+
+        rows.into_iter()
+            .map(
+                |(session_key, id, role, text, status, metadata_json, ts_ms)| {
+                    map_chat_row((id, role, text, status, metadata_json, ts_ms, false))
+                        .map(|message| (session_key, message))
+                },
+            )
+            .collect()
+    }
+
     pub async fn count_chat_messages(&self) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("count_chat_messages");
         let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM chat_messages")
             .fetch_one(self.pool())
             .await
@@ -83,10 +321,41 @@ impl SqliteStore {
     }
 }
 
-fn map_chat_row(
-    row: (String, String, String, String, String, i64),
-) -> Result<ChatMessage, DomainError> {
-    let (id, role, text, status, metadata_json, ts_ms) = row;
+async fn insert_chat_messages(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    session_key: &str,
+    messages: &[ChatMessage],
+) -> Result<(), DomainError> {
+    for message in messages {
+        let metadata_json =
+            util::value_to_json_text(&message.metadata).map_err(DomainError::Storage)?;
+        sqlx::query(
+            "INSERT INTO chat_messages(message_id, session_key, role, text, status, metadata_json, ts_ms) \
+             VALUES(?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(message_id) DO UPDATE SET \
+               session_key = excluded.session_key, \
+               role = excluded.role, \
+               text = excluded.text, \
+               status = excluded.status, \
+               metadata_json = excluded.metadata_json, \
+               ts_ms = excluded.ts_ms",
+        )
+        .bind(&message.id)
+        .bind(session_key)
+        .bind(&message.role)
+        .bind(&message.text)
+        .bind(&message.status)
+        .bind(metadata_json)
+        .bind(i64::try_from(message.ts).unwrap_or(i64::MAX))
+        .execute(&mut **tx)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to insert chat message: {error}")))?;
+    }
+    Ok(())
+}
+
+fn map_chat_row(row: ChatMessageRow) -> Result<ChatMessage, DomainError> {
+    let (id, role, text, status, metadata_json, ts_ms, pinned) = row;
     let metadata = util::json_text_to_value(&metadata_json).map_err(DomainError::Storage)?;
     Ok(ChatMessage {
         id,
@@ -95,5 +364,6 @@ fn map_chat_row(
         status,
         ts: u64::try_from(ts_ms).unwrap_or(0),
         metadata,
+        pinned,
     })
 }
@@ -7,6 +7,7 @@ use crate::{
 
 impl SqliteStore {
     pub async fn load_config_doc(&self) -> Result<Value, DomainError> {
+        let _timer = self.query_timer("load_config_doc");
         let Some(entry) = self.get_config_entry("root").await? else {
             return Ok(json!({}));
         };
@@ -19,6 +20,7 @@ impl SqliteStore {
     }
 
     pub async fn save_config_doc(&self, value: &Value) -> Result<(), DomainError> {
+        let _timer = self.query_timer("save_config_doc");
         if !value.is_object() {
             return Err(DomainError::InvalidRequest(
                 "config payload must be an object".to_owned(),
@@ -31,6 +33,7 @@ impl SqliteStore {
     }
 
     pub async fn get_config_entry(&self, key: &str) -> Result<Option<ConfigEntry>, DomainError> {
+        let _timer = self.query_timer("get_config_entry");
         let row = sqlx::query_as::<_, (String, String, i64)>(
             "SELECT key, value_json, updated_at_ms FROM config_entries WHERE key = ? LIMIT 1",
         )
@@ -47,30 +50,152 @@ impl SqliteStore {
         key: &str,
         value: &Value,
     ) -> Result<ConfigEntry, DomainError> {
+        let _timer = self.query_timer("set_config_entry");
+        let now = super::util::now_unix_ms();
+        upsert_config_entry_row(self.pool(), key, value, now).await?;
+
+        Ok(ConfigEntry {
+            key: key.to_owned(),
+            value: value.clone(),
+            updated_at_ms: now,
+        })
+    }
+
+    /// Stores `value` under `key` unless an entry already exists, and returns whichever entry
+    /// ends up stored, so concurrent first writers all agree on one value.
+    pub async fn insert_config_entry_if_absent(
+        &self,
+        key: &str,
+        value: &Value,
+    ) -> Result<ConfigEntry, DomainError> {
+        let _timer = self.query_timer("insert_config_entry_if_absent");
         let json_text = serde_json::to_string(value).map_err(|error| {
             DomainError::Storage(format!("failed to serialize config value: {error}"))
         })?;
-        let now = super::util::now_unix_ms();
-
         sqlx::query(
             "INSERT INTO config_entries(key, value_json, updated_at_ms) VALUES(?, ?, ?) \
-             ON CONFLICT(key) DO UPDATE SET value_json = excluded.value_json, updated_at_ms = excluded.updated_at_ms",
+             ON CONFLICT(key) DO NOTHING",
         )
         .bind(key)
         .bind(json_text)
-        .bind(i64::try_from(now).unwrap_or(i64::MAX))
+        .bind(i64::try_from(super::util::now_unix_ms()).unwrap_or(i64::MAX))
         .execute(self.pool())
         .await
-        .map_err(|error| DomainError::Storage(format!("failed to persist config entry: {error}")))?;
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to persist config entry: {error}"))
+        })?;
 
-        Ok(ConfigEntry {
-            key: key.to_owned(),
-            value: value.clone(),
-            updated_at_ms: now,
+        self.get_config_entry(key).await?.ok_or_else(|| {
+            DomainError::Storage(format!("config entry {key} vanished after insert"))
         })
     }
 
+    /// Replaces the entry under `key` with `next` only while it still holds `expected`, and reports
+    /// whether it did, so a state transition read from the entry happens at most once.
+    pub async fn compare_and_set_config_entry(
+        &self,
+        key: &str,
+        expected: &Value,
+        next: &Value,
+    ) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("compare_and_set_config_entry");
+        let serialize = |value: &Value| {
+            serde_json::to_string(value).map_err(|error| {
+                DomainError::Storage(format!("failed to serialize config value: {error}"))
+            })
+        };
+        let result = sqlx::query(
+            "UPDATE config_entries SET value_json = ?, updated_at_ms = ? \
+             WHERE key = ? AND value_json = ?",
+        )
+        .bind(serialize(next)?)
+        .bind(i64::try_from(super::util::now_unix_ms()).unwrap_or(i64::MAX))
+        .bind(key)
+        .bind(serialize(expected)?)
+        .execute(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to persist config entry: {error}"))
+        })?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Writes `entries` in one transaction. With `replace_prefix`, every other entry under that
+    /// prefix is removed in the same transaction; returns the removed keys.
+    pub async fn set_config_entries(
+        &self,
+        entries: &[(String, Value)],
+        replace_prefix: Option<&str>,
+    ) -> Result<Vec<String>, DomainError> {
+        let _timer = self.query_timer("set_config_entries");
+        let now = super::util::now_unix_ms();
+        let mut tx = self
+            .pool()
+            .begin()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        let mut removed = Vec::new();
+        if let Some(prefix) = replace_prefix {
+            removed = delete_config_prefix_rows(&mut *tx, prefix).await?;
+            removed.retain(|key| !entries.iter().any(|(written, _)| written == key));
+        }
+        for (key, value) in entries {
+            upsert_config_entry_row(&mut *tx, key, value, now).await?;
+        }
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))?;
+
+        Ok(removed)
+    }
+
+    /// Deletes every entry under `prefix` plus the listed `keys` in one transaction and returns
+    /// the deleted keys. `dry_run` only reports what would be deleted.
+    pub async fn delete_config_entries(
+        &self,
+        prefix: Option<&str>,
+        keys: &[String],
+        dry_run: bool,
+    ) -> Result<Vec<String>, DomainError> {
+        let _timer = self.query_timer("delete_config_entries");
+        let mut tx = self
+            .pool()
+            .begin()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        let mut deleted = match prefix {
+            Some(prefix) => delete_config_prefix_rows(&mut *tx, prefix).await?,
+            None => Vec::new(),
+        };
+        for key in keys {
+            if deleted.contains(key) {
+                continue;
+            }
+            let removed = sqlx::query("DELETE FROM config_entries WHERE key = ?")
+                .bind(key)
+                .execute(&mut *tx)
+                .await
+                .map_err(|error| {
+                    DomainError::Storage(format!("failed to delete config entry: {error}"))
+                })?;
+            if removed.rows_affected() > 0 {
+                deleted.push(key.clone());
+            }
+        }
+        let finished = if dry_run {
+            tx.rollback().await
+        } else {
+            tx.commit().await
+        };
+        finished.map_err(|error| DomainError::Storage(format!("failed to finish tx: {error}")))?;
+
+        deleted.sort();
+        Ok(deleted)
+    }
+
     pub async fn delete_config_entry(&self, key: &str) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("delete_config_entry");
         let result = sqlx::query("DELETE FROM config_entries WHERE key = ?")
             .bind(key)
             .execute(self.pool())
@@ -87,6 +212,7 @@ impl SqliteStore {
         prefix: &str,
         limit: Option<usize>,
     ) -> Result<Vec<ConfigEntry>, DomainError> {
+        let _timer = self.query_timer("list_config_entries");
         let mut query = String::from(
             "SELECT key, value_json, updated_at_ms FROM config_entries WHERE key LIKE ? ORDER BY updated_at_ms DESC",
         );
@@ -108,6 +234,50 @@ impl SqliteStore {
     }
 }
 
+async fn upsert_config_entry_row<'e, E>(
+    executor: E,
+    key: &str,
+    value: &Value,
+    now: u64,
+) -> Result<(), DomainError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    let json_text = serde_json::to_string(value).map_err(|error| {
+        DomainError::Storage(format!("failed to serialize config value: {error}"))
+    })?;
+
+    sqlx::query(
+        "INSERT INTO config_entries(key, value_json, updated_at_ms) VALUES(?, ?, ?) \
+         ON CONFLICT(key) DO UPDATE SET value_json = excluded.value_json, updated_at_ms = excluded.updated_at_ms",
+    )
+    .bind(key)
+    .bind(json_text)
+    .bind(i64::try_from(now).unwrap_or(i64::MAX))
+    .execute(executor)
+    .await
+    .map_err(|error| DomainError::Storage(format!("failed to persist config entry: {error}")))?;
+
+    Ok(())
+}
+
+/// Matches the prefix literally (unlike `LIKE`, `_` and `%` in keys are not wildcards).
+async fn delete_config_prefix_rows<'e, E>(
+    executor: E,
+    prefix: &str,
+) -> Result<Vec<String>, DomainError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    sqlx::query_scalar::<_, String>(
+        "DELETE FROM config_entries WHERE substr(key, 1, length(?1)) = ?1 RETURNING key",
+    )
+    .bind(prefix)
+    .fetch_all(executor)
+    .await
+    .map_err(|error| DomainError::Storage(format!("failed to delete config entries: {error}")))
+}
+
 fn map_config_entry_row(row: (String, String, i64)) -> Result<ConfigEntry, DomainError> {
     let (key, value_json, updated_at_ms) = row;
     let value = serde_json::from_str::<Value>(&value_json)
@@ -119,3 +289,136 @@ fn map_config_entry_row(row: (String, String, i64)) -> Result<ConfigEntry, Domai
         updated_at_ms: u64::try_from(updated_at_ms).unwrap_or(0),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::SqliteStore;
+
+    async fn make_store() -> (TempDir, SqliteStore) {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let store = SqliteStore::connect(&temp.path().join("state.db"))
+            .await
+            .expect("sqlite store should connect");
+        (temp, store)
+    }
+
+    #[tokio::test]
+    async fn compare_and_set_applies_only_over_the_expected_value() {
+        let (_temp, store) = make_store().await;
+        let pending = json!({ "status": "resolved" });
+        store
+            .set_config_entry("approval/1", &pending)
+            .await
+            .expect("entry should persist");
+
+        let consumed = json!({ "status": "consumed" });
+        let (first, second) = tokio::join!(
+            store.compare_and_set_config_entry("approval/1", &pending, &consumed),
+            store.compare_and_set_config_entry("approval/1", &pending, &consumed),
+        );
+        let swaps = [first, second]
+            .into_iter()
+            .map(|swapped| swapped.expect("compare and set should run"))
+            .filter(|swapped| *swapped)
+            .count();
+        assert_eq!(swaps, 1);
+        assert_eq!(
+            store
+                .get_config_entry("approval/1")
+                .await
+                .expect("entry should load")
+                .map(|entry| entry.value),
+            Some(consumed)
+        );
+        assert!(
+            !store
+                .compare_and_set_config_entry("missing", &pending, &pending)
+                .await
+                .expect("compare and set should run")
+        );
+    }
+
+    #[tokio::test]
+    async fn prefix_bulk_operations_match_literally_and_honor_dry_run() {
+        let (_temp, store) = make_store().await;
+        for key in ["runtime/a_b/1", "runtime/a_b/2", "runtime/axb/1", "root"] {
+            store
+                .set_config_entry(key, &json!({ "key": key }))
+                .await
+                .expect("entry should persist");
+        }
+
+        let preview = store
+            .delete_config_entries(Some("runtime/a_b/"), &[], true)
+            .await
+            .expect("dry run should succeed");
+        assert_eq!(preview, vec!["runtime/a_b/1", "runtime/a_b/2"]);
+        assert!(
+            store
+                .get_config_entry("runtime/a_b/1")
+                .await
+                .expect("entry should load")
+                .is_some()
+        );
+
+        let removed = store
+            .set_config_entries(
+                &[
+                    ("runtime/a_b/2".to_owned(), json!(2)),
+                    ("runtime/a_b/3".to_owned(), json!(3)),
+                ],
+                Some("runtime/a_b/"),
+            )
+            .await
+            .expect("replace should succeed");
+        assert_eq!(removed, vec!["runtime/a_b/1"]);
+
+        let deleted = store
+            .delete_config_entries(Some("runtime/a_b/"), &["root".to_owned()], false)
+            .await
+            .expect("delete should succeed");
+        assert_eq!(deleted, vec!["root", "runtime/a_b/2", "runtime/a_b/3"]);
+        let remaining = store
+            .list_config_entries("", None)
+            .await
+            .expect("entries should list");
+        assert_eq!(
+            remaining
+                .iter()
+                .map(|entry| entry.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["runtime/axb/1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn insert_if_absent_keeps_the_first_value() {
+        let (_temp, store) = make_store().await;
+        let inserts = (0..8).map(|index| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                store
+                    .insert_config_entry_if_absent("runtime/secret", &json!(format!("s{index}")))
+                    .await
+                    .expect("insert should succeed")
+                    .value
+            })
+        });
+        let mut stored = Vec::new();
+        for insert in inserts.collect::<Vec<_>>() {
+            stored.push(insert.await.expect("insert task should finish"));
+        }
+        assert!(stored.iter().all(|value| *value == stored[0]), "{stored:?}");
+        assert_eq!(
+            store
+                .get_config_entry("runtime/secret")
+                .await
+                .expect("entry should load")
+                .map(|entry| entry.value),
+            Some(stored[0].clone())
+        );
+    }
+}
@@ -1,7 +1,10 @@
 use crate::{
     domain::{
         error::DomainError,
-        models::{CronJobPatch, CronJobRecord, CronPayload, CronRunRecord, CronSchedule},
+        models::{
+            CronJobPatch, CronJobRecord, CronPayload, CronRunQuery, CronRunRecord, CronRunStats,
+            CronSchedule,
+        },
     },
     storage::{SqliteStore, util},
 };
@@ -17,6 +20,7 @@ type CronJobRow = (
     i64,
     Option<i64>,
     Option<i64>,
+    Option<i64>,
 );
 
 type CronRunRow = (
@@ -28,12 +32,15 @@ type CronRunRow = (
     i64,
     i64,
     i64,
+    i64,
+    Option<String>,
 );
 
 impl SqliteStore {
     pub async fn list_cron_jobs(&self) -> Result<Vec<CronJobRecord>, DomainError> {
+        let _timer = self.query_timer("list_cron_jobs");
         let rows = sqlx::query_as::<_, CronJobRow>(
-            "SELECT job_id, name, enabled, schedule_json, payload_json, metadata_json, created_at_ms, updated_at_ms, last_run_ms, next_run_ms \
+            "SELECT job_id, name, enabled, schedule_json, payload_json, metadata_json, created_at_ms, updated_at_ms, last_run_ms, next_run_ms, max_concurrent \
              FROM cron_jobs ORDER BY name ASC",
         )
         .fetch_all(self.pool())
@@ -44,8 +51,9 @@ impl SqliteStore {
     }
 
     pub async fn get_cron_job(&self, id: &str) -> Result<Option<CronJobRecord>, DomainError> {
+        let _timer = self.query_timer("get_cron_job");
         let row = sqlx::query_as::<_, CronJobRow>(
-            "SELECT job_id, name, enabled, schedule_json, payload_json, metadata_json, created_at_ms, updated_at_ms, last_run_ms, next_run_ms \
+            "SELECT job_id, name, enabled, schedule_json, payload_json, metadata_json, created_at_ms, updated_at_ms, last_run_ms, next_run_ms, max_concurrent \
              FROM cron_jobs WHERE job_id = ? LIMIT 1",
         )
         .bind(id)
@@ -57,14 +65,15 @@ impl SqliteStore {
     }
 
     pub async fn insert_cron_job(&self, job: &CronJobRecord) -> Result<(), DomainError> {
+        let _timer = self.query_timer("insert_cron_job");
         let schedule_json = util::to_json_text(&job.schedule).map_err(DomainError::Storage)?;
         let payload_json = util::to_json_text(&job.payload).map_err(DomainError::Storage)?;
         let metadata_json =
             util::value_to_json_text(&job.metadata).map_err(DomainError::Storage)?;
 
         sqlx::query(
-            "INSERT INTO cron_jobs(job_id, name, enabled, schedule_json, payload_json, metadata_json, created_at_ms, updated_at_ms, last_run_ms, next_run_ms) \
-             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO cron_jobs(job_id, name, enabled, schedule_json, payload_json, metadata_json, created_at_ms, updated_at_ms, last_run_ms, next_run_ms, max_concurrent) \
+             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&job.id)
         .bind(&job.name)
@@ -76,6 +85,7 @@ impl SqliteStore {
         .bind(i64::try_from(job.updated_at_ms).unwrap_or(i64::MAX))
         .bind(job.last_run_ms.map(|value| i64::try_from(value).unwrap_or(i64::MAX)))
         .bind(job.next_run_ms.map(|value| i64::try_from(value).unwrap_or(i64::MAX)))
+        .bind(job.max_concurrent.map(i64::from))
         .execute(self.pool())
         .await
         .map_err(|error| DomainError::Storage(format!("failed to insert cron job: {error}")))?;
@@ -87,6 +97,7 @@ impl SqliteStore {
         id: &str,
         patch: CronJobPatch,
     ) -> Result<CronJobRecord, DomainError> {
+        let _timer = self.query_timer("update_cron_job");
         let Some(mut existing) = self.get_cron_job(id).await? else {
             return Err(DomainError::NotFound(format!("cron job not found: {id}")));
         };
@@ -109,6 +120,9 @@ impl SqliteStore {
         if let Some(next_run_ms) = patch.next_run_ms {
             existing.next_run_ms = next_run_ms;
         }
+        if let Some(max_concurrent) = patch.max_concurrent {
+            existing.max_concurrent = max_concurrent;
+        }
         existing.updated_at_ms = util::now_unix_ms();
 
         let schedule_json = util::to_json_text(&existing.schedule).map_err(DomainError::Storage)?;
@@ -118,7 +132,7 @@ impl SqliteStore {
 
         sqlx::query(
             "UPDATE cron_jobs SET name = ?, enabled = ?, schedule_json = ?, payload_json = ?, metadata_json = ?, \
-             updated_at_ms = ?, last_run_ms = ?, next_run_ms = ? WHERE job_id = ?",
+             updated_at_ms = ?, last_run_ms = ?, next_run_ms = ?, max_concurrent = ? WHERE job_id = ?",
         )
         .bind(&existing.name)
         .bind(if existing.enabled { 1_i64 } else { 0_i64 })
@@ -128,6 +142,7 @@ impl SqliteStore {
         .bind(i64::try_from(existing.updated_at_ms).unwrap_or(i64::MAX))
         .bind(existing.last_run_ms.map(|value| i64::try_from(value).unwrap_or(i64::MAX)))
         .bind(existing.next_run_ms.map(|value| i64::try_from(value).unwrap_or(i64::MAX)))
+        .bind(existing.max_concurrent.map(i64::from))
         .bind(&existing.id)
         .execute(self.pool())
         .await
@@ -137,6 +152,7 @@ impl SqliteStore {
     }
 
     pub async fn remove_cron_job(&self, id: &str) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("remove_cron_job");
         let result = sqlx::query("DELETE FROM cron_jobs WHERE job_id = ?")
             .bind(id)
             .execute(self.pool())
@@ -146,9 +162,10 @@ impl SqliteStore {
     }
 
     pub async fn add_cron_run(&self, run: &CronRunRecord) -> Result<(), DomainError> {
+        let _timer = self.query_timer("add_cron_run");
         sqlx::query(
-            "INSERT INTO cron_runs(run_id, job_id, status, output, error, manual, started_at_ms, finished_at_ms) \
-             VALUES(?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO cron_runs(run_id, job_id, status, output, error, manual, started_at_ms, finished_at_ms, queue_wait_ms, agent_run_id) \
+             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&run.id)
         .bind(&run.job_id)
@@ -158,52 +175,149 @@ impl SqliteStore {
         .bind(if run.manual { 1_i64 } else { 0_i64 })
         .bind(i64::try_from(run.started_at_ms).unwrap_or(i64::MAX))
         .bind(i64::try_from(run.finished_at_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(run.queue_wait_ms).unwrap_or(i64::MAX))
+        .bind(&run.agent_run_id)
         .execute(self.pool())
         .await
         .map_err(|error| DomainError::Storage(format!("failed to insert cron run: {error}")))?;
         Ok(())
     }
 
+    pub async fn get_cron_run(&self, run_id: &str) -> Result<Option<CronRunRecord>, DomainError> {
+        let _timer = self.query_timer("get_cron_run");
+        let row = sqlx::query_as::<_, CronRunRow>(
+            "SELECT run_id, job_id, status, output, error, manual, started_at_ms, finished_at_ms, queue_wait_ms, agent_run_id \
+             FROM cron_runs WHERE run_id = ? LIMIT 1",
+        )
+        .bind(run_id)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to get cron run: {error}")))?;
+
+        row.map(map_cron_run_row).transpose()
+    }
+
     pub async fn list_cron_runs(
         &self,
-        job_id: Option<&str>,
-        limit: Option<usize>,
+        query: &CronRunQuery,
     ) -> Result<Vec<CronRunRecord>, DomainError> {
-        let query = if job_id.is_some() {
-            "SELECT run_id, job_id, status, output, error, manual, started_at_ms, finished_at_ms \
-             FROM cron_runs WHERE job_id = ? ORDER BY started_at_ms DESC"
-        } else {
-            "SELECT run_id, job_id, status, output, error, manual, started_at_ms, finished_at_ms \
-             FROM cron_runs ORDER BY started_at_ms DESC"
-        };
+        let _timer = self.query_timer("list_cron_runs");
+        let manual = query.manual.map(i64::from);
+        let since = query
+            .since_ms
+            .map(|value| i64::try_from(value).unwrap_or(i64::MAX));
+        let until = query
+            .until_ms
+            .map(|value| i64::try_from(value).unwrap_or(i64::MAX));
+        let (before_ms, before_id) = query
+            .before
+            .as_ref()
+            .map(|(started_at_ms, run_id)| {
+                (
+                    i64::try_from(*started_at_ms).unwrap_or(i64::MAX),
+                    run_id.as_str(),
+                )
+            })
+            .unzip();
+        let rows = sqlx::query_as::<_, CronRunRow>(
+            "SELECT run_id, job_id, status, output, error, manual, started_at_ms, finished_at_ms, queue_wait_ms, agent_run_id \
+             FROM cron_runs \
+             WHERE (? IS NULL OR job_id = ?) \
+               AND (? IS NULL OR status = ?) \
+               AND (? IS NULL OR manual = ?) \
+               AND (? IS NULL OR started_at_ms >= ?) \
+               AND (? IS NULL OR started_at_ms <= ?) \
+               AND (? IS NULL OR started_at_ms < ? OR (started_at_ms = ? AND run_id < ?)) \
+             ORDER BY started_at_ms DESC, run_id DESC LIMIT ?",
+        )
+        .bind(query.job_id.as_deref())
+        .bind(query.job_id.as_deref())
+        .bind(query.status.as_deref())
+        .bind(query.status.as_deref())
+        .bind(manual)
+        .bind(manual)
+        .bind(since)
+        .bind(since)
+        .bind(until)
+        .bind(until)
+        .bind(before_ms)
+        .bind(before_ms)
+        .bind(before_ms)
+        .bind(before_id)
+        .bind(
+            query
+                .limit
+                .map_or(-1, |limit| i64::try_from(limit).unwrap_or(i64::MAX)),
+        )
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list cron runs: {error}")))?;
 
-        let mut rows = if let Some(job_id) = job_id {
-            sqlx::query_as::<_, CronRunRow>(query)
-                .bind(job_id)
-                .fetch_all(self.pool())
-                .await
-                .map_err(|error| {
-                    DomainError::Storage(format!("failed to list cron runs: {error}"))
-                })?
-        } else {
-            sqlx::query_as::<_, CronRunRow>(query)
-                .fetch_all(self.pool())
-                .await
-                .map_err(|error| {
-                    DomainError::Storage(format!("failed to list cron runs: {error}"))
-                })?
-        }
-        .into_iter()
-        .map(map_cron_run_row)
-        .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter().map(map_cron_run_row).collect()
+    }
 
-        if let Some(limit) = limit {
-            rows.truncate(limit);
-        }
-        Ok(rows)
+    /// Per-job aggregates over the runs matching `query`; its cursor and limit are ignored.
+    pub async fn cron_run_stats(
+        &self,
+        query: &CronRunQuery,
+    ) -> Result<Vec<CronRunStats>, DomainError> {
+        let _timer = self.query_timer("cron_run_stats");
+        let manual = query.manual.map(i64::from);
+        let since = query
+            .since_ms
+            .map(|value| i64::try_from(value).unwrap_or(i64::MAX));
+        let until = query
+            .until_ms
+            .map(|value| i64::try_from(value).unwrap_or(i64::MAX));
+        let rows = sqlx::query_as::<_, (String, i64, i64, Option<f64>, i64)>(
+            "SELECT job_id, COUNT(*), SUM(CASE WHEN status = 'ok' THEN 1 ELSE 0 END), \
+                    AVG(finished_at_ms - started_at_ms), MAX(started_at_ms) \
+             FROM cron_runs \
+             WHERE (? IS NULL OR job_id = ?) \
+               AND (? IS NULL OR status = ?) \
+               AND (? IS NULL OR manual = ?) \
+               AND (? IS NULL OR started_at_ms >= ?) \
+               AND (? IS NULL OR started_at_ms <= ?) \
+             GROUP BY job_id ORDER BY job_id",
+        )
+        .bind(query.job_id.as_deref())
+        .bind(query.job_id.as_deref())
+        .bind(query.status.as_deref())
+        .bind(query.status.as_deref())
+        .bind(manual)
+        .bind(manual)
+        .bind(since)
+        .bind(since)
+        .bind(until)
+        .bind(until)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to aggregate cron runs: {error}")))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(job_id, runs, ok, avg_duration_ms, last_started_at_ms)| {
+                let runs = u64::try_from(runs).unwrap_or(0);
+                let ok = u64::try_from(ok).unwrap_or(0);
+                CronRunStats {
+                    job_id,
+                    runs,
+                    ok,
+                    errors: runs.saturating_sub(ok),
+                    success_rate: if runs == 0 {
+                        0.0
+                    } else {
+                        ok as f64 / runs as f64
+                    },
+                    avg_duration_ms: avg_duration_ms.unwrap_or(0.0).max(0.0).round() as u64,
+                    last_started_at_ms: u64::try_from(last_started_at_ms).unwrap_or(0),
+                }
+            })
+            .collect())
     }
 
     pub async fn prune_cron_runs(&self, limit: usize) -> Result<(), DomainError> {
+        let _timer = self.query_timer("prune_cron_runs");
         let ids = sqlx::query_scalar::<_, String>(
             "SELECT run_id FROM cron_runs ORDER BY started_at_ms DESC LIMIT -1 OFFSET ?",
         )
@@ -244,6 +358,7 @@ impl SqliteStore {
         last_run_ms: Option<u64>,
         next_run_ms: Option<u64>,
     ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("update_cron_job_runtime");
         sqlx::query("UPDATE cron_jobs SET last_run_ms = ?, next_run_ms = ?, updated_at_ms = ? WHERE job_id = ?")
             .bind(last_run_ms.map(|value| i64::try_from(value).unwrap_or(i64::MAX)))
             .bind(next_run_ms.map(|value| i64::try_from(value).unwrap_or(i64::MAX)))
@@ -268,6 +383,7 @@ fn map_cron_job_row(row: CronJobRow) -> Result<CronJobRecord, DomainError> {
         updated_at_ms,
         last_run_ms,
         next_run_ms,
+        max_concurrent,
     ) = row;
 
     let schedule =
@@ -287,11 +403,23 @@ fn map_cron_job_row(row: CronJobRow) -> Result<CronJobRecord, DomainError> {
         updated_at_ms: u64::try_from(updated_at_ms).unwrap_or(0),
         last_run_ms: last_run_ms.and_then(|value| u64::try_from(value).ok()),
         next_run_ms: next_run_ms.and_then(|value| u64::try_from(value).ok()),
+        max_concurrent: max_concurrent.and_then(|value| u32::try_from(value).ok()),
     })
 }
 
 fn map_cron_run_row(row: CronRunRow) -> Result<CronRunRecord, DomainError> {
-    let (id, job_id, status, output, error, manual, started_at_ms, finished_at_ms) = row;
+    let (
+        id,
+        job_id,
+        status,
+        output,
+        error,
+        manual,
+        started_at_ms,
+        finished_at_ms,
+        queue_wait_ms,
+        agent_run_id,
+    ) = row;
     Ok(CronRunRecord {
         id,
         job_id,
@@ -301,5 +429,7 @@ fn map_cron_run_row(row: CronRunRow) -> Result<CronRunRecord, DomainError> {
         manual: manual == 1,
         started_at_ms: u64::try_from(started_at_ms).unwrap_or(0),
         finished_at_ms: u64::try_from(finished_at_ms).unwrap_or(0),
+        queue_wait_ms: u64::try_from(queue_wait_ms).unwrap_or(0),
+        agent_run_id,
     })
 }
@@ -0,0 +1,172 @@
+use crate::{
+    domain::{
+        error::DomainError,
+        models::{DeliveryStatus, MessageDelivery},
+    },
+    storage::SqliteStore,
+};
+
+type MessageDeliveryRow = (
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    i64,
+    i64,
+    Option<i64>,
+    Option<i64>,
+    Option<i64>,
+);
+
+const DELIVERY_COLUMNS: &str = "id, session_key, run_id, channel, conversation_id, status, \
+     platform_message_id, error, created_at_ms, updated_at_ms, sent_at_ms, delivered_at_ms, read_at_ms";
+
+impl SqliteStore {
+    pub async fn upsert_message_delivery(
+        &self,
+        delivery: &MessageDelivery,
+    ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("upsert_message_delivery");
+        sqlx::query(&format!(
+            "INSERT INTO message_deliveries({DELIVERY_COLUMNS}) \
+             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(id) DO UPDATE SET status = excluded.status, \
+             platform_message_id = excluded.platform_message_id, error = excluded.error, \
+             updated_at_ms = excluded.updated_at_ms, sent_at_ms = excluded.sent_at_ms, \
+             delivered_at_ms = excluded.delivered_at_ms, read_at_ms = excluded.read_at_ms"
+        ))
+        .bind(&delivery.id)
+        .bind(&delivery.session_key)
+        .bind(delivery.run_id.as_deref())
+        .bind(&delivery.channel)
+        .bind(&delivery.conversation_id)
+        .bind(delivery.status.label())
+        .bind(delivery.platform_message_id.as_deref())
+        .bind(delivery.error.as_deref())
+        .bind(i64::try_from(delivery.created_at_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(delivery.updated_at_ms).unwrap_or(i64::MAX))
+        .bind(delivery.sent_at_ms.map(to_db_ms))
+        .bind(delivery.delivered_at_ms.map(to_db_ms))
+        .bind(delivery.read_at_ms.map(to_db_ms))
+        .execute(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to upsert message delivery: {error}"))
+        })?;
+        Ok(())
+    }
+
+    pub async fn get_message_delivery(
+        &self,
+        id: &str,
+    ) -> Result<Option<MessageDelivery>, DomainError> {
+        let _timer = self.query_timer("get_message_delivery");
+        let row = sqlx::query_as::<_, MessageDeliveryRow>(&format!(
+            "SELECT {DELIVERY_COLUMNS} FROM message_deliveries WHERE id = ? LIMIT 1"
+        ))
+        .bind(id)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to get message delivery: {error}"))
+        })?;
+
+        row.map(map_message_delivery_row).transpose()
+    }
+
+    pub async fn find_message_delivery_by_platform_id(
+        &self,
+        channel: &str,
+        platform_message_id: &str,
+    ) -> Result<Option<MessageDelivery>, DomainError> {
+        let _timer = self.query_timer("find_message_delivery_by_platform_id");
+        let row = sqlx::query_as::<_, MessageDeliveryRow>(&format!(
+            "SELECT {DELIVERY_COLUMNS} FROM message_deliveries \
+             WHERE channel = ? AND platform_message_id = ? \
+             ORDER BY created_at_ms DESC LIMIT 1"
+        ))
+        .bind(channel)
+        .bind(platform_message_id)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to find message delivery: {error}"))
+        })?;
+
+        row.map(map_message_delivery_row).transpose()
+    }
+
+    /// Lists deliveries newest first, optionally narrowed to one session and/or run.
+    pub async fn list_message_deliveries(
+        &self,
+        session_key: Option<&str>,
+        run_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<MessageDelivery>, DomainError> {
+        let _timer = self.query_timer("list_message_deliveries");
+        let rows = sqlx::query_as::<_, MessageDeliveryRow>(&format!(
+            "SELECT {DELIVERY_COLUMNS} FROM message_deliveries \
+             WHERE (? IS NULL OR session_key = ?) AND (? IS NULL OR run_id = ?) \
+             ORDER BY created_at_ms DESC, id ASC LIMIT ?"
+        ))
+        .bind(session_key)
+        .bind(session_key)
+        .bind(run_id)
+        .bind(run_id)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to list message deliveries: {error}"))
+        })?;
+
+        rows.into_iter().map(map_message_delivery_row).collect()
+    }
+}
+
+fn to_db_ms(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+fn from_db_ms(value: i64) -> u64 {
+    u64::try_from(value).unwrap_or(0)
+}
+
+fn map_message_delivery_row(row: MessageDeliveryRow) -> Result<MessageDelivery, DomainError> {
+    let (
+        id,
+        session_key,
+        run_id,
+        channel,
+        conversation_id,
+        status,
+        platform_message_id,
+        error,
+        created_at_ms,
+        updated_at_ms,
+        sent_at_ms,
+        delivered_at_ms,
+        read_at_ms,
+    ) = row;
+    let status = DeliveryStatus::parse(&status)
+        .ok_or_else(|| DomainError::Storage(format!("invalid delivery status: {status}")))?;
+    Ok(MessageDelivery {
+        id,
+        session_key,
+        run_id,
+        channel,
+        conversation_id,
+        status,
+        platform_message_id,
+        error,
+        created_at_ms: from_db_ms(created_at_ms),
+        updated_at_ms: from_db_ms(updated_at_ms),
+        sent_at_ms: sent_at_ms.map(from_db_ms),
+        delivered_at_ms: delivered_at_ms.map(from_db_ms),
+        read_at_ms: read_at_ms.map(from_db_ms),
+    })
+}
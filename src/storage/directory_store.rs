@@ -0,0 +1,398 @@
+use sqlx::{QueryBuilder, Sqlite};
+
+use crate::{
+    domain::{
+        error::DomainError,
+        models::{ChannelDirectoryEntry, ChannelDirectoryInput},
+    },
+    storage::{SqliteStore, util},
+};
+
+const MAX_DIRECTORY_PARTICIPANTS: usize = 32;
+/// Joins participants in `participants_search`, the plain-text column `query` filters on.
+const PARTICIPANT_SEPARATOR: &str = "\n";
+
+type DirectoryRow = (
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+    i64,
+    i64,
+    i64,
+);
+
+impl SqliteStore {
+    /// Folds one inbound observation into the conversation's entry. The read and the write share
+    /// one `BEGIN IMMEDIATE` transaction, so concurrent messages on a conversation each count and
+    /// keep their participant.
+    pub async fn record_channel_directory_entry(
+        &self,
+        input: &ChannelDirectoryInput,
+    ) -> Result<ChannelDirectoryEntry, DomainError> {
+        let _timer = self.query_timer("record_channel_directory_entry");
+        let mut tx = self
+            .pool()
+            .begin_with("BEGIN IMMEDIATE")
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        let existing =
+            fetch_directory_entry(&mut *tx, &input.channel, &input.conversation_id).await?;
+
+        let entry = match existing {
+            Some(mut entry) => {
+                if input.title.is_some() {
+                    entry.title.clone_from(&input.title);
+                }
+                if let Some(kind) = &input.kind {
+                    entry.kind.clone_from(kind);
+                }
+                if let Some(participant) = &input.participant {
+                    merge_participant(&mut entry.participants, participant);
+                }
+                entry.message_count = entry.message_count.saturating_add(1);
+                entry.last_seen_ms = entry.last_seen_ms.max(input.seen_at_ms);
+                entry
+            }
+            None => {
+                let mut participants = Vec::new();
+                if let Some(participant) = &input.participant {
+                    merge_participant(&mut participants, participant);
+                }
+                ChannelDirectoryEntry {
+                    channel: input.channel.clone(),
+                    conversation_id: input.conversation_id.clone(),
+                    title: input.title.clone(),
+                    kind: input.kind.clone().unwrap_or_else(|| "direct".to_owned()),
+                    participants,
+                    message_count: 1,
+                    first_seen_ms: input.seen_at_ms,
+                    last_seen_ms: input.seen_at_ms,
+                }
+            }
+        };
+
+        let participants_json =
+            util::to_json_text(&entry.participants).map_err(DomainError::Storage)?;
+        sqlx::query(
+            "INSERT INTO channel_directory(channel, conversation_id, title, kind, participants_json, \
+             participants_search, message_count, first_seen_ms, last_seen_ms) \
+             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(channel, conversation_id) DO UPDATE SET \
+               title = excluded.title, \
+               kind = excluded.kind, \
+               participants_json = excluded.participants_json, \
+               participants_search = excluded.participants_search, \
+               message_count = excluded.message_count, \
+               last_seen_ms = excluded.last_seen_ms",
+        )
+        .bind(&entry.channel)
+        .bind(&entry.conversation_id)
+        .bind(&entry.title)
+        .bind(&entry.kind)
+        .bind(participants_json)
+        .bind(entry.participants.join(PARTICIPANT_SEPARATOR))
+        .bind(i64::try_from(entry.message_count).unwrap_or(i64::MAX))
+        .bind(i64::try_from(entry.first_seen_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(entry.last_seen_ms).unwrap_or(i64::MAX))
+        .execute(&mut *tx)
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to record channel directory entry: {error}"))
+        })?;
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))?;
+
+        Ok(entry)
+    }
+
+    pub async fn get_channel_directory_entry(
+        &self,
+        channel: &str,
+        conversation_id: &str,
+    ) -> Result<Option<ChannelDirectoryEntry>, DomainError> {
+        let _timer = self.query_timer("get_channel_directory_entry");
+        fetch_directory_entry(self.pool(), channel, conversation_id).await
+    }
+
+    /// Entries for the listed `(channel, conversation_id)` pairs that exist, in one query per
+    /// batch of pairs.
+    pub async fn get_channel_directory_entries(
+        &self,
+        conversations: &[(&str, &str)],
+    ) -> Result<Vec<ChannelDirectoryEntry>, DomainError> {
+        let _timer = self.query_timer("get_channel_directory_entries");
+        let mut entries = Vec::with_capacity(conversations.len());
+        for batch in conversations.chunks(util::MAX_BATCH_BINDS / 2) {
+            let mut builder = QueryBuilder::<Sqlite>::new(
+                "SELECT channel, conversation_id, title, kind, participants_json, message_count, \
+                 first_seen_ms, last_seen_ms FROM channel_directory \
+                 WHERE (channel, conversation_id) IN (",
+            );
+            builder.push_values(batch, |mut row, (channel, conversation_id)| {
+                row.push_bind(*channel).push_bind(*conversation_id);
+            });
+            builder.push(")");
+            let rows = builder
+                .build_query_as::<DirectoryRow>()
+                .fetch_all(self.pool())
+                .await
+                .map_err(|error| {
+                    DomainError::Storage(format!(
+                        "failed to get channel directory entries: {error}"
+                    ))
+                })?;
+            for row in rows {
+                entries.push(map_directory_row(row)?);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Most recently seen conversations, optionally on one channel and matching `query` in the
+    /// conversation id, title, or a participant. Filters apply before `limit`.
+    pub async fn list_channel_directory_entries(
+        &self,
+        channel: Option<&str>,
+        query: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ChannelDirectoryEntry>, DomainError> {
+        let _timer = self.query_timer("list_channel_directory_entries");
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            "SELECT channel, conversation_id, title, kind, participants_json, message_count, \
+             first_seen_ms, last_seen_ms FROM channel_directory",
+        );
+        let mut first = true;
+        if let Some(channel) = channel {
+            util::push_condition(&mut builder, &mut first)
+                .push("channel = ")
+                .push_bind(channel);
+        }
+        if let Some(query) = query {
+            let pattern = util::like_contains_pattern(query);
+            util::push_condition(&mut builder, &mut first)
+                .push("(conversation_id LIKE ")
+                .push_bind(pattern.clone())
+                .push(" ESCAPE '\\' OR title LIKE ")
+                .push_bind(pattern.clone())
+                .push(" ESCAPE '\\' OR participants_search LIKE ")
+                .push_bind(pattern)
+                .push(" ESCAPE '\\')");
+        }
+        builder
+            .push(" ORDER BY last_seen_ms DESC LIMIT ")
+            .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+        let rows = builder
+            .build_query_as::<DirectoryRow>()
+            .fetch_all(self.pool())
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to list channel directory: {error}"))
+            })?;
+
+        rows.into_iter().map(map_directory_row).collect()
+    }
+}
+
+async fn fetch_directory_entry<'e, E>(
+    executor: E,
+    channel: &str,
+    conversation_id: &str,
+) -> Result<Option<ChannelDirectoryEntry>, DomainError>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let row = sqlx::query_as::<_, DirectoryRow>(
+        "SELECT channel, conversation_id, title, kind, participants_json, message_count, \
+         first_seen_ms, last_seen_ms \
+         FROM channel_directory WHERE channel = ? AND conversation_id = ? LIMIT 1",
+    )
+    .bind(channel)
+    .bind(conversation_id)
+    .fetch_optional(executor)
+    .await
+    .map_err(|error| {
+        DomainError::Storage(format!("failed to get channel directory entry: {error}"))
+    })?;
+
+    row.map(map_directory_row).transpose()
+}
+
+fn merge_participant(participants: &mut Vec<String>, participant: &str) {
+    participants.retain(|existing| existing != participant);
+    participants.push(participant.to_owned());
+    if participants.len() > MAX_DIRECTORY_PARTICIPANTS {
+        let overflow = participants.len() - MAX_DIRECTORY_PARTICIPANTS;
+        participants.drain(..overflow);
+    }
+}
+
+fn map_directory_row(row: DirectoryRow) -> Result<ChannelDirectoryEntry, DomainError> {
+    let (
+        channel,
+        conversation_id,
+        title,
+        kind,
+        participants_json,
+        message_count,
+        first_seen_ms,
+        last_seen_ms,
+    ) = row;
+    let participants =
+        util::from_json_text::<Vec<String>>(&participants_json).map_err(DomainError::Storage)?;
+
+    Ok(ChannelDirectoryEntry {
+        channel,
+        conversation_id,
+        title,
+        kind,
+        participants,
+        message_count: u64::try_from(message_count).unwrap_or(0),
+        first_seen_ms: u64::try_from(first_seen_ms).unwrap_or(0),
+        last_seen_ms: u64::try_from(last_seen_ms).unwrap_or(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::SqliteStore;
+    use crate::domain::models::ChannelDirectoryInput;
+
+    async fn make_store() -> (TempDir, SqliteStore) {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let store = SqliteStore::connect(&temp.path().join("state.db"))
+            .await
+            .expect("sqlite store should connect");
+        (temp, store)
+    }
+
+    fn directory_input(
+        participant: &str,
+        title: Option<&str>,
+        seen_at_ms: u64,
+    ) -> ChannelDirectoryInput {
+        ChannelDirectoryInput {
+            channel: "telegram".to_owned(),
+            conversation_id: "42".to_owned(),
+            title: title.map(str::to_owned),
+            kind: Some("group".to_owned()),
+            participant: Some(participant.to_owned()),
+            seen_at_ms,
+        }
+    }
+
+    #[tokio::test]
+    async fn record_channel_directory_entry_merges_observations() {
+        let (_temp, store) = make_store().await;
+        store
+            .record_channel_directory_entry(&directory_input("alice", Some("Team"), 10))
+            .await
+            .expect("first observation should persist");
+        store
+            .record_channel_directory_entry(&directory_input("bob", None, 20))
+            .await
+            .expect("second observation should persist");
+        let entry = store
+            .record_channel_directory_entry(&directory_input("alice", Some("Team Chat"), 30))
+            .await
+            .expect("third observation should persist");
+
+        assert_eq!(entry.title.as_deref(), Some("Team Chat"));
+        assert_eq!(
+            entry.participants,
+            vec!["bob".to_owned(), "alice".to_owned()]
+        );
+        assert_eq!(entry.message_count, 3);
+        assert_eq!(entry.first_seen_ms, 10);
+        assert_eq!(entry.last_seen_ms, 30);
+
+        let listed = store
+            .list_channel_directory_entries(Some("telegram"), None, 10)
+            .await
+            .expect("directory should list");
+        assert_eq!(listed.len(), 1);
+        assert!(
+            store
+                .list_channel_directory_entries(Some("whatsapp"), None, 10)
+                .await
+                .expect("directory should list")
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn list_channel_directory_entries_filters_before_the_limit() {
+        let (_temp, store) = make_store().await;
+        for (index, (conversation_id, title, participant)) in [
+            ("ops-room", Some("Ops"), "carol"),
+            ("100%-club", None, "dave"),
+            ("general", Some("Town Hall"), "erin"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            store
+                .record_channel_directory_entry(&ChannelDirectoryInput {
+                    channel: "telegram".to_owned(),
+                    conversation_id: conversation_id.to_owned(),
+                    title: title.map(str::to_owned),
+                    kind: None,
+                    participant: Some(participant.to_owned()),
+                    seen_at_ms: u64::try_from(index).expect("index fits") * 10,
+                })
+                .await
+                .expect("observation should persist");
+        }
+
+        let find = async |query: &str| {
+            store
+                .list_channel_directory_entries(Some("telegram"), Some(query), 1)
+                .await
+                .expect("directory should list")
+                .into_iter()
+                .map(|entry| entry.conversation_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(find("OPS").await, vec!["ops-room"]);
+        assert_eq!(find("carol").await, vec!["ops-room"]);
+        assert_eq!(find("town").await, vec!["general"]);
+        assert_eq!(find("0%").await, vec!["100%-club"]);
+        assert!(find("o_s").await.is_empty());
+        assert!(find("\"").await.is_empty());
+        assert!(find("n\"]").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn concurrent_observations_each_count() {
+        let (_temp, store) = make_store().await;
+        let inputs = (0..8)
+            .map(|index| directory_input(&format!("user-{index}"), None, index))
+            .collect::<Vec<_>>();
+        let recorded = futures_util::future::join_all(
+            inputs
+                .iter()
+                .map(|input| store.record_channel_directory_entry(input)),
+        )
+        .await;
+        assert!(recorded.iter().all(Result::is_ok));
+
+        let entry = store
+            .get_channel_directory_entry("telegram", "42")
+            .await
+            .expect("entry should load")
+            .expect("entry should exist");
+        assert_eq!(entry.message_count, 8);
+        assert_eq!(entry.participants.len(), 8);
+
+        let batch = store
+            .get_channel_directory_entries(&[("telegram", "42"), ("telegram", "missing")])
+            .await
+            .expect("entries should load");
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].message_count, 8);
+    }
+}
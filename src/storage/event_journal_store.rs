@@ -0,0 +1,229 @@
+use serde_json::Value;
+
+use crate::{
+    domain::{error::DomainError, models::JournaledEvent},
+    storage::{SqliteStore, util},
+};
+
+type JournaledEventRow = (i64, String, String, i64);
+
+impl SqliteStore {
+    /// Appends an event for every acknowledging client, or only for `target_key` when set, and
+    /// drops entries older than `retain_after_ms`. Returns the event's sequence number.
+    pub async fn append_journaled_event(
+        &self,
+        event: &str,
+        payload: &Value,
+        target_key: Option<&str>,
+        ts: u64,
+        retain_after_ms: u64,
+    ) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("append_journaled_event");
+        let payload_json = util::value_to_json_text(payload).map_err(DomainError::Storage)?;
+        let mut tx = self
+            .pool()
+            .begin()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        let retain_after_ms = i64::try_from(retain_after_ms).unwrap_or(i64::MAX);
+        sqlx::query("DELETE FROM event_journal WHERE ts_ms < ?")
+            .bind(retain_after_ms)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to prune event journal: {error}"))
+            })?;
+        sqlx::query("DELETE FROM event_ack_cursors WHERE updated_at_ms < ?")
+            .bind(retain_after_ms)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to prune event cursors: {error}"))
+            })?;
+        let seq = sqlx::query(
+            "INSERT INTO event_journal(event, payload_json, target_key, ts_ms) VALUES(?, ?, ?, ?)",
+        )
+        .bind(event)
+        .bind(payload_json)
+        .bind(target_key)
+        .bind(i64::try_from(ts).unwrap_or(i64::MAX))
+        .execute(&mut *tx)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to journal event: {error}")))?
+        .last_insert_rowid();
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))?;
+        Ok(u64::try_from(seq).unwrap_or(0))
+    }
+
+    /// Returns the last sequence number `client_key` acknowledged. A client seen for the first
+    /// time starts at the end of the journal, so it is only owed events published from now on.
+    pub async fn open_event_cursor(
+        &self,
+        client_key: &str,
+        now_ms: u64,
+    ) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("open_event_cursor");
+        let (acked_seq,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO event_ack_cursors(client_key, acked_seq, updated_at_ms) \
+             VALUES(?, (SELECT COALESCE(MAX(seq), 0) FROM event_journal), ?) \
+             ON CONFLICT(client_key) DO UPDATE SET updated_at_ms = excluded.updated_at_ms \
+             RETURNING acked_seq",
+        )
+        .bind(client_key)
+        .bind(i64::try_from(now_ms).unwrap_or(i64::MAX))
+        .fetch_one(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to open event cursor: {error}")))?;
+        Ok(u64::try_from(acked_seq).unwrap_or(0))
+    }
+
+    /// Events owed to `client_key` after `after_seq`, oldest first.
+    pub async fn list_unacked_events(
+        &self,
+        client_key: &str,
+        after_seq: u64,
+        limit: usize,
+    ) -> Result<Vec<JournaledEvent>, DomainError> {
+        let _timer = self.query_timer("list_unacked_events");
+        let rows = sqlx::query_as::<_, JournaledEventRow>(
+            "SELECT seq, event, payload_json, ts_ms FROM event_journal \
+             WHERE seq > ? AND (target_key IS NULL OR target_key = ?) ORDER BY seq LIMIT ?",
+        )
+        .bind(i64::try_from(after_seq).unwrap_or(i64::MAX))
+        .bind(client_key)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list unacked events: {error}")))?;
+        rows.into_iter().map(map_journaled_event_row).collect()
+    }
+
+    /// Acknowledges every event up to `seq` for `client_key`; acknowledgments never move the
+    /// cursor back or past the end of the journal. Returns the new cursor and how many events
+    /// are still owed after it.
+    pub async fn ack_events(
+        &self,
+        client_key: &str,
+        seq: u64,
+        now_ms: u64,
+    ) -> Result<(u64, u64), DomainError> {
+        let _timer = self.query_timer("ack_events");
+        let (acked_seq,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO event_ack_cursors(client_key, acked_seq, updated_at_ms) \
+             VALUES(?1, MIN(?2, (SELECT COALESCE(MAX(seq), 0) FROM event_journal)), ?3) \
+             ON CONFLICT(client_key) DO UPDATE SET \
+               acked_seq = MAX(acked_seq, excluded.acked_seq), \
+               updated_at_ms = excluded.updated_at_ms \
+             RETURNING acked_seq",
+        )
+        .bind(client_key)
+        .bind(i64::try_from(seq).unwrap_or(i64::MAX))
+        .bind(i64::try_from(now_ms).unwrap_or(i64::MAX))
+        .fetch_one(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to ack events: {error}")))?;
+        let (pending,) = sqlx::query_as::<_, (i64,)>(
+            "SELECT COUNT(*) FROM event_journal \
+             WHERE seq > ? AND (target_key IS NULL OR target_key = ?)",
+        )
+        .bind(acked_seq)
+        .bind(client_key)
+        .fetch_one(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to count unacked events: {error}"))
+        })?;
+        Ok((
+            u64::try_from(acked_seq).unwrap_or(0),
+            u64::try_from(pending).unwrap_or(0),
+        ))
+    }
+}
+
+fn map_journaled_event_row(row: JournaledEventRow) -> Result<JournaledEvent, DomainError> {
+    let (seq, event, payload_json, ts) = row;
+    Ok(JournaledEvent {
+        seq: u64::try_from(seq).unwrap_or(0),
+        event,
+        payload: util::json_text_to_value(&payload_json).map_err(DomainError::Storage)?,
+        ts: u64::try_from(ts).unwrap_or(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::storage::SqliteStore;
+
+    #[tokio::test]
+    async fn event_journal_replays_unacked_events_per_client() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let store = SqliteStore::connect(&temp.path().join("state.db"))
+            .await
+            .expect("sqlite store should connect");
+        let append = |event: &'static str, target: Option<&'static str>, ts: u64| {
+            let store = store.clone();
+            async move {
+                store
+                    .append_journaled_event(event, &json!({"ts": ts}), target, ts, 0)
+                    .await
+                    .expect("event should journal")
+            }
+        };
+
+        let before = append("exec.approval.requested", None, 10).await;
+        assert_eq!(
+            store
+                .open_event_cursor("operator:ui", 11)
+                .await
+                .expect("cursor"),
+            before
+        );
+        let broadcast = append("exec.approval.requested", None, 20).await;
+        let other_node = append("node.invoke.request", Some("node:a"), 30).await;
+        let own_node = append("node.invoke.request", Some("node:b"), 40).await;
+
+        let owed = store
+            .list_unacked_events("operator:ui", before, 10)
+            .await
+            .expect("events should list");
+        assert_eq!(
+            owed.iter().map(|event| event.seq).collect::<Vec<_>>(),
+            vec![broadcast]
+        );
+        let node_cursor = store.open_event_cursor("node:b", 41).await.expect("cursor");
+        assert_eq!(node_cursor, own_node);
+        assert!(other_node < own_node);
+
+        assert_eq!(
+            store
+                .ack_events("operator:ui", u64::MAX, 50)
+                .await
+                .expect("ack should store"),
+            (own_node, 0)
+        );
+        assert_eq!(
+            store
+                .ack_events("operator:ui", before, 60)
+                .await
+                .expect("ack should store"),
+            (own_node, 0)
+        );
+
+        store
+            .append_journaled_event("exec.approval.requested", &json!({}), None, 70, 45)
+            .await
+            .expect("event should journal");
+        assert!(
+            store
+                .list_unacked_events("operator:ui", 0, 10)
+                .await
+                .expect("events should list")
+                .iter()
+                .all(|event| event.ts >= 45)
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use sqlx::{QueryBuilder, Sqlite};
+
+use crate::{
+    domain::{
+        error::DomainError,
+        models::{IdentityLinkInput, PersonIdentity, PersonRecord},
+    },
+    storage::{SqliteStore, util},
+};
+
+type PersonRow = (String, Option<String>, i64, i64, i64);
+
+impl SqliteStore {
+    pub async fn link_person_identity(
+        &self,
+        input: &IdentityLinkInput,
+    ) -> Result<PersonRecord, DomainError> {
+        let _timer = self.query_timer("link_person_identity");
+        let existing_owner = self
+            .find_person_id_by_identity(&input.channel, &input.external_id)
+            .await?;
+
+        let person_id = match (&input.person_id, existing_owner) {
+            (Some(requested), Some(owner)) if *requested != owner => {
+                return Err(DomainError::InvalidRequest(format!(
+                    "identity {}:{} is already linked to person {owner}",
+                    input.channel, input.external_id
+                )));
+            }
+            (None, Some(owner)) => owner,
+            (Some(requested), _) => requested.clone(),
+            (None, None) => format!("person-{}", uuid::Uuid::new_v4().simple()),
+        };
+
+        let linked_at = i64::try_from(input.linked_at_ms).unwrap_or(i64::MAX);
+        let mut tx = self.pool().begin().await.map_err(|error| {
+            DomainError::Storage(format!("failed to begin identity link: {error}"))
+        })?;
+
+        sqlx::query(
+            "INSERT INTO persons(person_id, display_name, shared_session, created_at_ms, updated_at_ms) \
+             VALUES(?, ?, ?, ?, ?) \
+             ON CONFLICT(person_id) DO UPDATE SET \
+               display_name = COALESCE(excluded.display_name, persons.display_name), \
+               shared_session = CASE WHEN ? THEN excluded.shared_session ELSE persons.shared_session END, \
+               updated_at_ms = excluded.updated_at_ms",
+        )
+        .bind(&person_id)
+        .bind(&input.display_name)
+        .bind(i64::from(input.shared_session.unwrap_or(false)))
+        .bind(linked_at)
+        .bind(linked_at)
+        .bind(input.shared_session.is_some())
+        .execute(&mut *tx)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to upsert person: {error}")))?;
+
+        sqlx::query(
+            "INSERT INTO person_identities(channel, external_id, person_id, linked_at_ms) \
+             VALUES(?, ?, ?, ?) \
+             ON CONFLICT(channel, external_id) DO NOTHING",
+        )
+        .bind(&input.channel)
+        .bind(&input.external_id)
+        .bind(&person_id)
+        .bind(linked_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to link identity: {error}")))?;
+
+        tx.commit().await.map_err(|error| {
+            DomainError::Storage(format!("failed to commit identity link: {error}"))
+        })?;
+
+        self.get_person(&person_id)
+            .await?
+            .ok_or_else(|| DomainError::Storage(format!("person {person_id} vanished")))
+    }
+
+    /// Removes one identity link. Persons left without identities are deleted. Both deletes run
+    /// in one `BEGIN IMMEDIATE` transaction, so a concurrent link to the same person either lands
+    /// before the prune (and keeps the person) or after it (and recreates the person).
+    pub async fn unlink_person_identity(
+        &self,
+        channel: &str,
+        external_id: &str,
+    ) -> Result<Option<String>, DomainError> {
+        let _timer = self.query_timer("unlink_person_identity");
+        let mut tx = self
+            .pool()
+            .begin_with("BEGIN IMMEDIATE")
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to begin identity unlink: {error}"))
+            })?;
+
+        let Some(person_id) = sqlx::query_scalar::<_, String>(
+            "DELETE FROM person_identities WHERE channel = ? AND external_id = ? \
+             RETURNING person_id",
+        )
+        .bind(channel)
+        .bind(external_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to unlink identity: {error}")))?
+        else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            "DELETE FROM persons WHERE person_id = ? \
+             AND NOT EXISTS (SELECT 1 FROM person_identities WHERE person_id = ?)",
+        )
+        .bind(&person_id)
+        .bind(&person_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to prune person: {error}")))?;
+
+        tx.commit().await.map_err(|error| {
+            DomainError::Storage(format!("failed to commit identity unlink: {error}"))
+        })?;
+
+        Ok(Some(person_id))
+    }
+
+    pub async fn find_person_id_by_identity(
+        &self,
+        channel: &str,
+        external_id: &str,
+    ) -> Result<Option<String>, DomainError> {
+        let _timer = self.query_timer("find_person_id_by_identity");
+        sqlx::query_as::<_, (String,)>(
+            "SELECT person_id FROM person_identities WHERE channel = ? AND external_id = ? LIMIT 1",
+        )
+        .bind(channel)
+        .bind(external_id)
+        .fetch_optional(self.pool())
+        .await
+        .map(|row| row.map(|(person_id,)| person_id))
+        .map_err(|error| DomainError::Storage(format!("failed to resolve identity: {error}")))
+    }
+
+    pub async fn get_person(&self, person_id: &str) -> Result<Option<PersonRecord>, DomainError> {
+        let _timer = self.query_timer("get_person");
+        let row = sqlx::query_as::<_, PersonRow>(
+            "SELECT person_id, display_name, shared_session, created_at_ms, updated_at_ms \
+             FROM persons WHERE person_id = ? LIMIT 1",
+        )
+        .bind(person_id)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to get person: {error}")))?;
+
+        match row {
+            Some(row) => {
+                let identities = self.list_person_identities(person_id).await?;
+                Ok(Some(map_person_row(row, identities)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Display names (`None` when unset) of the listed persons that exist, in one query per
+    /// batch of ids.
+    pub async fn person_display_names(
+        &self,
+        person_ids: &[&str],
+    ) -> Result<HashMap<String, Option<String>>, DomainError> {
+        let _timer = self.query_timer("person_display_names");
+        let mut names = HashMap::with_capacity(person_ids.len());
+        for batch in person_ids.chunks(util::MAX_BATCH_BINDS) {
+            let mut builder = QueryBuilder::<Sqlite>::new(
+                "SELECT person_id, display_name FROM persons WHERE person_id IN (",
+            );
+            let mut ids = builder.separated(", ");
+            for person_id in batch {
+                ids.push_bind(*person_id);
+            }
+            builder.push(")");
+            let rows = builder
+                .build_query_as::<(String, Option<String>)>()
+                .fetch_all(self.pool())
+                .await
+                .map_err(|error| DomainError::Storage(format!("failed to get persons: {error}")))?;
+            names.extend(rows);
+        }
+        Ok(names)
+    }
+
+    pub async fn list_persons(&self, limit: usize) -> Result<Vec<PersonRecord>, DomainError> {
+        let _timer = self.query_timer("list_persons");
+        let rows = sqlx::query_as::<_, PersonRow>(
+            "SELECT person_id, display_name, shared_session, created_at_ms, updated_at_ms \
+             FROM persons ORDER BY updated_at_ms DESC LIMIT ?",
+        )
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list persons: {error}")))?;
+
+        let mut persons = Vec::with_capacity(rows.len());
+        for row in rows {
+            let identities = self.list_person_identities(&row.0).await?;
+            persons.push(map_person_row(row, identities));
+        }
+        Ok(persons)
+    }
+
+    async fn list_person_identities(
+        &self,
+        person_id: &str,
+    ) -> Result<Vec<PersonIdentity>, DomainError> {
+        let rows = sqlx::query_as::<_, (String, String, i64)>(
+            "SELECT channel, external_id, linked_at_ms FROM person_identities \
+             WHERE person_id = ? ORDER BY linked_at_ms ASC, channel ASC",
+        )
+        .bind(person_id)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to list person identities: {error}"))
+        })?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(channel, external_id, linked_at_ms)| PersonIdentity {
+                channel,
+                external_id,
+                linked_at_ms: u64::try_from(linked_at_ms).unwrap_or(0),
+            })
+            .collect())
+    }
+}
+
+fn map_person_row(row: PersonRow, identities: Vec<PersonIdentity>) -> PersonRecord {
+    let (person_id, display_name, shared_session, created_at_ms, updated_at_ms) = row;
+    PersonRecord {
+        person_id,
+        display_name,
+        shared_session: shared_session != 0,
+        identities,
+        created_at_ms: u64::try_from(created_at_ms).unwrap_or(0),
+        updated_at_ms: u64::try_from(updated_at_ms).unwrap_or(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::SqliteStore;
+    use crate::domain::models::IdentityLinkInput;
+
+    async fn make_store() -> (TempDir, SqliteStore) {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let store = SqliteStore::connect(&temp.path().join("state.db"))
+            .await
+            .expect("sqlite store should connect");
+        (temp, store)
+    }
+
+    fn link_input(person_id: Option<&str>, channel: &str, external_id: &str) -> IdentityLinkInput {
+        IdentityLinkInput {
+            person_id: person_id.map(str::to_owned),
+            display_name: Some("Ada".to_owned()),
+            shared_session: Some(true),
+            channel: channel.to_owned(),
+            external_id: external_id.to_owned(),
+            linked_at_ms: 10,
+        }
+    }
+
+    #[tokio::test]
+    async fn link_and_unlink_identities_manage_person_lifecycle() {
+        let (_temp, store) = make_store().await;
+        let person = store
+            .link_person_identity(&link_input(None, "telegram", "333"))
+            .await
+            .expect("first identity should link");
+        let person = store
+            .link_person_identity(&link_input(Some(&person.person_id), "slack", "U123"))
+            .await
+            .expect("second identity should link");
+        assert_eq!(person.identities.len(), 2);
+        assert!(person.shared_session);
+
+        assert!(
+            store
+                .link_person_identity(&link_input(Some("person-other"), "slack", "U123"))
+                .await
+                .is_err()
+        );
+
+        store
+            .unlink_person_identity("telegram", "333")
+            .await
+            .expect("identity should unlink");
+        store
+            .unlink_person_identity("slack", "U123")
+            .await
+            .expect("identity should unlink");
+        assert!(
+            store
+                .get_person(&person.person_id)
+                .await
+                .expect("person lookup should work")
+                .is_none()
+        );
+    }
+}
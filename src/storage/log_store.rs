@@ -0,0 +1,380 @@
+use sqlx::{QueryBuilder, Sqlite};
+
+use crate::{
+    domain::{
+        error::DomainError,
+        models::{GatewayLogEntry, GatewayLogQuery, LogShipment},
+    },
+    storage::{SqliteStore, util},
+};
+
+type GatewayLogRow = (String, String, String, Option<String>, Option<String>, i64);
+
+impl SqliteStore {
+    pub async fn append_gateway_log(&self, entry: &GatewayLogEntry) -> Result<(), DomainError> {
+        let _timer = self.query_timer("append_gateway_log");
+        sqlx::query(
+            "INSERT INTO logs(id, level, message, method, conn_id, ts_ms) VALUES(?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&entry.id)
+        .bind(entry.level.to_ascii_lowercase())
+        .bind(&entry.message)
+        .bind(entry.method.as_deref())
+        .bind(entry.conn_id.as_deref())
+        .bind(i64::try_from(entry.ts).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to append gateway log: {error}")))?;
+        Ok(())
+    }
+
+    /// Lists log rows newest first, filtered on the indexed level/method/conn columns.
+    pub async fn list_gateway_logs(
+        &self,
+        query: &GatewayLogQuery,
+    ) -> Result<Vec<GatewayLogEntry>, DomainError> {
+        let _timer = self.query_timer("list_gateway_logs");
+        let rows = gateway_logs_query("", query)
+            .build_query_as::<GatewayLogRow>()
+            .fetch_all(self.pool())
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to list gateway logs: {error}"))
+            })?;
+
+        Ok(rows.into_iter().map(map_gateway_log_row).collect())
+    }
+
+    pub async fn count_gateway_logs(&self) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("count_gateway_logs");
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM logs")
+            .fetch_one(self.pool())
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to count gateway logs: {error}"))
+            })?;
+        Ok(u64::try_from(count).unwrap_or(0))
+    }
+
+    /// Deletes everything but the newest `keep` rows and returns how many were removed.
+    pub async fn trim_gateway_logs(&self, keep: usize) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("trim_gateway_logs");
+        let result = sqlx::query(
+            "DELETE FROM logs WHERE seq <= \
+             (SELECT seq FROM logs ORDER BY seq DESC LIMIT 1 OFFSET ?)",
+        )
+        .bind(i64::try_from(keep).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to trim gateway logs: {error}")))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Buffers an entry for the log shipper, dropping the oldest beyond `max_entries`.
+    pub async fn enqueue_log_shipment(
+        &self,
+        kind: &str,
+        entry: &serde_json::Value,
+        ts: u64,
+        max_entries: usize,
+    ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("enqueue_log_shipment");
+        let entry_json = util::value_to_json_text(entry).map_err(DomainError::Storage)?;
+        let inserted =
+            sqlx::query("INSERT INTO log_shipments(kind, entry_json, ts_ms) VALUES(?, ?, ?)")
+                .bind(kind)
+                .bind(entry_json)
+                .bind(i64::try_from(ts).unwrap_or(i64::MAX))
+                .execute(self.pool())
+                .await
+                .map_err(|error| {
+                    DomainError::Storage(format!("failed to buffer log shipment: {error}"))
+                })?;
+        let max_entries = i64::try_from(max_entries).unwrap_or(i64::MAX);
+        if inserted.last_insert_rowid() > max_entries {
+            sqlx::query("DELETE FROM log_shipments WHERE seq <= ?")
+                .bind(inserted.last_insert_rowid() - max_entries)
+                .execute(self.pool())
+                .await
+                .map_err(|error| {
+                    DomainError::Storage(format!("failed to trim log shipments: {error}"))
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Oldest buffered entries first.
+    pub async fn list_log_shipments(&self, limit: usize) -> Result<Vec<LogShipment>, DomainError> {
+        let _timer = self.query_timer("list_log_shipments");
+        let rows = sqlx::query_as::<_, (i64, String, String, i64)>(
+            "SELECT seq, kind, entry_json, ts_ms FROM log_shipments ORDER BY seq ASC LIMIT ?",
+        )
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list log shipments: {error}")))?;
+
+        rows.into_iter()
+            .map(|(seq, kind, entry_json, ts_ms)| {
+                Ok(LogShipment {
+                    seq: u64::try_from(seq).unwrap_or(0),
+                    kind,
+                    entry: util::json_text_to_value(&entry_json).map_err(DomainError::Storage)?,
+                    ts: u64::try_from(ts_ms).unwrap_or(0),
+                })
+            })
+            .collect()
+    }
+
+    /// Removes shipped entries up to and including `seq`.
+    pub async fn ack_log_shipments(&self, seq: u64) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("ack_log_shipments");
+        let result = sqlx::query("DELETE FROM log_shipments WHERE seq <= ?")
+            .bind(i64::try_from(seq).unwrap_or(i64::MAX))
+            .execute(self.pool())
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to ack log shipments: {error}"))
+            })?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn count_log_shipments(&self) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("count_log_shipments");
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM log_shipments")
+            .fetch_one(self.pool())
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to count log shipments: {error}"))
+            })?;
+        Ok(u64::try_from(count).unwrap_or(0))
+    }
+}
+
+/// `SELECT` behind [`SqliteStore::list_gateway_logs`], after `prefix`. The `WHERE` clause names
+/// only the filters that are set, so SQLite can pick the matching index.
+fn gateway_logs_query(prefix: &str, query: &GatewayLogQuery) -> QueryBuilder<'static, Sqlite> {
+    let mut builder = QueryBuilder::new(format!(
+        "{prefix}SELECT id, level, message, method, conn_id, ts_ms FROM logs"
+    ));
+    let mut first = true;
+    if let Some(level) = &query.level {
+        util::push_condition(&mut builder, &mut first)
+            .push("level = ")
+            .push_bind(level.to_ascii_lowercase());
+    }
+    if let Some(method) = &query.method {
+        util::push_condition(&mut builder, &mut first)
+            .push("method = ")
+            .push_bind(method.clone());
+    }
+    if let Some(conn_id) = &query.conn_id {
+        util::push_condition(&mut builder, &mut first)
+            .push("conn_id = ")
+            .push_bind(conn_id.clone());
+    }
+    builder
+        .push(" ORDER BY seq DESC LIMIT ")
+        .push_bind(i64::try_from(query.limit).unwrap_or(i64::MAX));
+    builder
+}
+
+fn map_gateway_log_row(row: GatewayLogRow) -> GatewayLogEntry {
+    let (id, level, message, method, conn_id, ts_ms) = row;
+    GatewayLogEntry {
+        id,
+        level,
+        message,
+        method,
+        conn_id,
+        ts: u64::try_from(ts_ms).unwrap_or(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use sqlx::Row;
+
+    use super::{SqliteStore, gateway_logs_query};
+    use crate::domain::models::{GatewayLogEntry, GatewayLogQuery};
+
+    async fn make_store() -> (TempDir, SqliteStore) {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let store = SqliteStore::connect(&temp.path().join("state.db"))
+            .await
+            .expect("sqlite store should connect");
+        (temp, store)
+    }
+
+    fn entry(id: &str, level: &str, method: Option<&str>) -> GatewayLogEntry {
+        GatewayLogEntry {
+            id: id.to_owned(),
+            level: level.to_owned(),
+            message: format!("message {id}"),
+            method: method.map(str::to_owned),
+            conn_id: Some("conn-1".to_owned()),
+            ts: 1_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn gateway_logs_filter_newest_first_and_trim_to_bound() {
+        let (_temp, store) = make_store().await;
+        store
+            .append_gateway_log(&entry("a", "info", Some("health")))
+            .await
+            .expect("append should succeed");
+        store
+            .append_gateway_log(&entry("b", "WARN", Some("chat.send")))
+            .await
+            .expect("append should succeed");
+        store
+            .append_gateway_log(&entry("c", "info", Some("chat.send")))
+            .await
+            .expect("append should succeed");
+
+        let all = store
+            .list_gateway_logs(&GatewayLogQuery {
+                limit: 10,
+                ..GatewayLogQuery::default()
+            })
+            .await
+            .expect("list should succeed");
+        assert_eq!(
+            all.iter().map(|log| log.id.as_str()).collect::<Vec<_>>(),
+            ["c", "b", "a"]
+        );
+
+        let warn = store
+            .list_gateway_logs(&GatewayLogQuery {
+                level: Some("Warn".to_owned()),
+                limit: 10,
+                ..GatewayLogQuery::default()
+            })
+            .await
+            .expect("list should succeed");
+        assert_eq!(warn.len(), 1);
+        assert_eq!(warn[0].level, "warn");
+
+        let chat_info = store
+            .list_gateway_logs(&GatewayLogQuery {
+                level: Some("info".to_owned()),
+                method: Some("chat.send".to_owned()),
+                limit: 10,
+                ..GatewayLogQuery::default()
+            })
+            .await
+            .expect("list should succeed");
+        assert_eq!(chat_info.len(), 1);
+        assert_eq!(chat_info[0].id, "c");
+
+        assert_eq!(store.trim_gateway_logs(2).await.expect("trim"), 1);
+        assert_eq!(store.count_gateway_logs().await.expect("count"), 2);
+        assert_eq!(store.trim_gateway_logs(2).await.expect("trim"), 0);
+    }
+
+    #[tokio::test]
+    async fn gateway_log_filters_use_their_indexes() {
+        let (_temp, store) = make_store().await;
+        let plan = async |query: GatewayLogQuery| {
+            gateway_logs_query("EXPLAIN QUERY PLAN ", &query)
+                .build()
+                .fetch_all(store.pool())
+                .await
+                .expect("query plan should load")
+                .iter()
+                .map(|row| row.get::<String, _>("detail"))
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+        let filtered =
+            |level: Option<&str>, method: Option<&str>, conn_id: Option<&str>| GatewayLogQuery {
+                level: level.map(str::to_owned),
+                method: method.map(str::to_owned),
+                conn_id: conn_id.map(str::to_owned),
+                limit: 10,
+            };
+
+        for (query, index) in [
+            (filtered(Some("warn"), None, None), "idx_logs_level"),
+            (filtered(None, Some("chat.send"), None), "idx_logs_method"),
+            (filtered(None, None, Some("conn-1")), "idx_logs_conn"),
+        ] {
+            let plan = plan(query).await;
+            assert!(plan.contains(index), "expected {index} in plan: {plan}");
+        }
+    }
+
+    #[tokio::test]
+    async fn migration_moves_legacy_config_entry_logs() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let db_path = temp.path().join("state.db");
+        let store = SqliteStore::connect(&db_path)
+            .await
+            .expect("sqlite store should connect");
+        store
+            .set_config_entry(
+                "logs/1000-legacy",
+                &json!({ "id": "logs/1000-legacy", "level": "WARN", "message": "old", "method": "health", "connId": null, "ts": 1000 }),
+            )
+            .await
+            .expect("legacy entry should store");
+        drop(store);
+
+        let store = SqliteStore::connect(&db_path)
+            .await
+            .expect("sqlite store should reconnect");
+        let logs = store
+            .list_gateway_logs(&GatewayLogQuery {
+                limit: 10,
+                ..GatewayLogQuery::default()
+            })
+            .await
+            .expect("list should succeed");
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].id, "logs/1000-legacy");
+        assert_eq!(logs[0].level, "warn");
+        assert_eq!(logs[0].method.as_deref(), Some("health"));
+        assert_eq!(logs[0].conn_id, None);
+        assert_eq!(logs[0].ts, 1000);
+        assert!(
+            store
+                .list_config_entries("logs/", None)
+                .await
+                .expect("config entries should list")
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn log_shipments_buffer_in_order_drop_oldest_and_ack() {
+        let (_temp, store) = make_store().await;
+        for index in 0..5 {
+            store
+                .enqueue_log_shipment("log", &json!({ "index": index }), 1_000, 3)
+                .await
+                .expect("enqueue should succeed");
+        }
+
+        let buffered = store
+            .list_log_shipments(10)
+            .await
+            .expect("list should succeed");
+        assert_eq!(
+            buffered
+                .iter()
+                .map(|shipment| shipment.entry["index"].as_u64())
+                .collect::<Vec<_>>(),
+            [Some(2), Some(3), Some(4)]
+        );
+
+        store
+            .ack_log_shipments(buffered[1].seq)
+            .await
+            .expect("ack should succeed");
+        assert_eq!(store.count_log_shipments().await.ok(), Some(1));
+    }
+}
@@ -1,8 +1,146 @@
+use std::{collections::BTreeSet, time::Duration};
+
 use sqlx::{Executor, SqlitePool};
+use tokio::time::{Instant, sleep};
+
+use crate::{
+    domain::{error::DomainError, session_key::canonicalize_session_key},
+    storage::now_unix_ms,
+};
+
+const MIGRATION_LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Advisory lock that serializes schema migrations across processes sharing one database file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationLockOptions {
+    /// How long to wait for another instance to finish migrating before failing startup.
+    pub wait_timeout: Duration,
+    /// A lock whose heartbeat is older than this is considered abandoned and taken over.
+    pub stale_after: Duration,
+}
+
+impl Default for MigrationLockOptions {
+    fn default() -> Self {
+        Self {
+            wait_timeout: Duration::from_secs(30),
+            stale_after: Duration::from_secs(15),
+        }
+    }
+}
+
+pub async fn migrate_with_lock(
+    pool: &SqlitePool,
+    options: MigrationLockOptions,
+) -> Result<(), DomainError> {
+    let owner = format!(
+        "pid-{}-{}",
+        std::process::id(),
+        uuid::Uuid::new_v4().simple()
+    );
+    acquire_migration_lock(pool, &owner, options).await?;
+
+    let heartbeat = spawn_lock_heartbeat(pool.clone(), owner.clone(), options.stale_after / 3);
+    let result = migrate(pool).await;
+    heartbeat.abort();
+
+    let released = release_migration_lock(pool, &owner).await;
+    result.and(released)
+}
+
+async fn acquire_migration_lock(
+    pool: &SqlitePool,
+    owner: &str,
+    options: MigrationLockOptions,
+) -> Result<(), DomainError> {
+    pool.execute(
+        "CREATE TABLE IF NOT EXISTS migration_lock (
+            id INTEGER PRIMARY KEY NOT NULL CHECK (id = 1),
+            owner TEXT NOT NULL,
+            acquired_at_ms INTEGER NOT NULL,
+            heartbeat_at_ms INTEGER NOT NULL
+        );",
+    )
+    .await
+    .map_err(|error| DomainError::Storage(format!("failed to create migration lock: {error}")))?;
+
+    let deadline = Instant::now() + options.wait_timeout;
+    let stale_after_ms = i64::try_from(options.stale_after.as_millis()).unwrap_or(i64::MAX);
+    loop {
+        let now = i64::try_from(now_unix_ms()).unwrap_or(i64::MAX);
+        sqlx::query(
+            "INSERT INTO migration_lock(id, owner, acquired_at_ms, heartbeat_at_ms) \
+             VALUES(1, ?, ?, ?) \
+             ON CONFLICT(id) DO UPDATE SET \
+               owner = excluded.owner, \
+               acquired_at_ms = excluded.acquired_at_ms, \
+               heartbeat_at_ms = excluded.heartbeat_at_ms \
+             WHERE migration_lock.heartbeat_at_ms < ?",
+        )
+        .bind(owner)
+        .bind(now)
+        .bind(now)
+        .bind(now.saturating_sub(stale_after_ms))
+        .execute(pool)
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to acquire migration lock: {error}"))
+        })?;
 
-use crate::domain::error::DomainError;
+        let holder = sqlx::query_as::<_, (String, i64)>(
+            "SELECT owner, heartbeat_at_ms FROM migration_lock WHERE id = 1",
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to read migration lock: {error}")))?;
 
-pub async fn migrate(pool: &SqlitePool) -> Result<(), DomainError> {
+        match holder {
+            Some((holder, _)) if holder == owner => return Ok(()),
+            Some((holder, heartbeat_at_ms)) if Instant::now() >= deadline => {
+                return Err(DomainError::Unavailable(format!(
+                    "database migration lock is held by another instance ({holder}, last heartbeat {}ms ago); \
+                     refusing to start while it migrates",
+                    now.saturating_sub(heartbeat_at_ms)
+                )));
+            }
+            _ => sleep(MIGRATION_LOCK_POLL_INTERVAL).await,
+        }
+    }
+}
+
+fn spawn_lock_heartbeat(
+    pool: SqlitePool,
+    owner: String,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval.max(MIGRATION_LOCK_POLL_INTERVAL));
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let now = i64::try_from(now_unix_ms()).unwrap_or(i64::MAX);
+            let _ = sqlx::query(
+                "UPDATE migration_lock SET heartbeat_at_ms = ? WHERE id = 1 AND owner = ?",
+            )
+            .bind(now)
+            .bind(&owner)
+            .execute(&pool)
+            .await;
+        }
+    })
+}
+
+async fn release_migration_lock(pool: &SqlitePool, owner: &str) -> Result<(), DomainError> {
+    sqlx::query("DELETE FROM migration_lock WHERE id = 1 AND owner = ?")
+        .bind(owner)
+        .execute(pool)
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to release migration lock: {error}"))
+        })?;
+    Ok(())
+}
+
+async fn migrate(pool: &SqlitePool) -> Result<(), DomainError> {
     let migration = r#"
     PRAGMA journal_mode = WAL;
     PRAGMA synchronous = NORMAL;
@@ -34,6 +172,45 @@ pub async fn migrate(pool: &SqlitePool) -> Result<(), DomainError> {
     );
     CREATE INDEX IF NOT EXISTS idx_chat_messages_session_ts ON chat_messages(session_key, ts_ms ASC);
 
+    CREATE TABLE IF NOT EXISTS chat_pins (
+        message_id TEXT PRIMARY KEY NOT NULL,
+        session_key TEXT NOT NULL,
+        pinned_by TEXT,
+        pinned_at_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_chat_pins_session ON chat_pins(session_key);
+
+    CREATE TABLE IF NOT EXISTS chat_read_markers (
+        client_id TEXT NOT NULL,
+        session_key TEXT NOT NULL,
+        message_id TEXT NOT NULL,
+        message_ts_ms INTEGER NOT NULL,
+        marked_at_ms INTEGER NOT NULL,
+        PRIMARY KEY(client_id, session_key)
+    );
+    CREATE INDEX IF NOT EXISTS idx_chat_read_markers_session ON chat_read_markers(session_key);
+
+    CREATE TABLE IF NOT EXISTS session_kv (
+        session_key TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value_json TEXT NOT NULL,
+        size_bytes INTEGER NOT NULL,
+        updated_at_ms INTEGER NOT NULL,
+        expires_at_ms INTEGER,
+        PRIMARY KEY(session_key, key)
+    );
+
+    CREATE TABLE IF NOT EXISTS chat_archive_segments (
+        id TEXT PRIMARY KEY NOT NULL,
+        session_key TEXT NOT NULL,
+        file_name TEXT NOT NULL,
+        first_ts_ms INTEGER NOT NULL,
+        last_ts_ms INTEGER NOT NULL,
+        message_count INTEGER NOT NULL,
+        created_at_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_chat_archive_segments_session ON chat_archive_segments(session_key, last_ts_ms DESC);
+
     CREATE TABLE IF NOT EXISTS agent_runs (
         run_id TEXT PRIMARY KEY NOT NULL,
         agent_id TEXT NOT NULL,
@@ -47,6 +224,8 @@ pub async fn migrate(pool: &SqlitePool) -> Result<(), DomainError> {
         completed_at_ms INTEGER
     );
     CREATE INDEX IF NOT EXISTS idx_agent_runs_updated ON agent_runs(updated_at_ms DESC);
+    CREATE INDEX IF NOT EXISTS idx_agent_runs_agent_created ON agent_runs(agent_id, created_at_ms);
+    CREATE INDEX IF NOT EXISTS idx_agent_runs_session_created ON agent_runs(session_key, created_at_ms);
 
     CREATE TABLE IF NOT EXISTS cron_jobs (
         job_id TEXT PRIMARY KEY NOT NULL,
@@ -58,7 +237,8 @@ pub async fn migrate(pool: &SqlitePool) -> Result<(), DomainError> {
         created_at_ms INTEGER NOT NULL,
         updated_at_ms INTEGER NOT NULL,
         last_run_ms INTEGER,
-        next_run_ms INTEGER
+        next_run_ms INTEGER,
+        max_concurrent INTEGER
     );
     CREATE INDEX IF NOT EXISTS idx_cron_jobs_next_run ON cron_jobs(next_run_ms ASC);
 
@@ -70,9 +250,13 @@ pub async fn migrate(pool: &SqlitePool) -> Result<(), DomainError> {
         error TEXT,
         manual INTEGER NOT NULL,
         started_at_ms INTEGER NOT NULL,
-        finished_at_ms INTEGER NOT NULL
+        finished_at_ms INTEGER NOT NULL,
+        queue_wait_ms INTEGER NOT NULL DEFAULT 0,
+        agent_run_id TEXT
     );
     CREATE INDEX IF NOT EXISTS idx_cron_runs_job_started ON cron_runs(job_id, started_at_ms DESC);
+    CREATE INDEX IF NOT EXISTS idx_cron_runs_started ON cron_runs(started_at_ms DESC, run_id DESC);
+    CREATE INDEX IF NOT EXISTS idx_cron_runs_status_started ON cron_runs(status, started_at_ms DESC);
 
     CREATE TABLE IF NOT EXISTS nodes (
         node_id TEXT PRIMARY KEY NOT NULL,
@@ -98,7 +282,8 @@ pub async fn migrate(pool: &SqlitePool) -> Result<(), DomainError> {
         status TEXT NOT NULL,
         reason TEXT,
         created_at_ms INTEGER NOT NULL,
-        resolved_at_ms INTEGER
+        resolved_at_ms INTEGER,
+        expires_at_ms INTEGER
     );
     CREATE INDEX IF NOT EXISTS idx_node_pair_requests_created ON node_pair_requests(created_at_ms DESC);
 
@@ -117,6 +302,33 @@ pub async fn migrate(pool: &SqlitePool) -> Result<(), DomainError> {
     );
     CREATE INDEX IF NOT EXISTS idx_node_invokes_node_requested ON node_invokes(node_id, requested_at_ms DESC);
 
+    CREATE TABLE IF NOT EXISTS node_invoke_queue (
+        invoke_id TEXT PRIMARY KEY NOT NULL,
+        node_id TEXT NOT NULL,
+        expires_at_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_node_invoke_queue_node ON node_invoke_queue(node_id, expires_at_ms);
+
+    CREATE TABLE IF NOT EXISTS node_file_transfers (
+        transfer_id TEXT PRIMARY KEY NOT NULL,
+        node_id TEXT NOT NULL,
+        direction TEXT NOT NULL,
+        gateway_path TEXT NOT NULL,
+        node_path TEXT NOT NULL,
+        size_bytes INTEGER,
+        max_bytes INTEGER NOT NULL,
+        sha256 TEXT,
+        transferred_bytes INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        invoke_id TEXT,
+        error TEXT,
+        token_hash TEXT NOT NULL,
+        created_at_ms INTEGER NOT NULL,
+        updated_at_ms INTEGER NOT NULL,
+        expires_at_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_node_file_transfers_node ON node_file_transfers(node_id, created_at_ms DESC);
+
     CREATE TABLE IF NOT EXISTS node_events (
         event_id TEXT PRIMARY KEY NOT NULL,
         node_id TEXT NOT NULL,
@@ -125,11 +337,713 @@ pub async fn migrate(pool: &SqlitePool) -> Result<(), DomainError> {
         ts_ms INTEGER NOT NULL
     );
     CREATE INDEX IF NOT EXISTS idx_node_events_node_ts ON node_events(node_id, ts_ms DESC);
+
+    CREATE TABLE IF NOT EXISTS node_metadata_history (
+        entry_id TEXT PRIMARY KEY NOT NULL,
+        node_id TEXT NOT NULL,
+        metadata_json TEXT NOT NULL,
+        ts_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_node_metadata_history_node_ts ON node_metadata_history(node_id, ts_ms DESC);
+
+    CREATE TABLE IF NOT EXISTS channel_directory (
+        channel TEXT NOT NULL,
+        conversation_id TEXT NOT NULL,
+        title TEXT,
+        kind TEXT NOT NULL,
+        participants_json TEXT NOT NULL,
+        participants_search TEXT NOT NULL DEFAULT '',
+        message_count INTEGER NOT NULL,
+        first_seen_ms INTEGER NOT NULL,
+        last_seen_ms INTEGER NOT NULL,
+        PRIMARY KEY(channel, conversation_id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_channel_directory_last_seen ON channel_directory(last_seen_ms DESC);
+
+    CREATE TABLE IF NOT EXISTS persons (
+        person_id TEXT PRIMARY KEY NOT NULL,
+        display_name TEXT,
+        shared_session INTEGER NOT NULL,
+        created_at_ms INTEGER NOT NULL,
+        updated_at_ms INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS person_identities (
+        channel TEXT NOT NULL,
+        external_id TEXT NOT NULL,
+        person_id TEXT NOT NULL,
+        linked_at_ms INTEGER NOT NULL,
+        PRIMARY KEY(channel, external_id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_person_identities_person ON person_identities(person_id);
+
+    CREATE TABLE IF NOT EXISTS outbound_queue (
+        id TEXT PRIMARY KEY NOT NULL,
+        channel TEXT NOT NULL,
+        payload_json TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        queued_at_ms INTEGER NOT NULL,
+        release_at_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_outbound_queue_release ON outbound_queue(release_at_ms ASC);
+
+    CREATE TABLE IF NOT EXISTS privacy_audit (
+        id TEXT PRIMARY KEY NOT NULL,
+        action TEXT NOT NULL,
+        subject_json TEXT NOT NULL,
+        summary_json TEXT NOT NULL,
+        requested_by TEXT NOT NULL,
+        created_at_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_privacy_audit_created ON privacy_audit(created_at_ms DESC);
+
+    CREATE TABLE IF NOT EXISTS tombstones (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        table_name TEXT NOT NULL,
+        row_key TEXT NOT NULL,
+        row_json TEXT NOT NULL,
+        deleted_at_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_tombstones_row ON tombstones(table_name, row_key);
+
+    CREATE TABLE IF NOT EXISTS event_journal (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        event TEXT NOT NULL,
+        payload_json TEXT NOT NULL,
+        target_key TEXT,
+        ts_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_event_journal_ts ON event_journal(ts_ms);
+
+    CREATE TABLE IF NOT EXISTS event_ack_cursors (
+        client_key TEXT PRIMARY KEY NOT NULL,
+        acked_seq INTEGER NOT NULL,
+        updated_at_ms INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS wizard_sessions (
+        id TEXT PRIMARY KEY NOT NULL,
+        kind TEXT NOT NULL,
+        status TEXT NOT NULL,
+        step_index INTEGER NOT NULL,
+        answers_json TEXT NOT NULL,
+        result_json TEXT,
+        started_by TEXT,
+        cancel_reason TEXT,
+        created_at_ms INTEGER NOT NULL,
+        updated_at_ms INTEGER NOT NULL,
+        completed_at_ms INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS idx_wizard_sessions_kind ON wizard_sessions(kind, status, updated_at_ms DESC);
+
+    CREATE TABLE IF NOT EXISTS tools (
+        name TEXT PRIMARY KEY NOT NULL,
+        description TEXT NOT NULL,
+        input_schema_json TEXT NOT NULL,
+        executor_json TEXT NOT NULL,
+        created_at_ms INTEGER NOT NULL,
+        updated_at_ms INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS tool_grants (
+        agent_id TEXT NOT NULL,
+        tool_name TEXT NOT NULL,
+        granted_at_ms INTEGER NOT NULL,
+        PRIMARY KEY(agent_id, tool_name)
+    );
+
+    CREATE TABLE IF NOT EXISTS tool_calls (
+        id TEXT PRIMARY KEY NOT NULL,
+        run_id TEXT NOT NULL,
+        agent_id TEXT NOT NULL,
+        tool_name TEXT NOT NULL,
+        args_json TEXT NOT NULL,
+        status TEXT NOT NULL,
+        result_json TEXT,
+        error TEXT,
+        started_at_ms INTEGER NOT NULL,
+        completed_at_ms INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS idx_tool_calls_run ON tool_calls(run_id, started_at_ms ASC);
+
+    CREATE TABLE IF NOT EXISTS logs (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        level TEXT NOT NULL,
+        message TEXT NOT NULL,
+        method TEXT,
+        conn_id TEXT,
+        ts_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_logs_level ON logs(level, seq DESC);
+    CREATE INDEX IF NOT EXISTS idx_logs_method ON logs(method, seq DESC);
+    CREATE INDEX IF NOT EXISTS idx_logs_conn ON logs(conn_id, seq DESC);
+
+    CREATE TABLE IF NOT EXISTS log_shipments (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        kind TEXT NOT NULL,
+        entry_json TEXT NOT NULL,
+        ts_ms INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS message_deliveries (
+        id TEXT PRIMARY KEY NOT NULL,
+        session_key TEXT NOT NULL,
+        run_id TEXT,
+        channel TEXT NOT NULL,
+        conversation_id TEXT NOT NULL,
+        status TEXT NOT NULL,
+        platform_message_id TEXT,
+        error TEXT,
+        created_at_ms INTEGER NOT NULL,
+        updated_at_ms INTEGER NOT NULL,
+        sent_at_ms INTEGER,
+        delivered_at_ms INTEGER,
+        read_at_ms INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS idx_message_deliveries_session ON message_deliveries(session_key, created_at_ms DESC);
+    CREATE INDEX IF NOT EXISTS idx_message_deliveries_run ON message_deliveries(run_id);
+    CREATE INDEX IF NOT EXISTS idx_message_deliveries_platform ON message_deliveries(channel, platform_message_id);
+
+    CREATE TABLE IF NOT EXISTS agent_usage (
+        agent_id TEXT PRIMARY KEY NOT NULL,
+        sessions_count INTEGER NOT NULL DEFAULT 0,
+        messages_count INTEGER NOT NULL DEFAULT 0,
+        last_activity_at_ms INTEGER
+    );
+
+    CREATE TABLE IF NOT EXISTS replication_changes (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        table_name TEXT NOT NULL,
+        operation TEXT NOT NULL,
+        key_json TEXT NOT NULL,
+        row_json TEXT,
+        ts_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_replication_changes_ts ON replication_changes(ts_ms);
+
+    CREATE TABLE IF NOT EXISTS replication_state (
+        id INTEGER PRIMARY KEY NOT NULL CHECK (id = 1),
+        epoch INTEGER NOT NULL DEFAULT 0,
+        applied_seq INTEGER NOT NULL DEFAULT 0,
+        pruned_seq INTEGER NOT NULL DEFAULT 0,
+        peer_url TEXT,
+        fenced_at_ms INTEGER,
+        fenced_by_url TEXT
+    );
+    INSERT OR IGNORE INTO replication_state(id) VALUES(1);
+
+    INSERT OR IGNORE INTO logs(id, level, message, method, conn_id, ts_ms)
+    SELECT
+        key,
+        lower(COALESCE(json_extract(value_json, '$.level'), 'info')),
+        COALESCE(json_extract(value_json, '$.message'), ''),
+        json_extract(value_json, '$.method'),
+        json_extract(value_json, '$.connId'),
+        COALESCE(json_extract(value_json, '$.ts'), updated_at_ms)
+    FROM config_entries
+    WHERE key LIKE 'logs/%'
+    ORDER BY updated_at_ms ASC, key ASC;
+    DELETE FROM config_entries WHERE key LIKE 'logs/%';
     "#;
 
     pool.execute(migration)
         .await
         .map_err(|error| DomainError::Storage(format!("migration failed: {error}")))?;
+    add_missing_columns(pool).await?;
+    // Entries written before `participants_search` existed get it from their JSON list.
+    pool.execute(
+        "UPDATE channel_directory SET participants_search = \
+         (SELECT COALESCE(group_concat(value, char(10)), '') FROM json_each(participants_json)) \
+         WHERE participants_search = '' AND participants_json != '[]'",
+    )
+    .await
+    .map_err(|error| DomainError::Storage(format!("migration failed: {error}")))?;
+    pool.execute(agent_usage_triggers().as_str())
+        .await
+        .map_err(|error| DomainError::Storage(format!("migration failed: {error}")))?;
+
+    normalize_session_keys(pool).await?;
+    rebuild_agent_usage(pool).await
+}
+
+/// Columns added to existing tables after they were first created, as `(table, column, type)`.
+/// `CREATE TABLE IF NOT EXISTS` leaves older databases without them.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("cron_jobs", "max_concurrent", "INTEGER"),
+    ("cron_runs", "queue_wait_ms", "INTEGER NOT NULL DEFAULT 0"),
+    ("cron_runs", "agent_run_id", "TEXT"),
+    ("node_pair_requests", "expires_at_ms", "INTEGER"),
+    ("tombstones", "operation", "TEXT NOT NULL DEFAULT 'delete'"),
+    (
+        "channel_directory",
+        "participants_search",
+        "TEXT NOT NULL DEFAULT ''",
+    ),
+];
+
+async fn add_missing_columns(pool: &SqlitePool) -> Result<(), DomainError> {
+    let map_error =
+        |error: sqlx::Error| DomainError::Storage(format!("column migration failed: {error}"));
+    for (table, column, column_type) in ADDED_COLUMNS {
+        let present = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?",
+        )
+        .bind(table)
+        .bind(column)
+        .fetch_one(pool)
+        .await
+        .map_err(map_error)?;
+        if present == 0 {
+            pool.execute(format!("ALTER TABLE {table} ADD COLUMN {column} {column_type}").as_str())
+                .await
+                .map_err(map_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// SQL for the agent id of a session key column, mirroring `agent:<id>:<rest>` parsing. Other
+/// keys yield `NULL` and are not counted.
+fn agent_id_sql(column: &str) -> String {
+    let rest = format!("substr({column}, 7)");
+    format!(
+        "(CASE WHEN substr({column}, 1, 6) = 'agent:' THEN NULLIF(trim(CASE \
+         WHEN instr({rest}, ':') > 0 THEN substr({rest}, 1, instr({rest}, ':') - 1) \
+         ELSE {rest} END), '') END)"
+    )
+}
+
+/// Triggers that keep `agent_usage` in step with `sessions` and `chat_messages` inside the
+/// writing statement's transaction, whichever code path writes.
+fn agent_usage_triggers() -> String {
+    let bump = |agent: &str, sessions: u8, messages: u8, activity: &str| {
+        format!(
+            "INSERT INTO agent_usage(agent_id, sessions_count, messages_count, last_activity_at_ms) \
+             SELECT agent_id, {sessions}, {messages}, {activity} FROM (SELECT {agent} AS agent_id) \
+             WHERE agent_id IS NOT NULL \
+             ON CONFLICT(agent_id) DO UPDATE SET \
+               sessions_count = sessions_count + excluded.sessions_count, \
+               messages_count = messages_count + excluded.messages_count, \
+               last_activity_at_ms = max(COALESCE(last_activity_at_ms, 0), \
+                 excluded.last_activity_at_ms);"
+        )
+    };
+    let decrement = |agent: &str, column: &str| {
+        format!("UPDATE agent_usage SET {column} = max({column} - 1, 0) WHERE agent_id = {agent};")
+    };
+    let session_new = agent_id_sql("NEW.id");
+    let session_old = agent_id_sql("OLD.id");
+    let message_new = agent_id_sql("NEW.session_key");
+    let message_old = agent_id_sql("OLD.session_key");
+    // Messages are rewritten in place with an upsert, which moves the count if the session did.
+    format!(
+        "DROP TRIGGER IF EXISTS agent_usage_message_replace;
+         CREATE TRIGGER IF NOT EXISTS agent_usage_session_insert AFTER INSERT ON sessions BEGIN {} END;
+         CREATE TRIGGER IF NOT EXISTS agent_usage_session_touch AFTER UPDATE OF updated_at_ms ON sessions \
+           BEGIN {} END;
+         CREATE TRIGGER IF NOT EXISTS agent_usage_session_delete AFTER DELETE ON sessions BEGIN {} END;
+         CREATE TRIGGER IF NOT EXISTS agent_usage_message_insert AFTER INSERT ON chat_messages \
+           BEGIN {} END;
+         CREATE TRIGGER IF NOT EXISTS agent_usage_message_update AFTER UPDATE ON chat_messages \
+           BEGIN {} {} END;
+         CREATE TRIGGER IF NOT EXISTS agent_usage_message_delete AFTER DELETE ON chat_messages \
+           BEGIN {} END;",
+        bump(&session_new, 1, 0, "NEW.updated_at_ms"),
+        bump(&session_new, 0, 0, "NEW.updated_at_ms"),
+        decrement(&session_old, "sessions_count"),
+        bump(&message_new, 0, 1, "NEW.ts_ms"),
+        decrement(&message_old, "messages_count"),
+        bump(&message_new, 0, 1, "NEW.ts_ms"),
+        decrement(&message_old, "messages_count"),
+    )
+}
+
+/// Recounts `agent_usage` from the stored rows, covering databases written before the counters
+/// existed and session keys renamed by [`normalize_session_keys`].
+async fn rebuild_agent_usage(pool: &SqlitePool) -> Result<(), DomainError> {
+    let map_error =
+        |error: sqlx::Error| DomainError::Storage(format!("agent usage migration failed: {error}"));
+    let session_agent = agent_id_sql("id");
+    let message_agent = agent_id_sql("session_key");
+    let mut tx = pool.begin().await.map_err(map_error)?;
+    sqlx::query("DELETE FROM agent_usage")
+        .execute(&mut *tx)
+        .await
+        .map_err(map_error)?;
+    sqlx::query(&format!(
+        "INSERT INTO agent_usage(agent_id, sessions_count, messages_count, last_activity_at_ms) \
+         SELECT agent_id, SUM(sessions), SUM(messages), MAX(activity) FROM ( \
+           SELECT {session_agent} AS agent_id, 1 AS sessions, 0 AS messages, updated_at_ms AS activity \
+           FROM sessions \
+           UNION ALL \
+           SELECT {message_agent}, 0, 1, ts_ms FROM chat_messages \
+         ) WHERE agent_id IS NOT NULL GROUP BY agent_id"
+    ))
+    .execute(&mut *tx)
+    .await
+    .map_err(map_error)?;
+    tx.commit().await.map_err(map_error)
+}
+
+/// Tables that reference sessions, with the column holding the session key.
+const SESSION_KEY_COLUMNS: &[(&str, &str)] = &[
+    ("sessions", "id"),
+    ("chat_messages", "session_key"),
+    ("chat_pins", "session_key"),
+    ("session_kv", "session_key"),
+    ("chat_read_markers", "session_key"),
+    ("chat_archive_segments", "session_key"),
+    ("agent_runs", "session_key"),
+    ("message_deliveries", "session_key"),
+];
+
+/// Resolves rows that would collide once `?1` (a stored key) is renamed to `?2` (its canonical
+/// form), for the tables keyed by session: the canonical session row wins, and of two
+/// `session_kv` entries or read markers for the same key the newer one is kept.
+const SESSION_KEY_CONFLICTS: &[&str] = &[
+    "DELETE FROM sessions WHERE id = ?1 AND EXISTS (SELECT 1 FROM sessions WHERE id = ?2)",
+    "DELETE FROM session_kv WHERE session_key = ?2 AND EXISTS (SELECT 1 FROM session_kv AS other \
+     WHERE other.session_key = ?1 AND other.key = session_kv.key \
+     AND other.updated_at_ms > session_kv.updated_at_ms)",
+    "DELETE FROM session_kv WHERE session_key = ?1 AND EXISTS (SELECT 1 FROM session_kv AS other \
+     WHERE other.session_key = ?2 AND other.key = session_kv.key)",
+    "DELETE FROM chat_read_markers WHERE session_key = ?2 AND EXISTS (SELECT 1 \
+     FROM chat_read_markers AS other WHERE other.session_key = ?1 \
+     AND other.client_id = chat_read_markers.client_id \
+     AND other.message_ts_ms > chat_read_markers.message_ts_ms)",
+    "DELETE FROM chat_read_markers WHERE session_key = ?1 AND EXISTS (SELECT 1 \
+     FROM chat_read_markers AS other WHERE other.session_key = ?2 \
+     AND other.client_id = chat_read_markers.client_id)",
+];
+
+/// Rewrites session keys stored before they were canonicalized, such as `agent:main: main`.
+/// When both spellings exist, [`SESSION_KEY_CONFLICTS`] settles the colliding rows and the rest
+/// of the other spelling's rows move onto the canonical key, so none are left behind. Keys that
+/// do not parse are left untouched.
+async fn normalize_session_keys(pool: &SqlitePool) -> Result<(), DomainError> {
+    let map_error =
+        |error: sqlx::Error| DomainError::Storage(format!("session key migration failed: {error}"));
 
+    let mut stored = BTreeSet::new();
+    for (table, column) in SESSION_KEY_COLUMNS {
+        let rows: Vec<(String,)> = sqlx::query_as(&format!(
+            "SELECT DISTINCT {column} FROM {table} WHERE {column} IS NOT NULL"
+        ))
+        .fetch_all(pool)
+        .await
+        .map_err(map_error)?;
+        stored.extend(rows.into_iter().map(|(key,)| key));
+    }
+    let renames = stored
+        .into_iter()
+        .filter_map(|key| {
+            let canonical = canonicalize_session_key(&key).ok()?;
+            (canonical != key).then_some((key, canonical))
+        })
+        .collect::<Vec<_>>();
+    if renames.is_empty() {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await.map_err(map_error)?;
+    for (key, canonical) in &renames {
+        for statement in SESSION_KEY_CONFLICTS {
+            sqlx::query(statement)
+                .bind(key)
+                .bind(canonical)
+                .execute(&mut *tx)
+                .await
+                .map_err(map_error)?;
+        }
+        for (table, column) in SESSION_KEY_COLUMNS {
+            sqlx::query(&format!(
+                "UPDATE {table} SET {column} = ?2 WHERE {column} = ?1"
+            ))
+            .bind(key)
+            .bind(canonical)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_error)?;
+        }
+    }
+    tx.commit().await.map_err(map_error)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::MigrationLockOptions;
+    use crate::{
+        domain::models::{AgentUsageRecord, ChatMessage, SessionRecord},
+        storage::{SqliteStore, now_unix_ms},
+    };
+
+    #[tokio::test]
+    async fn reconnect_normalizes_stored_session_keys() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let db_path = temp.path().join("state.db");
+        let store = SqliteStore::connect(&db_path)
+            .await
+            .expect("sqlite store should connect");
+        for id in [
+            "agent:main:main",
+            "agent:main: main",
+            "Agent:Ops:inbox",
+            "legacy",
+        ] {
+            sqlx::query(
+                "INSERT INTO sessions(id, title, tags_json, metadata_json, created_at_ms, updated_at_ms) \
+                 VALUES(?, 'Session', '[]', '{}', 0, 0)",
+            )
+            .bind(id)
+            .execute(store.pool())
+            .await
+            .expect("session should insert");
+        }
+        sqlx::query(
+            "INSERT INTO chat_messages(message_id, session_key, role, text, status, metadata_json, ts_ms) \
+             VALUES('msg-1', 'agent:main: main', 'user', 'hi', 'final', '{}', 0)",
+        )
+        .execute(store.pool())
+        .await
+        .expect("message should insert");
+        for statement in [
+            "INSERT INTO chat_pins(message_id, session_key, pinned_at_ms) \
+             VALUES('msg-1', 'agent:main: main', 0)",
+            "INSERT INTO session_kv(session_key, key, value_json, size_bytes, updated_at_ms) VALUES \
+             ('agent:main:main', 'shared', '\"canonical\"', 11, 10), \
+             ('agent:main: main', 'shared', '\"newer\"', 7, 20), \
+             ('agent:main:main', 'kept', '\"canonical\"', 11, 30), \
+             ('agent:main: main', 'kept', '\"older\"', 7, 5), \
+             ('agent:main: main', 'moved', '\"only\"', 6, 0)",
+            "INSERT INTO chat_read_markers(client_id, session_key, message_id, message_ts_ms, \
+             marked_at_ms) VALUES('ui', 'agent:main:main', 'm-1', 1, 1), \
+             ('ui', 'agent:main: main', 'm-2', 2, 2)",
+        ] {
+            sqlx::query(statement)
+                .execute(store.pool())
+                .await
+                .expect("row should insert");
+        }
+
+        let reopened = SqliteStore::connect(&db_path)
+            .await
+            .expect("sqlite store should reconnect");
+        let sessions: Vec<(String,)> = sqlx::query_as("SELECT id FROM sessions ORDER BY id")
+            .fetch_all(reopened.pool())
+            .await
+            .expect("sessions should be readable");
+        assert_eq!(
+            sessions.into_iter().map(|(id,)| id).collect::<Vec<_>>(),
+            vec!["agent:main:main", "agent:ops:inbox", "legacy"]
+        );
+        let (session_key,): (String,) =
+            sqlx::query_as("SELECT session_key FROM chat_messages WHERE message_id = 'msg-1'")
+                .fetch_one(reopened.pool())
+                .await
+                .expect("message should be readable");
+        assert_eq!(session_key, "agent:main:main");
+
+        for (table, column) in super::SESSION_KEY_COLUMNS {
+            let leftover: i64 = sqlx::query_scalar(&format!(
+                "SELECT COUNT(*) FROM {table} WHERE {column} IN ('agent:main: main', 'Agent:Ops:inbox')"
+            ))
+            .fetch_one(reopened.pool())
+            .await
+            .expect("table should be readable");
+            assert_eq!(leftover, 0, "{table} should keep no old-key rows");
+        }
+        let kv: Vec<(String, String)> = sqlx::query_as(
+            "SELECT key, value_json FROM session_kv WHERE session_key = 'agent:main:main' ORDER BY key",
+        )
+        .fetch_all(reopened.pool())
+        .await
+        .expect("session kv should be readable");
+        assert_eq!(
+            kv,
+            vec![
+                ("kept".to_owned(), "\"canonical\"".to_owned()),
+                ("moved".to_owned(), "\"only\"".to_owned()),
+                ("shared".to_owned(), "\"newer\"".to_owned()),
+            ]
+        );
+        let (marker,): (String,) = sqlx::query_as(
+            "SELECT message_id FROM chat_read_markers WHERE session_key = 'agent:main:main'",
+        )
+        .fetch_one(reopened.pool())
+        .await
+        .expect("read marker should be readable");
+        assert_eq!(marker, "m-2");
+        let (pinned,): (String,) =
+            sqlx::query_as("SELECT session_key FROM chat_pins WHERE message_id = 'msg-1'")
+                .fetch_one(reopened.pool())
+                .await
+                .expect("pin should be readable");
+        assert_eq!(pinned, "agent:main:main");
+    }
+
+    #[tokio::test]
+    async fn reconnect_adds_columns_missing_from_older_tables() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let db_path = temp.path().join("state.db");
+        let store = SqliteStore::connect(&db_path)
+            .await
+            .expect("sqlite store should connect");
+        for (table, column, _) in super::ADDED_COLUMNS {
+            sqlx::query(&format!("ALTER TABLE {table} DROP COLUMN {column}"))
+                .execute(store.pool())
+                .await
+                .expect("column should drop");
+        }
+
+        let reopened = SqliteStore::connect(&db_path)
+            .await
+            .expect("sqlite store should reconnect");
+        for (table, column, _) in super::ADDED_COLUMNS {
+            let present: i64 =
+                sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?")
+                    .bind(table)
+                    .bind(column)
+                    .fetch_one(reopened.pool())
+                    .await
+                    .expect("table info should be readable");
+            assert_eq!(present, 1, "{table}.{column} should be restored");
+        }
+    }
+
+    #[tokio::test]
+    async fn agent_usage_counters_follow_writes_and_rebuild_on_connect() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let db_path = temp.path().join("state.db");
+        let store = SqliteStore::connect(&db_path)
+            .await
+            .expect("sqlite store should connect");
+        for (id, updated_at_ms) in [
+            ("agent:main:a", 10),
+            ("agent:main:b", 30),
+            ("agent:ops:x", 5),
+        ] {
+            store
+                .upsert_session(&SessionRecord {
+                    id: id.to_owned(),
+                    title: id.to_owned(),
+                    tags: Vec::new(),
+                    metadata: json!({}),
+                    created_at_ms: 0,
+                    updated_at_ms,
+                })
+                .await
+                .expect("session should upsert");
+        }
+        let message = |id: &str, ts: u64| ChatMessage {
+            id: id.to_owned(),
+            role: "user".to_owned(),
+            text: "hi".to_owned(),
+            status: "final".to_owned(),
+            metadata: json!({}),
+            ts,
+            pinned: false,
+        };
+        store
+            .append_chat_messages("agent:main:a", &[message("m-1", 40), message("m-2", 50)])
+            .await
+            .expect("messages should append");
+        store
+            .append_chat_messages("agent:main:a", &[message("m-2", 50)])
+            .await
+            .expect("replaced message should append");
+        store
+            .append_chat_messages("legacy", &[message("m-3", 60)])
+            .await
+            .expect("unscoped message should append");
+        assert!(
+            store
+                .remove_session("agent:ops:x")
+                .await
+                .expect("session should remove")
+        );
+
+        let expected = vec![
+            AgentUsageRecord {
+                agent_id: "main".to_owned(),
+                sessions_count: 2,
+                messages_count: 2,
+                last_activity_at_ms: Some(50),
+            },
+            AgentUsageRecord {
+                agent_id: "ops".to_owned(),
+                sessions_count: 0,
+                messages_count: 0,
+                last_activity_at_ms: Some(5),
+            },
+        ];
+        assert_eq!(
+            store.list_agent_usage().await.expect("usage should list"),
+            expected
+        );
+
+        sqlx::query("UPDATE agent_usage SET sessions_count = 99")
+            .execute(store.pool())
+            .await
+            .expect("counters should update");
+        let reopened = SqliteStore::connect(&db_path)
+            .await
+            .expect("sqlite store should reconnect");
+        assert_eq!(
+            reopened
+                .list_agent_usage()
+                .await
+                .expect("usage should list"),
+            expected[..1]
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_waits_for_live_lock_and_takes_over_stale_lock() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let db_path = temp.path().join("state.db");
+        let store = SqliteStore::connect(&db_path)
+            .await
+            .expect("sqlite store should connect");
+        sqlx::query(
+            "INSERT INTO migration_lock(id, owner, acquired_at_ms, heartbeat_at_ms) VALUES(1, 'other', ?, ?)",
+        )
+        .bind(i64::try_from(now_unix_ms()).unwrap_or(i64::MAX))
+        .bind(i64::try_from(now_unix_ms()).unwrap_or(i64::MAX))
+        .execute(store.pool())
+        .await
+        .expect("lock row should insert");
+
+        let options = MigrationLockOptions {
+            wait_timeout: Duration::from_millis(250),
+            stale_after: Duration::from_secs(60),
+        };
+        let error = SqliteStore::connect_with_lock_options(&db_path, options)
+            .await
+            .expect_err("live lock should block startup");
+        assert!(
+            error
+                .to_string()
+                .contains("held by another instance (other")
+        );
+
+        sqlx::query("UPDATE migration_lock SET heartbeat_at_ms = 0")
+            .execute(store.pool())
+            .await
+            .expect("lock row should update");
+        let recovered = SqliteStore::connect_with_lock_options(&db_path, options)
+            .await
+            .expect("stale lock should be taken over");
+        let remaining: Option<(String,)> =
+            sqlx::query_as("SELECT owner FROM migration_lock WHERE id = 1")
+                .fetch_optional(recovered.pool())
+                .await
+                .expect("lock table should be readable");
+        assert!(remaining.is_none());
+    }
+}
@@ -1,12 +1,36 @@
 mod agent_store;
+mod chat_archive_store;
 mod chat_store;
 mod config_store;
 mod cron_store;
+mod delivery_store;
+mod directory_store;
+mod event_journal_store;
+mod identity_store;
+mod log_store;
 mod migrations;
+mod node_file_store;
 mod node_store;
+mod outbound_queue_store;
+mod postgres_migration;
+mod privacy_store;
+mod query_metrics;
+mod redis_backend;
+mod replication_store;
+mod session_kv_store;
 mod sessions_store;
 mod sqlite_store;
+mod tombstone_store;
+mod tool_store;
 mod util;
+mod wizard_store;
 
+pub use migrations::MigrationLockOptions;
+pub use postgres_migration::{
+    MigrationProgress, PostgresMigrationOptions, PostgresMigrationReport, STORAGE_CUTOVER_KEY,
+    TableMigrationReport, redact_database_url,
+};
+pub use query_metrics::{QueryMetrics, SLOW_QUERY_WINDOW, SlowOperation};
+pub use redis_backend::{IdempotencyClaim, RedisBackend};
 pub use sqlite_store::SqliteStore;
 pub(crate) use util::now_unix_ms;
@@ -0,0 +1,171 @@
+use crate::{
+    domain::{error::DomainError, models::NodeFileTransfer},
+    storage::SqliteStore,
+};
+
+type NodeFileTransferRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    Option<i64>,
+    i64,
+    Option<String>,
+    i64,
+    String,
+    Option<String>,
+    Option<String>,
+    String,
+    i64,
+    i64,
+    i64,
+);
+
+const NODE_FILE_TRANSFER_COLUMNS: &str = "transfer_id, node_id, direction, gateway_path, \
+     node_path, size_bytes, max_bytes, sha256, transferred_bytes, status, invoke_id, error, \
+     token_hash, created_at_ms, updated_at_ms, expires_at_ms";
+
+impl SqliteStore {
+    pub async fn upsert_node_file_transfer(
+        &self,
+        transfer: &NodeFileTransfer,
+    ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("upsert_node_file_transfer");
+        sqlx::query(&format!(
+            "INSERT INTO node_file_transfers({NODE_FILE_TRANSFER_COLUMNS}) \
+             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(transfer_id) DO UPDATE SET size_bytes = excluded.size_bytes, \
+             sha256 = excluded.sha256, transferred_bytes = excluded.transferred_bytes, \
+             status = excluded.status, invoke_id = excluded.invoke_id, error = excluded.error, \
+             updated_at_ms = excluded.updated_at_ms"
+        ))
+        .bind(&transfer.transfer_id)
+        .bind(&transfer.node_id)
+        .bind(&transfer.direction)
+        .bind(&transfer.gateway_path)
+        .bind(&transfer.node_path)
+        .bind(
+            transfer
+                .size_bytes
+                .map(|value| i64::try_from(value).unwrap_or(i64::MAX)),
+        )
+        .bind(i64::try_from(transfer.max_bytes).unwrap_or(i64::MAX))
+        .bind(&transfer.sha256)
+        .bind(i64::try_from(transfer.transferred_bytes).unwrap_or(i64::MAX))
+        .bind(&transfer.status)
+        .bind(&transfer.invoke_id)
+        .bind(&transfer.error)
+        .bind(&transfer.token_hash)
+        .bind(i64::try_from(transfer.created_at_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(transfer.updated_at_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(transfer.expires_at_ms).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to save node file transfer: {error}"))
+        })?;
+        Ok(())
+    }
+
+    pub async fn get_node_file_transfer(
+        &self,
+        transfer_id: &str,
+    ) -> Result<Option<NodeFileTransfer>, DomainError> {
+        let _timer = self.query_timer("get_node_file_transfer");
+        let row = sqlx::query_as::<_, NodeFileTransferRow>(&format!(
+            "SELECT {NODE_FILE_TRANSFER_COLUMNS} FROM node_file_transfers \
+             WHERE transfer_id = ? LIMIT 1"
+        ))
+        .bind(transfer_id)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to load node file transfer: {error}"))
+        })?;
+        Ok(row.map(map_node_file_transfer_row))
+    }
+
+    /// Lists transfers newest first, optionally for a single node.
+    pub async fn list_node_file_transfers(
+        &self,
+        node_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<NodeFileTransfer>, DomainError> {
+        let _timer = self.query_timer("list_node_file_transfers");
+        let rows = sqlx::query_as::<_, NodeFileTransferRow>(&format!(
+            "SELECT {NODE_FILE_TRANSFER_COLUMNS} FROM node_file_transfers \
+             WHERE (? IS NULL OR node_id = ?) ORDER BY created_at_ms DESC LIMIT ?"
+        ))
+        .bind(node_id)
+        .bind(node_id)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to list node file transfers: {error}"))
+        })?;
+        Ok(rows.into_iter().map(map_node_file_transfer_row).collect())
+    }
+
+    /// Marks open transfers past their deadline `expired` and returns them.
+    pub async fn expire_node_file_transfers(
+        &self,
+        now_ms: u64,
+    ) -> Result<Vec<NodeFileTransfer>, DomainError> {
+        let _timer = self.query_timer("expire_node_file_transfers");
+        let now = i64::try_from(now_ms).unwrap_or(i64::MAX);
+        let rows = sqlx::query_as::<_, NodeFileTransferRow>(&format!(
+            "UPDATE node_file_transfers SET status = 'expired', updated_at_ms = ? \
+             WHERE status IN ('pending', 'active') AND expires_at_ms <= ? \
+             RETURNING {NODE_FILE_TRANSFER_COLUMNS}"
+        ))
+        .bind(now)
+        .bind(now)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to expire node file transfers: {error}"))
+        })?;
+        Ok(rows.into_iter().map(map_node_file_transfer_row).collect())
+    }
+}
+
+fn map_node_file_transfer_row(row: NodeFileTransferRow) -> NodeFileTransfer {
+    let (
+        transfer_id,
+        node_id,
+        direction,
+        gateway_path,
+        node_path,
+        size_bytes,
+        max_bytes,
+        sha256,
+        transferred_bytes,
+        status,
+        invoke_id,
+        error,
+        token_hash,
+        created_at_ms,
+        updated_at_ms,
+        expires_at_ms,
+    ) = row;
+    NodeFileTransfer {
+        transfer_id,
+        node_id,
+        direction,
+        gateway_path,
+        node_path,
+        size_bytes: size_bytes.map(|value| u64::try_from(value).unwrap_or(0)),
+        max_bytes: u64::try_from(max_bytes).unwrap_or(0),
+        sha256,
+        transferred_bytes: u64::try_from(transferred_bytes).unwrap_or(0),
+        status,
+        invoke_id,
+        error,
+        token_hash,
+        created_at_ms: u64::try_from(created_at_ms).unwrap_or(0),
+        updated_at_ms: u64::try_from(updated_at_ms).unwrap_or(0),
+        expires_at_ms: u64::try_from(expires_at_ms).unwrap_or(0),
+    }
+}
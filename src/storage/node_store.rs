@@ -4,8 +4,8 @@ use crate::{
     domain::{
         error::DomainError,
         models::{
-            NodeEventRecord, NodeInvokeInput, NodeInvokeRecord, NodePairRequestInput,
-            NodePairRequestRecord, NodeRecord,
+            NodeEventRecord, NodeInvokeInput, NodeInvokeRecord, NodeMetadataEntry,
+            NodePairRequestInput, NodePairRequestRecord, NodeRecord, QueuedNodeInvoke,
         },
     },
     storage::{SqliteStore, util},
@@ -35,6 +35,7 @@ type NodePairRow = (
     Option<String>,
     i64,
     Option<i64>,
+    Option<i64>,
 );
 
 type NodeInvokeRow = (
@@ -51,8 +52,24 @@ type NodeInvokeRow = (
     Option<i64>,
 );
 
+type QueuedNodeInvokeRow = (
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    String,
+    Option<String>,
+    Option<String>,
+    i64,
+    i64,
+    Option<i64>,
+    i64,
+);
+
 impl SqliteStore {
     pub async fn list_nodes(&self) -> Result<Vec<NodeRecord>, DomainError> {
+        let _timer = self.query_timer("list_nodes");
         let rows = sqlx::query_as::<_, NodeRow>(
             "SELECT node_id, display_name, platform, device_family, commands_json, paired, status, last_seen_ms, metadata_json \
              FROM nodes ORDER BY last_seen_ms DESC",
@@ -65,6 +82,7 @@ impl SqliteStore {
     }
 
     pub async fn get_node(&self, node_id: &str) -> Result<Option<NodeRecord>, DomainError> {
+        let _timer = self.query_timer("get_node");
         let row = sqlx::query_as::<_, NodeRow>(
             "SELECT node_id, display_name, platform, device_family, commands_json, paired, status, last_seen_ms, metadata_json \
              FROM nodes WHERE node_id = ? LIMIT 1",
@@ -78,6 +96,7 @@ impl SqliteStore {
     }
 
     pub async fn upsert_node(&self, node: &NodeRecord) -> Result<(), DomainError> {
+        let _timer = self.query_timer("upsert_node");
         let commands_json = util::to_json_text(&node.commands).map_err(DomainError::Storage)?;
         let metadata_json =
             util::value_to_json_text(&node.metadata).map_err(DomainError::Storage)?;
@@ -110,6 +129,7 @@ impl SqliteStore {
         node_id: &str,
         display_name: &str,
     ) -> Result<NodeRecord, DomainError> {
+        let _timer = self.query_timer("rename_node");
         sqlx::query("UPDATE nodes SET display_name = ?, last_seen_ms = ? WHERE node_id = ?")
             .bind(display_name)
             .bind(i64::try_from(util::now_unix_ms()).unwrap_or(i64::MAX))
@@ -126,7 +146,9 @@ impl SqliteStore {
     pub async fn add_node_pair_request(
         &self,
         input: NodePairRequestInput,
+        expires_at_ms: u64,
     ) -> Result<NodePairRequestRecord, DomainError> {
+        let _timer = self.query_timer("add_node_pair_request");
         let request = NodePairRequestRecord {
             request_id: format!("pair-{}", uuid::Uuid::new_v4()),
             node_id: input.node_id,
@@ -139,12 +161,13 @@ impl SqliteStore {
             reason: None,
             created_at_ms: util::now_unix_ms(),
             resolved_at_ms: None,
+            expires_at_ms: Some(expires_at_ms),
         };
 
         let commands_json = util::to_json_text(&request.commands).map_err(DomainError::Storage)?;
         sqlx::query(
-            "INSERT INTO node_pair_requests(request_id, node_id, display_name, platform, device_family, commands_json, public_key, status, reason, created_at_ms, resolved_at_ms) \
-             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO node_pair_requests(request_id, node_id, display_name, platform, device_family, commands_json, public_key, status, reason, created_at_ms, resolved_at_ms, expires_at_ms) \
+             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&request.request_id)
         .bind(&request.node_id)
@@ -157,6 +180,7 @@ impl SqliteStore {
         .bind(&request.reason)
         .bind(i64::try_from(request.created_at_ms).unwrap_or(i64::MAX))
         .bind(Option::<i64>::None)
+        .bind(i64::try_from(expires_at_ms).unwrap_or(i64::MAX))
         .execute(self.pool())
         .await
         .map_err(|error| DomainError::Storage(format!("failed to insert pair request: {error}")))?;
@@ -164,9 +188,12 @@ impl SqliteStore {
         Ok(request)
     }
 
+    /// Lists every pair request, newest first, after marking overdue pending ones `expired`.
     pub async fn list_node_pair_requests(&self) -> Result<Vec<NodePairRequestRecord>, DomainError> {
+        let _timer = self.query_timer("list_node_pair_requests");
+        self.expire_node_pair_requests(util::now_unix_ms()).await?;
         let rows = sqlx::query_as::<_, NodePairRow>(
-            "SELECT request_id, node_id, display_name, platform, device_family, commands_json, public_key, status, reason, created_at_ms, resolved_at_ms \
+            "SELECT request_id, node_id, display_name, platform, device_family, commands_json, public_key, status, reason, created_at_ms, resolved_at_ms, expires_at_ms \
              FROM node_pair_requests ORDER BY created_at_ms DESC",
         )
         .fetch_all(self.pool())
@@ -182,11 +209,24 @@ impl SqliteStore {
         approved: bool,
         reason: Option<String>,
     ) -> Result<NodePairRequestRecord, DomainError> {
+        let _timer = self.query_timer("resolve_node_pair_request");
         let Some(mut request) = self.get_node_pair_request(request_id).await? else {
             return Err(DomainError::NotFound(format!(
                 "pair request not found: {request_id}"
             )));
         };
+        let now = util::now_unix_ms();
+        if request.status == "pending"
+            && request.expires_at_ms.is_some_and(|expires| now >= expires)
+        {
+            self.expire_node_pair_requests(now).await?;
+            request.status = "expired".to_owned();
+        }
+        if request.status == "expired" {
+            return Err(DomainError::InvalidRequest(format!(
+                "pair request expired: {request_id}"
+            )));
+        }
 
         request.status = if approved { "approved" } else { "rejected" }.to_owned();
         request.reason = reason;
@@ -231,18 +271,8 @@ impl SqliteStore {
         &self,
         input: NodeInvokeInput,
     ) -> Result<NodeInvokeRecord, DomainError> {
-        let Some(node) = self.get_node(&input.node_id).await? else {
-            return Err(DomainError::NotFound(format!(
-                "node not found: {}",
-                input.node_id
-            )));
-        };
-        if !node.paired {
-            return Err(DomainError::NotPaired(format!(
-                "node is not paired: {}",
-                input.node_id
-            )));
-        }
+        let _timer = self.query_timer("create_node_invoke");
+        self.require_paired_node(&input.node_id).await?;
 
         let now = util::now_unix_ms();
         let invoke = NodeInvokeRecord {
@@ -262,40 +292,191 @@ impl SqliteStore {
             completed_at_ms: Some(now),
         };
 
-        let args_json = util::to_json_text(&invoke.args).map_err(DomainError::Storage)?;
-        let input_json = invoke
-            .input
-            .as_ref()
-            .map(util::value_to_json_text)
-            .transpose()
-            .map_err(DomainError::Storage)?;
-        let result_json = invoke
-            .result
-            .as_ref()
-            .map(util::value_to_json_text)
-            .transpose()
-            .map_err(DomainError::Storage)?;
+        insert_node_invoke(self.pool(), &invoke).await?;
+        Ok(invoke)
+    }
+
+    /// Stores an invoke as `queued` for delivery when the node reconnects.
+    pub async fn queue_node_invoke(
+        &self,
+        input: NodeInvokeInput,
+        expires_at_ms: u64,
+    ) -> Result<QueuedNodeInvoke, DomainError> {
+        let _timer = self.query_timer("queue_node_invoke");
+        self.require_paired_node(&input.node_id).await?;
+
+        let now = util::now_unix_ms();
+        let invoke = NodeInvokeRecord {
+            request_id: format!("invoke-{}", uuid::Uuid::new_v4()),
+            node_id: input.node_id,
+            command: input.command,
+            args: input.args,
+            input: input.input,
+            status: "queued".to_owned(),
+            result: None,
+            error: None,
+            requested_at_ms: now,
+            updated_at_ms: now,
+            completed_at_ms: None,
+        };
 
+        let mut tx = self
+            .pool()
+            .begin()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        insert_node_invoke(&mut *tx, &invoke).await?;
         sqlx::query(
-            "INSERT INTO node_invokes(invoke_id, node_id, command, args_json, input_json, status, result_json, error, requested_at_ms, updated_at_ms, completed_at_ms) \
-             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO node_invoke_queue(invoke_id, node_id, expires_at_ms) VALUES(?, ?, ?)",
         )
         .bind(&invoke.request_id)
         .bind(&invoke.node_id)
-        .bind(&invoke.command)
-        .bind(args_json)
-        .bind(input_json)
-        .bind(&invoke.status)
-        .bind(result_json)
-        .bind(&invoke.error)
-        .bind(i64::try_from(invoke.requested_at_ms).unwrap_or(i64::MAX))
-        .bind(i64::try_from(invoke.updated_at_ms).unwrap_or(i64::MAX))
-        .bind(invoke.completed_at_ms.map(|value| i64::try_from(value).unwrap_or(i64::MAX)))
+        .bind(i64::try_from(expires_at_ms).unwrap_or(i64::MAX))
+        .execute(&mut *tx)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to queue node invoke: {error}")))?;
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))?;
+
+        Ok(QueuedNodeInvoke {
+            invoke,
+            expires_at_ms,
+        })
+    }
+
+    /// Lists queued invokes oldest first, optionally for a single node.
+    pub async fn list_queued_node_invokes(
+        &self,
+        node_id: Option<&str>,
+    ) -> Result<Vec<QueuedNodeInvoke>, DomainError> {
+        let _timer = self.query_timer("list_queued_node_invokes");
+        let rows = sqlx::query_as::<_, QueuedNodeInvokeRow>(
+            "SELECT i.invoke_id, i.node_id, i.command, i.args_json, i.input_json, i.status, i.result_json, i.error, i.requested_at_ms, i.updated_at_ms, i.completed_at_ms, q.expires_at_ms \
+             FROM node_invoke_queue q JOIN node_invokes i ON i.invoke_id = q.invoke_id \
+             WHERE (? IS NULL OR q.node_id = ?) \
+             ORDER BY i.requested_at_ms ASC, i.invoke_id ASC",
+        )
+        .bind(node_id)
+        .bind(node_id)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list queued invokes: {error}")))?;
+
+        rows.into_iter()
+            .map(|row| {
+                let (
+                    request_id,
+                    node_id,
+                    command,
+                    args_json,
+                    input_json,
+                    status,
+                    result_json,
+                    error,
+                    requested_at_ms,
+                    updated_at_ms,
+                    completed_at_ms,
+                    expires_at_ms,
+                ) = row;
+                Ok(QueuedNodeInvoke {
+                    invoke: map_invoke_row((
+                        request_id,
+                        node_id,
+                        command,
+                        args_json,
+                        input_json,
+                        status,
+                        result_json,
+                        error,
+                        requested_at_ms,
+                        updated_at_ms,
+                        completed_at_ms,
+                    ))?,
+                    expires_at_ms: u64::try_from(expires_at_ms).unwrap_or(0),
+                })
+            })
+            .collect()
+    }
+
+    /// Marks queued invokes past their deadline as `expired` and drops them from the queue.
+    pub async fn expire_queued_node_invokes(&self, now_ms: u64) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("expire_queued_node_invokes");
+        let now = i64::try_from(now_ms).unwrap_or(i64::MAX);
+        let mut tx = self
+            .pool()
+            .begin()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        sqlx::query(
+            "UPDATE node_invokes SET status = 'expired', error = 'node did not reconnect before the queued invoke expired', \
+             updated_at_ms = ?, completed_at_ms = ? \
+             WHERE invoke_id IN (SELECT invoke_id FROM node_invoke_queue WHERE expires_at_ms <= ?)",
+        )
+        .bind(now)
+        .bind(now)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to expire queued invokes: {error}")))?;
+        let removed = sqlx::query("DELETE FROM node_invoke_queue WHERE expires_at_ms <= ?")
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to expire queued invokes: {error}"))
+            })?
+            .rows_affected();
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))?;
+        Ok(removed)
+    }
+
+    /// Removes an invoke from the queue and records `status` on it. Returns `None` when the
+    /// invoke is not currently queued.
+    pub async fn dequeue_node_invoke(
+        &self,
+        request_id: &str,
+        status: &str,
+    ) -> Result<Option<NodeInvokeRecord>, DomainError> {
+        let _timer = self.query_timer("dequeue_node_invoke");
+        let removed = sqlx::query("DELETE FROM node_invoke_queue WHERE invoke_id = ?")
+            .bind(request_id)
+            .execute(self.pool())
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to dequeue invoke: {error}")))?
+            .rows_affected();
+        if removed == 0 {
+            return Ok(None);
+        }
+
+        let now = i64::try_from(util::now_unix_ms()).unwrap_or(i64::MAX);
+        let completed_at = (status != "delivered").then_some(now);
+        sqlx::query(
+            "UPDATE node_invokes SET status = ?, updated_at_ms = ?, completed_at_ms = ? WHERE invoke_id = ?",
+        )
+        .bind(status)
+        .bind(now)
+        .bind(completed_at)
+        .bind(request_id)
         .execute(self.pool())
         .await
-        .map_err(|error| DomainError::Storage(format!("failed to create node invoke: {error}")))?;
+        .map_err(|error| DomainError::Storage(format!("failed to update queued invoke: {error}")))?;
 
-        Ok(invoke)
+        self.get_node_invoke(request_id).await
+    }
+
+    async fn require_paired_node(&self, node_id: &str) -> Result<(), DomainError> {
+        let Some(node) = self.get_node(node_id).await? else {
+            return Err(DomainError::NotFound(format!("node not found: {node_id}")));
+        };
+        if !node.paired {
+            return Err(DomainError::NotPaired(format!(
+                "node is not paired: {node_id}"
+            )));
+        }
+        Ok(())
     }
 
     pub async fn update_node_invoke_result(
@@ -305,6 +486,7 @@ impl SqliteStore {
         payload: Option<Value>,
         error: Option<String>,
     ) -> Result<NodeInvokeRecord, DomainError> {
+        let _timer = self.query_timer("update_node_invoke_result");
         let Some(mut invoke) = self.get_node_invoke(request_id).await? else {
             return Err(DomainError::NotFound(format!(
                 "invoke request not found: {request_id}"
@@ -326,6 +508,12 @@ impl SqliteStore {
             .transpose()
             .map_err(DomainError::Storage)?;
 
+        sqlx::query("DELETE FROM node_invoke_queue WHERE invoke_id = ?")
+            .bind(request_id)
+            .execute(self.pool())
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to dequeue invoke: {error}")))?;
+
         sqlx::query(
             "UPDATE node_invokes SET status = ?, result_json = ?, error = ?, updated_at_ms = ?, completed_at_ms = ? WHERE invoke_id = ?",
         )
@@ -346,6 +534,7 @@ impl SqliteStore {
         &self,
         request_id: &str,
     ) -> Result<Option<NodeInvokeRecord>, DomainError> {
+        let _timer = self.query_timer("get_node_invoke");
         let row = sqlx::query_as::<_, NodeInvokeRow>(
             "SELECT invoke_id, node_id, command, args_json, input_json, status, result_json, error, requested_at_ms, updated_at_ms, completed_at_ms \
              FROM node_invokes WHERE invoke_id = ? LIMIT 1",
@@ -364,6 +553,7 @@ impl SqliteStore {
         event: String,
         payload: Option<Value>,
     ) -> Result<NodeEventRecord, DomainError> {
+        let _timer = self.query_timer("add_node_event");
         let record = NodeEventRecord {
             id: format!("evt-{}", uuid::Uuid::new_v4()),
             node_id,
@@ -399,6 +589,7 @@ impl SqliteStore {
         node_id: Option<&str>,
         limit: Option<usize>,
     ) -> Result<Vec<NodeEventRecord>, DomainError> {
+        let _timer = self.query_timer("list_node_events");
         let query = if node_id.is_some() {
             "SELECT event_id, node_id, event, payload_json, ts_ms FROM node_events WHERE node_id = ? ORDER BY ts_ms DESC"
         } else {
@@ -432,6 +623,7 @@ impl SqliteStore {
     }
 
     pub async fn trim_node_events(&self, limit: usize) -> Result<(), DomainError> {
+        let _timer = self.query_timer("trim_node_events");
         let ids = sqlx::query_scalar::<_, String>(
             "SELECT event_id FROM node_events ORDER BY ts_ms DESC LIMIT -1 OFFSET ?",
         )
@@ -468,12 +660,109 @@ impl SqliteStore {
         Ok(())
     }
 
+    pub async fn add_node_metadata_entry(
+        &self,
+        node_id: &str,
+        metadata: Value,
+    ) -> Result<NodeMetadataEntry, DomainError> {
+        let _timer = self.query_timer("add_node_metadata_entry");
+        let entry = NodeMetadataEntry {
+            id: format!("meta-{}", uuid::Uuid::new_v4()),
+            node_id: node_id.to_owned(),
+            metadata,
+            ts: util::now_unix_ms(),
+        };
+        let metadata_json =
+            util::value_to_json_text(&entry.metadata).map_err(DomainError::Storage)?;
+
+        sqlx::query(
+            "INSERT INTO node_metadata_history(entry_id, node_id, metadata_json, ts_ms) VALUES(?, ?, ?, ?)",
+        )
+        .bind(&entry.id)
+        .bind(&entry.node_id)
+        .bind(metadata_json)
+        .bind(i64::try_from(entry.ts).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to insert node metadata entry: {error}"))
+        })?;
+
+        Ok(entry)
+    }
+
+    pub async fn list_node_metadata_history(
+        &self,
+        node_id: &str,
+        limit: usize,
+    ) -> Result<Vec<NodeMetadataEntry>, DomainError> {
+        let _timer = self.query_timer("list_node_metadata_history");
+        sqlx::query_as::<_, (String, String, String, i64)>(
+            "SELECT entry_id, node_id, metadata_json, ts_ms FROM node_metadata_history WHERE node_id = ? ORDER BY ts_ms DESC, rowid DESC LIMIT ?",
+        )
+        .bind(node_id)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to list node metadata history: {error}"))
+        })?
+        .into_iter()
+        .map(|(id, node_id, metadata_json, ts_ms)| {
+            Ok(NodeMetadataEntry {
+                id,
+                node_id,
+                metadata: util::json_text_to_value(&metadata_json).map_err(DomainError::Storage)?,
+                ts: u64::try_from(ts_ms).unwrap_or(0),
+            })
+        })
+        .collect()
+    }
+
+    pub async fn trim_node_metadata_history(
+        &self,
+        node_id: &str,
+        limit: usize,
+    ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("trim_node_metadata_history");
+        sqlx::query(
+            "DELETE FROM node_metadata_history WHERE node_id = ? AND entry_id NOT IN (SELECT entry_id FROM node_metadata_history WHERE node_id = ? ORDER BY ts_ms DESC, rowid DESC LIMIT ?)",
+        )
+        .bind(node_id)
+        .bind(node_id)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to trim node metadata history: {error}"))
+        })?;
+        Ok(())
+    }
+
+    /// Marks pending pair requests past their deadline as `expired`.
+    pub async fn expire_node_pair_requests(&self, now_ms: u64) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("expire_node_pair_requests");
+        let now = i64::try_from(now_ms).unwrap_or(i64::MAX);
+        let expired = sqlx::query(
+            "UPDATE node_pair_requests SET status = 'expired', resolved_at_ms = ? \
+             WHERE status = 'pending' AND expires_at_ms IS NOT NULL AND expires_at_ms <= ?",
+        )
+        .bind(now)
+        .bind(now)
+        .execute(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to expire pair requests: {error}"))
+        })?;
+        Ok(expired.rows_affected())
+    }
+
     async fn get_node_pair_request(
         &self,
         request_id: &str,
     ) -> Result<Option<NodePairRequestRecord>, DomainError> {
         let row = sqlx::query_as::<_, NodePairRow>(
-            "SELECT request_id, node_id, display_name, platform, device_family, commands_json, public_key, status, reason, created_at_ms, resolved_at_ms \
+            "SELECT request_id, node_id, display_name, platform, device_family, commands_json, public_key, status, reason, created_at_ms, resolved_at_ms, expires_at_ms \
              FROM node_pair_requests WHERE request_id = ? LIMIT 1",
         )
         .bind(request_id)
@@ -528,6 +817,7 @@ fn map_pair_row(row: NodePairRow) -> Result<NodePairRequestRecord, DomainError>
         reason,
         created_at_ms,
         resolved_at_ms,
+        expires_at_ms,
     ) = row;
 
     let commands =
@@ -545,9 +835,52 @@ fn map_pair_row(row: NodePairRow) -> Result<NodePairRequestRecord, DomainError>
         reason,
         created_at_ms: u64::try_from(created_at_ms).unwrap_or(0),
         resolved_at_ms: resolved_at_ms.and_then(|value| u64::try_from(value).ok()),
+        expires_at_ms: expires_at_ms.and_then(|value| u64::try_from(value).ok()),
     })
 }
 
+async fn insert_node_invoke<'e, E>(
+    executor: E,
+    invoke: &NodeInvokeRecord,
+) -> Result<(), DomainError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    let args_json = util::to_json_text(&invoke.args).map_err(DomainError::Storage)?;
+    let input_json = invoke
+        .input
+        .as_ref()
+        .map(util::value_to_json_text)
+        .transpose()
+        .map_err(DomainError::Storage)?;
+    let result_json = invoke
+        .result
+        .as_ref()
+        .map(util::value_to_json_text)
+        .transpose()
+        .map_err(DomainError::Storage)?;
+
+    sqlx::query(
+        "INSERT INTO node_invokes(invoke_id, node_id, command, args_json, input_json, status, result_json, error, requested_at_ms, updated_at_ms, completed_at_ms) \
+         VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&invoke.request_id)
+    .bind(&invoke.node_id)
+    .bind(&invoke.command)
+    .bind(args_json)
+    .bind(input_json)
+    .bind(&invoke.status)
+    .bind(result_json)
+    .bind(&invoke.error)
+    .bind(i64::try_from(invoke.requested_at_ms).unwrap_or(i64::MAX))
+    .bind(i64::try_from(invoke.updated_at_ms).unwrap_or(i64::MAX))
+    .bind(invoke.completed_at_ms.map(|value| i64::try_from(value).unwrap_or(i64::MAX)))
+    .execute(executor)
+    .await
+    .map_err(|error| DomainError::Storage(format!("failed to create node invoke: {error}")))?;
+    Ok(())
+}
+
 fn map_invoke_row(row: NodeInvokeRow) -> Result<NodeInvokeRecord, DomainError> {
     let (
         request_id,
@@ -0,0 +1,96 @@
+use crate::{
+    domain::{error::DomainError, models::QueuedOutboundMessage},
+    storage::{SqliteStore, util},
+};
+
+type QueuedOutboundRow = (String, String, String, i64, i64, i64);
+
+impl SqliteStore {
+    pub async fn enqueue_outbound_message(
+        &self,
+        message: &QueuedOutboundMessage,
+    ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("enqueue_outbound_message");
+        let payload_json =
+            util::value_to_json_text(&message.payload).map_err(DomainError::Storage)?;
+        sqlx::query(
+            "INSERT INTO outbound_queue(id, channel, payload_json, attempts, queued_at_ms, release_at_ms) \
+             VALUES(?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&message.id)
+        .bind(&message.channel)
+        .bind(payload_json)
+        .bind(i64::from(message.attempts))
+        .bind(i64::try_from(message.queued_at_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(message.release_at_ms).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to enqueue outbound message: {error}"))
+        })?;
+        Ok(())
+    }
+
+    /// Lists queued messages released at or before `due_at_ms`, oldest first.
+    pub async fn list_outbound_messages(
+        &self,
+        channel: Option<&str>,
+        due_at_ms: u64,
+        limit: usize,
+    ) -> Result<Vec<QueuedOutboundMessage>, DomainError> {
+        let _timer = self.query_timer("list_outbound_messages");
+        let rows = sqlx::query_as::<_, QueuedOutboundRow>(
+            "SELECT id, channel, payload_json, attempts, queued_at_ms, release_at_ms \
+             FROM outbound_queue \
+             WHERE release_at_ms <= ? AND (? IS NULL OR channel = ?) \
+             ORDER BY queued_at_ms ASC, id ASC LIMIT ?",
+        )
+        .bind(i64::try_from(due_at_ms).unwrap_or(i64::MAX))
+        .bind(channel)
+        .bind(channel)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list outbound queue: {error}")))?;
+
+        rows.into_iter().map(map_queued_outbound_row).collect()
+    }
+
+    pub async fn delete_outbound_message(&self, id: &str) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("delete_outbound_message");
+        let result = sqlx::query("DELETE FROM outbound_queue WHERE id = ?")
+            .bind(id)
+            .execute(self.pool())
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to delete outbound message: {error}"))
+            })?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn record_outbound_attempt(&self, id: &str) -> Result<u32, DomainError> {
+        let _timer = self.query_timer("record_outbound_attempt");
+        let row = sqlx::query_as::<_, (i64,)>(
+            "UPDATE outbound_queue SET attempts = attempts + 1 WHERE id = ? RETURNING attempts",
+        )
+        .bind(id)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to record outbound attempt: {error}"))
+        })?;
+        Ok(row.map_or(0, |(attempts,)| u32::try_from(attempts).unwrap_or(u32::MAX)))
+    }
+}
+
+fn map_queued_outbound_row(row: QueuedOutboundRow) -> Result<QueuedOutboundMessage, DomainError> {
+    let (id, channel, payload_json, attempts, queued_at_ms, release_at_ms) = row;
+    Ok(QueuedOutboundMessage {
+        id,
+        channel,
+        payload: util::json_text_to_value(&payload_json).map_err(DomainError::Storage)?,
+        attempts: u32::try_from(attempts).unwrap_or(0),
+        queued_at_ms: u64::try_from(queued_at_ms).unwrap_or(0),
+        release_at_ms: u64::try_from(release_at_ms).unwrap_or(0),
+    })
+}
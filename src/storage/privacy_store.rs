@@ -0,0 +1,244 @@
+use crate::{
+    domain::{
+        error::DomainError,
+        models::{PrivacyAuditRecord, SessionPurgeCounts},
+    },
+    storage::{SqliteStore, util},
+};
+
+type PrivacyAuditRow = (String, String, String, String, String, i64);
+
+impl SqliteStore {
+    /// Lists every session key referenced by sessions, chat messages (live or archived), or agent
+    /// runs that matches the SQL `LIKE` pattern.
+    pub async fn list_session_keys_like(&self, pattern: &str) -> Result<Vec<String>, DomainError> {
+        let _timer = self.query_timer("list_session_keys_like");
+        let rows = sqlx::query_as::<_, (String,)>(
+            "SELECT id FROM sessions WHERE id LIKE ?1 \
+             UNION SELECT session_key FROM chat_messages WHERE session_key LIKE ?1 \
+             UNION SELECT session_key FROM agent_runs WHERE session_key LIKE ?1 \
+             UNION SELECT session_key FROM chat_archive_segments WHERE session_key LIKE ?1 \
+             ORDER BY 1",
+        )
+        .bind(pattern)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list session keys: {error}")))?;
+        Ok(rows.into_iter().map(|(key,)| key).collect())
+    }
+
+    /// Irreversibly removes the session row, chat history, scratchpad, agent runs, and delivery
+    /// records for `session_key`.
+    pub async fn purge_session_data(
+        &self,
+        session_key: &str,
+    ) -> Result<SessionPurgeCounts, DomainError> {
+        let _timer = self.query_timer("purge_session_data");
+        let mut tx = self
+            .pool()
+            .begin()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+
+        let messages = sqlx::query("DELETE FROM chat_messages WHERE session_key = ?")
+            .bind(session_key)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to purge messages: {error}")))?
+            .rows_affected();
+        sqlx::query("DELETE FROM chat_pins WHERE session_key = ?")
+            .bind(session_key)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to purge chat pins: {error}")))?;
+        sqlx::query("DELETE FROM session_kv WHERE session_key = ?")
+            .bind(session_key)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to purge session scratchpad: {error}"))
+            })?;
+        sqlx::query("DELETE FROM chat_read_markers WHERE session_key = ?")
+            .bind(session_key)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to purge chat read markers: {error}"))
+            })?;
+        let runs = sqlx::query("DELETE FROM agent_runs WHERE session_key = ?")
+            .bind(session_key)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to purge agent runs: {error}")))?
+            .rows_affected();
+        sqlx::query("DELETE FROM message_deliveries WHERE session_key = ?")
+            .bind(session_key)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to purge message deliveries: {error}"))
+            })?;
+        let sessions = sqlx::query("DELETE FROM sessions WHERE id = ?")
+            .bind(session_key)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to purge session: {error}")))?
+            .rows_affected();
+
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))?;
+
+        Ok(SessionPurgeCounts {
+            sessions,
+            messages,
+            runs,
+        })
+    }
+
+    pub async fn delete_channel_directory_entry(
+        &self,
+        channel: &str,
+        conversation_id: &str,
+    ) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("delete_channel_directory_entry");
+        let result =
+            sqlx::query("DELETE FROM channel_directory WHERE channel = ? AND conversation_id = ?")
+                .bind(channel)
+                .bind(conversation_id)
+                .execute(self.pool())
+                .await
+                .map_err(|error| {
+                    DomainError::Storage(format!(
+                        "failed to delete channel directory entry: {error}"
+                    ))
+                })?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn record_privacy_audit(
+        &self,
+        record: &PrivacyAuditRecord,
+    ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("record_privacy_audit");
+        let subject_json =
+            util::value_to_json_text(&record.subject).map_err(DomainError::Storage)?;
+        let summary_json =
+            util::value_to_json_text(&record.summary).map_err(DomainError::Storage)?;
+        sqlx::query(
+            "INSERT INTO privacy_audit(id, action, subject_json, summary_json, requested_by, created_at_ms) \
+             VALUES(?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&record.id)
+        .bind(&record.action)
+        .bind(subject_json)
+        .bind(summary_json)
+        .bind(&record.requested_by)
+        .bind(i64::try_from(record.created_at_ms).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to record privacy audit: {error}")))?;
+        Ok(())
+    }
+
+    pub async fn list_privacy_audit(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<PrivacyAuditRecord>, DomainError> {
+        let _timer = self.query_timer("list_privacy_audit");
+        let rows = sqlx::query_as::<_, PrivacyAuditRow>(
+            "SELECT id, action, subject_json, summary_json, requested_by, created_at_ms \
+             FROM privacy_audit ORDER BY created_at_ms DESC, rowid DESC LIMIT ?",
+        )
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list privacy audit: {error}")))?;
+
+        rows.into_iter().map(map_privacy_audit_row).collect()
+    }
+}
+
+fn map_privacy_audit_row(row: PrivacyAuditRow) -> Result<PrivacyAuditRecord, DomainError> {
+    let (id, action, subject_json, summary_json, requested_by, created_at_ms) = row;
+    Ok(PrivacyAuditRecord {
+        id,
+        action,
+        subject: util::json_text_to_value(&subject_json).map_err(DomainError::Storage)?,
+        summary: util::json_text_to_value(&summary_json).map_err(DomainError::Storage)?,
+        requested_by,
+        created_at_ms: u64::try_from(created_at_ms).unwrap_or(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::SqliteStore;
+    use crate::domain::models::{ChatMessage, SessionRecord};
+
+    async fn make_store() -> (TempDir, SqliteStore) {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let store = SqliteStore::connect(&temp.path().join("state.db"))
+            .await
+            .expect("sqlite store should connect");
+        (temp, store)
+    }
+
+    #[tokio::test]
+    async fn purge_session_data_removes_only_the_target_session() {
+        let (_temp, store) = make_store().await;
+        for key in [
+            "agent:main:telegram:chat:333",
+            "agent:main:telegram:chat:444",
+        ] {
+            store
+                .upsert_session(&SessionRecord {
+                    id: key.to_owned(),
+                    title: key.to_owned(),
+                    tags: Vec::new(),
+                    metadata: json!({}),
+                    created_at_ms: 1,
+                    updated_at_ms: 1,
+                })
+                .await
+                .expect("session should upsert");
+            store
+                .append_chat_messages(
+                    key,
+                    &[ChatMessage {
+                        id: format!("msg-{key}"),
+                        role: "user".to_owned(),
+                        text: "hello".to_owned(),
+                        status: "final".to_owned(),
+                        ts: 1,
+                        metadata: json!({}),
+                        pinned: false,
+                    }],
+                )
+                .await
+                .expect("message should append");
+        }
+
+        let keys = store
+            .list_session_keys_like("agent:%:telegram:chat:333")
+            .await
+            .expect("keys should list");
+        assert_eq!(keys, vec!["agent:main:telegram:chat:333".to_owned()]);
+
+        let counts = store
+            .purge_session_data("agent:main:telegram:chat:333")
+            .await
+            .expect("purge should succeed");
+        assert_eq!((counts.sessions, counts.messages, counts.runs), (1, 1, 0));
+        assert_eq!(
+            store
+                .count_chat_messages()
+                .await
+                .expect("messages should count"),
+            1
+        );
+    }
+}
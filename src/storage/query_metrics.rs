@@ -0,0 +1,281 @@
+//! Latency histograms for `SqliteStore` queries, labelled by logical operation (the store method
+//! that ran them). Totals since startup feed the `/metrics` endpoint; per-minute slots over the
+//! last hour feed `doctor.storage.slowQueries`.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Write as _,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::storage::now_unix_ms;
+
+/// Upper bounds of the histogram buckets in microseconds; a final bucket catches the rest.
+const BUCKET_BOUNDS_US: [u64; 10] = [
+    1_000, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000, 2_500_000,
+];
+/// Length of the window `slow_operations` reports on.
+pub const SLOW_QUERY_WINDOW: Duration = Duration::from_secs(60 * 60);
+const SLOT_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; BUCKET_BOUNDS_US.len() + 1],
+    count: u64,
+    sum_us: u64,
+    max_us: u64,
+}
+
+impl Histogram {
+    fn record(&mut self, elapsed_us: u64) {
+        let bucket = BUCKET_BOUNDS_US
+            .iter()
+            .position(|bound| elapsed_us <= *bound)
+            .unwrap_or(BUCKET_BOUNDS_US.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum_us = self.sum_us.saturating_add(elapsed_us);
+        self.max_us = self.max_us.max(elapsed_us);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        for (bucket, count) in self.buckets.iter_mut().zip(other.buckets) {
+            *bucket += count;
+        }
+        self.count += other.count;
+        self.sum_us = self.sum_us.saturating_add(other.sum_us);
+        self.max_us = self.max_us.max(other.max_us);
+    }
+
+    /// Upper bound of the bucket holding quantile `q`, capped at the slowest sample.
+    fn quantile_us(&self, q: f64) -> u64 {
+        let rank = ((self.count as f64) * q).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKET_BOUNDS_US
+                    .get(index)
+                    .map_or(self.max_us, |bound| (*bound).min(self.max_us));
+            }
+        }
+        self.max_us
+    }
+}
+
+#[derive(Debug, Default)]
+struct OperationStats {
+    total: Histogram,
+    /// `(slot start ms, histogram)` for the minutes inside [`SLOW_QUERY_WINDOW`], oldest first.
+    recent: VecDeque<(u64, Histogram)>,
+}
+
+/// One operation in the `doctor.storage.slowQueries` report.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowOperation {
+    pub operation: &'static str,
+    pub calls: u64,
+    pub avg_ms: f64,
+    /// Bucket upper bound, so an estimate rather than an exact percentile.
+    pub p95_ms: f64,
+    pub max_ms: f64,
+    pub total_ms: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct QueryMetrics {
+    operations: Mutex<BTreeMap<&'static str, OperationStats>>,
+}
+
+impl QueryMetrics {
+    pub fn record(&self, operation: &'static str, elapsed: Duration) {
+        let elapsed_us = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.record_at(operation, elapsed_us, now_unix_ms());
+    }
+
+    fn record_at(&self, operation: &'static str, elapsed_us: u64, now_ms: u64) {
+        let slot = now_ms - now_ms % SLOT_MS;
+        let mut operations = self
+            .operations
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let stats = operations.entry(operation).or_default();
+        stats.total.record(elapsed_us);
+        match stats.recent.back_mut() {
+            Some((start, histogram)) if *start == slot => histogram.record(elapsed_us),
+            _ => {
+                let mut histogram = Histogram::default();
+                histogram.record(elapsed_us);
+                stats.recent.push_back((slot, histogram));
+            }
+        }
+        let cutoff = window_start(now_ms);
+        while stats
+            .recent
+            .front()
+            .is_some_and(|(start, _)| *start < cutoff)
+        {
+            stats.recent.pop_front();
+        }
+    }
+
+    /// Operations that ran within the last hour, slowest first by p95 and then by maximum.
+    pub fn slow_operations(&self, limit: usize) -> Vec<SlowOperation> {
+        self.slow_operations_at(limit, now_unix_ms())
+    }
+
+    fn slow_operations_at(&self, limit: usize, now_ms: u64) -> Vec<SlowOperation> {
+        let cutoff = window_start(now_ms);
+        let operations = self
+            .operations
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let mut report = operations
+            .iter()
+            .filter_map(|(operation, stats)| {
+                let mut window = Histogram::default();
+                for (_, histogram) in stats.recent.iter().filter(|(start, _)| *start >= cutoff) {
+                    window.merge(histogram);
+                }
+                (window.count > 0).then(|| SlowOperation {
+                    operation,
+                    calls: window.count,
+                    avg_ms: us_to_ms(window.sum_us / window.count),
+                    p95_ms: us_to_ms(window.quantile_us(0.95)),
+                    max_ms: us_to_ms(window.max_us),
+                    total_ms: us_to_ms(window.sum_us),
+                })
+            })
+            .collect::<Vec<_>>();
+        report.sort_by(|left, right| {
+            right
+                .p95_ms
+                .total_cmp(&left.p95_ms)
+                .then(right.max_ms.total_cmp(&left.max_ms))
+        });
+        report.truncate(limit);
+        report
+    }
+
+    /// Prometheus text exposition of the totals since startup.
+    pub fn render_prometheus(&self) -> String {
+        let operations = self
+            .operations
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let mut out = String::from(
+            "# HELP reclaw_storage_query_duration_seconds SQLite store query latency by operation.\n\
+             # TYPE reclaw_storage_query_duration_seconds histogram\n",
+        );
+        for (operation, stats) in operations.iter() {
+            let histogram = &stats.total;
+            let mut cumulative = 0;
+            for (index, count) in histogram.buckets.iter().enumerate() {
+                cumulative += count;
+                let le = BUCKET_BOUNDS_US
+                    .get(index)
+                    .map_or_else(|| "+Inf".to_owned(), |bound| us_to_seconds(*bound));
+                let _ = writeln!(
+                    out,
+                    "reclaw_storage_query_duration_seconds_bucket{{operation=\"{operation}\",le=\"{le}\"}} {cumulative}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "reclaw_storage_query_duration_seconds_sum{{operation=\"{operation}\"}} {}",
+                us_to_seconds(histogram.sum_us)
+            );
+            let _ = writeln!(
+                out,
+                "reclaw_storage_query_duration_seconds_count{{operation=\"{operation}\"}} {}",
+                histogram.count
+            );
+        }
+        out
+    }
+}
+
+/// Records the time from creation to drop under `operation`.
+pub struct QueryTimer<'a> {
+    metrics: &'a QueryMetrics,
+    operation: &'static str,
+    started: Instant,
+}
+
+impl<'a> QueryTimer<'a> {
+    pub fn start(metrics: &'a QueryMetrics, operation: &'static str) -> Self {
+        Self {
+            metrics,
+            operation,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for QueryTimer<'_> {
+    fn drop(&mut self) {
+        self.metrics.record(self.operation, self.started.elapsed());
+    }
+}
+
+fn window_start(now_ms: u64) -> u64 {
+    let window_ms = u64::try_from(SLOW_QUERY_WINDOW.as_millis()).unwrap_or(u64::MAX);
+    let slot = now_ms - now_ms % SLOT_MS;
+    slot.saturating_sub(window_ms - SLOT_MS)
+}
+
+fn us_to_ms(us: u64) -> f64 {
+    us as f64 / 1_000.0
+}
+
+fn us_to_seconds(us: u64) -> String {
+    format!("{}", us as f64 / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueryMetrics, SLOT_MS};
+
+    #[test]
+    fn query_metrics_report_slow_operations_and_prometheus_histograms() {
+        let metrics = QueryMetrics::default();
+        let now = 10 * 60 * SLOT_MS;
+        metrics.record_at("list_sessions", 800, now);
+        metrics.record_at("list_sessions", 3_000, now);
+        metrics.record_at("search_chat_messages", 120_000, now - 2 * SLOT_MS);
+        metrics.record_at("search_chat_messages", 40_000, now);
+        metrics.record_at("prune_cron_runs", 900_000, now - 61 * SLOT_MS);
+
+        let report = metrics.slow_operations_at(10, now);
+        assert_eq!(
+            report
+                .iter()
+                .map(|entry| (entry.operation, entry.calls))
+                .collect::<Vec<_>>(),
+            vec![("search_chat_messages", 2), ("list_sessions", 2)]
+        );
+        assert_eq!(report[0].max_ms, 120.0);
+        assert_eq!(report[0].p95_ms, 120.0);
+        assert_eq!(report[0].avg_ms, 80.0);
+        assert_eq!(report[1].p95_ms, 3.0);
+        assert_eq!(metrics.slow_operations_at(1, now).len(), 1);
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains(
+            "reclaw_storage_query_duration_seconds_bucket{operation=\"list_sessions\",le=\"0.001\"} 1\n"
+        ));
+        assert!(text.contains(
+            "reclaw_storage_query_duration_seconds_bucket{operation=\"list_sessions\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(text.contains(
+            "reclaw_storage_query_duration_seconds_count{operation=\"prune_cron_runs\"} 1\n"
+        ));
+        assert!(text.contains(
+            "reclaw_storage_query_duration_seconds_sum{operation=\"list_sessions\"} 0.0038\n"
+        ));
+    }
+}
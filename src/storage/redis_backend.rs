@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use redis::{
+    AsyncCommands,
+    aio::{ConnectionManager, ConnectionManagerConfig},
+};
+use serde_json::Value;
+
+use crate::{domain::error::DomainError, storage::util};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Commands are on the request path (auth checks, idempotency claims), so a stalled Redis must
+/// fail fast and let callers fall back.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Shared state for horizontally scaled gateways: sliding-window rate limits, idempotency key
+/// claims, and a read-through cache of config entries. Every key lives under `prefix`; cached
+/// config entries also sit under the id of the database they were read from, since each
+/// instance keeps its own.
+#[derive(Clone)]
+pub struct RedisBackend {
+    connection: ConnectionManager,
+    prefix: String,
+    store_id: String,
+}
+
+impl std::fmt::Debug for RedisBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisBackend")
+            .field("prefix", &self.prefix)
+            .field("store_id", &self.store_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Outcome of [`RedisBackend::claim_idempotency_key`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    Claimed,
+    /// Another request (possibly on another instance) holds the key; carries its fingerprint.
+    Held(String),
+}
+
+impl RedisBackend {
+    pub async fn connect(url: &str, prefix: &str, store_id: &str) -> Result<Self, DomainError> {
+        let client = redis::Client::open(url)
+            .map_err(|error| DomainError::Unavailable(format!("invalid redis url: {error}")))?;
+        let config = ConnectionManagerConfig::new()
+            .set_factor(2)
+            .set_max_delay(500)
+            .set_number_of_retries(2)
+            .set_connection_timeout(CONNECT_TIMEOUT)
+            .set_response_timeout(RESPONSE_TIMEOUT);
+        let connection = ConnectionManager::new_with_config(client, config)
+            .await
+            .map_err(|error| {
+                DomainError::Unavailable(format!("failed to connect to redis: {error}"))
+            })?;
+        Ok(Self {
+            connection,
+            prefix: prefix.to_owned(),
+            store_id: store_id.to_owned(),
+        })
+    }
+
+    fn key(&self, kind: &str, name: &str) -> String {
+        namespaced_key(&self.prefix, kind, name)
+    }
+
+    fn config_key(&self, key: &str) -> String {
+        namespaced_key(&self.prefix, "config", &format!("{}:{key}", self.store_id))
+    }
+
+    /// Drops attempts older than the window and returns `(count, oldest_ms)` for `key`.
+    pub async fn attempts(
+        &self,
+        scope: &str,
+        key: &str,
+        now_ms: u64,
+        window_ms: u64,
+    ) -> Result<(u64, Option<u64>), DomainError> {
+        let key = self.key(&format!("rl:{scope}"), key);
+        let cutoff = now_ms.saturating_sub(window_ms);
+        let (count, oldest): (u64, Vec<(String, f64)>) = redis::pipe()
+            .atomic()
+            .zrembyscore(&key, "-inf", format!("({cutoff}"))
+            .ignore()
+            .zcard(&key)
+            .zrange_withscores(&key, 0, 0)
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(redis_error)?;
+        Ok((count, oldest.first().map(|(_, score)| score_to_ms(*score))))
+    }
+
+    /// Adds an attempt at `now_ms` and returns the in-window count including it.
+    pub async fn record_attempt(
+        &self,
+        scope: &str,
+        key: &str,
+        now_ms: u64,
+        window_ms: u64,
+    ) -> Result<u64, DomainError> {
+        let key = self.key(&format!("rl:{scope}"), key);
+        let cutoff = now_ms.saturating_sub(window_ms);
+        let member = format!("{now_ms}-{}", uuid::Uuid::new_v4().simple());
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .zrembyscore(&key, "-inf", format!("({cutoff}"))
+            .ignore()
+            .zadd(&key, member, now_ms)
+            .ignore()
+            .zcard(&key)
+            .pexpire(&key, i64::try_from(window_ms).unwrap_or(i64::MAX))
+            .ignore()
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(redis_error)?;
+        Ok(count)
+    }
+
+    pub async fn clear_attempts(&self, scope: &str, key: &str) -> Result<(), DomainError> {
+        let key = self.key(&format!("rl:{scope}"), key);
+        self.connection
+            .clone()
+            .del::<_, ()>(key)
+            .await
+            .map_err(redis_error)
+    }
+
+    /// Atomically claims `key` for `ttl_ms`, storing `fingerprint` so later holders can tell
+    /// whether a retry matches the original request.
+    pub async fn claim_idempotency_key(
+        &self,
+        key: &str,
+        fingerprint: &str,
+        ttl_ms: u64,
+    ) -> Result<IdempotencyClaim, DomainError> {
+        let key = self.key("idem", key);
+        let mut connection = self.connection.clone();
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(fingerprint)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async(&mut connection)
+            .await
+            .map_err(redis_error)?;
+        if claimed.is_some() {
+            return Ok(IdempotencyClaim::Claimed);
+        }
+        let existing: Option<String> = connection.get(&key).await.map_err(redis_error)?;
+        Ok(existing.map_or(IdempotencyClaim::Claimed, IdempotencyClaim::Held))
+    }
+
+    pub async fn cached_config_entry(&self, key: &str) -> Result<Option<Value>, DomainError> {
+        let raw: Option<String> = self
+            .connection
+            .clone()
+            .get(self.config_key(key))
+            .await
+            .map_err(redis_error)?;
+        raw.as_deref()
+            .map(util::json_text_to_value)
+            .transpose()
+            .map_err(DomainError::Storage)
+    }
+
+    pub async fn cache_config_entry(
+        &self,
+        key: &str,
+        value: &Value,
+        ttl_ms: u64,
+    ) -> Result<(), DomainError> {
+        let text = util::value_to_json_text(value).map_err(DomainError::Storage)?;
+        self.connection
+            .clone()
+            .pset_ex::<_, _, ()>(self.config_key(key), text, ttl_ms)
+            .await
+            .map_err(redis_error)
+    }
+
+    pub async fn invalidate_config_entry(&self, key: &str) -> Result<(), DomainError> {
+        self.connection
+            .clone()
+            .del::<_, ()>(self.config_key(key))
+            .await
+            .map_err(redis_error)
+    }
+}
+
+fn namespaced_key(prefix: &str, kind: &str, name: &str) -> String {
+    format!("{prefix}{kind}:{name}")
+}
+
+fn score_to_ms(score: f64) -> u64 {
+    // Scores are whole milliseconds written by `record_attempt`.
+    if score.is_finite() && score > 0.0 {
+        score as u64
+    } else {
+        0
+    }
+}
+
+fn redis_error(error: redis::RedisError) -> DomainError {
+    DomainError::Unavailable(format!("redis error: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{namespaced_key, score_to_ms};
+
+    #[test]
+    fn redis_keys_are_namespaced_and_scores_decode() {
+        assert_eq!(
+            namespaced_key("reclaw:", "rl:auth", "10.0.0.1:cli"),
+            "reclaw:rl:auth:10.0.0.1:cli"
+        );
+        assert_eq!(score_to_ms(1_700_000_000_123.0), 1_700_000_000_123);
+        assert_eq!(score_to_ms(f64::NAN), 0);
+    }
+}
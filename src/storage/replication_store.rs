@@ -0,0 +1,668 @@
+use std::{collections::HashMap, path::Path};
+
+use serde_json::Value;
+use sqlx::{Connection, SqliteConnection};
+
+use crate::{
+    domain::{
+        error::DomainError,
+        models::{ReplicatedChange, ReplicationState},
+    },
+    storage::{
+        SqliteStore,
+        util::{self, NOW_MS_SQL, quote_identifier},
+    },
+};
+
+const SOURCE_SCHEMA: &str = "replica_source";
+/// Tables that are never shipped: this instance's own replication bookkeeping, the migration
+/// lock, `agent_usage` (its triggers recount it as sessions and messages arrive) and `tombstones`
+/// (the standby's own append-only triggers write them as deletes arrive).
+const UNREPLICATED_TABLES: &[&str] = &[
+    "migration_lock",
+    "agent_usage",
+    "tombstones",
+    "replication_changes",
+    "replication_state",
+];
+/// `operation` of a captured insert or update.
+pub const UPSERT_OPERATION: &str = "upsert";
+/// `operation` of a captured delete.
+pub const DELETE_OPERATION: &str = "delete";
+
+type ChangeRow = (i64, String, String, String, Option<String>);
+type ReplicationStateRow = (i64, i64, i64, Option<String>, Option<i64>, Option<String>);
+
+impl SqliteStore {
+    /// Installs triggers that record every insert, update and delete on the replicated tables in
+    /// `replication_changes` when `enabled`, and removes them otherwise. Like the append-only
+    /// triggers they are rebuilt on every call, so they cover columns added since.
+    pub async fn set_change_capture(&self, enabled: bool) -> Result<(), DomainError> {
+        let _timer = self.query_timer("set_change_capture");
+        let map_error = |error: sqlx::Error| {
+            DomainError::Storage(format!("failed to configure change capture: {error}"))
+        };
+        let mut tx = self.pool().begin().await.map_err(map_error)?;
+        let mut statements = sqlx::query_scalar::<_, String>(
+            "SELECT name FROM sqlite_master WHERE type = 'trigger' \
+             AND name LIKE 'replicate\\_%' ESCAPE '\\'",
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(map_error)?
+        .into_iter()
+        .map(|name| format!("DROP TRIGGER IF EXISTS {}", quote_identifier(&name)))
+        .collect::<Vec<_>>();
+        if enabled {
+            for table in replicated_tables(&mut tx, "main").await? {
+                let columns = table_columns(&mut tx, "main", &table).await?;
+                statements.extend(capture_triggers(&table, &columns)?);
+            }
+        }
+        for statement in statements {
+            sqlx::query(&statement)
+                .execute(&mut *tx)
+                .await
+                .map_err(map_error)?;
+        }
+        tx.commit().await.map_err(map_error)
+    }
+
+    /// Captured changes after `after_seq`, oldest first, or `None` when some of them were already
+    /// pruned and the standby has to load a snapshot instead.
+    pub async fn list_replicated_changes(
+        &self,
+        after_seq: u64,
+        limit: usize,
+    ) -> Result<Option<Vec<ReplicatedChange>>, DomainError> {
+        let _timer = self.query_timer("list_replicated_changes");
+        let map_error = |error: sqlx::Error| {
+            DomainError::Storage(format!("failed to list replicated changes: {error}"))
+        };
+        // One read transaction, so a prune cannot slip in between the check and the read.
+        let mut tx = self.pool().begin().await.map_err(map_error)?;
+        let pruned_seq =
+            sqlx::query_scalar::<_, i64>("SELECT pruned_seq FROM replication_state WHERE id = 1")
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(map_error)?;
+        let after_seq = i64::try_from(after_seq).unwrap_or(i64::MAX);
+        if after_seq < pruned_seq {
+            return Ok(None);
+        }
+        let rows = sqlx::query_as::<_, ChangeRow>(
+            "SELECT seq, table_name, operation, key_json, row_json FROM replication_changes \
+             WHERE seq > ? ORDER BY seq LIMIT ?",
+        )
+        .bind(after_seq)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(map_error)?;
+        tx.commit().await.map_err(map_error)?;
+        rows.into_iter()
+            .map(map_change_row)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some)
+    }
+
+    /// Applies changes shipped from the primary in one transaction and records the last one as
+    /// applied. An upsert updates the row in place and inserts it only when missing, so this
+    /// database's own insert triggers see each row once. Tables and columns missing here are
+    /// skipped, so a standby one migration behind still follows. Returns the applied sequence.
+    pub async fn apply_replicated_changes(
+        &self,
+        changes: &[ReplicatedChange],
+    ) -> Result<Option<u64>, DomainError> {
+        let _timer = self.query_timer("apply_replicated_changes");
+        let Some(last) = changes.last() else {
+            return Ok(None);
+        };
+        let mut tx = self.pool().begin().await.map_err(apply_error)?;
+        let mut schemas = HashMap::<&str, Vec<(String, i64)>>::new();
+        for change in changes {
+            if UNREPLICATED_TABLES.contains(&change.table.as_str()) {
+                continue;
+            }
+            if !schemas.contains_key(change.table.as_str()) {
+                let columns = table_columns(&mut tx, "main", &change.table).await?;
+                schemas.insert(&change.table, columns);
+            }
+            let columns = &schemas[change.table.as_str()];
+            if !columns.is_empty() {
+                apply_change(&mut tx, change, columns).await?;
+            }
+        }
+        sqlx::query("UPDATE replication_state SET applied_seq = ? WHERE id = 1")
+            .bind(i64::try_from(last.seq).unwrap_or(i64::MAX))
+            .execute(&mut *tx)
+            .await
+            .map_err(apply_error)?;
+        tx.commit().await.map_err(apply_error)?;
+        Ok(Some(last.seq))
+    }
+
+    /// Drops captured changes up to `through_seq`, plus any written before `before_ms`, and
+    /// remembers how far the log was pruned so a standby behind that point reloads a snapshot.
+    pub async fn prune_replicated_changes(
+        &self,
+        through_seq: u64,
+        before_ms: u64,
+    ) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("prune_replicated_changes");
+        let map_error = |error: sqlx::Error| {
+            DomainError::Storage(format!("failed to prune replicated changes: {error}"))
+        };
+        let mut tx = self.pool().begin().await.map_err(map_error)?;
+        let pruned = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MAX(seq) FROM replication_changes WHERE seq <= MAX(?, \
+             COALESCE((SELECT MAX(seq) FROM replication_changes WHERE ts_ms < ?), 0))",
+        )
+        .bind(i64::try_from(through_seq).unwrap_or(i64::MAX))
+        .bind(i64::try_from(before_ms).unwrap_or(i64::MAX))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_error)?;
+        let Some(pruned) = pruned else {
+            return Ok(0);
+        };
+        let removed = sqlx::query("DELETE FROM replication_changes WHERE seq <= ?")
+            .bind(pruned)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_error)?
+            .rows_affected();
+        sqlx::query("UPDATE replication_state SET pruned_seq = MAX(pruned_seq, ?) WHERE id = 1")
+            .bind(pruned)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_error)?;
+        tx.commit().await.map_err(map_error)?;
+        Ok(removed)
+    }
+
+    pub async fn load_replication_state(&self) -> Result<ReplicationState, DomainError> {
+        let _timer = self.query_timer("load_replication_state");
+        let row = sqlx::query_as::<_, ReplicationStateRow>(
+            "SELECT epoch, applied_seq, pruned_seq, peer_url, fenced_at_ms, fenced_by_url \
+             FROM replication_state WHERE id = 1",
+        )
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to load replication state: {error}"))
+        })?;
+        let Some((epoch, applied_seq, pruned_seq, peer_url, fenced_at_ms, fenced_by_url)) = row
+        else {
+            return Ok(ReplicationState::default());
+        };
+        Ok(ReplicationState {
+            epoch: u64::try_from(epoch).unwrap_or(0),
+            applied_seq: u64::try_from(applied_seq).unwrap_or(0),
+            pruned_seq: u64::try_from(pruned_seq).unwrap_or(0),
+            peer_url,
+            fenced_at_ms: fenced_at_ms.and_then(|ms| u64::try_from(ms).ok()),
+            fenced_by_url,
+        })
+    }
+
+    /// Records a promotion (`fenced_at_ms` unset) or this primary being fenced by a newer one.
+    pub async fn set_replication_epoch(
+        &self,
+        epoch: u64,
+        fenced_at_ms: Option<u64>,
+        fenced_by_url: Option<&str>,
+    ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("set_replication_epoch");
+        sqlx::query(
+            "UPDATE replication_state SET epoch = ?, fenced_at_ms = ?, fenced_by_url = ? \
+             WHERE id = 1",
+        )
+        .bind(i64::try_from(epoch).unwrap_or(i64::MAX))
+        .bind(fenced_at_ms.map(|ms| i64::try_from(ms).unwrap_or(i64::MAX)))
+        .bind(fenced_by_url)
+        .execute(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to save replication epoch: {error}"))
+        })?;
+        Ok(())
+    }
+
+    pub async fn set_replication_peer(&self, peer_url: &str) -> Result<(), DomainError> {
+        let _timer = self.query_timer("set_replication_peer");
+        sqlx::query("UPDATE replication_state SET peer_url = ? WHERE id = 1")
+            .bind(peer_url)
+            .execute(self.pool())
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to save replication peer: {error}"))
+            })?;
+        Ok(())
+    }
+
+    /// Makes the rows of every replicated table match those of the primary's snapshot at `path`
+    /// in one transaction, and takes over its epoch and change sequence so the standby
+    /// continues from the change log. Tables and columns missing on either side are skipped.
+    /// Returns the number of rows copied.
+    pub async fn restore_from_snapshot(&self, path: &Path) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("restore_from_snapshot");
+        let mut conn = self.pool().acquire().await.map_err(restore_error)?;
+        sqlx::query(&format!("ATTACH DATABASE ? AS {SOURCE_SCHEMA}"))
+            .bind(path.display().to_string())
+            .execute(&mut *conn)
+            .await
+            .map_err(restore_error)?;
+        let copied = copy_attached_tables(&mut conn).await;
+        let detached = sqlx::query(&format!("DETACH DATABASE {SOURCE_SCHEMA}"))
+            .execute(&mut *conn)
+            .await;
+        let rows = copied?;
+        detached.map_err(restore_error)?;
+        Ok(rows)
+    }
+}
+
+async fn copy_attached_tables(conn: &mut SqliteConnection) -> Result<u64, DomainError> {
+    let mut tx = conn.begin().await.map_err(restore_error)?;
+    let source = replicated_tables(&mut tx, SOURCE_SCHEMA).await?;
+    let tables = replicated_tables(&mut tx, "main")
+        .await?
+        .into_iter()
+        .filter(|table| source.contains(table))
+        .collect::<Vec<_>>();
+
+    // Rows gone from the primary are deleted and the rest upserted, so a standby in append-only
+    // mode tombstones only what actually changed.
+    let mut rows = 0;
+    for table in tables {
+        let source = table_columns(&mut tx, SOURCE_SCHEMA, &table).await?;
+        let columns = table_columns(&mut tx, "main", &table)
+            .await?
+            .into_iter()
+            .filter(|(name, _)| source.iter().any(|(source, _)| source == name))
+            .collect::<Vec<_>>();
+        let keys = primary_key(&columns)
+            .into_iter()
+            .map(quote_identifier)
+            .collect::<Vec<_>>()
+            .join(", ");
+        if keys.is_empty() {
+            continue;
+        }
+        let names = columns
+            .iter()
+            .map(|(name, _)| quote_identifier(name))
+            .collect::<Vec<_>>();
+        let assignments = names
+            .iter()
+            .map(|name| format!("{name} = excluded.{name}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let names = names.join(", ");
+
+        let quoted = quote_identifier(&table);
+        sqlx::query(&format!(
+            "DELETE FROM main.{quoted} WHERE ({keys}) NOT IN \
+             (SELECT {keys} FROM {SOURCE_SCHEMA}.{quoted})"
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(restore_error)?;
+        rows += sqlx::query(&format!(
+            "INSERT INTO main.{quoted} ({names}) SELECT {names} FROM {SOURCE_SCHEMA}.{quoted} \
+             WHERE true ON CONFLICT DO UPDATE SET {assignments}"
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(restore_error)?
+        .rows_affected();
+    }
+
+    // The snapshot holds the primary's change log up to the moment it was taken; the standby
+    // asks for changes after its highest sequence, which `sqlite_sequence` keeps even when the
+    // log was pruned empty.
+    sqlx::query(&format!(
+        "UPDATE main.replication_state SET \
+           epoch = COALESCE((SELECT epoch FROM {SOURCE_SCHEMA}.replication_state WHERE id = 1), 0), \
+           applied_seq = MAX( \
+             COALESCE((SELECT MAX(seq) FROM {SOURCE_SCHEMA}.replication_changes), 0), \
+             COALESCE((SELECT seq FROM {SOURCE_SCHEMA}.sqlite_sequence \
+               WHERE name = 'replication_changes'), 0)) \
+         WHERE id = 1"
+    ))
+    .execute(&mut *tx)
+    .await
+    .map_err(restore_error)?;
+    tx.commit().await.map_err(restore_error)?;
+    Ok(rows)
+}
+
+async fn replicated_tables(
+    conn: &mut SqliteConnection,
+    schema: &str,
+) -> Result<Vec<String>, DomainError> {
+    let tables = sqlx::query_scalar::<_, String>(&format!(
+        "SELECT name FROM {schema}.sqlite_master WHERE type = 'table' \
+         AND name NOT LIKE 'sqlite_%' ORDER BY name"
+    ))
+    .fetch_all(conn)
+    .await
+    .map_err(|error| DomainError::Storage(format!("failed to list tables: {error}")))?;
+    Ok(tables
+        .into_iter()
+        .filter(|table| !UNREPLICATED_TABLES.contains(&table.as_str()))
+        .collect())
+}
+
+/// `(name, pk)` of every column, where `pk` is the column's position in the primary key or 0.
+async fn table_columns(
+    conn: &mut SqliteConnection,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<(String, i64)>, DomainError> {
+    let sql = format!("PRAGMA {schema}.table_info({})", quote_identifier(table));
+    Ok(
+        sqlx::query_as::<_, (i64, String, String, i64, Option<String>, i64)>(&sql)
+            .fetch_all(conn)
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to read columns of {table}: {error}"))
+            })?
+            .into_iter()
+            .map(|(_, name, _, _, _, pk)| (name, pk))
+            .collect(),
+    )
+}
+
+fn primary_key(columns: &[(String, i64)]) -> Vec<&str> {
+    let mut keys = columns.iter().filter(|(_, pk)| *pk > 0).collect::<Vec<_>>();
+    keys.sort_by_key(|(_, pk)| *pk);
+    keys.into_iter().map(|(name, _)| name.as_str()).collect()
+}
+
+/// The insert, update and delete triggers that record `table`'s changes. An update that moves
+/// the primary key also records a delete of the old key.
+fn capture_triggers(table: &str, columns: &[(String, i64)]) -> Result<Vec<String>, DomainError> {
+    let keys = primary_key(columns);
+    if keys.is_empty() {
+        return Err(DomainError::Storage(format!(
+            "table {table} has no primary key"
+        )));
+    }
+    let key = |row: &str| {
+        let values = keys
+            .iter()
+            .map(|name| format!("{row}.{}", quote_identifier(name)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("json_array({values})")
+    };
+    let fields = columns
+        .iter()
+        .map(|(name, _)| format!("'{name}', NEW.{}", quote_identifier(name)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let record = |operation: &str, key: &str, row: &str, filter: &str| {
+        format!(
+            "INSERT INTO replication_changes(table_name, operation, key_json, row_json, ts_ms) \
+             SELECT '{table}', '{operation}', {key}, {row}, {NOW_MS_SQL}{filter};"
+        )
+    };
+    let quoted = quote_identifier(table);
+    let upsert = record(
+        UPSERT_OPERATION,
+        &key("NEW"),
+        &format!("json_object({fields})"),
+        "",
+    );
+    let moved = record(
+        DELETE_OPERATION,
+        &key("OLD"),
+        "NULL",
+        &format!(" WHERE {} IS NOT {}", key("OLD"), key("NEW")),
+    );
+    let deleted = record(DELETE_OPERATION, &key("OLD"), "NULL", "");
+    Ok(vec![
+        format!(
+            "CREATE TRIGGER replicate_{table}_insert AFTER INSERT ON {quoted} BEGIN {upsert} END"
+        ),
+        format!(
+            "CREATE TRIGGER replicate_{table}_update AFTER UPDATE ON {quoted} BEGIN {moved} {upsert} END"
+        ),
+        format!(
+            "CREATE TRIGGER replicate_{table}_delete AFTER DELETE ON {quoted} BEGIN {deleted} END"
+        ),
+    ])
+}
+
+async fn apply_change(
+    conn: &mut SqliteConnection,
+    change: &ReplicatedChange,
+    columns: &[(String, i64)],
+) -> Result<(), DomainError> {
+    let table = quote_identifier(&change.table);
+    let matches = primary_key(columns)
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            format!(
+                "{} = json_extract(?1, '$[{index}]')",
+                quote_identifier(name)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let key_json = change.key.to_string();
+    match change.operation.as_str() {
+        DELETE_OPERATION => {
+            sqlx::query(&format!("DELETE FROM main.{table} WHERE {matches}"))
+                .bind(&key_json)
+                .execute(&mut *conn)
+                .await
+                .map_err(apply_error)?;
+        }
+        UPSERT_OPERATION => {
+            let Some(row) = change.row.as_ref().and_then(Value::as_object) else {
+                return Err(DomainError::Storage(format!(
+                    "replicated change {} has no row",
+                    change.seq
+                )));
+            };
+            let present = columns
+                .iter()
+                .filter(|(name, _)| row.contains_key(name))
+                .map(|(name, _)| {
+                    (
+                        quote_identifier(name),
+                        format!("json_extract(?2, '$.\"{name}\"')"),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let row_json = util::value_to_json_text(&Value::Object(row.clone()))
+                .map_err(DomainError::Storage)?;
+            let assignments = present
+                .iter()
+                .map(|(name, value)| format!("{name} = {value}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let updated = sqlx::query(&format!(
+                "UPDATE main.{table} SET {assignments} WHERE {matches}"
+            ))
+            .bind(&key_json)
+            .bind(&row_json)
+            .execute(&mut *conn)
+            .await
+            .map_err(apply_error)?
+            .rows_affected();
+            if updated == 0 {
+                let (names, values): (Vec<_>, Vec<_>) = present.into_iter().unzip();
+                sqlx::query(&format!(
+                    "INSERT INTO main.{table} ({}) VALUES ({})",
+                    names.join(", "),
+                    values.join(", ")
+                ))
+                .bind(&key_json)
+                .bind(&row_json)
+                .execute(&mut *conn)
+                .await
+                .map_err(apply_error)?;
+            }
+        }
+        other => {
+            return Err(DomainError::Storage(format!(
+                "replicated change {} has unknown operation {other}",
+                change.seq
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn map_change_row(row: ChangeRow) -> Result<ReplicatedChange, DomainError> {
+    let (seq, table, operation, key_json, row_json) = row;
+    Ok(ReplicatedChange {
+        seq: u64::try_from(seq).unwrap_or(0),
+        table,
+        operation,
+        key: util::json_text_to_value(&key_json).map_err(DomainError::Storage)?,
+        row: row_json
+            .map(|row| util::json_text_to_value(&row))
+            .transpose()
+            .map_err(DomainError::Storage)?,
+    })
+}
+
+fn apply_error(error: sqlx::Error) -> DomainError {
+    DomainError::Storage(format!("failed to apply replicated changes: {error}"))
+}
+
+fn restore_error(error: sqlx::Error) -> DomainError {
+    DomainError::Storage(format!("failed to restore snapshot: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{domain::models::SessionRecord, storage::SqliteStore};
+
+    #[tokio::test]
+    async fn captured_changes_replay_onto_another_database() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let primary = SqliteStore::connect(&temp.path().join("primary.db"))
+            .await
+            .expect("primary store should connect");
+        let standby = SqliteStore::connect(&temp.path().join("standby.db"))
+            .await
+            .expect("standby store should connect");
+        let session = |id: &str, title: &str| SessionRecord {
+            id: id.to_owned(),
+            title: title.to_owned(),
+            tags: vec!["kept".to_owned()],
+            metadata: json!({ "n": 1.5 }),
+            created_at_ms: 1,
+            updated_at_ms: 2,
+        };
+
+        primary
+            .set_change_capture(true)
+            .await
+            .expect("change capture should enable");
+        for (id, title) in [("agent:main:one", "One"), ("agent:main:two", "Two")] {
+            primary
+                .upsert_session(&session(id, title))
+                .await
+                .expect("session should save");
+        }
+        primary
+            .upsert_session(&session("agent:main:one", "Renamed"))
+            .await
+            .expect("session should update");
+        primary
+            .remove_session("agent:main:two")
+            .await
+            .expect("session should delete");
+
+        let changes = primary
+            .list_replicated_changes(0, 100)
+            .await
+            .expect("changes should list")
+            .expect("nothing is pruned yet");
+        assert_eq!(changes.len(), 4);
+        let applied = standby
+            .apply_replicated_changes(&changes)
+            .await
+            .expect("changes should apply");
+        assert_eq!(applied, changes.last().map(|change| change.seq));
+        let replica = standby
+            .get_session("agent:main:one")
+            .await
+            .expect("lookup should succeed")
+            .expect("session should be replicated");
+        assert_eq!(replica.title, "Renamed");
+        assert_eq!(replica.tags, vec!["kept".to_owned()]);
+        assert_eq!(replica.metadata, json!({ "n": 1.5 }));
+        assert!(
+            standby
+                .get_session("agent:main:two")
+                .await
+                .expect("lookup should succeed")
+                .is_none()
+        );
+        assert_eq!(
+            standby
+                .load_replication_state()
+                .await
+                .expect("state should load")
+                .applied_seq,
+            changes[3].seq
+        );
+        assert!(
+            standby
+                .list_replicated_changes(0, 100)
+                .await
+                .expect("changes should list")
+                .is_some_and(|changes| changes.is_empty()),
+            "applying must not capture changes again"
+        );
+
+        primary
+            .prune_replicated_changes(changes[1].seq, 0)
+            .await
+            .expect("changes should prune");
+        assert!(
+            primary
+                .list_replicated_changes(0, 100)
+                .await
+                .expect("changes should list")
+                .is_none(),
+            "a standby behind the pruned log needs a snapshot"
+        );
+        assert_eq!(
+            primary
+                .list_replicated_changes(changes[1].seq, 100)
+                .await
+                .expect("changes should list")
+                .map(|changes| changes.len()),
+            Some(2)
+        );
+
+        primary
+            .set_change_capture(false)
+            .await
+            .expect("change capture should disable");
+        primary
+            .upsert_session(&session("agent:main:three", "Three"))
+            .await
+            .expect("session should save");
+        assert_eq!(
+            primary
+                .list_replicated_changes(changes[3].seq, 100)
+                .await
+                .expect("changes should list")
+                .map(|changes| changes.len()),
+            Some(0)
+        );
+    }
+}
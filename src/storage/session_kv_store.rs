@@ -0,0 +1,169 @@
+use crate::{
+    domain::{error::DomainError, models::SessionKvEntry},
+    storage::{SqliteStore, now_unix_ms, util},
+};
+
+type SessionKvRow = (String, String, i64, i64, Option<i64>);
+
+impl SqliteStore {
+    /// Lists the live scratchpad entries of `session_key` ordered by key, dropping expired ones.
+    pub async fn list_session_kv(
+        &self,
+        session_key: &str,
+    ) -> Result<Vec<SessionKvEntry>, DomainError> {
+        let _timer = self.query_timer("list_session_kv");
+        purge_expired(self.pool(), session_key).await?;
+        let rows = sqlx::query_as::<_, SessionKvRow>(
+            "SELECT key, value_json, size_bytes, updated_at_ms, expires_at_ms FROM session_kv \
+             WHERE session_key = ? ORDER BY key",
+        )
+        .bind(session_key)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list scratchpad: {error}")))?;
+        rows.into_iter().map(map_session_kv_row).collect()
+    }
+
+    pub async fn get_session_kv(
+        &self,
+        session_key: &str,
+        key: &str,
+    ) -> Result<Option<SessionKvEntry>, DomainError> {
+        let _timer = self.query_timer("get_session_kv");
+        let row = sqlx::query_as::<_, SessionKvRow>(
+            "SELECT key, value_json, size_bytes, updated_at_ms, expires_at_ms FROM session_kv \
+             WHERE session_key = ? AND key = ? AND (expires_at_ms IS NULL OR expires_at_ms > ?)",
+        )
+        .bind(session_key)
+        .bind(key)
+        .bind(i64::try_from(now_unix_ms()).unwrap_or(i64::MAX))
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to load scratchpad entry: {error}"))
+        })?;
+        row.map(map_session_kv_row).transpose()
+    }
+
+    /// Stores `entry` for `session_key`, replacing the entry with the same key. Fails with
+    /// `InvalidRequest` when the write would leave the session with more than `max_keys` entries
+    /// or `max_total_bytes` of values; expired entries do not count.
+    pub async fn set_session_kv(
+        &self,
+        session_key: &str,
+        entry: &SessionKvEntry,
+        max_keys: usize,
+        max_total_bytes: u64,
+    ) -> Result<(), DomainError> {
+        let _timer = self.query_timer("set_session_kv");
+        let value_json = util::value_to_json_text(&entry.value).map_err(DomainError::Storage)?;
+        let mut tx = self
+            .pool()
+            .begin()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        purge_expired(&mut *tx, session_key).await?;
+
+        let (others, other_bytes) = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0) FROM session_kv \
+             WHERE session_key = ? AND key != ?",
+        )
+        .bind(session_key)
+        .bind(&entry.key)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to size scratchpad: {error}")))?;
+        let keys = usize::try_from(others)
+            .unwrap_or(usize::MAX)
+            .saturating_add(1);
+        if keys > max_keys {
+            return Err(DomainError::InvalidRequest(format!(
+                "session scratchpad is limited to {max_keys} keys"
+            )));
+        }
+        let total_bytes = u64::try_from(other_bytes)
+            .unwrap_or(u64::MAX)
+            .saturating_add(entry.size_bytes);
+        if total_bytes > max_total_bytes {
+            return Err(DomainError::InvalidRequest(format!(
+                "session scratchpad is limited to {max_total_bytes} bytes; \
+                 {total_bytes} would be stored"
+            )));
+        }
+
+        sqlx::query(
+            "INSERT INTO session_kv(session_key, key, value_json, size_bytes, updated_at_ms, expires_at_ms) \
+             VALUES(?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(session_key, key) DO UPDATE SET \
+               value_json = excluded.value_json, \
+               size_bytes = excluded.size_bytes, \
+               updated_at_ms = excluded.updated_at_ms, \
+               expires_at_ms = excluded.expires_at_ms",
+        )
+        .bind(session_key)
+        .bind(&entry.key)
+        .bind(value_json)
+        .bind(i64::try_from(entry.size_bytes).unwrap_or(i64::MAX))
+        .bind(i64::try_from(entry.updated_at_ms).unwrap_or(i64::MAX))
+        .bind(
+            entry
+                .expires_at_ms
+                .map(|expires_at_ms| i64::try_from(expires_at_ms).unwrap_or(i64::MAX)),
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to store scratchpad: {error}")))?;
+
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))
+    }
+
+    /// Removes one scratchpad entry. Returns `false` when `session_key` had no live `key`.
+    pub async fn delete_session_kv(
+        &self,
+        session_key: &str,
+        key: &str,
+    ) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("delete_session_kv");
+        let result = sqlx::query(
+            "DELETE FROM session_kv WHERE session_key = ? AND key = ? \
+             AND (expires_at_ms IS NULL OR expires_at_ms > ?)",
+        )
+        .bind(session_key)
+        .bind(key)
+        .bind(i64::try_from(now_unix_ms()).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to delete scratchpad entry: {error}"))
+        })?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+async fn purge_expired<'e, E>(executor: E, session_key: &str) -> Result<(), DomainError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    sqlx::query("DELETE FROM session_kv WHERE session_key = ? AND expires_at_ms <= ?")
+        .bind(session_key)
+        .bind(i64::try_from(now_unix_ms()).unwrap_or(i64::MAX))
+        .execute(executor)
+        .await
+        .map_err(|error| {
+            DomainError::Storage(format!("failed to expire scratchpad entries: {error}"))
+        })?;
+    Ok(())
+}
+
+fn map_session_kv_row(row: SessionKvRow) -> Result<SessionKvEntry, DomainError> {
+    let (key, value_json, size_bytes, updated_at_ms, expires_at_ms) = row;
+    Ok(SessionKvEntry {
+        key,
+        value: util::json_text_to_value(&value_json).map_err(DomainError::Storage)?,
+        size_bytes: u64::try_from(size_bytes).unwrap_or(0),
+        updated_at_ms: u64::try_from(updated_at_ms).unwrap_or(0),
+        expires_at_ms: expires_at_ms.map(|expires_at_ms| u64::try_from(expires_at_ms).unwrap_or(0)),
+    })
+}
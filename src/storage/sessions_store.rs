@@ -1,10 +1,14 @@
 use crate::{
-    domain::{error::DomainError, models::SessionRecord},
+    domain::{
+        error::DomainError,
+        models::{AgentUsageRecord, SessionRecord},
+    },
     storage::{SqliteStore, util},
 };
 
 impl SqliteStore {
     pub async fn list_sessions(&self) -> Result<Vec<SessionRecord>, DomainError> {
+        let _timer = self.query_timer("list_sessions");
         let rows = sqlx::query_as::<_, (String, String, String, String, i64, i64)>(
             "SELECT id, title, tags_json, metadata_json, created_at_ms, updated_at_ms \
              FROM sessions ORDER BY updated_at_ms DESC",
@@ -17,6 +21,7 @@ impl SqliteStore {
     }
 
     pub async fn get_session(&self, id: &str) -> Result<Option<SessionRecord>, DomainError> {
+        let _timer = self.query_timer("get_session");
         let row = sqlx::query_as::<_, (String, String, String, String, i64, i64)>(
             "SELECT id, title, tags_json, metadata_json, created_at_ms, updated_at_ms \
              FROM sessions WHERE id = ? LIMIT 1",
@@ -30,33 +35,28 @@ impl SqliteStore {
     }
 
     pub async fn upsert_session(&self, session: &SessionRecord) -> Result<(), DomainError> {
-        let tags_json = util::to_json_text(&session.tags).map_err(DomainError::Storage)?;
-        let metadata_json =
-            util::value_to_json_text(&session.metadata).map_err(DomainError::Storage)?;
-
-        sqlx::query(
-            "INSERT INTO sessions(id, title, tags_json, metadata_json, created_at_ms, updated_at_ms) \
-             VALUES(?, ?, ?, ?, ?, ?) \
-             ON CONFLICT(id) DO UPDATE SET \
-               title = excluded.title, \
-               tags_json = excluded.tags_json, \
-               metadata_json = excluded.metadata_json, \
-               updated_at_ms = excluded.updated_at_ms",
-        )
-        .bind(&session.id)
-        .bind(&session.title)
-        .bind(tags_json)
-        .bind(metadata_json)
-        .bind(i64::try_from(session.created_at_ms).unwrap_or(i64::MAX))
-        .bind(i64::try_from(session.updated_at_ms).unwrap_or(i64::MAX))
-        .execute(self.pool())
-        .await
-        .map_err(|error| DomainError::Storage(format!("failed to upsert session: {error}")))?;
+        let _timer = self.query_timer("upsert_session");
+        upsert_session_row(self.pool(), session).await
+    }
 
-        Ok(())
+    /// Upserts every session in one transaction, so a bulk patch applies all or nothing.
+    pub async fn upsert_sessions(&self, sessions: &[SessionRecord]) -> Result<(), DomainError> {
+        let _timer = self.query_timer("upsert_sessions");
+        let mut tx = self
+            .pool()
+            .begin()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        for session in sessions {
+            upsert_session_row(&mut *tx, session).await?;
+        }
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))
     }
 
     pub async fn remove_session(&self, id: &str) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("remove_session");
         let result = sqlx::query("DELETE FROM sessions WHERE id = ?")
             .bind(id)
             .execute(self.pool())
@@ -66,6 +66,7 @@ impl SqliteStore {
     }
 
     pub async fn clear_sessions(&self) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("clear_sessions");
         let result = sqlx::query("DELETE FROM sessions")
             .execute(self.pool())
             .await
@@ -73,7 +74,36 @@ impl SqliteStore {
         Ok(result.rows_affected())
     }
 
+    /// Lists the per-agent session and message counters, which triggers keep current on every
+    /// write to `sessions` and `chat_messages`.
+    pub async fn list_agent_usage(&self) -> Result<Vec<AgentUsageRecord>, DomainError> {
+        let _timer = self.query_timer("list_agent_usage");
+        let rows = sqlx::query_as::<_, (String, i64, i64, Option<i64>)>(
+            "SELECT agent_id, sessions_count, messages_count, last_activity_at_ms \
+             FROM agent_usage ORDER BY agent_id",
+        )
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list agent usage: {error}")))?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(agent_id, sessions_count, messages_count, last_activity_at_ms)| {
+                    AgentUsageRecord {
+                        agent_id,
+                        sessions_count: u64::try_from(sessions_count).unwrap_or(0),
+                        messages_count: u64::try_from(messages_count).unwrap_or(0),
+                        last_activity_at_ms: last_activity_at_ms
+                            .and_then(|value| u64::try_from(value).ok()),
+                    }
+                },
+            )
+            .collect())
+    }
+
     pub async fn compact_sessions(&self, max_age_ms: u64) -> Result<u64, DomainError> {
+        let _timer = self.query_timer("compact_sessions");
         let now = util::now_unix_ms();
         let cutoff = now.saturating_sub(max_age_ms);
         let result = sqlx::query("DELETE FROM sessions WHERE updated_at_ms < ?")
@@ -87,6 +117,36 @@ impl SqliteStore {
     }
 }
 
+async fn upsert_session_row<'e, E>(executor: E, session: &SessionRecord) -> Result<(), DomainError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    let tags_json = util::to_json_text(&session.tags).map_err(DomainError::Storage)?;
+    let metadata_json =
+        util::value_to_json_text(&session.metadata).map_err(DomainError::Storage)?;
+
+    sqlx::query(
+        "INSERT INTO sessions(id, title, tags_json, metadata_json, created_at_ms, updated_at_ms) \
+         VALUES(?, ?, ?, ?, ?, ?) \
+         ON CONFLICT(id) DO UPDATE SET \
+           title = excluded.title, \
+           tags_json = excluded.tags_json, \
+           metadata_json = excluded.metadata_json, \
+           updated_at_ms = excluded.updated_at_ms",
+    )
+    .bind(&session.id)
+    .bind(&session.title)
+    .bind(tags_json)
+    .bind(metadata_json)
+    .bind(i64::try_from(session.created_at_ms).unwrap_or(i64::MAX))
+    .bind(i64::try_from(session.updated_at_ms).unwrap_or(i64::MAX))
+    .execute(executor)
+    .await
+    .map_err(|error| DomainError::Storage(format!("failed to upsert session: {error}")))?;
+
+    Ok(())
+}
+
 fn map_session_row(
     row: (String, String, String, String, i64, i64),
 ) -> Result<SessionRecord, DomainError> {
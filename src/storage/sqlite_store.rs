@@ -1,16 +1,30 @@
-use std::{path::Path, str::FromStr};
+use std::{path::Path, str::FromStr, sync::Arc};
 
 use sqlx::{SqlitePool, sqlite::SqliteConnectOptions};
 
-use crate::domain::error::DomainError;
+use crate::{
+    domain::error::DomainError,
+    storage::{
+        migrations::MigrationLockOptions,
+        query_metrics::{QueryMetrics, QueryTimer},
+    },
+};
 
 #[derive(Debug, Clone)]
 pub struct SqliteStore {
     pool: SqlitePool,
+    metrics: Arc<QueryMetrics>,
 }
 
 impl SqliteStore {
     pub async fn connect(path: &Path) -> Result<Self, DomainError> {
+        Self::connect_with_lock_options(path, MigrationLockOptions::default()).await
+    }
+
+    pub async fn connect_with_lock_options(
+        path: &Path,
+        lock_options: MigrationLockOptions,
+    ) -> Result<Self, DomainError> {
         if let Some(parent) = path.parent()
             && !parent.as_os_str().is_empty()
         {
@@ -29,9 +43,11 @@ impl SqliteStore {
             .await
             .map_err(|error| DomainError::Storage(format!("failed to connect sqlite: {error}")))?;
 
-        let store = Self { pool };
-        store.migrate().await?;
-        Ok(store)
+        super::migrations::migrate_with_lock(&pool, lock_options).await?;
+        Ok(Self {
+            pool,
+            metrics: Arc::default(),
+        })
     }
 
     #[must_use]
@@ -39,7 +55,27 @@ impl SqliteStore {
         &self.pool
     }
 
-    async fn migrate(&self) -> Result<(), DomainError> {
-        super::migrations::migrate(self.pool()).await
+    /// Latency of the store's operations, shared by its clones.
+    #[must_use]
+    pub fn query_metrics(&self) -> &QueryMetrics {
+        &self.metrics
+    }
+
+    /// Times the calling operation until the returned guard drops.
+    pub(crate) fn query_timer(&self, operation: &'static str) -> QueryTimer<'_> {
+        QueryTimer::start(&self.metrics, operation)
+    }
+
+    /// Writes a consistent, compacted copy of the database to `path`, which must not exist.
+    pub async fn vacuum_into(&self, path: &Path) -> Result<(), DomainError> {
+        let _timer = self.query_timer("vacuum_into");
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.display().to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to snapshot database: {error}"))
+            })?;
+        Ok(())
     }
 }
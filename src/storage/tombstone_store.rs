@@ -0,0 +1,299 @@
+use crate::{
+    domain::error::DomainError,
+    storage::{SqliteStore, util::NOW_MS_SQL},
+};
+
+/// Conversation tables whose deleted rows are kept in `tombstones` in append-only mode.
+const TOMBSTONED_TABLES: &[&str] = &[
+    "sessions",
+    "chat_messages",
+    "chat_pins",
+    "chat_archive_segments",
+    "agent_runs",
+    "message_deliveries",
+    "cron_jobs",
+    "cron_runs",
+];
+
+impl SqliteStore {
+    /// Installs the append-only triggers when `enabled`, and removes them otherwise. Every
+    /// `DELETE` on a [`TOMBSTONED_TABLES`] table, and every `UPDATE` that changes a row, then
+    /// first copies the old row into `tombstones` as JSON, and `tombstones` rejects updates and
+    /// deletes, so no code path can destroy a row version. Writers upsert rather than
+    /// `INSERT OR REPLACE`, whose implicit delete fires no trigger. The triggers are rebuilt on
+    /// every call so they cover columns added since.
+    pub async fn set_append_only(&self, enabled: bool) -> Result<(), DomainError> {
+        let _timer = self.query_timer("set_append_only");
+        let map_error = |error: sqlx::Error| {
+            DomainError::Storage(format!("failed to configure append-only mode: {error}"))
+        };
+        let mut tx = self.pool().begin().await.map_err(map_error)?;
+        let mut statements = vec![
+            "DROP TRIGGER IF EXISTS tombstones_no_update".to_owned(),
+            "DROP TRIGGER IF EXISTS tombstones_no_delete".to_owned(),
+        ];
+        statements.extend(TOMBSTONED_TABLES.iter().flat_map(|table| {
+            [
+                format!("DROP TRIGGER IF EXISTS tombstone_{table}"),
+                format!("DROP TRIGGER IF EXISTS tombstone_{table}_update"),
+            ]
+        }));
+        if enabled {
+            for table in TOMBSTONED_TABLES {
+                let columns = sqlx::query_as::<_, (String, i64)>(
+                    "SELECT name, pk FROM pragma_table_info(?) ORDER BY cid",
+                )
+                .bind(table)
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(map_error)?;
+                let key = columns
+                    .iter()
+                    .find(|(_, pk)| *pk == 1)
+                    .map(|(name, _)| name.as_str())
+                    .ok_or_else(|| {
+                        DomainError::Storage(format!("table {table} has no primary key"))
+                    })?;
+                let fields = |row: &str| {
+                    columns
+                        .iter()
+                        .map(|(name, _)| format!("'{name}', {row}.{name}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                let keep = |operation: &str| {
+                    format!(
+                        "INSERT INTO tombstones(table_name, row_key, row_json, deleted_at_ms, operation) \
+                         VALUES('{table}', OLD.{key}, json_object({}), {NOW_MS_SQL}, '{operation}');",
+                        fields("OLD")
+                    )
+                };
+                statements.push(format!(
+                    "CREATE TRIGGER tombstone_{table} BEFORE DELETE ON {table} BEGIN {} END",
+                    keep("delete")
+                ));
+                statements.push(format!(
+                    "CREATE TRIGGER tombstone_{table}_update BEFORE UPDATE ON {table} \
+                     WHEN json_object({}) IS NOT json_object({}) BEGIN {} END",
+                    fields("OLD"),
+                    fields("NEW"),
+                    keep("update")
+                ));
+            }
+            for (name, operation) in [("no_update", "UPDATE"), ("no_delete", "DELETE")] {
+                statements.push(format!(
+                    "CREATE TRIGGER tombstones_{name} BEFORE {operation} ON tombstones BEGIN \
+                     SELECT RAISE(ABORT, 'tombstones are append-only'); END"
+                ));
+            }
+        }
+        for statement in statements {
+            sqlx::query(&statement)
+                .execute(&mut *tx)
+                .await
+                .map_err(map_error)?;
+        }
+        tx.commit().await.map_err(map_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use crate::{
+        domain::models::{ChatMessage, SessionRecord},
+        storage::SqliteStore,
+    };
+
+    #[tokio::test]
+    async fn append_only_mode_keeps_deleted_rows_as_tombstones() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let store = SqliteStore::connect(&temp.path().join("state.db"))
+            .await
+            .expect("sqlite store should connect");
+        let session = |id: &str| SessionRecord {
+            id: id.to_owned(),
+            title: "Session".to_owned(),
+            tags: vec!["kept".to_owned()],
+            metadata: json!({}),
+            created_at_ms: 1,
+            updated_at_ms: 2,
+        };
+        let tombstones = |store: &SqliteStore| {
+            let pool = store.pool().clone();
+            async move {
+                sqlx::query_as::<_, (String, String, String)>(
+                    "SELECT table_name, row_key, row_json FROM tombstones ORDER BY seq",
+                )
+                .fetch_all(&pool)
+                .await
+                .expect("tombstones should be readable")
+            }
+        };
+
+        store
+            .set_append_only(true)
+            .await
+            .expect("append-only mode should enable");
+        store
+            .upsert_session(&session("agent:main:one"))
+            .await
+            .expect("session should save");
+        assert!(
+            store
+                .remove_session("agent:main:one")
+                .await
+                .expect("session should delete")
+        );
+        assert!(
+            store
+                .get_session("agent:main:one")
+                .await
+                .expect("lookup should succeed")
+                .is_none()
+        );
+        let kept = tombstones(&store).await;
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].0, "sessions");
+        assert_eq!(kept[0].1, "agent:main:one");
+        let row: Value = serde_json::from_str(&kept[0].2).expect("row should be json");
+        assert_eq!(row["title"], "Session");
+        assert_eq!(row["tags_json"], "[\"kept\"]");
+        assert!(
+            sqlx::query("DELETE FROM tombstones")
+                .execute(store.pool())
+                .await
+                .is_err()
+        );
+        assert!(
+            sqlx::query("UPDATE tombstones SET row_json = '{}'")
+                .execute(store.pool())
+                .await
+                .is_err()
+        );
+
+        store
+            .set_append_only(false)
+            .await
+            .expect("append-only mode should disable");
+        store
+            .upsert_session(&session("agent:main:two"))
+            .await
+            .expect("session should save");
+        store
+            .remove_session("agent:main:two")
+            .await
+            .expect("session should delete");
+        assert_eq!(tombstones(&store).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn append_only_mode_keeps_overwritten_rows_and_survives_restores() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let store = SqliteStore::connect(&temp.path().join("state.db"))
+            .await
+            .expect("sqlite store should connect");
+        let tombstones = |store: &SqliteStore| {
+            let pool = store.pool().clone();
+            async move {
+                sqlx::query_as::<_, (String, String, String)>(
+                    "SELECT table_name, operation, row_json FROM tombstones ORDER BY seq",
+                )
+                .fetch_all(&pool)
+                .await
+                .expect("tombstones should be readable")
+            }
+        };
+        let message = |text: &str| ChatMessage {
+            id: "message-1".to_owned(),
+            role: "user".to_owned(),
+            text: text.to_owned(),
+            status: "final".to_owned(),
+            ts: 1,
+            metadata: json!({}),
+            pinned: false,
+        };
+        let session = |title: &str| SessionRecord {
+            id: "agent:main:one".to_owned(),
+            title: title.to_owned(),
+            tags: Vec::new(),
+            metadata: json!({}),
+            created_at_ms: 1,
+            updated_at_ms: 2,
+        };
+
+        store
+            .set_append_only(true)
+            .await
+            .expect("append-only mode should enable");
+        store
+            .upsert_session(&session("First"))
+            .await
+            .expect("session should save");
+        store
+            .upsert_session(&session("First"))
+            .await
+            .expect("unchanged session should save");
+        assert!(
+            tombstones(&store).await.is_empty(),
+            "an update that changes nothing keeps nothing"
+        );
+        store
+            .upsert_session(&session("Second"))
+            .await
+            .expect("session should update");
+        store
+            .append_chat_messages("agent:main:one", &[message("draft")])
+            .await
+            .expect("message should append");
+        store
+            .append_chat_messages("agent:main:one", &[message("final")])
+            .await
+            .expect("message should be replaced");
+        let kept = tombstones(&store).await;
+        assert_eq!(kept.len(), 2, "{kept:?}");
+        assert_eq!(
+            (kept[0].0.as_str(), kept[0].1.as_str()),
+            ("sessions", "update")
+        );
+        assert!(kept[0].2.contains("First"));
+        assert_eq!(
+            (kept[1].0.as_str(), kept[1].1.as_str()),
+            ("chat_messages", "update")
+        );
+        assert!(kept[1].2.contains("draft"));
+
+        // A standby in append-only mode loads snapshots without touching its tombstones.
+        let primary = SqliteStore::connect(&temp.path().join("primary.db"))
+            .await
+            .expect("primary store should connect");
+        primary
+            .upsert_session(&session("Second"))
+            .await
+            .expect("session should save");
+        let snapshot = temp.path().join("snapshot.db");
+        primary
+            .vacuum_into(&snapshot)
+            .await
+            .expect("snapshot should write");
+        store
+            .restore_from_snapshot(&snapshot)
+            .await
+            .expect("restore should not touch tombstones");
+        let kept = tombstones(&store).await;
+        assert_eq!(kept.len(), 3, "{kept:?}");
+        assert_eq!(
+            (kept[2].0.as_str(), kept[2].1.as_str()),
+            ("chat_messages", "delete")
+        );
+        assert_eq!(
+            store
+                .get_session("agent:main:one")
+                .await
+                .expect("lookup should succeed")
+                .map(|session| session.title),
+            Some("Second".to_owned())
+        );
+    }
+}
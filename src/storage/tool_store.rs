@@ -0,0 +1,340 @@
+use crate::{
+    domain::{
+        error::DomainError,
+        models::{ToolCallRecord, ToolDefinition, ToolGrant},
+    },
+    storage::{SqliteStore, util},
+};
+
+type ToolRow = (String, String, String, String, i64, i64);
+type ToolCallRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    i64,
+    Option<i64>,
+);
+
+impl SqliteStore {
+    pub async fn upsert_tool(&self, tool: &ToolDefinition) -> Result<(), DomainError> {
+        let _timer = self.query_timer("upsert_tool");
+        let schema_json =
+            util::value_to_json_text(&tool.input_schema).map_err(DomainError::Storage)?;
+        let executor_json = util::to_json_text(&tool.executor).map_err(DomainError::Storage)?;
+        sqlx::query(
+            "INSERT INTO tools(name, description, input_schema_json, executor_json, created_at_ms, updated_at_ms) \
+             VALUES(?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(name) DO UPDATE SET description = excluded.description, \
+             input_schema_json = excluded.input_schema_json, executor_json = excluded.executor_json, \
+             updated_at_ms = excluded.updated_at_ms",
+        )
+        .bind(&tool.name)
+        .bind(&tool.description)
+        .bind(schema_json)
+        .bind(executor_json)
+        .bind(i64::try_from(tool.created_at_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(tool.updated_at_ms).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to upsert tool: {error}")))?;
+        Ok(())
+    }
+
+    pub async fn get_tool(&self, name: &str) -> Result<Option<ToolDefinition>, DomainError> {
+        let _timer = self.query_timer("get_tool");
+        let row = sqlx::query_as::<_, ToolRow>(
+            "SELECT name, description, input_schema_json, executor_json, created_at_ms, updated_at_ms \
+             FROM tools WHERE name = ? LIMIT 1",
+        )
+        .bind(name)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to get tool: {error}")))?;
+
+        row.map(map_tool_row).transpose()
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<ToolDefinition>, DomainError> {
+        let _timer = self.query_timer("list_tools");
+        let rows = sqlx::query_as::<_, ToolRow>(
+            "SELECT name, description, input_schema_json, executor_json, created_at_ms, updated_at_ms \
+             FROM tools ORDER BY name ASC",
+        )
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list tools: {error}")))?;
+
+        rows.into_iter().map(map_tool_row).collect()
+    }
+
+    /// Removes a tool definition together with every grant that references it.
+    pub async fn delete_tool(&self, name: &str) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("delete_tool");
+        let mut tx = self
+            .pool()
+            .begin()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to start tx: {error}")))?;
+        sqlx::query("DELETE FROM tool_grants WHERE tool_name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| {
+                DomainError::Storage(format!("failed to delete tool grants: {error}"))
+            })?;
+        let deleted = sqlx::query("DELETE FROM tools WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to delete tool: {error}")))?
+            .rows_affected();
+        tx.commit()
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to commit tx: {error}")))?;
+        Ok(deleted > 0)
+    }
+
+    pub async fn grant_tool(&self, grant: &ToolGrant) -> Result<(), DomainError> {
+        let _timer = self.query_timer("grant_tool");
+        sqlx::query(
+            "INSERT INTO tool_grants(agent_id, tool_name, granted_at_ms) VALUES(?, ?, ?) \
+             ON CONFLICT(agent_id, tool_name) DO NOTHING",
+        )
+        .bind(&grant.agent_id)
+        .bind(&grant.tool_name)
+        .bind(i64::try_from(grant.granted_at_ms).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to grant tool: {error}")))?;
+        Ok(())
+    }
+
+    pub async fn revoke_tool(&self, agent_id: &str, tool_name: &str) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("revoke_tool");
+        let result = sqlx::query("DELETE FROM tool_grants WHERE agent_id = ? AND tool_name = ?")
+            .bind(agent_id)
+            .bind(tool_name)
+            .execute(self.pool())
+            .await
+            .map_err(|error| DomainError::Storage(format!("failed to revoke tool: {error}")))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list_tool_grants(
+        &self,
+        agent_id: Option<&str>,
+    ) -> Result<Vec<ToolGrant>, DomainError> {
+        let _timer = self.query_timer("list_tool_grants");
+        let rows = sqlx::query_as::<_, (String, String, i64)>(
+            "SELECT agent_id, tool_name, granted_at_ms FROM tool_grants \
+             WHERE (? IS NULL OR agent_id = ?) ORDER BY agent_id ASC, tool_name ASC",
+        )
+        .bind(agent_id)
+        .bind(agent_id)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list tool grants: {error}")))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(agent_id, tool_name, granted_at_ms)| ToolGrant {
+                agent_id,
+                tool_name,
+                granted_at_ms: u64::try_from(granted_at_ms).unwrap_or(0),
+            })
+            .collect())
+    }
+
+    pub async fn has_tool_grant(
+        &self,
+        agent_id: &str,
+        tool_name: &str,
+    ) -> Result<bool, DomainError> {
+        let _timer = self.query_timer("has_tool_grant");
+        let row = sqlx::query_as::<_, (i64,)>(
+            "SELECT 1 FROM tool_grants WHERE agent_id = ? AND tool_name = ? LIMIT 1",
+        )
+        .bind(agent_id)
+        .bind(tool_name)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to check tool grant: {error}")))?;
+        Ok(row.is_some())
+    }
+
+    /// Inserts or replaces a tool call; used both when a call starts and when it finishes.
+    pub async fn upsert_tool_call(&self, call: &ToolCallRecord) -> Result<(), DomainError> {
+        let _timer = self.query_timer("upsert_tool_call");
+        let args_json = util::value_to_json_text(&call.args).map_err(DomainError::Storage)?;
+        let result_json = call
+            .result
+            .as_ref()
+            .map(util::value_to_json_text)
+            .transpose()
+            .map_err(DomainError::Storage)?;
+        sqlx::query(
+            "INSERT INTO tool_calls(id, run_id, agent_id, tool_name, args_json, status, result_json, error, started_at_ms, completed_at_ms) \
+             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(id) DO UPDATE SET status = excluded.status, result_json = excluded.result_json, \
+             error = excluded.error, completed_at_ms = excluded.completed_at_ms",
+        )
+        .bind(&call.id)
+        .bind(&call.run_id)
+        .bind(&call.agent_id)
+        .bind(&call.tool_name)
+        .bind(args_json)
+        .bind(&call.status)
+        .bind(result_json)
+        .bind(&call.error)
+        .bind(i64::try_from(call.started_at_ms).unwrap_or(i64::MAX))
+        .bind(
+            call.completed_at_ms
+                .map(|value| i64::try_from(value).unwrap_or(i64::MAX)),
+        )
+        .execute(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to upsert tool call: {error}")))?;
+        Ok(())
+    }
+
+    pub async fn list_tool_calls_by_run(
+        &self,
+        run_id: &str,
+    ) -> Result<Vec<ToolCallRecord>, DomainError> {
+        let _timer = self.query_timer("list_tool_calls_by_run");
+        let rows = sqlx::query_as::<_, ToolCallRow>(
+            "SELECT id, run_id, agent_id, tool_name, args_json, status, result_json, error, started_at_ms, completed_at_ms \
+             FROM tool_calls WHERE run_id = ? ORDER BY started_at_ms ASC, rowid ASC",
+        )
+        .bind(run_id)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to list tool calls: {error}")))?;
+
+        rows.into_iter().map(map_tool_call_row).collect()
+    }
+}
+
+fn map_tool_row(row: ToolRow) -> Result<ToolDefinition, DomainError> {
+    let (name, description, schema_json, executor_json, created_at_ms, updated_at_ms) = row;
+    Ok(ToolDefinition {
+        name,
+        description,
+        input_schema: util::json_text_to_value(&schema_json).map_err(DomainError::Storage)?,
+        executor: util::from_json_text(&executor_json).map_err(DomainError::Storage)?,
+        created_at_ms: u64::try_from(created_at_ms).unwrap_or(0),
+        updated_at_ms: u64::try_from(updated_at_ms).unwrap_or(0),
+    })
+}
+
+fn map_tool_call_row(row: ToolCallRow) -> Result<ToolCallRecord, DomainError> {
+    let (
+        id,
+        run_id,
+        agent_id,
+        tool_name,
+        args_json,
+        status,
+        result_json,
+        error,
+        started_at_ms,
+        completed_at_ms,
+    ) = row;
+    Ok(ToolCallRecord {
+        id,
+        run_id,
+        agent_id,
+        tool_name,
+        args: util::json_text_to_value(&args_json).map_err(DomainError::Storage)?,
+        status,
+        result: result_json
+            .as_deref()
+            .map(util::json_text_to_value)
+            .transpose()
+            .map_err(DomainError::Storage)?,
+        error,
+        started_at_ms: u64::try_from(started_at_ms).unwrap_or(0),
+        completed_at_ms: completed_at_ms.map(|value| u64::try_from(value).unwrap_or(0)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::SqliteStore;
+    use crate::domain::models::{ToolDefinition, ToolExecutor, ToolGrant};
+
+    async fn make_store() -> (TempDir, SqliteStore) {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let store = SqliteStore::connect(&temp.path().join("state.db"))
+            .await
+            .expect("sqlite store should connect");
+        (temp, store)
+    }
+
+    #[tokio::test]
+    async fn delete_tool_drops_its_grants() {
+        let (_temp, store) = make_store().await;
+        store
+            .upsert_tool(&ToolDefinition {
+                name: "weather".to_owned(),
+                description: "Current weather".to_owned(),
+                input_schema: json!({ "type": "object" }),
+                executor: ToolExecutor::Http {
+                    url: "http://127.0.0.1:9/weather".to_owned(),
+                    timeout_ms: Some(500),
+                },
+                created_at_ms: 1,
+                updated_at_ms: 1,
+            })
+            .await
+            .expect("tool should upsert");
+        store
+            .grant_tool(&ToolGrant {
+                agent_id: "main".to_owned(),
+                tool_name: "weather".to_owned(),
+                granted_at_ms: 2,
+            })
+            .await
+            .expect("grant should insert");
+        assert!(
+            store
+                .has_tool_grant("main", "weather")
+                .await
+                .expect("grant lookup should succeed")
+        );
+        let stored = store
+            .get_tool("weather")
+            .await
+            .expect("tool lookup should succeed")
+            .expect("tool should exist");
+        assert_eq!(
+            stored.executor,
+            ToolExecutor::Http {
+                url: "http://127.0.0.1:9/weather".to_owned(),
+                timeout_ms: Some(500),
+            }
+        );
+
+        assert!(
+            store
+                .delete_tool("weather")
+                .await
+                .expect("delete should succeed")
+        );
+        assert!(
+            store
+                .list_tool_grants(Some("main"))
+                .await
+                .expect("grants should list")
+                .is_empty()
+        );
+    }
+}
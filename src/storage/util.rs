@@ -1,5 +1,6 @@
 use serde::{Serialize, de::DeserializeOwned};
 use serde_json::Value;
+use sqlx::{QueryBuilder, Sqlite};
 
 pub fn now_unix_ms() -> u64 {
     match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
@@ -8,6 +9,44 @@ pub fn now_unix_ms() -> u64 {
     }
 }
 
+/// Current time in unix milliseconds, evaluated by SQLite, for use inside triggers.
+pub const NOW_MS_SQL: &str = "CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)";
+
+/// Bound parameters per statement for `IN (...)` lookups, well under SQLite's variable limit.
+pub const MAX_BATCH_BINDS: usize = 500;
+
+/// Double-quotes a table or column name for SQL built at runtime.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Starts the next condition of a `WHERE` clause assembled with `QueryBuilder`: ` WHERE ` while
+/// `first` is still set, ` AND ` afterwards.
+pub fn push_condition<'q, 'b>(
+    builder: &'b mut QueryBuilder<'q, Sqlite>,
+    first: &mut bool,
+) -> &'b mut QueryBuilder<'q, Sqlite> {
+    builder.push(if std::mem::take(first) {
+        " WHERE "
+    } else {
+        " AND "
+    })
+}
+
+/// A `LIKE ... ESCAPE '\\'` pattern matching any text that contains `needle` literally.
+pub fn like_contains_pattern(needle: &str) -> String {
+    let mut pattern = String::with_capacity(needle.len() + 2);
+    pattern.push('%');
+    for ch in needle.chars() {
+        if matches!(ch, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(ch);
+    }
+    pattern.push('%');
+    pattern
+}
+
 pub fn to_json_text<T: Serialize>(value: &T) -> Result<String, String> {
     serde_json::to_string(value).map_err(|error| error.to_string())
 }
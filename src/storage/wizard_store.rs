@@ -0,0 +1,201 @@
+use crate::{
+    domain::{error::DomainError, models::WizardSession},
+    storage::{SqliteStore, util},
+};
+
+type WizardSessionRow = (
+    String,
+    String,
+    String,
+    i64,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    i64,
+    i64,
+    Option<i64>,
+);
+
+const WIZARD_SESSION_COLUMNS: &str = "id, kind, status, step_index, answers_json, result_json, \
+     started_by, cancel_reason, created_at_ms, updated_at_ms, completed_at_ms";
+
+impl SqliteStore {
+    pub async fn upsert_wizard_session(&self, session: &WizardSession) -> Result<(), DomainError> {
+        let _timer = self.query_timer("upsert_wizard_session");
+        let answers_json =
+            util::value_to_json_text(&session.answers).map_err(DomainError::Storage)?;
+        let result_json = session
+            .result
+            .as_ref()
+            .map(util::value_to_json_text)
+            .transpose()
+            .map_err(DomainError::Storage)?;
+        sqlx::query(&format!(
+            "INSERT INTO wizard_sessions({WIZARD_SESSION_COLUMNS}) \
+             VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(id) DO UPDATE SET status = excluded.status, \
+             step_index = excluded.step_index, answers_json = excluded.answers_json, \
+             result_json = excluded.result_json, cancel_reason = excluded.cancel_reason, \
+             updated_at_ms = excluded.updated_at_ms, completed_at_ms = excluded.completed_at_ms"
+        ))
+        .bind(&session.id)
+        .bind(&session.kind)
+        .bind(&session.status)
+        .bind(i64::try_from(session.step_index).unwrap_or(i64::MAX))
+        .bind(answers_json)
+        .bind(result_json)
+        .bind(&session.started_by)
+        .bind(&session.cancel_reason)
+        .bind(i64::try_from(session.created_at_ms).unwrap_or(i64::MAX))
+        .bind(i64::try_from(session.updated_at_ms).unwrap_or(i64::MAX))
+        .bind(
+            session
+                .completed_at_ms
+                .map(|value| i64::try_from(value).unwrap_or(i64::MAX)),
+        )
+        .execute(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to save wizard session: {error}")))?;
+        Ok(())
+    }
+
+    pub async fn get_wizard_session(&self, id: &str) -> Result<Option<WizardSession>, DomainError> {
+        let _timer = self.query_timer("get_wizard_session");
+        let row = sqlx::query_as::<_, WizardSessionRow>(&format!(
+            "SELECT {WIZARD_SESSION_COLUMNS} FROM wizard_sessions WHERE id = ? LIMIT 1"
+        ))
+        .bind(id)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to load wizard session: {error}")))?;
+        row.map(map_wizard_session_row).transpose()
+    }
+
+    /// The most recently updated session of `kind`, preferring an active one.
+    pub async fn latest_wizard_session(
+        &self,
+        kind: &str,
+    ) -> Result<Option<WizardSession>, DomainError> {
+        let _timer = self.query_timer("latest_wizard_session");
+        let row = sqlx::query_as::<_, WizardSessionRow>(&format!(
+            "SELECT {WIZARD_SESSION_COLUMNS} FROM wizard_sessions WHERE kind = ? \
+             ORDER BY status = 'active' DESC, updated_at_ms DESC LIMIT 1"
+        ))
+        .bind(kind)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(|error| DomainError::Storage(format!("failed to load wizard session: {error}")))?;
+        row.map(map_wizard_session_row).transpose()
+    }
+}
+
+fn map_wizard_session_row(row: WizardSessionRow) -> Result<WizardSession, DomainError> {
+    let (
+        id,
+        kind,
+        status,
+        step_index,
+        answers_json,
+        result_json,
+        started_by,
+        cancel_reason,
+        created_at_ms,
+        updated_at_ms,
+        completed_at_ms,
+    ) = row;
+    Ok(WizardSession {
+        id,
+        kind,
+        status,
+        step_index: usize::try_from(step_index).unwrap_or(0),
+        answers: util::json_text_to_value(&answers_json).map_err(DomainError::Storage)?,
+        result: result_json
+            .as_deref()
+            .map(util::json_text_to_value)
+            .transpose()
+            .map_err(DomainError::Storage)?,
+        started_by,
+        cancel_reason,
+        created_at_ms: u64::try_from(created_at_ms).unwrap_or(0),
+        updated_at_ms: u64::try_from(updated_at_ms).unwrap_or(0),
+        completed_at_ms: completed_at_ms.and_then(|value| u64::try_from(value).ok()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{domain::models::WizardSession, storage::SqliteStore};
+
+    fn session(id: &str, status: &str, updated_at_ms: u64) -> WizardSession {
+        WizardSession {
+            id: id.to_owned(),
+            kind: "setup".to_owned(),
+            status: status.to_owned(),
+            step_index: 1,
+            answers: json!({ "auth": { "mode": "token" } }),
+            result: None,
+            started_by: Some("cli".to_owned()),
+            cancel_reason: None,
+            created_at_ms: 1,
+            updated_at_ms,
+            completed_at_ms: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn wizard_sessions_round_trip_and_latest_prefers_active() {
+        let temp = tempfile::tempdir().expect("temp dir should exist");
+        let store = SqliteStore::connect(&temp.path().join("state.db"))
+            .await
+            .expect("sqlite store should connect");
+
+        let active = session("w-1", "active", 10);
+        store
+            .upsert_wizard_session(&active)
+            .await
+            .expect("session should save");
+        store
+            .upsert_wizard_session(&session("w-2", "cancelled", 20))
+            .await
+            .expect("session should save");
+
+        assert_eq!(
+            store
+                .get_wizard_session("w-1")
+                .await
+                .expect("session should load"),
+            Some(active.clone())
+        );
+        let latest = store
+            .latest_wizard_session("setup")
+            .await
+            .expect("latest should load")
+            .expect("a session should exist");
+        assert_eq!(latest.id, "w-1");
+
+        let mut completed = active;
+        completed.status = "completed".to_owned();
+        completed.updated_at_ms = 5;
+        completed.result = Some(json!({ "written": false }));
+        store
+            .upsert_wizard_session(&completed)
+            .await
+            .expect("session should update");
+        let latest = store
+            .latest_wizard_session("setup")
+            .await
+            .expect("latest should load")
+            .expect("a session should exist");
+        assert_eq!(latest.id, "w-2");
+        assert!(
+            store
+                .latest_wizard_session("other")
+                .await
+                .expect("latest should load")
+                .is_none()
+        );
+    }
+}
@@ -1,11 +1,23 @@
 #[path = "runtime_integration/channels.rs"]
 mod channels;
+#[path = "runtime_integration/dispatch_hooks.rs"]
+mod dispatch_hooks;
+#[path = "runtime_integration/embedding.rs"]
+mod embedding;
 #[path = "runtime_integration/health.rs"]
 mod health;
 #[path = "runtime_integration/hooks.rs"]
 mod hooks;
 #[path = "runtime_integration/http_compat.rs"]
 mod http_compat;
+#[path = "runtime_integration/redis.rs"]
+mod redis;
+#[path = "runtime_integration/replay.rs"]
+mod replay;
+#[path = "runtime_integration/seed.rs"]
+mod seed;
+#[path = "runtime_integration/setup.rs"]
+mod setup;
 #[path = "runtime_integration/support.rs"]
 mod support;
 #[path = "runtime_integration/ws_protocol.rs"]
@@ -2,7 +2,15 @@ use std::net::Ipv4Addr;
 
 use axum::{Json, Router, http::header, routing::post};
 use futures_util::SinkExt;
-use reclaw_core::application::config::{AuthMode, ChannelWebhookPluginConfig};
+use reclaw_core::application::attachment_scan::AttachmentScan;
+use reclaw_core::application::config::{
+    AttachmentScanAction, AttachmentScanConfig, AttachmentScannerKind, AuthMode, ChannelPlugin,
+    ChannelPluginTransport, ChannelWebhookPluginConfig, ContentPolicyConfig, ContentRuleConfig,
+    ContentSeverity, QuietHoursWindow, ReplyFormat, ReplyProcessingConfig,
+    ReplyProcessingRuleConfig, WebhookSourceRule,
+};
+use reclaw_core::application::content_policy::ContentPolicy;
+use reclaw_core::application::reply_processing::ReplyProcessing;
 use reclaw_core::application::state::SharedState;
 use reclaw_core::interfaces::webhooks::{
     ChannelWebhookAdapter, ChannelWebhookRegistry, WebhookFuture,
@@ -183,29 +191,100 @@ async fn channels_inbound_requires_bearer_token_when_configured() {
 }
 
 #[tokio::test]
-async fn channels_inbound_routes_message_to_chat_session() {
+async fn channels_inbound_batch_dedups_and_reports_per_item_results() {
     let server = spawn_server_with(AuthMode::None, |config| {
         config.channels_inbound_token = Some("bridge-token".to_owned());
     })
     .await;
-
     let client = reqwest::Client::new();
-    let response = client
-        .post(format!("http://{}/channels/inbound", server.addr))
+    let url = format!("http://{}/channels/signal/inbound/batch", server.addr);
+
+    let invalid = client
+        .post(&url)
         .bearer_auth("bridge-token")
         .json(&json!({
-            "channel": "telegram",
-            "conversationId": "12345",
-            "text": "hello from channel",
-            "messageId": "m1"
+            "messages": [
+                { "conversationId": "room-1", "text": "fine", "messageId": "b0" },
+                { "conversationId": "room-1", "text": "   ", "messageId": "b1" }
+            ]
         }))
         .send()
         .await
-        .expect("inbound request should return");
+        .expect("batch request should return");
+    assert_eq!(invalid.status(), reqwest::StatusCode::BAD_REQUEST);
+    let invalid: Value = invalid.json().await.expect("response should be json");
+    assert_eq!(invalid["errors"][0]["index"], 1);
 
+    let response = client
+        .post(&url)
+        .bearer_auth("bridge-token")
+        .json(&json!({
+            "messages": [
+                { "conversationId": "room-1", "text": "first", "messageId": "m1" },
+                { "conversationId": "room-2", "text": "second", "messageId": "m2" },
+                { "conversationId": "room-1", "text": "first again", "messageId": "m1" }
+            ]
+        }))
+        .send()
+        .await
+        .expect("batch request should return");
     assert!(response.status().is_success());
     let payload: Value = response.json().await.expect("response should be json");
-    assert_eq!(payload["ok"], true);
+    assert_eq!(payload["count"], 3);
+    assert_eq!(payload["processed"], 2);
+    assert_eq!(payload["duplicates"], 1);
+    assert_eq!(payload["results"][0]["status"], "processed");
+    assert_eq!(
+        payload["results"][1]["sessionKey"],
+        "agent:main:signal:chat:room-2"
+    );
+    assert_eq!(payload["results"][2]["status"], "duplicate");
+    assert_eq!(payload["results"][2]["duplicateOf"], 0);
+
+    let replayed = client
+        .post(&url)
+        .bearer_auth("bridge-token")
+        .json(&json!({
+            "messages": [{ "conversationId": "room-2", "text": "second", "messageId": "m2" }]
+        }))
+        .send()
+        .await
+        .expect("batch request should return");
+    let replayed: Value = replayed.json().await.expect("response should be json");
+    assert_eq!(replayed["results"][0]["status"], "duplicate");
+    assert_eq!(replayed["processed"], 0);
+
+    assert_session_has_history(server.addr, "agent:main:signal:chat:room-1").await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn channels_inbound_routes_message_to_chat_session() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.channels_inbound_token = Some("bridge-token".to_owned());
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    // The second post is a bridge retry of the same message.
+    for _ in 0..2 {
+        let response = client
+            .post(format!("http://{}/channels/inbound", server.addr))
+            .bearer_auth("bridge-token")
+            .json(&json!({
+                "channel": "telegram",
+                "conversationId": "12345",
+                "text": "hello from channel",
+                "messageId": "m1"
+            }))
+            .send()
+            .await
+            .expect("inbound request should return");
+
+        assert!(response.status().is_success());
+        let payload: Value = response.json().await.expect("response should be json");
+        assert_eq!(payload["ok"], true);
+    }
 
     let mut ws = connect_gateway(server.addr).await;
     ws.send(Message::Text(
@@ -234,6 +313,384 @@ async fn channels_inbound_routes_message_to_chat_session() {
             .is_some_and(|messages| messages.len() >= 2)
     );
 
+    let directory = rpc_req(
+        &mut ws,
+        "inbound-2",
+        "channels.directory.list",
+        Some(json!({ "channel": "telegram" })),
+    )
+    .await;
+    assert_eq!(directory["payload"]["entries"][0]["messageCount"], 1);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn content_policy_redacts_and_blocks_channel_messages() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.content_policy = Some(
+            ContentPolicy::compile(ContentPolicyConfig {
+                rules: vec![
+                    ContentRuleConfig {
+                        severity: ContentSeverity::Medium,
+                        words: vec!["darn".to_owned()],
+                        patterns: Vec::new(),
+                    },
+                    ContentRuleConfig {
+                        severity: ContentSeverity::High,
+                        words: vec!["scam".to_owned()],
+                        patterns: vec!["^Echo: launch".to_owned()],
+                    },
+                ],
+                ..ContentPolicyConfig::default()
+            })
+            .expect("policy should compile"),
+        );
+    })
+    .await;
+
+    let mut watcher = connect_gateway(server.addr).await;
+    let mut watcher_connect = connect_frame(None, 1, PROTOCOL_VERSION, "operator", "watcher", &[]);
+    watcher_connect["params"]["caps"] = json!(["agent-events-v1"]);
+    watcher
+        .send(Message::Text(watcher_connect.to_string().into()))
+        .await
+        .expect("connect frame should send");
+    assert_eq!(recv_json(&mut watcher).await["ok"], true);
+
+    let client = reqwest::Client::new();
+    let inbound = |text: &str| {
+        client
+            .post(format!("http://{}/channels/inbound", server.addr))
+            .json(&json!({
+                "channel": "telegram",
+                "conversationId": "777",
+                "text": text,
+            }))
+            .send()
+    };
+
+    let redacted: Value = inbound("well darn")
+        .await
+        .expect("inbound request should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(redacted["ok"], true);
+    // Telegram replies are MarkdownV2, so the redaction mask arrives escaped.
+    assert_eq!(redacted["reply"], "Echo: well \\*\\*\\*");
+    assert_eq!(redacted["replyFormat"], "telegram");
+
+    let blocked: Value = inbound("a scam offer")
+        .await
+        .expect("inbound request should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(blocked["runId"], Value::Null);
+    assert_eq!(blocked["reply"], Value::Null);
+
+    let withheld: Value = inbound("launch now")
+        .await
+        .expect("inbound request should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert!(withheld["runId"].is_string());
+    assert_eq!(withheld["reply"], Value::Null);
+
+    let event = loop {
+        let frame = recv_json(&mut watcher).await;
+        if frame["event"] == "content.policy" && frame["payload"]["action"] == "block" {
+            break frame;
+        }
+    };
+    assert_eq!(event["payload"]["direction"], "inbound");
+    assert_eq!(event["payload"]["severity"], "high");
+    assert_eq!(event["payload"]["text"], "a scam offer");
+
+    // The outbound block event may still be queued ahead of the response.
+    let mut health = rpc_req(&mut watcher, "content-health", "health", None).await;
+    while health["id"] != "content-health" {
+        health = recv_json(&mut watcher).await;
+    }
+    let counters = &health["payload"]["contentPolicy"]["telegram"];
+    assert_eq!(counters["inbound"]["redact"], 1);
+    assert_eq!(counters["inbound"]["block"], 1);
+    assert_eq!(counters["outbound"]["block"], 1);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn linked_identities_share_one_session_across_channels() {
+    let server = spawn_server_with(AuthMode::None, |_| {}).await;
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let _ = recv_json(&mut ws).await;
+
+    let linked = rpc_req(
+        &mut ws,
+        "link-1",
+        "identities.link",
+        Some(json!({
+            "channel": "telegram",
+            "externalId": "333",
+            "displayName": "Ada",
+            "sharedSession": true
+        })),
+    )
+    .await;
+    assert_eq!(linked["ok"], true);
+    let person_id = linked["payload"]["person"]["personId"]
+        .as_str()
+        .expect("person id should be returned")
+        .to_owned();
+
+    let linked = rpc_req(
+        &mut ws,
+        "link-2",
+        "identities.link",
+        Some(json!({
+            "personId": person_id,
+            "channel": "slack",
+            "externalId": "U123"
+        })),
+    )
+    .await;
+    assert_eq!(linked["ok"], true);
+    assert_eq!(
+        linked["payload"]["person"]["identities"]
+            .as_array()
+            .map(Vec::len),
+        Some(2)
+    );
+
+    let client = reqwest::Client::new();
+    let mut session_keys = Vec::new();
+    for (channel, sender) in [("telegram", "333"), ("slack", "U123")] {
+        let payload: Value = client
+            .post(format!("http://{}/channels/inbound", server.addr))
+            .json(&json!({
+                "channel": channel,
+                "conversationId": format!("dm-{sender}"),
+                "senderId": sender,
+                "text": format!("hello from {channel}"),
+            }))
+            .send()
+            .await
+            .expect("inbound request should return")
+            .json()
+            .await
+            .expect("response should be json");
+        assert_eq!(payload["ok"], true);
+        session_keys.push(
+            payload["sessionKey"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned(),
+        );
+    }
+    let expected = format!("agent:main:person:{person_id}");
+    assert_eq!(session_keys, vec![expected.clone(), expected.clone()]);
+
+    let sent = rpc_req(
+        &mut ws,
+        "send-1",
+        "send",
+        Some(json!({
+            "personId": person_id,
+            "message": "reaching you wherever you are"
+        })),
+    )
+    .await;
+    assert_eq!(sent["ok"], true);
+    assert_eq!(sent["payload"]["sessionKey"], expected.as_str());
+
+    let listed = rpc_req(
+        &mut ws,
+        "list-1",
+        "identities.list",
+        Some(json!({ "channel": "slack" })),
+    )
+    .await;
+    assert_eq!(listed["ok"], true);
+    assert_eq!(listed["payload"]["persons"][0]["displayName"], "Ada");
+
+    // Without a shared session, `send` addresses the conversation inbound messages use.
+    let linked = rpc_req(
+        &mut ws,
+        "link-3",
+        "identities.link",
+        Some(json!({ "channel": "telegram", "externalId": "Chat_77" })),
+    )
+    .await;
+    let direct_person = linked["payload"]["person"]["personId"].clone();
+    let send_direct = json!({ "personId": direct_person, "message": "hello there" });
+    let unseen = rpc_req(&mut ws, "send-2", "send", Some(send_direct.clone())).await;
+    assert_eq!(unseen["ok"], false);
+
+    let inbound: Value = client
+        .post(format!("http://{}/channels/inbound", server.addr))
+        .json(&json!({
+            "channel": "telegram",
+            "conversationId": "Chat_77",
+            "senderId": "Chat_77",
+            "text": "hi",
+        }))
+        .send()
+        .await
+        .expect("inbound request should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(inbound["sessionKey"], "agent:main:telegram:chat:chat-77");
+    let sent = rpc_req(&mut ws, "send-3", "send", Some(send_direct)).await;
+    assert_eq!(sent["ok"], true, "{sent}");
+    assert_eq!(sent["payload"]["sessionKey"], inbound["sessionKey"]);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn privacy_export_and_delete_cover_channel_identity_data() {
+    let server = spawn_server_with(AuthMode::None, |_| {}).await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("http://{}/channels/inbound", server.addr))
+        .json(&json!({
+            "channel": "telegram",
+            "conversationId": "333",
+            "senderId": "333",
+            "text": "my personal data",
+            "metadata": { "senderName": "Ada" }
+        }))
+        .send()
+        .await
+        .expect("inbound request should return");
+    assert!(response.status().is_success());
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let _ = recv_json(&mut ws).await;
+
+    let subject = json!({ "channel": "telegram", "externalId": "333" });
+    let export = rpc_req(&mut ws, "export-1", "privacy.export", Some(subject.clone())).await;
+    assert_eq!(export["ok"], true);
+    let archive = &export["payload"]["archive"];
+    assert_eq!(
+        archive["sessions"][0]["sessionKey"],
+        "agent:main:telegram:chat:333"
+    );
+    assert!(
+        archive["sessions"][0]["messages"]
+            .as_array()
+            .is_some_and(|messages| messages.len() >= 2)
+    );
+    assert_eq!(archive["directoryEntries"][0]["participants"][0], "Ada");
+
+    let unconfirmed = rpc_req(&mut ws, "delete-1", "privacy.delete", Some(subject)).await;
+    assert_eq!(unconfirmed["ok"], false);
+
+    let deleted = rpc_req(
+        &mut ws,
+        "delete-2",
+        "privacy.delete",
+        Some(json!({ "channel": "telegram", "externalId": "333", "confirm": true })),
+    )
+    .await;
+    assert_eq!(deleted["ok"], true);
+    assert_eq!(deleted["payload"]["deleted"]["directoryEntries"], 1);
+
+    let history = rpc_req(
+        &mut ws,
+        "history-1",
+        "chat.history",
+        Some(json!({ "sessionKey": "agent:main:telegram:chat:333" })),
+    )
+    .await;
+    assert_eq!(history["payload"]["messages"], json!([]));
+
+    let audit = rpc_req(&mut ws, "audit-1", "privacy.audit.list", None).await;
+    assert_eq!(audit["payload"]["entries"][0]["action"], "delete");
+    assert_eq!(audit["payload"]["entries"][1]["action"], "export");
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn privacy_delete_is_refused_under_append_only() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.append_only = true;
+    })
+    .await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/channels/inbound", server.addr))
+        .json(&json!({
+            "channel": "telegram",
+            "conversationId": "333",
+            "senderId": "333",
+            "text": "my personal data"
+        }))
+        .send()
+        .await
+        .expect("inbound request should return");
+    assert!(response.status().is_success());
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let _ = recv_json(&mut ws).await;
+
+    let deleted = rpc_req(
+        &mut ws,
+        "delete-1",
+        "privacy.delete",
+        Some(json!({ "channel": "telegram", "externalId": "333", "confirm": true })),
+    )
+    .await;
+    assert_eq!(deleted["ok"], false);
+    assert!(
+        deleted["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("appendOnly"))
+    );
+
+    let history = rpc_req(
+        &mut ws,
+        "history-1",
+        "chat.history",
+        Some(json!({ "sessionKey": "agent:main:telegram:chat:333" })),
+    )
+    .await;
+    assert!(
+        history["payload"]["messages"]
+            .as_array()
+            .is_some_and(|messages| !messages.is_empty())
+    );
+    let audit = rpc_req(&mut ws, "audit-1", "privacy.audit.list", None).await;
+    assert_eq!(audit["payload"]["entries"], json!([]));
+
     server.stop().await;
 }
 
@@ -401,44 +858,9 @@ async fn telegram_webhook_ingests_updates_and_deduplicates() {
 }
 
 #[tokio::test]
-async fn telegram_webhook_can_send_outbound_reply() {
-    let mock_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
-        .await
-        .expect("mock listener should bind");
-    let mock_addr = mock_listener
-        .local_addr()
-        .expect("mock listener should expose local addr");
-    let (mock_shutdown_tx, mock_shutdown_rx) = oneshot::channel::<()>();
-    let (body_tx, mut body_rx) = mpsc::unbounded_channel::<Value>();
-
-    let app = Router::new().route(
-        "/bottest-token/sendMessage",
-        post({
-            let body_tx = body_tx.clone();
-            move |Json(body): Json<Value>| {
-                let body_tx = body_tx.clone();
-                async move {
-                    let _ = body_tx.send(body);
-                    Json(json!({
-                        "ok": true,
-                        "result": {"message_id": 9}
-                    }))
-                }
-            }
-        }),
-    );
-    let mock_join = tokio::spawn(async move {
-        let _ = axum::serve(mock_listener, app)
-            .with_graceful_shutdown(async {
-                let _ = mock_shutdown_rx.await;
-            })
-            .await;
-    });
-
+async fn telegram_webhook_populates_channel_directory() {
     let server = spawn_server_with(AuthMode::None, |config| {
         config.telegram_webhook_secret = Some("secret-123".to_owned());
-        config.telegram_bot_token = Some("test-token".to_owned());
-        config.telegram_api_base_url = format!("http://{mock_addr}");
     })
     .await;
 
@@ -447,21 +869,123 @@ async fn telegram_webhook_can_send_outbound_reply() {
         .post(format!("http://{}/channels/telegram/webhook", server.addr))
         .header("x-telegram-bot-api-secret-token", "secret-123")
         .json(&json!({
-            "update_id": 202,
+            "update_id": 201,
             "message": {
-                "message_id": 2,
-                "chat": { "id": 777 },
-                "text": "reply please"
+                "message_id": 21,
+                "chat": { "id": -1001, "type": "supergroup", "title": "Ops Room" },
+                "from": { "id": 333, "first_name": "Alice", "last_name": "Smith" },
+                "text": "status please"
             }
         }))
         .send()
         .await
         .expect("telegram webhook should return");
-
     assert!(response.status().is_success());
-    let payload: Value = response.json().await.expect("response should be json");
-    assert_eq!(payload["ok"], true);
-    assert_eq!(payload["outboundSent"], true);
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let _ = recv_json(&mut ws).await;
+
+    let directory = rpc_req(
+        &mut ws,
+        "directory-1",
+        "channels.directory.list",
+        Some(json!({ "channel": "telegram", "query": "alice" })),
+    )
+    .await;
+    assert_eq!(directory["ok"], true);
+    let entry = &directory["payload"]["entries"][0];
+    assert_eq!(entry["conversationId"], "1001");
+    assert_eq!(entry["title"], "Ops Room");
+    assert_eq!(entry["kind"], "group");
+    assert_eq!(entry["participants"][0], "Alice Smith");
+
+    let history = rpc_req(
+        &mut ws,
+        "history-1",
+        "chat.history",
+        Some(json!({ "sessionKey": "agent:main:telegram:chat:1001" })),
+    )
+    .await;
+    assert_eq!(history["payload"]["displayName"], "Ops Room (telegram)");
+
+    let sessions = rpc_req(&mut ws, "sessions-1", "sessions.list", None).await;
+    assert_eq!(
+        sessions["payload"]["sessions"][0]["displayName"],
+        "Ops Room (telegram)"
+    );
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn telegram_webhook_can_send_outbound_reply() {
+    let mock_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("mock listener should bind");
+    let mock_addr = mock_listener
+        .local_addr()
+        .expect("mock listener should expose local addr");
+    let (mock_shutdown_tx, mock_shutdown_rx) = oneshot::channel::<()>();
+    let (body_tx, mut body_rx) = mpsc::unbounded_channel::<Value>();
+
+    let app = Router::new().route(
+        "/bottest-token/sendMessage",
+        post({
+            let body_tx = body_tx.clone();
+            move |Json(body): Json<Value>| {
+                let body_tx = body_tx.clone();
+                async move {
+                    let _ = body_tx.send(body);
+                    Json(json!({
+                        "ok": true,
+                        "result": {"message_id": 9}
+                    }))
+                }
+            }
+        }),
+    );
+    let mock_join = tokio::spawn(async move {
+        let _ = axum::serve(mock_listener, app)
+            .with_graceful_shutdown(async {
+                let _ = mock_shutdown_rx.await;
+            })
+            .await;
+    });
+
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.telegram_webhook_secret = Some("secret-123".to_owned());
+        config.telegram_bot_token = Some("test-token".to_owned());
+        config.telegram_api_base_url = format!("http://{mock_addr}");
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("http://{}/channels/telegram/webhook", server.addr))
+        .header("x-telegram-bot-api-secret-token", "secret-123")
+        .json(&json!({
+            "update_id": 202,
+            "message": {
+                "message_id": 2,
+                "chat": { "id": 777 },
+                "text": "reply please!"
+            }
+        }))
+        .send()
+        .await
+        .expect("telegram webhook should return");
+
+    assert!(response.status().is_success());
+    let payload: Value = response.json().await.expect("response should be json");
+    assert_eq!(payload["ok"], true);
+    assert_eq!(payload["outboundSent"], true);
 
     let outbound = timeout(std::time::Duration::from_secs(2), body_rx.recv())
         .await
@@ -469,14 +993,362 @@ async fn telegram_webhook_can_send_outbound_reply() {
         .expect("outbound payload should exist");
 
     assert_eq!(outbound["chat_id"], 777);
+    assert_eq!(outbound["text"], "Echo: reply please\\!");
+    assert_eq!(outbound["parse_mode"], "MarkdownV2");
+
+    let _ = mock_shutdown_tx.send(());
+    let _ = mock_join.await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn telegram_managed_webhook_registers_reports_drift_and_deletes_on_shutdown() {
+    let mock_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("mock listener should bind");
+    let mock_addr = mock_listener
+        .local_addr()
+        .expect("mock listener should expose local addr");
+    let (call_tx, mut call_rx) = mpsc::unbounded_channel::<(String, Value)>();
+    let registered_url = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
+
+    let app = Router::new().route(
+        "/bottest-token/{method}",
+        post(
+            move |axum::extract::Path(method): axum::extract::Path<String>,
+                  Json(body): Json<Value>| {
+                let call_tx = call_tx.clone();
+                let registered_url = registered_url.clone();
+                async move {
+                    let mut url = registered_url.lock().expect("mock url lock");
+                    let result = match method.as_str() {
+                        "setWebhook" => {
+                            *url = body["url"].as_str().unwrap_or_default().to_owned();
+                            json!(true)
+                        }
+                        "getWebhookInfo" => json!({
+                            "url": *url,
+                            "pending_update_count": 150,
+                            "last_error_date": 1_700_000_000,
+                            "last_error_message": "Connection timed out",
+                        }),
+                        _ => {
+                            url.clear();
+                            json!(true)
+                        }
+                    };
+                    let _ = call_tx.send((method, body));
+                    Json(json!({ "ok": true, "result": result }))
+                }
+            },
+        ),
+    );
+    let mock_join = tokio::spawn(async move {
+        let _ = axum::serve(mock_listener, app).await;
+    });
+
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.telegram_webhook_secret = Some("secret-123".to_owned());
+        config.telegram_bot_token = Some("test-token".to_owned());
+        config.telegram_api_base_url = format!("http://{mock_addr}");
+        config.telegram_public_base_url = Some("https://bot.example.com".to_owned());
+    })
+    .await;
+
+    let (method, body) = timeout(std::time::Duration::from_secs(2), call_rx.recv())
+        .await
+        .expect("setWebhook should be called on startup")
+        .expect("mock call should exist");
+    assert_eq!(method, "setWebhook");
+    assert_eq!(
+        body["url"],
+        "https://bot.example.com/channels/telegram/webhook"
+    );
+    assert_eq!(body["secret_token"], "secret-123");
+    let (method, _) = timeout(std::time::Duration::from_secs(2), call_rx.recv())
+        .await
+        .expect("getWebhookInfo should follow registration")
+        .expect("mock call should exist");
+    assert_eq!(method, "getWebhookInfo");
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let _ = recv_json(&mut ws).await;
+
+    let mut webhook = Value::Null;
+    for attempt in 0..20 {
+        let status = rpc_req(
+            &mut ws,
+            &format!("status-{attempt}"),
+            "channels.status",
+            None,
+        )
+        .await;
+        webhook = status["payload"]["channels"]
+            .as_array()
+            .and_then(|channels| channels.iter().find(|channel| channel["id"] == "telegram"))
+            .map(|telegram| telegram["webhook"].clone())
+            .unwrap_or_default();
+        if webhook.get("checkedAtMs").is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(webhook["managed"], true);
+    assert_eq!(webhook["registered"], true);
+    assert_eq!(
+        webhook["registeredUrl"],
+        "https://bot.example.com/channels/telegram/webhook"
+    );
+    assert_eq!(webhook["pendingUpdateCount"], 150);
+    assert_eq!(webhook["drift"], json!(["pendingBacklog"]));
+    assert_eq!(webhook["lastErrorMessage"], "Connection timed out");
+    assert_eq!(webhook["lastErrorDateMs"], 1_700_000_000_000_u64);
+
+    server.stop().await;
+    let (method, _) = timeout(std::time::Duration::from_secs(2), call_rx.recv())
+        .await
+        .expect("deleteWebhook should be called on shutdown")
+        .expect("mock call should exist");
+    assert_eq!(method, "deleteWebhook");
+
+    mock_join.abort();
+}
+
+const TEAMS_TEST_KEY_N: &str = "3YZ2cw7wsOzGso9TJfuG2CC_xLqhXigVlfXwazmFdnqaZr1Ca2kbyMAonoDoL-gAbk3SrC1Xnbf70DoGDFBZKz-CLEgOdQYHFKU3ySxPa7wZEWJCOtPYNyPx9vBJV0hqUaUNMnAQ8IVKhC6ix6NFbUq7rXAOlmYJnsBk4wIe5-qXjXbS2mLwikAtfmLTDdvTGPujIcGtPu_Zye1Oa1iRH5nP_rTRUt3gWrTvg1x1YyeFYPAI7lV1R_psNpBmZxyKum6PDUbxEJrfI-GovZone65log6TsYFhLTzIV_egxlJaMy44IYu9HXKVxOGAAwk2wKi3kYNppzWQjwU3SnbaXQ";
+/// PKCS#8 RSA key matching `TEAMS_TEST_KEY_N`, for signing test tokens.
+const TEAMS_TEST_KEY_PKCS8: &str = "MIIEvAIBADANBgkqhkiG9w0BAQEFAASCBKYwggSiAgEAAoIBAQDdhnZzDvCw7Mayj1Ml+4bYIL/EuqFeKBWV9fBrOYV2eppmvUJraRvIwCiegOgv6ABuTdKsLVedt/vQOgYMUFkrP4IsSA51BgcUpTfJLE9rvBkRYkI609g3I/H28ElXSGpRpQ0ycBDwhUqELqLHo0VtSrutcA6WZgmewGTjAh7n6peNdtLaYvCKQC1+YtMN29MY+6Mhwa0+79nJ7U5rWJEfmc/+tNFS3eBatO+DXHVjJ4Vg8AjuVXVH+mw2kGZnHIq6bo8NRvEQmt8j4ai9mid7rmWiDpOxgWEtPMhX96DGUlozLjghi70dcpXE4YADCTbAqLeRg2mnNZCPBTdKdtpdAgMBAAECggEAFXUMzCBKWBjXNjyDxU4TxaOGb7vUHZasYJYt73Tm5I8Dnaf/YwAPXOagCJpb4ygJwqVvHpZVwkUr8wNryo2N1Y6VQUzJebRXwzwtpyLPnpPdqb1mxOP4MX8d1Nl5gSbWhYoDOTt5qJKAoUUTJ1s9QlTW0Pnlr9O6YtIn84UlETmNroH4LzTSO2v/78swTpOSAoAvJVe8FAdWa21/3l/LmWeyYrffVWNZl6wZzzA3DxI80AZknHhEQ15kSwdgbX5UyUkmdDmYuhNOcM69nNpjuXvuaHyXiSHSc3PUeoMHQoRsX+9DqKcqp9iKIGP7hSzHrSHA480yn/CaNYHVvgWNAQKBgQD/B1bLkFEjm52QDIOFH4ZkuD1HQvs2jR1vEIrGFQ8p21ixL5nyoJjeRoc5WqAFnADn2xx/eN4jP4313FqiQmKbY/JW/OSuNdYEZ2xzHo3l1TVoW5Jo7xXPmFn+/5HZpHd6z2wV+DoarjCkzqs7K6bIRx9Zy4uHYBPBqo83Vz/KUQKBgQDeXnTuPR/GyuVDHoKL9uNcY48HSCf/0XZe/47r3u4u67kk12btAI7t9FUC+L7EAsiTbiVLuQUVO8ZTc7XeZunp3mRbA1DNHGvKRyMqgdm+L+xUsFha6/vMFgD0UiBXw/wltOmCd5oXQ2nd47zOOzlDU+S/dwDRxz5vT7sSLxcATQKBgAe8bUGXix6NZeYsuwCayDTd8d3rhjtNZIst8Ue489DNOo+FZiIaBQkZEpkGyrvg05Y061Rikjp3fxLqMEWjxeH40i0ZVsaCE4/moz+KubszEH+t528PcZXuhSxbDE2R054CFMFF2l1c01Tsf/03gcOphKXwqrbcjzzcs6N2HhVBAoGAKn6KgeyUYfzsCwONlwm7MTClaJLVcgGpSUY1HcYgZj8NxoiTVr9AkSCcDlFSw0p04heucOYOnpwprlDydGneUZ76NppPmqLPxnwJsFnYLowh8Z0xhU6Lc9jQneAD5kHBscL8zL3maIOte4XF0vNmYgNqN1yaf3w/5i2qZjMxhhECgYB0Kumju0gRmwx9SA3sYPooHtT8n1zqQBweoPjOuXWlhnRtBYsTC0w1henSFTno8nIA2EU7lw6B91MnlbcAte422ENXSEOnHr7ARaiM4gTjtTseXsnuGGDisITabOKoGqpncasep8iinumdNNUNL4jTIpScO7C4l2nkbAwYcoH3fA==";
+
+/// RS256 token for audience `teams-app` from the Bot Framework issuer, valid until 2100.
+fn teams_token(kid: &str, service_url: &str) -> String {
+    use base64::{
+        Engine,
+        engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    };
+    use ring::signature::{RSA_PKCS1_SHA256, RsaKeyPair};
+
+    let segment = |value: Value| URL_SAFE_NO_PAD.encode(value.to_string());
+    let signing_input = format!(
+        "{}.{}",
+        segment(json!({ "alg": "RS256", "kid": kid, "typ": "JWT" })),
+        segment(json!({
+            "aud": "teams-app",
+            "iss": "https://api.botframework.com",
+            "serviceurl": service_url,
+            "nbf": 1_700_000_000,
+            "exp": 4_102_444_800_u64,
+        })),
+    );
+    let key = RsaKeyPair::from_pkcs8(&STANDARD.decode(TEAMS_TEST_KEY_PKCS8).expect("test key"))
+        .expect("test key should parse");
+    let mut signature = vec![0; key.public().modulus_len()];
+    key.sign(
+        &RSA_PKCS1_SHA256,
+        &ring::rand::SystemRandom::new(),
+        signing_input.as_bytes(),
+        &mut signature,
+    )
+    .expect("test token should sign");
+    format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature))
+}
+
+#[tokio::test]
+async fn teams_webhook_validates_tokens_and_tenants_and_replies_via_connector() {
+    let mock_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("mock listener should bind");
+    let mock_addr = mock_listener
+        .local_addr()
+        .expect("mock listener should expose local addr");
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<(String, String, Value)>();
+    let key_fetches = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counted_fetches = std::sync::Arc::clone(&key_fetches);
+
+    let app = Router::new()
+        .route(
+            "/openid",
+            axum::routing::get(move || async move {
+                Json(json!({ "jwks_uri": format!("http://{mock_addr}/keys") }))
+            }),
+        )
+        .route(
+            "/keys",
+            axum::routing::get(move || {
+                counted_fetches.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async {
+                    Json(json!({ "keys": [
+                        { "kty": "RSA", "kid": "k1", "n": TEAMS_TEST_KEY_N, "e": "AQAB" }
+                    ]}))
+                }
+            }),
+        )
+        .route(
+            "/token",
+            post(|body: String| async move {
+                assert!(body.contains("grant_type=client_credentials"));
+                assert!(body.contains("client_id=teams-app"));
+                Json(json!({ "access_token": "connector-token", "expires_in": 3600 }))
+            }),
+        )
+        .route(
+            "/v3/conversations/{conversation}/activities/{activity}",
+            post(
+                move |axum::extract::Path((conversation, activity)): axum::extract::Path<(
+                    String,
+                    String,
+                )>,
+                      headers: axum::http::HeaderMap,
+                      Json(body): Json<Value>| {
+                    let reply_tx = reply_tx.clone();
+                    async move {
+                        let auth = headers
+                            .get(header::AUTHORIZATION)
+                            .and_then(|value| value.to_str().ok())
+                            .unwrap_or_default()
+                            .to_owned();
+                        let _ = reply_tx.send((auth, format!("{conversation}/{activity}"), body));
+                        Json(json!({ "id": "reply-1" }))
+                    }
+                },
+            ),
+        );
+    let mock_join = tokio::spawn(async move {
+        let _ = axum::serve(mock_listener, app).await;
+    });
+
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.teams_app_id = Some("teams-app".to_owned());
+        config.teams_app_password = Some("teams-secret".to_owned());
+        config.teams_tenant_allowlist = vec!["tenant-a".to_owned()];
+        config.teams_openid_metadata_url = format!("http://{mock_addr}/openid");
+        config.teams_token_url = format!("http://{mock_addr}/token");
+    })
+    .await;
+
+    let activity = |id: &str, tenant: &str| {
+        json!({
+            "type": "message",
+            "id": id,
+            "serviceUrl": format!("http://{mock_addr}/"),
+            "channelId": "msteams",
+            "from": { "id": "29:ada", "name": "Ada" },
+            "conversation": {
+                "id": "19:ops@thread.v2",
+                "conversationType": "channel",
+                "tenantId": tenant,
+                "name": "Ops"
+            },
+            "recipient": { "id": "28:teams-app", "name": "Reclaw" },
+            "text": "<at>Reclaw</at> status please",
+            "channelData": { "tenant": { "id": tenant } }
+        })
+    };
+    let client = reqwest::Client::new();
+    let webhook_url = format!("http://{}/channels/teams/webhook", server.addr);
+    let token = teams_token("k1", &format!("http://{mock_addr}/"));
+
+    let unsigned = client
+        .post(&webhook_url)
+        .json(&activity("act-0", "tenant-a"))
+        .send()
+        .await
+        .expect("teams webhook should respond");
+    assert_eq!(unsigned.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let forged = client
+        .post(&webhook_url)
+        .bearer_auth(token.replace(".eyJhdWQ", ".eyJhdWR"))
+        .json(&activity("act-0", "tenant-a"))
+        .send()
+        .await
+        .expect("teams webhook should respond");
+    assert_eq!(forged.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let other_tenant = client
+        .post(&webhook_url)
+        .bearer_auth(&token)
+        .json(&activity("act-0", "tenant-b"))
+        .send()
+        .await
+        .expect("teams webhook should respond");
+    assert_eq!(other_tenant.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let response = client
+        .post(&webhook_url)
+        .bearer_auth(&token)
+        .json(&activity("act-1", "tenant-a"))
+        .send()
+        .await
+        .expect("teams webhook should respond");
+    assert!(response.status().is_success());
+    let payload: Value = response.json().await.expect("response should be json");
+    assert_eq!(payload["accepted"], true);
+    assert_eq!(payload["outboundSent"], true);
+    let session_key = payload["sessionKey"]
+        .as_str()
+        .expect("session key should exist")
+        .to_owned();
+    assert!(session_key.starts_with("agent:main:teams:chat:"));
+
+    let (auth, path, reply) = timeout(std::time::Duration::from_secs(2), reply_rx.recv())
+        .await
+        .expect("connector reply should arrive")
+        .expect("connector reply should exist");
+    assert_eq!(auth, "Bearer connector-token");
+    assert_eq!(path, "19:ops@thread.v2/act-1");
+    assert_eq!(reply["type"], "message");
+    assert_eq!(reply["replyToId"], "act-1");
     assert!(
-        outbound["text"]
+        reply["text"]
             .as_str()
-            .is_some_and(|text| text.contains("Echo:"))
+            .is_some_and(|text| text.contains("status please") && !text.contains("<at>"))
     );
 
-    let _ = mock_shutdown_tx.send(());
-    let _ = mock_join.await;
+    let duplicate = client
+        .post(&webhook_url)
+        .bearer_auth(&token)
+        .json(&activity("act-1", "tenant-a"))
+        .send()
+        .await
+        .expect("teams webhook should respond");
+    let duplicate: Value = duplicate.json().await.expect("response should be json");
+    assert_eq!(duplicate["duplicate"], true);
+
+    let other_service = client
+        .post(&webhook_url)
+        .bearer_auth(teams_token("k1", "https://smba.example/"))
+        .json(&activity("act-2", "tenant-a"))
+        .send()
+        .await
+        .expect("teams webhook should respond");
+    assert_eq!(other_service.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let fetches = key_fetches.load(std::sync::atomic::Ordering::SeqCst);
+    for _ in 0..3 {
+        let unknown_kid = client
+            .post(&webhook_url)
+            .bearer_auth(teams_token("k9", &format!("http://{mock_addr}/")))
+            .json(&activity("act-3", "tenant-a"))
+            .send()
+            .await
+            .expect("teams webhook should respond");
+        assert_eq!(unknown_kid.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+    assert_eq!(
+        key_fetches.load(std::sync::atomic::Ordering::SeqCst),
+        fetches,
+        "unknown kids must not refetch keys fetched moments ago"
+    );
+
+    assert_session_has_history(server.addr, &session_key).await;
+
+    mock_join.abort();
     server.stop().await;
 }
 
@@ -581,6 +1453,88 @@ async fn channel_webhook_proxies_to_configured_plugin_endpoint() {
     server.stop().await;
 }
 
+#[cfg(unix)]
+#[tokio::test]
+async fn channel_plugins_dir_adapters_verify_parse_and_respond() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let plugins_dir = tempfile::tempdir().expect("plugins dir should be created");
+    let script = plugins_dir.path().join("extchat.sh");
+    std::fs::write(
+        &script,
+        r#"#!/bin/sh
+input=$(cat)
+case "$input" in
+  *'"op":"verify"'*)
+    case "$input" in
+      *'"x-extchat-signature":"good"'*) echo '{"ok":true}' ;;
+      *) echo '{"ok":false,"status":403,"message":"bad signature"}' ;;
+    esac ;;
+  *'"op":"parse"'*)
+    echo '{"messages":[{"conversationId":"room-1","text":"hi from extchat","senderId":"u-1","messageId":"m-1"}]}' ;;
+  *'"op":"respond"'*)
+    echo '{"status":202,"body":{"handled":"'"$RECLAW_CHANNEL"'"}}' ;;
+esac
+"#,
+    )
+    .expect("plugin script should write");
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+        .expect("plugin script should be executable");
+    let server = spawn_server_with(AuthMode::None, move |config| {
+        config.channel_plugins.insert(
+            "extchat".to_owned(),
+            ChannelPlugin {
+                transport: ChannelPluginTransport::Command {
+                    program: script,
+                    args: Vec::new(),
+                },
+                timeout: std::time::Duration::from_secs(5),
+            },
+        );
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/channels/extchat/webhook", server.addr);
+    let payload = json!({"event": "message", "room": "room-1"});
+
+    let rejected = client
+        .post(&url)
+        .header("x-extchat-signature", "forged")
+        .json(&payload)
+        .send()
+        .await
+        .expect("plugin webhook should respond");
+    assert_eq!(rejected.status(), reqwest::StatusCode::FORBIDDEN);
+    let body: Value = rejected.json().await.expect("response should be json");
+    assert_eq!(body["error"]["message"], "bad signature");
+
+    let accepted = client
+        .post(&url)
+        .header("x-extchat-signature", "good")
+        .json(&payload)
+        .send()
+        .await
+        .expect("plugin webhook should respond");
+    assert_eq!(accepted.status(), reqwest::StatusCode::ACCEPTED);
+    let body: Value = accepted.json().await.expect("response should be json");
+    assert_eq!(body, json!({"handled": "extchat"}));
+    assert_session_has_history(server.addr, "agent:main:extchat:chat:room-1").await;
+
+    let duplicate = client
+        .post(&url)
+        .header("x-extchat-signature", "good")
+        .json(&payload)
+        .send()
+        .await
+        .expect("plugin webhook should respond");
+    let body: Value = duplicate.json().await.expect("response should be json");
+    assert_eq!(body["accepted"], false);
+    assert_eq!(body["duplicate"], true);
+
+    server.stop().await;
+}
+
 #[tokio::test]
 async fn slack_webhook_requires_configured_token() {
     let server = spawn_server_with(AuthMode::None, |_| {}).await;
@@ -868,30 +1822,126 @@ async fn slack_webhook_can_dispatch_outbound_reply() {
 }
 
 #[tokio::test]
-async fn discord_webhook_ingests_message_payload() {
+async fn slack_outbound_reply_is_queued_during_quiet_hours_unless_urgent() {
+    let (relay_addr, relay_shutdown_tx, relay_join, mut relay_rx) =
+        spawn_outbound_capture("/slack").await;
     let server = spawn_server_with(AuthMode::None, |config| {
-        config.discord_webhook_token = Some("discord-token".to_owned());
+        let now = chrono::Utc::now();
+        config.slack_webhook_token = Some("slack-token".to_owned());
+        config.slack_outbound_url = Some(format!("http://{relay_addr}/slack"));
+        config.quiet_hours.insert(
+            "slack".to_owned(),
+            QuietHoursWindow {
+                start: (now - chrono::Duration::hours(1)).time(),
+                end: (now + chrono::Duration::hours(1)).time(),
+                timezone: chrono_tz::UTC,
+            },
+        );
     })
     .await;
 
     let client = reqwest::Client::new();
-    let response = client
-        .post(format!("http://{}/channels/discord/webhook", server.addr))
-        .bearer_auth("discord-token")
-        .json(&json!({
-            "id": "msg-1",
-            "channel_id": "chan-1",
-            "content": "hello from discord",
-            "author": { "id": "user-1" }
-        }))
-        .send()
+    let send_event = |event_id: &'static str, urgent: bool| {
+        let mut request = client
+            .post(format!("http://{}/channels/slack/webhook", server.addr))
+            .bearer_auth("slack-token")
+            .json(&json!({
+                "type": "event_callback",
+                "event_id": event_id,
+                "event": {
+                    "type": "message",
+                    "channel": "C-quiet",
+                    "user": "U-quiet",
+                    "text": format!("message {event_id}"),
+                    "ts": "555.666"
+                }
+            }));
+        if urgent {
+            request = request.header("x-reclaw-urgent", "true");
+        }
+        request.send()
+    };
+
+    let payload: Value = send_event("Ev-quiet-1", false)
         .await
-        .expect("discord webhook should return");
-
-    assert!(response.status().is_success());
-    let payload: Value = response.json().await.expect("response should be json");
-    assert_eq!(payload["ok"], true);
-    assert_eq!(payload["accepted"], true);
+        .expect("slack webhook should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(payload["outboundSent"], false);
+    assert_eq!(payload["outboundQueued"], true);
+    assert!(
+        timeout(std::time::Duration::from_millis(300), relay_rx.recv())
+            .await
+            .is_err()
+    );
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let _ = recv_json(&mut ws).await;
+    let queue = rpc_req(
+        &mut ws,
+        "queue-1",
+        "channels.outbound.queue",
+        Some(json!({ "channel": "slack" })),
+    )
+    .await;
+    assert_eq!(queue["ok"], true);
+    assert_eq!(queue["payload"]["quietHours"]["slack"]["quiet"], true);
+    assert_eq!(
+        queue["payload"]["messages"][0]["payload"]["conversationId"],
+        "C-quiet"
+    );
+
+    let payload: Value = send_event("Ev-quiet-2", true)
+        .await
+        .expect("slack webhook should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(payload["outboundSent"], true);
+    let outbound = timeout(std::time::Duration::from_secs(2), relay_rx.recv())
+        .await
+        .expect("urgent outbound request should arrive")
+        .expect("outbound payload should exist");
+    assert_eq!(outbound.1["conversationId"], "C-quiet");
+
+    let _ = relay_shutdown_tx.send(());
+    let _ = relay_join.await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn discord_webhook_ingests_message_payload() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.discord_webhook_token = Some("discord-token".to_owned());
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("http://{}/channels/discord/webhook", server.addr))
+        .bearer_auth("discord-token")
+        .json(&json!({
+            "id": "msg-1",
+            "channel_id": "chan-1",
+            "content": "hello from discord",
+            "author": { "id": "user-1" }
+        }))
+        .send()
+        .await
+        .expect("discord webhook should return");
+
+    assert!(response.status().is_success());
+    let payload: Value = response.json().await.expect("response should be json");
+    assert_eq!(payload["ok"], true);
+    assert_eq!(payload["accepted"], true);
 
     let session_key = payload["sessionKey"]
         .as_str()
@@ -950,6 +2000,248 @@ async fn discord_webhook_can_dispatch_outbound_reply() {
     server.stop().await;
 }
 
+#[tokio::test]
+async fn reply_processing_formats_signs_and_splits_relayed_replies() {
+    let (relay_addr, relay_shutdown_tx, relay_join, mut relay_rx) =
+        spawn_outbound_capture("/discord").await;
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.discord_webhook_token = Some("discord-token".to_owned());
+        config.discord_outbound_url = Some(format!("http://{relay_addr}/discord"));
+        config.reply_processing = Some(
+            ReplyProcessing::compile(ReplyProcessingConfig {
+                defaults: ReplyProcessingRuleConfig {
+                    footer: Some("-- bot".to_owned()),
+                    ..ReplyProcessingRuleConfig::default()
+                },
+                channels: [(
+                    "discord".to_owned(),
+                    ReplyProcessingRuleConfig {
+                        format: Some(ReplyFormat::Plain),
+                        max_length: Some(20),
+                        suppress_unfurl: Some(true),
+                        ..ReplyProcessingRuleConfig::default()
+                    },
+                )]
+                .into(),
+                ..ReplyProcessingConfig::default()
+            })
+            .expect("reply processing should compile"),
+        );
+    })
+    .await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/channels/discord/webhook", server.addr))
+        .bearer_auth("discord-token")
+        .json(&json!({
+            "id": "discord-processed-1",
+            "channel_id": "discord-channel",
+            "content": "**please** relay these words",
+            "author": { "id": "discord-user" }
+        }))
+        .send()
+        .await
+        .expect("discord webhook should return");
+    let payload: Value = response.json().await.expect("response should be json");
+    assert_eq!(payload["outboundSent"], true);
+    assert_eq!(payload["reply"], "Echo: please relay these words\n\n-- bot");
+
+    let mut relayed = Vec::new();
+    for _ in 0..2 {
+        let (_, body) = timeout(std::time::Duration::from_secs(2), relay_rx.recv())
+            .await
+            .expect("discord outbound request should arrive")
+            .expect("outbound payload should exist");
+        relayed.push(body);
+    }
+    assert_eq!(relayed[0]["reply"], "Echo: please relay");
+    assert_eq!(relayed[1]["reply"], "these words\n\n-- bot");
+    assert_eq!(relayed[0]["part"], 1);
+    assert_eq!(relayed[1]["parts"], 2);
+    assert!(relayed.iter().all(|body| body["unfurlLinks"] == false));
+    assert_ne!(relayed[0]["deliveryId"], relayed[1]["deliveryId"]);
+
+    let _ = relay_shutdown_tx.send(());
+    let _ = relay_join.await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn operator_takeover_routes_inbound_to_operators_and_relays_their_replies() {
+    let (relay_addr, relay_shutdown_tx, relay_join, mut relay_rx) =
+        spawn_outbound_capture("/discord").await;
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.discord_webhook_token = Some("discord-token".to_owned());
+        config.discord_outbound_url = Some(format!("http://{relay_addr}/discord"));
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let post_message = |id: &'static str, text: &'static str| {
+        client
+            .post(format!("http://{}/channels/discord/webhook", server.addr))
+            .bearer_auth("discord-token")
+            .json(&json!({
+                "id": id,
+                "channel_id": "Support-Room",
+                "content": text,
+                "author": { "id": "discord-user" }
+            }))
+            .send()
+    };
+    async fn next_relay(relay_rx: &mut mpsc::UnboundedReceiver<(Option<String>, Value)>) -> Value {
+        timeout(std::time::Duration::from_secs(2), relay_rx.recv())
+            .await
+            .expect("relay request should arrive")
+            .expect("relay payload should exist")
+            .1
+    }
+
+    let first: Value = post_message("takeover-1", "hello")
+        .await
+        .expect("discord webhook should return")
+        .json()
+        .await
+        .expect("response should be json");
+    let session_key = first["sessionKey"]
+        .as_str()
+        .expect("session key should exist")
+        .to_owned();
+    assert_eq!(next_relay(&mut relay_rx).await["reply"], "Echo: hello");
+
+    let mut watcher = connect_gateway(server.addr).await;
+    let mut watcher_connect = connect_frame(None, 1, PROTOCOL_VERSION, "operator", "watcher", &[]);
+    watcher_connect["params"]["caps"] = json!(["agent-events-v1"]);
+    watcher
+        .send(Message::Text(watcher_connect.to_string().into()))
+        .await
+        .expect("connect frame should send");
+    assert_eq!(recv_json(&mut watcher).await["ok"], true);
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "agent-sam", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let _ = recv_json(&mut ws).await;
+
+    let started = rpc_req(
+        &mut ws,
+        "takeover-start",
+        "chat.takeover.start",
+        Some(json!({ "sessionKey": session_key, "reason": "billing question" })),
+    )
+    .await;
+    assert_eq!(started["ok"], true);
+    assert_eq!(started["payload"]["started"], true);
+    assert_eq!(
+        started["payload"]["takeover"]["target"]["channel"],
+        "discord"
+    );
+    let started_event = recv_json(&mut watcher).await;
+    assert_eq!(started_event["event"], "chat.takeover");
+    assert_eq!(started_event["payload"]["state"], "started");
+    assert_eq!(started_event["payload"]["operator"], "agent-sam");
+
+    let held: Value = post_message("takeover-2", "I need a human")
+        .await
+        .expect("discord webhook should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(held["accepted"], true);
+    assert_eq!(held["reply"], Value::Null);
+    assert_eq!(held["outboundSent"], false);
+    let inbound_event = recv_json(&mut watcher).await;
+    assert_eq!(inbound_event["payload"]["state"], "inbound");
+    assert_eq!(inbound_event["payload"]["conversationId"], "Support-Room");
+    assert_eq!(
+        inbound_event["payload"]["message"]["text"],
+        "I need a human"
+    );
+
+    let reply = rpc_req(
+        &mut ws,
+        "takeover-reply",
+        "chat.takeover.reply",
+        Some(json!({ "sessionKey": session_key, "message": "Sam here, let me check." })),
+    )
+    .await;
+    assert_eq!(reply["ok"], true);
+    assert_eq!(reply["payload"]["status"], "sent");
+    // The agent never answered the held message, so the operator reply is the next relay call.
+    let relayed = next_relay(&mut relay_rx).await;
+    assert_eq!(relayed["conversationId"], "Support-Room");
+    assert_eq!(relayed["reply"], "Sam here, let me check.");
+    assert_eq!(relayed["metadata"]["takeover"], true);
+    assert_eq!(relayed["deliveryId"], reply["payload"]["deliveryId"]);
+
+    let ended = rpc_req(
+        &mut ws,
+        "takeover-end",
+        "chat.takeover.end",
+        Some(json!({ "sessionKey": session_key })),
+    )
+    .await;
+    assert_eq!(ended["payload"]["ended"], true);
+    let not_active = rpc_req(
+        &mut ws,
+        "takeover-reply-2",
+        "chat.takeover.reply",
+        Some(json!({ "sessionKey": session_key, "message": "too late" })),
+    )
+    .await;
+    assert_eq!(not_active["ok"], false);
+
+    let resumed: Value = post_message("takeover-3", "thanks")
+        .await
+        .expect("discord webhook should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(resumed["reply"], "Echo: thanks");
+    assert_eq!(next_relay(&mut relay_rx).await["reply"], "Echo: thanks");
+
+    let history = rpc_req(
+        &mut ws,
+        "takeover-history",
+        "chat.history",
+        Some(json!({ "sessionKey": session_key })),
+    )
+    .await;
+    let transcript = history["payload"]["messages"]
+        .as_array()
+        .expect("messages should be an array")
+        .iter()
+        .map(|message| {
+            format!(
+                "{}: {}",
+                message["role"].as_str().unwrap_or_default(),
+                message["text"].as_str().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        transcript,
+        vec![
+            "user: hello",
+            "assistant: Echo: hello",
+            "system: agent-sam took over the conversation: billing question",
+            "user: I need a human",
+            "assistant: Sam here, let me check.",
+            "system: agent-sam ended the takeover; the agent resumes the conversation",
+            "user: thanks",
+            "assistant: Echo: thanks",
+        ]
+    );
+
+    let _ = relay_shutdown_tx.send(());
+    let _ = relay_join.await;
+    server.stop().await;
+}
+
 #[tokio::test]
 async fn signal_webhook_ingests_envelope_payload() {
     let server = spawn_server_with(AuthMode::None, |config| {
@@ -1039,6 +2331,146 @@ async fn signal_webhook_can_dispatch_outbound_reply() {
     server.stop().await;
 }
 
+#[tokio::test]
+async fn mattermost_outgoing_webhook_verifies_token_and_replies_via_incoming_webhook() {
+    let (hook_addr, hook_shutdown_tx, hook_join, mut hook_rx) =
+        spawn_outbound_capture("/hooks/mm").await;
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.mattermost_webhook_token = Some("mm-token".to_owned());
+        config.mattermost_incoming_webhook_url = Some(format!("http://{hook_addr}/hooks/mm"));
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/channels/mattermost/webhook", server.addr);
+    let outgoing = json!({
+        "token": "mm-token",
+        "team_domain": "acme",
+        "channel_id": "4xp9fdt8pjgzbmjxhdsrp5xz3e",
+        "channel_name": "town-square",
+        "user_id": "u1",
+        "user_name": "alice",
+        "post_id": "p1",
+        "text": "#ask status please",
+        "trigger_word": "#ask"
+    });
+
+    let mut forged = outgoing.clone();
+    forged["token"] = json!("wrong");
+    let rejected = client
+        .post(&url)
+        .json(&forged)
+        .send()
+        .await
+        .expect("mattermost webhook should return");
+    assert_eq!(rejected.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .post(&url)
+        .json(&outgoing)
+        .send()
+        .await
+        .expect("mattermost webhook should return");
+    assert!(response.status().is_success());
+    let payload: Value = response.json().await.expect("response should be json");
+    assert_eq!(payload["accepted"], true);
+    assert_eq!(
+        payload["sessionKey"],
+        "agent:main:mattermost:chat:4xp9fdt8pjgzbmjxhdsrp5xz3e"
+    );
+    assert_eq!(payload["outboundSent"], true);
+
+    let outbound = timeout(std::time::Duration::from_secs(2), hook_rx.recv())
+        .await
+        .expect("incoming webhook request should arrive")
+        .expect("outbound payload should exist");
+    assert_eq!(outbound.1["channel"], "town-square");
+    assert!(
+        outbound.1["text"]
+            .as_str()
+            .is_some_and(|text| text.contains("status please") && !text.contains("#ask"))
+    );
+
+    let duplicate: Value = client
+        .post(&url)
+        .json(&outgoing)
+        .send()
+        .await
+        .expect("mattermost webhook should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(duplicate["duplicate"], true);
+
+    let _ = hook_shutdown_tx.send(());
+    let _ = hook_join.await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn rocketchat_outgoing_webhook_ignores_bots_and_replies_via_incoming_webhook() {
+    let (hook_addr, hook_shutdown_tx, hook_join, mut hook_rx) =
+        spawn_outbound_capture("/hooks/rc").await;
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.rocketchat_webhook_token = Some("rc-token".to_owned());
+        config.rocketchat_incoming_webhook_url = Some(format!("http://{hook_addr}/hooks/rc"));
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/channels/rocketchat/webhook", server.addr);
+
+    let bot: Value = client
+        .post(&url)
+        .json(&json!({
+            "token": "rc-token",
+            "bot": { "i": "integration" },
+            "channel_id": "GENERAL",
+            "message_id": "m0",
+            "text": "Echo: earlier reply"
+        }))
+        .send()
+        .await
+        .expect("rocketchat webhook should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(bot["reason"], "bot-message");
+
+    let response = client
+        .post(&url)
+        .json(&json!({
+            "token": "rc-token",
+            "bot": false,
+            "channel_id": "GENERAL",
+            "channel_name": "general",
+            "message_id": "m1",
+            "user_id": "u1",
+            "user_name": "bob",
+            "text": "hello rocket"
+        }))
+        .send()
+        .await
+        .expect("rocketchat webhook should return");
+    assert!(response.status().is_success());
+    let payload: Value = response.json().await.expect("response should be json");
+    assert_eq!(payload["sessionKey"], "agent:main:rocketchat:chat:general");
+    assert_eq!(payload["outboundSent"], true);
+
+    let outbound = timeout(std::time::Duration::from_secs(2), hook_rx.recv())
+        .await
+        .expect("incoming webhook request should arrive")
+        .expect("outbound payload should exist");
+    assert_eq!(outbound.1["channel"], "GENERAL");
+    assert!(
+        outbound.1["text"]
+            .as_str()
+            .is_some_and(|text| text.contains("hello rocket"))
+    );
+
+    let _ = hook_shutdown_tx.send(());
+    let _ = hook_join.await;
+    server.stop().await;
+}
+
 #[tokio::test]
 async fn whatsapp_webhook_ingests_cloud_payload() {
     let server = spawn_server_with(AuthMode::None, |config| {
@@ -1135,3 +2567,364 @@ async fn whatsapp_webhook_can_dispatch_outbound_reply() {
     let _ = relay_join.await;
     server.stop().await;
 }
+
+#[tokio::test]
+async fn outbound_replies_track_delivery_receipts() {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("mock listener should bind");
+    let relay_addr = listener
+        .local_addr()
+        .expect("mock listener should expose local addr");
+    let (relay_tx, mut relay_rx) = mpsc::unbounded_channel::<Value>();
+    let relay = Router::new().route(
+        "/whatsapp",
+        post(move |Json(body): Json<Value>| {
+            let relay_tx = relay_tx.clone();
+            async move {
+                let _ = relay_tx.send(body);
+                Json(json!({ "messageId": "wamid.reply-1" }))
+            }
+        }),
+    );
+    let relay_join = tokio::spawn(async move {
+        let _ = axum::serve(listener, relay).await;
+    });
+
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.whatsapp_webhook_token = Some("whatsapp-token".to_owned());
+        config.whatsapp_outbound_url = Some(format!("http://{relay_addr}/whatsapp"));
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let webhook_url = format!("http://{}/channels/whatsapp/webhook", server.addr);
+
+    let inbound: Value = client
+        .post(&webhook_url)
+        .bearer_auth("whatsapp-token")
+        .json(&json!({
+            "entry": [{ "changes": [{ "value": { "messages": [{
+                "id": "wamid.inbound-1",
+                "from": "15550001111",
+                "text": { "body": "track me" }
+            }] } }] }]
+        }))
+        .send()
+        .await
+        .expect("whatsapp webhook should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(inbound["outboundSent"], true);
+    let run_id = inbound["runId"].as_str().expect("run id").to_owned();
+    let session_key = inbound["sessionKey"].as_str().expect("session").to_owned();
+
+    let relayed = timeout(std::time::Duration::from_secs(2), relay_rx.recv())
+        .await
+        .expect("relay request should arrive")
+        .expect("relay payload should exist");
+    let delivery_id = relayed["deliveryId"]
+        .as_str()
+        .expect("relay payload should carry deliveryId")
+        .to_owned();
+
+    let delivered: Value = client
+        .post(format!("http://{}/channels/whatsapp/receipts", server.addr))
+        .json(&json!({ "deliveryId": delivery_id, "status": "delivered" }))
+        .send()
+        .await
+        .expect("receipt should return")
+        .json()
+        .await
+        .expect("receipt response should be json");
+    assert_eq!(delivered["status"], "delivered");
+
+    let statuses: Value = client
+        .post(&webhook_url)
+        .bearer_auth("whatsapp-token")
+        .json(&json!({
+            "entry": [{ "changes": [{ "value": { "statuses": [
+                { "id": "wamid.reply-1", "status": "read" },
+                { "id": "wamid.reply-1", "status": "sent" }
+            ] } }] }]
+        }))
+        .send()
+        .await
+        .expect("status webhook should return")
+        .json()
+        .await
+        .expect("status response should be json");
+    assert_eq!(statuses["receipts"], 2);
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let _ = recv_json(&mut ws).await;
+
+    let status = rpc_req(
+        &mut ws,
+        "delivery-1",
+        "chat.deliveryStatus",
+        Some(json!({ "runId": run_id })),
+    )
+    .await;
+    let delivery = &status["payload"]["deliveries"][0];
+    assert_eq!(delivery["id"], delivery_id);
+    assert_eq!(delivery["status"], "read");
+    assert_eq!(delivery["platformMessageId"], "wamid.reply-1");
+    assert!(delivery["sentAtMs"].is_u64());
+    assert!(delivery["readAtMs"].is_u64());
+
+    let history = rpc_req(
+        &mut ws,
+        "history-1",
+        "chat.history",
+        Some(json!({ "sessionKey": session_key })),
+    )
+    .await;
+    let messages = history["payload"]["messages"]
+        .as_array()
+        .expect("messages should be an array");
+    let reply = messages
+        .iter()
+        .find(|message| message["role"] == "assistant")
+        .expect("assistant reply should exist");
+    assert_eq!(reply["delivery"]["status"], "read");
+    assert!(
+        messages
+            .iter()
+            .filter(|message| message["role"] == "user")
+            .all(|message| message.get("delivery").is_none())
+    );
+
+    relay_join.abort();
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn webhook_source_ranges_reject_spoofed_callers_and_refresh_published_lists() {
+    let ranges_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("ranges listener should bind");
+    let ranges_addr = ranges_listener
+        .local_addr()
+        .expect("ranges listener should expose addr");
+    let ranges_router = Router::new().route(
+        "/ranges.txt",
+        axum::routing::get(|| async { "# published\n203.0.113.0/24\n" }),
+    );
+    let ranges_join = tokio::spawn(async move {
+        axum::serve(ranges_listener, ranges_router)
+            .await
+            .expect("ranges server should run");
+    });
+
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.signal_webhook_token = Some("signal-token".to_owned());
+        config.webhook_sources.insert(
+            "signal".to_owned(),
+            WebhookSourceRule {
+                cidrs: vec!["10.0.0.0/8".parse().expect("cidr should parse")],
+                ranges_url: Some(format!("http://{ranges_addr}/ranges.txt")),
+            },
+        );
+        config.webhook_trusted_proxies = vec!["127.0.0.1".parse().expect("cidr should parse")];
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let post_signal = |forwarded: Option<&'static str>| {
+        let mut request = client
+            .post(format!("http://{}/channels/signal/webhook", server.addr))
+            .bearer_auth("signal-token")
+            .json(&json!({
+                "envelope": {
+                    "sourceNumber": "+123456789",
+                    "timestamp": 1700000000,
+                    "dataMessage": { "message": "hello from signal" }
+                }
+            }));
+        if let Some(forwarded) = forwarded {
+            request = request.header("x-forwarded-for", forwarded);
+        }
+        request.send()
+    };
+
+    let direct = post_signal(None).await.expect("webhook should respond");
+    assert_eq!(direct.status(), reqwest::StatusCode::FORBIDDEN);
+    let spoofed = post_signal(Some("198.51.100.7"))
+        .await
+        .expect("webhook should respond");
+    assert_eq!(spoofed.status(), reqwest::StatusCode::FORBIDDEN);
+    let body = spoofed.json::<Value>().await.expect("body should be json");
+    assert_eq!(body["error"]["code"], "FORBIDDEN");
+
+    let static_range = post_signal(Some("10.20.30.40"))
+        .await
+        .expect("webhook should respond");
+    assert!(static_range.status().is_success());
+
+    let mut published_allowed = false;
+    for _ in 0..50 {
+        let response = post_signal(Some("198.51.100.7, 203.0.113.5"))
+            .await
+            .expect("webhook should respond");
+        if response.status().is_success() {
+            published_allowed = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(published_allowed, "published ranges should be fetched");
+
+    let unguarded = client
+        .post(format!("http://{}/channels/discord/webhook", server.addr))
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("webhook should respond");
+    assert_ne!(unguarded.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let _ = recv_json(&mut ws).await;
+    let logs = rpc_req(
+        &mut ws,
+        "logs-1",
+        "logs.tail",
+        Some(json!({ "level": "warn", "limit": 20 })),
+    )
+    .await;
+    let rejected = logs["payload"]["entries"]
+        .as_array()
+        .expect("log entries should be an array")
+        .iter()
+        .filter(|entry| {
+            entry["message"]
+                .as_str()
+                .is_some_and(|message| message.starts_with("rejected signal webhook from"))
+        })
+        .count();
+    assert!(rejected >= 2);
+
+    ranges_join.abort();
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn inbound_attachments_are_scanned_before_storage() {
+    let attachment_scan = |action| {
+        AttachmentScan::compile(AttachmentScanConfig {
+            scanner: Some(AttachmentScannerKind::Command),
+            command: vec![
+                "sh".to_owned(),
+                "-c".to_owned(),
+                "grep -q EICAR \"$0\" && echo \"$0: Eicar-Test-Signature FOUND\" && exit 1; exit 0"
+                    .to_owned(),
+            ],
+            action,
+            ..AttachmentScanConfig::default()
+        })
+        .expect("attachment scan config should compile")
+    };
+    let mut attachments_dir = None;
+    let server = spawn_server_with(AuthMode::None, |config| {
+        attachments_dir = config.db_path.parent().map(|dir| dir.join("attachments"));
+        config.attachment_scan = Some(attachment_scan(AttachmentScanAction::Quarantine));
+    })
+    .await;
+    let attachments_dir = attachments_dir.expect("db path should have a parent");
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("http://{}/channels/inbound", server.addr))
+        .json(&json!({
+            "channel": "telegram",
+            "conversationId": "scan-1",
+            "text": "see attached",
+            "attachments": [
+                { "name": "notes.txt", "mimeType": "text/plain", "data": "aGVsbG8=" },
+                { "name": "invoice.txt", "data": "WDVPIEVJQ0FSIHRlc3Q=" },
+            ],
+        }))
+        .send()
+        .await
+        .expect("inbound request should return");
+    assert!(response.status().is_success());
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let _ = recv_json(&mut ws).await;
+    let history = rpc_req(
+        &mut ws,
+        "history",
+        "chat.history",
+        Some(json!({ "sessionKey": "agent:main:telegram:chat:scan-1", "limit": 10 })),
+    )
+    .await;
+    let messages = history["payload"]["messages"]
+        .as_array()
+        .expect("history should list messages");
+    let attachments = messages
+        .iter()
+        .find(|message| message["role"] == "user")
+        .and_then(|message| message["metadata"]["attachments"].as_array())
+        .expect("user message should carry attachment records");
+    assert_eq!(attachments.len(), 2);
+    assert_eq!(attachments[0]["status"], "stored");
+    assert_eq!(attachments[0]["scan"]["status"], "clean");
+    assert_eq!(attachments[0]["size"], 5);
+    assert_eq!(attachments[1]["status"], "quarantined");
+    assert_eq!(attachments[1]["scan"]["status"], "detected");
+    assert_eq!(attachments[1]["scan"]["signature"], "Eicar-Test-Signature");
+    assert_eq!(attachments[1]["scan"]["action"], "quarantine");
+    let quarantined = attachments[1]["id"].as_str().expect("attachment id");
+    assert!(
+        attachments_dir
+            .join("quarantine")
+            .join(quarantined)
+            .is_file()
+    );
+    server.stop().await;
+
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.attachment_scan = Some(attachment_scan(AttachmentScanAction::Reject));
+    })
+    .await;
+    let response = client
+        .post(format!("http://{}/channels/inbound", server.addr))
+        .json(&json!({
+            "channel": "telegram",
+            "conversationId": "scan-2",
+            "text": "run this",
+            "attachments": [{ "name": "setup.exe", "data": "TVqQAA==" }],
+        }))
+        .send()
+        .await
+        .expect("inbound request should return");
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let payload: Value = response.json().await.expect("response should be json");
+    assert!(
+        payload["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("blocked MIME type application/x-msdownload"))
+    );
+    server.stop().await;
+}
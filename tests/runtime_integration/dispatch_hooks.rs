@@ -0,0 +1,176 @@
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use reclaw_core::{
+    application::{
+        config::RuntimeConfig,
+        lifecycle::{self, MaintenanceWindow},
+        state::SharedState,
+    },
+    protocol::{ERROR_INVALID_REQUEST, ErrorShape, RequestFrame, ResponseFrame},
+    rpc::{
+        SessionContext,
+        dispatcher::dispatch_request,
+        methods,
+        middleware::{DispatchHook, DispatchHookFuture},
+        policy,
+    },
+};
+
+struct AliasHook;
+
+impl DispatchHook for AliasHook {
+    fn name(&self) -> &str {
+        "alias"
+    }
+
+    fn pre_dispatch<'a>(
+        &'a self,
+        _state: &'a SharedState,
+        _session: &'a mut SessionContext,
+        request: &'a mut RequestFrame,
+    ) -> DispatchHookFuture<'a, Result<(), ErrorShape>> {
+        Box::pin(async move {
+            match request.method.as_str() {
+                "ping" => request.method = "health".to_owned(),
+                "nudge" => request.method = "send".to_owned(),
+                _ => {}
+            }
+            Ok(())
+        })
+    }
+}
+
+struct DenyConfigHook;
+
+impl DispatchHook for DenyConfigHook {
+    fn name(&self) -> &str {
+        "deny-config"
+    }
+
+    fn priority(&self) -> i32 {
+        -10
+    }
+
+    fn pre_dispatch<'a>(
+        &'a self,
+        _state: &'a SharedState,
+        _session: &'a mut SessionContext,
+        request: &'a mut RequestFrame,
+    ) -> DispatchHookFuture<'a, Result<(), ErrorShape>> {
+        Box::pin(async move {
+            if request.method.starts_with("config.") {
+                return Err(ErrorShape::new(
+                    ERROR_INVALID_REQUEST,
+                    "tenant may not touch config",
+                ));
+            }
+            Ok(())
+        })
+    }
+}
+
+struct CountingHook {
+    responses: Arc<AtomicUsize>,
+}
+
+impl DispatchHook for CountingHook {
+    fn name(&self) -> &str {
+        "metrics"
+    }
+
+    fn post_dispatch<'a>(
+        &'a self,
+        _state: &'a SharedState,
+        _session: &'a SessionContext,
+        _request: &'a RequestFrame,
+        _response: &'a mut ResponseFrame,
+    ) -> DispatchHookFuture<'a, ()> {
+        Box::pin(async move {
+            self.responses.fetch_add(1, Ordering::Relaxed);
+        })
+    }
+}
+
+fn request(id: &str, method: &str) -> RequestFrame {
+    RequestFrame {
+        frame_type: "req".to_owned(),
+        id: id.to_owned(),
+        method: method.to_owned(),
+        params: None,
+        timeout_ms: None,
+    }
+}
+
+#[tokio::test]
+async fn dispatch_hooks_rewrite_short_circuit_and_observe_responses() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let config = RuntimeConfig::for_test(
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        0,
+        temp_dir.path().join("reclaw.db"),
+    );
+    let state = SharedState::new(
+        config,
+        methods::implemented_methods(),
+        methods::known_events(),
+    )
+    .await
+    .expect("shared state should build");
+
+    let responses = Arc::new(AtomicUsize::new(0));
+    state
+        .register_dispatch_hook(Arc::new(CountingHook {
+            responses: Arc::clone(&responses),
+        }))
+        .await;
+    state.register_dispatch_hook(Arc::new(AliasHook)).await;
+    state.register_dispatch_hook(Arc::new(DenyConfigHook)).await;
+    assert_eq!(
+        state.dispatch_hooks().await.names(),
+        vec!["deny-config", "metrics", "alias"]
+    );
+
+    let session = SessionContext {
+        conn_id: "conn-hooks".to_owned(),
+        role: "operator".to_owned(),
+        scopes: policy::default_operator_scopes(),
+        client_id: "embedder".to_owned(),
+        client_mode: "test".to_owned(),
+    };
+
+    let health = dispatch_request(&state, &session, &request("r-1", "ping")).await;
+    assert!(health.ok);
+
+    let denied = dispatch_request(&state, &session, &request("r-2", "config.get")).await;
+    assert!(!denied.ok);
+    assert_eq!(
+        denied.error.map(|error| error.message),
+        Some("tenant may not touch config".to_owned())
+    );
+
+    assert_eq!(responses.load(Ordering::Relaxed), 2);
+
+    // Gates apply to the method a hook rewrote the request to.
+    lifecycle::set_maintenance(
+        &state,
+        Some(MaintenanceWindow {
+            reason: Some("upgrade".to_owned()),
+            since_ms: 0,
+            until_ms: None,
+        }),
+    )
+    .await;
+    let gated = dispatch_request(&state, &session, &request("r-3", "nudge")).await;
+    assert!(!gated.ok);
+    assert!(
+        gated
+            .error
+            .is_some_and(|error| error.message.starts_with("send is unavailable"))
+    );
+}
@@ -0,0 +1,1240 @@
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use futures_util::SinkExt;
+use reclaw_core::{
+    application::{
+        agent_backend::{AgentBackend, AgentBackendFuture, AgentDeltaSender, AgentTurn},
+        chat_commands::{ChatCommand, ChatCommandFuture, ChatCommandInvocation, ChatCommands},
+        config::{
+            ChatCommandsConfig, ChatCommandsRuleConfig, HookDispatchLimits, HookOverflowAction,
+            RuntimeConfig,
+        },
+        server::{ServerBuilder, ServerHandle},
+        state::SharedState,
+        translator::{Translation, TranslationRequest, Translator, TranslatorFuture},
+    },
+    protocol::{ERROR_UNAVAILABLE, PROTOCOL_VERSION},
+};
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::support::{connect_frame, connect_gateway, recv_json, rpc_req};
+
+struct ShoutBackend;
+
+impl AgentBackend for ShoutBackend {
+    fn name(&self) -> &str {
+        "shout"
+    }
+
+    fn respond<'a>(
+        &'a self,
+        turn: AgentTurn<'a>,
+    ) -> AgentBackendFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            if turn.input == "fail" {
+                return Err("refused".to_owned());
+            }
+            Ok(format!("{}:{}", turn.agent_id, turn.input.to_uppercase()))
+        })
+    }
+}
+
+/// Replies `pong <args>` in the invocation's language.
+struct PingCommand;
+
+impl ChatCommand for PingCommand {
+    fn name(&self) -> &str {
+        "ping"
+    }
+
+    fn help(&self, locale: &str) -> String {
+        if locale.starts_with("de") {
+            "/ping – antwortet mit pong".to_owned()
+        } else {
+            "/ping – replies with pong".to_owned()
+        }
+    }
+
+    fn run<'a>(
+        &'a self,
+        _state: &'a SharedState,
+        invocation: ChatCommandInvocation<'a>,
+    ) -> ChatCommandFuture<'a> {
+        Box::pin(async move {
+            Ok(format!(
+                "pong {} from {}",
+                invocation.args, invocation.conversation.channel
+            ))
+        })
+    }
+}
+
+/// Fails `flaky` turns until two calls have failed, `down` turns while `down` is set, and stalls
+/// `slow` turns.
+#[derive(Default)]
+struct UnreliableBackend {
+    flaky_failures: AtomicUsize,
+    down: AtomicBool,
+}
+
+impl AgentBackend for UnreliableBackend {
+    fn name(&self) -> &str {
+        "unreliable"
+    }
+
+    fn respond<'a>(
+        &'a self,
+        turn: AgentTurn<'a>,
+    ) -> AgentBackendFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            match turn.input {
+                "flaky" if self.flaky_failures.fetch_add(1, Ordering::SeqCst) < 2 => {
+                    Err("overloaded".to_owned())
+                }
+                "down" if self.down.load(Ordering::SeqCst) => Err("offline".to_owned()),
+                "slow" => {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok("late".to_owned())
+                }
+                input => Ok(format!("ok:{input}")),
+            }
+        })
+    }
+}
+
+/// Holds every turn until the test adds permits to `gate`.
+struct GatedBackend {
+    gate: Arc<tokio::sync::Semaphore>,
+}
+
+impl AgentBackend for GatedBackend {
+    fn name(&self) -> &str {
+        "gated"
+    }
+
+    fn respond<'a>(
+        &'a self,
+        turn: AgentTurn<'a>,
+    ) -> AgentBackendFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            self.gate
+                .acquire()
+                .await
+                .map_err(|error| error.to_string())?
+                .forget();
+            Ok(format!("done:{}", turn.input))
+        })
+    }
+}
+
+/// Streams `one ` and then `two `, waiting for a permit on `gate` after each chunk.
+struct SteppedBackend {
+    gate: Arc<tokio::sync::Semaphore>,
+}
+
+impl AgentBackend for SteppedBackend {
+    fn name(&self) -> &str {
+        "stepped"
+    }
+
+    fn respond<'a>(
+        &'a self,
+        _turn: AgentTurn<'a>,
+    ) -> AgentBackendFuture<'a, Result<String, String>> {
+        Box::pin(async move { Ok("one two ".to_owned()) })
+    }
+
+    fn respond_streaming<'a>(
+        &'a self,
+        _turn: AgentTurn<'a>,
+        deltas: AgentDeltaSender,
+    ) -> AgentBackendFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            for chunk in ["one ", "two "] {
+                let _ = deltas.send(chunk.to_owned());
+                self.gate
+                    .acquire()
+                    .await
+                    .map_err(|error| error.to_string())?
+                    .forget();
+            }
+            Ok("one two ".to_owned())
+        })
+    }
+}
+
+/// Replies with the session scratchpad it was handed.
+struct ScratchpadBackend;
+
+impl AgentBackend for ScratchpadBackend {
+    fn name(&self) -> &str {
+        "scratchpad"
+    }
+
+    fn respond<'a>(
+        &'a self,
+        turn: AgentTurn<'a>,
+    ) -> AgentBackendFuture<'a, Result<String, String>> {
+        Box::pin(async move { Ok(Value::Object(turn.scratchpad.clone()).to_string()) })
+    }
+}
+
+/// Treats text starting with `hallo` as German; translating into English swaps that word, into
+/// any other language prefixes the target language.
+struct PhraseTranslator;
+
+impl Translator for PhraseTranslator {
+    fn name(&self) -> &str {
+        "phrases"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        request: TranslationRequest<'a>,
+    ) -> TranslatorFuture<'a, Result<Translation, String>> {
+        Box::pin(async move {
+            let source_language = request.source_language.map_or_else(
+                || {
+                    if request.text.starts_with("hallo") {
+                        "de".to_owned()
+                    } else {
+                        "en".to_owned()
+                    }
+                },
+                str::to_owned,
+            );
+            let text = if request.target_language == "en" {
+                request.text.replace("hallo", "hello")
+            } else {
+                format!("[{}] {}", request.target_language, request.text)
+            };
+            Ok(Translation {
+                text,
+                source_language,
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn server_builder_runs_with_custom_agent_backend_and_stops() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("listener should bind");
+    let config = RuntimeConfig::for_test(
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        0,
+        temp_dir.path().join("unused.db"),
+    );
+
+    let handle = ServerBuilder::new(config)
+        .db_path(temp_dir.path().join("embedded.db"))
+        .listener(listener)
+        .agent_backend(Arc::new(ShoutBackend))
+        .start()
+        .await
+        .expect("server should start");
+    assert!(temp_dir.path().join("embedded.db").exists());
+    assert_eq!(handle.state().agent_backend().await.name(), "shout");
+
+    let mut ws = connect_gateway(handle.local_addr()).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "embedder", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+    assert_eq!(handle.connection_count().await, 1);
+
+    let sent = rpc_req(
+        &mut ws,
+        "chat-1",
+        "chat.send",
+        Some(json!({ "sessionKey": "agent:main:embedded", "message": "hello" })),
+    )
+    .await;
+    assert_eq!(sent["ok"], true);
+    assert_eq!(sent["payload"]["message"], "main:HELLO");
+
+    let failed = rpc_req(
+        &mut ws,
+        "chat-2",
+        "chat.send",
+        Some(json!({ "sessionKey": "agent:main:embedded", "message": "fail" })),
+    )
+    .await;
+    assert_eq!(failed["ok"], false);
+    assert_eq!(failed["error"]["code"], ERROR_UNAVAILABLE);
+    assert_eq!(
+        failed["error"]["message"],
+        "agent backend shout failed: refused"
+    );
+
+    let health = handle.health().await.expect("health should load");
+    assert_eq!(health["ok"], true);
+
+    drop(ws);
+    handle.stop().await.expect("server should stop cleanly");
+}
+
+#[tokio::test]
+async fn agent_replay_reruns_recorded_context_and_diffs_outputs() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("listener should bind");
+    let config = RuntimeConfig::for_test(
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        0,
+        temp_dir.path().join("reclaw.db"),
+    );
+    let handle = ServerBuilder::new(config)
+        .listener(listener)
+        .agent_backend(Arc::new(ShoutBackend))
+        .replay_backend(Arc::new(UnreliableBackend::default()))
+        .start()
+        .await
+        .expect("server should start");
+
+    let mut ws = connect_gateway(handle.local_addr()).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "replay-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+
+    let run = rpc_req(
+        &mut ws,
+        "run",
+        "agent",
+        Some(json!({ "runId": "run-replay", "input": "hello" })),
+    )
+    .await;
+    assert_eq!(run["payload"]["result"]["output"], "main:HELLO");
+
+    let same = rpc_req(
+        &mut ws,
+        "replay-same",
+        "agent.replay",
+        Some(json!({ "runId": "run-replay" })),
+    )
+    .await;
+    assert_eq!(same["ok"], true);
+    assert_eq!(same["payload"]["identical"], true);
+    assert_eq!(same["payload"]["backend"]["recorded"], "shout");
+    assert_eq!(same["payload"]["context"]["identity"]["agentId"], "main");
+    assert_eq!(same["payload"]["context"]["input"], "hello");
+    assert_eq!(same["payload"]["configHash"]["changed"], false);
+
+    let patched = rpc_req(
+        &mut ws,
+        "config",
+        "config.patch",
+        Some(json!({ "patch": { "ui": { "theme": "dark" } } })),
+    )
+    .await;
+    assert_eq!(patched["ok"], true);
+
+    let other = rpc_req(
+        &mut ws,
+        "replay-other",
+        "agent.replay",
+        Some(json!({ "runId": "run-replay", "backend": "unreliable" })),
+    )
+    .await;
+    assert_eq!(other["payload"]["replay"]["output"], "ok:hello");
+    assert_eq!(other["payload"]["identical"], false);
+    assert_eq!(other["payload"]["configHash"]["changed"], true);
+    assert_eq!(
+        other["payload"]["diff"],
+        json!([
+            { "op": "delete", "lines": ["main:HELLO"] },
+            { "op": "insert", "lines": ["ok:hello"] },
+        ])
+    );
+
+    let chat = rpc_req(
+        &mut ws,
+        "chat",
+        "chat.send",
+        Some(json!({
+            "sessionKey": "agent:main:replay",
+            "message": "hi",
+            "idempotencyKey": "chat-replay",
+        })),
+    )
+    .await;
+    assert_eq!(chat["ok"], true);
+    let echoed = rpc_req(
+        &mut ws,
+        "replay-chat",
+        "agent.replay",
+        Some(json!({ "runId": "chat-replay", "backend": "echo" })),
+    )
+    .await;
+    assert_eq!(echoed["payload"]["original"]["output"], "main:HI");
+    assert_eq!(echoed["payload"]["replay"]["output"], "Echo: hi");
+
+    let unknown = rpc_req(
+        &mut ws,
+        "replay-unknown",
+        "agent.replay",
+        Some(json!({ "runId": "run-replay", "backend": "missing" })),
+    )
+    .await;
+    assert_eq!(unknown["ok"], false);
+    assert!(
+        unknown["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("unknown backend"))
+    );
+
+    let history = rpc_req(
+        &mut ws,
+        "history",
+        "chat.history",
+        Some(json!({ "sessionKey": "agent:main:main" })),
+    )
+    .await;
+    assert_eq!(
+        history["payload"]["messages"].as_array().map(Vec::len),
+        Some(2)
+    );
+
+    drop(ws);
+    handle.stop().await.expect("server should stop cleanly");
+}
+
+#[tokio::test]
+async fn failed_agent_runs_follow_retry_policy_and_can_be_retried() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("listener should bind");
+    let config = RuntimeConfig::for_test(
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        0,
+        temp_dir.path().join("reclaw.db"),
+    );
+    let backend = Arc::new(UnreliableBackend::default());
+    backend.down.store(true, Ordering::SeqCst);
+    let handle = ServerBuilder::new(config)
+        .listener(listener)
+        .agent_backend(backend.clone())
+        .start()
+        .await
+        .expect("server should start");
+
+    let mut ws = connect_gateway(handle.local_addr()).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "retry-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+
+    let invalid = rpc_req(
+        &mut ws,
+        "policy-bad",
+        "agents.update",
+        Some(json!({ "agentId": "main", "retryPolicy": { "retryOn": ["gremlins"] } })),
+    )
+    .await;
+    assert_eq!(invalid["ok"], false);
+
+    let updated = rpc_req(
+        &mut ws,
+        "policy",
+        "agents.update",
+        Some(json!({
+            "agentId": "main",
+            "retryPolicy": {
+                "maxAttempts": 3,
+                "backoffMs": 10,
+                "retryOn": ["backendError"],
+                "timeoutMs": 100,
+            },
+        })),
+    )
+    .await;
+    assert_eq!(updated["ok"], true);
+    let listed = rpc_req(&mut ws, "list", "agents.list", None).await;
+    assert_eq!(
+        listed["payload"]["agents"][0]["retryPolicy"]["maxAttempts"],
+        3
+    );
+
+    let flaky = rpc_req(
+        &mut ws,
+        "flaky",
+        "agent",
+        Some(json!({ "runId": "run-flaky", "input": "flaky" })),
+    )
+    .await;
+    assert_eq!(flaky["ok"], true, "{flaky}");
+    assert_eq!(flaky["payload"]["result"]["output"], "ok:flaky");
+    let waited = rpc_req(
+        &mut ws,
+        "flaky-wait",
+        "agent.wait",
+        Some(json!({ "runId": "run-flaky" })),
+    )
+    .await;
+    let attempts = waited["payload"]["attempts"]
+        .as_array()
+        .expect("attempt history should be recorded");
+    assert_eq!(attempts.len(), 3);
+    assert_eq!(attempts[0]["trigger"], "initial");
+    assert_eq!(attempts[0]["errorClass"], "backendError");
+    assert_eq!(attempts[1]["trigger"], "auto");
+    assert_eq!(attempts[2]["status"], "completed");
+
+    let slow = rpc_req(
+        &mut ws,
+        "slow",
+        "agent",
+        Some(json!({ "runId": "run-slow", "input": "slow" })),
+    )
+    .await;
+    assert_eq!(slow["ok"], false);
+    let waited = rpc_req(
+        &mut ws,
+        "slow-wait",
+        "agent.wait",
+        Some(json!({ "runId": "run-slow" })),
+    )
+    .await;
+    assert_eq!(waited["payload"]["status"], "error");
+    assert_eq!(
+        waited["payload"]["attempts"][0]["errorClass"], "timeout",
+        "timeouts are not in retryOn, so only one attempt runs"
+    );
+    assert_eq!(
+        waited["payload"]["attempts"].as_array().map(Vec::len),
+        Some(1)
+    );
+
+    let down = rpc_req(
+        &mut ws,
+        "down",
+        "agent",
+        Some(json!({ "runId": "run-down", "input": "down" })),
+    )
+    .await;
+    assert_eq!(down["ok"], false);
+    assert_eq!(
+        down["error"]["message"],
+        "agent backend unreliable failed: offline (after 3 attempts)"
+    );
+
+    let not_failed = rpc_req(
+        &mut ws,
+        "retry-done",
+        "agent.retry",
+        Some(json!({ "runId": "run-flaky" })),
+    )
+    .await;
+    assert_eq!(not_failed["ok"], false);
+
+    backend.down.store(false, Ordering::SeqCst);
+    let retried = rpc_req(
+        &mut ws,
+        "retry",
+        "agent.retry",
+        Some(json!({ "runId": "run-down" })),
+    )
+    .await;
+    assert_eq!(retried["ok"], true, "{retried}");
+    assert_eq!(retried["payload"]["result"]["output"], "ok:down");
+    let attempts = retried["payload"]["attempts"]
+        .as_array()
+        .expect("attempt history should be returned");
+    assert_eq!(attempts.len(), 4);
+    assert_eq!(attempts[3]["trigger"], "manual");
+    assert_eq!(attempts[3]["status"], "completed");
+
+    drop(ws);
+    handle.stop().await.expect("server should stop cleanly");
+}
+
+#[tokio::test]
+async fn chat_turns_in_foreign_languages_are_translated_both_ways() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("listener should bind");
+    let config = RuntimeConfig::for_test(
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        0,
+        temp_dir.path().join("translated.db"),
+    );
+    let handle = ServerBuilder::new(config)
+        .listener(listener)
+        .agent_backend(Arc::new(ShoutBackend))
+        .translator(Arc::new(PhraseTranslator))
+        .start()
+        .await
+        .expect("server should start");
+
+    let mut ws = connect_gateway(handle.local_addr()).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "embedder", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+
+    let german = rpc_req(
+        &mut ws,
+        "chat-de",
+        "chat.send",
+        Some(json!({ "sessionKey": "agent:main:intl", "message": "hallo welt" })),
+    )
+    .await;
+    assert_eq!(german["ok"], true);
+    assert_eq!(german["payload"]["message"], "[de] main:HELLO WELT");
+
+    let english = rpc_req(
+        &mut ws,
+        "chat-en",
+        "chat.send",
+        Some(json!({ "sessionKey": "agent:main:intl", "message": "status" })),
+    )
+    .await;
+    assert_eq!(english["payload"]["message"], "main:STATUS");
+
+    let history = rpc_req(
+        &mut ws,
+        "history-1",
+        "chat.history",
+        Some(json!({ "sessionKey": "agent:main:intl" })),
+    )
+    .await;
+    let messages = history["payload"]["messages"]
+        .as_array()
+        .expect("history should list messages");
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[0]["text"], "hello welt");
+    assert_eq!(
+        messages[0]["metadata"]["translation"],
+        json!({
+            "provider": "phrases",
+            "originalText": "hallo welt",
+            "sourceLanguage": "de",
+            "targetLanguage": "en",
+        })
+    );
+    assert_eq!(messages[1]["text"], "[de] main:HELLO WELT");
+    assert_eq!(
+        messages[1]["metadata"]["translation"]["originalText"],
+        "main:HELLO WELT"
+    );
+    assert_eq!(
+        messages[1]["metadata"]["translation"]["targetLanguage"],
+        "de"
+    );
+    assert!(messages[2]["metadata"].get("translation").is_none());
+    assert!(messages[3]["metadata"].get("translation").is_none());
+
+    drop(ws);
+    handle.stop().await.expect("server should stop cleanly");
+}
+
+#[tokio::test]
+async fn hook_agent_dispatches_queue_behind_the_concurrency_limit() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("listener should bind");
+    let mut config = RuntimeConfig::for_test(
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        0,
+        temp_dir.path().join("reclaw.db"),
+    );
+    config.hooks_enabled = true;
+    config.hooks_token = Some("hooks-token".to_owned());
+    config.hooks_dispatch = HookDispatchLimits {
+        max_concurrency: 1,
+        max_queue_depth: 1,
+        overflow: HookOverflowAction::Enqueue,
+    };
+    let gate = Arc::new(tokio::sync::Semaphore::new(0));
+    let handle = ServerBuilder::new(config)
+        .listener(listener)
+        .agent_backend(Arc::new(GatedBackend { gate: gate.clone() }))
+        .start()
+        .await
+        .expect("server should start");
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/hooks/agent", handle.local_addr());
+    let post = |message: &'static str| {
+        let request = client
+            .post(&url)
+            .bearer_auth("hooks-token")
+            .json(&json!({ "message": message }));
+        tokio::spawn(async move { request.send().await })
+    };
+    async fn wait_for_dispatch(handle: &ServerHandle, field: &str, expected: u64) -> Value {
+        for _ in 0..100 {
+            let health = handle.health().await.expect("health should load");
+            if health["hooksDispatch"][field] == expected {
+                return health;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("hooksDispatch.{field} never reached {expected}");
+    }
+
+    let running = post("first");
+    wait_for_dispatch(&handle, "active", 1).await;
+    let waiting = post("second");
+    wait_for_dispatch(&handle, "queued", 1).await;
+
+    let rejected = post("third")
+        .await
+        .expect("request task should finish")
+        .expect("hooks request should return");
+    assert_eq!(rejected.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    let health = wait_for_dispatch(&handle, "rejected", 1).await;
+    assert_eq!(health["hooksDispatch"]["overflow"], "enqueue");
+    assert_eq!(health["hooksDispatch"]["maxConcurrency"], 1);
+
+    gate.add_permits(2);
+    for request in [running, waiting] {
+        let response = request
+            .await
+            .expect("request task should finish")
+            .expect("hooks request should return");
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+    }
+    let health = wait_for_dispatch(&handle, "active", 0).await;
+    assert_eq!(health["hooksDispatch"]["queued"], 0);
+
+    handle.stop().await.expect("server should stop cleanly");
+}
+
+#[tokio::test]
+async fn session_scratchpad_is_limited_and_reaches_the_agent_backend() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("listener should bind");
+    let config = RuntimeConfig::for_test(
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        0,
+        temp_dir.path().join("reclaw.db"),
+    );
+    let handle = ServerBuilder::new(config)
+        .listener(listener)
+        .agent_backend(Arc::new(ScratchpadBackend))
+        .start()
+        .await
+        .expect("server should start");
+
+    let mut ws = connect_gateway(handle.local_addr()).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "kv-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+
+    let session = "agent:main:kv";
+    let set = rpc_req(
+        &mut ws,
+        "set-count",
+        "session.kv.set",
+        Some(json!({ "sessionKey": session, "key": "count", "value": 3 })),
+    )
+    .await;
+    assert_eq!(set["ok"], true, "{set}");
+    assert_eq!(set["payload"]["entry"]["sizeBytes"], 1);
+    assert!(set["payload"]["entry"]["expiresAtMs"].is_u64());
+    let set = rpc_req(
+        &mut ws,
+        "set-followup",
+        "session.kv.set",
+        Some(json!({
+            "sessionKey": session,
+            "key": "followUp",
+            "value": { "due": "friday" },
+            "ttlMs": 60_000,
+        })),
+    )
+    .await;
+    assert_eq!(set["ok"], true, "{set}");
+
+    let one = rpc_req(
+        &mut ws,
+        "get-one",
+        "session.kv.get",
+        Some(json!({ "sessionKey": session, "key": "count" })),
+    )
+    .await;
+    assert_eq!(one["payload"]["found"], true);
+    assert_eq!(one["payload"]["entry"]["value"], 3);
+    let all = rpc_req(
+        &mut ws,
+        "get-all",
+        "session.kv.get",
+        Some(json!({ "sessionKey": session })),
+    )
+    .await;
+    assert_eq!(all["payload"]["count"], 2);
+    assert_eq!(all["payload"]["entries"][0]["key"], "count");
+    assert_eq!(all["payload"]["limits"]["maxKeys"], 64);
+
+    let too_big = rpc_req(
+        &mut ws,
+        "too-big",
+        "session.kv.set",
+        Some(json!({ "sessionKey": session, "key": "blob", "value": "x".repeat(5_000) })),
+    )
+    .await;
+    assert_eq!(too_big["ok"], false);
+    assert_eq!(too_big["error"]["code"], "INVALID_REQUEST");
+    let bad_ttl = rpc_req(
+        &mut ws,
+        "bad-ttl",
+        "session.kv.set",
+        Some(json!({ "sessionKey": session, "key": "x", "value": 1, "ttlMs": 0 })),
+    )
+    .await;
+    assert_eq!(bad_ttl["ok"], false);
+    for index in 0..62 {
+        let filled = rpc_req(
+            &mut ws,
+            "fill",
+            "session.kv.set",
+            Some(json!({ "sessionKey": session, "key": format!("k{index}"), "value": index })),
+        )
+        .await;
+        assert_eq!(filled["ok"], true, "{filled}");
+    }
+    let over = rpc_req(
+        &mut ws,
+        "over",
+        "session.kv.set",
+        Some(json!({ "sessionKey": session, "key": "one-more", "value": true })),
+    )
+    .await;
+    assert_eq!(over["ok"], false);
+    assert!(
+        over["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("64 keys")),
+        "{over}"
+    );
+    for index in 0..62 {
+        let deleted = rpc_req(
+            &mut ws,
+            "drain",
+            "session.kv.delete",
+            Some(json!({ "sessionKey": session, "key": format!("k{index}") })),
+        )
+        .await;
+        assert_eq!(deleted["payload"]["deleted"], true);
+    }
+    let missing = rpc_req(
+        &mut ws,
+        "delete-missing",
+        "session.kv.delete",
+        Some(json!({ "sessionKey": session, "key": "k0" })),
+    )
+    .await;
+    assert_eq!(missing["payload"]["deleted"], false);
+
+    let run = rpc_req(
+        &mut ws,
+        "run",
+        "agent",
+        Some(json!({ "runId": "run-kv", "sessionKey": session, "input": "hi" })),
+    )
+    .await;
+    assert_eq!(run["ok"], true, "{run}");
+    let seen: Value = serde_json::from_str(
+        run["payload"]["result"]["output"]
+            .as_str()
+            .expect("output should be text"),
+    )
+    .expect("output should be the scratchpad");
+    assert_eq!(seen, json!({ "count": 3, "followUp": { "due": "friday" } }));
+
+    let other = rpc_req(
+        &mut ws,
+        "other-session",
+        "session.kv.get",
+        Some(json!({ "sessionKey": "agent:main:other", "key": "count" })),
+    )
+    .await;
+    assert_eq!(other["payload"]["found"], false);
+
+    drop(ws);
+    handle.stop().await.expect("server should stop cleanly");
+}
+
+#[tokio::test]
+async fn chat_commands_are_answered_before_agent_dispatch() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("listener should bind");
+    let mut config = RuntimeConfig::for_test(
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        0,
+        temp_dir.path().join("commands.db"),
+    );
+    config.channels_inbound_token = Some("bridge-token".to_owned());
+    config.chat_commands = Some(
+        ChatCommands::compile(ChatCommandsConfig {
+            defaults: ChatCommandsRuleConfig::default(),
+            channels: std::collections::BTreeMap::from([(
+                "discord".to_owned(),
+                ChatCommandsRuleConfig {
+                    enabled: Some(false),
+                    ..ChatCommandsRuleConfig::default()
+                },
+            )]),
+        })
+        .expect("chat commands should compile"),
+    );
+
+    let handle = ServerBuilder::new(config)
+        .listener(listener)
+        .agent_backend(Arc::new(ShoutBackend))
+        .chat_command(Arc::new(PingCommand))
+        .start()
+        .await
+        .expect("server should start");
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/channels/inbound", handle.local_addr());
+    let mut message_index = 0;
+    let mut send = async |channel: &str, text: &str, metadata: Value| -> Value {
+        message_index += 1;
+        client
+            .post(&url)
+            .bearer_auth("bridge-token")
+            .json(&json!({
+                "channel": channel,
+                "conversationId": "room-1",
+                "text": text,
+                "messageId": format!("m{message_index}"),
+                "metadata": metadata,
+            }))
+            .send()
+            .await
+            .expect("inbound request should return")
+            .json()
+            .await
+            .expect("response should be json")
+    };
+
+    let agent_reply = send("telegram", "hello", json!({})).await;
+    assert_eq!(agent_reply["reply"], "main:HELLO");
+    assert!(agent_reply["runId"].is_string());
+
+    let status = send("telegram", "/status", json!({})).await;
+    assert!(status["runId"].is_null());
+    let status_text = status["reply"].as_str().unwrap_or_default();
+    assert!(status_text.contains("Agent: main"));
+    assert!(status_text.contains("Replies: active"));
+
+    let help = send("telegram", "/help@reclaw_bot", json!({ "locale": "de-DE" })).await;
+    let help_text = help["reply"].as_str().unwrap_or_default();
+    assert!(help_text.starts_with("Verfügbare Befehle:"));
+    assert!(help_text.contains("/ping – antwortet mit pong"));
+    assert!(help_text.contains("/mute"));
+
+    let ping = send("telegram", "/ping now", json!({})).await;
+    assert_eq!(ping["reply"], "pong now from telegram");
+
+    let unknown_agent = send("telegram", "/agent ghost", json!({})).await;
+    assert!(
+        unknown_agent["reply"]
+            .as_str()
+            .is_some_and(|reply| reply.starts_with("Unknown agent \"ghost\""))
+    );
+
+    let muted = send("telegram", "/mute 1h", json!({})).await;
+    assert!(
+        muted["reply"]
+            .as_str()
+            .is_some_and(|reply| reply.starts_with("Agent muted until"))
+    );
+    let silenced = send("telegram", "still there?", json!({})).await;
+    assert!(silenced["reply"].is_null());
+    assert!(silenced["runId"].is_null());
+    let _ = send("telegram", "/mute off", json!({})).await;
+    let resumed = send("telegram", "back", json!({})).await;
+    assert_eq!(resumed["reply"], "main:BACK");
+
+    let reset = send("telegram", "/reset", json!({})).await;
+    assert!(
+        reset["reply"]
+            .as_str()
+            .is_some_and(|reply| reply.starts_with("Session reset: removed"))
+    );
+
+    let disabled = send("discord", "/status", json!({})).await;
+    assert_eq!(disabled["reply"], "main:/STATUS");
+
+    handle.stop().await.expect("server should stop");
+}
+
+#[tokio::test]
+async fn batched_chat_writes_flush_on_read_and_survive_shutdown() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let db_path = temp_dir.path().join("batched.db");
+    let spill_path = temp_dir.path().join("batched.db.chat-pending.jsonl");
+    std::fs::write(
+        &spill_path,
+        format!(
+            "{}\n",
+            json!({
+                "sessionKey": "agent:main:spilled",
+                "messages": [{
+                    "id": "msg-spilled",
+                    "role": "user",
+                    "text": "left over from the last run",
+                    "status": "final",
+                    "ts": 1,
+                    "metadata": {},
+                    "pinned": false,
+                }],
+            })
+        ),
+    )
+    .expect("spill file should be written");
+
+    let start = |db_path: std::path::PathBuf| async move {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .expect("listener should bind");
+        let mut config = RuntimeConfig::for_test(IpAddr::V4(Ipv4Addr::LOCALHOST), 0, db_path);
+        config.chat_write_batch = Some(Duration::from_secs(1));
+        ServerBuilder::new(config)
+            .listener(listener)
+            .agent_backend(Arc::new(ShoutBackend))
+            .start()
+            .await
+            .expect("server should start")
+    };
+    let history = |handle: &ServerHandle, session_key: &'static str| {
+        let addr = handle.local_addr();
+        async move {
+            let mut ws = connect_gateway(addr).await;
+            ws.send(Message::Text(
+                connect_frame(None, 1, PROTOCOL_VERSION, "operator", "embedder", &[])
+                    .to_string()
+                    .into(),
+            ))
+            .await
+            .expect("connect frame should send");
+            assert_eq!(recv_json(&mut ws).await["ok"], true);
+            let history = rpc_req(
+                &mut ws,
+                "history",
+                "chat.history",
+                Some(json!({ "sessionKey": session_key })),
+            )
+            .await;
+            history["payload"]["messages"]
+                .as_array()
+                .expect("history should list messages")
+                .iter()
+                .map(|message| message["text"].as_str().unwrap_or_default().to_owned())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let handle = start(db_path.clone()).await;
+    assert!(!spill_path.exists());
+    assert_eq!(
+        history(&handle, "agent:main:spilled").await,
+        vec!["left over from the last run"]
+    );
+
+    let mut ws = connect_gateway(handle.local_addr()).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "embedder", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+    for (index, text) in ["one", "two"].into_iter().enumerate() {
+        let sent = rpc_req(
+            &mut ws,
+            &format!("send-{index}"),
+            "chat.send",
+            Some(json!({ "sessionKey": "agent:main:batched", "message": text })),
+        )
+        .await;
+        assert_eq!(sent["ok"], true);
+    }
+    assert_eq!(
+        handle
+            .state()
+            .flush_chat_writes()
+            .await
+            .expect("flush should commit"),
+        4
+    );
+
+    let sent = rpc_req(
+        &mut ws,
+        "send-3",
+        "chat.send",
+        Some(json!({ "sessionKey": "agent:main:batched", "message": "three" })),
+    )
+    .await;
+    assert_eq!(sent["ok"], true);
+    assert_eq!(
+        history(&handle, "agent:main:batched").await,
+        vec!["one", "main:ONE", "two", "main:TWO", "three", "main:THREE"]
+    );
+
+    let sent = rpc_req(
+        &mut ws,
+        "send-4",
+        "chat.send",
+        Some(json!({ "sessionKey": "agent:main:batched", "message": "last" })),
+    )
+    .await;
+    assert_eq!(sent["ok"], true);
+    drop(ws);
+    handle.stop().await.expect("server should stop");
+
+    let handle = start(db_path).await;
+    assert_eq!(
+        history(&handle, "agent:main:batched").await.len(),
+        8,
+        "the shutdown flush should keep buffered messages"
+    );
+    handle.stop().await.expect("server should stop");
+}
+
+#[tokio::test]
+async fn cron_runs_tail_streams_agent_turn_output_while_running() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .expect("listener should bind");
+    let config = RuntimeConfig::for_test(
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        0,
+        temp_dir.path().join("reclaw.db"),
+    );
+    let gate = Arc::new(tokio::sync::Semaphore::new(0));
+    let handle = ServerBuilder::new(config)
+        .listener(listener)
+        .agent_backend(Arc::new(SteppedBackend { gate: gate.clone() }))
+        .start()
+        .await
+        .expect("server should start");
+    let connect = |client_id: &'static str| {
+        let addr = handle.local_addr();
+        async move {
+            let mut ws = connect_gateway(addr).await;
+            ws.send(Message::Text(
+                connect_frame(None, 1, PROTOCOL_VERSION, "operator", client_id, &[])
+                    .to_string()
+                    .into(),
+            ))
+            .await
+            .expect("connect frame should send");
+            assert_eq!(recv_json(&mut ws).await["ok"], true);
+            ws
+        }
+    };
+
+    let mut ws = connect("tail-test").await;
+    let add = rpc_req(
+        &mut ws,
+        "cron-add",
+        "cron.add",
+        Some(json!({
+            "id": "job-stepped",
+            "schedule": { "kind": "every", "everyMs": 3_600_000 },
+            "payload": { "kind": "agentTurn", "message": "count" }
+        })),
+    )
+    .await;
+    assert_eq!(add["ok"], true, "{add}");
+
+    let mut runner = connect("tail-runner").await;
+    let run = tokio::spawn(async move {
+        rpc_req(
+            &mut runner,
+            "cron-run",
+            "cron.run",
+            Some(json!({ "id": "job-stepped" })),
+        )
+        .await
+    });
+
+    async fn tail_until_output(ws: &mut crate::support::WsStream, after_seq: u64) -> Value {
+        for attempt in 0..100 {
+            let tail = rpc_req(
+                ws,
+                &format!("tail-{after_seq}-{attempt}"),
+                "cron.runs.tail",
+                Some(json!({ "jobId": "job-stepped", "afterSeq": after_seq })),
+            )
+            .await;
+            if tail["payload"]["chunks"]
+                .as_array()
+                .is_some_and(|chunks| !chunks.is_empty())
+            {
+                return tail["payload"].clone();
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("no output after seq {after_seq}");
+    }
+
+    let first = tail_until_output(&mut ws, 0).await;
+    assert_eq!(first["done"], false);
+    assert_eq!(first["chunks"].as_array().map(Vec::len), Some(1));
+    assert_eq!(first["chunks"][0]["seq"], 1);
+    assert_eq!(first["chunks"][0]["text"], "one ");
+    assert_eq!(first["lastSeq"], 1);
+
+    gate.add_permits(1);
+    let second = tail_until_output(&mut ws, 1).await;
+    assert_eq!(second["done"], false);
+    assert_eq!(second["chunks"].as_array().map(Vec::len), Some(1));
+    assert_eq!(second["chunks"][0]["seq"], 2);
+    assert_eq!(second["chunks"][0]["text"], "two ");
+    assert_eq!(second["lastSeq"], 2);
+
+    gate.add_permits(1);
+    let run = run.await.expect("run task should finish");
+    assert_eq!(run["payload"]["status"], "ok", "{run}");
+    assert_eq!(run["payload"]["output"], "one two ");
+
+    handle.stop().await.expect("server should stop cleanly");
+}
@@ -1,7 +1,14 @@
-use reclaw_core::application::config::AuthMode;
-use serde_json::Value;
+use futures_util::SinkExt;
+use reclaw_core::{
+    application::config::{AuthMode, GuardrailAction},
+    protocol::PROTOCOL_VERSION,
+};
+use serde_json::{Value, json};
+use tokio_tungstenite::tungstenite::Message;
 
-use super::support::spawn_server;
+use super::support::{
+    connect_frame, connect_gateway, recv_json, rpc_req, spawn_server, spawn_server_with,
+};
 
 #[tokio::test]
 async fn healthz_endpoint_returns_ok_payload() {
@@ -18,3 +25,586 @@ async fn healthz_endpoint_returns_ok_payload() {
 
     server.stop().await;
 }
+
+#[tokio::test]
+async fn resource_guardrails_shed_webhooks_and_refuse_agent_runs() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.guardrails.max_open_fds = Some(1);
+        config.guardrails.actions = vec![
+            GuardrailAction::Log,
+            GuardrailAction::ShedWebhooks,
+            GuardrailAction::RefuseAgentRuns,
+        ];
+    })
+    .await;
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "cli", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let hello = recv_json(&mut ws).await;
+    assert_eq!(hello["ok"], true);
+
+    let doctor = rpc_req(&mut ws, "doctor-1", "doctor.memory.status", None).await;
+    assert_eq!(doctor["ok"], true);
+    assert_eq!(doctor["payload"]["withinGuardrails"], false);
+    assert!(
+        doctor["payload"]["openFds"]
+            .as_u64()
+            .is_some_and(|fds| fds > 1)
+    );
+    assert_eq!(doctor["payload"]["breaches"][0]["metric"], "openFds");
+
+    let agent = rpc_req(
+        &mut ws,
+        "agent-1",
+        "agent",
+        Some(json!({ "input": "hello", "idempotencyKey": "guardrail-run" })),
+    )
+    .await;
+    assert_eq!(agent["ok"], false);
+    assert_eq!(agent["error"]["code"], "UNAVAILABLE");
+    assert_eq!(agent["error"]["retryable"], true);
+
+    let inbound = reqwest::Client::new()
+        .post(format!("http://{}/channels/inbound", server.addr))
+        .json(&json!({ "channel": "webchat", "conversationId": "c1", "text": "hi" }))
+        .send()
+        .await
+        .expect("inbound request should return");
+    assert_eq!(inbound.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    assert!(inbound.headers().contains_key("retry-after"));
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn overload_detector_sheds_low_priority_work_until_cooldown() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.overload.max_dispatch_latency_ms = Some(0);
+        config.overload.check_interval = std::time::Duration::from_millis(50);
+        config.overload.cooldown = std::time::Duration::from_secs(1);
+    })
+    .await;
+
+    let mut watcher = connect_gateway(server.addr).await;
+    let mut frame = connect_frame(None, 1, PROTOCOL_VERSION, "operator", "watcher", &[]);
+    frame["params"]["caps"] = json!(["agent-events-v1"]);
+    watcher
+        .send(Message::Text(frame.to_string().into()))
+        .await
+        .expect("connect frame should send");
+    assert_eq!(recv_json(&mut watcher).await["ok"], true);
+    async fn next_overload_event(watcher: &mut super::support::WsStream) -> Value {
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let frame = recv_json(watcher).await;
+                if frame["event"] == "overload" {
+                    return frame["payload"].clone();
+                }
+            }
+        })
+        .await
+        .expect("overload event should arrive")
+    }
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "cli", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+    assert_eq!(
+        rpc_req(&mut ws, "health-1", "health", None).await["ok"],
+        true
+    );
+
+    let shedding = next_overload_event(&mut watcher).await;
+    assert_eq!(shedding["state"], "shedding");
+    assert_eq!(shedding["breaches"][0]["metric"], "dispatchLatencyMs");
+
+    let history = rpc_req(
+        &mut ws,
+        "history-1",
+        "chat.history",
+        Some(json!({ "sessionKey": "agent:main:main" })),
+    )
+    .await;
+    assert_eq!(history["ok"], false);
+    assert_eq!(history["error"]["code"], "UNAVAILABLE");
+    assert_eq!(history["error"]["retryAfterMs"], 1000);
+
+    let inbound = reqwest::Client::new()
+        .post(format!("http://{}/channels/inbound", server.addr))
+        .json(&json!({ "channel": "webchat", "conversationId": "c1", "text": "hi" }))
+        .send()
+        .await
+        .expect("inbound request should return");
+    assert_eq!(inbound.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(inbound.headers()["retry-after"], "1");
+
+    let recovered = next_overload_event(&mut watcher).await;
+    assert_eq!(recovered["state"], "recovered");
+    let history = rpc_req(
+        &mut ws,
+        "history-2",
+        "chat.history",
+        Some(json!({ "sessionKey": "agent:main:main" })),
+    )
+    .await;
+    assert_eq!(history["ok"], true);
+    let health = rpc_req(&mut ws, "health-2", "health", None).await;
+    assert_eq!(health["payload"]["overload"]["shedRequests"], 2);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn log_shipping_buffers_during_collector_outage_and_ships_batches_with_token() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use axum::http::{HeaderMap, StatusCode};
+    use reclaw_core::application::config::{LogShipTarget, LogShippingConfig};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("collector listener should bind");
+    let collector_addr = listener.local_addr().expect("collector addr");
+    let accepting = Arc::new(AtomicBool::new(false));
+    let (batch_tx, mut batch_rx) = tokio::sync::mpsc::unbounded_channel::<(String, Value)>();
+    let app = axum::Router::new().route(
+        "/ingest",
+        axum::routing::post({
+            let accepting = Arc::clone(&accepting);
+            move |headers: HeaderMap, axum::Json(body): axum::Json<Value>| {
+                let accepting = Arc::clone(&accepting);
+                let batch_tx = batch_tx.clone();
+                async move {
+                    if !accepting.load(Ordering::SeqCst) {
+                        return StatusCode::SERVICE_UNAVAILABLE;
+                    }
+                    let auth = headers
+                        .get("authorization")
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_owned();
+                    let _ = batch_tx.send((auth, body));
+                    StatusCode::ACCEPTED
+                }
+            }
+        }),
+    );
+    let collector = tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.log_shipping = Some(LogShippingConfig {
+            target: LogShipTarget::Http(format!("http://{collector_addr}/ingest")),
+            token: Some("ship-token".to_owned()),
+            batch_size: 2,
+            interval: std::time::Duration::from_millis(50),
+            buffer_max_entries: 1_000,
+        });
+    })
+    .await;
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "cli", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let hello = recv_json(&mut ws).await;
+    assert_eq!(hello["ok"], true);
+
+    let mut outage = Value::Null;
+    for attempt in 0..50 {
+        let health = rpc_req(&mut ws, &format!("health-{attempt}"), "health", None).await;
+        outage = health["payload"]["logShipping"].clone();
+        if outage["lastError"].is_string() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert!(
+        outage["lastError"]
+            .as_str()
+            .is_some_and(|error| error.contains("503"))
+    );
+    assert!(
+        outage["pending"]
+            .as_u64()
+            .is_some_and(|pending| pending > 0)
+    );
+    assert_eq!(outage["shipped"], 0);
+
+    accepting.store(true, Ordering::SeqCst);
+    let (auth, batch) = tokio::time::timeout(std::time::Duration::from_secs(5), batch_rx.recv())
+        .await
+        .expect("collector should receive a batch")
+        .expect("batch should exist");
+    assert_eq!(auth, "Bearer ship-token");
+    assert_eq!(batch["source"], "reclaw");
+    let entries = batch["entries"]
+        .as_array()
+        .expect("entries should be an array");
+    assert!(!entries.is_empty() && entries.len() <= 2);
+    assert_eq!(entries[0]["kind"], "log");
+    assert_eq!(entries[0]["entry"]["method"], "health");
+
+    let mut recovered = Value::Null;
+    for attempt in 0..50 {
+        let health = rpc_req(&mut ws, &format!("health-ok-{attempt}"), "health", None).await;
+        recovered = health["payload"]["logShipping"].clone();
+        if recovered["shipped"]
+            .as_u64()
+            .is_some_and(|shipped| shipped > 1)
+        {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert!(
+        recovered["shipped"]
+            .as_u64()
+            .is_some_and(|shipped| shipped > 1)
+    );
+    assert!(recovered["lastShippedAtMs"].is_u64());
+    assert_eq!(recovered["consecutiveFailures"], 0);
+
+    server.stop().await;
+    collector.abort();
+}
+
+#[tokio::test]
+async fn maintenance_mode_and_scheduled_restart_and_shutdown() {
+    async fn connect_operator(
+        addr: std::net::SocketAddr,
+        client_id: &str,
+        caps: &[&str],
+    ) -> super::support::WsStream {
+        let mut ws = connect_gateway(addr).await;
+        let mut frame = connect_frame(None, 1, PROTOCOL_VERSION, "operator", client_id, &[]);
+        frame["params"]["caps"] = json!(caps);
+        ws.send(Message::Text(frame.to_string().into()))
+            .await
+            .expect("connect frame should send");
+        assert_eq!(recv_json(&mut ws).await["ok"], true);
+        ws
+    }
+    async fn next_event(watcher: &mut super::support::WsStream, event: &str) -> Value {
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let frame = recv_json(watcher).await;
+                if frame["event"] == event {
+                    return frame["payload"].clone();
+                }
+            }
+        })
+        .await
+        .expect("event should arrive")
+    }
+    async fn close_code(ws: &mut super::support::WsStream) -> u16 {
+        use futures_util::StreamExt;
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while let Some(Ok(message)) = ws.next().await {
+                if let Message::Close(frame) = message {
+                    return frame.map_or(0, |frame| u16::from(frame.code));
+                }
+            }
+            0
+        })
+        .await
+        .expect("connection should close")
+    }
+
+    let server = spawn_server(AuthMode::None).await;
+    let mut watcher = connect_operator(server.addr, "watcher", &["agent-events-v1"]).await;
+    let mut ws = connect_operator(server.addr, "cli", &[]).await;
+
+    let enabled = rpc_req(
+        &mut ws,
+        "maintenance-1",
+        "system.maintenance",
+        Some(json!({ "enabled": true, "reason": "db upgrade", "durationMs": 60_000 })),
+    )
+    .await;
+    assert_eq!(enabled["ok"], true, "{enabled}");
+    assert_eq!(enabled["payload"]["maintenance"]["enabled"], true);
+    assert_eq!(
+        enabled["payload"]["maintenance"]["window"]["reason"],
+        "db upgrade"
+    );
+    let event = next_event(&mut watcher, "maintenance").await;
+    assert_eq!(event["enabled"], true);
+
+    let send = rpc_req(
+        &mut ws,
+        "send-1",
+        "chat.send",
+        Some(json!({
+            "sessionKey": "agent:main:main",
+            "message": "hi",
+            "idempotencyKey": "maintenance-send-1",
+        })),
+    )
+    .await;
+    assert_eq!(send["ok"], false);
+    assert_eq!(send["error"]["code"], "UNAVAILABLE");
+    assert_eq!(send["error"]["retryable"], true);
+    assert!(
+        send["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("db upgrade"))
+    );
+    let sessions = rpc_req(&mut ws, "sessions-1", "sessions.list", None).await;
+    assert_eq!(sessions["ok"], true);
+    let inbound = reqwest::Client::new()
+        .post(format!("http://{}/channels/inbound", server.addr))
+        .json(&json!({ "channel": "webchat", "conversationId": "c1", "text": "hi" }))
+        .send()
+        .await
+        .expect("inbound request should return");
+    assert_eq!(inbound.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    assert!(inbound.headers().contains_key("retry-after"));
+
+    let disabled = rpc_req(
+        &mut ws,
+        "maintenance-2",
+        "system.maintenance",
+        Some(json!({ "enabled": false })),
+    )
+    .await;
+    assert_eq!(disabled["payload"]["maintenance"]["enabled"], false);
+    let send = rpc_req(
+        &mut ws,
+        "send-2",
+        "chat.send",
+        Some(json!({
+            "sessionKey": "agent:main:main",
+            "message": "hi",
+            "idempotencyKey": "maintenance-send-2",
+        })),
+    )
+    .await;
+    assert_eq!(send["ok"], true, "{send}");
+
+    let scheduled = rpc_req(
+        &mut ws,
+        "shutdown-1",
+        "system.shutdown",
+        Some(json!({ "delayMs": 60_000, "reason": "host reboot" })),
+    )
+    .await;
+    assert_eq!(scheduled["ok"], true, "{scheduled}");
+    assert_eq!(scheduled["payload"]["scheduled"]["kind"], "shutdown");
+    let event = next_event(&mut watcher, "shutdown").await;
+    assert_eq!(event["phase"], "scheduled");
+    assert_eq!(event["reason"], "host reboot");
+    let cancelled = rpc_req(
+        &mut ws,
+        "shutdown-2",
+        "system.shutdown",
+        Some(json!({ "cancel": true })),
+    )
+    .await;
+    assert_eq!(cancelled["payload"]["cancelled"]["reason"], "host reboot");
+    assert_eq!(
+        next_event(&mut watcher, "shutdown").await["phase"],
+        "cancelled"
+    );
+
+    let restart = rpc_req(&mut ws, "restart-1", "system.restart", None).await;
+    assert_eq!(restart["ok"], true, "{restart}");
+    assert_eq!(
+        next_event(&mut watcher, "shutdown").await["phase"],
+        "scheduled"
+    );
+    assert_eq!(
+        next_event(&mut watcher, "shutdown").await["phase"],
+        "draining"
+    );
+    let stopping = next_event(&mut watcher, "shutdown").await;
+    assert_eq!(stopping["phase"], "stopping");
+    assert_eq!(stopping["restart"], true);
+    assert_eq!(close_code(&mut watcher).await, 1012);
+    assert_eq!(close_code(&mut ws).await, 1012);
+
+    let mut ws = connect_operator(server.addr, "cli", &[]).await;
+    let history = rpc_req(
+        &mut ws,
+        "history-1",
+        "chat.history",
+        Some(json!({ "sessionKey": "agent:main:main" })),
+    )
+    .await;
+    assert_eq!(history["ok"], true, "{history}");
+    assert!(
+        history["payload"]["messages"]
+            .as_array()
+            .is_some_and(|messages| !messages.is_empty())
+    );
+
+    let shutdown = rpc_req(
+        &mut ws,
+        "shutdown-3",
+        "system.shutdown",
+        Some(json!({ "drain": false })),
+    )
+    .await;
+    assert_eq!(shutdown["ok"], true, "{shutdown}");
+    assert_eq!(close_code(&mut ws).await, 1001);
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        while tokio::net::TcpStream::connect(server.addr).await.is_ok() {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("listener should close after shutdown");
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn public_status_is_anonymous_cached_and_rate_limited() {
+    let disabled = spawn_server(AuthMode::None).await;
+    let response = reqwest::get(format!("http://{}/status/public", disabled.addr))
+        .await
+        .expect("status request should return");
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    disabled.stop().await;
+
+    let server = spawn_server_with(AuthMode::Token("status-secret".to_owned()), |config| {
+        config.public_status_enabled = true;
+        config.public_status_cache_ttl = std::time::Duration::from_secs(60);
+        config.public_status_rate_limit_per_minute = 3;
+    })
+    .await;
+    let url = format!("http://{}/status/public", server.addr);
+
+    let first = reqwest::get(&url)
+        .await
+        .expect("status request should return");
+    assert_eq!(first.status(), reqwest::StatusCode::OK);
+    assert_eq!(first.headers()["cache-control"], "public, max-age=60");
+    assert_eq!(first.headers()["access-control-allow-origin"], "*");
+    let first: Value = first.json().await.expect("status should be json");
+    assert_eq!(first["status"], "ok");
+    assert_eq!(first["nodes"]["connected"], 0);
+    assert!(first["uptimeMs"].is_u64());
+    let mut keys = first
+        .as_object()
+        .expect("status should be an object")
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(
+        keys,
+        [
+            "generatedAtMs",
+            "nodes",
+            "ok",
+            "status",
+            "uptimeMs",
+            "version"
+        ]
+    );
+
+    for _ in 0..2 {
+        let cached: Value = reqwest::get(&url)
+            .await
+            .expect("status request should return")
+            .json()
+            .await
+            .expect("status should be json");
+        assert_eq!(cached["generatedAtMs"], first["generatedAtMs"]);
+    }
+    let limited = reqwest::get(&url)
+        .await
+        .expect("status request should return");
+    assert_eq!(limited.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    assert!(limited.headers().contains_key("retry-after"));
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn storage_query_latency_is_reported_by_doctor_and_metrics() {
+    let server = spawn_server(AuthMode::Token("metrics-secret".to_owned())).await;
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(
+            Some("metrics-secret"),
+            1,
+            PROTOCOL_VERSION,
+            "operator",
+            "reclaw-test",
+            &["operator.read"],
+        )
+        .to_string()
+        .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let hello = recv_json(&mut ws).await;
+    assert_eq!(hello["ok"], true, "{hello}");
+
+    let listed = rpc_req(&mut ws, "sessions-1", "sessions.list", None).await;
+    assert_eq!(listed["ok"], true, "{listed}");
+    let report = rpc_req(
+        &mut ws,
+        "slow-1",
+        "doctor.storage.slowQueries",
+        Some(json!({ "limit": 200 })),
+    )
+    .await;
+    assert_eq!(report["ok"], true, "{report}");
+    assert_eq!(report["payload"]["windowMs"], 3_600_000);
+    let operations = report["payload"]["operations"]
+        .as_array()
+        .expect("operations should be listed");
+    let listed = operations
+        .iter()
+        .find(|entry| entry["operation"] == "list_sessions")
+        .expect("sessions.list should be timed");
+    assert!(listed["calls"].as_u64().is_some_and(|calls| calls >= 1));
+    assert!(listed["maxMs"].as_f64().is_some());
+
+    let url = format!("http://{}/metrics", server.addr);
+    let client = reqwest::Client::new();
+    let anonymous = client
+        .get(&url)
+        .send()
+        .await
+        .expect("metrics request should return");
+    assert_eq!(anonymous.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let response = client
+        .get(&url)
+        .bearer_auth("metrics-secret")
+        .send()
+        .await
+        .expect("metrics request should return");
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let text = response.text().await.expect("metrics should be text");
+    assert!(text.contains("# TYPE reclaw_storage_query_duration_seconds histogram"));
+    assert!(text.contains(
+        "reclaw_storage_query_duration_seconds_bucket{operation=\"list_sessions\",le=\"+Inf\"}"
+    ));
+
+    server.stop().await;
+}
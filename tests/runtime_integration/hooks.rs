@@ -6,10 +6,11 @@ use std::{fs, path::Path};
 use futures_util::SinkExt;
 use reclaw_core::{
     application::config::{
-        AuthMode, HookMappingAction, HookMappingConfig, HookMappingMatchConfig,
-        HookMappingTransformConfig,
+        AuthMode, HookMappingAction, HookMappingConfig, HookMappingCronConfig,
+        HookMappingMatchConfig, HookMappingTransformConfig, HookMatchPredicate, HookProvider,
     },
     protocol::PROTOCOL_VERSION,
+    security::signatures::{hex_encode, hmac_sha256},
 };
 use serde_json::{Value, json};
 use tokio_tungstenite::tungstenite::Message;
@@ -318,6 +319,13 @@ async fn hooks_mapping_dispatches_agent_action() {
             agent_id: Some("mapped-agent".to_owned()),
             session_key: Some("hook:mapped".to_owned()),
             transform: None,
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
         }];
     })
     .await;
@@ -366,6 +374,13 @@ async fn hooks_mapping_dispatches_wake_action() {
             agent_id: None,
             session_key: None,
             transform: None,
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
         }];
     })
     .await;
@@ -387,6 +402,125 @@ async fn hooks_mapping_dispatches_wake_action() {
     server.stop().await;
 }
 
+#[tokio::test]
+async fn hooks_mapping_schedules_reschedules_and_runs_cron_jobs() {
+    let mapping = |path: &str, cron: HookMappingCronConfig| HookMappingConfig {
+        id: Some(path.to_owned()),
+        path: path.to_owned(),
+        r#match: None,
+        action: HookMappingAction::ScheduleCron,
+        match_source: None,
+        wake_mode: None,
+        text: None,
+        text_template: None,
+        message: None,
+        message_template: Some("check deploy of {{sha}}".to_owned()),
+        name: None,
+        agent_id: None,
+        session_key: None,
+        transform: None,
+        cron: Some(cron),
+        response_template: None,
+        response_status: None,
+        provider: None,
+        secret: None,
+        priority: 0,
+        r#continue: false,
+    };
+    let server = spawn_server_with(AuthMode::None, move |config| {
+        config.hooks_enabled = true;
+        config.hooks_token = Some("hooks-token".to_owned());
+        config.hooks_mappings = vec![
+            mapping(
+                "ci/deployed",
+                HookMappingCronConfig {
+                    job_id: Some("followup-{{sha}}".to_owned()),
+                    name: Some("Follow up {{sha}}".to_owned()),
+                    delay_ms: Some(30 * 60 * 1000),
+                    ..HookMappingCronConfig::default()
+                },
+            ),
+            mapping(
+                "ci/recheck",
+                HookMappingCronConfig {
+                    job_id: Some("followup-{{sha}}".to_owned()),
+                    ..HookMappingCronConfig::default()
+                },
+            ),
+        ];
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let post = |path: &'static str| {
+        client
+            .post(format!("http://{}/hooks/{path}", server.addr))
+            .bearer_auth("hooks-token")
+            .json(&json!({ "sha": "abc123" }))
+            .send()
+    };
+    let before = u64::try_from(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("clock should be after epoch")
+            .as_millis(),
+    )
+    .expect("clock should fit in u64");
+    let scheduled: Value = post("ci/deployed")
+        .await
+        .expect("hooks request should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(scheduled["ok"], true);
+    assert_eq!(scheduled["jobId"], "followup-abc123");
+    assert_eq!(scheduled["rescheduled"], false);
+    let next_run_ms = scheduled["nextRunMs"]
+        .as_u64()
+        .expect("next run should be set");
+    assert!(next_run_ms >= before + 30 * 60 * 1000);
+    assert!(next_run_ms < before + 31 * 60 * 1000);
+
+    let rescheduled: Value = post("ci/deployed")
+        .await
+        .expect("hooks request should return")
+        .json()
+        .await
+        .expect("response should be json");
+    assert_eq!(rescheduled["jobId"], "followup-abc123");
+    assert_eq!(rescheduled["rescheduled"], true);
+    assert!(rescheduled["nextRunMs"].as_u64() >= Some(next_run_ms));
+
+    let response = post("ci/recheck")
+        .await
+        .expect("hooks request should return");
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let ran: Value = response.json().await.expect("response should be json");
+    assert_eq!(ran["jobId"], "followup-abc123");
+    assert!(ran["runId"].as_str().is_some_and(|id| !id.is_empty()));
+
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+    let jobs = rpc_req(&mut ws, "cron-1", "cron.list", None).await;
+    let job = &jobs["payload"]["jobs"][0];
+    assert_eq!(job["id"], "followup-abc123");
+    assert_eq!(job["name"], "Follow up abc123");
+    assert_eq!(job["schedule"]["kind"], "at");
+    assert_eq!(job["payload"]["kind"], "agentTurn");
+    assert_eq!(job["payload"]["message"], "check deploy of abc123");
+    assert_eq!(job["metadata"]["source"], "hook");
+    assert_eq!(job["metadata"]["mappingId"], "ci/deployed");
+
+    server.stop().await;
+}
+
 #[tokio::test]
 async fn hooks_mapping_honors_match_source_filter() {
     let server = spawn_server_with(AuthMode::None, |config| {
@@ -407,6 +541,13 @@ async fn hooks_mapping_honors_match_source_filter() {
             agent_id: None,
             session_key: Some("hook:source-filter".to_owned()),
             transform: None,
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
         }];
     })
     .await;
@@ -460,6 +601,13 @@ async fn hooks_mapping_renders_message_template_from_payload() {
             agent_id: None,
             session_key: Some("hook:template".to_owned()),
             transform: None,
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
         }];
     })
     .await;
@@ -511,6 +659,13 @@ async fn hooks_mapping_renders_header_query_and_path_context() {
             agent_id: None,
             session_key: Some("hook:context".to_owned()),
             transform: None,
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
         }];
     })
     .await;
@@ -572,6 +727,13 @@ async fn hooks_mapping_transform_can_override_agent_to_wake() {
                 module: "override.sh".to_owned(),
                 export: None,
             }),
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
         }];
     })
     .await;
@@ -627,6 +789,13 @@ async fn hooks_mapping_transform_null_skips_dispatch() {
                 module: "skip.sh".to_owned(),
                 export: None,
             }),
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
         }];
     })
     .await;
@@ -659,6 +828,11 @@ async fn hooks_mapping_supports_openclaw_style_match_object() {
             r#match: Some(HookMappingMatchConfig {
                 path: Some("match/object".to_owned()),
                 source: Some("github".to_owned()),
+                r#type: None,
+                subject: None,
+                headers: None,
+                query: None,
+                payload: None,
             }),
             action: HookMappingAction::Agent,
             match_source: None,
@@ -671,6 +845,13 @@ async fn hooks_mapping_supports_openclaw_style_match_object() {
             agent_id: None,
             session_key: Some("hook:match-object".to_owned()),
             transform: None,
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
         }];
     })
     .await;
@@ -693,3 +874,484 @@ async fn hooks_mapping_supports_openclaw_style_match_object() {
 
     server.stop().await;
 }
+
+#[tokio::test]
+async fn hooks_mapping_fans_out_on_header_query_and_payload_predicates() {
+    let mappings = serde_json::from_value::<Vec<HookMappingConfig>>(json!([
+        {
+            "match": { "path": "github", "headers": { "X-GitHub-Event": "push" } },
+            "message": "push",
+            "sessionKey": "hook:gh-push",
+        },
+        {
+            "match": {
+                "path": "github",
+                "headers": { "x-github-event": "pull_request" },
+                "query": { "env": { "equals": "prod" } },
+                "payload": {
+                    "action": { "regex": "^(opened|reopened)$" },
+                    "pull_request.draft": "false",
+                    "pull_request.labels[0]": { "exists": false },
+                },
+            },
+            "message": "pr",
+            "sessionKey": "hook:gh-pr",
+        },
+    ]))
+    .expect("mappings should deserialize");
+    let server = spawn_server_with(AuthMode::None, move |config| {
+        config.hooks_enabled = true;
+        config.hooks_token = Some("hooks-token".to_owned());
+        config.hooks_mappings = mappings;
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let post = |event: &'static str, query: &'static str, body: Value| {
+        client
+            .post(format!("http://{}/hooks/github{query}", server.addr))
+            .bearer_auth("hooks-token")
+            .header("x-github-event", event)
+            .json(&body)
+            .send()
+    };
+
+    let push = post("push", "", json!({ "ref": "refs/heads/main" }))
+        .await
+        .expect("hooks request should return");
+    assert_eq!(push.status(), reqwest::StatusCode::ACCEPTED);
+    let push: Value = push.json().await.expect("response should be json");
+    assert_eq!(push["sessionKey"], "hook:gh-push");
+
+    let opened = json!({ "action": "reopened", "pull_request": { "draft": false, "labels": [] } });
+    let pr = post("pull_request", "?env=prod", opened.clone())
+        .await
+        .expect("hooks request should return");
+    assert_eq!(pr.status(), reqwest::StatusCode::ACCEPTED);
+    let pr: Value = pr.json().await.expect("response should be json");
+    assert_eq!(pr["sessionKey"], "hook:gh-pr");
+
+    for (query, body) in [
+        ("?env=staging", opened.clone()),
+        (
+            "?env=prod",
+            json!({ "action": "closed", "pull_request": { "draft": false } }),
+        ),
+        (
+            "?env=prod",
+            json!({ "action": "opened", "pull_request": { "draft": false, "labels": ["wip"] } }),
+        ),
+    ] {
+        let unmatched = post("pull_request", query, body)
+            .await
+            .expect("hooks request should return");
+        assert_eq!(unmatched.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn hooks_mapping_accepts_structured_cloud_events() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.hooks_enabled = true;
+        config.hooks_token = Some("hooks-token".to_owned());
+        config.hooks_mappings = vec![HookMappingConfig {
+            id: Some("cloudevents".to_owned()),
+            path: String::new(),
+            r#match: Some(HookMappingMatchConfig {
+                path: Some("events".to_owned()),
+                source: Some("/billing".to_owned()),
+                r#type: Some("com.example.invoice.*".to_owned()),
+                subject: None,
+                headers: None,
+                query: None,
+                payload: None,
+            }),
+            action: HookMappingAction::Agent,
+            match_source: None,
+            wake_mode: None,
+            text: None,
+            text_template: None,
+            message: None,
+            message_template: Some("{{type}} for {{subject}} amount={{data.amount}}".to_owned()),
+            name: None,
+            agent_id: None,
+            session_key: Some("hook:cloudevents".to_owned()),
+            transform: None,
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
+        }];
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let event = json!({
+        "specversion": "1.0",
+        "id": "evt-1",
+        "source": "/billing",
+        "type": "com.example.invoice.paid",
+        "subject": "inv-9",
+        "data": { "amount": 42 }
+    });
+    let response = client
+        .post(format!("http://{}/hooks/events", server.addr))
+        .bearer_auth("hooks-token")
+        .header("content-type", "application/cloudevents+json")
+        .body(event.to_string())
+        .send()
+        .await
+        .expect("hooks request should return");
+    assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+
+    let history_texts = session_history_texts(server.addr, "hook:cloudevents").await;
+    assert!(
+        history_texts
+            .iter()
+            .any(|text| text.contains("com.example.invoice.paid for inv-9 amount=42"))
+    );
+
+    let mut unmatched = event;
+    unmatched["type"] = json!("com.example.refund.created");
+    let response = client
+        .post(format!("http://{}/hooks/events", server.addr))
+        .bearer_auth("hooks-token")
+        .header("content-type", "application/cloudevents+json")
+        .body(unmatched.to_string())
+        .send()
+        .await
+        .expect("hooks request should return");
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn hooks_mapping_renders_custom_response() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.hooks_enabled = true;
+        config.hooks_token = Some("hooks-token".to_owned());
+        config.hooks_mappings = vec![HookMappingConfig {
+            id: Some("slack-command".to_owned()),
+            path: "slack/command".to_owned(),
+            r#match: None,
+            action: HookMappingAction::Agent,
+            match_source: None,
+            wake_mode: None,
+            text: None,
+            text_template: None,
+            message: None,
+            message_template: Some("{{command}} {{text}}".to_owned()),
+            name: Some("Slack".to_owned()),
+            agent_id: None,
+            session_key: Some("hook:slack-command".to_owned()),
+            transform: None,
+            cron: None,
+            response_template: Some(json!({
+                "response_type": "ephemeral",
+                "text": "Working on `{{text}}` (run {{runId}})",
+                "blocks": [{ "type": "context", "session": "{{response.sessionKey}}" }]
+            })),
+            response_status: Some(200),
+            provider: None,
+            secret: None,
+            priority: 0,
+            r#continue: false,
+        }];
+    })
+    .await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/hooks/slack/command", server.addr))
+        .bearer_auth("hooks-token")
+        .json(&json!({ "command": "/deploy", "text": "api" }))
+        .send()
+        .await
+        .expect("hooks request should return");
+
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let payload: Value = response.json().await.expect("response should be json");
+    assert_eq!(payload["response_type"], "ephemeral");
+    let text = payload["text"].as_str().expect("text should render");
+    assert!(text.starts_with("Working on `api` (run "));
+    assert!(!text.ends_with("(run )"));
+    assert_eq!(payload["blocks"][0]["session"], "hook:slack-command");
+    assert!(payload.get("ok").is_none());
+
+    assert_session_has_history(server.addr, "hook:slack-command").await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn hooks_provider_mapping_verifies_stripe_signature_without_hooks_token() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.hooks_enabled = true;
+        config.hooks_token = Some("hooks-token".to_owned());
+        config.hooks_mappings = vec![HookMappingConfig {
+            id: Some("stripe".to_owned()),
+            path: "stripe".to_owned(),
+            r#match: Some(HookMappingMatchConfig {
+                path: None,
+                source: None,
+                r#type: Some("invoice.*".to_owned()),
+                subject: None,
+                headers: None,
+                query: None,
+                payload: None,
+            }),
+            action: HookMappingAction::Agent,
+            match_source: None,
+            wake_mode: None,
+            text: None,
+            text_template: None,
+            message: None,
+            message_template: None,
+            name: None,
+            agent_id: None,
+            session_key: Some("hook:stripe".to_owned()),
+            transform: None,
+            cron: None,
+            response_template: None,
+            response_status: None,
+            provider: Some(HookProvider::Stripe),
+            secret: Some("whsec_test".to_owned()),
+            priority: 0,
+            r#continue: false,
+        }];
+    })
+    .await;
+
+    let body = json!({
+        "id": "evt_1",
+        "type": "invoice.paid",
+        "data": { "object": { "id": "in_1", "object": "invoice" } }
+    })
+    .to_string();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("clock should be after epoch")
+        .as_secs();
+    let signature = hex_encode(&hmac_sha256(
+        b"whsec_test",
+        format!("{timestamp}.{body}").as_bytes(),
+    ));
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("http://{}/hooks/stripe", server.addr))
+        .header("content-type", "application/json")
+        .header("stripe-signature", format!("t={timestamp},v1={signature}"))
+        .body(body.clone())
+        .send()
+        .await
+        .expect("hooks request should return");
+    assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+    let texts = session_history_texts(server.addr, "hook:stripe").await;
+    assert!(
+        texts
+            .iter()
+            .any(|text| text.contains("Stripe event invoice.paid (evt_1) for invoice in_1"))
+    );
+
+    let forged = client
+        .post(format!("http://{}/hooks/stripe", server.addr))
+        .header("content-type", "application/json")
+        .header(
+            "stripe-signature",
+            format!("t={timestamp},v1={}", "0".repeat(64)),
+        )
+        .body(body)
+        .send()
+        .await
+        .expect("hooks request should return");
+    assert_eq!(forged.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    server.stop().await;
+}
+
+fn email_mapping(
+    path: &str,
+    message_template: Option<&str>,
+    session_key: &str,
+) -> HookMappingConfig {
+    HookMappingConfig {
+        id: None,
+        path: path.to_owned(),
+        r#match: None,
+        action: HookMappingAction::Agent,
+        match_source: None,
+        wake_mode: None,
+        text: None,
+        text_template: None,
+        message: None,
+        message_template: message_template.map(str::to_owned),
+        name: None,
+        agent_id: None,
+        session_key: Some(session_key.to_owned()),
+        transform: None,
+        cron: None,
+        response_template: None,
+        response_status: None,
+        provider: None,
+        secret: None,
+        priority: 0,
+        r#continue: false,
+    }
+}
+
+#[tokio::test]
+async fn hooks_email_routes_plus_addressed_recipients_to_mappings() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.hooks_enabled = true;
+        config.hooks_token = Some("hooks-token".to_owned());
+        config.hooks_email_enabled = true;
+        config.hooks_mappings = vec![
+            email_mapping(
+                "email/github",
+                Some("[{{tag}}] {{subject}}: {{text}}"),
+                "hook:email-github",
+            ),
+            email_mapping("email", None, "hook:email"),
+        ];
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/hooks/email", server.addr);
+
+    let form_data = "--b\r\n\
+        Content-Disposition: form-data; name=\"from\"\r\n\r\n\
+        Dev <dev@example.com>\r\n\
+        --b\r\n\
+        Content-Disposition: form-data; name=\"to\"\r\n\r\n\
+        agent+GitHub@example.com\r\n\
+        --b\r\n\
+        Content-Disposition: form-data; name=\"subject\"\r\n\r\n\
+        PR #42 opened\r\n\
+        --b\r\n\
+        Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+        Please review\r\n\
+        --b--\r\n";
+    let tagged = client
+        .post(&url)
+        .basic_auth("sendgrid", Some("hooks-token"))
+        .header("content-type", "multipart/form-data; boundary=b")
+        .body(form_data)
+        .send()
+        .await
+        .expect("email hook request should return");
+    assert_eq!(tagged.status(), reqwest::StatusCode::ACCEPTED);
+    let texts = session_history_texts(server.addr, "hook:email-github").await;
+    assert!(
+        texts
+            .iter()
+            .any(|text| text == "[github] PR #42 opened: Please review")
+    );
+
+    let fallback = client
+        .post(&url)
+        .header("authorization", "Bearer hooks-token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("recipient=agent%2Bunknown%40example.com&sender=ops%40example.com&subject=Disk+full&body-plain=Free+space")
+        .send()
+        .await
+        .expect("email hook request should return");
+    assert_eq!(fallback.status(), reqwest::StatusCode::ACCEPTED);
+    let texts = session_history_texts(server.addr, "hook:email").await;
+    assert!(
+        texts
+            .iter()
+            .any(|text| text.contains("Email from ops@example.com: Disk full\n\nFree space"))
+    );
+
+    let unauthorized = client
+        .post(&url)
+        .basic_auth("sendgrid", Some("wrong"))
+        .header("content-type", "multipart/form-data; boundary=b")
+        .body(form_data)
+        .send()
+        .await
+        .expect("email hook request should return");
+    assert_eq!(unauthorized.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn hooks_mappings_dispatch_by_priority_and_continue_with_diagnostics() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.hooks_enabled = true;
+        config.hooks_token = Some("hooks-token".to_owned());
+        let mapping = |id: &str, priority: i32, r#continue: bool| HookMappingConfig {
+            id: Some(id.to_owned()),
+            priority,
+            r#continue,
+            ..email_mapping("deploys", Some(id), &format!("hook:{id}"))
+        };
+        config.hooks_mappings = vec![
+            mapping("fallback", 0, false),
+            HookMappingConfig {
+                r#match: Some(HookMappingMatchConfig {
+                    path: None,
+                    source: None,
+                    r#type: None,
+                    subject: None,
+                    headers: Some(
+                        [(
+                            "x-env".to_owned(),
+                            HookMatchPredicate::Equals("staging".to_owned()),
+                        )]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    query: None,
+                    payload: None,
+                }),
+                ..mapping("staging", 20, true)
+            },
+            mapping("audit", 10, true),
+            mapping("late", -5, false),
+        ];
+    })
+    .await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/hooks/deploys", server.addr))
+        .bearer_auth("hooks-token")
+        .header("x-env", "production")
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("hooks request should return");
+    assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+    let payload: Value = response.json().await.expect("response should be json");
+    assert_eq!(payload["sessionKey"], "hook:audit", "{payload}");
+    let dispatched = payload["mappings"]["dispatched"]
+        .as_array()
+        .expect("dispatched should list mappings")
+        .iter()
+        .map(|entry| (entry["id"].clone(), entry["status"].clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        dispatched,
+        vec![
+            (json!("audit"), json!(202)),
+            (json!("fallback"), json!(202)),
+        ]
+    );
+    assert_eq!(
+        payload["mappings"]["skipped"],
+        json!([
+            {"id": "staging", "priority": 20, "reason": "match.headers"},
+            {"id": "late", "priority": -5, "reason": "shadowed"},
+        ])
+    );
+
+    assert_session_has_history(server.addr, "hook:audit").await;
+    assert_session_has_history(server.addr, "hook:fallback").await;
+    server.stop().await;
+}
@@ -1,7 +1,18 @@
-use reclaw_core::application::config::AuthMode;
+use futures_util::SinkExt;
+use reclaw_core::{
+    application::config::{AuthMode, NodeFilesConfig},
+    protocol::PROTOCOL_VERSION,
+};
 use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{Error as WsError, Message, client::IntoClientRequest, http::HeaderValue},
+};
 
-use super::support::{spawn_server, spawn_server_with};
+use super::support::{
+    connect_frame, connect_gateway, recv_json, rpc_req, spawn_server, spawn_server_with,
+};
 
 #[tokio::test]
 async fn openai_chat_completions_requires_gateway_auth() {
@@ -350,3 +361,561 @@ async fn tools_invoke_rejects_unknown_tool_name() {
 
     server.stop().await;
 }
+
+#[tokio::test]
+async fn named_api_keys_authorize_compat_endpoints_with_scopes_and_limits() {
+    let server = spawn_server_with(AuthMode::Token("gateway-secret".to_owned()), |config| {
+        config.openai_chat_completions_enabled = true;
+    })
+    .await;
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(
+            Some("gateway-secret"),
+            PROTOCOL_VERSION,
+            PROTOCOL_VERSION,
+            "operator",
+            "reclaw-test",
+            &[],
+        )
+        .to_string()
+        .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    let hello = recv_json(&mut ws).await;
+    assert_eq!(hello["ok"], true);
+
+    let read_only = rpc_req(
+        &mut ws,
+        "k-1",
+        "apikeys.create",
+        Some(json!({ "name": "dashboard", "scopes": ["operator.read"] })),
+    )
+    .await;
+    assert_eq!(read_only["ok"], true);
+    let read_only_secret = read_only["payload"]["secret"]
+        .as_str()
+        .expect("secret should be returned once")
+        .to_owned();
+    assert!(read_only["payload"]["key"].get("secretHash").is_none());
+
+    let writer = rpc_req(
+        &mut ws,
+        "k-2",
+        "apikeys.create",
+        Some(json!({ "name": "ci", "rateLimitPerMinute": 1 })),
+    )
+    .await;
+    assert_eq!(writer["ok"], true);
+    let writer_id = writer["payload"]["key"]["id"]
+        .as_str()
+        .expect("key id should be returned")
+        .to_owned();
+    let writer_secret = writer["payload"]["secret"]
+        .as_str()
+        .expect("secret should be returned once")
+        .to_owned();
+
+    let client = reqwest::Client::new();
+    let completion = |secret: String| {
+        client
+            .post(format!("http://{}/v1/chat/completions", server.addr))
+            .bearer_auth(secret)
+            .json(&json!({
+                "messages": [{"role": "user", "content": "hello"}]
+            }))
+            .send()
+    };
+
+    let forbidden = completion(read_only_secret)
+        .await
+        .expect("openai request should return");
+    assert_eq!(forbidden.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let allowed = completion(writer_secret.clone())
+        .await
+        .expect("openai request should return");
+    assert!(allowed.status().is_success());
+
+    let limited = completion(writer_secret.clone())
+        .await
+        .expect("openai request should return");
+    assert_eq!(limited.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    let payload: Value = limited.json().await.expect("response should be json");
+    assert_eq!(payload["error"]["type"], "rate_limit_error");
+
+    let revoked = rpc_req(
+        &mut ws,
+        "k-3",
+        "apikeys.revoke",
+        Some(json!({ "id": writer_id })),
+    )
+    .await;
+    assert_eq!(revoked["ok"], true);
+
+    let rejected = completion(writer_secret)
+        .await
+        .expect("openai request should return");
+    assert_eq!(rejected.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let listed = rpc_req(&mut ws, "k-4", "apikeys.list", None).await;
+    assert_eq!(listed["ok"], true);
+    assert_eq!(
+        listed["payload"]["keys"]
+            .as_array()
+            .map(Vec::len)
+            .unwrap_or_default(),
+        1
+    );
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn browser_routes_reject_disallowed_origins_before_handshake() {
+    let server = spawn_server_with(AuthMode::None, |config| {
+        config.openai_chat_completions_enabled = true;
+        config.allowed_origins = vec!["https://app.example".to_owned()];
+    })
+    .await;
+
+    let mut request = format!("ws://{}/", server.addr)
+        .into_client_request()
+        .expect("ws request should build");
+    request
+        .headers_mut()
+        .insert("origin", HeaderValue::from_static("https://evil.example"));
+    match connect_async(request).await {
+        Err(WsError::Http(response)) => assert_eq!(response.status(), 403),
+        other => panic!("cross-origin upgrade should be refused, got {other:?}"),
+    }
+
+    let mut request = format!("ws://{}/", server.addr)
+        .into_client_request()
+        .expect("ws request should build");
+    request
+        .headers_mut()
+        .insert("origin", HeaderValue::from_static("https://app.example"));
+    let (mut ws, _) = connect_async(request)
+        .await
+        .expect("allowed origin should upgrade");
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("http://{}/v1/chat/completions", server.addr))
+        .header("origin", "https://evil.example")
+        .json(&json!({
+            "model": "reclaw-core",
+            "messages": [{"role": "user", "content": "hello"}]
+        }))
+        .send()
+        .await
+        .expect("openai request should return");
+    assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+    let payload: Value = response.json().await.expect("response should be json");
+    assert_eq!(payload["error"]["code"], "FORBIDDEN");
+
+    let health = client
+        .get(format!("http://{}/healthz", server.addr))
+        .header("origin", "https://evil.example")
+        .send()
+        .await
+        .expect("healthz should return");
+    assert!(health.status().is_success());
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn nodes_poll_over_http_for_queued_invokes_and_journaled_events() {
+    let server = spawn_server(AuthMode::Token("gateway-secret".to_owned())).await;
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(
+            Some("gateway-secret"),
+            1,
+            PROTOCOL_VERSION,
+            "operator",
+            "reclaw-cli",
+            &[],
+        )
+        .to_string()
+        .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+
+    let client = reqwest::Client::new();
+    let poll_url = format!("http://{}/nodes/node-p/poll", server.addr);
+    let poll = |token: Option<&'static str>, body: Value| {
+        let mut request = client.post(&poll_url).json(&body);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        async move {
+            let response = request.send().await.expect("poll should send");
+            let status = response.status().as_u16();
+            let body = response.json::<Value>().await.expect("poll body is json");
+            (status, body)
+        }
+    };
+
+    let (status, body) = poll(None, json!({})).await;
+    assert_eq!(status, 401, "{body}");
+    let (status, _) = poll(Some("dtk_unknown"), json!({})).await;
+    assert_eq!(status, 401);
+
+    let (status, first) = poll(Some("gateway-secret"), json!({ "platform": "esp32" })).await;
+    assert_eq!(status, 200, "{first}");
+    assert_eq!(first["status"], "polling");
+    assert_eq!(first["invokes"], json!([]));
+    assert_eq!(first["events"], json!([]));
+
+    let queued = rpc_req(
+        &mut ws,
+        "invoke-1",
+        "node.invoke",
+        Some(json!({
+            "nodeId": "node-p",
+            "command": "ping",
+            "args": ["a"],
+            "queueIfOffline": true
+        })),
+    )
+    .await;
+    assert_eq!(queued["payload"]["queued"], true, "{queued}");
+    let request_id = queued["payload"]["requestId"].clone();
+    let approval = rpc_req(
+        &mut ws,
+        "approval-1",
+        "exec.approval.request",
+        Some(json!({ "command": "ls", "twoPhase": true, "timeoutMs": 60000 })),
+    )
+    .await;
+    assert_eq!(approval["ok"], true, "{approval}");
+
+    let (_, second) = poll(Some("gateway-secret"), json!({})).await;
+    assert_eq!(second["invokes"][0]["requestId"], request_id);
+    assert_eq!(second["invokes"][0]["args"], json!(["a"]));
+    assert_eq!(second["events"][0]["event"], "exec.approval.requested");
+    let seq = second["events"][0]["seq"].clone();
+
+    let (_, third) = poll(
+        Some("gateway-secret"),
+        json!({
+            "ackSeq": seq,
+            "results": [
+                { "requestId": request_id, "status": "completed", "payload": { "pong": true } },
+                { "requestId": "someone-else", "status": "completed" }
+            ]
+        }),
+    )
+    .await;
+    assert_eq!(third["ackedSeq"], seq);
+    assert_eq!(third["invokes"], json!([]));
+    assert_eq!(third["events"], json!([]));
+    assert_eq!(third["results"][0]["ok"], true);
+    assert_eq!(third["results"][1]["ok"], false);
+
+    let node = rpc_req(
+        &mut ws,
+        "describe-1",
+        "node.describe",
+        Some(json!({ "nodeId": "node-p" })),
+    )
+    .await;
+    assert_eq!(node["payload"]["status"], "polling", "{node}");
+    assert_eq!(node["payload"]["platform"], "esp32");
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn node_file_transfers_resume_and_verify_checksums() {
+    let files = tempfile::tempdir().expect("files dir should be created");
+    std::fs::create_dir_all(files.path().join("models")).expect("models dir should be created");
+    std::fs::write(files.path().join("models/tiny.bin"), b"hello edge model")
+        .expect("source file should be written");
+    let files_dir = files.path().to_path_buf();
+    let server = spawn_server_with(AuthMode::Token("gateway-secret".to_owned()), |config| {
+        config.node_files = Some(NodeFilesConfig {
+            dir: files_dir,
+            max_bytes: 64,
+        });
+    })
+    .await;
+    let mut ws = connect_gateway(server.addr).await;
+    ws.send(Message::Text(
+        connect_frame(
+            Some("gateway-secret"),
+            1,
+            PROTOCOL_VERSION,
+            "operator",
+            "reclaw-cli",
+            &[],
+        )
+        .to_string()
+        .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+
+    let client = reqwest::Client::new();
+    let base = format!("http://{}", server.addr);
+    let poll_invoke = || {
+        let request = client
+            .post(format!("{base}/nodes/node-f/poll"))
+            .bearer_auth("gateway-secret")
+            .json(&json!({}));
+        async move {
+            let body = request
+                .send()
+                .await
+                .expect("poll should send")
+                .json::<Value>()
+                .await
+                .expect("poll body is json");
+            body["invokes"][0].clone()
+        }
+    };
+    assert_eq!(poll_invoke().await, Value::Null);
+
+    let push = rpc_req(
+        &mut ws,
+        "push-1",
+        "node.file.push",
+        Some(json!({
+            "nodeId": "node-f",
+            "source": "models/tiny.bin",
+            "path": "/opt/models/tiny.bin"
+        })),
+    )
+    .await;
+    assert_eq!(push["ok"], true, "{push}");
+    let expected_sha = Sha256::digest(b"hello edge model")
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    assert_eq!(push["payload"]["sizeBytes"], 16);
+    assert_eq!(push["payload"]["sha256"], expected_sha);
+    assert_eq!(push["payload"]["status"], "pending");
+    assert!(push["payload"].get("tokenHash").is_none());
+
+    let invoke = poll_invoke().await;
+    assert_eq!(invoke["command"], "file.push", "{invoke}");
+    assert_eq!(invoke["input"]["path"], "/opt/models/tiny.bin");
+    let (url, token) = transfer_target(&base, &invoke);
+
+    let unauthorized = client.get(&url).send().await.expect("get should send");
+    assert_eq!(unauthorized.status().as_u16(), 401);
+    let resumed = client
+        .get(&url)
+        .bearer_auth(&token)
+        .header("range", "bytes=6-")
+        .send()
+        .await
+        .expect("ranged get should send");
+    assert_eq!(resumed.status().as_u16(), 206);
+    assert_eq!(resumed.headers()["content-range"], "bytes 6-15/16");
+    assert_eq!(
+        resumed.headers()["x-checksum-sha256"],
+        expected_sha.as_str()
+    );
+    assert_eq!(
+        resumed.bytes().await.expect("body should read").as_ref(),
+        b"edge model"
+    );
+    let status = rpc_req(
+        &mut ws,
+        "status-1",
+        "node.file.status",
+        Some(json!({ "transferId": push["payload"]["transferId"] })),
+    )
+    .await;
+    assert_eq!(status["payload"]["status"], "completed", "{status}");
+    assert_eq!(status["payload"]["transferredBytes"], 16);
+    let gone = client
+        .get(&url)
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("get should send");
+    assert_eq!(gone.status().as_u16(), 410);
+
+    let escape = rpc_req(
+        &mut ws,
+        "push-2",
+        "node.file.push",
+        Some(json!({ "nodeId": "node-f", "source": "../secrets", "path": "/tmp/x" })),
+    )
+    .await;
+    assert_eq!(escape["error"]["code"], "INVALID_REQUEST", "{escape}");
+
+    let pull = rpc_req(
+        &mut ws,
+        "pull-1",
+        "node.file.pull",
+        Some(json!({ "nodeId": "node-f", "path": "/var/log/app.log" })),
+    )
+    .await;
+    assert_eq!(pull["payload"]["gatewayPath"], "node-f/app.log", "{pull}");
+    let invoke = poll_invoke().await;
+    assert_eq!(invoke["command"], "file.pull");
+    let (url, token) = transfer_target(&base, &invoke);
+    let put = |range: &str, body: &'static [u8], checksum: Option<&str>| {
+        let mut request = client
+            .put(&url)
+            .bearer_auth(&token)
+            .header("content-range", range)
+            .body(body);
+        if let Some(checksum) = checksum {
+            request = request.header("x-checksum-sha256", checksum);
+        }
+        async move {
+            let response = request.send().await.expect("put should send");
+            let status = response.status().as_u16();
+            let received = response
+                .headers()
+                .get("x-received-bytes")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned);
+            let body = response.json::<Value>().await.expect("put body is json");
+            (status, received, body)
+        }
+    };
+
+    let (status, _, probe) = put("bytes */11", b"", None).await;
+    assert_eq!(status, 200, "{probe}");
+    assert_eq!(probe["receivedBytes"], 0);
+    let (status, _, first) = put("bytes 0-4/11", b"hello", None).await;
+    assert_eq!(status, 200, "{first}");
+    assert_eq!(first["receivedBytes"], 5);
+    assert_eq!(first["status"], "active");
+    let (status, received, _) = put("bytes 0-4/11", b"hello", None).await;
+    assert_eq!(status, 409);
+    assert_eq!(received.as_deref(), Some("5"));
+    let log_sha = Sha256::digest(b"hello world")
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    let (status, _, last) = put("bytes 5-10/11", b" world", Some(&log_sha)).await;
+    assert_eq!(status, 200, "{last}");
+    assert_eq!(last["status"], "completed");
+    assert_eq!(last["sha256"], log_sha);
+    assert_eq!(
+        std::fs::read(files.path().join("node-f/app.log")).expect("pulled file should exist"),
+        b"hello world"
+    );
+
+    let limited = rpc_req(
+        &mut ws,
+        "pull-2",
+        "node.file.pull",
+        Some(json!({
+            "nodeId": "node-f",
+            "path": "/var/log/big.log",
+            "sha256": "00".repeat(32)
+        })),
+    )
+    .await;
+    let invoke = poll_invoke().await;
+    assert_eq!(invoke["input"]["maxBytes"], 64);
+    let (url, token) = transfer_target(&base, &invoke);
+    let mismatch = client
+        .put(&url)
+        .bearer_auth(&token)
+        .header("content-range", "bytes 0-4/5")
+        .body("hello")
+        .send()
+        .await
+        .expect("put should send");
+    assert_eq!(mismatch.status().as_u16(), 422);
+    assert!(!files.path().join("node-f/big.log").exists());
+    let status = rpc_req(
+        &mut ws,
+        "status-2",
+        "node.file.status",
+        Some(json!({ "transferId": limited["payload"]["transferId"] })),
+    )
+    .await;
+    assert_eq!(status["payload"]["status"], "failed", "{status}");
+
+    let oversized = rpc_req(
+        &mut ws,
+        "pull-3",
+        "node.file.pull",
+        Some(json!({ "nodeId": "node-f", "path": "/var/log/huge.log" })),
+    )
+    .await;
+    let invoke = poll_invoke().await;
+    let (url, token) = transfer_target(&base, &invoke);
+    let too_large = client
+        .put(&url)
+        .bearer_auth(&token)
+        .header("content-range", "bytes 0-0/65")
+        .body("x")
+        .send()
+        .await
+        .expect("put should send");
+    assert_eq!(too_large.status().as_u16(), 413);
+
+    let queued = rpc_req(
+        &mut ws,
+        "push-3",
+        "node.file.push",
+        Some(json!({ "nodeId": "node-f", "source": "models/tiny.bin", "path": "/tmp/tiny" })),
+    )
+    .await;
+    let cancel = rpc_req(
+        &mut ws,
+        "cancel-1",
+        "node.file.cancel",
+        Some(json!({ "transferId": queued["payload"]["transferId"] })),
+    )
+    .await;
+    assert_eq!(cancel["payload"]["cancelled"], true, "{cancel}");
+    assert_eq!(cancel["payload"]["transfer"]["status"], "cancelled");
+    assert_eq!(poll_invoke().await, Value::Null);
+
+    let list = rpc_req(
+        &mut ws,
+        "list-1",
+        "node.file.list",
+        Some(json!({ "nodeId": "node-f" })),
+    )
+    .await;
+    assert_eq!(list["payload"]["count"], 5, "{list}");
+    let statuses = list["payload"]["transfers"]
+        .as_array()
+        .expect("transfers should be an array")
+        .iter()
+        .map(|transfer| transfer["status"].as_str().unwrap_or_default().to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statuses,
+        vec!["cancelled", "failed", "failed", "completed", "completed"]
+    );
+    assert_eq!(oversized["payload"]["direction"], "pull");
+
+    server.stop().await;
+}
+
+/// The download or upload URL and token a `file.push` or `file.pull` invoke hands the node.
+fn transfer_target(base: &str, invoke: &Value) -> (String, String) {
+    let input = &invoke["input"];
+    (
+        format!("{base}{}", input["url"].as_str().unwrap_or_default()),
+        input["token"].as_str().unwrap_or_default().to_owned(),
+    )
+}
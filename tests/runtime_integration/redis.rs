@@ -0,0 +1,297 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr},
+    sync::{Arc, Mutex},
+};
+
+use futures_util::SinkExt;
+use reclaw_core::{
+    application::{
+        config::{AuthMode, RuntimeConfig},
+        state::SharedState,
+    },
+    protocol::PROTOCOL_VERSION,
+    rpc::methods,
+};
+use serde_json::json;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::AbortHandle,
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::support::{
+    WsStream, connect_frame, connect_gateway, recv_json, rpc_req, spawn_server_with,
+};
+
+/// An in-memory stand-in for Redis that speaks just enough RESP for the gateway: `SET` (with
+/// `NX`/`PX`), `GET`, `PSETEX`, and `DEL`. Anything else gets an error reply.
+struct FakeRedis {
+    url: String,
+    tasks: Arc<Mutex<Vec<AbortHandle>>>,
+}
+
+impl FakeRedis {
+    async fn start() -> Self {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .expect("fake redis should bind");
+        let url = format!(
+            "redis://{}/",
+            listener.local_addr().expect("fake redis addr")
+        );
+        let data = Arc::new(Mutex::new(HashMap::<String, String>::new()));
+        let tasks = Arc::new(Mutex::new(Vec::new()));
+        let accept_tasks = Arc::clone(&tasks);
+        let accept = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let connection = tokio::spawn(serve(stream, Arc::clone(&data)));
+                accept_tasks
+                    .lock()
+                    .expect("tasks lock")
+                    .push(connection.abort_handle());
+            }
+        });
+        tasks
+            .lock()
+            .expect("tasks lock")
+            .push(accept.abort_handle());
+        Self { url, tasks }
+    }
+
+    /// Drops the listener and every open connection, as if Redis went away.
+    fn stop(&self) {
+        for task in self.tasks.lock().expect("tasks lock").drain(..) {
+            task.abort();
+        }
+    }
+}
+
+async fn serve(stream: TcpStream, data: Arc<Mutex<HashMap<String, String>>>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    while let Some(command) = read_command(&mut reader).await {
+        let reply = {
+            let mut data = data.lock().expect("data lock");
+            let args = command.iter().map(String::as_str).collect::<Vec<_>>();
+            match args.as_slice() {
+                [set, key, value, rest @ ..] if set.eq_ignore_ascii_case("SET") => {
+                    let nx = rest.iter().any(|arg| arg.eq_ignore_ascii_case("NX"));
+                    if nx && data.contains_key(*key) {
+                        "$-1\r\n".to_owned()
+                    } else {
+                        data.insert((*key).to_owned(), (*value).to_owned());
+                        "+OK\r\n".to_owned()
+                    }
+                }
+                [psetex, key, _, value] if psetex.eq_ignore_ascii_case("PSETEX") => {
+                    data.insert((*key).to_owned(), (*value).to_owned());
+                    "+OK\r\n".to_owned()
+                }
+                [get, key] if get.eq_ignore_ascii_case("GET") => match data.get(*key) {
+                    Some(value) => format!("${}\r\n{value}\r\n", value.len()),
+                    None => "$-1\r\n".to_owned(),
+                },
+                [del, keys @ ..] if del.eq_ignore_ascii_case("DEL") => {
+                    let removed = keys.iter().filter(|key| data.remove(**key).is_some());
+                    format!(":{}\r\n", removed.count())
+                }
+                _ => "-ERR unsupported by fake redis\r\n".to_owned(),
+            }
+        };
+        if writer.write_all(reply.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn read_command(
+    reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+) -> Option<Vec<String>> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.ok()?;
+    let count = line.trim_end().strip_prefix('*')?.parse::<usize>().ok()?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        line.clear();
+        reader.read_line(&mut line).await.ok()?;
+        let len = line.trim_end().strip_prefix('$')?.parse::<usize>().ok()?;
+        let mut bytes = vec![0; len + 2];
+        reader.read_exact(&mut bytes).await.ok()?;
+        bytes.truncate(len);
+        args.push(String::from_utf8(bytes).ok()?);
+    }
+    Some(args)
+}
+
+async fn state_with_redis(db_path: std::path::PathBuf, url: &str) -> SharedState {
+    let mut config = RuntimeConfig::for_test(IpAddr::V4(Ipv4Addr::LOCALHOST), 0, db_path);
+    config.redis_url = Some(url.to_owned());
+    SharedState::new(
+        config,
+        methods::implemented_methods(),
+        methods::known_events(),
+    )
+    .await
+    .expect("state should start with redis")
+}
+
+async fn connect_operator(addr: std::net::SocketAddr) -> WsStream {
+    let mut ws = connect_gateway(addr).await;
+    ws.send(Message::Text(
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-cli", &[])
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("connect frame should send");
+    assert_eq!(recv_json(&mut ws).await["ok"], true);
+    ws
+}
+
+#[tokio::test]
+async fn unreachable_redis_backend_fails_startup() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let mut config = RuntimeConfig::for_test(
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        0,
+        temp_dir.path().join("reclaw.db"),
+    );
+    config.redis_url = Some("redis://127.0.0.1:1/".to_owned());
+
+    let error = SharedState::new(
+        config,
+        methods::implemented_methods(),
+        methods::known_events(),
+    )
+    .await
+    .err()
+    .expect("startup should fail without redis");
+    assert!(error.to_string().contains("redis"), "{error}");
+}
+
+#[tokio::test]
+async fn cached_config_entries_stay_with_their_own_database() {
+    let redis = FakeRedis::start().await;
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let first = state_with_redis(temp_dir.path().join("first.db"), &redis.url).await;
+    let second = state_with_redis(temp_dir.path().join("second.db"), &redis.url).await;
+
+    first
+        .set_config_entry_value("runtime/linkSecret", &json!("first-secret"))
+        .await
+        .expect("config entry should save");
+    assert_eq!(
+        first
+            .get_config_entry_value("runtime/linkSecret")
+            .await
+            .expect("cached read should succeed"),
+        Some(json!("first-secret"))
+    );
+    assert_eq!(
+        second
+            .get_config_entry_value("runtime/linkSecret")
+            .await
+            .expect("read should succeed"),
+        None,
+        "another database must not see this one's cached entries"
+    );
+
+    second
+        .set_config_entry_value("runtime/linkSecret", &json!("second-secret"))
+        .await
+        .expect("config entry should save");
+    assert_eq!(
+        second
+            .get_config_entry_value("runtime/linkSecret")
+            .await
+            .expect("read should succeed"),
+        Some(json!("second-secret"))
+    );
+    assert_eq!(
+        first
+            .get_config_entry_value("runtime/linkSecret")
+            .await
+            .expect("read should succeed"),
+        Some(json!("first-secret"))
+    );
+    redis.stop();
+}
+
+#[tokio::test]
+async fn idempotency_keys_held_by_another_instance_are_retryable() {
+    let redis = FakeRedis::start().await;
+    let url = redis.url.clone();
+    let first = spawn_server_with(AuthMode::None, |config| {
+        config.redis_url = Some(url.clone());
+    })
+    .await;
+    let second = spawn_server_with(AuthMode::None, |config| {
+        config.redis_url = Some(url.clone());
+    })
+    .await;
+    let send = |key: &str, session_key: &str| {
+        Some(json!({
+            "sessionKey": session_key,
+            "message": "hello",
+            "idempotencyKey": key,
+        }))
+    };
+
+    let mut ws = connect_operator(first.addr).await;
+    let sent = rpc_req(
+        &mut ws,
+        "send-1",
+        "chat.send",
+        send("run-1", "agent:main:a"),
+    )
+    .await;
+    assert_eq!(sent["ok"], true, "{sent}");
+    let retried = rpc_req(
+        &mut ws,
+        "send-2",
+        "chat.send",
+        send("run-1", "agent:main:a"),
+    )
+    .await;
+    assert_eq!(retried["ok"], true, "{retried}");
+    assert_eq!(retried["payload"]["runId"], "run-1");
+
+    let mut other = connect_operator(second.addr).await;
+    let elsewhere = rpc_req(
+        &mut other,
+        "send-3",
+        "chat.send",
+        send("run-1", "agent:main:a"),
+    )
+    .await;
+    assert_eq!(elsewhere["ok"], false, "{elsewhere}");
+    assert_eq!(elsewhere["error"]["code"], "UNAVAILABLE");
+    assert_eq!(elsewhere["error"]["retryable"], true);
+    let mismatch = rpc_req(
+        &mut other,
+        "send-4",
+        "chat.send",
+        send("run-1", "agent:main:b"),
+    )
+    .await;
+    assert_eq!(mismatch["ok"], false, "{mismatch}");
+    assert_eq!(mismatch["error"]["code"], "INVALID_REQUEST");
+
+    redis.stop();
+    let fallback = rpc_req(
+        &mut other,
+        "send-5",
+        "chat.send",
+        send("run-2", "agent:main:a"),
+    )
+    .await;
+    assert_eq!(
+        fallback["ok"], true,
+        "runs should start on local state while redis is down: {fallback}"
+    );
+
+    first.stop().await;
+    second.stop().await;
+}
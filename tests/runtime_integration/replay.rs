@@ -0,0 +1,155 @@
+use std::{path::Path, time::Duration};
+
+use futures_util::SinkExt;
+use reclaw_core::{
+    application::config::AuthMode,
+    interfaces::ws_recording::{FrameDirection, RecordedFrame, load_recording, normalize_frame},
+    protocol::PROTOCOL_VERSION,
+};
+use serde_json::{Value, json};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::support::{
+    WsStream, connect_frame, connect_gateway, recv_json, rpc_req, spawn_server, spawn_server_with,
+};
+
+/// Longest pause replayed between two inbound frames; recordings keep their ordering and rough
+/// pacing without making tests as slow as the captured session.
+const MAX_REPLAY_GAP: Duration = Duration::from_millis(50);
+
+/// Replays the inbound frames of `frames` against a fresh server and asserts that every recorded
+/// response matches the new one after normalization. Events are skipped on both sides.
+async fn assert_replay_matches(frames: &[RecordedFrame]) {
+    let server = spawn_server(AuthMode::None).await;
+    let mut ws = connect_gateway(server.addr).await;
+
+    let mut last_offset_ms = 0;
+    for recorded in frames {
+        match recorded.direction {
+            FrameDirection::In => {
+                let gap = Duration::from_millis(recorded.offset_ms.saturating_sub(last_offset_ms));
+                last_offset_ms = recorded.offset_ms;
+                tokio::time::sleep(gap.min(MAX_REPLAY_GAP)).await;
+                let text = match &recorded.frame {
+                    Value::String(text) => text.clone(),
+                    frame => frame.to_string(),
+                };
+                ws.send(Message::Text(text.into()))
+                    .await
+                    .expect("replayed frame should send");
+            }
+            FrameDirection::Out if is_event(&recorded.frame) => {}
+            FrameDirection::Out => {
+                let actual = recv_response(&mut ws).await;
+                assert_eq!(
+                    normalize_frame(&actual),
+                    normalize_frame(&recorded.frame),
+                    "replayed response diverged from the recording"
+                );
+            }
+        }
+    }
+
+    server.stop().await;
+}
+
+async fn recv_response(ws: &mut WsStream) -> Value {
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), recv_json(ws))
+            .await
+            .expect("replayed response should arrive");
+        if !is_event(&frame) {
+            return frame;
+        }
+    }
+}
+
+fn is_event(frame: &Value) -> bool {
+    frame.get("type").and_then(Value::as_str) == Some("evt")
+}
+
+#[tokio::test]
+async fn recorded_fixtures_replay_with_equivalent_responses() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay");
+    let mut fixtures = std::fs::read_dir(&dir)
+        .expect("fixture dir should exist")
+        .map(|entry| entry.expect("entry should read").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect::<Vec<_>>();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "replay fixtures should exist");
+
+    for path in fixtures {
+        let frames = load_recording(&path).expect("fixture should load");
+        assert!(
+            frames
+                .iter()
+                .any(|frame| frame.direction == FrameDirection::Out),
+            "{} should record responses",
+            path.display()
+        );
+        assert_replay_matches(&frames).await;
+    }
+}
+
+#[tokio::test]
+async fn live_sessions_are_recorded_and_replay_against_a_fresh_server() {
+    let record_dir = tempfile::tempdir().expect("temp dir should exist");
+    let dir = record_dir.path().to_path_buf();
+    let server = spawn_server_with(AuthMode::None, move |config| {
+        config.ws_record_dir = Some(dir);
+    })
+    .await;
+    let mut ws = connect_gateway(server.addr).await;
+
+    let hello = rpc_req(
+        &mut ws,
+        "connect-1",
+        "connect",
+        connect_frame(None, 1, PROTOCOL_VERSION, "operator", "reclaw-test", &[])
+            .get("params")
+            .cloned(),
+    )
+    .await;
+    assert_eq!(hello["ok"], true);
+    let sent = rpc_req(
+        &mut ws,
+        "send-1",
+        "chat.send",
+        Some(json!({"sessionKey": "agent:main:replay", "message": "hello", "idempotencyKey": "replay-1"})),
+    )
+    .await;
+    assert_eq!(sent["ok"], true, "{sent}");
+    let missing = rpc_req(&mut ws, "missing-1", "no.such.method", None).await;
+    assert_eq!(missing["ok"], false);
+    ws.send(Message::Text("{not json".into()))
+        .await
+        .expect("invalid frame should send");
+    let invalid = recv_json(&mut ws).await;
+    assert_eq!(invalid["ok"], false);
+    drop(ws);
+    server.stop().await;
+
+    let recording = std::fs::read_dir(record_dir.path())
+        .expect("record dir should exist")
+        .map(|entry| entry.expect("entry should read").path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .expect("session should be recorded");
+    let frames = load_recording(&recording).expect("recording should load");
+    let inbound = frames
+        .iter()
+        .filter(|frame| frame.direction == FrameDirection::In)
+        .count();
+    assert_eq!(inbound, 4);
+    assert!(
+        frames
+            .windows(2)
+            .all(|pair| pair[0].offset_ms <= pair[1].offset_ms)
+    );
+    assert_eq!(
+        frames.last().map(|frame| frame.frame["ok"].clone()),
+        Some(json!(false))
+    );
+
+    assert_replay_matches(&frames).await;
+}
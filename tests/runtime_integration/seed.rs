@@ -0,0 +1,166 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use reclaw_core::{
+    application::{
+        config::{RuntimeConfig, SeedAgentConfig, SeedChannelConfig, SeedCronJobConfig},
+        seed::{SeedCounts, apply_seed},
+        state::SharedState,
+    },
+    rpc::methods,
+};
+use serde_json::{Value, json};
+
+fn seeded_config(db_path: std::path::PathBuf, model: &str, job_name: &str) -> RuntimeConfig {
+    let mut config = RuntimeConfig::for_test(IpAddr::V4(Ipv4Addr::LOCALHOST), 0, db_path);
+    config.seed.agents = vec![seed_agent(model, None, None)];
+    config.seed.cron_jobs = vec![SeedCronJobConfig {
+        id: "nightly".to_owned(),
+        name: Some(job_name.to_owned()),
+        enabled: true,
+        schedule: json!({ "kind": "every", "everyMs": 3_600_000 }),
+        payload: json!({ "kind": "systemEvent", "text": "nightly" }),
+        metadata: None,
+    }];
+    config.seed.channels = vec![SeedChannelConfig {
+        id: "pager".to_owned(),
+        account_id: None,
+        kind: None,
+        connected: None,
+    }];
+    config
+}
+
+fn seed_agent(model: &str, workspace: Option<String>, emoji: Option<&str>) -> SeedAgentConfig {
+    SeedAgentConfig {
+        name: "Ops".to_owned(),
+        workspace,
+        model: Some(model.to_owned()),
+        avatar: None,
+        emoji: emoji.map(ToOwned::to_owned),
+    }
+}
+
+async fn build_state(config: RuntimeConfig) -> SharedState {
+    SharedState::new(
+        config,
+        methods::implemented_methods(),
+        methods::known_events(),
+    )
+    .await
+    .expect("shared state should build")
+}
+
+#[tokio::test]
+async fn startup_seed_creates_once_and_patches_drift() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let db_path = temp_dir.path().join("reclaw.db");
+
+    let state = build_state(seeded_config(db_path.clone(), "model-a", "Nightly")).await;
+    let created = apply_seed(&state).await.expect("seed should apply");
+    let once = SeedCounts {
+        created: 1,
+        updated: 0,
+        unchanged: 0,
+    };
+    assert_eq!(created.agents, once);
+    assert_eq!(created.cron_jobs, once);
+
+    let again = apply_seed(&state).await.expect("seed should reapply");
+    let unchanged = SeedCounts {
+        created: 0,
+        updated: 0,
+        unchanged: 1,
+    };
+    assert_eq!(again.agents, unchanged);
+    assert_eq!(again.cron_jobs, unchanged);
+
+    let channels = methods::channels::handle_status(&state, None)
+        .await
+        .expect("channels.status should succeed");
+    assert!(channels["channels"].as_array().is_some_and(|channels| {
+        channels
+            .iter()
+            .any(|channel| channel["id"] == "pager" && channel["kind"] == "seed")
+    }));
+    drop(state);
+
+    let state = build_state(seeded_config(db_path, "model-b", "Nightly digest")).await;
+    let drifted = apply_seed(&state).await.expect("seed should patch drift");
+    let patched = SeedCounts {
+        created: 0,
+        updated: 1,
+        unchanged: 0,
+    };
+    assert_eq!(drifted.agents, patched);
+    assert_eq!(drifted.cron_jobs, patched);
+
+    let agents = methods::agents::handle_list(&state, None)
+        .await
+        .expect("agents.list should succeed");
+    let ops = agents["agents"]
+        .as_array()
+        .and_then(|agents| agents.iter().find(|agent| agent["id"] == "ops"))
+        .cloned()
+        .unwrap_or(Value::Null);
+    assert_eq!(ops["model"], "model-b");
+    let job = state
+        .get_cron_job("nightly")
+        .await
+        .expect("cron job should load")
+        .expect("cron job should exist");
+    assert_eq!(job.name, "Nightly digest");
+}
+
+#[tokio::test]
+async fn reseeding_applies_workspace_and_emoji_changes() {
+    let temp_dir = tempfile::tempdir().expect("temp dir should be created");
+    let db_path = temp_dir.path().join("reclaw.db");
+
+    let mut config = seeded_config(db_path.clone(), "model-a", "Nightly");
+    config.seed.agents = vec![seed_agent("model-a", None, Some("🛠"))];
+    let state = build_state(config).await;
+    apply_seed(&state).await.expect("seed should apply");
+    drop(state);
+
+    let workspace = temp_dir.path().join("ops-workspace");
+    let mut config = seeded_config(db_path.clone(), "model-a", "Nightly");
+    config.seed.agents = vec![seed_agent(
+        "model-a",
+        Some(workspace.display().to_string()),
+        Some("🚀"),
+    )];
+    let state = build_state(config).await;
+    let reseeded = apply_seed(&state).await.expect("seed should patch drift");
+    assert_eq!(reseeded.agents.updated, 1);
+    let again = apply_seed(&state).await.expect("seed should reapply");
+    assert_eq!(again.agents.unchanged, 1);
+
+    let agents = methods::agents::handle_list(&state, None)
+        .await
+        .expect("agents.list should succeed");
+    let ops = agents["agents"]
+        .as_array()
+        .and_then(|agents| agents.iter().find(|agent| agent["id"] == "ops"))
+        .cloned()
+        .unwrap_or(Value::Null);
+    assert_eq!(ops["workspace"], workspace.display().to_string());
+    assert_eq!(ops["emoji"], "🚀");
+    let identity = std::fs::read_to_string(workspace.join("IDENTITY.md"))
+        .expect("identity file should be bootstrapped in the new workspace");
+    assert!(identity.contains("- Emoji: 🚀"), "{identity}");
+    drop(state);
+
+    let mut config = seeded_config(db_path, "model-a", "Nightly");
+    config.seed.agents = vec![seed_agent(
+        "model-a",
+        Some(workspace.display().to_string()),
+        Some("✨"),
+    )];
+    let state = build_state(config).await;
+    let reseeded = apply_seed(&state).await.expect("seed should patch emoji");
+    assert_eq!(reseeded.agents.updated, 1);
+    let identity =
+        std::fs::read_to_string(workspace.join("IDENTITY.md")).expect("identity file should exist");
+    assert!(identity.contains("- Emoji: ✨"), "{identity}");
+    assert!(!identity.contains("🚀"), "{identity}");
+}
@@ -0,0 +1,101 @@
+use reclaw_core::application::config::AuthMode;
+use serde_json::{Value, json};
+
+use super::support::{spawn_server, spawn_server_with};
+
+#[tokio::test]
+async fn setup_writes_config_once_and_disables_itself() {
+    let config_dir = tempfile::tempdir().expect("temp dir should be created");
+    let config_path = config_dir.path().join("reclaw/config.toml");
+    let configured_path = config_path.clone();
+    let server = spawn_server_with(AuthMode::None, move |config| {
+        config.config_path = Some(configured_path);
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let status = client
+        .get(format!("http://{}/setup", server.addr))
+        .send()
+        .await
+        .expect("setup status should return");
+    assert_eq!(status.status(), reqwest::StatusCode::OK);
+    let status: Value = status.json().await.expect("response should be json");
+    assert_eq!(status["available"], true);
+    assert_eq!(status["configExists"], false);
+
+    let rebound = client
+        .post(format!("http://{}/setup", server.addr))
+        .header("host", format!("attacker.example:{}", server.addr.port()))
+        .header(
+            "origin",
+            format!("http://attacker.example:{}", server.addr.port()),
+        )
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("rebound setup should return");
+    assert_eq!(rebound.status(), reqwest::StatusCode::FORBIDDEN);
+    let cross_site = client
+        .post(format!("http://{}/setup", server.addr))
+        .header("origin", "https://evil.example")
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("cross-site setup should return");
+    assert_eq!(cross_site.status(), reqwest::StatusCode::FORBIDDEN);
+    assert!(!config_path.exists());
+
+    let completed = client
+        .post(format!("http://{}/setup", server.addr))
+        .json(&json!({ "agentName": "Ops Desk" }))
+        .send()
+        .await
+        .expect("setup should return");
+    assert_eq!(completed.status(), reqwest::StatusCode::OK);
+    let completed: Value = completed.json().await.expect("response should be json");
+    assert_eq!(completed["agentId"], "ops-desk");
+    assert_eq!(completed["restartRequired"], true);
+    let gateway_token = completed["gatewayToken"]
+        .as_str()
+        .expect("gateway token should be returned");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&config_path)
+            .expect("config should exist")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let written = std::fs::read_to_string(&config_path).expect("config should be written");
+    let parsed: toml::Value = toml::from_str(&written).expect("config should be valid TOML");
+    assert_eq!(parsed["gatewayToken"].as_str(), Some(gateway_token));
+    assert_eq!(parsed["hooksEnabled"].as_bool(), Some(true));
+    assert_eq!(parsed["hooksDefaultAgentId"].as_str(), Some("ops-desk"));
+
+    let again = client
+        .post(format!("http://{}/setup", server.addr))
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("second setup should return");
+    assert_eq!(again.status(), reqwest::StatusCode::GONE);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn setup_is_not_mounted_when_auth_is_configured() {
+    let server = spawn_server(AuthMode::Token("gateway-secret".to_owned())).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("http://{}/setup", server.addr))
+        .send()
+        .await
+        .expect("setup request should return");
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    server.stop().await;
+}